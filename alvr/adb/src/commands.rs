@@ -1,11 +1,13 @@
 // https://android.googlesource.com/platform/packages/modules/adb/+/refs/heads/main/docs/user/adb.1.md
 
-use crate::parse::{self, Device, ForwardedPorts};
+use crate::parse::{self, Device, ForwardedPorts, InstallFailure};
 use alvr_filesystem as afs;
 use anyhow::{Context, Result, anyhow};
 use std::{
     collections::HashSet,
+    fs::File,
     io::{Cursor, Read},
+    path::Path,
     process::Command,
     str::FromStr,
     time::Duration,
@@ -212,10 +214,87 @@ pub fn list_devices(adb_path: &str) -> Result<Vec<Device>> {
 ///////////
 // Packages
 
-pub fn install_package(adb_path: &str, device_serial: &str, apk_path: &str) -> Result<()> {
-    get_command(adb_path, &["-s", device_serial, "install", "-r", apk_path])
+// Returns the reason if the package manager rejected the APK
+pub fn install_package(
+    adb_path: &str,
+    device_serial: &str,
+    apk_path: &str,
+) -> Result<Option<InstallFailure>> {
+    let output = get_command(adb_path, &["-s", device_serial, "install", "-r", apk_path])
         .output()
         .context(format!("Failed to install {apk_path}"))?;
+    let text = format!(
+        "{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    Ok(parse::parse_install_failure(&text))
+}
+
+pub fn get_package_version(
+    adb_path: &str,
+    device_serial: &str,
+    application_id: &str,
+) -> Result<Option<String>> {
+    let output = get_command(
+        adb_path,
+        &[
+            "-s",
+            device_serial,
+            "shell",
+            "dumpsys",
+            "package",
+            application_id,
+        ],
+    )
+    .output()
+    .context(format!("Failed to get version of package {application_id}"))?;
+
+    Ok(parse::parse_version_name(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+// The versionName of the manifest of an APK file, read without installing it
+pub fn get_apk_version(apk_path: &Path) -> Result<Option<String>> {
+    let mut archive = ZipArchive::new(
+        File::open(apk_path).context(format!("Failed to open {}", apk_path.display()))?,
+    )?;
+    let mut manifest = vec![];
+    archive
+        .by_name("AndroidManifest.xml")?
+        .read_to_end(&mut manifest)?;
+
+    Ok(parse::parse_manifest_version_name(&manifest))
+}
+
+pub fn grant_permission(
+    adb_path: &str,
+    device_serial: &str,
+    application_id: &str,
+    permission: &str,
+) -> Result<()> {
+    let output = get_command(
+        adb_path,
+        &[
+            "-s",
+            device_serial,
+            "shell",
+            "pm",
+            "grant",
+            application_id,
+            permission,
+        ],
+    )
+    .output()
+    .context(format!("Failed to grant {permission} to {application_id}"))?;
+    let error = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+    if !output.status.success() || !error.is_empty() {
+        return Err(anyhow!(
+            "Failed to grant {permission} to {application_id}: {error}"
+        ));
+    }
 
     Ok(())
 }
@@ -1,15 +1,77 @@
 pub mod commands;
 mod parse;
 
+pub use parse::InstallFailure;
+
 use alvr_common::anyhow::Result;
 use alvr_common::{dbg_connection, error, warn};
 use alvr_session::WiredClientAutoLaunchConfig;
 use alvr_system_info::{
     ClientFlavor, PACKAGE_NAME_GITHUB_DEV, PACKAGE_NAME_GITHUB_STABLE, PACKAGE_NAME_STORE,
 };
+use parse::ConnectionState;
 use std::collections::HashSet;
 use std::time::Duration;
 
+// Permissions requested by the client at runtime, which can be granted ahead of time over ADB.
+// Permissions that do not apply to the device are rejected by the package manager.
+pub const CLIENT_RUNTIME_PERMISSIONS: &[&str] = &[
+    "android.permission.RECORD_AUDIO",
+    "com.oculus.permission.BODY_TRACKING",
+    "com.oculus.permission.EYE_TRACKING",
+    "com.oculus.permission.FACE_TRACKING",
+    "com.picovr.permission.EYE_TRACKING",
+    "com.picovr.permission.FACE_TRACKING",
+];
+
+pub enum WiredDeviceStatus {
+    NotFound,
+    // USB debugging must be allowed from the headset
+    Unauthorized,
+    // The current user cannot access the USB device, usually missing udev rules on Linux
+    NoPermissions,
+    Ready {
+        serial: String,
+        model: Option<String>,
+    },
+}
+
+// Ports forwarded over ADB to a wireless client are listed with a 127.0.0.1 serial and ignored
+pub fn get_wired_device(adb_path: &str) -> Result<WiredDeviceStatus> {
+    let devices = commands::list_devices(adb_path)?
+        .into_iter()
+        .filter(|d| {
+            d.serial
+                .as_ref()
+                .is_some_and(|s| !s.starts_with("127.0.0.1"))
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(device) = devices
+        .iter()
+        .find(|d| matches!(d.connection_state, Some(ConnectionState::Device)))
+    {
+        Ok(WiredDeviceStatus::Ready {
+            serial: device.serial.clone().unwrap(),
+            model: device.model.clone(),
+        })
+    } else if devices.iter().any(|d| {
+        matches!(
+            d.connection_state,
+            Some(ConnectionState::Unauthorized | ConnectionState::Authorizing)
+        )
+    }) {
+        Ok(WiredDeviceStatus::Unauthorized)
+    } else if devices
+        .iter()
+        .any(|d| matches!(d.connection_state, Some(ConnectionState::NoPermissions)))
+    {
+        Ok(WiredDeviceStatus::NoPermissions)
+    } else {
+        Ok(WiredDeviceStatus::NotFound)
+    }
+}
+
 pub enum WiredConnectionStatus {
     Ready,
     NotReady(String),
@@ -1,3 +1,5 @@
+use std::fmt::{self, Display, Formatter};
+
 // https://cs.android.com/android/platform/superproject/main/+/7dbe542b9a93fb3cee6c528e16e2d02a26da7cc0:packages/modules/adb/transport.cpp;l=1409
 // The serial number is printed with a "%-22s" format, meaning that it's a left-aligned space-padded string of 22 characters.
 const SERIAL_NUMBER_COLUMN_LENGTH: usize = 22;
@@ -154,3 +156,382 @@ fn parse_port(value: &str) -> Option<u16> {
 
     maybe_port.and_then(|p| p.parse::<u16>().ok())
 }
+
+// https://cs.android.com/android/platform/superproject/main/+/main:frameworks/base/core/java/android/content/pm/PackageManager.java
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstallFailure {
+    InsufficientStorage,
+    // A package with the same name but signed with a different key is installed
+    SignatureMismatch,
+    VersionDowngrade,
+    Other(String),
+}
+
+impl Display for InstallFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            InstallFailure::InsufficientStorage => write!(f, "Not enough storage on the device"),
+            InstallFailure::SignatureMismatch => write!(
+                f,
+                "The installed package has a different signature and must be uninstalled first"
+            ),
+            InstallFailure::VersionDowngrade => write!(
+                f,
+                "The installed package is newer and must be uninstalled first"
+            ),
+            InstallFailure::Other(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+// `adb install` prints "Success", or a line like "Failure [INSTALL_FAILED_...: details]"
+pub fn parse_install_failure(output: &str) -> Option<InstallFailure> {
+    if output.lines().any(|line| line.trim() == "Success") {
+        return None;
+    }
+
+    let failure = if output.contains("INSTALL_FAILED_INSUFFICIENT_STORAGE") {
+        InstallFailure::InsufficientStorage
+    } else if output.contains("INSTALL_FAILED_UPDATE_INCOMPATIBLE")
+        || output.contains("INSTALL_FAILED_SHARED_USER_INCOMPATIBLE")
+    {
+        InstallFailure::SignatureMismatch
+    } else if output.contains("INSTALL_FAILED_VERSION_DOWNGRADE") {
+        InstallFailure::VersionDowngrade
+    } else {
+        InstallFailure::Other(
+            output
+                .lines()
+                .map(|line| line.trim())
+                .rfind(|line| !line.is_empty())
+                .unwrap_or("Unknown error")
+                .to_owned(),
+        )
+    };
+
+    Some(failure)
+}
+
+// From the output of `dumpsys package <application ID>`
+pub fn parse_version_name(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("versionName="))
+        .map(|version| version.trim().to_owned())
+}
+
+// Android binary XML, used for the AndroidManifest.xml inside of an APK
+// https://cs.android.com/android/platform/superproject/main/+/main:frameworks/base/libs/androidfw/include/androidfw/ResourceTypes.h
+const RES_STRING_POOL_TYPE: u16 = 0x0001;
+const RES_XML_START_ELEMENT_TYPE: u16 = 0x0102;
+const UTF8_FLAG: u32 = 1 << 8;
+const TYPE_STRING: u8 = 0x03;
+const ATTRIBUTE_SIZE: usize = 20;
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn parse_string_pool(chunk: &[u8]) -> Option<Vec<String>> {
+    let header_size = read_u16(chunk, 2)? as usize;
+    let string_count = read_u32(chunk, 8)? as usize;
+    let flags = read_u32(chunk, 16)?;
+    let strings_start = read_u32(chunk, 20)? as usize;
+
+    (0..string_count)
+        .map(|index| {
+            let offset = strings_start + read_u32(chunk, header_size + index * 4)? as usize;
+
+            if flags & UTF8_FLAG != 0 {
+                // The UTF-16 length then the UTF-8 length, each one or two bytes long
+                let skip_length = |offset: usize| {
+                    let first = *chunk.get(offset)? as usize;
+                    Some(if first & 0x80 != 0 {
+                        (
+                            ((first & 0x7F) << 8) | *chunk.get(offset + 1)? as usize,
+                            offset + 2,
+                        )
+                    } else {
+                        (first, offset + 1)
+                    })
+                };
+                let (_, offset) = skip_length(offset)?;
+                let (length, offset) = skip_length(offset)?;
+
+                Some(String::from_utf8_lossy(chunk.get(offset..offset + length)?).into_owned())
+            } else {
+                let first = read_u16(chunk, offset)? as usize;
+                let (length, offset) = if first & 0x8000 != 0 {
+                    (
+                        ((first & 0x7FFF) << 16) | read_u16(chunk, offset + 2)? as usize,
+                        offset + 4,
+                    )
+                } else {
+                    (first, offset + 2)
+                };
+                let units = (0..length)
+                    .map(|i| read_u16(chunk, offset + i * 2))
+                    .collect::<Option<Vec<_>>>()?;
+
+                Some(String::from_utf16_lossy(&units))
+            }
+        })
+        .collect()
+}
+
+// The versionName attribute of the <manifest> element
+pub fn parse_manifest_version_name(manifest: &[u8]) -> Option<String> {
+    let mut strings = vec![];
+
+    let mut offset = read_u16(manifest, 2)? as usize;
+    while offset < manifest.len() {
+        let chunk_type = read_u16(manifest, offset)?;
+        let chunk_size = read_u32(manifest, offset + 4)? as usize;
+        let chunk = manifest.get(offset..offset + chunk_size)?;
+        if chunk_size == 0 {
+            return None;
+        }
+
+        if chunk_type == RES_STRING_POOL_TYPE {
+            strings = parse_string_pool(chunk)?;
+        } else if chunk_type == RES_XML_START_ELEMENT_TYPE {
+            let header_size = read_u16(chunk, 2)? as usize;
+            let name = read_u32(chunk, header_size + 4)? as usize;
+
+            if strings.get(name).map(String::as_str) == Some("manifest") {
+                let attributes_start = header_size + read_u16(chunk, header_size + 8)? as usize;
+                let attribute_size = read_u16(chunk, header_size + 10)? as usize;
+                let attribute_count = read_u16(chunk, header_size + 12)? as usize;
+
+                for index in 0..attribute_count {
+                    let attribute = attributes_start + index * attribute_size.max(ATTRIBUTE_SIZE);
+                    let name = read_u32(chunk, attribute + 4)? as usize;
+                    if strings.get(name).map(String::as_str) != Some("versionName") {
+                        continue;
+                    }
+
+                    // The raw value is the string index, or the typed value holds it
+                    let raw_value = read_u32(chunk, attribute + 8)?;
+                    let value_index = if raw_value != u32::MAX {
+                        raw_value
+                    } else if *chunk.get(attribute + 15)? == TYPE_STRING {
+                        read_u32(chunk, attribute + 16)?
+                    } else {
+                        return None;
+                    };
+
+                    return strings.get(value_index as usize).cloned();
+                }
+
+                return None;
+            }
+        }
+
+        offset += chunk_size;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_install_failure() {
+        assert_eq!(
+            parse_install_failure("Performing Streamed Install\nSuccess\n"),
+            None
+        );
+        assert_eq!(
+            parse_install_failure(
+                "Performing Streamed Install\nadb: failed to install client.apk: Failure [INSTALL_FAILED_INSUFFICIENT_STORAGE]\n"
+            ),
+            Some(InstallFailure::InsufficientStorage)
+        );
+        assert_eq!(
+            parse_install_failure(
+                "Failure [INSTALL_FAILED_UPDATE_INCOMPATIBLE: Existing package alvr.client.stable signatures do not match newer version; ignoring!]"
+            ),
+            Some(InstallFailure::SignatureMismatch)
+        );
+        assert_eq!(
+            parse_install_failure("Failure [INSTALL_FAILED_SHARED_USER_INCOMPATIBLE]"),
+            Some(InstallFailure::SignatureMismatch)
+        );
+        assert_eq!(
+            parse_install_failure(
+                "Failure [INSTALL_FAILED_VERSION_DOWNGRADE: Downgrade detected: Update version code 10 is older than current 20]"
+            ),
+            Some(InstallFailure::VersionDowngrade)
+        );
+        // The last line is reported for unknown failures
+        assert_eq!(
+            parse_install_failure(
+                "Performing Streamed Install\nFailure [INSTALL_PARSE_FAILED_NOT_APK]\n\n"
+            ),
+            Some(InstallFailure::Other(
+                "Failure [INSTALL_PARSE_FAILED_NOT_APK]".into()
+            ))
+        );
+        assert_eq!(
+            parse_install_failure(""),
+            Some(InstallFailure::Other("Unknown error".into()))
+        );
+    }
+
+    #[test]
+    fn test_parse_version_name() {
+        let output = "\
+Packages:
+  Package [alvr.client.stable] (c0ffee):
+    userId=10123
+    versionCode=2100 minSdk=29 targetSdk=32
+    versionName=21.0.0
+    splits=[base]
+";
+        assert_eq!(parse_version_name(output), Some("21.0.0".into()));
+        assert_eq!(
+            parse_version_name("Unable to find package: alvr.client"),
+            None
+        );
+    }
+
+    fn push_u16(data: &mut Vec<u8>, value: u16) {
+        data.extend(value.to_le_bytes());
+    }
+
+    fn push_u32(data: &mut Vec<u8>, value: u32) {
+        data.extend(value.to_le_bytes());
+    }
+
+    fn string_pool(strings: &[&str], utf8: bool) -> Vec<u8> {
+        let mut string_data = vec![];
+        let mut offsets = vec![];
+        for string in strings {
+            offsets.push(string_data.len() as u32);
+            if utf8 {
+                string_data.push(string.encode_utf16().count() as u8);
+                string_data.push(string.len() as u8);
+                string_data.extend(string.as_bytes());
+                string_data.push(0);
+            } else {
+                let units = string.encode_utf16().collect::<Vec<_>>();
+                push_u16(&mut string_data, units.len() as u16);
+                for unit in units {
+                    push_u16(&mut string_data, unit);
+                }
+                push_u16(&mut string_data, 0);
+            }
+        }
+        while string_data.len() % 4 != 0 {
+            string_data.push(0);
+        }
+
+        let header_size = 28;
+        let strings_start = header_size + offsets.len() as u32 * 4;
+        let mut chunk = vec![];
+        push_u16(&mut chunk, RES_STRING_POOL_TYPE);
+        push_u16(&mut chunk, header_size as u16);
+        push_u32(&mut chunk, strings_start + string_data.len() as u32);
+        push_u32(&mut chunk, strings.len() as u32);
+        push_u32(&mut chunk, 0);
+        push_u32(&mut chunk, if utf8 { UTF8_FLAG } else { 0 });
+        push_u32(&mut chunk, strings_start);
+        push_u32(&mut chunk, 0);
+        for offset in offsets {
+            push_u32(&mut chunk, offset);
+        }
+        chunk.extend(string_data);
+
+        chunk
+    }
+
+    // Attributes are (name, raw value, typed value) string indices
+    fn start_element(name: u32, attributes: &[(u32, u32, u32)]) -> Vec<u8> {
+        let mut chunk = vec![];
+        push_u16(&mut chunk, RES_XML_START_ELEMENT_TYPE);
+        push_u16(&mut chunk, 16);
+        push_u32(
+            &mut chunk,
+            16 + 20 + attributes.len() as u32 * ATTRIBUTE_SIZE as u32,
+        );
+        push_u32(&mut chunk, 1); // Line number
+        push_u32(&mut chunk, u32::MAX); // Comment
+        push_u32(&mut chunk, u32::MAX); // Namespace
+        push_u32(&mut chunk, name);
+        push_u16(&mut chunk, 20); // Attributes start
+        push_u16(&mut chunk, ATTRIBUTE_SIZE as u16);
+        push_u16(&mut chunk, attributes.len() as u16);
+        push_u16(&mut chunk, 0);
+        push_u16(&mut chunk, 0);
+        push_u16(&mut chunk, 0);
+        for (name, raw_value, typed_value) in attributes {
+            push_u32(&mut chunk, 0); // Namespace
+            push_u32(&mut chunk, *name);
+            push_u32(&mut chunk, *raw_value);
+            push_u16(&mut chunk, 8);
+            chunk.push(0);
+            chunk.push(TYPE_STRING);
+            push_u32(&mut chunk, *typed_value);
+        }
+
+        chunk
+    }
+
+    fn manifest(utf8: bool, attributes: &[(u32, u32, u32)]) -> Vec<u8> {
+        let strings = [
+            "versionCode",
+            "versionName",
+            "manifest",
+            "21.0.0",
+            "package",
+        ];
+        let body = [
+            string_pool(&strings, utf8),
+            start_element(4, &[]),
+            start_element(2, attributes),
+        ]
+        .concat();
+
+        let mut data = vec![];
+        push_u16(&mut data, 0x0003);
+        push_u16(&mut data, 8);
+        push_u32(&mut data, 8 + body.len() as u32);
+        data.extend(body);
+
+        data
+    }
+
+    #[test]
+    fn test_parse_manifest_version_name() {
+        for utf8 in [false, true] {
+            assert_eq!(
+                parse_manifest_version_name(&manifest(utf8, &[(0, 0, 0), (1, 3, 3)])),
+                Some("21.0.0".into())
+            );
+            // Only the typed value holds the string
+            assert_eq!(
+                parse_manifest_version_name(&manifest(utf8, &[(1, u32::MAX, 3)])),
+                Some("21.0.0".into())
+            );
+            assert_eq!(
+                parse_manifest_version_name(&manifest(utf8, &[(0, 0, 0)])),
+                None
+            );
+        }
+
+        // Truncated data
+        let data = manifest(false, &[(1, 3, 3)]);
+        assert_eq!(parse_manifest_version_name(&data[..data.len() - 10]), None);
+        assert_eq!(parse_manifest_version_name(&[]), None);
+    }
+}
@@ -19,7 +19,9 @@ use alvr_graphics::{
 };
 use alvr_packets::{ButtonEntry, ButtonValue, FaceData, TrackingData};
 use alvr_session::{
-    CodecType, FoveatedEncodingConfig, MediacodecPropType, MediacodecProperty, UpscalingConfig,
+    ChromaSubsampling, CodecType, FoveatedEncodingConfig, FoveatedEncodingMode, MediacodecPropType,
+    MediacodecProperty, Settings, StreamAspectRatio, TransferFunction, UpscalingConfig,
+    settings_schema::Switch,
 };
 use std::{
     cell::RefCell,
@@ -53,6 +55,15 @@ pub struct AlvrClientCapabilities {
     prefer_10bit: bool,
     preferred_encoding_gamma: f32,
     prefer_hdr: bool,
+    decoder_chroma_422: bool,
+    decoder_chroma_444: bool,
+}
+
+#[repr(u8)]
+pub enum AlvrChromaSubsampling {
+    Yuv420 = 0,
+    Yuv422 = 1,
+    Yuv444 = 2,
 }
 
 #[repr(u8)]
@@ -65,6 +76,7 @@ pub enum AlvrEvent {
         encoding_gamma: f32,
         enable_foveated_encoding: bool,
         enable_hdr: bool,
+        chroma_subsampling: AlvrChromaSubsampling,
     },
     StreamingStopped,
     Haptics {
@@ -224,6 +236,13 @@ pub extern "C" fn alvr_initialize(capabilities: AlvrClientCapabilities) {
         prefer_10bit: capabilities.prefer_10bit,
         preferred_encoding_gamma: capabilities.preferred_encoding_gamma,
         prefer_hdr: capabilities.prefer_hdr,
+        depth_layers: false,
+        space_warp: false,
+        decoder_chroma_422: capabilities.decoder_chroma_422,
+        decoder_chroma_444: capabilities.decoder_chroma_444,
+        radial_foveated_encoding: false,
+        per_eye_foveated_encoding: false,
+        flat_display: false,
     };
     *CLIENT_CORE_CONTEXT.lock() = Some(ClientCoreContext::new(capabilities));
 }
@@ -277,6 +296,16 @@ pub extern "C" fn alvr_poll_event(out_event: *mut AlvrEvent) -> bool {
                         .negotiated_config
                         .enable_foveated_encoding,
                     enable_hdr: stream_config.negotiated_config.enable_hdr,
+                    chroma_subsampling: match stream_config
+                        .negotiated_config
+                        .ext()
+                        .map(|ext| ext.chroma_subsampling)
+                        .unwrap_or_default()
+                    {
+                        ChromaSubsampling::Yuv420 => AlvrChromaSubsampling::Yuv420,
+                        ChromaSubsampling::Yuv422 => AlvrChromaSubsampling::Yuv422,
+                        ChromaSubsampling::Yuv444 => AlvrChromaSubsampling::Yuv444,
+                    },
                 }
             }
             ClientCoreEvent::StreamingStopped => AlvrEvent::StreamingStopped,
@@ -366,7 +395,7 @@ pub extern "C" fn alvr_send_battery(device_id: u64, gauge_value: f32, is_plugged
 #[unsafe(no_mangle)]
 pub extern "C" fn alvr_send_playspace(width: f32, height: f32) {
     if let Some(context) = &*CLIENT_CORE_CONTEXT.lock() {
-        context.send_playspace(Some(Vec2::new(width, height)));
+        context.send_playspace(Some(Vec2::new(width, height)), vec![]);
     }
 }
 
@@ -684,12 +713,15 @@ pub extern "C" fn alvr_start_stream_opengl(config: AlvrStreamConfig) {
         convert_swapchain_array(config.swapchain_textures, config.swapchain_length);
     let foveated_encoding = config.enable_foveation.then_some(FoveatedEncodingConfig {
         force_enable: true,
+        mode: FoveatedEncodingMode::TwoZone,
         center_size_x: config.foveation_center_size_x,
         center_size_y: config.foveation_center_size_y,
         center_shift_x: config.foveation_center_shift_x,
         center_shift_y: config.foveation_center_shift_y,
         edge_ratio_x: config.foveation_edge_ratio_x,
         edge_ratio_y: config.foveation_edge_ratio_y,
+        right_eye: Switch::Disabled,
+        edge_preservation_strength: 0.0, // Only used by the server
     });
     let upscaling = config.enable_upscaling.then_some(UpscalingConfig {
         edge_direction: config.upscaling_edge_direction,
@@ -697,6 +729,15 @@ pub extern "C" fn alvr_start_stream_opengl(config: AlvrStreamConfig) {
         edge_threshold: config.upscaling_edge_threshold,
         upscale_factor: config.upscale_factor,
     });
+    // The stream settings are stored when the StreamingStarted event is polled
+    let (transfer_function, letterbox) = serde_json::from_str::<Settings>(&SETTINGS.lock())
+        .map(|settings| {
+            (
+                settings.video.encoder_config.transfer_function,
+                settings.video.aspect_ratio == StreamAspectRatio::Letterbox,
+            )
+        })
+        .unwrap_or((TransferFunction::Gamma22, false));
 
     STREAM_RENDERER.set(Some(StreamRenderer::new(
         GRAPHICS_CONTEXT.with_borrow(|c| c.as_ref().unwrap().clone()),
@@ -706,9 +747,11 @@ pub extern "C" fn alvr_start_stream_opengl(config: AlvrStreamConfig) {
         SDR_FORMAT_GL,
         foveated_encoding,
         true,
+        transfer_function,
         false, // TODO: limited range fix config
         1.0,   // TODO: encoding gamma config
         upscaling,
+        letterbox,
     )));
 }
 
@@ -751,6 +794,7 @@ pub extern "C" fn alvr_render_lobby_opengl(
                 ],
                 None,
                 None,
+                &[],
                 render_background,
                 false,
             );
@@ -805,6 +849,7 @@ pub extern "C" fn alvr_render_stream_opengl(
                     },
                 ],
                 None,
+                false,
             );
         }
     });
@@ -840,6 +885,7 @@ pub struct AlvrMediacodecOption {
 #[repr(C)]
 pub struct AlvrDecoderConfig {
     codec: AlvrCodecType,
+    chroma_subsampling: AlvrChromaSubsampling,
     force_software_decoder: bool,
     max_buffering_frames: f32,
     buffering_history_weight: f32,
@@ -858,9 +904,15 @@ pub extern "C" fn alvr_create_decoder(config: AlvrDecoderConfig) {
             AlvrCodecType::Hevc => CodecType::Hevc,
             AlvrCodecType::AV1 => CodecType::AV1,
         },
+        chroma_subsampling: match config.chroma_subsampling {
+            AlvrChromaSubsampling::Yuv420 => ChromaSubsampling::Yuv420,
+            AlvrChromaSubsampling::Yuv422 => ChromaSubsampling::Yuv422,
+            AlvrChromaSubsampling::Yuv444 => ChromaSubsampling::Yuv444,
+        },
         force_software_decoder: config.force_software_decoder,
         max_buffering_frames: config.max_buffering_frames,
         buffering_history_weight: config.buffering_history_weight,
+        max_queued_frames: video_decoder::default_max_queued_frames(),
         options: if !config.options.is_null() {
             let options =
                 unsafe { slice::from_raw_parts(config.options, config.options_count as usize) };
@@ -933,6 +985,10 @@ pub extern "C" fn alvr_get_frame(
 ) -> bool {
     if let Some(source) = &mut *DECODER_SOURCE.lock()
         && let Some((timestamp, buffer_ptr)) = source.get_frame()
+        && CLIENT_CORE_CONTEXT
+            .lock()
+            .as_ref()
+            .is_none_or(|context| context.is_frame_displayable(timestamp))
     {
         unsafe {
             *out_timestamp_ns = timestamp.as_nanos() as u64;
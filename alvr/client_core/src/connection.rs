@@ -1,30 +1,39 @@
 #![allow(clippy::if_same_then_else)]
 
 use crate::{
-    ClientCapabilities, ClientCoreEvent,
-    logging_backend::{LOG_CHANNEL_SENDER, LogMirrorData},
+    ClientCapabilities, ClientCoreEvent, DepthFrame, FrameEncoderStats, MotionVectorsFrame,
+    VideoDecoderInput,
+    event_queue::EventQueue,
+    logging_backend::{self, LOG_CHANNEL_SENDER, LogMirrorData},
     sockets::AnnouncerSocket,
-    statistics::StatisticsManager,
-    storage::Config,
+    statistics::{self, StatisticsManager},
+    storage::{Config, ReconnectBackoff, ServerStorage, StoredRecentering},
+    video_content::VideoContentDetector,
 };
 use alvr_common::{
-    ALVR_VERSION, AnyhowToCon, ConResult, ConnectionError, ConnectionState, LifecycleState,
-    ViewParams, dbg_connection, debug, error, info,
+    ALVR_VERSION, AnyhowToCon, ClipboardSync, ConResult, ConnectionError, ConnectionState,
+    LifecycleState, ViewParams, dbg_connection, debug, error,
+    glam::Vec2,
+    info,
     parking_lot::{Condvar, Mutex, RwLock},
     wait_rwlock, warn,
 };
 use alvr_packets::{
     AUDIO, ClientConnectionResult, ClientControlPacket, ClientStatistics, ConnectionAcceptedInfo,
-    HAPTICS, Haptics, STATISTICS, ServerControlPacket, StreamConfigPacket, TRACKING, TrackingData,
-    VIDEO, VideoPacketHeader, VideoStreamingCapabilities, VideoStreamingCapabilitiesExt,
+    DEPTH, DepthPacketHeader, HAPTICS, Haptics, MOTION_VECTORS, MotionVectorsPacketHeader,
+    PERIPHERAL_INPUT, PeripheralInput, STATISTICS, ServerControlPacket, StreamConfigPacket,
+    TRACKING, TrackingData, VIDEO, VideoLossReport, VideoPacketHeader, VideoStreamingCapabilities,
+    VideoStreamingCapabilitiesExt,
 };
 use alvr_session::{SocketProtocol, settings_schema::Switch};
 use alvr_sockets::{
-    ControlSocketSender, KEEPALIVE_INTERVAL, KEEPALIVE_TIMEOUT, PeerType, ProtoControlSocket,
+    ControlSocketReceiver, ControlSocketSender, PeerLiveness, PeerType, ProtoControlSocket,
     StreamSender, StreamSocketBuilder,
 };
 use std::{
     collections::VecDeque,
+    mem,
+    net::{IpAddr, TcpStream, UdpSocket},
     sync::{Arc, mpsc},
     thread,
     time::{Duration, Instant},
@@ -42,19 +51,47 @@ const INITIAL_MESSAGE: &str = concat!(
 );
 const SUCCESS_CONNECT_MESSAGE: &str = "Successful connection!\nPlease wait...";
 const STREAM_STARTING_MESSAGE: &str = "The stream will begin soon\nPlease wait...";
-const SERVER_RESTART_MESSAGE: &str = "The streamer is restarting\nPlease wait...";
+const SERVER_RESTART_MESSAGE: &str = "SteamVR is restarting\nPlease wait...";
 const SERVER_DISCONNECTED_MESSAGE: &str = "The streamer has disconnected.";
 const CONNECTION_TIMEOUT_MESSAGE: &str = "Connection timeout.";
+const STANDBY_DISCONNECT_MESSAGE: &str = "Disconnected because the headset was in standby.";
+const WAKE_HINT_MESSAGE: &str = "Press B to wake up the PC";
+const FORGET_HINT_MESSAGE: &str = "Hold A to forget the streamers";
+const MAX_HISTORY_LINES: usize = 3;
+const GIVE_UP_MESSAGE: &str = concat!(
+    "The streamer could not be found.\n",
+    "Open ALVR on your PC, then take off and\n",
+    "put on the headset to search again",
+);
 
 const SOCKET_INIT_RETRY_INTERVAL: Duration = Duration::from_millis(500);
 const CONNECTION_RETRY_INTERVAL: Duration = Duration::from_secs(1);
 const HANDSHAKE_ACTION_TIMEOUT: Duration = Duration::from_secs(2);
 const STREAMING_RECV_TIMEOUT: Duration = Duration::from_millis(500);
+const MICROPHONE_MUTED_POLL_INTERVAL: Duration = Duration::from_millis(100);
+// Lifecycle changes are checked with this period while waiting to retry
+const LIFECYCLE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+// Longer than a SteamVR restart. The streamer is searched again afterwards
+const DRIVER_RESUME_TIMEOUT: Duration = Duration::from_secs(60);
+#[cfg(target_os = "android")]
+const WIFI_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 const MAX_UNREAD_PACKETS: usize = 10; // Applies per stream
+const MAX_HAPTICS_PACKET_SIZE: usize = 1024;
+// Depth frames kept until the video frame with the same timestamp is displayed
+const MAX_DEPTH_FRAMES: usize = 8;
+const MAX_MOTION_VECTORS_FRAMES: usize = 8;
 
 pub type DecoderCallback = dyn FnMut(Duration, &[u8]) -> bool + Send;
 
+// Connection kept open by the streamer while SteamVR restarts
+pub struct KeptConnection {
+    control_socket: TcpStream,
+    // Only UDP streams are resumed
+    udp_socket: Option<UdpSocket>,
+    server_ip: IpAddr,
+}
+
 #[derive(Default)]
 pub struct ConnectionContext {
     pub state: RwLock<ConnectionState>,
@@ -62,13 +99,46 @@ pub struct ConnectionContext {
     pub control_sender: Mutex<Option<ControlSocketSender<ClientControlPacket>>>,
     pub tracking_sender: Mutex<Option<StreamSender<TrackingData>>>,
     pub statistics_sender: Mutex<Option<StreamSender<ClientStatistics>>>,
+    // Set only if keyboard and mouse passthrough is enabled
+    pub peripheral_input_sender: Mutex<Option<StreamSender<PeripheralInput>>>,
+    // Set only if clipboard sync is enabled
+    pub clipboard_sync: Mutex<Option<ClipboardSync>>,
     pub statistics_manager: Mutex<Option<StatisticsManager>>,
-    pub decoder_callback: Mutex<Option<Box<DecoderCallback>>>,
+    pub decoder_input: Mutex<Option<Box<dyn VideoDecoderInput>>>,
     pub global_view_params_queue: Mutex<VecDeque<(Duration, [ViewParams; 2])>>,
+    // Same length as global_view_params_queue
+    pub frame_encoder_stats_queue: Mutex<VecDeque<(Duration, FrameEncoderStats)>>,
+    // Frames with an older timestamp are decoded but not displayed, while an intra refresh cycle
+    // is in progress
+    pub frames_hidden_until: Mutex<Option<Duration>>,
+    pub video_loss_report: Mutex<VideoLossReport>,
+    // Frames newer than this timestamp are flashed for the photodiode latency test
+    pub latency_test_flash_after: Mutex<Option<Duration>>,
+    // Set when the server disconnected because of standby, cleared when the headset leaves it
+    pub standby_disconnected: Mutex<bool>,
+    // Set when the server announced a SteamVR restart, cleared on the next connection
+    pub server_restarting: Mutex<bool>,
+    // Resumed by the next connection, instead of searching for the streamer
+    pub kept_connection: Mutex<Option<KeptConnection>>,
+    // Toggled by a button macro on the server. The microphone is not recorded while muted
+    pub microphone_muted: Mutex<bool>,
+    // Set by the privacy setting. The face, eye and body data is not sent while set
+    pub biometric_streams_blocked: Mutex<bool>,
+    pub video_content_detector: Mutex<VideoContentDetector>,
+    // Set while the server reduces the frame rate because the scene is static
+    pub server_idle_throttled: Mutex<bool>,
+    pub depth_frames: Mutex<VecDeque<DepthFrame>>,
+    // Set while the game doesn't submit depth, no depth is received
+    pub depth_stream_paused: Mutex<bool>,
+    pub motion_vectors_frames: Mutex<VecDeque<MotionVectorsFrame>>,
     pub max_prediction: RwLock<Duration>,
+    // Set while connected, identifies the entry of the streamer in ServerStorage
+    pub server_address: Mutex<Option<IpAddr>>,
+    // Last area sent with PlayspaceSync
+    pub playspace_area: Mutex<Option<Vec2>>,
 }
 
-fn set_hud_message(event_queue: &Mutex<VecDeque<ClientCoreEvent>>, message: &str) {
+fn set_hud_message(event_queue: &EventQueue, message: &str) {
     let message = format!(
         "ALVR v{}\nhostname: {}\nIP: {}\n\n{message}",
         *ALVR_VERSION,
@@ -76,9 +146,32 @@ fn set_hud_message(event_queue: &Mutex<VecDeque<ClientCoreEvent>>, message: &str
         alvr_system_info::local_ip(),
     );
 
-    event_queue
-        .lock()
-        .push_back(ClientCoreEvent::UpdateHudMessage(message));
+    event_queue.push(ClientCoreEvent::UpdateHudMessage(message));
+}
+
+pub(crate) fn set_initial_hud_message(event_queue: &EventQueue) {
+    let mut message = INITIAL_MESSAGE.to_owned();
+    if Config::load().server_mac_address.is_some() {
+        message += &format!("\n\n{WAKE_HINT_MESSAGE}");
+    }
+
+    let storage = ServerStorage::load();
+    if !storage.servers.is_empty() {
+        message += "\n\nRecent streamers:";
+        for entry in storage.servers.iter().take(MAX_HISTORY_LINES) {
+            let time = chrono::DateTime::from_timestamp(entry.last_connection_time as i64, 0)
+                .map(|time| {
+                    time.with_timezone(&chrono::Local)
+                        .format("%F %R")
+                        .to_string()
+                })
+                .unwrap_or_default();
+            message += &format!("\n{} ({time})", entry.address);
+        }
+        message += &format!("\n{FORGET_HINT_MESSAGE}");
+    }
+
+    set_hud_message(event_queue, &message);
 }
 
 fn is_streaming(ctx: &ConnectionContext) -> bool {
@@ -89,14 +182,16 @@ pub fn connection_lifecycle_loop(
     capabilities: ClientCapabilities,
     ctx: Arc<ConnectionContext>,
     lifecycle_state: Arc<RwLock<LifecycleState>>,
-    event_queue: Arc<Mutex<VecDeque<ClientCoreEvent>>>,
+    event_queue: Arc<EventQueue>,
 ) {
     dbg_connection!("connection_lifecycle_loop: Begin");
 
-    set_hud_message(&event_queue, INITIAL_MESSAGE);
+    set_initial_hud_message(&event_queue);
 
     while *lifecycle_state.read() != LifecycleState::ShuttingDown {
-        if *lifecycle_state.read() == LifecycleState::Resumed {
+        if *ctx.standby_disconnected.lock() {
+            debug!("Skip try connection because the headset is in standby");
+        } else if *lifecycle_state.read() == LifecycleState::Resumed {
             if let Err(e) = connection_pipeline(
                 capabilities.clone(),
                 Arc::clone(&ctx),
@@ -106,6 +201,9 @@ pub fn connection_lifecycle_loop(
                 let message = format!("Connection error:\n{e}\nCheck the PC for more details");
                 set_hud_message(&event_queue, &message);
                 error!("Connection error: {e}");
+            } else if *ctx.server_restarting.lock() {
+                // Overrides the disconnection messages of the streaming threads
+                set_hud_message(&event_queue, SERVER_RESTART_MESSAGE);
             }
         } else {
             debug!("Skip try connection because the device is sleeping");
@@ -120,20 +218,85 @@ pub fn connection_lifecycle_loop(
     dbg_connection!("connection_lifecycle_loop: End");
 }
 
+// The last packet of the previous driver is the acknowledged DriverRestarting, so the socket is
+// at a packet boundary
+fn keep_connection(
+    ctx: &ConnectionContext,
+    control_receiver: ControlSocketReceiver<ServerControlPacket>,
+    udp_socket: Option<UdpSocket>,
+    server_ip: IpAddr,
+) {
+    if let Some(control_socket) = control_receiver.into_stream() {
+        *ctx.kept_connection.lock() = Some(KeptConnection {
+            control_socket,
+            udp_socket,
+            server_ip,
+        });
+    }
+}
+
+// Returns None if SteamVR didn't come back in time, the streamer is then searched again
+fn resume_kept_connection(
+    kept: KeptConnection,
+    lifecycle_state: &RwLock<LifecycleState>,
+) -> Option<(ProtoControlSocket, Option<UdpSocket>)> {
+    let (_, mut control_receiver) = ProtoControlSocket::from_stream(kept.control_socket)
+        .split::<ClientControlPacket, ServerControlPacket>(HANDSHAKE_ACTION_TIMEOUT)
+        .ok()?;
+
+    let deadline = Instant::now() + DRIVER_RESUME_TIMEOUT;
+    while Instant::now() < deadline && *lifecycle_state.read() == LifecycleState::Resumed {
+        match control_receiver.recv(HANDSHAKE_ACTION_TIMEOUT) {
+            Ok(ServerControlPacket::DriverResumed) => {
+                if let Some(socket) = &kept.udp_socket {
+                    alvr_sockets::discard_received_datagrams(socket).ok();
+                }
+
+                return Some((
+                    ProtoControlSocket::from_stream(control_receiver.into_stream()?),
+                    kept.udp_socket,
+                ));
+            }
+            // Sent by the previous driver before it stopped
+            Ok(_) | Err(ConnectionError::TryAgain(_)) => (),
+            Err(_) => return None,
+        }
+    }
+
+    None
+}
+
 fn connection_pipeline(
     capabilities: ClientCapabilities,
     ctx: Arc<ConnectionContext>,
     lifecycle_state: Arc<RwLock<LifecycleState>>,
-    event_queue: Arc<Mutex<VecDeque<ClientCoreEvent>>>,
+    event_queue: Arc<EventQueue>,
 ) -> ConResult {
     dbg_connection!("connection_pipeline: Begin");
 
-    let (mut proto_control_socket, server_ip) = {
+    let kept_connection = ctx.kept_connection.lock().take();
+    let (mut proto_control_socket, server_ip, kept_udp_socket) = if let Some(kept) = kept_connection
+    {
+        let server_ip = kept.server_ip;
+        let Some((socket, udp_socket)) = resume_kept_connection(kept, &lifecycle_state) else {
+            info!("SteamVR did not resume the connection");
+            set_initial_hud_message(&event_queue);
+
+            return Ok(());
+        };
+        info!("Connection resumed after the SteamVR restart");
+        set_hud_message(&event_queue, SUCCESS_CONNECT_MESSAGE);
+        *ctx.server_restarting.lock() = false;
+        *ctx.microphone_muted.lock() = false;
+
+        (socket, server_ip, udp_socket)
+    } else {
         let config = Config::load();
         let announcer_socket = AnnouncerSocket::new(&config.hostname).to_con()?;
         let listener_socket =
             alvr_sockets::get_server_listener(HANDSHAKE_ACTION_TIMEOUT).to_con()?;
 
+        let mut retry = 0;
         loop {
             if *lifecycle_state.write() != LifecycleState::Resumed {
                 return Ok(());
@@ -146,8 +309,41 @@ fn connection_pipeline(
                 PeerType::Server(&listener_socket),
             ) {
                 set_hud_message(&event_queue, SUCCESS_CONNECT_MESSAGE);
-                break pair;
+                *ctx.server_restarting.lock() = false;
+                *ctx.microphone_muted.lock() = false;
+                let (socket, server_ip) = pair;
+                break (socket, server_ip, None);
+            }
+
+            // The search restarts from the base interval when the headset is resumed
+            if config
+                .reconnect_backoff
+                .max_retries
+                .is_some_and(|max_retries| retry >= max_retries)
+            {
+                info!("Streamer not found after {retry} retries, stop searching");
+                set_hud_message(&event_queue, GIVE_UP_MESSAGE);
+
+                while *lifecycle_state.read() == LifecycleState::Resumed {
+                    thread::sleep(LIFECYCLE_POLL_INTERVAL);
+                }
+                set_initial_hud_message(&event_queue);
+
+                return Ok(());
+            }
+
+            let retry_deadline = Instant::now()
+                + config
+                    .reconnect_backoff
+                    .retry_interval(retry)
+                    .saturating_sub(SOCKET_INIT_RETRY_INTERVAL);
+            while *lifecycle_state.read() == LifecycleState::Resumed
+                && Instant::now() < retry_deadline
+            {
+                thread::sleep(LIFECYCLE_POLL_INTERVAL);
             }
+
+            retry += 1;
         }
     };
 
@@ -182,7 +378,15 @@ fn connection_pipeline(
                         prefer_hdr: capabilities.prefer_hdr,
                         ext_str: String::new(),
                     }
-                    .with_ext(VideoStreamingCapabilitiesExt {}),
+                    .with_ext(VideoStreamingCapabilitiesExt {
+                        depth_layers: capabilities.depth_layers,
+                        space_warp: capabilities.space_warp,
+                        decoder_chroma_422: capabilities.decoder_chroma_422,
+                        decoder_chroma_444: capabilities.decoder_chroma_444,
+                        radial_foveated_encoding: capabilities.radial_foveated_encoding,
+                        per_eye_foveated_encoding: capabilities.per_eye_foveated_encoding,
+                        flat_display: capabilities.flat_display,
+                    }),
                 ),
             },
         )))
@@ -199,6 +403,32 @@ fn connection_pipeline(
     let negotiated_config = stream_config.negotiated_config;
 
     *ctx.max_prediction.write() = Duration::from_millis(settings.headset.max_prediction_ms);
+    *ctx.biometric_streams_blocked.lock() = settings.privacy.block_biometric_streams;
+    *ctx.video_content_detector.lock() = VideoContentDetector::default();
+    *ctx.server_idle_throttled.lock() = false;
+    *ctx.depth_stream_paused.lock() = false;
+
+    let mut config = Config::load();
+    let reconnect_config = &settings.connection.client_reconnect;
+    let reconnect_backoff = ReconnectBackoff {
+        base_interval: Duration::from_millis(reconnect_config.base_interval_ms),
+        max_interval: Duration::from_secs(reconnect_config.max_interval_s),
+        max_retries: reconnect_config.max_retries.as_option().copied(),
+    };
+    if config.safe_mode != settings.extra.client_safe_mode
+        || config.graphics_api != settings.extra.client_graphics_api
+        || config.reconnect_backoff != reconnect_backoff
+        || config.lobby_marker_selection != settings.headset.lobby_marker_selection
+    {
+        config.safe_mode = settings.extra.client_safe_mode;
+        config.graphics_api = settings.extra.client_graphics_api;
+        config.reconnect_backoff = reconnect_backoff;
+        config.lobby_marker_selection = settings.headset.lobby_marker_selection;
+        config.store();
+    }
+
+    *ctx.server_address.lock() = Some(server_ip);
+    ServerStorage::report_connection(server_ip);
 
     *ctx.statistics_manager.lock() = Some(StatisticsManager::new(
         settings.connection.statistics_history_size,
@@ -208,6 +438,13 @@ fn connection_pipeline(
         .split(STREAMING_RECV_TIMEOUT)
         .to_con()?;
 
+    let keepalive_interval = Duration::from_millis(settings.connection.keepalive.interval_ms);
+    let keepalive_timeout = Duration::from_millis(settings.connection.keepalive.timeout_ms);
+    // Sending to a dead server fails instead of blocking the disconnection
+    control_sender
+        .set_write_timeout(keepalive_timeout)
+        .to_con()?;
+
     match control_receiver.recv(HANDSHAKE_ACTION_TIMEOUT) {
         Ok(ServerControlPacket::StartStream) => {
             info!("Stream starting");
@@ -216,6 +453,19 @@ fn connection_pipeline(
         Ok(ServerControlPacket::Restarting) => {
             info!("Server restarting");
             set_hud_message(&event_queue, SERVER_RESTART_MESSAGE);
+            *ctx.server_restarting.lock() = true;
+            return Ok(());
+        }
+        Ok(ServerControlPacket::DriverRestarting) => {
+            info!("Server restarting, the connection is kept");
+            set_hud_message(&event_queue, SERVER_RESTART_MESSAGE);
+            *ctx.server_restarting.lock() = true;
+            if control_sender
+                .send(&ClientControlPacket::RestartAck)
+                .is_ok()
+            {
+                keep_connection(&ctx, control_receiver, kept_udp_socket, server_ip);
+            }
             return Ok(());
         }
         Err(e) => {
@@ -237,14 +487,25 @@ fn connection_pipeline(
     };
 
     dbg_connection!("connection_pipeline: create StreamSocket");
-    let stream_socket_builder = StreamSocketBuilder::listen_for_server(
-        Duration::from_secs(1),
-        settings.connection.stream_port,
-        stream_protocol,
-        settings.connection.dscp,
-        settings.connection.client_buffer_config,
-    )
-    .to_con()?;
+    // The server keeps its socket under the same conditions
+    let kept_udp_socket = kept_udp_socket.filter(|socket| {
+        matches!(stream_protocol, SocketProtocol::Udp)
+            && socket
+                .local_addr()
+                .is_ok_and(|address| address.port() == settings.connection.stream_port)
+    });
+    let stream_socket_builder = if let Some(socket) = kept_udp_socket {
+        StreamSocketBuilder::Udp(socket)
+    } else {
+        StreamSocketBuilder::listen_for_server(
+            Duration::from_secs(1),
+            settings.connection.stream_port,
+            stream_protocol,
+            settings.connection.dscp,
+            settings.connection.client_buffer_config,
+        )
+        .to_con()?
+    };
 
     dbg_connection!("connection_pipeline: Send StreamReady");
     if let Err(e) = control_sender.send(&ClientControlPacket::StreamReady) {
@@ -260,6 +521,12 @@ fn connection_pipeline(
         settings.connection.packet_size as _,
         HANDSHAKE_ACTION_TIMEOUT,
     )?;
+    stream_socket
+        .set_write_timeout(keepalive_timeout)
+        .to_con()?;
+    let udp_socket = stream_socket
+        .try_clone_udp_socket()
+        .and_then(|res| res.ok());
 
     info!("Connected to server");
 
@@ -267,39 +534,118 @@ fn connection_pipeline(
         stream_socket.subscribe_to_stream::<VideoPacketHeader>(VIDEO, MAX_UNREAD_PACKETS);
     let mut game_audio_receiver = stream_socket.subscribe_to_stream(AUDIO, MAX_UNREAD_PACKETS);
     let tracking_sender = stream_socket.request_stream(TRACKING);
-    let mut haptics_receiver =
-        stream_socket.subscribe_to_stream::<Haptics>(HAPTICS, MAX_UNREAD_PACKETS);
+    let mut haptics_receiver = stream_socket.subscribe_to_stream_with_max_size::<Haptics>(
+        HAPTICS,
+        MAX_UNREAD_PACKETS,
+        MAX_HAPTICS_PACKET_SIZE,
+    );
     let statistics_sender = stream_socket.request_stream(STATISTICS);
+    let depth_receiver = negotiated_config
+        .ext()
+        .is_ok_and(|ext| ext.enable_depth_stream)
+        .then(|| stream_socket.subscribe_to_stream::<DepthPacketHeader>(DEPTH, MAX_UNREAD_PACKETS));
+    let motion_vectors_receiver = negotiated_config
+        .ext()
+        .is_ok_and(|ext| ext.enable_space_warp)
+        .then(|| {
+            stream_socket.subscribe_to_stream::<MotionVectorsPacketHeader>(
+                MOTION_VECTORS,
+                MAX_UNREAD_PACKETS,
+            )
+        });
+    let peripheral_input_sender = settings
+        .headset
+        .peripheral_input
+        .as_option()
+        .map(|_| stream_socket.request_stream(PERIPHERAL_INPUT));
+    let clipboard_sync = settings.headset.clipboard_sync.as_option().map(|config| {
+        ClipboardSync::new(
+            config.client_to_server,
+            config.server_to_client,
+            config.max_size_kb as usize * 1024,
+        )
+    });
 
     let video_receive_thread = thread::spawn({
         let ctx = Arc::clone(&ctx);
         move || {
             let mut stream_corrupted = true;
+            // Some while the decoder is recovering through intra refresh
+            let mut refresh_frames_left = None;
+
+            let request_recovery = |intra_refresh_period: Option<u32>| {
+                if let Some(sender) = &mut *ctx.control_sender.lock() {
+                    if intra_refresh_period.is_some() {
+                        sender.send(&ClientControlPacket::IntraRefreshRecovery).ok();
+                    } else {
+                        sender.send(&ClientControlPacket::RequestIdr).ok();
+                    }
+                }
+                if intra_refresh_period.is_some() && settings.connection.avoid_video_glitching {
+                    *ctx.frames_hidden_until.lock() = Some(Duration::MAX);
+                }
+            };
+
             while is_streaming(&ctx) {
                 let data = match video_receiver.recv(STREAMING_RECV_TIMEOUT) {
                     Ok(data) => data,
                     Err(ConnectionError::TryAgain(_)) => continue,
                     Err(ConnectionError::Other(_)) => return,
                 };
-                let Ok((header, nal)) = data.get() else {
-                    return;
+                let (header, nal) = match data.get() {
+                    Ok(packet) => packet,
+                    Err(e) => {
+                        warn!("Dropped malformed video packet: {e}");
+                        continue;
+                    }
                 };
 
                 if let Some(stats) = &mut *ctx.statistics_manager.lock() {
                     stats.report_video_packet_received(header.timestamp);
                 }
 
+                *ctx.server_idle_throttled.lock() = header.idle_throttled;
+
+                {
+                    let report = &mut *ctx.video_loss_report.lock();
+                    report.frames_received += 1;
+                    if data.had_packet_loss() {
+                        report.frames_lost += 1;
+                    }
+                }
+
+                ctx.video_content_detector.lock().report_frame(
+                    nal.len(),
+                    header.is_idr,
+                    Instant::now(),
+                );
+
                 if header.is_idr {
                     stream_corrupted = false;
+                    refresh_frames_left = None;
+                    *ctx.frames_hidden_until.lock() = None;
                 } else if data.had_packet_loss() {
                     stream_corrupted = true;
-                    if let Some(sender) = &mut *ctx.control_sender.lock() {
-                        sender.send(&ClientControlPacket::RequestIdr).ok();
-                    }
+                    refresh_frames_left = header.intra_refresh_period;
+                    request_recovery(header.intra_refresh_period);
                     warn!("Network dropped video packet");
+                } else if let Some(frames_left) = &mut refresh_frames_left {
+                    *frames_left = frames_left.saturating_sub(1);
+                    if *frames_left == 0 {
+                        // The refresh cycle completed, this frame is the first one fully refreshed
+                        stream_corrupted = false;
+                        refresh_frames_left = None;
+                        if let Some(hidden_until) = &mut *ctx.frames_hidden_until.lock() {
+                            *hidden_until = header.timestamp;
+                        }
+                    }
                 }
 
-                if !stream_corrupted || !settings.connection.avoid_video_glitching {
+                // With intra refresh, the decoder must receive the frames to recover
+                if !stream_corrupted
+                    || refresh_frames_left.is_some()
+                    || !settings.connection.avoid_video_glitching
+                {
                     // The view params must be enqueued before calling the decoder callback, there
                     // is no problem if the callback fails
                     {
@@ -312,19 +658,38 @@ fn connection_pipeline(
                         while global_view_params_queue_lock.len() > 128 {
                             global_view_params_queue_lock.pop_front();
                         }
+
+                        let frame_encoder_stats_queue_lock =
+                            &mut ctx.frame_encoder_stats_queue.lock();
+
+                        frame_encoder_stats_queue_lock.push_back((
+                            header.timestamp,
+                            FrameEncoderStats {
+                                is_idr: header.is_idr,
+                                average_qp: header.average_qp,
+                                encode_duration: header.encode_duration,
+                            },
+                        ));
+
+                        while frame_encoder_stats_queue_lock.len() > 128 {
+                            frame_encoder_stats_queue_lock.pop_front();
+                        }
                     }
 
                     let submitted = ctx
-                        .decoder_callback
+                        .decoder_input
                         .lock()
                         .as_mut()
-                        .is_some_and(|callback| callback(header.timestamp, nal));
+                        .is_some_and(|decoder| decoder.push_nal(header.timestamp, nal));
 
                     if !submitted {
-                        stream_corrupted = true;
-                        if let Some(sender) = &mut *ctx.control_sender.lock() {
-                            sender.send(&ClientControlPacket::RequestIdr).ok();
+                        if let Some(stats) = &mut *ctx.statistics_manager.lock() {
+                            stats.report_decoder_queue_drop();
                         }
+
+                        stream_corrupted = true;
+                        refresh_frames_left = header.intra_refresh_period;
+                        request_recovery(header.intra_refresh_period);
                         warn!("Dropped video packet. Reason: Decoder saturation")
                     }
                 } else {
@@ -367,15 +732,21 @@ fn connection_pipeline(
             let ctx = Arc::clone(&ctx);
             move || {
                 while is_streaming(&ctx) {
+                    if *ctx.microphone_muted.lock() {
+                        thread::sleep(MICROPHONE_MUTED_POLL_INTERVAL);
+                        continue;
+                    }
+
                     let ctx = Arc::clone(&ctx);
                     match audio::record_audio_blocking(
-                        Arc::new(move || is_streaming(&ctx)),
+                        Arc::new(move || is_streaming(&ctx) && !*ctx.microphone_muted.lock()),
                         microphone_sender.clone(),
                         &device,
                         1,
                         false,
                     ) {
-                        Ok(()) => break,
+                        // Stopped because of a disconnection or because it was muted
+                        Ok(()) => (),
                         Err(e) => {
                             error!("Audio record error: {e}");
 
@@ -399,11 +770,15 @@ fn connection_pipeline(
                     Err(ConnectionError::TryAgain(_)) => continue,
                     Err(ConnectionError::Other(_)) => return,
                 };
-                let Ok(haptics) = data.get_header() else {
-                    return;
+                let haptics = match data.get_header() {
+                    Ok(haptics) => haptics,
+                    Err(e) => {
+                        warn!("Dropped malformed haptics packet: {e}");
+                        continue;
+                    }
                 };
 
-                event_queue.lock().push_back(ClientCoreEvent::Haptics {
+                event_queue.push(ClientCoreEvent::Haptics {
                     device_id: haptics.device_id,
                     duration: haptics.duration,
                     frequency: haptics.frequency,
@@ -413,6 +788,122 @@ fn connection_pipeline(
         }
     });
 
+    let depth_receive_thread = thread::spawn({
+        let ctx = Arc::clone(&ctx);
+        move || {
+            let Some(mut depth_receiver) = depth_receiver else {
+                return;
+            };
+
+            while is_streaming(&ctx) {
+                let data = match depth_receiver.recv(STREAMING_RECV_TIMEOUT) {
+                    Ok(packet) => packet,
+                    Err(ConnectionError::TryAgain(_)) => continue,
+                    Err(ConnectionError::Other(_)) => return,
+                };
+                let (header, buffer) = match data.get() {
+                    Ok(packet) => packet,
+                    Err(e) => {
+                        warn!("Dropped malformed depth packet: {e}");
+                        continue;
+                    }
+                };
+
+                let Some(count) = header
+                    .view_resolution
+                    .x
+                    .checked_mul(2)
+                    .and_then(|width| width.checked_mul(header.view_resolution.y))
+                else {
+                    warn!(
+                        "Dropped depth packet with invalid resolution {}",
+                        header.view_resolution
+                    );
+                    continue;
+                };
+                let Some(data) = alvr_common::decode_depth(buffer, count as usize) else {
+                    warn!("Received an invalid depth packet");
+                    continue;
+                };
+
+                let mut depth_frames_lock = ctx.depth_frames.lock();
+                depth_frames_lock.push_back(DepthFrame {
+                    timestamp: header.timestamp,
+                    view_resolution: header.view_resolution,
+                    near_z: header.near_z,
+                    far_z: header.far_z,
+                    data,
+                });
+                if depth_frames_lock.len() > MAX_DEPTH_FRAMES {
+                    depth_frames_lock.pop_front();
+                }
+            }
+        }
+    });
+
+    let motion_vectors_receive_thread = thread::spawn({
+        let ctx = Arc::clone(&ctx);
+        move || {
+            let Some(mut motion_vectors_receiver) = motion_vectors_receiver else {
+                return;
+            };
+
+            while is_streaming(&ctx) {
+                let data = match motion_vectors_receiver.recv(STREAMING_RECV_TIMEOUT) {
+                    Ok(packet) => packet,
+                    Err(ConnectionError::TryAgain(_)) => continue,
+                    Err(ConnectionError::Other(_)) => return,
+                };
+                let (header, buffer) = match data.get() {
+                    Ok(packet) => packet,
+                    Err(e) => {
+                        warn!("Dropped malformed motion vectors packet: {e}");
+                        continue;
+                    }
+                };
+
+                // Two views of two components
+                let Some(count) = header
+                    .grid_size
+                    .x
+                    .checked_mul(header.grid_size.y)
+                    .and_then(|blocks| blocks.checked_mul(4))
+                else {
+                    warn!(
+                        "Dropped motion vectors packet with invalid grid {}",
+                        header.grid_size
+                    );
+                    continue;
+                };
+                if header.block_size == 0
+                    || header.grid_size.min_element() == 0
+                    || header.view_resolution.min_element() == 0
+                    || buffer.len() != count as usize * 2
+                {
+                    warn!("Received an invalid motion vectors packet");
+                    continue;
+                }
+                let data = buffer
+                    .chunks_exact(2)
+                    .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+                    .collect();
+
+                let mut frames_lock = ctx.motion_vectors_frames.lock();
+                frames_lock.push_back(MotionVectorsFrame {
+                    timestamp: header.timestamp,
+                    reference_timestamp: header.reference_timestamp,
+                    view_resolution: header.view_resolution,
+                    block_size: header.block_size,
+                    grid_size: header.grid_size,
+                    data,
+                });
+                if frames_lock.len() > MAX_MOTION_VECTORS_FRAMES {
+                    frames_lock.pop_front();
+                }
+            }
+        }
+    });
+
     let (log_channel_sender, log_channel_receiver) = mpsc::channel();
 
     let control_send_thread = thread::spawn({
@@ -424,6 +915,10 @@ fn connection_pipeline(
 
             #[cfg(target_os = "android")]
             let mut battery_deadline = Instant::now();
+            #[cfg(target_os = "android")]
+            let mut clipboard_deadline = Instant::now();
+            #[cfg(target_os = "android")]
+            let mut wifi_deadline = Instant::now();
 
             while is_streaming(&ctx) && *lifecycle_state.read() == LifecycleState::Resumed {
                 if let Ok(packet) = log_channel_receiver.recv_timeout(STREAMING_RECV_TIMEOUT)
@@ -439,9 +934,14 @@ fn connection_pipeline(
                 if Instant::now() > keepalive_deadline
                     && let Some(sender) = &mut *ctx.control_sender.lock()
                 {
-                    sender.send(&ClientControlPacket::KeepAlive).ok();
+                    if let Err(e) = sender.send(&ClientControlPacket::KeepAlive) {
+                        info!("Server disconnected. Cause: {e:?}");
+                        set_hud_message(&event_queue, SERVER_DISCONNECTED_MESSAGE);
 
-                    keepalive_deadline = Instant::now() + KEEPALIVE_INTERVAL;
+                        break;
+                    }
+
+                    keepalive_deadline = Instant::now() + keepalive_interval;
                 }
 
                 #[cfg(target_os = "android")]
@@ -457,8 +957,66 @@ fn connection_pipeline(
                             .ok();
                     }
 
+                    if let Some(status) = alvr_system_info::get_thermal_status()
+                        && let Some(sender) = &mut *ctx.control_sender.lock()
+                    {
+                        use alvr_packets::ThermalStatus;
+
+                        let status = match status {
+                            0 => ThermalStatus::None,
+                            1 => ThermalStatus::Light,
+                            2 => ThermalStatus::Moderate,
+                            3 => ThermalStatus::Severe,
+                            4 => ThermalStatus::Critical,
+                            5 => ThermalStatus::Emergency,
+                            _ => ThermalStatus::Shutdown,
+                        };
+                        sender
+                            .send(&ClientControlPacket::ThermalStatus(status))
+                            .ok();
+                    }
+
                     battery_deadline = Instant::now() + Duration::from_secs(5);
                 }
+
+                #[cfg(target_os = "android")]
+                if Instant::now() > clipboard_deadline {
+                    let maybe_text = ctx.clipboard_sync.lock().as_mut().and_then(|sync| {
+                        if sync.sends_local_changes()
+                            && let Some(text) = alvr_system_info::get_clipboard_text()
+                        {
+                            sync.report_local_text(text, Instant::now());
+                        }
+
+                        sync.poll_outgoing_text(Instant::now())
+                    });
+                    if let Some(text) = maybe_text
+                        && let Some(sender) = &mut *ctx.control_sender.lock()
+                    {
+                        sender.send(&ClientControlPacket::Clipboard(text)).ok();
+                    }
+
+                    clipboard_deadline = Instant::now() + alvr_common::CLIPBOARD_POLL_INTERVAL;
+                }
+
+                #[cfg(target_os = "android")]
+                if Instant::now() > wifi_deadline {
+                    let info = alvr_system_info::get_wifi_connection().map(|connection| {
+                        alvr_packets::WifiLinkInfo {
+                            frequency_mhz: connection.frequency_mhz,
+                            channel_width_mhz: connection.channel_width_mhz,
+                            rssi_dbm: connection.rssi_dbm,
+                            tx_link_speed_mbps: connection.tx_link_speed_mbps,
+                            rx_link_speed_mbps: connection.rx_link_speed_mbps,
+                            bssid: connection.bssid,
+                        }
+                    });
+                    if let Some(sender) = &mut *ctx.control_sender.lock() {
+                        sender.send(&ClientControlPacket::WifiLinkInfo(info)).ok();
+                    }
+
+                    wifi_deadline = Instant::now() + WIFI_POLL_INTERVAL;
+                }
             }
 
             disconnect_notif.notify_one();
@@ -470,38 +1028,155 @@ fn connection_pipeline(
         let event_queue = Arc::clone(&event_queue);
         let disconnect_notif = Arc::clone(&disconnect_notif);
         move || {
-            let mut disconnection_deadline = Instant::now() + KEEPALIVE_TIMEOUT;
+            let mut liveness = PeerLiveness::new(keepalive_timeout, Instant::now());
+            // The server assumes the streams are not blocked until they are reported
+            let mut reported_biometrics_blocked = false;
+            let mut restart_acked = false;
             while is_streaming(&ctx) {
                 let maybe_packet = control_receiver.recv(STREAMING_RECV_TIMEOUT);
 
                 match maybe_packet {
                     Ok(ServerControlPacket::DecoderConfig(config)) => {
-                        event_queue
-                            .lock()
-                            .push_back(ClientCoreEvent::DecoderConfig {
-                                codec: config.codec,
-                                config_nal: config.config_buffer,
-                            });
+                        event_queue.push(ClientCoreEvent::DecoderConfig {
+                            codec: config.codec,
+                            config_nal: config.config_buffer,
+                        });
                     }
                     Ok(ServerControlPacket::Restarting) => {
                         info!("{SERVER_RESTART_MESSAGE}");
                         set_hud_message(&event_queue, SERVER_RESTART_MESSAGE);
+                        *ctx.server_restarting.lock() = true;
                         disconnect_notif.notify_one();
                     }
+                    Ok(ServerControlPacket::DriverRestarting) => {
+                        info!("{SERVER_RESTART_MESSAGE}");
+                        set_hud_message(&event_queue, SERVER_RESTART_MESSAGE);
+                        *ctx.server_restarting.lock() = true;
+                        // Nothing else is sent to the current driver after the ack
+                        if let Some(mut sender) = ctx.control_sender.lock().take() {
+                            restart_acked = sender.send(&ClientControlPacket::RestartAck).is_ok();
+                        }
+                        disconnect_notif.notify_one();
+
+                        break;
+                    }
+                    Ok(ServerControlPacket::DriverResumed) => {
+                        error!("Unexpected DriverResumed packet");
+                    }
                     Ok(ServerControlPacket::RealTimeConfig(config)) => {
-                        event_queue
-                            .lock()
-                            .push_back(ClientCoreEvent::RealTimeConfig(config));
+                        let blocked = config.block_biometric_streams;
+                        *ctx.biometric_streams_blocked.lock() = blocked;
+                        if blocked != reported_biometrics_blocked
+                            && let Some(sender) = &mut *ctx.control_sender.lock()
+                        {
+                            info!(
+                                "Biometric streams {}",
+                                if blocked { "blocked" } else { "unblocked" }
+                            );
+                            sender
+                                .send(&ClientControlPacket::BiometricStreamsBlocked(blocked))
+                                .ok();
+                            reported_biometrics_blocked = blocked;
+                        }
+
+                        event_queue.push(ClientCoreEvent::RealTimeConfig(config));
                     }
                     Ok(ServerControlPacket::StartStream) => {
                         error!("Unexpected StartStream paceket");
                     }
                     Ok(ServerControlPacket::KeepAlive) => (),
+                    Ok(ServerControlPacket::RequestVideoLossReport) => {
+                        let report = mem::take(&mut *ctx.video_loss_report.lock());
+                        if let Some(sender) = &mut *ctx.control_sender.lock() {
+                            sender
+                                .send(&ClientControlPacket::VideoLossReport(report))
+                                .ok();
+                        }
+                    }
+                    Ok(ServerControlPacket::LatencyTestFlash(timestamp)) => {
+                        *ctx.latency_test_flash_after.lock() = Some(timestamp);
+                    }
+                    Ok(ServerControlPacket::ServerMacAddress(mac_address)) => {
+                        let mut config = Config::load();
+                        if config.server_mac_address != Some(mac_address) {
+                            config.server_mac_address = Some(mac_address);
+                            config.store();
+                        }
+
+                        ServerStorage::update(server_ip, |entry| {
+                            entry.mac_address = Some(mac_address);
+                        });
+                    }
+                    Ok(ServerControlPacket::RecenteringOrigin(origin)) => {
+                        let playspace_area = *ctx.playspace_area.lock();
+                        ServerStorage::update(server_ip, |entry| {
+                            entry.recentering = Some(StoredRecentering {
+                                origin,
+                                playspace_area,
+                            });
+                        });
+                    }
+                    Ok(ServerControlPacket::DepthSubmission(submitted)) => {
+                        *ctx.depth_stream_paused.lock() = !submitted;
+                        if !submitted {
+                            ctx.depth_frames.lock().clear();
+                            ctx.motion_vectors_frames.lock().clear();
+                        }
+                    }
+                    Ok(ServerControlPacket::RequestClientLog) => {
+                        let log = logging_backend::read_log_files();
+                        if let Some(sender) = &mut *ctx.control_sender.lock() {
+                            sender.send(&ClientControlPacket::ClientLog(log)).ok();
+                        }
+                    }
+                    Ok(ServerControlPacket::StartTraceCapture) => {
+                        alvr_common::start_trace_capture(statistics::client_time());
+                    }
+                    Ok(ServerControlPacket::StopTraceCapture) => {
+                        let spans = alvr_common::stop_trace_capture();
+                        if let Some(sender) = &mut *ctx.control_sender.lock() {
+                            sender.send(&ClientControlPacket::TraceSpans(spans)).ok();
+                        }
+                    }
+                    Ok(ServerControlPacket::TimeSyncRequest(server_time)) => {
+                        let client_time = statistics::client_time();
+                        if let Some(sender) = &mut *ctx.control_sender.lock() {
+                            sender
+                                .send(&ClientControlPacket::TimeSyncResponse {
+                                    server_time,
+                                    client_time,
+                                })
+                                .ok();
+                        }
+                    }
+                    Ok(ServerControlPacket::SetMicrophoneMuted(muted)) => {
+                        info!("Microphone {}", if muted { "muted" } else { "unmuted" });
+                        *ctx.microphone_muted.lock() = muted;
+                    }
+                    Ok(ServerControlPacket::StandbyDisconnect) => {
+                        info!("{STANDBY_DISCONNECT_MESSAGE}");
+                        set_hud_message(&event_queue, STANDBY_DISCONNECT_MESSAGE);
+                        *ctx.standby_disconnected.lock() = true;
+                        disconnect_notif.notify_one();
+                    }
+                    #[cfg(target_os = "android")]
+                    Ok(ServerControlPacket::Clipboard(text)) => {
+                        if let Some(text) = ctx
+                            .clipboard_sync
+                            .lock()
+                            .as_mut()
+                            .and_then(|sync| sync.receive_remote_text(text))
+                        {
+                            alvr_system_info::set_clipboard_text(&text);
+                        }
+                    }
+                    #[cfg(not(target_os = "android"))]
+                    Ok(ServerControlPacket::Clipboard(_)) => (),
                     Ok(
                         ServerControlPacket::Reserved(_) | ServerControlPacket::ReservedBuffer(_),
                     ) => {}
                     Err(ConnectionError::TryAgain(_)) => {
-                        if Instant::now() > disconnection_deadline {
+                        if liveness.is_dead(Instant::now()) {
                             info!("{CONNECTION_TIMEOUT_MESSAGE}");
                             set_hud_message(&event_queue, CONNECTION_TIMEOUT_MESSAGE);
                             disconnect_notif.notify_one();
@@ -516,8 +1191,10 @@ fn connection_pipeline(
                     }
                 }
 
-                disconnection_deadline = Instant::now() + KEEPALIVE_TIMEOUT;
+                liveness.report_received(Instant::now());
             }
+
+            (control_receiver, restart_acked)
         }
     });
 
@@ -543,6 +1220,8 @@ fn connection_pipeline(
     *ctx.control_sender.lock() = Some(control_sender);
     *ctx.tracking_sender.lock() = Some(tracking_sender);
     *ctx.statistics_sender.lock() = Some(statistics_sender);
+    *ctx.peripheral_input_sender.lock() = peripheral_input_sender;
+    *ctx.clipboard_sync.lock() = clipboard_sync;
     if let Switch::Enabled(filter_level) = settings.extra.logging.client_log_report_level {
         *LOG_CHANNEL_SENDER.lock() = Some(LogMirrorData {
             sender: log_channel_sender,
@@ -550,7 +1229,7 @@ fn connection_pipeline(
             debug_groups_config: settings.extra.logging.debug_groups,
         });
     }
-    event_queue.lock().push_back(streaming_start_event);
+    event_queue.push(streaming_start_event);
 
     *connection_state_lock = ConnectionState::Streaming;
 
@@ -562,13 +1241,15 @@ fn connection_pipeline(
     *connection_state_lock = ConnectionState::Disconnecting;
 
     *ctx.control_sender.lock() = None;
+    *ctx.server_address.lock() = None;
     *ctx.tracking_sender.lock() = None;
     *ctx.statistics_sender.lock() = None;
+    *ctx.peripheral_input_sender.lock() = None;
+    *ctx.clipboard_sync.lock() = None;
+    *ctx.latency_test_flash_after.lock() = None;
     *LOG_CHANNEL_SENDER.lock() = None;
 
-    event_queue
-        .lock()
-        .push_back(ClientCoreEvent::StreamingStopped);
+    event_queue.push(ClientCoreEvent::StreamingStopped);
 
     // Remove lock to allow threads to properly exit:
     drop(connection_state_lock);
@@ -579,10 +1260,19 @@ fn connection_pipeline(
     game_audio_thread.join().ok();
     microphone_thread.join().ok();
     haptics_receive_thread.join().ok();
+    depth_receive_thread.join().ok();
+    motion_vectors_receive_thread.join().ok();
     control_send_thread.join().ok();
-    control_receive_thread.join().ok();
+    if let Ok((control_receiver, true)) = control_receive_thread.join() {
+        keep_connection(&ctx, control_receiver, udp_socket, server_ip);
+    }
     stream_receive_thread.join().ok();
 
+    ctx.depth_frames.lock().clear();
+    ctx.motion_vectors_frames.lock().clear();
+    // In case the connection was lost during a capture
+    alvr_common::stop_trace_capture();
+
     dbg_connection!("connection_pipeline: End");
 
     Ok(())
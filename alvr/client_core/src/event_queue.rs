@@ -0,0 +1,45 @@
+use crate::ClientCoreEvent;
+use alvr_common::parking_lot::Mutex;
+use std::{
+    collections::VecDeque,
+    sync::mpsc::{self, Receiver, Sender},
+};
+
+// Events are kept for poll_event() until the first subscription. From then on they are sent to
+// the subscribers only, so that the queue does not grow when it is never polled.
+#[derive(Default)]
+pub struct EventQueue {
+    queue: Mutex<VecDeque<ClientCoreEvent>>,
+    subscribers: Mutex<Vec<Sender<ClientCoreEvent>>>,
+}
+
+impl EventQueue {
+    pub fn push(&self, event: ClientCoreEvent) {
+        let subscribers = &mut *self.subscribers.lock();
+        if subscribers.is_empty() {
+            self.queue.lock().push_back(event);
+
+            return;
+        }
+
+        // Dropped receivers are removed
+        subscribers.retain(|sender| sender.send(event.clone()).is_ok());
+    }
+
+    pub fn pop(&self) -> Option<ClientCoreEvent> {
+        self.queue.lock().pop_front()
+    }
+
+    // The events not yet polled are moved to the new receiver
+    pub fn subscribe(&self) -> Receiver<ClientCoreEvent> {
+        let (sender, receiver) = mpsc::channel();
+
+        let subscribers = &mut *self.subscribers.lock();
+        for event in self.queue.lock().drain(..) {
+            sender.send(event).ok();
+        }
+        subscribers.push(sender);
+
+        receiver
+    }
+}
@@ -1,3 +1,28 @@
+//! Streaming client of ALVR, without the XR API code. It can be embedded in a custom headset app
+//! that drives it from its own render loop:
+//!
+//! 1. Create a [`ClientCoreContext`] with the [`ClientCapabilities`] of the device and call
+//!    [`ClientCoreContext::resume`]. The server is discovered and connected in a background thread.
+//! 2. Call [`ClientCoreContext::poll_event`] every frame, or receive the events on a channel
+//!    returned by [`ClientCoreContext::subscribe_events`] if the frontend has no frame loop.
+//!    [`ClientCoreEvent::StreamingStarted`] carries the stream configuration,
+//!    [`ClientCoreEvent::DecoderConfig`] the codec to use.
+//! 3. Submit the device poses with [`ClientCoreContext::send_view_params`] and
+//!    [`ClientCoreContext::send_tracking`], and the input with [`ClientCoreContext::send_buttons`].
+//! 4. Create a decoder with [`video_decoder::create_decoder`], register it with
+//!    [`ClientCoreContext::set_decoder`] and pull the decoded frames from the
+//!    [`video_decoder::VideoDecoderSource`]. A frontend with its own decoder and graphics interop
+//!    implements [`VideoDecoderInput`] instead. Report each step of the frame with the `report_*`
+//!    methods to keep the latency statistics and the frame pacing working.
+//! 5. Call [`ClientCoreContext::pause`] when the app is not visible, and drop the context on exit.
+//!
+//! The tracking can come from any source, the poses are only expected in the tracking space of
+//! the device. The mock client in `alvr/client_mock` is a minimal desktop frontend, which decodes
+//! the video to a window with a software decoder and sends synthetic head poses.
+//!
+//! The Rust API is versioned separately from ALVR and follows semver. The network protocol is
+//! versioned with ALVR, so the client and the server must still use the same ALVR version.
+
 #![allow(
     non_upper_case_globals,
     non_snake_case,
@@ -7,10 +32,12 @@
 
 mod c_api;
 mod connection;
+mod event_queue;
 mod logging_backend;
 mod sockets;
 mod statistics;
 mod storage;
+mod video_content;
 
 #[cfg(target_os = "android")]
 mod audio;
@@ -18,27 +45,73 @@ mod audio;
 pub mod video_decoder;
 
 use alvr_common::{
-    ConnectionState, LifecycleState, ViewParams, dbg_client_core, error,
+    ConnectionState, HEAD_ID, LifecycleState, dbg_client_core, error,
     glam::{UVec2, Vec2},
+    info,
     parking_lot::{Mutex, RwLock},
     warn,
 };
-use alvr_packets::{
-    BatteryInfo, ButtonEntry, ClientControlPacket, RealTimeConfig, StreamConfig, TrackingData,
-};
-use alvr_session::CodecType;
-use alvr_system_info::Platform;
-use connection::{ConnectionContext, DecoderCallback};
+use alvr_packets::{BatteryInfo, ClientControlPacket};
+use connection::ConnectionContext;
+use event_queue::EventQueue;
 use std::{
-    collections::{HashSet, VecDeque},
-    sync::Arc,
+    collections::HashSet,
+    sync::{Arc, mpsc},
     thread::{self, JoinHandle},
-    time::Duration,
+    time::{Duration, Instant},
 };
-use storage::Config;
+use storage::{Config, MarkerAnchorStorage, ServerStorage};
 
+pub use alvr_common::{DeviceMotion, Fov, Pose, ViewParams};
+pub use alvr_packets::{
+    ButtonEntry, ButtonValue, FaceData, InHeadsetMenuAction, PeripheralInput, RealTimeConfig,
+    StreamConfig, TrackingData, TrackingSpace,
+};
+pub use alvr_session::{
+    ButtonMacroAction, ClientGraphicsApi, CodecType, FoveatedEncodingEyeConfig,
+};
+pub use alvr_system_info::Platform;
+pub use connection::DecoderCallback;
 pub use logging_backend::init_logging;
 
+/// To be called before creating the OpenXR instance. Returns true if the optional extensions
+/// should not be enabled, because safe mode is set or the previous startup did not complete.
+pub fn begin_startup() -> bool {
+    let mut config = Config::load();
+
+    if config.startup_pending {
+        warn!("The previous startup did not complete. Starting in safe mode");
+    }
+    let safe_mode = config.safe_mode || config.startup_pending;
+
+    config.startup_pending = true;
+    config.store();
+
+    safe_mode
+}
+
+/// Graphics API to use for the session, set in the streamer settings of the last connection
+pub fn graphics_api() -> ClientGraphicsApi {
+    Config::load().graphics_api
+}
+
+/// Whether the markers can be chosen as origin in the lobby, as set on the last connection
+pub fn lobby_marker_selection() -> bool {
+    Config::load().lobby_marker_selection
+}
+
+/// To be called once the app has started successfully
+pub fn complete_startup() {
+    let mut config = Config::load();
+    if config.startup_pending {
+        config.startup_pending = false;
+        config.store();
+    }
+}
+
+/// Events polled with [`ClientCoreContext::poll_event`] or received from
+/// [`ClientCoreContext::subscribe_events`]
+#[derive(Clone)]
 pub enum ClientCoreEvent {
     UpdateHudMessage(String),
     StreamingStarted(Box<StreamConfig>),
@@ -57,7 +130,22 @@ pub enum ClientCoreEvent {
     RealTimeConfig(RealTimeConfig),
 }
 
-// Note: this struct may change without breaking network protocol changes
+/// Input of a video decoder owned by the frontend. It is implemented by
+/// [`video_decoder::VideoDecoderSink`] and by closures
+pub trait VideoDecoderInput: Send {
+    /// Returns true if the frame was successfully submitted to the decoder. The decoded frame must
+    /// be reported with [`ClientCoreContext::report_frame_decoded`]
+    fn push_nal(&mut self, timestamp: Duration, nal: &[u8]) -> bool;
+}
+
+impl<F: FnMut(Duration, &[u8]) -> bool + Send> VideoDecoderInput for F {
+    fn push_nal(&mut self, timestamp: Duration, nal: &[u8]) -> bool {
+        self(timestamp, nal)
+    }
+}
+
+/// Features supported by the device, sent to the server during the handshake.
+/// Note: this struct may change without breaking network protocol changes
 #[derive(Clone)]
 pub struct ClientCapabilities {
     pub platform: Platform,
@@ -71,18 +159,62 @@ pub struct ClientCapabilities {
     pub prefer_10bit: bool,
     pub preferred_encoding_gamma: f32,
     pub prefer_hdr: bool,
+    pub depth_layers: bool,
+    pub space_warp: bool,
+    pub decoder_chroma_422: bool,
+    pub decoder_chroma_444: bool,
+    pub radial_foveated_encoding: bool,
+    pub per_eye_foveated_encoding: bool,
+    pub flat_display: bool,
+}
+
+/// Low resolution depth of both views side by side, row by row, scaled to u16. Depth 0 maps to the
+/// view distance near_z and depth 1 to far_z, as in XR_KHR_composition_layer_depth
+#[derive(Clone)]
+pub struct DepthFrame {
+    pub timestamp: Duration,
+    pub view_resolution: UVec2,
+    pub near_z: f32,
+    pub far_z: f32,
+    pub data: Vec<u16>,
+}
+
+/// Motion vectors of the blocks of both views, left view first, row by row, as pairs of quarter
+/// pixels of view_resolution. Each vector points to the match of the block in the frame with
+/// reference_timestamp. Blocks without a match have alvr_packets::INVALID_MOTION_VECTOR as x
+#[derive(Clone)]
+pub struct MotionVectorsFrame {
+    pub timestamp: Duration,
+    pub reference_timestamp: Duration,
+    pub view_resolution: UVec2,
+    pub block_size: u32,
+    pub grid_size: UVec2,
+    pub data: Vec<i16>,
+}
+
+/// Encoder statistics of a video frame, stamped by the server for diagnostics
+#[derive(Clone, Copy, Debug)]
+pub struct FrameEncoderStats {
+    pub is_idr: bool,
+    /// None if the encoder doesn't report it
+    pub average_qp: Option<u32>,
+    /// From the composition of the frame to the end of the encoding
+    pub encode_duration: Duration,
 }
 
+/// Handle of the client. All methods can be called from any thread.
 pub struct ClientCoreContext {
     platform: Platform,
     lifecycle_state: Arc<RwLock<LifecycleState>>,
-    event_queue: Arc<Mutex<VecDeque<ClientCoreEvent>>>,
+    event_queue: Arc<EventQueue>,
     connection_context: Arc<ConnectionContext>,
     connection_thread: Arc<Mutex<Option<JoinHandle<()>>>>,
     last_good_global_view_params: Mutex<[ViewParams; 2]>,
+    displayed_frame_encoder_stats: Mutex<Option<FrameEncoderStats>>,
 }
 
 impl ClientCoreContext {
+    /// Starts the connection thread. No connection is attempted before [`Self::resume`]
     pub fn new(capabilities: ClientCapabilities) -> Self {
         dbg_client_core!("Create");
 
@@ -102,7 +234,7 @@ impl ClientCoreContext {
         let platform = capabilities.platform;
 
         let lifecycle_state = Arc::new(RwLock::new(LifecycleState::Idle));
-        let event_queue = Arc::new(Mutex::new(VecDeque::new()));
+        let event_queue = Arc::new(EventQueue::default());
         let connection_context = Arc::new(ConnectionContext::default());
         let connection_thread = thread::spawn({
             let lifecycle_state = Arc::clone(&lifecycle_state);
@@ -125,15 +257,18 @@ impl ClientCoreContext {
             connection_context,
             connection_thread: Arc::new(Mutex::new(Some(connection_thread))),
             last_good_global_view_params: Mutex::new([ViewParams::DUMMY; 2]),
+            displayed_frame_encoder_stats: Mutex::new(None),
         }
     }
 
+    /// Allows the connection to the server
     pub fn resume(&self) {
         dbg_client_core!("resume");
 
         *self.lifecycle_state.write() = LifecycleState::Resumed;
     }
 
+    /// Stops the stream and waits for the disconnection
     pub fn pause(&self) {
         dbg_client_core!("pause");
 
@@ -150,12 +285,23 @@ impl ClientCoreContext {
         }
     }
 
+    /// Returns the next event, if any. Should be called every frame. Always returns None after
+    /// [`Self::subscribe_events`] has been called
     pub fn poll_event(&self) -> Option<ClientCoreEvent> {
         dbg_client_core!("poll_event");
 
-        self.event_queue.lock().pop_front()
+        self.event_queue.pop()
     }
 
+    /// Returns a channel receiving every following event, starting with the ones not yet polled.
+    /// There can be multiple subscribers, each receives a copy of the events
+    pub fn subscribe_events(&self) -> mpsc::Receiver<ClientCoreEvent> {
+        dbg_client_core!("subscribe_events");
+
+        self.event_queue.subscribe()
+    }
+
+    /// The gauge value is between 0 and 1
     pub fn send_battery(&self, device_id: u64, gauge_value: f32, is_plugged: bool) {
         dbg_client_core!("send_battery");
 
@@ -170,14 +316,74 @@ impl ClientCoreContext {
         }
     }
 
-    pub fn send_playspace(&self, area: Option<Vec2>) {
+    /// The perimeter points are on the floor of the tracking space, as (x, z) pairs
+    pub fn send_playspace(&self, area: Option<Vec2>, perimeter: Vec<Vec2>) {
         dbg_client_core!("send_playspace");
 
+        *self.connection_context.playspace_area.lock() = area;
+
+        if let Some(sender) = &mut *self.connection_context.control_sender.lock() {
+            sender
+                .send(&ClientControlPacket::PlayspaceSync { area, perimeter })
+                .ok();
+
+            // The server recenters on PlayspaceSync, the stored origin is restored after it
+            if let Some(address) = *self.connection_context.server_address.lock()
+                && let Some(recentering) = ServerStorage::load()
+                    .get(address)
+                    .and_then(|entry| entry.recentering)
+                    .filter(|recentering| recentering.matches_playspace(area))
+            {
+                sender
+                    .send(&ClientControlPacket::RestoreRecentering(recentering.origin))
+                    .ok();
+            }
+        }
+    }
+
+    /// Reference space the device poses are sent in
+    pub fn send_tracking_space(&self, space: TrackingSpace) {
+        dbg_client_core!("send_tracking_space");
+
         if let Some(sender) = &mut *self.connection_context.control_sender.lock() {
-            sender.send(&ClientControlPacket::PlayspaceSync(area)).ok();
+            sender.send(&ClientControlPacket::TrackingSpace(space)).ok();
         }
     }
 
+    /// Actions of the in-headset menu that are handled by the server
+    pub fn send_in_headset_menu_action(&self, action: InHeadsetMenuAction) {
+        dbg_client_core!("send_in_headset_menu_action");
+
+        if let Some(sender) = &mut *self.connection_context.control_sender.lock() {
+            sender
+                .send(&ClientControlPacket::InHeadsetMenu(action))
+                .ok();
+        }
+    }
+
+    /// Actions triggered by the client gestures, handled like the button macros of the server
+    pub fn send_streamer_action(&self, action: ButtonMacroAction) {
+        dbg_client_core!("send_streamer_action");
+
+        if let Some(sender) = &mut *self.connection_context.control_sender.lock() {
+            sender
+                .send(&ClientControlPacket::StreamerAction(action))
+                .ok();
+        }
+    }
+
+    /// Lets the server check that the views are unwarped with its foveation parameters
+    pub fn send_foveated_encoding_applied(&self, eyes: Option<[FoveatedEncodingEyeConfig; 2]>) {
+        dbg_client_core!("send_foveated_encoding_applied");
+
+        if let Some(sender) = &mut *self.connection_context.control_sender.lock() {
+            sender
+                .send(&ClientControlPacket::FoveatedEncodingApplied(eyes))
+                .ok();
+        }
+    }
+
+    /// Identifies the controller model in use and the buttons it has
     pub fn send_active_interaction_profile(
         &self,
         device_id: u64,
@@ -197,6 +403,7 @@ impl ClientCoreContext {
         }
     }
 
+    /// Sends the changed button values
     pub fn send_buttons(&self, entries: Vec<ButtonEntry>) {
         dbg_client_core!("send_buttons");
 
@@ -205,7 +412,7 @@ impl ClientCoreContext {
         }
     }
 
-    // These must be in its local space, as if the head pose is in the origin.
+    /// These must be in its local space, as if the head pose is in the origin.
     pub fn send_view_params(&self, views: [ViewParams; 2]) {
         dbg_client_core!("send_view_params");
 
@@ -216,9 +423,23 @@ impl ClientCoreContext {
         }
     }
 
-    pub fn send_tracking(&self, data: TrackingData) {
+    /// Sends the device poses. The poll timestamp is the target display time of the frame
+    pub fn send_tracking(&self, mut data: TrackingData) {
         dbg_client_core!("send_tracking");
 
+        // Also dropped here in case the data was sampled before the setting changed
+        if *self.connection_context.biometric_streams_blocked.lock() {
+            data.face = FaceData::default();
+            data.body = None;
+        }
+
+        if let Some((_, motion)) = data.device_motions.iter().find(|(id, _)| *id == *HEAD_ID) {
+            self.connection_context
+                .video_content_detector
+                .lock()
+                .report_head_motion(motion.angular_velocity.length(), Instant::now());
+        }
+
         if let Some(sender) = &mut *self.connection_context.tracking_sender.lock() {
             sender.send_header(&data).ok();
 
@@ -228,6 +449,20 @@ impl ClientCoreContext {
         }
     }
 
+    /// Whether the face, eye and body data must not be sampled, set by the privacy setting
+    pub fn biometric_streams_blocked(&self) -> bool {
+        *self.connection_context.biometric_streams_blocked.lock()
+    }
+
+    /// Whether video content is detected in front of a still head. Used to reduce the sharpening
+    pub fn video_content_detected(&self) -> bool {
+        self.connection_context
+            .video_content_detector
+            .lock()
+            .detected()
+    }
+
+    /// Whether the headset is worn
     pub fn send_proximity_state(&self, headset_is_worn: bool) {
         if let Some(sender) = &mut *self.connection_context.control_sender.lock() {
             sender
@@ -236,6 +471,118 @@ impl ClientCoreContext {
         }
     }
 
+    /// The headset is not worn or the app is not visible
+    pub fn send_standby_state(&self, in_standby: bool) {
+        dbg_client_core!("send_standby_state");
+
+        if !in_standby {
+            *self.connection_context.standby_disconnected.lock() = false;
+        }
+
+        if let Some(sender) = &mut *self.connection_context.control_sender.lock() {
+            sender
+                .send(&ClientControlPacket::StandbyState(in_standby))
+                .ok();
+        }
+    }
+
+    /// Clears the connection history, the recentering and the Wake-on-LAN address stored for the
+    /// streamers
+    pub fn forget_servers(&self) {
+        dbg_client_core!("forget_servers");
+
+        info!("Forgetting the streamers");
+
+        ServerStorage::clear();
+
+        let mut config = Config::load();
+        config.server_mac_address = None;
+        config.store();
+
+        if *self.connection_context.state.read() == ConnectionState::Disconnected {
+            connection::set_initial_hud_message(&self.event_queue);
+        }
+    }
+
+    /// Returns false if no server was connected before
+    pub fn wake_server(&self) -> bool {
+        dbg_client_core!("wake_server");
+
+        let Some(mac_address) = Config::load().server_mac_address else {
+            return false;
+        };
+
+        info!("Sending Wake-on-LAN packet");
+        if let Err(e) = sockets::send_wake_on_lan(mac_address) {
+            warn!("Failed to send Wake-on-LAN packet: {e}");
+        }
+
+        true
+    }
+
+    /// Pose of the origin marker in the tracking reference space
+    pub fn send_marker_origin(&self, marker_pose: Pose) {
+        dbg_client_core!("send_marker_origin");
+
+        if let Some(sender) = &mut *self.connection_context.control_sender.lock() {
+            sender
+                .send(&ClientControlPacket::MarkerOrigin(marker_pose))
+                .ok();
+        }
+    }
+
+    /// Pose of the marker stored by store_marker_anchor(), in the tracking space without the tracking
+    /// origin
+    pub fn stored_marker_anchor(
+        &self,
+        tracking_space: TrackingSpace,
+        marker_code: &str,
+    ) -> Option<Pose> {
+        dbg_client_core!("stored_marker_anchor");
+
+        MarkerAnchorStorage::load().get(tracking_space, marker_code)
+    }
+
+    /// Remembers the pose of the marker for the next sessions
+    pub fn store_marker_anchor(
+        &self,
+        tracking_space: TrackingSpace,
+        marker_code: &str,
+        pose: Pose,
+    ) {
+        dbg_client_core!("store_marker_anchor");
+
+        let mut storage = MarkerAnchorStorage::load();
+        storage.set(tracking_space, marker_code, pose);
+        storage.store();
+    }
+
+    /// Returns false if not streaming or if keyboard and mouse passthrough is disabled
+    pub fn send_peripheral_input(&self, input: PeripheralInput) -> bool {
+        dbg_client_core!("send_peripheral_input");
+
+        let Some(sender) = &mut *self.connection_context.peripheral_input_sender.lock() else {
+            return false;
+        };
+
+        // A lost release would leave the key or button stuck on the server
+        if matches!(
+            input,
+            PeripheralInput::Key { .. } | PeripheralInput::MouseButton { .. }
+        ) {
+            if let Some(control_sender) = &mut *self.connection_context.control_sender.lock() {
+                control_sender
+                    .send(&ClientControlPacket::PeripheralInput(input))
+                    .ok();
+            }
+        } else {
+            sender.send_header(&input).ok();
+        }
+
+        true
+    }
+
+    /// Time from the tracking poll to the display of the frame, used to predict the poses
     pub fn get_total_prediction_offset(&self) -> Duration {
         dbg_client_core!("get_total_prediction_offset");
 
@@ -251,15 +598,22 @@ impl ClientCoreContext {
 
     /// The callback should return true if the frame was successfully submitted to the decoder
     pub fn set_decoder_input_callback(&self, callback: Box<DecoderCallback>) {
-        dbg_client_core!("set_decoder_input_callback");
+        self.set_decoder(callback);
+    }
+
+    /// Replaces the decoder that receives the video frames. An IDR frame is requested so that the
+    /// new decoder can start decoding
+    pub fn set_decoder(&self, decoder: impl VideoDecoderInput + 'static) {
+        dbg_client_core!("set_decoder");
 
-        *self.connection_context.decoder_callback.lock() = Some(callback);
+        *self.connection_context.decoder_input.lock() = Some(Box::new(decoder));
 
         if let Some(sender) = &mut *self.connection_context.control_sender.lock() {
             sender.send(&ClientControlPacket::RequestIdr).ok();
         }
     }
 
+    /// Call when a frame comes out of the decoder
     pub fn report_frame_decoded(&self, timestamp: Duration) {
         dbg_client_core!("report_frame_decoded");
 
@@ -268,6 +622,28 @@ impl ClientCoreContext {
         }
     }
 
+    /// Returns false if the frame was decoded during an intra refresh cycle and should be skipped
+    pub fn is_frame_displayable(&self, timestamp: Duration) -> bool {
+        self.connection_context
+            .frames_hidden_until
+            .lock()
+            .is_none_or(|hidden_until| timestamp >= hidden_until)
+    }
+
+    /// Returns true for the first displayed frame that should be flashed for the latency test
+    pub fn start_latency_test_flash(&self, timestamp: Duration) -> bool {
+        let flash_after_lock = &mut *self.connection_context.latency_test_flash_after.lock();
+
+        if flash_after_lock.is_some_and(|flash_after| timestamp > flash_after) {
+            *flash_after_lock = None;
+
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The connection is restarted
     pub fn report_fatal_decoder_error(&self, error: &str) {
         error!("Fatal decoder error, restarting connection: {error}");
 
@@ -275,24 +651,94 @@ impl ClientCoreContext {
         *self.connection_context.state.write() = ConnectionState::Disconnecting;
     }
 
+    /// Call before rendering a decoded frame. Returns the view params the frame was rendered with
     pub fn report_compositor_start(&self, timestamp: Duration) -> [ViewParams; 2] {
+        self.try_report_compositor_start(timestamp)
+            .unwrap_or_else(|| *self.last_good_global_view_params.lock())
+    }
+
+    /// Like [`Self::report_compositor_start`], but returns None if the view params of the frame
+    /// are not known anymore, instead of the view params of the last frame they were found for
+    pub fn try_report_compositor_start(&self, timestamp: Duration) -> Option<[ViewParams; 2]> {
         dbg_client_core!("report_compositor_start");
 
         if let Some(stats) = &mut *self.connection_context.statistics_manager.lock() {
             stats.report_compositor_start(timestamp);
         }
 
-        let global_view_params_lock = &mut *self.last_good_global_view_params.lock();
-        for (ts, params) in &*self.connection_context.global_view_params_queue.lock() {
-            if *ts == timestamp {
-                *global_view_params_lock = *params;
-                break;
-            }
+        let view_params = self
+            .connection_context
+            .global_view_params_queue
+            .lock()
+            .iter()
+            .find(|(ts, _)| *ts == timestamp)
+            .map(|(_, params)| *params);
+        if let Some(params) = view_params {
+            *self.last_good_global_view_params.lock() = params;
         }
 
-        *global_view_params_lock
+        if let Some((_, stats)) = self
+            .connection_context
+            .frame_encoder_stats_queue
+            .lock()
+            .iter()
+            .find(|(ts, _)| *ts == timestamp)
+        {
+            *self.displayed_frame_encoder_stats.lock() = Some(*stats);
+        }
+
+        view_params
+    }
+
+    /// Encoder statistics of the last frame passed to [`Self::report_compositor_start`]
+    pub fn displayed_frame_encoder_stats(&self) -> Option<FrameEncoderStats> {
+        *self.displayed_frame_encoder_stats.lock()
+    }
+
+    /// Returns the depth of the frame with this timestamp, if the depth stream was negotiated and
+    /// the depth has been received
+    pub fn depth_frame(&self, timestamp: Duration) -> Option<DepthFrame> {
+        self.connection_context
+            .depth_frames
+            .lock()
+            .iter()
+            .find(|frame| frame.timestamp == timestamp)
+            .cloned()
+    }
+
+    /// Returns the motion vectors of the frame with this timestamp, if space warp was negotiated and
+    /// the motion vectors have been received
+    pub fn motion_vectors_frame(&self, timestamp: Duration) -> Option<MotionVectorsFrame> {
+        self.connection_context
+            .motion_vectors_frames
+            .lock()
+            .iter()
+            .find(|frame| frame.timestamp == timestamp)
+            .cloned()
+    }
+
+    /// True while the game doesn't submit depth. The depth resources can be released meanwhile
+    pub fn depth_stream_paused(&self) -> bool {
+        *self.connection_context.depth_stream_paused.lock()
+    }
+
+    /// Call when the last frame is reprojected because no new frame was ready. The frame is not
+    /// submitted again with report_submit(), so the pacing statistics sent to the server only
+    /// include the new frames
+    pub fn report_extrapolated_frame(&self) {
+        dbg_client_core!("report_extrapolated_frame");
+
+        // The server sends the frames at a reduced rate on purpose, they are not missed
+        if *self.connection_context.server_idle_throttled.lock() {
+            return;
+        }
+
+        if let Some(stats) = &mut *self.connection_context.statistics_manager.lock() {
+            stats.report_extrapolated_frame();
+        }
     }
 
+    /// Call after submitting a frame to the compositor
     pub fn report_submit(&self, timestamp: Duration, vsync_queue: Duration) {
         dbg_client_core!("report_submit");
 
@@ -309,6 +755,7 @@ impl ClientCoreContext {
         }
     }
 
+    /// Platform passed in the capabilities
     pub fn platform(&self) -> Platform {
         self.platform
     }
@@ -1,16 +1,122 @@
 use alvr_common::{
-    DebugGroupsConfig, LogSeverity,
+    ALVR_VERSION, DebugGroupsConfig, LogSeverity,
     log::{Level, Record},
     parking_lot::Mutex,
 };
 use alvr_packets::ClientControlPacket;
 use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
     sync::{LazyLock, mpsc},
     time::{Duration, Instant},
 };
 
 const LOG_REPEAT_TIMEOUT: Duration = Duration::from_secs(1);
 
+const LOG_FILE_MAX_SIZE: u64 = 2 * 1024 * 1024;
+// Including the current one. The files are also rotated at every startup
+const LOG_FILE_COUNT: usize = 3;
+// Limit of the log sent to the server on request, the older lines are dropped
+const LOG_UPLOAD_MAX_SIZE: usize = 1024 * 1024;
+
+struct LogFile {
+    dir: PathBuf,
+    file: File,
+    size: u64,
+}
+
+static LOG_FILE: Mutex<Option<LogFile>> = Mutex::new(None);
+
+#[cfg(target_os = "android")]
+fn log_dir() -> Option<PathBuf> {
+    // Retrieved with: adb pull /sdcard/Android/data/<package name>/files/client_log.txt
+    alvr_system_info::external_files_dir()
+}
+
+#[cfg(not(target_os = "android"))]
+fn log_dir() -> Option<PathBuf> {
+    app_dirs2::app_root(
+        app_dirs2::AppDataType::UserData,
+        &app_dirs2::AppInfo {
+            name: "ALVR Client",
+            author: "ALVR",
+        },
+    )
+    .ok()
+}
+
+fn log_file_path(dir: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        dir.join("client_log.txt")
+    } else {
+        dir.join(format!("client_log.{index}.txt"))
+    }
+}
+
+// The oldest file is overwritten and the others are shifted by one
+fn rotate_log_files(dir: &Path) {
+    for index in (1..LOG_FILE_COUNT).rev() {
+        fs::rename(log_file_path(dir, index - 1), log_file_path(dir, index)).ok();
+    }
+}
+
+fn create_log_file(dir: PathBuf) -> Option<LogFile> {
+    rotate_log_files(&dir);
+    let file = File::create(log_file_path(&dir, 0)).ok()?;
+
+    Some(LogFile { dir, file, size: 0 })
+}
+
+fn write_log_file(level: Level, message: &str) {
+    let mut log_file_lock = LOG_FILE.lock();
+    let Some(log_file) = &mut *log_file_lock else {
+        return;
+    };
+
+    let line = format!(
+        "{} [{level}] {message}\n",
+        chrono::Local::now().format("%F %T%.3f")
+    );
+    if log_file.file.write_all(line.as_bytes()).is_ok() {
+        log_file.size += line.len() as u64;
+    }
+
+    if log_file.size > LOG_FILE_MAX_SIZE {
+        // The file must be closed before renaming it on Windows
+        let dir = log_file_lock.take().unwrap().dir;
+        *log_file_lock = create_log_file(dir);
+    }
+}
+
+// Content of the log files, from the oldest line
+pub fn read_log_files() -> String {
+    let Some(dir) = LOG_FILE
+        .lock()
+        .as_ref()
+        .map(|log_file| log_file.dir.clone())
+    else {
+        return String::new();
+    };
+
+    let mut log = String::new();
+    for index in (0..LOG_FILE_COUNT).rev() {
+        if let Ok(text) = fs::read_to_string(log_file_path(&dir, index)) {
+            log += &text;
+        }
+    }
+
+    if log.len() > LOG_UPLOAD_MAX_SIZE {
+        let mut start = log.len() - LOG_UPLOAD_MAX_SIZE;
+        while !log.is_char_boundary(start) {
+            start += 1;
+        }
+        log.drain(..start);
+    }
+
+    log
+}
+
 pub struct LogMirrorData {
     pub sender: mpsc::Sender<ClientControlPacket>,
     pub filter_level: LogSeverity,
@@ -96,6 +202,8 @@ pub fn init_logging() {
             android_logger::Config::default()
                 .with_tag("[ALVR NATIVE-RUST]")
                 .format(|f, record| {
+                    write_log_file(record.level(), &record.args().to_string());
+
                     if send_log(record) {
                         writeln!(f, "{}", record.args())
                     } else {
@@ -107,9 +215,10 @@ pub fn init_logging() {
     }
     #[cfg(not(target_os = "android"))]
     {
-        use std::io::Write;
         env_logger::builder()
             .format(|f, record| {
+                write_log_file(record.level(), &record.args().to_string());
+
                 if send_log(record) {
                     writeln!(f, "{}", record.args())
                 } else {
@@ -120,5 +229,15 @@ pub fn init_logging() {
             .ok();
     }
 
+    if let Some(dir) = log_dir() {
+        fs::create_dir_all(&dir).ok();
+        *LOG_FILE.lock() = create_log_file(dir);
+    }
+    alvr_common::info!(
+        "ALVR client v{}, started at {}",
+        *ALVR_VERSION,
+        chrono::Local::now().format("%F %T")
+    );
+
     alvr_common::set_panic_hook();
 }
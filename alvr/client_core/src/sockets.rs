@@ -1,5 +1,8 @@
 use alvr_common::anyhow::{Result, bail};
 use mdns_sd::{ServiceDaemon, ServiceInfo};
+use std::net::{Ipv4Addr, UdpSocket};
+
+const WAKE_ON_LAN_PORT: u16 = 9;
 
 pub struct AnnouncerSocket {
     hostname: String,
@@ -37,3 +40,18 @@ impl AnnouncerSocket {
         Ok(())
     }
 }
+
+// The magic packet is 6 bytes of 0xFF followed by the MAC address repeated 16 times. It is
+// broadcast since the PC has no IP address while sleeping.
+pub fn send_wake_on_lan(mac_address: [u8; 6]) -> Result<()> {
+    let mut packet = vec![0xFF; 6];
+    for _ in 0..16 {
+        packet.extend_from_slice(&mac_address);
+    }
+
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.set_broadcast(true)?;
+    socket.send_to(&packet, (Ipv4Addr::BROADCAST, WAKE_ON_LAN_PORT))?;
+
+    Ok(())
+}
@@ -1,10 +1,23 @@
-use alvr_common::SlidingWindowAverage;
+use alvr_common::{SlidingWindowAverage, trace_instant, trace_span, warn};
 use alvr_packets::ClientStatistics;
 use std::{
     collections::VecDeque,
+    sync::LazyLock,
     time::{Duration, Instant},
 };
 
+// Window used to detect when frame extrapolation hides a persistent underperformance
+const EXTRAPOLATION_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const EXTRAPOLATION_WARNING_RATIO: f32 = 0.25;
+
+static CLIENT_CLOCK_EPOCH: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+// Clock used in the time sync with the server. It is monotonic like the server clock, so the
+// estimated offset doesn't jump when the wall clock is adjusted.
+pub(crate) fn client_time() -> Duration {
+    CLIENT_CLOCK_EPOCH.elapsed()
+}
+
 struct HistoryFrame {
     input_acquired: Instant,
     video_packet_received: Instant,
@@ -16,6 +29,11 @@ pub struct StatisticsManager {
     max_history_size: usize,
     prev_vsync: Instant,
     total_pipeline_latency_average: SlidingWindowAverage<Duration>,
+    decoder_queue_drops_total: u32,
+    extrapolated_frames_total: u32,
+    extrapolation_check_start: Instant,
+    submitted_frames_partial_sum: u32,
+    extrapolated_frames_partial_sum: u32,
 }
 
 impl StatisticsManager {
@@ -28,10 +46,17 @@ impl StatisticsManager {
                 Duration::ZERO,
                 max_history_size,
             ),
+            decoder_queue_drops_total: 0,
+            extrapolated_frames_total: 0,
+            extrapolation_check_start: Instant::now(),
+            submitted_frames_partial_sum: 0,
+            extrapolated_frames_partial_sum: 0,
         }
     }
 
     pub fn report_input_acquired(&mut self, target_timestamp: Duration) {
+        trace_instant("Pose sample", Instant::now(), target_timestamp);
+
         if !self
             .history_buffer
             .iter()
@@ -60,6 +85,9 @@ impl StatisticsManager {
             .find(|frame| frame.client_stats.target_timestamp == target_timestamp)
         {
             frame.video_packet_received = Instant::now();
+            frame.client_stats.video_packet_received_time = client_time();
+
+            trace_instant("Receive", frame.video_packet_received, target_timestamp);
         }
     }
 
@@ -69,8 +97,11 @@ impl StatisticsManager {
             .iter_mut()
             .find(|frame| frame.client_stats.target_timestamp == target_timestamp)
         {
+            let now = Instant::now();
             frame.client_stats.video_decode =
-                Instant::now().saturating_duration_since(frame.video_packet_received);
+                now.saturating_duration_since(frame.video_packet_received);
+
+            trace_span("Decode", frame.video_packet_received, now, target_timestamp);
         }
     }
 
@@ -80,12 +111,47 @@ impl StatisticsManager {
             .iter_mut()
             .find(|frame| frame.client_stats.target_timestamp == target_timestamp)
         {
-            frame.client_stats.video_decoder_queue = Instant::now().saturating_duration_since(
-                frame.video_packet_received + frame.client_stats.video_decode,
-            );
+            let decoded = frame.video_packet_received + frame.client_stats.video_decode;
+            let now = Instant::now();
+            frame.client_stats.video_decoder_queue = now.saturating_duration_since(decoded);
+
+            trace_span("Decoder queue", decoded, now, target_timestamp);
         }
     }
 
+    pub fn report_decoder_queue_drop(&mut self) {
+        self.decoder_queue_drops_total += 1;
+    }
+
+    pub fn report_extrapolated_frame(&mut self) {
+        self.extrapolated_frames_total += 1;
+        self.extrapolated_frames_partial_sum += 1;
+
+        self.check_extrapolation_ratio();
+    }
+
+    fn check_extrapolation_ratio(&mut self) {
+        if self.extrapolation_check_start.elapsed() < EXTRAPOLATION_CHECK_INTERVAL {
+            return;
+        }
+
+        let total_frames = self.submitted_frames_partial_sum + self.extrapolated_frames_partial_sum;
+        if total_frames > 0 {
+            let ratio = self.extrapolated_frames_partial_sum as f32 / total_frames as f32;
+            if ratio > EXTRAPOLATION_WARNING_RATIO {
+                warn!(
+                    "{:.0}% of the frames were extrapolated in the last {}s: the game or the stream cannot keep up with the headset refresh rate",
+                    ratio * 100.0,
+                    EXTRAPOLATION_CHECK_INTERVAL.as_secs()
+                );
+            }
+        }
+
+        self.extrapolation_check_start = Instant::now();
+        self.submitted_frames_partial_sum = 0;
+        self.extrapolated_frames_partial_sum = 0;
+    }
+
     // vsync_queue is the latency between this call and the vsync. it cannot be measured by ALVR and
     // should be reported by the VR runtime
     pub fn report_submit(&mut self, target_timestamp: Duration, vsync_queue: Duration) {
@@ -96,11 +162,10 @@ impl StatisticsManager {
             .iter_mut()
             .find(|frame| frame.client_stats.target_timestamp == target_timestamp)
         {
-            frame.client_stats.rendering = now.saturating_duration_since(
-                frame.video_packet_received
-                    + frame.client_stats.video_decode
-                    + frame.client_stats.video_decoder_queue,
-            );
+            let compositor_start = frame.video_packet_received
+                + frame.client_stats.video_decode
+                + frame.client_stats.video_decoder_queue;
+            frame.client_stats.rendering = now.saturating_duration_since(compositor_start);
             frame.client_stats.vsync_queue = vsync_queue;
             frame.client_stats.total_pipeline_latency =
                 now.saturating_duration_since(frame.input_acquired) + vsync_queue;
@@ -110,14 +175,25 @@ impl StatisticsManager {
             let vsync = now + vsync_queue;
             frame.client_stats.frame_interval = vsync.saturating_duration_since(self.prev_vsync);
             self.prev_vsync = vsync;
+
+            trace_span("Compositor", compositor_start, now, target_timestamp);
+            trace_span("Submit", now, vsync, target_timestamp);
         }
+
+        self.submitted_frames_partial_sum += 1;
+        self.check_extrapolation_ratio();
     }
 
     pub fn summary(&self, target_timestamp: Duration) -> Option<ClientStatistics> {
         self.history_buffer
             .iter()
             .find(|frame| frame.client_stats.target_timestamp == target_timestamp)
-            .map(|frame| frame.client_stats.clone())
+            .map(|frame| ClientStatistics {
+                decoder_queue_drops_total: self.decoder_queue_drops_total,
+                extrapolated_frames_total: self.extrapolated_frames_total,
+                send_time: client_time(),
+                ..frame.client_stats.clone()
+            })
     }
 
     // latency used for head prediction
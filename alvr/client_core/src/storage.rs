@@ -1,10 +1,25 @@
-use alvr_common::{error, info};
+use alvr_common::{Pose, error, glam::Vec2, info, warn};
+use alvr_packets::TrackingSpace;
+use alvr_session::ClientGraphicsApi;
 use app_dirs2::{AppDataType, AppInfo};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf};
+use serde_json as json;
+use std::{
+    fs,
+    net::IpAddr,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-fn config_path() -> PathBuf {
+// Version of the format of servers.json. Increase it when a change can't be read by older clients
+// and add its migration to ServerStorage::migrate()
+const SERVER_STORAGE_VERSION: u32 = 1;
+const MAX_STORED_SERVERS: usize = 16;
+// The guardian is considered unchanged if its size is within this distance
+const PLAYSPACE_TOLERANCE: f32 = 0.05;
+
+fn storage_path(file_name: &str) -> PathBuf {
     app_dirs2::app_root(
         AppDataType::UserConfig,
         &AppInfo {
@@ -13,13 +28,61 @@ fn config_path() -> PathBuf {
         },
     )
     .unwrap()
-    .join("session.json")
+    .join(file_name)
+}
+
+fn config_path() -> PathBuf {
+    storage_path("session.json")
+}
+
+// Synced from the server settings
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectBackoff {
+    pub base_interval: Duration,
+    pub max_interval: Duration,
+    pub max_retries: Option<u32>,
+}
+
+impl Default for ReconnectBackoff {
+    fn default() -> Self {
+        Self {
+            base_interval: Duration::from_secs(1),
+            max_interval: Duration::from_secs(30),
+            max_retries: None,
+        }
+    }
+}
+
+impl ReconnectBackoff {
+    // Waiting time after the failed attempt with this index, starting from 0
+    pub fn retry_interval(&self, retry: u32) -> Duration {
+        self.base_interval
+            .saturating_mul(2_u32.saturating_pow(retry))
+            .min(self.max_interval)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct Config {
     pub hostname: String,
     pub protocol_id: String,
+    // Learned from the last connected server, used for Wake-on-LAN
+    #[serde(default)]
+    pub server_mac_address: Option<[u8; 6]>,
+    // Skip the optional OpenXR extensions on startup. Synced from the server settings
+    #[serde(default)]
+    pub safe_mode: bool,
+    // Synced from the server settings, used on the next startup
+    #[serde(default)]
+    pub graphics_api: ClientGraphicsApi,
+    // Set while starting up, if still set on the next startup the client crashed
+    #[serde(default)]
+    pub startup_pending: bool,
+    #[serde(default)]
+    pub reconnect_backoff: ReconnectBackoff,
+    // Synced from the server settings. The lobby tracks the markers only if set
+    #[serde(default)]
+    pub lobby_marker_selection: bool,
 }
 
 impl Default for Config {
@@ -35,6 +98,12 @@ impl Default for Config {
                 rng.random_range(0..10),
             ),
             protocol_id: alvr_common::protocol_id(),
+            server_mac_address: None,
+            safe_mode: false,
+            graphics_api: ClientGraphicsApi::Auto,
+            startup_pending: false,
+            reconnect_backoff: ReconnectBackoff::default(),
+            lobby_marker_selection: false,
         }
     }
 }
@@ -66,3 +135,194 @@ impl Config {
         }
     }
 }
+
+// Last recentering done by the user, restored on the next connection if the guardian is unchanged
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct StoredRecentering {
+    // In the tracking reference space
+    pub origin: Pose,
+    pub playspace_area: Option<Vec2>,
+}
+
+impl StoredRecentering {
+    pub fn matches_playspace(&self, area: Option<Vec2>) -> bool {
+        match (self.playspace_area, area) {
+            (Some(stored), Some(area)) => stored.abs_diff_eq(area, PLAYSPACE_TOLERANCE),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+// The streamers are identified by their IP address, they don't send another identity
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ServerEntry {
+    pub address: IpAddr,
+    #[serde(default)]
+    pub mac_address: Option<[u8; 6]>,
+    // Seconds since the Unix epoch
+    #[serde(default)]
+    pub last_connection_time: u64,
+    #[serde(default)]
+    pub connection_count: u32,
+    #[serde(default)]
+    pub recentering: Option<StoredRecentering>,
+}
+
+// State kept for each streamer the client connected to, in servers.json. The most recently
+// connected streamer is first
+#[derive(Serialize, Deserialize, Default)]
+pub struct ServerStorage {
+    pub servers: Vec<ServerEntry>,
+}
+
+impl ServerStorage {
+    pub fn load() -> Self {
+        let Ok(storage_string) = fs::read_to_string(storage_path("servers.json")) else {
+            return Self::default();
+        };
+
+        match json::from_str::<json::Value>(&storage_string)
+            .map_err(|e| e.to_string())
+            .and_then(Self::migrate)
+        {
+            Ok(storage) => storage,
+            Err(e) => {
+                info!("Error parsing the streamers storage, starting over: {e}");
+
+                Self::default()
+            }
+        }
+    }
+
+    // Upgrades the file of an older client one version at a time, before parsing it. The file of a
+    // newer client is not read, it is overwritten on the next change
+    fn migrate(storage_json: json::Value) -> Result<Self, String> {
+        let version = storage_json["version"].as_u64().ok_or("missing version")?;
+        if version > SERVER_STORAGE_VERSION as u64 {
+            return Err(format!("unsupported version {version}"));
+        }
+
+        // The migrations from the older versions go here, in order. The fields added without
+        // breaking the older clients have a serde default instead
+
+        json::from_value(storage_json).map_err(|e| e.to_string())
+    }
+
+    pub fn store(&self) {
+        let mut storage_json = json::to_value(self).unwrap();
+        storage_json["version"] = SERVER_STORAGE_VERSION.into();
+
+        if let Err(e) = fs::write(storage_path("servers.json"), storage_json.to_string()) {
+            error!("Error writing the streamers storage: {e}")
+        }
+    }
+
+    pub fn clear() {
+        let path = storage_path("servers.json");
+        if path.exists()
+            && let Err(e) = fs::remove_file(path)
+        {
+            warn!("Error removing the streamers storage: {e}");
+        }
+    }
+
+    pub fn get(&self, address: IpAddr) -> Option<&ServerEntry> {
+        self.servers.iter().find(|entry| entry.address == address)
+    }
+
+    // Edits the entry of the streamer, created if missing, and stores the file
+    pub fn update(address: IpAddr, update: impl FnOnce(&mut ServerEntry)) {
+        let mut storage = Self::load();
+
+        let entry = if let Some(entry) = storage
+            .servers
+            .iter_mut()
+            .find(|entry| entry.address == address)
+        {
+            entry
+        } else {
+            storage.servers.push(ServerEntry {
+                address,
+                mac_address: None,
+                last_connection_time: 0,
+                connection_count: 0,
+                recentering: None,
+            });
+            storage.servers.last_mut().unwrap()
+        };
+        update(entry);
+
+        storage
+            .servers
+            .sort_by_key(|entry| std::cmp::Reverse(entry.last_connection_time));
+        storage.servers.truncate(MAX_STORED_SERVERS);
+
+        storage.store();
+    }
+
+    // Called when the stream starts
+    pub fn report_connection(address: IpAddr) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        Self::update(address, |entry| {
+            entry.last_connection_time = now;
+            entry.connection_count += 1;
+        });
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MarkerAnchor {
+    pub marker_code: String,
+    // The pose is valid only in the same space, the stage and the local spaces are not related
+    pub tracking_space: TrackingSpace,
+    // Without the tracking origin set by the client
+    pub pose: Pose,
+}
+
+// Poses of the origin markers, in marker_anchors.json. For a static room the origin is set on
+// startup, without waiting for the marker to be seen again
+#[derive(Serialize, Deserialize, Default)]
+pub struct MarkerAnchorStorage {
+    pub anchors: Vec<MarkerAnchor>,
+}
+
+impl MarkerAnchorStorage {
+    pub fn load() -> Self {
+        fs::read_to_string(storage_path("marker_anchors.json"))
+            .ok()
+            .and_then(|storage_string| json::from_str(&storage_string).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn get(&self, tracking_space: TrackingSpace, marker_code: &str) -> Option<Pose> {
+        self.anchors
+            .iter()
+            .find(|anchor| {
+                anchor.tracking_space == tracking_space && anchor.marker_code == marker_code
+            })
+            .map(|anchor| anchor.pose)
+    }
+
+    pub fn set(&mut self, tracking_space: TrackingSpace, marker_code: &str, pose: Pose) {
+        self.anchors.retain(|anchor| {
+            anchor.tracking_space != tracking_space || anchor.marker_code != marker_code
+        });
+        self.anchors.push(MarkerAnchor {
+            marker_code: marker_code.to_owned(),
+            tracking_space,
+            pose,
+        });
+    }
+
+    pub fn store(&self) {
+        let storage_string = json::to_string(self).unwrap();
+        if let Err(e) = fs::write(storage_path("marker_anchors.json"), storage_string) {
+            error!("Error writing the marker anchors: {e}")
+        }
+    }
+}
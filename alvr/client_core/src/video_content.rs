@@ -0,0 +1,69 @@
+use std::time::{Duration, Instant};
+
+// Weight of each frame in the average sizes, which follow the content in about a second
+const FRAME_SIZE_AVERAGE_WEIGHT: f32 = 0.02;
+const STILL_ANGULAR_SPEED: f32 = 0.1; // rad/s
+const MOVING_ANGULAR_SPEED: f32 = 0.5; // rad/s
+const STILL_DURATION: Duration = Duration::from_secs(2);
+// Ratios of the average frame size to the one while moving. The gap avoids toggling on scenes
+// with a little motion
+const ENTER_RATIO: f32 = 0.5;
+const EXIT_RATIO: f32 = 0.25;
+
+// Cheap detection of video playing in front of a still head, for example a movie in a virtual
+// theater. With a still head, static content like menus produces small frames since only the
+// changes are encoded, while moving pictures keep using a large part of the bitrate. The bitrate
+// used is estimated from the frames encoded while the head moves.
+#[derive(Default)]
+pub struct VideoContentDetector {
+    frame_size_average: f32,
+    moving_frame_size_average: f32,
+    head_moving: bool,
+    still_since: Option<Instant>,
+    detected: bool,
+}
+
+impl VideoContentDetector {
+    pub fn report_head_motion(&mut self, angular_speed: f32, now: Instant) {
+        if angular_speed < STILL_ANGULAR_SPEED {
+            self.still_since.get_or_insert(now);
+        } else {
+            self.still_since = None;
+        }
+        self.head_moving = angular_speed > MOVING_ANGULAR_SPEED;
+    }
+
+    // IDR frames are skipped, their size does not depend on the motion
+    pub fn report_frame(&mut self, size: usize, is_idr: bool, now: Instant) {
+        if is_idr {
+            return;
+        }
+
+        let size = size as f32;
+        self.frame_size_average += (size - self.frame_size_average) * FRAME_SIZE_AVERAGE_WEIGHT;
+        if self.head_moving {
+            if self.moving_frame_size_average == 0.0 {
+                self.moving_frame_size_average = size;
+            }
+            self.moving_frame_size_average +=
+                (size - self.moving_frame_size_average) * FRAME_SIZE_AVERAGE_WEIGHT;
+        }
+
+        // The last state is kept while the head is not still
+        if let Some(still_since) = self.still_since
+            && now.saturating_duration_since(still_since) >= STILL_DURATION
+            && self.moving_frame_size_average > 0.0
+        {
+            let ratio = self.frame_size_average / self.moving_frame_size_average;
+            if self.detected && ratio < EXIT_RATIO {
+                self.detected = false;
+            } else if !self.detected && ratio > ENTER_RATIO {
+                self.detected = true;
+            }
+        }
+    }
+
+    pub fn detected(&self) -> bool {
+        self.detected
+    }
+}
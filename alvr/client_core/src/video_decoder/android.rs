@@ -6,7 +6,7 @@ use alvr_common::{
     parking_lot::{Condvar, Mutex},
     warn,
 };
-use alvr_session::{CodecType, MediacodecPropType};
+use alvr_session::{ChromaSubsampling, CodecType, MediacodecPropType};
 use ndk::{
     hardware_buffer::HardwareBufferUsage,
     media::{
@@ -254,6 +254,19 @@ fn decoder_lifecycle(
     format.set_i32("width", 512);
     format.set_i32("height", 1024);
     format.set_buffer("csd-0", &csd_0);
+    // 4:2:0 is the default. The other subsamplings are negotiated only for HEVC decoders that list
+    // the color format
+    match config.chroma_subsampling {
+        ChromaSubsampling::Yuv420 => (),
+        ChromaSubsampling::Yuv422 => format.set_i32(
+            "color-format",
+            alvr_system_info::COLOR_FORMAT_YUV422_FLEXIBLE,
+        ),
+        ChromaSubsampling::Yuv444 => format.set_i32(
+            "color-format",
+            alvr_system_info::COLOR_FORMAT_YUV444_FLEXIBLE,
+        ),
+    }
 
     for (key, prop) in &config.options {
         let maybe_error = match prop.ty {
@@ -2,28 +2,137 @@
 mod android;
 
 use alvr_common::anyhow::Result;
-use alvr_session::{CodecType, MediacodecProperty};
-use std::time::Duration;
+use alvr_session::{ChromaSubsampling, CodecType, MediacodecProperty};
+use std::{collections::VecDeque, time::Duration};
+
+const GIB: u64 = 1 << 30;
 
 #[derive(Clone, Default, PartialEq)]
 pub struct VideoDecoderConfig {
     pub codec: CodecType,
+    pub chroma_subsampling: ChromaSubsampling,
     pub force_software_decoder: bool,
     pub max_buffering_frames: f32,
     pub buffering_history_weight: f32,
+    pub max_queued_frames: usize,
     pub options: Vec<(String, MediacodecProperty)>,
     pub config_buffer: Vec<u8>,
 }
 
+// Queue size used when not set in the settings. Encoded frames can reach a few MB during recovery,
+// so devices with less memory get a smaller queue
+pub fn default_max_queued_frames() -> usize {
+    match alvr_system_info::total_memory_bytes() {
+        Some(bytes) if bytes < 6 * GIB => 8,
+        Some(bytes) if bytes < 10 * GIB => 16,
+        Some(_) => 32,
+        None => 16,
+    }
+}
+
+// Annex B NAL unit types for h264 and HEVC, OBU types for AV1
+const H264_NAL_TYPE_IDR: u8 = 5;
+const HEVC_NAL_TYPE_IDR_W_RADL: u8 = 19;
+const HEVC_NAL_TYPE_IDR_N_LP: u8 = 20;
+const AV1_OBU_TYPE_SEQUENCE_HEADER: u8 = 1;
+
+// AV1 frames don't have a dedicated type, the server repeats the sequence header only on key frames
+fn is_idr(codec: CodecType, nal: &[u8]) -> bool {
+    match codec {
+        CodecType::H264 | CodecType::Hevc => nal.windows(4).any(|window| {
+            if window[..3] != [0, 0, 1] {
+                return false;
+            }
+
+            if codec == CodecType::H264 {
+                window[3] & 0x1F == H264_NAL_TYPE_IDR
+            } else {
+                let nal_type = (window[3] >> 1) & 0x3F;
+                nal_type == HEVC_NAL_TYPE_IDR_W_RADL || nal_type == HEVC_NAL_TYPE_IDR_N_LP
+            }
+        }),
+        CodecType::AV1 => {
+            let mut offset = 0;
+            while let Some(&header) = nal.get(offset) {
+                if (header >> 3) & 0xF == AV1_OBU_TYPE_SEQUENCE_HEADER {
+                    return true;
+                }
+                let has_extension = header & 0b100 != 0;
+                let has_size = header & 0b10 != 0;
+                if !has_size {
+                    // The OBU extends to the end of the buffer
+                    return false;
+                }
+                offset += if has_extension { 2 } else { 1 };
+
+                // leb128 size
+                let mut size = 0;
+                for i in 0..8 {
+                    let Some(&byte) = nal.get(offset) else {
+                        return false;
+                    };
+                    offset += 1;
+                    size |= ((byte & 0x7F) as usize) << (i * 7);
+                    if byte & 0x80 == 0 {
+                        break;
+                    }
+                }
+                offset += size;
+            }
+
+            false
+        }
+    }
+}
+
 pub struct VideoDecoderSink {
     #[cfg(target_os = "android")]
     inner: android::VideoDecoderSink,
+    // Frames received while the decoder had no free input buffer. They are submitted in order on
+    // the next calls to push_nal()
+    frame_queue: VecDeque<(Duration, Vec<u8>)>,
+    max_queued_frames: usize,
+    codec: CodecType,
 }
 
 impl VideoDecoderSink {
+    // Returns false if this or an older queued frame has been dropped because the queue is full
+    pub fn push_nal(&mut self, timestamp: Duration, nal: &[u8]) -> bool {
+        self.submit_queued_frames();
+
+        if self.frame_queue.is_empty() && self.submit(timestamp, nal) {
+            return true;
+        }
+
+        // The queued frames are not needed to decode an IDR, and the decoder wouldn't be able to
+        // catch up with them after a long stall anyway
+        if is_idr(self.codec, nal) {
+            self.frame_queue.clear();
+        }
+        self.frame_queue.push_back((timestamp, nal.to_vec()));
+
+        let mut dropped = false;
+        while self.frame_queue.len() > self.max_queued_frames {
+            self.frame_queue.pop_front();
+            dropped = true;
+        }
+
+        !dropped
+    }
+
+    fn submit_queued_frames(&mut self) {
+        while let Some((timestamp, nal)) = self.frame_queue.pop_front() {
+            if !self.submit(timestamp, &nal) {
+                self.frame_queue.push_front((timestamp, nal));
+
+                break;
+            }
+        }
+    }
+
     // returns true if frame has been successfully enqueued
     #[allow(unused_variables)]
-    pub fn push_nal(&mut self, timestamp: Duration, nal: &[u8]) -> bool {
+    fn submit(&mut self, timestamp: Duration, nal: &[u8]) -> bool {
         #[cfg(target_os = "android")]
         {
             alvr_common::show_err(self.inner.push_frame_nal(timestamp, nal)).unwrap_or(false)
@@ -33,6 +142,12 @@ impl VideoDecoderSink {
     }
 }
 
+impl crate::VideoDecoderInput for VideoDecoderSink {
+    fn push_nal(&mut self, timestamp: Duration, nal: &[u8]) -> bool {
+        VideoDecoderSink::push_nal(self, timestamp, nal)
+    }
+}
+
 pub struct VideoDecoderSource {
     #[cfg(target_os = "android")]
     inner: android::VideoDecoderSource,
@@ -56,6 +171,9 @@ pub fn create_decoder(
     config: VideoDecoderConfig,
     report_frame_decoded: impl Fn(Result<Duration>) + Send + Sync + 'static,
 ) -> (VideoDecoderSink, VideoDecoderSource) {
+    let frame_queue = VecDeque::with_capacity(config.max_queued_frames);
+    let max_queued_frames = config.max_queued_frames;
+
     #[cfg(target_os = "android")]
     {
         let (sink, source) = android::video_decoder_split(
@@ -66,10 +184,92 @@ pub fn create_decoder(
         .unwrap();
 
         (
-            VideoDecoderSink { inner: sink },
+            VideoDecoderSink {
+                inner: sink,
+                frame_queue,
+                max_queued_frames,
+                codec: config.codec,
+            },
             VideoDecoderSource { inner: source },
         )
     }
     #[cfg(not(target_os = "android"))]
-    (VideoDecoderSink {}, VideoDecoderSource {})
+    (
+        VideoDecoderSink {
+            frame_queue,
+            max_queued_frames,
+            codec: config.codec,
+        },
+        VideoDecoderSource {},
+    )
+}
+
+#[cfg(all(test, not(target_os = "android")))]
+mod tests {
+    use super::*;
+
+    const H264_IDR: &[u8] = &[
+        0, 0, 0, 1, 0x67, 0x42, 0, 0, 1, 0x68, 0xCE, 0, 0, 1, 0x65, 0x88,
+    ];
+    const H264_P: &[u8] = &[0, 0, 0, 1, 0x41, 0x9A];
+    const HEVC_IDR: &[u8] = &[0, 0, 0, 1, 0x40, 0x01, 0, 0, 1, 0x26, 0x01, 0xAF];
+    const HEVC_P: &[u8] = &[0, 0, 0, 1, 0x02, 0x01, 0xD0];
+    // Temporal delimiter, sequence header and frame OBUs
+    const AV1_KEY: &[u8] = &[0x12, 0x00, 0x0A, 0x02, 0x00, 0x00, 0x32, 0x01, 0x10];
+    // Temporal delimiter and frame OBUs
+    const AV1_INTER: &[u8] = &[0x12, 0x00, 0x32, 0x02, 0x30, 0x00];
+
+    fn sink(codec: CodecType, max_queued_frames: usize) -> VideoDecoderSink {
+        create_decoder(
+            VideoDecoderConfig {
+                codec,
+                max_queued_frames,
+                ..Default::default()
+            },
+            |_| (),
+        )
+        .0
+    }
+
+    fn queued_timestamps(sink: &VideoDecoderSink) -> Vec<u64> {
+        sink.frame_queue
+            .iter()
+            .map(|(timestamp, _)| timestamp.as_millis() as u64)
+            .collect()
+    }
+
+    #[test]
+    fn idr_detection() {
+        assert!(is_idr(CodecType::H264, H264_IDR));
+        assert!(!is_idr(CodecType::H264, H264_P));
+        assert!(is_idr(CodecType::Hevc, HEVC_IDR));
+        assert!(!is_idr(CodecType::Hevc, HEVC_P));
+        assert!(is_idr(CodecType::AV1, AV1_KEY));
+        assert!(!is_idr(CodecType::AV1, AV1_INTER));
+        assert!(!is_idr(CodecType::AV1, &[0x12]));
+    }
+
+    #[test]
+    fn drops_oldest_when_full() {
+        let mut sink = sink(CodecType::H264, 2);
+
+        assert!(sink.push_nal(Duration::from_millis(1), H264_P));
+        assert!(sink.push_nal(Duration::from_millis(2), H264_P));
+        assert!(!sink.push_nal(Duration::from_millis(3), H264_P));
+        assert_eq!(queued_timestamps(&sink), [2, 3]);
+    }
+
+    #[test]
+    fn idr_supersedes_queued_frames() {
+        let mut sink = sink(CodecType::Hevc, 4);
+
+        for timestamp in 1..=3 {
+            sink.push_nal(Duration::from_millis(timestamp), HEVC_P);
+        }
+        assert!(sink.push_nal(Duration::from_millis(4), HEVC_IDR));
+        assert_eq!(queued_timestamps(&sink), [4]);
+
+        assert!(sink.push_nal(Duration::from_millis(5), HEVC_P));
+        assert_eq!(queued_timestamps(&sink), [4, 5]);
+    }
 }
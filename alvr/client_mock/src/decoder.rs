@@ -0,0 +1,95 @@
+use alvr_client_core::VideoDecoderInput;
+use alvr_common::{error, warn};
+use alvr_session::CodecType;
+use eframe::egui::ColorImage;
+use openh264::{decoder::Decoder, formats::YUVSource};
+use std::{sync::mpsc, time::Duration};
+
+pub struct DecodedFrame {
+    pub timestamp: Duration,
+    pub size: usize,
+    // None if the frame could not be decoded
+    pub image: Option<ColorImage>,
+}
+
+// Decodes the video on the CPU with OpenH264, in the thread that receives the video. The stream has
+// both views side by side. Only H.264 is supported, the frames of the other codecs are forwarded
+// without image so that the statistics keep working
+pub struct SoftwareDecoder {
+    decoder: Option<Decoder>,
+    frame_sender: mpsc::Sender<DecodedFrame>,
+}
+
+impl SoftwareDecoder {
+    pub fn new(
+        codec: CodecType,
+        config_nal: &[u8],
+        frame_sender: mpsc::Sender<DecodedFrame>,
+    ) -> Self {
+        let decoder = if codec == CodecType::H264 {
+            match Decoder::new() {
+                Ok(mut decoder) => {
+                    // SPS and PPS, they produce no frame
+                    for nal in openh264::nal_units(config_nal) {
+                        decoder.decode(nal).ok();
+                    }
+
+                    Some(decoder)
+                }
+                Err(e) => {
+                    error!("Failed to create the H.264 decoder: {e}");
+
+                    None
+                }
+            }
+        } else {
+            warn!("The mock client decodes only H.264, the {codec:?} frames are not shown");
+
+            None
+        };
+
+        Self {
+            decoder,
+            frame_sender,
+        }
+    }
+
+    fn decode(&mut self, nal: &[u8]) -> Option<ColorImage> {
+        let decoder = self.decoder.as_mut()?;
+
+        let mut image = None;
+        for nal in openh264::nal_units(nal) {
+            match decoder.decode(nal) {
+                Ok(Some(yuv)) => {
+                    let (width, height) = yuv.dimensions();
+                    let mut rgba = vec![0; width * height * 4];
+                    yuv.write_rgba8(&mut rgba);
+
+                    image = Some(ColorImage::from_rgba_unmultiplied([width, height], &rgba));
+                }
+                Ok(None) => (),
+                Err(e) => {
+                    warn!("Failed to decode frame: {e}");
+
+                    return None;
+                }
+            }
+        }
+
+        image
+    }
+}
+
+impl VideoDecoderInput for SoftwareDecoder {
+    fn push_nal(&mut self, timestamp: Duration, nal: &[u8]) -> bool {
+        let image = self.decode(nal);
+
+        self.frame_sender
+            .send(DecodedFrame {
+                timestamp,
+                size: nal.len(),
+                image,
+            })
+            .is_ok()
+    }
+}
@@ -1,3 +1,5 @@
+mod decoder;
+
 use alvr_client_core::{ClientCapabilities, ClientCoreContext, ClientCoreEvent};
 use alvr_common::{
     DeviceMotion, HEAD_ID, Pose, RelaxedAtomic, ViewParams,
@@ -6,9 +8,13 @@ use alvr_common::{
 };
 use alvr_packets::{FaceData, TrackingData};
 use alvr_session::CodecType;
+use decoder::{DecodedFrame, SoftwareDecoder};
 use eframe::{
     Frame, NativeOptions,
-    egui::{CentralPanel, Context, RichText, Slider, ViewportBuilder},
+    egui::{
+        CentralPanel, ColorImage, Context, Image, RichText, Slider, TextureHandle, TextureOptions,
+        ViewportBuilder, load::SizedTexture,
+    },
 };
 use std::{
     f32::consts::{FRAC_PI_2, PI},
@@ -26,7 +32,6 @@ struct WindowInput {
     yaw: f32,
     pitch: f32,
     use_random_orientation: bool,
-    emulated_decode_ms: u64,
     emulated_compositor_ms: u64,
     emulated_vsync_ms: u64,
 }
@@ -38,7 +43,6 @@ impl Default for WindowInput {
             yaw: 0.0,
             pitch: 0.0,
             use_random_orientation: true,
-            emulated_decode_ms: 5,
             emulated_compositor_ms: 1,
             emulated_vsync_ms: 25,
         }
@@ -53,6 +57,8 @@ struct WindowOutput {
     resolution: UVec2,
     decoder_codec: Option<CodecType>,
     current_frame_timestamp: Duration,
+    received_frames: u64,
+    received_bytes: u64,
 }
 
 impl Default for WindowOutput {
@@ -64,6 +70,8 @@ impl Default for WindowOutput {
             resolution: UVec2::ZERO,
             decoder_codec: None,
             current_frame_timestamp: Duration::ZERO,
+            received_frames: 0,
+            received_bytes: 0,
         }
     }
 }
@@ -73,18 +81,24 @@ pub struct Window {
     input_sender: mpsc::Sender<WindowInput>,
     output: WindowOutput,
     output_receiver: mpsc::Receiver<WindowOutput>,
+    image_receiver: mpsc::Receiver<ColorImage>,
+    // Last decoded frame
+    texture: Option<TextureHandle>,
 }
 
 impl Window {
     fn new(
         input_sender: mpsc::Sender<WindowInput>,
         output_receiver: mpsc::Receiver<WindowOutput>,
+        image_receiver: mpsc::Receiver<ColorImage>,
     ) -> Self {
         Self {
             input: WindowInput::default(),
             input_sender,
             output: WindowOutput::default(),
             output_receiver,
+            image_receiver,
+            texture: None,
         }
     }
 }
@@ -94,6 +108,18 @@ impl eframe::App for Window {
         while let Ok(output) = self.output_receiver.try_recv() {
             self.output = output;
         }
+        if let Some(image) = self.image_receiver.try_iter().last() {
+            match &mut self.texture {
+                Some(texture) => texture.set(image, TextureOptions::LINEAR),
+                None => {
+                    self.texture =
+                        Some(context.load_texture("frame", image, TextureOptions::LINEAR));
+                }
+            }
+        }
+        if !self.output.connected {
+            self.texture = None;
+        }
 
         let mut input = self.input.clone();
 
@@ -109,6 +135,11 @@ impl eframe::App for Window {
                 "Current frame: {:?}",
                 self.output.current_frame_timestamp
             ));
+            ui.label(format!(
+                "Received frames: {} ({} KB)",
+                self.output.received_frames,
+                self.output.received_bytes / 1000
+            ));
             ui.add_space(10.0);
             ui.horizontal(|ui| {
                 ui.label("Height:");
@@ -126,6 +157,10 @@ impl eframe::App for Window {
                 &mut input.use_random_orientation,
                 "Use randomized orientation offset",
             );
+            if let Some(texture) = &self.texture {
+                ui.add_space(10.0);
+                ui.add(Image::from_texture(SizedTexture::from_handle(texture)).shrink_to_fit());
+            }
         });
 
         if input != self.input {
@@ -188,6 +223,7 @@ fn tracking_thread(
 fn client_thread(
     output_sender: mpsc::Sender<WindowOutput>,
     input_receiver: mpsc::Receiver<WindowInput>,
+    image_sender: mpsc::Sender<ColorImage>,
 ) {
     let capabilities = ClientCapabilities {
         platform: alvr_system_info::platform(None, None),
@@ -201,9 +237,20 @@ fn client_thread(
         prefer_10bit: false,
         preferred_encoding_gamma: 1.0,
         prefer_hdr: false,
+        depth_layers: false,
+        space_warp: false,
+        decoder_chroma_422: false,
+        decoder_chroma_444: false,
+        radial_foveated_encoding: false,
+        per_eye_foveated_encoding: false,
+        flat_display: false,
     };
     let client_core_context = Arc::new(ClientCoreContext::new(capabilities));
 
+    // The frames are decoded in the video receive thread and forwarded to the main loop
+    let (frame_sender, frame_receiver) = mpsc::channel::<DecodedFrame>();
+    let events = client_core_context.subscribe_events();
+
     client_core_context.resume();
 
     let streaming = Arc::new(RelaxedAtomic::new(false));
@@ -217,7 +264,7 @@ fn client_thread(
     'main_loop: loop {
         let input_lock = window_input.read();
 
-        while let Some(event) = client_core_context.poll_event() {
+        while let Ok(event) = events.try_recv() {
             match event {
                 ClientCoreEvent::UpdateHudMessage(message) => {
                     window_output.hud_message = message;
@@ -254,7 +301,14 @@ fn client_thread(
                     window_output.resolution = UVec2::ZERO;
                     window_output.decoder_codec = None;
                 }
-                ClientCoreEvent::DecoderConfig { codec, .. } => {
+                ClientCoreEvent::DecoderConfig { codec, config_nal } => {
+                    if !got_decoder_config.value() {
+                        client_core_context.set_decoder(SoftwareDecoder::new(
+                            codec,
+                            &config_nal,
+                            frame_sender.clone(),
+                        ));
+                    }
                     got_decoder_config.set(true);
 
                     window_output.decoder_codec = Some(codec);
@@ -265,6 +319,23 @@ fn client_thread(
             output_sender.send(window_output.clone()).ok();
         }
 
+        let mut received_frames = false;
+        while let Ok(frame) = frame_receiver.try_recv() {
+            client_core_context.report_frame_decoded(frame.timestamp);
+
+            window_output.current_frame_timestamp = frame.timestamp;
+            window_output.received_frames += 1;
+            window_output.received_bytes += frame.size as u64;
+            received_frames = true;
+
+            if let Some(image) = frame.image {
+                image_sender.send(image).ok();
+            }
+        }
+        if received_frames {
+            output_sender.send(window_output.clone()).ok();
+        }
+
         thread::sleep(Duration::from_millis(3));
 
         client_core_context.report_compositor_start(window_output.current_frame_timestamp);
@@ -303,18 +374,25 @@ fn main() {
 
     let (input_sender, input_receiver) = mpsc::channel::<WindowInput>();
     let (output_sender, output_receiver) = mpsc::channel::<WindowOutput>();
+    let (image_sender, image_receiver) = mpsc::channel::<ColorImage>();
 
     let client_thread = thread::spawn(|| {
-        client_thread(output_sender, input_receiver);
+        client_thread(output_sender, input_receiver, image_sender);
     });
 
     eframe::run_native(
         "Mock client",
         NativeOptions {
-            viewport: ViewportBuilder::default().with_inner_size((400.0, 400.0)),
+            viewport: ViewportBuilder::default().with_inner_size((800.0, 700.0)),
             ..Default::default()
         },
-        Box::new(|_| Ok(Box::new(Window::new(input_sender, output_receiver)))),
+        Box::new(|_| {
+            Ok(Box::new(Window::new(
+                input_sender,
+                output_receiver,
+                image_receiver,
+            )))
+        }),
     )
     .ok();
 
@@ -0,0 +1,174 @@
+use alvr_common::*;
+use alvr_packets::{ButtonEntry, ButtonValue};
+use openxr as xr;
+use std::collections::HashSet;
+
+pub const EXT_HAND_INTERACTION_EXTENSION_NAME: &str = "XR_EXT_hand_interaction";
+
+const HAND_INTERACTION_PROFILE_PATH: &str = "/interaction_profiles/ext/hand_interaction_ext";
+
+// For the controllers without analog trigger or grip
+const CLICK_THRESHOLD: f32 = 0.9;
+
+struct HandActions {
+    pinch_value: xr::Action<f32>,
+    pinch_ready: xr::Action<bool>,
+    aim_activate_value: xr::Action<f32>,
+    grasp_value: xr::Action<f32>,
+}
+
+// Pinch, aim and grasp of the hand interaction profile, used by the runtime for the hands tracked
+// without controllers. They are sent as the equivalent inputs of the controller profile. The poses
+// are not bound, the hand skeleton is used instead
+pub struct HandInteractionActions {
+    hands: [HandActions; 2],
+}
+
+impl HandInteractionActions {
+    pub fn new(
+        instance: &xr::Instance,
+        action_set: &xr::ActionSet,
+        extra_extensions: &[String],
+    ) -> xr::Result<Self> {
+        if !extra_extensions.contains(&EXT_HAND_INTERACTION_EXTENSION_NAME.to_owned()) {
+            return Err(xr::sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+        }
+
+        let hands = ["left", "right"].map(|hand| {
+            let display_hand = if hand == "left" { "Left" } else { "Right" };
+
+            HandActions {
+                pinch_value: action_set
+                    .create_action(
+                        &format!("{hand}_hand_pinch_value"),
+                        &format!("{display_hand} hand pinch value"),
+                        &[],
+                    )
+                    .unwrap(),
+                pinch_ready: action_set
+                    .create_action(
+                        &format!("{hand}_hand_pinch_ready"),
+                        &format!("{display_hand} hand pinch ready"),
+                        &[],
+                    )
+                    .unwrap(),
+                aim_activate_value: action_set
+                    .create_action(
+                        &format!("{hand}_hand_aim_activate_value"),
+                        &format!("{display_hand} hand aim activate value"),
+                        &[],
+                    )
+                    .unwrap(),
+                grasp_value: action_set
+                    .create_action(
+                        &format!("{hand}_hand_grasp_value"),
+                        &format!("{display_hand} hand grasp value"),
+                        &[],
+                    )
+                    .unwrap(),
+            }
+        });
+
+        let path = |hand: &str, input: &str| {
+            instance
+                .string_to_path(&format!("/user/hand/{hand}/input/{input}"))
+                .unwrap()
+        };
+        let mut bindings = vec![];
+        for (actions, hand) in hands.iter().zip(["left", "right"]) {
+            bindings.push(xr::Binding::new(
+                &actions.pinch_value,
+                path(hand, "pinch_ext/value"),
+            ));
+            bindings.push(xr::Binding::new(
+                &actions.pinch_ready,
+                path(hand, "pinch_ext/ready_ext"),
+            ));
+            bindings.push(xr::Binding::new(
+                &actions.aim_activate_value,
+                path(hand, "aim_activate_ext/value"),
+            ));
+            bindings.push(xr::Binding::new(
+                &actions.grasp_value,
+                path(hand, "grasp_ext/value"),
+            ));
+        }
+
+        instance.suggest_interaction_profile_bindings(
+            instance
+                .string_to_path(HAND_INTERACTION_PROFILE_PATH)
+                .unwrap(),
+            &bindings,
+        )?;
+
+        Ok(Self { hands })
+    }
+
+    // Entries only for the inputs of the controller profile. Nothing is sent while the hand uses
+    // another profile
+    pub fn get_buttons<G>(
+        &self,
+        xr_session: &xr::Session<G>,
+        input_ids: &HashSet<u64>,
+    ) -> Vec<ButtonEntry> {
+        let mut button_entries = vec![];
+
+        let ids = [
+            (
+                *LEFT_TRIGGER_VALUE_ID,
+                *LEFT_TRIGGER_CLICK_ID,
+                *LEFT_TRIGGER_TOUCH_ID,
+                *LEFT_SQUEEZE_VALUE_ID,
+                *LEFT_SQUEEZE_CLICK_ID,
+            ),
+            (
+                *RIGHT_TRIGGER_VALUE_ID,
+                *RIGHT_TRIGGER_CLICK_ID,
+                *RIGHT_TRIGGER_TOUCH_ID,
+                *RIGHT_SQUEEZE_VALUE_ID,
+                *RIGHT_SQUEEZE_CLICK_ID,
+            ),
+        ];
+        for (
+            actions,
+            (trigger_value, trigger_click, trigger_touch, squeeze_value, squeeze_click),
+        ) in self.hands.iter().zip(ids)
+        {
+            let (Ok(pinch), Ok(ready), Ok(aim_activate), Ok(grasp)) = (
+                actions.pinch_value.state(xr_session, xr::Path::NULL),
+                actions.pinch_ready.state(xr_session, xr::Path::NULL),
+                actions.aim_activate_value.state(xr_session, xr::Path::NULL),
+                actions.grasp_value.state(xr_session, xr::Path::NULL),
+            ) else {
+                continue;
+            };
+            if !pinch.is_active {
+                continue;
+            }
+
+            let mut push = |id, value| {
+                if input_ids.contains(&id) {
+                    button_entries.push(ButtonEntry { path_id: id, value });
+                }
+            };
+
+            if pinch.changed_since_last_sync || aim_activate.changed_since_last_sync {
+                let value = f32::max(pinch.current_state, aim_activate.current_state);
+                push(trigger_value, ButtonValue::Scalar(value));
+                push(trigger_click, ButtonValue::Binary(value >= CLICK_THRESHOLD));
+            }
+            if ready.changed_since_last_sync {
+                push(trigger_touch, ButtonValue::Binary(ready.current_state));
+            }
+            if grasp.changed_since_last_sync {
+                push(squeeze_value, ButtonValue::Scalar(grasp.current_state));
+                push(
+                    squeeze_click,
+                    ButtonValue::Binary(grasp.current_state >= CLICK_THRESHOLD),
+                );
+            }
+        }
+
+        button_entries
+    }
+}
@@ -0,0 +1,1110 @@
+use crate::extra_extensions::get_instance_proc;
+use alvr_common::{parking_lot::Mutex, warn};
+use openxr::{self as xr, AnyGraphics, sys};
+use std::{
+    collections::HashMap,
+    error::Error,
+    ffi::{c_char, c_void},
+    fmt::{self, Display, Formatter},
+    ptr,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+pub const EXT_SPATIAL_ENTITY_EXTENSION_NAME: &str = "XR_EXT_spatial_entity";
+pub const EXT_SPATIAL_MARKER_TRACKING_EXTENSION_NAME: &str = "XR_EXT_spatial_marker_tracking";
+
+// New markers are searched periodically, already discovered markers are updated on every poll. Once
+// the runtime sent a discovery recommended event, new markers are searched only on these events
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(1);
+// Some runtimes never complete the creation future, for example if the marker tracking permission
+// is missing
+const CONTEXT_CREATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+static TYPE_SPATIAL_CONTEXT_CREATE_INFO_EXT: LazyLock<xr::StructureType> =
+    LazyLock::new(|| xr::StructureType::from_raw(1000740001));
+static TYPE_CREATE_SPATIAL_CONTEXT_COMPLETION_EXT: LazyLock<xr::StructureType> =
+    LazyLock::new(|| xr::StructureType::from_raw(1000740002));
+static TYPE_SPATIAL_DISCOVERY_SNAPSHOT_CREATE_INFO_EXT: LazyLock<xr::StructureType> =
+    LazyLock::new(|| xr::StructureType::from_raw(1000740003));
+static TYPE_CREATE_SPATIAL_DISCOVERY_SNAPSHOT_COMPLETION_INFO_EXT: LazyLock<xr::StructureType> =
+    LazyLock::new(|| xr::StructureType::from_raw(1000740004));
+static TYPE_CREATE_SPATIAL_DISCOVERY_SNAPSHOT_COMPLETION_EXT: LazyLock<xr::StructureType> =
+    LazyLock::new(|| xr::StructureType::from_raw(1000740005));
+static TYPE_SPATIAL_COMPONENT_DATA_QUERY_CONDITION_EXT: LazyLock<xr::StructureType> =
+    LazyLock::new(|| xr::StructureType::from_raw(1000740006));
+static TYPE_SPATIAL_COMPONENT_DATA_QUERY_RESULT_EXT: LazyLock<xr::StructureType> =
+    LazyLock::new(|| xr::StructureType::from_raw(1000740007));
+static TYPE_SPATIAL_BUFFER_GET_INFO_EXT: LazyLock<xr::StructureType> =
+    LazyLock::new(|| xr::StructureType::from_raw(1000740008));
+static TYPE_SPATIAL_COMPONENT_BOUNDED_2D_LIST_EXT: LazyLock<xr::StructureType> =
+    LazyLock::new(|| xr::StructureType::from_raw(1000740009));
+static TYPE_SPATIAL_ENTITY_FROM_ID_CREATE_INFO_EXT: LazyLock<xr::StructureType> =
+    LazyLock::new(|| xr::StructureType::from_raw(1000740013));
+static TYPE_SPATIAL_UPDATE_SNAPSHOT_CREATE_INFO_EXT: LazyLock<xr::StructureType> =
+    LazyLock::new(|| xr::StructureType::from_raw(1000740014));
+static TYPE_EVENT_DATA_SPATIAL_DISCOVERY_RECOMMENDED_EXT: LazyLock<xr::StructureType> =
+    LazyLock::new(|| xr::StructureType::from_raw(1000740015));
+static TYPE_SPATIAL_CAPABILITY_CONFIGURATION_QR_CODE_EXT: LazyLock<xr::StructureType> =
+    LazyLock::new(|| xr::StructureType::from_raw(1000743000));
+static TYPE_SPATIAL_COMPONENT_MARKER_LIST_EXT: LazyLock<xr::StructureType> =
+    LazyLock::new(|| xr::StructureType::from_raw(1000743006));
+static TYPE_FUTURE_CANCEL_INFO_EXT: LazyLock<xr::StructureType> =
+    LazyLock::new(|| xr::StructureType::from_raw(1000469000));
+static TYPE_FUTURE_POLL_INFO_EXT: LazyLock<xr::StructureType> =
+    LazyLock::new(|| xr::StructureType::from_raw(1000469001));
+static TYPE_FUTURE_POLL_RESULT_EXT: LazyLock<xr::StructureType> =
+    LazyLock::new(|| xr::StructureType::from_raw(1000469003));
+
+#[repr(transparent)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+struct SpatialContextEXT(u64);
+
+#[repr(transparent)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+struct SpatialSnapshotEXT(u64);
+
+#[repr(transparent)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+struct SpatialEntityEXT(u64);
+
+#[repr(transparent)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+struct SpatialEntityIdEXT(u64);
+
+#[repr(transparent)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+struct FutureEXT(u64);
+
+#[repr(transparent)]
+#[derive(Copy, Clone, Eq, PartialEq)]
+struct SpatialCapabilityEXT(i32);
+impl SpatialCapabilityEXT {
+    const MARKER_TRACKING_QR_CODE: SpatialCapabilityEXT = Self(1000743000);
+}
+
+#[repr(transparent)]
+#[derive(Copy, Clone, Eq, PartialEq)]
+struct SpatialComponentTypeEXT(i32);
+impl SpatialComponentTypeEXT {
+    const BOUNDED_2D: SpatialComponentTypeEXT = Self(1);
+    const MARKER: SpatialComponentTypeEXT = Self(1000743000);
+}
+
+#[repr(transparent)]
+#[derive(Copy, Clone, Eq, PartialEq)]
+struct SpatialEntityTrackingStateEXT(i32);
+impl SpatialEntityTrackingStateEXT {
+    const TRACKING: SpatialEntityTrackingStateEXT = Self(3);
+}
+
+#[repr(transparent)]
+#[derive(Copy, Clone, Eq, PartialEq)]
+struct SpatialBufferTypeEXT(i32);
+impl SpatialBufferTypeEXT {
+    const STRING: SpatialBufferTypeEXT = Self(1);
+}
+
+#[repr(transparent)]
+#[derive(Copy, Clone, Eq, PartialEq)]
+struct FutureStateEXT(i32);
+impl FutureStateEXT {
+    const READY: FutureStateEXT = Self(2);
+}
+
+#[repr(C)]
+struct SpatialCapabilityConfigurationQrCodeEXT {
+    ty: xr::StructureType,
+    next: *const c_void,
+    capability: SpatialCapabilityEXT,
+    enabled_component_count: u32,
+    enabled_components: *const SpatialComponentTypeEXT,
+}
+
+#[repr(C)]
+struct SpatialContextCreateInfoEXT {
+    ty: xr::StructureType,
+    next: *const c_void,
+    capability_config_count: u32,
+    capability_configs: *const *const SpatialCapabilityConfigurationQrCodeEXT,
+}
+
+#[repr(C)]
+struct EventDataSpatialDiscoveryRecommendedEXT {
+    ty: xr::StructureType,
+    next: *const c_void,
+    spatial_context: SpatialContextEXT,
+}
+
+#[repr(C)]
+struct CreateSpatialContextCompletionEXT {
+    ty: xr::StructureType,
+    next: *mut c_void,
+    future_result: sys::Result,
+    spatial_context: SpatialContextEXT,
+}
+
+#[repr(C)]
+struct SpatialDiscoverySnapshotCreateInfoEXT {
+    ty: xr::StructureType,
+    next: *const c_void,
+    component_type_count: u32,
+    component_types: *const SpatialComponentTypeEXT,
+}
+
+#[repr(C)]
+struct CreateSpatialDiscoverySnapshotCompletionInfoEXT {
+    ty: xr::StructureType,
+    next: *const c_void,
+    base_space: sys::Space,
+    time: sys::Time,
+    future: FutureEXT,
+}
+
+#[repr(C)]
+struct CreateSpatialDiscoverySnapshotCompletionEXT {
+    ty: xr::StructureType,
+    next: *mut c_void,
+    future_result: sys::Result,
+    snapshot: SpatialSnapshotEXT,
+}
+
+#[repr(C)]
+struct SpatialComponentDataQueryConditionEXT {
+    ty: xr::StructureType,
+    next: *const c_void,
+    component_type_count: u32,
+    component_types: *const SpatialComponentTypeEXT,
+}
+
+#[repr(C)]
+struct SpatialComponentDataQueryResultEXT {
+    ty: xr::StructureType,
+    next: *mut c_void,
+    entity_id_capacity_input: u32,
+    entity_id_count_output: u32,
+    entity_ids: *mut SpatialEntityIdEXT,
+    entity_state_capacity_input: u32,
+    entity_state_count_output: u32,
+    entity_states: *mut SpatialEntityTrackingStateEXT,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SpatialBufferEXT {
+    buffer_id: u64,
+    buffer_type: SpatialBufferTypeEXT,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SpatialMarkerDataEXT {
+    capability: SpatialCapabilityEXT,
+    marker_id: u32,
+    data: SpatialBufferEXT,
+}
+
+#[repr(C)]
+struct SpatialComponentMarkerListEXT {
+    ty: xr::StructureType,
+    next: *mut c_void,
+    marker_count: u32,
+    markers: *mut SpatialMarkerDataEXT,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SpatialBounded2DDataEXT {
+    center: sys::Posef,
+    extents: sys::Extent2Df,
+}
+
+#[repr(C)]
+struct SpatialComponentBounded2DListEXT {
+    ty: xr::StructureType,
+    next: *mut c_void,
+    bound_count: u32,
+    bounds: *mut SpatialBounded2DDataEXT,
+}
+
+#[repr(C)]
+struct SpatialBufferGetInfoEXT {
+    ty: xr::StructureType,
+    next: *const c_void,
+    buffer_id: u64,
+}
+
+#[repr(C)]
+struct SpatialEntityFromIdCreateInfoEXT {
+    ty: xr::StructureType,
+    next: *const c_void,
+    entity_id: SpatialEntityIdEXT,
+}
+
+#[repr(C)]
+struct SpatialUpdateSnapshotCreateInfoEXT {
+    ty: xr::StructureType,
+    next: *const c_void,
+    entity_count: u32,
+    entities: *const SpatialEntityEXT,
+    component_type_count: u32,
+    component_types: *const SpatialComponentTypeEXT,
+    base_space: sys::Space,
+    time: sys::Time,
+}
+
+#[repr(C)]
+struct FutureCancelInfoEXT {
+    ty: xr::StructureType,
+    next: *const c_void,
+    future: FutureEXT,
+}
+
+#[repr(C)]
+struct FuturePollInfoEXT {
+    ty: xr::StructureType,
+    next: *const c_void,
+    future: FutureEXT,
+}
+
+#[repr(C)]
+struct FuturePollResultEXT {
+    ty: xr::StructureType,
+    next: *mut c_void,
+    state: FutureStateEXT,
+}
+
+type CreateSpatialContextAsyncEXT = unsafe extern "system" fn(
+    sys::Session,
+    *const SpatialContextCreateInfoEXT,
+    *mut FutureEXT,
+) -> sys::Result;
+type CreateSpatialContextCompleteEXT = unsafe extern "system" fn(
+    sys::Session,
+    FutureEXT,
+    *mut CreateSpatialContextCompletionEXT,
+) -> sys::Result;
+type DestroySpatialContextEXT = unsafe extern "system" fn(SpatialContextEXT) -> sys::Result;
+type CreateSpatialDiscoverySnapshotAsyncEXT = unsafe extern "system" fn(
+    SpatialContextEXT,
+    *const SpatialDiscoverySnapshotCreateInfoEXT,
+    *mut FutureEXT,
+) -> sys::Result;
+type CreateSpatialDiscoverySnapshotCompleteEXT = unsafe extern "system" fn(
+    SpatialContextEXT,
+    *const CreateSpatialDiscoverySnapshotCompletionInfoEXT,
+    *mut CreateSpatialDiscoverySnapshotCompletionEXT,
+) -> sys::Result;
+type QuerySpatialComponentDataEXT = unsafe extern "system" fn(
+    SpatialSnapshotEXT,
+    *const SpatialComponentDataQueryConditionEXT,
+    *mut SpatialComponentDataQueryResultEXT,
+) -> sys::Result;
+type DestroySpatialSnapshotEXT = unsafe extern "system" fn(SpatialSnapshotEXT) -> sys::Result;
+type GetSpatialBufferStringEXT = unsafe extern "system" fn(
+    SpatialSnapshotEXT,
+    *const SpatialBufferGetInfoEXT,
+    u32,
+    *mut u32,
+    *mut c_char,
+) -> sys::Result;
+type CreateSpatialEntityFromIdEXT = unsafe extern "system" fn(
+    SpatialContextEXT,
+    *const SpatialEntityFromIdCreateInfoEXT,
+    *mut SpatialEntityEXT,
+) -> sys::Result;
+type DestroySpatialEntityEXT = unsafe extern "system" fn(SpatialEntityEXT) -> sys::Result;
+type CreateSpatialUpdateSnapshotEXT = unsafe extern "system" fn(
+    SpatialContextEXT,
+    *const SpatialUpdateSnapshotCreateInfoEXT,
+    *mut SpatialSnapshotEXT,
+) -> sys::Result;
+type PollFutureEXT = unsafe extern "system" fn(
+    sys::Instance,
+    *const FuturePollInfoEXT,
+    *mut FuturePollResultEXT,
+) -> sys::Result;
+type CancelFutureEXT =
+    unsafe extern "system" fn(sys::Instance, *const FutureCancelInfoEXT) -> sys::Result;
+
+struct ExtFns {
+    create_spatial_context_async: CreateSpatialContextAsyncEXT,
+    create_spatial_context_complete: CreateSpatialContextCompleteEXT,
+    destroy_spatial_context: DestroySpatialContextEXT,
+    create_spatial_discovery_snapshot_async: CreateSpatialDiscoverySnapshotAsyncEXT,
+    create_spatial_discovery_snapshot_complete: CreateSpatialDiscoverySnapshotCompleteEXT,
+    query_spatial_component_data: QuerySpatialComponentDataEXT,
+    destroy_spatial_snapshot: DestroySpatialSnapshotEXT,
+    get_spatial_buffer_string: GetSpatialBufferStringEXT,
+    create_spatial_entity_from_id: CreateSpatialEntityFromIdEXT,
+    destroy_spatial_entity: DestroySpatialEntityEXT,
+    create_spatial_update_snapshot: CreateSpatialUpdateSnapshotEXT,
+    poll_future: PollFutureEXT,
+    cancel_future: CancelFutureEXT,
+}
+
+// Data of the tracked entities contained in a snapshot. Components not requested are left empty.
+struct SnapshotData {
+    entity_ids: Vec<SpatialEntityIdEXT>,
+    entity_states: Vec<SpatialEntityTrackingStateEXT>,
+    markers: Vec<SpatialMarkerDataEXT>,
+    bounds: Vec<SpatialBounded2DDataEXT>,
+}
+
+enum State {
+    CreatingContext {
+        future: FutureEXT,
+        start: Instant,
+    },
+    Idle {
+        context: SpatialContextEXT,
+        last_discovery: Option<Instant>,
+    },
+    Discovering {
+        context: SpatialContextEXT,
+        future: FutureEXT,
+    },
+    CreationFailed(MarkerTrackingError),
+}
+
+impl State {
+    fn context(&self) -> Option<SpatialContextEXT> {
+        match self {
+            State::CreatingContext { .. } | State::CreationFailed(_) => None,
+            State::Idle { context, .. } | State::Discovering { context, .. } => Some(*context),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MarkerTrackingError {
+    // The runtime does not support the spatial entity or the marker tracking extension
+    ExtensionNotPresent,
+    // The context cannot be used and must be recreated
+    ContextCreationFailed(sys::Result),
+    // The runtime did not create the context within CONTEXT_CREATION_TIMEOUT
+    ContextCreationTimeout,
+    // The decoded string of a marker could not be read
+    BufferDecode(sys::Result),
+    RuntimeError(sys::Result),
+}
+
+impl Display for MarkerTrackingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MarkerTrackingError::ExtensionNotPresent => {
+                write!(f, "Marker tracking is not supported by the runtime")
+            }
+            MarkerTrackingError::ContextCreationFailed(e) => {
+                write!(f, "Failed to create the spatial context: {e}")
+            }
+            MarkerTrackingError::ContextCreationTimeout => {
+                write!(f, "Timed out creating the spatial context")
+            }
+            MarkerTrackingError::BufferDecode(e) => {
+                write!(f, "Failed to read the marker data: {e}")
+            }
+            MarkerTrackingError::RuntimeError(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl Error for MarkerTrackingError {}
+
+impl From<sys::Result> for MarkerTrackingError {
+    fn from(result: sys::Result) -> Self {
+        MarkerTrackingError::RuntimeError(result)
+    }
+}
+
+type MarkerResult<T> = Result<T, MarkerTrackingError>;
+
+// Markers reported by QRCodesSpatialContext
+#[derive(Clone, PartialEq, Debug)]
+pub enum MarkerFilter {
+    // Used by the lobby, to choose a marker
+    All,
+    // Decoded strings of the QR codes
+    Codes(Vec<String>),
+}
+
+impl MarkerFilter {
+    fn accepts(&self, code: &str) -> bool {
+        match self {
+            MarkerFilter::All => true,
+            MarkerFilter::Codes(codes) => codes.iter().any(|c| c == code),
+        }
+    }
+}
+
+struct Inner {
+    state: State,
+    filter: MarkerFilter,
+    // Decoded QR code string to entity. Entities are kept alive to get updates without discovery
+    spatial_entities: HashMap<String, (SpatialEntityIdEXT, SpatialEntityEXT)>,
+    // Result of the last successful poll, in its first base space
+    last_markers: Vec<(String, xr::Posef)>,
+    // Set when the runtime sent a discovery recommended event, the timer is not used anymore
+    event_driven_discovery: bool,
+    discovery_recommended: bool,
+}
+
+// Some runtimes advertise the extensions without exposing all the functions
+fn get_proc<G, FnTy>(session: &xr::Session<G>, method_name: &str) -> MarkerResult<FnTy> {
+    get_instance_proc(session, method_name).map_err(|e| match e {
+        sys::Result::ERROR_EXTENSION_NOT_PRESENT | sys::Result::ERROR_FUNCTION_UNSUPPORTED => {
+            MarkerTrackingError::ExtensionNotPresent
+        }
+        e => MarkerTrackingError::RuntimeError(e),
+    })
+}
+
+// Tracks QR codes using the spatial entity framework. The context creation and the discovery of
+// new markers are asynchronous and are advanced by `poll`.
+pub struct QRCodesSpatialContext {
+    session: xr::Session<AnyGraphics>,
+    ext_fns: ExtFns,
+    inner: Mutex<Inner>,
+}
+
+impl QRCodesSpatialContext {
+    pub fn new<G>(
+        session: &xr::Session<G>,
+        extra_extensions: &[String],
+        filter: MarkerFilter,
+    ) -> MarkerResult<Self> {
+        if !extra_extensions.contains(&EXT_SPATIAL_ENTITY_EXTENSION_NAME.to_owned())
+            || !extra_extensions.contains(&EXT_SPATIAL_MARKER_TRACKING_EXTENSION_NAME.to_owned())
+            || session.instance().exts().ext_future.is_none()
+        {
+            return Err(MarkerTrackingError::ExtensionNotPresent);
+        }
+
+        let ext_fns = ExtFns {
+            create_spatial_context_async: get_proc(session, "xrCreateSpatialContextAsyncEXT")?,
+            create_spatial_context_complete: get_proc(
+                session,
+                "xrCreateSpatialContextCompleteEXT",
+            )?,
+            destroy_spatial_context: get_proc(session, "xrDestroySpatialContextEXT")?,
+            create_spatial_discovery_snapshot_async: get_proc(
+                session,
+                "xrCreateSpatialDiscoverySnapshotAsyncEXT",
+            )?,
+            create_spatial_discovery_snapshot_complete: get_proc(
+                session,
+                "xrCreateSpatialDiscoverySnapshotCompleteEXT",
+            )?,
+            query_spatial_component_data: get_proc(session, "xrQuerySpatialComponentDataEXT")?,
+            destroy_spatial_snapshot: get_proc(session, "xrDestroySpatialSnapshotEXT")?,
+            get_spatial_buffer_string: get_proc(session, "xrGetSpatialBufferStringEXT")?,
+            create_spatial_entity_from_id: get_proc(session, "xrCreateSpatialEntityFromIdEXT")?,
+            destroy_spatial_entity: get_proc(session, "xrDestroySpatialEntityEXT")?,
+            create_spatial_update_snapshot: get_proc(session, "xrCreateSpatialUpdateSnapshotEXT")?,
+            poll_future: get_proc(session, "xrPollFutureEXT")?,
+            cancel_future: get_proc(session, "xrCancelFutureEXT")?,
+        };
+
+        let components = [
+            SpatialComponentTypeEXT::MARKER,
+            SpatialComponentTypeEXT::BOUNDED_2D,
+        ];
+        let qr_code_config = SpatialCapabilityConfigurationQrCodeEXT {
+            ty: *TYPE_SPATIAL_CAPABILITY_CONFIGURATION_QR_CODE_EXT,
+            next: ptr::null(),
+            capability: SpatialCapabilityEXT::MARKER_TRACKING_QR_CODE,
+            enabled_component_count: components.len() as u32,
+            enabled_components: components.as_ptr(),
+        };
+        let capability_configs = [ptr::from_ref(&qr_code_config)];
+        let create_info = SpatialContextCreateInfoEXT {
+            ty: *TYPE_SPATIAL_CONTEXT_CREATE_INFO_EXT,
+            next: ptr::null(),
+            capability_config_count: capability_configs.len() as u32,
+            capability_configs: capability_configs.as_ptr(),
+        };
+
+        let mut future = FutureEXT(0);
+        unsafe {
+            super::xr_res((ext_fns.create_spatial_context_async)(
+                session.as_raw(),
+                &create_info,
+                &mut future,
+            ))
+            .map_err(MarkerTrackingError::ContextCreationFailed)?;
+        }
+
+        Ok(Self {
+            session: session.clone().into_any_graphics(),
+            ext_fns,
+            inner: Mutex::new(Inner {
+                state: State::CreatingContext {
+                    future,
+                    start: Instant::now(),
+                },
+                filter,
+                spatial_entities: HashMap::new(),
+                last_markers: vec![],
+                event_driven_discovery: false,
+                discovery_recommended: false,
+            }),
+        })
+    }
+
+    // Changes the tracked markers without recreating the context. The entities of the markers
+    // still accepted are kept, so they are reported without waiting for a new discovery.
+    pub fn set_filter(&self, filter: MarkerFilter) {
+        let inner = &mut *self.inner.lock();
+        if inner.filter == filter {
+            return;
+        }
+
+        inner.spatial_entities.retain(|code, (_, entity)| {
+            let keep = filter.accepts(code);
+            if !keep {
+                unsafe { (self.ext_fns.destroy_spatial_entity)(*entity) };
+            }
+
+            keep
+        });
+        inner.last_markers.retain(|(code, _)| filter.accepts(code));
+        inner.filter = filter;
+
+        // Look for the newly accepted markers right away
+        if let State::Idle { last_discovery, .. } = &mut inner.state {
+            *last_discovery = None;
+        }
+    }
+
+    // Handles the events not parsed by openxrs. The runtime recommends a new discovery when the
+    // markers may have changed, for example when a new marker comes into view
+    pub fn handle_event(&self, event: &sys::EventDataBuffer) {
+        if event.ty != *TYPE_EVENT_DATA_SPATIAL_DISCOVERY_RECOMMENDED_EXT {
+            return;
+        }
+        let event =
+            unsafe { &*ptr::from_ref(event).cast::<EventDataSpatialDiscoveryRecommendedEXT>() };
+
+        let inner = &mut *self.inner.lock();
+        if inner.state.context() == Some(event.spatial_context) {
+            inner.event_driven_discovery = true;
+            inner.discovery_recommended = true;
+        }
+    }
+
+    // Returns the poses of the tracked markers, identified by their decoded string. Markers that
+    // are not currently visible are not returned. After ContextCreationFailed or
+    // ContextCreationTimeout, every poll fails.
+    pub fn poll(
+        &self,
+        base_space: &xr::Space,
+        time: xr::Time,
+    ) -> MarkerResult<Vec<(String, xr::Posef)>> {
+        let mut markers = self.poll_in_spaces(&[base_space], time)?;
+
+        Ok(markers.pop().unwrap_or_default())
+    }
+
+    // Like poll(), with the poses located in each of the base spaces, in the same order. The
+    // discovery runs once, in the first space.
+    pub fn poll_in_spaces(
+        &self,
+        base_spaces: &[&xr::Space],
+        time: xr::Time,
+    ) -> MarkerResult<Vec<Vec<(String, xr::Posef)>>> {
+        let Some(first_space) = base_spaces.first() else {
+            return Ok(vec![]);
+        };
+
+        let inner = &mut *self.inner.lock();
+
+        self.advance_state(inner, first_space, time)?;
+
+        let Some(context) = inner.state.context() else {
+            return Ok(vec![vec![]; base_spaces.len()]);
+        };
+
+        let markers = base_spaces
+            .iter()
+            .map(|base_space| self.update_markers(inner, context, base_space, time))
+            .collect::<MarkerResult<Vec<_>>>()?;
+        inner.last_markers = markers[0].clone();
+
+        Ok(markers)
+    }
+
+    // Returns the markers of the last successful poll without querying the runtime, for example to
+    // render at a higher rate than tracking is polled. The poses are located in the first base
+    // space at the time of that poll.
+    pub fn last_markers(&self) -> Vec<(String, xr::Posef)> {
+        self.inner.lock().last_markers.clone()
+    }
+
+    fn advance_state(
+        &self,
+        inner: &mut Inner,
+        base_space: &xr::Space,
+        time: xr::Time,
+    ) -> MarkerResult<()> {
+        match inner.state {
+            State::CreatingContext { future, start } => {
+                if !self.is_future_ready(future)? {
+                    if start.elapsed() < CONTEXT_CREATION_TIMEOUT {
+                        return Ok(());
+                    }
+
+                    self.cancel_future(future);
+                    inner.state =
+                        State::CreationFailed(MarkerTrackingError::ContextCreationTimeout);
+
+                    return Err(MarkerTrackingError::ContextCreationTimeout);
+                }
+
+                let mut completion = CreateSpatialContextCompletionEXT {
+                    ty: *TYPE_CREATE_SPATIAL_CONTEXT_COMPLETION_EXT,
+                    next: ptr::null_mut(),
+                    future_result: sys::Result::SUCCESS,
+                    spatial_context: SpatialContextEXT(0),
+                };
+                let res = unsafe {
+                    super::xr_res((self.ext_fns.create_spatial_context_complete)(
+                        self.session.as_raw(),
+                        future,
+                        &mut completion,
+                    ))
+                }
+                .and_then(|()| super::xr_res(completion.future_result));
+                if let Err(e) = res {
+                    // The future is consumed, the creation cannot be retried
+                    let error = MarkerTrackingError::ContextCreationFailed(e);
+                    inner.state = State::CreationFailed(error);
+
+                    return Err(error);
+                }
+
+                inner.state = State::Idle {
+                    context: completion.spatial_context,
+                    last_discovery: None,
+                };
+            }
+            State::Idle {
+                context,
+                last_discovery,
+            } if inner.discovery_recommended
+                || last_discovery.is_none_or(|time| {
+                    !inner.event_driven_discovery && time.elapsed() > DISCOVERY_TIMEOUT
+                }) =>
+            {
+                let components = [
+                    SpatialComponentTypeEXT::MARKER,
+                    SpatialComponentTypeEXT::BOUNDED_2D,
+                ];
+                let create_info = SpatialDiscoverySnapshotCreateInfoEXT {
+                    ty: *TYPE_SPATIAL_DISCOVERY_SNAPSHOT_CREATE_INFO_EXT,
+                    next: ptr::null(),
+                    component_type_count: components.len() as u32,
+                    component_types: components.as_ptr(),
+                };
+
+                let mut future = FutureEXT(0);
+                unsafe {
+                    super::xr_res((self.ext_fns.create_spatial_discovery_snapshot_async)(
+                        context,
+                        &create_info,
+                        &mut future,
+                    ))?;
+                }
+
+                inner.state = State::Discovering { context, future };
+                inner.discovery_recommended = false;
+            }
+            State::Discovering { context, future } => {
+                if self.is_future_ready(future)? {
+                    // Go back to idle even if the discovery fails, to retry later
+                    inner.state = State::Idle {
+                        context,
+                        last_discovery: Some(Instant::now()),
+                    };
+
+                    let res = self.complete_discovery(inner, context, future, base_space, time);
+                    if res.is_err() {
+                        // Retried with the timer until the next event
+                        inner.event_driven_discovery = false;
+                    }
+                    res?;
+                }
+            }
+            State::CreationFailed(error) => return Err(error),
+            State::Idle { .. } => (),
+        }
+
+        Ok(())
+    }
+
+    fn is_future_ready(&self, future: FutureEXT) -> xr::Result<bool> {
+        let poll_info = FuturePollInfoEXT {
+            ty: *TYPE_FUTURE_POLL_INFO_EXT,
+            next: ptr::null(),
+            future,
+        };
+        let mut poll_result = FuturePollResultEXT {
+            ty: *TYPE_FUTURE_POLL_RESULT_EXT,
+            next: ptr::null_mut(),
+            state: FutureStateEXT(0),
+        };
+
+        unsafe {
+            super::xr_res((self.ext_fns.poll_future)(
+                self.session.instance().as_raw(),
+                &poll_info,
+                &mut poll_result,
+            ))?;
+        }
+
+        Ok(poll_result.state == FutureStateEXT::READY)
+    }
+
+    fn cancel_future(&self, future: FutureEXT) {
+        let cancel_info = FutureCancelInfoEXT {
+            ty: *TYPE_FUTURE_CANCEL_INFO_EXT,
+            next: ptr::null(),
+            future,
+        };
+
+        unsafe {
+            (self.ext_fns.cancel_future)(self.session.instance().as_raw(), &cancel_info);
+        }
+    }
+
+    fn complete_discovery(
+        &self,
+        inner: &mut Inner,
+        context: SpatialContextEXT,
+        future: FutureEXT,
+        base_space: &xr::Space,
+        time: xr::Time,
+    ) -> MarkerResult<()> {
+        let completion_info = CreateSpatialDiscoverySnapshotCompletionInfoEXT {
+            ty: *TYPE_CREATE_SPATIAL_DISCOVERY_SNAPSHOT_COMPLETION_INFO_EXT,
+            next: ptr::null(),
+            base_space: base_space.as_raw(),
+            time,
+            future,
+        };
+        let mut completion = CreateSpatialDiscoverySnapshotCompletionEXT {
+            ty: *TYPE_CREATE_SPATIAL_DISCOVERY_SNAPSHOT_COMPLETION_EXT,
+            next: ptr::null_mut(),
+            future_result: sys::Result::SUCCESS,
+            snapshot: SpatialSnapshotEXT(0),
+        };
+        unsafe {
+            super::xr_res((self.ext_fns.create_spatial_discovery_snapshot_complete)(
+                context,
+                &completion_info,
+                &mut completion,
+            ))?;
+        }
+        super::xr_res(completion.future_result)?;
+
+        let snapshot = completion.snapshot;
+        let res = self.add_discovered_entities(inner, context, snapshot);
+
+        unsafe { (self.ext_fns.destroy_spatial_snapshot)(snapshot) };
+
+        res
+    }
+
+    fn add_discovered_entities(
+        &self,
+        inner: &mut Inner,
+        context: SpatialContextEXT,
+        snapshot: SpatialSnapshotEXT,
+    ) -> MarkerResult<()> {
+        let data = self.query_spatial_component_data(snapshot, true)?;
+
+        for (idx, marker) in data.markers.iter().enumerate() {
+            if data.entity_states[idx] != SpatialEntityTrackingStateEXT::TRACKING
+                || marker.data.buffer_type != SpatialBufferTypeEXT::STRING
+            {
+                continue;
+            }
+
+            let code = self.get_buffer_string(snapshot, marker.data.buffer_id)?;
+            if inner.spatial_entities.contains_key(&code) || !inner.filter.accepts(&code) {
+                continue;
+            }
+
+            let create_info = SpatialEntityFromIdCreateInfoEXT {
+                ty: *TYPE_SPATIAL_ENTITY_FROM_ID_CREATE_INFO_EXT,
+                next: ptr::null(),
+                entity_id: data.entity_ids[idx],
+            };
+            let mut entity = SpatialEntityEXT(0);
+            unsafe {
+                super::xr_res((self.ext_fns.create_spatial_entity_from_id)(
+                    context,
+                    &create_info,
+                    &mut entity,
+                ))?;
+            }
+
+            inner
+                .spatial_entities
+                .insert(code, (data.entity_ids[idx], entity));
+        }
+
+        Ok(())
+    }
+
+    fn update_markers(
+        &self,
+        inner: &Inner,
+        context: SpatialContextEXT,
+        base_space: &xr::Space,
+        time: xr::Time,
+    ) -> MarkerResult<Vec<(String, xr::Posef)>> {
+        if inner.spatial_entities.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let entities = inner
+            .spatial_entities
+            .values()
+            .map(|(_, entity)| *entity)
+            .collect::<Vec<_>>();
+        let components = [SpatialComponentTypeEXT::BOUNDED_2D];
+        let create_info = SpatialUpdateSnapshotCreateInfoEXT {
+            ty: *TYPE_SPATIAL_UPDATE_SNAPSHOT_CREATE_INFO_EXT,
+            next: ptr::null(),
+            entity_count: entities.len() as u32,
+            entities: entities.as_ptr(),
+            component_type_count: components.len() as u32,
+            component_types: components.as_ptr(),
+            base_space: base_space.as_raw(),
+            time,
+        };
+        let mut snapshot = SpatialSnapshotEXT(0);
+        unsafe {
+            super::xr_res((self.ext_fns.create_spatial_update_snapshot)(
+                context,
+                &create_info,
+                &mut snapshot,
+            ))?;
+        }
+
+        let res = self.query_spatial_component_data(snapshot, false);
+
+        unsafe { (self.ext_fns.destroy_spatial_snapshot)(snapshot) };
+
+        let data = res?;
+
+        let markers = inner
+            .spatial_entities
+            .iter()
+            .filter_map(|(code, (entity_id, _))| {
+                let idx = data.entity_ids.iter().position(|id| id == entity_id)?;
+
+                (data.entity_states[idx] == SpatialEntityTrackingStateEXT::TRACKING)
+                    .then(|| (code.clone(), data.bounds[idx].center))
+            })
+            .collect();
+
+        Ok(markers)
+    }
+
+    // Some runtimes report more entities in the second call than in the first one. In that case the
+    // counts are queried again, once, instead of failing the poll
+    fn query_spatial_component_data(
+        &self,
+        snapshot: SpatialSnapshotEXT,
+        query_markers: bool,
+    ) -> xr::Result<SnapshotData> {
+        match self.try_query_spatial_component_data(snapshot, query_markers) {
+            Err(sys::Result::ERROR_SIZE_INSUFFICIENT) => {
+                warn!("Marker tracking: the entity count changed while querying, retrying");
+
+                self.try_query_spatial_component_data(snapshot, query_markers)
+            }
+            res => res,
+        }
+    }
+
+    fn try_query_spatial_component_data(
+        &self,
+        snapshot: SpatialSnapshotEXT,
+        query_markers: bool,
+    ) -> xr::Result<SnapshotData> {
+        let components = if query_markers {
+            vec![
+                SpatialComponentTypeEXT::MARKER,
+                SpatialComponentTypeEXT::BOUNDED_2D,
+            ]
+        } else {
+            vec![SpatialComponentTypeEXT::BOUNDED_2D]
+        };
+        let condition = SpatialComponentDataQueryConditionEXT {
+            ty: *TYPE_SPATIAL_COMPONENT_DATA_QUERY_CONDITION_EXT,
+            next: ptr::null(),
+            component_type_count: components.len() as u32,
+            component_types: components.as_ptr(),
+        };
+
+        // First call to get the entity count
+        let mut query_result = SpatialComponentDataQueryResultEXT {
+            ty: *TYPE_SPATIAL_COMPONENT_DATA_QUERY_RESULT_EXT,
+            next: ptr::null_mut(),
+            entity_id_capacity_input: 0,
+            entity_id_count_output: 0,
+            entity_ids: ptr::null_mut(),
+            entity_state_capacity_input: 0,
+            entity_state_count_output: 0,
+            entity_states: ptr::null_mut(),
+        };
+        unsafe {
+            super::xr_res((self.ext_fns.query_spatial_component_data)(
+                snapshot,
+                &condition,
+                &mut query_result,
+            ))?;
+        }
+
+        let marker_count = query_result.entity_id_count_output;
+
+        let mut data = SnapshotData {
+            entity_ids: vec![SpatialEntityIdEXT(0); marker_count as usize],
+            entity_states: vec![SpatialEntityTrackingStateEXT(0); marker_count as usize],
+            markers: if query_markers {
+                vec![
+                    SpatialMarkerDataEXT {
+                        capability: SpatialCapabilityEXT(0),
+                        marker_id: 0,
+                        data: SpatialBufferEXT {
+                            buffer_id: 0,
+                            buffer_type: SpatialBufferTypeEXT(0),
+                        },
+                    };
+                    marker_count as usize
+                ]
+            } else {
+                vec![]
+            },
+            bounds: vec![
+                SpatialBounded2DDataEXT {
+                    center: xr::Posef::IDENTITY,
+                    extents: sys::Extent2Df::default(),
+                };
+                marker_count as usize
+            ],
+        };
+
+        let mut bounded_2d_list = SpatialComponentBounded2DListEXT {
+            ty: *TYPE_SPATIAL_COMPONENT_BOUNDED_2D_LIST_EXT,
+            next: ptr::null_mut(),
+            bound_count: marker_count,
+            bounds: data.bounds.as_mut_ptr(),
+        };
+        let mut marker_list = SpatialComponentMarkerListEXT {
+            ty: *TYPE_SPATIAL_COMPONENT_MARKER_LIST_EXT,
+            next: (&raw mut bounded_2d_list).cast(),
+            marker_count,
+            markers: data.markers.as_mut_ptr(),
+        };
+
+        query_result.next = if query_markers {
+            (&raw mut marker_list).cast()
+        } else {
+            (&raw mut bounded_2d_list).cast()
+        };
+        query_result.entity_id_capacity_input = marker_count;
+        query_result.entity_ids = data.entity_ids.as_mut_ptr();
+        query_result.entity_state_capacity_input = marker_count;
+        query_result.entity_states = data.entity_states.as_mut_ptr();
+
+        unsafe {
+            super::xr_res((self.ext_fns.query_spatial_component_data)(
+                snapshot,
+                &condition,
+                &mut query_result,
+            ))?;
+        }
+
+        let count = query_result.entity_id_count_output as usize;
+        data.entity_ids.truncate(count);
+        data.entity_states.truncate(count);
+        data.bounds.truncate(count);
+        if query_markers {
+            data.markers.truncate(count);
+        }
+
+        Ok(data)
+    }
+
+    fn get_buffer_string(
+        &self,
+        snapshot: SpatialSnapshotEXT,
+        buffer_id: u64,
+    ) -> MarkerResult<String> {
+        let get_info = SpatialBufferGetInfoEXT {
+            ty: *TYPE_SPATIAL_BUFFER_GET_INFO_EXT,
+            next: ptr::null(),
+            buffer_id,
+        };
+
+        let mut count = 0;
+        unsafe {
+            super::xr_res((self.ext_fns.get_spatial_buffer_string)(
+                snapshot,
+                &get_info,
+                0,
+                &mut count,
+                ptr::null_mut(),
+            ))
+            .map_err(MarkerTrackingError::BufferDecode)?;
+        }
+
+        let mut buffer = vec![0_u8; count as usize];
+        unsafe {
+            super::xr_res((self.ext_fns.get_spatial_buffer_string)(
+                snapshot,
+                &get_info,
+                count,
+                &mut count,
+                buffer.as_mut_ptr().cast(),
+            ))
+            .map_err(MarkerTrackingError::BufferDecode)?;
+        }
+
+        // The count includes the null terminator
+        buffer.truncate(count.saturating_sub(1) as usize);
+
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
+
+impl Drop for QRCodesSpatialContext {
+    fn drop(&mut self) {
+        let inner = self.inner.get_mut();
+
+        unsafe {
+            for (_, entity) in inner.spatial_entities.values() {
+                (self.ext_fns.destroy_spatial_entity)(*entity);
+            }
+
+            let pending_future = match inner.state {
+                State::CreatingContext { future, .. } | State::Discovering { future, .. } => {
+                    Some(future)
+                }
+                State::Idle { .. } | State::CreationFailed(_) => None,
+            };
+            let context = inner.state.context();
+
+            if let Some(future) = pending_future {
+                self.cancel_future(future);
+            }
+
+            if let Some(context) = context {
+                (self.ext_fns.destroy_spatial_context)(context);
+            }
+        }
+    }
+}
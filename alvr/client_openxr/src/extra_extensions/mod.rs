@@ -5,10 +5,13 @@ mod eye_tracking_social;
 mod face_tracking2_fb;
 mod face_tracking_pico;
 mod facial_tracking_htc;
+mod hand_interaction;
+mod marker_tracking;
 mod motion_tracking_bd;
 mod multimodal_input;
 mod passthrough_fb;
 mod passthrough_htc;
+mod scene_fb;
 
 pub use body_tracking_bd::*;
 pub use body_tracking_fb::*;
@@ -17,10 +20,13 @@ pub use eye_tracking_social::*;
 pub use face_tracking_pico::*;
 pub use face_tracking2_fb::*;
 pub use facial_tracking_htc::*;
+pub use hand_interaction::*;
+pub use marker_tracking::*;
 pub use motion_tracking_bd::*;
 pub use multimodal_input::*;
 pub use passthrough_fb::*;
 pub use passthrough_htc::*;
+pub use scene_fb::*;
 use std::ffi::CString;
 use std::mem;
 
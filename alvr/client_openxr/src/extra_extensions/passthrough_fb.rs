@@ -5,15 +5,32 @@ use openxr::{
 };
 use std::ptr;
 
+pub enum PassthroughColorMapFB<'a> {
+    None,
+    BrightnessContrastSaturation {
+        brightness: f32,
+        contrast: f32,
+        saturation: f32,
+    },
+    // RGB8 data with resolution^3 entries, red changes fastest, then green, then blue
+    ColorLut {
+        data: &'a [u8],
+        resolution: u32,
+        weight: f32,
+    },
+}
+
 pub struct PassthroughFB {
     handle: sys::PassthroughFB,
     layer_handle: sys::PassthroughLayerFB,
     layer: sys::CompositionLayerPassthroughFB,
+    color_lut: Option<sys::PassthroughColorLutMETA>,
     ext_fns: raw::PassthroughFB,
+    color_lut_ext_fns: Option<raw::PassthroughColorLutMETA>,
 }
 
 impl PassthroughFB {
-    pub fn new(session: &xr::Session<xr::OpenGlEs>, platform: Platform) -> xr::Result<Self> {
+    pub fn new<G>(session: &xr::Session<G>, platform: Platform) -> xr::Result<Self> {
         let ext_fns = session
             .instance()
             .exts()
@@ -67,10 +84,130 @@ impl PassthroughFB {
             handle,
             layer_handle,
             layer,
+            color_lut: None,
             ext_fns,
+            color_lut_ext_fns: session.instance().exts().meta_passthrough_color_lut,
         })
     }
 
+    pub fn supports_color_lut(&self) -> bool {
+        self.color_lut_ext_fns.is_some()
+    }
+
+    // edge_color with alpha 0 disables edge rendering
+    pub fn set_style(
+        &mut self,
+        texture_opacity: f32,
+        edge_color: sys::Color4f,
+        color_map: PassthroughColorMapFB,
+    ) -> xr::Result<()> {
+        let mut brightness_contrast_saturation = sys::PassthroughBrightnessContrastSaturationFB {
+            ty: sys::PassthroughBrightnessContrastSaturationFB::TYPE,
+            next: ptr::null(),
+            brightness: 0.0,
+            contrast: 1.0,
+            saturation: 1.0,
+        };
+        let mut color_map_lut = sys::PassthroughColorMapLutMETA {
+            ty: sys::PassthroughColorMapLutMETA::TYPE,
+            next: ptr::null(),
+            color_lut: sys::PassthroughColorLutMETA::NULL,
+            weight: 1.0,
+        };
+
+        let mut new_color_lut = None;
+        let next = match color_map {
+            PassthroughColorMapFB::None => ptr::null(),
+            PassthroughColorMapFB::BrightnessContrastSaturation {
+                brightness,
+                contrast,
+                saturation,
+            } => {
+                brightness_contrast_saturation.brightness = brightness;
+                brightness_contrast_saturation.contrast = contrast;
+                brightness_contrast_saturation.saturation = saturation;
+
+                (&raw const brightness_contrast_saturation).cast()
+            }
+            PassthroughColorMapFB::ColorLut {
+                data,
+                resolution,
+                weight,
+            } => {
+                let color_lut = self.create_color_lut(data, resolution)?;
+                new_color_lut = Some(color_lut);
+
+                color_map_lut.color_lut = color_lut;
+                color_map_lut.weight = weight;
+
+                (&raw const color_map_lut).cast()
+            }
+        };
+
+        let style = sys::PassthroughStyleFB {
+            ty: sys::PassthroughStyleFB::TYPE,
+            next,
+            texture_opacity_factor: texture_opacity,
+            edge_color,
+        };
+        let res = unsafe {
+            super::xr_res((self.ext_fns.passthrough_layer_set_style)(
+                self.layer_handle,
+                &style,
+            ))
+        };
+
+        // The previous LUT can only be destroyed once it is not referenced by the layer anymore
+        let (retired_color_lut, kept_color_lut) = if res.is_ok() {
+            (self.color_lut.take(), new_color_lut)
+        } else {
+            (new_color_lut, self.color_lut.take())
+        };
+        self.color_lut = kept_color_lut;
+        if let Some(color_lut) = retired_color_lut {
+            self.destroy_color_lut(color_lut);
+        }
+
+        res
+    }
+
+    fn create_color_lut(
+        &self,
+        data: &[u8],
+        resolution: u32,
+    ) -> xr::Result<sys::PassthroughColorLutMETA> {
+        let ext_fns = self
+            .color_lut_ext_fns
+            .ok_or(sys::Result::ERROR_EXTENSION_NOT_PRESENT)?;
+
+        let mut color_lut = sys::PassthroughColorLutMETA::NULL;
+        let info = sys::PassthroughColorLutCreateInfoMETA {
+            ty: sys::PassthroughColorLutCreateInfoMETA::TYPE,
+            next: ptr::null(),
+            channels: sys::PassthroughColorLutChannelsMETA::RGB,
+            resolution,
+            data: sys::PassthroughColorLutDataMETA {
+                buffer_size: data.len() as u32,
+                buffer: data.as_ptr(),
+            },
+        };
+        unsafe {
+            super::xr_res((ext_fns.create_passthrough_color_lut)(
+                self.handle,
+                &info,
+                &mut color_lut,
+            ))?
+        };
+
+        Ok(color_lut)
+    }
+
+    fn destroy_color_lut(&self, color_lut: sys::PassthroughColorLutMETA) {
+        if let Some(ext_fns) = &self.color_lut_ext_fns {
+            unsafe { (ext_fns.destroy_passthrough_color_lut)(color_lut) };
+        }
+    }
+
     // return reference to make sure the passthrough handle is not dropped while the layer is in use
     pub fn layer(&self) -> &sys::CompositionLayerPassthroughFB {
         &self.layer
@@ -79,6 +216,10 @@ impl PassthroughFB {
 
 impl Drop for PassthroughFB {
     fn drop(&mut self) {
+        if let Some(color_lut) = self.color_lut.take() {
+            self.destroy_color_lut(color_lut);
+        }
+
         unsafe {
             (self.ext_fns.destroy_passthrough_layer)(self.layer_handle);
             (self.ext_fns.destroy_passthrough)(self.handle);
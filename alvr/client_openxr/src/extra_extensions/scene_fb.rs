@@ -0,0 +1,315 @@
+use alvr_common::{
+    Pose,
+    glam::{Vec2, Vec3},
+    warn,
+};
+use openxr::{self as xr, raw, sys};
+use std::{mem, ptr};
+
+pub const SCENE_PERMISSION: &str = "com.oculus.permission.USE_SCENE";
+
+enum State {
+    QueryingRoom(sys::AsyncRequestIdFB),
+    QueryingFloor(sys::AsyncRequestIdFB),
+    Ready {
+        floor_space: sys::Space,
+        vertices: Vec<Vec2>,
+    },
+    Unavailable,
+}
+
+// OpenXR doesn't expose the guardian polygon. The floor of the room captured by the space setup is
+// used instead, its boundary follows the walls. The room is queried asynchronously: the results
+// arrive as events, which must be forwarded to handle_event().
+pub struct RoomBoundaryFB {
+    session: xr::Session<xr::AnyGraphics>,
+    query_fns: raw::SpatialEntityQueryFB,
+    scene_fns: raw::SceneFB,
+    state: State,
+}
+
+impl RoomBoundaryFB {
+    pub fn new<G>(session: &xr::Session<G>) -> xr::Result<Self> {
+        let exts = session.instance().exts();
+        let (Some(query_fns), Some(scene_fns), Some(_)) = (
+            exts.fb_spatial_entity_query,
+            exts.fb_scene,
+            exts.fb_spatial_entity,
+        ) else {
+            return Err(sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+        };
+
+        let mut this = Self {
+            session: session.clone().into_any_graphics(),
+            query_fns,
+            scene_fns,
+            state: State::Unavailable,
+        };
+
+        let filter = sys::SpaceComponentFilterInfoFB {
+            ty: sys::SpaceComponentFilterInfoFB::TYPE,
+            next: ptr::null(),
+            component_type: sys::SpaceComponentTypeFB::ROOM_LAYOUT,
+        };
+        let request_id = this.query(ptr::from_ref(&filter).cast())?;
+        this.state = State::QueryingRoom(request_id);
+
+        Ok(this)
+    }
+
+    fn query(
+        &self,
+        filter: *const sys::SpaceFilterInfoBaseHeaderFB,
+    ) -> xr::Result<sys::AsyncRequestIdFB> {
+        let info = sys::SpaceQueryInfoFB {
+            ty: sys::SpaceQueryInfoFB::TYPE,
+            next: ptr::null(),
+            query_action: sys::SpaceQueryActionFB::LOAD,
+            max_result_count: 1,
+            timeout: sys::Duration::INFINITE,
+            filter,
+            exclude_filter: ptr::null(),
+        };
+
+        let mut request_id = sys::AsyncRequestIdFB::from_raw(0);
+        unsafe {
+            super::xr_res((self.query_fns.query_spaces)(
+                self.session.as_raw(),
+                ptr::from_ref(&info).cast(),
+                &mut request_id,
+            ))?;
+        }
+
+        Ok(request_id)
+    }
+
+    fn retrieve_results(
+        &self,
+        request_id: sys::AsyncRequestIdFB,
+    ) -> xr::Result<Vec<sys::SpaceQueryResultFB>> {
+        let mut results = sys::SpaceQueryResultsFB {
+            ty: sys::SpaceQueryResultsFB::TYPE,
+            next: ptr::null_mut(),
+            result_capacity_input: 0,
+            result_count_output: 0,
+            results: ptr::null_mut(),
+        };
+        unsafe {
+            super::xr_res((self.query_fns.retrieve_space_query_results)(
+                self.session.as_raw(),
+                request_id,
+                &mut results,
+            ))?;
+        }
+
+        let mut buffer = Vec::with_capacity(results.result_count_output as usize);
+        results.result_capacity_input = results.result_count_output;
+        results.results = buffer.as_mut_ptr();
+        unsafe {
+            super::xr_res((self.query_fns.retrieve_space_query_results)(
+                self.session.as_raw(),
+                request_id,
+                &mut results,
+            ))?;
+            buffer.set_len(results.result_count_output as usize);
+        }
+
+        Ok(buffer)
+    }
+
+    fn destroy_space(&self, space: sys::Space) {
+        unsafe { (self.session.instance().fp().destroy_space)(space) };
+    }
+
+    fn floor_uuid(&self, room_space: sys::Space) -> xr::Result<sys::UuidEXT> {
+        let mut layout = sys::RoomLayoutFB {
+            ty: sys::RoomLayoutFB::TYPE,
+            next: ptr::null(),
+            floor_uuid: sys::UuidEXT { data: [0; 16] },
+            ceiling_uuid: sys::UuidEXT { data: [0; 16] },
+            wall_uuid_capacity_input: 0,
+            wall_uuid_count_output: 0,
+            wall_uuids: ptr::null_mut(),
+        };
+        unsafe {
+            super::xr_res((self.scene_fns.get_space_room_layout)(
+                self.session.as_raw(),
+                room_space,
+                &mut layout,
+            ))?;
+        }
+
+        Ok(layout.floor_uuid)
+    }
+
+    fn boundary_vertices(&self, floor_space: sys::Space) -> xr::Result<Vec<Vec2>> {
+        let mut boundary = sys::Boundary2DFB {
+            ty: sys::Boundary2DFB::TYPE,
+            next: ptr::null(),
+            vertex_capacity_input: 0,
+            vertex_count_output: 0,
+            vertices: ptr::null_mut(),
+        };
+        unsafe {
+            super::xr_res((self.scene_fns.get_space_boundary2_d)(
+                self.session.as_raw(),
+                floor_space,
+                &mut boundary,
+            ))?;
+        }
+
+        let mut vertices = Vec::<sys::Vector2f>::with_capacity(boundary.vertex_count_output as _);
+        boundary.vertex_capacity_input = boundary.vertex_count_output;
+        boundary.vertices = vertices.as_mut_ptr();
+        unsafe {
+            super::xr_res((self.scene_fns.get_space_boundary2_d)(
+                self.session.as_raw(),
+                floor_space,
+                &mut boundary,
+            ))?;
+            vertices.set_len(boundary.vertex_count_output as usize);
+        }
+
+        Ok(vertices.iter().map(|v| Vec2::new(v.x, v.y)).collect())
+    }
+
+    fn on_results_available(&mut self, request_id: sys::AsyncRequestIdFB) -> xr::Result<bool> {
+        match self.state {
+            State::QueryingRoom(id) if id == request_id => {
+                let results = self.retrieve_results(request_id)?;
+                let Some(room) = results.first() else {
+                    return Ok(false);
+                };
+                let floor_uuid = self.floor_uuid(room.space);
+                for result in &results {
+                    self.destroy_space(result.space);
+                }
+
+                let mut floor_uuid = floor_uuid?;
+                let filter = sys::SpaceUuidFilterInfoFB {
+                    ty: sys::SpaceUuidFilterInfoFB::TYPE,
+                    next: ptr::null(),
+                    uuid_count: 1,
+                    uuids: &mut floor_uuid,
+                };
+                self.state = State::QueryingFloor(self.query(ptr::from_ref(&filter).cast())?);
+
+                Ok(false)
+            }
+            State::QueryingFloor(id) if id == request_id => {
+                let results = self.retrieve_results(request_id)?;
+                let Some(floor) = results.first() else {
+                    return Ok(false);
+                };
+                for result in &results[1..] {
+                    self.destroy_space(result.space);
+                }
+
+                match self.boundary_vertices(floor.space) {
+                    Ok(vertices) => {
+                        self.state = State::Ready {
+                            floor_space: floor.space,
+                            vertices,
+                        };
+
+                        Ok(true)
+                    }
+                    Err(e) => {
+                        self.destroy_space(floor.space);
+
+                        Err(e)
+                    }
+                }
+            }
+            _ => Ok(false),
+        }
+    }
+
+    // Returns true when the room boundary becomes available
+    pub fn handle_event(&mut self, event: &sys::EventDataBuffer) -> bool {
+        let pending_request = match self.state {
+            State::QueryingRoom(id) | State::QueryingFloor(id) => id,
+            _ => return false,
+        };
+
+        if event.ty == sys::EventDataSpaceQueryResultsAvailableFB::TYPE {
+            let event = unsafe {
+                &*ptr::from_ref(event).cast::<sys::EventDataSpaceQueryResultsAvailableFB>()
+            };
+
+            match self.on_results_available(event.request_id) {
+                Ok(ready) => ready,
+                Err(e) => {
+                    warn!("Failed to get the room boundary: {e}");
+                    self.state = State::Unavailable;
+
+                    false
+                }
+            }
+        } else if event.ty == sys::EventDataSpaceQueryCompleteFB::TYPE {
+            let event =
+                unsafe { &*ptr::from_ref(event).cast::<sys::EventDataSpaceQueryCompleteFB>() };
+
+            // The results event is sent before the completion, if any space was found
+            if event.request_id == pending_request {
+                if event.result.into_raw() < 0 {
+                    warn!("Room query failed: {:?}", event.result);
+                }
+                self.state = State::Unavailable;
+            }
+
+            false
+        } else {
+            false
+        }
+    }
+
+    // Vertices of the room boundary on the floor, in the XZ plane of the base space
+    pub fn perimeter(&self, base_space: &xr::Space, time: xr::Time) -> Option<Vec<Vec2>> {
+        let State::Ready {
+            floor_space,
+            vertices,
+        } = &self.state
+        else {
+            return None;
+        };
+
+        let mut location = unsafe { mem::zeroed::<sys::SpaceLocation>() };
+        location.ty = sys::SpaceLocation::TYPE;
+        unsafe {
+            super::xr_res((self.session.instance().fp().locate_space)(
+                *floor_space,
+                base_space.as_raw(),
+                time,
+                &mut location,
+            ))
+            .ok()?;
+        }
+        if !location.location_flags.contains(
+            xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID,
+        ) {
+            return None;
+        }
+        let floor_pose = Pose::from(location.pose);
+
+        // The boundary is in the XY plane of the floor, its Z axis points up
+        Some(
+            vertices
+                .iter()
+                .map(|vertex| {
+                    let point = floor_pose.transform_point(Vec3::new(vertex.x, vertex.y, 0.0));
+
+                    Vec2::new(point.x, point.z)
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Drop for RoomBoundaryFB {
+    fn drop(&mut self) {
+        if let State::Ready { floor_space, .. } = self.state {
+            self.destroy_space(floor_space);
+        }
+    }
+}
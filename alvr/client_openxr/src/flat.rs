@@ -0,0 +1,181 @@
+// Flat display client for Android TV. It shows the left view of the stream of the headset and sends
+// no tracking, so it doesn't need an OpenXR runtime.
+
+use crate::stream::ParsedStreamConfig;
+use alvr_client_core::{
+    ClientCapabilities, ClientCoreContext, ClientCoreEvent,
+    video_decoder::{self, VideoDecoderConfig, VideoDecoderSource},
+};
+use alvr_common::{anyhow::Result, glam::UVec2, info};
+use alvr_graphics::{
+    FlatDisplaySurface, GraphicsContext, SDR_FORMAT_GL, StreamRenderer, StreamViewParams,
+};
+use alvr_session::ColorRange;
+use android_activity::{AndroidApp, MainEvent, PollEvent};
+use std::{rc::Rc, sync::Arc, thread, time::Duration};
+
+const IDLE_POLL_TIMEOUT: Duration = Duration::from_millis(100);
+const FRAME_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+// Resolution hint for the server, the stream uses the resolution negotiated with the headset
+const DISPLAY_RESOLUTION: UVec2 = UVec2::new(1920, 1080);
+
+struct FlatRenderer {
+    // Dropped before the surface, which owns the textures
+    renderer: StreamRenderer,
+    surface: FlatDisplaySurface,
+}
+
+pub fn entry_point(app: AndroidApp) {
+    alvr_client_core::init_logging();
+
+    info!("Starting the flat display client");
+
+    let capabilities = ClientCapabilities {
+        platform: alvr_system_info::platform(None, None),
+        default_view_resolution: DISPLAY_RESOLUTION,
+        max_view_resolution: DISPLAY_RESOLUTION,
+        refresh_rates: vec![60.0],
+        foveated_encoding: true,
+        encoder_high_profile: true,
+        encoder_10_bits: false,
+        encoder_av1: false,
+        prefer_10bit: false,
+        preferred_encoding_gamma: 1.0,
+        prefer_hdr: false,
+        depth_layers: false,
+        space_warp: false,
+        decoder_chroma_422: false,
+        decoder_chroma_444: false,
+        radial_foveated_encoding: true,
+        per_eye_foveated_encoding: true,
+        flat_display: true,
+    };
+    let core_context = Arc::new(ClientCoreContext::new(capabilities));
+    let graphics_context = Rc::new(GraphicsContext::new_gl());
+
+    let mut window = None;
+    let mut stream_config = None::<ParsedStreamConfig>;
+    let mut decoder = None::<(VideoDecoderConfig, VideoDecoderSource)>;
+    let mut renderer = None::<FlatRenderer>;
+
+    let mut should_quit = false;
+    while !should_quit {
+        let timeout = if renderer.is_some() {
+            Duration::ZERO
+        } else {
+            IDLE_POLL_TIMEOUT
+        };
+        app.poll_events(Some(timeout), |event| match event {
+            PollEvent::Main(MainEvent::Resume { .. }) => core_context.resume(),
+            PollEvent::Main(MainEvent::Pause) => core_context.pause(),
+            PollEvent::Main(MainEvent::InitWindow { .. }) => window = app.native_window(),
+            PollEvent::Main(MainEvent::TerminateWindow { .. }) => {
+                renderer = None;
+                window = None;
+            }
+            PollEvent::Main(MainEvent::Destroy) => should_quit = true,
+            _ => (),
+        });
+
+        while let Some(event) = core_context.poll_event() {
+            match event {
+                ClientCoreEvent::StreamingStarted(config) => {
+                    stream_config = Some(ParsedStreamConfig::new(&config));
+                    renderer = None;
+                }
+                ClientCoreEvent::StreamingStopped => {
+                    stream_config = None;
+                    decoder = None;
+                    renderer = None;
+                }
+                ClientCoreEvent::DecoderConfig { codec, config_nal } => {
+                    if let Some(config) = &stream_config {
+                        let new_config = VideoDecoderConfig {
+                            codec,
+                            chroma_subsampling: config.chroma_subsampling,
+                            force_software_decoder: config.force_software_decoder,
+                            max_buffering_frames: config.max_buffering_frames,
+                            buffering_history_weight: config.buffering_history_weight,
+                            max_queued_frames: config.max_queued_frames,
+                            options: config.decoder_options.clone(),
+                            config_buffer: config_nal,
+                        };
+
+                        if decoder
+                            .as_ref()
+                            .is_none_or(|(config, _)| *config != new_config)
+                        {
+                            let (sink, source) =
+                                video_decoder::create_decoder(new_config.clone(), {
+                                    let ctx = Arc::clone(&core_context);
+                                    move |maybe_timestamp: Result<Duration>| match maybe_timestamp {
+                                        Ok(timestamp) => ctx.report_frame_decoded(timestamp),
+                                        Err(e) => ctx.report_fatal_decoder_error(&e.to_string()),
+                                    }
+                                });
+                            decoder = Some((new_config, source));
+
+                            core_context.set_decoder(sink);
+                        }
+                    }
+                }
+                // The rest is for the headsets
+                _ => (),
+            }
+        }
+
+        if renderer.is_none()
+            && let (Some(config), Some(window)) = (&stream_config, &window)
+        {
+            let surface = FlatDisplaySurface::new(
+                Rc::clone(&graphics_context),
+                window.ptr().as_ptr().cast(),
+                config.view_resolution,
+            );
+            renderer = Some(FlatRenderer {
+                renderer: StreamRenderer::new(
+                    Rc::clone(&graphics_context),
+                    config.view_resolution,
+                    config.view_resolution,
+                    surface.view_textures(),
+                    SDR_FORMAT_GL,
+                    config.foveated_encoding_config.clone(),
+                    false,
+                    config.transfer_function,
+                    config.color_range == ColorRange::Full && !config.enable_hdr,
+                    config.encoding_gamma,
+                    None,
+                    false,
+                ),
+                surface,
+            });
+        }
+
+        let (Some(flat_renderer), Some((_, source))) = (&renderer, &mut decoder) else {
+            continue;
+        };
+
+        let Some((timestamp, buffer_ptr)) = source
+            .get_frame()
+            .filter(|(timestamp, _)| core_context.is_frame_displayable(*timestamp))
+        else {
+            thread::sleep(FRAME_POLL_INTERVAL);
+            continue;
+        };
+
+        // The view is shown as is, without reprojection
+        let view_params = core_context.report_compositor_start(timestamp);
+        flat_renderer.renderer.render(
+            buffer_ptr,
+            [0, 1].map(|idx| StreamViewParams {
+                swapchain_index: 0,
+                input_view_params: view_params[idx],
+                output_view_params: view_params[idx],
+            }),
+            None,
+            false,
+        );
+        flat_renderer.surface.present();
+    }
+}
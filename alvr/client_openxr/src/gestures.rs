@@ -0,0 +1,162 @@
+use alvr_common::Pose;
+use alvr_packets::{ButtonEntry, ButtonValue};
+use alvr_session::{GestureAction, GestureConfig, GestureHand, GestureTrigger, HandPoseGesture};
+use std::time::{Duration, Instant};
+
+// Indices of the OpenXR hand joints
+const PALM_JOINT: usize = 0;
+const THUMB_TIP_JOINT: usize = 5;
+const INDEX_TIP_JOINT: usize = 10;
+const FINGER_TIP_JOINTS: [usize; 4] = [10, 15, 20, 25];
+const PINCH_DISTANCE: f32 = 0.015; // m
+// Curled fingers reach about half of the distance of the extended ones
+const FIST_DISTANCE: f32 = 0.06; // m
+// Analog inputs like the grip of the Quest controllers. The gap avoids counting the noise around
+// the threshold as taps
+const SCALAR_PRESS_THRESHOLD: f32 = 0.6;
+const SCALAR_RELEASE_THRESHOLD: f32 = 0.4;
+
+fn pose_detected(joints: &[Pose; 26], pose: HandPoseGesture) -> bool {
+    let distance = |a: usize, b: usize| joints[a].position.distance(joints[b].position);
+
+    match pose {
+        HandPoseGesture::Pinch => distance(THUMB_TIP_JOINT, INDEX_TIP_JOINT) < PINCH_DISTANCE,
+        HandPoseGesture::Fist => FINGER_TIP_JOINTS
+            .iter()
+            .all(|tip| distance(*tip, PALM_JOINT) < FIST_DISTANCE),
+    }
+}
+
+enum Detector {
+    DoubleTap {
+        button_id: u64,
+        max_interval: Duration,
+        pressed: bool,
+        // Time of the press that can start a double tap
+        first_press: Option<Instant>,
+    },
+    HandPose {
+        hand_idx: usize,
+        pose: HandPoseGesture,
+        hold_duration: Duration,
+        held_since: Option<Instant>,
+        triggered: bool,
+    },
+}
+
+struct Gesture {
+    detector: Detector,
+    action: GestureAction,
+}
+
+// Recognizes the gestures of the settings from the buttons and the hand skeletons read by the
+// input thread. A double tap triggers on the second press, and a third press starts a new double
+// tap. A hand pose triggers once held for its duration, and must be released before it can
+// trigger again. The buttons are still sent to the server.
+pub struct GestureRecognizer {
+    gestures: Vec<Gesture>,
+}
+
+impl GestureRecognizer {
+    pub fn new(configs: &[GestureConfig]) -> Self {
+        let gestures = configs
+            .iter()
+            .filter(|config| config.enabled)
+            .map(|config| Gesture {
+                detector: match &config.trigger {
+                    GestureTrigger::DoubleTap {
+                        button,
+                        max_interval_ms,
+                    } => Detector::DoubleTap {
+                        button_id: alvr_common::hash_string(button),
+                        max_interval: Duration::from_millis(*max_interval_ms),
+                        pressed: false,
+                        first_press: None,
+                    },
+                    GestureTrigger::HandPose {
+                        hand,
+                        pose,
+                        hold_duration_ms,
+                    } => Detector::HandPose {
+                        hand_idx: match hand {
+                            GestureHand::Left => 0,
+                            GestureHand::Right => 1,
+                        },
+                        pose: *pose,
+                        hold_duration: Duration::from_millis(*hold_duration_ms),
+                        held_since: None,
+                        triggered: false,
+                    },
+                },
+                action: config.action,
+            })
+            .collect();
+
+        Self { gestures }
+    }
+
+    // Returns the actions of the gestures completed since the last update
+    pub fn update(
+        &mut self,
+        button_entries: &[ButtonEntry],
+        hand_skeletons: [Option<&[Pose; 26]>; 2],
+        now: Instant,
+    ) -> Vec<GestureAction> {
+        let mut actions = vec![];
+        for gesture in &mut self.gestures {
+            match &mut gesture.detector {
+                Detector::DoubleTap {
+                    button_id,
+                    max_interval,
+                    pressed,
+                    first_press,
+                } => {
+                    for entry in button_entries {
+                        if entry.path_id != *button_id {
+                            continue;
+                        }
+
+                        let was_pressed = *pressed;
+                        *pressed = match entry.value {
+                            ButtonValue::Binary(value) => value,
+                            ButtonValue::Scalar(value) if was_pressed => {
+                                value > SCALAR_RELEASE_THRESHOLD
+                            }
+                            ButtonValue::Scalar(value) => value > SCALAR_PRESS_THRESHOLD,
+                        };
+
+                        if *pressed && !was_pressed {
+                            if first_press.is_some_and(|time| now - time <= *max_interval) {
+                                *first_press = None;
+                                actions.push(gesture.action);
+                            } else {
+                                *first_press = Some(now);
+                            }
+                        }
+                    }
+                }
+                Detector::HandPose {
+                    hand_idx,
+                    pose,
+                    hold_duration,
+                    held_since,
+                    triggered,
+                } => {
+                    if hand_skeletons[*hand_idx].is_some_and(|joints| pose_detected(joints, *pose))
+                    {
+                        let since = *held_since.get_or_insert(now);
+                        if !*triggered && now - since >= *hold_duration {
+                            *triggered = true;
+                            actions.push(gesture.action);
+                        }
+                    } else {
+                        *held_since = None;
+                        *triggered = false;
+                    }
+                }
+            }
+        }
+
+        actions
+    }
+}
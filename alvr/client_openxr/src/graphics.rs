@@ -1,42 +1,208 @@
 use alvr_common::glam::UVec2;
-use alvr_graphics::GraphicsContext;
-use alvr_session::ClientsidePostProcessingConfig;
+use alvr_graphics::{GraphicsContext, SwapchainImage};
+use alvr_session::{ClientSwapchainFormat, ClientsidePostProcessingConfig};
 use openxr as xr;
 use std::ptr;
 
-#[allow(unused)]
-pub fn session_create_info(ctx: &GraphicsContext) -> xr::opengles::SessionCreateInfo {
-    #[cfg(target_os = "android")]
-    {
-        xr::opengles::SessionCreateInfo::Android {
-            display: ctx.egl_display.as_ptr(),
-            config: ctx.egl_config.as_ptr(),
-            context: ctx.egl_context.as_ptr(),
+#[cfg(feature = "vulkan")]
+use alvr_common::anyhow::{Result, anyhow};
+#[cfg(feature = "vulkan")]
+use alvr_graphics::{
+    VulkanLoader,
+    ash::vk::{self, Handle},
+};
+
+// Graphics APIs the client can create an OpenXR session with
+pub trait ClientGraphics: xr::Graphics<Format = u32, SwapchainImage: SwapchainImage> {
+    const SDR_FORMAT: u32;
+
+    fn session_create_info(ctx: &GraphicsContext) -> Self::SessionCreateInfo;
+
+    fn choose_swapchain_format(
+        supported_formats: &[u32],
+        enable_hdr: bool,
+        preferred_format: ClientSwapchainFormat,
+    ) -> u32;
+
+    // None if the streamed depth cannot be uploaded with this graphics API
+    const DEPTH_FORMAT: Option<u32>;
+
+    fn upload_depth(
+        ctx: &GraphicsContext,
+        image: Self::SwapchainImage,
+        resolution: UVec2,
+        data: &[u16],
+    );
+
+    // None if space warp cannot be used with this graphics API
+    const MOTION_VECTOR_FORMAT: Option<u32>;
+
+    // data has 4 floats per pixel, the motion vector in NDC then two unused components
+    fn upload_motion_vectors(
+        ctx: &GraphicsContext,
+        image: Self::SwapchainImage,
+        resolution: UVec2,
+        data: &[f32],
+    );
+}
+
+impl ClientGraphics for xr::OpenGlEs {
+    const SDR_FORMAT: u32 = alvr_graphics::SDR_FORMAT_GL;
+
+    #[allow(unused)]
+    fn session_create_info(ctx: &GraphicsContext) -> xr::opengles::SessionCreateInfo {
+        #[cfg(target_os = "android")]
+        {
+            let gles = ctx.gles().unwrap();
+
+            xr::opengles::SessionCreateInfo::Android {
+                display: gles.egl_display.as_ptr(),
+                config: gles.egl_config.as_ptr(),
+                context: gles.egl_context.as_ptr(),
+            }
+        }
+        #[cfg(not(target_os = "android"))]
+        unimplemented!()
+    }
+
+    fn choose_swapchain_format(
+        supported_formats: &[u32],
+        enable_hdr: bool,
+        preferred_format: ClientSwapchainFormat,
+    ) -> u32 {
+        alvr_graphics::choose_swapchain_format(supported_formats, enable_hdr, preferred_format)
+    }
+
+    const DEPTH_FORMAT: Option<u32> = Some(alvr_graphics::DEPTH_FORMAT_GL);
+
+    fn upload_depth(ctx: &GraphicsContext, image: u32, resolution: UVec2, data: &[u16]) {
+        ctx.upload_depth_gles(image, resolution, data);
+    }
+
+    const MOTION_VECTOR_FORMAT: Option<u32> = Some(alvr_graphics::MOTION_VECTOR_FORMAT_GL);
+
+    fn upload_motion_vectors(ctx: &GraphicsContext, image: u32, resolution: UVec2, data: &[f32]) {
+        ctx.upload_motion_vectors_gles(image, resolution, data);
+    }
+}
+
+#[cfg(feature = "vulkan")]
+impl ClientGraphics for xr::Vulkan {
+    const SDR_FORMAT: u32 = alvr_graphics::SDR_FORMAT_VK;
+
+    fn session_create_info(ctx: &GraphicsContext) -> xr::vulkan::SessionCreateInfo {
+        let vk = ctx.vulkan().unwrap();
+
+        xr::vulkan::SessionCreateInfo {
+            instance: vk.instance.handle().as_raw() as _,
+            physical_device: vk.physical_device.as_raw() as _,
+            device: vk.device.handle().as_raw() as _,
+            queue_family_index: vk.queue_family_index,
+            queue_index: 0,
+        }
+    }
+
+    fn choose_swapchain_format(
+        supported_formats: &[u32],
+        enable_hdr: bool,
+        preferred_format: ClientSwapchainFormat,
+    ) -> u32 {
+        alvr_graphics::choose_vk_swapchain_format(supported_formats, enable_hdr, preferred_format)
+    }
+
+    const DEPTH_FORMAT: Option<u32> = None;
+
+    fn upload_depth(_: &GraphicsContext, _: u64, _: UVec2, _: &[u16]) {
+        unreachable!("depth is not supported with Vulkan")
+    }
+
+    const MOTION_VECTOR_FORMAT: Option<u32> = None;
+
+    fn upload_motion_vectors(_: &GraphicsContext, _: u64, _: UVec2, _: &[f32]) {
+        unreachable!("space warp is not supported with Vulkan")
+    }
+}
+
+// Lets the OpenXR runtime create the Vulkan instance and device used by wgpu
+#[cfg(feature = "vulkan")]
+pub struct XrVulkanLoader<'a> {
+    pub instance: &'a xr::Instance,
+    pub system: xr::SystemId,
+}
+
+#[cfg(feature = "vulkan")]
+impl VulkanLoader for XrVulkanLoader<'_> {
+    fn create_instance(
+        &self,
+        get_instance_proc_addr: vk::PFN_vkGetInstanceProcAddr,
+        create_info: &vk::InstanceCreateInfo,
+    ) -> Result<vk::Instance> {
+        let instance = unsafe {
+            self.instance.create_vulkan_instance(
+                self.system,
+                #[allow(clippy::missing_transmute_annotations)]
+                std::mem::transmute(get_instance_proc_addr),
+                ptr::from_ref(create_info).cast(),
+            )
+        }
+        .map_err(|e| anyhow!("{e}"))?
+        .map_err(|e| anyhow!("{:?}", vk::Result::from_raw(e)))?;
+
+        Ok(vk::Instance::from_raw(instance as _))
+    }
+
+    fn physical_device(&self, instance: vk::Instance) -> Result<vk::PhysicalDevice> {
+        let physical_device = unsafe {
+            self.instance
+                .vulkan_graphics_device(self.system, instance.as_raw() as _)
         }
+        .map_err(|e| anyhow!("{e}"))?;
+
+        Ok(vk::PhysicalDevice::from_raw(physical_device as _))
+    }
+
+    fn create_device(
+        &self,
+        get_instance_proc_addr: vk::PFN_vkGetInstanceProcAddr,
+        physical_device: vk::PhysicalDevice,
+        create_info: &vk::DeviceCreateInfo,
+    ) -> Result<vk::Device> {
+        let device = unsafe {
+            self.instance.create_vulkan_device(
+                self.system,
+                #[allow(clippy::missing_transmute_annotations)]
+                std::mem::transmute(get_instance_proc_addr),
+                physical_device.as_raw() as _,
+                ptr::from_ref(create_info).cast(),
+            )
+        }
+        .map_err(|e| anyhow!("{e}"))?
+        .map_err(|e| anyhow!("{:?}", vk::Result::from_raw(e)))?;
+
+        Ok(vk::Device::from_raw(device as _))
     }
-    #[cfg(not(target_os = "android"))]
-    unimplemented!()
 }
 
-pub fn swapchain_format(
+pub fn swapchain_format<G: ClientGraphics>(
     gfx_ctx: &GraphicsContext,
-    session: &xr::Session<xr::OpenGlEs>,
+    session: &xr::Session<G>,
     enable_hdr: bool,
+    preferred_format: ClientSwapchainFormat,
 ) -> u32 {
     gfx_ctx.make_current();
 
     let formats = session.enumerate_swapchain_formats().unwrap();
-    alvr_graphics::choose_swapchain_format(&formats, enable_hdr)
+    G::choose_swapchain_format(&formats, enable_hdr, preferred_format)
 }
 
 #[allow(unused_variables)]
-pub fn create_swapchain(
-    session: &xr::Session<xr::OpenGlEs>,
+pub fn create_swapchain<G: ClientGraphics>(
+    session: &xr::Session<G>,
     gfx_ctx: &GraphicsContext,
     resolution: UVec2,
     format: u32,
     foveation: Option<&xr::FoveationProfileFB>,
-) -> xr::Swapchain<xr::OpenGlEs> {
+) -> xr::Swapchain<G> {
     gfx_ctx.make_current();
 
     let swapchain_info = xr::SwapchainCreateInfo {
@@ -54,6 +220,71 @@ pub fn create_swapchain(
     session.create_swapchain(&swapchain_info).unwrap()
 }
 
+pub fn create_depth_swapchain<G: ClientGraphics>(
+    session: &xr::Session<G>,
+    gfx_ctx: &GraphicsContext,
+    resolution: UVec2,
+    format: u32,
+) -> xr::Swapchain<G> {
+    create_upload_swapchain(
+        session,
+        gfx_ctx,
+        resolution,
+        format,
+        xr::SwapchainUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+    )
+}
+
+pub fn create_motion_vector_swapchain<G: ClientGraphics>(
+    session: &xr::Session<G>,
+    gfx_ctx: &GraphicsContext,
+    resolution: UVec2,
+    format: u32,
+) -> xr::Swapchain<G> {
+    create_upload_swapchain(
+        session,
+        gfx_ctx,
+        resolution,
+        format,
+        xr::SwapchainUsageFlags::COLOR_ATTACHMENT,
+    )
+}
+
+// Swapchain whose images are written by the CPU
+fn create_upload_swapchain<G: ClientGraphics>(
+    session: &xr::Session<G>,
+    gfx_ctx: &GraphicsContext,
+    resolution: UVec2,
+    format: u32,
+    attachment_usage: xr::SwapchainUsageFlags,
+) -> xr::Swapchain<G> {
+    gfx_ctx.make_current();
+
+    let swapchain_info = xr::SwapchainCreateInfo {
+        create_flags: xr::SwapchainCreateFlags::EMPTY,
+        usage_flags: attachment_usage | xr::SwapchainUsageFlags::TRANSFER_DST,
+        format,
+        sample_count: 1,
+        width: resolution.x,
+        height: resolution.y,
+        face_count: 1,
+        array_size: 1,
+        mip_count: 1,
+    };
+
+    session.create_swapchain(&swapchain_info).unwrap()
+}
+
+pub struct ProjectionLayerDepthConfig<'a, G: xr::Graphics> {
+    pub swapchains: &'a [xr::Swapchain<G>; 2],
+    pub rect: xr::Rect2Di,
+    pub near_z: f32,
+    pub far_z: f32,
+    // Set to let the runtime synthesize frames from the depth (XR_FB_space_warp). Same rect as
+    // the depth
+    pub motion_vector_swapchains: Option<&'a [xr::Swapchain<G>; 2]>,
+}
+
 pub struct ProjectionLayerAlphaConfig {
     pub premultiplied: bool,
 }
@@ -61,19 +292,23 @@ pub struct ProjectionLayerAlphaConfig {
 // This is needed to work around lifetime limitations. Deref cannot be implemented because there are
 // nested references, and in a way or the other I would get `cannot return reference to temporary
 // value`
-pub struct ProjectionLayerBuilder<'a> {
+pub struct ProjectionLayerBuilder<'a, G: xr::Graphics> {
     reference_space: &'a xr::Space,
-    layers: [xr::CompositionLayerProjectionView<'a, xr::OpenGlEs>; 2],
+    layers: [xr::CompositionLayerProjectionView<'a, G>; 2],
     alpha: Option<ProjectionLayerAlphaConfig>,
     composition_layer_settings: Option<xr::sys::CompositionLayerSettingsFB>,
+    // Boxed so that the views can point to them
+    _depth_infos: Option<Box<[xr::sys::CompositionLayerDepthInfoKHR; 2]>>,
+    _space_warp_infos: Option<Box<[xr::sys::CompositionLayerSpaceWarpInfoFB; 2]>>,
 }
 
-impl<'a> ProjectionLayerBuilder<'a> {
+impl<'a, G: xr::Graphics> ProjectionLayerBuilder<'a, G> {
     pub fn new(
         reference_space: &'a xr::Space,
-        layers: [xr::CompositionLayerProjectionView<'a, xr::OpenGlEs>; 2],
+        layers: [xr::CompositionLayerProjectionView<'a, G>; 2],
         alpha: Option<ProjectionLayerAlphaConfig>,
         clientside_post_processing_config: Option<ClientsidePostProcessingConfig>,
+        depth: Option<ProjectionLayerDepthConfig<'a, G>>,
     ) -> Self {
         let composition_layer_settings = clientside_post_processing_config
             .map(|post_processing| {
@@ -87,15 +322,75 @@ impl<'a> ProjectionLayerBuilder<'a> {
                 next: std::ptr::null(),
                 layer_flags: flags,
             });
+        let space_warp_infos = depth.as_ref().and_then(|depth| {
+            let motion_vector_swapchains = depth.motion_vector_swapchains?;
+
+            Some(Box::new([0, 1].map(|idx| {
+                xr::sys::CompositionLayerSpaceWarpInfoFB {
+                    ty: xr::StructureType::COMPOSITION_LAYER_SPACE_WARP_INFO_FB,
+                    next: ptr::null(),
+                    layer_flags: xr::sys::CompositionLayerSpaceWarpInfoFlagsFB::EMPTY,
+                    motion_vector_sub_image: xr::SwapchainSubImage::new()
+                        .swapchain(&motion_vector_swapchains[idx])
+                        .image_rect(depth.rect)
+                        .into_raw(),
+                    // The reference space doesn't move between frames
+                    app_space_delta_pose: xr::Posef::IDENTITY,
+                    depth_sub_image: xr::SwapchainSubImage::new()
+                        .swapchain(&depth.swapchains[idx])
+                        .image_rect(depth.rect)
+                        .into_raw(),
+                    min_depth: 0.0,
+                    max_depth: 1.0,
+                    near_z: depth.near_z,
+                    far_z: depth.far_z,
+                }
+            })))
+        });
+        let depth_infos = depth.map(|depth| {
+            Box::new([0, 1].map(|idx| {
+                xr::sys::CompositionLayerDepthInfoKHR {
+                    ty: xr::StructureType::COMPOSITION_LAYER_DEPTH_INFO_KHR,
+                    next: space_warp_infos
+                        .as_ref()
+                        .map_or(ptr::null(), |infos| ptr::from_ref(&infos[idx]).cast()),
+                    sub_image: xr::SwapchainSubImage::new()
+                        .swapchain(&depth.swapchains[idx])
+                        .image_rect(depth.rect)
+                        .into_raw(),
+                    min_depth: 0.0,
+                    max_depth: 1.0,
+                    near_z: depth.near_z,
+                    far_z: depth.far_z,
+                }
+            }))
+        });
+
+        let layers = if let Some(depth_infos) = &depth_infos {
+            let [left, right] = layers;
+            [(left, &depth_infos[0]), (right, &depth_infos[1])].map(|(layer, depth_info)| unsafe {
+                xr::CompositionLayerProjectionView::from_raw(
+                    xr::sys::CompositionLayerProjectionView {
+                        next: ptr::from_ref(depth_info).cast(),
+                        ..layer.into_raw()
+                    },
+                )
+            })
+        } else {
+            layers
+        };
+
         Self {
             reference_space,
             layers,
             alpha,
             composition_layer_settings,
+            _depth_infos: depth_infos,
+            _space_warp_infos: space_warp_infos,
         }
     }
 
-    pub fn build(&self) -> xr::CompositionLayerProjection<'_, xr::OpenGlEs> {
+    pub fn build(&self) -> xr::CompositionLayerProjection<'_, G> {
         let mut flags = xr::CompositionLayerFlags::EMPTY;
 
         if let Some(alpha) = &self.alpha {
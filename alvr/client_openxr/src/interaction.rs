@@ -3,7 +3,8 @@ use crate::{
     extra_extensions::{
         self, BODY_JOINT_SET_FULL_BODY_META, BodyJointSetBD, BodyTrackerBD, BodyTrackerFB,
         EyeTrackerSocial, FULL_BODY_JOINT_COUNT_META, FaceTracker2FB, FaceTrackerPico,
-        FacialTrackerHTC, MotionTrackerBD, MultimodalMeta,
+        FacialTrackerHTC, HandInteractionActions, MarkerFilter, MarkerTrackingError,
+        MotionTrackerBD, MultimodalMeta, QRCodesSpatialContext,
     },
 };
 use alvr_common::{
@@ -11,12 +12,14 @@ use alvr_common::{
     *,
 };
 use alvr_graphics::HandData;
-use alvr_packets::{ButtonEntry, ButtonValue, FaceData, FaceExpressions, StreamConfig};
+use alvr_packets::{
+    ButtonEntry, ButtonValue, FaceData, FaceExpressions, StreamConfig, TrackingSpace,
+};
 use alvr_session::{BodyTrackingBDConfig, BodyTrackingSourcesConfig, FaceTrackingSourcesConfig};
 use openxr as xr;
 use std::{
     collections::{HashMap, HashSet},
-    time::Duration,
+    time::{Duration, Instant},
 };
 use xr::SpaceLocationFlags;
 
@@ -145,6 +148,7 @@ pub struct InteractionSourcesConfig {
     pub face_tracking: Option<FaceTrackingSourcesConfig>,
     pub body_tracking: Option<BodyTrackingSourcesConfig>,
     pub prefers_multimodal_input: bool,
+    pub marker_filter: Option<MarkerFilter>,
 }
 
 impl InteractionSourcesConfig {
@@ -168,27 +172,35 @@ impl InteractionSourcesConfig {
                 .multimodal_tracking
                 .as_option()
                 .is_some_and(|c| c.enabled),
+            marker_filter: config
+                .settings
+                .headset
+                .marker_origin
+                .as_option()
+                .map(|c| MarkerFilter::Codes(vec![c.marker_code.clone()])),
         }
     }
 }
 
 pub struct InteractionContext {
-    xr_session: xr::Session<xr::OpenGlEs>,
+    xr_session: xr::Session<xr::AnyGraphics>,
     xr_system: xr::SystemId,
     extra_extensions: Vec<String>,
     platform: Platform,
     pub action_set: xr::ActionSet,
     pub button_actions: HashMap<u64, ButtonAction>,
     pub hands_interaction: [HandInteraction; 2],
+    pub hand_interaction_actions: Option<HandInteractionActions>,
     multimodal_handle: Option<MultimodalMeta>,
     pub multimodal_hands_enabled: bool,
     pub face_sources: FaceSources,
     pub body_source: Option<BodyTracker>,
+    pub marker_source: Option<QRCodesSpatialContext>,
 }
 
 impl InteractionContext {
-    pub fn new(
-        xr_session: xr::Session<xr::OpenGlEs>,
+    pub fn new<G>(
+        xr_session: xr::Session<G>,
         extra_extensions: Vec<String>,
         xr_system: xr::SystemId,
         platform: Platform,
@@ -205,8 +217,18 @@ impl InteractionContext {
             xr::Binding::new(action, action.instance().string_to_path(path).unwrap())
         }
 
+        // The native profiles of the newer Quest controllers are used when available, so that the
+        // runtime does not emulate the Touch controllers
         let controllers_profile_path = match platform {
-            p if p.is_quest() => QUEST_CONTROLLER_PROFILE_PATH, // todo: create new controller profile for quest pro and 3
+            Platform::Quest3 | Platform::Quest3S
+                if xr_instance.exts().meta_touch_controller_plus.is_some() =>
+            {
+                QUEST_PLUS_CONTROLLER_PROFILE_PATH
+            }
+            Platform::QuestPro if xr_instance.exts().fb_touch_controller_pro.is_some() => {
+                QUEST_PRO_CONTROLLER_PROFILE_PATH
+            }
+            p if p.is_quest() => QUEST_CONTROLLER_PROFILE_PATH,
             Platform::PicoG3 => PICO_G3_CONTROLLER_PROFILE_PATH,
             Platform::PicoNeo3 => PICO_NEO3_CONTROLLER_PROFILE_PATH,
             Platform::Pico4Ultra => PICO4S_CONTROLLER_PROFILE_PATH,
@@ -308,6 +330,11 @@ impl InteractionContext {
             "/user/hand/right/output/haptic",
         ));
 
+        let hand_interaction_actions = check_ext_object(
+            "HandInteractionActions",
+            HandInteractionActions::new(xr_instance, &action_set, &extra_extensions),
+        );
+
         let multimodal_handle = check_ext_object(
             "MultimodalMeta",
             MultimodalMeta::new(xr_session.clone(), &extra_extensions, xr_system),
@@ -455,7 +482,7 @@ impl InteractionContext {
         xr_session.attach_action_sets(&[&action_set]).unwrap();
 
         Self {
-            xr_session,
+            xr_session: xr_session.into_any_graphics(),
             xr_system,
             extra_extensions,
             platform,
@@ -489,6 +516,7 @@ impl InteractionContext {
                     skeleton_tracker: right_hand_tracker,
                 },
             ],
+            hand_interaction_actions,
             multimodal_handle,
             multimodal_hands_enabled: false,
             face_sources: FaceSources {
@@ -497,6 +525,7 @@ impl InteractionContext {
                 face_expressions_tracker,
             },
             body_source: None,
+            marker_source: None,
         }
     }
 
@@ -668,11 +697,34 @@ impl InteractionContext {
                 }
             }
         }
+
+        // The marker context does not conflict with the other sources. It is reconfigured instead
+        // of recreated, so the markers already discovered are not lost
+        if let Some(filter) = &config.marker_filter {
+            if let Some(source) = &self.marker_source {
+                source.set_filter(filter.clone());
+            } else {
+                self.marker_source = match QRCodesSpatialContext::new(
+                    &self.xr_session,
+                    &self.extra_extensions,
+                    filter.clone(),
+                ) {
+                    Ok(source) => Some(source),
+                    Err(MarkerTrackingError::ExtensionNotPresent) => None,
+                    Err(e) => {
+                        warn!("Failed to create QRCodesSpatialContext: {e}");
+                        None
+                    }
+                };
+            }
+        } else {
+            self.marker_source = None;
+        }
     }
 }
 
-pub fn get_reference_space(
-    xr_session: &xr::Session<xr::OpenGlEs>,
+pub fn get_reference_space<G>(
+    xr_session: &xr::Session<G>,
     ty: xr::ReferenceSpaceType,
 ) -> xr::Space {
     xr_session
@@ -680,8 +732,34 @@ pub fn get_reference_space(
         .unwrap()
 }
 
-pub fn get_head_data(
-    xr_session: &xr::Session<xr::OpenGlEs>,
+// Local floor avoids relying on the guardian floor calibration. The local space is the last resort,
+// its origin is at the head height and must be offset by the server recentering.
+pub fn select_tracking_space<G>(
+    xr_session: &xr::Session<G>,
+    use_local_floor_space: bool,
+) -> TrackingSpace {
+    if use_local_floor_space && xr_session.instance().exts().ext_local_floor.is_some() {
+        return TrackingSpace::LocalFloor;
+    }
+
+    let available_spaces = xr_session.enumerate_reference_spaces().unwrap_or_default();
+    if available_spaces.contains(&xr::ReferenceSpaceType::STAGE) {
+        TrackingSpace::Stage
+    } else {
+        TrackingSpace::Local
+    }
+}
+
+pub fn tracking_space_type(space: TrackingSpace) -> xr::ReferenceSpaceType {
+    match space {
+        TrackingSpace::LocalFloor => xr::ReferenceSpaceType::LOCAL_FLOOR_EXT,
+        TrackingSpace::Stage => xr::ReferenceSpaceType::STAGE,
+        TrackingSpace::Local => xr::ReferenceSpaceType::LOCAL,
+    }
+}
+
+pub fn get_head_data<G>(
+    xr_session: &xr::Session<G>,
     platform: Platform,
     stage_reference_space: &xr::Space,
     view_reference_space: &xr::Space,
@@ -717,7 +795,7 @@ pub fn get_head_data(
     }
 
     let mut motion = DeviceMotion {
-        pose: crate::from_xr_pose(head_location.pose),
+        pose: Pose::from(head_location.pose),
         linear_velocity: if head_velocity
             .velocity_flags
             .contains(xr::SpaceVelocityFlags::LINEAR_VALID)
@@ -775,11 +853,11 @@ pub fn get_head_data(
     let view_params = if f32::abs(current_ipd_m - last_ipd_m) > IPD_CHANGE_EPS {
         Some([
             ViewParams {
-                pose: motion.pose.inverse() * crate::from_xr_pose(views[0].pose),
+                pose: motion.pose.inverse() * Pose::from(views[0].pose),
                 fov: crate::from_xr_fov(views[0].fov),
             },
             ViewParams {
-                pose: motion.pose.inverse() * crate::from_xr_pose(views[1].pose),
+                pose: motion.pose.inverse() * Pose::from(views[1].pose),
                 fov: crate::from_xr_fov(views[1].fov),
             },
         ])
@@ -791,8 +869,8 @@ pub fn get_head_data(
 }
 
 #[expect(clippy::too_many_arguments)]
-pub fn get_hand_data(
-    xr_session: &xr::Session<xr::OpenGlEs>,
+pub fn get_hand_data<G>(
+    xr_session: &xr::Session<G>,
     platform: Platform,
     reference_space: &xr::Space,
     time: Duration,
@@ -917,7 +995,7 @@ pub fn get_hand_data(
 
         let mut joints: [_; 26] = joint_locations
             .iter()
-            .map(|j| crate::from_xr_pose(j.pose))
+            .map(|j| Pose::from(j.pose))
             .collect::<Vec<_>>()
             .try_into()
             .unwrap();
@@ -936,8 +1014,81 @@ pub fn get_hand_data(
     }
 }
 
-pub fn update_buttons(
-    xr_session: &xr::Session<xr::OpenGlEs>,
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HandInputSource {
+    Controller,
+    Hand,
+}
+
+// Selects the input source of a hand. A switch happens only after the other source has been active
+// for the whole delay, while a lost source keeps the current selection.
+pub struct InputSourceSwitch {
+    source: Option<HandInputSource>,
+    candidate: Option<(HandInputSource, Instant)>,
+    delay: Duration,
+}
+
+impl InputSourceSwitch {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            source: None,
+            candidate: None,
+            delay,
+        }
+    }
+
+    pub fn update(&mut self, detected: Option<HandInputSource>) -> Option<HandInputSource> {
+        let Some(detected) = detected else {
+            self.candidate = None;
+            return self.source;
+        };
+
+        if self.source.is_none() {
+            self.source = Some(detected);
+        } else if self.source != Some(detected) {
+            match self.candidate {
+                Some((candidate, since)) if candidate == detected => {
+                    if since.elapsed() >= self.delay {
+                        self.source = Some(detected);
+                        self.candidate = None;
+                    }
+                }
+                _ => self.candidate = Some((detected, Instant::now())),
+            }
+        } else {
+            self.candidate = None;
+        }
+
+        self.source
+    }
+
+    // Hides the source that is not selected. While switching to the hand, the controller device
+    // follows the hand grip pose instead of disappearing
+    pub fn filter_hand_data(&mut self, hand_data: &mut HandData, multimodal: bool) {
+        let controller_active =
+            hand_data.grip_motion.is_some() && (multimodal || hand_data.skeleton_joints.is_none());
+        let detected = if controller_active {
+            Some(HandInputSource::Controller)
+        } else if hand_data.skeleton_joints.is_some() {
+            Some(HandInputSource::Hand)
+        } else {
+            None
+        };
+
+        match self.update(detected) {
+            Some(HandInputSource::Controller) => {
+                if !controller_active {
+                    hand_data.skeleton_joints = None;
+                }
+            }
+            Some(HandInputSource::Hand) => hand_data.grip_motion = None,
+            None => (),
+        }
+    }
+}
+
+pub fn update_buttons<G>(
+    xr_session: &xr::Session<G>,
     button_actions: &HashMap<u64, ButtonAction>,
 ) -> Vec<ButtonEntry> {
     let mut button_entries = Vec::with_capacity(2);
@@ -974,8 +1125,8 @@ pub fn update_buttons(
 }
 
 // Note: Using the headset view space in order to get heading-independent eye gazes
-pub fn get_face_data(
-    xr_session: &xr::Session<xr::OpenGlEs>,
+pub fn get_face_data<G>(
+    xr_session: &xr::Session<G>,
     sources: &FaceSources,
     view_reference_space: &xr::Space,
     time: Duration,
@@ -1052,7 +1203,7 @@ pub fn get_body_skeleton(
         if location_flags
             .contains(SpaceLocationFlags::ORIENTATION_VALID | SpaceLocationFlags::POSITION_VALID)
         {
-            Some(crate::from_xr_pose(pose))
+            Some(Pose::from(pose))
         } else {
             None
         }
@@ -1124,7 +1275,7 @@ pub fn get_bd_motion_trackers(source: &BodyTracker, time: Duration) -> Vec<(u64,
             joints.push((
                 joints_ids[i],
                 DeviceMotion {
-                    pose: crate::from_xr_pose(item.local_pose.pose),
+                    pose: Pose::from(item.local_pose.pose),
                     linear_velocity: crate::from_xr_vec3(item.local_pose.linear_velocity),
                     angular_velocity: crate::from_xr_vec3(item.local_pose.angular_velocity),
                 },
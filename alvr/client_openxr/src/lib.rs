@@ -1,74 +1,65 @@
 mod c_api;
 mod extra_extensions;
+#[cfg(target_os = "android")]
+mod flat;
+mod gestures;
 mod graphics;
 mod interaction;
 mod lobby;
+mod menu;
+mod motion_vectors;
 mod passthrough;
+#[cfg(target_os = "android")]
+mod peripherals;
+mod pose_history;
 mod stream;
 
-use crate::stream::ParsedStreamConfig;
+use crate::{graphics::ClientGraphics, stream::ParsedStreamConfig};
 use alvr_client_core::{ClientCapabilities, ClientCoreContext, ClientCoreEvent};
 use alvr_common::{
-    Fov, HAND_LEFT_ID, Pose, debug, error,
+    Fov, HAND_LEFT_ID, debug, error,
     glam::{Quat, UVec2, Vec3},
     info,
-    parking_lot::RwLock,
+    parking_lot::{Mutex, RwLock},
+    warn,
 };
 use alvr_graphics::GraphicsContext;
-use alvr_session::{BodyTrackingBDConfig, BodyTrackingSourcesConfig};
+use alvr_session::{BodyTrackingBDConfig, BodyTrackingSourcesConfig, ClientGraphicsApi};
 use alvr_system_info::Platform;
 use extra_extensions::{
     BD_BODY_TRACKING_EXTENSION_NAME, BD_MOTION_TRACKING_EXTENSION_NAME,
-    META_BODY_TRACKING_FIDELITY_EXTENSION_NAME, META_BODY_TRACKING_FULL_BODY_EXTENSION_NAME,
-    META_DETACHED_CONTROLLERS_EXTENSION_NAME,
-    META_SIMULTANEOUS_HANDS_AND_CONTROLLERS_EXTENSION_NAME, PICO_CONFIGURATION_EXTENSION_NAME,
+    EXT_HAND_INTERACTION_EXTENSION_NAME, EXT_SPATIAL_ENTITY_EXTENSION_NAME,
+    EXT_SPATIAL_MARKER_TRACKING_EXTENSION_NAME, META_BODY_TRACKING_FIDELITY_EXTENSION_NAME,
+    META_BODY_TRACKING_FULL_BODY_EXTENSION_NAME, META_DETACHED_CONTROLLERS_EXTENSION_NAME,
+    META_SIMULTANEOUS_HANDS_AND_CONTROLLERS_EXTENSION_NAME, MarkerFilter,
+    PICO_CONFIGURATION_EXTENSION_NAME,
 };
 use interaction::{InteractionContext, InteractionSourcesConfig};
 use lobby::Lobby;
+use menu::{InHeadsetMenu, InHeadsetMenuOverlay, PrivacyIndicatorOverlay};
 use openxr as xr;
 use passthrough::PassthroughLayer;
-use std::{ffi::CStr, path::Path, rc::Rc, sync::Arc, thread, time::Duration};
+use std::{
+    ffi::CStr,
+    path::Path,
+    rc::Rc,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
 use stream::StreamContext;
 
+// The startup is considered successful after rendering for this long
+const STARTUP_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
 fn from_xr_vec3(v: xr::Vector3f) -> Vec3 {
     Vec3::new(v.x, v.y, v.z)
 }
 
-fn to_xr_vec3(v: Vec3) -> xr::Vector3f {
-    xr::Vector3f {
-        x: v.x,
-        y: v.y,
-        z: v.z,
-    }
-}
-
 fn from_xr_quat(q: xr::Quaternionf) -> Quat {
     Quat::from_xyzw(q.x, q.y, q.z, q.w)
 }
 
-fn to_xr_quat(q: Quat) -> xr::Quaternionf {
-    xr::Quaternionf {
-        x: q.x,
-        y: q.y,
-        z: q.z,
-        w: q.w,
-    }
-}
-
-fn from_xr_pose(p: xr::Posef) -> Pose {
-    Pose {
-        orientation: from_xr_quat(p.orientation),
-        position: from_xr_vec3(p.position),
-    }
-}
-
-fn to_xr_pose(p: Pose) -> xr::Posef {
-    xr::Posef {
-        orientation: to_xr_quat(p.orientation),
-        position: to_xr_vec3(p.position),
-    }
-}
-
 fn from_xr_fov(f: xr::Fovf) -> Fov {
     Fov {
         left: f.angle_left,
@@ -116,18 +107,14 @@ fn default_view() -> xr::View {
 }
 
 // This exists to circumvent dead-code analysis
-fn create_session(
+fn create_session<G: ClientGraphics>(
     xr_instance: &xr::Instance,
     xr_system: xr::SystemId,
     graphics_context: &GraphicsContext,
-) -> (
-    xr::Session<xr::OpenGlEs>,
-    xr::FrameWaiter,
-    xr::FrameStream<xr::OpenGlEs>,
-) {
+) -> (xr::Session<G>, xr::FrameWaiter, xr::FrameStream<G>) {
     unsafe {
         xr_instance
-            .create_session(xr_system, &graphics::session_create_info(graphics_context))
+            .create_session::<G>(xr_system, &G::session_create_info(graphics_context))
             .unwrap()
     }
 }
@@ -173,12 +160,12 @@ pub fn entry_point() {
             .collect::<Vec<_>>()
     );
 
-    // todo: switch to vulkan
-    assert!(available_extensions.khr_opengl_es_enable);
+    let safe_mode = alvr_client_core::begin_startup();
 
     let mut exts = xr::ExtensionSet::default();
     exts.bd_controller_interaction = available_extensions.bd_controller_interaction;
     exts.ext_eye_gaze_interaction = available_extensions.ext_eye_gaze_interaction;
+    exts.ext_future = available_extensions.ext_future;
     exts.ext_hand_tracking = available_extensions.ext_hand_tracking;
     exts.ext_local_floor = available_extensions.ext_local_floor;
     exts.ext_user_presence = available_extensions.ext_user_presence;
@@ -191,17 +178,29 @@ pub fn entry_point() {
     exts.fb_foveation = available_extensions.fb_foveation;
     exts.fb_foveation_configuration = available_extensions.fb_foveation_configuration;
     exts.fb_passthrough = available_extensions.fb_passthrough;
+    exts.fb_scene = available_extensions.fb_scene;
+    exts.fb_space_warp = available_extensions.fb_space_warp;
+    exts.fb_spatial_entity = available_extensions.fb_spatial_entity;
+    exts.fb_spatial_entity_query = available_extensions.fb_spatial_entity_query;
     exts.fb_swapchain_update_state = available_extensions.fb_swapchain_update_state;
+    exts.fb_touch_controller_pro = available_extensions.fb_touch_controller_pro;
     exts.htc_facial_tracking = available_extensions.htc_facial_tracking;
     exts.htc_passthrough = available_extensions.htc_passthrough;
     exts.htc_vive_focus3_controller_interaction =
         available_extensions.htc_vive_focus3_controller_interaction;
+    exts.meta_passthrough_color_lut = available_extensions.meta_passthrough_color_lut;
+    exts.meta_touch_controller_plus = available_extensions.meta_touch_controller_plus;
     #[cfg(target_os = "android")]
     {
         exts.khr_android_create_instance = true;
     }
+    exts.khr_composition_layer_depth = available_extensions.khr_composition_layer_depth;
     exts.khr_convert_timespec_time = true;
-    exts.khr_opengl_es_enable = true;
+    exts.khr_opengl_es_enable = available_extensions.khr_opengl_es_enable;
+    #[cfg(feature = "vulkan")]
+    {
+        exts.khr_vulkan_enable2 = available_extensions.khr_vulkan_enable2;
+    }
     exts.other = available_extensions
         .other
         .into_iter()
@@ -214,11 +213,30 @@ pub fn entry_point() {
                 BD_BODY_TRACKING_EXTENSION_NAME,
                 BD_MOTION_TRACKING_EXTENSION_NAME,
                 PICO_CONFIGURATION_EXTENSION_NAME,
+                EXT_HAND_INTERACTION_EXTENSION_NAME,
+                EXT_SPATIAL_ENTITY_EXTENSION_NAME,
+                EXT_SPATIAL_MARKER_TRACKING_EXTENSION_NAME,
             ]
             .contains(&CStr::from_bytes_with_nul(ext).unwrap().to_str().unwrap())
         })
         .collect();
 
+    // Only the extensions needed for streaming are enabled
+    if safe_mode {
+        info!("Safe mode: optional extensions are disabled");
+        exts.ext_eye_gaze_interaction = false;
+        exts.fb_body_tracking = false;
+        exts.fb_eye_tracking_social = false;
+        exts.fb_face_tracking2 = false;
+        exts.fb_passthrough = false;
+        exts.fb_touch_controller_pro = false;
+        exts.htc_facial_tracking = false;
+        exts.htc_passthrough = false;
+        exts.meta_passthrough_color_lut = false;
+        exts.meta_touch_controller_plus = false;
+        exts.other.clear();
+    }
+
     let available_layers = xr_entry.enumerate_layers().unwrap();
     info!("OpenXR available layers: {available_layers:#?}");
 
@@ -258,10 +276,77 @@ pub fn entry_point() {
             .ok()
             .map(|s| s.runtime_version.into_raw()),
     );
+    if let Ok(properties) = xr_instance.properties() {
+        info!(
+            "OpenXR runtime: {} {}, platform: {platform}",
+            properties.runtime_name, properties.runtime_version
+        );
+    }
 
-    let graphics_context = Rc::new(GraphicsContext::new_gl());
+    let graphics_api = alvr_client_core::graphics_api();
+    info!("Graphics API: {graphics_api:?}");
 
+    #[cfg(not(feature = "vulkan"))]
+    if graphics_api == ClientGraphicsApi::Vulkan {
+        warn!("This client is built without Vulkan support, using OpenGL ES");
+    }
+
+    #[cfg(feature = "vulkan")]
+    if graphics_api == ClientGraphicsApi::Vulkan && !exts.khr_vulkan_enable2 {
+        warn!("The runtime does not support Vulkan, using OpenGL ES");
+    }
+
+    #[cfg(feature = "vulkan")]
+    if exts.khr_vulkan_enable2 && graphics_api != ClientGraphicsApi::OpenGlEs {
+        let xr_system = xr_instance
+            .system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)
+            .unwrap();
+
+        // Must be called before creating the Vulkan instance
+        let _ = xr_instance
+            .graphics_requirements::<xr::Vulkan>(xr_system)
+            .unwrap();
+
+        match GraphicsContext::new_vulkan(&graphics::XrVulkanLoader {
+            instance: &xr_instance,
+            system: xr_system,
+        }) {
+            Ok(graphics_context) => {
+                session_loop::<xr::Vulkan>(
+                    &xr_instance,
+                    &exts,
+                    &other_exts,
+                    platform,
+                    Rc::new(graphics_context),
+                );
+
+                return;
+            }
+            Err(e) => warn!("Failed to create Vulkan context, falling back to OpenGL ES: {e:?}"),
+        }
+    }
+
+    assert!(exts.khr_opengl_es_enable);
+
+    session_loop::<xr::OpenGlEs>(
+        &xr_instance,
+        &exts,
+        &other_exts,
+        platform,
+        Rc::new(GraphicsContext::new_gl()),
+    );
+}
+
+fn session_loop<G: ClientGraphics>(
+    xr_instance: &xr::Instance,
+    exts: &xr::ExtensionSet,
+    other_exts: &[String],
+    platform: Platform,
+    graphics_context: Rc<GraphicsContext>,
+) {
     let mut last_lobby_message = String::new();
+    let startup_instant = Instant::now();
+    let mut startup_completed = false;
 
     'session_loop: loop {
         let xr_system = xr_instance
@@ -269,12 +354,10 @@ pub fn entry_point() {
             .unwrap();
 
         // mandatory call
-        let _ = xr_instance
-            .graphics_requirements::<xr::OpenGlEs>(xr_system)
-            .unwrap();
+        let _ = xr_instance.graphics_requirements::<G>(xr_system).unwrap();
 
         let (xr_session, mut xr_frame_waiter, mut xr_frame_stream) =
-            create_session(&xr_instance, xr_system, &graphics_context);
+            create_session::<G>(xr_instance, xr_system, &graphics_context);
 
         let views_config = xr_instance
             .enumerate_view_configuration_views(
@@ -306,6 +389,20 @@ pub fn entry_point() {
                 .unwrap();
         }
 
+        #[cfg(target_os = "android")]
+        let (decoder_chroma_422, decoder_chroma_444) = (
+            alvr_system_info::decoder_supports_color_format(
+                "video/hevc",
+                alvr_system_info::COLOR_FORMAT_YUV422_FLEXIBLE,
+            ),
+            alvr_system_info::decoder_supports_color_format(
+                "video/hevc",
+                alvr_system_info::COLOR_FORMAT_YUV444_FLEXIBLE,
+            ),
+        );
+        #[cfg(not(target_os = "android"))]
+        let (decoder_chroma_422, decoder_chroma_444) = (false, false);
+
         let capabilities = ClientCapabilities {
             platform,
             default_view_resolution,
@@ -321,12 +418,24 @@ pub fn entry_point() {
             prefer_10bit: false,
             preferred_encoding_gamma: 1.0,
             prefer_hdr: false,
+            depth_layers: exts.khr_composition_layer_depth && G::DEPTH_FORMAT.is_some(),
+            space_warp: exts.fb_space_warp
+                && exts.khr_composition_layer_depth
+                && G::DEPTH_FORMAT.is_some()
+                && G::MOTION_VECTOR_FORMAT.is_some(),
+            decoder_chroma_422,
+            decoder_chroma_444,
+            radial_foveated_encoding: true,
+            per_eye_foveated_encoding: true,
+            flat_display: false,
         };
         let core_context = Arc::new(ClientCoreContext::new(capabilities));
+        #[cfg(target_os = "android")]
+        peripherals::set_core_context(&core_context);
 
         let interaction_context = Arc::new(RwLock::new(InteractionContext::new(
             xr_session.clone(),
-            other_exts.clone(),
+            other_exts.to_vec(),
             xr_system,
             platform,
         )));
@@ -356,17 +465,24 @@ pub fn entry_point() {
             face_tracking: None,
             body_tracking: lobby_body_tracking_config,
             prefers_multimodal_input: true,
+            // All markers are shown, so that one can be chosen as origin
+            marker_filter: alvr_client_core::lobby_marker_selection().then_some(MarkerFilter::All),
         };
         interaction_context
             .write()
             .select_sources(&lobby_interaction_sources);
 
         let mut session_running = false;
-        let mut stream_context = None::<StreamContext>;
+        let mut stream_context = None::<StreamContext<G>>;
         let mut passthrough_layer = None;
+        let mut passthrough_style = None;
+        let mut in_headset_menu = None::<InHeadsetMenuOverlay<G>>;
+        let mut privacy_indicator = None::<PrivacyIndicatorOverlay<G>>;
 
         let mut event_storage = xr::EventDataBuffer::new();
         let mut headset_is_worn = true;
+        let mut session_visible = false;
+        let mut headset_in_standby = false;
         'render_loop: loop {
             while let Some(event) = xr_instance.poll_event(&mut event_storage).unwrap() {
                 match event {
@@ -382,7 +498,12 @@ pub fn entry_point() {
 
                             core_context.resume();
 
-                            passthrough_layer = PassthroughLayer::new(&xr_session, platform).ok();
+                            passthrough_layer = PassthroughLayer::new(
+                                &xr_session,
+                                platform,
+                                passthrough_style.as_ref(),
+                            )
+                            .ok();
 
                             session_running = true;
                         }
@@ -398,6 +519,12 @@ pub fn entry_point() {
                         xr::SessionState::EXITING | xr::SessionState::LOSS_PENDING => {
                             break 'render_loop;
                         }
+                        xr::SessionState::VISIBLE | xr::SessionState::FOCUSED => {
+                            session_visible = true;
+                        }
+                        xr::SessionState::SYNCHRONIZED | xr::SessionState::IDLE => {
+                            session_visible = false;
+                        }
                         _ => (),
                     },
                     xr::Event::ReferenceSpaceChangePending(event) => {
@@ -431,14 +558,29 @@ pub fn entry_point() {
 
                         core_context.send_proximity_state(event.is_user_present());
                     }
+                    xr::Event::SpaceQueryResultsAvailableFB(_)
+                    | xr::Event::SpaceQueryCompleteFB(_) => {
+                        let event: *const xr::sys::EventDataBuffer = event_storage.as_raw();
+                        if let Some(stream) = &mut stream_context {
+                            stream.handle_room_boundary_event(unsafe { &*event });
+                        }
+                    }
                     xr::Event::Unknown => {
-                        // use event_storage.as_raw(), reinterpret as sys::BaseInStructure, get type
-                        // and then reinterpret as the event struct
+                        let event: *const xr::sys::EventDataBuffer = event_storage.as_raw();
+                        if let Some(source) = &interaction_context.read().marker_source {
+                            source.handle_event(unsafe { &*event });
+                        }
                     }
                     _ => (),
                 }
             }
 
+            if headset_in_standby != (!headset_is_worn || !session_visible) {
+                headset_in_standby = !headset_in_standby;
+
+                core_context.send_standby_state(headset_in_standby);
+            }
+
             if !session_running {
                 thread::sleep(Duration::from_millis(100));
                 continue;
@@ -451,7 +593,30 @@ pub fn entry_point() {
                         lobby.update_hud_message(&message);
                     }
                     ClientCoreEvent::StreamingStarted(config) => {
-                        let config = ParsedStreamConfig::new(&config);
+                        passthrough_style =
+                            config.settings.video.passthrough_style.as_option().cloned();
+
+                        let menu_state = config.settings.headset.in_headset_menu.as_option().map(
+                            |menu_config| {
+                                Arc::new(Mutex::new(InHeadsetMenu::new(
+                                    Arc::clone(&core_context),
+                                    menu_config,
+                                    &config.settings,
+                                )))
+                            },
+                        );
+                        in_headset_menu = menu_state.as_ref().map(|state| {
+                            InHeadsetMenuOverlay::new(
+                                &xr_session,
+                                Rc::clone(&graphics_context),
+                                Arc::clone(state),
+                            )
+                        });
+
+                        let mut config = ParsedStreamConfig::new(&config);
+                        if let Some(marker_code) = lobby.selected_marker() {
+                            config.set_lobby_marker_origin(marker_code);
+                        }
 
                         let context = StreamContext::new(
                             Arc::clone(&core_context),
@@ -459,25 +624,35 @@ pub fn entry_point() {
                             Rc::clone(&graphics_context),
                             Arc::clone(&interaction_context),
                             config,
+                            menu_state,
                         );
 
                         if !context.uses_passthrough() {
                             passthrough_layer = None;
+                        } else if let Some(layer) = &mut passthrough_layer {
+                            layer.set_style(passthrough_style.as_ref());
                         }
 
                         stream_context = Some(context);
 
                         core_context.send_proximity_state(headset_is_worn);
+                        core_context.send_standby_state(headset_in_standby);
                     }
                     ClientCoreEvent::StreamingStopped => {
-                        if passthrough_layer.is_none() {
-                            passthrough_layer = PassthroughLayer::new(&xr_session, platform).ok();
+                        passthrough_style = None;
+
+                        if let Some(layer) = &mut passthrough_layer {
+                            layer.set_style(None);
+                        } else {
+                            passthrough_layer =
+                                PassthroughLayer::new(&xr_session, platform, None).ok();
                         }
 
                         interaction_context
                             .write()
                             .select_sources(&lobby_interaction_sources);
 
+                        in_headset_menu = None;
                         stream_context = None;
                     }
                     ClientCoreEvent::Haptics {
@@ -507,8 +682,21 @@ pub fn entry_point() {
                         }
                     }
                     ClientCoreEvent::RealTimeConfig(config) => {
+                        if config.passthrough_style != passthrough_style {
+                            passthrough_style = config.passthrough_style.clone();
+
+                            if let Some(layer) = &mut passthrough_layer {
+                                layer.set_style(passthrough_style.as_ref());
+                            }
+                        }
+
                         if config.passthrough.is_some() && passthrough_layer.is_none() {
-                            passthrough_layer = PassthroughLayer::new(&xr_session, platform).ok();
+                            passthrough_layer = PassthroughLayer::new(
+                                &xr_session,
+                                platform,
+                                passthrough_style.as_ref(),
+                            )
+                            .ok();
                         } else if config.passthrough.is_none() && passthrough_layer.is_some() {
                             passthrough_layer = None;
                         }
@@ -516,6 +704,10 @@ pub fn entry_point() {
                         if let Some(stream) = &mut stream_context {
                             stream.update_real_time_config(&config);
                         }
+
+                        if let Some(menu) = &in_headset_menu {
+                            menu.update_real_time_config(&config);
+                        }
                     }
                 }
             }
@@ -546,6 +738,13 @@ pub fn entry_point() {
                 continue;
             }
 
+            if stream_context.is_none() && lobby.wake_button_pressed() {
+                core_context.wake_server();
+            }
+            if stream_context.is_none() && lobby.forget_button_held() {
+                core_context.forget_servers();
+            }
+
             // todo: allow rendering lobby and stream layers at the same time and add cross fade
             let (layer, display_time) = if let Some(stream) = &mut stream_context {
                 stream.render(frame_interval, vsync_time)
@@ -553,18 +752,41 @@ pub fn entry_point() {
                 (lobby.render(vsync_time), vsync_time)
             };
 
-            let layers: &[&xr::CompositionLayerBase<_>] =
-                if let Some(passthrough_layer) = &passthrough_layer {
-                    &[passthrough_layer, &layer.build()]
-                } else {
-                    &[&layer.build()]
-                };
+            let menu_layer = in_headset_menu.as_mut().and_then(|menu| menu.render());
+
+            // The face, eye and body data is sent only while streaming
+            if stream_context.is_some() && core_context.biometric_streams_blocked() {
+                if privacy_indicator.is_none() {
+                    privacy_indicator = Some(PrivacyIndicatorOverlay::new(
+                        &xr_session,
+                        Rc::clone(&graphics_context),
+                    ));
+                }
+            } else {
+                privacy_indicator = None;
+            }
+            let privacy_layer = privacy_indicator
+                .as_mut()
+                .map(|indicator| indicator.render());
+
+            let projection_layer = layer.build();
+            let mut layers: Vec<&xr::CompositionLayerBase<_>> = vec![];
+            if let Some(passthrough_layer) = &passthrough_layer {
+                layers.push(passthrough_layer);
+            }
+            layers.push(&projection_layer);
+            if let Some(menu_layer) = &menu_layer {
+                layers.push(menu_layer);
+            }
+            if let Some(privacy_layer) = &privacy_layer {
+                layers.push(privacy_layer);
+            }
 
             graphics_context.make_current();
             let res = xr_frame_stream.end(
                 to_xr_time(display_time),
                 xr::EnvironmentBlendMode::OPAQUE,
-                layers,
+                &layers,
             );
 
             if let Err(e) = res {
@@ -581,6 +803,11 @@ pub fn entry_point() {
                         .unwrap();
                 }
             }
+
+            if !startup_completed && startup_instant.elapsed() > STARTUP_GRACE_PERIOD {
+                alvr_client_core::complete_startup();
+                startup_completed = true;
+            }
         }
     }
 
@@ -600,6 +827,12 @@ fn xr_runtime_now(xr_instance: &xr::Instance) -> Option<xr::Time> {
 fn android_main(app: android_activity::AndroidApp) {
     use android_activity::{InputStatus, MainEvent, PollEvent};
 
+    if alvr_system_info::is_television() {
+        flat::entry_point(app);
+
+        return;
+    }
+
     let rendering_thread = thread::spawn(|| {
         // workaround for the Pico runtime
         let context = ndk_context::android_context();
@@ -609,6 +842,8 @@ fn android_main(app: android_activity::AndroidApp) {
         entry_point();
     });
 
+    let mut peripheral_input = peripherals::PeripheralInputSource::default();
+
     let mut should_quit = false;
     while !should_quit {
         app.poll_events(Some(Duration::from_millis(100)), |event| match event {
@@ -617,7 +852,7 @@ fn android_main(app: android_activity::AndroidApp) {
             }
             PollEvent::Main(MainEvent::InputAvailable) => {
                 if let Ok(mut iter) = app.input_events_iter() {
-                    while iter.next(|_| InputStatus::Unhandled) {}
+                    while iter.next(|event| peripheral_input.handle_event(&app, event)) {}
                 }
             }
             _ => (),
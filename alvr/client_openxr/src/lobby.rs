@@ -1,28 +1,47 @@
 use crate::{
-    graphics::{self, ProjectionLayerAlphaConfig, ProjectionLayerBuilder},
-    interaction::{self, InteractionContext},
+    graphics::{self, ClientGraphics, ProjectionLayerAlphaConfig, ProjectionLayerBuilder},
+    interaction::{self, ButtonAction, InteractionContext},
 };
-use alvr_common::{Pose, ViewParams, glam::UVec2, parking_lot::RwLock};
-use alvr_graphics::{GraphicsContext, LobbyRenderer, LobbyViewParams, SDR_FORMAT_GL};
+use alvr_common::{
+    Pose, RIGHT_A_CLICK_ID, RIGHT_B_CLICK_ID, ViewParams, debug, glam::UVec2, info,
+    parking_lot::RwLock,
+};
+use alvr_graphics::{GraphicsContext, HandData, LobbyMarker, LobbyRenderer, LobbyViewParams};
 use alvr_system_info::Platform;
 use openxr as xr;
-use std::{rc::Rc, sync::Arc, time::Duration};
+use std::{
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+const MARKER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+// Distance from the marker center within which a hand or controller selects it
+const MARKER_TOUCH_DISTANCE: f32 = 0.1;
+// XR_HAND_JOINT_INDEX_TIP_EXT
+const INDEX_TIP_JOINT: usize = 10;
+const FORGET_HOLD_DURATION: Duration = Duration::from_secs(3);
 
 // todo: add interaction?
-pub struct Lobby {
-    xr_session: xr::Session<xr::OpenGlEs>,
+pub struct Lobby<G: ClientGraphics> {
+    xr_session: xr::Session<G>,
     interaction_ctx: Arc<RwLock<InteractionContext>>,
     platform: Platform,
     reference_space: xr::Space,
-    swapchains: [xr::Swapchain<xr::OpenGlEs>; 2],
+    swapchains: [xr::Swapchain<G>; 2],
     view_resolution: UVec2,
     reference_space_type: xr::ReferenceSpaceType,
     renderer: LobbyRenderer,
+    markers: Vec<(String, Pose)>,
+    last_marker_poll: Instant,
+    touched_marker: Option<String>,
+    selected_marker: Option<String>,
+    forget_press_start: Option<Instant>,
 }
 
-impl Lobby {
+impl<G: ClientGraphics> Lobby<G> {
     pub fn new(
-        xr_session: xr::Session<xr::OpenGlEs>,
+        xr_session: xr::Session<G>,
         gfx_ctx: Rc<GraphicsContext>,
         interaction_ctx: Arc<RwLock<InteractionContext>>,
         platform: Platform,
@@ -40,26 +59,16 @@ impl Lobby {
         let reference_space = interaction::get_reference_space(&xr_session, reference_space_type);
 
         let swapchains = [
-            graphics::create_swapchain(&xr_session, &gfx_ctx, view_resolution, SDR_FORMAT_GL, None),
-            graphics::create_swapchain(&xr_session, &gfx_ctx, view_resolution, SDR_FORMAT_GL, None),
+            graphics::create_swapchain(&xr_session, &gfx_ctx, view_resolution, G::SDR_FORMAT, None),
+            graphics::create_swapchain(&xr_session, &gfx_ctx, view_resolution, G::SDR_FORMAT, None),
         ];
 
         let renderer = LobbyRenderer::new(
             gfx_ctx,
             view_resolution,
             [
-                swapchains[0]
-                    .enumerate_images()
-                    .unwrap()
-                    .iter()
-                    .map(|i| *i as _)
-                    .collect(),
-                swapchains[1]
-                    .enumerate_images()
-                    .unwrap()
-                    .iter()
-                    .map(|i| *i as _)
-                    .collect(),
+                swapchains[0].enumerate_images().unwrap(),
+                swapchains[1].enumerate_images().unwrap(),
             ],
             initial_hud_message,
         );
@@ -73,6 +82,11 @@ impl Lobby {
             view_resolution,
             reference_space_type,
             renderer,
+            markers: vec![],
+            last_marker_poll: Instant::now(),
+            touched_marker: None,
+            selected_marker: None,
+            forget_press_start: None,
         }
     }
 
@@ -85,7 +99,112 @@ impl Lobby {
         self.renderer.update_hud_message(message);
     }
 
-    pub fn render(&mut self, vsync_time: Duration) -> ProjectionLayerBuilder<'_> {
+    // Code of the marker chosen by touching it, used as origin once connected
+    pub fn selected_marker(&self) -> Option<String> {
+        self.selected_marker.clone()
+    }
+
+    // Touching a marker selects it, touching the selected marker again clears the selection
+    fn update_markers(&mut self, xr_time: xr::Time, hand_data: [&HandData; 2]) {
+        if let Some(source) = &self.interaction_ctx.read().marker_source {
+            if self.last_marker_poll.elapsed() > MARKER_POLL_INTERVAL {
+                self.last_marker_poll = Instant::now();
+
+                if let Err(e) = source.poll(&self.reference_space, xr_time) {
+                    debug!("Marker tracking poll failed: {e}");
+                }
+            }
+
+            // Between polls the markers are drawn where they were last seen
+            self.markers = source
+                .last_markers()
+                .into_iter()
+                .map(|(code, pose)| (code, Pose::from(pose)))
+                .collect();
+        }
+
+        let touch_points = hand_data
+            .iter()
+            .filter_map(|data| {
+                data.skeleton_joints
+                    .map(|joints| joints[INDEX_TIP_JOINT].position)
+                    .or(data.grip_motion.map(|motion| motion.pose.position))
+            })
+            .collect::<Vec<_>>();
+        let touched_marker = self
+            .markers
+            .iter()
+            .find(|(_, pose)| {
+                touch_points
+                    .iter()
+                    .any(|point| point.distance(pose.position) < MARKER_TOUCH_DISTANCE)
+            })
+            .map(|(code, _)| code.clone());
+
+        if touched_marker != self.touched_marker {
+            if let Some(code) = &touched_marker {
+                if self.selected_marker.as_ref() == Some(code) {
+                    info!("Lobby origin marker cleared");
+                    self.selected_marker = None;
+                } else {
+                    info!("Lobby origin marker selected: {code}");
+                    self.selected_marker = Some(code.clone());
+                }
+            }
+
+            self.touched_marker = touched_marker;
+        }
+    }
+
+    // True once A is held for FORGET_HOLD_DURATION, then the button must be held again. Uses the
+    // actions state of the last rendered frame
+    pub fn forget_button_held(&mut self) -> bool {
+        let pressed = if let Some(ButtonAction::Binary(action)) = self
+            .interaction_ctx
+            .read()
+            .button_actions
+            .get(&*RIGHT_A_CLICK_ID)
+        {
+            action
+                .state(&self.xr_session, xr::Path::NULL)
+                .is_ok_and(|state| state.current_state)
+        } else {
+            false
+        };
+
+        if !pressed {
+            self.forget_press_start = None;
+
+            return false;
+        }
+
+        let start = *self.forget_press_start.get_or_insert_with(Instant::now);
+        if start.elapsed() > FORGET_HOLD_DURATION {
+            self.forget_press_start = None;
+
+            true
+        } else {
+            false
+        }
+    }
+
+    // Uses the actions state of the last rendered frame
+    pub fn wake_button_pressed(&self) -> bool {
+        if let Some(ButtonAction::Binary(action)) = self
+            .interaction_ctx
+            .read()
+            .button_actions
+            .get(&*RIGHT_B_CLICK_ID)
+        {
+            action
+                .state(&self.xr_session, xr::Path::NULL)
+                .is_ok_and(|state| state.changed_since_last_sync && state.current_state)
+        } else {
+            false
+        }
+    }
+
+    pub fn render(&mut self, vsync_time: Duration) -> ProjectionLayerBuilder<'_, G> {
         let xr_vsync_time = crate::to_xr_time(vsync_time);
 
         let (flags, maybe_views) = self
@@ -130,6 +249,16 @@ impl Lobby {
             &mut Pose::default(),
         );
 
+        self.update_markers(xr_vsync_time, [&left_hand_data, &right_hand_data]);
+        let markers = self
+            .markers
+            .iter()
+            .map(|(code, pose)| LobbyMarker {
+                pose: *pose,
+                selected: self.selected_marker.as_ref() == Some(code),
+            })
+            .collect::<Vec<_>>();
+
         let additional_motions = self
             .interaction_ctx
             .read()
@@ -165,14 +294,14 @@ impl Lobby {
             [
                 LobbyViewParams {
                     view_params: ViewParams {
-                        pose: crate::from_xr_pose(views[0].pose),
+                        pose: Pose::from(views[0].pose),
                         fov: crate::from_xr_fov(views[0].fov),
                     },
                     swapchain_index: left_swapchain_idx,
                 },
                 LobbyViewParams {
                     view_params: ViewParams {
-                        pose: crate::from_xr_pose(views[1].pose),
+                        pose: Pose::from(views[1].pose),
                         fov: crate::from_xr_fov(views[1].fov),
                     },
                     swapchain_index: right_swapchain_idx,
@@ -181,6 +310,7 @@ impl Lobby {
             [left_hand_data, right_hand_data],
             body_skeleton,
             additional_motions,
+            &markers,
             false,
             cfg!(debug_assertions),
         );
@@ -222,6 +352,7 @@ impl Lobby {
                 premultiplied: true,
             }),
             None,
+            None,
         )
     }
 }
@@ -0,0 +1,433 @@
+use crate::graphics::{self, ClientGraphics};
+use alvr_client_core::ClientCoreContext;
+use alvr_common::{
+    RIGHT_A_CLICK_ID, RIGHT_THUMBSTICK_X_ID, RIGHT_THUMBSTICK_Y_ID, glam::UVec2, info,
+    parking_lot::Mutex,
+};
+use alvr_graphics::{GraphicsContext, MenuRenderer};
+use alvr_packets::{ButtonEntry, ButtonValue, InHeadsetMenuAction, RealTimeConfig};
+use alvr_session::{
+    BitrateMode, ClientsidePostProcessingConfig, ClientsidePostProcessingSharpeningMode,
+    InHeadsetMenuConfig, Settings,
+};
+use openxr as xr;
+use std::{
+    collections::HashSet,
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+const MENU_RESOLUTION: u32 = 512;
+const MENU_SIDE_M: f32 = 0.6;
+const MENU_DISTANCE_M: f32 = 1.0;
+const THUMBSTICK_THRESHOLD: f32 = 0.7;
+// The indicator is at the lower left of the view, out of the way of the content
+const PRIVACY_INDICATOR_SIDE_M: f32 = 0.2;
+const PRIVACY_INDICATOR_POSITION: xr::Vector3f = xr::Vector3f {
+    x: -0.35,
+    y: -0.3,
+    z: -MENU_DISTANCE_M,
+};
+// The text is rendered again for each update of the frame statistics
+const FRAME_STATS_INTERVAL: Duration = Duration::from_millis(500);
+// Used as starting point when the adaptive bitrate has no maximum
+const UNLIMITED_BITRATE_START_MBPS: u64 = 100;
+
+#[derive(Clone, Copy, PartialEq)]
+enum MenuEntry {
+    Bitrate,
+    Recenter,
+    Passthrough,
+    Sharpening,
+}
+
+const MENU_ENTRIES: [MenuEntry; 4] = [
+    MenuEntry::Bitrate,
+    MenuEntry::Recenter,
+    MenuEntry::Passthrough,
+    MenuEntry::Sharpening,
+];
+
+fn sharpening_enabled(config: Option<&ClientsidePostProcessingConfig>) -> bool {
+    config
+        .is_some_and(|config| config.sharpening != ClientsidePostProcessingSharpeningMode::Disabled)
+}
+
+// Menu state, updated by the input thread and read by the rendering thread. The values are only
+// requests: the server applies them to the session, which is not sent back to the client except for
+// the real-time settings.
+pub struct InHeadsetMenu {
+    core_context: Arc<ClientCoreContext>,
+    open_chord_ids: HashSet<u64>,
+    pressed_chord_ids: HashSet<u64>,
+    bitrate_step_mbps: u64,
+    open: bool,
+    selected: usize,
+    bitrate_mbps: Option<u64>,
+    passthrough: bool,
+    sharpening: bool,
+    // Thumbstick direction as (x, y), to react only to the movement out of the center
+    thumbstick_direction: (i32, i32),
+    text_dirty: bool,
+    last_text_update: Instant,
+}
+
+impl InHeadsetMenu {
+    pub fn new(
+        core_context: Arc<ClientCoreContext>,
+        config: &InHeadsetMenuConfig,
+        settings: &Settings,
+    ) -> Self {
+        let bitrate_mbps = match &settings.video.bitrate.mode {
+            BitrateMode::ConstantMbps(mbps) => Some(*mbps),
+            BitrateMode::Adaptive {
+                max_throughput_mbps,
+                ..
+            } => max_throughput_mbps.as_option().copied(),
+        };
+
+        Self {
+            core_context,
+            open_chord_ids: config
+                .open_chord
+                .iter()
+                .map(|path| alvr_common::hash_string(path))
+                .collect(),
+            pressed_chord_ids: HashSet::new(),
+            bitrate_step_mbps: config.bitrate_step_mbps.max(1),
+            open: false,
+            selected: 0,
+            bitrate_mbps,
+            passthrough: settings.video.passthrough.as_option().is_some(),
+            sharpening: sharpening_enabled(settings.video.clientside_post_processing.as_option()),
+            thumbstick_direction: (0, 0),
+            text_dirty: true,
+            last_text_update: Instant::now(),
+        }
+    }
+
+    pub fn update_real_time_config(&mut self, config: &RealTimeConfig) {
+        self.passthrough = config.passthrough.is_some();
+        self.sharpening = sharpening_enabled(config.clientside_post_processing.as_ref());
+        self.text_dirty = true;
+    }
+
+    fn send_action(&self, action: InHeadsetMenuAction) {
+        info!("In-headset menu: {action:?}");
+        self.core_context.send_in_headset_menu_action(action);
+    }
+
+    // direction: -1 or 1 for left and right, 0 for confirm
+    fn activate_entry(&mut self, direction: i32) {
+        match MENU_ENTRIES[self.selected] {
+            MenuEntry::Bitrate => {
+                if direction == 0 {
+                    return;
+                }
+
+                let current = self.bitrate_mbps.unwrap_or(UNLIMITED_BITRATE_START_MBPS);
+                let bitrate_mbps = if direction > 0 {
+                    current + self.bitrate_step_mbps
+                } else {
+                    current
+                        .saturating_sub(self.bitrate_step_mbps)
+                        .max(self.bitrate_step_mbps)
+                };
+                self.bitrate_mbps = Some(bitrate_mbps);
+
+                self.send_action(InHeadsetMenuAction::SetBitrateMbps(bitrate_mbps));
+            }
+            MenuEntry::Recenter => {
+                if direction == 0 {
+                    self.send_action(InHeadsetMenuAction::Recenter);
+                }
+            }
+            MenuEntry::Passthrough => {
+                self.passthrough = !self.passthrough;
+                self.send_action(InHeadsetMenuAction::SetPassthrough(self.passthrough));
+            }
+            MenuEntry::Sharpening => {
+                self.sharpening = !self.sharpening;
+                self.send_action(InHeadsetMenuAction::SetSharpening(self.sharpening));
+            }
+        }
+
+        self.text_dirty = true;
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.thumbstick_direction = (0, 0);
+        self.text_dirty = true;
+    }
+
+    // Returns the entries to send to the server. While the menu is open, only button releases are
+    // forwarded, so that buttons pressed before opening the menu do not remain stuck.
+    pub fn handle_buttons(&mut self, entries: Vec<ButtonEntry>) -> Vec<ButtonEntry> {
+        let mut toggle_requested = false;
+        for entry in &entries {
+            if self.open_chord_ids.contains(&entry.path_id)
+                && let ButtonValue::Binary(pressed) = entry.value
+            {
+                let was_complete = self.pressed_chord_ids == self.open_chord_ids;
+                if pressed {
+                    self.pressed_chord_ids.insert(entry.path_id);
+                } else {
+                    self.pressed_chord_ids.remove(&entry.path_id);
+                }
+
+                toggle_requested |= !was_complete && self.pressed_chord_ids == self.open_chord_ids;
+            }
+        }
+
+        if toggle_requested {
+            self.toggle();
+
+            return entries
+                .into_iter()
+                .filter(|entry| matches!(entry.value, ButtonValue::Binary(false)))
+                .collect();
+        }
+
+        if !self.open {
+            return entries;
+        }
+
+        let mut forwarded_entries = vec![];
+        for entry in entries {
+            let axis_direction = |value: f32| {
+                if value > THUMBSTICK_THRESHOLD {
+                    1
+                } else if value < -THUMBSTICK_THRESHOLD {
+                    -1
+                } else {
+                    0
+                }
+            };
+
+            match entry.value {
+                ButtonValue::Scalar(value) if entry.path_id == *RIGHT_THUMBSTICK_Y_ID => {
+                    let direction = axis_direction(value);
+                    if direction != self.thumbstick_direction.1 {
+                        self.thumbstick_direction.1 = direction;
+
+                        // Thumbstick up is positive, while entries are listed top to bottom
+                        if direction != 0 {
+                            self.selected = (self.selected as i32 - direction)
+                                .rem_euclid(MENU_ENTRIES.len() as i32)
+                                as usize;
+                            self.text_dirty = true;
+                        }
+                    }
+                }
+                ButtonValue::Scalar(value) if entry.path_id == *RIGHT_THUMBSTICK_X_ID => {
+                    let direction = axis_direction(value);
+                    if direction != self.thumbstick_direction.0 {
+                        self.thumbstick_direction.0 = direction;
+
+                        if direction != 0 {
+                            self.activate_entry(direction);
+                        }
+                    }
+                }
+                ButtonValue::Binary(true) if entry.path_id == *RIGHT_A_CLICK_ID => {
+                    self.activate_entry(0);
+                }
+                ButtonValue::Binary(false) => forwarded_entries.push(entry),
+                _ => (),
+            }
+        }
+
+        forwarded_entries
+    }
+
+    fn text(&self) -> String {
+        let on_off = |value| if value { "On" } else { "Off" };
+
+        let mut text = "Quick settings\n".to_owned();
+        for (idx, entry) in MENU_ENTRIES.iter().enumerate() {
+            let label = match entry {
+                MenuEntry::Bitrate => format!(
+                    "Bitrate: {}",
+                    self.bitrate_mbps
+                        .map(|mbps| format!("{mbps} Mbps"))
+                        .unwrap_or_else(|| "Auto".into())
+                ),
+                MenuEntry::Recenter => "Recenter".into(),
+                MenuEntry::Passthrough => format!("Passthrough: {}", on_off(self.passthrough)),
+                MenuEntry::Sharpening => format!("Sharpening: {}", on_off(self.sharpening)),
+            };
+
+            text += &if idx == self.selected {
+                format!("\n> {label} <")
+            } else {
+                format!("\n{label}")
+            };
+        }
+
+        if let Some(stats) = self.core_context.displayed_frame_encoder_stats() {
+            text += &format!(
+                "\n\nFrame: QP {}, encoded in {:.1} ms{}",
+                stats
+                    .average_qp
+                    .map(|qp| qp.to_string())
+                    .unwrap_or_else(|| "-".into()),
+                stats.encode_duration.as_secs_f32() * 1000.0,
+                if stats.is_idr { ", IDR" } else { "" }
+            );
+        }
+
+        text
+    }
+}
+
+// Head-locked quad layer that shows the menu while it is open
+pub struct InHeadsetMenuOverlay<G: ClientGraphics> {
+    state: Arc<Mutex<InHeadsetMenu>>,
+    view_reference_space: xr::Space,
+    swapchain: xr::Swapchain<G>,
+    renderer: MenuRenderer,
+}
+
+impl<G: ClientGraphics> InHeadsetMenuOverlay<G> {
+    pub fn new(
+        xr_session: &xr::Session<G>,
+        gfx_ctx: Rc<GraphicsContext>,
+        state: Arc<Mutex<InHeadsetMenu>>,
+    ) -> Self {
+        let resolution = UVec2::ONE * MENU_RESOLUTION;
+
+        let view_reference_space = xr_session
+            .create_reference_space(xr::ReferenceSpaceType::VIEW, xr::Posef::IDENTITY)
+            .unwrap();
+
+        let swapchain =
+            graphics::create_swapchain(xr_session, &gfx_ctx, resolution, G::SDR_FORMAT, None);
+
+        let renderer =
+            MenuRenderer::new(gfx_ctx, resolution, swapchain.enumerate_images().unwrap());
+
+        Self {
+            state,
+            view_reference_space,
+            swapchain,
+            renderer,
+        }
+    }
+
+    pub fn update_real_time_config(&self, config: &RealTimeConfig) {
+        self.state.lock().update_real_time_config(config);
+    }
+
+    pub fn render(&mut self) -> Option<xr::CompositionLayerQuad<'_, G>> {
+        {
+            let mut state = self.state.lock();
+            if !state.open {
+                return None;
+            }
+
+            if state.text_dirty || state.last_text_update.elapsed() > FRAME_STATS_INTERVAL {
+                self.renderer.update_text(&state.text());
+                state.text_dirty = false;
+                state.last_text_update = Instant::now();
+            }
+        }
+
+        let swapchain_idx = self.swapchain.acquire_image().unwrap();
+        self.swapchain.wait_image(xr::Duration::INFINITE).unwrap();
+
+        self.renderer.render(swapchain_idx);
+
+        self.swapchain.release_image().unwrap();
+
+        Some(head_locked_quad(
+            &self.view_reference_space,
+            &self.swapchain,
+            xr::Vector3f {
+                x: 0.0,
+                y: 0.0,
+                z: -MENU_DISTANCE_M,
+            },
+            MENU_SIDE_M,
+        ))
+    }
+}
+
+// Head-locked quad layer shown while the face, eye and body data is blocked by the privacy setting
+pub struct PrivacyIndicatorOverlay<G: ClientGraphics> {
+    view_reference_space: xr::Space,
+    swapchain: xr::Swapchain<G>,
+    renderer: MenuRenderer,
+}
+
+impl<G: ClientGraphics> PrivacyIndicatorOverlay<G> {
+    pub fn new(xr_session: &xr::Session<G>, gfx_ctx: Rc<GraphicsContext>) -> Self {
+        let resolution = UVec2::ONE * MENU_RESOLUTION;
+
+        let view_reference_space = xr_session
+            .create_reference_space(xr::ReferenceSpaceType::VIEW, xr::Posef::IDENTITY)
+            .unwrap();
+
+        let swapchain =
+            graphics::create_swapchain(xr_session, &gfx_ctx, resolution, G::SDR_FORMAT, None);
+
+        let renderer =
+            MenuRenderer::new(gfx_ctx, resolution, swapchain.enumerate_images().unwrap());
+        renderer.update_text("Privacy mode\nFace, eye and body\ntracking not sent");
+
+        Self {
+            view_reference_space,
+            swapchain,
+            renderer,
+        }
+    }
+
+    pub fn render(&mut self) -> xr::CompositionLayerQuad<'_, G> {
+        let swapchain_idx = self.swapchain.acquire_image().unwrap();
+        self.swapchain.wait_image(xr::Duration::INFINITE).unwrap();
+
+        self.renderer.render(swapchain_idx);
+
+        self.swapchain.release_image().unwrap();
+
+        head_locked_quad(
+            &self.view_reference_space,
+            &self.swapchain,
+            PRIVACY_INDICATOR_POSITION,
+            PRIVACY_INDICATOR_SIDE_M,
+        )
+    }
+}
+
+fn head_locked_quad<'a, G: ClientGraphics>(
+    view_reference_space: &'a xr::Space,
+    swapchain: &'a xr::Swapchain<G>,
+    position: xr::Vector3f,
+    side_m: f32,
+) -> xr::CompositionLayerQuad<'a, G> {
+    xr::CompositionLayerQuad::new()
+        .layer_flags(xr::CompositionLayerFlags::BLEND_TEXTURE_SOURCE_ALPHA)
+        .space(view_reference_space)
+        .eye_visibility(xr::EyeVisibility::BOTH)
+        .sub_image(
+            xr::SwapchainSubImage::new()
+                .swapchain(swapchain)
+                .image_array_index(0)
+                .image_rect(xr::Rect2Di {
+                    offset: xr::Offset2Di { x: 0, y: 0 },
+                    extent: xr::Extent2Di {
+                        width: MENU_RESOLUTION as _,
+                        height: MENU_RESOLUTION as _,
+                    },
+                }),
+        )
+        .pose(xr::Posef {
+            orientation: xr::Quaternionf::IDENTITY,
+            position,
+        })
+        .size(xr::Extent2Df {
+            width: side_m,
+            height: side_m,
+        })
+}
@@ -0,0 +1,211 @@
+use alvr_client_core::MotionVectorsFrame;
+use alvr_common::{
+    Fov, ViewParams,
+    glam::{UVec2, Vec2, Vec3},
+};
+use alvr_packets::INVALID_MOTION_VECTOR;
+
+// Converts the block motion vectors of a view to the per pixel motion vectors of XR_FB_space_warp,
+// the motion in NDC from the reference frame to the frame, 4 floats per pixel. The runtime expects
+// only the motion of the objects: the head rotation between the two frames is removed, the head
+// translation is ignored. Blocks without a match have no motion. The rows are bottom-up, like the
+// GL swapchain images.
+pub fn to_space_warp(
+    frame: &MotionVectorsFrame,
+    view_idx: usize,
+    resolution: UVec2,
+    view: ViewParams,
+    reference_view: ViewParams,
+) -> Vec<f32> {
+    let view_resolution = frame.view_resolution.as_vec2();
+    let grid_size = frame.grid_size;
+    let view_offset = view_idx * (grid_size.x * grid_size.y) as usize;
+
+    // From the reference view to the view
+    let rotation = view.pose.orientation.inverse() * reference_view.pose.orientation;
+
+    let mut data = Vec::with_capacity((resolution.x * resolution.y * 4) as usize);
+    for y in (0..resolution.y).rev() {
+        for x in 0..resolution.x {
+            // In pixels of the motion vectors, top-down
+            let pixel =
+                (Vec2::new(x as f32, y as f32) + 0.5) * view_resolution / resolution.as_vec2();
+
+            let block = (pixel / frame.block_size as f32)
+                .as_uvec2()
+                .min(grid_size - 1);
+            let idx = view_offset + (block.y * grid_size.x + block.x) as usize;
+            let vector = (frame.data[idx * 2], frame.data[idx * 2 + 1]);
+
+            let motion = if vector.0 == INVALID_MOTION_VECTOR {
+                Vec2::ZERO
+            } else {
+                let reference_pixel = pixel + Vec2::new(vector.0 as f32, vector.1 as f32) / 4.0;
+                let direction =
+                    rotation * unproject(reference_view.fov, reference_pixel / view_resolution);
+
+                // Behind the view after the head rotation
+                if direction.z >= 0.0 {
+                    Vec2::ZERO
+                } else {
+                    to_ndc(pixel / view_resolution) - project(view.fov, direction)
+                }
+            };
+
+            data.extend([motion.x, motion.y, 0.0, 0.0]);
+        }
+    }
+
+    data
+}
+
+// uv is top-down in [0, 1], NDC is bottom-up in [-1, 1]
+fn to_ndc(uv: Vec2) -> Vec2 {
+    Vec2::new(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0)
+}
+
+// Direction in view space of the point at uv, top-down
+fn unproject(fov: Fov, uv: Vec2) -> Vec3 {
+    Vec3::new(
+        fov.left.tan() + uv.x * (fov.right.tan() - fov.left.tan()),
+        fov.up.tan() + uv.y * (fov.down.tan() - fov.up.tan()),
+        -1.0,
+    )
+}
+
+// NDC of a direction in view space, in front of the view
+fn project(fov: Fov, direction: Vec3) -> Vec2 {
+    let tangent = Vec2::new(direction.x, direction.y) / -direction.z;
+
+    Vec2::new(
+        2.0 * (tangent.x - fov.left.tan()) / (fov.right.tan() - fov.left.tan()) - 1.0,
+        2.0 * (tangent.y - fov.down.tan()) / (fov.up.tan() - fov.down.tan()) - 1.0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alvr_common::{Pose, glam::Quat};
+    use std::{f32::consts::FRAC_PI_4, time::Duration};
+
+    const EPSILON: f32 = 1e-4;
+
+    fn view(yaw: f32) -> ViewParams {
+        ViewParams {
+            pose: Pose {
+                orientation: Quat::from_rotation_y(yaw),
+                position: Vec3::ZERO,
+            },
+            fov: Fov {
+                left: -FRAC_PI_4,
+                right: FRAC_PI_4,
+                up: FRAC_PI_4,
+                down: -FRAC_PI_4,
+            },
+        }
+    }
+
+    // One 64x64 block per view
+    fn frame(left: (i16, i16), right: (i16, i16)) -> MotionVectorsFrame {
+        MotionVectorsFrame {
+            timestamp: Duration::from_millis(20),
+            reference_timestamp: Duration::from_millis(10),
+            view_resolution: UVec2::new(64, 64),
+            block_size: 64,
+            grid_size: UVec2::new(1, 1),
+            data: vec![left.0, left.1, right.0, right.1],
+        }
+    }
+
+    fn motion(data: &[f32]) -> Vec2 {
+        Vec2::new(data[0], data[1])
+    }
+
+    #[test]
+    fn ndc_round_trip() {
+        let fov = view(0.0).fov;
+        for uv in [Vec2::ZERO, Vec2::ONE, Vec2::new(0.25, 0.75)] {
+            let ndc = project(fov, unproject(fov, uv));
+            assert!(ndc.distance(to_ndc(uv)) < EPSILON, "{ndc}");
+        }
+    }
+
+    #[test]
+    fn object_motion_without_head_motion() {
+        // The block moved 8 pixels right and 4 pixels up since the reference frame
+        let data = to_space_warp(
+            &frame((-32, 16), (0, 0)),
+            0,
+            UVec2::new(2, 2),
+            view(0.0),
+            view(0.0),
+        );
+
+        assert_eq!(data.len(), 16);
+        for pixel in data.chunks_exact(4) {
+            assert!(motion(pixel).distance(Vec2::new(0.25, 0.125)) < EPSILON);
+        }
+    }
+
+    #[test]
+    fn views_are_separate() {
+        let data = to_space_warp(
+            &frame((-32, 16), (0, 0)),
+            1,
+            UVec2::new(2, 2),
+            view(0.0),
+            view(0.0),
+        );
+
+        assert!(data.iter().all(|value| value.abs() < EPSILON));
+    }
+
+    #[test]
+    fn head_rotation_is_removed() {
+        // Turning the head left moves the static scene right in the view
+        let yaw: f32 = 0.1;
+        let shift = (yaw.tan() * 32.0 * 4.0) as i16;
+        let data = to_space_warp(
+            &frame((-shift, 0), (0, 0)),
+            0,
+            UVec2::new(1, 1),
+            view(yaw),
+            view(0.0),
+        );
+
+        // The vector is rounded to quarter pixels
+        assert!(motion(&data).length() < 0.01, "{}", motion(&data));
+    }
+
+    #[test]
+    fn invalid_blocks_have_no_motion() {
+        let data = to_space_warp(
+            &frame((INVALID_MOTION_VECTOR, 0), (0, 0)),
+            0,
+            UVec2::new(1, 1),
+            view(0.0),
+            view(0.0),
+        );
+
+        assert_eq!(motion(&data), Vec2::ZERO);
+    }
+
+    #[test]
+    fn rows_are_bottom_up() {
+        // 2 blocks in a column, only the top one moves
+        let frame = MotionVectorsFrame {
+            timestamp: Duration::from_millis(20),
+            reference_timestamp: Duration::from_millis(10),
+            view_resolution: UVec2::new(32, 64),
+            block_size: 32,
+            grid_size: UVec2::new(1, 2),
+            data: vec![-32, 0, 0, 0, 0, 0, 0, 0],
+        };
+
+        let data = to_space_warp(&frame, 0, UVec2::new(1, 2), view(0.0), view(0.0));
+
+        assert!(motion(&data[..4]).length() < EPSILON);
+        assert!(motion(&data[4..]).x > 0.0);
+    }
+}
@@ -1,17 +1,27 @@
-use crate::extra_extensions::{PassthroughFB, PassthroughHTC};
-use alvr_common::anyhow::{Result, bail};
+use crate::extra_extensions::{PassthroughColorMapFB, PassthroughFB, PassthroughHTC};
+use alvr_common::{
+    anyhow::{Result, bail},
+    warn,
+};
+use alvr_session::{PassthroughColorLutConfig, PassthroughColorMap, PassthroughStyleConfig};
 use alvr_system_info::Platform;
 use openxr::{self as xr};
 use std::{marker::PhantomData, ops::Deref, ptr};
 
-pub struct PassthroughLayer<'a> {
+const COLOR_LUT_RESOLUTION: u32 = 16;
+
+pub struct PassthroughLayer<'a, G> {
     handle_fb: Option<PassthroughFB>,
     handle_htc: Option<PassthroughHTC>,
-    _marker: PhantomData<&'a ()>,
+    _marker: PhantomData<&'a G>,
 }
 
-impl PassthroughLayer<'_> {
-    pub fn new(session: &xr::Session<xr::OpenGlEs>, platform: Platform) -> Result<Self> {
+impl<G> PassthroughLayer<'_, G> {
+    pub fn new(
+        session: &xr::Session<G>,
+        platform: Platform,
+        style: Option<&PassthroughStyleConfig>,
+    ) -> Result<Self> {
         let mut handle_fb = None;
         let mut handle_htc = None;
 
@@ -24,16 +34,114 @@ impl PassthroughLayer<'_> {
             bail!("No passthrough extension available");
         };
 
-        Ok(Self {
+        let mut layer = Self {
             handle_fb,
             handle_htc,
             _marker: PhantomData,
-        })
+        };
+        if style.is_some() {
+            layer.set_style(style);
+        }
+
+        Ok(layer)
+    }
+
+    // None restores the default style. Styling is only supported by the FB extension, otherwise the
+    // passthrough is left unchanged.
+    pub fn set_style(&mut self, style: Option<&PassthroughStyleConfig>) {
+        let Some(handle) = &mut self.handle_fb else {
+            if style.is_some() {
+                warn!("Passthrough styling is not supported on this headset");
+            }
+
+            return;
+        };
+
+        let Some(style) = style else {
+            handle
+                .set_style(
+                    1.0,
+                    xr::sys::Color4f::default(),
+                    PassthroughColorMapFB::None,
+                )
+                .ok();
+
+            return;
+        };
+
+        let edge_color = style
+            .edge_color
+            .as_option()
+            .map(|color| xr::sys::Color4f {
+                r: color.red,
+                g: color.green,
+                b: color.blue,
+                a: color.alpha,
+            })
+            .unwrap_or_default();
+
+        let color_lut_data;
+        let color_map = match style.color_map.as_option() {
+            Some(PassthroughColorMap::BrightnessContrastSaturation {
+                brightness,
+                contrast,
+                saturation,
+            }) => PassthroughColorMapFB::BrightnessContrastSaturation {
+                brightness: *brightness,
+                contrast: *contrast,
+                saturation: *saturation,
+            },
+            Some(PassthroughColorMap::ColorLut(config)) if handle.supports_color_lut() => {
+                color_lut_data = color_lut(config, COLOR_LUT_RESOLUTION);
+
+                PassthroughColorMapFB::ColorLut {
+                    data: &color_lut_data,
+                    resolution: COLOR_LUT_RESOLUTION,
+                    weight: config.weight,
+                }
+            }
+            Some(PassthroughColorMap::ColorLut(_)) => {
+                warn!("Passthrough color LUT is not supported on this headset");
+
+                PassthroughColorMapFB::None
+            }
+            None => PassthroughColorMapFB::None,
+        };
+
+        if let Err(e) = handle.set_style(style.opacity, edge_color, color_map) {
+            warn!("Failed to set passthrough style: {e}");
+        }
     }
 }
 
-impl<'a> Deref for PassthroughLayer<'a> {
-    type Target = xr::CompositionLayerBase<'a, xr::OpenGlEs>;
+// Generates an RGB LUT that applies gamma, then tint, then posterization
+fn color_lut(config: &PassthroughColorLutConfig, resolution: u32) -> Vec<u8> {
+    let tint = [config.tint_red, config.tint_green, config.tint_blue];
+    let max_index = (resolution - 1) as f32;
+
+    let mut data = Vec::with_capacity((resolution * resolution * resolution * 3) as usize);
+    for blue in 0..resolution {
+        for green in 0..resolution {
+            for red in 0..resolution {
+                for (channel, input) in [red, green, blue].into_iter().enumerate() {
+                    let mut value = (input as f32 / max_index).powf(config.gamma) * tint[channel];
+
+                    if let Some(levels) = config.posterize_levels.as_option() {
+                        let steps = u32::max(*levels, 2) as f32 - 1.0;
+                        value = (value * steps).round() / steps;
+                    }
+
+                    data.push((value.clamp(0.0, 1.0) * 255.0).round() as u8);
+                }
+            }
+        }
+    }
+
+    data
+}
+
+impl<'a, G: xr::Graphics> Deref for PassthroughLayer<'a, G> {
+    type Target = xr::CompositionLayerBase<'a, G>;
 
     fn deref(&self) -> &Self::Target {
         if let Some(handle) = &self.handle_fb {
@@ -0,0 +1,150 @@
+use alvr_client_core::ClientCoreContext;
+use alvr_common::{glam::Vec2, parking_lot::Mutex};
+use alvr_packets::{MouseButton, PeripheralInput};
+use android_activity::{
+    AndroidApp, InputStatus,
+    input::{Axis, ButtonState, InputEvent, KeyAction, KeyMapChar, MotionAction, Source},
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Weak},
+};
+
+const MOUSE_BUTTONS: [MouseButton; 5] = [
+    MouseButton::Left,
+    MouseButton::Right,
+    MouseButton::Middle,
+    MouseButton::Back,
+    MouseButton::Forward,
+];
+
+// Input events are received on the Android main thread, while the core context is recreated by the
+// rendering thread for each OpenXR session
+static CORE_CONTEXT: Mutex<Weak<ClientCoreContext>> = Mutex::new(Weak::new());
+
+pub fn set_core_context(context: &Arc<ClientCoreContext>) {
+    *CORE_CONTEXT.lock() = Arc::downgrade(context);
+}
+
+fn pressed_buttons(state: ButtonState) -> [bool; 5] {
+    [
+        state.primary(),
+        state.secondary(),
+        state.teriary(),
+        state.back(),
+        state.forward(),
+    ]
+}
+
+// Converts the events of the Bluetooth keyboard and mouse paired to the headset. Events of the
+// headset buttons and of the XR controllers are left to the system.
+#[derive(Default)]
+pub struct PeripheralInputSource {
+    // Device ID -> is external. The query goes through JNI so it is done once per device
+    external_devices: HashMap<i32, bool>,
+    last_pointer_position: Option<Vec2>,
+    last_pressed_buttons: [bool; 5],
+}
+
+impl PeripheralInputSource {
+    fn is_external(&mut self, device_id: i32) -> bool {
+        *self
+            .external_devices
+            .entry(device_id)
+            .or_insert_with(|| alvr_system_info::is_external_input_device(device_id))
+    }
+
+    pub fn handle_event(&mut self, app: &AndroidApp, event: &InputEvent) -> InputStatus {
+        let Some(context) = CORE_CONTEXT.lock().upgrade() else {
+            return InputStatus::Unhandled;
+        };
+
+        let mut inputs = vec![];
+        match event {
+            InputEvent::KeyEvent(key)
+                if matches!(key.source(), Source::Keyboard)
+                    && self.is_external(key.device_id()) =>
+            {
+                let pressed = match key.action() {
+                    KeyAction::Down => true,
+                    KeyAction::Up => false,
+                    _ => return InputStatus::Unhandled,
+                };
+
+                let unicode = app
+                    .device_key_character_map(key.device_id())
+                    .ok()
+                    .and_then(|map| map.get(key.key_code(), key.meta_state()).ok())
+                    .and_then(|key_char| match key_char {
+                        KeyMapChar::Unicode(unicode) => Some(unicode),
+                        _ => None,
+                    });
+
+                inputs.push(PeripheralInput::Key {
+                    scancode: key.scan_code() as u16,
+                    unicode,
+                    pressed,
+                });
+            }
+            InputEvent::MotionEvent(motion)
+                if matches!(motion.source(), Source::Mouse | Source::MouseRelative)
+                    && self.is_external(motion.device_id()) =>
+            {
+                let pointer = motion.pointer_at_index(0);
+                let position = Vec2::new(pointer.x(), pointer.y());
+
+                match motion.action() {
+                    MotionAction::Move | MotionAction::HoverMove => {
+                        // Relative axes are not clamped to the screen borders, but not all mice
+                        // report them
+                        let relative = Vec2::new(
+                            pointer.axis_value(Axis::RelativeX),
+                            pointer.axis_value(Axis::RelativeY),
+                        );
+                        let delta = if relative != Vec2::ZERO {
+                            relative
+                        } else {
+                            self.last_pointer_position
+                                .map(|last| position - last)
+                                .unwrap_or(Vec2::ZERO)
+                        };
+
+                        if delta != Vec2::ZERO {
+                            inputs.push(PeripheralInput::MouseMove(delta));
+                        }
+                    }
+                    MotionAction::Scroll => inputs.push(PeripheralInput::MouseScroll(Vec2::new(
+                        pointer.axis_value(Axis::Hscroll),
+                        pointer.axis_value(Axis::Vscroll),
+                    ))),
+                    _ => (),
+                }
+                self.last_pointer_position = Some(position);
+
+                let pressed_buttons = pressed_buttons(motion.button_state());
+                for (idx, button) in MOUSE_BUTTONS.into_iter().enumerate() {
+                    if pressed_buttons[idx] != self.last_pressed_buttons[idx] {
+                        inputs.push(PeripheralInput::MouseButton {
+                            button,
+                            pressed: pressed_buttons[idx],
+                        });
+                    }
+                }
+                self.last_pressed_buttons = pressed_buttons;
+            }
+            _ => return InputStatus::Unhandled,
+        }
+
+        // Events that don't produce any input are left to the system
+        let mut handled = !inputs.is_empty();
+        for input in inputs {
+            handled &= context.send_peripheral_input(input);
+        }
+
+        if handled {
+            InputStatus::Handled
+        } else {
+            InputStatus::Unhandled
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use alvr_common::{Pose, glam::Quat};
+use std::{collections::VecDeque, time::Duration};
+
+// Limits the error when the tracking is lost for a long time
+const MAX_EXTRAPOLATION: Duration = Duration::from_millis(100);
+
+// Ring buffer of timestamped poses. Queries between two samples are interpolated, queries past the
+// newest sample are extrapolated from the two newest samples.
+pub struct PoseHistory {
+    samples: VecDeque<(Duration, Pose)>,
+    capacity: usize,
+}
+
+impl PoseHistory {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(2);
+
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    // Samples older than the newest one are discarded
+    pub fn push(&mut self, timestamp: Duration, pose: Pose) {
+        if self
+            .samples
+            .back()
+            .is_some_and(|(newest, _)| *newest >= timestamp)
+        {
+            return;
+        }
+
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((timestamp, pose));
+    }
+
+    // Returns None for timestamps older than the buffer
+    pub fn pose_at(&self, timestamp: Duration) -> Option<Pose> {
+        let idx = self.samples.partition_point(|(t, _)| *t < timestamp);
+
+        if let Some(&(t1, pose1)) = self.samples.get(idx) {
+            if t1 == timestamp {
+                return Some(pose1);
+            }
+
+            let (t0, pose0) = *self.samples.get(idx.checked_sub(1)?)?;
+            let factor = (timestamp - t0).as_secs_f32() / (t1 - t0).as_secs_f32();
+
+            Some(pose0.lerp(pose1, factor))
+        } else {
+            let &(t1, pose1) = self.samples.back()?;
+            let Some(&(t0, pose0)) = self.samples.len().checked_sub(2).map(|i| &self.samples[i])
+            else {
+                return Some(pose1);
+            };
+
+            let timestamp = Duration::min(timestamp, t1 + MAX_EXTRAPOLATION);
+            let factor = (timestamp - t1).as_secs_f32() / (t1 - t0).as_secs_f32();
+
+            // Constant angular and linear velocity. q and -q are the same rotation, the one with a
+            // positive w turns by less than half a turn.
+            let mut delta = pose1.orientation * pose0.orientation.conjugate();
+            if delta.w < 0.0 {
+                delta = -delta;
+            }
+            let (axis, angle) = delta.to_axis_angle();
+            Some(Pose {
+                orientation: (Quat::from_axis_angle(axis, angle * factor) * pose1.orientation)
+                    .normalize(),
+                position: pose1.position + (pose1.position - pose0.position) * factor,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alvr_common::{assert_pose_eq, glam::Vec3};
+    use std::f32::consts::FRAC_PI_2;
+
+    fn ms(value: u64) -> Duration {
+        Duration::from_millis(value)
+    }
+
+    fn yaw_pose(angle: f32, x: f32) -> Pose {
+        Pose {
+            orientation: Quat::from_rotation_y(angle),
+            position: Vec3::new(x, 0.0, 0.0),
+        }
+    }
+
+    #[test]
+    fn exact_timestamps_return_the_samples() {
+        let mut history = PoseHistory::new(4);
+        history.push(ms(10), yaw_pose(0.0, 0.0));
+        history.push(ms(20), yaw_pose(0.5, 1.0));
+
+        assert_pose_eq(history.pose_at(ms(10)).unwrap(), yaw_pose(0.0, 0.0));
+        assert_pose_eq(history.pose_at(ms(20)).unwrap(), yaw_pose(0.5, 1.0));
+    }
+
+    #[test]
+    fn interpolates_between_samples() {
+        let mut history = PoseHistory::new(4);
+        history.push(ms(10), yaw_pose(0.0, 0.0));
+        history.push(ms(20), yaw_pose(FRAC_PI_2, 1.0));
+        history.push(ms(40), yaw_pose(FRAC_PI_2, 3.0));
+
+        assert_pose_eq(
+            history.pose_at(ms(15)).unwrap(),
+            yaw_pose(FRAC_PI_2 / 2.0, 0.5),
+        );
+        assert_pose_eq(history.pose_at(ms(35)).unwrap(), yaw_pose(FRAC_PI_2, 2.5));
+    }
+
+    #[test]
+    fn interpolation_takes_the_shortest_arc() {
+        let mut history = PoseHistory::new(4);
+        history.push(ms(0), yaw_pose(0.0, 0.0));
+        // Same rotation as yaw_pose(0.2, 0.0), in the other hemisphere
+        history.push(
+            ms(10),
+            Pose {
+                orientation: -Quat::from_rotation_y(0.2),
+                position: Vec3::ZERO,
+            },
+        );
+
+        assert_pose_eq(history.pose_at(ms(5)).unwrap(), yaw_pose(0.1, 0.0));
+    }
+
+    #[test]
+    fn extrapolates_with_constant_velocity() {
+        let mut history = PoseHistory::new(4);
+        history.push(ms(0), yaw_pose(0.0, 0.0));
+        history.push(ms(10), yaw_pose(0.1, 1.0));
+
+        assert_pose_eq(history.pose_at(ms(25)).unwrap(), yaw_pose(0.25, 2.5));
+    }
+
+    #[test]
+    fn extrapolation_takes_the_shortest_arc() {
+        let mut history = PoseHistory::new(4);
+        history.push(ms(0), yaw_pose(0.0, 0.0));
+        history.push(
+            ms(10),
+            Pose {
+                orientation: -Quat::from_rotation_y(0.1),
+                position: Vec3::ZERO,
+            },
+        );
+
+        assert_pose_eq(history.pose_at(ms(20)).unwrap(), yaw_pose(0.2, 0.0));
+    }
+
+    #[test]
+    fn extrapolation_is_clamped() {
+        let mut history = PoseHistory::new(4);
+        history.push(ms(0), yaw_pose(0.0, 0.0));
+        history.push(ms(10), yaw_pose(0.01, 0.1));
+
+        let limit = history.pose_at(ms(10) + MAX_EXTRAPOLATION).unwrap();
+        assert_pose_eq(limit, yaw_pose(0.11, 1.1));
+        assert_pose_eq(history.pose_at(ms(1000)).unwrap(), limit);
+    }
+
+    #[test]
+    fn single_sample_is_held() {
+        let mut history = PoseHistory::new(4);
+        assert!(history.pose_at(ms(0)).is_none());
+
+        history.push(ms(10), yaw_pose(0.3, 1.0));
+        assert_pose_eq(history.pose_at(ms(50)).unwrap(), yaw_pose(0.3, 1.0));
+    }
+
+    #[test]
+    fn old_timestamps_are_rejected() {
+        let mut history = PoseHistory::new(2);
+        history.push(ms(10), yaw_pose(0.0, 0.0));
+        history.push(ms(20), yaw_pose(0.0, 1.0));
+        history.push(ms(30), yaw_pose(0.0, 2.0));
+
+        // The first sample was evicted
+        assert!(history.pose_at(ms(15)).is_none());
+        assert_pose_eq(history.pose_at(ms(25)).unwrap(), yaw_pose(0.0, 1.5));
+    }
+
+    #[test]
+    fn out_of_order_samples_are_discarded() {
+        let mut history = PoseHistory::new(4);
+        history.push(ms(10), yaw_pose(0.0, 0.0));
+        history.push(ms(20), yaw_pose(0.0, 1.0));
+        history.push(ms(15), yaw_pose(0.0, 5.0));
+        history.push(ms(20), yaw_pose(0.0, 5.0));
+
+        assert_pose_eq(history.pose_at(ms(15)).unwrap(), yaw_pose(0.0, 0.5));
+        assert_pose_eq(history.pose_at(ms(20)).unwrap(), yaw_pose(0.0, 1.0));
+    }
+}
@@ -1,24 +1,38 @@
 use crate::{
-    graphics::{self, ProjectionLayerAlphaConfig, ProjectionLayerBuilder},
-    interaction::{self, InteractionContext, InteractionSourcesConfig},
+    extra_extensions::{MarkerFilter, RoomBoundaryFB},
+    gestures::GestureRecognizer,
+    graphics::{
+        self, ClientGraphics, ProjectionLayerAlphaConfig, ProjectionLayerBuilder,
+        ProjectionLayerDepthConfig,
+    },
+    interaction::{self, InputSourceSwitch, InteractionContext, InteractionSourcesConfig},
+    menu::InHeadsetMenu,
+    motion_vectors,
+    pose_history::PoseHistory,
 };
 use alvr_client_core::{
-    ClientCoreContext,
+    ClientCoreContext, DepthFrame,
     video_decoder::{self, VideoDecoderConfig, VideoDecoderSource},
 };
 use alvr_common::{
-    DETACHED_CONTROLLER_LEFT_ID, DETACHED_CONTROLLER_RIGHT_ID, HAND_LEFT_ID, HAND_RIGHT_ID,
+    DETACHED_CONTROLLER_LEFT_ID, DETACHED_CONTROLLER_RIGHT_ID, Fov, HAND_LEFT_ID, HAND_RIGHT_ID,
     HEAD_ID, Pose, RelaxedAtomic, ViewParams,
     anyhow::Result,
-    error,
-    glam::{UVec2, Vec2},
-    parking_lot::RwLock,
+    debug, error,
+    glam::{Quat, UVec2, Vec2, Vec3},
+    info,
+    parking_lot::{Mutex, RwLock},
+    warn,
 };
 use alvr_graphics::{GraphicsContext, StreamRenderer, StreamViewParams};
-use alvr_packets::{RealTimeConfig, StreamConfig, TrackingData};
+use alvr_packets::{FaceData, RealTimeConfig, StreamConfig, TrackingData, TrackingSpace};
 use alvr_session::{
-    ClientsideFoveationConfig, ClientsideFoveationMode, ClientsidePostProcessingConfig, CodecType,
-    FoveatedEncodingConfig, MediacodecProperty, PassthroughMode, UpscalingConfig,
+    ChromaSubsampling, ClientSwapchainFormat, ClientsideFoveationConfig, ClientsideFoveationMode,
+    ClientsidePostProcessingConfig, ClientsidePostProcessingSharpeningMode, CodecType, ColorRange,
+    FoveatedEncodingConfig, FoveatedEncodingMode, GestureAction, GestureConfig,
+    InputSourceSwitchConfig, MarkerOriginConfig, MarkerOriginMode, MediacodecProperty,
+    PassthroughMode, StreamAspectRatio, TransferFunction, UpscalingConfig, ViewOverrideConfig,
+    settings_schema::Switch,
 };
 use alvr_system_info::Platform;
 use openxr as xr;
@@ -31,22 +45,47 @@ use std::{
 };
 
 const DECODER_MAX_TIMEOUT_MULTIPLIER: f32 = 0.8;
+const MIN_IPD_OVERRIDE_MM: f32 = 50.0;
+const MAX_IPD_OVERRIDE_MM: f32 = 80.0;
+const MAX_CONVERGENCE_DEG: f32 = 2.0;
+// Marker tracking is much slower than the input loop, there is no point in polling it every time
+const MARKER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+// Moving the tracking origin restarts the input loop, ignore small changes caused by jitter
+const MARKER_ORIGIN_MIN_DISTANCE: f32 = 0.01;
+const MARKER_ORIGIN_MIN_ANGLE_DEG: f32 = 1.0;
 
 pub struct ParsedStreamConfig {
     pub view_resolution: UVec2,
     pub refresh_rate_hint: f32,
     pub encoding_gamma: f32,
     pub enable_hdr: bool,
+    pub color_range: ColorRange,
+    pub transfer_function: TransferFunction,
     pub passthrough: Option<PassthroughMode>,
     pub foveated_encoding_config: Option<FoveatedEncodingConfig>,
     pub clientside_foveation_config: Option<ClientsideFoveationConfig>,
     pub clientside_post_processing: Option<ClientsidePostProcessingConfig>,
     pub upscaling: Option<UpscalingConfig>,
+    pub letterbox: bool,
+    pub swapchain_format: ClientSwapchainFormat,
+    pub chroma_subsampling: ChromaSubsampling,
     pub force_software_decoder: bool,
     pub max_buffering_frames: f32,
     pub buffering_history_weight: f32,
+    pub max_queued_frames: usize,
     pub decoder_options: Vec<(String, MediacodecProperty)>,
     pub interaction_sources: InteractionSourcesConfig,
+    pub view_override: Option<ViewOverrideConfig>,
+    pub marker_origin: Option<MarkerOriginConfig>,
+    pub use_local_floor_space: bool,
+    pub latency_test_flash_duration: Option<u32>,
+    pub frame_extrapolation: bool,
+    pub pose_history_size: usize,
+    pub input_source_switch: Option<InputSourceSwitchConfig>,
+    pub hand_interaction_profile: bool,
+    pub gestures: Vec<GestureConfig>,
+    pub enable_depth_stream: bool,
+    pub enable_space_warp: bool,
 }
 
 impl ParsedStreamConfig {
@@ -56,12 +95,40 @@ impl ParsedStreamConfig {
             refresh_rate_hint: config.negotiated_config.refresh_rate_hint,
             encoding_gamma: config.negotiated_config.encoding_gamma,
             enable_hdr: config.negotiated_config.enable_hdr,
+            color_range: config.settings.video.encoder_config.color_range,
+            transfer_function: config.settings.video.encoder_config.transfer_function,
             passthrough: config.settings.video.passthrough.as_option().cloned(),
             foveated_encoding_config: config
                 .negotiated_config
                 .enable_foveated_encoding
                 .then(|| config.settings.video.foveated_encoding.as_option().cloned())
-                .flatten(),
+                .flatten()
+                .map(|foveated_encoding| {
+                    let ext = config.negotiated_config.ext().ok();
+
+                    // The server falls back to two zones if the radial mode was not negotiated
+                    let foveated_encoding = if ext
+                        .as_ref()
+                        .is_some_and(|ext| ext.enable_radial_foveated_encoding)
+                    {
+                        foveated_encoding
+                    } else {
+                        FoveatedEncodingConfig {
+                            mode: FoveatedEncodingMode::TwoZone,
+                            ..foveated_encoding
+                        }
+                    };
+
+                    // Older servers mirror the parameters of the settings for the right eye
+                    if let Some(eyes) = ext.and_then(|ext| ext.foveated_encoding_eyes) {
+                        foveated_encoding.with_eyes(eyes)
+                    } else {
+                        FoveatedEncodingConfig {
+                            right_eye: Switch::Disabled,
+                            ..foveated_encoding
+                        }
+                    }
+                }),
             clientside_foveation_config: config
                 .settings
                 .video
@@ -75,40 +142,138 @@ impl ParsedStreamConfig {
                 .as_option()
                 .cloned(),
             upscaling: config.settings.video.upscaling.as_option().cloned(),
+            letterbox: config.settings.video.aspect_ratio == StreamAspectRatio::Letterbox,
+            swapchain_format: config.settings.video.client_swapchain_format,
+            chroma_subsampling: config
+                .negotiated_config
+                .ext()
+                .map(|ext| ext.chroma_subsampling)
+                .unwrap_or_default(),
             force_software_decoder: config.settings.video.force_software_decoder,
             max_buffering_frames: config.settings.video.max_buffering_frames,
             buffering_history_weight: config.settings.video.buffering_history_weight,
+            max_queued_frames: config
+                .settings
+                .video
+                .max_queued_encoded_frames
+                .map(|frames| frames as usize)
+                .unwrap_or_else(video_decoder::default_max_queued_frames),
             decoder_options: config.settings.video.mediacodec_extra_options.clone(),
             interaction_sources: InteractionSourcesConfig::new(config),
+            view_override: config.settings.headset.view_override.as_option().cloned(),
+            marker_origin: config.settings.headset.marker_origin.as_option().cloned(),
+            use_local_floor_space: config.settings.headset.use_local_floor_space,
+            latency_test_flash_duration: config
+                .settings
+                .extra
+                .photodiode_latency_test
+                .as_option()
+                .map(|config| config.flash_duration),
+            frame_extrapolation: config.settings.video.client_frame_extrapolation,
+            pose_history_size: config.settings.headset.pose_history_size,
+            input_source_switch: config
+                .settings
+                .headset
+                .controllers
+                .as_option()
+                .and_then(|c| c.input_source_switch.as_option())
+                .cloned(),
+            hand_interaction_profile: config
+                .settings
+                .headset
+                .controllers
+                .as_option()
+                .is_some_and(|c| c.hand_interaction_profile),
+            gestures: config.settings.headset.gestures.clone(),
+            enable_depth_stream: config
+                .negotiated_config
+                .ext()
+                .is_ok_and(|ext| ext.enable_depth_stream),
+            enable_space_warp: config
+                .negotiated_config
+                .ext()
+                .is_ok_and(|ext| ext.enable_space_warp),
+        }
+    }
+
+    // The marker chosen in the lobby is used only if the marker origin setting is disabled. Its
+    // pose is sent to the server like in the server marker origin mode.
+    pub fn set_lobby_marker_origin(&mut self, marker_code: String) {
+        if self.marker_origin.is_none() {
+            self.interaction_sources.marker_filter =
+                Some(MarkerFilter::Codes(vec![marker_code.clone()]));
+            self.marker_origin = Some(MarkerOriginConfig {
+                marker_code,
+                mode: MarkerOriginMode::Server,
+                follow: Switch::Disabled,
+                remember_pose: false,
+            });
         }
     }
 }
 
-pub struct StreamContext {
+struct DepthSwapchains<G: ClientGraphics> {
+    resolution: UVec2,
+    depth: [xr::Swapchain<G>; 2],
+    depth_images: [Vec<G::SwapchainImage>; 2],
+    // Only with space warp, same resolution as the depth
+    motion_vectors: Option<[xr::Swapchain<G>; 2]>,
+    motion_vector_images: Option<[Vec<G::SwapchainImage>; 2]>,
+}
+
+// Shared with the input thread, replaced when the reference space changes
+#[derive(Clone)]
+struct InputSpaces {
+    tracking: Arc<xr::Space>,
+    view: Arc<xr::Space>,
+    tracking_origin: Pose,
+}
+
+pub struct StreamContext<G: ClientGraphics> {
     core_context: Arc<ClientCoreContext>,
-    xr_session: xr::Session<xr::OpenGlEs>,
+    xr_session: xr::Session<G>,
     interaction_context: Arc<RwLock<InteractionContext>>,
-    stage_reference_space: Arc<xr::Space>,
+    tracking_space: TrackingSpace,
+    tracking_reference_space: Arc<xr::Space>,
     view_reference_space: Arc<xr::Space>,
-    swapchains: [xr::Swapchain<xr::OpenGlEs>; 2],
+    swapchains: [xr::Swapchain<G>; 2],
+    // Created when the first depth frame is received, with its resolution
+    depth_swapchains: Option<DepthSwapchains<G>>,
     last_good_view_params: [ViewParams; 2],
+    // Recent headset views, used when the runtime fails to locate them at the display time and
+    // when the view params of a frame are not known anymore
+    headset_view_history: [PoseHistory; 2],
+    last_headset_fovs: [Fov; 2],
+    // The staging texture holds a decoded frame that can be reprojected
+    has_last_frame: bool,
+    // Transforms from the overridden views back to the headset views, relative to each view
+    view_corrections: Arc<Mutex<[Pose; 2]>>,
+    // Origin of the tracking space used for tracking and rendering, moved by the co-location marker
+    tracking_origin: Pose,
+    pending_tracking_origin: Arc<Mutex<Option<Pose>>>,
+    input_spaces: Arc<RwLock<InputSpaces>>,
     input_thread: Option<JoinHandle<()>>,
     input_thread_running: Arc<RelaxedAtomic>,
     config: ParsedStreamConfig,
     target_view_resolution: UVec2,
+    gfx_ctx: Rc<GraphicsContext>,
     renderer: StreamRenderer,
     decoder: Option<(VideoDecoderConfig, VideoDecoderSource)>,
     use_custom_reprojection: bool,
+    latency_test_frames_left: u32,
+    in_headset_menu: Option<Arc<Mutex<InHeadsetMenu>>>,
+    room_boundary: Option<RoomBoundaryFB>,
 }
 
-impl StreamContext {
+impl<G: ClientGraphics> StreamContext<G> {
     pub fn new(
         core_ctx: Arc<ClientCoreContext>,
-        xr_session: xr::Session<xr::OpenGlEs>,
+        xr_session: xr::Session<G>,
         gfx_ctx: Rc<GraphicsContext>,
         interaction_ctx: Arc<RwLock<InteractionContext>>,
         config: ParsedStreamConfig,
-    ) -> StreamContext {
+        in_headset_menu: Option<Arc<Mutex<InHeadsetMenu>>>,
+    ) -> Self {
         interaction_ctx
             .write()
             .select_sources(&config.interaction_sources);
@@ -154,7 +319,34 @@ impl StreamContext {
             config.view_resolution,
             &config.upscaling,
         );
-        let format = graphics::swapchain_format(&gfx_ctx, &xr_session, config.enable_hdr);
+        let format = graphics::swapchain_format(
+            &gfx_ctx,
+            &xr_session,
+            config.enable_hdr,
+            config.swapchain_format,
+        );
+        let is_srgb_format = gfx_ctx.swapchain_format_to_wgpu(format).is_srgb();
+        if config.swapchain_format == ClientSwapchainFormat::Srgb
+            && !config.enable_hdr
+            && !is_srgb_format
+        {
+            warn!("The runtime does not support sRGB swapchains, using a linear one");
+        }
+
+        // The decoded frames are sRGB encoded. They are converted to linear light for sRGB
+        // swapchains, which encode them again on write, and for the runtimes that treat linear
+        // swapchains as actual linear light. The HDR float16 swapchain keeps the per headset
+        // behavior.
+        let auto_srgb_correction = core_ctx.platform() != Platform::Lynx
+            && !((core_ctx.platform().is_pico()
+                || (core_ctx.platform() == Platform::SamsungGalaxyXR))
+                && config.enable_hdr);
+        let enable_srgb_correction = match config.swapchain_format {
+            _ if config.enable_hdr => auto_srgb_correction,
+            ClientSwapchainFormat::Auto => auto_srgb_correction,
+            ClientSwapchainFormat::Srgb => true,
+            ClientSwapchainFormat::Linear => is_srgb_format,
+        };
 
         let swapchains = [
             graphics::create_swapchain(
@@ -174,33 +366,31 @@ impl StreamContext {
         ];
 
         let renderer = StreamRenderer::new(
-            gfx_ctx,
+            Rc::clone(&gfx_ctx),
             config.view_resolution,
             target_view_resolution,
             [
-                swapchains[0]
-                    .enumerate_images()
-                    .unwrap()
-                    .iter()
-                    .map(|i| *i as _)
-                    .collect(),
-                swapchains[1]
-                    .enumerate_images()
-                    .unwrap()
-                    .iter()
-                    .map(|i| *i as _)
-                    .collect(),
+                swapchains[0].enumerate_images().unwrap(),
+                swapchains[1].enumerate_images().unwrap(),
             ],
             format,
             config.foveated_encoding_config.clone(),
-            core_ctx.platform() != Platform::Lynx
-                && !((core_ctx.platform().is_pico()
-                    || (core_ctx.platform() == Platform::SamsungGalaxyXR))
-                    && config.enable_hdr),
+            enable_srgb_correction,
+            config.transfer_function,
+            // With limited range the decoder conversion is already correct
             // TODO: Find a driver heuristic for the limited range bug instead?
-            core_ctx.platform() != Platform::SamsungGalaxyXR && !config.enable_hdr,
+            config.color_range == ColorRange::Full
+                && core_ctx.platform() != Platform::SamsungGalaxyXR
+                && !config.enable_hdr,
             config.encoding_gamma,
             config.upscaling.clone(),
+            config.letterbox,
+        );
+        core_ctx.send_foveated_encoding_applied(
+            config
+                .foveated_encoding_config
+                .as_ref()
+                .map(FoveatedEncodingConfig::eyes),
         );
 
         {
@@ -219,34 +409,102 @@ impl StreamContext {
 
         let input_thread_running = Arc::new(RelaxedAtomic::new(false));
 
-        let stage_reference_space = Arc::new(interaction::get_reference_space(
+        let tracking_space =
+            interaction::select_tracking_space(&xr_session, config.use_local_floor_space);
+        info!("Tracking space: {tracking_space:?}");
+
+        let tracking_reference_space = Arc::new(interaction::get_reference_space(
             &xr_session,
-            xr::ReferenceSpaceType::STAGE,
+            interaction::tracking_space_type(tracking_space),
         ));
         let view_reference_space = Arc::new(interaction::get_reference_space(
             &xr_session,
             xr::ReferenceSpaceType::VIEW,
         ));
 
+        let input_spaces = Arc::new(RwLock::new(InputSpaces {
+            tracking: Arc::clone(&tracking_reference_space),
+            view: Arc::clone(&view_reference_space),
+            tracking_origin: Pose::IDENTITY,
+        }));
+
         let mut this = StreamContext {
             use_custom_reprojection: core_ctx.platform().is_yvr(),
             core_context: core_ctx,
             xr_session,
             interaction_context: interaction_ctx,
-            stage_reference_space,
+            tracking_space,
+            tracking_reference_space,
             view_reference_space,
             swapchains,
+            depth_swapchains: None,
             last_good_view_params: [ViewParams::DUMMY; 2],
+            headset_view_history: [
+                PoseHistory::new(config.pose_history_size),
+                PoseHistory::new(config.pose_history_size),
+            ],
+            last_headset_fovs: [crate::from_xr_fov(crate::default_view().fov); 2],
+            has_last_frame: false,
+            view_corrections: Arc::new(Mutex::new([Pose::IDENTITY; 2])),
+            tracking_origin: Pose::IDENTITY,
+            pending_tracking_origin: Arc::new(Mutex::new(None)),
+            input_spaces,
             input_thread: None,
             input_thread_running,
             config,
             target_view_resolution,
+            gfx_ctx,
             renderer,
             decoder: None,
+            latency_test_frames_left: 0,
+            in_headset_menu,
+            room_boundary: None,
         };
 
+        #[cfg(target_os = "android")]
+        alvr_system_info::try_get_permission(crate::extra_extensions::SCENE_PERMISSION);
+        this.room_boundary = RoomBoundaryFB::new(&this.xr_session).ok();
+
         this.update_reference_space();
 
+        this.input_thread_running.set(true);
+        this.input_thread = Some(thread::spawn({
+            let core_ctx = Arc::clone(&this.core_context);
+            let xr_session = this.xr_session.clone().into_any_graphics();
+            let interaction_ctx = Arc::clone(&this.interaction_context);
+            let tracking_space = this.tracking_space;
+            let input_spaces = Arc::clone(&this.input_spaces);
+            let refresh_rate = this.config.refresh_rate_hint;
+            let view_override = this.config.view_override.clone();
+            let marker_origin = this.config.marker_origin.clone();
+            let input_source_switch = this.config.input_source_switch.clone();
+            let hand_interaction_profile = this.config.hand_interaction_profile;
+            let gestures = this.config.gestures.clone();
+            let pending_tracking_origin = Arc::clone(&this.pending_tracking_origin);
+            let view_corrections = Arc::clone(&this.view_corrections);
+            let in_headset_menu = this.in_headset_menu.clone();
+            let running = Arc::clone(&this.input_thread_running);
+            move || {
+                stream_input_loop(
+                    &core_ctx,
+                    xr_session,
+                    &interaction_ctx,
+                    tracking_space,
+                    &input_spaces,
+                    refresh_rate,
+                    view_override,
+                    marker_origin,
+                    input_source_switch,
+                    hand_interaction_profile,
+                    &gestures,
+                    &pending_tracking_origin,
+                    &view_corrections,
+                    in_headset_menu.as_deref(),
+                    running,
+                )
+            }
+        }));
+
         this
     }
 
@@ -254,59 +512,108 @@ impl StreamContext {
         self.config.passthrough.is_some()
     }
 
+    // The input thread picks up the new spaces on its next iteration
     pub fn update_reference_space(&mut self) {
-        self.input_thread_running.set(false);
-
-        self.stage_reference_space = Arc::new(interaction::get_reference_space(
-            &self.xr_session,
-            xr::ReferenceSpaceType::STAGE,
-        ));
+        self.tracking_reference_space = Arc::new(
+            self.xr_session
+                .create_reference_space(
+                    interaction::tracking_space_type(self.tracking_space),
+                    xr::Posef::from(self.tracking_origin),
+                )
+                .unwrap(),
+        );
         self.view_reference_space = Arc::new(interaction::get_reference_space(
             &self.xr_session,
             xr::ReferenceSpaceType::VIEW,
         ));
+        *self.input_spaces.write() = InputSpaces {
+            tracking: Arc::clone(&self.tracking_reference_space),
+            view: Arc::clone(&self.view_reference_space),
+            tracking_origin: self.tracking_origin,
+        };
 
-        self.core_context.send_playspace(
-            self.xr_session
-                .reference_space_bounds_rect(xr::ReferenceSpaceType::STAGE)
-                .unwrap()
-                .map(|a| Vec2::new(a.width, a.height)),
-        );
+        // Most runtimes report the bounds only for the stage space
+        let bounds = [
+            interaction::tracking_space_type(self.tracking_space),
+            xr::ReferenceSpaceType::STAGE,
+        ]
+        .into_iter()
+        .find_map(|ty| {
+            let rect = self.xr_session.reference_space_bounds_rect(ty).ok()??;
 
-        if let Some(running) = self.input_thread.take() {
-            running.join().ok();
+            Some((Vec2::new(rect.width, rect.height), ty))
+        });
+        let playspace_area = bounds.map(|(area, _)| area);
+        let playspace_perimeter = self
+            .room_boundary
+            .as_ref()
+            .and_then(|boundary| {
+                let time = crate::xr_runtime_now(self.xr_session.instance())?;
+
+                boundary.perimeter(&self.tracking_reference_space, time)
+            })
+            .or_else(|| bounds.and_then(|(area, ty)| self.bounds_rect_perimeter(area, ty)))
+            .unwrap_or_default();
+
+        self.core_context.send_tracking_space(self.tracking_space);
+        self.core_context
+            .send_playspace(playspace_area, playspace_perimeter);
+    }
+
+    // The playspace is sent again once the room boundary is available
+    pub fn handle_room_boundary_event(&mut self, event: &xr::sys::EventDataBuffer) {
+        if let Some(boundary) = &mut self.room_boundary
+            && boundary.handle_event(event)
+        {
+            self.update_reference_space();
         }
+    }
 
-        self.input_thread_running.set(true);
+    // Fallback without a room boundary. OpenXR exposes only the bounding rectangle of the guardian,
+    // centered on the origin of the space it was queried for. The corners are converted to the
+    // tracking space, which is offset by the recentering and may not be the same space.
+    fn bounds_rect_perimeter(
+        &self,
+        area: Vec2,
+        bounds_space_type: xr::ReferenceSpaceType,
+    ) -> Option<Vec<Vec2>> {
+        let bounds_space = self
+            .xr_session
+            .create_reference_space(bounds_space_type, xr::Posef::IDENTITY)
+            .ok()?;
+        let time = crate::xr_runtime_now(self.xr_session.instance())?;
+        let location = bounds_space
+            .locate(&self.tracking_reference_space, time)
+            .ok()?;
+        if !location.location_flags.contains(
+            xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID,
+        ) {
+            return None;
+        }
+        let bounds_pose = Pose::from(location.pose);
 
-        self.input_thread = Some(thread::spawn({
-            let core_ctx = Arc::clone(&self.core_context);
-            let xr_session = self.xr_session.clone();
-            let interaction_ctx = Arc::clone(&self.interaction_context);
-            let stage_reference_space = Arc::clone(&self.stage_reference_space);
-            let view_reference_space = Arc::clone(&self.view_reference_space);
-            let refresh_rate = self.config.refresh_rate_hint;
-            let running = Arc::clone(&self.input_thread_running);
-            move || {
-                stream_input_loop(
-                    &core_ctx,
-                    xr_session,
-                    &interaction_ctx,
-                    &stage_reference_space,
-                    &view_reference_space,
-                    refresh_rate,
-                    running,
-                )
-            }
-        }));
+        let half_area = area / 2.0;
+        let perimeter = [(-1.0, -1.0), (-1.0, 1.0), (1.0, 1.0), (1.0, -1.0)]
+            .into_iter()
+            .map(|(x, z)| {
+                let point =
+                    bounds_pose.transform_point(Vec3::new(x * half_area.x, 0.0, z * half_area.y));
+
+                Vec2::new(point.x, point.z)
+            })
+            .collect();
+
+        Some(perimeter)
     }
 
     pub fn maybe_initialize_decoder(&mut self, codec: CodecType, config_nal: Vec<u8>) {
         let new_config = VideoDecoderConfig {
             codec,
+            chroma_subsampling: self.config.chroma_subsampling,
             force_software_decoder: self.config.force_software_decoder,
             max_buffering_frames: self.config.max_buffering_frames,
             buffering_history_weight: self.config.buffering_history_weight,
+            max_queued_frames: self.config.max_queued_frames,
             options: self.config.decoder_options.clone(),
             config_buffer: config_nal,
         };
@@ -318,7 +625,7 @@ impl StreamContext {
         };
 
         if let Some(config) = maybe_config {
-            let (mut sink, source) = video_decoder::create_decoder(config.clone(), {
+            let (sink, source) = video_decoder::create_decoder(config.clone(), {
                 let ctx = Arc::clone(&self.core_context);
                 move |maybe_timestamp: Result<Duration>| match maybe_timestamp {
                     Ok(timestamp) => ctx.report_frame_decoded(timestamp),
@@ -327,9 +634,7 @@ impl StreamContext {
             });
             self.decoder = Some((config, source));
 
-            self.core_context.set_decoder_input_callback(Box::new(
-                move |timestamp, buffer| -> bool { sink.push_nal(timestamp, buffer) },
-            ));
+            self.core_context.set_decoder(sink);
         }
     }
 
@@ -342,7 +647,13 @@ impl StreamContext {
         &mut self,
         frame_interval: Duration,
         vsync_time: Duration,
-    ) -> (ProjectionLayerBuilder<'_>, Duration) {
+    ) -> (ProjectionLayerBuilder<'_, G>, Duration) {
+        let pending_tracking_origin = self.pending_tracking_origin.lock().take();
+        if let Some(origin) = pending_tracking_origin {
+            self.tracking_origin = origin;
+            self.update_reference_space();
+        }
+
         let xr_vsync_time = xr::Time::from_nanos(vsync_time.as_nanos() as _);
         let frame_poll_deadline = Instant::now()
             + Duration::from_secs_f32(
@@ -351,16 +662,35 @@ impl StreamContext {
         let mut frame_result = None;
         if let Some((_, source)) = &mut self.decoder {
             while frame_result.is_none() && Instant::now() < frame_poll_deadline {
-                frame_result = source.get_frame();
+                frame_result = source
+                    .get_frame()
+                    .filter(|(timestamp, _)| self.core_context.is_frame_displayable(*timestamp));
                 thread::sleep(Duration::from_micros(500));
             }
         }
 
+        let extrapolate =
+            frame_result.is_none() && self.config.frame_extrapolation && self.has_last_frame;
+
         let (timestamp, view_params, buffer_ptr) =
             if let Some((timestamp, buffer_ptr)) = frame_result {
-                let view_params = self.core_context.report_compositor_start(timestamp);
+                // The view params of frames that arrive too late are not known anymore. The frame
+                // was rendered with the headset views sent for its timestamp, which are
+                // interpolated from the history.
+                let view_params = self
+                    .core_context
+                    .try_report_compositor_start(timestamp)
+                    .unwrap_or_else(|| self.historical_view_params(timestamp));
 
                 self.last_good_view_params = view_params;
+                self.has_last_frame = true;
+
+                if let Some(flash_duration) = self.config.latency_test_flash_duration
+                    && self.core_context.start_latency_test_flash(timestamp)
+                {
+                    info!("Latency test: flashing frame {timestamp:?} at vsync {vsync_time:?}");
+                    self.latency_test_frames_left = flash_duration;
+                }
 
                 (timestamp, view_params, buffer_ptr)
             } else {
@@ -382,19 +712,52 @@ impl StreamContext {
             .locate_views(
                 xr::ViewConfigurationType::PRIMARY_STEREO,
                 xr_vsync_time,
-                &self.stage_reference_space,
+                &self.tracking_reference_space,
             )
             .unwrap();
 
-        let current_headset_views = if flags.contains(xr::ViewStateFlags::ORIENTATION_VALID) {
+        let mut headset_views_valid = flags.contains(xr::ViewStateFlags::ORIENTATION_VALID);
+        let current_headset_views = if headset_views_valid {
+            for (idx, view) in maybe_views.iter().enumerate() {
+                let mut pose = Pose::from(view.pose);
+                // Keep the last known position if only the orientation is tracked
+                if !flags.contains(xr::ViewStateFlags::POSITION_VALID)
+                    && let Some(last_pose) = self.headset_view_history[idx].pose_at(vsync_time)
+                {
+                    pose.position = last_pose.position;
+                }
+
+                self.headset_view_history[idx].push(vsync_time, pose);
+                self.last_headset_fovs[idx] = crate::from_xr_fov(view.fov);
+            }
+
             maybe_views
         } else {
-            vec![crate::default_view(), crate::default_view()]
+            // Extrapolate the recent poses to the display time instead of snapping to the origin
+            [0, 1]
+                .map(|idx| {
+                    if let Some(pose) = self.headset_view_history[idx].pose_at(vsync_time) {
+                        headset_views_valid = true;
+
+                        xr::View {
+                            pose: xr::Posef::from(pose),
+                            fov: crate::to_xr_fov(self.last_headset_fovs[idx]),
+                        }
+                    } else {
+                        crate::default_view()
+                    }
+                })
+                .to_vec()
         };
 
         // The poses and FoVs we received from the PC runtime, which may differ and/or include
-        // altered FoVs based on settings and view conversions done for canting.
-        let input_view_params = view_params;
+        // altered FoVs based on settings and view conversions done for canting. The IPD and
+        // convergence override is undone so the frame is shown to the actual eyes as rendered.
+        let view_corrections = *self.view_corrections.lock();
+        let input_view_params = [0, 1].map(|idx| ViewParams {
+            pose: view_params[idx].pose * view_corrections[idx],
+            fov: view_params[idx].fov,
+        });
         let mut output_view_params = input_view_params;
         // Avoid passing invalid timestamp to runtime.
         // `timestamp` is generally a current vsync time, but may be repeated if frames are
@@ -406,14 +769,17 @@ impl StreamContext {
         // (shinyquagsire23) I don't entirely trust runtimes to implement CompositionLayerProjectionView
         // correctly, but if we do trust them, avoid doing rotation ourselves. Otherwise, rerender.
         // Ex: YVR/PFDMR has issues with aspect ratio mismatches and passthrough compositing.
-        if self.use_custom_reprojection {
+        //
+        // The same reprojection is used to extrapolate the last frame when no new frame is ready.
+        // The frame is drawn at a large distance, so only the head rotation is compensated.
+        if self.use_custom_reprojection || (extrapolate && headset_views_valid) {
             output_view_params = [
                 ViewParams {
-                    pose: crate::from_xr_pose(current_headset_views[0].pose),
+                    pose: Pose::from(current_headset_views[0].pose),
                     fov: crate::from_xr_fov(current_headset_views[0].fov),
                 },
                 ViewParams {
-                    pose: crate::from_xr_pose(current_headset_views[1].pose),
+                    pose: Pose::from(current_headset_views[1].pose),
                     fov: crate::from_xr_fov(current_headset_views[1].fov),
                 },
             ];
@@ -421,6 +787,10 @@ impl StreamContext {
             openxr_display_time = vsync_time;
         }
 
+        if extrapolate {
+            self.core_context.report_extrapolated_frame();
+        }
+
         self.renderer.render(
             buffer_ptr,
             [
@@ -436,11 +806,31 @@ impl StreamContext {
                 },
             ],
             self.config.passthrough.as_ref(),
+            self.latency_test_frames_left > 0,
         );
+        self.latency_test_frames_left = self.latency_test_frames_left.saturating_sub(1);
 
         self.swapchains[0].release_image().unwrap();
         self.swapchains[1].release_image().unwrap();
 
+        if self.core_context.depth_stream_paused() {
+            self.depth_swapchains = None;
+        }
+
+        // The depth matches the views of the frame, it cannot be used if they are reprojected
+        let depth = if self.config.enable_depth_stream
+            && !buffer_ptr.is_null()
+            && !self.use_custom_reprojection
+        {
+            self.upload_depth(timestamp)
+        } else {
+            None
+        };
+        // The runtime synthesizes the next frame from the motion vectors and the depth
+        let motion_vectors_uploaded = self.config.enable_space_warp
+            && depth.is_some()
+            && self.upload_motion_vectors(timestamp, view_params);
+
         if !buffer_ptr.is_null()
             && let Some(xr_now) = crate::xr_runtime_now(self.xr_session.instance())
         {
@@ -458,18 +848,24 @@ impl StreamContext {
             },
         };
 
-        let clientside_post_processing = self
+        let mut clientside_post_processing = self
             .xr_session
             .instance()
             .exts()
             .fb_composition_layer_settings
             .and(self.config.clientside_post_processing.clone());
+        if let Some(config) = &mut clientside_post_processing
+            && config.disable_sharpening_on_video
+            && self.core_context.video_content_detected()
+        {
+            config.sharpening = ClientsidePostProcessingSharpeningMode::Disabled;
+        }
 
         let layer = ProjectionLayerBuilder::new(
-            &self.stage_reference_space,
+            &self.tracking_reference_space,
             [
                 xr::CompositionLayerProjectionView::new()
-                    .pose(crate::to_xr_pose(output_view_params[0].pose))
+                    .pose(xr::Posef::from(output_view_params[0].pose))
                     .fov(crate::to_xr_fov(output_view_params[0].fov))
                     .sub_image(
                         xr::SwapchainSubImage::new()
@@ -478,7 +874,7 @@ impl StreamContext {
                             .image_rect(rect),
                     ),
                 xr::CompositionLayerProjectionView::new()
-                    .pose(crate::to_xr_pose(output_view_params[1].pose))
+                    .pose(xr::Posef::from(output_view_params[1].pose))
                     .fov(crate::to_xr_fov(output_view_params[1].fov))
                     .sub_image(
                         xr::SwapchainSubImage::new()
@@ -501,35 +897,299 @@ impl StreamContext {
                     ),
                 }),
             clientside_post_processing,
+            depth.and_then(|depth| {
+                let swapchains = self.depth_swapchains.as_ref()?;
+
+                Some(ProjectionLayerDepthConfig {
+                    swapchains: &swapchains.depth,
+                    rect: xr::Rect2Di {
+                        offset: xr::Offset2Di { x: 0, y: 0 },
+                        extent: xr::Extent2Di {
+                            width: depth.view_resolution.x as _,
+                            height: depth.view_resolution.y as _,
+                        },
+                    },
+                    near_z: depth.near_z,
+                    far_z: depth.far_z,
+                    motion_vector_swapchains: swapchains
+                        .motion_vectors
+                        .as_ref()
+                        .filter(|_| motion_vectors_uploaded),
+                })
+            }),
         );
 
         (layer, openxr_display_time)
     }
+
+    // Views sent to the streamer for this timestamp, rebuilt from the headset view history
+    fn historical_view_params(&self, timestamp: Duration) -> [ViewParams; 2] {
+        let view_corrections = *self.view_corrections.lock();
+
+        [0, 1].map(
+            |idx| match self.headset_view_history[idx].pose_at(timestamp) {
+                Some(pose) => ViewParams {
+                    pose: pose * view_corrections[idx].inverse(),
+                    fov: self.last_good_view_params[idx].fov,
+                },
+                None => self.last_good_view_params[idx],
+            },
+        )
+    }
+
+    // Uploads the depth received for the frame, if any, to the depth swapchains
+    fn upload_depth(&mut self, timestamp: Duration) -> Option<DepthFrame> {
+        let format = G::DEPTH_FORMAT?;
+        let depth = self.core_context.depth_frame(timestamp)?;
+        let resolution = depth.view_resolution;
+
+        if self
+            .depth_swapchains
+            .as_ref()
+            .is_none_or(|swapchains| swapchains.resolution != resolution)
+        {
+            let depth = [0, 1].map(|_| {
+                graphics::create_depth_swapchain(
+                    &self.xr_session,
+                    &self.gfx_ctx,
+                    resolution,
+                    format,
+                )
+            });
+            let depth_images = [
+                depth[0].enumerate_images().unwrap(),
+                depth[1].enumerate_images().unwrap(),
+            ];
+            let (motion_vectors, motion_vector_images) = G::MOTION_VECTOR_FORMAT
+                .filter(|_| self.config.enable_space_warp)
+                .map(|format| {
+                    let swapchains = [0, 1].map(|_| {
+                        graphics::create_motion_vector_swapchain(
+                            &self.xr_session,
+                            &self.gfx_ctx,
+                            resolution,
+                            format,
+                        )
+                    });
+                    let images = [
+                        swapchains[0].enumerate_images().unwrap(),
+                        swapchains[1].enumerate_images().unwrap(),
+                    ];
+
+                    (swapchains, images)
+                })
+                .unzip();
+
+            self.depth_swapchains = Some(DepthSwapchains {
+                resolution,
+                depth,
+                depth_images,
+                motion_vectors,
+                motion_vector_images,
+            });
+        }
+        let DepthSwapchains {
+            depth: swapchains,
+            depth_images: images,
+            ..
+        } = self.depth_swapchains.as_mut()?;
+
+        // Each row contains the row of the left view followed by the one of the right view. The
+        // rows are sent top-down, the GL swapchain images are stored bottom-up
+        let width = resolution.x as usize;
+        for (idx, swapchain) in swapchains.iter_mut().enumerate() {
+            let image_index = swapchain.acquire_image().unwrap();
+            swapchain.wait_image(xr::Duration::INFINITE).unwrap();
+
+            let data = depth
+                .data
+                .chunks_exact(width * 2)
+                .rev()
+                .flat_map(|row| &row[idx * width..(idx + 1) * width])
+                .copied()
+                .collect::<Vec<_>>();
+            G::upload_depth(
+                &self.gfx_ctx,
+                images[idx][image_index as usize],
+                resolution,
+                &data,
+            );
+
+            swapchain.release_image().unwrap();
+        }
+
+        Some(depth)
+    }
+
+    // Uploads the motion vectors received for the frame, if any, to the motion vector swapchains.
+    // Call after upload_depth(), the motion vectors have the resolution of the depth
+    fn upload_motion_vectors(&mut self, timestamp: Duration, view_params: [ViewParams; 2]) -> bool {
+        let Some(frame) = self.core_context.motion_vectors_frame(timestamp) else {
+            return false;
+        };
+        let reference_view_params = self.historical_view_params(frame.reference_timestamp);
+        let Some(DepthSwapchains {
+            resolution,
+            motion_vectors: Some(swapchains),
+            motion_vector_images: Some(images),
+            ..
+        }) = &mut self.depth_swapchains
+        else {
+            return false;
+        };
+
+        for (idx, swapchain) in swapchains.iter_mut().enumerate() {
+            let image_index = swapchain.acquire_image().unwrap();
+            swapchain.wait_image(xr::Duration::INFINITE).unwrap();
+
+            let data = motion_vectors::to_space_warp(
+                &frame,
+                idx,
+                *resolution,
+                view_params[idx],
+                reference_view_params[idx],
+            );
+            G::upload_motion_vectors(
+                &self.gfx_ctx,
+                images[idx][image_index as usize],
+                *resolution,
+                &data,
+            );
+
+            swapchain.release_image().unwrap();
+        }
+
+        true
+    }
 }
 
-impl Drop for StreamContext {
+impl<G: ClientGraphics> Drop for StreamContext<G> {
     fn drop(&mut self) {
         self.input_thread_running.set(false);
         self.input_thread.take().unwrap().join().ok();
     }
 }
 
+// Moves the views symmetrically around their center to match the IPD override and rotates them
+// around the vertical axis for convergence. Views are relative to the head.
+fn override_local_views(views: [ViewParams; 2], config: &ViewOverrideConfig) -> [ViewParams; 2] {
+    let center = (views[0].pose.position + views[1].pose.position) / 2.0;
+    let half_eye_offset = if let Some(ipd_mm) = config.ipd_mm.as_option() {
+        let ipd_m = ipd_mm.clamp(MIN_IPD_OVERRIDE_MM, MAX_IPD_OVERRIDE_MM) / 1000.0;
+
+        (views[1].pose.position - views[0].pose.position).normalize_or(Vec3::X) * ipd_m / 2.0
+    } else {
+        (views[1].pose.position - views[0].pose.position) / 2.0
+    };
+    let convergence_rad = config
+        .convergence_deg
+        .clamp(-MAX_CONVERGENCE_DEG, MAX_CONVERGENCE_DEG)
+        .to_radians();
+
+    [
+        ViewParams {
+            pose: Pose {
+                orientation: views[0].pose.orientation * Quat::from_rotation_y(-convergence_rad),
+                position: center - half_eye_offset,
+            },
+            fov: views[0].fov,
+        },
+        ViewParams {
+            pose: Pose {
+                orientation: views[1].pose.orientation * Quat::from_rotation_y(convergence_rad),
+                position: center + half_eye_offset,
+            },
+            fov: views[1].fov,
+        },
+    ]
+}
+
+fn pose_changed(old: Pose, new: Pose) -> bool {
+    new.position.distance(old.position) > MARKER_ORIGIN_MIN_DISTANCE
+        || new.orientation.angle_between(old.orientation) > MARKER_ORIGIN_MIN_ANGLE_DEG.to_radians()
+}
+
+// marker_pose is located in the tracking reference space
+fn apply_marker_pose(
+    core_ctx: &ClientCoreContext,
+    config: &MarkerOriginConfig,
+    tracking_origin: Pose,
+    pending_tracking_origin: &Mutex<Option<Pose>>,
+    marker_pose: Pose,
+) {
+    match config.mode {
+        MarkerOriginMode::Server => core_ctx.send_marker_origin(marker_pose),
+        MarkerOriginMode::Client => {
+            // The marker is located relative to the current origin
+            let origin = (tracking_origin * marker_pose).marker_floor_origin();
+
+            if pose_changed(tracking_origin, origin) {
+                *pending_tracking_origin.lock() = Some(origin);
+            }
+        }
+    }
+}
+
+#[expect(clippy::too_many_arguments)]
 fn stream_input_loop(
     core_ctx: &ClientCoreContext,
-    xr_session: xr::Session<xr::OpenGlEs>,
+    xr_session: xr::Session<xr::AnyGraphics>,
     interaction_ctx: &RwLock<InteractionContext>,
-    stage_reference_space: &xr::Space,
-    view_reference_space: &xr::Space,
+    tracking_space: TrackingSpace,
+    input_spaces: &RwLock<InputSpaces>,
     refresh_rate: f32,
+    view_override: Option<ViewOverrideConfig>,
+    marker_origin: Option<MarkerOriginConfig>,
+    input_source_switch: Option<InputSourceSwitchConfig>,
+    hand_interaction_profile: bool,
+    gestures: &[GestureConfig],
+    pending_tracking_origin: &Mutex<Option<Pose>>,
+    view_corrections: &Mutex<[Pose; 2]>,
+    in_headset_menu: Option<&Mutex<InHeadsetMenu>>,
     running: Arc<RelaxedAtomic>,
 ) {
     let mut last_controller_poses = [Pose::IDENTITY; 2];
     let mut last_palm_poses = [Pose::IDENTITY; 2];
+    let mut input_source_switches = input_source_switch.map(|config| {
+        [0, 1].map(|_| InputSourceSwitch::new(Duration::from_millis(config.switch_delay_ms)))
+    });
     let mut last_view_params = [ViewParams::DUMMY; 2];
+    let mut last_marker_poll = Instant::now();
+    let mut gesture_recognizer = GestureRecognizer::new(gestures);
+
+    // The remembered marker pose is located without the tracking origin, which changes in the client
+    // mode. It is used until the marker is seen again
+    let (anchor_space, mut stored_anchor) = match &marker_origin {
+        Some(config) if config.remember_pose => {
+            let space = interaction::get_reference_space(
+                &xr_session,
+                interaction::tracking_space_type(tracking_space),
+            );
+            let anchor = core_ctx.stored_marker_anchor(tracking_space, &config.marker_code);
+            if let Some(pose) = anchor {
+                let tracking_origin = input_spaces.read().tracking_origin;
+                apply_marker_pose(
+                    core_ctx,
+                    config,
+                    tracking_origin,
+                    pending_tracking_origin,
+                    tracking_origin.inverse() * pose,
+                );
+            }
+
+            (Some(space), anchor)
+        }
+        _ => (None, None),
+    };
 
     let mut deadline = Instant::now();
     let frame_interval = Duration::from_secs_f32(1.0 / refresh_rate);
     while running.value() {
+        let spaces = input_spaces.read().clone();
+        let tracking_reference_space = &*spaces.tracking;
+        let view_reference_space = &*spaces.view;
+        let tracking_origin = spaces.tracking_origin;
+
         let int_ctx = &*interaction_ctx.read();
         // Streaming related inputs are updated here. Make sure every input poll is done in this
         // thread
@@ -549,7 +1209,7 @@ fn stream_input_loop(
         let Some((head_motion, local_views)) = interaction::get_head_data(
             &xr_session,
             core_ctx.platform(),
-            stage_reference_space,
+            tracking_reference_space,
             view_reference_space,
             now,
             target_time,
@@ -559,28 +1219,38 @@ fn stream_input_loop(
         };
 
         if let Some(views) = local_views {
-            core_ctx.send_view_params(views);
+            // last_view_params must hold the headset views, used to detect IPD changes
             last_view_params = views;
+
+            if let Some(config) = &view_override {
+                let overridden_views = override_local_views(views, config);
+                *view_corrections.lock() =
+                    [0, 1].map(|idx| overridden_views[idx].pose.inverse() * views[idx].pose);
+
+                core_ctx.send_view_params(overridden_views);
+            } else {
+                core_ctx.send_view_params(views);
+            }
         }
 
         let mut device_motions = Vec::with_capacity(3);
 
         device_motions.push((*HEAD_ID, head_motion));
 
-        let left_hand_data = crate::interaction::get_hand_data(
+        let mut left_hand_data = crate::interaction::get_hand_data(
             &xr_session,
             core_ctx.platform(),
-            stage_reference_space,
+            tracking_reference_space,
             now,
             target_time,
             &int_ctx.hands_interaction[0],
             &mut last_controller_poses[0],
             &mut last_palm_poses[0],
         );
-        let right_hand_data = crate::interaction::get_hand_data(
+        let mut right_hand_data = crate::interaction::get_hand_data(
             &xr_session,
             core_ctx.platform(),
-            stage_reference_space,
+            tracking_reference_space,
             now,
             target_time,
             &int_ctx.hands_interaction[1],
@@ -588,6 +1258,11 @@ fn stream_input_loop(
             &mut last_palm_poses[1],
         );
 
+        if let Some([left_switch, right_switch]) = &mut input_source_switches {
+            left_switch.filter_hand_data(&mut left_hand_data, int_ctx.multimodal_hands_enabled);
+            right_switch.filter_hand_data(&mut right_hand_data, int_ctx.multimodal_hands_enabled);
+        }
+
         // Note: When multimodal input is enabled, we are sure that when free hands are used
         // (not holding controllers) the controller data is None.
         if (int_ctx.multimodal_hands_enabled || left_hand_data.skeleton_joints.is_none())
@@ -612,21 +1287,27 @@ fn stream_input_loop(
             device_motions.push((*DETACHED_CONTROLLER_RIGHT_ID, detached_controller));
         }
 
-        let face = interaction::get_face_data(
-            &xr_session,
-            &int_ctx.face_sources,
-            view_reference_space,
-            now,
-        );
+        // The privacy setting stops the sampling, not only the sending
+        let (face, body) = if core_ctx.biometric_streams_blocked() {
+            (FaceData::default(), None)
+        } else {
+            let face = interaction::get_face_data(
+                &xr_session,
+                &int_ctx.face_sources,
+                view_reference_space,
+                now,
+            );
 
-        let body = int_ctx
-            .body_source
-            .as_ref()
-            .and_then(|source| interaction::get_body_skeleton(source, stage_reference_space, now));
+            let body = int_ctx.body_source.as_ref().and_then(|source| {
+                interaction::get_body_skeleton(source, tracking_reference_space, now)
+            });
 
-        if let Some(source) = &int_ctx.body_source {
-            device_motions.append(&mut interaction::get_bd_motion_trackers(source, now));
-        }
+            if let Some(source) = &int_ctx.body_source {
+                device_motions.append(&mut interaction::get_bd_motion_trackers(source, now));
+            }
+
+            (face, body)
+        };
 
         // Even though the server is already adding the motion-to-photon latency, here we use
         // target_time as the poll_timestamp to compensate for the fact that video frames are sent
@@ -644,7 +1325,79 @@ fn stream_input_loop(
             body,
         });
 
-        let button_entries = interaction::update_buttons(&xr_session, &int_ctx.button_actions);
+        // While the marker is not visible the last origin is kept. A followed marker is sent every
+        // frame, the server filters it.
+        if let Some(config) = &marker_origin
+            && let Some(source) = &int_ctx.marker_source
+            && (last_marker_poll.elapsed() > MARKER_POLL_INTERVAL
+                || (config.follow.enabled() && config.mode == MarkerOriginMode::Server))
+        {
+            last_marker_poll = Instant::now();
+
+            let base_spaces = if let Some(space) = &anchor_space {
+                vec![tracking_reference_space, space]
+            } else {
+                vec![tracking_reference_space]
+            };
+            match source.poll_in_spaces(&base_spaces, crate::to_xr_time(now)) {
+                Ok(markers) => {
+                    let find_marker = |markers: &[(String, xr::Posef)]| {
+                        markers
+                            .iter()
+                            .find(|(code, _)| *code == config.marker_code)
+                            .map(|(_, pose)| Pose::from(*pose))
+                    };
+
+                    if let Some(marker_pose) = find_marker(&markers[0]) {
+                        apply_marker_pose(
+                            core_ctx,
+                            config,
+                            tracking_origin,
+                            pending_tracking_origin,
+                            marker_pose,
+                        );
+                    }
+
+                    // The file is written only when the marker moved
+                    if let Some(anchor_pose) = markers.get(1).and_then(|m| find_marker(m))
+                        && stored_anchor.is_none_or(|stored| pose_changed(stored, anchor_pose))
+                    {
+                        core_ctx.store_marker_anchor(
+                            tracking_space,
+                            &config.marker_code,
+                            anchor_pose,
+                        );
+                        stored_anchor = Some(anchor_pose);
+                    }
+                }
+                Err(e) => debug!("Marker tracking poll failed: {e}"),
+            }
+        }
+
+        let mut button_entries = interaction::update_buttons(&xr_session, &int_ctx.button_actions);
+        if hand_interaction_profile && let Some(actions) = &int_ctx.hand_interaction_actions {
+            button_entries
+                .extend(actions.get_buttons(&xr_session, &int_ctx.hands_interaction[0].input_ids));
+        }
+        let hand_skeletons = [
+            left_hand_data.skeleton_joints.as_ref(),
+            right_hand_data.skeleton_joints.as_ref(),
+        ];
+        for action in gesture_recognizer.update(&button_entries, hand_skeletons, Instant::now()) {
+            info!("Gesture: {action:?}");
+
+            match action {
+                GestureAction::ToggleInHeadsetMenu => {
+                    if let Some(menu) = in_headset_menu {
+                        menu.lock().toggle();
+                    }
+                }
+                GestureAction::Streamer(action) => core_ctx.send_streamer_action(action),
+            }
+        }
+        if let Some(menu) = in_headset_menu {
+            button_entries = menu.lock().handle_buttons(button_entries);
+        }
         if !button_entries.is_empty() {
             core_ctx.send_buttons(button_entries);
         }
@@ -0,0 +1,187 @@
+use glam::Vec2;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AxisCurve {
+    Linear,
+    Power(f32),
+    // (input, output) pairs between 0 and 1, interpolated linearly. The curve implicitly starts
+    // at (0, 0) and ends at (1, 1)
+    Points(Vec<(f32, f32)>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AxisResponse {
+    pub inner_deadzone: f32,
+    pub outer_deadzone: f32,
+    pub anti_deadzone: f32,
+    pub curve: AxisCurve,
+}
+
+impl AxisResponse {
+    pub const fn deadzones(inner_deadzone: f32, outer_deadzone: f32) -> Self {
+        Self {
+            inner_deadzone,
+            outer_deadzone,
+            anti_deadzone: 0.0,
+            curve: AxisCurve::Linear,
+        }
+    }
+}
+
+// Input and output between 0 and 1. The curve is always non decreasing
+pub fn evaluate_axis_curve(curve: &AxisCurve, value: f32) -> f32 {
+    let value = value.clamp(0.0, 1.0);
+
+    match curve {
+        AxisCurve::Linear => value,
+        AxisCurve::Power(exponent) => value.powf(exponent.max(0.01)),
+        AxisCurve::Points(points) => {
+            let mut points = points
+                .iter()
+                .map(|(input, output)| (input.clamp(0.0, 1.0), output.clamp(0.0, 1.0)))
+                .collect::<Vec<_>>();
+            points.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            // Points going down are flattened, so that the curve never inverts the input direction
+            let mut max_output = 0.0;
+            for point in &mut points {
+                max_output = f32::max(max_output, point.1);
+                point.1 = max_output;
+            }
+
+            let interpolate = |from: (f32, f32), to: (f32, f32)| {
+                let span = to.0 - from.0;
+                if span > f32::EPSILON {
+                    from.1 + (to.1 - from.1) * (value - from.0) / span
+                } else {
+                    to.1
+                }
+            };
+
+            let mut previous = (0.0, 0.0);
+            for point in points {
+                if value <= point.0 {
+                    return interpolate(previous, point);
+                }
+                previous = point;
+            }
+
+            interpolate(previous, (1.0, 1.0))
+        }
+    }
+}
+
+// Applies the deadzones and the response curve to an axis value between 0 and 1
+pub fn process_axis_value(value: f32, response: &AxisResponse) -> f32 {
+    let value = value.clamp(0.0, 1.0);
+    let inner_edge = response.inner_deadzone.clamp(0.0, 1.0);
+    let outer_edge = f32::max(1.0 - response.outer_deadzone.clamp(0.0, 1.0), inner_edge);
+
+    if value <= inner_edge {
+        0.0
+    } else if value >= outer_edge {
+        1.0
+    } else {
+        let normalized = (value - inner_edge) / (outer_edge - inner_edge);
+        let anti_deadzone = response.anti_deadzone.clamp(0.0, 1.0);
+
+        anti_deadzone + (1.0 - anti_deadzone) * evaluate_axis_curve(&response.curve, normalized)
+    }
+}
+
+// The deadzones are radial, so that the direction of the thumbstick is preserved
+pub fn process_thumbstick_value(value: Vec2, response: &AxisResponse) -> Vec2 {
+    let magnitude = value.length();
+    if magnitude <= f32::EPSILON {
+        return Vec2::ZERO;
+    }
+
+    value / magnitude * process_axis_value(magnitude, response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_monotonic(curve: &AxisCurve) {
+        let mut last = 0.0;
+        for i in 0..=100 {
+            let value = evaluate_axis_curve(curve, i as f32 / 100.0);
+            assert!((0.0..=1.0).contains(&value));
+            assert!(value >= last, "{curve:?} decreases at {i}");
+            last = value;
+        }
+    }
+
+    #[test]
+    fn test_deadzone_edges() {
+        let response = AxisResponse::deadzones(0.1, 0.05);
+
+        assert_eq!(process_axis_value(0.0, &response), 0.0);
+        assert_eq!(process_axis_value(0.1, &response), 0.0);
+        assert!(process_axis_value(0.11, &response) < 0.02);
+        assert!((process_axis_value(0.525, &response) - 0.5).abs() < 1e-5);
+        assert_eq!(process_axis_value(0.95, &response), 1.0);
+        assert_eq!(process_axis_value(1.5, &response), 1.0);
+
+        // Overlapping deadzones act as a threshold
+        let response = AxisResponse::deadzones(0.6, 0.6);
+        assert_eq!(process_axis_value(0.59, &response), 0.0);
+        assert_eq!(process_axis_value(0.61, &response), 1.0);
+    }
+
+    #[test]
+    fn test_anti_deadzone() {
+        let response = AxisResponse {
+            anti_deadzone: 0.2,
+            ..AxisResponse::deadzones(0.1, 0.0)
+        };
+
+        assert_eq!(process_axis_value(0.1, &response), 0.0);
+        assert!((process_axis_value(0.1001, &response) - 0.2).abs() < 1e-3);
+        assert_eq!(process_axis_value(1.0, &response), 1.0);
+    }
+
+    #[test]
+    fn test_curves_are_monotonic() {
+        assert_monotonic(&AxisCurve::Linear);
+        assert_monotonic(&AxisCurve::Power(0.5));
+        assert_monotonic(&AxisCurve::Power(3.0));
+        assert_monotonic(&AxisCurve::Points(vec![]));
+        assert_monotonic(&AxisCurve::Points(vec![
+            (0.8, 0.3),
+            (0.2, 0.5),
+            (0.5, 0.4),
+            (0.5, 0.6),
+        ]));
+        assert_monotonic(&AxisCurve::Points(vec![(1.0, 0.8)]));
+    }
+
+    #[test]
+    fn test_curve_points() {
+        let curve = AxisCurve::Points(vec![(0.5, 0.2)]);
+
+        assert_eq!(evaluate_axis_curve(&curve, 0.0), 0.0);
+        assert!((evaluate_axis_curve(&curve, 0.25) - 0.1).abs() < 1e-5);
+        assert!((evaluate_axis_curve(&curve, 0.5) - 0.2).abs() < 1e-5);
+        assert!((evaluate_axis_curve(&curve, 0.75) - 0.6).abs() < 1e-5);
+        assert_eq!(evaluate_axis_curve(&curve, 1.0), 1.0);
+    }
+
+    #[test]
+    fn test_thumbstick_is_radial() {
+        let response = AxisResponse::deadzones(0.2, 0.0);
+
+        assert_eq!(
+            process_thumbstick_value(Vec2::new(0.1, 0.1), &response),
+            Vec2::ZERO
+        );
+
+        let value = process_thumbstick_value(Vec2::new(0.3, -0.4), &response);
+        assert!((value.normalize() - Vec2::new(0.6, -0.8)).length() < 1e-5);
+        assert!((value.length() - 0.375).abs() < 1e-5);
+
+        let value = process_thumbstick_value(Vec2::new(1.0, 1.0), &response);
+        assert!((value.length() - 1.0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,192 @@
+use std::time::{Duration, Instant};
+
+pub const CLIPBOARD_POLL_INTERVAL: Duration = Duration::from_millis(250);
+// A change is sent only after the clipboard stayed the same for this long, to skip intermediate
+// states of apps that write the clipboard multiple times
+const DEBOUNCE_INTERVAL: Duration = Duration::from_millis(500);
+
+// Clipboard state of one side of the connection. The local clipboard is polled and the changes are
+// compared to the last synchronized text, so that text received from the peer is not echoed back.
+pub struct ClipboardSync {
+    send_local_changes: bool,
+    apply_remote_changes: bool,
+    max_size_bytes: usize,
+    last_local_text: Option<String>,
+    last_synced_text: Option<String>,
+    pending_text: Option<(String, Instant)>,
+}
+
+impl ClipboardSync {
+    pub fn new(
+        send_local_changes: bool,
+        apply_remote_changes: bool,
+        max_size_bytes: usize,
+    ) -> Self {
+        Self {
+            send_local_changes,
+            apply_remote_changes,
+            max_size_bytes,
+            last_local_text: None,
+            last_synced_text: None,
+            pending_text: None,
+        }
+    }
+
+    // If false, the local clipboard should not be read at all
+    pub fn sends_local_changes(&self) -> bool {
+        self.send_local_changes
+    }
+
+    // Call with the current content of the local clipboard
+    pub fn report_local_text(&mut self, text: String, now: Instant) {
+        if !self.send_local_changes || self.last_local_text.as_ref() == Some(&text) {
+            return;
+        }
+        self.last_local_text = Some(text.clone());
+
+        if self.last_synced_text.as_ref() == Some(&text) {
+            self.pending_text = None;
+        } else {
+            self.pending_text = Some((text, now));
+        }
+    }
+
+    // Returns the text to send to the peer. Text over the size limit is dropped.
+    pub fn poll_outgoing_text(&mut self, now: Instant) -> Option<String> {
+        if let Some((_, changed_time)) = &self.pending_text
+            && now >= *changed_time + DEBOUNCE_INTERVAL
+        {
+            let (text, _) = self.pending_text.take().unwrap();
+
+            if text.len() <= self.max_size_bytes {
+                self.last_synced_text = Some(text.clone());
+
+                return Some(text);
+            }
+        }
+
+        None
+    }
+
+    // Returns the text to write into the local clipboard, if within the size limit
+    pub fn receive_remote_text(&mut self, text: String) -> Option<String> {
+        if !self.apply_remote_changes || text.len() > self.max_size_bytes {
+            return None;
+        }
+
+        self.last_synced_text = Some(text.clone());
+        self.pending_text = None;
+
+        Some(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debounce() {
+        let mut sync = ClipboardSync::new(true, true, 1024);
+        let start = Instant::now();
+
+        sync.report_local_text("a".into(), start);
+        assert_eq!(sync.poll_outgoing_text(start), None);
+
+        // The timer restarts on every change
+        let changed = start + DEBOUNCE_INTERVAL / 2;
+        sync.report_local_text("ab".into(), changed);
+        assert_eq!(sync.poll_outgoing_text(start + DEBOUNCE_INTERVAL), None);
+
+        // Reporting the same text does not restart the timer
+        sync.report_local_text("ab".into(), changed + DEBOUNCE_INTERVAL / 2);
+        assert_eq!(
+            sync.poll_outgoing_text(changed + DEBOUNCE_INTERVAL),
+            Some("ab".into())
+        );
+        assert_eq!(
+            sync.poll_outgoing_text(changed + DEBOUNCE_INTERVAL * 2),
+            None
+        );
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let mut sync = ClipboardSync::new(true, true, 4);
+        let start = Instant::now();
+        let later = start + DEBOUNCE_INTERVAL;
+
+        sync.report_local_text("12345".into(), start);
+        assert_eq!(sync.poll_outgoing_text(later), None);
+
+        // The oversized text is not retried while it stays in the clipboard
+        sync.report_local_text("12345".into(), later);
+        assert_eq!(sync.poll_outgoing_text(later + DEBOUNCE_INTERVAL), None);
+
+        // The limit is in bytes
+        assert_eq!(sync.receive_remote_text("ééé".into()), None);
+        assert_eq!(sync.receive_remote_text("éé".into()), Some("éé".into()));
+    }
+
+    #[test]
+    fn test_remote_text_is_not_echoed() {
+        let mut sync = ClipboardSync::new(true, true, 1024);
+        let start = Instant::now();
+
+        let text = sync.receive_remote_text("remote".into()).unwrap();
+        sync.report_local_text(text, start);
+        assert_eq!(sync.poll_outgoing_text(start + DEBOUNCE_INTERVAL), None);
+
+        // A local change that reverts to the synced text is not sent either
+        let later = start + DEBOUNCE_INTERVAL;
+        sync.report_local_text("local".into(), later);
+        sync.report_local_text("remote".into(), later);
+        assert_eq!(sync.poll_outgoing_text(later + DEBOUNCE_INTERVAL), None);
+
+        // Remote text cancels a pending local change
+        sync.report_local_text("local".into(), later);
+        sync.receive_remote_text("remote 2".into());
+        assert_eq!(sync.poll_outgoing_text(later + DEBOUNCE_INTERVAL), None);
+    }
+
+    #[test]
+    fn test_directions() {
+        let start = Instant::now();
+
+        let mut receive_only = ClipboardSync::new(false, true, 1024);
+        receive_only.report_local_text("local".into(), start);
+        assert_eq!(
+            receive_only.poll_outgoing_text(start + DEBOUNCE_INTERVAL),
+            None
+        );
+        assert_eq!(
+            receive_only.receive_remote_text("remote".into()),
+            Some("remote".into())
+        );
+
+        let mut send_only = ClipboardSync::new(true, false, 1024);
+        assert_eq!(send_only.receive_remote_text("remote".into()), None);
+        send_only.report_local_text("local".into(), start);
+        assert_eq!(
+            send_only.poll_outgoing_text(start + DEBOUNCE_INTERVAL),
+            Some("local".into())
+        );
+    }
+
+    #[test]
+    fn test_sent_text_is_not_resent() {
+        let mut sync = ClipboardSync::new(true, true, 1024);
+        let start = Instant::now();
+
+        sync.report_local_text("local".into(), start);
+        assert_eq!(
+            sync.poll_outgoing_text(start + DEBOUNCE_INTERVAL),
+            Some("local".into())
+        );
+
+        // The peer echoing back the same text does not trigger another send
+        sync.receive_remote_text("local".into());
+        sync.report_local_text("local".into(), start + DEBOUNCE_INTERVAL);
+        assert_eq!(sync.poll_outgoing_text(start + DEBOUNCE_INTERVAL * 2), None);
+    }
+}
@@ -0,0 +1,205 @@
+use crate::{DeviceMotion, Pose};
+use glam::{EulerRot, Quat, Vec3};
+
+// Offset expressed in the settings as position in meters and XYZ euler rotation in degrees
+pub fn pose_from_offset(position: [f32; 3], rotation_deg: [f32; 3]) -> Pose {
+    Pose {
+        orientation: Quat::from_euler(
+            EulerRot::XYZ,
+            rotation_deg[0].to_radians(),
+            rotation_deg[1].to_radians(),
+            rotation_deg[2].to_radians(),
+        ),
+        position: Vec3::from_array(position),
+    }
+}
+
+// Inverse of pose_from_offset, with the rotation rounded to 0.1°
+pub fn offset_from_pose(pose: Pose) -> ([f32; 3], [f32; 3]) {
+    let (x, y, z) = pose.orientation.to_euler(EulerRot::XYZ);
+    let round = |angle: f32| (angle.to_degrees() * 10.0).round() / 10.0;
+
+    (pose.position.to_array(), [round(x), round(y), round(z)])
+}
+
+// Moves the tracked point rigidly by an offset expressed in the device local space. The angular
+// velocity is unchanged while the linear velocity gains the tangential component.
+pub fn apply_controller_offset(motion: DeviceMotion, offset: Pose) -> DeviceMotion {
+    DeviceMotion {
+        pose: motion.pose * offset,
+        linear_velocity: motion.linear_velocity
+            + motion
+                .angular_velocity
+                .cross(motion.pose.orientation * offset.position),
+        angular_velocity: motion.angular_velocity,
+    }
+}
+
+// Relative pose of the right controller in the left controller space
+pub fn controllers_delta(left_pose: Pose, right_pose: Pose) -> Pose {
+    left_pose.inverse() * right_pose
+}
+
+// Calibration with the two controllers held side by side, pointing in the same direction, with
+// the right one at expected_separation_m along the left controller X axis. Returns the correction
+// to append to the left controller offset (mirrored for the right one). Since the offsets are
+// mirrored, only the components that do not cancel out can be measured: the separation along X
+// and the rotations around Y and Z. Each controller takes half of the measured error.
+pub fn suggested_offset_correction(delta: Pose, expected_separation_m: f32) -> Pose {
+    let (_, y, z) = delta.orientation.to_euler(EulerRot::XYZ);
+    let half_rotation = Quat::from_euler(EulerRot::XYZ, 0.0, y / 2.0, z / 2.0);
+
+    // Separation measured along the X axis of the corrected left controller
+    let separation_m = (half_rotation.conjugate() * delta.position).x;
+
+    Pose {
+        orientation: half_rotation,
+        position: half_rotation * Vec3::new((separation_m - expected_separation_m) / 2.0, 0.0, 0.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_pose_eq;
+
+    const EPSILON: f32 = 1e-4;
+
+    #[test]
+    fn test_identity_offset() {
+        let motion = DeviceMotion {
+            pose: Pose {
+                orientation: Quat::from_rotation_y(1.0),
+                position: Vec3::new(0.1, 1.2, -0.3),
+            },
+            linear_velocity: Vec3::new(0.5, 0.0, 0.0),
+            angular_velocity: Vec3::new(0.0, 2.0, 0.0),
+        };
+
+        let offset_motion = apply_controller_offset(motion, pose_from_offset([0.0; 3], [0.0; 3]));
+
+        assert_pose_eq(offset_motion.pose, motion.pose);
+        assert!(
+            offset_motion
+                .linear_velocity
+                .abs_diff_eq(motion.linear_velocity, EPSILON)
+        );
+        assert!(
+            offset_motion
+                .angular_velocity
+                .abs_diff_eq(motion.angular_velocity, EPSILON)
+        );
+    }
+
+    #[test]
+    fn test_offset_is_local() {
+        // Controller rotated 90° to the left: its forward axis (-Z) points towards world -X
+        let pose = Pose {
+            orientation: Quat::from_rotation_y(90_f32.to_radians()),
+            position: Vec3::new(0.0, 1.0, 0.0),
+        };
+        let motion = DeviceMotion {
+            pose,
+            ..DeviceMotion::IDENTITY
+        };
+
+        let offset_motion =
+            apply_controller_offset(motion, pose_from_offset([0.0, 0.0, -0.1], [0.0; 3]));
+
+        assert_pose_eq(
+            offset_motion.pose,
+            Pose {
+                orientation: pose.orientation,
+                position: Vec3::new(-0.1, 1.0, 0.0),
+            },
+        );
+    }
+
+    #[test]
+    fn test_tangential_velocity() {
+        let motion = DeviceMotion {
+            pose: Pose::IDENTITY,
+            linear_velocity: Vec3::ZERO,
+            angular_velocity: Vec3::new(0.0, 1.0, 0.0),
+        };
+
+        // A point 1m in front of the rotation center moves sideways at 1 m/s
+        let offset_motion =
+            apply_controller_offset(motion, pose_from_offset([0.0, 0.0, -1.0], [0.0; 3]));
+
+        assert!(
+            offset_motion
+                .linear_velocity
+                .abs_diff_eq(Vec3::new(-1.0, 0.0, 0.0), EPSILON)
+        );
+        assert!(
+            offset_motion
+                .angular_velocity
+                .abs_diff_eq(motion.angular_velocity, EPSILON)
+        );
+    }
+
+    #[test]
+    fn test_mirroring() {
+        let position = [0.01, -0.02, -0.11];
+        let rotation = [10.0, 20.0, 30.0];
+
+        // Same convention used so far for the right controller offsets
+        let expected = pose_from_offset(
+            [-position[0], position[1], position[2]],
+            [rotation[0], -rotation[1], -rotation[2]],
+        );
+        let left = pose_from_offset(position, rotation);
+        assert_pose_eq(left.mirrored_x(), expected);
+
+        // Mirroring preserves the composition of offsets
+        let profile = pose_from_offset([0.0, 0.01, 0.02], [-5.0, 3.0, 8.0]);
+        assert_pose_eq(
+            (left * profile).mirrored_x(),
+            left.mirrored_x() * profile.mirrored_x(),
+        );
+    }
+
+    #[test]
+    fn test_offset_round_trip() {
+        let position = [0.01, -0.02, -0.11];
+        let rotation = [10.0, -20.0, 30.0];
+
+        let (new_position, new_rotation) = offset_from_pose(pose_from_offset(position, rotation));
+
+        for i in 0..3 {
+            assert!((new_position[i] - position[i]).abs() < EPSILON);
+            assert!((new_rotation[i] - rotation[i]).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn test_calibration() {
+        let separation = 0.08;
+        let error = pose_from_offset([0.005, 0.0, 0.0], [0.0, 4.0, 0.0]);
+
+        // Both controllers are physically aligned, but their reported poses are off by the
+        // mirrored error
+        let left_pose = Pose {
+            orientation: Quat::IDENTITY,
+            position: Vec3::new(-separation / 2.0, 1.0, 0.0),
+        } * error;
+        let right_pose = Pose {
+            orientation: Quat::IDENTITY,
+            position: Vec3::new(separation / 2.0, 1.0, 0.0),
+        } * error.mirrored_x();
+
+        let correction =
+            suggested_offset_correction(controllers_delta(left_pose, right_pose), separation);
+
+        let corrected_delta =
+            controllers_delta(left_pose * correction, right_pose * correction.mirrored_x());
+        assert_pose_eq(
+            corrected_delta,
+            Pose {
+                orientation: Quat::IDENTITY,
+                position: Vec3::new(separation, 0.0, 0.0),
+            },
+        );
+    }
+}
@@ -0,0 +1,90 @@
+// Lossless compression of the streamed depth buffers. Each value is predicted from the one on its
+// left, and the difference is stored as a zigzag varint. Depth is smooth, so most differences fit
+// in one byte.
+pub fn encode_depth(values: &[u16]) -> Vec<u8> {
+    let mut buffer = Vec::with_capacity(values.len());
+
+    let mut previous = 0_u16;
+    for value in values {
+        let delta = value.wrapping_sub(previous) as i16;
+        previous = *value;
+
+        let mut zigzag = ((delta << 1) ^ (delta >> 15)) as u16;
+        while zigzag >= 0x80 {
+            buffer.push((zigzag as u8) | 0x80);
+            zigzag >>= 7;
+        }
+        buffer.push(zigzag as u8);
+    }
+
+    buffer
+}
+
+// Returns None if the buffer does not contain exactly count values
+pub fn decode_depth(buffer: &[u8], count: usize) -> Option<Vec<u16>> {
+    // Each value takes at least one byte. This bounds the allocation when count comes from the peer
+    if count > buffer.len() {
+        return None;
+    }
+
+    let mut values = Vec::with_capacity(count);
+
+    let mut bytes = buffer.iter();
+    let mut previous = 0_u16;
+    while values.len() < count {
+        let mut zigzag = 0_u16;
+        let mut shift = 0;
+        loop {
+            let byte = *bytes.next()?;
+            if shift > 14 {
+                return None;
+            }
+            zigzag |= ((byte & 0x7f) as u16) << shift;
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+
+        let delta = ((zigzag >> 1) as i16) ^ -((zigzag & 1) as i16);
+        previous = previous.wrapping_add(delta as u16);
+        values.push(previous);
+    }
+
+    bytes.next().is_none().then_some(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let values = [0, 1, 0, 65535, 0, 32768, 32767, 100, 101, 99, 65535, 65534];
+        let buffer = encode_depth(&values);
+
+        assert_eq!(decode_depth(&buffer, values.len()).unwrap(), values);
+    }
+
+    #[test]
+    fn test_smooth_depth_is_compressed() {
+        let values = (0..1000).map(|i| 20000 + i * 3).collect::<Vec<u16>>();
+        let buffer = encode_depth(&values);
+
+        // Only the first value needs more than one byte
+        assert_eq!(buffer.len(), values.len() + 2);
+        assert_eq!(decode_depth(&buffer, values.len()).unwrap(), values);
+    }
+
+    #[test]
+    fn test_invalid_buffer() {
+        let buffer = encode_depth(&[1000, 2000, 3000]);
+
+        assert!(decode_depth(&buffer, 4).is_none());
+        assert!(decode_depth(&buffer, 2).is_none());
+        assert!(decode_depth(&buffer[..buffer.len() - 1], 3).is_none());
+        assert!(decode_depth(&[0xff, 0xff, 0xff, 0x01], 1).is_none());
+        assert!(decode_depth(&buffer, usize::MAX).is_none());
+    }
+}
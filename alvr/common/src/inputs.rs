@@ -6,9 +6,12 @@ use std::{
 
 macro_rules! interaction_profile {
     ($ty:ident, $path:expr) => {
+        interaction_profile!($ty, $path, "_controller");
+    };
+    ($ty:ident, $path:expr, $suffix:expr) => {
         paste::paste! {
             pub const [<$ty _CONTROLLER_PROFILE_PATH>]: &str =
-                concat!("/interaction_profiles/", $path, "_controller");
+                concat!("/interaction_profiles/", $path, $suffix);
             pub static [<$ty _CONTROLLER_PROFILE_ID>]: LazyLock<u64> =
                 LazyLock::new(|| hash_string([<$ty _CONTROLLER_PROFILE_PATH>]));
         }
@@ -16,6 +19,9 @@ macro_rules! interaction_profile {
 }
 
 interaction_profile!(QUEST, "oculus/touch");
+// Profiles of the extensions XR_META_touch_controller_plus and XR_FB_touch_controller_pro
+interaction_profile!(QUEST_PLUS, "meta/touch", "_controller_plus");
+interaction_profile!(QUEST_PRO, "facebook/touch", "_controller_pro");
 interaction_profile!(VIVE, "htc/vive");
 interaction_profile!(INDEX, "valve/index");
 interaction_profile!(PICO_NEO3, "bytedance/pico_neo3");
@@ -157,41 +163,59 @@ pub struct InteractionProfileInfo {
 
 pub static CONTROLLER_PROFILE_INFO: LazyLock<HashMap<u64, InteractionProfileInfo>> =
     LazyLock::new(|| {
+        // The Touch Plus and Touch Pro profiles contain all inputs of the Touch profile. Their
+        // additional inputs are not used
+        let quest_button_set: HashSet<u64> = [
+            *LEFT_X_CLICK_ID,
+            *LEFT_X_TOUCH_ID,
+            *LEFT_Y_CLICK_ID,
+            *LEFT_Y_TOUCH_ID,
+            *LEFT_MENU_CLICK_ID,
+            *LEFT_SQUEEZE_VALUE_ID,
+            *LEFT_TRIGGER_VALUE_ID,
+            *LEFT_TRIGGER_TOUCH_ID,
+            *LEFT_THUMBSTICK_X_ID,
+            *LEFT_THUMBSTICK_Y_ID,
+            *LEFT_THUMBSTICK_CLICK_ID,
+            *LEFT_THUMBSTICK_TOUCH_ID,
+            *LEFT_THUMBREST_TOUCH_ID,
+            *RIGHT_A_CLICK_ID,
+            *RIGHT_A_TOUCH_ID,
+            *RIGHT_B_CLICK_ID,
+            *RIGHT_B_TOUCH_ID,
+            *RIGHT_SYSTEM_CLICK_ID,
+            *RIGHT_SQUEEZE_VALUE_ID,
+            *RIGHT_TRIGGER_VALUE_ID,
+            *RIGHT_TRIGGER_TOUCH_ID,
+            *RIGHT_THUMBSTICK_X_ID,
+            *RIGHT_THUMBSTICK_Y_ID,
+            *RIGHT_THUMBSTICK_CLICK_ID,
+            *RIGHT_THUMBSTICK_TOUCH_ID,
+            *RIGHT_THUMBREST_TOUCH_ID,
+        ]
+        .into_iter()
+        .collect();
+
         [
             (
                 *QUEST_CONTROLLER_PROFILE_ID,
                 InteractionProfileInfo {
                     path: QUEST_CONTROLLER_PROFILE_PATH,
-                    button_set: [
-                        *LEFT_X_CLICK_ID,
-                        *LEFT_X_TOUCH_ID,
-                        *LEFT_Y_CLICK_ID,
-                        *LEFT_Y_TOUCH_ID,
-                        *LEFT_MENU_CLICK_ID,
-                        *LEFT_SQUEEZE_VALUE_ID,
-                        *LEFT_TRIGGER_VALUE_ID,
-                        *LEFT_TRIGGER_TOUCH_ID,
-                        *LEFT_THUMBSTICK_X_ID,
-                        *LEFT_THUMBSTICK_Y_ID,
-                        *LEFT_THUMBSTICK_CLICK_ID,
-                        *LEFT_THUMBSTICK_TOUCH_ID,
-                        *LEFT_THUMBREST_TOUCH_ID,
-                        *RIGHT_A_CLICK_ID,
-                        *RIGHT_A_TOUCH_ID,
-                        *RIGHT_B_CLICK_ID,
-                        *RIGHT_B_TOUCH_ID,
-                        *RIGHT_SYSTEM_CLICK_ID,
-                        *RIGHT_SQUEEZE_VALUE_ID,
-                        *RIGHT_TRIGGER_VALUE_ID,
-                        *RIGHT_TRIGGER_TOUCH_ID,
-                        *RIGHT_THUMBSTICK_X_ID,
-                        *RIGHT_THUMBSTICK_Y_ID,
-                        *RIGHT_THUMBSTICK_CLICK_ID,
-                        *RIGHT_THUMBSTICK_TOUCH_ID,
-                        *RIGHT_THUMBREST_TOUCH_ID,
-                    ]
-                    .into_iter()
-                    .collect(),
+                    button_set: quest_button_set.clone(),
+                },
+            ),
+            (
+                *QUEST_PLUS_CONTROLLER_PROFILE_ID,
+                InteractionProfileInfo {
+                    path: QUEST_PLUS_CONTROLLER_PROFILE_PATH,
+                    button_set: quest_button_set.clone(),
+                },
+            ),
+            (
+                *QUEST_PRO_CONTROLLER_PROFILE_ID,
+                InteractionProfileInfo {
+                    path: QUEST_PRO_CONTROLLER_PROFILE_PATH,
+                    button_set: quest_button_set,
                 },
             ),
             (
@@ -1,9 +1,16 @@
+mod analog_input;
 mod average;
 mod c_api;
+mod clipboard;
 mod connection_result;
+mod controller_offsets;
+mod depth;
 mod inputs;
+mod log_rotation;
 mod logging;
 mod primitives;
+mod trace;
+mod transform;
 mod version;
 
 use parking_lot::{Condvar, Mutex, RwLockWriteGuard};
@@ -17,13 +24,20 @@ pub use parking_lot;
 pub use semver;
 pub use settings_schema;
 
+pub use analog_input::*;
 pub use average::*;
 pub use c_api::*;
+pub use clipboard::*;
 pub use connection_result::*;
+pub use controller_offsets::*;
+pub use depth::*;
 pub use inputs::*;
 pub use log::{debug, error, info, warn};
+pub use log_rotation::*;
 pub use logging::*;
 pub use primitives::*;
+pub use trace::*;
+pub use transform::*;
 pub use version::*;
 
 pub const ALVR_NAME: &str = "ALVR";
@@ -0,0 +1,202 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+// Path of the index-th older file: session_log.txt -> session_log.1.txt
+pub fn rotated_log_path(path: &Path, index: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{stem}.{index}.{}", extension.to_string_lossy()),
+        None => format!("{stem}.{index}"),
+    };
+
+    path.with_file_name(file_name)
+}
+
+// Log file that is moved aside when it reaches max_size_bytes. At most max_files files are kept,
+// counting the current one, the oldest is deleted. Files are rotated only between lines, so a
+// file can exceed the size by the length of a line.
+pub struct RotatingFileWriter {
+    path: PathBuf,
+    max_size_bytes: u64,
+    max_files: usize,
+    // Closed while rotating, since open files cannot be renamed on Windows
+    file: Option<File>,
+    size: u64,
+    at_line_start: bool,
+}
+
+impl RotatingFileWriter {
+    // The current file is truncated and the older files left by a previous session are removed
+    pub fn new(path: PathBuf, max_size_bytes: u64, max_files: usize) -> io::Result<Self> {
+        let max_files = max_files.max(1);
+
+        let mut index = 1;
+        loop {
+            let rotated_path = rotated_log_path(&path, index);
+            if !rotated_path.exists() {
+                break;
+            }
+            fs::remove_file(rotated_path)?;
+            index += 1;
+        }
+
+        Ok(Self {
+            file: Some(File::create(&path)?),
+            path,
+            max_size_bytes,
+            max_files,
+            size: 0,
+            at_line_start: true,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if let Some(mut file) = self.file.take() {
+            file.flush()?;
+        }
+
+        if self.max_files > 1 {
+            let oldest = rotated_log_path(&self.path, self.max_files - 1);
+            if oldest.exists() {
+                fs::remove_file(oldest)?;
+            }
+            for index in (1..self.max_files - 1).rev() {
+                let from = rotated_log_path(&self.path, index);
+                if from.exists() {
+                    fs::rename(from, rotated_log_path(&self.path, index + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated_log_path(&self.path, 1))?;
+        }
+
+        self.file = Some(File::create(&self.path)?);
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+impl Write for RotatingFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.at_line_start && self.size > 0 && self.size + buf.len() as u64 > self.max_size_bytes
+        {
+            self.rotate()?;
+        }
+
+        // If the rotation failed, keep writing to the current file
+        if self.file.is_none() {
+            self.file = Some(
+                OpenOptions::new()
+                    .append(true)
+                    .create(true)
+                    .open(&self.path)?,
+            );
+        }
+        let count = match &mut self.file {
+            Some(file) => file.write(buf)?,
+            None => 0,
+        };
+        self.size += count as u64;
+        if count > 0 {
+            self.at_line_start = buf[count - 1] == b'\n';
+        }
+
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_log_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("alvr_log_rotation_{name}"));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+
+        dir.join("session_log.txt")
+    }
+
+    fn read(path: &Path) -> Option<String> {
+        fs::read_to_string(path).ok()
+    }
+
+    #[test]
+    fn test_rotated_path() {
+        assert_eq!(
+            rotated_log_path(Path::new("logs/session_log.txt"), 2),
+            Path::new("logs/session_log.2.txt")
+        );
+        assert_eq!(
+            rotated_log_path(Path::new("logs/session_log"), 1),
+            Path::new("logs/session_log.1")
+        );
+    }
+
+    #[test]
+    fn test_rotation_boundaries() {
+        let path = temp_log_path("boundaries");
+        let mut writer = RotatingFileWriter::new(path.clone(), 10, 3).unwrap();
+
+        // A file filled exactly to the limit is not rotated
+        writer.write_all(b"12345\n").unwrap();
+        writer.write_all(b"678\n").unwrap();
+        assert_eq!(read(&path).unwrap(), "12345\n678\n");
+        assert!(read(&rotated_log_path(&path, 1)).is_none());
+
+        // One byte more starts a new file
+        writer.write_all(b"a\n").unwrap();
+        assert_eq!(read(&path).unwrap(), "a\n");
+        assert_eq!(read(&rotated_log_path(&path, 1)).unwrap(), "12345\n678\n");
+
+        // A line written in parts is not split, even if it goes over the limit
+        writer.write_all(b"bbbbb").unwrap();
+        writer.write_all(b"ccccc\n").unwrap();
+        assert_eq!(read(&path).unwrap(), "a\nbbbbbccccc\n");
+
+        // Only the newest files are kept
+        writer.write_all(b"d\n").unwrap();
+        writer.write_all(b"eeeeeeeeeeee\n").unwrap();
+        writer.flush().unwrap();
+        assert_eq!(read(&path).unwrap(), "eeeeeeeeeeee\n");
+        assert_eq!(read(&rotated_log_path(&path, 1)).unwrap(), "d\n");
+        assert_eq!(
+            read(&rotated_log_path(&path, 2)).unwrap(),
+            "a\nbbbbbccccc\n"
+        );
+        assert!(read(&rotated_log_path(&path, 3)).is_none());
+
+        // A new session starts from a clean set of files
+        drop(writer);
+        let writer = RotatingFileWriter::new(path.clone(), 10, 3).unwrap();
+        assert_eq!(read(&path).unwrap(), "");
+        assert!(read(&rotated_log_path(&path, 1)).is_none());
+        drop(writer);
+
+        fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn test_single_file() {
+        let path = temp_log_path("single");
+        let mut writer = RotatingFileWriter::new(path.clone(), 4, 1).unwrap();
+
+        writer.write_all(b"abc\n").unwrap();
+        writer.write_all(b"de\n").unwrap();
+        writer.flush().unwrap();
+        assert_eq!(read(&path).unwrap(), "de\n");
+        assert!(read(&rotated_log_path(&path, 1)).is_none());
+
+        fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+}
@@ -1,9 +1,10 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow, bail};
 use backtrace::Backtrace;
-use parking_lot::Mutex;
+use log::LevelFilter;
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
 use settings_schema::SettingsSchema;
-use std::{error::Error, fmt::Display, sync::OnceLock};
+use std::{error::Error, fmt::Display, str::FromStr, sync::OnceLock};
 
 pub const SERVER_IMPL_DBG_LABEL: &str = "SERVER IMPL";
 pub const CLIENT_IMPL_DBG_LABEL: &str = "CLIENT IMPL";
@@ -17,6 +18,7 @@ pub const ENCODER_DBG_LABEL: &str = "ENCODER";
 pub const DECODER_DBG_LABEL: &str = "DECODER";
 
 static POPUP_CALLBACK: OnceLock<fn(&str, &str, LogSeverity)> = OnceLock::new();
+static TARGET_LEVELS: RwLock<TargetLevels> = RwLock::new(TargetLevels(vec![]));
 
 #[macro_export]
 macro_rules! dbg_server_impl {
@@ -151,6 +153,70 @@ pub fn is_enabled_debug_group(target: &str, config: &DebugGroupsConfig) -> bool
         || (config.decoder && target == DECODER_DBG_LABEL)
 }
 
+// Log level overrides by target, parsed from a list like "alvr_sockets=debug,
+// alvr_server_core::bitrate=trace". A target matches its submodules too, the longest match wins.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct TargetLevels(Vec<(String, LevelFilter)>);
+
+impl TargetLevels {
+    pub fn level(&self, target: &str) -> Option<LevelFilter> {
+        self.0
+            .iter()
+            .filter(|(prefix, _)| {
+                target
+                    .strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, level)| *level)
+    }
+
+    pub fn max_level(&self) -> LevelFilter {
+        self.0
+            .iter()
+            .map(|(_, level)| *level)
+            .max()
+            .unwrap_or(LevelFilter::Off)
+    }
+}
+
+impl FromStr for TargetLevels {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let mut levels = vec![];
+        for entry in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let Some((target, level)) = entry.split_once('=') else {
+                bail!("Missing level for \"{entry}\", expected <target>=<level>");
+            };
+            let target = target.trim();
+            if target.is_empty() {
+                bail!("Missing target for \"{entry}\"");
+            }
+            let level = LevelFilter::from_str(level.trim())
+                .map_err(|_| anyhow!("Invalid level \"{}\" for {target}", level.trim()))?;
+
+            levels.push((target.to_owned(), level));
+        }
+
+        Ok(Self(levels))
+    }
+}
+
+// Replaces the level overrides at runtime. The logger must check target_level() for each record.
+pub fn set_target_levels(default_level: LevelFilter, levels: TargetLevels) {
+    log::set_max_level(default_level.max(levels.max_level()));
+    *TARGET_LEVELS.write() = levels;
+}
+
+pub fn target_level(target: &str) -> Option<LevelFilter> {
+    TARGET_LEVELS.read().level(target)
+}
+
 #[derive(
     SettingsSchema, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
 )]
@@ -297,3 +363,38 @@ impl<T, E: Error + Send + Sync + 'static> ToAny<T> for Result<T, E> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_levels() {
+        let levels = TargetLevels::from_str(
+            " alvr_sockets=debug, alvr_server_core=warn,alvr_server_core::bitrate=TRACE,",
+        )
+        .unwrap();
+
+        assert_eq!(levels.level("alvr_sockets"), Some(LevelFilter::Debug));
+        assert_eq!(
+            levels.level("alvr_sockets::backend"),
+            Some(LevelFilter::Debug)
+        );
+        assert_eq!(levels.level("alvr_sockets_extra"), None);
+        assert_eq!(
+            levels.level("alvr_server_core::lib"),
+            Some(LevelFilter::Warn)
+        );
+        assert_eq!(
+            levels.level("alvr_server_core::bitrate"),
+            Some(LevelFilter::Trace)
+        );
+        assert_eq!(levels.level("alvr_client_core"), None);
+        assert_eq!(levels.max_level(), LevelFilter::Trace);
+
+        assert_eq!(TargetLevels::from_str("").unwrap(), TargetLevels::default());
+        assert!(TargetLevels::from_str("alvr_sockets").is_err());
+        assert!(TargetLevels::from_str("alvr_sockets=loud").is_err());
+        assert!(TargetLevels::from_str("=debug").is_err());
+    }
+}
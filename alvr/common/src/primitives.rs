@@ -1,3 +1,4 @@
+use crate::Pose;
 use glam::{Quat, Vec3};
 use serde::{Deserialize, Serialize};
 use std::{ops::Mul, time::Duration};
@@ -20,34 +21,22 @@ impl Fov {
     };
 }
 
-#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug)]
-pub struct Pose {
-    pub orientation: Quat,
-    pub position: Vec3,
-}
-
 impl Pose {
-    pub const IDENTITY: Self = Pose {
-        orientation: Quat::IDENTITY,
-        position: Vec3::ZERO,
-    };
-
-    pub fn inverse(&self) -> Pose {
-        let inverse_orientation = self.orientation.conjugate();
-        Pose {
-            orientation: inverse_orientation,
-            position: inverse_orientation * -self.position,
-        }
-    }
-}
-
-impl Mul<Pose> for Pose {
-    type Output = Pose;
+    // Origin on the floor below a marker. The forward direction is taken from the marker axis
+    // closest to the horizontal plane: the marker normal pointing into the wall if the marker is
+    // hung, or the marker top if it lies on the floor.
+    pub fn marker_floor_origin(&self) -> Pose {
+        let up = self.orientation * Vec3::Y;
+        let normal = self.orientation * Vec3::Z;
+        let forward = if up.y.abs() < normal.y.abs() {
+            up
+        } else {
+            -normal
+        };
 
-    fn mul(self, rhs: Pose) -> Pose {
         Pose {
-            orientation: self.orientation * rhs.orientation,
-            position: self.position + self.orientation * rhs.position,
+            orientation: Quat::from_rotation_y(f32::atan2(-forward.x, -forward.z)),
+            position: Vec3::new(self.position.x, 0.0, self.position.z),
         }
     }
 }
@@ -0,0 +1,111 @@
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+// Bounds the memory used by a capture, a few minutes of streaming at high refresh rates
+const MAX_TRACE_SPANS: usize = 200_000;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TraceSpan {
+    pub stage: String,
+    // In the clock passed to start_trace_capture()
+    pub start: Duration,
+    // Zero for instant events
+    pub duration: Duration,
+    pub target_timestamp: Duration,
+}
+
+struct TraceCapture {
+    origin_instant: Instant,
+    origin_time: Duration,
+    spans: Vec<TraceSpan>,
+}
+
+// Checked before touching the buffer, so recording a span outside of a capture is a single atomic
+// load
+static CAPTURE_ACTIVE: AtomicBool = AtomicBool::new(false);
+static CAPTURE: Mutex<Option<TraceCapture>> = Mutex::new(None);
+
+pub fn is_trace_capture_active() -> bool {
+    CAPTURE_ACTIVE.load(Ordering::Relaxed)
+}
+
+// origin_time is the current time in the timebase of the recorded spans. Any previous capture is
+// discarded.
+pub fn start_trace_capture(origin_time: Duration) {
+    *CAPTURE.lock() = Some(TraceCapture {
+        origin_instant: Instant::now(),
+        origin_time,
+        spans: vec![],
+    });
+    CAPTURE_ACTIVE.store(true, Ordering::Relaxed);
+}
+
+pub fn stop_trace_capture() -> Vec<TraceSpan> {
+    CAPTURE_ACTIVE.store(false, Ordering::Relaxed);
+
+    CAPTURE
+        .lock()
+        .take()
+        .map(|capture| capture.spans)
+        .unwrap_or_default()
+}
+
+// Spans that started before the capture are dropped
+pub fn trace_span(stage: &str, start: Instant, end: Instant, target_timestamp: Duration) {
+    if !is_trace_capture_active() {
+        return;
+    }
+
+    if let Some(capture) = &mut *CAPTURE.lock()
+        && capture.spans.len() < MAX_TRACE_SPANS
+        && let Some(offset) = start.checked_duration_since(capture.origin_instant)
+    {
+        capture.spans.push(TraceSpan {
+            stage: stage.to_owned(),
+            start: capture.origin_time + offset,
+            duration: end.saturating_duration_since(start),
+            target_timestamp,
+        });
+    }
+}
+
+pub fn trace_instant(stage: &str, instant: Instant, target_timestamp: Duration) {
+    trace_span(stage, instant, instant, target_timestamp);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture() {
+        let before = Instant::now();
+        trace_instant("ignored", before, Duration::ZERO);
+
+        let origin_time = Duration::from_secs(100);
+        start_trace_capture(origin_time);
+        assert!(is_trace_capture_active());
+
+        // Started before the capture
+        trace_instant("early", before, Duration::ZERO);
+
+        let start = Instant::now();
+        let end = start + Duration::from_millis(3);
+        trace_span("stage", start, end, Duration::from_millis(16));
+
+        let spans = stop_trace_capture();
+        assert!(!is_trace_capture_active());
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].stage, "stage");
+        assert!(spans[0].start >= origin_time);
+        assert_eq!(spans[0].duration, Duration::from_millis(3));
+        assert_eq!(spans[0].target_timestamp, Duration::from_millis(16));
+
+        trace_instant("ignored", Instant::now(), Duration::ZERO);
+        assert!(stop_trace_capture().is_empty());
+    }
+}
@@ -0,0 +1,364 @@
+use glam::{Mat3, Quat, Vec3};
+use serde::{Deserialize, Serialize};
+use std::ops::Mul;
+
+// Layout of vr::HmdMatrix34_t: three rows of a rotation and a translation column
+pub type HmdMatrix34 = [[f32; 4]; 3];
+
+// Rigid transform. Poses compose right to left: (a * b) applies b first, then a.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug)]
+pub struct Pose {
+    pub orientation: Quat,
+    pub position: Vec3,
+}
+
+impl Pose {
+    pub const IDENTITY: Self = Pose {
+        orientation: Quat::IDENTITY,
+        position: Vec3::ZERO,
+    };
+
+    pub fn inverse(&self) -> Pose {
+        let inverse_orientation = self.orientation.conjugate();
+        Pose {
+            orientation: inverse_orientation,
+            position: inverse_orientation * -self.position,
+        }
+    }
+
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        self.position + self.orientation * point
+    }
+
+    // Directions and velocities are only rotated
+    pub fn transform_vector(&self, vector: Vec3) -> Vec3 {
+        self.orientation * vector
+    }
+
+    // Linear interpolation of the position and spherical interpolation of the orientation, along
+    // the shortest arc
+    pub fn lerp(&self, other: Pose, t: f32) -> Pose {
+        Pose {
+            orientation: self.orientation.slerp(other.orientation, t).normalize(),
+            position: self.position.lerp(other.position, t),
+        }
+    }
+
+    // Reflection across the YZ plane, which maps a left handed device to the right one. The
+    // reflection is applied on both sides of the transform (M * pose * M), so the result is still
+    // a rigid transform and mirroring preserves composition: mirror(a * b) = mirror(a) * mirror(b).
+    // Composing a pose with a raw reflection would flip the handedness of the rotation instead.
+    pub fn mirrored_x(&self) -> Pose {
+        let [x, y, z, w] = self.orientation.to_array();
+
+        Pose {
+            orientation: Quat::from_xyzw(x, -y, -z, w),
+            position: Vec3::new(-self.position.x, self.position.y, self.position.z),
+        }
+    }
+
+    pub fn to_hmd_matrix34(&self) -> HmdMatrix34 {
+        let rotation = Mat3::from_quat(self.orientation);
+
+        let mut matrix = [[0.0; 4]; 3];
+        for (row, row_values) in matrix.iter_mut().enumerate() {
+            for (col, value) in row_values.iter_mut().take(3).enumerate() {
+                *value = rotation.col(col)[row];
+            }
+            row_values[3] = self.position[row];
+        }
+
+        matrix
+    }
+
+    // Returns None if the matrix is not a rotation and a translation. A reflected matrix has no
+    // quaternion equivalent and must be mirrored with mirrored_x() instead.
+    pub fn from_hmd_matrix34(matrix: &HmdMatrix34) -> Option<Pose> {
+        let rotation = Mat3::from_cols_array_2d(&[
+            [matrix[0][0], matrix[1][0], matrix[2][0]],
+            [matrix[0][1], matrix[1][1], matrix[2][1]],
+            [matrix[0][2], matrix[1][2], matrix[2][2]],
+        ]);
+
+        let is_orthonormal = (rotation * rotation.transpose()).abs_diff_eq(Mat3::IDENTITY, 1e-3);
+        if !is_orthonormal || rotation.determinant() < 0.0 {
+            return None;
+        }
+
+        Some(Pose {
+            orientation: Quat::from_mat3(&rotation).normalize(),
+            position: Vec3::new(matrix[0][3], matrix[1][3], matrix[2][3]),
+        })
+    }
+}
+
+impl Mul<Pose> for Pose {
+    type Output = Pose;
+
+    fn mul(self, rhs: Pose) -> Pose {
+        Pose {
+            orientation: self.orientation * rhs.orientation,
+            position: self.transform_point(rhs.position),
+        }
+    }
+}
+
+#[cfg(feature = "openxr")]
+impl From<openxr_sys::Posef> for Pose {
+    fn from(pose: openxr_sys::Posef) -> Self {
+        let o = pose.orientation;
+        let p = pose.position;
+
+        Pose {
+            orientation: Quat::from_xyzw(o.x, o.y, o.z, o.w),
+            position: Vec3::new(p.x, p.y, p.z),
+        }
+    }
+}
+
+#[cfg(feature = "openxr")]
+impl From<Pose> for openxr_sys::Posef {
+    fn from(pose: Pose) -> Self {
+        let o = pose.orientation;
+        let p = pose.position;
+
+        openxr_sys::Posef {
+            orientation: openxr_sys::Quaternionf {
+                x: o.x,
+                y: o.y,
+                z: o.z,
+                w: o.w,
+            },
+            position: openxr_sys::Vector3f {
+                x: p.x,
+                y: p.y,
+                z: p.z,
+            },
+        }
+    }
+}
+
+// Shared by the tests of the crates that handle poses. q and -q are the same rotation
+#[cfg(any(test, feature = "test-utils"))]
+#[track_caller]
+pub fn assert_pose_eq(a: Pose, b: Pose) {
+    const EPSILON: f32 = 1e-4;
+
+    assert!(
+        a.position.abs_diff_eq(b.position, EPSILON),
+        "{a:?} != {b:?}"
+    );
+    // Sine of half the angle between the orientations, precise for small angles
+    assert!(
+        (a.orientation * b.orientation.conjugate()).xyz().length() < EPSILON,
+        "{a:?} != {b:?}"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::EulerRot;
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    const EPSILON: f32 = 1e-4;
+
+    // Fixed poses covering the identity, single axis and combined rotations, a half turn and
+    // near gimbal lock pitches. Each is paired with the next one for the binary identities.
+    fn test_poses() -> Vec<Pose> {
+        [
+            ((0.0, 0.0, 0.0), Vec3::ZERO),
+            ((FRAC_PI_2, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0)),
+            ((0.0, FRAC_PI_2 - 0.01, 0.0), Vec3::new(0.0, -2.0, 0.5)),
+            ((0.0, 0.0, -FRAC_PI_2), Vec3::new(0.0, 0.0, -3.0)),
+            ((PI, 0.0, 0.0), Vec3::new(-0.2, 1.6, 0.1)),
+            ((0.3, -0.7, 1.1), Vec3::new(4.0, -1.0, 2.5)),
+            ((-2.5, 0.4, -0.2), Vec3::new(-0.01, 0.02, -0.03)),
+            ((1.9, -FRAC_PI_2 + 0.01, 3.0), Vec3::new(2.0, 2.0, -2.0)),
+        ]
+        .into_iter()
+        .map(|((yaw, pitch, roll), position)| Pose {
+            orientation: Quat::from_euler(EulerRot::YXZ, yaw, pitch, roll),
+            position,
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_compose() {
+        let a = Pose {
+            orientation: Quat::from_rotation_y(FRAC_PI_2),
+            position: Vec3::new(1.0, 0.0, 0.0),
+        };
+        let b = Pose {
+            orientation: Quat::IDENTITY,
+            position: Vec3::new(0.0, 0.0, -1.0),
+        };
+
+        // b is applied first, then rotated by a, which turns -Z into -X
+        assert_pose_eq(
+            a * b,
+            Pose {
+                orientation: a.orientation,
+                position: Vec3::new(0.0, 0.0, 0.0),
+            },
+        );
+        assert!(
+            a.transform_point(Vec3::new(0.0, 0.0, -1.0))
+                .abs_diff_eq(Vec3::ZERO, EPSILON)
+        );
+        assert!(
+            a.transform_vector(Vec3::new(0.0, 0.0, -1.0))
+                .abs_diff_eq(Vec3::new(-1.0, 0.0, 0.0), EPSILON)
+        );
+    }
+
+    #[test]
+    fn test_identities() {
+        let poses = test_poses();
+
+        for (index, &a) in poses.iter().enumerate() {
+            let b = poses[(index + 1) % poses.len()];
+
+            assert_pose_eq(a * Pose::IDENTITY, a);
+            assert_pose_eq(Pose::IDENTITY * a, a);
+            assert_pose_eq(a * a.inverse(), Pose::IDENTITY);
+            assert_pose_eq(a.inverse() * a, Pose::IDENTITY);
+            assert_pose_eq(a.inverse().inverse(), a);
+            assert_pose_eq((a * b).inverse(), b.inverse() * a.inverse());
+            assert_pose_eq((a * b) * a, a * (b * a));
+
+            let point = b.position;
+            assert!(
+                a.inverse()
+                    .transform_point(a.transform_point(point))
+                    .abs_diff_eq(point, EPSILON)
+            );
+            assert!(
+                (a * b)
+                    .transform_point(point)
+                    .abs_diff_eq(a.transform_point(b.transform_point(point)), EPSILON)
+            );
+
+            assert_pose_eq(a.mirrored_x().mirrored_x(), a);
+            assert_pose_eq((a * b).mirrored_x(), a.mirrored_x() * b.mirrored_x());
+            assert_pose_eq(a.inverse().mirrored_x(), a.mirrored_x().inverse());
+        }
+    }
+
+    #[test]
+    fn test_mirroring() {
+        // A controller on the left, yawed to the right, mirrors to a controller on the right yawed
+        // to the left
+        let left = Pose {
+            orientation: Quat::from_rotation_y(-0.3),
+            position: Vec3::new(-0.2, 1.0, -0.3),
+        };
+        let right = left.mirrored_x();
+
+        assert!(
+            right
+                .position
+                .abs_diff_eq(Vec3::new(0.2, 1.0, -0.3), EPSILON)
+        );
+        assert_pose_eq(
+            right,
+            Pose {
+                orientation: Quat::from_rotation_y(0.3),
+                position: right.position,
+            },
+        );
+
+        // The mirrored pose maps mirrored points like the reflection of the original
+        let reflect = |v: Vec3| Vec3::new(-v.x, v.y, v.z);
+        let point = Vec3::new(0.1, 0.2, 0.3);
+        assert!(
+            right
+                .transform_point(reflect(point))
+                .abs_diff_eq(reflect(left.transform_point(point)), EPSILON)
+        );
+    }
+
+    #[test]
+    fn test_lerp() {
+        let a = Pose {
+            orientation: Quat::IDENTITY,
+            position: Vec3::ZERO,
+        };
+        let b = Pose {
+            orientation: Quat::from_rotation_z(1.0),
+            position: Vec3::new(2.0, 0.0, 0.0),
+        };
+
+        assert_pose_eq(a.lerp(b, 0.0), a);
+        assert_pose_eq(a.lerp(b, 1.0), b);
+        assert_pose_eq(
+            a.lerp(b, 0.5),
+            Pose {
+                orientation: Quat::from_rotation_z(0.5),
+                position: Vec3::new(1.0, 0.0, 0.0),
+            },
+        );
+
+        // The interpolation takes the shortest arc even if the quaternions have opposite signs
+        let b_negated = Pose {
+            orientation: -b.orientation,
+            ..b
+        };
+        assert_pose_eq(a.lerp(b_negated, 0.5), a.lerp(b, 0.5));
+    }
+
+    #[test]
+    fn test_matrix_conversion() {
+        // 90° around Y: X maps to -Z
+        let pose = Pose {
+            orientation: Quat::from_rotation_y(FRAC_PI_2),
+            position: Vec3::new(1.0, 2.0, 3.0),
+        };
+        let matrix = pose.to_hmd_matrix34();
+        let expected = [
+            [0.0, 0.0, 1.0, 1.0],
+            [0.0, 1.0, 0.0, 2.0],
+            [-1.0, 0.0, 0.0, 3.0],
+        ];
+        for (row, expected_row) in matrix.iter().zip(expected) {
+            for (value, expected_value) in row.iter().zip(expected_row) {
+                assert!((value - expected_value).abs() < EPSILON);
+            }
+        }
+
+        for pose in test_poses() {
+            let matrix = pose.to_hmd_matrix34();
+            assert_pose_eq(Pose::from_hmd_matrix34(&matrix).unwrap(), pose);
+
+            // The matrix transforms points like the pose
+            let point = Vec3::new(0.3, -0.2, 0.1);
+            let transformed = Vec3::from_array(std::array::from_fn(|row| {
+                matrix[row][0] * point.x
+                    + matrix[row][1] * point.y
+                    + matrix[row][2] * point.z
+                    + matrix[row][3]
+            }));
+            assert!(transformed.abs_diff_eq(pose.transform_point(point), EPSILON));
+        }
+
+        // Reflections and scales are rejected
+        let mut reflected = Pose::IDENTITY.to_hmd_matrix34();
+        reflected[0][0] = -1.0;
+        assert!(Pose::from_hmd_matrix34(&reflected).is_none());
+        let mut scaled = Pose::IDENTITY.to_hmd_matrix34();
+        scaled[1][1] = 2.0;
+        assert!(Pose::from_hmd_matrix34(&scaled).is_none());
+    }
+
+    #[cfg(feature = "openxr")]
+    #[test]
+    fn test_openxr_conversion() {
+        for pose in test_poses() {
+            let xr_pose = openxr_sys::Posef::from(pose);
+            assert_eq!(xr_pose.orientation.w, pose.orientation.w);
+            assert_eq!(xr_pose.position.z, pose.position.z);
+            assert_pose_eq(Pose::from(xr_pose), pose);
+        }
+    }
+}
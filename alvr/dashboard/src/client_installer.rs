@@ -0,0 +1,379 @@
+use alvr_adb::{InstallFailure, WiredDeviceStatus, commands as adb};
+use alvr_common::{
+    ALVR_VERSION, RelaxedAtomic,
+    anyhow::{Result, anyhow, bail},
+    info,
+    parking_lot::Mutex,
+};
+use alvr_system_info::{ClientFlavor, PACKAGE_NAME_GITHUB_DEV, PACKAGE_NAME_GITHUB_STABLE};
+use eframe::egui;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        mpsc::{self, RecvTimeoutError},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+const APK_NAME: &str = "alvr_client_android.apk";
+const REFRESH_INTERVAL: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Copy)]
+pub enum InstallerTask {
+    Refresh,
+    // With uninstall_first, the app data of the client is lost
+    Install { uninstall_first: bool },
+    Launch,
+    GrantPermissions,
+}
+
+pub struct InstalledClient {
+    pub application_id: String,
+    pub version: Option<String>,
+}
+
+impl InstalledClient {
+    pub fn is_up_to_date(&self) -> bool {
+        self.version.as_ref() == Some(&ALVR_VERSION.to_string())
+    }
+}
+
+pub struct HeadsetStatus {
+    pub device: WiredDeviceStatus,
+    // Only checked if the device is ready
+    pub client: Option<InstalledClient>,
+}
+
+#[derive(Default)]
+pub struct InstallerState {
+    // None until ADB is available
+    pub headset: Option<HeadsetStatus>,
+    // Message and progress of the running task, if any
+    pub progress: Option<(String, Option<f32>)>,
+    // Outcome of the last task, either an information or an error
+    pub result: Option<Result<String, String>>,
+    // The last install failed because the installed client must be removed first
+    pub requires_uninstall: bool,
+}
+
+// Installs and updates the client on a headset connected over USB. The headset is checked
+// periodically only while the devices tab is shown, ADB is downloaded only for an explicit task.
+pub struct ClientInstaller {
+    tasks_sender: Option<mpsc::Sender<InstallerTask>>,
+    state: Arc<Mutex<InstallerState>>,
+    shown: Arc<RelaxedAtomic>,
+    flavor: Arc<Mutex<ClientFlavor>>,
+    context: Arc<Mutex<Option<egui::Context>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ClientInstaller {
+    pub fn new() -> Self {
+        let (tasks_sender, tasks_receiver) = mpsc::channel();
+        let state = Arc::new(Mutex::new(InstallerState::default()));
+        let shown = Arc::new(RelaxedAtomic::new(false));
+        let flavor = Arc::new(Mutex::new(ClientFlavor::Github));
+        let context = Arc::new(Mutex::new(None::<egui::Context>));
+
+        let thread = thread::spawn({
+            let state = Arc::clone(&state);
+            let shown = Arc::clone(&shown);
+            let flavor = Arc::clone(&flavor);
+            let context = Arc::clone(&context);
+            move || {
+                let layout = crate::get_filesystem_layout();
+
+                loop {
+                    let task = match tasks_receiver.recv_timeout(REFRESH_INTERVAL) {
+                        Ok(task) => task,
+                        Err(RecvTimeoutError::Timeout) => {
+                            if !shown.value() || adb::get_adb_path(&layout).is_none() {
+                                continue;
+                            }
+                            shown.set(false);
+
+                            InstallerTask::Refresh
+                        }
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    };
+
+                    let request_repaint = || {
+                        if let Some(context) = &*context.lock() {
+                            context.request_repaint();
+                        }
+                    };
+                    let report_progress = |message: &str, progress: Option<f32>| {
+                        state.lock().progress = Some((message.to_owned(), progress));
+                        request_repaint();
+                    };
+
+                    let flavor = flavor.lock().clone();
+                    let result = run_task(&layout, task, &flavor, &state, &report_progress);
+
+                    {
+                        let mut state_lock = state.lock();
+                        state_lock.progress = None;
+                        match result {
+                            Ok(Some(message)) => {
+                                info!("{message}");
+                                state_lock.result = Some(Ok(message));
+                            }
+                            Ok(None) => (),
+                            Err(e) => state_lock.result = Some(Err(e.to_string())),
+                        }
+                    }
+
+                    if !matches!(task, InstallerTask::Refresh) {
+                        refresh(&layout, &flavor, &state).ok();
+                    }
+
+                    request_repaint();
+                }
+            }
+        });
+
+        Self {
+            tasks_sender: Some(tasks_sender),
+            state,
+            shown,
+            flavor,
+            context,
+            thread: Some(thread),
+        }
+    }
+
+    // Called every frame the devices tab is shown
+    pub fn update(&self, context: &egui::Context, flavor: &ClientFlavor) {
+        self.shown.set(true);
+        *self.flavor.lock() = flavor.clone();
+        if self.context.lock().is_none() {
+            *self.context.lock() = Some(context.clone());
+        }
+    }
+
+    pub fn state(&self) -> &Mutex<InstallerState> {
+        &self.state
+    }
+
+    pub fn run(&self, task: InstallerTask) {
+        {
+            let mut state = self.state.lock();
+            state.result = None;
+            state.progress = Some(("Starting".into(), None));
+        }
+
+        if let Some(sender) = &self.tasks_sender {
+            sender.send(task).ok();
+        }
+    }
+}
+
+impl Drop for ClientInstaller {
+    fn drop(&mut self) {
+        self.tasks_sender.take();
+        self.thread.take().unwrap().join().ok();
+    }
+}
+
+// The APK shipped with the streamer or downloaded by the launcher, which is placed next to the
+// installation. APKs left over from another version are skipped.
+fn find_local_apk(layout: &alvr_filesystem::Layout) -> Option<PathBuf> {
+    [&layout.static_resources_dir, &layout.executables_dir]
+        .into_iter()
+        .flat_map(|dir| dir.ancestors().take(3))
+        .map(|dir| dir.join(APK_NAME))
+        .chain([cached_apk_path()])
+        .filter(|path| path.is_file())
+        .find(|path| match check_apk_version(path) {
+            Ok(()) => true,
+            Err(e) => {
+                info!("Skipping {}: {e}", path.display());
+
+                false
+            }
+        })
+}
+
+fn check_apk_version(path: &Path) -> Result<()> {
+    match adb::get_apk_version(path)? {
+        Some(version) if version == ALVR_VERSION.to_string() => Ok(()),
+        Some(version) => bail!("The APK is for v{version}"),
+        None => bail!("The APK has no version"),
+    }
+}
+
+fn cached_apk_path() -> PathBuf {
+    env::temp_dir().join(format!("alvr_client_android_v{}.apk", *ALVR_VERSION))
+}
+
+fn download_apk(report_progress: &dyn Fn(&str, Option<f32>)) -> Result<PathBuf> {
+    let repo = if alvr_common::is_stable() {
+        "ALVR"
+    } else if alvr_common::is_nightly() {
+        "ALVR-nightly"
+    } else {
+        bail!("No client APK found. Development builds must provide their own APK");
+    };
+    let url = format!(
+        "https://github.com/alvr-org/{repo}/releases/download/v{}/{APK_NAME}",
+        *ALVR_VERSION
+    );
+
+    let buffer = adb::download(&url, |downloaded, total| {
+        report_progress(
+            "Downloading the client",
+            total.map(|total| downloaded as f32 / total as f32),
+        )
+    })
+    .map_err(|e| anyhow!("Failed to download the client from {url}: {e}"))?;
+
+    let path = cached_apk_path();
+    fs::write(&path, buffer)?;
+    check_apk_version(&path)
+        .map_err(|e| anyhow!("The downloaded client does not match this streamer: {e}"))?;
+
+    Ok(path)
+}
+
+// The package of the APK matching this streamer
+fn expected_application_id() -> &'static str {
+    if alvr_common::is_stable() {
+        PACKAGE_NAME_GITHUB_STABLE
+    } else {
+        PACKAGE_NAME_GITHUB_DEV
+    }
+}
+
+fn refresh(
+    layout: &alvr_filesystem::Layout,
+    flavor: &ClientFlavor,
+    state: &Mutex<InstallerState>,
+) -> Result<()> {
+    let adb_path = adb::get_adb_path(layout).ok_or(anyhow!("ADB is not available"))?;
+    let device = alvr_adb::get_wired_device(&adb_path)?;
+
+    let client = if let WiredDeviceStatus::Ready { serial, .. } = &device {
+        alvr_adb::get_process_name(&adb_path, serial, flavor).map(|application_id| {
+            let version = adb::get_package_version(&adb_path, serial, &application_id)
+                .ok()
+                .flatten();
+
+            InstalledClient {
+                application_id,
+                version,
+            }
+        })
+    } else {
+        None
+    };
+
+    state.lock().headset = Some(HeadsetStatus { device, client });
+
+    Ok(())
+}
+
+fn ready_device(adb_path: &str) -> Result<String> {
+    match alvr_adb::get_wired_device(adb_path)? {
+        WiredDeviceStatus::Ready { serial, .. } => Ok(serial),
+        WiredDeviceStatus::NotFound => bail!("No headset is connected over USB"),
+        WiredDeviceStatus::Unauthorized => {
+            bail!("Allow USB debugging from the prompt shown in the headset")
+        }
+        WiredDeviceStatus::NoPermissions => {
+            bail!("No permission to access the USB device, check the udev rules")
+        }
+    }
+}
+
+fn grant_permissions(adb_path: &str, serial: &str, application_id: &str) -> usize {
+    alvr_adb::CLIENT_RUNTIME_PERMISSIONS
+        .iter()
+        .filter(|permission| {
+            adb::grant_permission(adb_path, serial, application_id, permission).is_ok()
+        })
+        .count()
+}
+
+// Returns a message to show on success
+fn run_task(
+    layout: &alvr_filesystem::Layout,
+    task: InstallerTask,
+    flavor: &ClientFlavor,
+    state: &Mutex<InstallerState>,
+    report_progress: &dyn Fn(&str, Option<f32>),
+) -> Result<Option<String>> {
+    let adb_path = adb::require_adb(layout, |downloaded, total| {
+        report_progress(
+            "Downloading ADB",
+            total.map(|total| downloaded as f32 / total as f32),
+        )
+    })?;
+
+    let installed_id = |serial: &str| {
+        alvr_adb::get_process_name(&adb_path, serial, flavor)
+            .ok_or(anyhow!("The client is not installed"))
+    };
+
+    match task {
+        InstallerTask::Refresh => {
+            refresh(layout, flavor, state)?;
+
+            Ok(None)
+        }
+        InstallerTask::Install { uninstall_first } => {
+            let serial = ready_device(&adb_path)?;
+            let apk_path = match find_local_apk(layout) {
+                Some(path) => path,
+                None => download_apk(report_progress)?,
+            };
+
+            let application_id = expected_application_id();
+            if uninstall_first {
+                report_progress("Uninstalling the client", None);
+                adb::uninstall_package(&adb_path, &serial, application_id)?;
+            }
+
+            report_progress("Installing the client", None);
+            let failure = adb::install_package(&adb_path, &serial, &apk_path.to_string_lossy())?;
+            state.lock().requires_uninstall = matches!(
+                failure,
+                Some(InstallFailure::SignatureMismatch | InstallFailure::VersionDowngrade)
+            );
+            if let Some(failure) = failure {
+                bail!("Failed to install the client: {failure}");
+            }
+
+            let granted = grant_permissions(&adb_path, &serial, application_id);
+
+            // Report what the package manager registered, not what was expected
+            let version = adb::get_package_version(&adb_path, &serial, application_id)?.ok_or(
+                anyhow!("The client was installed but its version is unknown"),
+            )?;
+
+            Ok(Some(format!(
+                "Installed client v{version} from {}, granted {granted} permissions",
+                apk_path.display()
+            )))
+        }
+        InstallerTask::Launch => {
+            let serial = ready_device(&adb_path)?;
+            let application_id = installed_id(&serial)?;
+            adb::start_application(&adb_path, &serial, &application_id)?;
+
+            Ok(Some(format!("Launched {application_id}")))
+        }
+        InstallerTask::GrantPermissions => {
+            let serial = ready_device(&adb_path)?;
+            let application_id = installed_id(&serial)?;
+            let granted = grant_permissions(&adb_path, &serial, &application_id);
+
+            Ok(Some(format!(
+                "Granted {granted} of {} permissions to {application_id}",
+                alvr_adb::CLIENT_RUNTIME_PERMISSIONS.len()
+            )))
+        }
+    }
+}
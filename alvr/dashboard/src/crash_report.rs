@@ -0,0 +1,390 @@
+use alvr_common::{ALVR_VERSION, anyhow::Result};
+use alvr_filesystem::Layout;
+use alvr_session::SessionConfig;
+use std::{
+    env,
+    fs::{self, File},
+    io::{Seek, Write},
+    path::{Path, PathBuf},
+};
+use zip::{ZipWriter, write::SimpleFileOptions};
+
+const STEAMVR_LOG_LINES: usize = 200;
+
+fn tail_lines(text: &str, count: usize) -> &str {
+    let text = text.trim_end();
+
+    text.rmatch_indices('\n')
+        .nth(count.saturating_sub(1))
+        .map_or(text, |(idx, _)| &text[idx + 1..])
+}
+
+// Replaces the personal information found in the logs: the client hostnames and names, with the
+// same names used by SessionConfig::redacted(), the IP addresses and the home directory, which
+// contains the user name
+pub struct LogRedactor {
+    replacements: Vec<(String, String)>,
+}
+
+impl LogRedactor {
+    pub fn new(session: &SessionConfig, home_dir: Option<String>) -> Self {
+        let mut hostnames = session.client_connections.keys().collect::<Vec<_>>();
+        hostnames.sort();
+
+        let mut replacements = vec![];
+        for (idx, hostname) in hostnames.into_iter().enumerate() {
+            let name = format!("client{}", idx + 1);
+            let connection = &session.client_connections[hostname];
+
+            replacements.push((hostname.clone(), name.clone()));
+            if !connection.display_name.is_empty() {
+                replacements.push((connection.display_name.clone(), name));
+            }
+            // IPv6 addresses are not found by the IPv4 pattern
+            for ip in connection.current_ip.iter().chain(&connection.manual_ips) {
+                if ip.is_ipv6() {
+                    replacements.push((ip.to_string(), "x:x:x:x".into()));
+                }
+            }
+        }
+        if let Some(home_dir) = home_dir.filter(|dir| !dir.is_empty()) {
+            replacements.push((home_dir, "~".into()));
+        }
+        // Replace the longest strings first, a hostname can contain another one
+        replacements.sort_by_key(|(from, _)| std::cmp::Reverse(from.len()));
+
+        Self { replacements }
+    }
+
+    pub fn from_environment(session: &SessionConfig) -> Self {
+        let home_var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+
+        Self::new(session, env::var(home_var).ok())
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        let mut text = redact_ipv4_addresses(text);
+        for (from, to) in &self.replacements {
+            text = text.replace(from.as_str(), to);
+        }
+
+        text
+    }
+}
+
+fn is_ipv4_address(candidate: &str) -> bool {
+    let parts = candidate.split('.').collect::<Vec<_>>();
+
+    parts.len() == 4
+        && parts
+            .iter()
+            .all(|part| (1..=3).contains(&part.len()) && part.parse::<u8>().is_ok())
+}
+
+// Addresses are found as runs of digits and dots. Longer runs, like version numbers, are kept.
+fn redact_ipv4_addresses(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find(|c: char| c.is_ascii_digit()) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let run = &rest[..end];
+        let candidate = run.trim_end_matches('.');
+
+        if is_ipv4_address(candidate) {
+            output.push_str("x.x.x.x");
+            output.push_str(&run[candidate.len()..]);
+        } else {
+            output.push_str(run);
+        }
+        rest = &rest[end..];
+    }
+    output.push_str(rest);
+
+    output
+}
+
+#[cfg(target_os = "linux")]
+fn gpu_info() -> String {
+    wgpu::Instance::default()
+        .enumerate_adapters(wgpu::Backends::all())
+        .iter()
+        .map(|adapter| {
+            let info = adapter.get_info();
+
+            format!(
+                "{} ({:?}, {:?}): {} {}\n",
+                info.name, info.backend, info.device_type, info.driver, info.driver_info
+            )
+        })
+        .collect()
+}
+
+#[cfg(windows)]
+fn gpu_info() -> String {
+    use std::{os::windows::process::CommandExt, process::Command};
+
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-Command",
+            "Get-CimInstance Win32_VideoController | Format-List Name, DriverVersion, DriverDate",
+        ])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn gpu_info() -> String {
+    String::new()
+}
+
+// The log and the older files moved aside by the log rotation, newest first
+pub fn log_entries(path: &Path, redactor: &LogRedactor) -> Vec<(String, Vec<u8>)> {
+    let paths = [path.to_owned()]
+        .into_iter()
+        .chain((1..).map(|index| alvr_common::rotated_log_path(path, index)))
+        .take_while(|path| path.exists());
+
+    paths
+        .filter_map(|path| {
+            let name = path.file_name()?.to_string_lossy().into_owned();
+            let content = fs::read(&path).ok()?;
+
+            Some((
+                name,
+                redactor
+                    .redact(&String::from_utf8_lossy(&content))
+                    .into_bytes(),
+            ))
+        })
+        .collect()
+}
+
+// Files included in the crash report. Missing logs are skipped.
+pub fn collect_report_entries(layout: &Layout, session: &SessionConfig) -> Vec<(String, Vec<u8>)> {
+    let redactor = LogRedactor::from_environment(session);
+
+    let mut entries = vec![("version.txt".into(), ALVR_VERSION.to_string().into_bytes())];
+
+    entries.extend(log_entries(&layout.session_log(), &redactor));
+    entries.extend(log_entries(&layout.crash_log(), &redactor));
+    entries.push((
+        "session.json".into(),
+        serde_json::to_vec_pretty(&session.redacted()).unwrap(),
+    ));
+    entries.push(("gpu.txt".into(), gpu_info().into_bytes()));
+
+    if let Ok(log) =
+        alvr_server_io::steamvr_log_dir().and_then(|dir| Ok(fs::read(dir.join("vrserver.txt"))?))
+    {
+        entries.push((
+            "vrserver.txt".into(),
+            redactor
+                .redact(tail_lines(
+                    &String::from_utf8_lossy(&log),
+                    STEAMVR_LOG_LINES,
+                ))
+                .into_bytes(),
+        ));
+    }
+
+    entries
+}
+
+pub fn write_report(writer: impl Write + Seek, entries: &[(String, Vec<u8>)]) -> Result<()> {
+    let mut zip = ZipWriter::new(writer);
+    for (name, content) in entries {
+        zip.start_file(name.as_str(), SimpleFileOptions::default())?;
+        zip.write_all(content)?;
+    }
+    zip.finish()?;
+
+    Ok(())
+}
+
+pub fn create_report(layout: &Layout, session: &SessionConfig) -> Result<PathBuf> {
+    let dir = layout.crash_reports_dir();
+    fs::create_dir_all(&dir)?;
+
+    let path = dir.join(format!(
+        "crash_report.{}.zip",
+        chrono::Local::now().format("%F.%H-%M-%S")
+    ));
+    write_report(
+        File::create(&path)?,
+        &collect_report_entries(layout, session),
+    )?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alvr_common::ConnectionState;
+    use alvr_session::ClientConnectionConfig;
+    use std::{
+        collections::HashSet,
+        io::{Cursor, Read},
+        net::IpAddr,
+    };
+    use zip::ZipArchive;
+
+    fn session_with_clients(clients: &[(&str, &str, &[&str])]) -> SessionConfig {
+        let mut session = SessionConfig::default();
+        for (hostname, display_name, ips) in clients {
+            let ips = ips
+                .iter()
+                .map(|ip| ip.parse::<IpAddr>().unwrap())
+                .collect::<HashSet<_>>();
+
+            session.client_connections.insert(
+                hostname.to_string(),
+                ClientConnectionConfig {
+                    display_name: display_name.to_string(),
+                    current_ip: ips.iter().next().copied(),
+                    manual_ips: ips,
+                    trusted: true,
+                    connection_state: ConnectionState::Disconnected,
+                    settings_overrides: vec![],
+                },
+            );
+        }
+
+        session
+    }
+
+    #[test]
+    fn test_redact_clients() {
+        let session = session_with_clients(&[
+            ("b.client.alvr", "Bob's Quest", &["192.168.1.20"]),
+            ("a.client.alvr", "", &["fe80::1"]),
+        ]);
+        let redactor = LogRedactor::new(&session, Some("/home/bob".into()));
+
+        assert_eq!(
+            redactor.redact(
+                "Bob's Quest (b.client.alvr) connected from 192.168.1.20:9944, a.client.alvr from fe80::1"
+            ),
+            "client2 (client2) connected from x.x.x.x:9944, client1 from x:x:x:x"
+        );
+        assert_eq!(
+            redactor.redact("Loaded /home/bob/.config/alvr/session.json"),
+            "Loaded ~/.config/alvr/session.json"
+        );
+
+        // The names match the redacted session
+        let redacted_session = session.redacted();
+        assert_eq!(
+            redacted_session.client_connections["client1"].manual_ips,
+            HashSet::new()
+        );
+        assert!(redacted_session.client_connections.contains_key("client2"));
+    }
+
+    #[test]
+    fn test_redact_ipv4_addresses() {
+        assert_eq!(
+            redact_ipv4_addresses("peer 10.0.0.1, gateway 10.0.0.254."),
+            "peer x.x.x.x, gateway x.x.x.x."
+        );
+        // Version numbers and out of range values are kept
+        assert_eq!(
+            redact_ipv4_addresses("ALVR 21.0.0, driver 31.0.15.3667, build 1.2.3.4.5"),
+            "ALVR 21.0.0, driver 31.0.15.3667, build 1.2.3.4.5"
+        );
+        assert_eq!(redact_ipv4_addresses("300.1.1.1"), "300.1.1.1");
+    }
+
+    #[test]
+    fn test_log_entries() {
+        let dir = env::temp_dir().join(format!("alvr_crash_report_{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        let log_path = dir.join("session_log.txt");
+
+        assert!(
+            log_entries(
+                &log_path,
+                &LogRedactor::new(&SessionConfig::default(), None)
+            )
+            .is_empty()
+        );
+
+        let session = session_with_clients(&[("client.alvr", "", &[])]);
+        fs::write(&log_path, "newest client.alvr").unwrap();
+        fs::write(
+            alvr_common::rotated_log_path(&log_path, 1),
+            "older 192.168.0.2",
+        )
+        .unwrap();
+        fs::write(alvr_common::rotated_log_path(&log_path, 2), "oldest").unwrap();
+        // Not reached, after a missing file
+        fs::write(alvr_common::rotated_log_path(&log_path, 4), "stale").unwrap();
+
+        let entries = log_entries(&log_path, &LogRedactor::new(&session, None));
+        fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(
+            entries,
+            [
+                ("session_log.txt".to_string(), b"newest client1".to_vec()),
+                ("session_log.1.txt".to_string(), b"older x.x.x.x".to_vec()),
+                ("session_log.2.txt".to_string(), b"oldest".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tail_lines() {
+        assert_eq!(tail_lines("a\nb\nc\n", 2), "b\nc");
+        assert_eq!(tail_lines("a\nb", 5), "a\nb");
+        assert_eq!(tail_lines("", 5), "");
+    }
+
+    #[test]
+    fn test_report_round_trip() {
+        let session = SessionConfig::default();
+        let entries = vec![
+            ("version.txt".to_string(), b"1.0.0".to_vec()),
+            (
+                "session.json".to_string(),
+                serde_json::to_vec_pretty(&session.redacted()).unwrap(),
+            ),
+        ];
+
+        let mut buffer = Cursor::new(vec![]);
+        write_report(&mut buffer, &entries).unwrap();
+
+        let mut archive = ZipArchive::new(buffer).unwrap();
+        assert_eq!(archive.len(), entries.len());
+        for (name, content) in &entries {
+            let mut file_content = vec![];
+            archive
+                .by_name(name)
+                .unwrap()
+                .read_to_end(&mut file_content)
+                .unwrap();
+            assert_eq!(file_content, *content);
+        }
+
+        // The redacted session can still be loaded
+        let mut session_json = String::new();
+        archive
+            .by_name("session.json")
+            .unwrap()
+            .read_to_string(&mut session_json)
+            .unwrap();
+        serde_json::from_str::<SessionConfig>(&session_json).unwrap();
+    }
+}
@@ -0,0 +1,60 @@
+use alvr_common::AxisResponse;
+use alvr_gui_common::theme;
+use alvr_session::{AnalogInputProcessingMode, Settings, settings_schema::Switch};
+use eframe::{
+    egui::{Color32, Frame, Rect, Shape, Stroke, Ui, pos2, vec2},
+    emath::RectTransform,
+};
+
+const CURVE_SIZE: f32 = 100.0;
+const CURVE_SAMPLES: usize = 100;
+
+// Curves of the custom analog input processing, empty if it is not enabled
+pub fn custom_analog_responses(settings: &Settings) -> Vec<(&'static str, AxisResponse)> {
+    if let Switch::Enabled(config) = &settings.headset.controllers
+        && let Switch::Enabled(AnalogInputProcessingMode::Custom(axes)) = &config.analog_processing
+    {
+        vec![
+            ("Thumbsticks", axes.thumbsticks.axis_response()),
+            ("Triggers", axes.triggers.axis_response()),
+            ("Grips", axes.grips.axis_response()),
+        ]
+    } else {
+        vec![]
+    }
+}
+
+pub fn analog_curves_ui(ui: &mut Ui, responses: &[(&str, AxisResponse)]) {
+    ui.horizontal(|ui| {
+        for (name, response) in responses {
+            ui.vertical(|ui| {
+                ui.label(*name);
+
+                Frame::canvas(ui.style()).show(ui, |ui| {
+                    let (_, canvas_rect) = ui.allocate_space(vec2(CURVE_SIZE, CURVE_SIZE));
+                    let to_screen = RectTransform::from_to(
+                        Rect::from_x_y_ranges(0.0..=1.0, 1.0..=0.0),
+                        canvas_rect,
+                    );
+                    let painter = ui.painter().with_clip_rect(canvas_rect);
+
+                    // Unprocessed input, for reference
+                    painter.add(Shape::line(
+                        vec![to_screen * pos2(0.0, 0.0), to_screen * pos2(1.0, 1.0)],
+                        Stroke::new(1.0, Color32::GRAY),
+                    ));
+
+                    let points = (0..=CURVE_SAMPLES)
+                        .map(|i| {
+                            let input = i as f32 / CURVE_SAMPLES as f32;
+                            let output = alvr_common::process_axis_value(input, response);
+
+                            to_screen * pos2(input, output)
+                        })
+                        .collect();
+                    painter.add(Shape::line(points, Stroke::new(2.0, theme::FG)));
+                });
+            });
+        }
+    });
+}
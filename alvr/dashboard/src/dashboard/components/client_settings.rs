@@ -0,0 +1,156 @@
+use super::settings::{MIN_COLUMN_SIZE, TopLevelEntry};
+use crate::dashboard::ServerRequest;
+use alvr_gui_common::theme;
+use alvr_packets::{ClientConnectionsAction, PathSegment};
+use alvr_session::{SessionConfig, SettingsOverride};
+use eframe::{
+    egui::{self, Frame, Grid, RichText, ScrollArea, Window},
+    emath::Align2,
+};
+use serde_json as json;
+
+// Shows the settings in effect for a client, with its overrides applied on top of the global
+// settings. Each edit becomes an override of the edited subtree.
+pub struct ClientSettingsWindow {
+    hostname: String,
+    selected_top_tab_id: String,
+    top_level_entries: Vec<TopLevelEntry>,
+    overrides: Vec<SettingsOverride>,
+    session_settings_json: json::Value,
+}
+
+impl ClientSettingsWindow {
+    pub fn new(hostname: String, session: &SessionConfig) -> Self {
+        let top_level_entries = super::settings::top_level_entries();
+
+        let mut window = Self {
+            hostname,
+            selected_top_tab_id: top_level_entries[0].id.id.clone(),
+            top_level_entries,
+            overrides: vec![],
+            session_settings_json: json::Value::Null,
+        };
+        window.update_session(session);
+
+        window
+    }
+
+    pub fn update_session(&mut self, session: &SessionConfig) {
+        self.overrides = session
+            .client_connections
+            .get(&self.hostname)
+            .map(|config| config.settings_overrides.clone())
+            .unwrap_or_default();
+
+        self.session_settings_json = json::to_value(&session.session_settings).unwrap();
+        alvr_session::apply_settings_overrides(&mut self.session_settings_json, &self.overrides);
+    }
+
+    fn set_overrides_request(&self) -> ServerRequest {
+        ServerRequest::UpdateClientList {
+            hostname: self.hostname.clone(),
+            action: ClientConnectionsAction::SetSettingsOverrides(self.overrides.clone()),
+        }
+    }
+
+    // Returns false when the window is closed
+    pub fn ui(&mut self, ctx: &egui::Context, requests: &mut Vec<ServerRequest>) -> bool {
+        let mut open = true;
+
+        Window::new(format!("Settings for {}", self.hostname))
+            .anchor(Align2::CENTER_CENTER, (0.0, 0.0))
+            .collapsible(false)
+            .default_height(500.0)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(
+                    "Changes made here only apply to this client, on top of the global settings.",
+                );
+                ui.add_space(theme::FRAME_PADDING);
+
+                Frame::group(ui.style())
+                    .fill(theme::DARKER_BG)
+                    .inner_margin(theme::FRAME_PADDING)
+                    .show(ui, |ui| {
+                        ui.horizontal_wrapped(|ui| {
+                            for entry in &self.top_level_entries {
+                                ui.selectable_value(
+                                    &mut self.selected_top_tab_id,
+                                    entry.id.id.clone(),
+                                    RichText::new(entry.id.display.clone()).raised(),
+                                );
+                            }
+                        })
+                    });
+
+                if !self.overrides.is_empty() {
+                    ui.add_space(theme::FRAME_PADDING);
+                    ui.label("Overridden settings:");
+
+                    let mut removed_idx = None;
+                    for (idx, settings_override) in self.overrides.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            if ui.button("Reset").clicked() {
+                                removed_idx = Some(idx);
+                            }
+                            ui.label(RichText::new(settings_override.path.join(".")).monospace());
+                        });
+                    }
+
+                    if let Some(idx) = removed_idx {
+                        self.overrides.remove(idx);
+                        requests.push(self.set_overrides_request());
+                    }
+                }
+
+                ui.add_space(theme::FRAME_PADDING);
+
+                let mut response = None;
+                ScrollArea::new([false, true])
+                    .id_salt(format!("{}_client_scroll", self.hostname))
+                    .show(ui, |ui| {
+                        Grid::new(format!("{}_client_grid", self.hostname))
+                            .striped(true)
+                            .num_columns(2)
+                            .min_col_width(MIN_COLUMN_SIZE)
+                            .show(ui, |ui| {
+                                let entry = self
+                                    .top_level_entries
+                                    .iter_mut()
+                                    .find(|entry| entry.id.id == self.selected_top_tab_id)
+                                    .unwrap();
+
+                                response = entry.control.ui(
+                                    ui,
+                                    &mut self.session_settings_json[&entry.id.id],
+                                    false,
+                                );
+
+                                ui.end_row();
+                            })
+                    });
+
+                // The first segment of the path is "session_settings"
+                if let Some(response) = response {
+                    let path = response.path[1..]
+                        .iter()
+                        .map(|segment| match segment {
+                            PathSegment::Name(name) => name.clone(),
+                            PathSegment::Index(idx) => idx.to_string(),
+                        })
+                        .collect();
+
+                    alvr_session::insert_settings_override(
+                        &mut self.overrides,
+                        SettingsOverride {
+                            path,
+                            value: response.value,
+                        },
+                    );
+                    requests.push(self.set_overrides_request());
+                }
+            });
+
+        open
+    }
+}
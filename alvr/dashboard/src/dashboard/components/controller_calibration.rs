@@ -0,0 +1,258 @@
+use crate::dashboard::ServerRequest;
+use alvr_common::{
+    HAND_LEFT_PATH, HAND_RIGHT_PATH, Pose,
+    glam::{EulerRot, Vec3},
+};
+use alvr_events::TrackingEvent;
+use alvr_gui_common::theme;
+use alvr_packets::PathValuePair;
+use alvr_session::{ControllersConfig, SessionConfig};
+use eframe::egui::{self, Frame, Grid, RichText, Slider, Ui};
+use serde_json as json;
+
+// Tracking events older than this are considered stale
+const TRACKING_TIMEOUT_S: f64 = 1.0;
+
+const PROFILE_OFFSETS_PATH: &str =
+    "session_settings.headset.controllers.content.profile_pose_offsets.content";
+
+fn position_label(position: Vec3) -> String {
+    format!(
+        "{:.1}, {:.1}, {:.1} cm",
+        position.x * 100.0,
+        position.y * 100.0,
+        position.z * 100.0
+    )
+}
+
+fn rotation_label(pose: Pose) -> String {
+    let (x, y, z) = pose.orientation.to_euler(EulerRot::XYZ);
+
+    format!(
+        "{:.1}°, {:.1}°, {:.1}°",
+        x.to_degrees(),
+        y.to_degrees(),
+        z.to_degrees()
+    )
+}
+
+// Computes suggested per-profile offsets while the user holds the controllers side by side. The
+// tracking events are sent by the server only when tracking logging is enabled.
+pub struct ControllerCalibration {
+    session: Option<SessionConfig>,
+    controllers_config: Option<ControllersConfig>,
+    log_tracking: bool,
+    left_pose: Option<Pose>,
+    right_pose: Option<Pose>,
+    last_tracking_time: Option<f64>,
+    expected_separation_cm: f32,
+}
+
+impl ControllerCalibration {
+    pub fn new() -> Self {
+        Self {
+            session: None,
+            controllers_config: None,
+            log_tracking: false,
+            left_pose: None,
+            right_pose: None,
+            last_tracking_time: None,
+            expected_separation_cm: 8.0,
+        }
+    }
+
+    pub fn update_session(&mut self, session: &SessionConfig) {
+        let settings = session.to_settings();
+
+        self.controllers_config = settings.headset.controllers.into_option();
+        self.log_tracking = settings.extra.logging.log_tracking;
+        self.session = Some(session.clone());
+    }
+
+    pub fn update_tracking(&mut self, event: &TrackingEvent, time: f64) {
+        for (path, motion) in &event.device_motions {
+            if path == HAND_LEFT_PATH {
+                self.left_pose = Some(motion.pose);
+            } else if path == HAND_RIGHT_PATH {
+                self.right_pose = Some(motion.pose);
+            }
+        }
+
+        self.last_tracking_time = Some(time);
+    }
+
+    // Offset currently applied to the left controller, and the profile part of it
+    fn current_offsets(config: &ControllersConfig) -> (Pose, Pose) {
+        let base_offset = alvr_common::pose_from_offset(
+            config.left_controller_position_offset,
+            config.left_controller_rotation_offset,
+        );
+        let profile_offset = config
+            .profile_pose_offsets
+            .iter()
+            .find(|offset| offset.emulation_mode == config.emulation_mode)
+            .map(|offset| {
+                alvr_common::pose_from_offset(offset.position_offset, offset.rotation_offset)
+            })
+            .unwrap_or(Pose::IDENTITY);
+
+        (base_offset * profile_offset, profile_offset)
+    }
+
+    fn apply_profile_offset_request(&self, profile_offset: Pose) -> Option<ServerRequest> {
+        let session = self.session.as_ref()?;
+        let config = self.controllers_config.as_ref()?;
+
+        let session_settings = json::to_value(&session.session_settings).ok()?;
+        let controllers = session_settings.pointer("/headset/controllers/content")?;
+        let mut offsets = controllers["profile_pose_offsets"]["content"]
+            .as_array()?
+            .clone();
+
+        let (position, rotation) = alvr_common::offset_from_pose(profile_offset);
+
+        // The entries of the session have the same order of the ones of the settings
+        let entry = if let Some(idx) = config
+            .profile_pose_offsets
+            .iter()
+            .position(|offset| offset.emulation_mode == config.emulation_mode)
+        {
+            &mut offsets[idx]
+        } else {
+            let mut entry = controllers["profile_pose_offsets"]["element"].clone();
+            entry["emulation_mode"] = controllers["emulation_mode"].clone();
+            offsets.push(entry);
+
+            offsets.last_mut().unwrap()
+        };
+        entry["position_offset"]["content"] = json::to_value(position).ok()?;
+        entry["rotation_offset"]["content"] = json::to_value(rotation).ok()?;
+
+        Some(ServerRequest::SetSessionValues(vec![PathValuePair {
+            path: alvr_packets::parse_path(PROFILE_OFFSETS_PATH),
+            value: json::Value::Array(offsets),
+        }]))
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) -> Option<ServerRequest> {
+        let mut request = None;
+        let mut applied_offset = None;
+
+        Frame::group(ui.style())
+            .fill(theme::SECTION_BG)
+            .inner_margin(egui::vec2(
+                theme::FRAME_PADDING + theme::FRAME_TEXT_SPACING,
+                theme::FRAME_PADDING,
+            ))
+            .show(ui, |ui| {
+                ui.heading("Controller Offset Calibration");
+                ui.label(
+                    "Hold the controllers side by side, pointing forward with the grips parallel, \
+                    and set their distance below. The suggested offset corrects the rotation around \
+                    the vertical and forward axes and the horizontal position of the grip pose, \
+                    for the current emulation mode.",
+                );
+                ui.add_space(theme::FRAME_PADDING);
+
+                let Some(config) = &self.controllers_config else {
+                    ui.label("Controllers are disabled.");
+                    return;
+                };
+
+                if !self.log_tracking {
+                    ui.horizontal(|ui| {
+                        ui.label("The calibration requires tracking logging.");
+                        if ui.button("Enable").clicked() {
+                            request = Some(ServerRequest::SetSessionValues(vec![PathValuePair {
+                                path: alvr_packets::parse_path(
+                                    "session_settings.extra.logging.log_tracking",
+                                ),
+                                value: json::Value::Bool(true),
+                            }]));
+                        }
+                    });
+                    return;
+                }
+
+                // The poses are updated live
+                ui.ctx().request_repaint();
+
+                let time = ui.input(|input| input.time);
+                let (Some(left_pose), Some(right_pose)) = (self.left_pose, self.right_pose) else {
+                    ui.label("Waiting for the controllers...");
+                    return;
+                };
+                if self
+                    .last_tracking_time
+                    .is_none_or(|last_time| time - last_time > TRACKING_TIMEOUT_S)
+                {
+                    ui.label("Waiting for the controllers...");
+                    return;
+                }
+
+                let (left_offset, profile_offset) = Self::current_offsets(config);
+                let delta = alvr_common::controllers_delta(
+                    left_pose * left_offset,
+                    right_pose * left_offset.mirrored_x(),
+                );
+                let suggested_offset = profile_offset
+                    * alvr_common::suggested_offset_correction(
+                        delta,
+                        self.expected_separation_cm / 100.0,
+                    );
+
+                Grid::new("controller-calibration")
+                    .num_columns(2)
+                    .spacing(egui::vec2(8.0, 8.0))
+                    .show(ui, |ui| {
+                        ui.label("Controllers distance");
+                        ui.add(
+                            Slider::new(&mut self.expected_separation_cm, 0.0..=30.0)
+                                .step_by(0.5)
+                                .suffix(" cm"),
+                        );
+                        ui.end_row();
+
+                        ui.label("Right controller position");
+                        ui.label(RichText::new(position_label(delta.position)).monospace());
+                        ui.end_row();
+
+                        ui.label("Right controller rotation");
+                        ui.label(RichText::new(rotation_label(delta)).monospace());
+                        ui.end_row();
+
+                        ui.label("Current profile offset");
+                        ui.label(
+                            RichText::new(format!(
+                                "{} / {}",
+                                position_label(profile_offset.position),
+                                rotation_label(profile_offset)
+                            ))
+                            .monospace(),
+                        );
+                        ui.end_row();
+
+                        ui.label("Suggested profile offset");
+                        ui.label(
+                            RichText::new(format!(
+                                "{} / {}",
+                                position_label(suggested_offset.position),
+                                rotation_label(suggested_offset)
+                            ))
+                            .monospace(),
+                        );
+                        ui.end_row();
+                    });
+
+                if ui.button("Apply suggested offset").clicked() {
+                    applied_offset = Some(suggested_offset);
+                }
+            });
+
+        if let Some(offset) = applied_offset {
+            request = self.apply_profile_offset_request(offset);
+        }
+
+        request
+    }
+}
@@ -0,0 +1,84 @@
+use super::CloseAction;
+use crate::dashboard::ServerRequest;
+use alvr_gui_common::ModalButton;
+use alvr_packets::PathValuePair;
+use eframe::egui::{Context, OpenUrl, RichText, Ui};
+use std::path::PathBuf;
+
+const FORCE_SOFTWARE_ENCODING_PATH: &str =
+    "session_settings.video.encoder_config.software.force_software_encoding";
+
+pub struct CrashPopup {
+    report_path: Option<PathBuf>,
+    crash_loop: bool,
+    hardware_encoding: bool,
+}
+
+impl CrashPopup {
+    pub fn new(report_path: Option<PathBuf>, crash_loop: bool, hardware_encoding: bool) -> Self {
+        Self {
+            report_path,
+            crash_loop,
+            hardware_encoding,
+        }
+    }
+
+    pub fn ui(&self, context: &Context) -> Option<CloseAction> {
+        // The hardware encoder is the most common cause of driver crashes
+        let software_encoding_button = ModalButton::Custom("Use software encoding".into());
+        let buttons = if self.hardware_encoding {
+            vec![software_encoding_button.clone(), ModalButton::Close]
+        } else {
+            vec![ModalButton::Close]
+        };
+
+        let result = alvr_gui_common::modal(
+            context,
+            "SteamVR crashed",
+            Some(|ui: &mut Ui| {
+                if self.crash_loop {
+                    ui.label(
+                        RichText::new("SteamVR crashed repeatedly in the last minutes.").strong(),
+                    );
+                } else {
+                    ui.label("SteamVR closed unexpectedly while ALVR was running.");
+                }
+
+                if let Some(path) = &self.report_path {
+                    ui.label("A report was saved. Please attach it to your bug report:");
+                    ui.label(RichText::new(path.to_string_lossy()).monospace());
+
+                    if let Some(dir) = path.parent()
+                        && ui.button("Open reports directory").clicked()
+                    {
+                        ui.ctx().open_url(OpenUrl::same_tab(format!(
+                            "file://{}",
+                            dir.to_string_lossy()
+                        )));
+                    }
+                }
+
+                if self.hardware_encoding {
+                    ui.add_space(5.0);
+                    ui.label(
+                        "If the crash happens when a headset connects, try switching to software \
+                        encoding.",
+                    );
+                }
+            }),
+            &buttons,
+            Some(490.0),
+        )?;
+
+        if result == software_encoding_button {
+            Some(CloseAction::CloseWithRequest(
+                ServerRequest::SetSessionValues(vec![PathValuePair {
+                    path: alvr_packets::parse_path(FORCE_SOFTWARE_ENCODING_PATH),
+                    value: serde_json::Value::Bool(true),
+                }]),
+            ))
+        } else {
+            Some(CloseAction::Close)
+        }
+    }
+}
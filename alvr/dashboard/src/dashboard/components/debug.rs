@@ -1,7 +1,8 @@
 use crate::dashboard::ServerRequest;
-use eframe::egui::Ui;
+use eframe::egui::{RichText, Ui};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-pub fn debug_tab_ui(ui: &mut Ui) -> Option<ServerRequest> {
+pub fn debug_tab_ui(ui: &mut Ui, mirror_open: &mut bool) -> Option<ServerRequest> {
     let mut request = None;
 
     ui.label(
@@ -27,5 +28,83 @@ For that, use other means of recording, for example through headset or desktop V
         }
     });
 
+    ui.separator();
+
+    ui.label(
+        "The client saves its log to a file, which can be retrieved while streaming. It is saved in
+the server log folder. Without a connection, use ADB to pull client_log.txt from the files folder of
+the client app in /sdcard/Android/data.",
+    );
+
+    if ui.button("Retrieve client log").clicked() {
+        request = Some(ServerRequest::RequestClientLog);
+    }
+
+    ui.separator();
+
+    ui.label(
+        "External trackers are aligned with the playspace using the headset pose reported by both
+systems. While streaming, start the calibration then move and turn your head until it completes.",
+    );
+
+    if ui.button("Calibrate external trackers").clicked() {
+        request = Some(ServerRequest::CalibrateExternalTrackers);
+    }
+
+    ui.separator();
+
+    ui.label(
+        "A trace records the timing of each stage of the pipeline on both the streamer and the client,
+aligned to the synchronized clock. It is saved in the server log folder and can be opened with
+chrome://tracing or ui.perfetto.dev.",
+    );
+
+    if ui.button("Capture 5 seconds of trace").clicked() {
+        request = Some(ServerRequest::CaptureTrace);
+    }
+
+    ui.separator();
+
+    ui.label(
+        "The mirror shows what is sent to the headset, at a reduced rate and resolution. Frames are
+read back only while the window is open.",
+    );
+
+    ui.toggle_value(mirror_open, "Show headset mirror");
+
+    ui.separator();
+
+    ui.label(
+        "The test pattern replaces the game image with color bars, gray ramps, a moving bar and a
+clock. Use it to check that the stream works without a game running. If SteamVR is not running, the
+pattern is streamed by a separate process, encoded with H.264 on the CPU and without foveated
+encoding, HDR, depth or space warp. All the gray steps should be distinguishable, otherwise the
+color range or transfer function settings don't match the client. To estimate the end-to-end
+latency, take a picture showing both the clock in the headset and the one below: the difference is
+the latency.",
+    );
+
+    ui.columns(4, |ui| {
+        if ui[0].button("Start test pattern").clicked() {
+            request = Some(ServerRequest::StartTestPattern);
+        }
+
+        if ui[1].button("Stop test pattern").clicked() {
+            request = Some(ServerRequest::StopTestPattern);
+        }
+
+        // Same format as the clock drawn by the streamer
+        let time_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO)
+            .as_millis();
+        ui[2].label(
+            RichText::new(format!("{:02}.{:03}", time_ms / 1000 % 100, time_ms % 1000))
+                .monospace()
+                .size(20.0),
+        );
+        ui[2].ctx().request_repaint();
+    });
+
     request
 }
@@ -1,14 +1,19 @@
+use super::{ClientSettingsWindow, ControllerCalibration};
 use crate::dashboard::ServerRequest;
 use alvr_common::ConnectionState;
+use alvr_events::TrackingEvent;
 use alvr_gui_common::theme::{self, log_colors};
-use alvr_packets::ClientConnectionsAction;
+use alvr_packets::{ClientConnectionsAction, WifiLinkInfo};
 use alvr_session::{ClientConnectionConfig, SessionConfig};
 use alvr_sockets::WIRED_CLIENT_HOSTNAME;
+#[cfg(not(target_arch = "wasm32"))]
+use alvr_system_info::ClientFlavor;
 use eframe::{
     egui::{self, Frame, Grid, Layout, ProgressBar, RichText, TextEdit, Ui, Window},
     emath::{Align, Align2},
     epaint::Color32,
 };
+use std::collections::{HashMap, HashSet};
 
 struct EditPopupState {
     new_devices: bool,
@@ -21,6 +26,16 @@ pub struct DevicesTab {
     trusted_devices: Option<Vec<(String, ClientConnectionConfig)>>,
     edit_popup_state: Option<EditPopupState>,
     adb_download_progress: Option<f32>,
+    wifi_links: HashMap<String, WifiLinkInfo>,
+    // Clients that reported the face, eye and body data as blocked
+    biometrics_blocked: HashSet<String>,
+    controller_calibration: ControllerCalibration,
+    session: Option<SessionConfig>,
+    client_settings_window: Option<ClientSettingsWindow>,
+    #[cfg(not(target_arch = "wasm32"))]
+    client_installer: crate::client_installer::ClientInstaller,
+    #[cfg(not(target_arch = "wasm32"))]
+    wired_client_flavor: ClientFlavor,
 }
 
 impl DevicesTab {
@@ -30,6 +45,15 @@ impl DevicesTab {
             trusted_devices: None,
             edit_popup_state: None,
             adb_download_progress: None,
+            wifi_links: HashMap::new(),
+            biometrics_blocked: HashSet::new(),
+            controller_calibration: ControllerCalibration::new(),
+            session: None,
+            client_settings_window: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            client_installer: crate::client_installer::ClientInstaller::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            wired_client_flavor: ClientFlavor::Github,
         }
     }
 
@@ -43,12 +67,43 @@ impl DevicesTab {
 
         self.trusted_devices = Some(trusted_clients);
         self.new_devices = Some(untrusted_clients);
+
+        self.controller_calibration.update_session(session);
+        if let Some(window) = &mut self.client_settings_window {
+            window.update_session(session);
+        }
+        self.session = Some(session.clone());
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.wired_client_flavor = session.to_settings().connection.wired_client_type;
+        }
+    }
+
+    pub fn update_tracking(&mut self, event: &TrackingEvent, time: f64) {
+        self.controller_calibration.update_tracking(event, time);
     }
 
     pub fn update_adb_download_progress(&mut self, progress: f32) {
         self.adb_download_progress = Some(progress);
     }
 
+    pub fn update_wifi_link(&mut self, hostname: String, link: Option<WifiLinkInfo>) {
+        if let Some(link) = link {
+            self.wifi_links.insert(hostname, link);
+        } else {
+            self.wifi_links.remove(&hostname);
+        }
+    }
+
+    pub fn update_biometrics_blocked(&mut self, hostname: String, blocked: bool) {
+        if blocked {
+            self.biometrics_blocked.insert(hostname);
+        } else {
+            self.biometrics_blocked.remove(&hostname);
+        }
+    }
+
     pub fn ui(&mut self, ui: &mut Ui, connected_to_server: bool) -> Vec<ServerRequest> {
         let mut requests = vec![];
 
@@ -84,6 +139,7 @@ impl DevicesTab {
                 });
         }
 
+        let mut client_settings_hostname = None;
         ui.vertical_centered_justified(|ui| {
             if let Some(clients) = &mut self.trusted_devices
                 && let Some(request) = wired_client_section(
@@ -92,11 +148,21 @@ impl DevicesTab {
                         .iter()
                         .find(|(hostname, _)| hostname == WIRED_CLIENT_HOSTNAME),
                     self.adb_download_progress,
+                    &mut client_settings_hostname,
                 )
             {
                 requests.push(request);
             }
 
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                ui.add_space(theme::FRAME_PADDING);
+
+                self.client_installer
+                    .update(ui.ctx(), &self.wired_client_flavor);
+                headset_client_section(ui, &self.client_installer);
+            }
+
             ui.add_space(theme::FRAME_PADDING);
 
             if let Some(clients) = &self.new_devices
@@ -115,13 +181,36 @@ impl DevicesTab {
                         .filter(|(hostname, _)| hostname != WIRED_CLIENT_HOSTNAME)
                         .collect::<Vec<_>>()
                         .as_slice(),
+                    &self.wifi_links,
+                    &self.biometrics_blocked,
                     &mut self.edit_popup_state,
+                    &mut client_settings_hostname,
                 )
             {
                 requests.push(request);
             }
+
+            if connected_to_server {
+                ui.add_space(theme::FRAME_PADDING);
+
+                if let Some(request) = self.controller_calibration.ui(ui) {
+                    requests.push(request);
+                }
+            }
         });
 
+        if let Some(hostname) = client_settings_hostname
+            && let Some(session) = &self.session
+        {
+            self.client_settings_window = Some(ClientSettingsWindow::new(hostname, session));
+        }
+
+        if let Some(window) = &mut self.client_settings_window
+            && !window.ui(ui.ctx(), &mut requests)
+        {
+            self.client_settings_window = None;
+        }
+
         if let Some(mut state) = self.edit_popup_state.take() {
             Window::new("Edit connection")
                 .anchor(Align2::CENTER_CENTER, (0.0, 0.0))
@@ -190,6 +279,7 @@ fn wired_client_section(
     ui: &mut Ui,
     maybe_client: Option<&(String, ClientConnectionConfig)>,
     adb_download_progress: Option<f32>,
+    client_settings_hostname: &mut Option<String>,
 ) -> Option<ServerRequest> {
     let mut request = None;
 
@@ -239,7 +329,7 @@ fn wired_client_section(
                                 ui.add(ProgressBar::new(progress).animate(true).show_percentage());
                             });
                             ui.end_row();
-                        } else if let Some((_, data)) = maybe_client {
+                        } else if let Some((hostname, data)) = maybe_client {
                             ui.horizontal(|ui| {
                                 ui.label(&data.display_name);
                             });
@@ -247,6 +337,14 @@ fn wired_client_section(
                                 connection_label(ui, &data.connection_state);
                             });
                             ui.end_row();
+
+                            ui.label("");
+                            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                                if ui.button("Settings").clicked() {
+                                    *client_settings_hostname = Some(hostname.clone());
+                                }
+                            });
+                            ui.end_row();
                         }
                     });
             });
@@ -255,6 +353,154 @@ fn wired_client_section(
     request
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+fn headset_client_section(ui: &mut Ui, installer: &crate::client_installer::ClientInstaller) {
+    use crate::client_installer::InstallerTask;
+    use alvr_adb::WiredDeviceStatus;
+    use alvr_common::ALVR_VERSION;
+
+    let mut task = None;
+
+    let state = installer.state().lock();
+    let ready = state
+        .headset
+        .as_ref()
+        .is_some_and(|h| matches!(h.device, WiredDeviceStatus::Ready { .. }));
+    let client = state.headset.as_ref().and_then(|h| h.client.as_ref());
+
+    Frame::group(ui.style())
+        .fill(theme::SECTION_BG)
+        .inner_margin(egui::vec2(
+            theme::FRAME_PADDING + theme::FRAME_TEXT_SPACING,
+            theme::FRAME_PADDING,
+        ))
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading("Client App over USB");
+
+                // Extend to the right
+                ui.with_layout(Layout::right_to_left(Align::Center), |_| ());
+            });
+
+            match state.headset.as_ref().map(|h| &h.device) {
+                None => {
+                    ui.label(
+                        "Connect the headset with a USB cable to install or update the client.",
+                    );
+                }
+                Some(WiredDeviceStatus::NotFound) => {
+                    ui.label("No headset is connected over USB.");
+                }
+                Some(WiredDeviceStatus::Unauthorized) => {
+                    ui.colored_label(
+                        log_colors::WARNING_LIGHT,
+                        "Allow USB debugging from the prompt shown in the headset.",
+                    );
+                }
+                Some(WiredDeviceStatus::NoPermissions) => {
+                    ui.colored_label(
+                        log_colors::WARNING_LIGHT,
+                        "No permission to access the USB device, check the udev rules.",
+                    );
+                }
+                Some(WiredDeviceStatus::Ready { model, .. }) => {
+                    ui.label(format!(
+                        "Connected headset: {}",
+                        model.as_deref().unwrap_or("Unknown model")
+                    ));
+
+                    match client {
+                        Some(client) if client.is_up_to_date() => {
+                            ui.label(format!(
+                                "The client {} is up to date (v{})",
+                                client.application_id, *ALVR_VERSION
+                            ));
+                        }
+                        Some(client) => {
+                            ui.colored_label(
+                                log_colors::WARNING_LIGHT,
+                                format!(
+                                    "Installed client: {} v{}, streamer: v{}",
+                                    client.application_id,
+                                    client.version.as_deref().unwrap_or("unknown"),
+                                    *ALVR_VERSION
+                                ),
+                            );
+                        }
+                        None => {
+                            ui.label("The client is not installed.");
+                        }
+                    }
+                }
+            }
+
+            if let Some((message, progress)) = &state.progress {
+                ui.horizontal(|ui| {
+                    ui.label(message);
+                    if let Some(progress) = progress {
+                        ui.add(ProgressBar::new(*progress).animate(true).show_percentage());
+                    } else {
+                        ui.spinner();
+                    }
+                });
+            } else {
+                ui.horizontal(|ui| {
+                    if ready {
+                        let install_label = match client {
+                            Some(client) if client.is_up_to_date() => "Reinstall client",
+                            Some(_) => "Update client",
+                            None => "Install client",
+                        };
+                        if ui.button(install_label).clicked() {
+                            task = Some(InstallerTask::Install {
+                                uninstall_first: false,
+                            });
+                        }
+
+                        if client.is_some() {
+                            if ui.button("Launch client").clicked() {
+                                task = Some(InstallerTask::Launch);
+                            }
+                            if ui.button("Grant permissions").clicked() {
+                                task = Some(InstallerTask::GrantPermissions);
+                            }
+                        }
+                    } else if ui.button("Check for a headset").clicked() {
+                        task = Some(InstallerTask::Refresh);
+                    }
+                });
+
+                if state.requires_uninstall && ready {
+                    ui.colored_label(
+                        log_colors::WARNING_LIGHT,
+                        "The installed client must be removed first. This deletes its settings.",
+                    );
+                    if ui.button("Uninstall and install").clicked() {
+                        task = Some(InstallerTask::Install {
+                            uninstall_first: true,
+                        });
+                    }
+                }
+            }
+
+            match &state.result {
+                Some(Ok(message)) => {
+                    ui.label(message);
+                }
+                Some(Err(message)) => {
+                    ui.colored_label(log_colors::ERROR_LIGHT, message);
+                }
+                None => (),
+            }
+        });
+
+    drop(state);
+
+    if let Some(task) = task {
+        installer.run(task);
+    }
+}
+
 fn new_clients_section(
     ui: &mut Ui,
     clients: &[(String, ClientConnectionConfig)],
@@ -306,7 +552,10 @@ fn new_clients_section(
 fn trusted_clients_section(
     ui: &mut Ui,
     clients: &[&(String, ClientConnectionConfig)],
+    wifi_links: &HashMap<String, WifiLinkInfo>,
+    biometrics_blocked: &HashSet<String>,
     edit_popup_state: &mut Option<EditPopupState>,
+    client_settings_hostname: &mut Option<String>,
 ) -> Option<ServerRequest> {
     let mut request = None;
 
@@ -361,6 +610,9 @@ fn trusted_clients_section(
                                             action: ClientConnectionsAction::RemoveEntry,
                                         });
                                     }
+                                    if ui.button("Settings").clicked() {
+                                        *client_settings_hostname = Some(hostname.clone());
+                                    }
                                     if ui.button("Edit").clicked() {
                                         *edit_popup_state = Some(EditPopupState {
                                             new_devices: false,
@@ -373,6 +625,27 @@ fn trusted_clients_section(
                                         });
                                     }
                                 });
+
+                                // The last link is stale once disconnected
+                                let connected = matches!(
+                                    data.connection_state,
+                                    ConnectionState::Connected | ConnectionState::Streaming
+                                );
+                                if let Some(link) = wifi_links.get(hostname)
+                                    && connected
+                                {
+                                    ui.end_row();
+
+                                    ui.label(format!("Wi-Fi: {link}"));
+                                }
+                                if biometrics_blocked.contains(hostname) && connected {
+                                    ui.end_row();
+
+                                    ui.colored_label(
+                                        log_colors::WARNING_LIGHT,
+                                        "Privacy mode: face, eye and body data blocked",
+                                    );
+                                }
                             });
                     });
             }
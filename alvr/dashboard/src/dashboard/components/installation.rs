@@ -1,12 +1,37 @@
 use crate::dashboard::ServerRequest;
-use alvr_gui_common::theme;
-use eframe::egui::{Frame, Grid, RichText, ScrollArea, Ui};
+use alvr_events::SteamvrVideoSettings;
+use alvr_gui_common::theme::{self, log_colors};
+use alvr_session::{FrameSize, Settings};
+use eframe::egui::{Button, Frame, Grid, RichText, ScrollArea, Slider, Ui};
 use std::{
     path::PathBuf,
     time::{Duration, Instant},
 };
 
 const DRIVER_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+// Limits of the SteamVR video settings
+const MIN_STEAMVR_RESOLUTION_SCALE: f32 = 0.2;
+const MAX_STEAMVR_RESOLUTION_SCALE: f32 = 5.0;
+// Ratios of the rendered to the streamed resolution, per axis, outside of which the warning is shown
+const MAX_RENDER_TO_STREAM_RATIO: f32 = 1.5;
+const MIN_RENDER_TO_STREAM_RATIO: f32 = 0.75;
+
+fn resolution_scale_slider(ui: &mut Ui, scale: &mut f32) {
+    ui.add(
+        Slider::new(
+            scale,
+            MIN_STEAMVR_RESOLUTION_SCALE..=MAX_STEAMVR_RESOLUTION_SCALE,
+        )
+        .step_by(0.01)
+        .custom_formatter(|value, _| format!("{:.0}%", value * 100.0))
+        .custom_parser(|text| {
+            text.trim_end_matches('%')
+                .parse::<f64>()
+                .ok()
+                .map(|v| v / 100.0)
+        }),
+    );
+}
 
 pub enum InstallationTabRequest {
     OpenSetupWizard,
@@ -16,6 +41,10 @@ pub enum InstallationTabRequest {
 pub struct InstallationTab {
     drivers: Vec<PathBuf>,
     last_update_instant: Instant,
+    steamvr_video_settings: Option<SteamvrVideoSettings>,
+    edited_steamvr_video_settings: SteamvrVideoSettings,
+    // Emulated headset and transcoding resolution scales, if both are relative
+    stream_scales: Option<(f32, f32)>,
 }
 
 impl InstallationTab {
@@ -23,6 +52,9 @@ impl InstallationTab {
         Self {
             drivers: vec![],
             last_update_instant: Instant::now(),
+            steamvr_video_settings: None,
+            edited_steamvr_video_settings: SteamvrVideoSettings::default(),
+            stream_scales: None,
         }
     }
 
@@ -30,6 +62,133 @@ impl InstallationTab {
         self.drivers = list;
     }
 
+    pub fn update_steamvr_video_settings(&mut self, settings: SteamvrVideoSettings) {
+        self.edited_steamvr_video_settings = settings.clone();
+        self.steamvr_video_settings = Some(settings);
+    }
+
+    pub fn update_settings(&mut self, settings: &Settings) {
+        self.stream_scales = match (
+            &settings.video.emulated_headset_view_resolution,
+            &settings.video.transcoding_view_resolution,
+        ) {
+            (FrameSize::Scale(emulated), FrameSize::Scale(transcoding)) => {
+                Some((*emulated, *transcoding))
+            }
+            _ => None,
+        };
+    }
+
+    // SteamVR renders the recommended resolution multiplied by its scale, in pixel count, and the
+    // encoder resizes it to the transcoding resolution
+    fn resolution_warning(&self, steamvr_scale: f32) -> Option<String> {
+        let (emulated, transcoding) = self.stream_scales?;
+        let ratio = emulated * steamvr_scale.sqrt() / transcoding;
+
+        if ratio > MAX_RENDER_TO_STREAM_RATIO {
+            Some(format!(
+                "SteamVR renders {:.0}% of the streamed resolution, the extra pixels are lost when \
+                encoding. Lower the SteamVR resolution or raise the transcoding resolution",
+                ratio * 100.0
+            ))
+        } else if ratio < MIN_RENDER_TO_STREAM_RATIO {
+            Some(format!(
+                "SteamVR renders {:.0}% of the streamed resolution, the frames are upscaled \
+                before encoding. Raise the SteamVR resolution or lower the transcoding resolution",
+                ratio * 100.0
+            ))
+        } else {
+            None
+        }
+    }
+
+    fn steamvr_video_settings_ui(&mut self, ui: &mut Ui) -> Option<ServerRequest> {
+        let Some(current) = &self.steamvr_video_settings else {
+            ui.label("SteamVR settings not found");
+
+            return None;
+        };
+
+        let mut request = None;
+        let edited = &mut self.edited_steamvr_video_settings;
+
+        Grid::new("steamvr_video_settings")
+            .num_columns(2)
+            .show(ui, |ui| {
+                let mut custom_resolution = edited.resolution_scale.is_some();
+                ui.checkbox(&mut custom_resolution, "Custom resolution");
+                if custom_resolution {
+                    let scale = edited.resolution_scale.get_or_insert(1.0);
+                    resolution_scale_slider(ui, scale);
+                } else {
+                    edited.resolution_scale = None;
+                    ui.label("Automatic");
+                }
+                ui.end_row();
+
+                ui.label("Motion smoothing");
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut edited.motion_smoothing, None, "Default");
+                    ui.selectable_value(&mut edited.motion_smoothing, Some(true), "On");
+                    ui.selectable_value(&mut edited.motion_smoothing, Some(false), "Off");
+                });
+                ui.end_row();
+
+                let mut removed_app = None;
+                for (idx, (app_id, scale)) in edited.app_resolution_scales.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.button("Remove").clicked() {
+                            removed_app = Some(idx);
+                        }
+                        ui.label(format!("App {app_id}"));
+                    });
+                    resolution_scale_slider(ui, scale);
+                    ui.end_row();
+                }
+                if let Some(idx) = removed_app {
+                    edited.app_resolution_scales.remove(idx);
+                }
+            });
+
+        // The resolution of the applications is relative to the global one
+        let global_scale = edited.resolution_scale;
+        let mut warnings = vec![];
+        if let Some(scale) = global_scale {
+            warnings.extend(self.resolution_warning(scale));
+        }
+        for (app_id, scale) in &self.edited_steamvr_video_settings.app_resolution_scales {
+            if let Some(warning) = self.resolution_warning(global_scale.unwrap_or(1.0) * scale) {
+                warnings.push(format!("App {app_id}: {warning}"));
+            }
+        }
+        for warning in warnings {
+            ui.colored_label(log_colors::WARNING_LIGHT, warning);
+        }
+
+        ui.label("The changes are used by the games started afterwards");
+
+        ui.columns(2, |ui| {
+            if ui[0]
+                .add_enabled(
+                    self.edited_steamvr_video_settings != *current,
+                    Button::new("Apply"),
+                )
+                .clicked()
+            {
+                request = Some(ServerRequest::SetSteamvrVideoSettings(
+                    self.edited_steamvr_video_settings.clone(),
+                ));
+            }
+            if ui[1].button("Restore SteamVR defaults").clicked() {
+                request = Some(ServerRequest::SetSteamvrVideoSettings(
+                    SteamvrVideoSettings::default(),
+                ));
+            }
+        });
+
+        request
+    }
+
     pub fn ui(&mut self, ui: &mut Ui) -> Vec<InstallationTabRequest> {
         let mut requests = vec![];
 
@@ -38,6 +197,11 @@ impl InstallationTab {
             requests.push(InstallationTabRequest::ServerRequest(
                 ServerRequest::GetDriverList,
             ));
+            if self.steamvr_video_settings.is_none() {
+                requests.push(InstallationTabRequest::ServerRequest(
+                    ServerRequest::GetSteamvrVideoSettings,
+                ));
+            }
 
             self.last_update_instant = now;
         }
@@ -91,6 +255,19 @@ impl InstallationTab {
                         ));
                     }
                 });
+
+            Frame::group(ui.style())
+                .fill(theme::SECTION_BG)
+                .inner_margin(theme::FRAME_PADDING)
+                .show(ui, |ui| {
+                    ui.vertical_centered_justified(|ui| {
+                        ui.label(RichText::new("SteamVR video settings").size(18.0));
+                    });
+
+                    if let Some(request) = self.steamvr_video_settings_ui(ui) {
+                        requests.push(InstallationTabRequest::ServerRequest(request));
+                    }
+                });
         });
 
         requests
@@ -1,13 +1,14 @@
-use alvr_common::LogSeverity;
+use crate::dashboard::ServerRequest;
+use alvr_common::{LogSeverity, TargetLevels};
 use alvr_events::{Event, EventType};
 use alvr_gui_common::theme::log_colors;
 use alvr_session::{RawEventsConfig, Settings};
 use eframe::{
-    egui::{Grid, OpenUrl, OutputCommand, RichText, ScrollArea, Ui},
+    egui::{Button, Grid, OpenUrl, OutputCommand, RichText, ScrollArea, TextEdit, Ui},
     epaint::Color32,
 };
 use settings_schema::Switch;
-use std::collections::VecDeque;
+use std::{collections::VecDeque, str::FromStr};
 
 struct Entry {
     color: Color32,
@@ -20,6 +21,9 @@ pub struct LogsTab {
     raw_events_config: Switch<RawEventsConfig>,
     entries: VecDeque<Entry>,
     log_limit: usize,
+    target_levels: String,
+    target_levels_edit: String,
+    target_levels_error: Option<String>,
 }
 
 impl LogsTab {
@@ -30,11 +34,21 @@ impl LogsTab {
             }),
             entries: VecDeque::new(),
             log_limit: 1000,
+            target_levels: String::new(),
+            target_levels_edit: String::new(),
+            target_levels_error: None,
         }
     }
 
     pub fn update_settings(&mut self, settings: &Settings) {
         self.raw_events_config = settings.extra.logging.show_raw_events.clone();
+
+        // Keep the text being edited
+        let target_levels = &settings.extra.logging.target_levels;
+        if self.target_levels_edit == self.target_levels {
+            self.target_levels_edit = target_levels.clone();
+        }
+        self.target_levels = target_levels.clone();
     }
 
     pub fn push_event(&mut self, event: Event) {
@@ -72,7 +86,9 @@ impl LogsTab {
         }
     }
 
-    pub fn ui(&mut self, ui: &mut Ui) {
+    pub fn ui(&mut self, ui: &mut Ui) -> Option<ServerRequest> {
+        let mut request = None;
+
         ui.horizontal(|ui| {
             if ui.button("Copy all").clicked() {
                 ui.output_mut(|out| {
@@ -100,6 +116,35 @@ impl LogsTab {
             }
         });
 
+        ui.horizontal(|ui| {
+            ui.label("Log levels:");
+            ui.add(
+                TextEdit::singleline(&mut self.target_levels_edit)
+                    .hint_text("alvr_sockets=debug, alvr_server_core::bitrate=trace")
+                    .desired_width(400.0),
+            );
+            if ui
+                .add_enabled(
+                    self.target_levels_edit != self.target_levels,
+                    Button::new("Apply"),
+                )
+                .clicked()
+            {
+                match TargetLevels::from_str(&self.target_levels_edit) {
+                    Ok(_) => {
+                        self.target_levels_error = None;
+                        self.target_levels_edit = self.target_levels_edit.trim().to_owned();
+                        request =
+                            Some(ServerRequest::SetLogLevels(self.target_levels_edit.clone()));
+                    }
+                    Err(e) => self.target_levels_error = Some(e.to_string()),
+                }
+            }
+            if let Some(error) = &self.target_levels_error {
+                ui.colored_label(log_colors::ERROR_LIGHT, error);
+            }
+        });
+
         ScrollArea::both()
             .stick_to_bottom(true)
             .auto_shrink([false, false])
@@ -121,5 +166,7 @@ impl LogsTab {
                         }
                     });
             });
+
+        request
     }
 }
@@ -0,0 +1,56 @@
+use eframe::egui::{ColorImage, Context, TextureHandle, TextureOptions, Window, vec2};
+
+pub struct MirrorWindow {
+    pub open: bool,
+    texture: Option<TextureHandle>,
+}
+
+impl MirrorWindow {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            texture: None,
+        }
+    }
+
+    pub fn ui(&mut self, context: &Context, new_frame: Option<ColorImage>) {
+        if !self.open {
+            self.texture = None;
+
+            return;
+        }
+
+        if let Some(frame) = new_frame {
+            if let Some(texture) = &mut self.texture {
+                texture.set(frame, TextureOptions::LINEAR);
+            } else {
+                self.texture =
+                    Some(context.load_texture("headset_mirror", frame, TextureOptions::LINEAR));
+            }
+        }
+
+        Window::new("Headset mirror")
+            .open(&mut self.open)
+            .resizable(true)
+            .default_size(vec2(480.0, 270.0))
+            .show(context, |ui| {
+                if let Some(texture) = &self.texture {
+                    // Fit the window while keeping the aspect ratio of the frame
+                    let available = ui.available_size();
+                    let aspect_ratio = texture.aspect_ratio();
+                    let size = if available.x / available.y > aspect_ratio {
+                        vec2(available.y * aspect_ratio, available.y)
+                    } else {
+                        vec2(available.x, available.x / aspect_ratio)
+                    };
+
+                    ui.image((texture.id(), size));
+                } else {
+                    ui.label(
+                        "Waiting for the stream. The mirror shows the image before encoding while \
+                        streaming, on Windows only.",
+                    );
+                }
+            });
+    }
+}
@@ -1,7 +1,12 @@
 mod about;
+mod analog_curves;
+mod client_settings;
+mod controller_calibration;
+mod crash_popup;
 mod debug;
 mod devices;
 mod logs;
+mod mirror_window;
 mod new_version_popup;
 mod notifications;
 mod settings;
@@ -13,9 +18,14 @@ mod statistics;
 mod installation;
 
 pub use about::*;
+pub use analog_curves::*;
+pub use client_settings::*;
+pub use controller_calibration::*;
+pub use crash_popup::*;
 pub use debug::*;
 pub use devices::*;
 pub use logs::*;
+pub use mirror_window::*;
 pub use new_version_popup::*;
 pub use notifications::*;
 pub use settings::*;
@@ -1,25 +1,64 @@
 use super::{
-    NestingInfo, SettingControl,
+    NestingInfo, SettingControl, analog_curves_ui, custom_analog_responses,
     presets::{PresetControl, builtin_schema},
 };
 use crate::dashboard::ServerRequest;
-use alvr_gui_common::{DisplayString, theme};
-use alvr_session::{SessionSettings, Settings};
-use eframe::egui::{Align, Frame, Grid, Layout, RichText, ScrollArea, Ui};
+use alvr_common::AxisResponse;
+use alvr_gui_common::{
+    DisplayString,
+    theme::{self, log_colors::WARNING_LIGHT},
+};
+use alvr_session::{ApplyScope, SessionSettings, SettingChange, Settings};
+use eframe::egui::{Align, Frame, Grid, Id, Layout, RichText, ScrollArea, Ui};
 #[cfg(target_arch = "wasm32")]
 use instant::Instant;
 use serde_json as json;
 use settings_schema::SchemaNode;
-use std::time::Duration;
 #[cfg(not(target_arch = "wasm32"))]
 use std::time::Instant;
+use std::{sync::Arc, time::Duration};
 
 const DATA_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
-const MIN_COLUMN_SIZE: f32 = 300.0;
+pub(super) const MIN_COLUMN_SIZE: f32 = 300.0;
+
+// Egui temporary data with the paths of the pending changes, read by the section controls
+pub(super) fn pending_changes_id() -> Id {
+    Id::new("pending_setting_changes")
+}
+
+pub(super) struct TopLevelEntry {
+    pub id: DisplayString,
+    pub control: SettingControl,
+}
+
+// One control for each tab of the settings, rooted at "session_settings"
+pub(super) fn top_level_entries() -> Vec<TopLevelEntry> {
+    let nesting_info = NestingInfo {
+        path: vec!["session_settings".into()],
+        indentation_level: 0,
+    };
+    let schema = Settings::schema(alvr_session::session_settings_default());
+
+    // Top level node must be a section
+    let SchemaNode::Section { entries, .. } = schema else {
+        unreachable!();
+    };
 
-struct TopLevelEntry {
-    id: DisplayString,
-    control: SettingControl,
+    entries
+        .into_iter()
+        .map(|entry| {
+            let id = entry.name;
+            let display = super::get_display_name(&id, &entry.strings);
+
+            let mut nesting_info = nesting_info.clone();
+            nesting_info.path.push(id.clone().into());
+
+            TopLevelEntry {
+                id: DisplayString { id, display },
+                control: SettingControl::new(nesting_info, entry.content),
+            }
+        })
+        .collect()
 }
 
 pub struct SettingsTab {
@@ -35,38 +74,13 @@ pub struct SettingsTab {
     eye_face_tracking_preset: PresetControl,
     top_level_entries: Vec<TopLevelEntry>,
     session_settings_json: Option<json::Value>,
+    analog_responses: Vec<(&'static str, AxisResponse)>,
+    pending_changes: Arc<Vec<SettingChange>>,
     last_update_instant: Instant,
 }
 
 impl SettingsTab {
     pub fn new() -> Self {
-        let nesting_info = NestingInfo {
-            path: vec!["session_settings".into()],
-            indentation_level: 0,
-        };
-        let schema = Settings::schema(alvr_session::session_settings_default());
-
-        // Top level node must be a section
-        let SchemaNode::Section { entries, .. } = schema else {
-            unreachable!();
-        };
-
-        let top_level_entries = entries
-            .into_iter()
-            .map(|entry| {
-                let id = entry.name;
-                let display = super::get_display_name(&id, &entry.strings);
-
-                let mut nesting_info = nesting_info.clone();
-                nesting_info.path.push(id.clone().into());
-
-                TopLevelEntry {
-                    id: DisplayString { id, display },
-                    control: SettingControl::new(nesting_info, entry.content),
-                }
-            })
-            .collect();
-
         Self {
             selected_top_tab_id: "presets".into(),
             resolution_preset: PresetControl::new(builtin_schema::resolution_schema()),
@@ -80,8 +94,10 @@ impl SettingsTab {
                 builtin_schema::hand_tracking_interaction_schema(),
             ),
             eye_face_tracking_preset: PresetControl::new(builtin_schema::eye_face_tracking_schema()),
-            top_level_entries,
+            top_level_entries: top_level_entries(),
             session_settings_json: None,
+            analog_responses: vec![],
+            pending_changes: Arc::new(vec![]),
             last_update_instant: Instant::now(),
         }
     }
@@ -109,6 +125,54 @@ impl SettingsTab {
         self.session_settings_json = Some(settings_json);
     }
 
+    pub fn update_settings(&mut self, settings: &Settings) {
+        self.analog_responses = custom_analog_responses(settings);
+    }
+
+    // Changes made while streaming that need a restart of the stream or of SteamVR
+    pub fn update_pending_changes(&mut self, changes: Vec<SettingChange>) {
+        self.pending_changes = Arc::new(changes);
+    }
+
+    fn apply_bar_ui(&self, ui: &mut Ui) -> Option<ServerRequest> {
+        let scope = alvr_session::required_apply_scope(&self.pending_changes)?;
+
+        let mut request = None;
+        Frame::group(ui.style())
+            .fill(theme::DARKER_BG)
+            .inner_margin(theme::FRAME_PADDING)
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    let changed_paths = self
+                        .pending_changes
+                        .iter()
+                        .map(|change| change.path.join("."))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    ui.colored_label(
+                        WARNING_LIGHT,
+                        format!(
+                            "● {} changed settings are not applied yet",
+                            self.pending_changes.len()
+                        ),
+                    )
+                    .on_hover_text(changed_paths);
+
+                    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                        if scope == ApplyScope::DriverRestart {
+                            if ui.button("Apply (restarts SteamVR)").clicked() {
+                                request = Some(ServerRequest::RestartSteamvr);
+                            }
+                        } else if ui.button("Apply (restarts the stream)").clicked() {
+                            request = Some(ServerRequest::RestartStream);
+                        }
+                    });
+                });
+            });
+
+        request
+    }
+
     pub fn ui(&mut self, ui: &mut Ui) -> Vec<ServerRequest> {
         let mut requests = vec![];
 
@@ -121,6 +185,11 @@ impl SettingsTab {
             self.last_update_instant = now;
         }
 
+        requests.extend(self.apply_bar_ui(ui));
+        ui.data_mut(|data| {
+            data.insert_temp(pending_changes_id(), Arc::clone(&self.pending_changes))
+        });
+
         let mut path_value_pairs = vec![];
         ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
             Frame::group(ui.style())
@@ -134,10 +203,20 @@ impl SettingsTab {
                             RichText::new("Presets").raised().size(15.0),
                         );
                         for entry in &mut self.top_level_entries {
+                            let changed = self
+                                .pending_changes
+                                .iter()
+                                .any(|change| change.path.first() == Some(&entry.id.id));
+                            let label = if changed {
+                                format!("{} ●", entry.id.display)
+                            } else {
+                                entry.id.display.clone()
+                            };
+
                             ui.selectable_value(
                                 &mut self.selected_top_tab_id,
                                 entry.id.id.clone(),
-                                RichText::new(entry.id.display.clone()).raised().size(15.0),
+                                RichText::new(label).raised().size(15.0),
                             );
                         }
                     })
@@ -213,6 +292,12 @@ impl SettingsTab {
                                 }
 
                                 ui.end_row();
+
+                                if entry.id.id == "headset" && !self.analog_responses.is_empty() {
+                                    ui.label("Analog response curves");
+                                    analog_curves_ui(ui, &self.analog_responses);
+                                    ui.end_row();
+                                }
                             }
                         })
                 });
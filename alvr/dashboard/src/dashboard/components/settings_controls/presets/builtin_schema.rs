@@ -34,7 +34,7 @@ pub fn resolution_schema() -> PresetSchemaNode {
         name: "Resolution".into(),
         strings: [(
             "help".into(),
-            "Choosing too high resolution (commonly 'High (width: 5184)') may result in high latency or black screen.".into(),
+            "Choosing too high resolution (commonly 'High (width: 5184)') may result in high latency or black screen. Choose 'Custom' to enter the exact resolution in the Video settings, for example to match the headset panel.".into(),
         )]
         .into_iter()
         .collect(),
@@ -80,6 +80,29 @@ pub fn resolution_schema() -> PresetSchemaNode {
             .collect(),
             content: None,
         })
+        // The width and height are kept from the session and edited in the Video settings
+        .chain([HigherOrderChoiceOption {
+            display_name: "Custom".into(),
+            modifiers: vec![
+                string_modifier(
+                    "session_settings.video.transcoding_view_resolution.variant",
+                    "Absolute",
+                ),
+                bool_modifier(
+                    "session_settings.video.transcoding_view_resolution.Absolute.height.set",
+                    true,
+                ),
+                string_modifier(
+                    "session_settings.video.emulated_headset_view_resolution.variant",
+                    "Absolute",
+                ),
+                bool_modifier(
+                    "session_settings.video.emulated_headset_view_resolution.Absolute.height.set",
+                    true,
+                ),
+            ],
+            content: None,
+        }])
         .collect(),
         default_option_display_name: "Medium (width: 4288)".into(),
         gui: ChoiceControlType::Dropdown,
@@ -151,30 +174,20 @@ pub fn encoder_preset_schema() -> PresetSchemaNode {
         )]
         .into_iter()
         .collect(),
-        flags: ["steamvr-restart".into()].into_iter().collect(),
-        options: [
-            ("Speed", "Speed", "P1"),
-            ("Balanced", "Balanced", "P3"),
-            ("Quality", "Quality", "P5"),
-        ]
-        .into_iter()
-        .map(|(key, val_amd, val_nv)| HigherOrderChoiceOption {
-            display_name: key.into(),
-            modifiers: [
-                string_modifier(
-                    "session_settings.video.encoder_config.nvenc.quality_preset.variant",
-                    val_nv,
-                ),
-                string_modifier(
-                    "session_settings.video.encoder_config.quality_preset.variant",
-                    val_amd,
-                ),
-            ]
+        flags: HashSet::new(),
+        options: ["Speed", "Balanced", "Quality"]
             .into_iter()
+            .map(|key| HigherOrderChoiceOption {
+                display_name: key.into(),
+                modifiers: [string_modifier(
+                    "session_settings.video.encoder_effort.variant",
+                    key,
+                )]
+                .into_iter()
+                .collect(),
+                content: None,
+            })
             .collect(),
-            content: None,
-        })
-        .collect(),
         default_option_display_name: "Speed".into(),
         gui: ChoiceControlType::ButtonGroup,
     })
@@ -206,6 +219,7 @@ shutterring and high encode/decode latency!"
                 display_name: key.into(),
                 modifiers: [
                     bool_modifier(&format!("{PREFIX}.enabled"), true),
+                    string_modifier(&format!("{PREFIX}.content.mode.variant"), "TwoZone"),
                     num_modifier(
                         &format!("{PREFIX}.content.center_size_x"),
                         &val_size_x.to_string(),
@@ -6,10 +6,14 @@ use alvr_gui_common::{
         log_colors::{INFO_LIGHT, WARNING_LIGHT},
     },
 };
-use alvr_packets::PathValuePair;
-use alvr_session::settings_schema::{SchemaEntry, SchemaNode};
+use alvr_packets::{PathSegment, PathValuePair};
+use alvr_session::{
+    SettingChange,
+    settings_schema::{SchemaEntry, SchemaNode},
+};
 use eframe::egui::Ui;
 use serde_json as json;
+use std::sync::Arc;
 
 struct Entry {
     id: DisplayString,
@@ -18,6 +22,8 @@ struct Entry {
     hidden: bool,
     steamvr_restart_flag: bool,
     real_time_flag: bool,
+    // Path in the format of SettingChange
+    path: Vec<String>,
     control: SettingControl,
 }
 
@@ -49,6 +55,16 @@ impl Control {
                 let mut nesting_info = nesting_info.clone();
                 nesting_info.path.push(id.clone().into());
 
+                let path = nesting_info
+                    .path
+                    .iter()
+                    .skip(1)
+                    .map(|segment| match segment {
+                        PathSegment::Name(name) => name.clone(),
+                        PathSegment::Index(index) => index.to_string(),
+                    })
+                    .collect();
+
                 Entry {
                     id: DisplayString { id, display },
                     help,
@@ -56,6 +72,7 @@ impl Control {
                     hidden,
                     steamvr_restart_flag,
                     real_time_flag,
+                    path,
                     control: SettingControl::new(nesting_info, entry.content),
                 }
             })
@@ -101,6 +118,12 @@ impl Control {
             false
         };
 
+        let pending_changes = ui.data(|data| {
+            data.get_temp::<Arc<Vec<SettingChange>>>(
+                crate::dashboard::components::settings::pending_changes_id(),
+            )
+        });
+
         if !collapsed {
             for (i, entry) in self.entries.iter_mut().enumerate() {
                 if entry.hidden {
@@ -131,6 +154,14 @@ impl Control {
                             "This setting can be changed in real-time during streaming!",
                         );
                     }
+                    if pending_changes.as_ref().is_some_and(|changes| {
+                        changes
+                            .iter()
+                            .any(|change| change.path.starts_with(&entry.path))
+                    }) {
+                        ui.colored_label(WARNING_LIGHT, "●")
+                            .on_hover_text_at_pointer("Changed, not applied yet");
+                    }
                 });
 
                 if let Some(string) = &entry.notice {
@@ -4,18 +4,37 @@ use crate::dashboard::components::{
     up_down::{self, UpDownResult},
 };
 use alvr_packets::PathValuePair;
-use alvr_session::settings_schema::SchemaNode;
+use alvr_session::{ListEntryHeader, settings_schema::SchemaNode};
 use eframe::{
-    egui::{Layout, Ui},
+    egui::{Id, Layout, Stroke, TextEdit, Ui},
     emath::Align,
 };
 use serde_json as json;
 
+// Payload of the drag handle. The list ID prevents dropping the entry into another list
+struct DraggedEntry {
+    list_id: Id,
+    index: usize,
+}
+
+// Edits that change the indices of the entries. They are applied after drawing the list
+enum ListEdit {
+    Remove(usize),
+    Duplicate(usize),
+    Insert(usize),
+    Move { from: usize, to: usize },
+    Push,
+}
+
 pub struct Control {
     nesting_info: NestingInfo,
+    list_id: Id,
     default_element: SchemaNode,
     default: Vec<json::Value>,
+    header: ListEntryHeader,
     controls: Vec<SettingControl>,
+    // Index of the entry and the display name being typed
+    editing_name: Option<(usize, String)>,
 }
 
 impl Control {
@@ -24,14 +43,89 @@ impl Control {
         default_element: SchemaNode,
         default: Vec<json::Value>,
     ) -> Self {
+        let list_id = Id::new(format!("{:?}", nesting_info.path));
+        let header = alvr_session::list_entry_header(&default_element);
+
         Self {
             nesting_info,
+            list_id,
             default_element,
             default,
+            header,
             controls: vec![],
+            editing_name: None,
         }
     }
 
+    fn header_ui(
+        &mut self,
+        ui: &mut Ui,
+        idx: usize,
+        entry: &mut json::Value,
+    ) -> Option<PathValuePair> {
+        let mut request = None;
+
+        fn get_request(
+            nesting_info: &NestingInfo,
+            idx: usize,
+            field: &str,
+            value: json::Value,
+        ) -> Option<PathValuePair> {
+            let mut path = nesting_info.path.clone();
+            path.push("content".into());
+            path.push(idx.into());
+            path.push(field.into());
+
+            Some(PathValuePair { path, value })
+        }
+
+        if self.header.enabled
+            && let json::Value::Bool(enabled_mut) = &mut entry["enabled"]
+            && alvr_gui_common::switch(ui, enabled_mut).clicked()
+        {
+            request = get_request(
+                &self.nesting_info,
+                idx,
+                "enabled",
+                json::Value::Bool(*enabled_mut),
+            );
+        }
+
+        if self.header.display_name
+            && let json::Value::String(name_mut) = &mut entry["display_name"]
+        {
+            let textbox = match &mut self.editing_name {
+                Some((editing_idx, editing_name_mut)) if *editing_idx == idx => {
+                    TextEdit::singleline(editing_name_mut)
+                }
+                _ => TextEdit::singleline(name_mut),
+            };
+
+            let response = ui.add(
+                textbox
+                    .hint_text(format!("Entry {}", idx + 1))
+                    .desired_width(150.),
+            );
+            if response.lost_focus()
+                && let Some((editing_idx, editing_name)) = self.editing_name.take()
+                && editing_idx == idx
+            {
+                request = get_request(
+                    &self.nesting_info,
+                    idx,
+                    "display_name",
+                    json::Value::String(editing_name.clone()),
+                );
+                *name_mut = editing_name;
+            }
+            if response.gained_focus() {
+                self.editing_name = Some((idx, name_mut.clone()));
+            }
+        }
+
+        request
+    }
+
     pub fn ui(
         &mut self,
         ui: &mut Ui,
@@ -65,6 +159,7 @@ impl Control {
             })
             .inner;
 
+        let element = session_fragment["element"].clone();
         let session_content = session_fragment["content"].as_array_mut().unwrap();
 
         while session_content.len() > self.controls.len() {
@@ -81,58 +176,127 @@ impl Control {
             self.controls.pop();
         }
 
-        if !collapsed {
-            ui.end_row();
+        if collapsed {
+            return request;
+        }
 
-            let mut idx = 0;
-            while idx < self.controls.len() {
-                let delete_element = ui
-                    .horizontal(|ui| {
-                        ui.add_space(INDENTATION_STEP * self.nesting_info.indentation_level as f32);
+        ui.end_row();
 
-                        let delete_element = ui.button("❌").clicked();
+        let mut edit = None;
+        for (idx, entry) in session_content.iter_mut().enumerate() {
+            let row_response = ui
+                .horizontal(|ui| {
+                    ui.add_space(INDENTATION_STEP * self.nesting_info.indentation_level as f32);
 
-                        let up_down_result = up_down::up_down_buttons(ui, idx, self.controls.len());
+                    ui.dnd_drag_source(
+                        self.list_id.with(idx),
+                        DraggedEntry {
+                            list_id: self.list_id,
+                            index: idx,
+                        },
+                        |ui| ui.label("☰"),
+                    )
+                    .response
+                    .on_hover_text("Drag to reorder");
 
-                        if up_down_result != UpDownResult::None {
-                            if up_down_result == UpDownResult::Up {
-                                session_content.swap(idx, idx - 1);
-                            } else {
-                                session_content.swap(idx, idx + 1);
-                            }
+                    if ui.button("❌").on_hover_text("Remove").clicked() {
+                        edit = Some(ListEdit::Remove(idx));
+                    }
 
-                            request =
-                                get_content_request(&self.nesting_info, session_content.clone());
+                    match up_down::up_down_buttons(ui, idx, self.controls.len()) {
+                        UpDownResult::Up => {
+                            edit = Some(ListEdit::Move {
+                                from: idx,
+                                to: idx - 1,
+                            })
+                        }
+                        UpDownResult::Down => {
+                            edit = Some(ListEdit::Move {
+                                from: idx,
+                                to: idx + 1,
+                            })
                         }
+                        UpDownResult::None => (),
+                    }
 
-                        delete_element
-                    })
-                    .inner;
+                    if ui.button("⧉").on_hover_text("Duplicate").clicked() {
+                        edit = Some(ListEdit::Duplicate(idx));
+                    }
+                    if ui.button("➕").on_hover_text("Insert above").clicked() {
+                        edit = Some(ListEdit::Insert(idx));
+                    }
 
-                if delete_element {
-                    session_content.remove(idx);
-                    self.controls.remove(idx);
+                    request = self.header_ui(ui, idx, entry).or(request.take());
+                })
+                .response;
 
-                    request = get_content_request(&self.nesting_info, session_content.clone());
+            if let Some(dragged) = row_response.dnd_hover_payload::<DraggedEntry>()
+                && dragged.list_id == self.list_id
+                && dragged.index != idx
+            {
+                // Show on which side of the row the entry will be placed
+                let rect = row_response.rect;
+                let y = if dragged.index > idx {
+                    rect.top()
                 } else {
-                    request = self.controls[idx]
-                        .ui(ui, &mut session_content[idx], true)
-                        .or(request);
-                }
+                    rect.bottom()
+                };
+                ui.painter().hline(
+                    rect.x_range(),
+                    y,
+                    Stroke::new(2.0, ui.visuals().selection.bg_fill),
+                );
+            }
+            if let Some(dragged) = row_response.dnd_release_payload::<DraggedEntry>()
+                && dragged.list_id == self.list_id
+                && dragged.index != idx
+            {
+                edit = Some(ListEdit::Move {
+                    from: dragged.index,
+                    to: idx,
+                });
+            }
 
-                ui.end_row();
+            request = self.controls[idx].ui(ui, entry, true).or(request);
 
-                idx += 1;
-            }
+            ui.end_row();
+        }
 
-            ui.label(" ");
-            if ui.button("Add element").clicked() {
-                let mut session_content =
-                    session_fragment["content"].as_array_mut().unwrap().clone();
-                session_content.push(session_fragment["element"].clone());
+        ui.label(" ");
+        if ui.button("Add element").clicked() {
+            edit = Some(ListEdit::Push);
+        }
 
-                request = get_content_request(&self.nesting_info, session_content);
-            }
+        if let Some(edit) = edit {
+            let first_changed_idx = match edit {
+                ListEdit::Remove(idx) => {
+                    session_content.remove(idx);
+                    idx
+                }
+                ListEdit::Duplicate(idx) => {
+                    session_content.insert(idx + 1, session_content[idx].clone());
+                    idx + 1
+                }
+                ListEdit::Insert(idx) => {
+                    session_content.insert(idx, element);
+                    idx
+                }
+                ListEdit::Move { from, to } => {
+                    let entry = session_content.remove(from);
+                    session_content.insert(to, entry);
+                    usize::min(from, to)
+                }
+                ListEdit::Push => {
+                    session_content.push(element);
+                    session_content.len() - 1
+                }
+            };
+
+            // The controls hold the index of their entry, the moved ones are recreated
+            self.controls.truncate(first_changed_idx);
+            self.editing_name = None;
+
+            request = get_content_request(&self.nesting_info, session_content.clone());
         }
 
         request
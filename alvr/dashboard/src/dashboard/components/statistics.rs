@@ -1,6 +1,8 @@
 use crate::dashboard::{ServerRequest, theme::graph_colors};
-use alvr_events::{GraphStatistics, StatisticsSummary};
-use alvr_gui_common::theme;
+use alvr_events::{
+    BitrateBenchmarkReport, BitrateBenchmarkState, GraphStatistics, StatisticsSummary,
+};
+use alvr_gui_common::theme::{self, log_colors};
 use eframe::{
     egui::{
         Align2, Color32, CornerRadius, FontId, Frame, Grid, Painter, Rect, RichText, ScrollArea,
@@ -22,6 +24,7 @@ fn draw_lines(painter: &Painter, points: Vec<Pos2>, color: Color32) {
 pub struct StatisticsTab {
     history: VecDeque<GraphStatistics>,
     last_statistics_summary: Option<StatisticsSummary>,
+    last_bitrate_benchmark: Option<BitrateBenchmarkReport>,
 }
 
 impl StatisticsTab {
@@ -31,6 +34,7 @@ impl StatisticsTab {
                 .into_iter()
                 .collect(),
             last_statistics_summary: None,
+            last_bitrate_benchmark: None,
         }
     }
 
@@ -38,12 +42,18 @@ impl StatisticsTab {
         self.last_statistics_summary = Some(statistics);
     }
 
+    pub fn update_bitrate_benchmark(&mut self, report: BitrateBenchmarkReport) {
+        self.last_bitrate_benchmark = Some(report);
+    }
+
     pub fn update_graph_statistics(&mut self, statistics: GraphStatistics) {
         self.history.pop_front();
         self.history.push_back(statistics);
     }
 
     pub fn ui(&self, ui: &mut Ui) -> Option<ServerRequest> {
+        let mut request = None;
+
         if let Some(stats) = &self.last_statistics_summary {
             ScrollArea::new([false, true]).show(ui, |ui| {
                 let available_width = ui.available_width();
@@ -51,6 +61,7 @@ impl StatisticsTab {
                 self.draw_fps_graph(ui, available_width);
                 self.draw_bitrate_graph(ui, available_width);
                 self.draw_statistics_overview(ui, stats);
+                request = self.draw_bitrate_benchmark(ui, available_width);
             });
         } else {
             ui.heading(
@@ -59,7 +70,7 @@ impl StatisticsTab {
             );
         }
 
-        None
+        request
     }
 
     fn draw_graph(
@@ -161,6 +172,48 @@ impl StatisticsTab {
                         offset += value * 1000.0;
                     }
                 }
+
+                // With streamer-side reprojection the head rotation is fresher than the rest of the
+                // frame, show the latency of the rotation on top of the pipeline latency
+                if self
+                    .history
+                    .iter()
+                    .any(|stats| stats.server_reprojection_s > 0.0)
+                {
+                    draw_lines(
+                        painter,
+                        self.history
+                            .iter()
+                            .enumerate()
+                            .map(|(i, stats)| {
+                                let latency_s =
+                                    stats.total_pipeline_latency_s - stats.server_reprojection_s;
+                                to_screen_trans * pos2(i as f32, latency_s * 1000.0)
+                            })
+                            .collect(),
+                        theme::FG,
+                    );
+                }
+
+                // Wi-Fi roams and band changes, which often explain latency spikes
+                let canvas_rect = to_screen_trans.to();
+                for (i, stats) in self.history.iter().enumerate() {
+                    if let Some(transition) = &stats.wifi_transition {
+                        let x = (to_screen_trans * pos2(i as f32, 0.0)).x;
+                        painter.vline(
+                            x,
+                            canvas_rect.y_range(),
+                            Stroke::new(1.0, log_colors::WARNING_LIGHT),
+                        );
+                        painter.text(
+                            pos2(x, canvas_rect.top()),
+                            Align2::LEFT_TOP,
+                            transition,
+                            FontId::proportional(12.0),
+                            log_colors::WARNING_LIGHT,
+                        );
+                    }
+                }
             },
             |ui, stats| {
                 use graph_colors::*;
@@ -185,6 +238,14 @@ impl StatisticsTab {
                         stats.total_pipeline_latency_s,
                         theme::FG,
                     );
+                    if stats.server_reprojection_s > 0.0 {
+                        label(
+                            ui,
+                            "Head Rotation Latency (line)",
+                            stats.total_pipeline_latency_s - stats.server_reprojection_s,
+                            theme::FG,
+                        );
+                    }
                     label(ui, "ALVR Latency", transmission_total_latency_s, theme::FG);
                     label(
                         ui,
@@ -203,6 +264,14 @@ impl StatisticsTab {
                     label(ui, "Network", stats.network_s, NETWORK);
                     label(ui, "Encode", stats.encoder_s, TRANSCODE);
                     label(ui, "Streamer Compositor", stats.server_compositor_s, RENDER);
+                    if stats.server_reprojection_s > 0.0 {
+                        label(
+                            ui,
+                            "Streamer Reprojection (saved)",
+                            stats.server_reprojection_s,
+                            RENDER,
+                        );
+                    }
                     label(
                         ui,
                         "Game Render (not ALVR latency)",
@@ -282,6 +351,8 @@ impl StatisticsTab {
                 let mut encoder_latency_limiter = Vec::with_capacity(GRAPH_HISTORY_SIZE);
                 let mut max_throughput = Vec::with_capacity(GRAPH_HISTORY_SIZE);
                 let mut min_throughput = Vec::with_capacity(GRAPH_HISTORY_SIZE);
+                let mut thermal_limiter = Vec::with_capacity(GRAPH_HISTORY_SIZE);
+                let mut bandwidth_budget_limiter = Vec::with_capacity(GRAPH_HISTORY_SIZE);
                 let mut requested_bitrate = Vec::with_capacity(GRAPH_HISTORY_SIZE);
                 let mut recorded_throughput = Vec::with_capacity(GRAPH_HISTORY_SIZE);
                 let mut recorded_bitrate = Vec::with_capacity(GRAPH_HISTORY_SIZE);
@@ -306,6 +377,12 @@ impl StatisticsTab {
                     if let Some(value) = d.manual_min_throughput_bps {
                         min_throughput.push(to_screen_trans * pos2(i as f32, value / 1e6))
                     }
+                    if let Some(value) = d.thermal_limiter_bps {
+                        thermal_limiter.push(to_screen_trans * pos2(i as f32, value / 1e6))
+                    }
+                    if let Some(value) = d.bandwidth_budget_limiter_bps {
+                        bandwidth_budget_limiter.push(to_screen_trans * pos2(i as f32, value / 1e6))
+                    }
                     requested_bitrate
                         .push(to_screen_trans * pos2(i as f32, d.requested_bitrate_bps / 1e6));
                     recorded_throughput.push(
@@ -345,6 +422,16 @@ impl StatisticsTab {
                     min_throughput,
                     graph_colors::MIN_MAX_LATENCY_THROUGHPUT,
                 );
+                draw_lines(
+                    painter,
+                    thermal_limiter,
+                    graph_colors::ENCODER_DECODER_LATENCY_LIMITER,
+                );
+                draw_lines(
+                    painter,
+                    bandwidth_budget_limiter,
+                    graph_colors::MIN_MAX_LATENCY_THROUGHPUT,
+                );
                 draw_lines(painter, requested_bitrate, graph_colors::REQUESTED_BITRATE);
                 draw_lines(
                     painter,
@@ -409,7 +496,25 @@ impl StatisticsTab {
                     );
                     maybe_label(
                         ui,
-                        "Requested bitrate",
+                        "Thermal limiter",
+                        td.thermal_limiter_bps,
+                        graph_colors::ENCODER_DECODER_LATENCY_LIMITER,
+                    );
+                    maybe_label(
+                        ui,
+                        "Bandwidth budget limiter",
+                        td.bandwidth_budget_limiter_bps,
+                        graph_colors::MIN_MAX_LATENCY_THROUGHPUT,
+                    );
+                    maybe_label(
+                        ui,
+                        "Audio and control overhead",
+                        td.stream_overhead_bps,
+                        theme::FG,
+                    );
+                    maybe_label(
+                        ui,
+                        "Effective video bitrate",
                         Some(td.requested_bitrate_bps),
                         graph_colors::REQUESTED_BITRATE,
                     );
@@ -448,6 +553,12 @@ impl StatisticsTab {
             ui[0].label("Bitrate:");
             ui[1].label(format!("{:.1} Mbps", statistics.video_mbits_per_sec));
 
+            ui[0].label("Audio bitrate:");
+            ui[1].label(format!("{:.0} kbps", statistics.audio_kbits_per_sec));
+
+            ui[0].label("Haptics bitrate:");
+            ui[1].label(format!("{:.1} kbps", statistics.haptics_kbits_per_sec));
+
             ui[0].label("Total latency:");
             ui[1].label(format!("{:.0} ms", statistics.total_latency_ms));
 
@@ -457,6 +568,21 @@ impl StatisticsTab {
             ui[0].label("Transport latency:");
             ui[1].label(format!("{:.2} ms", statistics.network_latency_ms));
 
+            let one_way_label = |latency_ms: Option<f32>| {
+                latency_ms
+                    .map(|latency_ms| format!("{latency_ms:.2} ms"))
+                    .unwrap_or_else(|| "Waiting for clock sync".into())
+            };
+            ui[0]
+                .label("Uplink latency:")
+                .on_hover_text("From the headset to the streamer");
+            ui[1].label(one_way_label(statistics.uplink_latency_ms));
+
+            ui[0].label("Downlink latency:").on_hover_text(
+                "From the streamer to the headset, for the last packet of each frame",
+            );
+            ui[1].label(one_way_label(statistics.downlink_latency_ms));
+
             ui[0].label("Decoder latency:");
             ui[1].label(format!("{:.2} ms", statistics.decode_latency_ms));
 
@@ -476,6 +602,174 @@ impl StatisticsTab {
                     "unplugged"
                 }
             ));
+
+            ui[0].label("Headset thermal status");
+            ui[1].label(
+                statistics
+                    .hmd_thermal_status
+                    .map(|status| format!("{status:?}"))
+                    .unwrap_or_else(|| "Unknown".into()),
+            );
+
+            ui[0].label("IDR requests:");
+            ui[1].label(statistics.idr_requests_total.to_string());
+
+            ui[0].label("Intra refresh recoveries:");
+            ui[1].label(statistics.intra_refresh_recoveries_total.to_string());
+
+            ui[0].label("Discarded tracking packets:");
+            ui[1].label(statistics.tracking_packets_discarded_total.to_string());
+
+            ui[0].label("Decoder queue drops:");
+            ui[1].label(statistics.decoder_queue_drops_total.to_string());
+
+            ui[0].label("Extrapolated frames:");
+            ui[1].label(statistics.extrapolated_frames_total.to_string());
+
+            ui[0].label("Idle time:");
+            ui[1].label(format!("{:.0} s", statistics.idle_time_total_s));
+
+            ui[0].label("Clock drift:");
+            ui[1].label(format!(
+                "{:.1} ppm ({:.0}% confidence)",
+                statistics.clock_drift_ppm,
+                statistics.clock_sync_confidence * 100.0
+            ));
+        });
+    }
+
+    fn draw_bitrate_benchmark(&self, ui: &mut Ui, available_width: f32) -> Option<ServerRequest> {
+        let mut request = None;
+
+        ui.add_space(10.0);
+        ui.label(RichText::new("Bitrate benchmark").size(20.0));
+        ui.label(
+            "Holds increasing bitrates for a few seconds each, measuring the network latency and \
+            the frame loss, then recommends the highest bitrate that meets the targets. The \
+            parameters are in Video > Bitrate > Benchmark.",
+        );
+
+        let report = self.last_bitrate_benchmark.as_ref();
+        let running = report
+            .is_some_and(|report| matches!(report.state, BitrateBenchmarkState::Running { .. }));
+
+        ui.horizontal(|ui| {
+            if running {
+                if ui.button("Stop").clicked() {
+                    request = Some(ServerRequest::StopBitrateBenchmark);
+                }
+            } else if ui.button("Start").clicked() {
+                request = Some(ServerRequest::StartBitrateBenchmark);
+            }
+
+            if let Some(report) = report {
+                ui.label(match &report.state {
+                    BitrateBenchmarkState::Running { bitrate_mbps } => format!(
+                        "Step {}/{}: {bitrate_mbps} Mbps",
+                        report.steps.len() + 1,
+                        report.step_count
+                    ),
+                    BitrateBenchmarkState::Finished => "Finished".into(),
+                    BitrateBenchmarkState::Aborted(reason) => format!("Aborted: {reason}"),
+                });
+            }
         });
+
+        let Some(report) = report else {
+            return request;
+        };
+
+        if !running {
+            ui.label(match report.recommended_bitrate_mbps {
+                Some(bitrate_mbps) => format!("Recommended bitrate: {bitrate_mbps} Mbps"),
+                None => "No bitrate met the targets".into(),
+            });
+        }
+
+        if report.steps.is_empty() {
+            return request;
+        }
+
+        // Network latency of each step, the x axis is the step index
+        let max_latency_ms = report
+            .steps
+            .iter()
+            .filter_map(|step| step.latency_percentile_ms)
+            .fold(1.0, f32::max)
+            * 1.2;
+        Frame::canvas(ui.style()).show(ui, |ui| {
+            let (_id, canvas_rect) = ui.allocate_space(available_width * vec2(1.0, 0.2));
+            let data_rect =
+                Rect::from_x_y_ranges(-0.5..=report.step_count as f32 - 0.5, max_latency_ms..=0.0);
+            let to_screen = RectTransform::from_to(data_rect, canvas_rect);
+            let painter = ui.painter().with_clip_rect(canvas_rect);
+
+            for (index, step) in report.steps.iter().enumerate() {
+                if let Some(latency_ms) = step.latency_percentile_ms {
+                    let color = if step.passed {
+                        theme::OK_GREEN
+                    } else {
+                        theme::KO_RED
+                    };
+                    painter.rect_filled(
+                        Rect::from_two_pos(
+                            to_screen * pos2(index as f32 - 0.3, 0.0),
+                            to_screen * pos2(index as f32 + 0.3, latency_ms),
+                        ),
+                        CornerRadius::ZERO,
+                        color,
+                    );
+                }
+                painter.text(
+                    to_screen * pos2(index as f32, 0.0),
+                    Align2::CENTER_BOTTOM,
+                    step.bitrate_mbps.to_string(),
+                    FontId::monospace(12.0),
+                    theme::FG,
+                );
+            }
+
+            painter.text(
+                to_screen * pos2(-0.5, max_latency_ms),
+                Align2::LEFT_TOP,
+                format!("{max_latency_ms:.0} ms"),
+                FontId::monospace(20.0),
+                Color32::GRAY,
+            );
+        });
+
+        Grid::new("bitrate_benchmark")
+            .num_columns(5)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Bitrate");
+                ui.label("Latency");
+                ui.label("Jitter");
+                ui.label("Loss");
+                ui.label("Result");
+                ui.end_row();
+
+                fn ms_label(ui: &mut Ui, value_ms: Option<f32>) {
+                    ui.label(value_ms.map_or("-".into(), |value| format!("{value:.2} ms")));
+                }
+
+                for step in &report.steps {
+                    ui.label(format!("{} Mbps", step.bitrate_mbps));
+                    ms_label(ui, step.latency_percentile_ms);
+                    ms_label(ui, step.jitter_ms);
+                    ui.label(
+                        step.loss_percent
+                            .map_or("-".into(), |loss| format!("{loss:.1}%")),
+                    );
+                    if step.passed {
+                        ui.colored_label(theme::OK_GREEN, "Passed");
+                    } else {
+                        ui.colored_label(theme::KO_RED, "Failed");
+                    }
+                    ui.end_row();
+                }
+            });
+
+        request
     }
 }
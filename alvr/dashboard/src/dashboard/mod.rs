@@ -1,7 +1,8 @@
 mod components;
 
 use self::components::{
-    DevicesTab, LogsTab, NotificationBar, SettingsTab, SetupWizard, SetupWizardRequest,
+    DevicesTab, LogsTab, MirrorWindow, NotificationBar, SettingsTab, SetupWizard,
+    SetupWizardRequest,
 };
 use crate::{
     DataSources,
@@ -11,8 +12,8 @@ use alvr_common::{
     LogEntry,
     parking_lot::{Condvar, Mutex},
 };
-use alvr_events::EventType;
-use alvr_gui_common::theme;
+use alvr_events::{EventType, SteamvrVideoSettings};
+use alvr_gui_common::theme::{self, log_colors};
 use alvr_packets::{ClientConnectionsAction, PathValuePair};
 use alvr_session::SessionConfig;
 use eframe::egui::{
@@ -33,13 +34,26 @@ pub enum ServerRequest {
     },
     CaptureFrame,
     InsertIdr,
+    RequestClientLog,
+    // Log level overrides by target, see TargetLevels
+    SetLogLevels(String),
+    CalibrateExternalTrackers,
+    CaptureTrace,
+    // Reconnects the streaming clients
+    RestartStream,
     StartRecording,
     StopRecording,
+    StartTestPattern,
+    StopTestPattern,
+    StartBitrateBenchmark,
+    StopBitrateBenchmark,
     AddFirewallRules,
     RemoveFirewallRules,
     GetDriverList,
     RegisterAlvrDriver,
     UnregisterDriver(PathBuf),
+    GetSteamvrVideoSettings,
+    SetSteamvrVideoSettings(SteamvrVideoSettings),
     RestartSteamvr,
     ShutdownSteamvr,
 }
@@ -72,8 +86,15 @@ pub struct Dashboard {
     notification_bar: NotificationBar,
     setup_wizard: SetupWizard,
     new_version_popup: Option<components::NewVersionPopup>,
+    crash_popup: Option<components::CrashPopup>,
+    mirror_window: MirrorWindow,
     setup_wizard_open: bool,
     session: Option<SessionConfig>,
+    peripheral_input_injection_active: bool,
+    encoding_paused: bool,
+    eye_gaze_forwarding_active: bool,
+    // Backend used in place of the selected one, reset when SteamVR disconnects
+    encoder_fallback: Option<String>,
 }
 
 impl Dashboard {
@@ -111,6 +132,12 @@ impl Dashboard {
             setup_wizard_open: false,
             session: None,
             new_version_popup: None,
+            crash_popup: None,
+            mirror_window: MirrorWindow::new(),
+            peripheral_input_injection_active: false,
+            encoding_paused: false,
+            eye_gaze_forwarding_active: false,
+            encoder_fallback: None,
         }
     }
 
@@ -146,6 +173,9 @@ impl eframe::App for Dashboard {
         let mut requests = vec![];
 
         let connected_to_server = self.data_sources.server_connected();
+        if !connected_to_server {
+            self.encoder_fallback = None;
+        }
 
         while let Some(event) = self.data_sources.poll_event() {
             self.logs_tab.push_event(event.inner.clone());
@@ -161,12 +191,18 @@ impl eframe::App for Dashboard {
                 EventType::StatisticsSummary(statistics) => {
                     self.statistics_tab.update_statistics(statistics)
                 }
+                EventType::BitrateBenchmark(report) => {
+                    self.statistics_tab.update_bitrate_benchmark(report)
+                }
                 EventType::Session(session) => {
                     let settings = session.to_settings();
 
                     self.connections_tab.update_client_list(&session);
                     self.settings_tab.update_session(&session.session_settings);
+                    self.settings_tab.update_settings(&settings);
                     self.logs_tab.update_settings(&settings);
+                    #[cfg(not(target_arch = "wasm32"))]
+                    self.installation_tab.update_settings(&settings);
                     self.notification_bar.update_settings(&settings);
                     if self.just_opened {
                         if settings.extra.open_setup_wizard {
@@ -181,16 +217,60 @@ impl eframe::App for Dashboard {
                 EventType::ServerRequestsSelfRestart => self.restart_steamvr(&mut requests),
                 #[cfg(not(target_arch = "wasm32"))]
                 EventType::DriversList(list) => self.installation_tab.update_drivers(list),
+                #[cfg(not(target_arch = "wasm32"))]
+                EventType::SteamvrVideoSettings(settings) => self
+                    .installation_tab
+                    .update_steamvr_video_settings(settings),
                 EventType::Adb(adb_event) => self
                     .connections_tab
                     .update_adb_download_progress(adb_event.download_progress),
                 EventType::NewVersionFound { version, message } => {
                     self.new_version_popup = Some(NewVersionPopup::new(version, message));
                 }
-                EventType::DebugGroup { .. }
-                | EventType::Tracking(_)
-                | EventType::Buttons(_)
-                | EventType::Haptics(_) => (),
+                EventType::PeripheralInputInjection { active } => {
+                    self.peripheral_input_injection_active = active;
+                }
+                EventType::EncodingPaused { paused } => {
+                    self.encoding_paused = paused;
+                }
+                EventType::EyeGazeForwarding { active } => {
+                    self.eye_gaze_forwarding_active = active;
+                }
+                EventType::DriverCrashed {
+                    report_path,
+                    crash_loop,
+                } => {
+                    let hardware_encoding = self.session.as_ref().is_some_and(|session| {
+                        !session
+                            .session_settings
+                            .video
+                            .encoder_config
+                            .software
+                            .force_software_encoding
+                    });
+
+                    self.crash_popup = Some(components::CrashPopup::new(
+                        report_path,
+                        crash_loop,
+                        hardware_encoding,
+                    ));
+                }
+                EventType::Tracking(tracking) => self
+                    .connections_tab
+                    .update_tracking(&tracking, context.input(|input| input.time)),
+                EventType::WifiLink { hostname, link } => {
+                    self.connections_tab.update_wifi_link(hostname, link)
+                }
+                EventType::BiometricStreamsBlocked { hostname, blocked } => self
+                    .connections_tab
+                    .update_biometrics_blocked(hostname, blocked),
+                EventType::EncoderFallback { used, .. } => {
+                    self.encoder_fallback = Some(used);
+                }
+                EventType::PendingSettingChanges(changes) => {
+                    self.settings_tab.update_pending_changes(changes)
+                }
+                EventType::DebugGroup { .. } | EventType::Buttons(_) | EventType::Haptics(_) => (),
             }
         }
 
@@ -260,6 +340,39 @@ impl eframe::App for Dashboard {
                         |ui| {
                             ui.add_space(5.0);
 
+                            if connected_to_server && self.peripheral_input_injection_active {
+                                ui.label(
+                                    RichText::new("⌨ Keyboard and mouse active")
+                                        .color(theme::OK_GREEN)
+                                        .size(13.0),
+                                );
+                            }
+
+                            if connected_to_server && self.eye_gaze_forwarding_active {
+                                ui.label(
+                                    RichText::new("👁 Eye gaze forwarded")
+                                        .color(log_colors::WARNING_LIGHT)
+                                        .size(13.0),
+                                );
+                            }
+
+                            if connected_to_server && let Some(backend) = &self.encoder_fallback {
+                                ui.label(
+                                    RichText::new(format!("⚠ Encoding with {backend} (fallback)"))
+                                        .color(log_colors::WARNING_LIGHT)
+                                        .size(13.0),
+                                )
+                                .on_hover_text("The selected encoder failed, see the logs");
+                            }
+
+                            if connected_to_server && self.encoding_paused {
+                                ui.label(
+                                    RichText::new("⏸ Stream paused (headset standby)")
+                                        .color(log_colors::WARNING_LIGHT)
+                                        .size(13.0),
+                                );
+                            }
+
                             if connected_to_server {
                                 if ui.button("Restart SteamVR").clicked() {
                                     self.restart_steamvr(&mut requests);
@@ -305,7 +418,13 @@ impl eframe::App for Dashboard {
                                 }
                             }
                             Tab::Settings => {
-                                requests.extend(self.settings_tab.ui(ui));
+                                for request in self.settings_tab.ui(ui) {
+                                    if matches!(request, ServerRequest::RestartSteamvr) {
+                                        self.restart_steamvr(&mut requests);
+                                    } else {
+                                        requests.push(request);
+                                    }
+                                }
                             }
                             #[cfg(not(target_arch = "wasm32"))]
                             Tab::Installation => {
@@ -322,9 +441,15 @@ impl eframe::App for Dashboard {
                                     }
                                 }
                             }
-                            Tab::Logs => self.logs_tab.ui(ui),
+                            Tab::Logs => {
+                                if let Some(request) = self.logs_tab.ui(ui) {
+                                    requests.push(request);
+                                }
+                            }
                             Tab::Debug => {
-                                if let Some(request) = components::debug_tab_ui(ui) {
+                                if let Some(request) =
+                                    components::debug_tab_ui(ui, &mut self.mirror_window.open)
+                                {
                                     requests.push(request);
                                 }
                             }
@@ -352,6 +477,21 @@ impl eframe::App for Dashboard {
             self.new_version_popup = None;
         }
 
+        if let Some(popup) = &self.crash_popup
+            && let Some(action) = popup.ui(context)
+        {
+            if let CloseAction::CloseWithRequest(request) = action {
+                requests.push(request);
+            }
+
+            self.crash_popup = None;
+        }
+
+        self.data_sources
+            .set_mirror_enabled(self.mirror_window.open);
+        self.mirror_window
+            .ui(context, self.data_sources.take_mirror_frame());
+
         for request in requests {
             self.data_sources.request(request);
         }
@@ -1,4 +1,4 @@
-use crate::dashboard::ServerRequest;
+use crate::{crash_report, dashboard::ServerRequest, steamvr_launcher};
 use alvr_common::{
     ALVR_VERSION, RelaxedAtomic, debug, error, info,
     parking_lot::Mutex,
@@ -11,8 +11,11 @@ use alvr_server_io::ServerSessionManager;
 use eframe::egui;
 use serde::Serialize;
 use std::{
+    collections::VecDeque,
+    fs,
     io::ErrorKind,
     net::{SocketAddr, TcpStream},
+    process::Command,
     str::FromStr,
     sync::{Arc, mpsc},
     thread::{self, JoinHandle},
@@ -25,6 +28,12 @@ use tungstenite::{
 
 const LOCAL_REQUEST_TIMEOUT: Duration = Duration::from_millis(200);
 const REMOTE_REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+const CRASH_LOOP_WINDOW: Duration = Duration::from_secs(600);
+const CRASH_LOOP_COUNT: usize = 3;
+const MIRROR_POLL_INTERVAL: Duration = Duration::from_millis(100);
+// The mirror is (re)started if no frame is received for this long, for example after SteamVR
+// restarts
+const MIRROR_RESTART_INTERVAL: Duration = Duration::from_secs(1);
 
 enum SessionSource {
     Local(Box<ServerSessionManager>),
@@ -87,10 +96,7 @@ fn report_event_local(
 ) {
     sender
         .send(PolledEvent {
-            inner: Event {
-                timestamp: "".into(),
-                event_type,
-            },
+            inner: Event::new("".into(), event_type),
             from_dashboard: false,
         })
         .ok();
@@ -109,6 +115,30 @@ fn report_session_local(
     )
 }
 
+// The body is the width and height as little endian u32, followed by the RGBA pixels
+fn get_mirror_frame(rq: &ureq::Agent, uri: &str) -> Option<egui::ColorImage> {
+    let body = rq
+        .get(uri)
+        .header("X-ALVR", "true")
+        .call()
+        .ok()?
+        .into_body()
+        .read_to_vec()
+        .ok()?;
+
+    let width = u32::from_le_bytes(body.get(0..4)?.try_into().ok()?) as usize;
+    let height = u32::from_le_bytes(body.get(4..8)?.try_into().ok()?) as usize;
+    let pixels = &body[8..];
+    if pixels.len() != width * height * 4 {
+        return None;
+    }
+
+    Some(egui::ColorImage::from_rgba_unmultiplied(
+        [width, height],
+        pixels,
+    ))
+}
+
 pub struct PolledEvent {
     pub inner: Event,
     pub from_dashboard: bool,
@@ -119,10 +149,14 @@ pub struct DataSources {
     requests_sender: mpsc::Sender<ServerRequest>,
     events_receiver: mpsc::Receiver<PolledEvent>,
     server_connected: Arc<RelaxedAtomic>,
+    mirror_enabled: Arc<RelaxedAtomic>,
+    mirror_frame: Arc<Mutex<Option<egui::ColorImage>>>,
     version_check_thread: Option<JoinHandle<Option<()>>>,
     requests_thread: Option<JoinHandle<()>>,
     events_thread: Option<JoinHandle<()>>,
     ping_thread: Option<JoinHandle<()>>,
+    crash_watchdog_thread: Option<JoinHandle<()>>,
+    mirror_thread: Option<JoinHandle<()>>,
 }
 
 impl DataSources {
@@ -136,6 +170,8 @@ impl DataSources {
         let running = Arc::new(RelaxedAtomic::new(true));
         let (requests_sender, requests_receiver) = mpsc::channel();
         let server_connected = Arc::new(RelaxedAtomic::new(false));
+        let mirror_enabled = Arc::new(RelaxedAtomic::new(false));
+        let mirror_frame = Arc::new(Mutex::new(None));
 
         let session_manager = get_local_session_source();
         let port = session_manager.settings().connection.web_server_port;
@@ -247,6 +283,16 @@ impl DataSources {
 
                                     report_session_local(&context, &events_sender, session_manager);
                                 }
+                                ServerRequest::SetLogLevels(spec) => {
+                                    session_manager
+                                        .session_mut()
+                                        .session_settings
+                                        .extra
+                                        .logging
+                                        .target_levels = spec;
+
+                                    report_session_local(&context, &events_sender, session_manager);
+                                }
                                 ServerRequest::AddFirewallRules => {
                                     if let Err(e) = alvr_server_io::firewall_rules(
                                         FirewallRulesAction::Add,
@@ -302,10 +348,61 @@ impl DataSources {
                                         )
                                     }
                                 }
+                                // SteamVR is not running, the file can be changed directly
+                                ServerRequest::GetSteamvrVideoSettings => {
+                                    match alvr_server_io::get_steamvr_video_settings() {
+                                        Ok(settings) => report_event_local(
+                                            &context,
+                                            &events_sender,
+                                            EventType::SteamvrVideoSettings(settings),
+                                        ),
+                                        Err(e) => {
+                                            warn!("Failed to read the SteamVR video settings: {e}")
+                                        }
+                                    }
+                                }
+                                ServerRequest::SetSteamvrVideoSettings(settings) => {
+                                    let res =
+                                        alvr_server_io::steamvr_video_settings_edits(&settings)
+                                            .and_then(|edits| {
+                                                alvr_server_io::write_steamvr_setting_edits(&edits)
+                                            })
+                                            .and_then(|_| {
+                                                alvr_server_io::get_steamvr_video_settings()
+                                            });
+                                    match res {
+                                        Ok(settings) => report_event_local(
+                                            &context,
+                                            &events_sender,
+                                            EventType::SteamvrVideoSettings(settings),
+                                        ),
+                                        Err(e) => error!(
+                                            "Failed to change the SteamVR video settings: {e}"
+                                        ),
+                                    }
+                                }
+                                // Streamed by a separate process, which is then stopped through
+                                // its web server like the driver
+                                ServerRequest::StartTestPattern => {
+                                    let exe = crate::get_filesystem_layout().test_pattern_exe();
+                                    if let Err(e) = Command::new(&exe).spawn() {
+                                        error!(
+                                            "Failed to launch the test pattern streamer ({}): {e}",
+                                            exe.display()
+                                        );
+                                    }
+                                }
                                 ServerRequest::CaptureFrame
                                 | ServerRequest::InsertIdr
+                                | ServerRequest::RequestClientLog
+                                | ServerRequest::CalibrateExternalTrackers
+                                | ServerRequest::CaptureTrace
+                                | ServerRequest::RestartStream
                                 | ServerRequest::StartRecording
-                                | ServerRequest::StopRecording => {
+                                | ServerRequest::StopRecording
+                                | ServerRequest::StopTestPattern
+                                | ServerRequest::StartBitrateBenchmark
+                                | ServerRequest::StopBitrateBenchmark => {
                                     warn!(
                                         "Cannot perform action, streamer (SteamVR) is not connected."
                                     )
@@ -365,8 +462,34 @@ impl DataSources {
                                 }
                                 ServerRequest::CaptureFrame => post("capture-frame"),
                                 ServerRequest::InsertIdr => post("insert-idr"),
+                                ServerRequest::RequestClientLog => post("client-log/request"),
+                                ServerRequest::SetLogLevels(spec) => {
+                                    post_body(&rq, &base_uri, "log/levels", Some(spec))
+                                }
+                                ServerRequest::CalibrateExternalTrackers => {
+                                    post("external-trackers/calibrate")
+                                }
+                                ServerRequest::CaptureTrace => post("trace/capture"),
+                                ServerRequest::RestartStream => post("stream/restart"),
                                 ServerRequest::StartRecording => post("recording/start"),
                                 ServerRequest::StopRecording => post("recording/stop"),
+                                ServerRequest::StartTestPattern => post("test-pattern/start"),
+                                ServerRequest::StopTestPattern => post("test-pattern/stop"),
+                                ServerRequest::StartBitrateBenchmark => {
+                                    post("bitrate-benchmark/start")
+                                }
+                                ServerRequest::StopBitrateBenchmark => {
+                                    post("bitrate-benchmark/stop")
+                                }
+                                ServerRequest::GetSteamvrVideoSettings => {
+                                    get("steamvr/video-settings")
+                                }
+                                ServerRequest::SetSteamvrVideoSettings(settings) => post_body(
+                                    &rq,
+                                    &base_uri,
+                                    "steamvr/video-settings",
+                                    Some(settings),
+                                ),
                                 ServerRequest::RestartSteamvr => post("restart-steamvr"),
                                 ServerRequest::ShutdownSteamvr => post("shutdown-steamvr"),
                             }
@@ -378,8 +501,77 @@ impl DataSources {
             }
         });
 
+        // The driver leaves its marker file behind if SteamVR closes without shutting it down. A
+        // marker found at startup is from a crash that happened while the dashboard was closed.
+        let crash_watchdog_thread = thread::spawn({
+            let running = Arc::clone(&running);
+            let context = context.clone();
+            let events_sender = events_sender.clone();
+            let server_connected = Arc::clone(&server_connected);
+            move || {
+                let filesystem_layout = crate::get_filesystem_layout();
+                let mut crash_times = VecDeque::new();
+                let mut was_connected = false;
+                let mut check_pending = true;
+
+                while running.value() {
+                    let connected = server_connected.value();
+                    if connected {
+                        check_pending = false;
+                    } else if was_connected {
+                        check_pending = true;
+                    }
+                    was_connected = connected;
+
+                    // Wait for SteamVR to exit, a clean shutdown removes the marker before the web
+                    // server stops
+                    if check_pending && !steamvr_launcher::is_steamvr_running() {
+                        check_pending = false;
+
+                        if fs::remove_file(filesystem_layout.driver_running_marker()).is_ok() {
+                            crash_times.push_back(Instant::now());
+                            while crash_times
+                                .front()
+                                .is_some_and(|time| time.elapsed() > CRASH_LOOP_WINDOW)
+                            {
+                                crash_times.pop_front();
+                            }
+                            let crash_loop = crash_times.len() >= CRASH_LOOP_COUNT;
+
+                            error!("SteamVR closed unexpectedly while the ALVR driver was running");
+
+                            let session = get_local_session_source().session().clone();
+                            let report_path =
+                                match crash_report::create_report(&filesystem_layout, &session) {
+                                    Ok(path) => {
+                                        info!("Crash report saved to {}", path.display());
+                                        Some(path)
+                                    }
+                                    Err(e) => {
+                                        error!("Failed to create the crash report: {e}");
+                                        None
+                                    }
+                                };
+
+                            report_event_local(
+                                &context,
+                                &events_sender,
+                                EventType::DriverCrashed {
+                                    report_path,
+                                    crash_loop,
+                                },
+                            );
+                        }
+                    }
+
+                    thread::sleep(Duration::from_secs(1));
+                }
+            }
+        });
+
         let events_thread = thread::spawn({
             let running = Arc::clone(&running);
+            let context = context.clone();
             let session_source = Arc::clone(&session_source);
             move || {
                 while running.value() {
@@ -514,15 +706,68 @@ impl DataSources {
             }
         });
 
+        // Polled only while the mirror window is open, to keep the overhead low
+        let mirror_thread = thread::spawn({
+            let running = Arc::clone(&running);
+            let context = context.clone();
+            let server_connected = Arc::clone(&server_connected);
+            let mirror_enabled = Arc::clone(&mirror_enabled);
+            let mirror_frame = Arc::clone(&mirror_frame);
+            move || {
+                let base_uri = format!("http://127.0.0.1:{port}/api/mirror");
+                let rq: ureq::Agent = ureq::Agent::config_builder()
+                    .timeout_global(Some(LOCAL_REQUEST_TIMEOUT))
+                    .build()
+                    .into();
+                let post = |path: &str| {
+                    rq.post(format!("{base_uri}/{path}"))
+                        .header("X-ALVR", "true")
+                        .send_empty()
+                        .ok();
+                };
+
+                // Time of the last start request or received frame, None while stopped
+                let mut last_activity: Option<Instant> = None;
+                while running.value() {
+                    if mirror_enabled.value() && server_connected.value() {
+                        if last_activity.is_none_or(|time| time.elapsed() > MIRROR_RESTART_INTERVAL)
+                        {
+                            post("start");
+                            last_activity = Some(Instant::now());
+                        }
+
+                        if let Some(frame) = get_mirror_frame(&rq, &format!("{base_uri}/frame")) {
+                            *mirror_frame.lock() = Some(frame);
+                            last_activity = Some(Instant::now());
+                            context.request_repaint();
+                        }
+                    } else if last_activity.is_some() {
+                        post("stop");
+                        last_activity = None;
+                    }
+
+                    thread::sleep(MIRROR_POLL_INTERVAL);
+                }
+
+                if last_activity.is_some() {
+                    post("stop");
+                }
+            }
+        });
+
         Self {
             requests_sender,
             events_receiver,
             server_connected,
+            mirror_enabled,
+            mirror_frame,
             running,
             version_check_thread: Some(version_check_thread),
             requests_thread: Some(requests_thread),
             events_thread: Some(events_thread),
             ping_thread: Some(ping_thread),
+            crash_watchdog_thread: Some(crash_watchdog_thread),
+            mirror_thread: Some(mirror_thread),
         }
     }
 
@@ -537,6 +782,15 @@ impl DataSources {
     pub fn server_connected(&self) -> bool {
         self.server_connected.value()
     }
+
+    pub fn set_mirror_enabled(&self, enabled: bool) {
+        self.mirror_enabled.set(enabled);
+    }
+
+    // The latest frame not yet displayed
+    pub fn take_mirror_frame(&self) -> Option<egui::ColorImage> {
+        self.mirror_frame.lock().take()
+    }
 }
 
 impl Drop for DataSources {
@@ -547,5 +801,7 @@ impl Drop for DataSources {
         self.requests_thread.take().unwrap().join().ok();
         self.events_thread.take().unwrap().join().ok();
         self.ping_thread.take().unwrap().join().ok();
+        self.crash_watchdog_thread.take().unwrap().join().ok();
+        self.mirror_thread.take().unwrap().join().ok();
     }
 }
@@ -26,13 +26,13 @@ pub fn init_logging(event_sender: mpsc::Sender<PolledEvent>) {
             event_sender
                 .lock()
                 .send(PolledEvent {
-                    inner: Event {
-                        timestamp: timestamp.clone(),
-                        event_type: EventType::Log(LogEntry {
+                    inner: Event::new(
+                        timestamp.clone(),
+                        EventType::Log(LogEntry {
                             severity: LogSeverity::from_log_level(record.level()),
                             content: format!("{}", record.args()),
                         }),
-                    },
+                    ),
                     from_dashboard: true,
                 })
                 .ok();
@@ -3,6 +3,10 @@
 
 mod dashboard;
 
+#[cfg(not(target_arch = "wasm32"))]
+mod client_installer;
+#[cfg(not(target_arch = "wasm32"))]
+mod crash_report;
 #[cfg(not(target_arch = "wasm32"))]
 mod data_sources;
 #[cfg(target_arch = "wasm32")]
@@ -73,6 +77,18 @@ fn main() {
         steamvr_launcher::LAUNCHER.lock().launch_steamvr()
     }
 
+    std::thread::spawn(|| {
+        if let Err(e) = steamvr_launcher::client_discovery_launch_loop() {
+            alvr_common::warn!("Headset discovery for launching SteamVR stopped: {e}");
+        }
+    });
+
+    std::thread::spawn(|| {
+        if let Err(e) = alvr_sockets::socket_keeper_loop(&get_filesystem_layout().socket_keeper()) {
+            alvr_common::warn!("The connection will not be kept while SteamVR restarts: {e}");
+        }
+    });
+
     let ico = IconDir::read(Cursor::new(include_bytes!("../resources/dashboard.ico"))).unwrap();
     let image = ico.entries().first().unwrap().decode().unwrap();
 
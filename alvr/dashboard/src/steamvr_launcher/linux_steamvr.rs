@@ -6,9 +6,9 @@ use alvr_common::anyhow::bail;
 use alvr_common::{debug, error, info, warn};
 use sysinfo::Process;
 
-pub fn launch_steamvr_with_steam() {
+pub fn launch_steam_app(app_id: u32) {
     Command::new("steam")
-        .args(["steam://rungameid/250820"])
+        .args([format!("steam://rungameid/{app_id}")])
         .spawn()
         .ok();
 }
@@ -9,10 +9,13 @@ use alvr_common::{
     anyhow::{Context, Result},
     debug, error,
     glam::bool,
+    info,
     parking_lot::Mutex,
     warn,
 };
 use alvr_filesystem::{self as afs};
+use alvr_sockets::{MDNS_DEVICE_ID_KEY, MDNS_SERVICE_TYPE};
+use mdns_sd::{ServiceDaemon, ServiceEvent};
 use serde_json::{self, json};
 use std::{
     ffi::OsStr,
@@ -25,6 +28,15 @@ use std::{
 use sysinfo::{ProcessesToUpdate, System};
 
 const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+const STEAMVR_APP_ID: u32 = 250820;
+// mDNS reports each client only once, browsing is restarted to see clients already announced
+const DISCOVERY_REBROWSE_INTERVAL: Duration = Duration::from_secs(10);
+// Avoids launching SteamVR in a loop if it fails to start or is closed by the user while the
+// headset is still on
+const DISCOVERY_LAUNCH_COOLDOWN: Duration = Duration::from_secs(60);
+const STEAMVR_STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+// Time left to the ALVR driver to load before launching the app
+const APP_LAUNCH_DELAY: Duration = Duration::from_secs(5);
 const DRIVER_KEY: &str = "driver_alvr_server";
 const BLOCKED_KEY: &str = "blocked_by_safe_mode";
 
@@ -35,6 +47,15 @@ pub fn is_steamvr_running() -> bool {
         != 0
 }
 
+#[allow(unused_variables)]
+fn launch_steam_app(app_id: u32) {
+    #[cfg(windows)]
+    windows_steamvr::launch_steam_app(app_id);
+
+    #[cfg(target_os = "linux")]
+    linux_steamvr::launch_steam_app(app_id);
+}
+
 pub fn maybe_kill_steamvr() {
     let mut system = System::new_all();
 
@@ -192,11 +213,7 @@ impl Launcher {
                 error!("Failed to find SteamVR files to directly launch SteamVR");
             }
         } else {
-            #[cfg(windows)]
-            windows_steamvr::launch_steamvr_with_steam();
-
-            #[cfg(target_os = "linux")]
-            linux_steamvr::launch_steamvr_with_steam();
+            launch_steam_app(STEAMVR_APP_ID);
         }
     }
 
@@ -216,6 +233,62 @@ impl Launcher {
     }
 }
 
+// Launches SteamVR, and optionally a Steam app, when a trusted client is discovered while SteamVR
+// is not running. This runs for the whole lifetime of the dashboard.
+pub fn client_discovery_launch_loop() -> Result<()> {
+    let daemon = ServiceDaemon::new()?;
+    let mut last_launch_time = None::<Instant>;
+
+    loop {
+        let receiver = daemon.browse(MDNS_SERVICE_TYPE)?;
+        let browse_deadline = Instant::now() + DISCOVERY_REBROWSE_INTERVAL;
+
+        while let Ok(event) = receiver.recv_deadline(browse_deadline) {
+            let ServiceEvent::ServiceResolved(info) = event else {
+                continue;
+            };
+            let hostname = info
+                .get_property_val_str(MDNS_DEVICE_ID_KEY)
+                .unwrap_or_else(|| info.get_hostname());
+
+            let session = data_sources::get_read_only_local_session();
+            let config = &session.settings().extra.steamvr_launcher;
+            if !config.launch_on_client_discovery
+                || !session
+                    .client_list()
+                    .get(hostname)
+                    .is_some_and(|client| client.trusted)
+                || last_launch_time.is_some_and(|time| time.elapsed() < DISCOVERY_LAUNCH_COOLDOWN)
+                || is_steamvr_running()
+            {
+                continue;
+            }
+
+            info!("Trusted client {hostname} discovered, launching SteamVR");
+            last_launch_time = Some(Instant::now());
+            LAUNCHER.lock().launch_steamvr();
+
+            if let Some(app_id) = config.autolaunch_app_id.as_option() {
+                let start_time = Instant::now();
+                while start_time.elapsed() < STEAMVR_STARTUP_TIMEOUT && !is_steamvr_running() {
+                    thread::sleep(Duration::from_millis(500));
+                }
+
+                if is_steamvr_running() {
+                    thread::sleep(APP_LAUNCH_DELAY);
+
+                    info!("Launching Steam app {app_id}");
+                    launch_steam_app(*app_id);
+                } else {
+                    warn!("SteamVR did not start, Steam app {app_id} not launched");
+                }
+            }
+        }
+
+        daemon.stop_browse(MDNS_SERVICE_TYPE).ok();
+    }
+}
+
 // Singleton with exclusive access
 pub static LAUNCHER: Mutex<Launcher> = Mutex::new(Launcher {
     _phantom: PhantomData,
@@ -3,9 +3,9 @@ use std::process::Command;
 
 const CREATE_NO_WINDOW: u32 = 0x0800_0000;
 
-pub fn launch_steamvr_with_steam() {
+pub fn launch_steam_app(app_id: u32) {
     Command::new("cmd")
-        .args(["/C", "start", "steam://rungameid/250820"])
+        .args(["/C", "start", &format!("steam://rungameid/{app_id}")])
         .creation_flags(CREATE_NO_WINDOW)
         .spawn()
         .ok();
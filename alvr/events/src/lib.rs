@@ -1,15 +1,23 @@
 use alvr_common::{DeviceMotion, LogEntry, LogSeverity, Pose, info};
-use alvr_packets::{ButtonValue, FaceData};
-use alvr_session::SessionConfig;
+use alvr_packets::{ButtonValue, FaceData, ThermalStatus, WifiLinkInfo};
+use alvr_session::{SessionConfig, SettingChange};
 use serde::{Deserialize, Serialize};
 use std::{path::PathBuf, time::Duration};
 
+// Version of the JSON layout of Event, including the statistics. It is incremented when existing
+// fields or variants are removed, renamed or change meaning. Adding new event types or new fields
+// does not change the version, so consumers should ignore the unknown ones.
+pub const EVENTS_SCHEMA_VERSION: u32 = 1;
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct StatisticsSummary {
     pub video_packets_total: usize,
     pub video_packets_per_sec: usize,
     pub video_mbytes_total: usize,
     pub video_mbits_per_sec: f32,
+    // Send rates including the protocol overhead
+    pub audio_kbits_per_sec: f32,
+    pub haptics_kbits_per_sec: f32,
     pub total_latency_ms: f32,
     pub network_latency_ms: f32,
     pub encode_latency_ms: f32,
@@ -18,6 +26,19 @@ pub struct StatisticsSummary {
     pub server_fps: u32,
     pub battery_hmd: u32,
     pub hmd_plugged: bool,
+    pub hmd_thermal_status: Option<ThermalStatus>,
+    pub idr_requests_total: usize,
+    pub intra_refresh_recoveries_total: usize,
+    pub tracking_packets_discarded_total: usize,
+    // Time spent at the reduced frame rate of the idle throttling
+    pub idle_time_total_s: f32,
+    pub decoder_queue_drops_total: usize,
+    pub extrapolated_frames_total: usize,
+    pub clock_drift_ppm: f32,
+    pub clock_sync_confidence: f32,
+    // One-way latencies, None until the clocks are synchronized
+    pub uplink_latency_ms: Option<f32>,
+    pub downlink_latency_ms: Option<f32>,
 }
 
 // Bitrate statistics minus the empirical output value
@@ -29,6 +50,10 @@ pub struct BitrateDirectives {
     pub encoder_latency_limiter_bps: Option<f32>,
     pub manual_max_throughput_bps: Option<f32>,
     pub manual_min_throughput_bps: Option<f32>,
+    pub thermal_limiter_bps: Option<f32>,
+    pub bandwidth_budget_limiter_bps: Option<f32>,
+    // Audio, control and protocol overhead
+    pub stream_overhead_bps: Option<f32>,
     pub requested_bitrate_bps: f32,
 }
 
@@ -43,11 +68,42 @@ pub struct GraphStatistics {
     pub decoder_queue_s: f32,
     pub client_compositor_s: f32,
     pub vsync_queue_s: f32,
+    // Part of the total latency recovered by the streamer-side reprojection for the head rotation
+    pub server_reprojection_s: f32,
     pub client_fps: f32,
     pub server_fps: f32,
     pub bitrate_directives: BitrateDirectives,
     pub throughput_bps: f32,
     pub bitrate_bps: f32,
+    // Change of the Wi-Fi link of the client since the previous frame, like a roam
+    #[serde(default)]
+    pub wifi_transition: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BitrateBenchmarkStep {
+    pub bitrate_mbps: u64,
+    // None if no frame was displayed by the client
+    pub latency_percentile_ms: Option<f32>,
+    pub jitter_ms: Option<f32>,
+    // None if the client did not report it in time
+    pub loss_percent: Option<f32>,
+    pub passed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum BitrateBenchmarkState {
+    Running { bitrate_mbps: u64 },
+    Finished,
+    Aborted(String),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BitrateBenchmarkReport {
+    pub state: BitrateBenchmarkState,
+    pub step_count: usize,
+    pub steps: Vec<BitrateBenchmarkStep>,
+    pub recommended_bitrate_mbps: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -76,30 +132,90 @@ pub struct AdbEvent {
     pub download_progress: f32,
 }
 
+// Video settings in steamvr.vrsettings. None means the SteamVR default
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct SteamvrVideoSettings {
+    // Render resolution multiplier, relative to the pixel count recommended by the driver
+    pub resolution_scale: Option<f32>,
+    pub motion_smoothing: Option<bool>,
+    // Steam app ID and resolution multiplier of the applications with their own resolution
+    pub app_resolution_scales: Vec<(String, f32)>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(tag = "id", content = "data")]
 pub enum EventType {
     Log(LogEntry),
-    DebugGroup { group: String, message: String },
+    DebugGroup {
+        group: String,
+        message: String,
+    },
     Session(Box<SessionConfig>),
     StatisticsSummary(StatisticsSummary),
     GraphStatistics(GraphStatistics),
+    BitrateBenchmark(BitrateBenchmarkReport),
     Tracking(Box<TrackingEvent>),
     Buttons(Vec<ButtonEvent>),
     Haptics(HapticsEvent),
     DriversList(Vec<PathBuf>),
+    SteamvrVideoSettings(SteamvrVideoSettings),
     ServerRequestsSelfRestart,
     Adb(AdbEvent),
-    NewVersionFound { version: String, message: String },
+    NewVersionFound {
+        version: String,
+        message: String,
+    },
+    PeripheralInputInjection {
+        active: bool,
+    },
+    EncodingPaused {
+        paused: bool,
+    },
+    EyeGazeForwarding {
+        active: bool,
+    },
+    // Sent by the dashboard itself when SteamVR closed without shutting down the driver
+    DriverCrashed {
+        report_path: Option<PathBuf>,
+        crash_loop: bool,
+    },
+    // None if the client is not connected over Wi-Fi
+    WifiLink {
+        hostname: String,
+        link: Option<WifiLinkInfo>,
+    },
+    // Settings changed while streaming that are not applied live. Empty when there are none left
+    PendingSettingChanges(Vec<SettingChange>),
+    // Reported by the client when it applies the privacy setting, false when it disconnects
+    BiometricStreamsBlocked {
+        hostname: String,
+        blocked: bool,
+    },
+    // The selected encoder backend failed to initialize and the used one is encoding instead
+    EncoderFallback {
+        selected: String,
+        used: String,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Event {
+    // Events sent by versions without this field are read as version 0
+    #[serde(default)]
+    pub schema_version: u32,
     pub timestamp: String,
     pub event_type: EventType,
 }
 
 impl Event {
+    pub fn new(timestamp: String, event_type: EventType) -> Self {
+        Self {
+            schema_version: EVENTS_SCHEMA_VERSION,
+            timestamp,
+            event_type,
+        }
+    }
+
     pub fn event_type_string(&self) -> String {
         match &self.event_type {
             EventType::Log(entry) => match entry.severity {
@@ -112,13 +228,23 @@ impl Event {
             EventType::Session(_) => "SESSION".to_string(),
             EventType::StatisticsSummary(_) => "STATS".to_string(),
             EventType::GraphStatistics(_) => "GRAPH".to_string(),
+            EventType::BitrateBenchmark(_) => "BENCHMARK".to_string(),
             EventType::Tracking(_) => "TRACKING".to_string(),
             EventType::Buttons(_) => "BUTTONS".to_string(),
             EventType::Haptics(_) => "HAPTICS".to_string(),
             EventType::DriversList(_) => "DRV LIST".to_string(),
+            EventType::SteamvrVideoSettings(_) => "STEAMVR".to_string(),
             EventType::ServerRequestsSelfRestart => "RESTART".to_string(),
             EventType::Adb(_) => "ADB".to_string(),
             EventType::NewVersionFound { .. } => "NEW VER".to_string(),
+            EventType::PeripheralInputInjection { .. } => "INJECTION".to_string(),
+            EventType::EncodingPaused { .. } => "STANDBY".to_string(),
+            EventType::EyeGazeForwarding { .. } => "EYE GAZE".to_string(),
+            EventType::DriverCrashed { .. } => "CRASH".to_string(),
+            EventType::WifiLink { .. } => "WIFI".to_string(),
+            EventType::PendingSettingChanges(_) => "SETTINGS".to_string(),
+            EventType::BiometricStreamsBlocked { .. } => "PRIVACY".to_string(),
+            EventType::EncoderFallback { .. } => "ENCODER".to_string(),
         }
     }
 
@@ -128,13 +254,36 @@ impl Event {
             EventType::DebugGroup { message, .. } => message.clone(),
             EventType::Session(_) => "Updated".into(),
             EventType::StatisticsSummary(_) | EventType::GraphStatistics(_) => "".into(),
+            EventType::BitrateBenchmark(report) => serde_json::to_string(report).unwrap(),
             EventType::Tracking(tracking) => serde_json::to_string(tracking).unwrap(),
             EventType::Buttons(buttons) => serde_json::to_string(buttons).unwrap(),
             EventType::Haptics(haptics) => serde_json::to_string(haptics).unwrap(),
             EventType::DriversList(drivers) => serde_json::to_string(drivers).unwrap(),
+            EventType::SteamvrVideoSettings(settings) => serde_json::to_string(settings).unwrap(),
             EventType::ServerRequestsSelfRestart => "Request for server restart".into(),
             EventType::Adb(adb) => serde_json::to_string(adb).unwrap(),
             EventType::NewVersionFound { version, .. } => version.clone(),
+            EventType::PeripheralInputInjection { active } => {
+                if *active { "Active" } else { "Stopped" }.into()
+            }
+            EventType::EncodingPaused { paused } => {
+                if *paused { "Paused" } else { "Resumed" }.into()
+            }
+            EventType::EyeGazeForwarding { active } => {
+                if *active { "Active" } else { "Stopped" }.into()
+            }
+            EventType::DriverCrashed { report_path, .. } => report_path
+                .as_ref()
+                .map(|path| path.to_string_lossy().into())
+                .unwrap_or_default(),
+            EventType::WifiLink { link, .. } => serde_json::to_string(link).unwrap(),
+            EventType::PendingSettingChanges(changes) => serde_json::to_string(changes).unwrap(),
+            EventType::BiometricStreamsBlocked { blocked, .. } => {
+                if *blocked { "Blocked" } else { "Unblocked" }.into()
+            }
+            EventType::EncoderFallback { selected, used } => {
+                format!("Using {used} instead of {selected}")
+            }
         }
     }
 }
@@ -177,6 +177,11 @@ impl Layout {
         self.executables_dir.join(dashboard_fname())
     }
 
+    // Streams the test pattern without SteamVR
+    pub fn test_pattern_exe(&self) -> PathBuf {
+        self.executables_dir.join(exec_fname("alvr_test_pattern"))
+    }
+
     pub fn local_adb_exe(&self) -> PathBuf {
         self.executables_dir
             .join("platform-tools")
@@ -235,6 +240,26 @@ impl Layout {
         self.log_dir.join("crash_log.txt")
     }
 
+    // Present while the driver is running, left behind if SteamVR closes without shutting down
+    // the driver
+    pub fn driver_running_marker(&self) -> PathBuf {
+        self.config_dir.join("driver_running")
+    }
+
+    // Endpoint of the dashboard that keeps the connection sockets of the driver while SteamVR
+    // restarts
+    pub fn socket_keeper(&self) -> PathBuf {
+        self.config_dir.join("socket_keeper")
+    }
+
+    pub fn crash_reports_dir(&self) -> PathBuf {
+        if cfg!(target_os = "linux") {
+            self.log_dir.join("alvr_crash_reports")
+        } else {
+            self.log_dir.join("crash_reports")
+        }
+    }
+
     pub fn openvr_driver_lib_dir(&self) -> PathBuf {
         let platform = if cfg!(windows) {
             "win64"
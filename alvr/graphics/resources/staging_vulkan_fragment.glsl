@@ -0,0 +1,24 @@
+#version 450
+
+layout(push_constant) uniform PushConstants {
+    int view_idx;
+    int fix_limited_range;
+} pc;
+
+// Combined with an immutable sampler that does the YCbCr to RGB conversion
+layout(set = 0, binding = 0) uniform sampler2D tex;
+
+// Convert from limited colors to full
+const float LIMITED_MIN = 16.0 / 255.0;
+const float LIMITED_MAX = 235.0 / 255.0;
+
+layout(location = 0) in vec2 uv;
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    vec3 color = texture(tex, uv).rgb;
+    if (pc.fix_limited_range != 0) {
+        color = LIMITED_MIN + ((LIMITED_MAX - LIMITED_MIN) * color);
+    }
+    out_color = vec4(color, 1.0);
+}
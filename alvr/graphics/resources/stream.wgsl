@@ -4,6 +4,7 @@ const THRESHOLD: f32 = 0.04045;
 const GAMMA: vec3f = vec3f(2.4);
 
 override ENABLE_SRGB_CORRECTION: bool;
+override TRANSFER_FUNCTION: u32; // 0: sRGB, 1: Gamma 2.2
 override ENCODING_GAMMA: f32;
 
 override ENABLE_UPSCALING: bool = false;
@@ -12,6 +13,7 @@ override UPSCALE_EDGE_THRESHOLD: f32 = 4.0/255.0;
 override UPSCALE_EDGE_SHARPNESS: f32 = 2.0;
 
 override ENABLE_FFE: bool = false;
+override FFE_MODE: u32 = 0; // 0: two zones, 1: radial
 
 override VIEW_WIDTH_RATIO: f32 = 0.0;
 override VIEW_HEIGHT_RATIO: f32 = 0.0;
@@ -39,6 +41,12 @@ override B_RIGHT_Y: f32 = 0.0;
 override C_RIGHT_X: f32 = 0.0;
 override C_RIGHT_Y: f32 = 0.0;
 
+// Inverse of the quadratic mapping of RadialFoveation::warp()
+override RADIAL_CENTER_X: f32 = 0.5;
+override RADIAL_CENTER_Y: f32 = 0.5;
+override RADIAL_ALPHA: f32 = 1.0;
+override RADIAL_SCALE: f32 = 1.0;
+
 struct PushConstant {
     reprojection_transform: mat4x4f,
     view_idx: u32,
@@ -76,46 +84,58 @@ fn fragment_main(@location(0) uv: vec2f) -> @location(0) vec4f {
     var upscale_source_resolution = 1.0;
     if ENABLE_FFE {
         let view_size_ratio = vec2f(VIEW_WIDTH_RATIO, VIEW_HEIGHT_RATIO);
-        let edge_ratio = vec2f(EDGE_X_RATIO, EDGE_Y_RATIO);
-
-        let c1 = vec2f(C1_X, C1_Y);
-        let c2 = vec2f(C2_X, C2_Y);
-        let lo_bound = vec2f(LO_BOUND_X, LO_BOUND_Y);
-        let hi_bound = vec2f(HI_BOUND_X, HI_BOUND_Y);
-
-        let a_left = vec2f(A_LEFT_X, A_LEFT_Y);
-        let b_left = vec2f(B_LEFT_X, B_LEFT_Y);
-
-        let a_right = vec2f(A_RIGHT_X, A_RIGHT_Y);
-        let b_right = vec2f(B_RIGHT_X, B_RIGHT_Y);
-        let c_right = vec2f(C_RIGHT_X, C_RIGHT_Y);
 
         if pc.view_idx == 1 {
             corrected_uv.x = 1.0 - corrected_uv.x;
         }
 
-        let center = (corrected_uv - c1) * edge_ratio / c2;
-        let left_edge = (-b_left + sqrt(b_left * b_left + 4.0 * a_left * corrected_uv)) / (2.0 * a_left);
-        let right_edge = (-b_right + sqrt(b_right * b_right - 4.0 * (c_right - a_right * corrected_uv))) / (2.0 * a_right);
-
-        if corrected_uv.x < lo_bound.x {
-            corrected_uv.x = left_edge.x;
-            upscale_source_resolution = upscale_source_resolution * edge_ratio.x;
-        } else if corrected_uv.x > hi_bound.x {
-            corrected_uv.x = right_edge.x;
-            upscale_source_resolution = upscale_source_resolution * edge_ratio.x;
-        } else {
-            corrected_uv.x = center.x;
-        }
+        if FFE_MODE == 1 {
+            let center = vec2f(RADIAL_CENTER_X, RADIAL_CENTER_Y);
+            let side = select(1.0 - center, center, corrected_uv < center);
+            let t = (corrected_uv - center) / side;
+            let u = 2.0 * t / (RADIAL_ALPHA + sqrt(RADIAL_ALPHA * RADIAL_ALPHA + 4.0 * (1.0 - RADIAL_ALPHA) * abs(t)));
+            corrected_uv = center + u * side;
 
-        if corrected_uv.y < lo_bound.y {
-            corrected_uv.y = left_edge.y;
-            upscale_source_resolution = upscale_source_resolution * edge_ratio.y;
-        } else if corrected_uv.y > hi_bound.y {
-            corrected_uv.y = right_edge.y;
-            upscale_source_resolution = upscale_source_resolution * edge_ratio.y;
+            let source_pixel_ratio = (RADIAL_ALPHA + 2.0 * (1.0 - RADIAL_ALPHA) * abs(u)) / RADIAL_SCALE;
+            upscale_source_resolution = source_pixel_ratio.x * source_pixel_ratio.y;
         } else {
-            corrected_uv.y = center.y;
+            let edge_ratio = vec2f(EDGE_X_RATIO, EDGE_Y_RATIO);
+
+            let c1 = vec2f(C1_X, C1_Y);
+            let c2 = vec2f(C2_X, C2_Y);
+            let lo_bound = vec2f(LO_BOUND_X, LO_BOUND_Y);
+            let hi_bound = vec2f(HI_BOUND_X, HI_BOUND_Y);
+
+            let a_left = vec2f(A_LEFT_X, A_LEFT_Y);
+            let b_left = vec2f(B_LEFT_X, B_LEFT_Y);
+
+            let a_right = vec2f(A_RIGHT_X, A_RIGHT_Y);
+            let b_right = vec2f(B_RIGHT_X, B_RIGHT_Y);
+            let c_right = vec2f(C_RIGHT_X, C_RIGHT_Y);
+
+            let center = (corrected_uv - c1) * edge_ratio / c2;
+            let left_edge = (-b_left + sqrt(b_left * b_left + 4.0 * a_left * corrected_uv)) / (2.0 * a_left);
+            let right_edge = (-b_right + sqrt(b_right * b_right - 4.0 * (c_right - a_right * corrected_uv))) / (2.0 * a_right);
+
+            if corrected_uv.x < lo_bound.x {
+                corrected_uv.x = left_edge.x;
+                upscale_source_resolution = upscale_source_resolution * edge_ratio.x;
+            } else if corrected_uv.x > hi_bound.x {
+                corrected_uv.x = right_edge.x;
+                upscale_source_resolution = upscale_source_resolution * edge_ratio.x;
+            } else {
+                corrected_uv.x = center.x;
+            }
+
+            if corrected_uv.y < lo_bound.y {
+                corrected_uv.y = left_edge.y;
+                upscale_source_resolution = upscale_source_resolution * edge_ratio.y;
+            } else if corrected_uv.y > hi_bound.y {
+                corrected_uv.y = right_edge.y;
+                upscale_source_resolution = upscale_source_resolution * edge_ratio.y;
+            } else {
+                corrected_uv.y = center.y;
+            }
         }
 
         corrected_uv = corrected_uv * view_size_ratio;
@@ -132,7 +152,9 @@ fn fragment_main(@location(0) uv: vec2f) -> @location(0) vec4f {
         color = textureSample(stream_texture, stream_sampler, corrected_uv).rgb;
     }
 
-    if ENABLE_SRGB_CORRECTION {
+    if ENABLE_SRGB_CORRECTION && TRANSFER_FUNCTION == 1 {
+        color = pow(max(color, vec3f(0.0)), vec3f(2.2));
+    } else if ENABLE_SRGB_CORRECTION {
         let condition = vec3f(f32(color.r < THRESHOLD), f32(color.g < THRESHOLD), f32(color.b < THRESHOLD));
         let lowValues = color * DIV12;
         let highValues = pow((color + vec3f(0.055)) * DIV1, GAMMA);
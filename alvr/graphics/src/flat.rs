@@ -0,0 +1,174 @@
+use crate::{GraphicsContext, SDR_FORMAT_GL};
+use alvr_common::glam::UVec2;
+use glow::{self as gl, HasContext};
+use khronos_egl as egl;
+use std::{ffi::c_void, rc::Rc};
+use wgpu::hal::api;
+
+// Shows the left view of the stream on an Android window, for the flat display clients. The context
+// is bound to the window surface only while presenting, wgpu expects the dummy surface otherwise.
+pub struct FlatDisplaySurface {
+    context: Rc<GraphicsContext>,
+    surface: egl::Surface,
+    view_resolution: UVec2,
+    // SDR_FORMAT_GL, the right view is rendered but not shown
+    view_textures: [gl::Texture; 2],
+    read_framebuffer: gl::Framebuffer,
+}
+
+impl FlatDisplaySurface {
+    // window is an ANativeWindow, it must outlive the surface
+    pub fn new(context: Rc<GraphicsContext>, window: *mut c_void, view_resolution: UVec2) -> Self {
+        let gles = context.gles().unwrap();
+
+        let surface = unsafe {
+            gles.adapter.as_hal::<api::Gles, _, _>(|raw_adapter| {
+                let egl_instance = raw_adapter
+                    .unwrap()
+                    .adapter_context()
+                    .egl_instance()
+                    .unwrap();
+
+                egl_instance
+                    .create_window_surface(gles.egl_display, gles.egl_config, window, None)
+                    .unwrap()
+            })
+        };
+
+        context.make_current();
+        let gl = &gles.gl_context;
+        let (view_textures, read_framebuffer) = unsafe {
+            let view_textures = [(); 2].map(|_| {
+                let texture = gl.create_texture().unwrap();
+                gl.bind_texture(gl::TEXTURE_2D, Some(texture));
+                gl.tex_storage_2d(
+                    gl::TEXTURE_2D,
+                    1,
+                    SDR_FORMAT_GL,
+                    view_resolution.x as i32,
+                    view_resolution.y as i32,
+                );
+
+                texture
+            });
+            gl.bind_texture(gl::TEXTURE_2D, None);
+
+            (view_textures, gl.create_framebuffer().unwrap())
+        };
+
+        Self {
+            context,
+            surface,
+            view_resolution,
+            view_textures,
+            read_framebuffer,
+        }
+    }
+
+    // Targets for the StreamRenderer, one image per view
+    pub fn view_textures(&self) -> [Vec<u32>; 2] {
+        self.view_textures.map(|texture| vec![texture.0.get()])
+    }
+
+    // The rows of the GL textures are stored bottom-up, as for the OpenXR swapchains. The view is
+    // letterboxed to fit the window
+    pub fn present(&self) {
+        let resolution = self.view_resolution;
+        let gles = self.context.gles().unwrap();
+        let gl = &gles.gl_context;
+
+        unsafe {
+            gles.adapter.as_hal::<api::Gles, _, _>(|raw_adapter| {
+                let egl_instance = raw_adapter
+                    .unwrap()
+                    .adapter_context()
+                    .egl_instance()
+                    .unwrap();
+
+                egl_instance
+                    .make_current(
+                        gles.egl_display,
+                        Some(self.surface),
+                        Some(self.surface),
+                        Some(gles.egl_context),
+                    )
+                    .unwrap();
+
+                let mut width = 0;
+                let mut height = 0;
+                egl_instance
+                    .query_surface(gles.egl_display, self.surface, egl::WIDTH, &mut width)
+                    .ok();
+                egl_instance
+                    .query_surface(gles.egl_display, self.surface, egl::HEIGHT, &mut height)
+                    .ok();
+                let window_resolution = UVec2::new(width as u32, height as u32);
+
+                let scale = (window_resolution.as_vec2() / resolution.as_vec2()).min_element();
+                let size = (resolution.as_vec2() * scale).as_uvec2();
+                let offset = (window_resolution - size) / 2;
+
+                gl.bind_framebuffer(gl::DRAW_FRAMEBUFFER, None);
+                gl.disable(gl::SCISSOR_TEST);
+                gl.viewport(0, 0, width, height);
+                gl.clear_color(0.0, 0.0, 0.0, 1.0);
+                gl.clear(gl::COLOR_BUFFER_BIT);
+
+                gl.bind_framebuffer(gl::READ_FRAMEBUFFER, Some(self.read_framebuffer));
+                gl.framebuffer_texture_2d(
+                    gl::READ_FRAMEBUFFER,
+                    gl::COLOR_ATTACHMENT0,
+                    gl::TEXTURE_2D,
+                    Some(self.view_textures[0]),
+                    0,
+                );
+                gl.blit_framebuffer(
+                    0,
+                    0,
+                    resolution.x as i32,
+                    resolution.y as i32,
+                    offset.x as i32,
+                    offset.y as i32,
+                    (offset.x + size.x) as i32,
+                    (offset.y + size.y) as i32,
+                    gl::COLOR_BUFFER_BIT,
+                    gl::LINEAR,
+                );
+                gl.bind_framebuffer(gl::READ_FRAMEBUFFER, None);
+
+                egl_instance
+                    .swap_buffers(gles.egl_display, self.surface)
+                    .ok();
+            })
+        };
+
+        self.context.make_current();
+    }
+}
+
+impl Drop for FlatDisplaySurface {
+    fn drop(&mut self) {
+        let gles = self.context.gles().unwrap();
+
+        self.context.make_current();
+
+        unsafe {
+            gles.gl_context.delete_framebuffer(self.read_framebuffer);
+            for texture in self.view_textures {
+                gles.gl_context.delete_texture(texture);
+            }
+
+            gles.adapter.as_hal::<api::Gles, _, _>(|raw_adapter| {
+                let egl_instance = raw_adapter
+                    .unwrap()
+                    .adapter_context()
+                    .egl_instance()
+                    .unwrap();
+
+                egl_instance
+                    .destroy_surface(gles.egl_display, self.surface)
+                    .ok();
+            })
+        };
+    }
+}
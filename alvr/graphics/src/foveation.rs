@@ -0,0 +1,197 @@
+use alvr_common::glam::{UVec2, Vec2};
+
+// Radial foveated encoding. Along each axis, the source coordinate is a quadratic function of the
+// compressed one around the center: s = c + u * (alpha + (1 - alpha) * |u|) * side, where u is the
+// compressed distance from the center relative to the distance of the center from the border. The
+// compression grows smoothly from the center to the edges instead of in two steps. The server
+// compression shaders implement warp() and the client stream shader implements unwarp(), they
+// must be kept in sync.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RadialFoveation {
+    // Center of the foveation in the view, mirrored horizontally for the right view
+    pub center: Vec2,
+    // Slope of the mapping at the center. The slope at the edges is 2 - alpha
+    pub alpha: f32,
+    // Size of the compressed view relative to the source view
+    pub scale: f32,
+}
+
+impl RadialFoveation {
+    pub fn new(center_strength: f32, edge_compression: f32, center_shift: Vec2) -> Self {
+        let alpha = 2.0 / (edge_compression + 1.0);
+
+        Self {
+            center: 0.5 + center_shift * 0.25,
+            alpha,
+            scale: center_strength * alpha,
+        }
+    }
+
+    // Resolution of the compressed view, aligned for the encoder, and the part of it covered by
+    // the content. The rest is padding
+    pub fn optimized_resolution(&self, view_resolution: UVec2) -> (UVec2, Vec2) {
+        let optimized_resolution = self.scale * view_resolution.as_vec2();
+        let optimized_resolution_aligned = (optimized_resolution / 32.0).ceil() * 32.0;
+
+        (
+            optimized_resolution_aligned.as_uvec2(),
+            optimized_resolution / optimized_resolution_aligned,
+        )
+    }
+
+    // Maps normalized coordinates of the compressed view to the source view
+    pub fn warp(&self, compressed: Vec2) -> Vec2 {
+        let side = self.side(compressed);
+        let u = (compressed - self.center) / side;
+
+        self.center + u * (self.alpha + (1.0 - self.alpha) * u.abs()) * side
+    }
+
+    // Maps normalized coordinates of the source view to the compressed view
+    pub fn unwarp(&self, source: Vec2) -> Vec2 {
+        let side = self.side(source);
+        let t = (source - self.center) / side;
+        let root = (self.alpha * self.alpha + 4.0 * (1.0 - self.alpha) * t.abs()).map(f32::sqrt);
+
+        self.center + 2.0 * t / (self.alpha + root) * side
+    }
+
+    // Source pixels covered by a compressed pixel along each axis
+    pub fn source_pixel_ratio(&self, compressed: Vec2) -> Vec2 {
+        let u = (compressed - self.center) / self.side(compressed);
+
+        (self.alpha + 2.0 * (1.0 - self.alpha) * u.abs()) / self.scale
+    }
+
+    // The mapping keeps the center and the borders in place, so the side is the same in both
+    // spaces
+    fn side(&self, coords: Vec2) -> Vec2 {
+        Vec2::select(coords.cmplt(self.center), self.center, 1.0 - self.center)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE_SIZE: UVec2 = UVec2::new(256, 256);
+
+    struct Image {
+        size: UVec2,
+        pixels: Vec<f32>,
+    }
+
+    impl Image {
+        fn from_fn(size: UVec2, f: impl Fn(Vec2) -> f32) -> Self {
+            let pixels = (0..size.y)
+                .flat_map(|y| (0..size.x).map(move |x| UVec2::new(x, y)))
+                .map(|pos| f((pos.as_vec2() + 0.5) / size.as_vec2()))
+                .collect();
+
+            Self { size, pixels }
+        }
+
+        fn texel(&self, x: i32, y: i32) -> f32 {
+            let x = x.clamp(0, self.size.x as i32 - 1) as u32;
+            let y = y.clamp(0, self.size.y as i32 - 1) as u32;
+
+            self.pixels[(y * self.size.x + x) as usize]
+        }
+
+        // Bilinear filtering with clamp to edge, like the samplers of the shaders
+        fn sample(&self, uv: Vec2) -> f32 {
+            let pos = uv * self.size.as_vec2() - 0.5;
+            let base = pos.floor();
+            let frac = pos - base;
+            let (x, y) = (base.x as i32, base.y as i32);
+
+            let top = self.texel(x, y) * (1.0 - frac.x) + self.texel(x + 1, y) * frac.x;
+            let bottom = self.texel(x, y + 1) * (1.0 - frac.x) + self.texel(x + 1, y + 1) * frac.x;
+
+            top * (1.0 - frac.y) + bottom * frac.y
+        }
+    }
+
+    // Smooth pattern with a few periods along each axis
+    fn pattern(uv: Vec2) -> f32 {
+        0.5 + 0.25 * f32::sin(uv.x * 20.0) * f32::cos(uv.y * 14.0) + 0.25 * uv.x * uv.y
+    }
+
+    // Compresses the source image as the server shaders do, then restores it as the client shader
+    fn round_trip(foveation: &RadialFoveation, source: &Image) -> Image {
+        let (compressed_size, view_ratio) = foveation.optimized_resolution(source.size);
+        let compressed = Image::from_fn(compressed_size, |uv| {
+            source.sample(foveation.warp(uv / view_ratio))
+        });
+
+        Image::from_fn(source.size, |uv| {
+            compressed.sample(foveation.unwarp(uv) * view_ratio)
+        })
+    }
+
+    #[test]
+    fn test_mapping() {
+        let foveation = RadialFoveation::new(1.0, 3.0, Vec2::new(0.4, -0.2));
+
+        assert!(foveation.warp(Vec2::ZERO).abs_diff_eq(Vec2::ZERO, 1e-6));
+        assert!(foveation.warp(Vec2::ONE).abs_diff_eq(Vec2::ONE, 1e-6));
+        assert!(
+            foveation
+                .warp(foveation.center)
+                .abs_diff_eq(foveation.center, 1e-6)
+        );
+
+        for i in 0..=20 {
+            let coords = Vec2::new(i as f32 / 20.0, 1.0 - i as f32 / 20.0);
+            let mapped = foveation.unwarp(foveation.warp(coords));
+
+            assert!(mapped.abs_diff_eq(coords, 1e-5), "{coords} -> {mapped}");
+        }
+
+        // Full resolution at the center and the chosen compression at the borders
+        let ratio = foveation.source_pixel_ratio(foveation.center);
+        assert!(ratio.abs_diff_eq(Vec2::ONE, 1e-5));
+        let ratio = foveation.source_pixel_ratio(Vec2::ZERO);
+        assert!(ratio.abs_diff_eq(Vec2::splat(3.0), 1e-5));
+
+        let (resolution, view_ratio) = foveation.optimized_resolution(UVec2::new(2000, 1000));
+        assert_eq!(resolution, UVec2::new(1024, 512));
+        assert!(view_ratio.cmple(Vec2::ONE).all());
+    }
+
+    #[test]
+    fn test_image_round_trip() {
+        let source = Image::from_fn(SOURCE_SIZE, pattern);
+
+        for (center_strength, edge_compression) in [(1.0, 2.0), (1.0, 4.0), (0.8, 3.0)] {
+            let foveation = RadialFoveation::new(center_strength, edge_compression, Vec2::ZERO);
+            let restored = round_trip(&foveation, &source);
+
+            let mut center_error = 0_f32;
+            let mut total_error = 0.0;
+            for y in 0..SOURCE_SIZE.y {
+                for x in 0..SOURCE_SIZE.x {
+                    let idx = (y * SOURCE_SIZE.x + x) as usize;
+                    let error = (restored.pixels[idx] - source.pixels[idx]).abs();
+
+                    let uv = (UVec2::new(x, y).as_vec2() + 0.5) / SOURCE_SIZE.as_vec2();
+                    if (uv - 0.5).abs().cmplt(Vec2::splat(0.1)).all() {
+                        center_error = center_error.max(error);
+                    }
+                    total_error += error;
+                }
+            }
+            let mean_error = total_error / source.pixels.len() as f32;
+
+            // The center is kept almost intact, the edges lose only the finest details
+            assert!(
+                center_error < 0.005,
+                "{center_strength} {edge_compression}: center {center_error}"
+            );
+            assert!(
+                mean_error < 0.005,
+                "{center_strength} {edge_compression}: mean {mean_error}"
+            );
+        }
+    }
+}
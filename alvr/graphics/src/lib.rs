@@ -1,14 +1,29 @@
+#[cfg(target_os = "android")]
+mod flat;
+mod foveation;
 mod lobby;
+mod menu;
 mod staging;
+#[cfg(feature = "vulkan")]
+mod staging_vulkan;
 mod stream;
+#[cfg(feature = "vulkan")]
+mod vulkan;
 
+#[cfg(target_os = "android")]
+pub use flat::*;
+pub use foveation::*;
 pub use lobby::*;
+pub use menu::*;
 pub use stream::*;
+#[cfg(feature = "vulkan")]
+pub use vulkan::*;
 
 use alvr_common::{
     DeviceMotion, Fov, Pose,
     glam::{Mat4, UVec2, Vec4},
 };
+use alvr_session::ClientSwapchainFormat;
 use glow::{self as gl, HasContext};
 use khronos_egl as egl;
 use std::{ffi::c_void, ptr};
@@ -19,6 +34,8 @@ use wgpu::{
 
 pub const SDR_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
 pub const SDR_FORMAT_GL: u32 = gl::RGBA8;
+pub const DEPTH_FORMAT_GL: u32 = gl::DEPTH_COMPONENT16;
+pub const MOTION_VECTOR_FORMAT_GL: u32 = gl::RGBA16F;
 pub const GL_TEXTURE_EXTERNAL_OES: u32 = 0x8D65;
 pub const MAX_PUSH_CONSTANTS_SIZE: u32 = 128;
 
@@ -39,6 +56,12 @@ pub struct HandData {
     pub skeleton_joints: Option<[Pose; 26]>,
 }
 
+// Marker detected in the lobby. The selected one is used as origin once connected
+pub struct LobbyMarker {
+    pub pose: Pose,
+    pub selected: bool,
+}
+
 pub fn check_error(gl: &gl::Context, message_context: &str) {
     let err = unsafe { gl.get_error() };
     if err != glow::NO_ERROR {
@@ -59,7 +82,7 @@ macro_rules! ck {
 }
 pub(crate) use ck;
 
-fn projection_from_fov(fov: Fov) -> Mat4 {
+fn projection_from_fov(fov: Fov, flip_y: bool) -> Mat4 {
     const NEAR: f32 = 0.1;
 
     let tanl = f32::tan(fov.left);
@@ -67,24 +90,36 @@ fn projection_from_fov(fov: Fov) -> Mat4 {
     let tanu = f32::tan(fov.up);
     let tand = f32::tan(fov.down);
     let a = 2.0 / (tanr - tanl);
-    let b = 2.0 / (tanu - tand);
+    let mut b = 2.0 / (tanu - tand);
     let c = (tanr + tanl) / (tanr - tanl);
-    let d = (tanu + tand) / (tanu - tand);
+    let mut d = (tanu + tand) / (tanu - tand);
+
+    // note: OpenXR GL swapchain images are stored bottom-up, while wgpu renders top-down like
+    // Vulkan. For GL swapchains the b and d components should be flipped.
+    if flip_y {
+        b = -b;
+        d = -d;
+    }
 
-    // note: for wgpu compatibility, the b and d components should be flipped. Maybe a bug in the
-    // viewport handling in wgpu?
     Mat4::from_cols(
         Vec4::new(a, 0.0, c, 0.0),
-        Vec4::new(0.0, -b, -d, 0.0),
+        Vec4::new(0.0, b, d, 0.0),
         Vec4::new(0.0, 0.0, -1.0, -NEAR),
         Vec4::new(0.0, 0.0, -1.0, 0.0),
     )
     .transpose()
 }
 
-pub fn choose_swapchain_format(supported_formats: &[u32], enable_hdr: bool) -> u32 {
+pub fn choose_swapchain_format(
+    supported_formats: &[u32],
+    enable_hdr: bool,
+    preferred_format: ClientSwapchainFormat,
+) -> u32 {
     // Priority-sorted list of swapchain formats we'll accept--
     let mut app_supported_swapchain_formats = vec![gl::SRGB8_ALPHA8, gl::RGBA8];
+    if preferred_format == ClientSwapchainFormat::Linear {
+        app_supported_swapchain_formats.reverse();
+    }
 
     // float16 is required for HDR output. However, float16 swapchains
     // have a high perf cost, so only use these if HDR is enabled.
@@ -123,11 +158,35 @@ pub fn create_texture(device: &Device, resolution: UVec2, format: TextureFormat)
         sample_count: 1,
         dimension: TextureDimension::D2,
         format,
-        usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+        // Render attachment is needed by the Vulkan staging pass
+        usage: TextureUsages::COPY_DST
+            | TextureUsages::TEXTURE_BINDING
+            | TextureUsages::RENDER_ATTACHMENT,
         view_formats: &[],
     })
 }
 
+// Raw handle of an OpenXR swapchain image: a GL texture name or a VkImage
+pub trait SwapchainImage: Copy {
+    fn create_texture(
+        device: &Device,
+        image: Self,
+        resolution: UVec2,
+        format: TextureFormat,
+    ) -> Texture;
+}
+
+impl SwapchainImage for u32 {
+    fn create_texture(
+        device: &Device,
+        image: Self,
+        resolution: UVec2,
+        format: TextureFormat,
+    ) -> Texture {
+        create_texture_from_gles(device, image, resolution, format)
+    }
+}
+
 #[cfg(not(any(target_os = "macos", target_os = "ios")))]
 fn create_texture_from_gles(
     device: &Device,
@@ -187,30 +246,52 @@ fn create_texture_from_gles(_: &Device, _: u32, _: UVec2, _: TextureFormat) -> T
     unimplemented!()
 }
 
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+fn texture_to_gles(texture: &Texture) -> gl::Texture {
+    use wgpu::hal::{api, gles::TextureInner};
+
+    unsafe {
+        texture.as_hal::<api::Gles, _, _>(|tex| {
+            let TextureInner::Texture { raw, .. } = tex.unwrap().inner else {
+                panic!("invalid texture type");
+            };
+            raw
+        })
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+fn texture_to_gles(_: &Texture) -> gl::Texture {
+    unimplemented!()
+}
+
 // This is used to convert OpenXR swapchains to wgpu
-pub fn create_gl_swapchain(
+pub fn create_swapchain<I: SwapchainImage>(
     device: &Device,
-    gl_textures: &[u32],
+    images: &[I],
     resolution: UVec2,
     format: TextureFormat,
 ) -> Vec<TextureView> {
-    gl_textures
+    images
         .iter()
-        .map(|gl_tex| {
-            create_texture_from_gles(device, *gl_tex, resolution, format)
-                .create_view(&Default::default())
+        .map(|image| {
+            I::create_texture(device, *image, resolution, format).create_view(&Default::default())
         })
         .collect()
 }
 
-pub struct GraphicsContext {
-    _instance: Instance,
+// Created once, the size difference doesn't matter
+#[cfg_attr(feature = "vulkan", allow(clippy::large_enum_variant))]
+pub enum GraphicsBackend {
+    Gles(GlesContext),
+    #[cfg(feature = "vulkan")]
+    Vulkan(VulkanContext),
+}
 
+pub struct GlesContext {
     #[cfg(not(any(windows, target_os = "macos", target_os = "ios")))]
     adapter: wgpu::Adapter,
 
-    device: Device,
-    queue: Queue,
     pub egl_display: egl::Display,
     pub egl_config: egl::Config,
     pub egl_context: egl::Context,
@@ -225,6 +306,13 @@ pub struct GraphicsContext {
     image_target_texture_2d: ImageTargetTexture2DFn,
 }
 
+pub struct GraphicsContext {
+    _instance: Instance,
+    device: Device,
+    queue: Queue,
+    pub backend: GraphicsBackend,
+}
+
 impl GraphicsContext {
     #[cfg(not(any(windows, target_os = "macos", target_os = "ios")))]
     pub fn new_gl() -> Self {
@@ -332,18 +420,20 @@ impl GraphicsContext {
 
         Self {
             _instance: instance,
-            adapter,
             device,
             queue,
-            egl_display,
-            egl_config,
-            egl_context,
-            gl_context,
-            dummy_surface,
-            create_image,
-            destroy_image,
-            get_native_client_buffer,
-            image_target_texture_2d,
+            backend: GraphicsBackend::Gles(GlesContext {
+                adapter,
+                egl_display,
+                egl_config,
+                egl_context,
+                gl_context,
+                dummy_surface,
+                create_image,
+                destroy_image,
+                get_native_client_buffer,
+                image_target_texture_2d,
+            }),
         }
     }
 
@@ -352,27 +442,126 @@ impl GraphicsContext {
         unimplemented!()
     }
 
+    pub fn gles(&self) -> Option<&GlesContext> {
+        match &self.backend {
+            GraphicsBackend::Gles(gles) => Some(gles),
+            #[cfg(feature = "vulkan")]
+            GraphicsBackend::Vulkan(_) => None,
+        }
+    }
+
+    #[cfg(feature = "vulkan")]
+    pub fn vulkan(&self) -> Option<&VulkanContext> {
+        match &self.backend {
+            GraphicsBackend::Vulkan(vulkan) => Some(vulkan),
+            GraphicsBackend::Gles(_) => None,
+        }
+    }
+
+    // This is a no-op for Vulkan
     pub fn make_current(&self) {
         #[cfg(not(any(windows, target_os = "macos", target_os = "ios")))]
+        if let Some(gles) = self.gles() {
+            unsafe {
+                gles.adapter
+                    .as_hal::<wgpu::hal::api::Gles, _, _>(|raw_adapter| {
+                        let egl_instance = raw_adapter
+                            .unwrap()
+                            .adapter_context()
+                            .egl_instance()
+                            .unwrap();
+
+                        egl_instance
+                            .make_current(
+                                gles.egl_display,
+                                Some(gles.dummy_surface),
+                                Some(gles.dummy_surface),
+                                Some(gles.egl_context),
+                            )
+                            .unwrap();
+                    })
+            };
+        }
+    }
+
+    // Uploads 16 bit depth values to a DEPTH_FORMAT_GL texture, row by row
+    pub fn upload_depth_gles(&self, texture: u32, resolution: UVec2, data: &[u16]) {
+        let gl_ctx = &self.gles().expect("not a GLES context").gl_context;
+
+        let bytes = data
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect::<Vec<_>>();
+
         unsafe {
-            self.adapter
-                .as_hal::<wgpu::hal::api::Gles, _, _>(|raw_adapter| {
-                    let egl_instance = raw_adapter
-                        .unwrap()
-                        .adapter_context()
-                        .egl_instance()
-                        .unwrap();
+            gl_ctx.bind_texture(
+                gl::TEXTURE_2D,
+                Some(gl::NativeTexture(
+                    std::num::NonZeroU32::new(texture).unwrap(),
+                )),
+            );
+            gl_ctx.pixel_store_i32(gl::UNPACK_ALIGNMENT, 2);
+            gl_ctx.tex_sub_image_2d(
+                gl::TEXTURE_2D,
+                0,
+                0,
+                0,
+                resolution.x as i32,
+                resolution.y as i32,
+                gl::DEPTH_COMPONENT,
+                gl::UNSIGNED_SHORT,
+                gl::PixelUnpackData::Slice(Some(&bytes)),
+            );
+            gl_ctx.pixel_store_i32(gl::UNPACK_ALIGNMENT, 4);
+            gl_ctx.bind_texture(gl::TEXTURE_2D, None);
+        }
+        check_error(gl_ctx, "upload depth");
+    }
 
-                    egl_instance
-                        .make_current(
-                            self.egl_display,
-                            Some(self.dummy_surface),
-                            Some(self.dummy_surface),
-                            Some(self.egl_context),
-                        )
-                        .unwrap();
-                })
-        };
+    // Uploads RGBA motion vectors to a MOTION_VECTOR_FORMAT_GL texture, row by row. GLES converts
+    // the floats to half floats
+    pub fn upload_motion_vectors_gles(&self, texture: u32, resolution: UVec2, data: &[f32]) {
+        let gl_ctx = &self.gles().expect("not a GLES context").gl_context;
+
+        let bytes = data
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect::<Vec<_>>();
+
+        unsafe {
+            gl_ctx.bind_texture(
+                gl::TEXTURE_2D,
+                Some(gl::NativeTexture(
+                    std::num::NonZeroU32::new(texture).unwrap(),
+                )),
+            );
+            gl_ctx.tex_sub_image_2d(
+                gl::TEXTURE_2D,
+                0,
+                0,
+                0,
+                resolution.x as i32,
+                resolution.y as i32,
+                gl::RGBA,
+                gl::FLOAT,
+                gl::PixelUnpackData::Slice(Some(&bytes)),
+            );
+            gl_ctx.bind_texture(gl::TEXTURE_2D, None);
+        }
+        check_error(gl_ctx, "upload motion vectors");
+    }
+
+    // Converts a swapchain format of the current backend (GL or Vulkan enum)
+    pub fn swapchain_format_to_wgpu(&self, format: u32) -> TextureFormat {
+        match &self.backend {
+            GraphicsBackend::Gles(_) => gl_format_to_wgpu(format),
+            #[cfg(feature = "vulkan")]
+            GraphicsBackend::Vulkan(_) => vk_format_to_wgpu(format),
+        }
+    }
+
+    fn projection_from_fov(&self, fov: Fov) -> Mat4 {
+        projection_from_fov(fov, matches!(self.backend, GraphicsBackend::Gles(_)))
     }
 
     /// # Safety
@@ -386,34 +575,36 @@ impl GraphicsContext {
     ) {
         const EGL_NATIVE_BUFFER_ANDROID: u32 = 0x3140;
 
+        let gles = self.gles().expect("not a GLES context");
+
         if !buffer.is_null() {
-            let client_buffer = unsafe { (self.get_native_client_buffer)(buffer) };
-            check_error(&self.gl_context, "get_native_client_buffer");
+            let client_buffer = unsafe { (gles.get_native_client_buffer)(buffer) };
+            check_error(&gles.gl_context, "get_native_client_buffer");
 
             let image = unsafe {
-                (self.create_image)(
-                    self.egl_display.as_ptr(),
+                (gles.create_image)(
+                    gles.egl_display.as_ptr(),
                     egl::NO_CONTEXT,
                     EGL_NATIVE_BUFFER_ANDROID,
                     client_buffer,
                     ptr::null(),
                 )
             };
-            check_error(&self.gl_context, "create_image");
+            check_error(&gles.gl_context, "create_image");
 
             unsafe {
-                self.gl_context
+                gles.gl_context
                     .bind_texture(GL_TEXTURE_EXTERNAL_OES, Some(texture))
             };
-            check_error(&self.gl_context, "bind texture OES");
+            check_error(&gles.gl_context, "bind texture OES");
 
-            unsafe { (self.image_target_texture_2d)(GL_TEXTURE_EXTERNAL_OES, image) };
-            check_error(&self.gl_context, "image_target_texture_2d");
+            unsafe { (gles.image_target_texture_2d)(GL_TEXTURE_EXTERNAL_OES, image) };
+            check_error(&gles.gl_context, "image_target_texture_2d");
 
             render_cb();
 
-            unsafe { (self.destroy_image)(self.egl_display.as_ptr(), image) };
-            check_error(&self.gl_context, "destroy_image");
+            unsafe { (gles.destroy_image)(gles.egl_display.as_ptr(), image) };
+            check_error(&gles.gl_context, "destroy_image");
         }
     }
 }
@@ -1,5 +1,5 @@
-use super::{GraphicsContext, MAX_PUSH_CONSTANTS_SIZE, SDR_FORMAT};
-use crate::HandData;
+use super::{GraphicsContext, MAX_PUSH_CONSTANTS_SIZE, SDR_FORMAT, SwapchainImage};
+use crate::{HandData, LobbyMarker};
 use alvr_common::{
     BodySkeleton, DeviceMotion, ViewParams,
     glam::{IVec2, Mat4, Quat, UVec2, Vec3},
@@ -14,11 +14,11 @@ use wgpu::{
     BindGroupLayoutEntry, BindingResource, BindingType, BlendComponent, BlendFactor,
     BlendOperation, BlendState, Color, ColorTargetState, ColorWrites, CommandEncoderDescriptor,
     Device, Extent3d, FilterMode, FragmentState, LoadOp, Operations, Origin3d,
-    PipelineLayoutDescriptor, PrimitiveState, PrimitiveTopology, PushConstantRange, RenderPass,
-    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
-    SamplerBindingType, SamplerDescriptor, ShaderModuleDescriptor, ShaderStages, StoreOp,
-    TexelCopyBufferLayout, TexelCopyTextureInfo, Texture, TextureAspect, TextureSampleType,
-    TextureView, TextureViewDimension, VertexState, include_wgsl,
+    PipelineLayoutDescriptor, PrimitiveState, PrimitiveTopology, PushConstantRange, Queue,
+    RenderPass, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, SamplerBindingType, SamplerDescriptor, ShaderModuleDescriptor,
+    ShaderStages, StoreOp, TexelCopyBufferLayout, TexelCopyTextureInfo, Texture, TextureAspect,
+    TextureSampleType, TextureView, TextureViewDimension, VertexState, include_wgsl,
 };
 
 const TRANSFORM_CONST_SIZE: u32 = mem::size_of::<Mat4>() as u32;
@@ -26,7 +26,7 @@ const OBJECT_TYPE_CONST_SIZE: u32 = mem::size_of::<u32>() as u32;
 const FLOOR_SIDE_CONST_SIZE: u32 = mem::size_of::<f32>() as u32;
 const COLOR_CONST_SIZE: u32 = mem::size_of::<u32>() as u32;
 
-const QUAD_PUSH_CONTANTS_SIZE: u32 =
+pub(crate) const QUAD_PUSH_CONTANTS_SIZE: u32 =
     TRANSFORM_CONST_SIZE + OBJECT_TYPE_CONST_SIZE + FLOOR_SIDE_CONST_SIZE;
 const LINE_PUSH_CONTANTS_SIZE: u32 = TRANSFORM_CONST_SIZE + COLOR_CONST_SIZE;
 const _: () = assert!(
@@ -35,8 +35,8 @@ const _: () = assert!(
     "Push constants size exceeds the maximum size"
 );
 
-const TRANSFORM_CONST_OFFSET: u32 = 0;
-const OBJECT_TYPE_CONST_OFFSET: u32 = TRANSFORM_CONST_SIZE;
+pub(crate) const TRANSFORM_CONST_OFFSET: u32 = 0;
+pub(crate) const OBJECT_TYPE_CONST_OFFSET: u32 = TRANSFORM_CONST_SIZE;
 const FLOOR_SIDE_CONST_OFFSET: u32 = OBJECT_TYPE_CONST_OFFSET + OBJECT_TYPE_CONST_SIZE;
 const COLOR_CONST_OFFSET: u32 = TRANSFORM_CONST_SIZE;
 
@@ -45,6 +45,7 @@ const HUD_DIST: f32 = 5.0;
 const HUD_SIDE: f32 = 3.5;
 const HUD_TEXTURE_SIDE: usize = 1024;
 const FONT_SIZE: f32 = 50.0;
+const MARKER_OUTLINE_SIDE: f32 = 0.15;
 
 const FAST_BORDER_OFFSETS: [IVec2; 8] = [
     IVec2::new(0, -3),
@@ -154,7 +155,7 @@ const BODY_JOINT_RELATIONS_BD: [(usize, usize); 23] = [
     (21, 23),
 ];
 
-fn create_pipeline(
+pub(crate) fn create_pipeline(
     device: &Device,
     label: &str,
     bind_group_layouts: &[&BindGroupLayout],
@@ -212,6 +213,137 @@ fn create_pipeline(
     })
 }
 
+// Bind group used by lobby_quad.wgsl to sample a texture
+pub(crate) fn create_texture_bind_group(
+    device: &Device,
+    texture: &Texture,
+) -> (BindGroupLayout, BindGroup) {
+    let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: None,
+        entries: &[
+            BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: true },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 1,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    });
+
+    let bind_group = device.create_bind_group(&BindGroupDescriptor {
+        label: None,
+        layout: &bind_group_layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(&texture.create_view(&Default::default())),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::Sampler(&device.create_sampler(&SamplerDescriptor {
+                    mag_filter: FilterMode::Linear,
+                    min_filter: FilterMode::Linear,
+                    ..Default::default()
+                })),
+            },
+        ],
+    });
+
+    (bind_group_layout, bind_group)
+}
+
+// Renders centered white text with a black border into a square RGBA texture
+pub(crate) fn write_text_texture(
+    queue: &Queue,
+    texture: &Texture,
+    texture_side: usize,
+    message: &str,
+) {
+    let ubuntu_font =
+        FontRef::try_from_slice(include_bytes!("../resources/Ubuntu-Medium.ttf")).unwrap();
+
+    let section_glyphs = Layout::default()
+        .h_align(HorizontalAlign::Center)
+        .v_align(VerticalAlign::Center)
+        .calculate_glyphs(
+            &[&ubuntu_font],
+            &SectionGeometry {
+                screen_position: (texture_side as f32 / 2_f32, texture_side as f32 / 2_f32),
+                ..Default::default()
+            },
+            &[SectionText {
+                text: message,
+                scale: FONT_SIZE.into(),
+                font_id: FontId(0),
+            }],
+        );
+
+    let scaled_font = ubuntu_font.as_scaled(FONT_SIZE);
+
+    let mut buffer = vec![0; texture_side * texture_side * 4];
+
+    for section_glyph in section_glyphs {
+        if let Some(outlined) = scaled_font.outline_glyph(section_glyph.glyph) {
+            let bounds = outlined.px_bounds();
+
+            outlined.draw(|x, y, alpha| {
+                let x = x as i32 + bounds.min.x as i32;
+                let y = y as i32 + bounds.min.y as i32;
+
+                if x >= MAX_BORDER_OFFSET
+                    && y >= MAX_BORDER_OFFSET
+                    && x < texture_side as i32 - MAX_BORDER_OFFSET
+                    && y < texture_side as i32 - MAX_BORDER_OFFSET
+                {
+                    let coord = (y as usize * texture_side + x as usize) * 4;
+                    let value = (alpha * 255.0) as u8;
+
+                    buffer[coord] = value;
+                    buffer[coord + 1] = value;
+                    buffer[coord + 2] = value;
+
+                    // Render opacity with border
+                    for offset in &FAST_BORDER_OFFSETS {
+                        let coord =
+                            ((y + offset.y) as usize * texture_side + (x + offset.x) as usize) * 4;
+                        buffer[coord + 3] = u8::max(buffer[coord + 3], value);
+                    }
+                }
+            });
+        }
+    }
+
+    queue.write_texture(
+        TexelCopyTextureInfo {
+            texture,
+            mip_level: 0,
+            origin: Origin3d::ZERO,
+            aspect: TextureAspect::All,
+        },
+        &buffer,
+        TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(texture_side as u32 * 4),
+            rows_per_image: Some(texture_side as u32),
+        },
+        Extent3d {
+            width: texture_side as u32,
+            height: texture_side as u32,
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
 pub struct LobbyViewParams {
     pub swapchain_index: u32,
     pub view_params: ViewParams,
@@ -227,10 +359,10 @@ pub struct LobbyRenderer {
 }
 
 impl LobbyRenderer {
-    pub fn new(
+    pub fn new<I: SwapchainImage>(
         context: Rc<GraphicsContext>,
         view_resolution: UVec2,
-        swapchain_textures: [Vec<u32>; 2],
+        swapchain_textures: [Vec<I>; 2],
         initial_hud_message: &str,
     ) -> Self {
         let device = &context.device;
@@ -238,27 +370,7 @@ impl LobbyRenderer {
         let hud_texture =
             super::create_texture(device, UVec2::ONE * HUD_TEXTURE_SIDE as u32, SDR_FORMAT);
 
-        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            label: None,
-            entries: &[
-                BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: ShaderStages::FRAGMENT,
-                    ty: BindingType::Texture {
-                        sample_type: TextureSampleType::Float { filterable: true },
-                        view_dimension: TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    count: None,
-                },
-                BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: ShaderStages::FRAGMENT,
-                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
-                    count: None,
-                },
-            ],
-        });
+        let (bind_group_layout, bind_group) = create_texture_bind_group(device, &hud_texture);
 
         let quad_pipeline = create_pipeline(
             device,
@@ -278,32 +390,9 @@ impl LobbyRenderer {
             PrimitiveTopology::LineList,
         );
 
-        let bind_group = device.create_bind_group(&BindGroupDescriptor {
-            label: None,
-            layout: &bind_group_layout,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::TextureView(
-                        &hud_texture.create_view(&Default::default()),
-                    ),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::Sampler(&device.create_sampler(
-                        &SamplerDescriptor {
-                            mag_filter: FilterMode::Linear,
-                            min_filter: FilterMode::Linear,
-                            ..Default::default()
-                        },
-                    )),
-                },
-            ],
-        });
-
         let render_targets = [
-            super::create_gl_swapchain(device, &swapchain_textures[0], view_resolution, SDR_FORMAT),
-            super::create_gl_swapchain(device, &swapchain_textures[1], view_resolution, SDR_FORMAT),
+            super::create_swapchain(device, &swapchain_textures[0], view_resolution, SDR_FORMAT),
+            super::create_swapchain(device, &swapchain_textures[1], view_resolution, SDR_FORMAT),
         ];
 
         let this = Self {
@@ -321,91 +410,22 @@ impl LobbyRenderer {
     }
 
     pub fn update_hud_message(&self, message: &str) {
-        let ubuntu_font =
-            FontRef::try_from_slice(include_bytes!("../resources/Ubuntu-Medium.ttf")).unwrap();
-
-        let section_glyphs = Layout::default()
-            .h_align(HorizontalAlign::Center)
-            .v_align(VerticalAlign::Center)
-            .calculate_glyphs(
-                &[&ubuntu_font],
-                &SectionGeometry {
-                    screen_position: (
-                        HUD_TEXTURE_SIDE as f32 / 2_f32,
-                        HUD_TEXTURE_SIDE as f32 / 2_f32,
-                    ),
-                    ..Default::default()
-                },
-                &[SectionText {
-                    text: message,
-                    scale: FONT_SIZE.into(),
-                    font_id: FontId(0),
-                }],
-            );
-
-        let scaled_font = ubuntu_font.as_scaled(FONT_SIZE);
-
-        let mut buffer = vec![0; HUD_TEXTURE_SIDE * HUD_TEXTURE_SIDE * 4];
-
-        for section_glyph in section_glyphs {
-            if let Some(outlined) = scaled_font.outline_glyph(section_glyph.glyph) {
-                let bounds = outlined.px_bounds();
-
-                outlined.draw(|x, y, alpha| {
-                    let x = x as i32 + bounds.min.x as i32;
-                    let y = y as i32 + bounds.min.y as i32;
-
-                    if x >= MAX_BORDER_OFFSET
-                        && y >= MAX_BORDER_OFFSET
-                        && x < HUD_TEXTURE_SIDE as i32 - MAX_BORDER_OFFSET
-                        && y < HUD_TEXTURE_SIDE as i32 - MAX_BORDER_OFFSET
-                    {
-                        let coord = (y as usize * HUD_TEXTURE_SIDE + x as usize) * 4;
-                        let value = (alpha * 255.0) as u8;
-
-                        buffer[coord] = value;
-                        buffer[coord + 1] = value;
-                        buffer[coord + 2] = value;
-
-                        // Render opacity with border
-                        for offset in &FAST_BORDER_OFFSETS {
-                            let coord = ((y + offset.y) as usize * HUD_TEXTURE_SIDE
-                                + (x + offset.x) as usize)
-                                * 4;
-                            buffer[coord + 3] = u8::max(buffer[coord + 3], value);
-                        }
-                    }
-                });
-            }
-        }
-
-        self.context.queue.write_texture(
-            TexelCopyTextureInfo {
-                texture: &self.hud_texture,
-                mip_level: 0,
-                origin: Origin3d::ZERO,
-                aspect: TextureAspect::All,
-            },
-            &buffer,
-            TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(HUD_TEXTURE_SIDE as u32 * 4),
-                rows_per_image: Some(HUD_TEXTURE_SIDE as u32),
-            },
-            Extent3d {
-                width: HUD_TEXTURE_SIDE as u32,
-                height: HUD_TEXTURE_SIDE as u32,
-                depth_or_array_layers: 1,
-            },
+        write_text_texture(
+            &self.context.queue,
+            &self.hud_texture,
+            HUD_TEXTURE_SIDE,
+            message,
         );
     }
 
+    #[expect(clippy::too_many_arguments)]
     pub fn render(
         &self,
         view_params: [LobbyViewParams; 2],
         hand_data: [HandData; 2],
         body_skeleton: Option<BodySkeleton>,
         additional_motions: Option<Vec<DeviceMotion>>,
+        markers: &[LobbyMarker],
         render_background: bool,
         show_velocities: bool,
     ) {
@@ -422,7 +442,7 @@ impl LobbyRenderer {
                 view_input.view_params.pose.position,
             )
             .inverse();
-            let view_proj = super::projection_from_fov(view_input.view_params.fov) * view;
+            let view_proj = self.context.projection_from_fov(view_input.view_params.fov) * view;
 
             let clear_color = if render_background {
                 Color {
@@ -645,6 +665,41 @@ impl LobbyRenderer {
                     }
                 }
             }
+
+            // Square outline on the marker plane, with a segment along the normal
+            for marker in markers {
+                let color = if marker.selected {
+                    [0, 255, 0, 255]
+                } else {
+                    [255, 200, 0, 255]
+                };
+                pass.set_push_constants(ShaderStages::VERTEX_FRAGMENT, COLOR_CONST_OFFSET, &color);
+
+                let marker_transform =
+                    Mat4::from_rotation_translation(marker.pose.orientation, marker.pose.position);
+                let half_side = MARKER_OUTLINE_SIDE / 2.0;
+                let corners = [
+                    Vec3::new(-half_side, -half_side, 0.0),
+                    Vec3::new(half_side, -half_side, 0.0),
+                    Vec3::new(half_side, half_side, 0.0),
+                    Vec3::new(-half_side, half_side, 0.0),
+                ];
+                for (start, end) in corners.iter().zip(corners.iter().cycle().skip(1)) {
+                    let transform = Mat4::from_scale_rotation_translation(
+                        Vec3::ONE * MARKER_OUTLINE_SIDE,
+                        Quat::from_rotation_arc(-Vec3::Z, (*end - *start).normalize()),
+                        *start,
+                    );
+                    transform_draw(&mut pass, view_proj * marker_transform * transform, 2);
+                }
+
+                let transform = Mat4::from_scale_rotation_translation(
+                    Vec3::ONE * half_side,
+                    Quat::from_rotation_arc(-Vec3::Z, Vec3::Z),
+                    Vec3::ZERO,
+                );
+                transform_draw(&mut pass, view_proj * marker_transform * transform, 2);
+            }
         }
 
         self.context.queue.submit(Some(encoder.finish()));
@@ -0,0 +1,126 @@
+use super::{GraphicsBackend, GraphicsContext, SDR_FORMAT, SwapchainImage};
+use crate::lobby::{
+    OBJECT_TYPE_CONST_OFFSET, QUAD_PUSH_CONTANTS_SIZE, TRANSFORM_CONST_OFFSET, create_pipeline,
+    create_texture_bind_group, write_text_texture,
+};
+use alvr_common::glam::{Mat4, UVec2, Vec3};
+use std::rc::Rc;
+use wgpu::{
+    BindGroup, Color, CommandEncoderDescriptor, LoadOp, Operations, PrimitiveTopology,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, ShaderStages, StoreOp,
+    Texture, TextureView, include_wgsl,
+};
+
+const MENU_TEXTURE_SIDE: usize = 512;
+
+// Renders the in-headset menu text into a square swapchain, to be submitted as a quad layer
+pub struct MenuRenderer {
+    context: Rc<GraphicsContext>,
+    pipeline: RenderPipeline,
+    text_texture: Texture,
+    bind_group: BindGroup,
+    render_targets: Vec<TextureView>,
+}
+
+impl MenuRenderer {
+    pub fn new<I: SwapchainImage>(
+        context: Rc<GraphicsContext>,
+        resolution: UVec2,
+        swapchain_textures: Vec<I>,
+    ) -> Self {
+        let device = &context.device;
+
+        let text_texture =
+            super::create_texture(device, UVec2::ONE * MENU_TEXTURE_SIDE as u32, SDR_FORMAT);
+
+        let (bind_group_layout, bind_group) = create_texture_bind_group(device, &text_texture);
+
+        let pipeline = create_pipeline(
+            device,
+            "menu_quad",
+            &[&bind_group_layout],
+            QUAD_PUSH_CONTANTS_SIZE,
+            include_wgsl!("../resources/lobby_quad.wgsl"),
+            PrimitiveTopology::TriangleStrip,
+        );
+
+        let render_targets =
+            super::create_swapchain(device, &swapchain_textures, resolution, SDR_FORMAT);
+
+        Self {
+            context,
+            pipeline,
+            text_texture,
+            bind_group,
+            render_targets,
+        }
+    }
+
+    pub fn update_text(&self, text: &str) {
+        write_text_texture(
+            &self.context.queue,
+            &self.text_texture,
+            MENU_TEXTURE_SIDE,
+            text,
+        );
+    }
+
+    pub fn render(&self, swapchain_index: u32) {
+        let mut encoder = self
+            .context
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("menu_command_encoder"),
+            });
+
+        {
+            // Translucent background, with premultiplied alpha
+            let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("menu"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &self.render_targets[swapchain_index as usize],
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color {
+                            r: 0.0,
+                            g: 0.0,
+                            b: 0.015,
+                            a: 0.75,
+                        }),
+                        store: StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+
+            // The quad vertices span -0.5..0.5. GL swapchain images are stored bottom-up
+            let y_scale = if matches!(self.context.backend, GraphicsBackend::Gles(_)) {
+                -2.0
+            } else {
+                2.0
+            };
+            let transform = Mat4::from_scale(Vec3::new(2.0, y_scale, 1.0));
+            let transform_data = transform
+                .to_cols_array()
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect::<Vec<u8>>();
+
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            pass.set_push_constants(
+                ShaderStages::VERTEX_FRAGMENT,
+                OBJECT_TYPE_CONST_OFFSET,
+                &1_u32.to_le_bytes(),
+            );
+            pass.set_push_constants(
+                ShaderStages::VERTEX_FRAGMENT,
+                TRANSFORM_CONST_OFFSET,
+                &transform_data,
+            );
+            pass.draw(0..4, 0..1);
+        }
+
+        self.context.queue.submit(Some(encoder.finish()));
+    }
+}
@@ -64,7 +64,7 @@ impl StagingRenderer {
         view_resolution: UVec2,
         fix_limited_range: bool,
     ) -> Self {
-        let gl = &context.gl_context;
+        let gl = &context.gles().unwrap().gl_context;
         context.make_current();
 
         // Add #defines into the shader after the first line
@@ -118,7 +118,7 @@ impl StagingRenderer {
 
     #[allow(unused_variables)]
     pub fn render(&self, hardware_buffer: *mut c_void) {
-        let gl = &self.context.gl_context;
+        let gl = &self.context.gles().unwrap().gl_context;
         self.context.make_current();
 
         unsafe {
@@ -149,7 +149,7 @@ impl StagingRenderer {
 
 impl Drop for StagingRenderer {
     fn drop(&mut self) {
-        let gl = &self.context.gl_context;
+        let gl = &self.context.gles().unwrap().gl_context;
         self.context.make_current();
 
         unsafe {
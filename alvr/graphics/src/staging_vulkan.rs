@@ -0,0 +1,836 @@
+use super::{GraphicsContext, vulkan::wgpu_format_to_vk};
+use alvr_common::{
+    anyhow::{Context, Result, bail},
+    error,
+    glam::UVec2,
+};
+use ash::{util, vk};
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    ffi::c_void,
+    io::Cursor,
+    rc::Rc,
+    time::Duration,
+};
+use wgpu::TextureFormat;
+
+const VERTEX_SHADER_SPV: &[u8] = include_bytes!("../resources/staging_vulkan_vertex.spv");
+const FRAGMENT_SHADER_SPV: &[u8] = include_bytes!("../resources/staging_vulkan_fragment.spv");
+
+// Multi-planar formats can use up to one descriptor per plane
+const MAX_PLANE_COUNT: u32 = 3;
+
+// The decoder cycles through a small set of buffers. A few more are kept when it reallocates them
+const MAX_IMPORTED_BUFFERS: usize = 16;
+
+// A frame copy takes well under a millisecond, a longer wait means the GPU is stuck
+const FENCE_TIMEOUT: Duration = Duration::from_millis(100);
+
+const COLOR_SUBRESOURCE_RANGE: vk::ImageSubresourceRange = vk::ImageSubresourceRange {
+    aspect_mask: vk::ImageAspectFlags::COLOR,
+    base_mip_level: 0,
+    level_count: 1,
+    base_array_layer: 0,
+    layer_count: 1,
+};
+
+#[cfg(target_os = "android")]
+fn hardware_buffer_size(buffer: *mut c_void) -> UVec2 {
+    #[repr(C)]
+    #[derive(Default)]
+    struct AHardwareBufferDesc {
+        width: u32,
+        height: u32,
+        layers: u32,
+        format: u32,
+        usage: u64,
+        stride: u32,
+        rfu0: u32,
+        rfu1: u64,
+    }
+
+    #[link(name = "android")]
+    unsafe extern "C" {
+        fn AHardwareBuffer_describe(buffer: *const c_void, desc: *mut AHardwareBufferDesc);
+    }
+
+    let mut desc = AHardwareBufferDesc::default();
+    unsafe { AHardwareBuffer_describe(buffer, &mut desc) };
+
+    UVec2::new(desc.width, desc.height)
+}
+
+#[cfg(not(target_os = "android"))]
+fn hardware_buffer_size(_: *mut c_void) -> UVec2 {
+    unimplemented!()
+}
+
+// Objects independent of the decoder output. Created on the first frame, so a failure only
+// disables the copy of the stream. Null handles are skipped by the destroy functions, which allows
+// cleaning up after a partial creation.
+#[derive(Default)]
+struct RenderObjects {
+    render_pass: vk::RenderPass,
+    vertex_shader: vk::ShaderModule,
+    fragment_shader: vk::ShaderModule,
+    image_views: [vk::ImageView; 2],
+    framebuffers: [vk::Framebuffer; 2],
+    command_pool: vk::CommandPool,
+    command_buffer: vk::CommandBuffer,
+    fence: vk::Fence,
+}
+
+impl RenderObjects {
+    unsafe fn new(
+        device: &ash::Device,
+        queue_family_index: u32,
+        staging_textures: [vk::Image; 2],
+        format: vk::Format,
+        resolution: UVec2,
+    ) -> Result<Self> {
+        let mut objects = Self::default();
+        if let Err(e) = unsafe {
+            objects.create(
+                device,
+                queue_family_index,
+                staging_textures,
+                format,
+                resolution,
+            )
+        } {
+            unsafe { objects.destroy(device) };
+
+            return Err(e);
+        }
+
+        Ok(objects)
+    }
+
+    unsafe fn create(
+        &mut self,
+        device: &ash::Device,
+        queue_family_index: u32,
+        staging_textures: [vk::Image; 2],
+        format: vk::Format,
+        resolution: UVec2,
+    ) -> Result<()> {
+        unsafe {
+            let attachments = [vk::AttachmentDescription::default()
+                .format(format)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .load_op(vk::AttachmentLoadOp::DONT_CARE)
+                .store_op(vk::AttachmentStoreOp::STORE)
+                .initial_layout(vk::ImageLayout::UNDEFINED)
+                .final_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)];
+            let color_attachments = [vk::AttachmentReference::default()
+                .attachment(0)
+                .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)];
+            let subpasses = [vk::SubpassDescription::default()
+                .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+                .color_attachments(&color_attachments)];
+            // Make the staging textures visible to the stream pass submitted afterwards by wgpu
+            let dependencies = [
+                vk::SubpassDependency::default()
+                    .src_subpass(vk::SUBPASS_EXTERNAL)
+                    .dst_subpass(0)
+                    .src_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+                    .dst_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+                    .src_access_mask(vk::AccessFlags::SHADER_READ)
+                    .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE),
+                vk::SubpassDependency::default()
+                    .src_subpass(0)
+                    .dst_subpass(vk::SUBPASS_EXTERNAL)
+                    .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+                    .dst_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+                    .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                    .dst_access_mask(vk::AccessFlags::SHADER_READ),
+            ];
+            self.render_pass = device.create_render_pass(
+                &vk::RenderPassCreateInfo::default()
+                    .attachments(&attachments)
+                    .subpasses(&subpasses)
+                    .dependencies(&dependencies),
+                None,
+            )?;
+
+            let create_shader = |spv: &[u8]| -> Result<_> {
+                let code = util::read_spv(&mut Cursor::new(spv))?;
+                Ok(device.create_shader_module(
+                    &vk::ShaderModuleCreateInfo::default().code(&code),
+                    None,
+                )?)
+            };
+            self.vertex_shader = create_shader(VERTEX_SHADER_SPV)?;
+            self.fragment_shader = create_shader(FRAGMENT_SHADER_SPV)?;
+
+            for (idx, image) in staging_textures.into_iter().enumerate() {
+                self.image_views[idx] = device.create_image_view(
+                    &vk::ImageViewCreateInfo::default()
+                        .image(image)
+                        .view_type(vk::ImageViewType::TYPE_2D)
+                        .format(format)
+                        .subresource_range(COLOR_SUBRESOURCE_RANGE),
+                    None,
+                )?;
+                self.framebuffers[idx] = device.create_framebuffer(
+                    &vk::FramebufferCreateInfo::default()
+                        .render_pass(self.render_pass)
+                        .attachments(&[self.image_views[idx]])
+                        .width(resolution.x)
+                        .height(resolution.y)
+                        .layers(1),
+                    None,
+                )?;
+            }
+
+            self.command_pool = device.create_command_pool(
+                &vk::CommandPoolCreateInfo::default()
+                    .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+                    .queue_family_index(queue_family_index),
+                None,
+            )?;
+            self.command_buffer = device.allocate_command_buffers(
+                &vk::CommandBufferAllocateInfo::default()
+                    .command_pool(self.command_pool)
+                    .level(vk::CommandBufferLevel::PRIMARY)
+                    .command_buffer_count(1),
+            )?[0];
+            self.fence = device.create_fence(&vk::FenceCreateInfo::default(), None)?;
+        }
+
+        Ok(())
+    }
+
+    unsafe fn destroy(&self, device: &ash::Device) {
+        unsafe {
+            device.destroy_fence(self.fence, None);
+            // Also frees the command buffer
+            device.destroy_command_pool(self.command_pool, None);
+            for framebuffer in self.framebuffers {
+                device.destroy_framebuffer(framebuffer, None);
+            }
+            for view in self.image_views {
+                device.destroy_image_view(view, None);
+            }
+            device.destroy_shader_module(self.vertex_shader, None);
+            device.destroy_shader_module(self.fragment_shader, None);
+            device.destroy_render_pass(self.render_pass, None);
+        }
+    }
+}
+
+// The sampler conversion depends on the decoder output format, which is known only after the
+// first frame. Immutable samplers are part of the pipeline layout, so the pipeline is recreated too.
+#[derive(Default)]
+struct ExternalFormatObjects {
+    external_format: u64,
+    conversion: vk::SamplerYcbcrConversion,
+    sampler: vk::Sampler,
+    set_layout: vk::DescriptorSetLayout,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set: vk::DescriptorSet,
+}
+
+impl ExternalFormatObjects {
+    unsafe fn new(
+        device: &ash::Device,
+        render_objects: &RenderObjects,
+        format_props: &vk::AndroidHardwareBufferFormatPropertiesANDROID,
+        resolution: UVec2,
+    ) -> Result<Self> {
+        let mut objects = Self {
+            external_format: format_props.external_format,
+            ..Default::default()
+        };
+        if let Err(e) = unsafe { objects.create(device, render_objects, format_props, resolution) }
+        {
+            unsafe { objects.destroy(device) };
+
+            return Err(e);
+        }
+
+        Ok(objects)
+    }
+
+    unsafe fn create(
+        &mut self,
+        device: &ash::Device,
+        render_objects: &RenderObjects,
+        format_props: &vk::AndroidHardwareBufferFormatPropertiesANDROID,
+        resolution: UVec2,
+    ) -> Result<()> {
+        // Without separate reconstruction filter support, min and mag filters must match the
+        // chroma filter
+        let filter = if format_props
+            .format_features
+            .contains(vk::FormatFeatureFlags::SAMPLED_IMAGE_YCBCR_CONVERSION_LINEAR_FILTER)
+        {
+            vk::Filter::LINEAR
+        } else {
+            vk::Filter::NEAREST
+        };
+
+        unsafe {
+            let mut external_format =
+                vk::ExternalFormatANDROID::default().external_format(format_props.external_format);
+            self.conversion = device.create_sampler_ycbcr_conversion(
+                &vk::SamplerYcbcrConversionCreateInfo::default()
+                    .push_next(&mut external_format)
+                    .format(vk::Format::UNDEFINED)
+                    .ycbcr_model(format_props.suggested_ycbcr_model)
+                    .ycbcr_range(format_props.suggested_ycbcr_range)
+                    .components(format_props.sampler_ycbcr_conversion_components)
+                    .x_chroma_offset(format_props.suggested_x_chroma_offset)
+                    .y_chroma_offset(format_props.suggested_y_chroma_offset)
+                    .chroma_filter(filter),
+                None,
+            )?;
+
+            let mut conversion_info =
+                vk::SamplerYcbcrConversionInfo::default().conversion(self.conversion);
+            self.sampler = device.create_sampler(
+                &vk::SamplerCreateInfo::default()
+                    .push_next(&mut conversion_info)
+                    .mag_filter(filter)
+                    .min_filter(filter)
+                    .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE),
+                None,
+            )?;
+
+            let immutable_samplers = [self.sampler];
+            let bindings = [vk::DescriptorSetLayoutBinding::default()
+                .binding(0)
+                .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+                .immutable_samplers(&immutable_samplers)];
+            self.set_layout = device.create_descriptor_set_layout(
+                &vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings),
+                None,
+            )?;
+
+            // view_idx, fix_limited_range
+            let push_constant_ranges = [vk::PushConstantRange::default()
+                .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT)
+                .offset(0)
+                .size(2 * size_of::<i32>() as u32)];
+            self.pipeline_layout = device.create_pipeline_layout(
+                &vk::PipelineLayoutCreateInfo::default()
+                    .set_layouts(&[self.set_layout])
+                    .push_constant_ranges(&push_constant_ranges),
+                None,
+            )?;
+
+            let stages = [
+                vk::PipelineShaderStageCreateInfo::default()
+                    .stage(vk::ShaderStageFlags::VERTEX)
+                    .module(render_objects.vertex_shader)
+                    .name(c"main"),
+                vk::PipelineShaderStageCreateInfo::default()
+                    .stage(vk::ShaderStageFlags::FRAGMENT)
+                    .module(render_objects.fragment_shader)
+                    .name(c"main"),
+            ];
+            let extent = vk::Extent2D {
+                width: resolution.x,
+                height: resolution.y,
+            };
+            let viewports = [vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: extent.width as f32,
+                height: extent.height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }];
+            let scissors = [vk::Rect2D {
+                offset: vk::Offset2D::default(),
+                extent,
+            }];
+            let blend_attachments = [vk::PipelineColorBlendAttachmentState::default()
+                .color_write_mask(vk::ColorComponentFlags::RGBA)];
+            self.pipeline = device
+                .create_graphics_pipelines(
+                    vk::PipelineCache::null(),
+                    &[vk::GraphicsPipelineCreateInfo::default()
+                        .stages(&stages)
+                        .vertex_input_state(&vk::PipelineVertexInputStateCreateInfo::default())
+                        .input_assembly_state(
+                            &vk::PipelineInputAssemblyStateCreateInfo::default()
+                                .topology(vk::PrimitiveTopology::TRIANGLE_STRIP),
+                        )
+                        .viewport_state(
+                            &vk::PipelineViewportStateCreateInfo::default()
+                                .viewports(&viewports)
+                                .scissors(&scissors),
+                        )
+                        .rasterization_state(
+                            &vk::PipelineRasterizationStateCreateInfo::default()
+                                .polygon_mode(vk::PolygonMode::FILL)
+                                .cull_mode(vk::CullModeFlags::NONE)
+                                .line_width(1.0),
+                        )
+                        .multisample_state(
+                            &vk::PipelineMultisampleStateCreateInfo::default()
+                                .rasterization_samples(vk::SampleCountFlags::TYPE_1),
+                        )
+                        .color_blend_state(
+                            &vk::PipelineColorBlendStateCreateInfo::default()
+                                .attachments(&blend_attachments),
+                        )
+                        .layout(self.pipeline_layout)
+                        .render_pass(render_objects.render_pass)
+                        .subpass(0)],
+                    None,
+                )
+                .map_err(|(_, e)| e)?[0];
+
+            let pool_sizes = [vk::DescriptorPoolSize::default()
+                .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(MAX_PLANE_COUNT)];
+            self.descriptor_pool = device.create_descriptor_pool(
+                &vk::DescriptorPoolCreateInfo::default()
+                    .max_sets(1)
+                    .pool_sizes(&pool_sizes),
+                None,
+            )?;
+            self.descriptor_set = device.allocate_descriptor_sets(
+                &vk::DescriptorSetAllocateInfo::default()
+                    .descriptor_pool(self.descriptor_pool)
+                    .set_layouts(&[self.set_layout]),
+            )?[0];
+        }
+
+        Ok(())
+    }
+
+    unsafe fn destroy(&self, device: &ash::Device) {
+        unsafe {
+            device.destroy_descriptor_pool(self.descriptor_pool, None);
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+            device.destroy_descriptor_set_layout(self.set_layout, None);
+            device.destroy_sampler(self.sampler, None);
+            device.destroy_sampler_ycbcr_conversion(self.conversion, None);
+        }
+    }
+}
+
+// The imported memory holds a reference to the hardware buffer, so the buffer cannot be freed and
+// its address reused by another buffer while it is in the cache
+struct ImportedBuffer {
+    buffer: *mut vk::AHardwareBuffer,
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    image_view: vk::ImageView,
+}
+
+impl ImportedBuffer {
+    unsafe fn new(
+        device: &ash::Device,
+        buffer: *mut vk::AHardwareBuffer,
+        format_objects: &ExternalFormatObjects,
+        allocation_size: u64,
+        memory_type_bits: u32,
+        size: UVec2,
+    ) -> Result<Self> {
+        if memory_type_bits == 0 {
+            bail!("No memory type for the decoder buffer");
+        }
+
+        let mut imported = Self {
+            buffer,
+            image: vk::Image::null(),
+            memory: vk::DeviceMemory::null(),
+            image_view: vk::ImageView::null(),
+        };
+        if let Err(e) = unsafe {
+            imported.create(
+                device,
+                format_objects,
+                allocation_size,
+                memory_type_bits.trailing_zeros(),
+                size,
+            )
+        } {
+            unsafe { imported.destroy(device) };
+
+            return Err(e).context("Failed to import the decoder buffer");
+        }
+
+        Ok(imported)
+    }
+
+    unsafe fn create(
+        &mut self,
+        device: &ash::Device,
+        format_objects: &ExternalFormatObjects,
+        allocation_size: u64,
+        memory_type_index: u32,
+        size: UVec2,
+    ) -> Result<()> {
+        unsafe {
+            let mut external_memory_info = vk::ExternalMemoryImageCreateInfo::default()
+                .handle_types(vk::ExternalMemoryHandleTypeFlags::ANDROID_HARDWARE_BUFFER_ANDROID);
+            let mut external_format = vk::ExternalFormatANDROID::default()
+                .external_format(format_objects.external_format);
+            self.image = device.create_image(
+                &vk::ImageCreateInfo::default()
+                    .push_next(&mut external_memory_info)
+                    .push_next(&mut external_format)
+                    .image_type(vk::ImageType::TYPE_2D)
+                    .format(vk::Format::UNDEFINED)
+                    .extent(vk::Extent3D {
+                        width: size.x,
+                        height: size.y,
+                        depth: 1,
+                    })
+                    .mip_levels(1)
+                    .array_layers(1)
+                    .samples(vk::SampleCountFlags::TYPE_1)
+                    .tiling(vk::ImageTiling::OPTIMAL)
+                    .usage(vk::ImageUsageFlags::SAMPLED)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                    .initial_layout(vk::ImageLayout::UNDEFINED),
+                None,
+            )?;
+
+            let mut import_info =
+                vk::ImportAndroidHardwareBufferInfoANDROID::default().buffer(self.buffer);
+            let mut dedicated_info = vk::MemoryDedicatedAllocateInfo::default().image(self.image);
+            self.memory = device.allocate_memory(
+                &vk::MemoryAllocateInfo::default()
+                    .push_next(&mut import_info)
+                    .push_next(&mut dedicated_info)
+                    .allocation_size(allocation_size)
+                    .memory_type_index(memory_type_index),
+                None,
+            )?;
+            device.bind_image_memory(self.image, self.memory, 0)?;
+
+            let mut conversion_info =
+                vk::SamplerYcbcrConversionInfo::default().conversion(format_objects.conversion);
+            self.image_view = device.create_image_view(
+                &vk::ImageViewCreateInfo::default()
+                    .push_next(&mut conversion_info)
+                    .image(self.image)
+                    .view_type(vk::ImageViewType::TYPE_2D)
+                    .format(vk::Format::UNDEFINED)
+                    .subresource_range(COLOR_SUBRESOURCE_RANGE),
+                None,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    unsafe fn destroy(&self, device: &ash::Device) {
+        unsafe {
+            device.destroy_image_view(self.image_view, None);
+            device.destroy_image(self.image, None);
+            device.free_memory(self.memory, None);
+        }
+    }
+}
+
+// Vulkan counterpart of the GL staging renderer. The decoder AHardwareBuffer is imported as a
+// VkImage and converted to RGB by a sampler YCbCr conversion.
+pub struct VulkanStagingRenderer {
+    context: Rc<GraphicsContext>,
+    staging_textures: [vk::Image; 2],
+    format: vk::Format,
+    render_objects: RefCell<Option<RenderObjects>>,
+    format_objects: RefCell<Option<ExternalFormatObjects>>,
+    // Least recently used first
+    imported_buffers: RefCell<VecDeque<ImportedBuffer>>,
+    // Set if the last submission did not complete within the timeout
+    submission_pending: Cell<bool>,
+    error_reported: Cell<bool>,
+    resolution: UVec2,
+    fix_limited_range: bool,
+}
+
+impl VulkanStagingRenderer {
+    pub fn new(
+        context: Rc<GraphicsContext>,
+        staging_textures: [vk::Image; 2],
+        format: TextureFormat,
+        view_resolution: UVec2,
+        fix_limited_range: bool,
+    ) -> Self {
+        Self {
+            context,
+            staging_textures,
+            format: wgpu_format_to_vk(format),
+            render_objects: RefCell::new(None),
+            format_objects: RefCell::new(None),
+            imported_buffers: RefCell::new(VecDeque::new()),
+            submission_pending: Cell::new(false),
+            error_reported: Cell::new(false),
+            resolution: view_resolution,
+            fix_limited_range,
+        }
+    }
+
+    // On failure the staging textures keep the last frame. Only the first error is logged, the
+    // same error would be repeated for every frame
+    pub fn render(&self, hardware_buffer: *mut c_void) {
+        if let Err(e) = self.try_render(hardware_buffer) {
+            if !self.error_reported.replace(true) {
+                error!("Failed to copy the decoder frame: {e:?}");
+            }
+        } else {
+            self.error_reported.set(false);
+        }
+    }
+
+    fn wait_submission(&self, device: &ash::Device, fence: vk::Fence) -> Result<()> {
+        unsafe {
+            device
+                .wait_for_fences(&[fence], true, FENCE_TIMEOUT.as_nanos() as u64)
+                .context("Timed out waiting for the frame copy")?;
+            device.reset_fences(&[fence])?;
+        }
+        self.submission_pending.set(false);
+
+        Ok(())
+    }
+
+    fn try_render(&self, hardware_buffer: *mut c_void) -> Result<()> {
+        let vk_ctx = self.context.vulkan().context("Not a Vulkan context")?;
+        let device = &vk_ctx.device;
+        let buffer = hardware_buffer.cast::<vk::AHardwareBuffer>();
+
+        let mut render_objects = self.render_objects.borrow_mut();
+        let render_objects = match &mut *render_objects {
+            Some(objects) => objects,
+            slot => slot.insert(unsafe {
+                RenderObjects::new(
+                    device,
+                    vk_ctx.queue_family_index,
+                    self.staging_textures,
+                    self.format,
+                    self.resolution,
+                )?
+            }),
+        };
+
+        // The command buffer and the cached images cannot be touched while still in use
+        if self.submission_pending.get() {
+            self.wait_submission(device, render_objects.fence)?;
+        }
+
+        let mut format_props = vk::AndroidHardwareBufferFormatPropertiesANDROID::default();
+        let mut buffer_props =
+            vk::AndroidHardwareBufferPropertiesANDROID::default().push_next(&mut format_props);
+        unsafe {
+            vk_ctx
+                .external_memory_fns
+                .get_android_hardware_buffer_properties(buffer, &mut buffer_props)
+        }?;
+        let allocation_size = buffer_props.allocation_size;
+        let memory_type_bits = buffer_props.memory_type_bits;
+
+        let mut imported_buffers = self.imported_buffers.borrow_mut();
+        let mut format_objects = self.format_objects.borrow_mut();
+        let objects = match &mut *format_objects {
+            Some(objects) if objects.external_format == format_props.external_format => objects,
+            slot => {
+                // The image views of the cached imports refer to the old conversion
+                for imported in imported_buffers.drain(..) {
+                    unsafe { imported.destroy(device) };
+                }
+                if let Some(objects) = slot.take() {
+                    unsafe { objects.destroy(device) };
+                }
+
+                slot.insert(unsafe {
+                    ExternalFormatObjects::new(
+                        device,
+                        render_objects,
+                        &format_props,
+                        self.resolution,
+                    )?
+                })
+            }
+        };
+
+        // Importing the buffer every frame is expensive, reuse the previous import if any
+        let imported = match imported_buffers
+            .iter()
+            .position(|imported| imported.buffer == buffer)
+        {
+            Some(idx) => imported_buffers
+                .remove(idx)
+                .context("Missing imported buffer")?,
+            None => {
+                if imported_buffers.len() >= MAX_IMPORTED_BUFFERS
+                    && let Some(oldest) = imported_buffers.pop_front()
+                {
+                    unsafe { oldest.destroy(device) };
+                }
+
+                unsafe {
+                    ImportedBuffer::new(
+                        device,
+                        buffer,
+                        objects,
+                        allocation_size,
+                        memory_type_bits,
+                        hardware_buffer_size(hardware_buffer),
+                    )?
+                }
+            }
+        };
+        let image = imported.image;
+        let image_view = imported.image_view;
+        imported_buffers.push_back(imported);
+
+        unsafe {
+            let image_infos = [vk::DescriptorImageInfo::default()
+                .image_view(image_view)
+                .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)];
+            device.update_descriptor_sets(
+                &[vk::WriteDescriptorSet::default()
+                    .dst_set(objects.descriptor_set)
+                    .dst_binding(0)
+                    .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .image_info(&image_infos)],
+                &[],
+            );
+
+            let cmd = render_objects.command_buffer;
+            device.begin_command_buffer(
+                cmd,
+                &vk::CommandBufferBeginInfo::default()
+                    .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+            )?;
+
+            // Acquire the image from the decoder. The content written by the decoder is preserved
+            // by the foreign queue family transfer even with the undefined layout
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[vk::ImageMemoryBarrier::default()
+                    .src_access_mask(vk::AccessFlags::empty())
+                    .dst_access_mask(vk::AccessFlags::SHADER_READ)
+                    .old_layout(vk::ImageLayout::UNDEFINED)
+                    .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                    .src_queue_family_index(vk::QUEUE_FAMILY_FOREIGN_EXT)
+                    .dst_queue_family_index(vk_ctx.queue_family_index)
+                    .image(image)
+                    .subresource_range(COLOR_SUBRESOURCE_RANGE)],
+            );
+
+            for (view_idx, framebuffer) in render_objects.framebuffers.iter().enumerate() {
+                device.cmd_begin_render_pass(
+                    cmd,
+                    &vk::RenderPassBeginInfo::default()
+                        .render_pass(render_objects.render_pass)
+                        .framebuffer(*framebuffer)
+                        .render_area(vk::Rect2D {
+                            offset: vk::Offset2D::default(),
+                            extent: vk::Extent2D {
+                                width: self.resolution.x,
+                                height: self.resolution.y,
+                            },
+                        }),
+                    vk::SubpassContents::INLINE,
+                );
+                device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::GRAPHICS, objects.pipeline);
+                device.cmd_bind_descriptor_sets(
+                    cmd,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    objects.pipeline_layout,
+                    0,
+                    &[objects.descriptor_set],
+                    &[],
+                );
+                let push_constants = [view_idx as i32, self.fix_limited_range as i32]
+                    .iter()
+                    .flat_map(|v| v.to_le_bytes())
+                    .collect::<Vec<u8>>();
+                device.cmd_push_constants(
+                    cmd,
+                    objects.pipeline_layout,
+                    vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                    0,
+                    &push_constants,
+                );
+                device.cmd_draw(cmd, 4, 1, 0, 0);
+                device.cmd_end_render_pass(cmd);
+            }
+
+            // Release the image back to the decoder
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[vk::ImageMemoryBarrier::default()
+                    .src_access_mask(vk::AccessFlags::SHADER_READ)
+                    .dst_access_mask(vk::AccessFlags::empty())
+                    .old_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                    .new_layout(vk::ImageLayout::GENERAL)
+                    .src_queue_family_index(vk_ctx.queue_family_index)
+                    .dst_queue_family_index(vk::QUEUE_FAMILY_FOREIGN_EXT)
+                    .image(image)
+                    .subresource_range(COLOR_SUBRESOURCE_RANGE)],
+            );
+
+            device.end_command_buffer(cmd)?;
+
+            device.queue_submit(
+                vk_ctx.queue,
+                &[vk::SubmitInfo::default().command_buffers(&[cmd])],
+                render_objects.fence,
+            )?;
+        }
+        self.submission_pending.set(true);
+
+        // The decoder can overwrite the buffer as soon as the frame is released
+        self.wait_submission(device, render_objects.fence)
+    }
+}
+
+impl Drop for VulkanStagingRenderer {
+    fn drop(&mut self) {
+        let Some(vk_ctx) = self.context.vulkan() else {
+            return;
+        };
+        let device = &vk_ctx.device;
+
+        let Some(render_objects) = self.render_objects.get_mut().take() else {
+            return;
+        };
+
+        if self.submission_pending.get()
+            && self.wait_submission(device, render_objects.fence).is_err()
+        {
+            // Destroying objects in use is undefined behavior, leaking them is the lesser evil
+            error!("Frame copy still pending, leaking the Vulkan staging objects");
+            return;
+        }
+
+        unsafe {
+            for imported in self.imported_buffers.get_mut().drain(..) {
+                imported.destroy(device);
+            }
+            if let Some(objects) = self.format_objects.get_mut().take() {
+                objects.destroy(device);
+            }
+            render_objects.destroy(device);
+        }
+    }
+}
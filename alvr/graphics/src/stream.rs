@@ -1,9 +1,17 @@
-use super::{GraphicsContext, MAX_PUSH_CONSTANTS_SIZE, staging::StagingRenderer};
+#[cfg(feature = "vulkan")]
+use super::staging_vulkan::VulkanStagingRenderer;
+use super::{
+    GraphicsBackend, GraphicsContext, MAX_PUSH_CONSTANTS_SIZE, RadialFoveation, SwapchainImage,
+    staging::StagingRenderer,
+};
 use alvr_common::{
     ViewParams,
-    glam::{self, Mat4, UVec2, Vec3, Vec4},
+    glam::{self, Mat4, UVec2, Vec2, Vec3, Vec4},
+};
+use alvr_session::{
+    FoveatedEncodingConfig, FoveatedEncodingEyeConfig, FoveatedEncodingMode, PassthroughMode,
+    TransferFunction, UpscalingConfig,
 };
-use alvr_session::{FoveatedEncodingConfig, PassthroughMode, UpscalingConfig};
 use std::{ffi::c_void, iter, mem, rc::Rc};
 use wgpu::{
     BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
@@ -42,35 +50,48 @@ pub struct StreamViewParams {
 
 #[derive(Debug)]
 struct ViewObjects {
+    // The foveated encoding parameters can differ between the views
+    pipeline: RenderPipeline,
     bind_group: BindGroup,
     render_target: Vec<TextureView>,
 }
 
+// Copies the decoded frame into the staging textures
+#[cfg_attr(feature = "vulkan", allow(clippy::large_enum_variant))]
+enum StagingBackend {
+    Gles(StagingRenderer),
+    #[cfg(feature = "vulkan")]
+    Vulkan(VulkanStagingRenderer),
+}
+
 pub struct StreamRenderer {
     context: Rc<GraphicsContext>,
-    staging_renderer: StagingRenderer,
-    pipeline: RenderPipeline,
+    staging_renderer: StagingBackend,
     views_objects: [ViewObjects; 2],
+    // Width over height of the stream views, set if the quad keeps it instead of filling the FOV
+    letterbox_aspect_ratio: Option<f32>,
 }
 
 impl StreamRenderer {
     #[expect(clippy::too_many_arguments)]
     #[cfg_attr(any(target_os = "macos", target_os = "ios"), expect(unused))]
-    pub fn new(
+    pub fn new<I: SwapchainImage>(
         context: Rc<GraphicsContext>,
         base_view_resolution: UVec2,
         target_view_resolution: UVec2,
-        swapchain_textures: [Vec<u32>; 2],
+        swapchain_textures: [Vec<I>; 2],
         target_format: u32,
         foveated_encoding: Option<FoveatedEncodingConfig>,
         enable_srgb_correction: bool,
+        transfer_function: TransferFunction,
         fix_limited_range: bool,
         encoding_gamma: f32,
         upscaling: Option<UpscalingConfig>,
+        letterbox: bool,
     ) -> Self {
         let device = &context.device;
 
-        let target_format = super::gl_format_to_wgpu(target_format);
+        let target_format = context.swapchain_format_to_wgpu(target_format);
 
         let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
             label: None,
@@ -100,18 +121,16 @@ impl StreamRenderer {
 
         constants.extend([
             ("ENABLE_SRGB_CORRECTION", enable_srgb_correction.into()),
+            ("TRANSFER_FUNCTION", (transfer_function as u32).into()),
             ("ENCODING_GAMMA", encoding_gamma.into()),
         ]);
 
-        let staging_resolution = if let Some(foveated_encoding) = foveated_encoding {
-            let (staging_resolution, ffe_constants) =
-                foveated_encoding_shader_constants(base_view_resolution, foveated_encoding);
-            constants.extend(ffe_constants);
-
-            staging_resolution
-        } else {
-            base_view_resolution
-        };
+        let (staging_resolution, views_ffe_constants) =
+            if let Some(foveated_encoding) = foveated_encoding {
+                foveated_encoding_shader_constants(base_view_resolution, foveated_encoding)
+            } else {
+                (base_view_resolution, [vec![], vec![]])
+            };
 
         if let Some(upscaling) = upscaling {
             constants.extend([
@@ -128,47 +147,53 @@ impl StreamRenderer {
             ]);
         };
 
-        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+        // Note: Layout cannot be inferred because of a bug with push constants
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
             label: None,
-            // Note: Layout cannot be inferred because of a bug with push constants
-            layout: Some(&device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[PushConstantRange {
+                stages: ShaderStages::VERTEX_FRAGMENT,
+                range: 0..PUSH_CONSTANTS_SIZE,
+            }],
+        });
+
+        let pipelines = views_ffe_constants.map(|ffe_constants| {
+            let constants = [constants.as_slice(), &ffe_constants].concat();
+
+            device.create_render_pipeline(&RenderPipelineDescriptor {
                 label: None,
-                bind_group_layouts: &[&bind_group_layout],
-                push_constant_ranges: &[PushConstantRange {
-                    stages: ShaderStages::VERTEX_FRAGMENT,
-                    range: 0..PUSH_CONSTANTS_SIZE,
-                }],
-            })),
-            vertex: VertexState {
-                module: &shader_module,
-                entry_point: None,
-                compilation_options: PipelineCompilationOptions {
-                    constants: &constants,
-                    zero_initialize_workgroup_memory: false,
+                layout: Some(&pipeline_layout),
+                vertex: VertexState {
+                    module: &shader_module,
+                    entry_point: None,
+                    compilation_options: PipelineCompilationOptions {
+                        constants: &constants,
+                        zero_initialize_workgroup_memory: false,
+                    },
+                    buffers: &[],
                 },
-                buffers: &[],
-            },
-            primitive: PrimitiveState {
-                topology: PrimitiveTopology::TriangleStrip,
-                ..Default::default()
-            },
-            depth_stencil: None,
-            multisample: Default::default(),
-            fragment: Some(FragmentState {
-                module: &shader_module,
-                entry_point: None,
-                compilation_options: PipelineCompilationOptions {
-                    constants: &constants,
-                    zero_initialize_workgroup_memory: false,
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleStrip,
+                    ..Default::default()
                 },
-                targets: &[Some(ColorTargetState {
-                    format: target_format,
-                    blend: None,
-                    write_mask: ColorWrites::ALL,
-                })],
-            }),
-            multiview: None,
-            cache: None,
+                depth_stencil: None,
+                multisample: Default::default(),
+                fragment: Some(FragmentState {
+                    module: &shader_module,
+                    entry_point: None,
+                    compilation_options: PipelineCompilationOptions {
+                        constants: &constants,
+                        zero_initialize_workgroup_memory: false,
+                    },
+                    targets: &[Some(ColorTargetState {
+                        format: target_format,
+                        blend: None,
+                        write_mask: ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+                cache: None,
+            })
         });
 
         let sampler = device.create_sampler(&SamplerDescriptor {
@@ -178,8 +203,8 @@ impl StreamRenderer {
         });
 
         let mut view_objects = vec![];
-        let mut staging_textures_gl = vec![];
-        for target_swapchain in &swapchain_textures {
+        let mut staging_textures = vec![];
+        for (pipeline, target_swapchain) in pipelines.into_iter().zip(&swapchain_textures) {
             let staging_texture = super::create_texture(device, staging_resolution, target_format);
 
             let bind_group = device.create_bind_group(&BindGroupDescriptor {
@@ -199,7 +224,7 @@ impl StreamRenderer {
                 ],
             });
 
-            let render_target = super::create_gl_swapchain(
+            let render_target = super::create_swapchain(
                 device,
                 target_swapchain,
                 target_view_resolution,
@@ -207,40 +232,45 @@ impl StreamRenderer {
             );
 
             view_objects.push(ViewObjects {
+                pipeline,
                 bind_group,
                 render_target,
             });
 
-            #[cfg(not(any(target_os = "macos", target_os = "ios")))]
-            {
-                let staging_texture_gl = unsafe {
-                    staging_texture.as_hal::<wgpu::hal::api::Gles, _, _>(|tex| {
-                        let wgpu::hal::gles::TextureInner::Texture { raw, .. } = tex.unwrap().inner
-                        else {
-                            panic!("invalid texture type");
-                        };
-                        raw
-                    })
-                };
-                staging_textures_gl.push(staging_texture_gl);
-            }
+            staging_textures.push(staging_texture);
         }
-
-        let staging_renderer = StagingRenderer::new(
-            Rc::clone(&context),
-            staging_textures_gl.try_into().unwrap(),
-            staging_resolution,
-            fix_limited_range,
-        );
+        let staging_textures: [_; 2] = staging_textures.try_into().unwrap();
+
+        let staging_renderer = match &context.backend {
+            GraphicsBackend::Gles(_) => StagingBackend::Gles(StagingRenderer::new(
+                Rc::clone(&context),
+                staging_textures.each_ref().map(super::texture_to_gles),
+                staging_resolution,
+                fix_limited_range,
+            )),
+            #[cfg(feature = "vulkan")]
+            GraphicsBackend::Vulkan(_) => StagingBackend::Vulkan(VulkanStagingRenderer::new(
+                Rc::clone(&context),
+                staging_textures
+                    .each_ref()
+                    .map(super::vulkan::texture_to_vulkan),
+                target_format,
+                staging_resolution,
+                fix_limited_range,
+            )),
+        };
 
         Self {
             context,
             staging_renderer,
-            pipeline,
             views_objects: view_objects.try_into().unwrap(),
+            letterbox_aspect_ratio: letterbox
+                .then(|| base_view_resolution.x as f32 / base_view_resolution.y as f32),
         }
     }
 
+    /// If `flash` is true, the views are cleared to white instead of showing the stream.
+    ///
     /// # Safety
     /// `hardware_buffer` must be a valid pointer to a ANativeWindowBuffer.
     pub fn render(
@@ -248,10 +278,15 @@ impl StreamRenderer {
         hardware_buffer: *mut c_void,
         view_params: [StreamViewParams; 2],
         passthrough: Option<&PassthroughMode>,
+        flash: bool,
     ) {
         // if hardware_buffer is available copy stream to staging texture
         if !hardware_buffer.is_null() {
-            self.staging_renderer.render(hardware_buffer);
+            match &self.staging_renderer {
+                StagingBackend::Gles(renderer) => renderer.render(hardware_buffer),
+                #[cfg(feature = "vulkan")]
+                StagingBackend::Vulkan(renderer) => renderer.render(hardware_buffer),
+            }
         }
 
         let mut encoder = self
@@ -267,13 +302,17 @@ impl StreamRenderer {
                         [view_params.swapchain_index as usize],
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: LoadOp::Clear(Color::BLACK),
+                        load: LoadOp::Clear(if flash { Color::WHITE } else { Color::BLACK }),
                         store: StoreOp::Store,
                     },
                 })],
                 ..Default::default()
             });
 
+            if flash {
+                continue;
+            }
+
             let input_fov = view_params.input_view_params.fov;
 
             let tanl = f32::tan(input_fov.left);
@@ -281,8 +320,19 @@ impl StreamRenderer {
             let tanu = f32::tan(input_fov.up);
             let tand = f32::tan(input_fov.down);
 
-            let width = tanr - tanl;
-            let height = tanu - tand;
+            let mut width = tanr - tanl;
+            let mut height = tanu - tand;
+            let center_x = tanl + width / 2.0;
+            let center_y = tand + height / 2.0;
+
+            // Shrink the quad in one direction, the black clear color fills the bars
+            if let Some(aspect_ratio) = self.letterbox_aspect_ratio {
+                if aspect_ratio > width / height {
+                    height = width / aspect_ratio;
+                } else {
+                    width = height * aspect_ratio;
+                }
+            }
             let quad_depth = 1000.0;
 
             let output_mat4 = Mat4::from_translation(view_params.output_view_params.pose.position)
@@ -297,14 +347,12 @@ impl StreamRenderer {
                     y: 0.0,
                     z: -quad_depth * 0.5,
                 }) * Mat4::from_scale(Vec3::new(quad_depth, quad_depth, quad_depth * 0.5))
-                    * Mat4::from_translation(Vec3::new(
-                        width / 2.0 + tanl,
-                        height / 2.0 + tand,
-                        -1.0,
-                    ))
+                    * Mat4::from_translation(Vec3::new(center_x, center_y, -1.0))
                     * Mat4::from_scale(Vec3::new(width, height, 1.));
             let view_mat = output_mat4.inverse() * input_mat4;
-            let proj_mat = super::projection_from_fov(view_params.output_view_params.fov);
+            let proj_mat = self
+                .context
+                .projection_from_fov(view_params.output_view_params.fov);
 
             let transform = proj_mat * view_mat * model_mat;
 
@@ -314,7 +362,7 @@ impl StreamRenderer {
                 .flat_map(|v| v.to_le_bytes())
                 .collect::<Vec<u8>>();
 
-            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_pipeline(&self.views_objects[view_idx].pipeline);
             render_pass.set_push_constants(
                 ShaderStages::VERTEX_FRAGMENT,
                 TRANSFORM_CONST_OFFSET,
@@ -437,15 +485,70 @@ fn set_passthrough_push_constants(render_pass: &mut RenderPass, config: Option<&
     }
 }
 
+// Constants of the stream shader for the left and right views. Both halves of the frame have the
+// size of the largest compressed view, which is returned, and the other view is padded
 pub fn foveated_encoding_shader_constants(
     expanded_view_resolution: UVec2,
     config: FoveatedEncodingConfig,
-) -> (UVec2, Vec<(&'static str, f64)>) {
+) -> (UVec2, [Vec<(&'static str, f64)>; 2]) {
+    let eyes = config
+        .eyes()
+        .map(|eye| eye_shader_constants(expanded_view_resolution, config.mode, eye));
+
+    let optimized_view_resolution = eyes[0].0.max(eyes[1].0);
+    let optimized_view_resolution_aligned =
+        optimized_view_resolution.map(|v| (v / 32.).ceil() * 32.);
+
+    let constants = eyes.map(|(optimized_view_resolution, mut constants)| {
+        let view_ratio = optimized_view_resolution / optimized_view_resolution_aligned;
+        constants.extend([
+            ("VIEW_WIDTH_RATIO", view_ratio.x),
+            ("VIEW_HEIGHT_RATIO", view_ratio.y),
+        ]);
+
+        constants.iter().map(|(k, v)| (*k, *v as f64)).collect()
+    });
+
+    (optimized_view_resolution_aligned.as_uvec2(), constants)
+}
+
+// Returns the compressed view resolution before the alignment for the encoder
+fn eye_shader_constants(
+    expanded_view_resolution: UVec2,
+    mode: FoveatedEncodingMode,
+    eye: FoveatedEncodingEyeConfig,
+) -> (Vec2, Vec<(&'static str, f32)>) {
+    if let FoveatedEncodingMode::Radial {
+        center_strength,
+        edge_compression,
+    } = mode
+    {
+        let foveation = RadialFoveation::new(
+            center_strength,
+            edge_compression,
+            glam::vec2(eye.center_shift_x, eye.center_shift_y),
+        );
+
+        let constants = vec![
+            ("ENABLE_FFE", 1.),
+            ("FFE_MODE", 1.),
+            ("RADIAL_CENTER_X", foveation.center.x),
+            ("RADIAL_CENTER_Y", foveation.center.y),
+            ("RADIAL_ALPHA", foveation.alpha),
+            ("RADIAL_SCALE", foveation.scale),
+        ];
+
+        return (
+            foveation.scale * expanded_view_resolution.as_vec2(),
+            constants,
+        );
+    }
+
     let view_resolution = expanded_view_resolution.as_vec2();
 
-    let center_size = glam::vec2(config.center_size_x, config.center_size_y);
-    let center_shift = glam::vec2(config.center_shift_x, config.center_shift_y);
-    let edge_ratio = glam::vec2(config.edge_ratio_x, config.edge_ratio_y);
+    let center_size = glam::vec2(eye.center_size_x, eye.center_size_y);
+    let center_shift = glam::vec2(eye.center_shift_x, eye.center_shift_y);
+    let edge_ratio = glam::vec2(eye.edge_ratio_x, eye.edge_ratio_y);
 
     let edge_size = view_resolution - center_size * view_resolution;
     let center_size_aligned =
@@ -460,11 +563,6 @@ pub fn foveated_encoding_shader_constants(
 
     let optimized_view_resolution = foveation_scale * view_resolution;
 
-    let optimized_view_resolution_aligned =
-        optimized_view_resolution.map(|v| (v / 32.).ceil() * 32.);
-
-    let view_ratio_aligned = optimized_view_resolution / optimized_view_resolution_aligned;
-
     let c0 = (1. - center_size_aligned) * 0.5;
     let c1 = (edge_ratio - 1.) * c0 * (center_shift_aligned + 1.) / edge_ratio;
     let c2 = (edge_ratio - 1.) * center_size_aligned + 1.;
@@ -485,10 +583,8 @@ pub fn foveated_encoding_shader_constants(
     let c_right = (c2 * edge_ratio - c2) * (c1 - hi_bound_c + c2 * hi_bound_c)
         / (edge_ratio * (1. - hi_bound_c) * (1. - hi_bound_c));
 
-    let constants = [
+    let constants = vec![
         ("ENABLE_FFE", 1.),
-        ("VIEW_WIDTH_RATIO", view_ratio_aligned.x),
-        ("VIEW_HEIGHT_RATIO", view_ratio_aligned.y),
         ("EDGE_X_RATIO", edge_ratio.x),
         ("EDGE_Y_RATIO", edge_ratio.y),
         ("C1_X", c1.x),
@@ -509,12 +605,9 @@ pub fn foveated_encoding_shader_constants(
         ("B_RIGHT_Y", b_right.y),
         ("C_RIGHT_X", c_right.x),
         ("C_RIGHT_Y", c_right.y),
-    ]
-    .iter()
-    .map(|(k, v)| (*k, *v as f64))
-    .collect();
+    ];
 
-    (optimized_view_resolution_aligned.as_uvec2(), constants)
+    (optimized_view_resolution, constants)
 }
 
 pub fn compute_target_view_resolution(
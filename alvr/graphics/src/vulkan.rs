@@ -0,0 +1,288 @@
+use super::{GraphicsBackend, GraphicsContext, MAX_PUSH_CONSTANTS_SIZE, SwapchainImage};
+use alvr_common::{
+    anyhow::{Context, Result, bail},
+    glam::UVec2,
+};
+use alvr_session::ClientSwapchainFormat;
+use ash::vk::{self, Handle};
+use wgpu::{
+    Device, DeviceDescriptor, Extent3d, Features, Instance, InstanceFlags, Limits, MemoryHints,
+    Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureUses, Trace,
+    hal::{self, MemoryFlags, api::Vulkan},
+};
+
+pub use ash;
+
+pub const SDR_FORMAT_VK: u32 = vk::Format::R8G8B8A8_UNORM.as_raw() as u32;
+
+const API_VERSION: u32 = vk::API_VERSION_1_1;
+
+// The Vulkan instance and device must be created by the OpenXR runtime, which adds the extensions
+// it needs and chooses the physical device.
+pub trait VulkanLoader {
+    fn create_instance(
+        &self,
+        get_instance_proc_addr: vk::PFN_vkGetInstanceProcAddr,
+        create_info: &vk::InstanceCreateInfo,
+    ) -> Result<vk::Instance>;
+
+    fn physical_device(&self, instance: vk::Instance) -> Result<vk::PhysicalDevice>;
+
+    fn create_device(
+        &self,
+        get_instance_proc_addr: vk::PFN_vkGetInstanceProcAddr,
+        physical_device: vk::PhysicalDevice,
+        create_info: &vk::DeviceCreateInfo,
+    ) -> Result<vk::Device>;
+}
+
+// The handles are owned by wgpu, they must not be destroyed
+pub struct VulkanContext {
+    pub instance: ash::Instance,
+    pub physical_device: vk::PhysicalDevice,
+    pub device: ash::Device,
+    pub queue_family_index: u32,
+    pub(crate) queue: vk::Queue,
+    pub(crate) external_memory_fns: ash::android::external_memory_android_hardware_buffer::Device,
+}
+
+impl GraphicsContext {
+    pub fn new_vulkan(loader: &impl VulkanLoader) -> Result<Self> {
+        // Validation layers are not bundled with the client
+        let flags = if cfg!(debug_assertions) {
+            InstanceFlags::DEBUG
+        } else {
+            InstanceFlags::empty()
+        };
+
+        let entry = unsafe { ash::Entry::load() }?;
+        let get_instance_proc_addr = entry.static_fn().get_instance_proc_addr;
+
+        let instance_extensions =
+            hal::vulkan::Instance::desired_extensions(&entry, API_VERSION, flags)?;
+        let instance_extension_ptrs = instance_extensions
+            .iter()
+            .map(|ext| ext.as_ptr())
+            .collect::<Vec<_>>();
+        let app_info = vk::ApplicationInfo::default()
+            .application_name(c"ALVR Client")
+            .engine_name(c"ALVR")
+            .api_version(API_VERSION);
+        let raw_instance = loader.create_instance(
+            get_instance_proc_addr,
+            &vk::InstanceCreateInfo::default()
+                .application_info(&app_info)
+                .enabled_extension_names(&instance_extension_ptrs),
+        )?;
+        let instance = unsafe { ash::Instance::load(entry.static_fn(), raw_instance) };
+
+        let hal_instance = unsafe {
+            hal::vulkan::Instance::from_raw(
+                entry,
+                instance.clone(),
+                API_VERSION,
+                0, // The Android SDK version is only used for driver workarounds
+                None,
+                instance_extensions,
+                flags,
+                false,
+                None,
+            )
+        }?;
+
+        let physical_device = loader.physical_device(raw_instance)?;
+        let exposed_adapter = hal_instance
+            .expose_adapter(physical_device)
+            .context("Vulkan physical device unsupported by wgpu")?;
+
+        // NV12 support enables the sampler YCbCr conversion feature needed for the decoder images
+        let features = Features::PUSH_CONSTANTS | Features::TEXTURE_FORMAT_NV12;
+        if !exposed_adapter.features.contains(features) {
+            bail!(
+                "Missing Vulkan features: {:?}",
+                features - exposed_adapter.features
+            );
+        }
+
+        let queue_family_index =
+            unsafe { instance.get_physical_device_queue_family_properties(physical_device) }
+                .iter()
+                .position(|props| props.queue_flags.contains(vk::QueueFlags::GRAPHICS))
+                .context("No Vulkan graphics queue")? as u32;
+
+        let mut device_extensions = exposed_adapter.adapter.required_device_extensions(features);
+        device_extensions.extend([
+            ash::android::external_memory_android_hardware_buffer::NAME,
+            ash::ext::queue_family_foreign::NAME,
+        ]);
+        let device_extension_ptrs = device_extensions
+            .iter()
+            .map(|ext| ext.as_ptr())
+            .collect::<Vec<_>>();
+
+        let mut physical_device_features = exposed_adapter
+            .adapter
+            .physical_device_features(&device_extensions, features);
+        let queue_priorities = [1.0];
+        let queue_create_infos = [vk::DeviceQueueCreateInfo::default()
+            .queue_family_index(queue_family_index)
+            .queue_priorities(&queue_priorities)];
+        let raw_device = loader.create_device(
+            get_instance_proc_addr,
+            physical_device,
+            &physical_device_features.add_to_device_create(
+                vk::DeviceCreateInfo::default()
+                    .queue_create_infos(&queue_create_infos)
+                    .enabled_extension_names(&device_extension_ptrs),
+            ),
+        )?;
+        let device = unsafe { ash::Device::load(instance.fp_v1_0(), raw_device) };
+
+        let open_device = unsafe {
+            exposed_adapter.adapter.device_from_raw(
+                device.clone(),
+                None,
+                &device_extensions,
+                features,
+                &MemoryHints::Performance,
+                queue_family_index,
+                0,
+            )
+        }?;
+
+        let wgpu_instance = unsafe { Instance::from_hal::<Vulkan>(hal_instance) };
+        let adapter = unsafe { wgpu_instance.create_adapter_from_hal(exposed_adapter) };
+        let (wgpu_device, queue) = unsafe {
+            adapter.create_device_from_hal(
+                open_device,
+                &DeviceDescriptor {
+                    label: None,
+                    required_features: features,
+                    required_limits: Limits {
+                        max_push_constant_size: MAX_PUSH_CONSTANTS_SIZE,
+                        ..adapter.limits()
+                    },
+                    memory_hints: MemoryHints::Performance,
+                    trace: Trace::Off,
+                },
+            )
+        }?;
+
+        let external_memory_fns =
+            ash::android::external_memory_android_hardware_buffer::Device::new(&instance, &device);
+        let raw_queue = unsafe { device.get_device_queue(queue_family_index, 0) };
+
+        Ok(Self {
+            _instance: wgpu_instance,
+            device: wgpu_device,
+            queue,
+            backend: GraphicsBackend::Vulkan(VulkanContext {
+                instance,
+                physical_device,
+                device,
+                queue_family_index,
+                queue: raw_queue,
+                external_memory_fns,
+            }),
+        })
+    }
+}
+
+pub fn choose_vk_swapchain_format(
+    supported_formats: &[u32],
+    enable_hdr: bool,
+    preferred_format: ClientSwapchainFormat,
+) -> u32 {
+    // Priority-sorted list of swapchain formats we'll accept--
+    let mut app_supported_swapchain_formats =
+        vec![vk::Format::R8G8B8A8_SRGB, vk::Format::R8G8B8A8_UNORM];
+    if preferred_format == ClientSwapchainFormat::Linear {
+        app_supported_swapchain_formats.reverse();
+    }
+
+    // float16 is required for HDR output. However, float16 swapchains
+    // have a high perf cost, so only use these if HDR is enabled.
+    if enable_hdr {
+        app_supported_swapchain_formats.insert(0, vk::Format::R16G16B16A16_SFLOAT);
+    }
+
+    for format in app_supported_swapchain_formats {
+        let format = format.as_raw() as u32;
+        if supported_formats.contains(&format) {
+            return format;
+        }
+    }
+
+    // If we can't enumerate, default to a required format
+    SDR_FORMAT_VK
+}
+
+pub fn vk_format_to_wgpu(format: u32) -> TextureFormat {
+    match vk::Format::from_raw(format as i32) {
+        vk::Format::R8G8B8A8_SRGB => TextureFormat::Rgba8UnormSrgb,
+        vk::Format::R8G8B8A8_UNORM => TextureFormat::Rgba8Unorm,
+        vk::Format::R16G16B16A16_SFLOAT => TextureFormat::Rgba16Float,
+        _ => panic!("Unsupported Vulkan format: {format}"),
+    }
+}
+
+pub(crate) fn wgpu_format_to_vk(format: TextureFormat) -> vk::Format {
+    match format {
+        TextureFormat::Rgba8UnormSrgb => vk::Format::R8G8B8A8_SRGB,
+        TextureFormat::Rgba8Unorm => vk::Format::R8G8B8A8_UNORM,
+        TextureFormat::Rgba16Float => vk::Format::R16G16B16A16_SFLOAT,
+        _ => panic!("Unsupported wgpu format: {format:?}"),
+    }
+}
+
+pub(crate) fn texture_to_vulkan(texture: &Texture) -> vk::Image {
+    unsafe { texture.as_hal::<Vulkan, _, _>(|tex| tex.unwrap().raw_handle()) }
+}
+
+// OpenXR gives VkImage handles as u64
+impl SwapchainImage for u64 {
+    fn create_texture(
+        device: &Device,
+        image: Self,
+        resolution: UVec2,
+        format: TextureFormat,
+    ) -> Texture {
+        let size = Extent3d {
+            width: resolution.x,
+            height: resolution.y,
+            depth_or_array_layers: 1,
+        };
+
+        unsafe {
+            let hal_texture = hal::vulkan::Device::texture_from_raw(
+                vk::Image::from_raw(image),
+                &hal::TextureDescriptor {
+                    label: None,
+                    size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format,
+                    usage: TextureUses::COLOR_TARGET,
+                    memory_flags: MemoryFlags::empty(),
+                    view_formats: vec![],
+                },
+                Some(Box::new(|| ())),
+            );
+
+            device.create_texture_from_hal::<Vulkan>(
+                hal_texture,
+                &TextureDescriptor {
+                    label: None,
+                    size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format,
+                    usage: TextureUsages::RENDER_ATTACHMENT,
+                    view_formats: &[],
+                },
+            )
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use crate::{
     InstallationInfo, Progress, ReleaseChannelsInfo, ReleaseInfo, UiMessage, WorkerMessage,
 };
-use alvr_common::{ToAny, anyhow::Result, semver::Version};
+use alvr_common::{ToAny, anyhow::Result, error, semver::Version, warn};
+use alvr_filesystem::Layout;
 use anyhow::{Context, bail};
 use flate2::read::GzDecoder;
 use futures_util::StreamExt;
@@ -14,7 +15,16 @@ use std::{
     sync::mpsc::{Receiver, Sender},
 };
 
-const APK_NAME: &str = "client.apk";
+const APK_NAME: &str = "alvr_client_android.apk";
+const SESSION_SNAPSHOT_NAME: &str = "session_snapshot.json";
+const VERSIONS_STATE_NAME: &str = "versions.json";
+
+#[derive(Default)]
+pub struct VersionsState {
+    pub active: Option<String>,
+    // Target of the rollback
+    pub previous: Option<String>,
+}
 
 pub fn installations_dir() -> PathBuf {
     data_dir().join("installations")
@@ -34,7 +44,7 @@ pub fn worker(
             let version_data = match fetch_all_releases(&req_client).await {
                 Ok(data) => data,
                 Err(e) => {
-                    eprintln!("Error fetching version data: {e}");
+                    error!("Error fetching version data: {e}");
                     return;
                 }
             };
@@ -141,12 +151,11 @@ fn install_and_launch_apk(
     }))?;
 
     let root = installations_dir().join(&release.version);
-    let apk_name = "alvr_client_android.apk";
-    let apk_path = root.join(apk_name);
+    let apk_path = root.join(APK_NAME);
     if !apk_path.exists() {
         let apk_url = release
             .assets
-            .get(apk_name)
+            .get(APK_NAME)
             .ok_or(anyhow::anyhow!("Unable to determine download URL"))?;
         let apk_buffer = alvr_adb::commands::download(apk_url, |downloaded, total| {
             let progress = total.map_or(0.0, |t| downloaded as f32 / t as f32);
@@ -177,12 +186,7 @@ fn install_and_launch_apk(
         .find_map(|d| d.serial.clone())
         .ok_or(anyhow::anyhow!("Failed to find connected device"))?;
 
-    let v = if release.version.starts_with('v') {
-        release.version[1..].to_string()
-    } else {
-        release.version
-    };
-    let version = Version::parse(&v).context("Failed to parse release version")?;
+    let version = parse_version(&release.version)?;
     let stable = version.pre.is_empty() && !version.build.contains("nightly");
     let application_id = if stable {
         alvr_system_info::PACKAGE_NAME_GITHUB_STABLE
@@ -202,7 +206,11 @@ fn install_and_launch_apk(
         message: "Installing new APK".into(),
         progress: 0.0,
     }))?;
-    alvr_adb::commands::install_package(&adb_path, &device_serial, &apk_path.to_string_lossy())?;
+    if let Some(failure) =
+        alvr_adb::commands::install_package(&adb_path, &device_serial, &apk_path.to_string_lossy())?
+    {
+        bail!("Failed to install the APK: {failure}");
+    }
 
     alvr_adb::commands::start_application(&adb_path, &device_serial, application_id)?;
 
@@ -308,6 +316,11 @@ async fn install_server(
     Ok(())
 }
 
+fn parse_version(version: &str) -> Result<Version> {
+    Version::parse(version.strip_prefix('v').unwrap_or(version))
+        .context("Failed to parse release version")
+}
+
 pub fn data_dir() -> PathBuf {
     if cfg!(target_os = "linux") {
         PathBuf::from(env::var("HOME").expect("Failed to determine home directory"))
@@ -322,6 +335,8 @@ pub fn data_dir() -> PathBuf {
 }
 
 pub fn get_installations() -> Vec<InstallationInfo> {
+    let active_version = load_versions_state().active;
+
     match fs::read_dir(installations_dir()) {
         Ok(entries) => entries
             .into_iter()
@@ -331,7 +346,7 @@ pub fn get_installations() -> Vec<InstallationInfo> {
                     .filter(|entry| match entry.file_type() {
                         Ok(file_type) => file_type.is_dir(),
                         Err(e) => {
-                            eprintln!("Failed to read entry file type: {e}");
+                            warn!("Failed to read entry file type: {e}");
                             false
                         }
                     })
@@ -347,16 +362,19 @@ pub fn get_installations() -> Vec<InstallationInfo> {
                             false
                         };
 
+                        let version = entry.file_name().to_string_lossy().to_string();
+
                         InstallationInfo {
-                            version: entry.file_name().to_string_lossy().into(),
                             is_apk_downloaded: entry.path().join(APK_NAME).exists(),
                             has_session_json,
+                            is_active: active_version.as_ref() == Some(&version),
+                            version,
                         }
                     })
             })
             .collect(),
         Err(e) => {
-            eprintln!("Failed to read versions dir: {e}");
+            error!("Failed to read versions dir: {e}");
             Vec::new()
         }
     }
@@ -379,7 +397,139 @@ pub fn launch_dashboard(version: &str) -> Result<()> {
 }
 
 pub fn delete_installation(version: &str) -> Result<()> {
+    alvr_server_io::driver_registration(
+        &[installation_layout(version).openvr_driver_root_dir],
+        false,
+    )
+    .ok();
+
     fs::remove_dir_all(installations_dir().join(version))?;
 
+    let mut state = load_versions_state();
+    if state.active.as_deref() == Some(version) {
+        state.active = None;
+    }
+    if state.previous.as_deref() == Some(version) {
+        state.previous = None;
+    }
+    store_versions_state(&state)
+}
+
+fn installation_layout(version: &str) -> Layout {
+    let installation_dir = installations_dir().join(version);
+
+    if cfg!(windows) {
+        Layout::new(&installation_dir)
+    } else {
+        Layout::new(&installation_dir.join("alvr_streamer_linux"))
+    }
+}
+
+pub fn load_versions_state() -> VersionsState {
+    let json = fs::read_to_string(data_dir().join(VERSIONS_STATE_NAME))
+        .ok()
+        .and_then(|text| serde_json::from_str::<serde_json::Value>(&text).ok())
+        .unwrap_or_default();
+    let field = |name: &str| json[name].as_str().map(String::from);
+
+    VersionsState {
+        active: field("active"),
+        previous: field("previous"),
+    }
+}
+
+fn store_versions_state(state: &VersionsState) -> Result<()> {
+    let json = serde_json::json!({
+        "active": state.active,
+        "previous": state.previous,
+    });
+    fs::write(
+        data_dir().join(VERSIONS_STATE_NAME),
+        serde_json::to_string_pretty(&json)?,
+    )?;
+
     Ok(())
 }
+
+// Makes the driver of this version the only ALVR driver registered with SteamVR
+fn register_driver(layout: &Layout) -> Result<()> {
+    let driver_dir = layout.openvr_driver_root_dir.clone();
+
+    let other_alvr_dirs = alvr_server_io::get_registered_drivers()?
+        .into_iter()
+        .filter(|path| {
+            path.to_string_lossy().to_lowercase().contains("alvr") && *path != driver_dir
+        })
+        .collect::<Vec<_>>();
+    alvr_server_io::driver_registration(&other_alvr_dirs, false)?;
+
+    alvr_server_io::driver_registration(&[driver_dir], true)
+}
+
+// The session of the version being replaced is snapshotted. When upgrading, the current session is
+// kept and the newer streamer extrapolates it when loading it. When downgrading, the snapshot of the
+// older version is restored instead, since it may not be able to read the newer session.
+pub fn activate_version(version: &str) -> Result<()> {
+    let mut state = load_versions_state();
+    let layout = installation_layout(version);
+
+    let current_version = state
+        .active
+        .clone()
+        .filter(|active| active != version && installations_dir().join(active).exists());
+    if let Some(current_version) = &current_version {
+        let current_session = installation_layout(current_version).session();
+        if current_session.exists() {
+            fs::copy(
+                &current_session,
+                installations_dir()
+                    .join(current_version)
+                    .join(SESSION_SNAPSHOT_NAME),
+            )?;
+        }
+
+        // Versions that cannot be parsed are treated as newer, so the session is kept
+        let is_downgrade = matches!(
+            (parse_version(current_version), parse_version(version)),
+            (Ok(current), Ok(target)) if target < current
+        );
+        if is_downgrade {
+            let snapshot = installations_dir()
+                .join(version)
+                .join(SESSION_SNAPSHOT_NAME);
+            if snapshot.exists() {
+                fs::copy(snapshot, layout.session())?;
+            }
+        } else if cfg!(windows) && current_session.exists() {
+            // On Windows, each installation has its own session
+            fs::copy(&current_session, layout.session())?;
+        }
+    }
+
+    // SteamVR may not be installed yet, in which case the dashboard registers the driver later
+    if let Err(e) = register_driver(&layout) {
+        warn!("Failed to register the SteamVR driver: {e}");
+    }
+
+    if state.active.as_deref() != Some(version) {
+        if current_version.is_some() {
+            state.previous = current_version;
+        }
+        state.active = Some(version.to_owned());
+        store_versions_state(&state)?;
+    }
+
+    Ok(())
+}
+
+// Switches back to the previously active version. Returns the version rolled back to.
+pub fn rollback() -> Result<String> {
+    let previous = load_versions_state()
+        .previous
+        .filter(|previous| installations_dir().join(previous).exists())
+        .context("No version to roll back to")?;
+
+    activate_version(&previous)?;
+
+    Ok(previous)
+}
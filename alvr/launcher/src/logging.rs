@@ -0,0 +1,56 @@
+use crate::actions;
+use alvr_common::{RotatingFileWriter, log::LevelFilter};
+use std::{
+    fs,
+    io::{self, Write},
+};
+
+const LOG_FILE_NAME: &str = "launcher_log.txt";
+const MAX_LOG_SIZE_BYTES: u64 = 1024 * 1024;
+const MAX_LOG_FILES: usize = 2;
+
+// Writes to stderr and to the log file in the launcher data directory. Errors are ignored, stderr
+// is not available when the launcher is started without a console
+struct LogWriter {
+    file: Option<RotatingFileWriter>,
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::stderr().write_all(buf).ok();
+        if let Some(file) = &mut self.file {
+            file.write_all(buf).ok();
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(file) = &mut self.file {
+            file.flush().ok();
+        }
+        io::stderr().flush().ok();
+
+        Ok(())
+    }
+}
+
+pub fn init_logging() {
+    let data_dir = actions::data_dir();
+    let file = fs::create_dir_all(&data_dir)
+        .and_then(|_| {
+            RotatingFileWriter::new(
+                data_dir.join(LOG_FILE_NAME),
+                MAX_LOG_SIZE_BYTES,
+                MAX_LOG_FILES,
+            )
+        })
+        .map_err(|e| eprintln!("Failed to create the launcher log file: {e}"))
+        .ok();
+
+    env_logger::Builder::new()
+        .filter(Some("reqwest"), LevelFilter::Off)
+        .filter_level(LevelFilter::Info)
+        .target(env_logger::Target::Pipe(Box::new(LogWriter { file })))
+        .init();
+}
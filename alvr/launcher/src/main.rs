@@ -1,4 +1,5 @@
 mod actions;
+mod logging;
 mod ui;
 
 use eframe::egui::{IconData, ViewportBuilder};
@@ -42,9 +43,12 @@ pub struct InstallationInfo {
     version: String,
     is_apk_downloaded: bool,
     has_session_json: bool, // Only relevant on Windows
+    is_active: bool,        // Its driver is the one registered with SteamVR
 }
 
 fn main() {
+    logging::init_logging();
+
     let (worker_message_sender, worker_message_receiver) = mpsc::channel::<WorkerMessage>();
     let (ui_message_sender, ui_message_receiver) = mpsc::channel::<UiMessage>();
 
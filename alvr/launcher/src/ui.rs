@@ -49,6 +49,7 @@ pub struct Launcher {
     state: State,
     release_channels_info: Option<ReleaseChannelsInfo>,
     installations: Vec<InstallationInfo>,
+    versions_state: actions::VersionsState,
     popup: PopupType,
 }
 
@@ -66,6 +67,7 @@ impl Launcher {
             state: State::Default,
             release_channels_info: None,
             installations: actions::get_installations(),
+            versions_state: actions::load_versions_state(),
             popup: PopupType::None,
         }
     }
@@ -220,6 +222,11 @@ impl Launcher {
         }
     }
 
+    fn refresh_installations(&mut self) {
+        self.installations = actions::get_installations();
+        self.versions_state = actions::load_versions_state();
+    }
+
     fn edit_popup(&self, ctx: &Context, version: String) -> PopupType {
         let mut delete_version = false;
         let response = alvr_gui_common::modal(
@@ -269,7 +276,7 @@ impl Launcher {
                     self.state = State::Error(format!("Failed to delete version: {e}"));
                 }
 
-                self.installations = actions::get_installations();
+                self.refresh_installations();
 
                 PopupType::None
             }
@@ -287,8 +294,7 @@ impl eframe::App for Launcher {
                     self.state = State::Installing(progress);
                 }
                 WorkerMessage::Done => {
-                    // Refresh installations
-                    self.installations = actions::get_installations();
+                    self.refresh_installations();
                     self.state = State::Default;
                 }
                 WorkerMessage::Error(e) => self.state = State::Error(e),
@@ -304,6 +310,7 @@ impl eframe::App for Launcher {
                         None => "Fetching latest release...".into(),
                     });
 
+                    let mut refresh_installations = false;
                     for installation in &self.installations {
                         let path = actions::installations_dir().join(&installation.version);
 
@@ -314,7 +321,17 @@ impl eframe::App for Launcher {
                                 Grid::new(&installation.version)
                                     .num_columns(2)
                                     .show(ui, |ui| {
-                                        ui.label(&installation.version);
+                                        if installation.is_active {
+                                            ui.label(
+                                                RichText::new(format!(
+                                                    "{} (active)",
+                                                    installation.version
+                                                ))
+                                                .strong(),
+                                            );
+                                        } else {
+                                            ui.label(&installation.version);
+                                        }
                                         ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
                                             if ui.button("Edit").clicked() {
                                                 self.popup = PopupType::EditVersion(
@@ -357,9 +374,14 @@ impl eframe::App for Launcher {
                                             };
 
                                             if ui.button("Launch").clicked() {
-                                                match actions::launch_dashboard(
+                                                match actions::activate_version(
                                                     &installation.version,
-                                                ) {
+                                                )
+                                                .and_then(|()| {
+                                                    actions::launch_dashboard(
+                                                        &installation.version,
+                                                    )
+                                                }) {
                                                     Ok(()) => {
                                                         self.ui_message_sender
                                                             .send(UiMessage::Quit)
@@ -373,11 +395,56 @@ impl eframe::App for Launcher {
                                                     }
                                                 }
                                             }
+
+                                            if ui
+                                                .add_enabled(
+                                                    !installation.is_active,
+                                                    Button::new("Set active"),
+                                                )
+                                                .on_hover_text(
+                                                    "Register the SteamVR driver of this version",
+                                                )
+                                                .clicked()
+                                            {
+                                                match actions::activate_version(
+                                                    &installation.version,
+                                                ) {
+                                                    Ok(()) => refresh_installations = true,
+                                                    Err(e) => {
+                                                        self.state = State::Error(format!(
+                                                            "Failed to activate version: {e}"
+                                                        ));
+                                                    }
+                                                }
+                                            }
                                         })
                                     })
                             });
                     }
 
+                    let rollback_version = self.versions_state.previous.clone().filter(|previous| {
+                        self.installations
+                            .iter()
+                            .any(|installation| installation.version == *previous)
+                    });
+                    if let Some(previous) = rollback_version
+                        && ui
+                            .button(format!("Roll back to {previous}"))
+                            .on_hover_text(
+                                "Register the driver of the previously active version and restore its session",
+                            )
+                            .clicked()
+                    {
+                        match actions::rollback() {
+                            Ok(_) => refresh_installations = true,
+                            Err(e) => self.state = State::Error(format!("Failed to roll back: {e}")),
+                        }
+                    }
+
+                    if refresh_installations {
+                        self.refresh_installations();
+                    }
+
                     if ui
                         .add_enabled(
                             self.release_channels_info.is_some(),
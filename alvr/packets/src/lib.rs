@@ -1,11 +1,13 @@
 use alvr_common::{
-    BodySkeleton, ConnectionState, DeviceMotion, LogSeverity, Pose, ViewParams,
+    BodySkeleton, ConnectionState, DeviceMotion, LogSeverity, Pose, TraceSpan, ViewParams,
     anyhow::Result,
     glam::{Quat, UVec2, Vec2},
     semver::Version,
 };
 use alvr_session::{
-    ClientsidePostProcessingConfig, CodecType, PassthroughMode, SessionConfig, Settings,
+    ButtonMacroAction, ChromaSubsampling, ClientsidePostProcessingConfig, CodecType,
+    FoveatedEncodingEyeConfig, PassthroughMode, PassthroughStyleConfig, SessionConfig, Settings,
+    SettingsOverride,
 };
 use serde::{Deserialize, Serialize};
 use serde_json as json;
@@ -21,10 +23,26 @@ pub const HAPTICS: u16 = 1;
 pub const AUDIO: u16 = 2;
 pub const VIDEO: u16 = 3;
 pub const STATISTICS: u16 = 4;
+pub const PERIPHERAL_INPUT: u16 = 5;
+pub const MOTION_VECTORS: u16 = 6;
+pub const DEPTH: u16 = 7;
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct VideoStreamingCapabilitiesExt {
-    // Nothing for now
+    // The client can submit depth for the composition layers (XR_KHR_composition_layer_depth)
+    pub depth_layers: bool,
+    // The client can synthesize frames from the streamed motion vectors and depth (XR_FB_space_warp)
+    pub space_warp: bool,
+    // The decoder supports HEVC with 4:2:2 and 4:4:4 chroma subsampling (range extensions)
+    pub decoder_chroma_422: bool,
+    pub decoder_chroma_444: bool,
+    // The client can unwarp the radial foveated encoding
+    pub radial_foveated_encoding: bool,
+    // The client can unwarp different foveation parameters for each eye
+    pub per_eye_foveated_encoding: bool,
+    // The client shows the left view on a flat display and sends no tracking. The server streams
+    // to it alongside the headset
+    pub flat_display: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -52,11 +70,21 @@ impl VideoStreamingCapabilities {
     }
 
     pub fn ext(&self) -> Result<VideoStreamingCapabilitiesExt> {
-        let _ext_json = json::from_str::<json::Value>(&self.ext_str)?;
-
-        // decode values here
-
-        Ok(VideoStreamingCapabilitiesExt {})
+        let ext_json = json::from_str::<json::Value>(&self.ext_str)?;
+
+        Ok(VideoStreamingCapabilitiesExt {
+            depth_layers: ext_json["depth_layers"].as_bool().unwrap_or(false),
+            space_warp: ext_json["space_warp"].as_bool().unwrap_or(false),
+            decoder_chroma_422: ext_json["decoder_chroma_422"].as_bool().unwrap_or(false),
+            decoder_chroma_444: ext_json["decoder_chroma_444"].as_bool().unwrap_or(false),
+            radial_foveated_encoding: ext_json["radial_foveated_encoding"]
+                .as_bool()
+                .unwrap_or(false),
+            per_eye_foveated_encoding: ext_json["per_eye_foveated_encoding"]
+                .as_bool()
+                .unwrap_or(false),
+            flat_display: ext_json["flat_display"].as_bool().unwrap_or(false),
+        })
     }
 }
 
@@ -74,9 +102,17 @@ pub enum ClientConnectionResult {
     ClientStandby,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Default)]
 pub struct NegotiatedStreamingConfigExt {
-    // Nothing for now
+    pub enable_depth_stream: bool,
+    pub enable_space_warp: bool,
+    // Expected format of the decoded frames. The bitstream headers remain authoritative
+    pub chroma_subsampling: ChromaSubsampling,
+    // Otherwise the two zones mode is used, whatever the settings
+    pub enable_radial_foveated_encoding: bool,
+    // Foveation parameters of the left and right eyes used by the server. Older servers only mirror
+    // the parameters of the settings
+    pub foveated_encoding_eyes: Option<[FoveatedEncodingEyeConfig; 2]>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -100,15 +136,23 @@ impl NegotiatedStreamingConfig {
     }
 
     pub fn ext(&self) -> Result<NegotiatedStreamingConfigExt> {
-        let _ext_json = json::from_str::<json::Value>(&self.ext_str)?;
-
-        // decode values here
-
-        Ok(NegotiatedStreamingConfigExt {})
+        let ext_json = json::from_str::<json::Value>(&self.ext_str)?;
+
+        Ok(NegotiatedStreamingConfigExt {
+            enable_depth_stream: ext_json["enable_depth_stream"].as_bool().unwrap_or(false),
+            enable_space_warp: ext_json["enable_space_warp"].as_bool().unwrap_or(false),
+            chroma_subsampling: json::from_value(ext_json["chroma_subsampling"].clone())
+                .unwrap_or_default(),
+            enable_radial_foveated_encoding: ext_json["enable_radial_foveated_encoding"]
+                .as_bool()
+                .unwrap_or(false),
+            foveated_encoding_eyes: json::from_value(ext_json["foveated_encoding_eyes"].clone())
+                .unwrap_or_default(),
+        })
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct StreamConfigPacket {
     pub session: String, // JSON session that allows for extrapolation
     pub negotiated: NegotiatedStreamingConfig,
@@ -156,6 +200,25 @@ pub enum ServerControlPacket {
     Restarting,
     KeepAlive,
     RealTimeConfig(RealTimeConfig),
+    RequestVideoLossReport, // Used by the bitrate benchmark, the client replies with VideoLossReport
+    Clipboard(String),
+    LatencyTestFlash(Duration), // Flash the frames newer than this timestamp
+    StandbyDisconnect,          // The client should not reconnect until the headset leaves standby
+    ServerMacAddress([u8; 6]),  // Stored by the client to wake up the PC with Wake-on-LAN
+    RequestClientLog,           // The client replies with ClientLog
+    TimeSyncRequest(Duration),  // Server time. The client replies with TimeSyncResponse
+    SetMicrophoneMuted(bool),
+    StartTraceCapture,
+    StopTraceCapture, // The client replies with TraceSpans
+    // Origin set by the last recentering of the user, in the tracking reference space. Stored by
+    // the client to restore it on the next connection
+    RecenteringOrigin(Pose),
+    // Whether the game submits depth. The depth stream is paused while it doesn't
+    DepthSubmission(bool),
+    // The sockets are kept open while SteamVR restarts. The client replies with RestartAck and
+    // stops sending, then waits for DriverResumed, sent by the new driver on the same connection
+    DriverRestarting,
+    DriverResumed,
     Reserved(String),
     ReservedBuffer(Vec<u8>),
 }
@@ -167,6 +230,97 @@ pub struct BatteryInfo {
     pub is_plugged: bool,
 }
 
+// Mirrors Android PowerManager THERMAL_STATUS_* constants
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ThermalStatus {
+    None,
+    Light,
+    Moderate,
+    Severe,
+    Critical,
+    Emergency,
+    Shutdown,
+}
+
+// Wi-Fi connection of the client, as reported by Android
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WifiLinkInfo {
+    pub frequency_mhz: u32,
+    // None if the access point could not be found in the scan results
+    pub channel_width_mhz: Option<u32>,
+    pub rssi_dbm: i32,
+    // Negotiated PHY rates
+    pub tx_link_speed_mbps: Option<u32>,
+    pub rx_link_speed_mbps: Option<u32>,
+    // None without the location permission
+    pub bssid: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WifiBand {
+    Band2_4GHz,
+    Band5GHz,
+    Band6GHz,
+}
+
+impl fmt::Display for WifiBand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            WifiBand::Band2_4GHz => "2.4GHz",
+            WifiBand::Band5GHz => "5GHz",
+            WifiBand::Band6GHz => "6GHz",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+impl WifiLinkInfo {
+    pub fn band(&self) -> Option<WifiBand> {
+        match self.frequency_mhz {
+            2400..=2500 => Some(WifiBand::Band2_4GHz),
+            4900..5925 => Some(WifiBand::Band5GHz),
+            5925..=7125 => Some(WifiBand::Band6GHz),
+            _ => None,
+        }
+    }
+
+    pub fn channel(&self) -> Option<u32> {
+        let base_mhz = match self.band()? {
+            // Channel 14 is outside of the 5MHz spacing
+            WifiBand::Band2_4GHz if self.frequency_mhz == 2484 => return Some(14),
+            WifiBand::Band2_4GHz => 2407,
+            WifiBand::Band5GHz => 5000,
+            WifiBand::Band6GHz => 5950,
+        };
+
+        Some(self.frequency_mhz.saturating_sub(base_mhz) / 5)
+    }
+
+    // Rate of the PC to headset direction, the one used by the video
+    pub fn downlink_speed_mbps(&self) -> Option<u32> {
+        self.rx_link_speed_mbps.or(self.tx_link_speed_mbps)
+    }
+}
+
+impl fmt::Display for WifiLinkInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.band(), self.channel()) {
+            (Some(band), Some(channel)) => write!(f, "{band} channel {channel}")?,
+            _ => write!(f, "{}MHz", self.frequency_mhz)?,
+        }
+        if let Some(width) = self.channel_width_mhz {
+            write!(f, " ({width}MHz wide)")?;
+        }
+        write!(f, ", {}dBm", self.rssi_dbm)?;
+        if let Some(speed) = self.downlink_speed_mbps() {
+            write!(f, ", {speed}Mbps")?;
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Copy, Debug)]
 pub enum ButtonValue {
     Binary(bool),
@@ -179,14 +333,44 @@ pub struct ButtonEntry {
     pub value: ButtonValue,
 }
 
+// Video frames counted since the previous report
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug)]
+pub struct VideoLossReport {
+    pub frames_received: u32,
+    pub frames_lost: u32,
+}
+
+// Reference space the client tracks the devices in
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TrackingSpace {
+    LocalFloor,
+    Stage,
+    Local,
+}
+
+// Changes requested from the in-headset menu
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InHeadsetMenuAction {
+    SetBitrateMbps(u64),
+    Recenter,
+    SetPassthrough(bool),
+    SetSharpening(bool),
+}
+
 #[derive(Serialize, Deserialize)]
 pub enum ClientControlPacket {
-    PlayspaceSync(Option<Vec2>),
+    // The perimeter points are on the floor of the tracking space, as (x, z) pairs
+    PlayspaceSync {
+        area: Option<Vec2>,
+        perimeter: Vec<Vec2>,
+    },
     RequestIdr,
+    IntraRefreshRecovery, // Sent instead of RequestIdr if the stream uses intra refresh
     KeepAlive,
     StreamReady, // This flag notifies the server the client streaming socket is ready listening
     LocalViewParams([ViewParams; 2]), // In relation to head
     Battery(BatteryInfo),
+    ThermalStatus(ThermalStatus),
     Buttons(Vec<ButtonEntry>),
     ActiveInteractionProfile {
         device_id: u64,
@@ -198,10 +382,58 @@ pub enum ClientControlPacket {
         message: String,
     },
     ProximityState(bool),
+    StandbyState(bool), // The headset is not worn or the app is not visible
+    VideoLossReport(VideoLossReport),
+    MarkerOrigin(Pose), // Pose of the origin marker in the tracking reference space
+    Clipboard(String),
+    TrackingSpace(TrackingSpace),
+    InHeadsetMenu(InHeadsetMenuAction),
+    ClientLog(String), // Content of the client log files
+    TimeSyncResponse {
+        server_time: Duration,
+        client_time: Duration, // Taken when the request is received
+    },
+    TraceSpans(Vec<TraceSpan>),         // In the client clock
+    WifiLinkInfo(Option<WifiLinkInfo>), // None if the client is not connected over Wi-Fi
+    BiometricStreamsBlocked(bool),      // Sent when the client applies the privacy setting
+    StreamerAction(ButtonMacroAction),  // Triggered by a client gesture
+    // Echo of the foveation parameters of each eye applied by the client, checked by the server
+    FoveatedEncodingApplied(Option<[FoveatedEncodingEyeConfig; 2]>),
+    RestoreRecentering(Pose), // Sent after PlayspaceSync, with the origin stored for this streamer
+    // Key and mouse button events, which must not be lost. Motion and scroll use the stream socket
+    PeripheralInput(PeripheralInput),
+    RestartAck, // Last packet before the driver restart, the connection is handed over from here
     Reserved(String),
     ReservedBuffer(Vec<u8>),
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Back,
+    Forward,
+}
+
+// Events of the keyboard and mouse paired to the headset
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub enum PeripheralInput {
+    Key {
+        // Linux evdev key code, independent of the keyboard layout
+        scancode: u16,
+        // Character produced with the keyboard layout of the client, if any
+        unicode: Option<char>,
+        pressed: bool,
+    },
+    MouseMove(Vec2), // Relative motion in pixels
+    MouseButton {
+        button: MouseButton,
+        pressed: bool,
+    },
+    MouseScroll(Vec2), // In wheel notches, positive is up and right
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum FaceExpressions {
     Fb(Vec<f32>),   // 70 values
@@ -236,6 +468,42 @@ pub struct VideoPacketHeader {
     pub timestamp: Duration,
     pub global_view_params: [ViewParams; 2],
     pub is_idr: bool,
+    // Number of frames needed to refresh the whole image. None if the encoder uses IDR frames
+    pub intra_refresh_period: Option<u32>,
+    // Encoder statistics, for the diagnostics of the client. The QP is None if the encoder doesn't
+    // report it
+    pub average_qp: Option<u32>,
+    // From the composition of the frame to the end of the encoding
+    pub encode_duration: Duration,
+    // Set while the frame rate is reduced because the scene is static. The missing frames are
+    // expected
+    pub idle_throttled: bool,
+}
+
+// Sent on the MOTION_VECTORS stream, followed by the motion vectors of the blocks of the left view
+// then of the right view, row by row, as pairs of i16 in quarter pixels of view_resolution. Each
+// vector points from the block to its match in the reference frame, the previous frame sent. Blocks
+// without a match have INVALID_MOTION_VECTOR as x component
+#[derive(Serialize, Deserialize)]
+pub struct MotionVectorsPacketHeader {
+    pub timestamp: Duration,
+    pub reference_timestamp: Duration,
+    pub view_resolution: UVec2,
+    pub block_size: u32,
+    pub grid_size: UVec2,
+}
+
+pub const INVALID_MOTION_VECTOR: i16 = i16::MIN;
+
+// Sent on the DEPTH stream, followed by the depth of both views side by side, row by row, encoded
+// with alvr_common::encode_depth. The values are the game depth scaled to u16, mapped to the view
+// distances near_z and far_z (near_z > far_z for reversed depth, far_z can be infinite)
+#[derive(Serialize, Deserialize)]
+pub struct DepthPacketHeader {
+    pub timestamp: Duration,
+    pub view_resolution: UVec2,
+    pub near_z: f32,
+    pub far_z: f32,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -296,6 +564,7 @@ pub enum ClientConnectionsAction {
     RemoveEntry,
     UpdateCurrentIp(Option<IpAddr>),
     SetConnectionState(ConnectionState),
+    SetSettingsOverrides(Vec<SettingsOverride>),
 }
 
 #[derive(Serialize, Deserialize, Default, Clone)]
@@ -307,6 +576,14 @@ pub struct ClientStatistics {
     pub rendering: Duration,
     pub vsync_queue: Duration,
     pub total_pipeline_latency: Duration,
+    // Encoded frames dropped because the decoder queue was full, since the start of the stream
+    pub decoder_queue_drops_total: u32,
+    // Frames reprojected by the client because no new frame was ready, since the start of the
+    // stream
+    pub extrapolated_frames_total: u32,
+    // Client clock, like in TimeSyncResponse. Used with the clock sync for the one-way latencies
+    pub video_packet_received_time: Duration,
+    pub send_time: Duration,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -326,7 +603,9 @@ pub enum FirewallRulesAction {
 #[derive(Serialize, Deserialize, PartialEq, Clone)]
 pub struct RealTimeConfig {
     pub passthrough: Option<PassthroughMode>,
+    pub passthrough_style: Option<PassthroughStyleConfig>,
     pub clientside_post_processing: Option<ClientsidePostProcessingConfig>,
+    pub block_biometric_streams: bool,
     pub ext_str: String,
 }
 
@@ -334,11 +613,13 @@ impl RealTimeConfig {
     pub fn from_settings(settings: &Settings) -> Self {
         Self {
             passthrough: settings.video.passthrough.clone().into_option(),
+            passthrough_style: settings.video.passthrough_style.clone().into_option(),
             clientside_post_processing: settings
                 .video
                 .clientside_post_processing
                 .clone()
                 .into_option(),
+            block_biometric_streams: settings.privacy.block_biometric_streams,
             ext_str: String::new(), // No extensions for now
         }
     }
@@ -0,0 +1,152 @@
+use alvr_common::{
+    AxisResponse, INDEX_CONTROLLER_PROFILE_ID, LEFT_SQUEEZE_VALUE_ID, LEFT_THUMBSTICK_X_ID,
+    LEFT_THUMBSTICK_Y_ID, LEFT_TRIGGER_VALUE_ID, PICO_NEO3_CONTROLLER_PROFILE_ID,
+    PICO4_CONTROLLER_PROFILE_ID, PICO4S_CONTROLLER_PROFILE_ID, RIGHT_SQUEEZE_VALUE_ID,
+    RIGHT_THUMBSTICK_X_ID, RIGHT_THUMBSTICK_Y_ID, RIGHT_TRIGGER_VALUE_ID, glam::Vec2,
+};
+use alvr_packets::{ButtonEntry, ButtonValue};
+use alvr_session::{AnalogAxesConfig, AnalogInputProcessingMode};
+use std::collections::HashMap;
+
+struct AxesResponses {
+    thumbsticks: AxisResponse,
+    triggers: AxisResponse,
+    grips: AxisResponse,
+}
+
+impl AxesResponses {
+    fn from_config(config: &AnalogAxesConfig) -> Self {
+        Self {
+            thumbsticks: config.thumbsticks.axis_response(),
+            triggers: config.triggers.axis_response(),
+            grips: config.grips.axis_response(),
+        }
+    }
+
+    // Deadzones that cover the usual drift of each controller. Trackpads are not processed
+    fn for_profile(profile_id: u64) -> Self {
+        let thumbstick_deadzone = if profile_id == *INDEX_CONTROLLER_PROFILE_ID {
+            0.05
+        } else if [
+            *PICO_NEO3_CONTROLLER_PROFILE_ID,
+            *PICO4_CONTROLLER_PROFILE_ID,
+            *PICO4S_CONTROLLER_PROFILE_ID,
+        ]
+        .contains(&profile_id)
+        {
+            0.08
+        } else {
+            // Quest and other controllers
+            0.1
+        };
+
+        Self {
+            thumbsticks: AxisResponse::deadzones(thumbstick_deadzone, 0.02),
+            triggers: AxisResponse::deadzones(0.03, 0.02),
+            grips: AxisResponse::deadzones(0.03, 0.02),
+        }
+    }
+}
+
+// Applies the deadzones and the response curves to the analog inputs, before the button mapping
+pub struct AnalogInputProcessor {
+    profile_responses: AxesResponses,
+    thumbstick_values: HashMap<u64, Vec2>,
+}
+
+impl AnalogInputProcessor {
+    pub fn new(profile_id: u64) -> Self {
+        Self {
+            profile_responses: AxesResponses::for_profile(profile_id),
+            thumbstick_values: HashMap::new(),
+        }
+    }
+
+    pub fn set_profile(&mut self, profile_id: u64) {
+        self.profile_responses = AxesResponses::for_profile(profile_id);
+    }
+
+    // The mode is passed on each call so that the settings can be changed while streaming
+    pub fn process(
+        &mut self,
+        mode: &AnalogInputProcessingMode,
+        entries: &[ButtonEntry],
+    ) -> Vec<ButtonEntry> {
+        let custom_responses;
+        let responses = match mode {
+            AnalogInputProcessingMode::Automatic => &self.profile_responses,
+            AnalogInputProcessingMode::Custom(config) => {
+                custom_responses = AxesResponses::from_config(config);
+                &custom_responses
+            }
+        };
+
+        let thumbsticks = [
+            (*LEFT_THUMBSTICK_X_ID, *LEFT_THUMBSTICK_Y_ID),
+            (*RIGHT_THUMBSTICK_X_ID, *RIGHT_THUMBSTICK_Y_ID),
+        ];
+        let triggers = [*LEFT_TRIGGER_VALUE_ID, *RIGHT_TRIGGER_VALUE_ID];
+        let grips = [*LEFT_SQUEEZE_VALUE_ID, *RIGHT_SQUEEZE_VALUE_ID];
+
+        let mut processed_entries = Vec::with_capacity(entries.len());
+        let mut changed_thumbsticks = vec![];
+        for entry in entries {
+            let ButtonValue::Scalar(value) = entry.value else {
+                processed_entries.push(ButtonEntry {
+                    path_id: entry.path_id,
+                    value: entry.value,
+                });
+                continue;
+            };
+
+            let value = if let Some(&(x_id, y_id)) = thumbsticks
+                .iter()
+                .find(|(x_id, y_id)| entry.path_id == *x_id || entry.path_id == *y_id)
+            {
+                // Both axes are needed for the radial deadzone, they are sent together below
+                let thumbstick = self.thumbstick_values.entry(x_id).or_default();
+                if entry.path_id == x_id {
+                    thumbstick.x = value;
+                } else {
+                    thumbstick.y = value;
+                }
+                if !changed_thumbsticks.contains(&(x_id, y_id)) {
+                    changed_thumbsticks.push((x_id, y_id));
+                }
+
+                continue;
+            } else if triggers.contains(&entry.path_id) {
+                alvr_common::process_axis_value(value, &responses.triggers)
+            } else if grips.contains(&entry.path_id) {
+                alvr_common::process_axis_value(value, &responses.grips)
+            } else {
+                value
+            };
+
+            processed_entries.push(ButtonEntry {
+                path_id: entry.path_id,
+                value: ButtonValue::Scalar(value),
+            });
+        }
+
+        for (x_id, y_id) in changed_thumbsticks {
+            let value = alvr_common::process_thumbstick_value(
+                self.thumbstick_values[&x_id],
+                &responses.thumbsticks,
+            );
+
+            processed_entries.extend([
+                ButtonEntry {
+                    path_id: x_id,
+                    value: ButtonValue::Scalar(value.x),
+                },
+                ButtonEntry {
+                    path_id: y_id,
+                    value: ButtonValue::Scalar(value.y),
+                },
+            ]);
+        }
+
+        processed_entries
+    }
+}
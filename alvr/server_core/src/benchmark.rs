@@ -0,0 +1,338 @@
+use alvr_events::{BitrateBenchmarkReport, BitrateBenchmarkState, BitrateBenchmarkStep};
+use alvr_packets::VideoLossReport;
+use alvr_session::BitrateBenchmarkConfig;
+use std::time::{Duration, Instant};
+
+// Time given to the encoder and the network to adapt to a new bitrate before measuring
+const SETTLE_TIME: Duration = Duration::from_secs(1);
+const LOSS_REPORT_TIMEOUT: Duration = Duration::from_secs(1);
+// Higher bitrates are unlikely to pass after this many failed steps in a row
+const MAX_CONSECUTIVE_FAILED_STEPS: usize = 2;
+
+enum Phase {
+    Settling,
+    Measuring,
+    WaitingLossReport { deadline: Instant },
+}
+
+// Sweeps the bitrate from the minimum to the maximum configured value. Each step is held for a few
+// seconds while the network latency is sampled from the statistics, then the client is asked for
+// the number of video frames it received and lost.
+pub struct BitrateBenchmark {
+    config: BitrateBenchmarkConfig,
+    bitrates_mbps: Vec<u64>,
+    step_index: usize,
+    step_start: Instant,
+    phase: Phase,
+    // Replies to the request sent at the start of the measurement, used to reset the counters
+    pending_reset_reports: usize,
+    latency_samples: Vec<Duration>,
+    steps: Vec<BitrateBenchmarkStep>,
+    state: BitrateBenchmarkState,
+    state_changed: bool,
+}
+
+impl BitrateBenchmark {
+    pub fn new(config: &BitrateBenchmarkConfig, now: Instant) -> Self {
+        let max_bitrate_mbps = u64::max(config.min_bitrate_mbps, config.max_bitrate_mbps);
+        let bitrates_mbps = (config.min_bitrate_mbps..=max_bitrate_mbps)
+            .step_by(u64::max(config.step_mbps, 1) as usize)
+            .collect::<Vec<_>>();
+
+        Self {
+            state: BitrateBenchmarkState::Running {
+                bitrate_mbps: bitrates_mbps[0],
+            },
+            config: config.clone(),
+            bitrates_mbps,
+            step_index: 0,
+            step_start: now,
+            phase: Phase::Settling,
+            pending_reset_reports: 0,
+            latency_samples: vec![],
+            steps: vec![],
+            state_changed: true,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.state, BitrateBenchmarkState::Running { .. })
+    }
+
+    pub fn bitrate_bps(&self) -> Option<f32> {
+        if let BitrateBenchmarkState::Running { bitrate_mbps } = self.state {
+            Some(bitrate_mbps as f32 * 1e6)
+        } else {
+            None
+        }
+    }
+
+    pub fn report_network_latency(&mut self, latency: Duration) {
+        if matches!(self.phase, Phase::Measuring) && !latency.is_zero() {
+            self.latency_samples.push(latency);
+        }
+    }
+
+    pub fn report_video_loss(&mut self, report: VideoLossReport, now: Instant) {
+        if self.pending_reset_reports > 0 {
+            self.pending_reset_reports -= 1;
+        } else if matches!(self.phase, Phase::WaitingLossReport { .. }) {
+            self.finish_step(now, Some(report));
+        }
+    }
+
+    pub fn abort(&mut self, reason: &str) {
+        if self.is_running() {
+            self.state = BitrateBenchmarkState::Aborted(reason.into());
+            self.state_changed = true;
+        }
+    }
+
+    // Returns true if the client should be asked for a video loss report
+    pub fn update(&mut self, now: Instant) -> bool {
+        if !self.is_running() {
+            return false;
+        }
+
+        match self.phase {
+            Phase::Settling if now >= self.step_start + SETTLE_TIME => {
+                self.phase = Phase::Measuring;
+                self.latency_samples.clear();
+                self.pending_reset_reports += 1;
+
+                true
+            }
+            Phase::Measuring
+                if now >= self.step_start + Duration::from_secs(self.config.step_duration_s) =>
+            {
+                self.phase = Phase::WaitingLossReport {
+                    deadline: now + LOSS_REPORT_TIMEOUT,
+                };
+
+                true
+            }
+            Phase::WaitingLossReport { deadline } if now >= deadline => {
+                self.finish_step(now, None);
+
+                false
+            }
+            _ => false,
+        }
+    }
+
+    // Returns the report only if it changed since the last call
+    pub fn take_report(&mut self) -> Option<BitrateBenchmarkReport> {
+        if !self.state_changed {
+            return None;
+        }
+        self.state_changed = false;
+
+        Some(BitrateBenchmarkReport {
+            state: self.state.clone(),
+            step_count: self.bitrates_mbps.len(),
+            steps: self.steps.clone(),
+            recommended_bitrate_mbps: self.recommended_bitrate_mbps(),
+        })
+    }
+
+    pub fn recommended_bitrate_mbps(&self) -> Option<u64> {
+        self.steps
+            .iter()
+            .filter(|step| step.passed)
+            .map(|step| step.bitrate_mbps)
+            .max()
+    }
+
+    fn finish_step(&mut self, now: Instant, loss_report: Option<VideoLossReport>) {
+        let latency_percentile_ms =
+            percentile(&self.latency_samples, self.config.latency_percentile)
+                .map(|latency| latency.as_secs_f32() * 1000.0);
+        let jitter_ms =
+            standard_deviation(&self.latency_samples).map(|jitter| jitter.as_secs_f32() * 1000.0);
+        let loss_percent = loss_report.map(|report| {
+            if report.frames_received > 0 {
+                report.frames_lost as f32 / report.frames_received as f32 * 100.0
+            } else {
+                100.0
+            }
+        });
+
+        let passed = latency_percentile_ms
+            .is_some_and(|latency| latency <= self.config.target_latency_ms as f32)
+            && loss_percent.is_some_and(|loss| loss <= self.config.max_loss_percent);
+
+        self.steps.push(BitrateBenchmarkStep {
+            bitrate_mbps: self.bitrates_mbps[self.step_index],
+            latency_percentile_ms,
+            jitter_ms,
+            loss_percent,
+            passed,
+        });
+
+        let consecutive_failed_steps = self
+            .steps
+            .iter()
+            .rev()
+            .take_while(|step| !step.passed)
+            .count();
+
+        self.step_index += 1;
+        if self.step_index < self.bitrates_mbps.len()
+            && consecutive_failed_steps < MAX_CONSECUTIVE_FAILED_STEPS
+        {
+            self.step_start = now;
+            self.phase = Phase::Settling;
+            self.state = BitrateBenchmarkState::Running {
+                bitrate_mbps: self.bitrates_mbps[self.step_index],
+            };
+        } else {
+            self.state = BitrateBenchmarkState::Finished;
+        }
+
+        self.state_changed = true;
+    }
+}
+
+fn percentile(samples: &[Duration], percentile: u64) -> Option<Duration> {
+    if samples.is_empty() {
+        return None;
+    }
+
+    let mut sorted = samples.to_vec();
+    sorted.sort();
+
+    let rank = (sorted.len() as u64 * u64::min(percentile, 100)).div_ceil(100) as usize;
+
+    Some(sorted[rank.saturating_sub(1)])
+}
+
+fn standard_deviation(samples: &[Duration]) -> Option<Duration> {
+    if samples.is_empty() {
+        return None;
+    }
+
+    let mean = samples.iter().map(|s| s.as_secs_f32()).sum::<f32>() / samples.len() as f32;
+    let variance = samples
+        .iter()
+        .map(|s| (s.as_secs_f32() - mean).powi(2))
+        .sum::<f32>()
+        / samples.len() as f32;
+
+    Some(Duration::from_secs_f32(variance.sqrt()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alvr_session::SessionConfig;
+
+    fn config() -> BitrateBenchmarkConfig {
+        let mut config = SessionConfig::default()
+            .to_settings()
+            .video
+            .bitrate
+            .benchmark;
+        config.min_bitrate_mbps = 100;
+        config.max_bitrate_mbps = 300;
+        config.step_mbps = 100;
+        config.step_duration_s = 2;
+        config.latency_percentile = 90;
+        config.target_latency_ms = 10;
+        config.max_loss_percent = 1.0;
+
+        config
+    }
+
+    // Runs a whole step, returns the time at which the next step starts
+    fn run_step(
+        benchmark: &mut BitrateBenchmark,
+        start: Instant,
+        latency: Duration,
+        loss_report: VideoLossReport,
+    ) -> Instant {
+        let measure_start = start + SETTLE_TIME;
+        assert!(benchmark.update(measure_start));
+        benchmark.report_video_loss(VideoLossReport::default(), measure_start);
+
+        for _ in 0..10 {
+            benchmark.report_network_latency(latency);
+        }
+
+        let step_end = start + Duration::from_secs(config().step_duration_s);
+        assert!(benchmark.update(step_end));
+        benchmark.report_video_loss(loss_report, step_end);
+
+        step_end
+    }
+
+    #[test]
+    fn test_percentile() {
+        let samples = (1..=10).map(Duration::from_millis).collect::<Vec<_>>();
+
+        assert_eq!(percentile(&samples, 90), Some(Duration::from_millis(9)));
+        assert_eq!(percentile(&samples, 95), Some(Duration::from_millis(10)));
+        assert_eq!(percentile(&samples, 0), Some(Duration::from_millis(1)));
+        assert_eq!(percentile(&[], 95), None);
+    }
+
+    #[test]
+    fn test_bitrate_steps() {
+        let mut benchmark = BitrateBenchmark::new(&config(), Instant::now());
+
+        assert_eq!(benchmark.take_report().unwrap().step_count, 3);
+        assert_eq!(benchmark.bitrate_bps(), Some(100e6));
+    }
+
+    #[test]
+    fn test_sweep_recommends_highest_passed_step() {
+        let mut now = Instant::now();
+        let mut benchmark = BitrateBenchmark::new(&config(), now);
+        let good = VideoLossReport {
+            frames_received: 1000,
+            frames_lost: 0,
+        };
+
+        now = run_step(&mut benchmark, now, Duration::from_millis(5), good);
+        assert_eq!(benchmark.bitrate_bps(), Some(200e6));
+
+        now = run_step(&mut benchmark, now, Duration::from_millis(8), good);
+        assert_eq!(benchmark.bitrate_bps(), Some(300e6));
+
+        run_step(
+            &mut benchmark,
+            now,
+            Duration::from_millis(5),
+            VideoLossReport {
+                frames_received: 1000,
+                frames_lost: 50,
+            },
+        );
+
+        let report = benchmark.take_report().unwrap();
+        assert!(matches!(report.state, BitrateBenchmarkState::Finished));
+        assert_eq!(report.steps.len(), 3);
+        assert!(!report.steps[2].passed);
+        assert_eq!(report.recommended_bitrate_mbps, Some(200));
+        assert!(benchmark.take_report().is_none());
+    }
+
+    #[test]
+    fn test_missing_loss_report_fails_step() {
+        let start = Instant::now();
+        let mut benchmark = BitrateBenchmark::new(&config(), start);
+
+        assert!(benchmark.update(start + SETTLE_TIME));
+        benchmark.report_video_loss(VideoLossReport::default(), start + SETTLE_TIME);
+        benchmark.report_network_latency(Duration::from_millis(5));
+
+        let step_end = start + Duration::from_secs(2);
+        assert!(benchmark.update(step_end));
+        assert!(!benchmark.update(step_end + LOSS_REPORT_TIMEOUT));
+
+        let report = benchmark.take_report().unwrap();
+        assert_eq!(report.steps.len(), 1);
+        assert_eq!(report.steps[0].loss_percent, None);
+        assert!(!report.steps[0].passed);
+        assert_eq!(benchmark.bitrate_bps(), Some(200e6));
+    }
+}
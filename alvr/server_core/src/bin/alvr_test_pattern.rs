@@ -0,0 +1,24 @@
+// Streams the test pattern without SteamVR. Launched by the dashboard, which stops it through the
+// web server like the driver
+use alvr_filesystem as afs;
+use std::env;
+
+fn main() {
+    let Some(filesystem_layout) = env::current_exe()
+        .ok()
+        .and_then(|exe| afs::filesystem_layout_from_dashboard_exe(&exe))
+    else {
+        eprintln!("Failed to find the ALVR installation");
+        return;
+    };
+
+    alvr_server_core::initialize_environment(filesystem_layout.clone());
+
+    let log_to_disk = alvr_server_core::settings().extra.logging.log_to_disk;
+    alvr_server_core::init_logging(
+        log_to_disk.then(|| filesystem_layout.session_log()),
+        Some(filesystem_layout.crash_log()),
+    );
+
+    alvr_server_core::stream_test_pattern();
+}
@@ -1,5 +1,7 @@
-use alvr_common::SlidingWindowAverage;
+use crate::encoder::EncoderPresets;
+use alvr_common::{SlidingWindowAverage, info};
 use alvr_events::BitrateDirectives;
+use alvr_packets::ThermalStatus;
 use alvr_session::{
     BitrateAdaptiveFramerateConfig, BitrateConfig, BitrateMode, settings_schema::Switch,
 };
@@ -9,10 +11,15 @@ use std::{
 };
 
 const UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+// Window used to measure the traffic sent besides the video frames
+const OVERHEAD_WINDOW: Duration = Duration::from_secs(2);
+// The bandwidth budget never brings the video bitrate below this
+const MIN_BUDGET_VIDEO_BITRATE_BPS: f32 = 1e6;
 
 pub struct DynamicEncoderParams {
     pub bitrate_bps: f32,
     pub framerate: f32,
+    pub presets: EncoderPresets,
 }
 
 pub struct BitrateManager {
@@ -28,7 +35,13 @@ pub struct BitrateManager {
     last_frame_instant: Instant,
     last_update_instant: Instant,
     dynamic_decoder_max_bytes_per_frame: f32,
+    thermal_status: ThermalStatus,
+    bitrate_override_bps: Option<f32>,
+    menu_bitrate_mbps: Option<u64>,
+    last_total_sent_bytes: Option<usize>,
+    overhead_bytes_history: VecDeque<(Instant, usize)>,
     previous_config: Option<BitrateConfig>,
+    previous_presets: Option<EncoderPresets>,
     update_needed: bool,
 }
 
@@ -54,7 +67,13 @@ impl BitrateManager {
             last_frame_instant: Instant::now(),
             last_update_instant: Instant::now(),
             dynamic_decoder_max_bytes_per_frame: f32::MAX,
+            thermal_status: ThermalStatus::None,
+            bitrate_override_bps: None,
+            menu_bitrate_mbps: None,
+            last_total_sent_bytes: None,
+            overhead_bytes_history: VecDeque::new(),
             previous_config: None,
+            previous_presets: None,
             update_needed: true,
         }
     }
@@ -94,6 +113,39 @@ impl BitrateManager {
         self.packet_bytes_history.push_back((timestamp, size_bytes));
     }
 
+    // total_sent_bytes is the counter of the bytes sent on all streams and on the control socket,
+    // read after sending a video frame of video_bytes. Everything else sent in between is audio,
+    // control and protocol overhead
+    pub fn report_frame_sent(&mut self, video_bytes: usize, total_sent_bytes: usize) {
+        let now = Instant::now();
+
+        if let Some(last_total_sent_bytes) = self.last_total_sent_bytes {
+            let overhead_bytes = total_sent_bytes
+                .wrapping_sub(last_total_sent_bytes)
+                .saturating_sub(video_bytes);
+            self.overhead_bytes_history.push_back((now, overhead_bytes));
+        }
+        self.last_total_sent_bytes = Some(total_sent_bytes);
+
+        while self
+            .overhead_bytes_history
+            .front()
+            .is_some_and(|(instant, _)| now - *instant > OVERHEAD_WINDOW)
+        {
+            self.overhead_bytes_history.pop_front();
+        }
+    }
+
+    fn overhead_bps(&self) -> f32 {
+        let overhead_bytes = self
+            .overhead_bytes_history
+            .iter()
+            .map(|(_, bytes)| bytes)
+            .sum::<usize>();
+
+        overhead_bytes as f32 * 8.0 / OVERHEAD_WINDOW.as_secs_f32()
+    }
+
     // decoder_latency is used to learn a suitable maximum bitrate bound to avoid decoder runaway
     // latency
     pub fn report_frame_latencies(
@@ -145,18 +197,53 @@ impl BitrateManager {
         }
     }
 
+    pub fn report_thermal_status(&mut self, status: ThermalStatus) {
+        if status != self.thermal_status {
+            self.thermal_status = status;
+            self.update_needed = true;
+        }
+    }
+
+    // Used by the bitrate benchmark to bypass the bitrate mode and the limiters
+    pub fn set_bitrate_override(&mut self, bitrate_bps: Option<f32>) {
+        if bitrate_bps != self.bitrate_override_bps {
+            self.bitrate_override_bps = bitrate_bps;
+            self.update_needed = true;
+        }
+    }
+
+    // Set from the in-headset menu. It replaces the constant bitrate or the adaptive maximum
+    // throughput for the rest of the connection, the settings are not changed
+    pub fn set_menu_bitrate(&mut self, bitrate_mbps: u64) {
+        if self.menu_bitrate_mbps != Some(bitrate_mbps) {
+            self.menu_bitrate_mbps = Some(bitrate_mbps);
+            self.update_needed = true;
+        }
+    }
+
+    // The encoder is recreated by the driver when the presets change
     pub fn get_encoder_params(
         &mut self,
         config: &BitrateConfig,
+        presets: &EncoderPresets,
     ) -> Option<(DynamicEncoderParams, BitrateDirectives)> {
         let now = Instant::now();
 
+        if self.previous_presets.as_ref() != Some(presets) {
+            // Logged so that bug reports show the concrete encoder parameters
+            info!("Encoder presets: {presets:?}");
+
+            self.previous_presets = Some(presets.clone());
+            self.update_needed = true;
+        }
+
         if self.previous_config.as_ref() != Some(config) {
             self.previous_config = Some(config.clone());
             // Continue method. Always update bitrate in this case
         } else if !self.update_needed
             && (now < self.last_update_instant + UPDATE_INTERVAL
-                || matches!(config.mode, BitrateMode::ConstantMbps(_)))
+                || (matches!(config.mode, BitrateMode::ConstantMbps(_))
+                    && !config.total_bandwidth_budget_mbps.enabled()))
         {
             return None;
         }
@@ -172,7 +259,25 @@ impl BitrateManager {
 
         let mut bitrate_directives = BitrateDirectives::default();
 
-        let bitrate_bps = match &config.mode {
+        let menu_mode = self
+            .menu_bitrate_mbps
+            .map(|bitrate_mbps| match &config.mode {
+                BitrateMode::ConstantMbps(_) => BitrateMode::ConstantMbps(bitrate_mbps),
+                BitrateMode::Adaptive { .. } => {
+                    let mut mode = config.mode.clone();
+                    if let BitrateMode::Adaptive {
+                        max_throughput_mbps,
+                        ..
+                    } = &mut mode
+                    {
+                        *max_throughput_mbps = Switch::Enabled(bitrate_mbps);
+                    }
+
+                    mode
+                }
+            });
+
+        let mut bitrate_bps = match menu_mode.as_ref().unwrap_or(&config.mode) {
             BitrateMode::ConstantMbps(bitrate_mbps) => *bitrate_mbps as f32 * 1e6,
             BitrateMode::Adaptive {
                 saturation_multiplier,
@@ -239,12 +344,43 @@ impl BitrateManager {
             }
         };
 
+        if let Switch::Enabled(config) = &config.thermal_throttling {
+            let multiplier = match self.thermal_status {
+                ThermalStatus::None => 1.0,
+                ThermalStatus::Light => config.light_multiplier,
+                ThermalStatus::Moderate => config.moderate_multiplier,
+                _ => config.severe_multiplier,
+            };
+
+            if multiplier < 1.0 {
+                bitrate_bps *= multiplier;
+                bitrate_directives.thermal_limiter_bps = Some(bitrate_bps);
+            }
+        }
+
+        if let Switch::Enabled(budget_mbps) = &config.total_bandwidth_budget_mbps {
+            let overhead_bps = self.overhead_bps();
+            let max_bps = f32::max(
+                *budget_mbps as f32 * 1e6 - overhead_bps,
+                MIN_BUDGET_VIDEO_BITRATE_BPS,
+            );
+            bitrate_bps = f32::min(bitrate_bps, max_bps);
+
+            bitrate_directives.stream_overhead_bps = Some(overhead_bps);
+            bitrate_directives.bandwidth_budget_limiter_bps = Some(max_bps);
+        }
+
+        if let Some(override_bps) = self.bitrate_override_bps {
+            bitrate_bps = override_bps;
+        }
+
         bitrate_directives.requested_bitrate_bps = bitrate_bps;
 
         Some((
             DynamicEncoderParams {
                 bitrate_bps,
                 framerate: 1.0 / f32::min(frame_interval.as_secs_f32(), 1.0),
+                presets: presets.clone(),
             },
             bitrate_directives,
         ))
@@ -0,0 +1,287 @@
+use alvr_packets::{ButtonEntry, ButtonValue};
+use alvr_session::{ButtonMacroAction, ButtonMacroConfig};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+fn is_pressed(value: ButtonValue) -> bool {
+    match value {
+        ButtonValue::Binary(value) => value,
+        ButtonValue::Scalar(value) => value > 0.5,
+    }
+}
+
+fn released_value(value: ButtonValue) -> ButtonValue {
+    match value {
+        ButtonValue::Binary(_) => ButtonValue::Binary(false),
+        ButtonValue::Scalar(_) => ButtonValue::Scalar(0.0),
+    }
+}
+
+struct ButtonMacro {
+    chord: Vec<u64>,
+    hold_duration: Duration,
+    action: ButtonMacroAction,
+    consume_buttons: bool,
+    // Set while all the buttons of the chord are pressed
+    complete_since: Option<Instant>,
+    triggered: bool,
+    // Set from when the chord is completed until all its buttons are released
+    consuming: bool,
+}
+
+// Detects the button chords held for the configured duration. The action is triggered once per
+// hold, and the chord must be released before it can trigger again.
+pub struct ButtonMacroManager {
+    macros: Vec<ButtonMacro>,
+    // Last value received for each button
+    values: HashMap<u64, ButtonValue>,
+    // Actions requested by the client gestures, returned by the next poll_actions()
+    queued_actions: Vec<ButtonMacroAction>,
+}
+
+impl ButtonMacroManager {
+    pub fn new(configs: &[ButtonMacroConfig]) -> Self {
+        let macros = configs
+            .iter()
+            .filter(|config| config.enabled && !config.chord.is_empty())
+            .map(|config| ButtonMacro {
+                chord: config
+                    .chord
+                    .iter()
+                    .map(|path| alvr_common::hash_string(path))
+                    .collect(),
+                hold_duration: Duration::from_millis(config.hold_duration_ms),
+                action: config.action,
+                consume_buttons: config.consume_buttons,
+                complete_since: None,
+                triggered: false,
+                consuming: false,
+            })
+            .collect();
+
+        Self {
+            macros,
+            values: HashMap::new(),
+            queued_actions: vec![],
+        }
+    }
+
+    // Updates the chords and returns the entries to be sent to SteamVR. Consumed buttons are
+    // released when their chord is completed, since they were already sent as pressed.
+    pub fn process_buttons(&mut self, entries: Vec<ButtonEntry>, now: Instant) -> Vec<ButtonEntry> {
+        for entry in &entries {
+            self.values.insert(entry.path_id, entry.value);
+        }
+
+        let mut released_entries = vec![];
+        for button_macro in &mut self.macros {
+            let pressed_count = button_macro
+                .chord
+                .iter()
+                .filter(|id| self.values.get(id).is_some_and(|value| is_pressed(*value)))
+                .count();
+
+            if pressed_count == button_macro.chord.len() {
+                if button_macro.complete_since.is_none() {
+                    button_macro.complete_since = Some(now);
+
+                    if button_macro.consume_buttons && !button_macro.consuming {
+                        button_macro.consuming = true;
+                        released_entries.extend(button_macro.chord.iter().map(|id| ButtonEntry {
+                            path_id: *id,
+                            value: released_value(self.values[id]),
+                        }));
+                    }
+                }
+            } else {
+                button_macro.complete_since = None;
+                button_macro.triggered = false;
+
+                if pressed_count == 0 {
+                    button_macro.consuming = false;
+                }
+            }
+        }
+
+        let consumed_ids = self
+            .macros
+            .iter()
+            .filter(|button_macro| button_macro.consuming)
+            .flat_map(|button_macro| button_macro.chord.iter().copied())
+            .collect::<Vec<_>>();
+
+        entries
+            .into_iter()
+            .filter(|entry| !consumed_ids.contains(&entry.path_id))
+            .chain(released_entries)
+            .collect()
+    }
+
+    // Returns the actions of the chords held for long enough
+    pub fn poll_actions(&mut self, now: Instant) -> Vec<ButtonMacroAction> {
+        let mut actions = std::mem::take(&mut self.queued_actions);
+        for button_macro in &mut self.macros {
+            if !button_macro.triggered
+                && button_macro
+                    .complete_since
+                    .is_some_and(|since| now >= since + button_macro.hold_duration)
+            {
+                button_macro.triggered = true;
+                actions.push(button_macro.action);
+            }
+        }
+
+        actions
+    }
+
+    pub fn queue_action(&mut self, action: ButtonMacroAction) {
+        self.queued_actions.push(action);
+    }
+
+    // Time at which poll_actions should be called next
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.macros
+            .iter()
+            .filter(|button_macro| !button_macro.triggered)
+            .filter_map(|button_macro| {
+                button_macro
+                    .complete_since
+                    .map(|since| since + button_macro.hold_duration)
+            })
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOLD: Duration = Duration::from_millis(500);
+
+    fn config(chord: &[&str], consume_buttons: bool) -> ButtonMacroConfig {
+        ButtonMacroConfig {
+            enabled: true,
+            display_name: String::new(),
+            chord: chord.iter().map(|path| path.to_string()).collect(),
+            hold_duration_ms: HOLD.as_millis() as u64,
+            action: ButtonMacroAction::Recenter,
+            consume_buttons,
+        }
+    }
+
+    fn binary(path: &str, pressed: bool) -> ButtonEntry {
+        ButtonEntry {
+            path_id: alvr_common::hash_string(path),
+            value: ButtonValue::Binary(pressed),
+        }
+    }
+
+    fn summary(entries: &[ButtonEntry]) -> Vec<(u64, bool)> {
+        entries
+            .iter()
+            .map(|entry| (entry.path_id, is_pressed(entry.value)))
+            .collect()
+    }
+
+    #[test]
+    fn test_partial_chord_does_not_trigger() {
+        let mut manager = ButtonMacroManager::new(&[config(&["/a", "/b"], false)]);
+        let now = Instant::now();
+
+        manager.process_buttons(vec![binary("/a", true)], now);
+        assert_eq!(manager.next_deadline(), None);
+        assert!(manager.poll_actions(now + HOLD * 2).is_empty());
+
+        // Scalar values count as pressed above half
+        manager.process_buttons(
+            vec![ButtonEntry {
+                path_id: alvr_common::hash_string("/b"),
+                value: ButtonValue::Scalar(0.3),
+            }],
+            now,
+        );
+        assert!(manager.poll_actions(now + HOLD * 2).is_empty());
+    }
+
+    #[test]
+    fn test_hold_timing() {
+        let mut manager = ButtonMacroManager::new(&[config(&["/a", "/b"], false)]);
+        let now = Instant::now();
+
+        manager.process_buttons(vec![binary("/a", true), binary("/b", true)], now);
+        assert_eq!(manager.next_deadline(), Some(now + HOLD));
+        assert!(manager.poll_actions(now + HOLD / 2).is_empty());
+        assert_eq!(
+            manager.poll_actions(now + HOLD),
+            [ButtonMacroAction::Recenter]
+        );
+
+        // Triggered once per hold
+        assert!(manager.poll_actions(now + HOLD * 3).is_empty());
+        assert_eq!(manager.next_deadline(), None);
+    }
+
+    #[test]
+    fn test_release_before_hold_resets() {
+        let mut manager = ButtonMacroManager::new(&[config(&["/a", "/b"], false)]);
+        let now = Instant::now();
+
+        manager.process_buttons(vec![binary("/a", true), binary("/b", true)], now);
+        manager.process_buttons(vec![binary("/a", false)], now + HOLD / 2);
+        assert!(manager.poll_actions(now + HOLD).is_empty());
+
+        // The hold restarts when the chord is completed again, in any order
+        let later = now + HOLD * 2;
+        manager.process_buttons(vec![binary("/b", false)], later);
+        manager.process_buttons(vec![binary("/b", true)], later);
+        manager.process_buttons(vec![binary("/a", true)], later);
+        assert_eq!(manager.poll_actions(later + HOLD).len(), 1);
+
+        // Releasing a single button is enough to rearm the chord
+        manager.process_buttons(vec![binary("/b", false)], later + HOLD);
+        manager.process_buttons(vec![binary("/b", true)], later + HOLD);
+        assert_eq!(manager.poll_actions(later + HOLD * 2).len(), 1);
+    }
+
+    #[test]
+    fn test_buttons_pass_through() {
+        let mut manager = ButtonMacroManager::new(&[config(&["/a", "/b"], false)]);
+        let now = Instant::now();
+
+        let entries = manager.process_buttons(vec![binary("/a", true), binary("/b", true)], now);
+        assert_eq!(
+            summary(&entries),
+            summary(&[binary("/a", true), binary("/b", true)])
+        );
+    }
+
+    #[test]
+    fn test_buttons_are_consumed() {
+        let mut manager = ButtonMacroManager::new(&[config(&["/a", "/b"], true)]);
+        let now = Instant::now();
+
+        // Partial chords are sent normally
+        let entries = manager.process_buttons(vec![binary("/a", true), binary("/c", true)], now);
+        assert_eq!(entries.len(), 2);
+
+        // The button already sent as pressed is released
+        let entries = manager.process_buttons(vec![binary("/b", true)], now);
+        assert_eq!(
+            summary(&entries),
+            summary(&[binary("/a", false), binary("/b", false)])
+        );
+
+        // Dropped until the whole chord is released, other buttons are not affected
+        let entries = manager.process_buttons(vec![binary("/a", false), binary("/c", false)], now);
+        assert_eq!(summary(&entries), summary(&[binary("/c", false)]));
+        let entries = manager.process_buttons(vec![binary("/a", true)], now);
+        assert!(entries.is_empty());
+        manager.process_buttons(vec![binary("/a", false)], now);
+        manager.process_buttons(vec![binary("/b", false)], now);
+
+        let entries = manager.process_buttons(vec![binary("/a", true)], now);
+        assert_eq!(summary(&entries), summary(&[binary("/a", true)]));
+    }
+}
@@ -72,6 +72,7 @@ pub enum AlvrEvent {
     RestartPending,
     ShutdownPending,
     ProximityState(bool),
+    EncodingPaused(bool),
 }
 
 #[repr(C)]
@@ -266,8 +267,8 @@ pub unsafe extern "C" fn alvr_poll_event(out_event: *mut AlvrEvent, timeout_ns:
                     is_plugged: battery.is_plugged,
                 });
             },
-            ServerCoreEvent::PlayspaceSync(bounds) => unsafe {
-                *out_event = AlvrEvent::PlayspaceSync(bounds.to_array())
+            ServerCoreEvent::PlayspaceSync { area, .. } => unsafe {
+                *out_event = AlvrEvent::PlayspaceSync(area.to_array())
             },
             ServerCoreEvent::LocalViewParams(config) => unsafe {
                 *out_event = AlvrEvent::LocalViewParams([
@@ -293,10 +294,17 @@ pub unsafe extern "C" fn alvr_poll_event(out_event: *mut AlvrEvent, timeout_ns:
                 *out_event = AlvrEvent::ShutdownPending;
             },
             ServerCoreEvent::GameRenderLatencyFeedback(_)
+            | ServerCoreEvent::SetTestPattern(_)
+            | ServerCoreEvent::SetMirrorEnabled(_)
+            | ServerCoreEvent::SetIdleFrameRate(_)
+            | ServerCoreEvent::SetSteamvrSettings(_)
             | ServerCoreEvent::SetOpenvrProperty { .. } => {} // implementation not needed
             ServerCoreEvent::ProximityState(headset_is_worn) => unsafe {
                 *out_event = AlvrEvent::ProximityState(headset_is_worn);
             },
+            ServerCoreEvent::SetEncodingPaused { paused, .. } => unsafe {
+                *out_event = AlvrEvent::EncodingPaused(paused);
+            },
         }
 
         true
@@ -423,6 +431,14 @@ pub unsafe extern "C" fn alvr_set_video_config_nals(
     }
 }
 
+/// period_frames is 0 if the encoder doesn't use intra refresh
+#[unsafe(no_mangle)]
+pub extern "C" fn alvr_set_intra_refresh_period(period_frames: u32) {
+    if let Some(context) = &*SERVER_CORE_CONTEXT.read() {
+        context.set_intra_refresh_period((period_frames > 0).then_some(period_frames));
+    }
+}
+
 /// global_view_params must be an array of length 2
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn alvr_send_video_nal(
@@ -446,6 +462,7 @@ pub unsafe extern "C" fn alvr_send_video_nal(
             Duration::from_nanos(timestamp_ns),
             global_view_params,
             is_idr,
+            None,
             buffer.to_vec(),
         );
     }
@@ -480,6 +497,16 @@ pub extern "C" fn alvr_report_composed(timestamp_ns: u64, offset_ns: u64) {
     }
 }
 
+#[unsafe(no_mangle)]
+pub extern "C" fn alvr_report_server_reprojection(timestamp_ns: u64, pose_timestamp_ns: u64) {
+    if let Some(context) = &*SERVER_CORE_CONTEXT.read() {
+        context.report_server_reprojection(
+            Duration::from_nanos(timestamp_ns),
+            Duration::from_nanos(pose_timestamp_ns),
+        );
+    }
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn alvr_report_present(timestamp_ns: u64, offset_ns: u64) {
     if let Some(context) = &*SERVER_CORE_CONTEXT.read() {
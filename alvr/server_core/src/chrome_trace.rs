@@ -0,0 +1,200 @@
+use crate::ClockEstimate;
+use alvr_common::TraceSpan;
+use serde_json::{self as json, Value};
+use std::time::Duration;
+
+const MIN_CLOCK_CONFIDENCE: f32 = 0.5;
+
+const SERVER_PID: u32 = 1;
+const CLIENT_PID: u32 = 2;
+
+fn metadata_event(name: &str, pid: u32, tid: usize, value: &str) -> Value {
+    json::json!({
+        "name": name,
+        "ph": "M",
+        "pid": pid,
+        "tid": tid,
+        "args": { "name": value },
+    })
+}
+
+fn process_events(process_name: &str, pid: u32, spans: &[(Duration, &TraceSpan)]) -> Vec<Value> {
+    let mut events = vec![metadata_event("process_name", pid, 0, process_name)];
+
+    // One track per stage, in order of first appearance
+    let mut stages: Vec<&str> = vec![];
+    for (start, span) in spans {
+        let tid = match stages.iter().position(|stage| *stage == span.stage) {
+            Some(index) => index + 1,
+            None => {
+                stages.push(&span.stage);
+                events.push(metadata_event(
+                    "thread_name",
+                    pid,
+                    stages.len(),
+                    &span.stage,
+                ));
+
+                stages.len()
+            }
+        };
+
+        let mut event = json::json!({
+            "name": span.stage,
+            "cat": process_name,
+            "pid": pid,
+            "tid": tid,
+            "ts": start.as_nanos() as f64 / 1000.0,
+            "args": { "frame_ns": span.target_timestamp.as_nanos() as u64 },
+        });
+        if span.duration.is_zero() {
+            event["ph"] = "i".into();
+            event["s"] = "t".into();
+        } else {
+            event["ph"] = "X".into();
+            event["dur"] = (span.duration.as_nanos() as f64 / 1000.0).into();
+        }
+
+        events.push(event);
+    }
+
+    events
+}
+
+// Merges the two halves of a capture into a Chrome trace, which can also be opened with Perfetto.
+// The server spans are converted to the client clock. The returned flag is false if there was no
+// confident clock estimate, in which case each side starts at zero and they are not aligned.
+pub fn merge_traces(
+    server_spans: &[TraceSpan],
+    client_spans: &[TraceSpan],
+    clock_estimate: Option<&ClockEstimate>,
+) -> (Value, bool) {
+    let estimate = clock_estimate.filter(|e| e.confidence >= MIN_CLOCK_CONFIDENCE);
+
+    let mut server_spans = server_spans
+        .iter()
+        .map(|span| {
+            let start = estimate.map_or(span.start, |e| e.client_time(span.start));
+            (start, span)
+        })
+        .collect::<Vec<_>>();
+    let mut client_spans = client_spans
+        .iter()
+        .map(|span| (span.start, span))
+        .collect::<Vec<_>>();
+
+    let min_start = |spans: &[(Duration, &TraceSpan)]| spans.iter().map(|(start, _)| *start).min();
+    let rebase = |spans: &mut [(Duration, &TraceSpan)], origin: Duration| {
+        for (start, _) in spans {
+            *start = start.saturating_sub(origin);
+        }
+    };
+    if estimate.is_some() {
+        let origin = min_start(&server_spans)
+            .into_iter()
+            .chain(min_start(&client_spans))
+            .min()
+            .unwrap_or_default();
+        rebase(&mut server_spans, origin);
+        rebase(&mut client_spans, origin);
+    } else {
+        let server_origin = min_start(&server_spans).unwrap_or_default();
+        let client_origin = min_start(&client_spans).unwrap_or_default();
+        rebase(&mut server_spans, server_origin);
+        rebase(&mut client_spans, client_origin);
+    }
+
+    let mut events = process_events("Streamer", SERVER_PID, &server_spans);
+    events.extend(process_events("Client", CLIENT_PID, &client_spans));
+
+    let trace = json::json!({
+        "traceEvents": events,
+        "displayTimeUnit": "ms",
+    });
+
+    (trace, estimate.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(stage: &str, start_ms: u64, duration_ms: u64) -> TraceSpan {
+        TraceSpan {
+            stage: stage.into(),
+            start: Duration::from_millis(start_ms),
+            duration: Duration::from_millis(duration_ms),
+            target_timestamp: Duration::from_millis(1),
+        }
+    }
+
+    fn events_named<'a>(trace: &'a Value, name: &str) -> Vec<&'a Value> {
+        trace["traceEvents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|event| event["name"] == name)
+            .collect()
+    }
+
+    #[test]
+    fn test_aligned_merge() {
+        // The client clock is 1000s ahead
+        let estimate = ClockEstimate {
+            server_time: Duration::from_secs(10),
+            offset_ns: 1_000_000_000_000,
+            drift_ppm: 0.0,
+            uncertainty: Duration::from_micros(100),
+            confidence: 1.0,
+        };
+
+        let (trace, aligned) = merge_traces(
+            &[span("Encode", 10_000, 4)],
+            &[span("Decode", 1_010_010, 3), span("Receive", 1_010_008, 0)],
+            Some(&estimate),
+        );
+        assert!(aligned);
+
+        let encode = events_named(&trace, "Encode")[0];
+        assert_eq!(encode["ph"], "X");
+        assert_eq!(encode["ts"], 0.0);
+        assert_eq!(encode["dur"], 4000.0);
+        assert_eq!(encode["pid"], SERVER_PID);
+
+        let decode = events_named(&trace, "Decode")[0];
+        assert_eq!(decode["ts"], 10_000.0);
+        assert_eq!(decode["pid"], CLIENT_PID);
+
+        let receive = events_named(&trace, "Receive")[0];
+        assert_eq!(receive["ph"], "i");
+        assert_eq!(receive["ts"], 8000.0);
+        assert_ne!(receive["tid"], decode["tid"]);
+
+        assert_eq!(events_named(&trace, "process_name").len(), 2);
+        assert_eq!(events_named(&trace, "thread_name").len(), 3);
+    }
+
+    #[test]
+    fn test_unaligned_merge() {
+        let estimate = ClockEstimate {
+            server_time: Duration::ZERO,
+            offset_ns: 0,
+            drift_ppm: 0.0,
+            uncertainty: Duration::from_millis(50),
+            confidence: 0.1,
+        };
+
+        let (trace, aligned) = merge_traces(
+            &[span("Encode", 10_000, 4), span("Encode", 10_020, 4)],
+            &[span("Decode", 5_000_000, 3)],
+            Some(&estimate),
+        );
+        assert!(!aligned);
+
+        let encodes = events_named(&trace, "Encode");
+        assert_eq!(encodes[0]["ts"], 0.0);
+        assert_eq!(encodes[1]["ts"], 20_000.0);
+        assert_eq!(encodes[0]["tid"], encodes[1]["tid"]);
+        assert_eq!(events_named(&trace, "Decode")[0]["ts"], 0.0);
+    }
+}
@@ -0,0 +1,425 @@
+use std::{
+    collections::VecDeque,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+// Samples are grouped in windows and only the one with the lowest round trip time is kept, since
+// it is the least affected by queuing delays
+const WINDOW_DURATION: Duration = Duration::from_secs(1);
+// Number of windows used to fit the offset and drift
+const HISTORY_WINDOWS: usize = 60;
+const MIN_DRIFT_WINDOWS: usize = 10;
+const MIN_CONFIDENT_WINDOWS: usize = 5;
+// A window that disagrees with the fit by more than this is either a congested window or a clock
+// step. It is considered a step once several consecutive windows agree with each other.
+const STEP_THRESHOLD: Duration = Duration::from_millis(5);
+const STEP_CONFIRMATION_WINDOWS: usize = 3;
+// Offset uncertainty at which the confidence reaches zero
+const MAX_UNCERTAINTY: Duration = Duration::from_millis(10);
+
+static SERVER_CLOCK_EPOCH: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+// Monotonic server timestamp used in the time sync packets
+pub fn server_time() -> Duration {
+    SERVER_CLOCK_EPOCH.elapsed()
+}
+
+#[derive(Clone, Copy, Debug)]
+struct OffsetSample {
+    // Server time at the midpoint of the round trip
+    server_time: Duration,
+    // client time - server time
+    offset_ns: i64,
+    rtt: Duration,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ClockEstimate {
+    // Server time of the last window
+    pub server_time: Duration,
+    // client time - server time, at server_time
+    pub offset_ns: i64,
+    pub drift_ppm: f64,
+    // Bound of the offset error, from the round trip time and the residuals of the fit
+    pub uncertainty: Duration,
+    // Between 0 and 1. Consumers should ignore estimates with low confidence
+    pub confidence: f32,
+}
+
+impl ClockEstimate {
+    // Converts a server timestamp to the client clock, correcting the drift since the estimate
+    pub fn client_time(&self, server_time: Duration) -> Duration {
+        let elapsed_s = server_time.as_secs_f64() - self.server_time.as_secs_f64();
+        let offset_ns = self.offset_ns as f64 + self.drift_ppm * 1000.0 * elapsed_s;
+
+        Duration::from_nanos((server_time.as_nanos() as i64 + offset_ns as i64).max(0) as u64)
+    }
+
+    // Inverse of client_time(). The drift is evaluated at the uncorrected server time, the error is
+    // negligible
+    pub fn server_time_of(&self, client_time: Duration) -> Duration {
+        let approx_server_s = (client_time.as_nanos() as i64 - self.offset_ns) as f64 * 1e-9;
+        let elapsed_s = approx_server_s - self.server_time.as_secs_f64();
+        let offset_ns = self.offset_ns as f64 + self.drift_ppm * 1000.0 * elapsed_s;
+
+        Duration::from_nanos((client_time.as_nanos() as i64 - offset_ns as i64).max(0) as u64)
+    }
+}
+
+// Server time of an instant, in the clock of server_time()
+pub fn server_time_at(instant: Instant) -> Duration {
+    instant.saturating_duration_since(*SERVER_CLOCK_EPOCH)
+}
+
+#[derive(Clone, Copy)]
+struct Fit {
+    base_time: Duration,
+    base_offset_ns: f64,
+    // Nanoseconds per second
+    slope: f64,
+    residual_rms_ns: f64,
+}
+
+impl Fit {
+    fn offset_at(&self, server_time: Duration) -> f64 {
+        let dt = if server_time >= self.base_time {
+            (server_time - self.base_time).as_secs_f64()
+        } else {
+            -(self.base_time - server_time).as_secs_f64()
+        };
+
+        self.base_offset_ns + self.slope * dt
+    }
+}
+
+// PTP-like estimation of the offset and drift between the server and client clocks
+#[derive(Default)]
+pub struct ClockEstimator {
+    current_window: Option<(Duration, OffsetSample)>,
+    windows: VecDeque<OffsetSample>,
+    step_candidates: Vec<OffsetSample>,
+    fit: Option<Fit>,
+}
+
+impl ClockEstimator {
+    // request_time and response_time are server timestamps, client_time is the client timestamp
+    // taken when the request was received
+    pub fn add_sample(
+        &mut self,
+        request_time: Duration,
+        client_time: Duration,
+        response_time: Duration,
+    ) {
+        let Some(rtt) = response_time.checked_sub(request_time) else {
+            return;
+        };
+        let server_time = request_time + rtt / 2;
+        let sample = OffsetSample {
+            server_time,
+            offset_ns: client_time.as_nanos() as i64 - server_time.as_nanos() as i64,
+            rtt,
+        };
+
+        match &mut self.current_window {
+            Some((start, best)) if server_time.saturating_sub(*start) < WINDOW_DURATION => {
+                if sample.rtt < best.rtt {
+                    *best = sample;
+                }
+            }
+            _ => {
+                if let Some((_, best)) = self.current_window.take() {
+                    self.push_window(best);
+                }
+                self.current_window = Some((server_time, sample));
+            }
+        }
+    }
+
+    fn push_window(&mut self, sample: OffsetSample) {
+        if let Some(fit) = &self.fit {
+            let error = (sample.offset_ns as f64 - fit.offset_at(sample.server_time)).abs();
+
+            if error > STEP_THRESHOLD.as_nanos() as f64 {
+                let consistent = self.step_candidates.last().is_none_or(|last| {
+                    (sample.offset_ns - last.offset_ns).unsigned_abs()
+                        <= STEP_THRESHOLD.as_nanos() as u64
+                });
+                if !consistent {
+                    self.step_candidates.clear();
+                }
+                self.step_candidates.push(sample);
+
+                if self.step_candidates.len() < STEP_CONFIRMATION_WINDOWS {
+                    return;
+                }
+
+                // The clock was stepped, the old history is not valid anymore
+                self.windows.clear();
+                self.windows.extend(self.step_candidates.drain(..));
+                self.refit();
+
+                return;
+            }
+        }
+
+        self.step_candidates.clear();
+
+        self.windows.push_back(sample);
+        if self.windows.len() > HISTORY_WINDOWS {
+            self.windows.pop_front();
+        }
+
+        self.refit();
+    }
+
+    // Least squares fit of the offset over time. The drift is estimated only when the history is
+    // long enough to not be dominated by the noise.
+    fn refit(&mut self) {
+        let Some(first) = self.windows.front() else {
+            self.fit = None;
+            return;
+        };
+        let base_time = first.server_time;
+        let base_offset = first.offset_ns;
+
+        let points = self
+            .windows
+            .iter()
+            .map(|s| {
+                (
+                    s.server_time.saturating_sub(base_time).as_secs_f64(),
+                    (s.offset_ns - base_offset) as f64,
+                )
+            })
+            .collect::<Vec<_>>();
+        let count = points.len() as f64;
+
+        let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / count;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / count;
+
+        let variance_x = points
+            .iter()
+            .map(|(x, _)| (x - mean_x).powi(2))
+            .sum::<f64>();
+        let slope = if points.len() >= MIN_DRIFT_WINDOWS && variance_x > 0.0 {
+            points
+                .iter()
+                .map(|(x, y)| (x - mean_x) * (y - mean_y))
+                .sum::<f64>()
+                / variance_x
+        } else {
+            0.0
+        };
+        let intercept = mean_y - slope * mean_x;
+
+        let residual_rms_ns = (points
+            .iter()
+            .map(|(x, y)| (y - (intercept + slope * x)).powi(2))
+            .sum::<f64>()
+            / count)
+            .sqrt();
+
+        self.fit = Some(Fit {
+            base_time,
+            base_offset_ns: base_offset as f64 + intercept,
+            slope,
+            residual_rms_ns,
+        });
+    }
+
+    pub fn estimate(&self) -> Option<ClockEstimate> {
+        let fit = self.fit?;
+        let last = self.windows.back()?;
+
+        let min_rtt = self.windows.iter().map(|s| s.rtt).min()?;
+        let uncertainty = min_rtt / 2 + Duration::from_nanos(fit.residual_rms_ns as u64);
+
+        let history_factor = f32::min(
+            self.windows.len() as f32 / MIN_CONFIDENT_WINDOWS as f32,
+            1.0,
+        );
+        let uncertainty_factor = 1.0
+            - f32::min(
+                uncertainty.as_secs_f32() / MAX_UNCERTAINTY.as_secs_f32(),
+                1.0,
+            );
+
+        Some(ClockEstimate {
+            server_time: last.server_time,
+            offset_ns: fit.offset_at(last.server_time) as i64,
+            drift_ppm: fit.slope / 1000.0,
+            uncertainty,
+            confidence: history_factor * uncertainty_factor,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_INTERVAL: Duration = Duration::from_millis(200);
+
+    // Deterministic network delays: mostly around 1ms, with occasional spikes up to 30ms
+    struct Network(u64);
+
+    impl Network {
+        fn next_delay(&mut self) -> Duration {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let r = (self.0 >> 33) as f64 / (1u64 << 31) as f64;
+
+            if r < 0.7 {
+                Duration::from_secs_f64(0.001 + r * 0.001)
+            } else {
+                Duration::from_secs_f64(0.001 + (r - 0.7) * 0.1)
+            }
+        }
+    }
+
+    // Runs the exchanges for the given duration. client_clock maps the server time to the client
+    // time.
+    fn simulate(
+        estimator: &mut ClockEstimator,
+        network: &mut Network,
+        start: Duration,
+        duration: Duration,
+        client_clock: impl Fn(Duration) -> Duration,
+    ) -> Duration {
+        let mut time = start;
+        while time < start + duration {
+            let receive_time = time + network.next_delay();
+            let response_time = receive_time + network.next_delay();
+            estimator.add_sample(time, client_clock(receive_time), response_time);
+
+            time += SAMPLE_INTERVAL;
+        }
+
+        time
+    }
+
+    fn offset_error_ms(estimator: &ClockEstimator, time: Duration, expected: Duration) -> f64 {
+        let estimated = estimator.estimate().unwrap().client_time(time);
+
+        (estimated.as_secs_f64() - expected.as_secs_f64()).abs() * 1000.0
+    }
+
+    #[test]
+    fn test_fixed_offset() {
+        let offset = Duration::from_secs(1000);
+        let mut estimator = ClockEstimator::default();
+        let mut network = Network(1);
+
+        let end = simulate(
+            &mut estimator,
+            &mut network,
+            Duration::from_secs(10),
+            Duration::from_secs(30),
+            |t| t + offset,
+        );
+
+        let estimate = estimator.estimate().unwrap();
+        assert!(offset_error_ms(&estimator, end, end + offset) < 1.0);
+        assert!(estimate.drift_ppm.abs() < 20.0);
+        assert!(estimate.confidence > 0.5);
+    }
+
+    #[test]
+    fn test_drifting_clock() {
+        const DRIFT_PPM: f64 = 100.0;
+        let clock = |t: Duration| {
+            Duration::from_secs(50)
+                + Duration::from_secs_f64(t.as_secs_f64() * (1.0 + DRIFT_PPM * 1e-6))
+        };
+        let mut estimator = ClockEstimator::default();
+        let mut network = Network(2);
+
+        let end = simulate(
+            &mut estimator,
+            &mut network,
+            Duration::ZERO,
+            Duration::from_secs(60),
+            clock,
+        );
+
+        let estimate = estimator.estimate().unwrap();
+        assert!((estimate.drift_ppm - DRIFT_PPM).abs() < 10.0);
+        assert!(offset_error_ms(&estimator, end, clock(end)) < 1.0);
+
+        // The conversion back to the server clock is the inverse
+        let time = end + Duration::from_secs(10);
+        let round_trip = estimate.server_time_of(estimate.client_time(time));
+        assert!((round_trip.as_secs_f64() - time.as_secs_f64()).abs() < 1e-6);
+        let error_ms = (estimate.server_time_of(clock(time)).as_secs_f64() - time.as_secs_f64())
+            .abs()
+            * 1000.0;
+        assert!(error_ms < 2.0);
+    }
+
+    #[test]
+    fn test_stepped_clock() {
+        let offset = Duration::from_secs(20);
+        let mut estimator = ClockEstimator::default();
+        let mut network = Network(3);
+
+        let time = simulate(
+            &mut estimator,
+            &mut network,
+            Duration::ZERO,
+            Duration::from_secs(30),
+            |t| t + offset,
+        );
+
+        // The client clock jumps forward by 50ms
+        let stepped_offset = offset + Duration::from_millis(50);
+        let end = simulate(
+            &mut estimator,
+            &mut network,
+            time,
+            Duration::from_secs(10),
+            |t| t + stepped_offset,
+        );
+
+        assert!(offset_error_ms(&estimator, end, end + stepped_offset) < 1.0);
+        assert!(estimator.estimate().unwrap().confidence > 0.5);
+    }
+
+    #[test]
+    fn test_congestion_is_ignored() {
+        let offset = Duration::from_secs(5);
+        let mut estimator = ClockEstimator::default();
+        let mut network = Network(4);
+
+        let mut time = simulate(
+            &mut estimator,
+            &mut network,
+            Duration::ZERO,
+            Duration::from_secs(20),
+            |t| t + offset,
+        );
+
+        // A single congested window with a very asymmetric delay
+        for _ in 0..5 {
+            let receive_time = time + Duration::from_millis(80);
+            estimator.add_sample(
+                time,
+                receive_time + offset,
+                receive_time + Duration::from_millis(1),
+            );
+            time += SAMPLE_INTERVAL;
+        }
+
+        let end = simulate(
+            &mut estimator,
+            &mut network,
+            time,
+            Duration::from_secs(2),
+            |t| t + offset,
+        );
+
+        assert!(offset_error_ms(&estimator, end, end + offset) < 1.0);
+    }
+}
@@ -1,51 +1,78 @@
 use crate::{
     ConnectionContext, FILESYSTEM_LAYOUT, SESSION_MANAGER, ServerCoreEvent,
+    analog_input::AnalogInputProcessor,
     bitrate::BitrateManager,
+    button_macros::ButtonMacroManager,
+    chrome_trace, clock_sync, encoder,
     hand_gestures::HandGestureManager,
+    idle_throttling::IdleDetector,
     input_mapping::ButtonMappingManager,
+    metrics,
+    peripheral_input::PeripheralInputInjector,
     sockets::WelcomeSocket,
     statistics::StatisticsManager,
     tracking::{self, TrackingManager},
+    wifi_advisor::WifiAdvisor,
 };
 use alvr_adb::{WiredConnection, WiredConnectionStatus};
 use alvr_common::{
-    AnyhowToCon, BUTTON_INFO, CONTROLLER_PROFILE_INFO, ConResult, ConnectionError, ConnectionState,
-    LifecycleState, QUEST_CONTROLLER_PROFILE_PATH, con_bail, dbg_connection, debug, error,
+    AnyhowToCon, BUTTON_INFO, CLIPBOARD_POLL_INTERVAL, CONTROLLER_PROFILE_INFO, ClipboardSync,
+    ConResult, ConnectionError, ConnectionState, LifecycleState, QUEST_CONTROLLER_PROFILE_PATH,
+    RelaxedAtomic, con_bail, dbg_connection, debug, error,
     glam::{UVec2, Vec2},
     info,
-    parking_lot::{Condvar, Mutex, RwLock},
+    parking_lot::{Condvar, Mutex, RwLock, RwLockWriteGuard},
     settings_schema::Switch,
     warn,
 };
-use alvr_events::{AdbEvent, ButtonEvent, EventType};
+use alvr_events::{AdbEvent, BitrateBenchmarkState, ButtonEvent, EventType};
 use alvr_packets::{
-    AUDIO, ClientConnectionResult, ClientConnectionsAction, ClientControlPacket, ClientStatistics,
-    HAPTICS, NegotiatedStreamingConfig, NegotiatedStreamingConfigExt, RealTimeConfig, STATISTICS,
-    ServerControlPacket, StreamConfigPacket, TRACKING, TrackingData, VIDEO, VideoPacketHeader,
+    AUDIO, ButtonValue, ClientConnectionResult, ClientConnectionsAction, ClientControlPacket,
+    ClientStatistics, DEPTH, HAPTICS, InHeadsetMenuAction, MOTION_VECTORS,
+    NegotiatedStreamingConfig, NegotiatedStreamingConfigExt, PERIPHERAL_INPUT, PeripheralInput,
+    RealTimeConfig, STATISTICS, ServerControlPacket, StreamConfigPacket, TRACKING, ThermalStatus,
+    TrackingData, TrackingSpace, VIDEO, VideoPacketHeader,
 };
+use alvr_server_io::ServerSessionManager;
 use alvr_session::{
-    BodyTrackingSinkConfig, CodecType, ControllersEmulationMode, FrameSize, H264Profile,
-    OpenvrConfig, SessionConfig, SocketProtocol,
+    ApplyScope, BitrateMode, BitrateModeDefaultVariant, BodyTrackingSinkConfig, ButtonMacroAction,
+    ChromaSubsampling, ClientsidePostProcessingSharpeningModeDefaultVariant, CodecType,
+    ControllersEmulationMode, ExternalTrackerRole, FoveatedEncodingMode, FrameSize, H264Profile,
+    MarkerOriginMode, OpenvrConfig, PassthroughMode, PositionRecenteringMode, SessionConfig,
+    SocketProtocol, StandbyBehavior, VideoPacing, VideoRecoveryMode,
 };
 use alvr_sockets::{
-    CONTROL_PORT, KEEPALIVE_INTERVAL, KEEPALIVE_TIMEOUT, PeerType, ProtoControlSocket,
-    StreamSocketBuilder, WIRED_CLIENT_HOSTNAME,
+    CONTROL_PORT, ControlSocketReceiver, KeptSocket, KeptSockets, PeerLiveness, PeerType,
+    ProtoControlSocket, StreamPriority, StreamSocket, StreamSocketBuilder, WIRED_CLIENT_HOSTNAME,
 };
 use std::{
     collections::HashMap,
-    net::{IpAddr, Ipv4Addr},
+    fs,
+    net::{IpAddr, Ipv4Addr, UdpSocket},
     process::Command,
-    sync::{Arc, mpsc::RecvTimeoutError},
+    sync::{
+        Arc,
+        atomic::Ordering,
+        mpsc::{self, RecvTimeoutError},
+    },
     thread,
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
+use sysinfo::Networks;
 
 const RETRY_CONNECT_MIN_INTERVAL: Duration = Duration::from_secs(1);
 const HANDSHAKE_ACTION_TIMEOUT: Duration = Duration::from_secs(2);
 pub const STREAMING_RECV_TIMEOUT: Duration = Duration::from_millis(500);
 const REAL_TIME_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+const MARKER_PLAYSPACE_INTERVAL: Duration = Duration::from_secs(1);
+const BITRATE_BENCHMARK_UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+// Several samples per second are needed by the clock estimator to filter the network delays
+const TIME_SYNC_INTERVAL: Duration = Duration::from_millis(200);
+const TRACE_CAPTURE_DURATION: Duration = Duration::from_secs(5);
 
 const MAX_UNREAD_PACKETS: usize = 10; // Applies per stream
+// Limit for the tracking, statistics and input packets, which have no raw payload
+const MAX_INPUT_PACKET_SIZE: usize = 64 * 1024;
 
 pub struct VideoPacket {
     pub header: VideoPacketHeader,
@@ -64,6 +91,71 @@ fn is_streaming(client_hostname: &str) -> bool {
         .is_some_and(|c| c.connection_state == ConnectionState::Streaming)
 }
 
+// Passthrough mode of the settings, also when the passthrough is disabled
+fn configured_passthrough_mode() -> PassthroughMode {
+    let mut session = SESSION_MANAGER.read().session().clone();
+    session.session_settings.video.passthrough.enabled = true;
+
+    match session.to_settings().video.passthrough {
+        Switch::Enabled(mode) => mode,
+        Switch::Disabled => unreachable!(),
+    }
+}
+
+// Sharpening uses the quality mode when enabled from the headset
+fn set_sharpening(update: impl FnOnce(bool) -> bool) {
+    let mut session_manager_lock = SESSION_MANAGER.write();
+    let mut session_lock = session_manager_lock.session_mut();
+    let post_processing = &mut session_lock
+        .session_settings
+        .video
+        .clientside_post_processing;
+
+    let enabled = update(
+        post_processing.enabled
+            && !matches!(
+                post_processing.content.sharpening.variant,
+                ClientsidePostProcessingSharpeningModeDefaultVariant::Disabled
+            ),
+    );
+    post_processing.content.sharpening.variant = if enabled {
+        ClientsidePostProcessingSharpeningModeDefaultVariant::Quality
+    } else {
+        ClientsidePostProcessingSharpeningModeDefaultVariant::Disabled
+    };
+    post_processing.enabled |= enabled;
+}
+
+fn is_same_subnet(address: IpAddr, other: IpAddr, prefix: u8) -> bool {
+    match (address, other) {
+        (IpAddr::V4(address), IpAddr::V4(other)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            (address.to_bits() ^ other.to_bits()) & mask == 0
+        }
+        (IpAddr::V6(address), IpAddr::V6(other)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            (address.to_bits() ^ other.to_bits()) & mask == 0
+        }
+        _ => false,
+    }
+}
+
+// MAC address of the network interface used to reach the client, needed for Wake-on-LAN. Returns
+// None for loopback (wired) connections.
+fn local_mac_address(client_ip: IpAddr) -> Option<[u8; 6]> {
+    Networks::new_with_refreshed_list()
+        .list()
+        .values()
+        .filter(|data| {
+            data.ip_networks().iter().any(|network| {
+                !network.addr.is_loopback()
+                    && is_same_subnet(network.addr, client_ip, network.prefix)
+            })
+        })
+        .map(|data| data.mac_address().0)
+        .find(|mac| *mac != [0; 6])
+}
+
 pub fn contruct_openvr_config(session: &SessionConfig) -> OpenvrConfig {
     let old_config = session.openvr_config.clone();
     let settings = session.to_settings();
@@ -100,14 +192,16 @@ pub fn contruct_openvr_config(session: &SessionConfig) -> OpenvrConfig {
         false
     };
 
-    let body_tracking_vive_enabled =
-        if let Switch::Enabled(config) = &settings.headset.body_tracking {
-            matches!(config.sink, BodyTrackingSinkConfig::FakeViveTracker)
-        } else if let Switch::Enabled(config) = settings.headset.multimodal_tracking {
-            config.detached_controllers_steamvr_sink
-        } else {
-            false
-        };
+    // The external trackers are shown as fake Vive trackers
+    let body_tracking_vive_enabled = if settings.headset.external_trackers.enabled() {
+        true
+    } else if let Switch::Enabled(config) = &settings.headset.body_tracking {
+        matches!(config.sink, BodyTrackingSinkConfig::FakeViveTracker)
+    } else if let Switch::Enabled(config) = settings.headset.multimodal_tracking {
+        config.detached_controllers_steamvr_sink
+    } else {
+        false
+    };
 
     // Should be true if using full body tracking
     let body_tracking_has_legs = settings
@@ -115,22 +209,64 @@ pub fn contruct_openvr_config(session: &SessionConfig) -> OpenvrConfig {
         .body_tracking
         .as_option()
         .map(|c| c.sources.meta.prefer_full_body)
-        .unwrap_or(false);
+        .unwrap_or(false)
+        || settings
+            .headset
+            .external_trackers
+            .as_option()
+            .is_some_and(|c| {
+                c.assignments.iter().any(|assignment| {
+                    matches!(
+                        assignment.role,
+                        ExternalTrackerRole::LeftKnee
+                            | ExternalTrackerRole::LeftFoot
+                            | ExternalTrackerRole::RightKnee
+                            | ExternalTrackerRole::RightFoot
+                    )
+                })
+            });
 
+    let mut foveation_mode = 0;
+    let mut foveation_center_strength = 0.0;
+    let mut foveation_edge_compression = 0.0;
     let mut foveation_center_size_x = 0.0;
     let mut foveation_center_size_y = 0.0;
     let mut foveation_center_shift_x = 0.0;
     let mut foveation_center_shift_y = 0.0;
     let mut foveation_edge_ratio_x = 0.0;
     let mut foveation_edge_ratio_y = 0.0;
+    let mut foveation_right_center_size_x = 0.0;
+    let mut foveation_right_center_size_y = 0.0;
+    let mut foveation_right_center_shift_x = 0.0;
+    let mut foveation_right_center_shift_y = 0.0;
+    let mut foveation_right_edge_ratio_x = 0.0;
+    let mut foveation_right_edge_ratio_y = 0.0;
+    let mut foveation_edge_preservation_strength = 0.0;
     let enable_foveated_encoding = if let Switch::Enabled(config) = settings.video.foveated_encoding
     {
+        if let FoveatedEncodingMode::Radial {
+            center_strength,
+            edge_compression,
+        } = config.mode
+        {
+            foveation_mode = 1;
+            foveation_center_strength = center_strength;
+            foveation_edge_compression = edge_compression;
+        }
         foveation_center_size_x = config.center_size_x;
         foveation_center_size_y = config.center_size_y;
         foveation_center_shift_x = config.center_shift_x;
         foveation_center_shift_y = config.center_shift_y;
         foveation_edge_ratio_x = config.edge_ratio_x;
         foveation_edge_ratio_y = config.edge_ratio_y;
+        let [_, right] = config.eyes();
+        foveation_right_center_size_x = right.center_size_x;
+        foveation_right_center_size_y = right.center_size_y;
+        foveation_right_center_shift_x = right.center_shift_x;
+        foveation_right_center_shift_y = right.center_shift_y;
+        foveation_right_edge_ratio_x = right.edge_ratio_x;
+        foveation_right_edge_ratio_y = right.edge_ratio_y;
+        foveation_edge_preservation_strength = config.edge_preservation_strength;
 
         true
     } else {
@@ -154,6 +290,25 @@ pub fn contruct_openvr_config(session: &SessionConfig) -> OpenvrConfig {
     };
 
     let nvenc_overrides = settings.video.encoder_config.nvenc;
+    let mut intra_refresh_recovery_period = 0;
+    let mut enable_intra_refresh = nvenc_overrides.enable_intra_refresh;
+    let mut intra_refresh_period = nvenc_overrides.intra_refresh_period;
+    let mut intra_refresh_count = nvenc_overrides.intra_refresh_count;
+    let mut keyframe_interval_ms = settings
+        .video
+        .keyframe_interval_ms
+        .as_option()
+        .copied()
+        .unwrap_or(0);
+    if let VideoRecoveryMode::IntraRefresh { period_frames } = settings.video.recovery {
+        // The refresh cycle already cleans up the image periodically
+        keyframe_interval_ms = 0;
+        intra_refresh_recovery_period = period_frames;
+        // NVENC requires the refresh count to be smaller than the period
+        enable_intra_refresh = true;
+        intra_refresh_period = period_frames as i64;
+        intra_refresh_count = period_frames as i64 - 1;
+    }
     let amf_controls = settings.video.encoder_config.amf;
     let hdr_controls = settings.video.encoder_config.hdr;
 
@@ -161,39 +316,74 @@ pub fn contruct_openvr_config(session: &SessionConfig) -> OpenvrConfig {
         tracking_ref_only: settings.headset.tracking_ref_only,
         enable_vive_tracker_proxy: settings.headset.enable_vive_tracker_proxy,
         minimum_idr_interval_ms: settings.connection.minimum_idr_interval_ms,
+        keyframe_interval_ms,
+        server_reprojection: settings.video.server_reprojection,
         adapter_index: settings.video.adapter_index,
+        adapter_name: settings
+            .video
+            .adapter_name
+            .clone()
+            .into_option()
+            .unwrap_or_default(),
+        cross_adapter_encoding: settings.video.cross_adapter_encoding,
         codec: settings.video.preferred_codec as _,
         h264_profile: settings.video.encoder_config.h264_profile as u32,
         rate_control_mode: settings.video.encoder_config.rate_control_mode as u32,
         filler_data: settings.video.encoder_config.filler_data,
         entropy_coding: settings.video.encoder_config.entropy_coding as u32,
+        color_range: settings.video.encoder_config.color_range as u32,
+        transfer_function: settings.video.encoder_config.transfer_function as u32,
+        chroma_subsampling: settings.video.encoder_config.chroma_subsampling as u32,
         force_hdr_srgb_correction: hdr_controls.force_hdr_srgb_correction,
         clamp_hdr_extended_range: hdr_controls.clamp_hdr_extended_range,
-        enable_amf_pre_analysis: amf_controls.enable_pre_analysis,
         enable_vbaq: settings.video.encoder_config.enable_vbaq,
         enable_amf_hmqb: amf_controls.enable_hmqb,
-        use_amf_preproc: amf_controls.use_preproc,
         amf_preproc_sigma: amf_controls.preproc_sigma,
         amf_preproc_tor: amf_controls.preproc_tor,
-        nvenc_quality_preset: nvenc_overrides.quality_preset as u32,
-        encoder_quality_preset: settings.video.encoder_config.quality_preset as u32,
+        encoder_backend: settings.video.encoder_backend as u32,
         force_sw_encoding: settings
             .video
             .encoder_config
             .software
             .force_software_encoding,
         sw_thread_count: settings.video.encoder_config.software.thread_count,
+        encoder_async_depth: settings.video.encoder_config.async_depth.max(1),
+        sw_svt_av1_preset: settings.video.encoder_config.software.svt_av1_preset,
         controllers_enabled,
         controller_is_tracker,
         body_tracking_vive_enabled,
         body_tracking_has_legs,
         enable_foveated_encoding,
+        foveation_mode,
+        foveation_center_strength,
+        foveation_edge_compression,
         foveation_center_size_x,
         foveation_center_size_y,
         foveation_center_shift_x,
         foveation_center_shift_y,
         foveation_edge_ratio_x,
         foveation_edge_ratio_y,
+        foveation_right_center_size_x,
+        foveation_right_center_size_y,
+        foveation_right_center_shift_x,
+        foveation_right_center_shift_y,
+        foveation_right_edge_ratio_x,
+        foveation_right_edge_ratio_y,
+        foveation_edge_preservation_strength,
+        enable_gaze_bitrate_allocation: settings.video.gaze_bitrate_allocation.enabled(),
+        enable_depth_stream: settings.video.stream_depth.enabled(),
+        depth_resolution_divisor: settings
+            .video
+            .stream_depth
+            .as_option()
+            .map(|config| config.resolution_divisor.max(1))
+            .unwrap_or(1),
+        enable_space_warp: settings.video.space_warp.enabled(),
+        enable_eye_gaze: settings
+            .headset
+            .face_tracking
+            .as_option()
+            .is_some_and(|config| config.eye_gaze.enabled()),
         enable_color_correction,
         brightness,
         contrast,
@@ -202,14 +392,13 @@ pub fn contruct_openvr_config(session: &SessionConfig) -> OpenvrConfig {
         sharpening,
         linux_async_compute: settings.extra.patches.linux_async_compute,
         linux_async_reprojection: settings.extra.patches.linux_async_reprojection,
-        nvenc_tuning_preset: nvenc_overrides.tuning_preset as u32,
-        nvenc_multi_pass: nvenc_overrides.multi_pass as u32,
         nvenc_adaptive_quantization_mode: nvenc_overrides.adaptive_quantization_mode as u32,
         nvenc_low_delay_key_frame_scale: nvenc_overrides.low_delay_key_frame_scale,
         nvenc_refresh_rate: nvenc_overrides.refresh_rate,
-        enable_intra_refresh: nvenc_overrides.enable_intra_refresh,
-        intra_refresh_period: nvenc_overrides.intra_refresh_period,
-        intra_refresh_count: nvenc_overrides.intra_refresh_count,
+        enable_intra_refresh,
+        intra_refresh_period,
+        intra_refresh_count,
+        intra_refresh_recovery_period,
         max_num_ref_frames: nvenc_overrides.max_num_ref_frames,
         gop_length: nvenc_overrides.gop_length,
         p_frame_strategy: nvenc_overrides.p_frame_strategy,
@@ -251,6 +440,8 @@ pub fn handshake_loop(ctx: Arc<ConnectionContext>, lifecycle_state: Arc<RwLock<L
 
     let mut wired_connection = None;
 
+    resume_kept_connection(&ctx, &lifecycle_state);
+
     while *lifecycle_state.read() != LifecycleState::ShuttingDown {
         dbg_connection!("handshake_loop: Try connect to wired device");
 
@@ -470,7 +661,136 @@ fn try_connect(
         con_bail!("unreachable");
     };
 
-    dbg_connection!("try_connect: Pushing new client connection thread");
+    spawn_connection_thread(
+        ctx,
+        lifecycle_state,
+        proto_socket,
+        client_hostname,
+        client_ip,
+        None,
+    );
+
+    Ok(())
+}
+
+// Resumes the connection kept by the dashboard while SteamVR restarted. The client is waiting on
+// the same sockets, it starts the handshake again when it receives DriverResumed
+fn resume_kept_connection(
+    ctx: &Arc<ConnectionContext>,
+    lifecycle_state: &Arc<RwLock<LifecycleState>>,
+) {
+    let Some(layout) = FILESYSTEM_LAYOUT.get() else {
+        return;
+    };
+    let kept = match alvr_sockets::take_kept_sockets(&layout.socket_keeper()) {
+        Ok(Some(kept)) => kept,
+        Ok(None) => return,
+        Err(e) => {
+            debug!("No connection to resume, the dashboard is not running? {e}");
+            return;
+        }
+    };
+
+    let mut sockets = kept.sockets.into_iter();
+    let Some(KeptSocket::Tcp(control_socket)) = sockets.next() else {
+        warn!("Kept connection without control socket");
+        return;
+    };
+    let udp_socket = sockets.find_map(|socket| match socket {
+        KeptSocket::Udp(socket) => Some(socket),
+        KeptSocket::Tcp(_) => None,
+    });
+    let Ok(client_ip) = control_socket.peer_addr().map(|address| address.ip()) else {
+        return;
+    };
+
+    let client_hostname = kept.tag;
+    if !SESSION_MANAGER
+        .read()
+        .client_list()
+        .get(&client_hostname)
+        .is_some_and(|client| client.trusted)
+    {
+        return;
+    }
+
+    if let Some(socket) = &udp_socket {
+        alvr_sockets::discard_received_datagrams(socket).ok();
+    }
+
+    let mut proto_socket = ProtoControlSocket::from_stream(control_socket);
+    if let Err(e) = proto_socket.send(&ServerControlPacket::DriverResumed) {
+        warn!("Failed to resume the connection with {client_hostname}: {e}");
+        return;
+    }
+    info!("Resuming the connection with {client_hostname}");
+
+    spawn_connection_thread(
+        Arc::clone(ctx),
+        Arc::clone(lifecycle_state),
+        proto_socket,
+        client_hostname,
+        client_ip,
+        udp_socket,
+    );
+}
+
+// The sockets are kept by the dashboard. Without it the client is disconnected as usual and
+// reconnects to the new driver
+fn is_connection_keepable() -> bool {
+    FILESYSTEM_LAYOUT
+        .get()
+        .is_some_and(|layout| alvr_sockets::is_socket_keeper_running(&layout.socket_keeper()))
+}
+
+// Waits until the client stops sending, then gives the sockets to the dashboard. The connection
+// is resumed by the next driver
+fn hand_over_connection(
+    client_hostname: &str,
+    mut control_receiver: ControlSocketReceiver<ClientControlPacket>,
+    mut restart_acked: bool,
+    udp_socket: Option<UdpSocket>,
+) {
+    let deadline = Instant::now() + HANDSHAKE_ACTION_TIMEOUT;
+    while !restart_acked && Instant::now() < deadline {
+        match control_receiver.recv(STREAMING_RECV_TIMEOUT) {
+            Ok(ClientControlPacket::RestartAck) => restart_acked = true,
+            Ok(_) | Err(ConnectionError::TryAgain(_)) => (),
+            Err(_) => break,
+        }
+    }
+    let Some(control_socket) = restart_acked
+        .then(|| control_receiver.into_stream())
+        .flatten()
+    else {
+        warn!("The connection with {client_hostname} cannot be kept during the restart");
+        return;
+    };
+
+    let mut sockets = vec![KeptSocket::Tcp(control_socket)];
+    sockets.extend(udp_socket.map(KeptSocket::Udp));
+
+    let endpoint = FILESYSTEM_LAYOUT.get().unwrap().socket_keeper();
+    if let Err(e) = alvr_sockets::hand_over_sockets(
+        &endpoint,
+        &KeptSockets {
+            tag: client_hostname.to_owned(),
+            sockets,
+        },
+    ) {
+        warn!("Failed to keep the connection with {client_hostname} during the restart: {e}");
+    }
+}
+
+fn spawn_connection_thread(
+    ctx: Arc<ConnectionContext>,
+    lifecycle_state: Arc<RwLock<LifecycleState>>,
+    proto_socket: ProtoControlSocket,
+    client_hostname: String,
+    client_ip: IpAddr,
+    kept_udp_socket: Option<UdpSocket>,
+) {
+    dbg_connection!("spawn_connection_thread: Pushing new client connection thread");
 
     ctx.connection_threads.lock().push(thread::spawn({
         let ctx = Arc::clone(&ctx);
@@ -481,12 +801,19 @@ fn try_connect(
                 proto_socket,
                 client_hostname.clone(),
                 client_ip,
+                kept_udp_socket,
             ) {
                 error!("Handshake error for {client_hostname}: {e}");
             }
 
             let mut clients_to_be_removed = ctx.clients_to_be_removed.lock();
 
+            // Back to the global settings
+            let mut session_manager_lock = SESSION_MANAGER.write();
+            if session_manager_lock.active_client() == Some(client_hostname.as_str()) {
+                session_manager_lock.set_active_client(None);
+            }
+
             let action = if clients_to_be_removed.contains(&client_hostname) {
                 clients_to_be_removed.remove(&client_hostname);
 
@@ -494,13 +821,9 @@ fn try_connect(
             } else {
                 ClientConnectionsAction::SetConnectionState(ConnectionState::Disconnected)
             };
-            SESSION_MANAGER
-                .write()
-                .update_client_connections(client_hostname, action);
+            session_manager_lock.update_client_connections(client_hostname, action);
         }
     }));
-
-    Ok(())
 }
 
 fn connection_pipeline(
@@ -509,6 +832,7 @@ fn connection_pipeline(
     mut proto_socket: ProtoControlSocket,
     client_hostname: String,
     client_ip: IpAddr,
+    kept_udp_socket: Option<UdpSocket>,
 ) -> ConResult {
     dbg_connection!("connection_pipeline: Begin");
 
@@ -569,6 +893,20 @@ fn connection_pipeline(
         con_bail!("Only streaming clients are supported for now");
     };
 
+    if streaming_caps.ext().is_ok_and(|ext| ext.flat_display) {
+        return spectator_pipeline(
+            ctx,
+            lifecycle_state,
+            session_manager_lock,
+            proto_socket,
+            client_hostname,
+            client_ip,
+        );
+    }
+
+    // From now on, settings() include the overrides of this client
+    session_manager_lock.set_active_client(Some(client_hostname.clone()));
+
     dbg_connection!("connection_pipeline: setting up negotiated streaming config");
 
     let initial_settings = session_manager_lock.settings().clone();
@@ -591,13 +929,47 @@ fn connection_pipeline(
             }
         };
 
-        UVec2::new(align32(res.x), align32(res.y))
+        res.round()
+            .as_uvec2()
+            .max(UVec2::ONE * encoder::VIEW_RESOLUTION_ALIGNMENT)
     }
 
-    let mut transcoding_view_resolution = get_view_res(
+    let codec = if initial_settings.video.preferred_codec == CodecType::AV1 {
+        let codec = if streaming_caps.encoder_av1 {
+            CodecType::AV1
+        } else {
+            CodecType::Hevc
+        };
+
+        if codec != CodecType::AV1 {
+            warn!("AV1 encoding is not supported by the client.");
+        }
+
+        codec
+    } else {
+        initial_settings.video.preferred_codec
+    };
+
+    let requested_view_resolution = get_view_res(
         initial_settings.video.transcoding_view_resolution.clone(),
         streaming_caps.default_view_resolution,
     );
+    let mut transcoding_view_resolution = encoder::align_view_resolution(requested_view_resolution);
+    if let FrameSize::Absolute { width, height } =
+        initial_settings.video.transcoding_view_resolution
+        && (width % encoder::VIEW_RESOLUTION_ALIGNMENT != 0
+            || height.is_some_and(|height| height % encoder::VIEW_RESOLUTION_ALIGNMENT != 0))
+    {
+        warn!(
+            "Resolution {}x{} is not a multiple of {} as required by the encoder. Using {}x{}.",
+            requested_view_resolution.x,
+            requested_view_resolution.y,
+            encoder::VIEW_RESOLUTION_ALIGNMENT,
+            transcoding_view_resolution.x,
+            transcoding_view_resolution.y,
+        );
+    }
+
     if transcoding_view_resolution.x > streaming_caps.max_view_resolution.x
         || transcoding_view_resolution.y > streaming_caps.max_view_resolution.y
     {
@@ -629,6 +1001,21 @@ fn connection_pipeline(
         }
     }
 
+    let limited_view_resolution =
+        encoder::limit_view_resolution(transcoding_view_resolution, codec);
+    if limited_view_resolution != transcoding_view_resolution {
+        warn!(
+            "Resolution {}x{} exceeds the maximum frame size supported by the {codec:?} encoders. \
+            Using {}x{}.",
+            transcoding_view_resolution.x,
+            transcoding_view_resolution.y,
+            limited_view_resolution.x,
+            limited_view_resolution.y,
+        );
+        transcoding_view_resolution = limited_view_resolution;
+    }
+
+    // The game rendering resolution does not need to be aligned
     let emulated_headset_view_resolution = get_view_res(
         initial_settings
             .video
@@ -670,6 +1057,86 @@ fn connection_pipeline(
             false
         };
 
+    let enable_radial_foveated_encoding = if enable_foveated_encoding
+        && let Switch::Enabled(config) = &initial_settings.video.foveated_encoding
+        && matches!(config.mode, FoveatedEncodingMode::Radial { .. })
+    {
+        let client_support = streaming_caps
+            .ext()
+            .map(|ext| ext.radial_foveated_encoding)
+            .unwrap_or(false);
+
+        if !client_support {
+            warn!("Radial foveated encoding is not supported by the client. Using two zones");
+        }
+
+        client_support
+    } else {
+        false
+    };
+
+    let foveated_encoding_eyes = if enable_foveated_encoding
+        && let Switch::Enabled(config) = &initial_settings.video.foveated_encoding
+    {
+        let [left, right] = config.eyes();
+        let client_support = streaming_caps
+            .ext()
+            .map(|ext| ext.per_eye_foveated_encoding)
+            .unwrap_or(false);
+
+        if right != left && !client_support {
+            warn!("Per-eye foveated encoding is not supported by the client. Using the left eye");
+
+            Some([left, left])
+        } else {
+            Some([left, right])
+        }
+    } else {
+        None
+    };
+
+    let enable_depth_stream = if initial_settings.video.stream_depth.enabled() {
+        let client_support = streaming_caps
+            .ext()
+            .map(|ext| ext.depth_layers)
+            .unwrap_or(false);
+
+        if !client_support {
+            warn!("Depth streaming is not supported by the client.");
+        }
+
+        client_support
+    } else {
+        false
+    };
+
+    let enable_space_warp = if let Switch::Enabled(config) = &initial_settings.video.space_warp {
+        let client_support = streaming_caps
+            .ext()
+            .map(|ext| ext.space_warp)
+            .unwrap_or(false);
+
+        let server_support = ctx.motion_vectors_supported.value();
+
+        if !client_support {
+            warn!("Space warp is not supported by the client.");
+        } else if !enable_depth_stream {
+            warn!("Space warp requires depth streaming.");
+        } else if !server_support {
+            warn!("Space warp requires the motion estimation of NVENC on Windows.");
+        }
+
+        client_support
+            && server_support
+            && enable_depth_stream
+            && config
+                .refresh_rates
+                .iter()
+                .any(|rate| (rate - fps).abs() < 1.0)
+    } else {
+        false
+    };
+
     let encoder_profile = if initial_settings.video.encoder_config.h264_profile == H264Profile::High
     {
         let profile = if streaming_caps.encoder_high_profile {
@@ -711,20 +1178,30 @@ fn connection_pipeline(
         .encoding_gamma
         .unwrap_or(streaming_caps.preferred_encoding_gamma);
 
-    let codec = if initial_settings.video.preferred_codec == CodecType::AV1 {
-        let codec = if streaming_caps.encoder_av1 {
-            CodecType::AV1
-        } else {
-            CodecType::Hevc
-        };
+    let chroma_subsampling = match initial_settings.video.encoder_config.chroma_subsampling {
+        ChromaSubsampling::Yuv420 => ChromaSubsampling::Yuv420,
+        subsampling => {
+            let client_support = streaming_caps.ext().is_ok_and(|ext| {
+                if subsampling == ChromaSubsampling::Yuv422 {
+                    ext.decoder_chroma_422
+                } else {
+                    ext.decoder_chroma_444
+                }
+            });
 
-        if codec != CodecType::AV1 {
-            warn!("AV1 encoding is not supported by the client.");
+            if codec != CodecType::Hevc {
+                warn!("Chroma subsampling {subsampling:?} requires HEVC. Using 4:2:0");
+                ChromaSubsampling::Yuv420
+            } else if enable_hdr {
+                warn!("Chroma subsampling {subsampling:?} is not supported with HDR. Using 4:2:0");
+                ChromaSubsampling::Yuv420
+            } else if !client_support {
+                warn!("Chroma subsampling {subsampling:?} is not supported by the client.");
+                ChromaSubsampling::Yuv420
+            } else {
+                subsampling
+            }
         }
-
-        codec
-    } else {
-        initial_settings.video.preferred_codec
     };
 
     #[cfg(not(target_os = "windows"))]
@@ -759,11 +1236,28 @@ fn connection_pipeline(
             0
         };
 
+    // Without SteamVR the frames come from the test pattern, encoded on the CPU with H.264, which
+    // supports only the basic stream
+    let driverless = !ctx.hosted_by_driver;
+    let enable_foveated_encoding = enable_foveated_encoding && !driverless;
+    let enable_radial_foveated_encoding = enable_radial_foveated_encoding && !driverless;
+    let foveated_encoding_eyes = foveated_encoding_eyes.filter(|_| !driverless);
+    let enable_depth_stream = enable_depth_stream && !driverless;
+    let enable_space_warp = enable_space_warp && !driverless;
+    let enable_10_bits_encoding = enable_10_bits_encoding && !driverless;
+    let enable_hdr = enable_hdr && !driverless;
+    let encoding_gamma = if driverless { 1.0 } else { encoding_gamma };
+    let chroma_subsampling = if driverless {
+        ChromaSubsampling::Yuv420
+    } else {
+        chroma_subsampling
+    };
+
     let wired = client_ip.is_loopback();
 
     dbg_connection!("connection_pipeline: send streaming config");
     let stream_config_packet = StreamConfigPacket::new(
-        session_manager_lock.session(),
+        &session_manager_lock.active_client_session(),
         NegotiatedStreamingConfig {
             view_resolution: transcoding_view_resolution,
             refresh_rate_hint: fps,
@@ -774,33 +1268,77 @@ fn connection_pipeline(
             wired,
             ext_str: String::new(),
         }
-        .with_ext(NegotiatedStreamingConfigExt {}),
+        .with_ext(NegotiatedStreamingConfigExt {
+            enable_depth_stream,
+            enable_space_warp,
+            chroma_subsampling,
+            enable_radial_foveated_encoding,
+            foveated_encoding_eyes,
+        }),
     )
     .to_con()?;
     proto_socket.send(&stream_config_packet).to_con()?;
+    *ctx.headset_stream_config.lock() = Some(stream_config_packet);
 
     let (mut control_sender, mut control_receiver) =
         proto_socket.split(STREAMING_RECV_TIMEOUT).to_con()?;
 
-    let mut new_openvr_config = contruct_openvr_config(session_manager_lock.session());
+    let keepalive_config = initial_settings.connection.keepalive.clone();
+    let keepalive_timeout = Duration::from_millis(keepalive_config.timeout_ms);
+    // A dead client must not block the senders, otherwise the connection cannot be torn down
+    control_sender
+        .set_write_timeout(keepalive_timeout)
+        .to_con()?;
+
+    let mut new_openvr_config =
+        contruct_openvr_config(&session_manager_lock.active_client_session());
     new_openvr_config.eye_resolution_width = transcoding_view_resolution.x;
     new_openvr_config.eye_resolution_height = transcoding_view_resolution.y;
     new_openvr_config.target_eye_resolution_width = emulated_headset_view_resolution.x;
     new_openvr_config.target_eye_resolution_height = emulated_headset_view_resolution.y;
-    new_openvr_config.refresh_rate = fps as _;
+    // With space warp the headset synthesizes every other frame
+    let game_fps = if enable_space_warp { fps / 2.0 } else { fps };
+    new_openvr_config.refresh_rate = game_fps as _;
     new_openvr_config.enable_foveated_encoding = enable_foveated_encoding;
+    if !enable_radial_foveated_encoding {
+        new_openvr_config.foveation_mode = 0;
+    }
+    if let Some([_, right]) = foveated_encoding_eyes {
+        new_openvr_config.foveation_right_center_size_x = right.center_size_x;
+        new_openvr_config.foveation_right_center_size_y = right.center_size_y;
+        new_openvr_config.foveation_right_center_shift_x = right.center_shift_x;
+        new_openvr_config.foveation_right_center_shift_y = right.center_shift_y;
+        new_openvr_config.foveation_right_edge_ratio_x = right.edge_ratio_x;
+        new_openvr_config.foveation_right_edge_ratio_y = right.edge_ratio_y;
+    }
+    new_openvr_config.enable_depth_stream = enable_depth_stream;
+    new_openvr_config.enable_space_warp = enable_space_warp;
     new_openvr_config.h264_profile = encoder_profile as _;
     new_openvr_config.use_10bit_encoder = enable_10_bits_encoding;
     new_openvr_config.enable_hdr = enable_hdr;
     new_openvr_config.encoding_gamma = encoding_gamma;
+    new_openvr_config.chroma_subsampling = chroma_subsampling as _;
     new_openvr_config.codec = codec as _;
 
-    if session_manager_lock.session().openvr_config != new_openvr_config {
+    let openvr_config_changed = session_manager_lock.session().openvr_config != new_openvr_config;
+    if openvr_config_changed {
         session_manager_lock.session_mut().openvr_config = new_openvr_config;
+    }
 
-        control_sender.send(&ServerControlPacket::Restarting).ok();
+    // The test pattern reads the new config when the client connects, without restart
+    if openvr_config_changed && !driverless {
+        if !is_connection_keepable() {
+            control_sender.send(&ServerControlPacket::Restarting).ok();
+        } else if control_sender
+            .send(&ServerControlPacket::DriverRestarting)
+            .is_ok()
+        {
+            hand_over_connection(&client_hostname, control_receiver, false, None);
+        }
 
         crate::notify_restart_driver();
+
+        return Ok(());
     }
 
     dbg_connection!("connection_pipeline: Send StartStream packet");
@@ -816,7 +1354,7 @@ fn connection_pipeline(
 
     *ctx.statistics_manager.write() = Some(StatisticsManager::new(
         initial_settings.connection.statistics_history_size,
-        Duration::from_secs_f32(1.0 / fps),
+        Duration::from_secs_f32(1.0 / game_fps),
         if let Switch::Enabled(config) = &initial_settings.headset.controllers {
             config.steamvr_pipeline_frames
         } else {
@@ -825,7 +1363,7 @@ fn connection_pipeline(
     ));
 
     *ctx.bitrate_manager.lock() =
-        BitrateManager::new(initial_settings.video.bitrate.history_size, fps);
+        BitrateManager::new(initial_settings.video.bitrate.history_size, game_fps);
 
     let stream_protocol = if wired {
         SocketProtocol::Tcp
@@ -833,31 +1371,102 @@ fn connection_pipeline(
         initial_settings.connection.stream_protocol
     };
 
-    dbg_connection!("connection_pipeline: StreamSocket connect_to_client");
-    let mut stream_socket = StreamSocketBuilder::connect_to_client(
-        HANDSHAKE_ACTION_TIMEOUT,
-        client_ip,
-        initial_settings.connection.stream_port,
-        stream_protocol,
-        initial_settings.connection.dscp,
-        initial_settings.connection.server_buffer_config,
-        initial_settings.connection.packet_size as _,
-    )?;
-
-    let mut video_sender = stream_socket.request_stream(VIDEO);
-    let game_audio_sender: alvr_sockets::StreamSender<()> = stream_socket.request_stream(AUDIO);
+    // The client keeps its socket under the same conditions
+    let kept_udp_socket = kept_udp_socket.filter(|socket| {
+        matches!(stream_protocol, SocketProtocol::Udp)
+            && socket
+                .local_addr()
+                .is_ok_and(|address| address.port() == initial_settings.connection.stream_port)
+    });
+    let mut stream_socket = if let Some(socket) = kept_udp_socket {
+        dbg_connection!("connection_pipeline: StreamSocket from the kept socket");
+        StreamSocket::from_udp_socket(socket, initial_settings.connection.packet_size as _)
+            .to_con()?
+    } else {
+        dbg_connection!("connection_pipeline: StreamSocket connect_to_client");
+        StreamSocketBuilder::connect_to_client(
+            HANDSHAKE_ACTION_TIMEOUT,
+            client_ip,
+            initial_settings.connection.stream_port,
+            stream_protocol,
+            initial_settings.connection.dscp,
+            initial_settings.connection.server_buffer_config,
+            initial_settings.connection.packet_size as _,
+        )?
+    };
+    stream_socket
+        .set_write_timeout(keepalive_timeout)
+        .to_con()?;
+    let udp_socket = stream_socket
+        .try_clone_udp_socket()
+        .and_then(|res| res.ok());
+
+    // Audio and haptics are sent between the shards of the video frames
+    let mut video_sender = stream_socket.request_stream_with_priority(VIDEO, StreamPriority::Low);
+    if let VideoPacing::Paced {
+        frame_interval_percent,
+    } = initial_settings.connection.video_pacing
+    {
+        video_sender.set_pacing(Some(Duration::from_secs_f32(
+            frame_interval_percent / 100.0 / fps,
+        )));
+    }
+    if let Switch::Enabled(config) = &initial_settings.connection.video_packet_loss_simulation {
+        warn!(
+            "Simulating the loss of {}% of the video packets",
+            config.loss_percent
+        );
+        video_sender.simulate_packet_loss(config);
+    }
+    let sent_bytes_counter = stream_socket.sent_bytes_counter();
+    let control_sent_bytes_counter = control_sender.sent_bytes_counter();
+    let game_audio_sender: alvr_sockets::StreamSender<()> =
+        stream_socket.request_stream_with_priority(AUDIO, StreamPriority::High);
     let mut microphone_receiver: alvr_sockets::StreamReceiver<()> =
         stream_socket.subscribe_to_stream(AUDIO, MAX_UNREAD_PACKETS);
-    let tracking_receiver =
-        stream_socket.subscribe_to_stream::<TrackingData>(TRACKING, MAX_UNREAD_PACKETS);
+    let tracking_receiver = stream_socket.subscribe_to_stream_with_max_size::<TrackingData>(
+        TRACKING,
+        MAX_UNREAD_PACKETS,
+        MAX_INPUT_PACKET_SIZE,
+    );
     let haptics_sender = stream_socket.request_stream(HAPTICS);
-    let mut statics_receiver =
-        stream_socket.subscribe_to_stream::<ClientStatistics>(STATISTICS, MAX_UNREAD_PACKETS);
+    let depth_sender = enable_depth_stream
+        .then(|| stream_socket.request_stream_with_priority(DEPTH, StreamPriority::Low));
+    let motion_vectors_sender = enable_space_warp
+        .then(|| stream_socket.request_stream_with_priority(MOTION_VECTORS, StreamPriority::Low));
+    if let Some(stats) = &mut *ctx.statistics_manager.write() {
+        stats.set_stream_sent_bytes_counters(
+            game_audio_sender.sent_bytes_counter(),
+            haptics_sender.sent_bytes_counter(),
+        );
+    }
+    let mut statics_receiver = stream_socket.subscribe_to_stream_with_max_size::<ClientStatistics>(
+        STATISTICS,
+        MAX_UNREAD_PACKETS,
+        MAX_INPUT_PACKET_SIZE,
+    );
+    let peripheral_input = initial_settings
+        .headset
+        .peripheral_input
+        .clone()
+        .into_option()
+        .map(|config| {
+            let receiver = stream_socket.subscribe_to_stream_with_max_size::<PeripheralInput>(
+                PERIPHERAL_INPUT,
+                MAX_UNREAD_PACKETS,
+                MAX_INPUT_PACKET_SIZE,
+            );
+
+            (config, receiver)
+        });
 
     let (video_channel_sender, video_channel_receiver) =
         std::sync::mpsc::sync_channel(initial_settings.connection.max_queued_server_video_frames);
     *ctx.video_channel_sender.lock() = Some(video_channel_sender);
+    ctx.video_queue_len.store(0, Ordering::Relaxed);
     *ctx.haptics_sender.lock() = Some(haptics_sender);
+    *ctx.depth_sender.lock() = depth_sender;
+    *ctx.motion_vectors_sender.lock() = motion_vectors_sender;
 
     let video_send_thread = thread::spawn({
         let ctx = Arc::clone(&ctx);
@@ -872,15 +1481,33 @@ fn connection_pipeline(
                     Err(RecvTimeoutError::Timeout) => continue,
                     Err(RecvTimeoutError::Disconnected) => return,
                 };
+                ctx.video_queue_len
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |len| {
+                        Some(len.saturating_sub(1))
+                    })
+                    .ok();
 
                 ctx.tracking_manager
                     .read()
                     .unrecenter_view_params(&mut header.global_view_params);
 
                 // todo: use get_buffer and make encoder write to socket buffers directly to avoid copy
-                video_sender
-                    .send_header_with_payload(&header, &payload)
-                    .ok();
+                let send_start = Instant::now();
+                let send_result = video_sender.send_header_with_payload(&header, &payload);
+                alvr_common::trace_span("Send", send_start, Instant::now(), header.timestamp);
+
+                if send_result.is_ok() {
+                    ctx.bitrate_manager.lock().report_frame_sent(
+                        payload.len(),
+                        sent_bytes_counter
+                            .load(Ordering::Relaxed)
+                            .wrapping_add(control_sent_bytes_counter.load(Ordering::Relaxed)),
+                    );
+
+                    if let Some(stats) = &mut *ctx.statistics_manager.write() {
+                        stats.report_video_sent(header.timestamp);
+                    }
+                }
             }
         }
     });
@@ -1053,6 +1680,7 @@ fn connection_pipeline(
                 initial_settings,
                 hand_gesture_manager,
                 tracking_receiver,
+                Duration::from_secs_f32(1.0 / fps),
                 || is_streaming(&client_hostname),
             );
         }
@@ -1068,8 +1696,12 @@ fn connection_pipeline(
                     Err(ConnectionError::TryAgain(_)) => continue,
                     Err(ConnectionError::Other(_)) => return,
                 };
-                let Ok(client_stats) = data.get_header() else {
-                    return;
+                let client_stats = match data.get_header() {
+                    Ok(stats) => stats,
+                    Err(e) => {
+                        warn!("Dropped malformed statistics packet: {e}");
+                        continue;
+                    }
                 };
 
                 if let Some(stats) = &mut *ctx.statistics_manager.write() {
@@ -1088,56 +1720,371 @@ fn connection_pipeline(
                         network_latency,
                         decoder_latency,
                     );
+
+                    if let Some(benchmark) = &mut *ctx.bitrate_benchmark.lock() {
+                        benchmark.report_network_latency(network_latency);
+                    }
                 }
             }
         }
     });
 
-    let control_sender = Arc::new(Mutex::new(control_sender));
-
-    let real_time_update_thread = thread::spawn({
-        let control_sender = Arc::clone(&control_sender);
-        let client_hostname = client_hostname.clone();
-        move || {
-            let mut previous_config = None;
-            while is_streaming(&client_hostname) {
-                let config = {
-                    let session_manager_lock = SESSION_MANAGER.read();
-                    let settings = session_manager_lock.settings();
-
-                    RealTimeConfig::from_settings(settings)
-                };
-
-                let same_config = previous_config.as_ref().is_some_and(|prev| config == *prev);
-                if !same_config {
-                    previous_config = Some(config.clone());
-
-                    control_sender
-                        .lock()
-                        .send(&ServerControlPacket::RealTimeConfig(config))
-                        .ok();
-                }
-
-                thread::sleep(REAL_TIME_UPDATE_INTERVAL);
+    // Shared with the control thread, which receives the key and mouse button events
+    let peripheral_input = peripheral_input.and_then(|(config, receiver)| {
+        match PeripheralInputInjector::new(config) {
+            Ok(injector) => Some((Arc::new(Mutex::new(injector)), receiver)),
+            Err(e) => {
+                warn!("Keyboard and mouse passthrough unavailable: {e:?}");
+                None
             }
         }
     });
+    let peripheral_input_injector = peripheral_input
+        .as_ref()
+        .map(|(injector, _)| Arc::clone(injector));
 
-    let keepalive_thread = thread::spawn({
-        let control_sender = Arc::clone(&control_sender);
-        let disconnect_notif = Arc::clone(&disconnect_notif);
+    let peripheral_input_thread = if let Some((injector, mut receiver)) = peripheral_input {
         let client_hostname = client_hostname.clone();
-        move || {
+        thread::spawn(move || {
             while is_streaming(&client_hostname) {
-                if let Err(e) = control_sender.lock().send(&ServerControlPacket::KeepAlive) {
-                    info!("Client disconnected. Cause: {e:?}");
+                let data = match receiver.recv(STREAMING_RECV_TIMEOUT) {
+                    Ok(data) => data,
+                    Err(ConnectionError::TryAgain(_)) => continue,
+                    Err(ConnectionError::Other(_)) => return,
+                };
+                let input = match data.get_header() {
+                    Ok(input) => input,
+                    Err(e) => {
+                        warn!("Dropped malformed peripheral input packet: {e}");
+                        continue;
+                    }
+                };
+
+                injector.lock().inject(input);
+            }
+        })
+    } else {
+        thread::spawn(|| ())
+    };
+
+    let control_sender = Arc::new(Mutex::new(control_sender));
+
+    if let Some(mac_address) = local_mac_address(client_ip) {
+        control_sender
+            .lock()
+            .send(&ServerControlPacket::ServerMacAddress(mac_address))
+            .ok();
+    }
+
+    let (clipboard_thread, remote_clipboard_sender) =
+        if let Some(config) = initial_settings.headset.clipboard_sync.as_option() {
+            let (sender, receiver) = mpsc::channel::<String>();
+            let mut sync = ClipboardSync::new(
+                config.server_to_client,
+                config.client_to_server,
+                config.max_size_kb as usize * 1024,
+            );
+
+            let thread = thread::spawn({
+                let control_sender = Arc::clone(&control_sender);
+                let client_hostname = client_hostname.clone();
+                move || {
+                    let mut clipboard = match arboard::Clipboard::new() {
+                        Ok(clipboard) => clipboard,
+                        Err(e) => {
+                            warn!("Clipboard sync unavailable: {e}");
+                            return;
+                        }
+                    };
+
+                    while is_streaming(&client_hostname) {
+                        for text in receiver.try_iter() {
+                            if let Some(text) = sync.receive_remote_text(text)
+                                && let Err(e) = clipboard.set_text(text)
+                            {
+                                warn!("Failed to set clipboard text: {e}");
+                            }
+                        }
+
+                        // Fails if the clipboard is empty or does not contain text
+                        if sync.sends_local_changes()
+                            && let Ok(text) = clipboard.get_text()
+                        {
+                            sync.report_local_text(text, Instant::now());
+                        }
+
+                        if let Some(text) = sync.poll_outgoing_text(Instant::now()) {
+                            control_sender
+                                .lock()
+                                .send(&ServerControlPacket::Clipboard(text))
+                                .ok();
+                        }
+
+                        thread::sleep(CLIPBOARD_POLL_INTERVAL);
+                    }
+                }
+            });
+
+            (thread, Some(sender))
+        } else {
+            (thread::spawn(|| ()), None)
+        };
+
+    // Server half of the last trace capture, merged when the client half is received
+    let server_trace_spans = Arc::new(Mutex::new(None));
+
+    // Passthrough set by the button macros and the in-headset menu, Some(None) if disabled. It is
+    // not saved to the session and lasts until the client disconnects.
+    let passthrough_override = Arc::new(Mutex::new(None::<Option<PassthroughMode>>));
+
+    let real_time_update_thread = thread::spawn({
+        let ctx = Arc::clone(&ctx);
+        let control_sender = Arc::clone(&control_sender);
+        let client_hostname = client_hostname.clone();
+        let server_trace_spans = Arc::clone(&server_trace_spans);
+        let passthrough_override = Arc::clone(&passthrough_override);
+        let initial_settings = initial_settings.clone();
+        move || {
+            let mut previous_config = None;
+            let mut pending_setting_changes = vec![];
+            let mut trace_capture_deadline = None;
+            let mut previous_depth_submitted = None;
+            while is_streaming(&client_hostname) {
+                let depth_submitted = ctx.depth_submitted.value();
+                if enable_depth_stream && previous_depth_submitted != Some(depth_submitted) {
+                    previous_depth_submitted = Some(depth_submitted);
+
+                    control_sender
+                        .lock()
+                        .send(&ServerControlPacket::DepthSubmission(depth_submitted))
+                        .ok();
+                }
+
+                if ctx.client_log_requested.value() {
+                    ctx.client_log_requested.set(false);
+
+                    control_sender
+                        .lock()
+                        .send(&ServerControlPacket::RequestClientLog)
+                        .ok();
+                }
+
+                if ctx.trace_capture_requested.value() {
+                    ctx.trace_capture_requested.set(false);
+
+                    if trace_capture_deadline.is_some() {
+                        warn!("A trace capture is already in progress");
+                    } else {
+                        alvr_common::start_trace_capture(clock_sync::server_time());
+                        control_sender
+                            .lock()
+                            .send(&ServerControlPacket::StartTraceCapture)
+                            .ok();
+                        trace_capture_deadline = Some(Instant::now() + TRACE_CAPTURE_DURATION);
+
+                        info!("Capturing {}s of trace", TRACE_CAPTURE_DURATION.as_secs());
+                    }
+                }
+
+                if trace_capture_deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    trace_capture_deadline = None;
+
+                    *server_trace_spans.lock() = Some(alvr_common::stop_trace_capture());
+                    control_sender
+                        .lock()
+                        .send(&ServerControlPacket::StopTraceCapture)
+                        .ok();
+                }
+
+                let (config, setting_changes) = {
+                    let session_manager_lock = SESSION_MANAGER.read();
+                    let settings = session_manager_lock.settings();
+
+                    let mut config = RealTimeConfig::from_settings(settings);
+                    if let Some(passthrough) = &*passthrough_override.lock() {
+                        config.passthrough = passthrough.clone();
+                    }
+
+                    (
+                        config,
+                        alvr_session::setting_changes(&initial_settings, settings),
+                    )
+                };
+
+                // The changes applied live are not reported
+                let setting_changes = setting_changes
+                    .into_iter()
+                    .filter(|change| change.scope != ApplyScope::Live)
+                    .collect::<Vec<_>>();
+                if setting_changes != pending_setting_changes {
+                    pending_setting_changes = setting_changes.clone();
+
+                    alvr_events::send_event(EventType::PendingSettingChanges(setting_changes));
+                }
+
+                let same_config = previous_config.as_ref().is_some_and(|prev| config == *prev);
+                if !same_config {
+                    previous_config = Some(config.clone());
+
+                    control_sender
+                        .lock()
+                        .send(&ServerControlPacket::RealTimeConfig(config))
+                        .ok();
+                }
+
+                thread::sleep(REAL_TIME_UPDATE_INTERVAL);
+            }
+
+            if trace_capture_deadline.is_some() {
+                alvr_common::stop_trace_capture();
+            }
+
+            // The next connection uses the current settings
+            if !pending_setting_changes.is_empty() {
+                alvr_events::send_event(EventType::PendingSettingChanges(vec![]));
+            }
+        }
+    });
+
+    let bitrate_benchmark_thread = thread::spawn({
+        let ctx = Arc::clone(&ctx);
+        let control_sender = Arc::clone(&control_sender);
+        let client_hostname = client_hostname.clone();
+        let export_metrics = initial_settings
+            .extra
+            .metrics_exporter
+            .as_option()
+            .is_some();
+        move || {
+            let mut last_loss_report_request = Instant::now();
+            while is_streaming(&client_hostname) {
+                let finished_report = {
+                    let benchmark_lock = &mut *ctx.bitrate_benchmark.lock();
+
+                    // The benchmark requests its own reports, which are also counted by the metrics
+                    if benchmark_lock.is_none()
+                        && export_metrics
+                        && last_loss_report_request.elapsed() > REAL_TIME_UPDATE_INTERVAL
+                    {
+                        control_sender
+                            .lock()
+                            .send(&ServerControlPacket::RequestVideoLossReport)
+                            .ok();
+                        last_loss_report_request = Instant::now();
+                    }
+
+                    if let Some(benchmark) = benchmark_lock {
+                        if benchmark.update(Instant::now()) {
+                            control_sender
+                                .lock()
+                                .send(&ServerControlPacket::RequestVideoLossReport)
+                                .ok();
+                        }
+
+                        ctx.bitrate_manager
+                            .lock()
+                            .set_bitrate_override(benchmark.bitrate_bps());
+
+                        let report = benchmark.take_report();
+                        if let Some(report) = &report {
+                            alvr_events::send_event(EventType::BitrateBenchmark(report.clone()));
+                        }
+
+                        if benchmark.is_running() {
+                            None
+                        } else {
+                            *benchmark_lock = None;
+
+                            report
+                        }
+                    } else {
+                        None
+                    }
+                };
+
+                // The session is locked after releasing the benchmark to respect the lock order
+                if let Some(report) = finished_report
+                    && matches!(report.state, BitrateBenchmarkState::Finished)
+                {
+                    if let Some(bitrate_mbps) = report.recommended_bitrate_mbps {
+                        info!(
+                            "Bitrate benchmark finished. Recommended bitrate: {bitrate_mbps} Mbps"
+                        );
+
+                        let mut session_manager_lock = SESSION_MANAGER.write();
+                        if session_manager_lock
+                            .settings()
+                            .video
+                            .bitrate
+                            .benchmark
+                            .apply_result
+                        {
+                            let mut session_lock = session_manager_lock.session_mut();
+                            let mode = &mut session_lock.session_settings.video.bitrate.mode;
+                            match mode.variant {
+                                BitrateModeDefaultVariant::ConstantMbps => {
+                                    mode.ConstantMbps = bitrate_mbps;
+                                }
+                                BitrateModeDefaultVariant::Adaptive => {
+                                    mode.Adaptive.max_throughput_mbps.enabled = true;
+                                    mode.Adaptive.max_throughput_mbps.content = bitrate_mbps;
+                                }
+                            }
+                        }
+                    } else {
+                        warn!("Bitrate benchmark finished. No bitrate met the targets");
+                    }
+                }
+
+                thread::sleep(BITRATE_BENCHMARK_UPDATE_INTERVAL);
+            }
+
+            if let Some(mut benchmark) = ctx.bitrate_benchmark.lock().take() {
+                benchmark.abort("Client disconnected");
+                if let Some(report) = benchmark.take_report() {
+                    alvr_events::send_event(EventType::BitrateBenchmark(report));
+                }
+            }
+            ctx.bitrate_manager.lock().set_bitrate_override(None);
+        }
+    });
+
+    // Set by the control thread, the client may stop streaming while in standby
+    let client_in_standby = Arc::new(RelaxedAtomic::new(false));
+
+    let keepalive_thread = thread::spawn({
+        let control_sender = Arc::clone(&control_sender);
+        let disconnect_notif = Arc::clone(&disconnect_notif);
+        let client_hostname = client_hostname.clone();
+        let keepalive_interval = Duration::from_millis(keepalive_config.interval_ms);
+        move || {
+            while is_streaming(&client_hostname) {
+                if let Err(e) = control_sender.lock().send(&ServerControlPacket::KeepAlive) {
+                    info!("Client disconnected. Cause: {e:?}");
 
                     disconnect_notif.notify_one();
 
                     return;
                 }
 
-                thread::sleep(KEEPALIVE_INTERVAL);
+                thread::sleep(keepalive_interval);
+            }
+        }
+    });
+
+    let time_sync_thread = thread::spawn({
+        let control_sender = Arc::clone(&control_sender);
+        let client_hostname = client_hostname.clone();
+        move || {
+            while is_streaming(&client_hostname) {
+                control_sender
+                    .lock()
+                    .send(&ServerControlPacket::TimeSyncRequest(
+                        clock_sync::server_time(),
+                    ))
+                    .ok();
+
+                thread::sleep(TIME_SYNC_INTERVAL);
             }
         }
     });
@@ -1166,17 +2113,179 @@ fn connection_pipeline(
         });
         let controllers_emulation_mode =
             controllers_config.map(|config| config.emulation_mode.clone());
+        let mut analog_input_processor =
+            AnalogInputProcessor::new(*alvr_common::QUEST_CONTROLLER_PROFILE_ID);
+        let mut button_macros_config = controllers_config
+            .map(|config| config.button_macros.clone())
+            .unwrap_or_default();
+        let mut button_macro_manager = ButtonMacroManager::new(&button_macros_config);
+
+        let latency_test_button_id = session_manager_lock
+            .settings()
+            .extra
+            .photodiode_latency_test
+            .as_option()
+            .map(|config| alvr_common::hash_string(&config.trigger_button));
+
+        let standby_behavior = session_manager_lock.settings().connection.standby_behavior;
 
         let disconnect_notif = Arc::clone(&disconnect_notif);
         let control_sender = Arc::clone(&control_sender);
         let client_hostname = client_hostname.clone();
+        let client_in_standby = Arc::clone(&client_in_standby);
+        let passthrough_override = Arc::clone(&passthrough_override);
         move || {
-            let mut disconnection_deadline = Instant::now() + KEEPALIVE_TIMEOUT;
+            let mut liveness = PeerLiveness::new(keepalive_timeout, Instant::now());
+            let mut latency_test_button_pressed = false;
+            // Kept to update the chaperone when the recentering origin changes
+            let mut last_playspace: Option<(Vec2, Vec<Vec2>)> = None;
+            // A followed marker updates the origin every frame
+            let marker_follow = initial_settings
+                .headset
+                .marker_origin
+                .as_option()
+                .and_then(|config| config.follow.as_option().cloned());
+            let mut last_marker_playspace_instant = Instant::now() - MARKER_PLAYSPACE_INTERVAL;
+            let mut wifi_advisor = WifiAdvisor::new();
+            let send_playspace = |area: Vec2, perimeter: &[Vec2]| {
+                ctx.events_sender
+                    .send(ServerCoreEvent::PlayspaceSync {
+                        area,
+                        perimeter: ctx.tracking_manager.read().recenter_floor_points(perimeter),
+                    })
+                    .ok();
+            };
+            let recenter = |last_playspace: &Option<(Vec2, Vec<Vec2>)>| {
+                if !initial_settings.headset.tracking_ref_only {
+                    let session_manager_lock = SESSION_MANAGER.read();
+                    let config = &session_manager_lock.settings().headset;
+
+                    if config.marker_origin.as_option().is_none() {
+                        ctx.tracking_manager.write().clear_marker_origin();
+                    }
+
+                    // The origin is owned by the client marker tracking
+                    if config
+                        .marker_origin
+                        .as_option()
+                        .is_none_or(|c| c.mode != MarkerOriginMode::Client)
+                    {
+                        ctx.tracking_manager.write().recenter(
+                            config.position_recentering_mode,
+                            config.rotation_recentering_mode,
+                        );
+
+                        if let Some((area, perimeter)) = last_playspace {
+                            send_playspace(*area, perimeter);
+                        }
+
+                        // Restored by the client on the next connection
+                        let origin = ctx.tracking_manager.read().recentering_origin();
+                        control_sender
+                            .lock()
+                            .send(&ServerControlPacket::RecenteringOrigin(origin))
+                            .ok();
+                    }
+                }
+            };
+            let mut microphone_muted = false;
+            // Set while the headset is not worn or the client app is not visible
+            let mut standby_start: Option<Instant> = None;
+            let set_encoding_paused = |paused: bool| {
+                ctx.events_sender
+                    .send(ServerCoreEvent::SetEncodingPaused {
+                        paused,
+                        keepalive_frames: matches!(
+                            standby_behavior,
+                            StandbyBehavior::PauseEncoding {
+                                keepalive_frames: true
+                            }
+                        ),
+                    })
+                    .ok();
+                if !paused {
+                    // The client decoder may have been stopped while the headset was in standby
+                    ctx.events_sender.send(ServerCoreEvent::RequestIDR).ok();
+                }
+
+                alvr_events::send_event(EventType::EncodingPaused { paused });
+            };
+            let mut restart_acked = false;
             while is_streaming(&client_hostname) {
-                let packet = match control_receiver.recv(STREAMING_RECV_TIMEOUT) {
+                if let StandbyBehavior::DisconnectAfter { minutes } = standby_behavior
+                    && standby_start.is_some_and(|start| {
+                        start.elapsed() > Duration::from_secs(minutes as u64 * 60)
+                    })
+                {
+                    info!("Client {client_hostname} disconnected after {minutes} min in standby");
+
+                    control_sender
+                        .lock()
+                        .send(&ServerControlPacket::StandbyDisconnect)
+                        .ok();
+
+                    break;
+                }
+
+                for action in button_macro_manager.poll_actions(Instant::now()) {
+                    info!("Client {client_hostname} button macro: {action:?}");
+
+                    match action {
+                        ButtonMacroAction::Recenter => recenter(&last_playspace),
+                        ButtonMacroAction::TogglePassthrough => {
+                            let passthrough_override = &mut *passthrough_override.lock();
+                            let enabled = match passthrough_override {
+                                Some(passthrough) => passthrough.is_some(),
+                                None => SESSION_MANAGER
+                                    .read()
+                                    .settings()
+                                    .video
+                                    .passthrough
+                                    .as_option()
+                                    .is_some(),
+                            };
+                            *passthrough_override =
+                                Some((!enabled).then(configured_passthrough_mode));
+                        }
+                        ButtonMacroAction::ToggleMicrophoneMute => {
+                            microphone_muted = !microphone_muted;
+                            control_sender
+                                .lock()
+                                .send(&ServerControlPacket::SetMicrophoneMuted(microphone_muted))
+                                .ok();
+                        }
+                        ButtonMacroAction::RequestIdr => {
+                            ctx.events_sender.send(ServerCoreEvent::RequestIDR).ok();
+                        }
+                        ButtonMacroAction::ToggleBiometricStreams => {
+                            let mut session_manager_lock = SESSION_MANAGER.write();
+                            let mut session_lock = session_manager_lock.session_mut();
+                            let blocked = &mut session_lock
+                                .session_settings
+                                .privacy
+                                .block_biometric_streams;
+                            *blocked = !*blocked;
+                        }
+                        ButtonMacroAction::ToggleSharpening => set_sharpening(|enabled| !enabled),
+                        ButtonMacroAction::CaptureFrame => {
+                            ctx.events_sender.send(ServerCoreEvent::CaptureFrame).ok();
+                        }
+                    }
+                }
+
+                // Wake up in time to trigger the held button macros
+                let recv_timeout = button_macro_manager
+                    .next_deadline()
+                    .map(|deadline| {
+                        deadline
+                            .saturating_duration_since(Instant::now())
+                            .min(STREAMING_RECV_TIMEOUT)
+                    })
+                    .unwrap_or(STREAMING_RECV_TIMEOUT);
+                let packet = match control_receiver.recv(recv_timeout) {
                     Ok(packet) => packet,
                     Err(ConnectionError::TryAgain(_)) => {
-                        if Instant::now() > disconnection_deadline {
+                        if liveness.is_dead(Instant::now()) {
                             info!("Client disconnected. Timeout");
                             break;
                         } else {
@@ -1190,31 +2299,95 @@ fn connection_pipeline(
                 };
 
                 match packet {
-                    ClientControlPacket::PlayspaceSync(packet) => {
+                    ClientControlPacket::PlayspaceSync { area, perimeter } => {
                         if !initial_settings.headset.tracking_ref_only {
                             let session_manager_lock = SESSION_MANAGER.read();
                             let config = &session_manager_lock.settings().headset;
-                            ctx.tracking_manager.write().recenter(
-                                config.position_recentering_mode,
-                                config.rotation_recentering_mode,
-                            );
 
-                            let area = packet.unwrap_or(Vec2::new(2.0, 2.0));
+                            if config.marker_origin.as_option().is_none() {
+                                ctx.tracking_manager.write().clear_marker_origin();
+                            }
+
+                            // The client already moved its origin to the marker
+                            if config
+                                .marker_origin
+                                .as_option()
+                                .is_none_or(|c| c.mode != MarkerOriginMode::Client)
+                            {
+                                ctx.tracking_manager.write().recenter(
+                                    config.position_recentering_mode,
+                                    config.rotation_recentering_mode,
+                                );
+                            }
+
+                            let area = area.unwrap_or(Vec2::new(2.0, 2.0));
                             let wh = area.x * area.y;
-                            if wh.is_finite() && wh > 0.0 {
+                            let (area, perimeter) = if wh.is_finite() && wh > 0.0 {
                                 info!("Received new playspace with size: {}", area);
-                                ctx.events_sender
-                                    .send(ServerCoreEvent::PlayspaceSync(area))
-                                    .ok();
+
+                                (area, perimeter)
                             } else {
                                 warn!("Received invalid playspace size: {}", area);
-                                ctx.events_sender
-                                    .send(ServerCoreEvent::PlayspaceSync(Vec2::new(2.0, 2.0)))
-                                    .ok();
+
+                                (Vec2::new(2.0, 2.0), vec![])
+                            };
+
+                            send_playspace(area, &perimeter);
+                            last_playspace = Some((area, perimeter));
+                        }
+                    }
+                    ClientControlPacket::RestoreRecentering(origin) => {
+                        let marker_origin_mode = SESSION_MANAGER
+                            .read()
+                            .settings()
+                            .headset
+                            .marker_origin
+                            .as_option()
+                            .map(|config| config.mode);
+                        if !initial_settings.headset.tracking_ref_only
+                            && marker_origin_mode != Some(MarkerOriginMode::Client)
+                        {
+                            if marker_origin_mode.is_none() {
+                                ctx.tracking_manager.write().clear_marker_origin();
+                            }
+
+                            info!("Restoring the last recentering");
+                            ctx.tracking_manager.write().set_recentering_origin(origin);
+
+                            if let Some((area, perimeter)) = &last_playspace {
+                                send_playspace(*area, perimeter);
+                            }
+                        }
+                    }
+                    ClientControlPacket::MarkerOrigin(marker_pose) => {
+                        // The marker origin can be disabled during the stream
+                        let marker_origin_enabled = SESSION_MANAGER
+                            .read()
+                            .settings()
+                            .headset
+                            .marker_origin
+                            .as_option()
+                            .is_some();
+                        if !initial_settings.headset.tracking_ref_only && marker_origin_enabled {
+                            ctx.tracking_manager
+                                .write()
+                                .set_marker_origin(marker_pose, marker_follow.as_ref());
+
+                            if let Some((area, perimeter)) = &last_playspace
+                                && (marker_follow.is_none()
+                                    || last_marker_playspace_instant.elapsed()
+                                        > MARKER_PLAYSPACE_INTERVAL)
+                            {
+                                send_playspace(*area, perimeter);
+                                last_marker_playspace_instant = Instant::now();
                             }
                         }
                     }
                     ClientControlPacket::RequestIdr => {
+                        if let Some(stats) = &mut *ctx.statistics_manager.write() {
+                            stats.report_idr_request();
+                        }
+
                         if let Some(config) = ctx.decoder_config.lock().clone() {
                             control_sender
                                 .lock()
@@ -1223,6 +2396,22 @@ fn connection_pipeline(
                         }
                         ctx.events_sender.send(ServerCoreEvent::RequestIDR).ok();
                     }
+                    ClientControlPacket::IntraRefreshRecovery => {
+                        if let Some(stats) = &mut *ctx.statistics_manager.write() {
+                            stats.report_intra_refresh_recovery();
+                        }
+                    }
+                    ClientControlPacket::VideoLossReport(report) => {
+                        {
+                            let metrics = &mut *metrics::METRICS.lock();
+                            metrics.video_frames_received_total += report.frames_received as u64;
+                            metrics.video_frames_lost_total += report.frames_lost as u64;
+                        }
+
+                        if let Some(benchmark) = &mut *ctx.bitrate_benchmark.lock() {
+                            benchmark.report_video_loss(report, Instant::now());
+                        }
+                    }
                     ClientControlPacket::LocalViewParams(params) => {
                         ctx.events_sender
                             .send(ServerCoreEvent::LocalViewParams(params))
@@ -1241,7 +2430,27 @@ fn connection_pipeline(
                             );
                         }
                     }
+                    ClientControlPacket::ThermalStatus(status) => {
+                        if status >= ThermalStatus::Severe {
+                            warn!("Client {client_hostname} is thermal throttling ({status:?})");
+                        }
+
+                        if let Some(stats) = &mut *ctx.statistics_manager.write() {
+                            stats.report_thermal_status(status);
+                        }
+
+                        ctx.bitrate_manager.lock().report_thermal_status(status);
+                    }
                     ClientControlPacket::Buttons(entries) => {
+                        let idle_exited = ctx
+                            .idle_detector
+                            .lock()
+                            .as_mut()
+                            .and_then(|detector| detector.report_input(Instant::now()));
+                        if idle_exited.is_some() {
+                            crate::set_idle(&ctx, None);
+                        }
+
                         {
                             let session_manager_lock = SESSION_MANAGER.read();
                             if session_manager_lock
@@ -1265,6 +2474,58 @@ fn connection_pipeline(
                             }
                         }
 
+                        if let Some(button_id) = latency_test_button_id
+                            && let Some(entry) = entries.iter().find(|e| e.path_id == button_id)
+                        {
+                            let pressed = match entry.value {
+                                ButtonValue::Binary(value) => value,
+                                ButtonValue::Scalar(value) => value > 0.5,
+                            };
+
+                            // Frames with a newer target timestamp are the first ones rendered
+                            // with the tracking data received after the input
+                            if pressed
+                                && !latency_test_button_pressed
+                                && let Some(timestamp) = ctx
+                                    .statistics_manager
+                                    .read()
+                                    .as_ref()
+                                    .and_then(|stats| stats.last_tracking_timestamp())
+                            {
+                                info!(
+                                    "Latency test: input received at {:?}, flashing frames after timestamp {timestamp:?}",
+                                    SystemTime::now()
+                                        .duration_since(UNIX_EPOCH)
+                                        .unwrap_or_default()
+                                );
+
+                                control_sender
+                                    .lock()
+                                    .send(&ServerControlPacket::LatencyTestFlash(timestamp))
+                                    .ok();
+                            }
+                            latency_test_button_pressed = pressed;
+                        }
+
+                        // The macros can be edited while streaming
+                        if let Switch::Enabled(config) =
+                            &SESSION_MANAGER.read().settings().headset.controllers
+                            && config.button_macros != button_macros_config
+                        {
+                            button_macros_config = config.button_macros.clone();
+                            button_macro_manager = ButtonMacroManager::new(&button_macros_config);
+                        }
+                        let entries = button_macro_manager.process_buttons(entries, Instant::now());
+
+                        let entries = if let Switch::Enabled(config) =
+                            &SESSION_MANAGER.read().settings().headset.controllers
+                            && let Switch::Enabled(mode) = &config.analog_processing
+                        {
+                            analog_input_processor.process(mode, &entries)
+                        } else {
+                            entries
+                        };
+
                         if let Some(manager) = &mut controller_button_mapping_manager {
                             let button_entries = entries
                                 .iter()
@@ -1278,7 +2539,13 @@ fn connection_pipeline(
                             }
                         };
                     }
-                    ClientControlPacket::ActiveInteractionProfile { input_ids, .. } => {
+                    ClientControlPacket::ActiveInteractionProfile {
+                        profile_id,
+                        input_ids,
+                        ..
+                    } => {
+                        analog_input_processor.set_profile(profile_id);
+
                         controller_button_mapping_manager = if let Switch::Enabled(config) =
                             &SESSION_MANAGER.read().settings().headset.controllers
                         {
@@ -1306,23 +2573,224 @@ fn connection_pipeline(
                             .send(ServerCoreEvent::ProximityState(headset_is_worn))
                             .ok();
                     }
+                    ClientControlPacket::StandbyState(in_standby) => {
+                        if in_standby != standby_start.is_some() {
+                            info!(
+                                "Client {client_hostname} {} standby",
+                                if in_standby { "entered" } else { "left" }
+                            );
+
+                            standby_start = in_standby.then(Instant::now);
+                            client_in_standby.set(in_standby);
+
+                            if standby_behavior != StandbyBehavior::KeepStreaming {
+                                set_encoding_paused(in_standby);
+                            }
+                        }
+                    }
+                    ClientControlPacket::TimeSyncResponse {
+                        server_time,
+                        client_time,
+                    } => {
+                        if let Some(stats) = &mut *ctx.statistics_manager.write() {
+                            stats.report_time_sync(
+                                server_time,
+                                client_time,
+                                clock_sync::server_time(),
+                            );
+                        }
+                    }
+                    ClientControlPacket::TraceSpans(client_spans) => {
+                        let server_spans = server_trace_spans.lock().take().unwrap_or_default();
+
+                        let clock_estimate = ctx
+                            .statistics_manager
+                            .read()
+                            .as_ref()
+                            .and_then(|stats| stats.clock_estimate());
+                        let (trace, aligned) = chrome_trace::merge_traces(
+                            &server_spans,
+                            &client_spans,
+                            clock_estimate.as_ref(),
+                        );
+                        if !aligned {
+                            warn!(
+                                "The client clock is not synchronized, the client and streamer traces are not aligned"
+                            );
+                        }
+
+                        let path = FILESYSTEM_LAYOUT.get().unwrap().log_dir.join(format!(
+                            "trace.{client_hostname}.{}.json",
+                            chrono::Local::now().format("%F.%H-%M-%S")
+                        ));
+
+                        match fs::write(&path, trace.to_string()) {
+                            Ok(()) => info!("Trace saved to {}", path.display()),
+                            Err(e) => error!("Failed to save the trace: {e}"),
+                        }
+                    }
+                    ClientControlPacket::ClientLog(log) => {
+                        let path = FILESYSTEM_LAYOUT.get().unwrap().log_dir.join(format!(
+                            "client_log.{client_hostname}.{}.txt",
+                            chrono::Local::now().format("%F.%H-%M-%S")
+                        ));
+
+                        match fs::write(&path, log) {
+                            Ok(()) => info!("Client log saved to {}", path.display()),
+                            Err(e) => error!("Failed to save the client log: {e}"),
+                        }
+                    }
+                    ClientControlPacket::InHeadsetMenu(action) => {
+                        info!("Client {client_hostname} in-headset menu: {action:?}");
+
+                        match action {
+                            InHeadsetMenuAction::SetBitrateMbps(bitrate_mbps) => {
+                                ctx.bitrate_manager.lock().set_menu_bitrate(bitrate_mbps);
+                            }
+                            InHeadsetMenuAction::Recenter => recenter(&last_playspace),
+                            InHeadsetMenuAction::SetPassthrough(enabled) => {
+                                *passthrough_override.lock() =
+                                    Some(enabled.then(configured_passthrough_mode));
+                            }
+                            InHeadsetMenuAction::SetSharpening(enabled) => {
+                                set_sharpening(|_| enabled)
+                            }
+                        }
+                    }
+                    ClientControlPacket::TrackingSpace(space) => {
+                        info!("Client {client_hostname} tracking space: {space:?}");
+
+                        if space == TrackingSpace::Local
+                            && !matches!(
+                                SESSION_MANAGER
+                                    .read()
+                                    .settings()
+                                    .headset
+                                    .position_recentering_mode,
+                                PositionRecenteringMode::Local { .. }
+                            )
+                        {
+                            warn!(
+                                "The headset does not have a floor reference, set the position recentering mode to Local"
+                            );
+                        }
+                    }
+                    ClientControlPacket::Clipboard(text) => {
+                        if let Some(sender) = &remote_clipboard_sender {
+                            sender.send(text).ok();
+                        }
+                    }
+                    ClientControlPacket::WifiLinkInfo(link) => {
+                        let bitrate_mbps =
+                            match &SESSION_MANAGER.read().settings().video.bitrate.mode {
+                                BitrateMode::ConstantMbps(bitrate_mbps) => Some(*bitrate_mbps),
+                                BitrateMode::Adaptive {
+                                    max_throughput_mbps,
+                                    ..
+                                } => max_throughput_mbps.as_option().copied(),
+                            };
+
+                        let advice = wifi_advisor.update(link.as_ref(), bitrate_mbps);
+                        for transition in advice.transitions {
+                            info!("Client {client_hostname}: {transition}");
+
+                            if let Some(stats) = &mut *ctx.statistics_manager.write() {
+                                stats.report_wifi_transition(transition);
+                            }
+                        }
+                        for warning in advice.warnings {
+                            warn!("Client {client_hostname}: {warning}");
+                        }
+
+                        alvr_events::send_event(EventType::WifiLink {
+                            hostname: client_hostname.clone(),
+                            link,
+                        });
+                    }
+                    ClientControlPacket::BiometricStreamsBlocked(blocked) => {
+                        info!(
+                            "Client {client_hostname} {} the biometric streams",
+                            if blocked { "blocked" } else { "unblocked" }
+                        );
+
+                        ctx.biometric_streams_blocked.set(blocked);
+                        alvr_events::send_event(EventType::BiometricStreamsBlocked {
+                            hostname: client_hostname.clone(),
+                            blocked,
+                        });
+                    }
+                    // Run with the button macros, at the start of the next iteration
+                    ClientControlPacket::StreamerAction(action) => {
+                        button_macro_manager.queue_action(action);
+                    }
+                    ClientControlPacket::FoveatedEncodingApplied(eyes) => {
+                        if eyes != foveated_encoding_eyes {
+                            warn!(
+                                "Client {client_hostname} applied different foveation parameters \
+                                than the server, the image will be distorted"
+                            );
+                        }
+                    }
+                    ClientControlPacket::PeripheralInput(input) => {
+                        if let Some(injector) = &peripheral_input_injector {
+                            injector.lock().inject(input);
+                        }
+                    }
+                    ClientControlPacket::RestartAck => {
+                        restart_acked = true;
+                        break;
+                    }
                     ClientControlPacket::Reserved(_) | ClientControlPacket::ReservedBuffer(_) => (),
                 }
 
-                disconnection_deadline = Instant::now() + KEEPALIVE_TIMEOUT;
+                liveness.report_received(Instant::now());
+            }
+
+            if ctx.biometric_streams_blocked.value() {
+                ctx.biometric_streams_blocked.set(false);
+                alvr_events::send_event(EventType::BiometricStreamsBlocked {
+                    hostname: client_hostname.clone(),
+                    blocked: false,
+                });
+            }
+
+            // The encoder is reused by the next connection
+            if standby_start.is_some() && standby_behavior != StandbyBehavior::KeepStreaming {
+                set_encoding_paused(false);
             }
 
-            disconnect_notif.notify_one()
+            disconnect_notif.notify_one();
+
+            (control_receiver, restart_acked)
         }
     });
 
     let stream_receive_thread = thread::spawn({
         let disconnect_notif = Arc::clone(&disconnect_notif);
         let client_hostname = client_hostname.clone();
+        let mut liveness = keepalive_config
+            .stream_inactivity_timeout_ms
+            .into_option()
+            .map(|timeout_ms| PeerLiveness::new(Duration::from_millis(timeout_ms), Instant::now()));
         move || {
             while is_streaming(&client_hostname) {
+                if let Some(liveness) = &mut liveness {
+                    liveness.set_paused(client_in_standby.value(), Instant::now());
+                    if liveness.is_dead(Instant::now()) {
+                        info!("Client disconnected. No stream packets received");
+
+                        disconnect_notif.notify_one();
+
+                        return;
+                    }
+                }
+
                 match stream_socket.recv() {
-                    Ok(()) => (),
+                    Ok(()) => {
+                        if let Some(liveness) = &mut liveness {
+                            liveness.report_received(Instant::now());
+                        }
+                    }
                     Err(ConnectionError::TryAgain(_)) => continue,
                     Err(e) => {
                         info!("Client disconnected. Cause: {e}");
@@ -1337,6 +2805,8 @@ fn connection_pipeline(
     });
 
     let lifecycle_check_thread = thread::spawn({
+        let ctx = Arc::clone(&ctx);
+        let control_sender = Arc::clone(&control_sender);
         let disconnect_notif = Arc::clone(&disconnect_notif);
         let client_hostname = client_hostname.clone();
         move || {
@@ -1350,7 +2820,19 @@ fn connection_pipeline(
                 thread::sleep(STREAMING_RECV_TIMEOUT);
             }
 
-            disconnect_notif.notify_one()
+            // The sockets are handed over to the dashboard after the threads stop, the client
+            // waits for the new driver on the same connection
+            let restart_notified = *lifecycle_state.read() == LifecycleState::ShuttingDown
+                && ctx.driver_restarting.value()
+                && is_connection_keepable()
+                && control_sender
+                    .lock()
+                    .send(&ServerControlPacket::DriverRestarting)
+                    .is_ok();
+
+            disconnect_notif.notify_one();
+
+            restart_notified
         }
     });
 
@@ -1384,18 +2866,42 @@ fn connection_pipeline(
         .send(ServerCoreEvent::ClientConnected)
         .ok();
 
+    if let Switch::Enabled(config) = &initial_settings.video.idle_throttling {
+        *ctx.idle_detector.lock() = Some(IdleDetector::new(config, Instant::now()));
+        // The scene changes are detected on the mirror frames
+        ctx.events_sender
+            .send(ServerCoreEvent::SetMirrorEnabled(true))
+            .ok();
+    }
+
     dbg_connection!("connection_pipeline: handshake finished; unlocking streams");
     alvr_common::wait_rwlock(&disconnect_notif, &mut session_manager_lock);
     dbg_connection!("connection_pipeline: Begin connection shutdown");
 
     // This requests shutdown from threads
     *ctx.video_channel_sender.lock() = None;
+    // The spectators disconnect with the headset
+    *ctx.headset_stream_config.lock() = None;
+    ctx.spectator_video_senders.lock().clear();
     *ctx.haptics_sender.lock() = None;
+    *ctx.depth_sender.lock() = None;
+    *ctx.motion_vectors_sender.lock() = None;
+    if ctx.idle_detector.lock().take().is_some() {
+        ctx.events_sender
+            .send(ServerCoreEvent::SetIdleFrameRate(None))
+            .ok();
+        ctx.events_sender
+            .send(ServerCoreEvent::SetMirrorEnabled(
+                ctx.mirror_requested.value(),
+            ))
+            .ok();
+    }
+    metrics::METRICS.lock().reset_stream();
 
     *ctx.video_recording_file.lock() = None;
 
     session_manager_lock.update_client_connections(
-        client_hostname,
+        client_hostname.clone(),
         ClientConnectionsAction::SetConnectionState(ConnectionState::Disconnecting),
     );
 
@@ -1430,11 +2936,24 @@ fn connection_pipeline(
     microphone_thread.join().ok();
     tracking_receive_thread.join().ok();
     statistics_thread.join().ok();
+    peripheral_input_thread.join().ok();
+    clipboard_thread.join().ok();
     real_time_update_thread.join().ok();
-    control_receive_thread.join().ok();
+    bitrate_benchmark_thread.join().ok();
+    let control_receive_result = control_receive_thread.join();
     stream_receive_thread.join().ok();
     keepalive_thread.join().ok();
-    lifecycle_check_thread.join().ok();
+    time_sync_thread.join().ok();
+    if lifecycle_check_thread.join().unwrap_or(false)
+        && let Ok((control_receiver, restart_acked)) = control_receive_result
+    {
+        hand_over_connection(
+            &client_hostname,
+            control_receiver,
+            restart_acked,
+            udp_socket,
+        );
+    }
 
     ctx.events_sender
         .send(ServerCoreEvent::ClientDisconnected)
@@ -1444,3 +2963,186 @@ fn connection_pipeline(
 
     Ok(())
 }
+
+// Flat display clients show the video of the headset. They don't send tracking and they don't
+// become the active client, the streaming settings are the ones negotiated with the headset.
+fn spectator_pipeline(
+    ctx: Arc<ConnectionContext>,
+    lifecycle_state: Arc<RwLock<LifecycleState>>,
+    mut session_manager_lock: RwLockWriteGuard<'_, ServerSessionManager>,
+    mut proto_socket: ProtoControlSocket,
+    client_hostname: String,
+    client_ip: IpAddr,
+) -> ConResult {
+    dbg_connection!("spectator_pipeline: Begin");
+
+    let Some(stream_config_packet) = ctx.headset_stream_config.lock().clone() else {
+        debug!("Flat display client {client_hostname} waits for a headset to connect");
+        return Ok(());
+    };
+    proto_socket.send(&stream_config_packet).to_con()?;
+
+    let settings = session_manager_lock.settings().clone();
+    let keepalive_timeout = Duration::from_millis(settings.connection.keepalive.timeout_ms);
+
+    let (mut control_sender, mut control_receiver) =
+        proto_socket.split(STREAMING_RECV_TIMEOUT).to_con()?;
+    control_sender
+        .set_write_timeout(keepalive_timeout)
+        .to_con()?;
+
+    control_sender
+        .send(&ServerControlPacket::StartStream)
+        .to_con()?;
+    let signal = control_receiver.recv(HANDSHAKE_ACTION_TIMEOUT)?;
+    if !matches!(signal, ClientControlPacket::StreamReady) {
+        con_bail!("Got unexpected packet waiting for stream ack");
+    }
+
+    let stream_protocol = if client_ip.is_loopback() {
+        SocketProtocol::Tcp
+    } else {
+        settings.connection.stream_protocol
+    };
+    let mut stream_socket = StreamSocketBuilder::connect_to_client(
+        HANDSHAKE_ACTION_TIMEOUT,
+        client_ip,
+        settings.connection.stream_port,
+        stream_protocol,
+        settings.connection.dscp,
+        settings.connection.server_buffer_config,
+        settings.connection.packet_size as _,
+    )?;
+    stream_socket
+        .set_write_timeout(keepalive_timeout)
+        .to_con()?;
+    let mut video_sender = stream_socket.request_stream_with_priority(VIDEO, StreamPriority::Low);
+
+    let (video_channel_sender, video_channel_receiver) =
+        mpsc::sync_channel(settings.connection.max_queued_server_video_frames);
+    ctx.spectator_video_senders
+        .lock()
+        .push(video_channel_sender);
+
+    let video_send_thread = thread::spawn({
+        let ctx = Arc::clone(&ctx);
+        let client_hostname = client_hostname.clone();
+        move || {
+            while is_streaming(&client_hostname) {
+                let VideoPacket {
+                    mut header,
+                    payload,
+                } = match video_channel_receiver.recv_timeout(STREAMING_RECV_TIMEOUT) {
+                    Ok(packet) => packet,
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    // The headset disconnected
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+
+                ctx.tracking_manager
+                    .read()
+                    .unrecenter_view_params(&mut header.global_view_params);
+
+                video_sender
+                    .send_header_with_payload(&header, &payload)
+                    .ok();
+            }
+
+            set_disconnecting(&client_hostname);
+        }
+    });
+
+    let stream_receive_thread = thread::spawn({
+        let client_hostname = client_hostname.clone();
+        move || {
+            while is_streaming(&client_hostname) {
+                match stream_socket.recv() {
+                    Ok(()) | Err(ConnectionError::TryAgain(_)) => (),
+                    Err(e) => {
+                        info!("Flat display client disconnected. Cause: {e}");
+                        break;
+                    }
+                }
+            }
+
+            set_disconnecting(&client_hostname);
+        }
+    });
+
+    // The client needs the decoder config before the next IDR
+    if let Some(config) = ctx.decoder_config.lock().clone() {
+        control_sender
+            .send(&ServerControlPacket::DecoderConfig(config))
+            .ok();
+    }
+    ctx.events_sender.send(ServerCoreEvent::RequestIDR).ok();
+
+    session_manager_lock.update_client_connections(
+        client_hostname.clone(),
+        ClientConnectionsAction::SetConnectionState(ConnectionState::Streaming),
+    );
+    drop(session_manager_lock);
+
+    info!("Flat display client {client_hostname} connected");
+
+    let keepalive_interval = Duration::from_millis(settings.connection.keepalive.interval_ms);
+    let mut liveness = PeerLiveness::new(keepalive_timeout, Instant::now());
+    let mut last_keepalive_instant = Instant::now();
+    while is_streaming(&client_hostname) && *lifecycle_state.read() == LifecycleState::Resumed {
+        if last_keepalive_instant.elapsed() > keepalive_interval {
+            if let Err(e) = control_sender.send(&ServerControlPacket::KeepAlive) {
+                info!("Flat display client disconnected. Cause: {e:?}");
+                break;
+            }
+            last_keepalive_instant = Instant::now();
+        }
+
+        match control_receiver.recv(keepalive_interval.min(STREAMING_RECV_TIMEOUT)) {
+            Ok(packet) => {
+                liveness.report_received(Instant::now());
+
+                if let ClientControlPacket::RequestIdr = packet {
+                    if let Some(config) = ctx.decoder_config.lock().clone() {
+                        control_sender
+                            .send(&ServerControlPacket::DecoderConfig(config))
+                            .ok();
+                    }
+                    ctx.events_sender.send(ServerCoreEvent::RequestIDR).ok();
+                }
+            }
+            Err(ConnectionError::TryAgain(_)) => {
+                if liveness.is_dead(Instant::now()) {
+                    info!("Flat display client disconnected. Timeout");
+                    break;
+                }
+            }
+            Err(e) => {
+                info!("Flat display client disconnected. Cause: {e}");
+                break;
+            }
+        }
+    }
+
+    set_disconnecting(&client_hostname);
+
+    video_send_thread.join().ok();
+    stream_receive_thread.join().ok();
+
+    dbg_connection!("spectator_pipeline: End");
+
+    Ok(())
+}
+
+fn set_disconnecting(client_hostname: &str) {
+    let mut session_manager_lock = SESSION_MANAGER.write();
+    if session_manager_lock
+        .client_list()
+        .get(client_hostname)
+        .is_some_and(|c| c.connection_state == ConnectionState::Streaming)
+    {
+        session_manager_lock.update_client_connections(
+            client_hostname.to_owned(),
+            ClientConnectionsAction::SetConnectionState(ConnectionState::Disconnecting),
+        );
+    }
+}
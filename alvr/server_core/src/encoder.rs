@@ -0,0 +1,201 @@
+use alvr_common::glam::UVec2;
+use alvr_session::{
+    CodecType, EncoderConfig, EncoderEffort, EncoderQualityPreset, EncoderQualityPresetNvidia,
+    NvencMultiPass, NvencTuningPreset,
+};
+
+// The encoded views must be multiples of this size, which covers the block size of all codecs and
+// the alignment required by the hardware encoders
+pub const VIEW_RESOLUTION_ALIGNMENT: u32 = 32;
+
+// Backend specific presets that are controlled by the encoder effort setting
+#[derive(Clone, Debug, PartialEq)]
+pub struct EncoderPresets {
+    pub nvenc_quality_preset: EncoderQualityPresetNvidia,
+    pub nvenc_tuning_preset: NvencTuningPreset,
+    pub nvenc_multi_pass: NvencMultiPass,
+    // Used by AMF, VAAPI, Vulkan Video and QSV
+    pub quality_preset: EncoderQualityPreset,
+    pub amf_use_preproc: bool,
+    pub amf_enable_pre_analysis: bool,
+}
+
+// Speed matches the defaults of the detailed options. P6/P7 and the HighQuality tuning are left
+// out because they are too slow for streaming.
+pub fn encoder_presets(effort: EncoderEffort, config: &EncoderConfig) -> EncoderPresets {
+    match effort {
+        EncoderEffort::Speed => EncoderPresets {
+            nvenc_quality_preset: EncoderQualityPresetNvidia::P1,
+            nvenc_tuning_preset: NvencTuningPreset::LowLatency,
+            nvenc_multi_pass: NvencMultiPass::QuarterResolution,
+            quality_preset: EncoderQualityPreset::Speed,
+            amf_use_preproc: false,
+            amf_enable_pre_analysis: false,
+        },
+        EncoderEffort::Balanced => EncoderPresets {
+            nvenc_quality_preset: EncoderQualityPresetNvidia::P3,
+            nvenc_tuning_preset: NvencTuningPreset::LowLatency,
+            nvenc_multi_pass: NvencMultiPass::QuarterResolution,
+            quality_preset: EncoderQualityPreset::Balanced,
+            amf_use_preproc: false,
+            amf_enable_pre_analysis: false,
+        },
+        EncoderEffort::Quality => EncoderPresets {
+            nvenc_quality_preset: EncoderQualityPresetNvidia::P5,
+            nvenc_tuning_preset: NvencTuningPreset::LowLatency,
+            nvenc_multi_pass: NvencMultiPass::FullResolution,
+            quality_preset: EncoderQualityPreset::Quality,
+            // Pre-analysis requires preproc
+            amf_use_preproc: true,
+            amf_enable_pre_analysis: true,
+        },
+        EncoderEffort::Custom => EncoderPresets {
+            nvenc_quality_preset: config.nvenc.quality_preset,
+            nvenc_tuning_preset: config.nvenc.tuning_preset,
+            nvenc_multi_pass: config.nvenc.multi_pass,
+            quality_preset: config.quality_preset,
+            amf_use_preproc: config.amf.use_preproc,
+            amf_enable_pre_analysis: config.amf.enable_pre_analysis,
+        },
+    }
+}
+
+// Rounds down to the required alignment
+pub fn align_view_resolution(resolution: UVec2) -> UVec2 {
+    (resolution / VIEW_RESOLUTION_ALIGNMENT).max(UVec2::ONE) * VIEW_RESOLUTION_ALIGNMENT
+}
+
+// Maximum size of the encoded frame, which contains the two views side by side. These are the
+// limits of the hardware encoders for each codec.
+pub fn max_frame_resolution(codec: CodecType) -> UVec2 {
+    match codec {
+        CodecType::H264 => UVec2::new(4096, 4096),
+        CodecType::Hevc | CodecType::AV1 => UVec2::new(8192, 8192),
+    }
+}
+
+// Scales down the view resolution keeping the aspect ratio if the encoded frame would exceed the
+// codec limits. The result is aligned.
+pub fn limit_view_resolution(resolution: UVec2, codec: CodecType) -> UVec2 {
+    let max_frame = max_frame_resolution(codec);
+    let frame = UVec2::new(resolution.x * 2, resolution.y);
+
+    let scale = f32::min(
+        max_frame.x as f32 / frame.x as f32,
+        max_frame.y as f32 / frame.y as f32,
+    );
+
+    if scale < 1.0 {
+        align_view_resolution((resolution.as_vec2() * scale).as_uvec2())
+    } else {
+        align_view_resolution(resolution)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alvr_session::SessionConfig;
+
+    fn default_encoder_config() -> EncoderConfig {
+        SessionConfig::default().to_settings().video.encoder_config
+    }
+
+    #[test]
+    fn test_view_resolution_alignment() {
+        assert_eq!(
+            align_view_resolution(UVec2::new(2144, 2240)),
+            UVec2::new(2144, 2240)
+        );
+        // Quest 3 panel
+        assert_eq!(
+            align_view_resolution(UVec2::new(2064, 2208)),
+            UVec2::new(2048, 2208)
+        );
+        assert_eq!(align_view_resolution(UVec2::new(10, 0)), UVec2::new(32, 32));
+    }
+
+    #[test]
+    fn test_view_resolution_codec_limits() {
+        let resolution = UVec2::new(2880, 2720);
+
+        assert_eq!(
+            limit_view_resolution(resolution, CodecType::Hevc),
+            resolution
+        );
+
+        // Both views must fit in 4096 pixels with h264
+        let limited = limit_view_resolution(resolution, CodecType::H264);
+        assert!(limited.x * 2 <= 4096 && limited.y <= 4096);
+        assert_eq!(limited, align_view_resolution(limited));
+        let aspect_ratio_difference =
+            limited.x as f32 / limited.y as f32 - resolution.x as f32 / resolution.y as f32;
+        assert!(aspect_ratio_difference.abs() < 0.02);
+    }
+
+    #[test]
+    fn test_effort_presets_table() {
+        // Only Custom depends on the detailed options
+        let mut config = default_encoder_config();
+        config.nvenc.quality_preset = EncoderQualityPresetNvidia::P7;
+        config.quality_preset = EncoderQualityPreset::Quality;
+        config.amf.use_preproc = true;
+        for effort in [
+            EncoderEffort::Speed,
+            EncoderEffort::Balanced,
+            EncoderEffort::Quality,
+        ] {
+            assert_eq!(
+                encoder_presets(effort, &config),
+                encoder_presets(effort, &default_encoder_config()),
+                "{effort:?} must not depend on the detailed options"
+            );
+        }
+
+        let speed = encoder_presets(EncoderEffort::Speed, &default_encoder_config());
+        let balanced = encoder_presets(EncoderEffort::Balanced, &default_encoder_config());
+        let quality = encoder_presets(EncoderEffort::Quality, &default_encoder_config());
+        assert!(speed.nvenc_quality_preset as u32 <= balanced.nvenc_quality_preset as u32);
+        assert!(balanced.nvenc_quality_preset as u32 <= quality.nvenc_quality_preset as u32);
+        assert_eq!(speed.quality_preset, EncoderQualityPreset::Speed);
+        assert_eq!(balanced.quality_preset, EncoderQualityPreset::Balanced);
+        assert_eq!(quality.quality_preset, EncoderQualityPreset::Quality);
+        assert!(!quality.amf_enable_pre_analysis || quality.amf_use_preproc);
+    }
+
+    #[test]
+    fn test_default_effort_keeps_detailed_options() {
+        // Existing sessions keep using their detailed options
+        let settings = SessionConfig::default().to_settings();
+        assert_eq!(settings.video.encoder_effort, EncoderEffort::Custom);
+    }
+
+    #[test]
+    fn test_speed_matches_default_options() {
+        assert_eq!(
+            encoder_presets(EncoderEffort::Speed, &default_encoder_config()),
+            encoder_presets(EncoderEffort::Custom, &default_encoder_config())
+        );
+    }
+
+    #[test]
+    fn test_custom_uses_detailed_options() {
+        let mut config = default_encoder_config();
+        config.nvenc.quality_preset = EncoderQualityPresetNvidia::P4;
+        config.nvenc.tuning_preset = NvencTuningPreset::UltraLowLatency;
+        config.nvenc.multi_pass = NvencMultiPass::Disabled;
+        config.quality_preset = EncoderQualityPreset::Quality;
+        config.amf.use_preproc = true;
+
+        let presets = encoder_presets(EncoderEffort::Custom, &config);
+        assert_eq!(presets.nvenc_quality_preset, EncoderQualityPresetNvidia::P4);
+        assert_eq!(
+            presets.nvenc_tuning_preset,
+            NvencTuningPreset::UltraLowLatency
+        );
+        assert_eq!(presets.nvenc_multi_pass, NvencMultiPass::Disabled);
+        assert_eq!(presets.quality_preset, EncoderQualityPreset::Quality);
+        assert!(presets.amf_use_preproc);
+        assert!(!presets.amf_enable_pre_analysis);
+    }
+}
@@ -0,0 +1,486 @@
+use alvr_common::{
+    ViewParams,
+    glam::{Quat, UVec2, Vec2, Vec3},
+};
+use alvr_session::OpenvrConfig;
+
+const INVERSE_ITERATIONS: usize = 24;
+
+// Rectangle of the encoded frame in normalized coordinates, with the origin in the top-left corner
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameRect {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+// Frame regions to encode with an offset QP, on the H264/HEVC scale
+#[derive(Clone, Copy, Debug)]
+pub struct GazeRegions {
+    pub rects: [Option<FrameRect>; 2],
+    pub qp_offset: i32,
+}
+
+// Radial foveated encoding: along each axis the source coordinate is a quadratic function of the
+// compressed one around the center, with slope alpha at the center
+#[derive(Clone, Copy, Debug)]
+struct RadialFoveation {
+    center: Vec2,
+    alpha: f32,
+}
+
+// Foveated encoding parameters aligned to the pixel grid, as used by the compression shaders
+#[derive(Clone, Copy, Debug)]
+pub struct FoveationParams {
+    center_size: Vec2,
+    center_shift: Vec2,
+    edge_ratio: Vec2,
+    radial: Option<RadialFoveation>,
+    // Size of the compressed view in pixels, before the alignment for the encoder
+    optimized_resolution: Vec2,
+    // Part of each half of the frame covered by the compressed view, the rest is padding
+    eye_size_ratio: Vec2,
+}
+
+impl FoveationParams {
+    // Same as CalculateFoveationVars() on the C++ side
+    pub fn new(
+        view_resolution: UVec2,
+        center_size: Vec2,
+        center_shift: Vec2,
+        edge_ratio: Vec2,
+    ) -> Self {
+        let view_resolution = view_resolution.as_vec2();
+
+        let edge_size = view_resolution - center_size * view_resolution;
+        let center_size =
+            1.0 - (edge_size / (edge_ratio * 2.0)).ceil() * (edge_ratio * 2.0) / view_resolution;
+
+        let edge_size = view_resolution - center_size * view_resolution;
+        let center_shift = Vec2::select(
+            edge_size.cmpgt(Vec2::ZERO),
+            (center_shift * edge_size / (edge_ratio * 2.0)).ceil() * (edge_ratio * 2.0) / edge_size,
+            Vec2::ZERO,
+        );
+
+        let optimized_resolution =
+            (center_size + (1.0 - center_size) / edge_ratio) * view_resolution;
+        let optimized_resolution_aligned = (optimized_resolution / 32.0).ceil() * 32.0;
+
+        Self {
+            center_size,
+            center_shift,
+            edge_ratio,
+            radial: None,
+            optimized_resolution,
+            eye_size_ratio: optimized_resolution / optimized_resolution_aligned,
+        }
+    }
+
+    // Same as CalculateRadialFoveationVars() on the C++ side
+    pub fn new_radial(
+        view_resolution: UVec2,
+        center_strength: f32,
+        edge_compression: f32,
+        center_shift: Vec2,
+    ) -> Self {
+        let alpha = 2.0 / (edge_compression + 1.0);
+
+        let optimized_resolution = center_strength * alpha * view_resolution.as_vec2();
+        let optimized_resolution_aligned = (optimized_resolution / 32.0).ceil() * 32.0;
+
+        Self {
+            center_size: Vec2::ONE,
+            center_shift: Vec2::ZERO,
+            edge_ratio: Vec2::ONE,
+            radial: Some(RadialFoveation {
+                center: 0.5 + center_shift * 0.25,
+                alpha,
+            }),
+            optimized_resolution,
+            eye_size_ratio: optimized_resolution / optimized_resolution_aligned,
+        }
+    }
+
+    // The halves of the frame have the size of the largest compressed view, the other one is
+    // padded. Same as the C++ side
+    pub fn with_shared_frame(eyes: [Self; 2]) -> [Self; 2] {
+        let optimized_resolution = eyes[0]
+            .optimized_resolution
+            .max(eyes[1].optimized_resolution);
+        let optimized_resolution_aligned = (optimized_resolution / 32.0).ceil() * 32.0;
+
+        eyes.map(|eye| Self {
+            eye_size_ratio: eye.optimized_resolution / optimized_resolution_aligned,
+            ..eye
+        })
+    }
+
+    // Parameters of the left and right views
+    pub fn from_openvr_config(config: &OpenvrConfig) -> Option<[Self; 2]> {
+        if !config.enable_foveated_encoding {
+            return None;
+        }
+
+        let view_resolution = UVec2::new(config.eye_resolution_width, config.eye_resolution_height);
+        let center_shifts = [
+            Vec2::new(
+                config.foveation_center_shift_x,
+                config.foveation_center_shift_y,
+            ),
+            Vec2::new(
+                config.foveation_right_center_shift_x,
+                config.foveation_right_center_shift_y,
+            ),
+        ];
+
+        // ALVR_FOVEATION_MODE_RADIAL
+        let eyes = if config.foveation_mode == 1 {
+            center_shifts.map(|center_shift| {
+                Self::new_radial(
+                    view_resolution,
+                    config.foveation_center_strength,
+                    config.foveation_edge_compression,
+                    center_shift,
+                )
+            })
+        } else {
+            [
+                Self::new(
+                    view_resolution,
+                    Vec2::new(
+                        config.foveation_center_size_x,
+                        config.foveation_center_size_y,
+                    ),
+                    center_shifts[0],
+                    Vec2::new(config.foveation_edge_ratio_x, config.foveation_edge_ratio_y),
+                ),
+                Self::new(
+                    view_resolution,
+                    Vec2::new(
+                        config.foveation_right_center_size_x,
+                        config.foveation_right_center_size_y,
+                    ),
+                    center_shifts[1],
+                    Vec2::new(
+                        config.foveation_right_edge_ratio_x,
+                        config.foveation_right_edge_ratio_y,
+                    ),
+                ),
+            ]
+        };
+
+        Some(Self::with_shared_frame(eyes))
+    }
+
+    // Maps a point of the source view to the compressed view, without the padding
+    fn compressed_coords(&self, source: Vec2) -> Vec2 {
+        if let Some(radial) = &self.radial {
+            return Vec2::new(
+                radial_compressed_coord(source.x, radial.center.x, radial.alpha),
+                radial_compressed_coord(source.y, radial.center.y, radial.alpha),
+            );
+        }
+
+        Vec2::new(
+            foveation_compressed_coord(
+                source.x,
+                self.center_size.x,
+                self.center_shift.x,
+                self.edge_ratio.x,
+            ),
+            foveation_compressed_coord(
+                source.y,
+                self.center_size.y,
+                self.center_shift.y,
+                self.edge_ratio.y,
+            ),
+        )
+    }
+}
+
+// Maps a coordinate of the compressed view to the source view along one axis. Same as the
+// compression shaders.
+fn foveation_source_coord(x: f32, center_size: f32, center_shift: f32, edge_ratio: f32) -> f32 {
+    let c0 = (1.0 - center_size) / 2.0;
+    let c1 = (edge_ratio - 1.0) * c0 * (center_shift + 1.0) / edge_ratio;
+    let c2 = (edge_ratio - 1.0) * center_size + 1.0;
+
+    let lo_bound = c0 * (center_shift + 1.0) / c2;
+    let hi_bound = c0 * (center_shift - 1.0) / c2 + 1.0;
+
+    let center = x * c2 / edge_ratio + c1;
+    if x < lo_bound {
+        let g1 = x / lo_bound;
+        g1 * center + (1.0 - g1) * x * c2
+    } else if x > hi_bound {
+        let g2 = (1.0 - x) / (1.0 - hi_bound);
+        g2 * center + (1.0 - g2) * ((x - 1.0) * c2 + 1.0)
+    } else {
+        center
+    }
+}
+
+// The mapping is monotonic, so it is inverted with a bisection
+fn foveation_compressed_coord(
+    source: f32,
+    center_size: f32,
+    center_shift: f32,
+    edge_ratio: f32,
+) -> f32 {
+    let mut low = 0.0;
+    let mut high = 1.0;
+    for _ in 0..INVERSE_ITERATIONS {
+        let mid = (low + high) / 2.0;
+        if foveation_source_coord(mid, center_size, center_shift, edge_ratio) < source {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    (low + high) / 2.0
+}
+
+// Inverse of the quadratic mapping of the radial compression shaders along one axis
+fn radial_compressed_coord(source: f32, center: f32, alpha: f32) -> f32 {
+    let side = if source < center {
+        center
+    } else {
+        1.0 - center
+    };
+    let t = (source - center) / side;
+    let u = 2.0 * t / (alpha + f32::sqrt(alpha * alpha + 4.0 * (1.0 - alpha) * t.abs()));
+
+    center + u * side
+}
+
+// Normalized coordinates of the gaze point in the view, with the origin in the top-left corner. The
+// gaze and the view pose are relative to the head. Returns None if the gaze is outside of the view.
+pub fn gaze_view_coords(gaze: Quat, view: &ViewParams) -> Option<Vec2> {
+    let direction = view.pose.orientation.conjugate() * gaze * -Vec3::Z;
+    if direction.z >= 0.0 {
+        return None;
+    }
+
+    let tangent = Vec2::new(direction.x, direction.y) / -direction.z;
+    let fov = view.fov;
+    let coords = Vec2::new(
+        (tangent.x - fov.left.tan()) / (fov.right.tan() - fov.left.tan()),
+        (fov.up.tan() - tangent.y) / (fov.up.tan() - fov.down.tan()),
+    );
+
+    (coords.cmpge(Vec2::ZERO).all() && coords.cmple(Vec2::ONE).all()).then_some(coords)
+}
+
+// Maps a point of a view to the encoded frame, which contains the two views side by side
+pub fn view_to_frame_coords(
+    coords: Vec2,
+    is_right_view: bool,
+    foveation: Option<&FoveationParams>,
+) -> Vec2 {
+    let Some(params) = foveation else {
+        let offset = if is_right_view { 0.5 } else { 0.0 };
+
+        return Vec2::new(coords.x / 2.0 + offset, coords.y);
+    };
+
+    // The compression shaders flip the right view horizontally
+    let source = if is_right_view {
+        Vec2::new(1.0 - coords.x, coords.y)
+    } else {
+        coords
+    };
+    let compressed = params.compressed_coords(source) * params.eye_size_ratio;
+
+    if is_right_view {
+        Vec2::new(1.0 - compressed.x / 2.0, compressed.y)
+    } else {
+        Vec2::new(compressed.x / 2.0, compressed.y)
+    }
+}
+
+// Regions of the encoded frame around the gaze point, one for each view. region_size is the side
+// of the square region relative to the view size.
+pub fn gaze_frame_regions(
+    gaze: Quat,
+    views: &[ViewParams; 2],
+    region_size: f32,
+    foveation: Option<&[FoveationParams; 2]>,
+) -> [Option<FrameRect>; 2] {
+    let region = |view_idx: usize| {
+        let center = gaze_view_coords(gaze, &views[view_idx])?;
+        let half_size = Vec2::splat(region_size / 2.0);
+
+        let is_right_view = view_idx == 1;
+        let foveation = foveation.map(|eyes| &eyes[view_idx]);
+        let a = view_to_frame_coords(
+            (center - half_size).max(Vec2::ZERO),
+            is_right_view,
+            foveation,
+        );
+        let b = view_to_frame_coords(
+            (center + half_size).min(Vec2::ONE),
+            is_right_view,
+            foveation,
+        );
+
+        // The corners are swapped horizontally for the flipped right view
+        Some(FrameRect {
+            min: a.min(b),
+            max: a.max(b),
+        })
+    };
+
+    [region(0), region(1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alvr_common::{Fov, Pose};
+
+    const EPSILON: f32 = 1e-3;
+
+    fn view(orientation: Quat) -> ViewParams {
+        ViewParams {
+            pose: Pose {
+                orientation,
+                position: Vec3::ZERO,
+            },
+            fov: Fov {
+                left: -45_f32.to_radians(),
+                right: 45_f32.to_radians(),
+                up: 45_f32.to_radians(),
+                down: -45_f32.to_radians(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_gaze_view_coords() {
+        let center = gaze_view_coords(Quat::IDENTITY, &view(Quat::IDENTITY)).unwrap();
+        assert!(center.abs_diff_eq(Vec2::splat(0.5), EPSILON));
+
+        // Looking left by 40° is close to the left edge of the view
+        let gaze = Quat::from_rotation_y(40_f32.to_radians());
+        let left = gaze_view_coords(gaze, &view(Quat::IDENTITY)).unwrap();
+        let expected_x = (1.0 - 40_f32.to_radians().tan()) / 2.0;
+        assert!(left.abs_diff_eq(Vec2::new(expected_x, 0.5), EPSILON));
+
+        // Past the edge the gaze is not in the view
+        let gaze = Quat::from_rotation_y(50_f32.to_radians());
+        assert!(gaze_view_coords(gaze, &view(Quat::IDENTITY)).is_none());
+
+        let gaze = Quat::from_rotation_x(30_f32.to_radians());
+        let up = gaze_view_coords(gaze, &view(Quat::IDENTITY)).unwrap();
+        assert!(up.y < 0.5);
+
+        // Canted views
+        let canted = gaze_view_coords(
+            Quat::IDENTITY,
+            &view(Quat::from_rotation_y(10_f32.to_radians())),
+        )
+        .unwrap();
+        assert!(canted.x > 0.5);
+
+        // Outside of the view
+        let gaze = Quat::from_rotation_y(60_f32.to_radians());
+        assert!(gaze_view_coords(gaze, &view(Quat::IDENTITY)).is_none());
+    }
+
+    #[test]
+    fn test_foveation_inverse() {
+        let params = FoveationParams::new(
+            UVec2::new(2048, 2048),
+            Vec2::new(0.45, 0.4),
+            Vec2::new(0.4, 0.1),
+            Vec2::new(4.0, 5.0),
+        );
+
+        for i in 0..=20 {
+            let source = i as f32 / 20.0;
+            let compressed = foveation_compressed_coord(
+                source,
+                params.center_size.x,
+                params.center_shift.x,
+                params.edge_ratio.x,
+            );
+            let mapped = foveation_source_coord(
+                compressed,
+                params.center_size.x,
+                params.center_shift.x,
+                params.edge_ratio.x,
+            );
+
+            assert!((mapped - source).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn test_frame_regions() {
+        let views = [view(Quat::IDENTITY), view(Quat::IDENTITY)];
+
+        let [left, right] = gaze_frame_regions(Quat::IDENTITY, &views, 0.2, None);
+        let (left, right) = (left.unwrap(), right.unwrap());
+        assert!(left.min.abs_diff_eq(Vec2::new(0.2, 0.4), EPSILON));
+        assert!(left.max.abs_diff_eq(Vec2::new(0.3, 0.6), EPSILON));
+        assert!(right.min.abs_diff_eq(Vec2::new(0.7, 0.4), EPSILON));
+        assert!(right.max.abs_diff_eq(Vec2::new(0.8, 0.6), EPSILON));
+
+        // With foveated encoding the views are mirrored, so the regions stay symmetric
+        let params = FoveationParams::new(
+            UVec2::new(2048, 2048),
+            Vec2::new(0.45, 0.4),
+            Vec2::ZERO,
+            Vec2::new(4.0, 5.0),
+        );
+        let [left, right] =
+            gaze_frame_regions(Quat::IDENTITY, &views, 0.2, Some(&[params, params]));
+        let (left, right) = (left.unwrap(), right.unwrap());
+        assert!((left.min.x + right.max.x - 1.0).abs() < EPSILON);
+        assert!((left.max.x + right.min.x - 1.0).abs() < EPSILON);
+        assert!((left.min.y - right.min.y).abs() < EPSILON);
+
+        // The center is less compressed, so the region takes a larger part of the frame
+        assert!(left.max.x - left.min.x > 0.1);
+        assert!(left.max.y - left.min.y > 0.2);
+    }
+
+    #[test]
+    fn test_per_eye_frame() {
+        let resolution = UVec2::new(2048, 2048);
+        let left = FoveationParams::new(
+            resolution,
+            Vec2::new(0.45, 0.4),
+            Vec2::ZERO,
+            Vec2::new(4.0, 5.0),
+        );
+        // Larger center region, so a larger compressed view
+        let right = FoveationParams::new(
+            resolution,
+            Vec2::new(0.6, 0.4),
+            Vec2::ZERO,
+            Vec2::new(4.0, 5.0),
+        );
+        assert!(right.optimized_resolution.x > left.optimized_resolution.x);
+
+        let [left, right] = FoveationParams::with_shared_frame([left, right]);
+        assert!(right.eye_size_ratio.x <= 1.0);
+        assert!(left.eye_size_ratio.x < right.eye_size_ratio.x);
+        assert!((left.eye_size_ratio.y - right.eye_size_ratio.y).abs() < EPSILON);
+
+        // The left view does not reach the right half of the frame
+        let edge = view_to_frame_coords(Vec2::ONE, false, Some(&left));
+        assert!(edge.x < 0.5 * right.eye_size_ratio.x);
+
+        // Both centers are at full resolution, but the wider compressed right view moves its region
+        // toward the middle of the frame
+        let views = [view(Quat::IDENTITY), view(Quat::IDENTITY)];
+        let [left_region, right_region] =
+            gaze_frame_regions(Quat::IDENTITY, &views, 0.2, Some(&[left, right]));
+        let (left_region, right_region) = (left_region.unwrap(), right_region.unwrap());
+        let width = |rect: FrameRect| rect.max.x - rect.min.x;
+        assert!((width(left_region) - width(right_region)).abs() < EPSILON);
+        assert!(left_region.min.x + right_region.max.x - 1.0 < -EPSILON);
+    }
+}
@@ -12,13 +12,6 @@ use std::{
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-fn lerp_pose(a: Pose, b: Pose, fac: f32) -> Pose {
-    Pose {
-        orientation: a.orientation.lerp(b.orientation, fac),
-        position: a.position.lerp(b.position, fac),
-    }
-}
-
 pub static HAND_GESTURE_BUTTON_SET: LazyLock<HashSet<u64>> = LazyLock::new(|| {
     HashSet::from([
         *LEFT_X_CLICK_ID,
@@ -241,7 +234,7 @@ impl HandGestureManager {
         let thumb_curl =
             self.get_gesture_hover(palm, palm_depth, thumb_tip, thumb_rad, curl_min, curl_max);
         let index_curl = self.get_gesture_hover(
-            lerp_pose(index_metacarpal, index_proximal, 0.5),
+            index_metacarpal.lerp(index_proximal, 0.5),
             palm_depth,
             index_tip,
             index_rad,
@@ -249,7 +242,7 @@ impl HandGestureManager {
             curl_max,
         );
         let middle_curl = self.get_gesture_hover(
-            lerp_pose(middle_metacarpal, middle_proximal, 0.5),
+            middle_metacarpal.lerp(middle_proximal, 0.5),
             palm_depth,
             middle_tip,
             middle_rad,
@@ -257,7 +250,7 @@ impl HandGestureManager {
             curl_max,
         );
         let ring_curl = self.get_gesture_hover(
-            lerp_pose(ring_metacarpal, ring_proximal, 0.5),
+            ring_metacarpal.lerp(ring_proximal, 0.5),
             palm_depth,
             ring_tip,
             ring_rad,
@@ -265,7 +258,7 @@ impl HandGestureManager {
             curl_max,
         );
         let little_curl = self.get_gesture_hover(
-            lerp_pose(little_metacarpal, little_proximal, 0.5),
+            little_metacarpal.lerp(little_proximal, 0.5),
             palm_depth,
             little_tip,
             little_rad,
@@ -287,7 +280,7 @@ impl HandGestureManager {
 
         // Joystick
         let joystick_range = config.joystick_range * 0.01;
-        let joystick_center = lerp_pose(index_intermediate, index_distal, 0.5);
+        let joystick_center = index_intermediate.lerp(index_distal, 0.5);
 
         let joystick_up = joystick_center
             .orientation
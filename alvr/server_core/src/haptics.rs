@@ -1,15 +1,343 @@
+use alvr_common::settings_schema::Switch;
 use alvr_packets::Haptics;
-use alvr_session::HapticsConfig;
-use std::time::Duration;
+use alvr_session::{
+    HapticsBurstMergingConfig, HapticsConfig, HapticsGameOverride, HapticsLegacyPulsesConfig,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    f32::consts::PI,
+    time::{Duration, Instant},
+};
+
+// TriggerHapticPulse() of the legacy OpenVR input takes a duration of up to 3999µs, which games
+// use as the strength of the pulse. SteamVR forwards it as a vibration of that duration.
+const LEGACY_PULSE_MAX_DURATION: Duration = Duration::from_micros(3999);
+
+// The settings of the running game replace the global ones. The executable names are compared
+// ignoring the case, Windows file names are case insensitive
+pub fn game_config<'a>(
+    config: &'a HapticsConfig,
+    game_overrides: &'a [HapticsGameOverride],
+    executable_name: Option<&str>,
+) -> &'a HapticsConfig {
+    executable_name
+        .and_then(|name| {
+            game_overrides
+                .iter()
+                .find(|game| game.executable_name.eq_ignore_ascii_case(name))
+        })
+        .map_or(config, |game| &game.haptics)
+}
 
 pub fn map_haptics(config: &HapticsConfig, haptics: Haptics) -> Haptics {
+    let amplitude =
+        config.intensity_multiplier * f32::powf(haptics.amplitude, config.amplitude_curve);
+
     Haptics {
         duration: Duration::max(
             haptics.duration,
             Duration::from_secs_f32(config.min_duration_s),
         ),
-        amplitude: config.intensity_multiplier
-            * f32::powf(haptics.amplitude, config.amplitude_curve),
+        amplitude: if amplitude > 0.0 {
+            f32::max(amplitude, config.min_amplitude)
+        } else {
+            amplitude
+        },
+        ..haptics
+    }
+}
+
+fn is_stop(haptics: &Haptics) -> bool {
+    haptics.amplitude <= 0.0 || haptics.duration.is_zero()
+}
+
+#[derive(Default)]
+struct DeviceState {
+    // Time and strength of the legacy pulses inside the window
+    legacy_pulses: VecDeque<(Instant, f32)>,
+    // End and amplitude of the last vibration sent, without the gap extension
+    end: Option<Instant>,
+    amplitude: f32,
+}
+
+// The strength of a pulse is its duration relative to the maximum. The amplitude is the average of
+// the strengths inside the window, weighted with a Hann window so that the latest pulses count
+// more. The vibration lasts a window, the next pulse of the train replaces it.
+fn convert_legacy_pulse(
+    state: &mut DeviceState,
+    config: &HapticsLegacyPulsesConfig,
+    now: Instant,
+    haptics: Haptics,
+) -> Haptics {
+    let window = Duration::from_secs_f32(config.window_s);
+
+    let strength = haptics.duration.as_secs_f32() / LEGACY_PULSE_MAX_DURATION.as_secs_f32()
+        * haptics.amplitude;
+    state.legacy_pulses.push_back((now, strength));
+    while let Some((time, _)) = state.legacy_pulses.front()
+        && now - *time >= window
+    {
+        state.legacy_pulses.pop_front();
+    }
+
+    let mut weighted_sum = 0.0;
+    let mut weights_sum = 0.0;
+    for (time, strength) in &state.legacy_pulses {
+        let weight = 0.5 * (1.0 + f32::cos(PI * (now - *time).as_secs_f32() / config.window_s));
+        weighted_sum += weight * strength;
+        weights_sum += weight;
+    }
+
+    Haptics {
+        duration: window,
+        // The frequency of the legacy pulses is not meaningful, the client picks its own
+        frequency: 0.0,
+        amplitude: weighted_sum / weights_sum,
         ..haptics
     }
 }
+
+// Each event replaces the vibration on the client. An event that ends before the previous
+// vibration keeps its end and the strongest amplitude, otherwise the newer amplitude is used. The
+// vibration is extended by the maximum gap to reach the next event of the burst.
+fn merge_burst(
+    state: &mut DeviceState,
+    config: &HapticsBurstMergingConfig,
+    now: Instant,
+    haptics: Haptics,
+) -> Haptics {
+    if is_stop(&haptics) {
+        state.end = None;
+
+        return haptics;
+    }
+
+    let mut end = now + haptics.duration;
+    let mut amplitude = haptics.amplitude;
+    if let Some(previous_end) = state.end
+        && previous_end > end
+    {
+        end = previous_end;
+        amplitude = f32::max(amplitude, state.amplitude);
+    }
+    state.end = Some(end);
+    state.amplitude = amplitude;
+
+    Haptics {
+        duration: end - now + Duration::from_secs_f32(config.max_gap_s),
+        amplitude,
+        ..haptics
+    }
+}
+
+// Processing of the haptics events of the games before they are sent to the client. The state
+// is kept per device.
+#[derive(Default)]
+pub struct HapticsProcessor {
+    devices: HashMap<u64, DeviceState>,
+}
+
+impl HapticsProcessor {
+    pub fn process(&mut self, config: &HapticsConfig, now: Instant, haptics: Haptics) -> Haptics {
+        let state = self.devices.entry(haptics.device_id).or_default();
+
+        let haptics = match &config.legacy_pulses {
+            Switch::Enabled(legacy_config)
+                if !is_stop(&haptics) && haptics.duration <= LEGACY_PULSE_MAX_DURATION =>
+            {
+                convert_legacy_pulse(state, legacy_config, now, haptics)
+            }
+            _ => haptics,
+        };
+
+        let haptics = map_haptics(config, haptics);
+
+        if let Switch::Enabled(merge_config) = &config.merge_bursts {
+            merge_burst(state, merge_config, now, haptics)
+        } else {
+            haptics
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Time in ms, duration in µs and amplitude of the events
+    type Fixture = &'static [(u64, u64, f32)];
+
+    // A game calling TriggerHapticPulse() every frame at 90 Hz, with pulses at full strength for
+    // 100ms and then at half strength
+    const LEGACY_PULSE_TRAIN: Fixture = &[
+        (0, 3999, 1.0),
+        (11, 3999, 1.0),
+        (22, 3999, 1.0),
+        (33, 3999, 1.0),
+        (44, 3999, 1.0),
+        (55, 3999, 1.0),
+        (66, 3999, 1.0),
+        (77, 3999, 1.0),
+        (88, 3999, 1.0),
+        (100, 2000, 1.0),
+        (111, 2000, 1.0),
+        (122, 2000, 1.0),
+        (133, 2000, 1.0),
+        (144, 2000, 1.0),
+        (155, 2000, 1.0),
+        (166, 2000, 1.0),
+    ];
+
+    // A burst of short events every 10ms, a long rumble and a weak tick during the rumble,
+    // followed by a stop request
+    const SHORT_BURST: Fixture = &[
+        (0, 2000, 0.8),
+        (10, 2000, 0.8),
+        (20, 2000, 0.8),
+        (30, 2000, 0.8),
+        (100, 500_000, 1.0),
+        (200, 5000, 0.2),
+        (300, 0, 0.0),
+    ];
+
+    fn config() -> HapticsConfig {
+        HapticsConfig {
+            intensity_multiplier: 1.0,
+            amplitude_curve: 1.0,
+            min_duration_s: 0.0,
+            min_amplitude: 0.0,
+            merge_bursts: Switch::Disabled,
+            legacy_pulses: Switch::Disabled,
+        }
+    }
+
+    // The durations in seconds of the settings are not exact in f32
+    fn assert_duration_ms(duration: Duration, expected_ms: f32) {
+        assert!(
+            (duration.as_secs_f32() * 1000.0 - expected_ms).abs() < 1e-3,
+            "{duration:?} != {expected_ms}ms"
+        );
+    }
+
+    fn play(config: &HapticsConfig, fixture: Fixture) -> Vec<Haptics> {
+        let start = Instant::now();
+        let mut processor = HapticsProcessor::default();
+
+        fixture
+            .iter()
+            .map(|&(time_ms, duration_us, amplitude)| {
+                processor.process(
+                    config,
+                    start + Duration::from_millis(time_ms),
+                    Haptics {
+                        device_id: 1,
+                        duration: Duration::from_micros(duration_us),
+                        frequency: 160.0,
+                        amplitude,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_unprocessed() {
+        let output = play(&config(), SHORT_BURST);
+
+        for (haptics, &(_, duration_us, amplitude)) in output.iter().zip(SHORT_BURST) {
+            assert_eq!(haptics.duration, Duration::from_micros(duration_us));
+            assert_eq!(haptics.amplitude, amplitude);
+            assert_eq!(haptics.frequency, 160.0);
+        }
+    }
+
+    #[test]
+    fn test_legacy_pulses() {
+        let config = HapticsConfig {
+            legacy_pulses: Switch::Enabled(HapticsLegacyPulsesConfig { window_s: 0.05 }),
+            ..config()
+        };
+        let output = play(&config, LEGACY_PULSE_TRAIN);
+
+        for haptics in &output {
+            assert_duration_ms(haptics.duration, 50.0);
+            assert_eq!(haptics.frequency, 0.0);
+        }
+
+        // Full strength until the first weaker pulse, then a smooth decrease towards half
+        // strength, reached once the window contains only the weaker pulses
+        for haptics in &output[..9] {
+            assert!((haptics.amplitude - 1.0).abs() < 1e-4);
+        }
+        for pair in output[9..].windows(2) {
+            assert!(pair[1].amplitude <= pair[0].amplitude + 1e-6);
+        }
+        assert!(output[9].amplitude < 1.0 && output[9].amplitude > 0.6);
+        let half = 2000.0 / 3999.0;
+        assert!((output.last().unwrap().amplitude - half).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_merge_bursts() {
+        let config = HapticsConfig {
+            merge_bursts: Switch::Enabled(HapticsBurstMergingConfig { max_gap_s: 0.03 }),
+            ..config()
+        };
+        let output = play(&config, SHORT_BURST);
+
+        // The short events last until the next one of the burst
+        for haptics in &output[..4] {
+            assert_duration_ms(haptics.duration, 32.0);
+            assert_eq!(haptics.amplitude, 0.8);
+        }
+
+        assert_duration_ms(output[4].duration, 530.0);
+        assert_eq!(output[4].amplitude, 1.0);
+
+        // The tick does not shorten nor weaken the rumble
+        assert_duration_ms(output[5].duration, 430.0);
+        assert_eq!(output[5].amplitude, 1.0);
+
+        assert!(output[6].duration.is_zero());
+        assert_eq!(output[6].amplitude, 0.0);
+    }
+
+    #[test]
+    fn test_floors() {
+        let config = HapticsConfig {
+            min_duration_s: 0.01,
+            min_amplitude: 0.5,
+            ..config()
+        };
+        let output = play(&config, SHORT_BURST);
+
+        assert_duration_ms(output[0].duration, 10.0);
+        assert_eq!(output[0].amplitude, 0.8);
+        assert_eq!(output[4].duration, Duration::from_millis(500));
+        assert_eq!(output[5].amplitude, 0.5);
+
+        // Stop requests keep the zero amplitude
+        assert_eq!(output[6].amplitude, 0.0);
+    }
+
+    #[test]
+    fn test_game_override() {
+        let game_overrides = [HapticsGameOverride {
+            executable_name: "Game.exe".into(),
+            haptics: HapticsConfig {
+                intensity_multiplier: 2.0,
+                ..config()
+            },
+        }];
+        let global = config();
+
+        let selected = game_config(&global, &game_overrides, Some("game.EXE"));
+        assert_eq!(selected.intensity_multiplier, 2.0);
+        assert_eq!(play(selected, SHORT_BURST)[0].amplitude, 1.6);
+
+        for executable_name in [None, Some("other.exe")] {
+            let selected = game_config(&global, &game_overrides, executable_name);
+            assert_eq!(selected.intensity_multiplier, 1.0);
+        }
+    }
+}
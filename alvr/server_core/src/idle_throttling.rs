@@ -0,0 +1,222 @@
+use alvr_common::glam::UVec2;
+use alvr_session::IdleThrottlingConfig;
+use std::time::{Duration, Instant};
+
+// The frames are compared on a grid of luma samples, cheap enough to run on every mirror frame
+const SAMPLE_GRID_SIZE: usize = 64;
+// Mean absolute difference of the samples, out of 255, above which the scene changed. It tolerates
+// the jitter of a headset resting still and small animations like a blinking cursor
+const CHANGE_THRESHOLD: f32 = 1.0;
+
+fn sample_luma(resolution: UVec2, rgba: &[u8]) -> Vec<u8> {
+    let width = resolution.x as usize;
+    let height = resolution.y as usize;
+    if width == 0 || height == 0 || rgba.len() < width * height * 4 {
+        return vec![];
+    }
+
+    let mut samples = Vec::with_capacity(SAMPLE_GRID_SIZE * SAMPLE_GRID_SIZE);
+    for grid_y in 0..SAMPLE_GRID_SIZE {
+        let y = (2 * grid_y + 1) * height / (2 * SAMPLE_GRID_SIZE);
+        for grid_x in 0..SAMPLE_GRID_SIZE {
+            let x = (2 * grid_x + 1) * width / (2 * SAMPLE_GRID_SIZE);
+            let pixel = &rgba[(y * width + x) * 4..][..3];
+            let luma = 77 * pixel[0] as u32 + 150 * pixel[1] as u32 + 29 * pixel[2] as u32;
+            samples.push((luma >> 8) as u8);
+        }
+    }
+
+    samples
+}
+
+fn mean_difference(a: &[u8], b: &[u8]) -> f32 {
+    let sum = a
+        .iter()
+        .zip(b)
+        .map(|(a, b)| a.abs_diff(*b) as u32)
+        .sum::<u32>();
+
+    sum as f32 / a.len().max(1) as f32
+}
+
+// Detects when the composed frames stop changing and the controllers are not used. The frames are
+// the downscaled copies made for the mirror. The report functions return the new idle state when it
+// changes
+pub struct IdleDetector {
+    trigger_duration: Duration,
+    idle_fps: f32,
+    samples: Vec<u8>,
+    last_activity: Instant,
+    idle: bool,
+}
+
+impl IdleDetector {
+    pub fn new(config: &IdleThrottlingConfig, now: Instant) -> Self {
+        Self {
+            trigger_duration: Duration::from_secs_f32(config.trigger_seconds.max(0.0)),
+            idle_fps: config.idle_fps,
+            samples: vec![],
+            last_activity: now,
+            idle: false,
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+
+    pub fn idle_fps(&self) -> f32 {
+        self.idle_fps
+    }
+
+    pub fn report_frame(&mut self, resolution: UVec2, rgba: &[u8], now: Instant) -> Option<bool> {
+        let samples = sample_luma(resolution, rgba);
+        let changed = samples.len() != self.samples.len()
+            || mean_difference(&samples, &self.samples) > CHANGE_THRESHOLD;
+        self.samples = samples;
+
+        if changed {
+            self.report_activity(now)
+        } else if !self.idle
+            && now.saturating_duration_since(self.last_activity) >= self.trigger_duration
+        {
+            self.idle = true;
+
+            Some(true)
+        } else {
+            None
+        }
+    }
+
+    pub fn report_input(&mut self, now: Instant) -> Option<bool> {
+        self.report_activity(now)
+    }
+
+    fn report_activity(&mut self, now: Instant) -> Option<bool> {
+        self.last_activity = now;
+
+        if self.idle {
+            self.idle = false;
+
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESOLUTION: UVec2 = UVec2::new(160, 90);
+
+    fn detector(now: Instant) -> IdleDetector {
+        IdleDetector::new(
+            &IdleThrottlingConfig {
+                trigger_seconds: 10.0,
+                idle_fps: 5.0,
+            },
+            now,
+        )
+    }
+
+    fn frame(value: u8) -> Vec<u8> {
+        vec![value; (RESOLUTION.x * RESOLUTION.y * 4) as usize]
+    }
+
+    #[test]
+    fn static_frames_trigger_idle_after_the_delay() {
+        let start = Instant::now();
+        let mut detector = detector(start);
+
+        assert_eq!(detector.report_frame(RESOLUTION, &frame(100), start), None);
+        assert_eq!(
+            detector.report_frame(RESOLUTION, &frame(100), start + Duration::from_secs(5)),
+            None
+        );
+        assert_eq!(
+            detector.report_frame(RESOLUTION, &frame(100), start + Duration::from_secs(10)),
+            Some(true)
+        );
+        assert!(detector.is_idle());
+
+        // The transition is reported once
+        assert_eq!(
+            detector.report_frame(RESOLUTION, &frame(100), start + Duration::from_secs(11)),
+            None
+        );
+    }
+
+    #[test]
+    fn scene_change_exits_idle_and_restarts_the_delay() {
+        let start = Instant::now();
+        let mut detector = detector(start);
+
+        detector.report_frame(RESOLUTION, &frame(100), start);
+        detector.report_frame(RESOLUTION, &frame(100), start + Duration::from_secs(10));
+        assert!(detector.is_idle());
+
+        let changed = start + Duration::from_secs(12);
+        assert_eq!(
+            detector.report_frame(RESOLUTION, &frame(150), changed),
+            Some(false)
+        );
+        assert_eq!(
+            detector.report_frame(RESOLUTION, &frame(150), changed + Duration::from_secs(9)),
+            None
+        );
+        assert_eq!(
+            detector.report_frame(RESOLUTION, &frame(150), changed + Duration::from_secs(10)),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn input_exits_idle() {
+        let start = Instant::now();
+        let mut detector = detector(start);
+
+        detector.report_frame(RESOLUTION, &frame(100), start);
+        assert_eq!(detector.report_input(start + Duration::from_secs(1)), None);
+
+        // The input restarted the delay
+        assert_eq!(
+            detector.report_frame(RESOLUTION, &frame(100), start + Duration::from_secs(10)),
+            None
+        );
+        assert_eq!(
+            detector.report_frame(RESOLUTION, &frame(100), start + Duration::from_secs(11)),
+            Some(true)
+        );
+        assert_eq!(
+            detector.report_input(start + Duration::from_secs(12)),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn small_changes_are_ignored() {
+        let start = Instant::now();
+        let mut detector = detector(start);
+
+        detector.report_frame(RESOLUTION, &frame(100), start);
+
+        // A small spot changing completely
+        let mut spot = frame(100);
+        for y in 40..44 {
+            for x in 80..84 {
+                let idx = ((y * RESOLUTION.x + x) * 4) as usize;
+                spot[idx..idx + 3].fill(255);
+            }
+        }
+        detector.report_frame(RESOLUTION, &spot, start + Duration::from_secs(5));
+        detector.report_frame(RESOLUTION, &frame(100), start + Duration::from_secs(6));
+
+        // Noise of a single step on every pixel
+        assert_eq!(
+            detector.report_frame(RESOLUTION, &frame(101), start + Duration::from_secs(10)),
+            Some(true)
+        );
+    }
+}
@@ -357,12 +357,23 @@ pub fn automatic_bindings(
             ));
         }
     }
-    if s_set.contains(&*RIGHT_B_CLICK_ID) && d_set.contains(&*RIGHT_B_CLICK_ID) {
-        bindings.extend(map_button_pair_automatic(
-            ct(s_set, *RIGHT_B_CLICK_ID, *RIGHT_B_TOUCH_ID),
-            ct(d_set, *RIGHT_B_CLICK_ID, *RIGHT_B_TOUCH_ID),
-            config,
-        ));
+    if s_set.contains(&*RIGHT_B_CLICK_ID) {
+        let source = ct(s_set, *RIGHT_B_CLICK_ID, *RIGHT_B_TOUCH_ID);
+        if d_set.contains(&*RIGHT_B_CLICK_ID) {
+            bindings.extend(map_button_pair_automatic(
+                source,
+                ct(d_set, *RIGHT_B_CLICK_ID, *RIGHT_B_TOUCH_ID),
+                config,
+            ));
+        } else if d_set.contains(&*RIGHT_MENU_CLICK_ID) && !s_set.contains(&*RIGHT_MENU_CLICK_ID) {
+            // Controllers without face buttons, like the Vive Wands, have a menu button on both
+            // hands
+            bindings.extend(map_button_pair_automatic(
+                source,
+                click(*RIGHT_MENU_CLICK_ID),
+                config,
+            ));
+        }
     }
 
     // Squeeze buttons
@@ -529,12 +540,14 @@ pub fn automatic_bindings(
         }
     }
 
-    // Thumbrests
+    // Thumbrests. When the thumbstick is mapped to the trackpad, like on the Vive Wands, the
+    // trackpad touch is already driven by the thumbstick and the thumbrest is dropped
     if s_set.contains(&*LEFT_THUMBREST_TOUCH_ID) {
         let source = value(*LEFT_THUMBREST_TOUCH_ID);
         if d_set.contains(&*LEFT_THUMBREST_TOUCH_ID) {
             bindings.extend(map_button_pair_automatic(source, source, config));
-        } else if d_set.contains(&*LEFT_TRACKPAD_TOUCH_ID) {
+        } else if d_set.contains(&*LEFT_TRACKPAD_TOUCH_ID) && d_set.contains(&*LEFT_THUMBSTICK_X_ID)
+        {
             bindings.extend(map_button_pair_automatic(
                 source,
                 value(*LEFT_TRACKPAD_TOUCH_ID),
@@ -546,7 +559,9 @@ pub fn automatic_bindings(
         let source = value(*RIGHT_THUMBREST_TOUCH_ID);
         if d_set.contains(&*RIGHT_THUMBREST_TOUCH_ID) {
             bindings.extend(map_button_pair_automatic(source, source, config));
-        } else if d_set.contains(&*RIGHT_TRACKPAD_TOUCH_ID) {
+        } else if d_set.contains(&*RIGHT_TRACKPAD_TOUCH_ID)
+            && d_set.contains(&*RIGHT_THUMBSTICK_X_ID)
+        {
             bindings.extend(map_button_pair_automatic(
                 source,
                 value(*RIGHT_TRACKPAD_TOUCH_ID),
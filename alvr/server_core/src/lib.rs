@@ -1,24 +1,41 @@
+mod analog_input;
+mod benchmark;
 mod bitrate;
+mod button_macros;
 mod c_api;
+mod chrome_trace;
+mod clock_sync;
 mod connection;
+mod encoder;
+mod gaze_region;
 mod hand_gestures;
 mod haptics;
+mod idle_throttling;
 mod input_mapping;
 mod logging_backend;
+mod metrics;
+mod peripheral_input;
 mod sockets;
 mod statistics;
+mod test_pattern;
 mod tracking;
 mod web_server;
+mod wifi_advisor;
 
 pub use c_api::*;
+pub use clock_sync::ClockEstimate;
+pub use encoder::{EncoderPresets, encoder_presets};
+pub use gaze_region::{FrameRect, GazeRegions};
 pub use logging_backend::init_logging;
+pub use test_pattern::{draw_test_pattern, stream_test_pattern};
 pub use tracking::HandType;
 
 use crate::connection::VideoPacket;
 use alvr_common::{
     ConnectionState, DEVICE_ID_TO_PATH, DeviceMotion, LifecycleState, Pose, RelaxedAtomic,
     ViewParams, dbg_server_core, error,
-    glam::Vec2,
+    glam::{Quat, UVec2, Vec2},
+    info,
     parking_lot::{Mutex, RwLock},
     settings_schema::Switch,
     warn,
@@ -26,23 +43,26 @@ use alvr_common::{
 use alvr_events::{EventType, HapticsEvent};
 use alvr_filesystem as afs;
 use alvr_packets::{
-    BatteryInfo, ButtonEntry, ClientConnectionsAction, DecoderInitializationConfig, Haptics,
-    VideoPacketHeader,
+    BatteryInfo, ButtonEntry, ClientConnectionsAction, DecoderInitializationConfig,
+    DepthPacketHeader, Haptics, MotionVectorsPacketHeader, StreamConfigPacket, VideoPacketHeader,
 };
-use alvr_server_io::ServerSessionManager;
+use alvr_server_io::{ServerSessionManager, SteamvrSettingEdit};
 use alvr_session::{CodecType, OpenvrProperty, Settings};
 use alvr_sockets::StreamSender;
+use benchmark::BitrateBenchmark;
 use bitrate::{BitrateManager, DynamicEncoderParams};
+use gaze_region::FoveationParams;
+use idle_throttling::IdleDetector;
 use statistics::StatisticsManager;
 use std::{
     collections::HashSet,
     env,
     ffi::OsStr,
-    fs::File,
+    fs::{self, File},
     io::Write,
     sync::{
         Arc, LazyLock, OnceLock,
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         mpsc::{self, SyncSender, TrySendError},
     },
     thread::{self, JoinHandle},
@@ -78,7 +98,11 @@ pub enum ServerCoreEvent {
     ClientConnected,
     ClientDisconnected,
     Battery(BatteryInfo),
-    PlayspaceSync(Vec2),
+    // The perimeter is in the SteamVR tracking space
+    PlayspaceSync {
+        area: Vec2,
+        perimeter: Vec<Vec2>,
+    },
     LocalViewParams([ViewParams; 2]), // In relation to head
     Tracking {
         poll_timestamp: Duration,
@@ -86,24 +110,78 @@ pub enum ServerCoreEvent {
     Buttons(Vec<ButtonEntry>), // Note: this is after mapping
     RequestIDR,
     CaptureFrame,
+    SetTestPattern(bool),
+    SetMirrorEnabled(bool),
+    // While idle, frames are dropped before encoding to keep this frame rate. None restores the
+    // full frame rate
+    SetIdleFrameRate(Option<f32>),
+    // While paused, frames are dropped before encoding, except one per second with keepalive frames
+    SetEncodingPaused {
+        paused: bool,
+        keepalive_frames: bool,
+    },
     GameRenderLatencyFeedback(Duration), // only used for SteamVR
+    // Changes of steamvr.vrsettings requested by the dashboard
+    SetSteamvrSettings(Vec<SteamvrSettingEdit>),
     ShutdownPending,
     RestartPending,
     ProximityState(bool),
 }
 
+// Downscaled RGBA copy of the composed frame, shown by the dashboard mirror window
+struct MirrorFrame {
+    received: Instant,
+    resolution: UVec2,
+    rgba: Vec<u8>,
+}
+
 pub struct ConnectionContext {
     events_sender: mpsc::Sender<ServerCoreEvent>,
+    // False when streaming the test pattern without SteamVR
+    hosted_by_driver: bool,
     statistics_manager: RwLock<Option<StatisticsManager>>,
     bitrate_manager: Mutex<BitrateManager>,
     tracking_manager: RwLock<TrackingManager>,
     decoder_config: Mutex<Option<DecoderInitializationConfig>>,
+    intra_refresh_period: Mutex<Option<u32>>,
+    bitrate_benchmark: Mutex<Option<BitrateBenchmark>>,
+    client_log_requested: RelaxedAtomic,
+    external_trackers_calibration_requested: RelaxedAtomic,
+    trace_capture_requested: RelaxedAtomic,
+    // Set when SteamVR is restarted by the dashboard, so that the client is told to wait for it
+    driver_restarting: RelaxedAtomic,
+    // Reported by the client. The face, eye and body data is dropped while set
+    biometric_streams_blocked: RelaxedAtomic,
     video_mirror_sender: Mutex<Option<broadcast::Sender<Vec<u8>>>>,
     video_recording_file: Mutex<Option<File>>,
     connection_threads: Mutex<Vec<JoinHandle<()>>>,
     clients_to_be_removed: Mutex<HashSet<String>>,
     video_channel_sender: Mutex<Option<SyncSender<VideoPacket>>>,
+    // Encoded frames waiting for the video send thread
+    video_queue_len: AtomicUsize,
     haptics_sender: Mutex<Option<StreamSender<Haptics>>>,
+    haptics_processor: Mutex<haptics::HapticsProcessor>,
+    // Executable file name of the game rendering the scene, selects the per-game haptics
+    scene_application: Mutex<Option<String>>,
+    // Set only if the depth stream was negotiated
+    depth_sender: Mutex<Option<StreamSender<DepthPacketHeader>>>,
+    // Reported by the driver. The depth stream is paused while the game doesn't submit depth
+    depth_submitted: RelaxedAtomic,
+    // Set only if space warp was negotiated
+    motion_vectors_sender: Mutex<Option<StreamSender<MotionVectorsPacketHeader>>>,
+    // Reported by the driver when the graphics device is created. Space warp is negotiated only if
+    // the motion vectors can be estimated
+    motion_vectors_supported: RelaxedAtomic,
+    mirror_frame: Mutex<Option<MirrorFrame>>,
+    // Set while the dashboard shows the mirror. The mirror frames are also captured for the idle
+    // detection
+    mirror_requested: RelaxedAtomic,
+    // Set only while streaming with idle throttling enabled
+    idle_detector: Mutex<Option<IdleDetector>>,
+    // Stream config of the headset, sent as is to the flat display clients
+    headset_stream_config: Mutex<Option<StreamConfigPacket>>,
+    // Flat display clients receive a copy of the video of the headset
+    spectator_video_senders: Mutex<Vec<SyncSender<VideoPacket>>>,
 }
 
 pub fn create_recording_file(connection_context: &ConnectionContext, settings: &Settings) {
@@ -138,6 +216,31 @@ pub fn create_recording_file(connection_context: &ConnectionContext, settings: &
     }
 }
 
+// Applies a transition reported by the idle detector. The detector must not be locked
+fn set_idle(connection_context: &ConnectionContext, idle_fps: Option<f32>) {
+    if let Some(fps) = idle_fps {
+        info!("The scene is static, encoding at {fps} FPS");
+    } else {
+        info!("The scene changed, encoding at the full frame rate");
+    }
+
+    connection_context
+        .events_sender
+        .send(ServerCoreEvent::SetIdleFrameRate(idle_fps))
+        .ok();
+    if idle_fps.is_none() {
+        // The frames skipped by the encoder are not references anymore
+        connection_context
+            .events_sender
+            .send(ServerCoreEvent::RequestIDR)
+            .ok();
+    }
+
+    if let Some(stats) = &mut *connection_context.statistics_manager.write() {
+        stats.report_idle(idle_fps.is_some());
+    }
+}
+
 pub fn notify_restart_driver() {
     if sysinfo::System::new_all()
         .processes_by_name(OsStr::new(&afs::dashboard_fname()))
@@ -165,7 +268,6 @@ pub fn registered_button_set() -> HashSet<u64> {
 
 pub struct ServerCoreContext {
     lifecycle_state: Arc<RwLock<LifecycleState>>,
-    is_restarting: RelaxedAtomic,
     connection_context: Arc<ConnectionContext>,
     connection_thread: Arc<RwLock<Option<JoinHandle<()>>>>,
     webserver_runtime: Option<Runtime>,
@@ -173,8 +275,23 @@ pub struct ServerCoreContext {
 
 impl ServerCoreContext {
     pub fn new() -> (Self, mpsc::Receiver<ServerCoreEvent>) {
+        Self::create(true)
+    }
+
+    // Used to stream the test pattern without SteamVR. The negotiated config is applied without
+    // restart and the stream is limited to what the test pattern encoder supports
+    pub fn new_without_driver() -> (Self, mpsc::Receiver<ServerCoreEvent>) {
+        Self::create(false)
+    }
+
+    fn create(hosted_by_driver: bool) -> (Self, mpsc::Receiver<ServerCoreEvent>) {
         dbg_server_core!("Creating");
 
+        // Lets the dashboard recognize a crash of the driver
+        if hosted_by_driver && let Some(layout) = FILESYSTEM_LAYOUT.get() {
+            fs::write(layout.driver_running_marker(), "").ok();
+        }
+
         if SESSION_MANAGER
             .read()
             .settings()
@@ -203,18 +320,38 @@ impl ServerCoreContext {
 
         let connection_context = Arc::new(ConnectionContext {
             events_sender,
+            hosted_by_driver,
             statistics_manager: RwLock::new(Some(stats)),
             bitrate_manager: Mutex::new(BitrateManager::new(256, 60.0)),
             tracking_manager: RwLock::new(TrackingManager::new(
                 initial_settings.connection.statistics_history_size,
             )),
             decoder_config: Mutex::new(None),
+            intra_refresh_period: Mutex::new(None),
+            bitrate_benchmark: Mutex::new(None),
+            client_log_requested: RelaxedAtomic::new(false),
+            external_trackers_calibration_requested: RelaxedAtomic::new(false),
+            trace_capture_requested: RelaxedAtomic::new(false),
+            driver_restarting: RelaxedAtomic::new(false),
+            biometric_streams_blocked: RelaxedAtomic::new(false),
             video_mirror_sender: Mutex::new(None),
             video_recording_file: Mutex::new(None),
             connection_threads: Mutex::new(Vec::new()),
             clients_to_be_removed: Mutex::new(HashSet::new()),
             video_channel_sender: Mutex::new(None),
+            video_queue_len: AtomicUsize::new(0),
             haptics_sender: Mutex::new(None),
+            haptics_processor: Mutex::new(haptics::HapticsProcessor::default()),
+            scene_application: Mutex::new(None),
+            depth_sender: Mutex::new(None),
+            depth_submitted: RelaxedAtomic::new(false),
+            motion_vectors_sender: Mutex::new(None),
+            motion_vectors_supported: RelaxedAtomic::new(false),
+            mirror_frame: Mutex::new(None),
+            mirror_requested: RelaxedAtomic::new(false),
+            idle_detector: Mutex::new(None),
+            headset_stream_config: Mutex::new(None),
+            spectator_video_senders: Mutex::new(Vec::new()),
         });
 
         let webserver_runtime = Runtime::new().unwrap();
@@ -223,10 +360,20 @@ impl ServerCoreContext {
             async move { alvr_common::show_err(web_server::web_server(connection_context).await) }
         });
 
+        if let Switch::Enabled(config) = initial_settings.extra.metrics_exporter.clone() {
+            webserver_runtime.spawn({
+                let connection_context = Arc::clone(&connection_context);
+                async move {
+                    alvr_common::show_err(
+                        metrics::metrics_exporter(connection_context, config).await,
+                    )
+                }
+            });
+        }
+
         (
             Self {
                 lifecycle_state: Arc::new(RwLock::new(LifecycleState::StartingUp)),
-                is_restarting: RelaxedAtomic::new(false),
                 connection_context,
 
                 connection_thread: Arc::new(RwLock::new(None)),
@@ -276,16 +423,30 @@ impl ServerCoreContext {
             .copied()
     }
 
-    pub fn get_motion_to_photon_latency(&self) -> Duration {
-        dbg_server_core!("get_motion_to_photon_latency");
+    // Gaze relative to the head, None if it is not tracked or eye gaze forwarding is disabled
+    pub fn get_forwarded_eye_gaze(&self) -> Option<Quat> {
+        dbg_server_core!("get_forwarded_eye_gaze");
 
-        let latency = self
-            .connection_context
+        self.connection_context
+            .tracking_manager
+            .read()
+            .get_forwarded_gaze()
+    }
+
+    fn motion_to_photon_latency_average(&self) -> Duration {
+        self.connection_context
             .statistics_manager
             .read()
             .as_ref()
             .map(|stats| stats.motion_to_photon_latency_average())
-            .unwrap_or_default();
+            .unwrap_or_default()
+    }
+
+    // Prediction for the head
+    pub fn get_motion_to_photon_latency(&self) -> Duration {
+        dbg_server_core!("get_motion_to_photon_latency");
+
+        let latency = self.motion_to_photon_latency_average();
 
         let max_prediction =
             Duration::from_millis(SESSION_MANAGER.read().settings().headset.max_prediction_ms);
@@ -299,6 +460,25 @@ impl ServerCoreContext {
         }
     }
 
+    // Prediction for the controllers, limited separately from the head
+    pub fn get_controllers_motion_to_photon_latency(&self) -> Duration {
+        dbg_server_core!("get_controllers_motion_to_photon_latency");
+
+        let max_prediction_ms = SESSION_MANAGER
+            .read()
+            .settings()
+            .headset
+            .controllers
+            .as_option()
+            .map(|config| config.max_prediction_ms)
+            .unwrap_or_default();
+
+        Duration::min(
+            self.motion_to_photon_latency_average(),
+            Duration::from_millis(max_prediction_ms),
+        )
+    }
+
     pub fn get_tracker_pose_time_offset(&self) -> Duration {
         dbg_server_core!("get_tracker_pose_time_offset");
 
@@ -310,6 +490,18 @@ impl ServerCoreContext {
             .unwrap_or_default()
     }
 
+    // Estimated relation between the server and client clocks, None until enough time sync
+    // samples are collected
+    pub fn get_client_clock_estimate(&self) -> Option<ClockEstimate> {
+        dbg_server_core!("get_client_clock_estimate");
+
+        self.connection_context
+            .statistics_manager
+            .read()
+            .as_ref()
+            .and_then(|stats| stats.clock_estimate())
+    }
+
     pub fn send_haptics(&self, haptics: Haptics) {
         dbg_server_core!("send_haptics");
 
@@ -333,19 +525,54 @@ impl ServerCoreContext {
                 .headset
                 .controllers
                 .as_option()
-                .and_then(|c| c.haptics.as_option().cloned())
+                .and_then(|c| {
+                    let config = c.haptics.as_option()?;
+                    let scene_application = self.connection_context.scene_application.lock();
+
+                    Some(
+                        haptics::game_config(config, &c.game_haptics, scene_application.as_deref())
+                            .clone(),
+                    )
+                })
         };
 
         if let (Some(config), Some(sender)) = (
             haptics_config,
             &mut *self.connection_context.haptics_sender.lock(),
         ) {
-            sender
-                .send_header(&haptics::map_haptics(&config, haptics))
-                .ok();
+            let haptics = self.connection_context.haptics_processor.lock().process(
+                &config,
+                Instant::now(),
+                haptics,
+            );
+            sender.send_header(&haptics).ok();
         }
     }
 
+    // Called by the driver when the game rendering the scene changes, with its process ID. 0 if no
+    // game is running
+    pub fn report_scene_application(&self, pid: u32) {
+        dbg_server_core!("report_scene_application: pid={pid}");
+
+        let executable_name = (pid != 0)
+            .then(|| {
+                let pid = sysinfo::Pid::from_u32(pid);
+                let mut system = sysinfo::System::new();
+                system.refresh_processes(sysinfo::ProcessesToUpdate::Some(&[pid]), true);
+
+                system
+                    .process(pid)
+                    .map(|process| process.name().to_string_lossy().into_owned())
+            })
+            .flatten();
+
+        info!(
+            "Scene application: {}",
+            executable_name.as_deref().unwrap_or("none")
+        );
+        *self.connection_context.scene_application.lock() = executable_name;
+    }
+
     pub fn set_video_config_nals(&self, config_buffer: Vec<u8>, codec: CodecType) {
         dbg_server_core!("set_video_config_nals");
 
@@ -364,11 +591,30 @@ impl ServerCoreContext {
         });
     }
 
+    // Called by the encoder on initialization if the selected backend is not available
+    pub fn report_encoder_fallback(&self, selected_backend: &str, used_backend: &str) {
+        dbg_server_core!("report_encoder_fallback");
+
+        alvr_events::send_event(EventType::EncoderFallback {
+            selected: selected_backend.into(),
+            used: used_backend.into(),
+        });
+    }
+
+    // Should be called by the encoder on initialization. None if the encoder recovers from packet
+    // loss using IDR frames
+    pub fn set_intra_refresh_period(&self, period_frames: Option<u32>) {
+        dbg_server_core!("set_intra_refresh_period");
+
+        *self.connection_context.intra_refresh_period.lock() = period_frames;
+    }
+
     pub fn send_video_nal(
         &self,
         timestamp: Duration,
         global_view_params: [ViewParams; 2],
         is_idr: bool,
+        average_qp: Option<u32>,
         nal_buffer: Vec<u8>,
     ) {
         dbg_server_core!("send_video_nal");
@@ -380,6 +626,22 @@ impl ServerCoreContext {
 
         if let Some(sender) = &*self.connection_context.video_channel_sender.lock() {
             let buffer_size = nal_buffer.len();
+            let idle_throttled = self
+                .connection_context
+                .idle_detector
+                .lock()
+                .as_ref()
+                .is_some_and(IdleDetector::is_idle);
+
+            let mut encode_duration = Duration::ZERO;
+            if let Some(stats) = &mut *self.connection_context.statistics_manager.write() {
+                encode_duration = stats.report_frame_encoded(timestamp, buffer_size);
+
+                self.connection_context
+                    .bitrate_manager
+                    .lock()
+                    .report_frame_encoded(timestamp, encode_duration, buffer_size);
+            }
 
             if is_idr {
                 STREAM_CORRUPTED.store(false, Ordering::SeqCst);
@@ -423,14 +685,51 @@ impl ServerCoreContext {
                     file.write_all(&nal_buffer).ok();
                 }
 
+                // A slow spectator only corrupts its own stream, it recovers on the next IDR
+                self.connection_context
+                    .spectator_video_senders
+                    .lock()
+                    .retain(|sender| {
+                        let result = sender.try_send(VideoPacket {
+                            header: VideoPacketHeader {
+                                timestamp,
+                                global_view_params,
+                                is_idr,
+                                intra_refresh_period: *self
+                                    .connection_context
+                                    .intra_refresh_period
+                                    .lock(),
+                                average_qp,
+                                encode_duration,
+                                idle_throttled,
+                            },
+                            payload: nal_buffer.clone(),
+                        });
+                        !matches!(result, Err(TrySendError::Disconnected(_)))
+                    });
+
+                // Counted before sending, otherwise the send thread could dequeue the packet
+                // before it is counted
+                self.connection_context
+                    .video_queue_len
+                    .fetch_add(1, Ordering::Relaxed);
                 let sender_result = sender.try_send(VideoPacket {
                     header: VideoPacketHeader {
                         timestamp,
                         global_view_params,
                         is_idr,
+                        intra_refresh_period: *self.connection_context.intra_refresh_period.lock(),
+                        average_qp,
+                        encode_duration,
+                        idle_throttled,
                     },
                     payload: nal_buffer,
                 });
+                if sender_result.is_err() {
+                    self.connection_context
+                        .video_queue_len
+                        .fetch_sub(1, Ordering::Relaxed);
+                }
                 if matches!(sender_result, Err(TrySendError::Full(_))) {
                     STREAM_CORRUPTED.store(true, Ordering::SeqCst);
                     self.connection_context
@@ -442,16 +741,41 @@ impl ServerCoreContext {
             } else {
                 warn!("Dropping video packet. Reason: Waiting for IDR frame");
             }
+        }
+    }
 
-            if let Some(stats) = &mut *self.connection_context.statistics_manager.write() {
-                let encoder_latency = stats.report_frame_encoded(timestamp, buffer_size);
+    // Returns None if gaze-contingent bitrate allocation is disabled or there is no recent gaze, in
+    // which case the frame should be encoded uniformly. The view params are relative to the head.
+    pub fn get_gaze_regions(&self, local_view_params: &[ViewParams; 2]) -> Option<GazeRegions> {
+        dbg_server_core!("get_gaze_regions");
 
-                self.connection_context
-                    .bitrate_manager
-                    .lock()
-                    .report_frame_encoded(timestamp, encoder_latency, buffer_size);
-            }
-        }
+        let session_manager_lock = SESSION_MANAGER.read();
+        let Switch::Enabled(config) = &session_manager_lock
+            .settings()
+            .video
+            .gaze_bitrate_allocation
+        else {
+            return None;
+        };
+
+        let gaze = self
+            .connection_context
+            .tracking_manager
+            .read()
+            .get_gaze(Duration::from_millis(config.gaze_timeout_ms))?;
+
+        let foveation =
+            FoveationParams::from_openvr_config(&session_manager_lock.session().openvr_config);
+
+        Some(GazeRegions {
+            rects: gaze_region::gaze_frame_regions(
+                gaze,
+                local_view_params,
+                config.region_size,
+                foveation.as_ref(),
+            ),
+            qp_offset: -(config.qp_reduction as i32),
+        })
     }
 
     pub fn get_dynamic_encoder_params(&self) -> Option<DynamicEncoderParams> {
@@ -459,10 +783,13 @@ impl ServerCoreContext {
 
         let pair = {
             let session_manager_lock = SESSION_MANAGER.read();
+            let video = &session_manager_lock.settings().video;
+            let presets = encoder::encoder_presets(video.encoder_effort, &video.encoder_config);
+
             self.connection_context
                 .bitrate_manager
                 .lock()
-                .get_encoder_params(&session_manager_lock.settings().video.bitrate)
+                .get_encoder_params(&video.bitrate, &presets)
         };
 
         if let Some((params, stats)) = pair {
@@ -484,6 +811,140 @@ impl ServerCoreContext {
         }
     }
 
+    // The frame has been re-projected to the head pose with pose_timestamp before encoding
+    pub fn report_server_reprojection(&self, target_timestamp: Duration, pose_timestamp: Duration) {
+        dbg_server_core!("report_server_reprojection");
+
+        if let Some(stats) = &mut *self.connection_context.statistics_manager.write() {
+            stats.report_server_reprojection(target_timestamp, pose_timestamp);
+        }
+    }
+
+    // Called when the game starts or stops submitting a depth buffer with its frames
+    pub fn report_depth_submission(&self, submitted: bool) {
+        // The client is notified by the connection loop
+        self.connection_context.depth_submitted.set(submitted);
+
+        if submitted {
+            info!("The game started submitting depth, the depth stream can be used");
+        } else {
+            info!("The game stopped submitting depth, the depth stream is paused");
+        }
+    }
+
+    // data contains the depth of both views side by side, row by row
+    pub fn send_depth(
+        &self,
+        timestamp: Duration,
+        view_resolution: UVec2,
+        near_z: f32,
+        far_z: f32,
+        data: &[u16],
+    ) {
+        dbg_server_core!("send_depth");
+
+        if !self.connection_context.depth_submitted.value() {
+            return;
+        }
+
+        if let Some(sender) = &mut *self.connection_context.depth_sender.lock() {
+            sender
+                .send_header_with_payload(
+                    &DepthPacketHeader {
+                        timestamp,
+                        view_resolution,
+                        near_z,
+                        far_z,
+                    },
+                    &alvr_common::encode_depth(data),
+                )
+                .ok();
+        }
+    }
+
+    pub fn report_motion_vectors_support(&self, supported: bool) {
+        dbg_server_core!("report_motion_vectors_support");
+
+        self.connection_context
+            .motion_vectors_supported
+            .set(supported);
+    }
+
+    // data contains the motion vectors of the blocks of the left view followed by the right view
+    pub fn send_motion_vectors(
+        &self,
+        timestamp: Duration,
+        reference_timestamp: Duration,
+        view_resolution: UVec2,
+        block_size: u32,
+        grid_size: UVec2,
+        data: &[i16],
+    ) {
+        dbg_server_core!("send_motion_vectors");
+
+        // Only used together with the depth
+        if !self.connection_context.depth_submitted.value() {
+            return;
+        }
+
+        if let Some(sender) = &mut *self.connection_context.motion_vectors_sender.lock() {
+            let payload = data
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect::<Vec<_>>();
+
+            sender
+                .send_header_with_payload(
+                    &MotionVectorsPacketHeader {
+                        timestamp,
+                        reference_timestamp,
+                        view_resolution,
+                        block_size,
+                        grid_size,
+                    },
+                    &payload,
+                )
+                .ok();
+        }
+    }
+
+    // Called once the changes of steamvr.vrsettings requested by the dashboard are saved
+    pub fn report_steamvr_settings_changed(&self) {
+        dbg_server_core!("report_steamvr_settings_changed");
+
+        match alvr_server_io::get_steamvr_video_settings() {
+            Ok(settings) => alvr_events::send_event(EventType::SteamvrVideoSettings(settings)),
+            Err(e) => warn!("Failed to read back the SteamVR video settings: {e}"),
+        }
+    }
+
+    pub fn send_mirror_frame(&self, resolution: UVec2, rgba: &[u8]) {
+        dbg_server_core!("send_mirror_frame");
+
+        let now = Instant::now();
+
+        let transition = self
+            .connection_context
+            .idle_detector
+            .lock()
+            .as_mut()
+            .and_then(|detector| {
+                let idle = detector.report_frame(resolution, rgba, now)?;
+                Some(idle.then(|| detector.idle_fps()))
+            });
+        if let Some(idle_fps) = transition {
+            set_idle(&self.connection_context, idle_fps);
+        }
+
+        if self.connection_context.mirror_requested.value() {
+            *self.connection_context.mirror_frame.lock() = Some(MirrorFrame {
+                received: now,
+                resolution,
+                rgba: rgba.to_vec(),
+            });
+        }
+    }
+
     pub fn report_present(&self, target_timestamp: Duration, offset: Duration) {
         dbg_server_core!("report_present");
 
@@ -517,7 +978,7 @@ impl ServerCoreContext {
     pub fn restart(self) {
         dbg_server_core!("restart");
 
-        self.is_restarting.set(true);
+        self.connection_context.driver_restarting.set(true);
 
         // drop is called here for self
     }
@@ -527,6 +988,11 @@ impl Drop for ServerCoreContext {
     fn drop(&mut self) {
         dbg_server_core!("Drop");
 
+        // Removed first, the dashboard checks it as soon as the web server stops responding
+        if let Some(layout) = FILESYSTEM_LAYOUT.get() {
+            fs::remove_file(layout.driver_running_marker()).ok();
+        }
+
         // Invoke connection runtimes shutdown
         *self.lifecycle_state.write() = LifecycleState::ShuttingDown;
 
@@ -1,111 +1,208 @@
 use crate::SESSION_MANAGER;
-use alvr_common::{LogEntry, LogSeverity, log::LevelFilter};
+use alvr_common::{
+    DebugGroupsConfig, LogEntry, LogSeverity, RotatingFileWriter, TargetLevels,
+    log::{
+        LevelFilter, Record,
+        kv::{self, Key, Value, VisitSource},
+    },
+    settings_schema::Switch,
+    warn,
+};
 use alvr_events::{Event, EventType};
 use chrono::Local;
-use fern::Dispatch;
-use std::{fs, path::PathBuf, sync::LazyLock};
+use fern::{Dispatch, Output};
+use serde_json as json;
+use std::{
+    fmt::Arguments,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    sync::LazyLock,
+};
 use tokio::sync::broadcast;
 
 static CHANNEL_CAPACITY: usize = 256;
 pub static EVENTS_SENDER: LazyLock<broadcast::Sender<Event>> =
     LazyLock::new(|| broadcast::channel(CHANNEL_CAPACITY).0);
 
-pub fn init_logging(session_log_path: Option<PathBuf>, crash_log_path: Option<PathBuf>) {
-    let debug_groups_config = SESSION_MANAGER
+const DEFAULT_LEVEL: LevelFilter = if cfg!(debug_assertions) {
+    LevelFilter::Debug
+} else {
+    LevelFilter::Info
+};
+
+fn record_event(
+    message: &Arguments,
+    record: &Record,
+    debug_groups_config: &DebugGroupsConfig,
+) -> Event {
+    let maybe_event = format!("{message}");
+    let event_type = if maybe_event.starts_with('{') && maybe_event.ends_with('}') {
+        serde_json::from_str(&maybe_event).unwrap()
+    } else if record.level() == LevelFilter::Debug
+        && alvr_common::is_enabled_debug_group(record.target(), debug_groups_config)
+    {
+        EventType::DebugGroup {
+            group: record.target().to_string(),
+            message: message.to_string(),
+        }
+    } else {
+        EventType::Log(LogEntry {
+            severity: LogSeverity::from_log_level(record.level()),
+            content: message.to_string(),
+        })
+    };
+
+    Event::new(Local::now().format("%H:%M:%S.%3f").to_string(), event_type)
+}
+
+struct JsonFields(json::Map<String, json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        let value = if let Some(value) = value.to_bool() {
+            value.into()
+        } else if let Some(value) = value.to_i64() {
+            value.into()
+        } else if let Some(value) = value.to_u64() {
+            value.into()
+        } else if let Some(value) = value.to_f64() {
+            value.into()
+        } else {
+            value.to_string().into()
+        };
+        self.0.insert(key.to_string(), value);
+
+        Ok(())
+    }
+}
+
+fn json_line(event: &Event, record: &Record) -> String {
+    let mut fields = JsonFields(json::Map::new());
+    record.key_values().visit(&mut fields).ok();
+
+    json::json!({
+        "time": Local::now().to_rfc3339(),
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "type": event.event_type_string(),
+        "message": event.message(),
+        "fields": fields.0,
+    })
+    .to_string()
+}
+
+// Applies the target levels from the settings. They can be changed while running
+pub fn apply_target_levels() {
+    let spec = SESSION_MANAGER
         .read()
         .settings()
         .extra
         .logging
-        .debug_groups
+        .target_levels
         .clone();
 
+    let levels = spec.parse::<TargetLevels>().unwrap_or_else(|e| {
+        warn!("Invalid log target levels: {e}");
+        TargetLevels::default()
+    });
+    alvr_common::set_target_levels(DEFAULT_LEVEL, levels);
+}
+
+pub fn init_logging(session_log_path: Option<PathBuf>, crash_log_path: Option<PathBuf>) {
+    let logging_config = SESSION_MANAGER.read().settings().extra.logging.clone();
+    let debug_groups_config = logging_config.debug_groups.clone();
+
+    let text_dispatch = || {
+        let debug_groups_config = debug_groups_config.clone();
+        Dispatch::new().format(move |out, message, record| {
+            let event = record_event(message, record, &debug_groups_config);
+            out.finish(format_args!(
+                "{} [{}] {}",
+                event.timestamp,
+                event.event_type_string(),
+                event.message(),
+            ))
+        })
+    };
+
     let mut log_dispatch = Dispatch::new()
+        // Levels are checked by the filter, which can be changed at runtime
+        .level(LevelFilter::Trace)
         // Note: meta::target() is in the format <crate>::<module>
         .filter({
             let debug_groups_config = debug_groups_config.clone();
             move |meta| {
+                if let Some(level) = alvr_common::target_level(meta.target()) {
+                    return meta.level() <= level;
+                }
+
                 !meta.target().starts_with("mdns_sd")
+                    && meta.level() <= DEFAULT_LEVEL
                     && (meta.level() <= LevelFilter::Info
                         || alvr_common::filter_debug_groups(meta.target(), &debug_groups_config))
             }
         })
-        .format(move |out, message, record| {
-            let maybe_event = format!("{message}");
-            let event_type = if maybe_event.starts_with('{') && maybe_event.ends_with('}') {
-                serde_json::from_str(&maybe_event).unwrap()
-            } else if record.level() == LevelFilter::Debug
-                && alvr_common::is_enabled_debug_group(record.target(), &debug_groups_config)
-            {
-                EventType::DebugGroup {
-                    group: record.target().to_string(),
-                    message: message.to_string(),
-                }
+        .chain(Output::call({
+            let debug_groups_config = debug_groups_config.clone();
+            move |record| {
+                let event = record_event(record.args(), record, &debug_groups_config);
+                EVENTS_SENDER.send(event).ok();
+            }
+        }));
+
+    if let Some(path) = session_log_path {
+        let writer: Box<dyn Write + Send> =
+            if let Switch::Enabled(config) = &logging_config.log_rotation {
+                Box::new(
+                    RotatingFileWriter::new(
+                        path,
+                        config.max_file_size_mb * 1024 * 1024,
+                        config.max_files,
+                    )
+                    .unwrap(),
+                )
             } else {
-                EventType::Log(LogEntry {
-                    severity: LogSeverity::from_log_level(record.level()),
-                    content: message.to_string(),
-                })
-            };
-            let event = Event {
-                timestamp: Local::now().format("%H:%M:%S.%3f").to_string(),
-                event_type,
+                Box::new(
+                    fs::OpenOptions::new()
+                        .write(true)
+                        .create(true)
+                        .truncate(true)
+                        .open(path)
+                        .unwrap(),
+                )
             };
-            out.finish(format_args!(
-                "{} [{}] {}",
-                event.timestamp,
-                event.event_type_string(),
-                event.message(),
-            ));
 
-            EVENTS_SENDER.send(event).ok();
-        });
+        let file_dispatch = if logging_config.log_json_lines {
+            let debug_groups_config = debug_groups_config.clone();
+            Dispatch::new().format(move |out, message, record| {
+                let event = record_event(message, record, &debug_groups_config);
+                out.finish(format_args!("{}", json_line(&event, record)))
+            })
+        } else {
+            text_dispatch()
+        };
 
-    if cfg!(debug_assertions) {
-        log_dispatch = log_dispatch.level(LevelFilter::Debug)
-    } else {
-        log_dispatch = log_dispatch.level(LevelFilter::Info);
+        log_dispatch = log_dispatch.chain(file_dispatch.chain(writer));
+    } else if !cfg!(target_os = "linux") {
+        log_dispatch = log_dispatch.chain(text_dispatch().chain(io::stdout()));
     }
 
-    log_dispatch = if let Some(path) = session_log_path {
-        log_dispatch.chain(
-            fs::OpenOptions::new()
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .open(path)
-                .unwrap(),
-        )
-    } else if cfg!(target_os = "linux") {
-        // this sink is required to make sure all log gets processed and forwarded to the websocket
-        log_dispatch.chain(
-            fs::OpenOptions::new()
-                .write(true)
-                .open("/dev/null")
-                .unwrap(),
-        )
-    } else {
-        log_dispatch.chain(std::io::stdout())
-    };
-
-    log_dispatch = if let Some(path) = crash_log_path {
-        log_dispatch.chain(
-            Dispatch::new()
+    if let Some(path) = crash_log_path {
+        log_dispatch = log_dispatch.chain(
+            text_dispatch()
                 .level(LevelFilter::Error)
                 .chain(fern::log_file(path).unwrap()),
-        )
-    } else if cfg!(target_os = "linux") {
-        log_dispatch.chain(
-            fs::OpenOptions::new()
-                .write(true)
-                .open("/dev/null")
-                .unwrap(),
-        )
-    } else {
-        log_dispatch.chain(std::io::stderr())
-    };
+        );
+    } else if !cfg!(target_os = "linux") {
+        log_dispatch = log_dispatch.chain(text_dispatch().chain(io::stderr()));
+    }
 
     log_dispatch.apply().unwrap();
 
+    // Lowers the global level set by apply()
+    apply_target_levels();
+
     fn popup_callback(title: &str, message: &str, severity: LogSeverity) {
         let level = match severity {
             LogSeverity::Error => rfd::MessageLevel::Error,
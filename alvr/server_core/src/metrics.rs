@@ -0,0 +1,436 @@
+use crate::{ConnectionContext, SESSION_MANAGER};
+use alvr_common::{
+    ConnectionState, DEVICE_ID_TO_PATH,
+    anyhow::{Context, Result},
+    info,
+    parking_lot::Mutex,
+};
+use alvr_events::{GraphStatistics, StatisticsSummary};
+use alvr_session::{MetricsExporterConfig, MetricsExporterMode};
+use axum::{Router, extract::State, routing};
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    net::SocketAddr,
+    sync::{Arc, LazyLock, atomic::Ordering},
+    time::Duration,
+};
+use tokio::{
+    net::{TcpListener, UdpSocket},
+    time,
+};
+
+const PROMETHEUS_PREFIX: &str = "alvr_";
+
+// Latest statistics, filled by the statistics manager and read by the exporter
+pub static METRICS: LazyLock<Mutex<Metrics>> = LazyLock::new(|| Mutex::new(Metrics::default()));
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MetricKind {
+    Gauge,
+    Counter,
+}
+
+#[derive(Clone, Debug)]
+pub struct MetricSample {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub kind: MetricKind,
+    pub label: Option<(&'static str, String)>,
+    pub value: f64,
+}
+
+fn sample(name: &'static str, help: &'static str, kind: MetricKind, value: f64) -> MetricSample {
+    MetricSample {
+        name,
+        help,
+        kind,
+        label: None,
+        value,
+    }
+}
+
+fn labeled(
+    name: &'static str,
+    help: &'static str,
+    label: (&'static str, &str),
+    value: f64,
+) -> MetricSample {
+    MetricSample {
+        name,
+        help,
+        kind: MetricKind::Gauge,
+        label: Some((label.0, label.1.into())),
+        value,
+    }
+}
+
+#[derive(Default)]
+pub struct Metrics {
+    // Cleared when the client disconnects
+    pub graph_statistics: Option<GraphStatistics>,
+    pub summary: Option<StatisticsSummary>,
+    pub battery_gauges: HashMap<u64, f32>,
+    pub video_frames_received_total: u64,
+    pub video_frames_lost_total: u64,
+}
+
+impl Metrics {
+    pub fn reset_stream(&mut self) {
+        self.graph_statistics = None;
+        self.summary = None;
+        self.battery_gauges.clear();
+    }
+
+    pub fn samples(
+        &self,
+        connected_clients: usize,
+        encoder_queue_depth: usize,
+    ) -> Vec<MetricSample> {
+        use MetricKind::*;
+
+        let mut samples = vec![
+            sample(
+                "connected_clients",
+                "Number of streaming clients",
+                Gauge,
+                connected_clients as f64,
+            ),
+            sample(
+                "encoder_queue_depth",
+                "Encoded frames waiting to be sent",
+                Gauge,
+                encoder_queue_depth as f64,
+            ),
+            sample(
+                "video_frames_received_total",
+                "Video frames received by the client",
+                Counter,
+                self.video_frames_received_total as f64,
+            ),
+            sample(
+                "video_frames_lost_total",
+                "Video frames lost because of packet loss",
+                Counter,
+                self.video_frames_lost_total as f64,
+            ),
+        ];
+
+        if let Some(stats) = &self.graph_statistics {
+            const FPS_HELP: &str = "Frame rate";
+            samples.extend([
+                labeled("fps", FPS_HELP, ("source", "game"), stats.server_fps as _),
+                labeled("fps", FPS_HELP, ("source", "client"), stats.client_fps as _),
+            ]);
+            if let Some(summary) = &self.summary {
+                samples.push(labeled(
+                    "fps",
+                    FPS_HELP,
+                    ("source", "stream"),
+                    summary.video_packets_per_sec as _,
+                ));
+            }
+
+            const LATENCY_HELP: &str = "Latency of each step of the pipeline";
+            samples.extend(
+                [
+                    ("total", stats.total_pipeline_latency_s),
+                    ("game", stats.game_time_s),
+                    ("server_compositor", stats.server_compositor_s),
+                    ("encoder", stats.encoder_s),
+                    ("network", stats.network_s),
+                    ("decoder", stats.decoder_s),
+                    ("decoder_queue", stats.decoder_queue_s),
+                    ("client_compositor", stats.client_compositor_s),
+                    ("vsync_queue", stats.vsync_queue_s),
+                ]
+                .into_iter()
+                .map(|(step, value)| {
+                    labeled("latency_seconds", LATENCY_HELP, ("step", step), value as _)
+                }),
+            );
+
+            samples.extend([
+                sample(
+                    "bitrate_bps",
+                    "Bitrate of the video stream",
+                    Gauge,
+                    stats.bitrate_bps as _,
+                ),
+                sample(
+                    "requested_bitrate_bps",
+                    "Bitrate requested to the encoder",
+                    Gauge,
+                    stats.bitrate_directives.requested_bitrate_bps as _,
+                ),
+            ]);
+        }
+
+        if let Some(summary) = &self.summary {
+            samples.extend([
+                sample(
+                    "idr_requests_total",
+                    "Key frames requested to recover from packet loss",
+                    Counter,
+                    summary.idr_requests_total as _,
+                ),
+                sample(
+                    "decoder_queue_drops_total",
+                    "Frames dropped by the client because the decoder queue was full",
+                    Counter,
+                    summary.decoder_queue_drops_total as _,
+                ),
+                sample(
+                    "extrapolated_frames_total",
+                    "Frames reprojected by the client because no new frame was ready",
+                    Counter,
+                    summary.extrapolated_frames_total as _,
+                ),
+                sample(
+                    "idle_seconds_total",
+                    "Time spent at the reduced frame rate because the scene was static",
+                    Counter,
+                    summary.idle_time_total_s as _,
+                ),
+            ]);
+
+            const ONE_WAY_LATENCY_HELP: &str = "Network latency in each direction";
+            for (direction, latency_ms) in [
+                ("uplink", summary.uplink_latency_ms),
+                ("downlink", summary.downlink_latency_ms),
+            ] {
+                if let Some(latency_ms) = latency_ms {
+                    samples.push(labeled(
+                        "one_way_latency_seconds",
+                        ONE_WAY_LATENCY_HELP,
+                        ("direction", direction),
+                        latency_ms as f64 / 1000.0,
+                    ));
+                }
+            }
+        }
+
+        let mut battery_gauges = self.battery_gauges.iter().collect::<Vec<_>>();
+        battery_gauges.sort_by_key(|(id, _)| **id);
+        for (id, gauge) in battery_gauges {
+            let device = DEVICE_ID_TO_PATH
+                .get(id)
+                .map(|path| path.to_string())
+                .unwrap_or_else(|| format!("{id:x}"));
+            samples.push(labeled(
+                "battery_level",
+                "Battery charge, between 0 and 1",
+                ("device", &device),
+                *gauge as _,
+            ));
+        }
+
+        samples
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+// Text exposition format. Samples with the same name must be contiguous.
+pub fn prometheus_text(samples: &[MetricSample]) -> String {
+    let mut text = String::new();
+    let mut last_name = None;
+
+    for sample in samples {
+        let name = format!("{PROMETHEUS_PREFIX}{}", sample.name);
+
+        if last_name != Some(sample.name) {
+            let kind = match sample.kind {
+                MetricKind::Gauge => "gauge",
+                MetricKind::Counter => "counter",
+            };
+            writeln!(text, "# HELP {name} {}", sample.help).unwrap();
+            writeln!(text, "# TYPE {name} {kind}").unwrap();
+            last_name = Some(sample.name);
+        }
+
+        if let Some((key, value)) = &sample.label {
+            writeln!(
+                text,
+                "{name}{{{key}=\"{}\"}} {}",
+                escape_label_value(value),
+                sample.value
+            )
+            .unwrap();
+        } else {
+            writeln!(text, "{name} {}", sample.value).unwrap();
+        }
+    }
+
+    text
+}
+
+// Totals are sent as gauges, since statsd counters are increments
+pub fn statsd_lines(prefix: &str, samples: &[MetricSample]) -> Vec<String> {
+    samples
+        .iter()
+        .map(|sample| {
+            let label = sample
+                .label
+                .as_ref()
+                .map(|(_, value)| {
+                    let value = value
+                        .trim_start_matches('/')
+                        .replace([':', '|', '.', '/'], "_");
+                    format!(".{value}")
+                })
+                .unwrap_or_default();
+
+            format!("{prefix}.{}{label}:{}|g", sample.name, sample.value)
+        })
+        .collect()
+}
+
+fn current_samples(ctx: &ConnectionContext) -> Vec<MetricSample> {
+    let connected_clients = SESSION_MANAGER
+        .read()
+        .client_list()
+        .values()
+        .filter(|info| info.connection_state == ConnectionState::Streaming)
+        .count();
+
+    METRICS.lock().samples(
+        connected_clients,
+        ctx.video_queue_len.load(Ordering::Relaxed),
+    )
+}
+
+async fn get_metrics(State(text): State<Arc<Mutex<String>>>) -> String {
+    text.lock().clone()
+}
+
+pub async fn metrics_exporter(
+    ctx: Arc<ConnectionContext>,
+    config: MetricsExporterConfig,
+) -> Result<()> {
+    let mut interval = time::interval(Duration::from_millis(config.update_interval_ms.max(1)));
+
+    match config.mode {
+        MetricsExporterMode::Prometheus { bind_address } => {
+            let address = bind_address
+                .parse::<SocketAddr>()
+                .with_context(|| format!("Invalid metrics bind address {bind_address}"))?;
+
+            // The values are sampled at the update interval and not at each scrape
+            let text = Arc::new(Mutex::new(String::new()));
+            let router = Router::new()
+                .route("/metrics", routing::get(get_metrics))
+                .with_state(Arc::clone(&text));
+
+            let listener = TcpListener::bind(address).await?;
+            info!("Serving metrics on http://{address}/metrics");
+            tokio::spawn(async move { axum::serve(listener, router).await });
+
+            loop {
+                interval.tick().await;
+                *text.lock() = prometheus_text(&current_samples(&ctx));
+            }
+        }
+        MetricsExporterMode::Statsd {
+            server_address,
+            prefix,
+        } => {
+            let socket = UdpSocket::bind(SocketAddr::new([0, 0, 0, 0].into(), 0)).await?;
+            socket
+                .connect(&server_address)
+                .await
+                .with_context(|| format!("Invalid statsd server address {server_address}"))?;
+
+            loop {
+                interval.tick().await;
+
+                // Sent in separate datagrams to stay below the MTU
+                for line in statsd_lines(&prefix, &current_samples(&ctx)) {
+                    socket.send(line.as_bytes()).await.ok();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alvr_common::{HAND_LEFT_ID, HEAD_ID};
+
+    fn test_samples() -> Vec<MetricSample> {
+        vec![
+            sample("connected_clients", "Clients", MetricKind::Gauge, 1.0),
+            labeled("fps", "Frame rate", ("source", "game"), 90.0),
+            labeled("fps", "Frame rate", ("source", "client"), 72.5),
+            sample("frames_total", "Frames", MetricKind::Counter, 1000.0),
+        ]
+    }
+
+    #[test]
+    fn test_prometheus_format() {
+        assert_eq!(
+            prometheus_text(&test_samples()),
+            "# HELP alvr_connected_clients Clients
+# TYPE alvr_connected_clients gauge
+alvr_connected_clients 1
+# HELP alvr_fps Frame rate
+# TYPE alvr_fps gauge
+alvr_fps{source=\"game\"} 90
+alvr_fps{source=\"client\"} 72.5
+# HELP alvr_frames_total Frames
+# TYPE alvr_frames_total counter
+alvr_frames_total 1000
+"
+        );
+    }
+
+    #[test]
+    fn test_prometheus_label_escaping() {
+        let text = prometheus_text(&[labeled("test", "Test", ("device", "a\"b\\c\n"), 0.0)]);
+        assert!(text.ends_with("alvr_test{device=\"a\\\"b\\\\c\\n\"} 0\n"));
+    }
+
+    #[test]
+    fn test_statsd_format() {
+        assert_eq!(
+            statsd_lines("alvr", &test_samples()),
+            [
+                "alvr.connected_clients:1|g",
+                "alvr.fps.game:90|g",
+                "alvr.fps.client:72.5|g",
+                "alvr.frames_total:1000|g",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_samples_are_grouped() {
+        let metrics = Metrics {
+            graph_statistics: Some(GraphStatistics::default()),
+            summary: Some(StatisticsSummary::default()),
+            battery_gauges: [(*HEAD_ID, 0.5), (*HAND_LEFT_ID, 0.8)]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        let samples = metrics.samples(1, 0);
+
+        // Every name appears in a single contiguous block, as required by the exposition format
+        let mut names = samples.iter().map(|s| s.name).collect::<Vec<_>>();
+        names.dedup();
+        let mut unique_names = names.clone();
+        unique_names.sort();
+        unique_names.dedup();
+        assert_eq!(names.len(), unique_names.len());
+
+        let text = prometheus_text(&samples);
+        assert!(text.contains("alvr_battery_level{device=\"/user/head\"} 0.5\n"));
+        assert!(text.contains("alvr_latency_seconds{step=\"network\"} 0\n"));
+    }
+}
@@ -0,0 +1,202 @@
+#[cfg(windows)]
+mod sendinput;
+#[cfg(target_os = "linux")]
+mod uinput;
+
+#[cfg(windows)]
+use sendinput::InputBackend;
+#[cfg(target_os = "linux")]
+use uinput::InputBackend;
+
+use alvr_common::{anyhow::Result, glam::Vec2, info, warn};
+use alvr_events::EventType;
+use alvr_packets::{MouseButton, PeripheralInput};
+use alvr_session::{KeyboardInjectionMode, PeripheralInputConfig};
+use std::collections::{HashMap, HashSet};
+
+// Linux evdev key codes
+const KEY_LEFTCTRL: u16 = 29;
+const KEY_LEFTSHIFT: u16 = 42;
+const KEY_RIGHTSHIFT: u16 = 54;
+const KEY_LEFTALT: u16 = 56;
+const KEY_F12: u16 = 88;
+const KEY_RIGHTCTRL: u16 = 97;
+const KEY_RIGHTALT: u16 = 100;
+const KEY_LEFTMETA: u16 = 125;
+const KEY_RIGHTMETA: u16 = 126;
+
+// Scroll is injected in fractions of a wheel notch, as done by high resolution mice
+const WHEEL_DELTA: f32 = 120.0;
+
+#[cfg(not(any(windows, target_os = "linux")))]
+struct InputBackend;
+
+#[cfg(not(any(windows, target_os = "linux")))]
+impl InputBackend {
+    const SUPPORTS_UNICODE: bool = false;
+
+    fn new() -> Result<Self> {
+        alvr_common::anyhow::bail!("Not supported on this platform")
+    }
+
+    fn key(&mut self, _: u16, _: bool) {}
+    fn unicode(&mut self, _: char, _: bool) {}
+    fn mouse_move(&mut self, _: alvr_common::glam::IVec2) {}
+    fn mouse_button(&mut self, _: MouseButton, _: bool) {}
+    fn scroll(&mut self, _: alvr_common::glam::IVec2) {}
+}
+
+// Injects the keyboard and mouse events of the client into the OS. All keys and buttons still held
+// are released when the injector is disabled or dropped, to avoid stuck modifiers on disconnection.
+pub struct PeripheralInputInjector {
+    config: PeripheralInputConfig,
+    backend: InputBackend,
+    pressed_keys: HashSet<u16>,
+    // Scancode -> character, for keys injected as typed characters
+    pressed_unicode_keys: HashMap<u16, char>,
+    pressed_buttons: HashSet<MouseButton>,
+    // Sub-unit motion left after the sensitivity scaling
+    motion_remainder: Vec2,
+    scroll_remainder: Vec2,
+    enabled: bool,
+}
+
+impl PeripheralInputInjector {
+    pub fn new(config: PeripheralInputConfig) -> Result<Self> {
+        let backend = InputBackend::new()?;
+
+        if config.keyboard_mode == KeyboardInjectionMode::Unicode && !InputBackend::SUPPORTS_UNICODE
+        {
+            warn!("Typed characters keyboard mode is not supported on this platform");
+        }
+
+        alvr_events::send_event(EventType::PeripheralInputInjection { active: true });
+
+        Ok(Self {
+            config,
+            backend,
+            pressed_keys: HashSet::new(),
+            pressed_unicode_keys: HashMap::new(),
+            pressed_buttons: HashSet::new(),
+            motion_remainder: Vec2::ZERO,
+            scroll_remainder: Vec2::ZERO,
+            enabled: true,
+        })
+    }
+
+    fn any_key_pressed(&self, keys: &[u16]) -> bool {
+        keys.iter().any(|key| self.pressed_keys.contains(key))
+    }
+
+    pub fn inject(&mut self, input: PeripheralInput) {
+        if !self.enabled {
+            return;
+        }
+
+        match input {
+            PeripheralInput::Key {
+                scancode,
+                unicode,
+                pressed,
+            } => {
+                if pressed
+                    && scancode == KEY_F12
+                    && self.any_key_pressed(&[KEY_LEFTCTRL, KEY_RIGHTCTRL])
+                    && self.any_key_pressed(&[KEY_LEFTSHIFT, KEY_RIGHTSHIFT])
+                {
+                    info!("Keyboard and mouse passthrough stopped by the user");
+                    self.disable();
+
+                    return;
+                }
+
+                if !pressed && let Some(unicode) = self.pressed_unicode_keys.remove(&scancode) {
+                    self.backend.unicode(unicode, false);
+
+                    return;
+                }
+
+                // Shortcuts must be injected by position, otherwise they would type characters
+                let shortcut_modifier_pressed = self.any_key_pressed(&[
+                    KEY_LEFTCTRL,
+                    KEY_RIGHTCTRL,
+                    KEY_LEFTALT,
+                    KEY_RIGHTALT,
+                    KEY_LEFTMETA,
+                    KEY_RIGHTMETA,
+                ]);
+                if pressed
+                    && self.config.keyboard_mode == KeyboardInjectionMode::Unicode
+                    && InputBackend::SUPPORTS_UNICODE
+                    && !shortcut_modifier_pressed
+                    && let Some(unicode) = unicode.filter(|c| !c.is_control())
+                {
+                    self.backend.unicode(unicode, true);
+                    self.pressed_unicode_keys.insert(scancode, unicode);
+
+                    return;
+                }
+
+                if pressed {
+                    self.pressed_keys.insert(scancode);
+                } else {
+                    self.pressed_keys.remove(&scancode);
+                }
+                self.backend.key(scancode, pressed);
+            }
+            PeripheralInput::MouseMove(delta) => {
+                let motion = delta * self.config.mouse_sensitivity + self.motion_remainder;
+                let rounded = motion.round();
+                self.motion_remainder = motion - rounded;
+
+                if rounded != Vec2::ZERO {
+                    self.backend.mouse_move(rounded.as_ivec2());
+                }
+            }
+            PeripheralInput::MouseButton { button, pressed } => {
+                if pressed {
+                    self.pressed_buttons.insert(button);
+                } else {
+                    self.pressed_buttons.remove(&button);
+                }
+                self.backend.mouse_button(button, pressed);
+            }
+            PeripheralInput::MouseScroll(delta) => {
+                let scroll = delta * WHEEL_DELTA + self.scroll_remainder;
+                let rounded = scroll.round();
+                self.scroll_remainder = scroll - rounded;
+
+                if rounded != Vec2::ZERO {
+                    self.backend.scroll(rounded.as_ivec2());
+                }
+            }
+        }
+    }
+
+    fn release_all(&mut self) {
+        for scancode in self.pressed_keys.drain() {
+            self.backend.key(scancode, false);
+        }
+        for (_, unicode) in self.pressed_unicode_keys.drain() {
+            self.backend.unicode(unicode, false);
+        }
+        for button in self.pressed_buttons.drain() {
+            self.backend.mouse_button(button, false);
+        }
+    }
+
+    fn disable(&mut self) {
+        if self.enabled {
+            self.release_all();
+            self.enabled = false;
+
+            alvr_events::send_event(EventType::PeripheralInputInjection { active: false });
+        }
+    }
+}
+
+impl Drop for PeripheralInputInjector {
+    fn drop(&mut self) {
+        self.disable();
+    }
+}
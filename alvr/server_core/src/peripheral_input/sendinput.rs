@@ -0,0 +1,165 @@
+use alvr_common::{anyhow::Result, glam::IVec2, warn};
+use alvr_packets::MouseButton;
+use std::mem;
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    INPUT, INPUT_0, INPUT_KEYBOARD, INPUT_MOUSE, KEYBD_EVENT_FLAGS, KEYBDINPUT,
+    KEYEVENTF_EXTENDEDKEY, KEYEVENTF_KEYUP, KEYEVENTF_SCANCODE, KEYEVENTF_UNICODE,
+    MOUSE_EVENT_FLAGS, MOUSEEVENTF_HWHEEL, MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP,
+    MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP, MOUSEEVENTF_MOVE, MOUSEEVENTF_RIGHTDOWN,
+    MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_WHEEL, MOUSEEVENTF_XDOWN, MOUSEEVENTF_XUP, MOUSEINPUT,
+    SendInput, VIRTUAL_KEY,
+};
+
+const XBUTTON1: u32 = 1;
+const XBUTTON2: u32 = 2;
+
+// Evdev key codes up to KEY_F12 match the set 1 scancodes. The others are mapped to the extended
+// set 1 scancodes (prefixed by 0xE0). Returns the scancode and whether it is extended.
+fn evdev_to_set1_scancode(code: u16) -> Option<(u16, bool)> {
+    match code {
+        1..=88 => Some((code, false)),
+        89 => Some((0x73, false)),  // KEY_RO
+        92 => Some((0x79, false)),  // KEY_HENKAN
+        93 => Some((0x70, false)),  // KEY_KATAKANAHIRAGANA
+        94 => Some((0x7B, false)),  // KEY_MUHENKAN
+        96 => Some((0x1C, true)),   // KEY_KPENTER
+        97 => Some((0x1D, true)),   // KEY_RIGHTCTRL
+        98 => Some((0x35, true)),   // KEY_KPSLASH
+        99 => Some((0x37, true)),   // KEY_SYSRQ
+        100 => Some((0x38, true)),  // KEY_RIGHTALT
+        102 => Some((0x47, true)),  // KEY_HOME
+        103 => Some((0x48, true)),  // KEY_UP
+        104 => Some((0x49, true)),  // KEY_PAGEUP
+        105 => Some((0x4B, true)),  // KEY_LEFT
+        106 => Some((0x4D, true)),  // KEY_RIGHT
+        107 => Some((0x4F, true)),  // KEY_END
+        108 => Some((0x50, true)),  // KEY_DOWN
+        109 => Some((0x51, true)),  // KEY_PAGEDOWN
+        110 => Some((0x52, true)),  // KEY_INSERT
+        111 => Some((0x53, true)),  // KEY_DELETE
+        113 => Some((0x20, true)),  // KEY_MUTE
+        114 => Some((0x2E, true)),  // KEY_VOLUMEDOWN
+        115 => Some((0x30, true)),  // KEY_VOLUMEUP
+        124 => Some((0x7D, false)), // KEY_YEN
+        125 => Some((0x5B, true)),  // KEY_LEFTMETA
+        126 => Some((0x5C, true)),  // KEY_RIGHTMETA
+        127 => Some((0x5D, true)),  // KEY_COMPOSE
+        163 => Some((0x19, true)),  // KEY_NEXTSONG
+        164 => Some((0x22, true)),  // KEY_PLAYPAUSE
+        165 => Some((0x10, true)),  // KEY_PREVIOUSSONG
+        166 => Some((0x24, true)),  // KEY_STOPCD
+        _ => None,
+    }
+}
+
+fn keyboard_input(scan: u16, flags: KEYBD_EVENT_FLAGS) -> INPUT {
+    INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: VIRTUAL_KEY(0),
+                wScan: scan,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    }
+}
+
+fn mouse_input(dx: i32, dy: i32, data: u32, flags: MOUSE_EVENT_FLAGS) -> INPUT {
+    INPUT {
+        r#type: INPUT_MOUSE,
+        Anonymous: INPUT_0 {
+            mi: MOUSEINPUT {
+                dx,
+                dy,
+                mouseData: data,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    }
+}
+
+fn send(inputs: &[INPUT]) {
+    let sent = unsafe { SendInput(inputs, mem::size_of::<INPUT>() as i32) };
+
+    // Input is blocked by UIPI while an elevated window is focused
+    if sent as usize != inputs.len() {
+        warn!("SendInput failed: {}", std::io::Error::last_os_error());
+    }
+}
+
+pub struct InputBackend;
+
+impl InputBackend {
+    pub const SUPPORTS_UNICODE: bool = true;
+
+    pub fn new() -> Result<Self> {
+        Ok(Self)
+    }
+
+    pub fn key(&mut self, code: u16, pressed: bool) {
+        let Some((scan, extended)) = evdev_to_set1_scancode(code) else {
+            return;
+        };
+
+        let mut flags = KEYEVENTF_SCANCODE;
+        if extended {
+            flags |= KEYEVENTF_EXTENDEDKEY;
+        }
+        if !pressed {
+            flags |= KEYEVENTF_KEYUP;
+        }
+
+        send(&[keyboard_input(scan, flags)]);
+    }
+
+    pub fn unicode(&mut self, unicode: char, pressed: bool) {
+        let flags = if pressed {
+            KEYEVENTF_UNICODE
+        } else {
+            KEYEVENTF_UNICODE | KEYEVENTF_KEYUP
+        };
+
+        // Characters outside of the BMP are sent as a surrogate pair
+        let inputs = unicode
+            .encode_utf16(&mut [0; 2])
+            .iter()
+            .map(|unit| keyboard_input(*unit, flags))
+            .collect::<Vec<_>>();
+
+        send(&inputs);
+    }
+
+    pub fn mouse_move(&mut self, delta: IVec2) {
+        send(&[mouse_input(delta.x, delta.y, 0, MOUSEEVENTF_MOVE)]);
+    }
+
+    pub fn mouse_button(&mut self, button: MouseButton, pressed: bool) {
+        let (down_flags, up_flags, data) = match button {
+            MouseButton::Left => (MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP, 0),
+            MouseButton::Right => (MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP, 0),
+            MouseButton::Middle => (MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP, 0),
+            MouseButton::Back => (MOUSEEVENTF_XDOWN, MOUSEEVENTF_XUP, XBUTTON1),
+            MouseButton::Forward => (MOUSEEVENTF_XDOWN, MOUSEEVENTF_XUP, XBUTTON2),
+        };
+        let flags = if pressed { down_flags } else { up_flags };
+
+        send(&[mouse_input(0, 0, data, flags)]);
+    }
+
+    pub fn scroll(&mut self, delta: IVec2) {
+        let mut inputs = vec![];
+        if delta.y != 0 {
+            inputs.push(mouse_input(0, 0, delta.y as u32, MOUSEEVENTF_WHEEL));
+        }
+        if delta.x != 0 {
+            inputs.push(mouse_input(0, 0, delta.x as u32, MOUSEEVENTF_HWHEEL));
+        }
+
+        send(&inputs);
+    }
+}
@@ -0,0 +1,202 @@
+use alvr_common::{
+    anyhow::{Context, Result, bail},
+    glam::IVec2,
+    warn,
+};
+use alvr_packets::MouseButton;
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    mem,
+    os::{fd::AsRawFd, unix::fs::OpenOptionsExt},
+    slice,
+};
+
+// From linux/uinput.h and linux/input-event-codes.h
+const UI_DEV_CREATE: u64 = 0x5501;
+const UI_DEV_DESTROY: u64 = 0x5502;
+const UI_DEV_SETUP: u64 = 0x405c5503;
+const UI_SET_EVBIT: u64 = 0x40045564;
+const UI_SET_KEYBIT: u64 = 0x40045565;
+const UI_SET_RELBIT: u64 = 0x40045566;
+const BUS_VIRTUAL: u16 = 0x06;
+
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const SYN_REPORT: u16 = 0;
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const REL_HWHEEL: u16 = 0x06;
+const REL_WHEEL: u16 = 0x08;
+const REL_WHEEL_HI_RES: u16 = 0x0b;
+const REL_HWHEEL_HI_RES: u16 = 0x0c;
+const KEY_MICMUTE: u16 = 248;
+const BTN_LEFT: u16 = 0x110;
+const BTN_RIGHT: u16 = 0x111;
+const BTN_MIDDLE: u16 = 0x112;
+const BTN_SIDE: u16 = 0x113;
+const BTN_EXTRA: u16 = 0x114;
+
+// High resolution units per wheel notch
+const WHEEL_DELTA: i32 = 120;
+
+#[repr(C)]
+struct InputId {
+    bustype: u16,
+    vendor: u16,
+    product: u16,
+    version: u16,
+}
+
+#[repr(C)]
+struct UinputSetup {
+    id: InputId,
+    name: [u8; 80],
+    ff_effects_max: u32,
+}
+
+#[repr(C)]
+struct InputEvent {
+    time: libc::timeval,
+    ty: u16,
+    code: u16,
+    value: i32,
+}
+
+fn ioctl(file: &File, request: u64, arg: libc::c_ulong) -> Result<()> {
+    if unsafe { libc::ioctl(file.as_raw_fd(), request as _, arg) } < 0 {
+        bail!("{}", std::io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+// Virtual keyboard and mouse. Key codes sent by the client are already evdev codes.
+pub struct InputBackend {
+    device: File,
+    // Scroll in high resolution units not yet reported as whole notches
+    wheel_remainder: IVec2,
+}
+
+impl InputBackend {
+    pub const SUPPORTS_UNICODE: bool = false;
+
+    pub fn new() -> Result<Self> {
+        let device = OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open("/dev/uinput")
+            .context("Failed to open /dev/uinput")?;
+
+        ioctl(&device, UI_SET_EVBIT, EV_KEY as _)?;
+        for code in (1..=KEY_MICMUTE).chain(BTN_LEFT..=BTN_EXTRA) {
+            ioctl(&device, UI_SET_KEYBIT, code as _)?;
+        }
+
+        ioctl(&device, UI_SET_EVBIT, EV_REL as _)?;
+        for code in [
+            REL_X,
+            REL_Y,
+            REL_HWHEEL,
+            REL_WHEEL,
+            REL_WHEEL_HI_RES,
+            REL_HWHEEL_HI_RES,
+        ] {
+            ioctl(&device, UI_SET_RELBIT, code as _)?;
+        }
+
+        let mut setup = UinputSetup {
+            id: InputId {
+                bustype: BUS_VIRTUAL,
+                vendor: 0,
+                product: 0,
+                version: 1,
+            },
+            name: [0; 80],
+            ff_effects_max: 0,
+        };
+        let name = b"ALVR keyboard and mouse";
+        setup.name[..name.len()].copy_from_slice(name);
+
+        ioctl(&device, UI_DEV_SETUP, &setup as *const _ as _)?;
+        ioctl(&device, UI_DEV_CREATE, 0)?;
+
+        Ok(Self {
+            device,
+            wheel_remainder: IVec2::ZERO,
+        })
+    }
+
+    // A sync report is appended to make the events visible as a single update
+    fn send(&mut self, events: &[(u16, u16, i32)]) {
+        let events = events
+            .iter()
+            .chain([&(EV_SYN, SYN_REPORT, 0)])
+            .map(|&(ty, code, value)| InputEvent {
+                time: libc::timeval {
+                    tv_sec: 0,
+                    tv_usec: 0,
+                },
+                ty,
+                code,
+                value,
+            })
+            .collect::<Vec<_>>();
+
+        let bytes = unsafe {
+            slice::from_raw_parts(
+                events.as_ptr().cast::<u8>(),
+                events.len() * mem::size_of::<InputEvent>(),
+            )
+        };
+
+        if let Err(e) = self.device.write_all(bytes) {
+            warn!("Failed to write uinput events: {e}");
+        }
+    }
+
+    pub fn key(&mut self, code: u16, pressed: bool) {
+        if (1..=KEY_MICMUTE).contains(&code) {
+            self.send(&[(EV_KEY, code, pressed as i32)]);
+        }
+    }
+
+    pub fn unicode(&mut self, _: char, _: bool) {}
+
+    pub fn mouse_move(&mut self, delta: IVec2) {
+        self.send(&[(EV_REL, REL_X, delta.x), (EV_REL, REL_Y, delta.y)]);
+    }
+
+    pub fn mouse_button(&mut self, button: MouseButton, pressed: bool) {
+        let code = match button {
+            MouseButton::Left => BTN_LEFT,
+            MouseButton::Right => BTN_RIGHT,
+            MouseButton::Middle => BTN_MIDDLE,
+            MouseButton::Back => BTN_SIDE,
+            MouseButton::Forward => BTN_EXTRA,
+        };
+
+        self.send(&[(EV_KEY, code, pressed as i32)]);
+    }
+
+    pub fn scroll(&mut self, delta: IVec2) {
+        // Applications that do not support high resolution scrolling use the whole notches
+        self.wheel_remainder += delta;
+        let notches = self.wheel_remainder / WHEEL_DELTA;
+        self.wheel_remainder -= notches * WHEEL_DELTA;
+
+        self.send(&[
+            (EV_REL, REL_WHEEL_HI_RES, delta.y),
+            (EV_REL, REL_HWHEEL_HI_RES, delta.x),
+            (EV_REL, REL_WHEEL, notches.y),
+            (EV_REL, REL_HWHEEL, notches.x),
+        ]);
+    }
+}
+
+impl Drop for InputBackend {
+    fn drop(&mut self) {
+        ioctl(&self.device, UI_DEV_DESTROY, 0).ok();
+    }
+}
@@ -1,13 +1,19 @@
-use alvr_common::{HEAD_ID, SlidingWindowAverage};
+use crate::clock_sync::{self, ClockEstimate, ClockEstimator};
+use alvr_common::{HEAD_ID, SlidingWindowAverage, trace_instant, trace_span};
 use alvr_events::{BitrateDirectives, EventType, GraphStatistics, StatisticsSummary};
-use alvr_packets::ClientStatistics;
+use alvr_packets::{ClientStatistics, ThermalStatus};
 use std::{
     collections::{HashMap, VecDeque},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
     time::{Duration, Instant},
 };
 
 const FULL_REPORT_INTERVAL: Duration = Duration::from_millis(500);
 const EPS_INTERVAL: Duration = Duration::from_micros(1);
+const MIN_CLOCK_CONFIDENCE: f32 = 0.5;
 
 pub struct HistoryFrame {
     target_timestamp: Duration,
@@ -15,8 +21,12 @@ pub struct HistoryFrame {
     frame_present: Instant,
     frame_composed: Instant,
     frame_encoded: Instant,
+    // When the last packet of the frame was sent
+    video_sent: Option<Instant>,
     video_packet_bytes: usize,
     total_pipeline_latency: Duration,
+    // How much newer the head orientation the frame was re-projected to is
+    server_reprojection_offset: Duration,
 }
 
 impl Default for HistoryFrame {
@@ -28,12 +38,37 @@ impl Default for HistoryFrame {
             frame_present: now,
             frame_composed: now,
             frame_encoded: now,
+            video_sent: None,
             video_packet_bytes: 0,
             total_pipeline_latency: Duration::ZERO,
+            server_reprojection_offset: Duration::ZERO,
         }
     }
 }
 
+// Send rate of a stream, from the counter of the bytes sent including the protocol overhead
+struct StreamSendRate {
+    counter: Arc<AtomicUsize>,
+    last_total: usize,
+}
+
+impl StreamSendRate {
+    fn new(counter: Arc<AtomicUsize>) -> Self {
+        Self {
+            last_total: counter.load(Ordering::Relaxed),
+            counter,
+        }
+    }
+
+    fn kbits_per_sec(&mut self, interval_secs: f32) -> f32 {
+        let total = self.counter.load(Ordering::Relaxed);
+        let bytes = total.wrapping_sub(self.last_total);
+        self.last_total = total;
+
+        bytes as f32 * 8. / 1e3 / interval_secs
+    }
+}
+
 #[derive(Default, Clone)]
 struct BatteryData {
     gauge_value: f32,
@@ -51,11 +86,26 @@ pub struct StatisticsManager {
     video_bytes_total: usize,
     video_bytes_partial_sum: usize,
     battery_gauges: HashMap<u64, BatteryData>,
+    hmd_thermal_status: Option<ThermalStatus>,
+    idr_requests_total: usize,
+    intra_refresh_recoveries_total: usize,
+    tracking_packets_discarded_total: usize,
+    // Time with the frame rate reduced by the idle throttling
+    idle_duration_total: Duration,
+    idle_since: Option<Instant>,
     steamvr_pipeline_latency: Duration,
     motion_to_photon_latency_average: SlidingWindowAverage<Duration>,
     last_vsync_time: Instant,
     frame_interval: Duration,
     last_throughput_directives: BitrateDirectives,
+    clock_estimator: ClockEstimator,
+    pending_wifi_transition: Option<String>,
+    audio_send_rate: Option<StreamSendRate>,
+    haptics_send_rate: Option<StreamSendRate>,
+    // From the client to the server, measured on the statistics packets
+    uplink_latency_average: Option<SlidingWindowAverage<Duration>>,
+    // From the server to the client, measured on the last packet of each video frame
+    downlink_latency_average: Option<SlidingWindowAverage<Duration>>,
 }
 
 impl StatisticsManager {
@@ -76,6 +126,12 @@ impl StatisticsManager {
             video_bytes_total: 0,
             video_bytes_partial_sum: 0,
             battery_gauges: HashMap::new(),
+            hmd_thermal_status: None,
+            idr_requests_total: 0,
+            intra_refresh_recoveries_total: 0,
+            tracking_packets_discarded_total: 0,
+            idle_duration_total: Duration::ZERO,
+            idle_since: None,
             steamvr_pipeline_latency: Duration::from_secs_f32(
                 steamvr_pipeline_frames * nominal_server_frame_interval.as_secs_f32(),
             ),
@@ -86,20 +142,38 @@ impl StatisticsManager {
             last_vsync_time: Instant::now(),
             frame_interval: nominal_server_frame_interval,
             last_throughput_directives: BitrateDirectives::default(),
+            clock_estimator: ClockEstimator::default(),
+            pending_wifi_transition: None,
+            audio_send_rate: None,
+            haptics_send_rate: None,
+            uplink_latency_average: None,
+            downlink_latency_average: None,
         }
     }
 
+    pub fn set_stream_sent_bytes_counters(
+        &mut self,
+        audio_counter: Arc<AtomicUsize>,
+        haptics_counter: Arc<AtomicUsize>,
+    ) {
+        self.audio_send_rate = Some(StreamSendRate::new(audio_counter));
+        self.haptics_send_rate = Some(StreamSendRate::new(haptics_counter));
+    }
+
     pub fn report_tracking_received(&mut self, target_timestamp: Duration) {
         if !self
             .history_buffer
             .iter()
             .any(|frame| frame.target_timestamp == target_timestamp)
         {
+            let now = Instant::now();
             self.history_buffer.push_front(HistoryFrame {
                 target_timestamp,
-                tracking_received: Instant::now(),
+                tracking_received: now,
                 ..Default::default()
             });
+
+            trace_instant("Tracking received", now, target_timestamp);
         }
 
         if self.history_buffer.len() > self.max_history_size {
@@ -107,6 +181,12 @@ impl StatisticsManager {
         }
     }
 
+    pub fn last_tracking_timestamp(&self) -> Option<Duration> {
+        self.history_buffer
+            .front()
+            .map(|frame| frame.target_timestamp)
+    }
+
     pub fn report_frame_present(&mut self, target_timestamp: Duration, offset: Duration) {
         if let Some(frame) = self
             .history_buffer
@@ -120,6 +200,8 @@ impl StatisticsManager {
             self.last_frame_present_instant = now;
 
             frame.frame_present = now;
+
+            trace_span("Game", frame.tracking_received, now, target_timestamp);
         }
     }
 
@@ -130,6 +212,37 @@ impl StatisticsManager {
             .find(|frame| frame.target_timestamp == target_timestamp)
         {
             frame.frame_composed = Instant::now() - offset;
+
+            trace_span(
+                "Compositor",
+                frame.frame_present,
+                frame.frame_composed,
+                target_timestamp,
+            );
+        }
+    }
+
+    pub fn report_server_reprojection(
+        &mut self,
+        target_timestamp: Duration,
+        pose_timestamp: Duration,
+    ) {
+        let Some(pose_tracking_received) = self
+            .history_buffer
+            .iter()
+            .find(|frame| frame.target_timestamp == pose_timestamp)
+            .map(|frame| frame.tracking_received)
+        else {
+            return;
+        };
+
+        if let Some(frame) = self
+            .history_buffer
+            .iter_mut()
+            .find(|frame| frame.target_timestamp == target_timestamp)
+        {
+            frame.server_reprojection_offset =
+                pose_tracking_received.saturating_duration_since(frame.tracking_received);
         }
     }
 
@@ -153,6 +266,13 @@ impl StatisticsManager {
 
             frame.video_packet_bytes = bytes_count;
 
+            trace_span(
+                "Encode",
+                frame.frame_composed,
+                frame.frame_encoded,
+                target_timestamp,
+            );
+
             frame
                 .frame_encoded
                 .saturating_duration_since(frame.frame_composed)
@@ -161,11 +281,125 @@ impl StatisticsManager {
         }
     }
 
+    pub fn report_video_sent(&mut self, target_timestamp: Duration) {
+        if let Some(frame) = self
+            .history_buffer
+            .iter_mut()
+            .find(|frame| frame.target_timestamp == target_timestamp)
+        {
+            frame.video_sent = Some(Instant::now());
+        }
+    }
+
+    fn submit_one_way_latency(
+        average: &mut Option<SlidingWindowAverage<Duration>>,
+        max_history_size: usize,
+        latency: Duration,
+    ) {
+        match average {
+            Some(average) => average.submit_sample(latency),
+            None => *average = Some(SlidingWindowAverage::new(latency, max_history_size)),
+        }
+    }
+
+    // The timestamps of the client are converted with the clock sync, which assumes symmetric
+    // delays: only the queuing delays above the minimum round trip time show up as asymmetry.
+    fn report_one_way_latencies(&mut self, client_stats: &ClientStatistics, received: Instant) {
+        let Some(estimate) = self
+            .clock_estimator
+            .estimate()
+            .filter(|e| e.confidence >= MIN_CLOCK_CONFIDENCE)
+        else {
+            return;
+        };
+
+        let client_sent = estimate.server_time_of(client_stats.send_time);
+        let uplink = clock_sync::server_time_at(received).saturating_sub(client_sent);
+        Self::submit_one_way_latency(
+            &mut self.uplink_latency_average,
+            self.max_history_size,
+            uplink,
+        );
+
+        let video_sent = self
+            .history_buffer
+            .iter()
+            .find(|frame| frame.target_timestamp == client_stats.target_timestamp)
+            .and_then(|frame| frame.video_sent);
+        if let Some(video_sent) = video_sent
+            && client_stats.video_packet_received_time != Duration::ZERO
+        {
+            let client_received = estimate.server_time_of(client_stats.video_packet_received_time);
+            let downlink = client_received.saturating_sub(clock_sync::server_time_at(video_sent));
+            Self::submit_one_way_latency(
+                &mut self.downlink_latency_average,
+                self.max_history_size,
+                downlink,
+            );
+        }
+    }
+
     pub fn report_battery(&mut self, device_id: u64, gauge_value: f32, is_plugged: bool) {
         *self.battery_gauges.entry(device_id).or_default() = BatteryData {
             gauge_value,
             is_plugged,
         };
+        crate::metrics::METRICS
+            .lock()
+            .battery_gauges
+            .insert(device_id, gauge_value);
+    }
+
+    pub fn report_thermal_status(&mut self, status: ThermalStatus) {
+        self.hmd_thermal_status = Some(status);
+    }
+
+    // Marked on the next graph statistics
+    pub fn report_wifi_transition(&mut self, message: String) {
+        self.pending_wifi_transition = Some(message);
+    }
+
+    pub fn report_idr_request(&mut self) {
+        self.idr_requests_total += 1;
+    }
+
+    pub fn report_intra_refresh_recovery(&mut self) {
+        self.intra_refresh_recoveries_total += 1;
+    }
+
+    pub fn report_idle(&mut self, idle: bool) {
+        if idle {
+            self.idle_since.get_or_insert_with(Instant::now);
+        } else if let Some(since) = self.idle_since.take() {
+            self.idle_duration_total += since.elapsed();
+        }
+    }
+
+    fn idle_duration_total(&self) -> Duration {
+        self.idle_duration_total
+            + self
+                .idle_since
+                .map(|since| since.elapsed())
+                .unwrap_or_default()
+    }
+
+    pub fn report_time_sync(
+        &mut self,
+        request_time: Duration,
+        client_time: Duration,
+        response_time: Duration,
+    ) {
+        self.clock_estimator
+            .add_sample(request_time, client_time, response_time);
+    }
+
+    pub fn clock_estimate(&self) -> Option<ClockEstimate> {
+        self.clock_estimator.estimate()
+    }
+
+    // Tracking packets that arrived late or duplicated
+    pub fn report_tracking_discarded(&mut self, count: usize) {
+        self.tracking_packets_discarded_total += count;
     }
 
     pub fn report_throughput_stats(&mut self, stats: BitrateDirectives) {
@@ -175,9 +409,13 @@ impl StatisticsManager {
     // Called every frame. Some statistics are reported once every frame
     // Returns (network latency, game time latency)
     pub fn report_statistics(&mut self, client_stats: ClientStatistics) -> (Duration, Duration) {
+        self.report_one_way_latencies(&client_stats, Instant::now());
+
         self.motion_to_photon_latency_average
             .submit_sample(client_stats.total_pipeline_latency);
 
+        let idle_duration_total = self.idle_duration_total();
+
         if let Some(frame) = self
             .history_buffer
             .iter_mut()
@@ -223,7 +461,9 @@ impl StatisticsManager {
 
                 let interval_secs = FULL_REPORT_INTERVAL.as_secs_f32();
 
-                alvr_events::send_event(EventType::StatisticsSummary(StatisticsSummary {
+                let clock_estimate = self.clock_estimator.estimate();
+
+                let summary = StatisticsSummary {
                     video_packets_total: self.video_packets_total,
                     video_packets_per_sec: (self.video_packets_partial_sum as f32 / interval_secs)
                         as _,
@@ -250,7 +490,38 @@ impl StatisticsManager {
                         .cloned()
                         .unwrap_or_default()
                         .is_plugged,
-                }));
+                    hmd_thermal_status: self.hmd_thermal_status,
+                    idr_requests_total: self.idr_requests_total,
+                    intra_refresh_recoveries_total: self.intra_refresh_recoveries_total,
+                    tracking_packets_discarded_total: self.tracking_packets_discarded_total,
+                    idle_time_total_s: idle_duration_total.as_secs_f32(),
+                    decoder_queue_drops_total: client_stats.decoder_queue_drops_total as usize,
+                    extrapolated_frames_total: client_stats.extrapolated_frames_total as usize,
+                    clock_drift_ppm: clock_estimate
+                        .map(|e| e.drift_ppm as f32)
+                        .unwrap_or_default(),
+                    clock_sync_confidence: clock_estimate.map(|e| e.confidence).unwrap_or_default(),
+                    uplink_latency_ms: self
+                        .uplink_latency_average
+                        .as_ref()
+                        .map(|average| average.get_average().as_secs_f32() * 1000.),
+                    downlink_latency_ms: self
+                        .downlink_latency_average
+                        .as_ref()
+                        .map(|average| average.get_average().as_secs_f32() * 1000.),
+                    audio_kbits_per_sec: self
+                        .audio_send_rate
+                        .as_mut()
+                        .map(|rate| rate.kbits_per_sec(interval_secs))
+                        .unwrap_or_default(),
+                    haptics_kbits_per_sec: self
+                        .haptics_send_rate
+                        .as_mut()
+                        .map(|rate| rate.kbits_per_sec(interval_secs))
+                        .unwrap_or_default(),
+                };
+                crate::metrics::METRICS.lock().summary = Some(summary.clone());
+                alvr_events::send_event(EventType::StatisticsSummary(summary));
 
                 self.video_packets_partial_sum = 0;
                 self.video_bytes_partial_sum = 0;
@@ -264,7 +535,7 @@ impl StatisticsManager {
 
             // todo: use target timestamp in nanoseconds. the dashboard needs to use the first
             // timestamp as the graph time origin.
-            alvr_events::send_event(EventType::GraphStatistics(GraphStatistics {
+            let graph_statistics = GraphStatistics {
                 total_pipeline_latency_s: client_stats.total_pipeline_latency.as_secs_f32(),
                 game_time_s: game_time_latency.as_secs_f32(),
                 server_compositor_s: server_compositor_latency.as_secs_f32(),
@@ -274,12 +545,16 @@ impl StatisticsManager {
                 decoder_queue_s: client_stats.video_decoder_queue.as_secs_f32(),
                 client_compositor_s: client_stats.rendering.as_secs_f32(),
                 vsync_queue_s: client_stats.vsync_queue.as_secs_f32(),
+                server_reprojection_s: frame.server_reprojection_offset.as_secs_f32(),
                 client_fps,
                 server_fps,
                 bitrate_directives: self.last_throughput_directives.clone(),
                 throughput_bps,
                 bitrate_bps,
-            }));
+                wifi_transition: self.pending_wifi_transition.take(),
+            };
+            crate::metrics::METRICS.lock().graph_statistics = Some(graph_statistics.clone());
+            alvr_events::send_event(EventType::GraphStatistics(graph_statistics));
 
             (network_latency, game_time_latency)
         } else {
@@ -0,0 +1,397 @@
+use crate::{SESSION_MANAGER, ServerCoreContext, ServerCoreEvent};
+use alvr_common::{ViewParams, anyhow::Result, error, glam::UVec2, info, warn};
+use alvr_session::CodecType;
+use openh264::{
+    OpenH264API,
+    encoder::{BitRate, Encoder, EncoderConfig, FrameRate, FrameType},
+    formats::{RgbaSliceU8, YUVBuffer},
+};
+use std::{
+    sync::mpsc::RecvTimeoutError,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+const BLACK: [u8; 3] = [0, 0, 0];
+const WHITE: [u8; 3] = [255, 255, 255];
+const BARS: [[u8; 3]; 7] = [
+    [255, 255, 255],
+    [255, 255, 0],
+    [0, 255, 255],
+    [0, 255, 0],
+    [255, 0, 255],
+    [255, 0, 0],
+    [0, 0, 255],
+];
+
+// Steps around black and white, they merge with the neighbours if the color range or the transfer
+// function are handled incorrectly
+const RANGE_STEPS: [u8; 15] = [
+    0, 4, 8, 16, 24, 32, 64, 128, 192, 224, 232, 240, 248, 252, 255,
+];
+
+// Seven segment encoding, bit 0 to 6 are segments a to g
+const DIGIT_SEGMENTS: [u8; 10] = [0x3F, 0x06, 0x5B, 0x4F, 0x66, 0x6D, 0x7D, 0x07, 0x7F, 0x6F];
+
+const SWEEP_PERIOD_MS: u64 = 1000;
+
+// The pattern is mostly flat colors, this is enough for a sharp image
+const BITRATE_BPS: u32 = 30_000_000;
+
+struct Canvas<'a> {
+    rgba: &'a mut [u8],
+    width: i32,
+    height: i32,
+    row_pitch: usize,
+}
+
+impl Canvas<'_> {
+    fn fill_rect(&mut self, x: i32, y: i32, w: i32, h: i32, color: [u8; 3]) {
+        let x0 = x.clamp(0, self.width) as usize;
+        let x1 = (x + w).clamp(0, self.width) as usize;
+        let y0 = y.clamp(0, self.height) as usize;
+        let y1 = (y + h).clamp(0, self.height) as usize;
+
+        for row in y0..y1 {
+            let start = row * self.row_pitch;
+            for pixel in self.rgba[start + x0 * 4..start + x1 * 4].chunks_exact_mut(4) {
+                pixel.copy_from_slice(&[color[0], color[1], color[2], 255]);
+            }
+        }
+    }
+
+    // Digit cell is size x 2*size, thickness is size / 5
+    fn draw_digit(&mut self, x: i32, y: i32, size: i32, digit: usize) {
+        let t = i32::max(size / 5, 1);
+        let segments = DIGIT_SEGMENTS[digit];
+
+        let rects = [
+            (x, y, size, t),
+            (x + size - t, y, t, size),
+            (x + size - t, y + size, t, size),
+            (x, y + 2 * size - t, size, t),
+            (x, y + size, t, size),
+            (x, y, t, size),
+            (x, y + size - t / 2, size, t),
+        ];
+        for (segment, (x, y, w, h)) in rects.into_iter().enumerate() {
+            if segments & (1 << segment) != 0 {
+                self.fill_rect(x, y, w, h, WHITE);
+            }
+        }
+    }
+}
+
+// Draws color bars, a gray gradient and gray steps near black and white to check the color
+// pipeline, a sweeping bar to spot stutters and a clock into an RGBA image. The clock shows seconds
+// and milliseconds of time_ms, the wall clock time, like the one in the dashboard debug tab, so the
+// two can be compared to estimate the end-to-end latency. Also used by the driver
+pub fn draw_test_pattern(rgba: &mut [u8], width: u32, height: u32, row_pitch: usize, time_ms: u64) {
+    let mut canvas = Canvas {
+        rgba,
+        width: width as i32,
+        height: height as i32,
+        row_pitch,
+    };
+    let width = width as i32;
+    let height = height as i32;
+
+    let color_bars_height = height * 4 / 10;
+    let ramp_height = height / 10;
+    let bars_height = color_bars_height + 2 * ramp_height;
+    let sweep_height = height / 10;
+    for (i, color) in BARS.into_iter().enumerate() {
+        let x0 = width * i as i32 / BARS.len() as i32;
+        let x1 = width * (i as i32 + 1) / BARS.len() as i32;
+        canvas.fill_rect(x0, 0, x1 - x0, color_bars_height, color);
+    }
+
+    // Smooth gray gradient, to spot banding
+    for x in 0..width {
+        let value = (x * 255 / i32::max(width - 1, 1)) as u8;
+        canvas.fill_rect(x, color_bars_height, 1, ramp_height, [value; 3]);
+    }
+
+    for (i, value) in RANGE_STEPS.into_iter().enumerate() {
+        let x0 = width * i as i32 / RANGE_STEPS.len() as i32;
+        let x1 = width * (i as i32 + 1) / RANGE_STEPS.len() as i32;
+        canvas.fill_rect(
+            x0,
+            color_bars_height + ramp_height,
+            x1 - x0,
+            ramp_height,
+            [value; 3],
+        );
+    }
+
+    canvas.fill_rect(0, bars_height, width, height - bars_height, BLACK);
+
+    let sweep_width = i32::max(width / 50, 1);
+    let sweep_x =
+        ((width - sweep_width) as u64 * (time_ms % SWEEP_PERIOD_MS) / SWEEP_PERIOD_MS) as i32;
+    canvas.fill_rect(sweep_x, bars_height, sweep_width, sweep_height, WHITE);
+
+    // Clock in the format SS.mmm
+    let digits = [10000, 1000, 100, 10, 1].map(|divisor| (time_ms / divisor % 10) as usize);
+    let clock_area_top = bars_height + sweep_height;
+    let clock_area_height = height - clock_area_top;
+    let size = i32::min(clock_area_height * 2 / 5, width / 9);
+    let spacing = size / 2;
+    let clock_width = 5 * size + 5 * spacing;
+    let mut x = (width - clock_width) / 2;
+    let y = clock_area_top + (clock_area_height - 2 * size) / 2;
+    for (i, digit) in digits.into_iter().enumerate() {
+        canvas.draw_digit(x, y, size, digit);
+        x += size + spacing;
+
+        if i == 1 {
+            let t = i32::max(size / 5, 1);
+            canvas.fill_rect(x - spacing / 2 - t / 2, y + 2 * size - t, t, t, WHITE);
+            x += spacing;
+        }
+    }
+}
+
+// SPS and PPS, sent at the start of the IDR frames
+fn config_nals(bitstream: &[u8]) -> Vec<u8> {
+    let mut config = vec![];
+    for nal in openh264::nal_units(bitstream) {
+        let header = nal
+            .iter()
+            .position(|&byte| byte != 0)
+            .and_then(|start_code_end| nal.get(start_code_end + 1));
+        if matches!(header.map(|header| header & 0x1F), Some(7 | 8)) {
+            config.extend_from_slice(nal);
+        }
+    }
+
+    config
+}
+
+// Encodes the pattern on the CPU with OpenH264, both views side by side
+struct TestPatternEncoder {
+    encoder: Encoder,
+    view_resolution: UVec2,
+    frame_interval: Duration,
+    next_frame_instant: Instant,
+    rgba: Vec<u8>,
+    config: Vec<u8>,
+}
+
+impl TestPatternEncoder {
+    fn new(view_resolution: UVec2, fps: f32) -> Result<Self> {
+        let config = EncoderConfig::new()
+            .bitrate(BitRate::from_bps(BITRATE_BPS))
+            .max_frame_rate(FrameRate::from_hz(fps));
+        let encoder = Encoder::with_api_config(OpenH264API::from_source(), config)?;
+
+        Ok(Self {
+            encoder,
+            view_resolution,
+            frame_interval: Duration::from_secs_f32(1.0 / fps),
+            next_frame_instant: Instant::now(),
+            rgba: vec![0; (view_resolution.x * 2 * view_resolution.y * 4) as usize],
+            config: vec![],
+        })
+    }
+
+    fn request_idr(&mut self) {
+        self.encoder.force_intra_frame();
+    }
+
+    // Called for each tracking sample, the frames are throttled to the refresh rate
+    fn send_frame(
+        &mut self,
+        context: &ServerCoreContext,
+        timestamp: Duration,
+        local_view_params: [ViewParams; 2],
+    ) {
+        let now = Instant::now();
+        if now < self.next_frame_instant {
+            return;
+        }
+        self.next_frame_instant = Instant::max(self.next_frame_instant + self.frame_interval, now);
+
+        let Some(head_motion) = context.get_device_motion(*alvr_common::HEAD_ID, timestamp) else {
+            return;
+        };
+        let global_view_params = local_view_params.map(|params| ViewParams {
+            pose: head_motion.pose * params.pose,
+            fov: params.fov,
+        });
+
+        context.report_present(timestamp, Duration::ZERO);
+
+        let time_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO)
+            .as_millis() as u64;
+        let view_width = self.view_resolution.x as usize;
+        for view_idx in 0..2 {
+            draw_test_pattern(
+                &mut self.rgba[view_idx * view_width * 4..],
+                self.view_resolution.x,
+                self.view_resolution.y,
+                view_width * 2 * 4,
+                time_ms,
+            );
+        }
+
+        context.report_composed(timestamp, Duration::ZERO);
+
+        let yuv = YUVBuffer::from_rgb_source(RgbaSliceU8::new(
+            &self.rgba,
+            (view_width * 2, self.view_resolution.y as usize),
+        ));
+        let bitstream = match self.encoder.encode(&yuv) {
+            Ok(bitstream) => bitstream,
+            Err(e) => {
+                warn!("Failed to encode the test pattern: {e}");
+
+                return;
+            }
+        };
+        let is_idr = matches!(bitstream.frame_type(), FrameType::IDR);
+        let nal = bitstream.to_vec();
+
+        if is_idr {
+            let config = config_nals(&nal);
+            if config != self.config {
+                context.set_video_config_nals(config.clone(), CodecType::H264);
+                self.config = config;
+            }
+        }
+
+        context.send_video_nal(timestamp, global_view_params, is_idr, None, nal);
+    }
+}
+
+// Streams the test pattern without SteamVR, until the dashboard stops it. The resolution and the
+// refresh rate are the ones negotiated with the client
+pub fn stream_test_pattern() {
+    let (context, events_receiver) = ServerCoreContext::new_without_driver();
+    context.start_connection();
+
+    let mut encoder = None;
+    let mut local_view_params = [ViewParams::DUMMY; 2];
+    loop {
+        let event = match events_receiver.recv_timeout(Duration::from_millis(100)) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        match event {
+            ServerCoreEvent::ClientConnected => {
+                let config = SESSION_MANAGER.read().session().openvr_config.clone();
+                let view_resolution =
+                    UVec2::new(config.eye_resolution_width, config.eye_resolution_height);
+
+                info!("Streaming the test pattern at {view_resolution} per view");
+                encoder = TestPatternEncoder::new(view_resolution, config.refresh_rate as f32)
+                    .inspect_err(|e| error!("Failed to create the test pattern encoder: {e}"))
+                    .ok();
+            }
+            ServerCoreEvent::ClientDisconnected => encoder = None,
+            ServerCoreEvent::LocalViewParams(params) => local_view_params = params,
+            ServerCoreEvent::Tracking { poll_timestamp } => {
+                if let Some(encoder) = &mut encoder {
+                    encoder.send_frame(&context, poll_timestamp, local_view_params);
+                }
+            }
+            ServerCoreEvent::RequestIDR => {
+                if let Some(encoder) = &mut encoder {
+                    encoder.request_idr();
+                }
+            }
+            ServerCoreEvent::SetTestPattern(false)
+            | ServerCoreEvent::ShutdownPending
+            | ServerCoreEvent::RestartPending => break,
+            _ => (),
+        }
+    }
+
+    // The client is disconnected when the context is dropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: u32 = 90;
+    const HEIGHT: u32 = 100;
+
+    fn pixel(rgba: &[u8], x: usize, y: usize) -> [u8; 4] {
+        rgba[(y * WIDTH as usize + x) * 4..][..4]
+            .try_into()
+            .unwrap()
+    }
+
+    fn draw(time_ms: u64) -> Vec<u8> {
+        let mut rgba = vec![0; (WIDTH * HEIGHT * 4) as usize];
+        draw_test_pattern(&mut rgba, WIDTH, HEIGHT, WIDTH as usize * 4, time_ms);
+
+        rgba
+    }
+
+    #[test]
+    fn bars_and_steps() {
+        let rgba = draw(0);
+
+        // First and last color bars
+        assert_eq!(pixel(&rgba, 0, 0), [255, 255, 255, 255]);
+        assert_eq!(pixel(&rgba, WIDTH as usize - 1, 0), [0, 0, 255, 255]);
+
+        // Ends of the gray ramp and of the range steps
+        assert_eq!(pixel(&rgba, 0, 45), [0, 0, 0, 255]);
+        assert_eq!(pixel(&rgba, WIDTH as usize - 1, 45), [255, 255, 255, 255]);
+        assert_eq!(pixel(&rgba, 0, 55), [0, 0, 0, 255]);
+        assert_eq!(pixel(&rgba, WIDTH as usize - 1, 55), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn sweep_moves() {
+        let sweep_at = |time_ms| {
+            let rgba = draw(time_ms);
+            (0..WIDTH as usize).find(|&x| pixel(&rgba, x, 65)[0] == 255)
+        };
+
+        assert_eq!(sweep_at(0), Some(0));
+        assert!(sweep_at(500).unwrap() > WIDTH as usize / 3);
+    }
+
+    #[test]
+    fn clock_changes() {
+        assert_ne!(draw(12_345), draw(12_346));
+    }
+
+    #[test]
+    fn views_side_by_side() {
+        // The right view does not overflow the shared buffer
+        let mut rgba = vec![0; (WIDTH * 2 * HEIGHT * 4) as usize];
+        for view_idx in 0..2 {
+            draw_test_pattern(
+                &mut rgba[view_idx * WIDTH as usize * 4..],
+                WIDTH,
+                HEIGHT,
+                WIDTH as usize * 2 * 4,
+                0,
+            );
+        }
+
+        assert!(rgba.chunks_exact(4).all(|pixel| pixel[3] == 255));
+    }
+
+    #[test]
+    fn extracts_config_nals() {
+        let bitstream = [
+            0, 0, 0, 1, 0x67, 1, 2, // SPS
+            0, 0, 0, 1, 0x68, 3, // PPS
+            0, 0, 0, 1, 0x65, 4, 5, // IDR slice
+        ];
+
+        assert_eq!(
+            config_nals(&bitstream),
+            [0, 0, 0, 1, 0x67, 1, 2, 0, 0, 0, 1, 0x68, 3]
+        );
+    }
+}
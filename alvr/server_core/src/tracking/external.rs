@@ -0,0 +1,496 @@
+use crate::clock_sync;
+use alvr_common::{
+    BODY_CHEST_ID, BODY_HIPS_ID, BODY_LEFT_ELBOW_ID, BODY_LEFT_FOOT_ID, BODY_LEFT_KNEE_ID,
+    BODY_RIGHT_ELBOW_ID, BODY_RIGHT_FOOT_ID, BODY_RIGHT_KNEE_ID, DeviceMotion, Pose,
+    anyhow::Result,
+    glam::{EulerRot, Quat, Vec3, Vec4},
+    info, warn,
+};
+use alvr_session::{ExternalTrackerAssignment, ExternalTrackerRole};
+use rosc::{OscMessage, OscPacket, OscTime, OscType};
+use std::{
+    collections::{HashMap, VecDeque},
+    io::ErrorKind,
+    net::UdpSocket,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+// Name used for the headset pose reported by the external system
+const HEADSET_NAME: &str = "head";
+const MAX_HISTORY_SIZE: usize = 16;
+// Trackers without newer samples are considered lost
+const MAX_SAMPLE_AGE: Duration = Duration::from_millis(500);
+// The time tags of senders with a clock too far from ours are ignored
+const MAX_TIME_TAG_AGE: Duration = Duration::from_secs(1);
+const MIN_CALIBRATION_SAMPLES: usize = 30;
+// Seconds between 1900, the NTP epoch, and 1970
+const NTP_UNIX_OFFSET_S: u64 = 2_208_988_800;
+
+pub fn external_tracker_device_id(role: ExternalTrackerRole) -> u64 {
+    match role {
+        ExternalTrackerRole::Chest => *BODY_CHEST_ID,
+        ExternalTrackerRole::Hips => *BODY_HIPS_ID,
+        ExternalTrackerRole::LeftElbow => *BODY_LEFT_ELBOW_ID,
+        ExternalTrackerRole::RightElbow => *BODY_RIGHT_ELBOW_ID,
+        ExternalTrackerRole::LeftKnee => *BODY_LEFT_KNEE_ID,
+        ExternalTrackerRole::LeftFoot => *BODY_LEFT_FOOT_ID,
+        ExternalTrackerRole::RightKnee => *BODY_RIGHT_KNEE_ID,
+        ExternalTrackerRole::RightFoot => *BODY_RIGHT_FOOT_ID,
+    }
+}
+
+// VMC and VRChat use the Unity coordinate system, which is left handed with z forward
+fn from_unity_position(position: Vec3) -> Vec3 {
+    Vec3::new(position.x, position.y, -position.z)
+}
+
+fn from_unity_orientation(orientation: Quat) -> Quat {
+    Quat::from_xyzw(-orientation.x, -orientation.y, orientation.z, orientation.w)
+}
+
+enum TrackerUpdate {
+    Pose(Pose),
+    Position(Vec3),
+    Orientation(Quat),
+}
+
+fn floats(args: &[OscType]) -> Option<Vec<f32>> {
+    args.iter()
+        .map(|arg| match arg {
+            OscType::Float(value) => Some(*value),
+            OscType::Double(value) => Some(*value as f32),
+            _ => None,
+        })
+        .collect()
+}
+
+// Supports the trackers and bones of VMC, and the trackers of VRChat OSC, as sent by SlimeVR
+fn parse_message(message: &OscMessage) -> Option<(String, TrackerUpdate)> {
+    match message.addr.as_str() {
+        "/VMC/Ext/Bone/Pos" | "/VMC/Ext/Tra/Pos" | "/VMC/Ext/Hmd/Pos" => {
+            let values = floats(message.args.get(1..)?)?;
+            let (OscType::String(name), [px, py, pz, qx, qy, qz, qw]) =
+                (message.args.first()?, values.as_slice())
+            else {
+                return None;
+            };
+
+            // The headset is identified by its serial number
+            let name = if message.addr == "/VMC/Ext/Hmd/Pos" {
+                HEADSET_NAME.into()
+            } else {
+                name.clone()
+            };
+
+            Some((
+                name,
+                TrackerUpdate::Pose(Pose {
+                    orientation: from_unity_orientation(Quat::from_xyzw(*qx, *qy, *qz, *qw)),
+                    position: from_unity_position(Vec3::new(*px, *py, *pz)),
+                }),
+            ))
+        }
+        addr => {
+            let (name, component) = addr.strip_prefix("/tracking/trackers/")?.split_once('/')?;
+            let &[x, y, z] = floats(&message.args)?.as_slice() else {
+                return None;
+            };
+
+            let update = match component {
+                "position" => TrackerUpdate::Position(from_unity_position(Vec3::new(x, y, z))),
+                // Euler angles in degrees, applied in the Unity order: z, x, then y
+                "rotation" => TrackerUpdate::Orientation(from_unity_orientation(Quat::from_euler(
+                    EulerRot::YXZ,
+                    y.to_radians(),
+                    x.to_radians(),
+                    z.to_radians(),
+                ))),
+                _ => return None,
+            };
+
+            Some((name.into(), update))
+        }
+    }
+}
+
+// OSC time tags are NTP timestamps. The value 1 means "immediately"
+fn time_tag_server_time(
+    time_tag: OscTime,
+    receive_server_time: Duration,
+    receive_system_time: SystemTime,
+) -> Duration {
+    if time_tag.seconds == 0 {
+        return receive_server_time;
+    }
+
+    let unix_time =
+        Duration::from_secs((time_tag.seconds as u64).saturating_sub(NTP_UNIX_OFFSET_S))
+            + Duration::from_nanos((time_tag.fractional as u64 * 1_000_000_000) >> 32);
+    let age = receive_system_time
+        .duration_since(UNIX_EPOCH + unix_time)
+        .unwrap_or(Duration::ZERO);
+
+    if age < MAX_TIME_TAG_AGE {
+        receive_server_time.saturating_sub(age)
+    } else {
+        receive_server_time
+    }
+}
+
+// Finds the transform from the external space to the client space, given pairs of external and
+// client poses of the headset. The rotations are averaged first, then the translations.
+fn compute_calibration(pose_pairs: &[(Pose, Pose)]) -> Option<Pose> {
+    if pose_pairs.len() < MIN_CALIBRATION_SAMPLES {
+        return None;
+    }
+
+    let rotations = pose_pairs
+        .iter()
+        .map(|(external, client)| Vec4::from(client.orientation * external.orientation.inverse()))
+        .collect::<Vec<_>>();
+    let mut rotations_sum = Vec4::ZERO;
+    for rotation in &rotations {
+        // q and -q are the same rotation
+        if rotation.dot(rotations[0]) < 0.0 {
+            rotations_sum -= *rotation;
+        } else {
+            rotations_sum += *rotation;
+        }
+    }
+    let orientation = Quat::from_vec4(rotations_sum.try_normalize()?);
+
+    let position = pose_pairs
+        .iter()
+        .map(|(external, client)| client.position - orientation * external.position)
+        .sum::<Vec3>()
+        / pose_pairs.len() as f32;
+
+    Some(Pose {
+        orientation,
+        position,
+    })
+}
+
+struct CalibrationCapture {
+    deadline: Instant,
+    pose_pairs: Vec<(Pose, Pose)>,
+}
+
+// Receives the trackers of other systems over OSC. Their samples are timestamped with the server
+// clock, and rebased onto the client clock when selecting the sample for a tracking packet.
+pub struct ExternalTrackingSource {
+    socket: UdpSocket,
+    // Newest sample last
+    histories: HashMap<String, VecDeque<(Duration, Pose)>>,
+    // VRChat OSC sends the position and rotation in separate messages
+    partial_poses: HashMap<String, Pose>,
+    calibration: Pose,
+    calibration_capture: Option<CalibrationCapture>,
+}
+
+impl ExternalTrackingSource {
+    pub fn new(port: u16, calibration: Option<Pose>) -> Result<Self> {
+        let socket = UdpSocket::bind(format!("0.0.0.0:{port}"))?;
+        socket.set_nonblocking(true)?;
+
+        if calibration.is_none() {
+            warn!("External trackers are not calibrated");
+        }
+
+        Ok(Self {
+            socket,
+            histories: HashMap::new(),
+            partial_poses: HashMap::new(),
+            calibration: calibration.unwrap_or(Pose::IDENTITY),
+            calibration_capture: None,
+        })
+    }
+
+    // Reads all the received messages
+    pub fn poll(&mut self) {
+        let mut buffer = [0; rosc::decoder::MTU];
+        loop {
+            match self.socket.recv(&mut buffer) {
+                Ok(size) => {
+                    if let Ok((_, packet)) = rosc::decoder::decode_udp(&buffer[..size]) {
+                        self.handle_packet(packet, clock_sync::server_time(), SystemTime::now());
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return,
+                Err(e) => {
+                    warn!("External trackers socket error: {e}");
+                    return;
+                }
+            }
+        }
+    }
+
+    fn handle_packet(&mut self, packet: OscPacket, server_time: Duration, system_time: SystemTime) {
+        match packet {
+            OscPacket::Message(message) => {
+                if let Some((name, update)) = parse_message(&message) {
+                    self.push_update(name, server_time, update);
+                }
+            }
+            OscPacket::Bundle(bundle) => {
+                let bundle_time = time_tag_server_time(bundle.timetag, server_time, system_time);
+                for packet in bundle.content {
+                    self.handle_packet(packet, bundle_time, system_time);
+                }
+            }
+        }
+    }
+
+    fn push_update(&mut self, name: String, server_time: Duration, update: TrackerUpdate) {
+        let pose = match update {
+            TrackerUpdate::Pose(pose) => pose,
+            TrackerUpdate::Position(position) => {
+                let pose = self.partial_poses.entry(name.clone()).or_default();
+                pose.position = position;
+                *pose
+            }
+            TrackerUpdate::Orientation(orientation) => {
+                let pose = self.partial_poses.entry(name.clone()).or_default();
+                pose.orientation = orientation;
+                *pose
+            }
+        };
+
+        let history = self.histories.entry(name).or_default();
+        history.push_back((server_time, pose));
+        if history.len() > MAX_HISTORY_SIZE {
+            history.pop_front();
+        }
+    }
+
+    // Newest sample taken before server_time, in the external space. The samples and server_time
+    // are in the clock of clock_sync::server_time(). Samples older than MAX_SAMPLE_AGE are stale.
+    fn sample_at(&self, name: &str, server_time: Duration) -> Option<Pose> {
+        self.histories
+            .get(name)?
+            .iter()
+            .rev()
+            .find(|(time, _)| *time <= server_time)
+            .filter(|(time, _)| server_time - *time < MAX_SAMPLE_AGE)
+            .map(|(_, pose)| *pose)
+    }
+
+    // Motions of the assigned trackers in the client reference space. server_time is when the
+    // tracking of the client was received
+    pub fn get_device_motions(
+        &self,
+        assignments: &[ExternalTrackerAssignment],
+        server_time: Duration,
+    ) -> Vec<(u64, DeviceMotion)> {
+        assignments
+            .iter()
+            .filter_map(|assignment| {
+                let pose = self.sample_at(&assignment.name, server_time)?;
+
+                Some((
+                    external_tracker_device_id(assignment.role),
+                    DeviceMotion {
+                        pose: self.calibration * pose,
+                        linear_velocity: Vec3::ZERO,
+                        angular_velocity: Vec3::ZERO,
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    pub fn start_calibration(&mut self, duration: Duration) {
+        info!("Calibrating the external trackers");
+
+        self.calibration_capture = Some(CalibrationCapture {
+            deadline: Instant::now() + duration,
+            pose_pairs: vec![],
+        });
+    }
+
+    // Call with the headset pose of each tracking packet and the server time it was received at.
+    // Returns the new calibration when the capture is complete
+    pub fn update_calibration(&mut self, head_pose: Pose, server_time: Duration) -> Option<Pose> {
+        let external_head_pose = self.sample_at(HEADSET_NAME, server_time);
+
+        let capture = self.calibration_capture.as_mut()?;
+        if let Some(external_head_pose) = external_head_pose {
+            capture.pose_pairs.push((external_head_pose, head_pose));
+        }
+
+        if Instant::now() < capture.deadline {
+            return None;
+        }
+
+        let capture = self.calibration_capture.take()?;
+        if let Some(calibration) = compute_calibration(&capture.pose_pairs) {
+            info!(
+                "External trackers calibrated with {} samples",
+                capture.pose_pairs.len()
+            );
+            self.calibration = calibration;
+
+            Some(calibration)
+        } else {
+            warn!(
+                "External trackers calibration failed: the headset pose was received {} times",
+                capture.pose_pairs.len()
+            );
+
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alvr_common::assert_pose_eq;
+
+    #[test]
+    fn test_parse_messages() {
+        let message = OscMessage {
+            addr: "/VMC/Ext/Tra/Pos".into(),
+            args: [
+                OscType::String("tracker1".into()),
+                OscType::Float(1.0),
+                OscType::Float(2.0),
+                OscType::Float(3.0),
+            ]
+            .into_iter()
+            .chain(Quat::from_rotation_y(0.5).to_array().map(OscType::Float))
+            .collect(),
+        };
+        let Some((name, TrackerUpdate::Pose(pose))) = parse_message(&message) else {
+            panic!();
+        };
+        assert_eq!(name, "tracker1");
+        // A left handed rotation around y is a right handed rotation in the opposite direction
+        assert_pose_eq(
+            pose,
+            Pose {
+                orientation: Quat::from_rotation_y(-0.5),
+                position: Vec3::new(1.0, 2.0, -3.0),
+            },
+        );
+
+        let message = OscMessage {
+            addr: "/tracking/trackers/3/rotation".into(),
+            args: vec![
+                OscType::Float(0.0),
+                OscType::Float(90.0),
+                OscType::Float(0.0),
+            ],
+        };
+        let Some((name, TrackerUpdate::Orientation(orientation))) = parse_message(&message) else {
+            panic!();
+        };
+        assert_eq!(name, "3");
+        assert!(orientation.abs_diff_eq(Quat::from_rotation_y(-90_f32.to_radians()), 1e-4));
+
+        let message = OscMessage {
+            addr: "/tracking/trackers/3/position".into(),
+            args: vec![OscType::Float(0.0), OscType::Float(1.0)],
+        };
+        assert!(parse_message(&message).is_none());
+    }
+
+    #[test]
+    fn test_time_tag() {
+        let receive_system_time = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let receive_server_time = Duration::from_secs(100);
+
+        let time_tag = OscTime {
+            seconds: (1_000_000 + NTP_UNIX_OFFSET_S) as u32,
+            fractional: 0,
+        };
+        assert_eq!(
+            time_tag_server_time(
+                time_tag,
+                receive_server_time,
+                receive_system_time + Duration::from_millis(20)
+            ),
+            receive_server_time - Duration::from_millis(20)
+        );
+
+        // Immediate and out of sync time tags
+        let immediate = OscTime {
+            seconds: 0,
+            fractional: 1,
+        };
+        assert_eq!(
+            time_tag_server_time(immediate, receive_server_time, receive_system_time),
+            receive_server_time
+        );
+        assert_eq!(
+            time_tag_server_time(
+                time_tag,
+                receive_server_time,
+                receive_system_time + Duration::from_secs(60)
+            ),
+            receive_server_time
+        );
+    }
+
+    #[test]
+    fn test_sample_at() {
+        let mut source = ExternalTrackingSource::new(0, None).unwrap();
+        let pose_at = |x| Pose {
+            orientation: Quat::IDENTITY,
+            position: Vec3::new(x, 0.0, 0.0),
+        };
+        let ms = Duration::from_millis;
+
+        assert!(source.sample_at("tracker", ms(1000)).is_none());
+
+        source.push_update(
+            "tracker".into(),
+            ms(1000),
+            TrackerUpdate::Pose(pose_at(1.0)),
+        );
+        source.push_update(
+            "tracker".into(),
+            ms(1010),
+            TrackerUpdate::Pose(pose_at(2.0)),
+        );
+
+        // Newest sample before the query time
+        assert_pose_eq(source.sample_at("tracker", ms(1005)).unwrap(), pose_at(1.0));
+        assert_pose_eq(source.sample_at("tracker", ms(1010)).unwrap(), pose_at(2.0));
+        assert_pose_eq(source.sample_at("tracker", ms(1100)).unwrap(), pose_at(2.0));
+
+        // Samples newer than the query time are not used, even if no older one exists
+        assert!(source.sample_at("tracker", ms(999)).is_none());
+
+        // Stale samples are rejected
+        assert!(
+            source
+                .sample_at("tracker", ms(1010) + MAX_SAMPLE_AGE)
+                .is_none()
+        );
+        assert!(source.sample_at("other", ms(1010)).is_none());
+    }
+
+    #[test]
+    fn test_calibration() {
+        let calibration = Pose {
+            orientation: Quat::from_rotation_y(1.0) * Quat::from_rotation_x(0.1),
+            position: Vec3::new(0.5, -0.2, 3.0),
+        };
+
+        let pose_pairs = (0..MIN_CALIBRATION_SAMPLES)
+            .map(|i| {
+                let t = i as f32 * 0.1;
+                let external = Pose {
+                    orientation: Quat::from_rotation_y(t) * Quat::from_rotation_z(t * 0.3),
+                    position: Vec3::new(t.sin(), 1.6, t.cos()),
+                };
+
+                (external, calibration * external)
+            })
+            .collect::<Vec<_>>();
+
+        assert_pose_eq(compute_calibration(&pose_pairs).unwrap(), calibration);
+        assert!(compute_calibration(&pose_pairs[..MIN_CALIBRATION_SAMPLES - 1]).is_none());
+    }
+}
@@ -1,6 +1,9 @@
-use alvr_common::{anyhow::Result, glam::EulerRot};
+use alvr_common::{
+    anyhow::Result,
+    glam::{EulerRot, Quat},
+};
 use alvr_packets::{FaceData, FaceExpressions};
-use alvr_session::FaceTrackingSinkConfig;
+use alvr_session::{EyeGazeForwardingConfig, FaceTrackingSinkConfig};
 use rosc::{OscMessage, OscPacket, OscType};
 use std::{f32::consts::PI, net::UdpSocket};
 
@@ -8,6 +11,72 @@ const RAD_TO_DEG: f32 = 180.0 / PI;
 
 const VRCFT_PORT: u16 = 0xA1F7;
 
+// Left and right eyes closed amount, from the facial expressions
+fn eyes_closed_amount(face_data: &FaceData) -> (Option<f32>, Option<f32>) {
+    match &face_data.face_expressions {
+        Some(FaceExpressions::Fb(items)) => (Some(items[12]), Some(items[13])),
+        Some(FaceExpressions::Pico(items)) => (Some(items[28]), Some(items[38])),
+        Some(FaceExpressions::Htc { eye, .. }) => {
+            (eye.as_ref().map(|v| v[0]), eye.as_ref().map(|v| v[2]))
+        }
+        _ => (None, None),
+    }
+}
+
+// Smooths the gaze and holds it during blinks, before forwarding it to the sink
+pub struct EyeGazeFilter {
+    config: EyeGazeForwardingConfig,
+    eyes_combined: Option<Quat>,
+    eyes_social: [Option<Quat>; 2],
+}
+
+impl EyeGazeFilter {
+    pub fn new(config: EyeGazeForwardingConfig) -> Self {
+        Self {
+            config,
+            eyes_combined: None,
+            eyes_social: [None, None],
+        }
+    }
+
+    fn filter_gaze(&self, previous: Option<Quat>, new: Option<Quat>, closed: bool) -> Option<Quat> {
+        match (previous, new) {
+            (Some(previous), _) if closed => Some(previous),
+            (Some(previous), Some(new)) => Some(previous.slerp(new, 1.0 - self.config.smoothing)),
+            (_, new) => new,
+        }
+    }
+
+    pub fn filter(&mut self, face_data: &FaceData) -> FaceData {
+        let (left_closed, right_closed) = eyes_closed_amount(face_data);
+        let is_closed = |amount: Option<f32>| {
+            self.config
+                .blink_hold_threshold
+                .as_option()
+                .zip(amount)
+                .is_some_and(|(threshold, amount)| amount > *threshold)
+        };
+        let left_closed = is_closed(left_closed);
+        let right_closed = is_closed(right_closed);
+
+        self.eyes_combined = self.filter_gaze(
+            self.eyes_combined,
+            face_data.eyes_combined,
+            left_closed && right_closed,
+        );
+        self.eyes_social = [
+            self.filter_gaze(self.eyes_social[0], face_data.eyes_social[0], left_closed),
+            self.filter_gaze(self.eyes_social[1], face_data.eyes_social[1], right_closed),
+        ];
+
+        FaceData {
+            eyes_combined: self.eyes_combined,
+            eyes_social: self.eyes_social,
+            face_expressions: face_data.face_expressions.clone(),
+        }
+    }
+}
+
 pub struct FaceTrackingSink {
     config: FaceTrackingSinkConfig,
     socket: UdpSocket,
@@ -79,14 +148,7 @@ impl FaceTrackingSink {
                     );
                 }
 
-                let (left_eye_blink, right_eye_blink) = match &face_data.face_expressions {
-                    Some(FaceExpressions::Fb(items)) => (Some(items[12]), Some(items[13])),
-                    Some(FaceExpressions::Pico(items)) => (Some(items[28]), Some(items[38])),
-                    Some(FaceExpressions::Htc { eye, .. }) => {
-                        (eye.as_ref().map(|v| v[0]), eye.as_ref().map(|v| v[2]))
-                    }
-                    _ => (None, None),
-                };
+                let (left_eye_blink, right_eye_blink) = eyes_closed_amount(face_data);
 
                 if let (Some(left), Some(right)) = (left_eye_blink, right_eye_blink) {
                     self.send_osc_message(
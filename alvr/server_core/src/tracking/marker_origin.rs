@@ -0,0 +1,130 @@
+use alvr_common::Pose;
+use alvr_session::MarkerFollowConfig;
+use std::time::{Duration, Instant};
+
+// After this time without updates the origin jumps to the marker instead of gliding to it
+const MARKER_LOST_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Keeps the playspace origin on a moving marker, like one placed on a motion platform or inside a
+// vehicle. The jitter of the marker detection is smoothed out exponentially.
+pub struct MarkerOriginFilter {
+    smoothing: Duration,
+    follow_tilt: bool,
+    // Origin relative to the marker, fixed when the marker is first seen
+    marker_to_origin: Option<Pose>,
+    last: Option<(Instant, Pose)>,
+}
+
+impl MarkerOriginFilter {
+    pub fn new(config: &MarkerFollowConfig) -> Self {
+        Self {
+            smoothing: Duration::from_secs_f32(config.smoothing_s.max(0.0)),
+            follow_tilt: config.follow_tilt,
+            marker_to_origin: None,
+            last: None,
+        }
+    }
+
+    // Returns the new origin, in the client's reference space
+    pub fn update(&mut self, marker_pose: Pose, now: Instant) -> Pose {
+        let target = if self.follow_tilt {
+            // The origin is rigidly attached to the marker, so it pitches and rolls with it
+            let marker_to_origin = *self
+                .marker_to_origin
+                .get_or_insert_with(|| marker_pose.inverse() * marker_pose.marker_floor_origin());
+
+            marker_pose * marker_to_origin
+        } else {
+            marker_pose.marker_floor_origin()
+        };
+
+        let origin = match self.last {
+            Some((last_instant, last_origin))
+                if now.saturating_duration_since(last_instant) < MARKER_LOST_TIMEOUT
+                    && !self.smoothing.is_zero() =>
+            {
+                let dt = now.saturating_duration_since(last_instant);
+                let t = 1.0 - f32::exp(-dt.as_secs_f32() / self.smoothing.as_secs_f32());
+
+                last_origin.lerp(target, t)
+            }
+            _ => target,
+        };
+
+        self.last = Some((now, origin));
+
+        origin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alvr_common::glam::{Quat, Vec3};
+
+    fn filter(smoothing_s: f32, follow_tilt: bool) -> MarkerOriginFilter {
+        MarkerOriginFilter::new(&MarkerFollowConfig {
+            smoothing_s,
+            follow_tilt,
+        })
+    }
+
+    fn marker_at(position: Vec3) -> Pose {
+        // A marker lying flat on the floor
+        Pose {
+            orientation: Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2),
+            position,
+        }
+    }
+
+    #[test]
+    fn test_smoothing() {
+        let mut filter = filter(0.1, false);
+        let start = Instant::now();
+
+        let origin = filter.update(marker_at(Vec3::ZERO), start);
+        assert!(origin.position.length() < 1e-5);
+
+        // The origin glides to the moved marker
+        let target = Vec3::new(1.0, 0.0, 0.0);
+        let mut now = start;
+        let mut run = |steps| {
+            let mut origin = None;
+            for _ in 0..steps {
+                now += Duration::from_millis(10);
+                origin = Some(filter.update(marker_at(target), now));
+            }
+
+            origin.unwrap()
+        };
+        let origin = run(10);
+        assert!(origin.position.x > 0.5 && origin.position.x < 0.7);
+        let origin = run(100);
+        assert!(origin.position.distance(target) < 1e-3);
+
+        // After losing the marker the origin jumps to it
+        let target = Vec3::new(-2.0, 0.0, 0.0);
+        let origin = filter.update(marker_at(target), now + MARKER_LOST_TIMEOUT);
+        assert!(origin.position.distance(target) < 1e-5);
+    }
+
+    #[test]
+    fn test_follow_tilt() {
+        let mut filter = filter(0.0, true);
+        let now = Instant::now();
+
+        let marker = marker_at(Vec3::new(0.0, 0.5, 0.0));
+        let origin = filter.update(marker, now);
+        assert!(origin.position.distance(Vec3::ZERO) < 1e-5);
+        assert!(origin.orientation.angle_between(Quat::IDENTITY) < 1e-3);
+
+        // The platform pitches forward around the origin
+        let tilt = Pose {
+            orientation: Quat::from_rotation_x(0.2),
+            position: Vec3::ZERO,
+        };
+        let origin = filter.update(tilt * marker, now);
+        assert!(origin.position.distance(Vec3::ZERO) < 1e-5);
+        assert!(origin.orientation.angle_between(tilt.orientation) < 1e-3);
+    }
+}
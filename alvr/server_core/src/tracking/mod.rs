@@ -1,13 +1,17 @@
 mod body;
+mod external;
 mod face;
+mod marker_origin;
+mod reorder;
 mod vmc;
 
 pub use body::*;
+pub use external::*;
 pub use face::*;
 pub use vmc::*;
 
 use crate::{
-    ConnectionContext, SESSION_MANAGER, ServerCoreEvent,
+    ConnectionContext, SESSION_MANAGER, ServerCoreEvent, clock_sync,
     connection::STREAMING_RECV_TIMEOUT,
     hand_gestures::{self, HAND_GESTURE_BUTTON_SET, HandGestureManager},
     input_mapping::ButtonMappingManager,
@@ -16,22 +20,26 @@ use alvr_common::{
     BODY_CHEST_ID, BODY_HIPS_ID, BODY_LEFT_ELBOW_ID, BODY_LEFT_FOOT_ID, BODY_LEFT_KNEE_ID,
     BODY_RIGHT_ELBOW_ID, BODY_RIGHT_FOOT_ID, BODY_RIGHT_KNEE_ID, ConnectionError,
     DEVICE_ID_TO_PATH, DeviceMotion, HAND_LEFT_ID, HAND_RIGHT_ID, HEAD_ID, Pose, ViewParams,
-    glam::{Quat, Vec3},
+    glam::{Quat, Vec2, Vec3},
+    info,
     parking_lot::Mutex,
+    warn,
 };
 use alvr_events::{EventType, TrackingEvent};
-use alvr_packets::TrackingData;
+use alvr_packets::{FaceData, TrackingData};
 use alvr_session::{
-    BodyTrackingConfig, HeadsetConfig, PositionRecenteringMode, RotationRecenteringMode, Settings,
-    VMCConfig, settings_schema::Switch,
+    BodyTrackingConfig, HeadsetConfig, MarkerFollowConfig, PositionRecenteringMode,
+    RotationRecenteringMode, Settings, VMCConfig, settings_schema::Switch,
 };
 use alvr_sockets::StreamReceiver;
+use marker_origin::MarkerOriginFilter;
+use reorder::TrackingReorderBuffer;
 use std::{
     cmp::Ordering,
     collections::{HashMap, VecDeque},
     f32::consts::PI,
     sync::Arc,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 const DEG_TO_RAD: f32 = PI / 180.0;
@@ -54,8 +62,13 @@ struct MotionConfig {
 pub struct TrackingManager {
     last_head_pose: Pose,             // client's reference space
     inverse_recentering_origin: Pose, // client's reference space
+    marker_origin: Option<Pose>,      // client's reference space
+    marker_filter: Option<MarkerOriginFilter>,
     device_motions_history: HashMap<u64, VecDeque<(Duration, DeviceMotion)>>,
     hand_skeletons_history: [VecDeque<(Duration, [Pose; 26])>; 2],
+    last_gaze: Option<(Instant, Quat)>, // relative to the head
+    // Filtered gaze exposed to the applications, only if eye gaze forwarding is enabled
+    forwarded_gaze: Option<Quat>,
     max_history_size: usize,
 }
 
@@ -64,8 +77,12 @@ impl TrackingManager {
         TrackingManager {
             last_head_pose: Pose::IDENTITY,
             inverse_recentering_origin: Pose::IDENTITY,
+            marker_origin: None,
+            marker_filter: None,
             device_motions_history: HashMap::new(),
             hand_skeletons_history: [VecDeque::new(), VecDeque::new()],
+            last_gaze: None,
+            forwarded_gaze: None,
             max_history_size,
         }
     }
@@ -75,6 +92,11 @@ impl TrackingManager {
         position_recentering_mode: PositionRecenteringMode,
         rotation_recentering_mode: RotationRecenteringMode,
     ) {
+        // The marker origin is shared with other devices, it must not be moved by recentering
+        if self.marker_origin.is_some() {
+            return;
+        }
+
         let position = match position_recentering_mode {
             PositionRecenteringMode::Disabled => Vec3::ZERO,
             PositionRecenteringMode::LocalFloor => {
@@ -109,10 +131,63 @@ impl TrackingManager {
         .inverse();
     }
 
+    // With a follow config the origin is smoothed, otherwise it jumps to the marker
+    pub fn set_marker_origin(&mut self, marker_pose: Pose, follow: Option<&MarkerFollowConfig>) {
+        let origin = if let Some(config) = follow {
+            self.marker_filter
+                .get_or_insert_with(|| MarkerOriginFilter::new(config))
+                .update(marker_pose, Instant::now())
+        } else {
+            marker_pose.marker_floor_origin()
+        };
+
+        if self.marker_origin.is_none() {
+            info!("Playspace origin set from marker");
+        }
+
+        self.marker_origin = Some(origin);
+        self.inverse_recentering_origin = origin.inverse();
+    }
+
+    // Gives the origin back to recentering once the marker origin is disabled. The current origin
+    // is kept until the next recentering
+    pub fn clear_marker_origin(&mut self) {
+        if self.marker_origin.take().is_some() {
+            info!("Playspace origin released from marker");
+        }
+        self.marker_filter = None;
+    }
+
+    // In the client's reference space
+    pub fn recentering_origin(&self) -> Pose {
+        self.inverse_recentering_origin.inverse()
+    }
+
+    // Restores an origin returned by recentering_origin(), unless the marker origin is used
+    pub fn set_recentering_origin(&mut self, origin: Pose) {
+        if self.marker_origin.is_none() {
+            self.inverse_recentering_origin = origin.inverse();
+        }
+    }
+
     pub fn recenter_pose(&self, pose: Pose) -> Pose {
         self.inverse_recentering_origin * pose
     }
 
+    // Points on the floor, as (x, z) pairs
+    pub fn recenter_floor_points(&self, points: &[Vec2]) -> Vec<Vec2> {
+        points
+            .iter()
+            .map(|point| {
+                let position = self
+                    .inverse_recentering_origin
+                    .transform_point(Vec3::new(point.x, 0.0, point.y));
+
+                Vec2::new(position.x, position.z)
+            })
+            .collect()
+    }
+
     pub fn recenter_motion(&self, motion: DeviceMotion) -> DeviceMotion {
         self.inverse_recentering_origin * motion
     }
@@ -261,6 +336,27 @@ impl TrackingManager {
             .map(|(_, skeleton)| skeleton)
     }
 
+    pub fn report_gaze(&mut self, face: &FaceData) {
+        if let Some(gaze) = combined_gaze(face) {
+            self.last_gaze = Some((Instant::now(), gaze));
+        }
+    }
+
+    // Face data sent to the face tracking sink, without the gaze if it isn't forwarded
+    pub fn report_forwarded_face(&mut self, face: &FaceData) {
+        self.forwarded_gaze = combined_gaze(face);
+    }
+
+    pub fn get_forwarded_gaze(&self) -> Option<Quat> {
+        self.forwarded_gaze
+    }
+
+    pub fn get_gaze(&self, timeout: Duration) -> Option<Quat> {
+        self.last_gaze
+            .filter(|(instant, _)| instant.elapsed() < timeout)
+            .map(|(_, gaze)| gaze)
+    }
+
     pub fn unrecenter_view_params(&self, view_params: &mut [ViewParams; 2]) {
         for params in view_params {
             params.pose = self.inverse_recentering_origin.inverse() * params.pose;
@@ -268,13 +364,33 @@ impl TrackingManager {
     }
 }
 
+// If the eyes are only tracked separately, their average is used
+fn combined_gaze(face: &FaceData) -> Option<Quat> {
+    face.eyes_combined.or(match face.eyes_social {
+        [Some(left), Some(right)] => Some(left.slerp(right, 0.5)),
+        _ => None,
+    })
+}
+
 pub fn tracking_loop(
     ctx: &ConnectionContext,
     initial_settings: Settings,
     hand_gesture_manager: Arc<Mutex<HandGestureManager>>,
     mut tracking_receiver: StreamReceiver<TrackingData>,
+    frame_interval: Duration,
     is_streaming: impl Fn() -> bool,
 ) {
+    // Late packets are handled by the reorder buffer
+    tracking_receiver.set_discard_old_packets(false);
+    let mut reorder_buffer = TrackingReorderBuffer::new(
+        initial_settings
+            .connection
+            .tracking_jitter_buffer
+            .as_option()
+            .map(|frames| frame_interval.mul_f32(frames.clamp(0.0, 1.0)))
+            .unwrap_or(Duration::ZERO),
+    );
+
     let mut gestures_button_mapping_manager =
         initial_settings
             .headset
@@ -288,13 +404,19 @@ pub fn tracking_loop(
                 )
             });
 
-    let mut face_tracking_sink = initial_settings
-        .headset
-        .face_tracking
-        .into_option()
-        .and_then(|config| {
-            FaceTrackingSink::new(config.sink, initial_settings.connection.osc_local_port).ok()
-        });
+    let face_tracking_config = initial_settings.headset.face_tracking.into_option();
+    let mut face_tracking_sink = face_tracking_config.as_ref().and_then(|config| {
+        FaceTrackingSink::new(
+            config.sink.clone(),
+            initial_settings.connection.osc_local_port,
+        )
+        .ok()
+    });
+    let mut eye_gaze_filter = face_tracking_config
+        .and_then(|config| config.eye_gaze.into_option())
+        .map(EyeGazeFilter::new);
+    let mut eye_gaze_forwarding_active = false;
+    let mut biometrics_blocked = false;
 
     let mut body_tracking_sink = initial_settings
         .headset
@@ -310,14 +432,50 @@ pub fn tracking_loop(
         .into_option()
         .and_then(|config| VMCSink::new(config).ok());
 
+    let mut external_tracking_source = initial_settings
+        .headset
+        .external_trackers
+        .as_option()
+        .and_then(|config| {
+            ExternalTrackingSource::new(
+                config.port,
+                SESSION_MANAGER
+                    .read()
+                    .session()
+                    .external_trackers_calibration,
+            )
+            .inspect_err(|e| warn!("Failed to receive the external trackers: {e}"))
+            .ok()
+        });
+
     while is_streaming() {
-        let data = match tracking_receiver.recv(STREAMING_RECV_TIMEOUT) {
-            Ok(tracking) => tracking,
-            Err(ConnectionError::TryAgain(_)) => continue,
-            Err(ConnectionError::Other(_)) => return,
-        };
-        let Ok(mut tracking) = data.get_header() else {
-            return;
+        let Some(mut tracking) = reorder_buffer.pop_ready(Instant::now()) else {
+            let discarded_count = reorder_buffer.take_discarded_count();
+            if discarded_count > 0
+                && let Some(stats) = &mut *ctx.statistics_manager.write()
+            {
+                stats.report_tracking_discarded(discarded_count);
+            }
+
+            let timeout = reorder_buffer
+                .next_deadline()
+                .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+                .unwrap_or(STREAMING_RECV_TIMEOUT);
+            let data = match tracking_receiver.recv(timeout) {
+                Ok(tracking) => tracking,
+                Err(ConnectionError::TryAgain(_)) => continue,
+                Err(ConnectionError::Other(_)) => break,
+            };
+            let tracking = match data.get_header() {
+                Ok(tracking) => tracking,
+                Err(e) => {
+                    warn!("Dropped malformed tracking packet: {e}");
+                    continue;
+                }
+            };
+            reorder_buffer.push(data.index(), Instant::now(), tracking);
+
+            continue;
         };
 
         let timestamp = tracking.poll_timestamp;
@@ -326,6 +484,66 @@ pub fn tracking_loop(
             stats.report_tracking_received(timestamp);
         }
 
+        // The data is dropped as soon as the setting changes, without waiting for the client to
+        // apply it. The sinks receive empty data once, instead of stopping at the last values.
+        // The body trackers derived from the client data are not reported, so the driver shows
+        // them as disconnected.
+        let blocked = ctx.biometric_streams_blocked.value()
+            || SESSION_MANAGER
+                .read()
+                .settings()
+                .privacy
+                .block_biometric_streams;
+        let blocked_changed = blocked != biometrics_blocked;
+        biometrics_blocked = blocked;
+        if blocked {
+            tracking.face = FaceData::default();
+            tracking.body = None;
+        }
+
+        if let Some(source) = &mut external_tracking_source {
+            source.poll();
+
+            // The external samples are timed with the server clock, they are matched with the
+            // time the tracking is processed instead of its client timestamp
+            let receive_server_time = clock_sync::server_time();
+            let external_trackers_config = SESSION_MANAGER
+                .read()
+                .settings()
+                .headset
+                .external_trackers
+                .as_option()
+                .cloned();
+
+            if ctx.external_trackers_calibration_requested.value() {
+                ctx.external_trackers_calibration_requested.set(false);
+
+                if let Some(config) = &external_trackers_config {
+                    source.start_calibration(Duration::from_secs(config.calibration_duration_s));
+                }
+            }
+
+            // The calibration and the trackers use the client reference space, before recentering
+            if let Some((_, head_motion)) = tracking
+                .device_motions
+                .iter()
+                .find(|(id, _)| *id == *HEAD_ID)
+                && let Some(calibration) =
+                    source.update_calibration(head_motion.pose, receive_server_time)
+            {
+                SESSION_MANAGER
+                    .write()
+                    .session_mut()
+                    .external_trackers_calibration = Some(calibration);
+            }
+
+            if let Some(config) = &external_trackers_config {
+                let device_motions =
+                    source.get_device_motions(&config.assignments, receive_server_time);
+                tracking.device_motions.extend(device_motions);
+            }
+        }
+
         let controllers_config = {
             let data_lock = SESSION_MANAGER.read();
             data_lock
@@ -346,9 +564,13 @@ pub fn tracking_loop(
                     &tracking.device_motions,
                 ),
             );
-            tracking.device_motions.extend_from_slice(
-                &body::get_default_body_trackers_from_motion_trackers_bd(&tracking.device_motions),
-            );
+            if !biometrics_blocked {
+                tracking.device_motions.extend_from_slice(
+                    &body::get_default_body_trackers_from_motion_trackers_bd(
+                        &tracking.device_motions,
+                    ),
+                );
+            }
             if let Some(skeleton) = &tracking.body {
                 tracking
                     .device_motions
@@ -380,8 +602,31 @@ pub fn tracking_loop(
                 tracking_manager_lock.report_hand_skeleton(HandType::Right, timestamp, skeleton);
             }
 
-            if let Some(sink) = &mut face_tracking_sink {
-                sink.send_tracking(&tracking.face);
+            tracking_manager_lock.report_gaze(&tracking.face);
+
+            // The gaze is forwarded both to the sink and to the SteamVR eye tracking
+            let face = if let Some(filter) = &mut eye_gaze_filter {
+                filter.filter(&tracking.face)
+            } else {
+                FaceData {
+                    eyes_combined: None,
+                    eyes_social: [None, None],
+                    face_expressions: tracking.face.face_expressions.clone(),
+                }
+            };
+            tracking_manager_lock.report_forwarded_face(&face);
+
+            if let Some(sink) = &mut face_tracking_sink
+                && (!biometrics_blocked || blocked_changed)
+            {
+                sink.send_tracking(&face);
+            }
+
+            let active =
+                face.eyes_combined.is_some() || face.eyes_social.iter().any(Option::is_some);
+            if active != eye_gaze_forwarding_active {
+                eye_gaze_forwarding_active = active;
+                alvr_events::send_event(EventType::EyeGazeForwarding { active });
             }
 
             if session_manager_lock.settings().extra.logging.log_tracking {
@@ -517,4 +762,72 @@ pub fn tracking_loop(
             sink.send_tracking(&device_motions);
         }
     }
+
+    if eye_gaze_forwarding_active {
+        alvr_events::send_event(EventType::EyeGazeForwarding { active: false });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracking_manager_with_marker(marker_position: Vec3) -> TrackingManager {
+        let mut manager = TrackingManager::new(10);
+        // A marker hung on a wall
+        manager.set_marker_origin(
+            Pose {
+                orientation: Quat::IDENTITY,
+                position: marker_position,
+            },
+            None,
+        );
+
+        manager
+    }
+
+    #[test]
+    fn test_marker_origin_lies_on_the_floor() {
+        let manager = tracking_manager_with_marker(Vec3::new(1.0, 1.5, -2.0));
+
+        let origin = manager.recentering_origin();
+        assert!(origin.position.distance(Vec3::new(1.0, 0.0, -2.0)) < 1e-5);
+        // Facing into the wall
+        assert!((origin.orientation * -Vec3::Z).distance(-Vec3::Z) < 1e-5);
+    }
+
+    #[test]
+    fn test_recentering_is_blocked_by_marker_origin() {
+        let mut manager = tracking_manager_with_marker(Vec3::new(1.0, 1.5, 0.0));
+
+        manager.recenter(
+            PositionRecenteringMode::Disabled,
+            RotationRecenteringMode::Disabled,
+        );
+        manager.set_recentering_origin(Pose::IDENTITY);
+
+        let origin = manager.recentering_origin();
+        assert!(origin.position.distance(Vec3::new(1.0, 0.0, 0.0)) < 1e-5);
+    }
+
+    #[test]
+    fn test_recentering_resumes_after_clearing_marker_origin() {
+        let mut manager = tracking_manager_with_marker(Vec3::new(1.0, 1.5, 0.0));
+
+        manager.clear_marker_origin();
+        manager.recenter(
+            PositionRecenteringMode::Disabled,
+            RotationRecenteringMode::Disabled,
+        );
+        assert!(manager.recentering_origin().position.length() < 1e-5);
+
+        let origin = Pose {
+            orientation: Quat::from_rotation_y(1.0),
+            position: Vec3::new(0.0, 0.0, 3.0),
+        };
+        manager.set_recentering_origin(origin);
+        let restored = manager.recentering_origin();
+        assert!(restored.position.distance(origin.position) < 1e-5);
+        assert!(restored.orientation.angle_between(origin.orientation) < 1e-3);
+    }
 }
@@ -0,0 +1,284 @@
+use alvr_packets::{FaceData, TrackingData};
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum TrackedSource {
+    Device(u64),
+    HandSkeleton(usize),
+    Face,
+    Body,
+}
+
+// Wrapping comparison of sequence numbers
+fn is_before(lhs: u32, rhs: u32) -> bool {
+    (lhs.wrapping_sub(rhs) as i32) < 0
+}
+
+// Puts the tracking packets back in order of sequence number and drops the data older than what
+// was already applied for each device. Packets wait at most for the delay before being released.
+// With a zero delay they are released as soon as they arrive and the late packets are dropped.
+pub struct TrackingReorderBuffer {
+    delay: Duration,
+    // Sorted by sequence number
+    pending: VecDeque<(u32, Instant, TrackingData)>,
+    next_sequence: Option<u32>,
+    last_timestamps: HashMap<TrackedSource, Duration>,
+    discarded_count: usize,
+}
+
+impl TrackingReorderBuffer {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            pending: VecDeque::new(),
+            next_sequence: None,
+            last_timestamps: HashMap::new(),
+            discarded_count: 0,
+        }
+    }
+
+    pub fn push(&mut self, sequence: u32, arrival: Instant, data: TrackingData) {
+        if self.pending.iter().any(|(s, _, _)| *s == sequence) {
+            self.discarded_count += 1;
+            return;
+        }
+
+        let idx = self
+            .pending
+            .iter()
+            .position(|(s, _, _)| is_before(sequence, *s))
+            .unwrap_or(self.pending.len());
+        self.pending.insert(idx, (sequence, arrival, data));
+    }
+
+    // Time at which the next packet is released even if the previous packets are still missing
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending
+            .iter()
+            .map(|(_, arrival, _)| *arrival + self.delay)
+            .min()
+    }
+
+    // Returns the next packet to apply. Call until it returns None
+    pub fn pop_ready(&mut self, now: Instant) -> Option<TrackingData> {
+        loop {
+            let (sequence, _, _) = self.pending.front()?;
+
+            // A missing packet is waited for only until the oldest pending packet times out. Late
+            // packets are released immediately to be filtered
+            let ready = self.delay.is_zero()
+                || self
+                    .next_sequence
+                    .is_none_or(|next| !is_before(next, *sequence))
+                || self.next_deadline().is_some_and(|deadline| now >= deadline);
+            if !ready {
+                return None;
+            }
+
+            let (sequence, _, data) = self.pending.pop_front().unwrap();
+            if let Some(next) = self.next_sequence
+                && is_before(sequence, next)
+            {
+                // Without buffering, late packets are dropped like in the other streams
+                if self.delay.is_zero() {
+                    self.discarded_count += 1;
+                    continue;
+                }
+            } else {
+                self.next_sequence = Some(sequence.wrapping_add(1));
+            }
+
+            if let Some(data) = self.filter_stale(data) {
+                return Some(data);
+            }
+
+            self.discarded_count += 1;
+        }
+    }
+
+    // Number of packets discarded since the last call
+    pub fn take_discarded_count(&mut self) -> usize {
+        std::mem::take(&mut self.discarded_count)
+    }
+
+    // Removes the data older than the last applied for the same source. Returns None if nothing
+    // is left
+    fn filter_stale(&mut self, mut data: TrackingData) -> Option<TrackingData> {
+        let timestamp = data.poll_timestamp;
+        let last_timestamps = &mut self.last_timestamps;
+        let mut is_fresh = |source| {
+            let fresh = last_timestamps
+                .get(&source)
+                .is_none_or(|last| timestamp >= *last);
+            if fresh {
+                last_timestamps.insert(source, timestamp);
+            }
+
+            fresh
+        };
+
+        data.device_motions
+            .retain(|(id, _)| is_fresh(TrackedSource::Device(*id)));
+        for (idx, skeleton) in data.hand_skeletons.iter_mut().enumerate() {
+            if skeleton.is_some() && !is_fresh(TrackedSource::HandSkeleton(idx)) {
+                *skeleton = None;
+            }
+        }
+        if data.body.is_some() && !is_fresh(TrackedSource::Body) {
+            data.body = None;
+        }
+        let face_fresh = is_fresh(TrackedSource::Face);
+        if !face_fresh {
+            data.face = FaceData::default();
+        }
+
+        (face_fresh
+            || !data.device_motions.is_empty()
+            || data.hand_skeletons.iter().any(Option::is_some)
+            || data.body.is_some())
+        .then_some(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alvr_common::{DeviceMotion, HAND_LEFT_ID, HEAD_ID};
+
+    const FRAME_INTERVAL: Duration = Duration::from_millis(11);
+
+    fn packet(timestamp_ms: u64, device_ids: &[u64]) -> TrackingData {
+        TrackingData {
+            poll_timestamp: Duration::from_millis(timestamp_ms),
+            device_motions: device_ids
+                .iter()
+                .map(|id| (*id, DeviceMotion::default()))
+                .collect(),
+            hand_skeletons: [None, None],
+            face: FaceData::default(),
+            body: None,
+        }
+    }
+
+    // Sequence i has timestamp 10 * i
+    fn shuffled_sequence() -> Vec<u32> {
+        vec![0, 2, 1, 3, 5, 4, 4, 6, 9, 7, 8, 10, 12, 11, 13]
+    }
+
+    fn assert_monotonic(applied: &[TrackingData]) {
+        let mut last = HashMap::new();
+        for data in applied {
+            for (id, _) in &data.device_motions {
+                if let Some(last) = last.insert(*id, data.poll_timestamp) {
+                    assert!(data.poll_timestamp >= last);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_stale_packets_are_discarded() {
+        let mut buffer = TrackingReorderBuffer::new(Duration::ZERO);
+        let now = Instant::now();
+
+        let mut applied = vec![];
+        for sequence in shuffled_sequence() {
+            buffer.push(sequence, now, packet(sequence as u64 * 10, &[*HEAD_ID]));
+            while let Some(data) = buffer.pop_ready(now) {
+                applied.push(data);
+            }
+        }
+
+        assert_monotonic(&applied);
+        // 1, 4 (twice), 7, 8 and 11 arrive after a newer packet
+        assert_eq!(applied.len(), 9);
+        assert_eq!(buffer.take_discarded_count(), 6);
+        assert_eq!(buffer.take_discarded_count(), 0);
+    }
+
+    #[test]
+    fn test_packets_are_reordered() {
+        let mut buffer = TrackingReorderBuffer::new(FRAME_INTERVAL);
+        let mut now = Instant::now();
+
+        let mut applied = vec![];
+        for sequence in shuffled_sequence() {
+            buffer.push(sequence, now, packet(sequence as u64 * 10, &[*HEAD_ID]));
+            while let Some(data) = buffer.pop_ready(now) {
+                applied.push(data);
+            }
+            now += Duration::from_millis(2);
+        }
+        now += FRAME_INTERVAL;
+        while let Some(data) = buffer.pop_ready(now) {
+            applied.push(data);
+        }
+
+        assert_monotonic(&applied);
+        assert_eq!(
+            applied
+                .iter()
+                .map(|data| data.poll_timestamp.as_millis())
+                .collect::<Vec<_>>(),
+            (0..=13).map(|i| i * 10).collect::<Vec<_>>()
+        );
+        // Only the duplicate is discarded
+        assert_eq!(buffer.take_discarded_count(), 1);
+    }
+
+    #[test]
+    fn test_lost_packet_is_not_waited_forever() {
+        let mut buffer = TrackingReorderBuffer::new(FRAME_INTERVAL);
+        let now = Instant::now();
+
+        buffer.push(0, now, packet(0, &[*HEAD_ID]));
+        assert!(buffer.pop_ready(now).is_some());
+
+        // Sequence 1 is lost
+        buffer.push(2, now, packet(20, &[*HEAD_ID]));
+        assert!(buffer.pop_ready(now).is_none());
+        assert_eq!(buffer.next_deadline(), Some(now + FRAME_INTERVAL));
+        assert!(buffer.pop_ready(now + FRAME_INTERVAL).is_some());
+
+        // The late packet is stale
+        buffer.push(1, now, packet(10, &[*HEAD_ID]));
+        assert!(buffer.pop_ready(now + FRAME_INTERVAL).is_none());
+        assert_eq!(buffer.take_discarded_count(), 1);
+    }
+
+    #[test]
+    fn test_devices_are_filtered_independently() {
+        let mut buffer = TrackingReorderBuffer::new(Duration::ZERO);
+        let now = Instant::now();
+
+        buffer.push(0, now, packet(20, &[*HEAD_ID]));
+        assert!(buffer.pop_ready(now).is_some());
+
+        // In order but with an older timestamp. The controller was not updated by the previous
+        // packet
+        buffer.push(1, now, packet(10, &[*HEAD_ID, *HAND_LEFT_ID]));
+        let data = buffer.pop_ready(now).unwrap();
+        assert_eq!(data.device_motions.len(), 1);
+        assert_eq!(data.device_motions[0].0, *HAND_LEFT_ID);
+    }
+
+    #[test]
+    fn test_sequence_wrap_around() {
+        let mut buffer = TrackingReorderBuffer::new(FRAME_INTERVAL);
+        let now = Instant::now();
+
+        buffer.push(u32::MAX, now, packet(10, &[*HEAD_ID]));
+        buffer.push(0, now, packet(20, &[*HEAD_ID]));
+        buffer.push(u32::MAX - 1, now, packet(0, &[*HEAD_ID]));
+
+        let later = now + FRAME_INTERVAL;
+        let mut applied = vec![];
+        while let Some(data) = buffer.pop_ready(later) {
+            applied.push(data.poll_timestamp.as_millis());
+        }
+        assert_eq!(applied, [0, 10, 20]);
+    }
+}
@@ -1,9 +1,12 @@
 use crate::{
     ConnectionContext, FILESYSTEM_LAYOUT, SESSION_MANAGER, ServerCoreEvent,
-    logging_backend::EVENTS_SENDER,
+    benchmark::BitrateBenchmark,
+    logging_backend::{self, EVENTS_SENDER},
 };
-use alvr_common::{ConnectionState, LogEntry, anyhow::Result, error, info, log};
-use alvr_events::{ButtonEvent, EventType};
+use alvr_common::{
+    ConnectionState, LogEntry, TargetLevels, anyhow::Result, error, info, log, warn,
+};
+use alvr_events::{ButtonEvent, EventType, SteamvrVideoSettings};
 use alvr_packets::{ButtonEntry, ClientConnectionsAction, FirewallRulesAction, PathValuePair};
 use alvr_session::SessionConfig;
 use axum::{
@@ -18,7 +21,12 @@ use axum::{
     routing,
 };
 use serde_json as json;
-use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use tokio::{net::TcpListener, sync::broadcast::error::RecvError};
 use tower_http::{
     cors::{self, CorsLayer},
@@ -26,6 +34,7 @@ use tower_http::{
 };
 
 const X_ALVR: &str = "X-ALVR";
+const MIRROR_FRAME_TIMEOUT: Duration = Duration::from_secs(1);
 
 // This is the actual core part of cors
 // We require the X-ALVR header, but the browser forces a cors preflight
@@ -67,6 +76,7 @@ pub async fn web_server(connection_context: Arc<ConnectionContext>) -> Result<()
             Router::new()
                 .route("/events", routing::get(events_websocket))
                 .route("/log", routing::post(set_log))
+                .route("/log/levels", routing::post(set_log_levels))
                 .nest(
                     "/session",
                     Router::new()
@@ -80,6 +90,32 @@ pub async fn web_server(connection_context: Arc<ConnectionContext>) -> Result<()
                 .route("/buttons", routing::post(set_buttons))
                 .route("/insert-idr", routing::post(insert_idr))
                 .route("/capture-frame", routing::post(capture_frame))
+                .route("/client-log/request", routing::post(request_client_log))
+                .route(
+                    "/external-trackers/calibrate",
+                    routing::post(calibrate_external_trackers),
+                )
+                .route("/trace/capture", routing::post(capture_trace))
+                .route("/stream/restart", routing::post(restart_stream))
+                .nest(
+                    "/test-pattern",
+                    Router::new()
+                        .route("/start", routing::post(start_test_pattern))
+                        .route("/stop", routing::post(stop_test_pattern)),
+                )
+                .nest(
+                    "/mirror",
+                    Router::new()
+                        .route("/start", routing::post(start_mirror))
+                        .route("/stop", routing::post(stop_mirror))
+                        .route("/frame", routing::get(get_mirror_frame)),
+                )
+                .nest(
+                    "/bitrate-benchmark",
+                    Router::new()
+                        .route("/start", routing::post(start_bitrate_benchmark))
+                        .route("/stop", routing::post(stop_bitrate_benchmark)),
+                )
                 .nest(
                     "/recording",
                     Router::new()
@@ -103,7 +139,12 @@ pub async fn web_server(connection_context: Arc<ConnectionContext>) -> Result<()
                     "/steamvr",
                     Router::new()
                         .route("/restart", routing::post(restart_steamvr))
-                        .route("/shutdown", routing::post(shutdown_steamvr)),
+                        .route("/shutdown", routing::post(shutdown_steamvr))
+                        .route(
+                            "/video-settings",
+                            routing::get(get_steamvr_video_settings)
+                                .post(set_steamvr_video_settings),
+                        ),
                 )
                 .route(
                     "/version",
@@ -157,6 +198,23 @@ async fn set_log(Json(entry): Json<LogEntry>) {
     log::log!(level, "{}", entry.content);
 }
 
+// The levels are validated before being saved in the session
+async fn set_log_levels(Json(spec): Json<String>) {
+    if let Err(e) = spec.parse::<TargetLevels>() {
+        warn!("Invalid log target levels: {e}");
+        return;
+    }
+
+    SESSION_MANAGER
+        .write()
+        .session_mut()
+        .session_settings
+        .extra
+        .logging
+        .target_levels = spec;
+    logging_backend::apply_target_levels();
+}
+
 async fn get_session() {
     alvr_events::send_event(EventType::Session(Box::new(
         crate::SESSION_MANAGER.read().session().clone(),
@@ -165,10 +223,12 @@ async fn get_session() {
 
 async fn update_session(Json(config): Json<SessionConfig>) {
     *SESSION_MANAGER.write().session_mut() = config;
+    logging_backend::apply_target_levels();
 }
 
 async fn set_session_values(Json(descs): Json<Vec<PathValuePair>>) {
     SESSION_MANAGER.write().set_session_values(descs).ok();
+    logging_backend::apply_target_levels();
 }
 
 async fn update_client_connections(
@@ -196,6 +256,143 @@ async fn capture_frame(State(ctx): State<Arc<ConnectionContext>>) {
     ctx.events_sender.send(ServerCoreEvent::CaptureFrame).ok();
 }
 
+async fn request_client_log(State(ctx): State<Arc<ConnectionContext>>) {
+    if SESSION_MANAGER
+        .read()
+        .client_list()
+        .values()
+        .any(|client| client.connection_state == ConnectionState::Streaming)
+    {
+        ctx.client_log_requested.set(true);
+    } else {
+        warn!("Cannot request the client log, no client is streaming");
+    }
+}
+
+async fn calibrate_external_trackers(State(ctx): State<Arc<ConnectionContext>>) {
+    if SESSION_MANAGER
+        .read()
+        .client_list()
+        .values()
+        .any(|client| client.connection_state == ConnectionState::Streaming)
+    {
+        ctx.external_trackers_calibration_requested.set(true);
+    } else {
+        warn!("Cannot calibrate the external trackers, no client is streaming");
+    }
+}
+
+async fn capture_trace(State(ctx): State<Arc<ConnectionContext>>) {
+    if SESSION_MANAGER
+        .read()
+        .client_list()
+        .values()
+        .any(|client| client.connection_state == ConnectionState::Streaming)
+    {
+        ctx.trace_capture_requested.set(true);
+    } else {
+        warn!("Cannot capture a trace, no client is streaming");
+    }
+}
+
+// The clients reconnect with the current settings
+async fn restart_stream() {
+    let mut session_manager = SESSION_MANAGER.write();
+
+    let hostnames = session_manager
+        .client_list()
+        .iter()
+        .filter(|(_, client)| client.connection_state == ConnectionState::Streaming)
+        .map(|(hostname, _)| hostname.clone())
+        .collect::<Vec<_>>();
+
+    if hostnames.is_empty() {
+        warn!("Cannot restart the stream, no client is streaming");
+    }
+
+    for hostname in hostnames {
+        session_manager.update_client_connections(
+            hostname,
+            ClientConnectionsAction::SetConnectionState(ConnectionState::Disconnecting),
+        );
+    }
+}
+
+async fn start_test_pattern(State(ctx): State<Arc<ConnectionContext>>) {
+    ctx.events_sender
+        .send(ServerCoreEvent::SetTestPattern(true))
+        .ok();
+}
+
+async fn stop_test_pattern(State(ctx): State<Arc<ConnectionContext>>) {
+    ctx.events_sender
+        .send(ServerCoreEvent::SetTestPattern(false))
+        .ok();
+}
+
+async fn start_mirror(State(ctx): State<Arc<ConnectionContext>>) {
+    ctx.mirror_requested.set(true);
+    ctx.events_sender
+        .send(ServerCoreEvent::SetMirrorEnabled(true))
+        .ok();
+}
+
+async fn stop_mirror(State(ctx): State<Arc<ConnectionContext>>) {
+    ctx.mirror_requested.set(false);
+    // The capture continues for the idle detection
+    let idle_detection = ctx.idle_detector.lock().is_some();
+    ctx.events_sender
+        .send(ServerCoreEvent::SetMirrorEnabled(idle_detection))
+        .ok();
+    *ctx.mirror_frame.lock() = None;
+}
+
+// The body is the width and height as little endian u32, followed by the RGBA pixels. No content
+// is returned if no recent frame is available, for example when not streaming.
+async fn get_mirror_frame(State(ctx): State<Arc<ConnectionContext>>) -> Response {
+    if let Some(frame) = &*ctx.mirror_frame.lock()
+        && frame.received.elapsed() < MIRROR_FRAME_TIMEOUT
+    {
+        let mut body = Vec::with_capacity(8 + frame.rgba.len());
+        body.extend_from_slice(&frame.resolution.x.to_le_bytes());
+        body.extend_from_slice(&frame.resolution.y.to_le_bytes());
+        body.extend_from_slice(&frame.rgba);
+
+        Response::builder()
+            .header(CONTENT_TYPE, "application/octet-stream")
+            .body(body.into())
+            .unwrap()
+    } else {
+        Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .body(().into())
+            .unwrap()
+    }
+}
+
+async fn start_bitrate_benchmark(State(ctx): State<Arc<ConnectionContext>>) {
+    let session_manager_lock = SESSION_MANAGER.read();
+
+    if session_manager_lock
+        .client_list()
+        .values()
+        .any(|client| client.connection_state == ConnectionState::Streaming)
+    {
+        *ctx.bitrate_benchmark.lock() = Some(BitrateBenchmark::new(
+            &session_manager_lock.settings().video.bitrate.benchmark,
+            Instant::now(),
+        ));
+    } else {
+        warn!("Cannot start the bitrate benchmark, no client is streaming");
+    }
+}
+
+async fn stop_bitrate_benchmark(State(ctx): State<Arc<ConnectionContext>>) {
+    if let Some(benchmark) = &mut *ctx.bitrate_benchmark.lock() {
+        benchmark.abort("Stopped by the user");
+    }
+}
+
 async fn start_recording(State(ctx): State<Arc<ConnectionContext>>) {
     crate::create_recording_file(&ctx, crate::SESSION_MANAGER.read().settings())
 }
@@ -255,6 +452,29 @@ async fn unregister_driver(Json(path): Json<PathBuf>) {
     }
 }
 
+async fn get_steamvr_video_settings() {
+    match alvr_server_io::get_steamvr_video_settings() {
+        Ok(settings) => alvr_events::send_event(EventType::SteamvrVideoSettings(settings)),
+        Err(e) => warn!("Failed to read the SteamVR video settings: {e}"),
+    }
+}
+
+// SteamVR is running, the file is changed through the driver
+async fn set_steamvr_video_settings(
+    State(ctx): State<Arc<ConnectionContext>>,
+    Json(settings): Json<SteamvrVideoSettings>,
+) {
+    match alvr_server_io::steamvr_video_settings_edits(&settings) {
+        // The driver reports the settings once SteamVR saved them
+        Ok(edits) => {
+            ctx.events_sender
+                .send(ServerCoreEvent::SetSteamvrSettings(edits))
+                .ok();
+        }
+        Err(e) => error!("Failed to change the SteamVR video settings: {e}"),
+    }
+}
+
 async fn restart_steamvr(State(ctx): State<Arc<ConnectionContext>>) {
     ctx.events_sender.send(ServerCoreEvent::RestartPending).ok();
 }
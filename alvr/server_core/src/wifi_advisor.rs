@@ -0,0 +1,218 @@
+use alvr_packets::{WifiBand, WifiLinkInfo};
+
+const WEAK_RSSI_DBM: i32 = -70;
+// Part of the PHY rate left to the stream after the protocol overhead and the contention
+const USABLE_LINK_RATE_FRACTION: f32 = 0.6;
+// Margin before clearing a warning, so that it does not toggle when the value hovers around the
+// threshold
+const RSSI_HYSTERESIS_DB: i32 = 5;
+const LINK_RATE_HYSTERESIS: f32 = 1.2;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum WifiWarning {
+    Band2_4GHz,
+    DfsChannel,
+    LowLinkRate,
+    WeakSignal,
+}
+
+// Channels where the access point must leave when it detects a radar
+fn is_dfs_channel(info: &WifiLinkInfo) -> bool {
+    info.band() == Some(WifiBand::Band5GHz)
+        && info
+            .channel()
+            .is_some_and(|channel| (52..=144).contains(&channel))
+}
+
+pub struct WifiAdvice {
+    // Changes of the link, which are logged
+    pub transitions: Vec<String>,
+    // Configuration issues found with this update, which are notified once until they are solved
+    pub warnings: Vec<String>,
+}
+
+// Checks the Wi-Fi link of a client against the stream configuration
+pub struct WifiAdvisor {
+    last_info: Option<WifiLinkInfo>,
+    active_warnings: Vec<WifiWarning>,
+}
+
+impl WifiAdvisor {
+    pub fn new() -> Self {
+        Self {
+            last_info: None,
+            active_warnings: vec![],
+        }
+    }
+
+    // info is None if the client is not on Wi-Fi. bitrate_mbps is the configured video bitrate.
+    pub fn update(&mut self, info: Option<&WifiLinkInfo>, bitrate_mbps: Option<u64>) -> WifiAdvice {
+        let mut advice = WifiAdvice {
+            transitions: self.transitions(info),
+            warnings: vec![],
+        };
+        self.last_info = info.cloned();
+
+        let Some(info) = info else {
+            self.active_warnings.clear();
+
+            return advice;
+        };
+
+        let was_active = |warning| self.active_warnings.contains(&warning);
+
+        let mut warnings = vec![];
+        if info.band() == Some(WifiBand::Band2_4GHz) {
+            warnings.push((
+                WifiWarning::Band2_4GHz,
+                "Headset connected on 2.4GHz, use a 5GHz or 6GHz network for a lower latency"
+                    .to_owned(),
+            ));
+        }
+        if is_dfs_channel(info) {
+            warnings.push((
+                WifiWarning::DfsChannel,
+                format!(
+                    "Headset connected on the DFS channel {}, the router can switch channel at \
+                    any time and interrupt the stream",
+                    info.channel().unwrap_or_default()
+                ),
+            ));
+        }
+        if let (Some(speed), Some(bitrate)) = (info.downlink_speed_mbps(), bitrate_mbps) {
+            let mut threshold = bitrate as f32;
+            if was_active(WifiWarning::LowLinkRate) {
+                threshold *= LINK_RATE_HYSTERESIS;
+            }
+
+            if (speed as f32) * USABLE_LINK_RATE_FRACTION < threshold {
+                warnings.push((
+                    WifiWarning::LowLinkRate,
+                    format!("Link rate {speed}Mbps too low for {bitrate}Mbps bitrate"),
+                ));
+            }
+        }
+        let mut weak_threshold = WEAK_RSSI_DBM;
+        if was_active(WifiWarning::WeakSignal) {
+            weak_threshold += RSSI_HYSTERESIS_DB;
+        }
+        if info.rssi_dbm < weak_threshold {
+            warnings.push((
+                WifiWarning::WeakSignal,
+                format!("RSSI {}dBm, move closer to the router", info.rssi_dbm),
+            ));
+        }
+
+        advice.warnings = warnings
+            .iter()
+            .filter(|(warning, _)| !was_active(*warning))
+            .map(|(_, message)| message.clone())
+            .collect();
+        self.active_warnings = warnings.into_iter().map(|(warning, _)| warning).collect();
+
+        advice
+    }
+
+    fn transitions(&self, info: Option<&WifiLinkInfo>) -> Vec<String> {
+        let (old, new) = match (&self.last_info, info) {
+            (None, None) => return vec![],
+            (None, Some(new)) => return vec![format!("Wi-Fi link: {new}")],
+            (Some(_), None) => return vec!["Wi-Fi link lost".into()],
+            (Some(old), Some(new)) => (old, new),
+        };
+
+        let mut transitions = vec![];
+        if old.bssid.is_some() && new.bssid.is_some() && old.bssid != new.bssid {
+            transitions.push(format!(
+                "Roamed to the access point {} on {}",
+                new.bssid.as_deref().unwrap_or_default(),
+                new
+            ));
+        } else if old.band() != new.band() {
+            transitions.push(format!(
+                "Wi-Fi band changed from {} to {}",
+                old.band().map_or("unknown".into(), |band| band.to_string()),
+                new
+            ));
+        } else if old.channel() != new.channel() {
+            transitions.push(format!(
+                "Wi-Fi channel changed from {} to {}",
+                old.channel().unwrap_or_default(),
+                new
+            ));
+        }
+
+        transitions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(frequency_mhz: u32, rssi_dbm: i32, speed_mbps: u32) -> WifiLinkInfo {
+        WifiLinkInfo {
+            frequency_mhz,
+            channel_width_mhz: Some(80),
+            rssi_dbm,
+            tx_link_speed_mbps: Some(speed_mbps),
+            rx_link_speed_mbps: Some(speed_mbps),
+            bssid: Some("aa:bb:cc:dd:ee:ff".into()),
+        }
+    }
+
+    #[test]
+    fn test_channels() {
+        assert_eq!(link(2412, -50, 100).channel(), Some(1));
+        assert_eq!(link(2484, -50, 100).channel(), Some(14));
+        assert_eq!(link(5180, -50, 100).channel(), Some(36));
+        assert_eq!(link(5955, -50, 100).band(), Some(WifiBand::Band6GHz));
+        assert_eq!(link(5955, -50, 100).channel(), Some(1));
+
+        assert!(!is_dfs_channel(&link(5180, -50, 100)));
+        assert!(is_dfs_channel(&link(5500, -50, 100)));
+    }
+
+    #[test]
+    fn test_warnings() {
+        let mut advisor = WifiAdvisor::new();
+
+        let advice = advisor.update(Some(&link(5180, -50, 1200)), Some(400));
+        assert_eq!(advice.transitions.len(), 1);
+        assert!(advice.warnings.is_empty());
+
+        // 2.4GHz and a low link rate are reported once
+        let advice = advisor.update(Some(&link(2437, -50, 433)), Some(400));
+        assert!(advice.transitions[0].contains("band changed"));
+        assert_eq!(advice.warnings.len(), 2);
+        let advice = advisor.update(Some(&link(2437, -50, 433)), Some(400));
+        assert!(advice.transitions.is_empty());
+        assert!(advice.warnings.is_empty());
+
+        // The weak signal is cleared only past the hysteresis
+        assert_eq!(
+            advisor
+                .update(Some(&link(2437, -72, 433)), Some(400))
+                .warnings
+                .len(),
+            1
+        );
+        assert!(
+            advisor
+                .update(Some(&link(2437, -68, 433)), Some(400))
+                .warnings
+                .is_empty()
+        );
+        advisor.update(Some(&link(2437, -60, 433)), Some(400));
+        assert_eq!(
+            advisor
+                .update(Some(&link(2437, -72, 433)), Some(400))
+                .warnings
+                .len(),
+            1
+        );
+
+        let advice = advisor.update(None, Some(400));
+        assert_eq!(advice.transitions, vec!["Wi-Fi link lost".to_owned()]);
+    }
+}
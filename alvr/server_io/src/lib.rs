@@ -1,10 +1,12 @@
 mod firewall;
 mod openvr_drivers;
 mod openvrpaths;
+mod steamvr_settings;
 
 pub use firewall::*;
 pub use openvr_drivers::*;
 pub use openvrpaths::*;
+pub use steamvr_settings::*;
 
 use alvr_common::{
     ConnectionState,
@@ -29,11 +31,21 @@ fn save_session(session: &SessionConfig, path: &Path) -> Result<()> {
     Ok(())
 }
 
+// Settings with the overrides of the connected client, if any
+fn effective_settings(session: &SessionConfig, active_client: Option<&str>) -> Settings {
+    if let Some(hostname) = active_client {
+        session.with_client_overrides(hostname).to_settings()
+    } else {
+        session.to_settings()
+    }
+}
+
 // SessionConfig wrapper that saves session.json on destruction.
 pub struct SessionLock<'a> {
     session_desc: &'a mut SessionConfig,
     session_path: Option<&'a Path>,
     settings: &'a mut Settings,
+    active_client: Option<&'a str>,
 }
 
 impl Deref for SessionLock<'_> {
@@ -55,7 +67,7 @@ impl Drop for SessionLock<'_> {
             save_session(self.session_desc, session_path).ok();
         }
 
-        *self.settings = self.session_desc.to_settings();
+        *self.settings = effective_settings(self.session_desc, self.active_client);
         alvr_events::send_event(EventType::Session(Box::new(self.session_desc.clone())));
     }
 }
@@ -69,6 +81,8 @@ pub struct ServerSessionManager {
     session_config: SessionConfig,
     settings: Settings,
     session_path: Option<PathBuf>,
+    // Hostname of the client whose settings overrides are applied
+    active_client: Option<String>,
 }
 
 impl ServerSessionManager {
@@ -85,6 +99,7 @@ impl ServerSessionManager {
             session_config: session_config.clone(),
             settings: session_config.to_settings(),
             session_path,
+            active_client: None,
         }
     }
 
@@ -145,6 +160,7 @@ impl ServerSessionManager {
             session_desc: &mut self.session_config,
             session_path: self.session_path.as_deref(),
             settings: &mut self.settings,
+            active_client: self.active_client.as_deref(),
         }
     }
 
@@ -152,6 +168,28 @@ impl ServerSessionManager {
         &self.settings
     }
 
+    // Session with the overrides of the connected client applied, as sent to the client
+    pub fn active_client_session(&self) -> SessionConfig {
+        if let Some(hostname) = &self.active_client {
+            self.session_config.with_client_overrides(hostname)
+        } else {
+            self.session_config.clone()
+        }
+    }
+
+    pub fn active_client(&self) -> Option<&str> {
+        self.active_client.as_deref()
+    }
+
+    // Set when a client connects and cleared when it disconnects, to switch between its settings
+    // and the global ones
+    pub fn set_active_client(&mut self, hostname: Option<String>) {
+        if self.active_client != hostname {
+            self.active_client = hostname;
+            self.settings = effective_settings(&self.session_config, self.active_client.as_deref());
+        }
+    }
+
     // Note: "value" can be any session subtree, in json format.
     pub fn set_session_values(&mut self, descs: Vec<PathValuePair>) -> Result<()> {
         let mut session_json = serde_json::to_value(self.session_config.clone()).unwrap();
@@ -181,7 +219,7 @@ impl ServerSessionManager {
 
         // session_json has been updated
         self.session_config = serde_json::from_value(session_json)?;
-        self.settings = self.session_config.to_settings();
+        self.settings = effective_settings(&self.session_config, self.active_client.as_deref());
 
         if let Some(session_path) = &self.session_path {
             save_session(&self.session_config, session_path)?;
@@ -199,9 +237,11 @@ impl ServerSessionManager {
     pub fn update_client_connections(&mut self, hostname: String, action: ClientConnectionsAction) {
         let mut client_connections = self.session_config.client_connections.clone();
 
+        let is_active_client = self.active_client.as_ref() == Some(&hostname);
         let maybe_client_entry = client_connections.entry(hostname);
 
         let mut updated = false;
+        let mut settings_invalidated = false;
         match action {
             ClientConnectionsAction::AddIfMissing {
                 trusted,
@@ -214,6 +254,7 @@ impl ServerSessionManager {
                         manual_ips: manual_ips.into_iter().collect(),
                         trusted,
                         connection_state: ConnectionState::Disconnected,
+                        settings_overrides: vec![],
                     };
                     new_entry.insert(client_connection_desc);
 
@@ -266,10 +307,22 @@ impl ServerSessionManager {
                     updated = true;
                 }
             }
+            ClientConnectionsAction::SetSettingsOverrides(overrides) => {
+                if let Entry::Occupied(mut entry) = maybe_client_entry {
+                    entry.get_mut().settings_overrides = overrides;
+
+                    updated = true;
+                    settings_invalidated = is_active_client;
+                }
+            }
         }
 
         if updated {
             self.session_config.client_connections = client_connections;
+            if settings_invalidated {
+                self.settings =
+                    effective_settings(&self.session_config, self.active_client.as_deref());
+            }
 
             if let Some(session_path) = &self.session_path {
                 save_session(&self.session_config, session_path).ok();
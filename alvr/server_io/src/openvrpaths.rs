@@ -28,14 +28,12 @@ fn openvr_source_file_path() -> Result<PathBuf> {
 
 pub fn steamvr_settings_file_path() -> Result<PathBuf> {
     let path = if cfg!(windows) {
-        // N.B. if ever implementing this: given Steam can be installed on another
-        // drive, etc., this should probably start by looking at Windows registry keys.
-        bail!("Not implemented for Windows.") // Original motive for implementation had little reason for Windows.
+        // Steam can be installed on another drive, its config directory is listed by OpenVR
+        get_single_openvr_path("config")?
     } else {
-        dirs::data_dir()
+        dirs::data_dir().to_any()?.join("Steam/config")
     }
-    .to_any()?
-    .join("Steam/config/steamvr.vrsettings");
+    .join("steamvr.vrsettings");
 
     if path.exists() {
         Ok(path)
@@ -94,3 +92,8 @@ fn get_single_openvr_path(path_type: &str) -> Result<PathBuf> {
 pub fn steamvr_root_dir() -> Result<PathBuf> {
     get_single_openvr_path("runtime")
 }
+
+// Contains vrserver.txt and the logs of the other SteamVR processes
+pub fn steamvr_log_dir() -> Result<PathBuf> {
+    get_single_openvr_path("log")
+}
@@ -0,0 +1,194 @@
+use crate::openvrpaths;
+use alvr_common::{
+    ToAny,
+    anyhow::{Context, Result},
+    warn,
+};
+use alvr_events::SteamvrVideoSettings;
+use serde_json as json;
+use std::fs;
+
+const STEAMVR_SECTION: &str = "steamvr";
+const SUPERSAMPLE_SCALE_KEY: &str = "supersampleScale";
+// The supersample scale is used only with the custom resolution of the SteamVR video settings
+const SUPERSAMPLE_MANUAL_OVERRIDE_KEY: &str = "supersampleManualOverride";
+const MOTION_SMOOTHING_KEY: &str = "motionSmoothing";
+// Followed by the Steam app ID
+const APP_SECTION_PREFIX: &str = "steam.app.";
+// In percent
+const APP_RESOLUTION_SCALE_KEY: &str = "resolutionScale";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SteamvrSettingValue {
+    Bool(bool),
+    Int(i32),
+    Float(f32),
+}
+
+// Change of a key of steamvr.vrsettings. A None value removes the key, so that SteamVR uses its
+// default
+#[derive(Clone, PartialEq, Debug)]
+pub struct SteamvrSettingEdit {
+    pub section: String,
+    pub key: String,
+    pub value: Option<SteamvrSettingValue>,
+}
+
+impl SteamvrSettingEdit {
+    fn new(section: &str, key: &str, value: Option<SteamvrSettingValue>) -> Self {
+        Self {
+            section: section.into(),
+            key: key.into(),
+            value,
+        }
+    }
+}
+
+fn load_steamvr_settings_json() -> Result<json::Value> {
+    let path = openvrpaths::steamvr_settings_file_path()?;
+    let text = fs::read_to_string(&path).with_context(|| format!("Failed to read {path:?}"))?;
+
+    Ok(json::from_str(&text)?)
+}
+
+// The file as it was before ALVR changed it for the first time, next to the original
+fn backup_steamvr_settings() -> Result<()> {
+    let path = openvrpaths::steamvr_settings_file_path()?;
+    let backup_path = path.with_extension("vrsettings.alvr_backup");
+    if !backup_path.exists() {
+        fs::copy(&path, &backup_path)
+            .with_context(|| format!("Failed to back up SteamVR settings to {backup_path:?}"))?;
+    }
+
+    Ok(())
+}
+
+pub fn get_steamvr_video_settings() -> Result<SteamvrVideoSettings> {
+    let settings_json = load_steamvr_settings_json()?;
+    let steamvr = &settings_json[STEAMVR_SECTION];
+
+    let resolution_scale = steamvr[SUPERSAMPLE_MANUAL_OVERRIDE_KEY]
+        .as_bool()
+        .unwrap_or(false)
+        .then(|| steamvr[SUPERSAMPLE_SCALE_KEY].as_f64())
+        .flatten()
+        .map(|scale| scale as f32);
+
+    let mut app_resolution_scales = settings_json
+        .as_object()
+        .to_any()?
+        .iter()
+        .filter_map(|(section, values)| {
+            let app_id = section.strip_prefix(APP_SECTION_PREFIX)?;
+            let percent = values[APP_RESOLUTION_SCALE_KEY].as_f64()?;
+
+            Some((app_id.to_owned(), percent as f32 / 100.0))
+        })
+        .collect::<Vec<_>>();
+    app_resolution_scales.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    Ok(SteamvrVideoSettings {
+        resolution_scale,
+        motion_smoothing: steamvr[MOTION_SMOOTHING_KEY].as_bool(),
+        app_resolution_scales,
+    })
+}
+
+// Steam app IDs are decimal numbers. Anything else could be used to edit other sections
+fn is_steam_app_id(app_id: &str) -> bool {
+    !app_id.is_empty() && app_id.bytes().all(|c| c.is_ascii_digit())
+}
+
+// Changes needed to get from the current steamvr.vrsettings to the requested settings. Backs up
+// the file the first time, restoring the defaults only removes the keys
+pub fn steamvr_video_settings_edits(
+    settings: &SteamvrVideoSettings,
+) -> Result<Vec<SteamvrSettingEdit>> {
+    backup_steamvr_settings()?;
+
+    let current = get_steamvr_video_settings()?;
+
+    let mut edits = vec![
+        SteamvrSettingEdit::new(
+            STEAMVR_SECTION,
+            SUPERSAMPLE_MANUAL_OVERRIDE_KEY,
+            settings
+                .resolution_scale
+                .map(|_| SteamvrSettingValue::Bool(true)),
+        ),
+        SteamvrSettingEdit::new(
+            STEAMVR_SECTION,
+            SUPERSAMPLE_SCALE_KEY,
+            settings.resolution_scale.map(SteamvrSettingValue::Float),
+        ),
+        SteamvrSettingEdit::new(
+            STEAMVR_SECTION,
+            MOTION_SMOOTHING_KEY,
+            settings.motion_smoothing.map(SteamvrSettingValue::Bool),
+        ),
+    ];
+
+    for (app_id, _) in &current.app_resolution_scales {
+        if !settings
+            .app_resolution_scales
+            .iter()
+            .any(|(id, _)| id == app_id)
+        {
+            edits.push(SteamvrSettingEdit::new(
+                &format!("{APP_SECTION_PREFIX}{app_id}"),
+                APP_RESOLUTION_SCALE_KEY,
+                None,
+            ));
+        }
+    }
+    for (app_id, scale) in &settings.app_resolution_scales {
+        if !is_steam_app_id(app_id) {
+            warn!("Invalid Steam app ID {app_id:?}, skipping its resolution multiplier");
+            continue;
+        }
+
+        edits.push(SteamvrSettingEdit::new(
+            &format!("{APP_SECTION_PREFIX}{app_id}"),
+            APP_RESOLUTION_SCALE_KEY,
+            Some(SteamvrSettingValue::Int((scale * 100.0).round() as i32)),
+        ));
+    }
+
+    Ok(edits)
+}
+
+// Only while SteamVR is closed, otherwise it overwrites the file with its own copy of the settings.
+// While it runs the edits are made by the driver through the OpenVR settings API
+pub fn write_steamvr_setting_edits(edits: &[SteamvrSettingEdit]) -> Result<()> {
+    let mut settings_json = load_steamvr_settings_json()?;
+    let sections = settings_json
+        .as_object_mut()
+        .context("Failed to parse .vrsettings.")?;
+
+    for edit in edits {
+        if let Some(value) = edit.value {
+            let section = sections
+                .entry(edit.section.clone())
+                .or_insert_with(|| json::json!({}))
+                .as_object_mut()
+                .context("Failed to parse .vrsettings.")?;
+            let value = match value {
+                SteamvrSettingValue::Bool(value) => json::json!(value),
+                SteamvrSettingValue::Int(value) => json::json!(value),
+                SteamvrSettingValue::Float(value) => json::json!(value),
+            };
+            section.insert(edit.key.clone(), value);
+        } else if let Some(section) = sections
+            .get_mut(&edit.section)
+            .and_then(json::Value::as_object_mut)
+        {
+            section.remove(&edit.key);
+        }
+    }
+
+    let path = openvrpaths::steamvr_settings_file_path()?;
+    fs::write(&path, json::to_string_pretty(&settings_json)?)
+        .with_context(|| format!("Failed to write {path:?}"))?;
+
+    Ok(())
+}
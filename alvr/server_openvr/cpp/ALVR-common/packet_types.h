@@ -26,8 +26,36 @@ enum ALVR_ENTROPY_CODING {
     ALVR_CAVLC = 1,
 };
 
+enum ALVR_COLOR_RANGE {
+    ALVR_COLOR_RANGE_FULL = 0,
+    ALVR_COLOR_RANGE_LIMITED = 1,
+};
+
+enum ALVR_TRANSFER_FUNCTION {
+    ALVR_TRANSFER_FUNCTION_SRGB = 0,
+    ALVR_TRANSFER_FUNCTION_GAMMA22 = 1,
+};
+
+enum ALVR_CHROMA_SUBSAMPLING {
+    ALVR_CHROMA_SUBSAMPLING_420 = 0,
+    ALVR_CHROMA_SUBSAMPLING_422 = 1,
+    ALVR_CHROMA_SUBSAMPLING_444 = 2,
+};
+
+enum ALVR_FOVEATION_MODE {
+    ALVR_FOVEATION_MODE_TWO_ZONE = 0,
+    ALVR_FOVEATION_MODE_RADIAL = 1,
+};
+
 enum ALVR_ENCODER_QUALITY_PRESET { ALVR_QUALITY = 0, ALVR_BALANCED = 1, ALVR_SPEED = 2 };
 
+enum ALVR_ENCODER_BACKEND {
+    ALVR_ENCODER_BACKEND_AUTO = 0,
+    ALVR_ENCODER_BACKEND_VAAPI = 1,
+    ALVR_ENCODER_BACKEND_VULKAN_VIDEO = 2,
+    ALVR_ENCODER_BACKEND_SOFTWARE = 3,
+};
+
 enum ALVR_INPUT {
     ALVR_INPUT_FINGER_INDEX,
     ALVR_INPUT_FINGER_MIDDLE,
@@ -3,6 +3,7 @@
 #include "bindings.h"
 #include <memory>
 #include <mutex>
+#include <vector>
 
 #ifndef __APPLE__
 // Workaround symbol clash in openvr.h / openvr_driver.h
@@ -99,6 +100,55 @@ void _SetChaperoneArea(float areaWidth, float areaHeight) {
 #endif
 }
 
+void _SetChaperoneBounds(
+    const float* perimeterPoints, unsigned int pointCount, float areaWidth, float areaHeight
+) {
+    Debug("SetChaperoneBounds");
+
+#ifndef __APPLE__
+    std::unique_lock<std::mutex> lock(chaperone_mutex);
+
+    const vr::HmdMatrix34_t MATRIX_IDENTITY
+        = { { { 1.0, 0.0, 0.0, 0.0 }, { 0.0, 1.0, 0.0, 0.0 }, { 0.0, 0.0, 1.0, 0.0 } } };
+    // Default height of the SteamVR collision bounds
+    const float WALL_HEIGHT = 2.43f;
+
+    // One wall for each side of the perimeter
+    std::vector<vr::HmdQuad_t> walls(pointCount);
+    for (unsigned int i = 0; i < pointCount; i++) {
+        const float* a = &perimeterPoints[i * 2];
+        const float* b = &perimeterPoints[((i + 1) % pointCount) * 2];
+
+        walls[i].vCorners[0] = { { a[0], 0.0f, a[1] } };
+        walls[i].vCorners[1] = { { a[0], WALL_HEIGHT, a[1] } };
+        walls[i].vCorners[2] = { { b[0], WALL_HEIGHT, b[1] } };
+        walls[i].vCorners[3] = { { b[0], 0.0f, b[1] } };
+    }
+
+    auto setup = vr::VRChaperoneSetup();
+
+    if (setup != nullptr) {
+        setup->SetWorkingPerimeter(
+            reinterpret_cast<vr::HmdVector2_t*>(const_cast<float*>(perimeterPoints)), pointCount
+        );
+        setup->SetWorkingCollisionBoundsInfo(walls.data(), pointCount);
+        setup->SetWorkingStandingZeroPoseToRawTrackingPose(&MATRIX_IDENTITY);
+        setup->SetWorkingSeatedZeroPoseToRawTrackingPose(&MATRIX_IDENTITY);
+        setup->SetWorkingPlayAreaSize(areaWidth, areaHeight);
+        setup->CommitWorkingCopy(vr::EChaperoneConfigFile_Live);
+    }
+
+    auto settings = vr::VRSettings();
+
+    if (settings != nullptr) {
+        // Undo the chaperone hiding of SetChaperoneArea
+        settings->RemoveKeyInSection(
+            vr::k_pch_CollisionBounds_Section, vr::k_pch_CollisionBounds_FadeDistance_Float
+        );
+    }
+#endif
+}
+
 #ifdef __linux__
 std::unique_ptr<vr::HmdMatrix34_t> GetInvZeroPose() {
     Debug("GetInvZeroPose");
@@ -10,7 +10,9 @@
 #include "bindings.h"
 
 #ifdef _WIN32
+#include "platform/win32/AdapterSelection.h"
 #include "platform/win32/CEncoder.h"
+#include "platform/win32/MotionEstimation.h"
 #elif __APPLE__
 #include "platform/macos/CEncoder.h"
 #else
@@ -58,6 +60,10 @@ Hmd::~Hmd() {
     }
 
 #ifdef _WIN32
+    if (m_encodeD3DRender) {
+        m_encodeD3DRender->Shutdown();
+        m_encodeD3DRender.reset();
+    }
     if (m_D3DRender) {
         m_D3DRender->Shutdown();
         m_D3DRender.reset();
@@ -80,6 +86,15 @@ bool Hmd::activate() {
 
     vr::VRDriverInput()->CreateBooleanComponent(this->prop_container, "/proximity", &m_proximity);
 
+    if (Settings::Instance().m_enableEyeGaze) {
+        vr_properties->SetBoolProperty(
+            this->prop_container, vr::Prop_SupportsXrEyeGazeInteraction_Bool, true
+        );
+        vr::VRDriverInput()->CreateEyeTrackingComponent(
+            this->prop_container, "/eyetracking", &m_eyeTracking
+        );
+    }
+
 #ifdef _WIN32
     float originalIPD
         = vr::VRSettings()->GetFloat(vr::k_pch_SteamVR_Section, vr::k_pch_SteamVR_IPD_Float);
@@ -113,20 +128,37 @@ bool Hmd::activate() {
             m_D3DRender = std::make_shared<CD3DRender>();
 
             // Use the same adapter as vrcompositor uses. If another adapter is used, vrcompositor
-            // says "failed to open shared texture" and then crashes. It seems vrcompositor selects
-            // always(?) first adapter. vrcompositor may use Intel iGPU when user sets it as primary
-            // adapter. I don't know what happens on laptop which support optimus.
+            // says "failed to open shared texture" and then crashes. vrcompositor selects the first
+            // adapter of its Windows graphics preference, which may be the Intel iGPU on laptops.
             // Prop_GraphicsAdapterLuid_Uint64 is only for redirect display and is ignored on direct
-            // mode driver. So we can't specify an adapter for vrcompositor. m_nAdapterIndex is set
-            // 0 on the dashboard.
-            if (!m_D3DRender->Initialize(Settings::Instance().m_nAdapterIndex)) {
+            // mode driver. So we can't specify an adapter for vrcompositor.
+            AdapterTopology topology;
+            try {
+                topology = SelectAdapters();
+            } catch (Exception e) {
+                Error("%s", e.what());
+                return false;
+            }
+            if (!m_D3DRender->Initialize(topology.renderAdapter.index)) {
                 Error(
-                    "Could not create graphics device for adapter %d.  Requires a minimum of two "
-                    "graphics cards.\n",
-                    Settings::Instance().m_nAdapterIndex
+                    "Could not create graphics device for adapter %d (%ls).\n",
+                    topology.renderAdapter.index,
+                    topology.renderAdapter.name.c_str()
                 );
                 return false;
             }
+            if (topology.IsCrossAdapter()) {
+                m_encodeD3DRender = std::make_shared<CD3DRender>();
+                if (!m_encodeD3DRender->Initialize(topology.encodeAdapter.index)) {
+                    Error(
+                        "Could not create graphics device for the encoder on adapter %d (%ls).\n",
+                        topology.encodeAdapter.index,
+                        topology.encodeAdapter.name.c_str()
+                    );
+                    return false;
+                }
+            }
+            m_encodeAdapterName = topology.encodeAdapter.name;
 
             int32_t nDisplayAdapterIndex;
             if (!m_D3DRender->GetAdapterInfo(&nDisplayAdapterIndex, m_adapterName)) {
@@ -139,6 +171,9 @@ bool Hmd::activate() {
 
             m_directModeComponent
                 = std::make_shared<OvrDirectModeComponent>(m_D3DRender, m_poseHistory);
+
+            // The motion is estimated on the render adapter, where the frames are composed
+            ReportMotionVectorsSupport(MotionEstimation::IsSupported(m_D3DRender->GetDevice()));
 #endif
         }
 
@@ -235,7 +270,17 @@ void Hmd::StartStreaming() {
 #ifdef _WIN32
         m_encoder = std::make_shared<CEncoder>();
         try {
-            m_encoder->Initialize(m_D3DRender);
+            if (m_encodeD3DRender) {
+                Info(
+                    "SteamVR renders on %ls, encoding on %ls. The frames are copied between the "
+                    "adapters",
+                    m_adapterName.c_str(),
+                    m_encodeAdapterName.c_str()
+                );
+            } else {
+                Info("Rendering and encoding on %ls", m_adapterName.c_str());
+            }
+            m_encoder->Initialize(m_D3DRender, m_encodeD3DRender);
         } catch (Exception e) {
             Error(
                 "Your GPU does not meet the requirements for video encoding. %s %s\n%s %s\n",
@@ -267,6 +312,25 @@ void Hmd::StopStreaming() {
     vr::VRDriverInput()->UpdateBooleanComponent(m_proximity, false, 0.0);
 }
 
+void Hmd::SetEyeGaze(const FfiQuat* gaze) {
+    if (m_eyeTracking == vr::k_ulInvalidInputComponentHandle) {
+        return;
+    }
+
+    vr::VREyeTrackingData_t data = {};
+    data.bActive = true;
+    data.bValid = gaze != nullptr;
+    data.bTracked = gaze != nullptr;
+    if (gaze) {
+        // The gaze looks towards -Z. The target is one meter away from the head, along the gaze
+        data.vGazeTarget.v[0] = -2.0f * (gaze->x * gaze->z + gaze->w * gaze->y);
+        data.vGazeTarget.v[1] = -2.0f * (gaze->y * gaze->z - gaze->w * gaze->x);
+        data.vGazeTarget.v[2] = -1.0f + 2.0f * (gaze->x * gaze->x + gaze->y * gaze->y);
+    }
+
+    vr::VRDriverInput()->UpdateEyeTrackingComponent(m_eyeTracking, &data, 0.0);
+}
+
 void Hmd::SetViewParams(const FfiViewParams params[2]) {
     Debug("Hmd::SetViewParams");
 
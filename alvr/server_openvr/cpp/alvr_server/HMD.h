@@ -30,9 +30,12 @@ public:
     void StopStreaming();
     void SetViewParams(const FfiViewParams params[2]);
     void SetProximityState(bool headsetIsWorn);
+    void SetEyeGaze(const FfiQuat* gaze);
 
 private:
     vr::VRInputComponentHandle_t m_proximity;
+    // Only if eye gaze forwarding is enabled
+    vr::VRInputComponentHandle_t m_eyeTracking = vr::k_ulInvalidInputComponentHandle;
 
     FfiViewParams view_params[2];
 
@@ -48,6 +51,9 @@ private:
 
 #ifdef _WIN32
     std::shared_ptr<CD3DRender> m_D3DRender;
+    // Only if the encoder is on another adapter than SteamVR
+    std::shared_ptr<CD3DRender> m_encodeD3DRender;
+    std::wstring m_encodeAdapterName;
 #endif
 
 #ifdef _WIN32
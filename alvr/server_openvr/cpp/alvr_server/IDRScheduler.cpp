@@ -9,6 +9,7 @@ IDRScheduler::~IDRScheduler() { }
 
 void IDRScheduler::OnStreamStart() {
     m_minIDRFrameInterval = Settings::Instance().m_minimumIdrIntervalMs * 1000;
+    m_keyframeInterval = Settings::Instance().m_keyframeIntervalMs * 1000;
     m_scheduled = false;
     InsertIDR();
 }
@@ -23,11 +24,14 @@ void IDRScheduler::InsertIDR() {
 bool IDRScheduler::CheckIDRInsertion() {
     std::unique_lock lock(m_mutex);
 
-    if (m_scheduled) {
-        if (m_insertIDRTime <= GetTimestampUs()) {
-            m_scheduled = false;
-            return true;
-        }
+    uint64_t now = GetTimestampUs();
+
+    // A requested IDR also restarts the keyframe interval
+    if ((m_scheduled && m_insertIDRTime <= now)
+        || (m_keyframeInterval > 0 && now >= m_lastIDRTime + m_keyframeInterval)) {
+        m_scheduled = false;
+        m_lastIDRTime = now;
+        return true;
     }
     return false;
 }
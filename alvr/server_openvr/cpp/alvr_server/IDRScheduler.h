@@ -20,4 +20,7 @@ private:
     bool m_scheduled = false;
     std::mutex m_mutex;
     uint64_t m_minIDRFrameInterval = MIN_IDR_FRAME_INTERVAL;
+    // Periodic IDR frames, 0 if disabled
+    uint64_t m_keyframeInterval = 0;
+    uint64_t m_lastIDRTime = 0;
 };
@@ -97,7 +97,12 @@ void processHevcNals(unsigned char*& buf, int& len) {
 }
 
 void ParseFrameNals(
-    int codec, unsigned char* buf, int len, unsigned long long targetTimestampNs, bool isIdr
+    int codec,
+    unsigned char* buf,
+    int len,
+    unsigned long long targetTimestampNs,
+    bool isIdr,
+    int averageQp
 ) {
     static bool av1GotFrame = false;
 
@@ -114,5 +119,5 @@ void ParseFrameNals(
         SetVideoConfigNals(0, 0, codec);
     }
 
-    VideoSend(targetTimestampNs, buf, len, isIdr);
+    VideoSend(targetTimestampNs, buf, len, isIdr, averageQp);
 }
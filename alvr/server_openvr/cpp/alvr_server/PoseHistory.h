@@ -20,6 +20,8 @@ public:
     std::optional<TrackingHistoryFrame> GetBestPoseMatch(const vr::HmdMatrix34_t& pose) const;
     // Return the most recent pose known at the given timestamp
     std::optional<TrackingHistoryFrame> GetPoseAt(uint64_t timestampNs) const;
+    // Return the most recently received pose
+    std::optional<TrackingHistoryFrame> GetLatestPose() const;
 
     void SetTransform(const vr::HmdMatrix34_t& transform);
 
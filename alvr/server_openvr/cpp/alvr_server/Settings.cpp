@@ -44,15 +44,44 @@ void Settings::Load() {
         m_recommendedTargetWidth = config.get("target_eye_resolution_width").get<int64_t>() * 2;
         m_recommendedTargetHeight = config.get("target_eye_resolution_height").get<int64_t>();
         m_nAdapterIndex = (int32_t)config.get("adapter_index").get<int64_t>();
+        m_adapterName = config.get("adapter_name").get<std::string>();
+        m_crossAdapterEncoding = config.get("cross_adapter_encoding").get<bool>();
         m_captureFrameDir = config.get("capture_frame_dir").get<std::string>();
 
         m_enableFoveatedEncoding = config.get("enable_foveated_encoding").get<bool>();
+        m_foveationMode = (uint32_t)config.get("foveation_mode").get<int64_t>();
+        m_foveationCenterStrength
+            = (float)config.get("foveation_center_strength").get<double>();
+        m_foveationEdgeCompression
+            = (float)config.get("foveation_edge_compression").get<double>();
         m_foveationCenterSizeX = (float)config.get("foveation_center_size_x").get<double>();
         m_foveationCenterSizeY = (float)config.get("foveation_center_size_y").get<double>();
         m_foveationCenterShiftX = (float)config.get("foveation_center_shift_x").get<double>();
         m_foveationCenterShiftY = (float)config.get("foveation_center_shift_y").get<double>();
         m_foveationEdgeRatioX = (float)config.get("foveation_edge_ratio_x").get<double>();
         m_foveationEdgeRatioY = (float)config.get("foveation_edge_ratio_y").get<double>();
+        m_foveationRightCenterSizeX
+            = (float)config.get("foveation_right_center_size_x").get<double>();
+        m_foveationRightCenterSizeY
+            = (float)config.get("foveation_right_center_size_y").get<double>();
+        m_foveationRightCenterShiftX
+            = (float)config.get("foveation_right_center_shift_x").get<double>();
+        m_foveationRightCenterShiftY
+            = (float)config.get("foveation_right_center_shift_y").get<double>();
+        m_foveationRightEdgeRatioX
+            = (float)config.get("foveation_right_edge_ratio_x").get<double>();
+        m_foveationRightEdgeRatioY
+            = (float)config.get("foveation_right_edge_ratio_y").get<double>();
+        m_foveationEdgePreservationStrength
+            = (float)config.get("foveation_edge_preservation_strength").get<double>();
+
+        m_enableGazeBitrateAllocation = config.get("enable_gaze_bitrate_allocation").get<bool>();
+
+        m_enableDepthStream = config.get("enable_depth_stream").get<bool>();
+        m_enableEyeGaze = config.get("enable_eye_gaze").get<bool>();
+        m_depthResolutionDivisor
+            = (uint32_t)config.get("depth_resolution_divisor").get<int64_t>();
+        m_enableSpaceWarp = config.get("enable_space_warp").get<bool>();
 
         m_enableColorCorrection = config.get("enable_color_correction").get<bool>();
         m_brightness = (float)config.get("brightness").get<double>();
@@ -69,22 +98,22 @@ void Settings::Load() {
         m_use10bitEncoder = config.get("use_10bit_encoder").get<bool>();
         m_encodingGamma = config.get("encoding_gamma").get<double>();
         m_enableHdr = config.get("enable_hdr").get<bool>();
+        m_colorRange = (uint32_t)config.get("color_range").get<int64_t>();
+        m_transferFunction = (uint32_t)config.get("transfer_function").get<int64_t>();
+        m_chromaSubsampling = (uint32_t)config.get("chroma_subsampling").get<int64_t>();
         m_forceHdrSrgbCorrection = config.get("force_hdr_srgb_correction").get<bool>();
         m_clampHdrExtendedRange = config.get("clamp_hdr_extended_range").get<bool>();
-        m_enableAmfPreAnalysis = config.get("enable_amf_pre_analysis").get<bool>();
         m_enableVbaq = config.get("enable_vbaq").get<bool>();
         m_enableAmfHmqb = config.get("enable_amf_hmqb").get<bool>();
-        m_useAmfPreproc = config.get("use_amf_preproc").get<bool>();
         m_amfPreProcSigma = (uint32_t)config.get("amf_preproc_sigma").get<int64_t>();
         m_amfPreProcTor = (uint32_t)config.get("amf_preproc_tor").get<int64_t>();
-        m_encoderQualityPreset = (uint32_t)config.get("encoder_quality_preset").get<int64_t>();
         m_amdBitrateCorruptionFix = (bool)config.get("amd_bitrate_corruption_fix").get<bool>();
-        m_nvencQualityPreset = (uint32_t)config.get("nvenc_quality_preset").get<int64_t>();
+        m_encoderBackend = (uint32_t)config.get("encoder_backend").get<int64_t>();
         m_force_sw_encoding = config.get("force_sw_encoding").get<bool>();
         m_swThreadCount = (int32_t)config.get("sw_thread_count").get<int64_t>();
+        m_encoderAsyncDepth = (uint32_t)config.get("encoder_async_depth").get<int64_t>();
+        m_swSvtAv1Preset = (int32_t)config.get("sw_svt_av1_preset").get<int64_t>();
 
-        m_nvencTuningPreset = (uint32_t)config.get("nvenc_tuning_preset").get<int64_t>();
-        m_nvencMultiPass = (uint32_t)config.get("nvenc_multi_pass").get<int64_t>();
         m_nvencAdaptiveQuantizationMode
             = (uint32_t)config.get("nvenc_adaptive_quantization_mode").get<int64_t>();
         m_nvencLowDelayKeyFrameScale = config.get("nvenc_low_delay_key_frame_scale").get<int64_t>();
@@ -92,6 +121,8 @@ void Settings::Load() {
         m_nvencEnableIntraRefresh = config.get("enable_intra_refresh").get<bool>();
         m_nvencIntraRefreshPeriod = config.get("intra_refresh_period").get<int64_t>();
         m_nvencIntraRefreshCount = config.get("intra_refresh_count").get<int64_t>();
+        m_intraRefreshRecoveryPeriod
+            = (uint32_t)config.get("intra_refresh_recovery_period").get<int64_t>();
         m_nvencMaxNumRefFrames = config.get("max_num_ref_frames").get<int64_t>();
         m_nvencGopLength = config.get("gop_length").get<int64_t>();
         m_nvencPFrameStrategy = config.get("p_frame_strategy").get<int64_t>();
@@ -104,6 +135,8 @@ void Settings::Load() {
             = config.get("nvenc_enable_weighted_prediction").get<bool>();
 
         m_minimumIdrIntervalMs = config.get("minimum_idr_interval_ms").get<int64_t>();
+        m_keyframeIntervalMs = config.get("keyframe_interval_ms").get<int64_t>();
+        m_serverReprojection = config.get("server_reprojection").get<bool>();
 
         m_enableViveTrackerProxy = config.get("enable_vive_tracker_proxy").get<bool>();
         m_TrackingRefOnly = config.get("tracking_ref_only").get<bool>();
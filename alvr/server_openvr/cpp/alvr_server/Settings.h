@@ -22,15 +22,34 @@ public:
     int32_t m_recommendedTargetWidth;
     int32_t m_recommendedTargetHeight;
     int32_t m_nAdapterIndex;
+    std::string m_adapterName;
+    bool m_crossAdapterEncoding;
     std::string m_captureFrameDir;
 
     bool m_enableFoveatedEncoding;
+    uint32_t m_foveationMode;
+    float m_foveationCenterStrength;
+    float m_foveationEdgeCompression;
     float m_foveationCenterSizeX;
     float m_foveationCenterSizeY;
     float m_foveationCenterShiftX;
     float m_foveationCenterShiftY;
     float m_foveationEdgeRatioX;
     float m_foveationEdgeRatioY;
+    float m_foveationRightCenterSizeX;
+    float m_foveationRightCenterSizeY;
+    float m_foveationRightCenterShiftX;
+    float m_foveationRightCenterShiftY;
+    float m_foveationRightEdgeRatioX;
+    float m_foveationRightEdgeRatioY;
+    float m_foveationEdgePreservationStrength;
+
+    bool m_enableGazeBitrateAllocation;
+
+    bool m_enableDepthStream;
+    bool m_enableEyeGaze;
+    uint32_t m_depthResolutionDivisor;
+    bool m_enableSpaceWarp;
 
     bool m_enableColorCorrection;
     float m_brightness;
@@ -44,31 +63,33 @@ public:
     bool m_use10bitEncoder;
     double m_encodingGamma;
     bool m_enableHdr;
+    uint32_t m_colorRange;
+    uint32_t m_transferFunction;
+    uint32_t m_chromaSubsampling;
     bool m_forceHdrSrgbCorrection;
     bool m_clampHdrExtendedRange;
-    bool m_enableAmfPreAnalysis;
     bool m_enableVbaq;
     bool m_enableAmfHmqb;
-    bool m_useAmfPreproc;
     uint32_t m_amfPreProcSigma;
     uint32_t m_amfPreProcTor;
-    uint32_t m_encoderQualityPreset;
     bool m_amdBitrateCorruptionFix;
-    uint32_t m_nvencQualityPreset;
     uint32_t m_rateControlMode;
     bool m_fillerData;
     uint32_t m_entropyCoding;
+    uint32_t m_encoderBackend;
     bool m_force_sw_encoding;
     uint32_t m_swThreadCount;
+    uint32_t m_encoderAsyncDepth;
+    uint32_t m_swSvtAv1Preset;
 
-    uint32_t m_nvencTuningPreset;
-    uint32_t m_nvencMultiPass;
     uint32_t m_nvencAdaptiveQuantizationMode;
     int64_t m_nvencLowDelayKeyFrameScale;
     int64_t m_nvencRefreshRate;
     bool m_nvencEnableIntraRefresh;
     int64_t m_nvencIntraRefreshPeriod;
     int64_t m_nvencIntraRefreshCount;
+    // 0 if the stream recovers from packet loss with IDR frames
+    uint32_t m_intraRefreshRecoveryPeriod;
     int64_t m_nvencMaxNumRefFrames;
     int64_t m_nvencGopLength;
     int64_t m_nvencPFrameStrategy;
@@ -80,6 +101,8 @@ public:
     bool m_nvencEnableWeightedPrediction;
 
     uint64_t m_minimumIdrIntervalMs;
+    uint64_t m_keyframeIntervalMs;
+    bool m_serverReprojection;
 
     bool m_enableViveTrackerProxy = false;
     bool m_TrackingRefOnly = false;
@@ -21,14 +21,24 @@
 #include <string.h>
 #endif
 
+#include <cstring>
 #include <math.h>
 
 #include "ALVR-common/packet_types.h"
+#include "bindings.h"
 #include "openvr_driver_wrap.h"
 
 const float DEG_TO_RAD = (float)(M_PI / 180.);
 const double NS_PER_S = 1000000000.0;
 
+// Interval of the software encoding fallback warning. Same as the dashboard notification timeout,
+// so the warning stays visible while streaming
+const auto SW_FALLBACK_WARNING_INTERVAL = std::chrono::seconds(5);
+
+inline bool EncoderPresetsChanged(const FfiEncoderPresets& a, const FfiEncoderPresets& b) {
+    return memcmp(&a, &b, sizeof(FfiEncoderPresets)) != 0;
+}
+
 // Get elapsed time in us from Unix Epoch
 inline uint64_t GetTimestampUs() {
     auto duration = std::chrono::system_clock::now().time_since_epoch();
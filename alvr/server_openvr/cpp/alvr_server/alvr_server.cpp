@@ -38,6 +38,9 @@ std::unique_ptr<vr::HmdMatrix34_t> GetRawZeroPose() {
 bool IsOpenvrClientReady();
 #endif
 void _SetChaperoneArea(float areaWidth, float areaHeight);
+void _SetChaperoneBounds(
+    const float* perimeterPoints, unsigned int pointCount, float areaWidth, float areaHeight
+);
 
 vr::EVREventType VendorEvent_ALVRDriverResync
     = (vr::EVREventType)(vr::VREvent_VendorSpecific_Reserved_Start + ((vr::EVREventType)0xC0));
@@ -161,6 +164,11 @@ public:
                 }
             }
 #endif
+
+            // Selects the per-game settings
+            if (event.eventType == vr::VREvent_SceneApplicationChanged) {
+                ReportSceneApplication(event.data.process.pid);
+            }
         }
         if (vr::VRServerDriverHost()->IsExiting() && !shutdown_called) {
             Debug("DriverProvider: Received shutdown event");
@@ -209,13 +217,47 @@ void (*LogEncoder)(const char* stringPtr);
 void (*LogPeriodically)(const char* tag, const char* stringPtr);
 void (*DriverReadyIdle)(bool setDefaultChaprone);
 void (*SetVideoConfigNals)(const unsigned char* configBuffer, int len, int codec);
-void (*VideoSend)(unsigned long long targetTimestampNs, unsigned char* buf, int len, bool isIdr);
+void (*SetIntraRefreshPeriod)(unsigned int periodFrames);
+void (*ReportEncoderFallback)(const char* selectedBackend, const char* usedBackend);
+void (*VideoSend)(
+    unsigned long long targetTimestampNs, unsigned char* buf, int len, bool isIdr, int averageQp
+);
 void (*HapticsSend)(unsigned long long path, float duration_s, float frequency, float amplitude);
 void (*ShutdownRuntime)();
 unsigned long long (*PathStringToHash)(const char* path);
 void (*ReportPresent)(unsigned long long timestamp_ns, unsigned long long offset_ns);
 void (*ReportComposed)(unsigned long long timestamp_ns, unsigned long long offset_ns);
+void (*ReportServerReprojection)(
+    unsigned long long timestamp_ns, unsigned long long pose_timestamp_ns
+);
 FfiDynamicEncoderParams (*GetDynamicEncoderParams)();
+FfiEncoderPresets (*GetEncoderPresets)();
+FfiGazeRegions (*GetGazeRegions)();
+void (*ReportDepthSubmission)(bool submitted);
+void (*SendDepth)(
+    unsigned long long targetTimestampNs,
+    unsigned int width,
+    unsigned int height,
+    const unsigned short* data,
+    float nearZ,
+    float farZ
+);
+void (*ReportMotionVectorsSupport)(bool supported);
+void (*ReportSceneApplication)(unsigned int pid);
+void (*SendMotionVectors)(
+    unsigned long long targetTimestampNs,
+    unsigned long long referenceTimestampNs,
+    unsigned int viewWidth,
+    unsigned int viewHeight,
+    unsigned int blockSize,
+    unsigned int gridWidth,
+    unsigned int gridHeight,
+    const short* data
+);
+void (*SendMirrorFrame)(unsigned int width, unsigned int height, const unsigned char* rgba);
+void (*DrawTestPattern)(
+    unsigned char* data, unsigned int width, unsigned int height, unsigned int rowPitch
+);
 unsigned long long (*GetSerialNumber)(unsigned long long deviceID, char* outString);
 void (*SetOpenvrProps)(void* instancePtr, unsigned long long deviceID);
 void (*RegisterButtons)(void* instancePtr, unsigned long long deviceID);
@@ -519,10 +561,22 @@ void SetProximityState(bool headset_is_worn) {
     }
 }
 
+void SetEyeGaze(const FfiQuat* gaze) {
+    if (g_driver_provider.hmd) {
+        g_driver_provider.hmd->SetEyeGaze(gaze);
+    }
+}
+
 void SetChaperoneArea(float areaWidth, float areaHeight) {
     _SetChaperoneArea(areaWidth, areaHeight);
 }
 
+void SetChaperoneBounds(
+    const float* perimeterPoints, unsigned int pointCount, float areaWidth, float areaHeight
+) {
+    _SetChaperoneBounds(perimeterPoints, pointCount, areaWidth, areaHeight);
+}
+
 void CaptureFrame() {
 #ifndef __APPLE__
     if (g_driver_provider.hmd && g_driver_provider.hmd->m_encoder) {
@@ -530,3 +584,58 @@ void CaptureFrame() {
     }
 #endif
 }
+
+void SetTestPattern(bool enabled) {
+#ifndef __APPLE__
+    if (g_driver_provider.hmd && g_driver_provider.hmd->m_encoder) {
+        g_driver_provider.hmd->m_encoder->SetTestPattern(enabled);
+    }
+#endif
+}
+
+void SetMirrorEnabled(bool enabled) {
+#ifndef __APPLE__
+    if (g_driver_provider.hmd && g_driver_provider.hmd->m_encoder) {
+        g_driver_provider.hmd->m_encoder->SetMirrorEnabled(enabled);
+    }
+#endif
+}
+
+void SetEncodingPaused(bool paused, bool keepaliveFrames) {
+#ifndef __APPLE__
+    if (g_driver_provider.hmd && g_driver_provider.hmd->m_encoder) {
+        g_driver_provider.hmd->m_encoder->SetPaused(paused, keepaliveFrames);
+    }
+#endif
+}
+
+void SetIdleFrameRate(float fps) {
+#ifndef __APPLE__
+    if (g_driver_provider.hmd && g_driver_provider.hmd->m_encoder) {
+        g_driver_provider.hmd->m_encoder->SetIdleFrameRate(fps);
+    }
+#endif
+}
+
+void SetSteamvrSettingBool(const char* section, const char* key, bool value) {
+    vr::VRSettings()->SetBool(section, key, value);
+}
+
+void SetSteamvrSettingInt(const char* section, const char* key, int value) {
+    vr::VRSettings()->SetInt32(section, key, value);
+}
+
+void SetSteamvrSettingFloat(const char* section, const char* key, float value) {
+    vr::VRSettings()->SetFloat(section, key, value);
+}
+
+void RemoveSteamvrSetting(const char* section, const char* key) {
+    vr::VRSettings()->RemoveKeyInSection(section, key);
+}
+
+bool SyncSteamvrSettings() {
+    vr::EVRSettingsError error = vr::VRSettingsError_None;
+    vr::VRSettings()->Sync(true, &error);
+
+    return error == vr::VRSettingsError_None;
+}
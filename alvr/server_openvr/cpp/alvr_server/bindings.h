@@ -82,10 +82,31 @@ struct FfiButtonValue {
     };
 };
 
+// Backend specific presets chosen by the encoder effort setting. The bools are unsigned ints so
+// that the struct can be compared with memcmp
+struct FfiEncoderPresets {
+    unsigned int nvenc_quality_preset;
+    unsigned int nvenc_tuning_preset;
+    unsigned int nvenc_multi_pass;
+    unsigned int encoder_quality_preset;
+    unsigned int use_amf_preproc;
+    unsigned int enable_amf_pre_analysis;
+};
+
 struct FfiDynamicEncoderParams {
     unsigned int updated;
     unsigned long long bitrate_bps;
     float framerate;
+    // The encoder is recreated if they changed
+    FfiEncoderPresets presets;
+};
+
+// Frame regions around the gaze point, to be encoded with a lower QP. The rects are in normalized
+// frame coordinates, as left, top, right, bottom. qpOffset is on the H264/HEVC scale.
+struct FfiGazeRegions {
+    unsigned int count;
+    float rects[2][4];
+    int qpOffset;
 };
 
 extern "C" const unsigned char* FRAME_RENDER_VS_CSO_PTR;
@@ -121,8 +142,12 @@ extern "C" void (*LogEncoder)(const char* stringPtr);
 extern "C" void (*LogPeriodically)(const char* tag, const char* stringPtr);
 extern "C" void (*DriverReadyIdle)(bool setDefaultChaprone);
 extern "C" void (*SetVideoConfigNals)(const unsigned char* configBuffer, int len, int codec);
+extern "C" void (*SetIntraRefreshPeriod)(unsigned int periodFrames);
+// Called when the selected encoder backend failed and another one is used
+extern "C" void (*ReportEncoderFallback)(const char* selectedBackend, const char* usedBackend);
+// averageQp is -1 if the encoder doesn't report it
 extern "C" void (*VideoSend)(
-    unsigned long long targetTimestampNs, unsigned char* buf, int len, bool isIdr
+    unsigned long long targetTimestampNs, unsigned char* buf, int len, bool isIdr, int averageQp
 );
 extern "C" void (*HapticsSend)(
     unsigned long long path, float duration_s, float frequency, float amplitude
@@ -131,7 +156,41 @@ extern "C" void (*ShutdownRuntime)();
 extern "C" unsigned long long (*PathStringToHash)(const char* path);
 extern "C" void (*ReportPresent)(unsigned long long timestamp_ns, unsigned long long offset_ns);
 extern "C" void (*ReportComposed)(unsigned long long timestamp_ns, unsigned long long offset_ns);
+extern "C" void (*ReportServerReprojection)(
+    unsigned long long timestamp_ns, unsigned long long pose_timestamp_ns
+);
 extern "C" FfiDynamicEncoderParams (*GetDynamicEncoderParams)();
+// Used when the encoder is created, later changes come with the dynamic params
+extern "C" FfiEncoderPresets (*GetEncoderPresets)();
+extern "C" FfiGazeRegions (*GetGazeRegions)();
+extern "C" void (*ReportDepthSubmission)(bool submitted);
+extern "C" void (*SendDepth)(
+    unsigned long long targetTimestampNs,
+    unsigned int width,
+    unsigned int height,
+    const unsigned short* data,
+    float nearZ,
+    float farZ
+);
+extern "C" void (*ReportMotionVectorsSupport)(bool supported);
+extern "C" void (*ReportSceneApplication)(unsigned int pid);
+extern "C" void (*SendMotionVectors)(
+    unsigned long long targetTimestampNs,
+    unsigned long long referenceTimestampNs,
+    unsigned int viewWidth,
+    unsigned int viewHeight,
+    unsigned int blockSize,
+    unsigned int gridWidth,
+    unsigned int gridHeight,
+    const short* data
+);
+extern "C" void (*SendMirrorFrame)(
+    unsigned int width, unsigned int height, const unsigned char* rgba
+);
+// Draws the test pattern into a RGBA8 buffer, with the clock showing the current system time
+extern "C" void (*DrawTestPattern)(
+    unsigned char* data, unsigned int width, unsigned int height, unsigned int rowPitch
+);
 extern "C" unsigned long long (*GetSerialNumber)(unsigned long long deviceID, char* outString);
 extern "C" void (*SetOpenvrProps)(void* instancePtr, unsigned long long deviceID);
 extern "C" void (*RegisterButtons)(void* instancePtr, unsigned long long deviceID);
@@ -162,16 +221,39 @@ extern "C" void SetLocalViewParams(const FfiViewParams params[2]);
 extern "C" void SetBattery(unsigned long long deviceID, float gauge_value, bool is_plugged);
 extern "C" void SetButton(unsigned long long buttonID, FfiButtonValue value);
 extern "C" void SetProximityState(bool headset_is_worn);
+// Gaze relative to the head, null while it is not tracked
+extern "C" void SetEyeGaze(const FfiQuat* gaze);
 
 extern "C" void InitOpenvrClient();
 extern "C" void ShutdownOpenvrClient();
 extern "C" void SetChaperoneArea(float areaWidth, float areaHeight);
+// perimeterPoints contains (x, z) pairs on the floor
+extern "C" void SetChaperoneBounds(
+    const float* perimeterPoints, unsigned int pointCount, float areaWidth, float areaHeight
+);
 
 extern "C" void CaptureFrame();
+extern "C" void SetTestPattern(bool enabled);
+extern "C" void SetMirrorEnabled(bool enabled);
+extern "C" void SetEncodingPaused(bool paused, bool keepaliveFrames);
+// 0 restores the full frame rate
+extern "C" void SetIdleFrameRate(float fps);
+// Changes steamvr.vrsettings through the OpenVR settings API, SteamVR saves the file
+extern "C" void SetSteamvrSettingBool(const char* section, const char* key, bool value);
+extern "C" void SetSteamvrSettingInt(const char* section, const char* key, int value);
+extern "C" void SetSteamvrSettingFloat(const char* section, const char* key, float value);
+extern "C" void RemoveSteamvrSetting(const char* section, const char* key);
+// Writes the pending changes to steamvr.vrsettings. Returns false on failure
+extern "C" bool SyncSteamvrSettings();
 
 // NalParsing.cpp
 void ParseFrameNals(
-    int codec, unsigned char* buf, int len, unsigned long long targetTimestampNs, bool isIdr
+    int codec,
+    unsigned char* buf,
+    int len,
+    unsigned long long targetTimestampNs,
+    bool isIdr,
+    int averageQp = -1
 );
 
 // CrashHandler.cpp
@@ -14,29 +14,70 @@ SamplerState trilinearSampler {
 
 float4 main(float2 uv : TEXCOORD0) : SV_Target {
 	bool isRightEye = uv.x > 0.5;
-	float2 eyeUV = TextureToEyeUV(uv, isRightEye) / eyeSizeRatio;
+	EyeFoveationVars eye = eyes[isRightEye ? 1 : 0];
+	float2 centerSize = eye.centerSize;
+	float2 centerShift = eye.centerShift;
+	float2 edgeRatio = eye.edgeRatio;
+	float2 radialCenter = eye.radialCenter;
 
-	float2 c0 = (1. - centerSize) / 2.;
-	float2 c1 = (edgeRatio - 1.) * c0 * (centerShift + 1.) / edgeRatio;
-	float2 c2 = (edgeRatio - 1.) * centerSize + 1.;
+	float2 eyeUV = TextureToEyeUV(uv, isRightEye) / eye.eyeSizeRatio;
 
-	float2 loBound = c0 * (centerShift + 1.) / c2;
-	float2 hiBound = c0 * (centerShift - 1.) / c2 + 1.;
-	float2 underBound = float2(eyeUV.x < loBound.x, eyeUV.y < loBound.y);
-	float2 inBound = float2(loBound.x < eyeUV.x && eyeUV.x < hiBound.x,
-							loBound.y < eyeUV.y && eyeUV.y < hiBound.y);
-	float2 overBound = float2(eyeUV.x > hiBound.x, eyeUV.y > hiBound.y);
+	float2 compressedUV;
+	// Source pixels covered by each output pixel, minus one
+	float2 spreadRatio;
+	if (mode == 1) {
+		float2 side = eyeUV < radialCenter ? radialCenter : 1. - radialCenter;
+		float2 u = (eyeUV - radialCenter) / side;
+		compressedUV = radialCenter + u * (radialAlpha + (1. - radialAlpha) * abs(u)) * side;
+		spreadRatio = max((radialAlpha + 2. * (1. - radialAlpha) * abs(u)) / radialScale - 1., 0.);
+	} else {
+		float2 c0 = (1. - centerSize) / 2.;
+		float2 c1 = (edgeRatio - 1.) * c0 * (centerShift + 1.) / edgeRatio;
+		float2 c2 = (edgeRatio - 1.) * centerSize + 1.;
 
-	float2 center = eyeUV * c2 / edgeRatio + c1;
-	float2 d2 = eyeUV * c2;
-	float2 d3 = (eyeUV - 1.) * c2 + 1.;
-	float2 g1 = eyeUV / loBound;
-	float2 g2 = (1. - eyeUV) / (1. - hiBound);
+		float2 loBound = c0 * (centerShift + 1.) / c2;
+		float2 hiBound = c0 * (centerShift - 1.) / c2 + 1.;
+		float2 underBound = float2(eyeUV.x < loBound.x, eyeUV.y < loBound.y);
+		float2 inBound = float2(loBound.x < eyeUV.x && eyeUV.x < hiBound.x,
+								loBound.y < eyeUV.y && eyeUV.y < hiBound.y);
+		float2 overBound = float2(eyeUV.x > hiBound.x, eyeUV.y > hiBound.y);
 
-	float2 leftEdge = g1 * center + (1. - g1) * d2;
-	float2 rightEdge = g2 * center + (1. - g2) * d3;
+		float2 center = eyeUV * c2 / edgeRatio + c1;
+		float2 d2 = eyeUV * c2;
+		float2 d3 = (eyeUV - 1.) * c2 + 1.;
+		float2 g1 = eyeUV / loBound;
+		float2 g2 = (1. - eyeUV) / (1. - hiBound);
 
-	float2 compressedUV = underBound * leftEdge + inBound * center + overBound * rightEdge;
+		float2 leftEdge = g1 * center + (1. - g1) * d2;
+		float2 rightEdge = g2 * center + (1. - g2) * d3;
 
-	return compositionTexture.Sample(trilinearSampler, EyeToTextureUV(compressedUV, isRightEye));
+		compressedUV = underBound * leftEdge + inBound * center + overBound * rightEdge;
+		spreadRatio = (1. - inBound) * (edgeRatio - 1.);
+	}
+
+	float2 textureUV = EyeToTextureUV(compressedUV, isRightEye);
+
+	float4 color = compositionTexture.Sample(trilinearSampler, textureUV);
+
+	// Each peripheral output pixel covers several source pixels. Where the local luma contrast
+	// is high, blend in a supersampled value so edges don't turn into steps
+	float2 sourceResolution = float2(targetResolution.x * 2, targetResolution.y);
+	float2 spread = spreadRatio * .25 / sourceResolution;
+	if (edgePreservationStrength > 0. && (spread.x > 0. || spread.y > 0.)) {
+		float4 t0 = compositionTexture.Sample(trilinearSampler, textureUV + float2(-spread.x, -spread.y));
+		float4 t1 = compositionTexture.Sample(trilinearSampler, textureUV + float2(spread.x, -spread.y));
+		float4 t2 = compositionTexture.Sample(trilinearSampler, textureUV + float2(-spread.x, spread.y));
+		float4 t3 = compositionTexture.Sample(trilinearSampler, textureUV + float2(spread.x, spread.y));
+
+		const float3 LUMA = float3(0.299, 0.587, 0.114);
+		float l0 = dot(t0.rgb, LUMA);
+		float l1 = dot(t1.rgb, LUMA);
+		float l2 = dot(t2.rgb, LUMA);
+		float l3 = dot(t3.rgb, LUMA);
+		float localContrast = max(max(l0, l1), max(l2, l3)) - min(min(l0, l1), min(l2, l3));
+
+		color = lerp(color, (t0 + t1 + t2 + t3) * .25, saturate(localContrast * edgePreservationStrength * 4.));
+	}
+
+	return color;
 }
@@ -1,10 +1,20 @@
-cbuffer FoveationVars {
-	uint2 targetResolution;
-	uint2 optimizedResolution;
+struct EyeFoveationVars {
 	float2 eyeSizeRatio;
 	float2 centerSize;
 	float2 centerShift;
 	float2 edgeRatio;
+	float2 radialCenter;
+	float2 _align;
+};
+
+cbuffer FoveationVars {
+	uint2 targetResolution;
+	uint2 optimizedResolution;
+	float edgePreservationStrength;
+	uint mode; // 0: two zones, 1: radial
+	float radialAlpha;
+	float radialScale;
+	EyeFoveationVars eyes[2]; // Left and right
 };
 
 float2 TextureToEyeUV(float2 textureUV, bool isRightEye) {
@@ -22,6 +22,7 @@
 #include "alvr_server/Logger.h"
 #include "alvr_server/PoseHistory.h"
 #include "alvr_server/Settings.h"
+#include "alvr_server/Utils.h"
 #include "ffmpeg_helper.h"
 #include "protocol.h"
 
@@ -205,20 +206,18 @@ void CEncoder::Run() {
         alvr::VkContext vk_ctx(init.device_uuid.data(), {});
 
         FrameRender render(vk_ctx, init, m_fds);
-        auto output = render.CreateOutput();
 
-        alvr::VkFrame frame(
-            vk_ctx, output.image, output.imageInfo, output.size, output.memory, output.drm
-        );
+        std::unique_ptr<alvr::VkFrame> frame;
+        auto presets = GetEncoderPresets();
         auto encode_pipeline = alvr::EncodePipeline::Create(
-            &render,
-            vk_ctx,
-            frame,
-            output.imageInfo,
-            render.GetEncodingWidth(),
-            render.GetEncodingHeight()
+            &render, vk_ctx, frame, render.GetEncodingWidth(), render.GetEncodingHeight(), presets
         );
 
+        if (Settings::Instance().m_serverReprojection) {
+            // The frame is composited by the Vulkan layer, the layer poses are not available here
+            Warn("Streamer-side reprojection is not supported on Linux");
+        }
+
         bool valid_timestamps = true;
 
         fprintf(stderr, "CEncoder starting to read present packets");
@@ -226,10 +225,25 @@ void CEncoder::Run() {
         while (not m_exiting) {
             read_latest(client, (char*)&frame_info, sizeof(frame_info), m_exiting);
 
-            encode_pipeline->SetParams(GetDynamicEncoderParams());
+            auto params = GetDynamicEncoderParams();
+            if (params.updated && EncoderPresetsChanged(params.presets, presets)) {
+                // The presets are set when the ffmpeg encoder is opened
+                Info("The encoder effort changed, recreating the encoder");
+                presets = params.presets;
+                encode_pipeline.reset();
+                encode_pipeline = alvr::EncodePipeline::Create(
+                    &render,
+                    vk_ctx,
+                    frame,
+                    render.GetEncodingWidth(),
+                    render.GetEncodingHeight(),
+                    presets
+                );
+            }
+            encode_pipeline->SetParams(params);
 
             auto pose = m_poseHistory->GetBestPoseMatch((const vr::HmdMatrix34_t&)frame_info.pose);
-            if (!pose) {
+            if (!pose || SkipPausedFrame() || SkipIdleFrame()) {
                 continue;
             }
 
@@ -243,7 +257,7 @@ void CEncoder::Run() {
                 );
             }
 
-            render.Render(frame_info.image, frame_info.semaphore_value);
+            render.Render(frame_info.image, frame_info.semaphore_value, m_testPattern);
 
             if (!valid_timestamps) {
                 ReportPresent(pose->targetTimestampNs, 0);
@@ -290,7 +304,12 @@ void CEncoder::Run() {
             }
 
             ParseFrameNals(
-                encode_pipeline->GetCodec(), packet.data, packet.size, packet.pts, packet.isIDR
+                encode_pipeline->GetCodec(),
+                packet.data,
+                packet.size,
+                packet.pts,
+                packet.isIDR,
+                packet.averageQp
             );
         }
     } catch (std::exception& e) {
@@ -315,3 +334,45 @@ void CEncoder::OnStreamStart() { m_scheduler.OnStreamStart(); }
 void CEncoder::InsertIDR() { m_scheduler.InsertIDR(); }
 
 void CEncoder::CaptureFrame() { m_captureFrame = true; }
+
+void CEncoder::SetTestPattern(bool enabled) { m_testPattern = enabled; }
+
+void CEncoder::SetPaused(bool paused, bool keepaliveFrames) {
+    m_keepaliveFrames = keepaliveFrames;
+    m_paused = paused;
+}
+
+bool CEncoder::SkipPausedFrame() {
+    if (!m_paused) {
+        return false;
+    }
+    if (!m_keepaliveFrames) {
+        return true;
+    }
+
+    auto now = std::chrono::steady_clock::now();
+    if (now - m_lastKeepaliveFrame < std::chrono::seconds(1)) {
+        return true;
+    }
+    m_lastKeepaliveFrame = now;
+
+    return false;
+}
+
+void CEncoder::SetIdleFrameRate(float fps) { m_idleFps = fps; }
+
+// The client keeps showing the last frame in between
+bool CEncoder::SkipIdleFrame() {
+    float fps = m_idleFps;
+    if (fps <= 0.f) {
+        return false;
+    }
+
+    auto now = std::chrono::steady_clock::now();
+    if (now - m_lastIdleFrame < std::chrono::duration<float>(1.f / fps)) {
+        return true;
+    }
+    m_lastIdleFrame = now;
+
+    return false;
+}
@@ -3,6 +3,7 @@
 #include "alvr_server/IDRScheduler.h"
 #include "shared/threadtools.h"
 #include <atomic>
+#include <chrono>
 #include <memory>
 #include <poll.h>
 #include <sys/types.h>
@@ -21,6 +22,11 @@ public:
     void InsertIDR();
     bool IsConnected() { return m_connected; }
     void CaptureFrame();
+    void SetTestPattern(bool enabled);
+    // The mirror is not implemented for the Vulkan pipeline
+    void SetMirrorEnabled(bool) { }
+    void SetPaused(bool paused, bool keepaliveFrames);
+    void SetIdleFrameRate(float fps);
 
 private:
     void GetFds(int client, int (*fds)[6]);
@@ -32,4 +38,14 @@ private:
     int m_fds[6];
     bool m_connected = false;
     std::atomic_bool m_captureFrame = false;
+    std::atomic_bool m_testPattern = false;
+    std::atomic_bool m_paused = false;
+    std::atomic_bool m_keepaliveFrames = false;
+    std::chrono::steady_clock::time_point m_lastKeepaliveFrame;
+    // 0 if not idle
+    std::atomic<float> m_idleFps = 0.f;
+    std::chrono::steady_clock::time_point m_lastIdleFrame;
+
+    bool SkipPausedFrame();
+    bool SkipIdleFrame();
 };
@@ -3,10 +3,17 @@
 #include "EncodePipelineNvEnc.h"
 #include "EncodePipelineSW.h"
 #include "EncodePipelineVAAPI.h"
+#include "EncodePipelineVulkan.h"
+#include "FrameRender.h"
 #include "alvr_server/Logger.h"
 #include "alvr_server/Settings.h"
+#include "alvr_server/bindings.h"
 #include "ffmpeg_helper.h"
 
+#include <cstring>
+#include <functional>
+#include <vector>
+
 extern "C" {
 #include <libavcodec/avcodec.h>
 }
@@ -22,46 +29,142 @@ void alvr::EncodePipeline::SetParams(FfiDynamicEncoderParams params) {
 }
 
 std::unique_ptr<alvr::EncodePipeline> alvr::EncodePipeline::Create(
-    Renderer* render,
+    FrameRender* render,
     VkContext& vk_ctx,
-    VkFrame& input_frame,
-    VkImageCreateInfo& image_create_info,
+    std::unique_ptr<VkFrame>& input_frame,
     uint32_t width,
-    uint32_t height
+    uint32_t height,
+    const FfiEncoderPresets& presets
 ) {
-    if (Settings::Instance().m_force_sw_encoding == false) {
-        if (vk_ctx.nvidia) {
-            try {
-                auto nvenc = std::make_unique<alvr::EncodePipelineNvEnc>(
-                    render, vk_ctx, input_frame, image_create_info, width, height
-                );
-                Info("Using NvEnc encoder");
-                return nvenc;
-            } catch (std::exception& e) {
-                Error(
-                    "Failed to create NvEnc encoder: %s\nPlease make sure you have installed CUDA "
-                    "runtime.",
-                    e.what()
-                );
+    const auto& settings = Settings::Instance();
+
+    // Only NvEnc supports intra refresh, the other encoders recover with IDR frames
+    SetIntraRefreshPeriod(0);
+
+    // Only VAAPI supports the HEVC range extensions
+    auto warn_chroma_subsampling = [&](const char* encoder) {
+        if (settings.m_chromaSubsampling != ALVR_CHROMA_SUBSAMPLING_420) {
+            Warn("%s does not support 4:2:2 and 4:4:4 chroma subsampling, using 4:2:0", encoder);
+        }
+    };
+
+    // NvEnc imports the renderer output as an opaque fd, the other hardware backends as a dmabuf.
+    // The output is created again for each backend that is tried
+    auto create_input_frame = [&](Renderer::ExternalHandle handle) {
+        input_frame.reset();
+        auto output = render->CreateOutput(handle);
+        input_frame = std::make_unique<alvr::VkFrame>(
+            vk_ctx, output.image, output.imageInfo, output.size, output.memory, output.drm
+        );
+        return output.imageInfo;
+    };
+
+    auto try_nvenc = [&]() -> std::unique_ptr<alvr::EncodePipeline> {
+        try {
+            auto image_create_info = create_input_frame(Renderer::ExternalHandle::OpaqueFd);
+            auto nvenc = std::make_unique<alvr::EncodePipelineNvEnc>(
+                render, vk_ctx, *input_frame, image_create_info, width, height, presets
+            );
+            Info("Using NvEnc encoder");
+            warn_chroma_subsampling("NvEnc");
+            SetIntraRefreshPeriod(settings.m_intraRefreshRecoveryPeriod);
+            return nvenc;
+        } catch (std::exception& e) {
+            Error(
+                "Failed to create NvEnc encoder: %s\nPlease make sure you have installed CUDA "
+                "runtime.",
+                e.what()
+            );
+            return nullptr;
+        }
+    };
+    auto try_vaapi = [&]() -> std::unique_ptr<alvr::EncodePipeline> {
+        try {
+            create_input_frame(Renderer::ExternalHandle::DmaBuf);
+            auto vaapi = std::make_unique<alvr::EncodePipelineVAAPI>(
+                render, vk_ctx, *input_frame, width, height, presets
+            );
+            Info("Using VAAPI encoder");
+            return vaapi;
+        } catch (std::exception& e) {
+            Error(
+                "Failed to create VAAPI encoder: %s\nPlease make sure you have installed VAAPI "
+                "runtime.",
+                e.what()
+            );
+            return nullptr;
+        }
+    };
+    auto try_vulkan = [&]() -> std::unique_ptr<alvr::EncodePipeline> {
+        try {
+            create_input_frame(Renderer::ExternalHandle::DmaBuf);
+            auto vulkan = std::make_unique<alvr::EncodePipelineVulkan>(
+                render, vk_ctx, *input_frame, width, height, presets
+            );
+            Info("Using Vulkan video encoder");
+            warn_chroma_subsampling("Vulkan video");
+            return vulkan;
+        } catch (std::exception& e) {
+            Error(
+                "Failed to create Vulkan video encoder: %s\nPlease make sure your driver supports "
+                "Vulkan video encoding.",
+                e.what()
+            );
+            return nullptr;
+        }
+    };
+
+    struct Backend {
+        const char* name;
+        std::function<std::unique_ptr<alvr::EncodePipeline>()> create;
+    };
+
+    // Hardware backends in order of preference
+    std::vector<Backend> backends;
+    if (!settings.m_force_sw_encoding) {
+        switch (settings.m_encoderBackend) {
+        case ALVR_ENCODER_BACKEND_VAAPI:
+            backends = { { "VAAPI", try_vaapi }, { "Vulkan Video", try_vulkan } };
+            break;
+        case ALVR_ENCODER_BACKEND_VULKAN_VIDEO:
+            backends = { { "Vulkan Video", try_vulkan }, { "VAAPI", try_vaapi } };
+            break;
+        case ALVR_ENCODER_BACKEND_SOFTWARE:
+            break;
+        case ALVR_ENCODER_BACKEND_AUTO:
+        default:
+            if (vk_ctx.nvidia) {
+                backends = { { "NvEnc", try_nvenc }, { "Vulkan Video", try_vulkan } };
+            } else {
+                backends = { { "VAAPI", try_vaapi }, { "Vulkan Video", try_vulkan } };
             }
-        } else {
-            try {
-                auto vaapi = std::make_unique<alvr::EncodePipelineVAAPI>(
-                    render, vk_ctx, input_frame, width, height
-                );
-                Info("Using VAAPI encoder");
-                return vaapi;
-            } catch (std::exception& e) {
-                Error(
-                    "Failed to create VAAPI encoder: %s\nPlease make sure you have installed VAAPI "
-                    "runtime.",
-                    e.what()
+            break;
+        }
+    }
+
+    for (size_t i = 0; i < backends.size(); i++) {
+        if (auto pipeline = backends[i].create()) {
+            if (i > 0) {
+                Warn(
+                    "The %s encoder is not available, using %s instead",
+                    backends[0].name,
+                    backends[i].name
                 );
+                ReportEncoderFallback(backends[0].name, backends[i].name);
             }
+            return pipeline;
         }
     }
-    auto sw = std::make_unique<alvr::EncodePipelineSW>(render, width, height);
+
+    bool sw_requested = settings.m_force_sw_encoding
+        || settings.m_encoderBackend == ALVR_ENCODER_BACKEND_SOFTWARE;
+    create_input_frame(Renderer::ExternalHandle::None);
+    auto sw = std::make_unique<alvr::EncodePipelineSW>(render, width, height, !sw_requested);
     Info("Using SW encoder");
+    warn_chroma_subsampling("The software encoder");
+    if (!sw_requested) {
+        ReportEncoderFallback(backends[0].name, "software");
+    }
     return sw;
 }
 
@@ -82,6 +185,17 @@ bool alvr::EncodePipeline::GetEncoded(FramePacket& packet) {
     packet.size = encoder_packet->size;
     packet.pts = encoder_packet->pts;
     packet.isIDR = (encoder_packet->flags & AV_PKT_FLAG_KEY) != 0;
+
+    // Set by nvenc, the quality is the QP scaled by FF_QP2LAMBDA
+    size_t stats_size = 0;
+    auto stats = av_packet_get_side_data(encoder_packet, AV_PKT_DATA_QUALITY_STATS, &stats_size);
+    packet.averageQp = -1;
+    if (stats && stats_size >= sizeof(uint32_t)) {
+        uint32_t quality;
+        memcpy(&quality, stats, sizeof(quality));
+        packet.averageQp = (int)(quality / FF_QP2LAMBDA);
+    }
+
     return true;
 }
 
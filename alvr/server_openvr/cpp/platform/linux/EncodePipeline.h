@@ -8,6 +8,7 @@
 extern "C" struct AVCodecContext;
 extern "C" struct AVPacket;
 
+class FrameRender;
 class Renderer;
 
 namespace alvr {
@@ -21,6 +22,8 @@ struct FramePacket {
     int size;
     uint64_t pts;
     bool isIDR;
+    // -1 if the encoder doesn't report it
+    int averageQp = -1;
 };
 
 class EncodePipeline {
@@ -38,13 +41,15 @@ public:
     virtual int GetCodec();
 
     virtual void SetParams(FfiDynamicEncoderParams params);
+    // Creates the output of render for the first backend that initializes. input_frame receives
+    // the output and must outlive the pipeline
     static std::unique_ptr<EncodePipeline> Create(
-        Renderer* render,
+        FrameRender* render,
         VkContext& vk_ctx,
-        VkFrame& input_frame,
-        VkImageCreateInfo& image_create_info,
+        std::unique_ptr<VkFrame>& input_frame,
         uint32_t width,
-        uint32_t height
+        uint32_t height,
+        const FfiEncoderPresets& presets
     );
 
 protected:
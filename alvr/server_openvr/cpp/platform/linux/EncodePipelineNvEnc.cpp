@@ -66,7 +66,8 @@ alvr::EncodePipelineNvEnc::EncodePipelineNvEnc(
     VkFrame& input_frame,
     VkImageCreateInfo& image_create_info,
     uint32_t width,
-    uint32_t height
+    uint32_t height,
+    const FfiEncoderPresets& presets
 ) {
     r = render;
     vk_frame_ctx = std::make_unique<alvr::VkFrameCtx>(vk_ctx, image_create_info);
@@ -139,7 +140,7 @@ alvr::EncodePipelineNvEnc::EncodePipelineNvEnc(
 
     char preset[] = "p0";
     // replace 0 with preset number
-    preset[1] += settings.m_nvencQualityPreset;
+    preset[1] += presets.nvenc_quality_preset;
     av_opt_set(encoder_ctx->priv_data, "preset", preset, 0);
 
     if (settings.m_nvencAdaptiveQuantizationMode == 1) {
@@ -152,7 +153,8 @@ alvr::EncodePipelineNvEnc::EncodePipelineNvEnc(
         av_opt_set_int(encoder_ctx->priv_data, "weighted_pred", 1, 0);
     }
 
-    av_opt_set_int(encoder_ctx->priv_data, "tune", settings.m_nvencTuningPreset, 0);
+    av_opt_set_int(encoder_ctx->priv_data, "tune", presets.nvenc_tuning_preset, 0);
+    av_opt_set_int(encoder_ctx->priv_data, "multipass", presets.nvenc_multi_pass, 0);
     av_opt_set_int(encoder_ctx->priv_data, "zerolatency", 1, 0);
     // Delay isn't actually a delay instead its how many surfaces to encode at a time
     av_opt_set_int(encoder_ctx->priv_data, "delay", 1, 0);
@@ -168,7 +170,12 @@ alvr::EncodePipelineNvEnc::EncodePipelineNvEnc(
     encoder_ctx->sample_aspect_ratio = AVRational { 1, 1 };
     encoder_ctx->max_b_frames = 0;
     encoder_ctx->gop_size = INT16_MAX;
-    encoder_ctx->color_range = AVCOL_RANGE_JPEG;
+    if (settings.m_intraRefreshRecoveryPeriod > 0) {
+        // With intra refresh, the GOP size is used as the refresh period
+        av_opt_set_int(encoder_ctx->priv_data, "intra-refresh", 1, 0);
+        encoder_ctx->gop_size = settings.m_intraRefreshRecoveryPeriod;
+    }
+    set_color_properties(encoder_ctx);
     auto params = FfiDynamicEncoderParams {};
     params.updated = true;
     params.bitrate_bps = 30'000'000;
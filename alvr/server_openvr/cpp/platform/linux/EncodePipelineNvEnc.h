@@ -21,7 +21,8 @@ public:
         VkFrame& input_frame,
         VkImageCreateInfo& image_create_info,
         uint32_t width,
-        uint32_t height
+        uint32_t height,
+        const FfiEncoderPresets& presets
     );
 
     void PushFrame(uint64_t targetTimestampNs, bool idr) override;
@@ -1,10 +1,13 @@
 #include "EncodePipelineSW.h"
 
+#include <algorithm>
 #include <chrono>
+#include <cmath>
 
 #include "FormatConverter.h"
 #include "alvr_server/Logger.h"
 #include "alvr_server/Settings.h"
+#include "alvr_server/Utils.h"
 
 namespace {
 
@@ -31,9 +34,16 @@ void x264_log(void*, int level, const char* fmt, va_list args) {
 
 }
 
-alvr::EncodePipelineSW::EncodePipelineSW(Renderer* render, uint32_t width, uint32_t height) {
+alvr::EncodePipelineSW::EncodePipelineSW(
+    Renderer* render, uint32_t width, uint32_t height, bool fallback
+)
+    : fallback(fallback) {
     const auto& settings = Settings::Instance();
 
+    if (settings.m_codec != ALVR_CODEC_H264) {
+        Warn("Software encoding supports only h264 on Linux. Using h264 instead.");
+    }
+
     x264_param_default_preset(&param, "ultrafast", "zerolatency");
 
     param.pf_log = x264_log;
@@ -47,6 +57,25 @@ alvr::EncodePipelineSW::EncodePipelineSW(Renderer* render, uint32_t width, uint3
     param.i_height = height;
     param.rc.i_rc_method = X264_RC_ABR;
 
+    // The gaze quantizer offsets require adaptive quantization. Its strength is zeroed so that only
+    // the offsets apply
+    if (settings.m_enableGazeBitrateAllocation) {
+        param.rc.i_aq_mode = X264_AQ_VARIANCE;
+        param.rc.f_aq_strength = 0;
+    }
+
+    // The RGB to YUV conversion shader outputs limited range, it is expanded on the mapped planes
+    full_range = settings.m_colorRange == ALVR_COLOR_RANGE_FULL;
+    if (full_range) {
+        for (int i = 0; i < 256; i++) {
+            luma_lut[i] = std::clamp((int)std::lround((i - 16) * 255.0 / 219.0), 0, 255);
+            chroma_lut[i]
+                = std::clamp((int)std::lround((i - 128) * 255.0 / 224.0 + 128.0), 0, 255);
+        }
+    }
+    param.vui.b_fullrange = full_range;
+    param.vui.i_transfer = settings.m_transferFunction == ALVR_TRANSFER_FUNCTION_GAMMA22 ? 4 : 13;
+
     switch (settings.m_h264Profile) {
     case ALVR_H264_PROFILE_BASELINE:
         x264_param_apply_profile(&param, "baseline");
@@ -95,8 +124,22 @@ alvr::EncodePipelineSW::~EncodePipelineSW() {
 }
 
 void alvr::EncodePipelineSW::PushFrame(uint64_t targetTimestampNs, bool idr) {
+    if (fallback) {
+        auto now = std::chrono::steady_clock::now();
+        if (now > last_fallback_warning + SW_FALLBACK_WARNING_INTERVAL) {
+            last_fallback_warning = now;
+            Warn(
+                "No hardware encoder is available, streaming with software encoding. Expect "
+                "higher latency and CPU usage."
+            );
+        }
+    }
+
     rgbtoyuv->Convert(picture.img.plane, picture.img.i_stride);
     rgbtoyuv->Sync();
+    if (full_range) {
+        ExpandRange();
+    }
     timestamp.cpu = std::chrono::duration_cast<std::chrono::nanoseconds>(
                         std::chrono::steady_clock::now().time_since_epoch()
     )
@@ -106,6 +149,11 @@ void alvr::EncodePipelineSW::PushFrame(uint64_t targetTimestampNs, bool idr) {
     pts = picture.i_pts = targetTimestampNs;
     is_idr = idr;
 
+    picture.prop.quant_offsets = nullptr;
+    if (Settings::Instance().m_enableGazeBitrateAllocation) {
+        SetGazeRegions();
+    }
+
     int nnal = 0;
     nal_size = x264_encoder_encode(enc, &nal, &nnal, &picture, &picture_out);
     if (nal_size < 0) {
@@ -113,6 +161,51 @@ void alvr::EncodePipelineSW::PushFrame(uint64_t targetTimestampNs, bool idr) {
     }
 }
 
+void alvr::EncodePipelineSW::ExpandRange() {
+    for (int plane = 0; plane < 3; plane++) {
+        const auto& lut = plane == 0 ? luma_lut : chroma_lut;
+        int width = plane == 0 ? param.i_width : (param.i_width + 1) / 2;
+        int height = plane == 0 ? param.i_height : (param.i_height + 1) / 2;
+
+        for (int y = 0; y < height; y++) {
+            uint8_t* row = picture.img.plane[plane] + y * picture.img.i_stride[plane];
+            for (int x = 0; x < width; x++) {
+                row[x] = lut[row[x]];
+            }
+        }
+    }
+}
+
+void alvr::EncodePipelineSW::SetGazeRegions() {
+    auto regions = GetGazeRegions();
+    if (regions.count == 0) {
+        return;
+    }
+
+    // One offset per macroblock
+    int mbs_x = (param.i_width + 15) / 16;
+    int mbs_y = (param.i_height + 15) / 16;
+    quant_offsets.assign(mbs_x * mbs_y, 0);
+
+    for (unsigned int i = 0; i < regions.count; i++) {
+        const float* rect = regions.rects[i];
+        int left = std::clamp(int(rect[0] * param.i_width) / 16, 0, mbs_x);
+        int top = std::clamp(int(rect[1] * param.i_height) / 16, 0, mbs_y);
+        int right = std::clamp(int(std::ceil(rect[2] * param.i_width / 16)), 0, mbs_x);
+        int bottom = std::clamp(int(std::ceil(rect[3] * param.i_height / 16)), 0, mbs_y);
+
+        for (int y = top; y < bottom; y++) {
+            for (int x = left; x < right; x++) {
+                quant_offsets[y * mbs_x + x] = regions.qpOffset;
+            }
+        }
+    }
+
+    // x264 doesn't take ownership of the buffer
+    picture.prop.quant_offsets = quant_offsets.data();
+    picture.prop.quant_offsets_free = nullptr;
+}
+
 bool alvr::EncodePipelineSW::GetEncoded(FramePacket& packet) {
     if (!nal) {
         return false;
@@ -121,6 +214,7 @@ bool alvr::EncodePipelineSW::GetEncoded(FramePacket& packet) {
     packet.data = nal[0].p_payload;
     packet.pts = pts;
     packet.isIDR = is_idr;
+    packet.averageQp = picture_out.i_qpplus1 - 1;
     return packet.size > 0;
 }
 
@@ -2,6 +2,9 @@
 
 #include "EncodePipeline.h"
 
+#include <array>
+#include <chrono>
+#include <vector>
 #include <x264.h>
 
 class FormatConverter;
@@ -11,7 +14,7 @@ namespace alvr {
 class EncodePipelineSW : public EncodePipeline {
 public:
     ~EncodePipelineSW();
-    EncodePipelineSW(Renderer* render, uint32_t width, uint32_t height);
+    EncodePipelineSW(Renderer* render, uint32_t width, uint32_t height, bool fallback);
 
     void PushFrame(uint64_t targetTimestampNs, bool idr) override;
     bool GetEncoded(FramePacket& packet) override;
@@ -19,6 +22,9 @@ public:
     int GetCodec() override;
 
 private:
+    void SetGazeRegions();
+    void ExpandRange();
+
     x264_t* enc = nullptr;
     x264_param_t param;
     x264_picture_t picture;
@@ -27,6 +33,12 @@ private:
     int nal_size = 0;
     int64_t pts = 0;
     bool is_idr = false;
+    bool fallback = false;
+    std::chrono::steady_clock::time_point last_fallback_warning;
     FormatConverter* rgbtoyuv = nullptr;
+    std::vector<float> quant_offsets;
+    bool full_range = false;
+    std::array<uint8_t, 256> luma_lut;
+    std::array<uint8_t, 256> chroma_lut;
 };
 }
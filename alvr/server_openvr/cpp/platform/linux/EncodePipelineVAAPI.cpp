@@ -10,8 +10,10 @@ extern "C" {
 #include <libavfilter/avfilter.h>
 #include <libavfilter/buffersink.h>
 #include <libavfilter/buffersrc.h>
+#include <libavutil/frame.h>
 #include <libavutil/hwcontext.h>
 #include <libavutil/opt.h>
+#include <libavutil/pixdesc.h>
 }
 
 namespace {
@@ -28,6 +30,27 @@ const char* encoder(ALVR_CODEC codec) {
     throw std::runtime_error("invalid codec " + std::to_string(codec));
 }
 
+bool is_range_extension() {
+    return Settings::Instance().m_codec == ALVR_CODEC_HEVC
+        && Settings::Instance().m_chromaSubsampling != ALVR_CHROMA_SUBSAMPLING_420;
+}
+
+// Format of the encoder surfaces, the HEVC range extensions use packed formats
+AVPixelFormat encoder_sw_format() {
+    const auto& settings = Settings::Instance();
+    bool use10bit = (settings.m_codec == ALVR_CODEC_HEVC || settings.m_codec == ALVR_CODEC_AV1)
+        && settings.m_use10bitEncoder;
+
+    if (is_range_extension()) {
+        if (settings.m_chromaSubsampling == ALVR_CHROMA_SUBSAMPLING_422) {
+            return use10bit ? AV_PIX_FMT_Y210 : AV_PIX_FMT_YUYV422;
+        }
+        return use10bit ? AV_PIX_FMT_XV30 : AV_PIX_FMT_VUYX;
+    }
+
+    return use10bit ? AV_PIX_FMT_P010 : AV_PIX_FMT_NV12;
+}
+
 void set_hwframe_ctx(AVCodecContext* ctx, AVBufferRef* hw_device_ctx) {
     AVBufferRef* hw_frames_ref;
     AVHWFramesContext* frames_ctx = NULL;
@@ -38,11 +61,7 @@ void set_hwframe_ctx(AVCodecContext* ctx, AVBufferRef* hw_device_ctx) {
     }
     frames_ctx = (AVHWFramesContext*)(hw_frames_ref->data);
     frames_ctx->format = AV_PIX_FMT_VAAPI;
-    frames_ctx->sw_format = (Settings::Instance().m_codec == ALVR_CODEC_HEVC
-                             || Settings::Instance().m_codec == ALVR_CODEC_AV1)
-            && Settings::Instance().m_use10bitEncoder
-        ? AV_PIX_FMT_P010
-        : AV_PIX_FMT_NV12;
+    frames_ctx->sw_format = encoder_sw_format();
     frames_ctx->width = ctx->width;
     frames_ctx->height = ctx->height;
     frames_ctx->initial_pool_size = 3;
@@ -125,10 +144,41 @@ AVFrame* import_frame(AVBufferRef* hw_frames_ref, DrmImage& drm) {
     return va_frame;
 }
 
+// Attaches the gaze regions as regions of interest. Without regions the frame is encoded uniformly
+void set_gaze_regions(AVFrame* frame, int width, int height) {
+    auto regions = GetGazeRegions();
+    if (regions.count == 0) {
+        return;
+    }
+
+    AVFrameSideData* side_data = av_frame_new_side_data(
+        frame, AV_FRAME_DATA_REGIONS_OF_INTEREST, regions.count * sizeof(AVRegionOfInterest)
+    );
+    if (!side_data) {
+        return;
+    }
+
+    auto roi = reinterpret_cast<AVRegionOfInterest*>(side_data->data);
+    for (unsigned int i = 0; i < regions.count; i++) {
+        roi[i].self_size = sizeof(AVRegionOfInterest);
+        roi[i].left = regions.rects[i][0] * width;
+        roi[i].top = regions.rects[i][1] * height;
+        roi[i].right = regions.rects[i][2] * width;
+        roi[i].bottom = regions.rects[i][3] * height;
+        // The offset is relative to the quantizer range of the codec, 0-51 for H264 and HEVC
+        roi[i].qoffset = AVRational { regions.qpOffset, 51 };
+    }
+}
+
 }
 
 alvr::EncodePipelineVAAPI::EncodePipelineVAAPI(
-    Renderer* render, VkContext& vk_ctx, VkFrame& input_frame, uint32_t width, uint32_t height
+    Renderer* render,
+    VkContext& vk_ctx,
+    VkFrame& input_frame,
+    uint32_t width,
+    uint32_t height,
+    const FfiEncoderPresets& presets
 )
     : r(render) {
     /* VAAPI Encoding pipeline
@@ -199,8 +249,13 @@ alvr::EncodePipelineVAAPI::EncodePipelineVAAPI(
 
         break;
     case ALVR_CODEC_HEVC:
-        encoder_ctx->profile = Settings::Instance().m_use10bitEncoder ? FF_PROFILE_HEVC_MAIN_10
-                                                                      : FF_PROFILE_HEVC_MAIN;
+        if (is_range_extension()) {
+            encoder_ctx->profile = FF_PROFILE_HEVC_REXT;
+        } else {
+            encoder_ctx->profile = Settings::Instance().m_use10bitEncoder
+                ? FF_PROFILE_HEVC_MAIN_10
+                : FF_PROFILE_HEVC_MAIN;
+        }
         encoder_ctx->gop_size = INT16_MAX;
         break;
     case ALVR_CODEC_AV1:
@@ -226,7 +281,7 @@ alvr::EncodePipelineVAAPI::EncodePipelineVAAPI(
     encoder_ctx->sample_aspect_ratio = AVRational { 1, 1 };
     encoder_ctx->pix_fmt = AV_PIX_FMT_VAAPI;
     encoder_ctx->max_b_frames = 0;
-    encoder_ctx->color_range = AVCOL_RANGE_JPEG;
+    set_color_properties(encoder_ctx);
 
     auto params = FfiDynamicEncoderParams {};
     params.updated = true;
@@ -239,7 +294,7 @@ alvr::EncodePipelineVAAPI::EncodePipelineVAAPI(
         = Settings::Instance()
               .m_enableVbaq; // No noticable performance difference and should improve subjective
                              // quality by allocating more bits to smooth areas
-    switch (settings.m_encoderQualityPreset) {
+    switch (presets.encoder_quality_preset) {
     case ALVR_QUALITY:
         if (vk_ctx.amd) {
             quality.preset_mode = PRESET_MODE_QUALITY;
@@ -270,12 +325,18 @@ alvr::EncodePipelineVAAPI::EncodePipelineVAAPI(
         break;
     }
 
-    av_opt_set_int(encoder_ctx->priv_data, "async_depth", 1, 0);
+    av_opt_set_int(encoder_ctx->priv_data, "async_depth", settings.m_encoderAsyncDepth, 0);
 
     set_hwframe_ctx(encoder_ctx, hw_ctx);
 
     err = avcodec_open2(encoder_ctx, codec, NULL);
-    if (err < 0) {
+    if (err < 0 && is_range_extension()) {
+        throw alvr::AvException(
+            "Cannot open video encoder codec. The GPU may not support 4:2:2 or 4:4:4 encoding, "
+            "set the chroma subsampling to 4:2:0:",
+            err
+        );
+    } else if (err < 0) {
         throw alvr::AvException("Cannot open video encoder codec:", err);
     }
 
@@ -347,14 +408,8 @@ alvr::EncodePipelineVAAPI::EncodePipelineVAAPI(
     inputs->pad_idx = 0;
     inputs->next = NULL;
 
-    std::string filters = "scale_vaapi=out_range=full:format=";
-    if ((Settings::Instance().m_codec == ALVR_CODEC_HEVC
-         || Settings::Instance().m_codec == ALVR_CODEC_AV1)
-        && Settings::Instance().m_use10bitEncoder) {
-        filters += "p010";
-    } else {
-        filters += "nv12";
-    }
+    std::string filters = "scale_vaapi=out_range=" + std::string(filter_color_range())
+        + ":format=" + av_get_pix_fmt_name(encoder_sw_format());
     if ((err = avfilter_graph_parse_ptr(filter_graph, filters.c_str(), &inputs, &outputs, NULL))
         < 0) {
         throw alvr::AvException("avfilter_graph_parse_ptr failed:", err);
@@ -402,6 +457,10 @@ void alvr::EncodePipelineVAAPI::PushFrame(uint64_t targetTimestampNs, bool idr)
     encoder_frame->pict_type = idr ? AV_PICTURE_TYPE_I : AV_PICTURE_TYPE_NONE;
     encoder_frame->pts = targetTimestampNs;
 
+    if (Settings::Instance().m_enableGazeBitrateAllocation) {
+        set_gaze_regions(encoder_frame, encoder_ctx->width, encoder_ctx->height);
+    }
+
     if ((err = avcodec_send_frame(encoder_ctx, encoder_frame)) < 0) {
         throw alvr::AvException("avcodec_send_frame failed: ", err);
     }
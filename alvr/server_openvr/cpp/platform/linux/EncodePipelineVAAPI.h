@@ -22,7 +22,12 @@ class EncodePipelineVAAPI : public EncodePipeline {
 public:
     ~EncodePipelineVAAPI();
     EncodePipelineVAAPI(
-        Renderer* render, VkContext& vk_ctx, VkFrame& input_frame, uint32_t width, uint32_t height
+        Renderer* render,
+        VkContext& vk_ctx,
+        VkFrame& input_frame,
+        uint32_t width,
+        uint32_t height,
+        const FfiEncoderPresets& presets
     );
 
     void PushFrame(uint64_t targetTimestampNs, bool idr) override;
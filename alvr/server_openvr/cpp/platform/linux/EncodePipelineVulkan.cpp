@@ -0,0 +1,326 @@
+#include "EncodePipelineVulkan.h"
+#include "ALVR-common/packet_types.h"
+#include "alvr_server/Logger.h"
+#include "alvr_server/Settings.h"
+#include "ffmpeg_helper.h"
+#include <chrono>
+
+extern "C" {
+#include <libavcodec/avcodec.h>
+#include <libavfilter/avfilter.h>
+#include <libavfilter/buffersink.h>
+#include <libavfilter/buffersrc.h>
+#include <libavutil/hwcontext.h>
+#include <libavutil/opt.h>
+}
+
+namespace {
+
+const char* encoder(ALVR_CODEC codec) {
+    switch (codec) {
+    case ALVR_CODEC_H264:
+        return "h264_vulkan";
+    case ALVR_CODEC_HEVC:
+        return "hevc_vulkan";
+    case ALVR_CODEC_AV1:
+        throw std::runtime_error("AV1 is not supported by Vulkan video encoding");
+    }
+    throw std::runtime_error("invalid codec " + std::to_string(codec));
+}
+
+// Import the dmabuf of the renderer output as a vulkan frame of the encoding device
+AVFrame*
+map_frame(AVBufferRef* hw_device_ctx, AVBufferRef* drm_device_ctx, alvr::VkFrame& input_frame) {
+    AVBufferRef* hw_frames_ref = NULL;
+    if (!(hw_frames_ref = av_hwframe_ctx_alloc(hw_device_ctx))) {
+        throw std::runtime_error("Failed to create vulkan frame context.");
+    }
+    auto frames_ctx = (AVHWFramesContext*)(hw_frames_ref->data);
+    frames_ctx->format = AV_PIX_FMT_VULKAN;
+    frames_ctx->sw_format = input_frame.avFormat();
+    frames_ctx->width = input_frame.imageInfo().extent.width;
+    frames_ctx->height = input_frame.imageInfo().extent.height;
+    frames_ctx->initial_pool_size = 0;
+    int err;
+    if ((err = av_hwframe_ctx_init(hw_frames_ref)) < 0) {
+        av_buffer_unref(&hw_frames_ref);
+        throw alvr::AvException("Failed to initialize vulkan frame context:", err);
+    }
+
+    AVBufferRef* drm_frames_ref = NULL;
+    if (!(drm_frames_ref = av_hwframe_ctx_alloc(drm_device_ctx))) {
+        av_buffer_unref(&hw_frames_ref);
+        throw std::runtime_error("Failed to create DRM frame context.");
+    }
+    AVHWFramesContext* drm_frames_ctx = (AVHWFramesContext*)(drm_frames_ref->data);
+    drm_frames_ctx->format = AV_PIX_FMT_DRM_PRIME;
+    drm_frames_ctx->sw_format = frames_ctx->sw_format;
+    drm_frames_ctx->width = frames_ctx->width;
+    drm_frames_ctx->height = frames_ctx->height;
+    drm_frames_ctx->initial_pool_size = 0;
+    if ((err = av_hwframe_ctx_init(drm_frames_ref)) < 0) {
+        av_buffer_unref(&drm_frames_ref);
+        av_buffer_unref(&hw_frames_ref);
+        throw alvr::AvException("Failed to initialize DRM frame context:", err);
+    }
+
+    AVFrame* drm_frame = av_frame_alloc();
+    drm_frame->width = frames_ctx->width;
+    drm_frame->height = frames_ctx->height;
+    drm_frame->hw_frames_ctx = drm_frames_ref;
+    drm_frame->data[0] = (uint8_t*)(AVDRMFrameDescriptor*)input_frame;
+    drm_frame->format = AV_PIX_FMT_DRM_PRIME;
+    drm_frame->buf[0] = av_buffer_alloc(1);
+
+    AVFrame* mapped_frame = av_frame_alloc();
+    mapped_frame->format = AV_PIX_FMT_VULKAN;
+    mapped_frame->hw_frames_ctx = hw_frames_ref;
+    err = av_hwframe_map(mapped_frame, drm_frame, AV_HWFRAME_MAP_READ);
+    av_frame_free(&drm_frame);
+    if (err < 0) {
+        av_frame_free(&mapped_frame);
+        throw alvr::AvException("Failed to import DRM frame:", err);
+    }
+
+    return mapped_frame;
+}
+
+}
+
+alvr::EncodePipelineVulkan::EncodePipelineVulkan(
+    Renderer* render,
+    VkContext& vk_ctx,
+    VkFrame& input_frame,
+    uint32_t width,
+    uint32_t height,
+    const FfiEncoderPresets& presets
+)
+    : r(render) {
+    /* Vulkan video encoding pipeline
+     * The renderer output is exported as a dmabuf and imported in a separate vulkan device created
+     * by ffmpeg, which owns the video encode queue. Similarly to the VAAPI pipeline, a scale_vulkan
+     * filter converts the frame to a format compatible with the encoder, all on the GPU.
+     */
+    const auto& settings = Settings::Instance();
+
+    auto codec_id = ALVR_CODEC(settings.m_codec);
+    const char* encoder_name = encoder(codec_id);
+    const AVCodec* codec = avcodec_find_encoder_by_name(encoder_name);
+    if (codec == nullptr) {
+        throw std::runtime_error(
+            std::string("Failed to find encoder ") + encoder_name
+            + ". Vulkan video encoding requires FFmpeg 7.1 or newer"
+        );
+    }
+
+    if (settings.m_use10bitEncoder) {
+        throw std::runtime_error("10-bit encoding is not supported by Vulkan video encoding");
+    }
+
+    int err = av_hwdevice_ctx_create(
+        &drm_ctx, AV_HWDEVICE_TYPE_DRM, vk_ctx.devicePath.c_str(), NULL, 0
+    );
+    if (err < 0) {
+        throw alvr::AvException("Failed to create DRM device:", err);
+    }
+
+    err = av_hwdevice_ctx_create_derived(&hw_ctx, AV_HWDEVICE_TYPE_VULKAN, drm_ctx, 0);
+    if (err < 0) {
+        throw alvr::AvException("Failed to create a Vulkan video device:", err);
+    }
+
+    encoder_ctx = avcodec_alloc_context3(codec);
+    if (not encoder_ctx) {
+        throw std::runtime_error("failed to allocate Vulkan encoder");
+    }
+
+    switch (codec_id) {
+    case ALVR_CODEC_H264:
+        switch (settings.m_h264Profile) {
+        case ALVR_H264_PROFILE_BASELINE:
+            encoder_ctx->profile = FF_PROFILE_H264_CONSTRAINED_BASELINE;
+            break;
+        case ALVR_H264_PROFILE_MAIN:
+            encoder_ctx->profile = FF_PROFILE_H264_MAIN;
+            break;
+        default:
+        case ALVR_H264_PROFILE_HIGH:
+            encoder_ctx->profile = FF_PROFILE_H264_HIGH;
+            break;
+        }
+
+        switch (settings.m_entropyCoding) {
+        case ALVR_CABAC:
+            av_opt_set(encoder_ctx->priv_data, "coder", "cabac", 0);
+            break;
+        case ALVR_CAVLC:
+            av_opt_set(encoder_ctx->priv_data, "coder", "vlc", 0);
+            break;
+        }
+        break;
+    case ALVR_CODEC_HEVC:
+        encoder_ctx->profile = FF_PROFILE_HEVC_MAIN;
+        break;
+    case ALVR_CODEC_AV1:
+        break;
+    }
+
+    switch (settings.m_rateControlMode) {
+    case ALVR_VBR:
+        av_opt_set(encoder_ctx->priv_data, "rc_mode", "vbr", 0);
+        break;
+    case ALVR_CBR:
+    default:
+        av_opt_set(encoder_ctx->priv_data, "rc_mode", "cbr", 0);
+        break;
+    }
+
+    switch (presets.encoder_quality_preset) {
+    case ALVR_QUALITY:
+        av_opt_set(encoder_ctx->priv_data, "tune", "hq", 0);
+        break;
+    case ALVR_BALANCED:
+        av_opt_set(encoder_ctx->priv_data, "tune", "ll", 0);
+        break;
+    case ALVR_SPEED:
+    default:
+        av_opt_set(encoder_ctx->priv_data, "tune", "ull", 0);
+        break;
+    }
+
+    av_opt_set(encoder_ctx->priv_data, "usage", "stream", 0);
+    av_opt_set(encoder_ctx->priv_data, "content", "rendered", 0);
+    av_opt_set_int(encoder_ctx->priv_data, "async_depth", settings.m_encoderAsyncDepth, 0);
+
+    encoder_ctx->width = width;
+    encoder_ctx->height = height;
+    encoder_ctx->time_base = { 1, (int)1e9 };
+    encoder_ctx->sample_aspect_ratio = AVRational { 1, 1 };
+    encoder_ctx->pix_fmt = AV_PIX_FMT_VULKAN;
+    encoder_ctx->max_b_frames = 0;
+    encoder_ctx->gop_size = INT16_MAX;
+    set_color_properties(encoder_ctx);
+
+    auto params = FfiDynamicEncoderParams {};
+    params.updated = true;
+    params.bitrate_bps = 30'000'000;
+    params.framerate = settings.m_refreshRate;
+    SetParams(params);
+
+    mapped_frame = map_frame(hw_ctx, drm_ctx, input_frame);
+    encoder_frame = av_frame_alloc();
+
+    filter_graph = avfilter_graph_alloc();
+
+    AVFilterInOut* outputs = avfilter_inout_alloc();
+    AVFilterInOut* inputs = avfilter_inout_alloc();
+
+    std::stringstream buffer_filter_args;
+    buffer_filter_args << "video_size=" << mapped_frame->width << "x" << mapped_frame->height;
+    buffer_filter_args << ":pix_fmt=" << mapped_frame->format;
+    buffer_filter_args << ":time_base=" << encoder_ctx->time_base.num << "/"
+                       << encoder_ctx->time_base.den;
+    if ((err = avfilter_graph_create_filter(
+             &filter_in,
+             avfilter_get_by_name("buffer"),
+             "in",
+             buffer_filter_args.str().c_str(),
+             NULL,
+             filter_graph
+         ))) {
+        throw alvr::AvException("filter_in creation failed:", err);
+    }
+    AVBufferSrcParameters* par = av_buffersrc_parameters_alloc();
+    memset(par, 0, sizeof(*par));
+    par->format = AV_PIX_FMT_NONE;
+    par->hw_frames_ctx = av_buffer_ref(mapped_frame->hw_frames_ctx);
+    av_buffersrc_parameters_set(filter_in, par);
+    av_free(par);
+
+    if ((err = avfilter_graph_create_filter(
+             &filter_out, avfilter_get_by_name("buffersink"), "out", NULL, NULL, filter_graph
+         ))) {
+        throw alvr::AvException("filter_out creation failed:", err);
+    }
+
+    outputs->name = av_strdup("in");
+    outputs->filter_ctx = filter_in;
+    outputs->pad_idx = 0;
+    outputs->next = NULL;
+
+    inputs->name = av_strdup("out");
+    inputs->filter_ctx = filter_out;
+    inputs->pad_idx = 0;
+    inputs->next = NULL;
+
+    std::string filters
+        = "scale_vulkan=format=nv12:out_range=" + std::string(filter_color_range());
+    if ((err = avfilter_graph_parse_ptr(filter_graph, filters.c_str(), &inputs, &outputs, NULL))
+        < 0) {
+        throw alvr::AvException("avfilter_graph_parse_ptr failed:", err);
+    }
+
+    avfilter_inout_free(&outputs);
+    avfilter_inout_free(&inputs);
+
+    for (unsigned i = 0; i < filter_graph->nb_filters; ++i) {
+        filter_graph->filters[i]->hw_device_ctx = av_buffer_ref(hw_ctx);
+    }
+
+    if ((err = avfilter_graph_config(filter_graph, NULL))) {
+        throw alvr::AvException("avfilter_graph_config failed:", err);
+    }
+
+    // The encoder consumes the frames produced by the filter, so it shares its frame context
+    encoder_ctx->hw_frames_ctx = av_buffer_ref(av_buffersink_get_hw_frames_ctx(filter_out));
+
+    err = avcodec_open2(encoder_ctx, codec, NULL);
+    if (err < 0) {
+        throw alvr::AvException("Cannot open video encoder codec:", err);
+    }
+}
+
+alvr::EncodePipelineVulkan::~EncodePipelineVulkan() {
+    avfilter_graph_free(&filter_graph);
+    av_frame_free(&mapped_frame);
+    av_frame_free(&encoder_frame);
+    av_buffer_unref(&hw_ctx);
+    av_buffer_unref(&drm_ctx);
+}
+
+void alvr::EncodePipelineVulkan::PushFrame(uint64_t targetTimestampNs, bool idr) {
+    r->Sync();
+    timestamp.cpu = std::chrono::duration_cast<std::chrono::nanoseconds>(
+                        std::chrono::steady_clock::now().time_since_epoch()
+    )
+                        .count();
+    int err = av_buffersrc_add_frame_flags(
+        filter_in, mapped_frame, AV_BUFFERSRC_FLAG_PUSH | AV_BUFFERSRC_FLAG_KEEP_REF
+    );
+    if (err != 0) {
+        throw alvr::AvException("av_buffersrc_add_frame failed", err);
+    }
+    err = av_buffersink_get_frame(filter_out, encoder_frame);
+    if (err != 0) {
+        throw alvr::AvException("av_buffersink_get_frame failed", err);
+    }
+
+    encoder_frame->pict_type = idr ? AV_PICTURE_TYPE_I : AV_PICTURE_TYPE_NONE;
+    encoder_frame->pts = targetTimestampNs;
+
+    if ((err = avcodec_send_frame(encoder_ctx, encoder_frame)) < 0) {
+        throw alvr::AvException("avcodec_send_frame failed: ", err);
+    }
+    av_frame_unref(encoder_frame);
+}
+
+void alvr::EncodePipelineVulkan::SetParams(FfiDynamicEncoderParams params) {
+    if (!params.updated) {
+        return;
+    }
+    encoder_ctx->bit_rate = params.bitrate_bps;
+    encoder_ctx->framerate = AVRational { int(params.framerate * 1000), 1000 };
+    encoder_ctx->rc_buffer_size = encoder_ctx->bit_rate / params.framerate;
+    encoder_ctx->rc_max_rate = encoder_ctx->bit_rate;
+    encoder_ctx->rc_initial_buffer_occupancy = encoder_ctx->rc_buffer_size;
+}
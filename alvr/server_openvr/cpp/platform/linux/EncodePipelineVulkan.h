@@ -0,0 +1,40 @@
+#pragma once
+
+#include "EncodePipeline.h"
+
+extern "C" struct AVBufferRef;
+extern "C" struct AVCodecContext;
+extern "C" struct AVFilterContext;
+extern "C" struct AVFilterGraph;
+extern "C" struct AVFrame;
+
+class Renderer;
+
+namespace alvr {
+
+class EncodePipelineVulkan : public EncodePipeline {
+public:
+    ~EncodePipelineVulkan();
+    EncodePipelineVulkan(
+        Renderer* render,
+        VkContext& vk_ctx,
+        VkFrame& input_frame,
+        uint32_t width,
+        uint32_t height,
+        const FfiEncoderPresets& presets
+    );
+
+    void PushFrame(uint64_t targetTimestampNs, bool idr) override;
+    void SetParams(FfiDynamicEncoderParams params) override;
+
+private:
+    Renderer* r = nullptr;
+    AVBufferRef* hw_ctx = nullptr;
+    AVBufferRef* drm_ctx = nullptr;
+    AVFrame* mapped_frame = nullptr;
+    AVFrame* encoder_frame = nullptr;
+    AVFilterGraph* filter_graph = nullptr;
+    AVFilterContext* filter_in = nullptr;
+    AVFilterContext* filter_out = nullptr;
+};
+}
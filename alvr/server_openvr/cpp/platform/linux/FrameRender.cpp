@@ -3,6 +3,7 @@
 #include "alvr_server/Settings.h"
 #include "alvr_server/bindings.h"
 
+#include <algorithm>
 #include <filesystem>
 #include <fstream>
 
@@ -32,14 +33,6 @@ FrameRender::FrameRender(alvr::VkContext& ctx, init_packet& init, int fds[])
 
     Info("FrameRender: Input size %ux%u", m_width, m_height);
 
-    if (Settings::Instance().m_force_sw_encoding) {
-        m_handle = ExternalHandle::None;
-    } else if (ctx.amd || ctx.intel) {
-        m_handle = ExternalHandle::DmaBuf;
-    } else if (ctx.nvidia) {
-        m_handle = ExternalHandle::OpaqueFd;
-    }
-
     setupCustomShaders("pre");
 
     if (Settings::Instance().m_enableColorCorrection) {
@@ -68,8 +61,8 @@ FrameRender::~FrameRender() {
     }
 }
 
-FrameRender::Output FrameRender::CreateOutput() {
-    Renderer::CreateOutput(m_width, m_height, m_handle);
+FrameRender::Output FrameRender::CreateOutput(ExternalHandle handle) {
+    Renderer::CreateOutput(m_width, m_height, handle);
     return GetOutput();
 }
 
@@ -102,17 +95,30 @@ void FrameRender::setupColorCorrection() {
     AddPipeline(pipeline);
 }
 
-void FrameRender::setupFoveatedRendering() {
-    float targetEyeWidth = (float)m_width / 2;
-    float targetEyeHeight = (float)m_height;
-
-    float centerSizeX = (float)Settings::Instance().m_foveationCenterSizeX;
-    float centerSizeY = (float)Settings::Instance().m_foveationCenterSizeY;
-    float centerShiftX = (float)Settings::Instance().m_foveationCenterShiftX;
-    float centerShiftY = (float)Settings::Instance().m_foveationCenterShiftY;
-    float edgeRatioX = (float)Settings::Instance().m_foveationEdgeRatioX;
-    float edgeRatioY = (float)Settings::Instance().m_foveationEdgeRatioY;
-
+namespace {
+// Parameters of one eye, aligned to the pixel grid by the two zones mode
+struct EyeFoveation {
+    float centerSizeX;
+    float centerSizeY;
+    float centerShiftX;
+    float centerShiftY;
+    float edgeRatioX;
+    float edgeRatioY;
+    // Size of the compressed view before the alignment for the encoder
+    float optimizedEyeWidth;
+    float optimizedEyeHeight;
+};
+
+EyeFoveation calculateEyeFoveation(
+    float targetEyeWidth,
+    float targetEyeHeight,
+    float centerSizeX,
+    float centerSizeY,
+    float centerShiftX,
+    float centerShiftY,
+    float edgeRatioX,
+    float edgeRatioY
+) {
     float edgeSizeX = targetEyeWidth - centerSizeX * targetEyeWidth;
     float edgeSizeY = targetEyeHeight - centerSizeY * targetEyeHeight;
 
@@ -132,16 +138,61 @@ void FrameRender::setupFoveatedRendering() {
     float foveationScaleX = (centerSizeXAligned + (1. - centerSizeXAligned) / edgeRatioX);
     float foveationScaleY = (centerSizeYAligned + (1. - centerSizeYAligned) / edgeRatioY);
 
-    float optimizedEyeWidth = foveationScaleX * targetEyeWidth;
-    float optimizedEyeHeight = foveationScaleY * targetEyeHeight;
+    return { centerSizeXAligned,
+             centerSizeYAligned,
+             centerShiftXAligned,
+             centerShiftYAligned,
+             edgeRatioX,
+             edgeRatioY,
+             foveationScaleX * targetEyeWidth,
+             foveationScaleY * targetEyeHeight };
+}
+}
+
+void FrameRender::setupFoveatedRendering() {
+    auto& settings = Settings::Instance();
+
+    float targetEyeWidth = (float)m_width / 2;
+    float targetEyeHeight = (float)m_height;
+
+    bool radial = settings.m_foveationMode == ALVR_FOVEATION_MODE_RADIAL;
+    float radialAlpha = 2. / (settings.m_foveationEdgeCompression + 1.);
+    float radialScale = settings.m_foveationCenterStrength * radialAlpha;
+
+    EyeFoveation left = calculateEyeFoveation(
+        targetEyeWidth,
+        targetEyeHeight,
+        settings.m_foveationCenterSizeX,
+        settings.m_foveationCenterSizeY,
+        settings.m_foveationCenterShiftX,
+        settings.m_foveationCenterShiftY,
+        settings.m_foveationEdgeRatioX,
+        settings.m_foveationEdgeRatioY
+    );
+    EyeFoveation right = calculateEyeFoveation(
+        targetEyeWidth,
+        targetEyeHeight,
+        settings.m_foveationRightCenterSizeX,
+        settings.m_foveationRightCenterSizeY,
+        settings.m_foveationRightCenterShiftX,
+        settings.m_foveationRightCenterShiftY,
+        settings.m_foveationRightEdgeRatioX,
+        settings.m_foveationRightEdgeRatioY
+    );
+    if (radial) {
+        for (auto eye : { &left, &right }) {
+            eye->optimizedEyeWidth = radialScale * targetEyeWidth;
+            eye->optimizedEyeHeight = radialScale * targetEyeHeight;
+        }
+    }
 
-    // round the frame dimensions to a number of pixel multiple of 32 for the encoder
+    // The halves of the frame have the size of the largest compressed view, the other one is
+    // padded. Round the frame dimensions to a number of pixel multiple of 32 for the encoder
+    float optimizedEyeWidth = std::max(left.optimizedEyeWidth, right.optimizedEyeWidth);
+    float optimizedEyeHeight = std::max(left.optimizedEyeHeight, right.optimizedEyeHeight);
     auto optimizedEyeWidthAligned = (uint32_t)ceil(optimizedEyeWidth / 32.f) * 32;
     auto optimizedEyeHeightAligned = (uint32_t)ceil(optimizedEyeHeight / 32.f) * 32;
 
-    float eyeWidthRatioAligned = optimizedEyeWidth / optimizedEyeWidthAligned;
-    float eyeHeightRatioAligned = optimizedEyeHeight / optimizedEyeHeightAligned;
-
     m_width = optimizedEyeWidthAligned * 2;
     m_height = optimizedEyeHeightAligned;
 
@@ -153,14 +204,30 @@ void FrameRender::setupFoveatedRendering() {
         { (uint32_t)entries.size(), offsetof(FoveationVars, x), sizeof(FoveationVars::x) }         \
     );
 
-    ENTRY(eyeWidthRatio, eyeWidthRatioAligned);
-    ENTRY(eyeHeightRatio, eyeHeightRatioAligned);
-    ENTRY(centerSizeX, centerSizeXAligned);
-    ENTRY(centerSizeY, centerSizeYAligned);
-    ENTRY(centerShiftX, centerShiftXAligned);
-    ENTRY(centerShiftY, centerShiftYAligned);
-    ENTRY(edgeRatioX, edgeRatioX);
-    ENTRY(edgeRatioY, edgeRatioY);
+    ENTRY(eyeWidthRatio, left.optimizedEyeWidth / optimizedEyeWidthAligned);
+    ENTRY(eyeHeightRatio, left.optimizedEyeHeight / optimizedEyeHeightAligned);
+    ENTRY(centerSizeX, left.centerSizeX);
+    ENTRY(centerSizeY, left.centerSizeY);
+    ENTRY(centerShiftX, left.centerShiftX);
+    ENTRY(centerShiftY, left.centerShiftY);
+    ENTRY(edgeRatioX, left.edgeRatioX);
+    ENTRY(edgeRatioY, left.edgeRatioY);
+    ENTRY(edgePreservationStrength, settings.m_foveationEdgePreservationStrength);
+    ENTRY(mode, (int32_t)settings.m_foveationMode);
+    ENTRY(radialAlpha, radialAlpha);
+    ENTRY(radialScale, radialScale);
+    ENTRY(radialCenterX, .5f + settings.m_foveationCenterShiftX * .25f);
+    ENTRY(radialCenterY, .5f + settings.m_foveationCenterShiftY * .25f);
+    ENTRY(rightEyeWidthRatio, right.optimizedEyeWidth / optimizedEyeWidthAligned);
+    ENTRY(rightEyeHeightRatio, right.optimizedEyeHeight / optimizedEyeHeightAligned);
+    ENTRY(rightCenterSizeX, right.centerSizeX);
+    ENTRY(rightCenterSizeY, right.centerSizeY);
+    ENTRY(rightCenterShiftX, right.centerShiftX);
+    ENTRY(rightCenterShiftY, right.centerShiftY);
+    ENTRY(rightEdgeRatioX, right.edgeRatioX);
+    ENTRY(rightEdgeRatioY, right.edgeRatioY);
+    ENTRY(rightRadialCenterX, .5f + settings.m_foveationRightCenterShiftX * .25f);
+    ENTRY(rightRadialCenterY, .5f + settings.m_foveationRightCenterShiftY * .25f);
 #undef ENTRY
 
     RenderPipeline* pipeline = new RenderPipeline(this);
@@ -9,7 +9,8 @@ public:
     explicit FrameRender(alvr::VkContext& ctx, init_packet& init, int fds[]);
     ~FrameRender();
 
-    Output CreateOutput();
+    // The output memory is exported with the handle type imported by the encoder
+    Output CreateOutput(ExternalHandle handle);
     uint32_t GetEncodingWidth() const;
     uint32_t GetEncodingHeight() const;
 
@@ -33,6 +34,22 @@ private:
         float centerShiftY;
         float edgeRatioX;
         float edgeRatioY;
+        float edgePreservationStrength;
+        int32_t mode;
+        float radialAlpha;
+        float radialScale;
+        float radialCenterX;
+        float radialCenterY;
+        float rightEyeWidthRatio;
+        float rightEyeHeightRatio;
+        float rightCenterSizeX;
+        float rightCenterSizeY;
+        float rightCenterShiftX;
+        float rightCenterShiftY;
+        float rightEdgeRatioX;
+        float rightEdgeRatioY;
+        float rightRadialCenterX;
+        float rightRadialCenterY;
     };
 
     void setupColorCorrection();
@@ -41,7 +58,6 @@ private:
 
     uint32_t m_width;
     uint32_t m_height;
-    ExternalHandle m_handle = ExternalHandle::None;
     ColorCorrection m_colorCorrectionConstants;
     FoveationVars m_foveatedRenderingConstants;
     std::vector<RenderPipeline*> m_pipelines;
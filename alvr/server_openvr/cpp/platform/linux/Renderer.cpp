@@ -1,4 +1,5 @@
 #include "Renderer.h"
+#include "alvr_server/bindings.h"
 
 #include <algorithm>
 #include <array>
@@ -6,6 +7,7 @@
 #include <cstring>
 #include <fstream>
 #include <iostream>
+#include <unistd.h>
 
 #ifndef DRM_FORMAT_INVALID
 #define DRM_FORMAT_INVALID 0
@@ -108,10 +110,13 @@ Renderer::~Renderer() {
         vkFreeMemory(m_dev, image.memory, nullptr);
     }
 
-    vkDestroyImageView(m_dev, m_output.view, nullptr);
-    vkDestroyImage(m_dev, m_output.image, nullptr);
-    vkFreeMemory(m_dev, m_output.memory, nullptr);
-    vkDestroySemaphore(m_dev, m_output.semaphore, nullptr);
+    vkDestroyImageView(m_dev, m_testPatternImage.view, nullptr);
+    vkDestroyImage(m_dev, m_testPatternImage.image, nullptr);
+    vkFreeMemory(m_dev, m_testPatternImage.memory, nullptr);
+    vkDestroyBuffer(m_dev, m_testPatternBuffer, nullptr);
+    vkFreeMemory(m_dev, m_testPatternBufferMemory, nullptr);
+
+    DestroyOutput();
 
     vkDestroyQueryPool(m_dev, m_queryPool, nullptr);
     vkDestroyCommandPool(m_dev, m_commandPool, nullptr);
@@ -271,6 +276,8 @@ void Renderer::AddPipeline(RenderPipeline* pipeline) {
 }
 
 void Renderer::CreateOutput(uint32_t width, uint32_t height, ExternalHandle handle) {
+    DestroyOutput();
+
     m_output.imageInfo = {};
     m_output.imageInfo.sType = VK_STRUCTURE_TYPE_IMAGE_CREATE_INFO;
     m_output.imageInfo.imageType = VK_IMAGE_TYPE_2D;
@@ -478,6 +485,20 @@ void Renderer::CreateOutput(uint32_t width, uint32_t height, ExternalHandle hand
     VK_CHECK(vkCreateSemaphore(m_dev, &semInfo, nullptr, &m_output.semaphore));
 }
 
+void Renderer::DestroyOutput() {
+    vkDeviceWaitIdle(m_dev);
+
+    vkDestroyImageView(m_dev, m_output.view, nullptr);
+    vkDestroyImage(m_dev, m_output.image, nullptr);
+    vkFreeMemory(m_dev, m_output.memory, nullptr);
+    vkDestroySemaphore(m_dev, m_output.semaphore, nullptr);
+    if (m_output.drm.fd >= 0) {
+        close(m_output.drm.fd);
+    }
+
+    m_output = {};
+}
+
 void Renderer::ImportOutput(const DrmImage& drm) {
     vkDestroyImageView(m_dev, m_output.view, nullptr);
     vkDestroyImage(m_dev, m_output.image, nullptr);
@@ -564,7 +585,7 @@ void Renderer::ImportOutput(const DrmImage& drm) {
     VK_CHECK(vkCreateImageView(m_dev, &viewInfo, nullptr, &m_output.view));
 }
 
-void Renderer::Render(uint32_t index, uint64_t waitValue) {
+void Renderer::Render(uint32_t index, uint64_t waitValue, bool testPattern) {
     if (!m_inputImageCapture.empty()) {
         VkSemaphoreWaitInfo waitInfo = {};
         waitInfo.sType = VK_STRUCTURE_TYPE_SEMAPHORE_WAIT_INFO;
@@ -591,6 +612,10 @@ void Renderer::Render(uint32_t index, uint64_t waitValue) {
     vkCmdResetQueryPool(m_commandBuffer, m_queryPool, 0, 2);
     vkCmdWriteTimestamp(m_commandBuffer, VK_PIPELINE_STAGE_TOP_OF_PIPE_BIT, m_queryPool, 0);
 
+    if (testPattern) {
+        recordTestPatternUpload();
+    }
+
     for (size_t i = 0; i < m_pipelines.size(); ++i) {
         VkRect2D rect = {};
         VkImage in = VK_NULL_HANDLE;
@@ -599,7 +624,11 @@ void Renderer::Render(uint32_t index, uint64_t waitValue) {
         VkImage out = VK_NULL_HANDLE;
         VkImageView outView = VK_NULL_HANDLE;
         VkImageLayout* outLayout = nullptr;
-        if (i == 0) {
+        if (i == 0 && testPattern) {
+            in = m_testPatternImage.image;
+            inView = m_testPatternImage.view;
+            inLayout = &m_testPatternImage.layout;
+        } else if (i == 0) {
             auto& img = m_images[index];
             in = img.image;
             inView = img.view;
@@ -864,6 +893,135 @@ void Renderer::addStagingImage(uint32_t width, uint32_t height) {
     m_stagingImages.push_back({ image, VK_IMAGE_LAYOUT_UNDEFINED, memory, view });
 }
 
+void Renderer::createTestPattern() {
+    VkImageCreateInfo imageInfo = {};
+    imageInfo.sType = VK_STRUCTURE_TYPE_IMAGE_CREATE_INFO;
+    imageInfo.imageType = VK_IMAGE_TYPE_2D;
+    imageInfo.format = VK_FORMAT_R8G8B8A8_UNORM;
+    imageInfo.extent.width = m_imageSize.width;
+    imageInfo.extent.height = m_imageSize.height;
+    imageInfo.extent.depth = 1;
+    imageInfo.mipLevels = 1;
+    imageInfo.arrayLayers = 1;
+    imageInfo.samples = VK_SAMPLE_COUNT_1_BIT;
+    imageInfo.tiling = VK_IMAGE_TILING_OPTIMAL;
+    imageInfo.usage = VK_IMAGE_USAGE_TRANSFER_DST_BIT | VK_IMAGE_USAGE_SAMPLED_BIT;
+    imageInfo.sharingMode = VK_SHARING_MODE_EXCLUSIVE;
+    imageInfo.initialLayout = VK_IMAGE_LAYOUT_UNDEFINED;
+    VK_CHECK(vkCreateImage(m_dev, &imageInfo, nullptr, &m_testPatternImage.image));
+
+    VkMemoryRequirements memoryReqs;
+    vkGetImageMemoryRequirements(m_dev, m_testPatternImage.image, &memoryReqs);
+    VkMemoryAllocateInfo memoryAllocInfo = {};
+    memoryAllocInfo.sType = VK_STRUCTURE_TYPE_MEMORY_ALLOCATE_INFO;
+    memoryAllocInfo.allocationSize = memoryReqs.size;
+    memoryAllocInfo.memoryTypeIndex
+        = memoryTypeIndex(VK_MEMORY_PROPERTY_DEVICE_LOCAL_BIT, memoryReqs.memoryTypeBits);
+    VK_CHECK(vkAllocateMemory(m_dev, &memoryAllocInfo, nullptr, &m_testPatternImage.memory));
+    VK_CHECK(vkBindImageMemory(m_dev, m_testPatternImage.image, m_testPatternImage.memory, 0));
+
+    VkImageViewCreateInfo viewInfo = {};
+    viewInfo.sType = VK_STRUCTURE_TYPE_IMAGE_VIEW_CREATE_INFO;
+    viewInfo.viewType = VK_IMAGE_VIEW_TYPE_2D;
+    viewInfo.format = imageInfo.format;
+    viewInfo.image = m_testPatternImage.image;
+    viewInfo.subresourceRange.aspectMask = VK_IMAGE_ASPECT_COLOR_BIT;
+    viewInfo.subresourceRange.levelCount = 1;
+    viewInfo.subresourceRange.layerCount = 1;
+    VK_CHECK(vkCreateImageView(m_dev, &viewInfo, nullptr, &m_testPatternImage.view));
+
+    VkBufferCreateInfo bufferInfo = {};
+    bufferInfo.sType = VK_STRUCTURE_TYPE_BUFFER_CREATE_INFO;
+    bufferInfo.size = m_imageSize.width * m_imageSize.height * 4;
+    bufferInfo.usage = VK_BUFFER_USAGE_TRANSFER_SRC_BIT;
+    bufferInfo.sharingMode = VK_SHARING_MODE_EXCLUSIVE;
+    VK_CHECK(vkCreateBuffer(m_dev, &bufferInfo, nullptr, &m_testPatternBuffer));
+
+    vkGetBufferMemoryRequirements(m_dev, m_testPatternBuffer, &memoryReqs);
+    memoryAllocInfo.allocationSize = memoryReqs.size;
+    memoryAllocInfo.memoryTypeIndex = memoryTypeIndex(
+        VK_MEMORY_PROPERTY_HOST_VISIBLE_BIT | VK_MEMORY_PROPERTY_HOST_COHERENT_BIT,
+        memoryReqs.memoryTypeBits
+    );
+    VK_CHECK(vkAllocateMemory(m_dev, &memoryAllocInfo, nullptr, &m_testPatternBufferMemory));
+    VK_CHECK(vkBindBufferMemory(m_dev, m_testPatternBuffer, m_testPatternBufferMemory, 0));
+    VK_CHECK(vkMapMemory(
+        m_dev, m_testPatternBufferMemory, 0, VK_WHOLE_SIZE, 0, (void**)&m_testPatternData
+    ));
+}
+
+// The encoder waits for the previous frame before the next one is rendered, so the buffer is not
+// in use by the GPU anymore
+void Renderer::recordTestPatternUpload() {
+    if (!m_testPatternData) {
+        createTestPattern();
+    }
+
+    // The input image contains both eyes side by side
+    uint32_t eyeWidth = m_imageSize.width / 2;
+    uint32_t rowPitch = m_imageSize.width * 4;
+    DrawTestPattern(m_testPatternData, eyeWidth, m_imageSize.height, rowPitch);
+    DrawTestPattern(
+        m_testPatternData + eyeWidth * 4, m_imageSize.width - eyeWidth, m_imageSize.height, rowPitch
+    );
+
+    VkImageMemoryBarrier imageBarrier = {};
+    imageBarrier.sType = VK_STRUCTURE_TYPE_IMAGE_MEMORY_BARRIER;
+    imageBarrier.image = m_testPatternImage.image;
+    imageBarrier.subresourceRange.aspectMask = VK_IMAGE_ASPECT_COLOR_BIT;
+    imageBarrier.subresourceRange.layerCount = 1;
+    imageBarrier.subresourceRange.levelCount = 1;
+    imageBarrier.oldLayout = VK_IMAGE_LAYOUT_UNDEFINED;
+    imageBarrier.newLayout = VK_IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL;
+    imageBarrier.srcAccessMask = 0;
+    imageBarrier.dstAccessMask = VK_ACCESS_TRANSFER_WRITE_BIT;
+    vkCmdPipelineBarrier(
+        m_commandBuffer,
+        VK_PIPELINE_STAGE_TOP_OF_PIPE_BIT,
+        VK_PIPELINE_STAGE_TRANSFER_BIT,
+        0,
+        0,
+        nullptr,
+        0,
+        nullptr,
+        1,
+        &imageBarrier
+    );
+
+    VkBufferImageCopy region = {};
+    region.imageSubresource.aspectMask = VK_IMAGE_ASPECT_COLOR_BIT;
+    region.imageSubresource.layerCount = 1;
+    region.imageExtent.width = m_imageSize.width;
+    region.imageExtent.height = m_imageSize.height;
+    region.imageExtent.depth = 1;
+    vkCmdCopyBufferToImage(
+        m_commandBuffer,
+        m_testPatternBuffer,
+        m_testPatternImage.image,
+        VK_IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL,
+        1,
+        &region
+    );
+
+    imageBarrier.oldLayout = VK_IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL;
+    imageBarrier.newLayout = VK_IMAGE_LAYOUT_SHADER_READ_ONLY_OPTIMAL;
+    imageBarrier.srcAccessMask = VK_ACCESS_TRANSFER_WRITE_BIT;
+    imageBarrier.dstAccessMask = VK_ACCESS_SHADER_READ_BIT;
+    vkCmdPipelineBarrier(
+        m_commandBuffer,
+        VK_PIPELINE_STAGE_TRANSFER_BIT,
+        VK_PIPELINE_STAGE_COMPUTE_SHADER_BIT,
+        0,
+        0,
+        nullptr,
+        0,
+        nullptr,
+        1,
+        &imageBarrier
+    );
+    m_testPatternImage.layout = VK_IMAGE_LAYOUT_SHADER_READ_ONLY_OPTIMAL;
+}
+
 void Renderer::dumpImage(
     VkImage image,
     VkImageView imageView,
@@ -68,10 +68,12 @@ public:
 
     void AddPipeline(RenderPipeline* pipeline);
 
+    // Replaces the previous output, if any
     void CreateOutput(uint32_t width, uint32_t height, ExternalHandle handle);
+    void DestroyOutput();
     void ImportOutput(const DrmImage& drm);
 
-    void Render(uint32_t index, uint64_t waitValue);
+    void Render(uint32_t index, uint64_t waitValue, bool testPattern = false);
 
     void Sync();
 
@@ -110,6 +112,8 @@ public:
         uint32_t height,
         const std::string& filename
     );
+    void createTestPattern();
+    void recordTestPatternUpload();
     uint32_t memoryTypeIndex(VkMemoryPropertyFlags properties, uint32_t typeBits) const;
 
     struct {
@@ -129,6 +133,10 @@ public:
     std::vector<InputImage> m_images;
     std::vector<StagingImage> m_stagingImages;
     std::vector<RenderPipeline*> m_pipelines;
+    StagingImage m_testPatternImage;
+    VkBuffer m_testPatternBuffer = VK_NULL_HANDLE;
+    VkDeviceMemory m_testPatternBufferMemory = VK_NULL_HANDLE;
+    uint8_t* m_testPatternData = nullptr;
 
     VkInstance m_inst = VK_NULL_HANDLE;
     VkDevice m_dev = VK_NULL_HANDLE;
@@ -6,7 +6,9 @@
 #include <sys/sysmacros.h>
 #include <unistd.h>
 
+#include "ALVR-common/packet_types.h"
 #include "alvr_server/Logger.h"
+#include "alvr_server/Settings.h"
 #include "alvr_server/bindings.h"
 
 extern "C" {
@@ -351,3 +353,17 @@ alvr::VkFrame::make_av_frame(VkFrameCtx& frame_ctx) {
 
     return frame;
 }
+
+void alvr::set_color_properties(AVCodecContext* encoder_ctx) {
+    const auto& settings = Settings::Instance();
+
+    encoder_ctx->color_range
+        = settings.m_colorRange == ALVR_COLOR_RANGE_FULL ? AVCOL_RANGE_JPEG : AVCOL_RANGE_MPEG;
+    encoder_ctx->color_trc = settings.m_transferFunction == ALVR_TRANSFER_FUNCTION_GAMMA22
+        ? AVCOL_TRC_GAMMA22
+        : AVCOL_TRC_IEC61966_2_1;
+}
+
+const char* alvr::filter_color_range() {
+    return Settings::Instance().m_colorRange == ALVR_COLOR_RANGE_FULL ? "full" : "limited";
+}
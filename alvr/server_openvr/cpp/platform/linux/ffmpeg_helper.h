@@ -96,4 +96,10 @@ private:
     AVPixelFormat avformat;
 };
 
+// Sets the color range and transfer function chosen in the settings
+void set_color_properties(AVCodecContext* encoder_ctx);
+
+// Value of the out_range option of the scale filters
+const char* filter_color_range();
+
 }
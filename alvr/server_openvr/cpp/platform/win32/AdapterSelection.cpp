@@ -0,0 +1,201 @@
+#include "AdapterSelection.h"
+#include "alvr_server/Logger.h"
+#include "alvr_server/Settings.h"
+
+#include <algorithm>
+#include <cwctype>
+#include <dxgi1_6.h>
+#include <wrl.h>
+
+#pragma comment(lib, "advapi32.lib")
+
+using Microsoft::WRL::ComPtr;
+
+namespace {
+bool operator==(const LUID& a, const LUID& b) {
+    return a.HighPart == b.HighPart && a.LowPart == b.LowPart;
+}
+
+std::wstring ToLower(std::wstring text) {
+    std::transform(text.begin(), text.end(), text.begin(), [](wchar_t c) {
+        return (wchar_t)std::towlower(c);
+    });
+    return text;
+}
+
+std::wstring Utf8ToWide(const std::string& text) {
+    int size = MultiByteToWideChar(CP_UTF8, 0, text.c_str(), (int)text.size(), nullptr, 0);
+    std::wstring wide(size, L'\0');
+    MultiByteToWideChar(CP_UTF8, 0, text.c_str(), (int)text.size(), wide.data(), size);
+    return wide;
+}
+
+// The per-app choice of the Windows graphics settings, stored as "GpuPreference=N;" where N is a
+// DXGI_GPU_PREFERENCE. vrcompositor.exe is next to vrserver.exe, which loads the driver
+DXGI_GPU_PREFERENCE GetCompositorGpuPreference() {
+    wchar_t path[MAX_PATH];
+    DWORD length = GetModuleFileNameW(nullptr, path, MAX_PATH);
+    if (length == 0 || length == MAX_PATH) {
+        return DXGI_GPU_PREFERENCE_UNSPECIFIED;
+    }
+    std::wstring compositorPath(path, length);
+    compositorPath = compositorPath.substr(0, compositorPath.find_last_of(L'\\') + 1)
+        + L"vrcompositor.exe";
+
+    wchar_t value[64];
+    DWORD size = sizeof(value);
+    if (RegGetValueW(
+            HKEY_CURRENT_USER,
+            L"Software\\Microsoft\\DirectX\\UserGpuPreferences",
+            compositorPath.c_str(),
+            RRF_RT_REG_SZ,
+            nullptr,
+            value,
+            &size
+        )
+        != ERROR_SUCCESS) {
+        return DXGI_GPU_PREFERENCE_UNSPECIFIED;
+    }
+
+    auto preference = wcsstr(value, L"GpuPreference=");
+    if (!preference) {
+        return DXGI_GPU_PREFERENCE_UNSPECIFIED;
+    }
+    switch (_wtoi(preference + wcslen(L"GpuPreference="))) {
+    case 1:
+        return DXGI_GPU_PREFERENCE_MINIMUM_POWER;
+    case 2:
+        return DXGI_GPU_PREFERENCE_HIGH_PERFORMANCE;
+    default:
+        return DXGI_GPU_PREFERENCE_UNSPECIFIED;
+    }
+}
+
+const AdapterInfo* FindAdapter(const std::vector<AdapterInfo>& adapters, uint32_t index) {
+    auto it = std::find_if(adapters.begin(), adapters.end(), [&](const AdapterInfo& info) {
+        return info.index == index;
+    });
+    return it != adapters.end() ? &*it : nullptr;
+}
+
+// vrcompositor uses the first adapter enumerated for its process, which follows its graphics
+// preference. Returns null if it can't be determined
+const AdapterInfo* FindCompositorAdapter(const std::vector<AdapterInfo>& adapters) {
+    auto preference = GetCompositorGpuPreference();
+    if (preference == DXGI_GPU_PREFERENCE_UNSPECIFIED) {
+        return FindAdapter(adapters, 0);
+    }
+
+    ComPtr<IDXGIFactory6> factory;
+    ComPtr<IDXGIAdapter1> adapter;
+    if (FAILED(CreateDXGIFactory1(__uuidof(IDXGIFactory6), (void**)&factory))
+        || FAILED(factory->EnumAdapterByGpuPreference(
+            0, preference, __uuidof(IDXGIAdapter1), (void**)&adapter
+        ))) {
+        return nullptr;
+    }
+    DXGI_ADAPTER_DESC1 desc;
+    adapter->GetDesc1(&desc);
+
+    for (auto& info : adapters) {
+        if (info.luid == desc.AdapterLuid) {
+            return &info;
+        }
+    }
+    return nullptr;
+}
+
+const AdapterInfo& FindSelectedAdapter(const std::vector<AdapterInfo>& adapters) {
+    auto& settings = Settings::Instance();
+
+    if (!settings.m_adapterName.empty()) {
+        auto name = ToLower(Utf8ToWide(settings.m_adapterName));
+        for (auto& info : adapters) {
+            if (ToLower(info.name).find(name) != std::wstring::npos) {
+                return info;
+            }
+        }
+        Warn(
+            "No adapter name contains \"%s\", using the adapter index %d",
+            settings.m_adapterName.c_str(),
+            settings.m_nAdapterIndex
+        );
+    }
+
+    auto info = FindAdapter(adapters, settings.m_nAdapterIndex);
+    if (!info) {
+        Warn(
+            "There is no adapter %d, using %ls", settings.m_nAdapterIndex, adapters[0].name.c_str()
+        );
+        return adapters[0];
+    }
+    return *info;
+}
+}
+
+std::vector<AdapterInfo> EnumerateAdapters() {
+    std::vector<AdapterInfo> adapters;
+
+    ComPtr<IDXGIFactory1> factory;
+    if (FAILED(CreateDXGIFactory1(__uuidof(IDXGIFactory1), (void**)&factory))) {
+        return adapters;
+    }
+
+    ComPtr<IDXGIAdapter1> adapter;
+    for (UINT idx = 0; factory->EnumAdapters1(idx, &adapter) != DXGI_ERROR_NOT_FOUND; idx++) {
+        DXGI_ADAPTER_DESC1 desc;
+        adapter->GetDesc1(&desc);
+
+        // The Microsoft Basic Render Driver can't encode
+        if (desc.Flags & DXGI_ADAPTER_FLAG_SOFTWARE) {
+            continue;
+        }
+
+        adapters.push_back({ idx, desc.Description, desc.AdapterLuid, desc.DedicatedVideoMemory });
+    }
+
+    return adapters;
+}
+
+AdapterTopology SelectAdapters() {
+    auto adapters = EnumerateAdapters();
+    if (adapters.empty()) {
+        throw MakeException("No graphics adapter found");
+    }
+    for (auto& info : adapters) {
+        Info(
+            "Adapter %d: %ls, %llu MB",
+            info.index,
+            info.name.c_str(),
+            info.videoMemory / (1024 * 1024)
+        );
+    }
+
+    auto& selected = FindSelectedAdapter(adapters);
+
+    auto compositorInfo = FindCompositorAdapter(adapters);
+    if (!compositorInfo) {
+        Warn("Could not find the adapter of SteamVR, using %ls", selected.name.c_str());
+        return { selected, selected };
+    }
+    auto& compositor = *compositorInfo;
+
+    if (compositor.index == selected.index) {
+        return { selected, selected };
+    }
+
+    if (Settings::Instance().m_crossAdapterEncoding) {
+        return { compositor, selected };
+    }
+
+    Warn(
+        "The adapter %d (%ls) is selected but SteamVR renders on the adapter %d (%ls), which is "
+        "used instead. Enable cross-adapter encoding to encode on %ls",
+        selected.index,
+        selected.name.c_str(),
+        compositor.index,
+        compositor.name.c_str(),
+        selected.name.c_str()
+    );
+    return { compositor, compositor };
+}
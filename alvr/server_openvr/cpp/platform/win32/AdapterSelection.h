@@ -0,0 +1,28 @@
+#pragma once
+
+#include <d3d11.h>
+#include <stdint.h>
+#include <string>
+#include <vector>
+
+struct AdapterInfo {
+    uint32_t index;
+    std::wstring name;
+    LUID luid;
+    uint64_t videoMemory;
+};
+
+// Adapters used for the composition of the layers and for the encoding. The composition must use
+// the adapter of vrcompositor, which opens the swap textures created by the driver.
+struct AdapterTopology {
+    AdapterInfo renderAdapter;
+    AdapterInfo encodeAdapter;
+
+    bool IsCrossAdapter() const { return renderAdapter.index != encodeAdapter.index; }
+};
+
+std::vector<AdapterInfo> EnumerateAdapters();
+
+// Resolves the adapters from the settings and the graphics preference of vrcompositor. Throws if
+// there are no adapters
+AdapterTopology SelectAdapters();
@@ -1,5 +1,18 @@
 #include "CEncoder.h"
 
+#include "alvr_server/bindings.h"
+
+namespace {
+
+// NVENC is the only encoder that supports the HEVC range extensions
+void WarnChromaSubsampling(const char* encoder) {
+    if (Settings::Instance().m_chromaSubsampling != ALVR_CHROMA_SUBSAMPLING_420) {
+        Warn("%s does not support 4:2:2 and 4:4:4 chroma subsampling, using 4:2:0", encoder);
+    }
+}
+
+}
+
 CEncoder::CEncoder()
     : m_bExiting(false)
     , m_targetTimestampNs(0) {
@@ -13,12 +26,37 @@ CEncoder::~CEncoder() {
     }
 }
 
-void CEncoder::Initialize(std::shared_ptr<CD3DRender> d3dRender) {
-    m_FrameRender = std::make_shared<FrameRender>(d3dRender);
+void CEncoder::Initialize(
+    std::shared_ptr<CD3DRender> renderD3DRender, std::shared_ptr<CD3DRender> encodeD3DRender
+) {
+    m_FrameRender = std::make_shared<FrameRender>(renderD3DRender);
     m_FrameRender->Startup();
+    m_mirrorCapture = std::make_unique<MirrorCapture>(renderD3DRender);
+
+    if (Settings::Instance().m_enableSpaceWarp) {
+        try {
+            m_motionEstimation = std::make_unique<MotionEstimation>(
+                renderD3DRender,
+                Settings::Instance().m_renderWidth,
+                Settings::Instance().m_renderHeight
+            );
+        } catch (Exception e) {
+            Error("Space warp is not available, the motion cannot be estimated: %s", e.what());
+        }
+    }
+
+    auto d3dRender = renderD3DRender;
+    if (encodeD3DRender) {
+        m_crossAdapterCopy = std::make_unique<CrossAdapterCopy>(renderD3DRender, encodeD3DRender);
+        d3dRender = encodeD3DRender;
+    }
+
     uint32_t encoderWidth, encoderHeight;
     m_FrameRender->GetEncodingResolution(&encoderWidth, &encoderHeight);
 
+    // Overridden by the encoders that support intra refresh
+    SetIntraRefreshPeriod(0);
+
     Exception vplException;
     Exception vceException;
     Exception nvencException;
@@ -29,8 +67,9 @@ void CEncoder::Initialize(std::shared_ptr<CD3DRender> d3dRender) {
         try {
             Debug("Try to use VideoEncoderSW.\n");
             m_videoEncoder
-                = std::make_shared<VideoEncoderSW>(d3dRender, encoderWidth, encoderHeight);
+                = std::make_shared<VideoEncoderSW>(d3dRender, encoderWidth, encoderHeight, false);
             m_videoEncoder->Initialize();
+            WarnChromaSubsampling("The software encoder");
             return;
         } catch (Exception e) {
             swException = e;
@@ -42,6 +81,7 @@ void CEncoder::Initialize(std::shared_ptr<CD3DRender> d3dRender) {
         Debug("Try to use VideoEncoderAMF.\n");
         m_videoEncoder = std::make_shared<VideoEncoderAMF>(d3dRender, encoderWidth, encoderHeight);
         m_videoEncoder->Initialize();
+        WarnChromaSubsampling("AMF");
         return;
     } catch (Exception e) {
         vceException = e;
@@ -59,6 +99,7 @@ void CEncoder::Initialize(std::shared_ptr<CD3DRender> d3dRender) {
         Debug("Try to use VideoEncoderVPL.\n");
         m_videoEncoder = std::make_shared<VideoEncoderVPL>(d3dRender, encoderWidth, encoderHeight);
         m_videoEncoder->Initialize();
+        WarnChromaSubsampling("VPL");
         return;
     } catch (Exception e) {
         vplException = e;
@@ -66,8 +107,10 @@ void CEncoder::Initialize(std::shared_ptr<CD3DRender> d3dRender) {
 #ifdef ALVR_GPL
     try {
         Debug("Try to use VideoEncoderSW.\n");
-        m_videoEncoder = std::make_shared<VideoEncoderSW>(d3dRender, encoderWidth, encoderHeight);
+        m_videoEncoder
+            = std::make_shared<VideoEncoderSW>(d3dRender, encoderWidth, encoderHeight, true);
         m_videoEncoder->Initialize();
+        WarnChromaSubsampling("The software encoder");
         return;
     } catch (Exception e) {
         swException = e;
@@ -102,6 +145,7 @@ bool CEncoder::CopyToStaging(
     ID3D11Texture2D* pTexture[][2],
     vr::VRTextureBounds_t bounds[][2],
     vr::HmdMatrix34_t poses[],
+    const vr::HmdMatrix34_t& targetPose,
     int layerCount,
     bool recentering,
     uint64_t presentationTime,
@@ -114,8 +158,18 @@ bool CEncoder::CopyToStaging(
     m_FrameRender->Startup();
 
     m_FrameRender->RenderFrame(
-        pTexture, bounds, poses, layerCount, recentering, message, debugText
+        pTexture,
+        bounds,
+        poses,
+        targetPose,
+        layerCount,
+        recentering,
+        m_testPattern,
+        message,
+        debugText
     );
+    m_mirrorCapture->Capture(m_FrameRender->GetCompositionTexture().Get());
+
     return true;
 }
 
@@ -128,13 +182,26 @@ void CEncoder::Run() {
         if (m_bExiting)
             break;
 
-        if (m_FrameRender->GetTexture()) {
-            m_videoEncoder->Transmit(
-                m_FrameRender->GetTexture().Get(),
-                m_presentationTime,
-                m_targetTimestampNs,
-                m_scheduler.CheckIDRInsertion()
-            );
+        ID3D11Texture2D* texture = m_FrameRender->GetTexture().Get();
+        if (texture && !SkipPausedFrame() && !SkipIdleFrame()) {
+            if (m_crossAdapterCopy) {
+                texture = m_crossAdapterCopy->Copy(texture);
+            }
+            if (texture) {
+                m_videoEncoder->Transmit(
+                    texture,
+                    m_presentationTime,
+                    m_targetTimestampNs,
+                    m_scheduler.CheckIDRInsertion()
+                );
+
+                // After the frame is handed to the encoder, so that the estimation does not delay it
+                if (m_motionEstimation) {
+                    m_motionEstimation->Estimate(
+                        m_FrameRender->GetCompositionTexture().Get(), m_targetTimestampNs
+                    );
+                }
+            }
         }
 
         m_encodeFinished.Set();
@@ -160,3 +227,51 @@ void CEncoder::OnStreamStart() { m_scheduler.OnStreamStart(); }
 void CEncoder::InsertIDR() { m_scheduler.InsertIDR(); }
 
 void CEncoder::CaptureFrame() { }
+
+void CEncoder::SetTestPattern(bool enabled) { m_testPattern = enabled; }
+
+void CEncoder::SetMirrorEnabled(bool enabled) {
+    if (m_mirrorCapture) {
+        m_mirrorCapture->SetEnabled(enabled);
+    }
+}
+
+void CEncoder::SetPaused(bool paused, bool keepaliveFrames) {
+    m_keepaliveFrames = keepaliveFrames;
+    m_paused = paused;
+}
+
+bool CEncoder::SkipPausedFrame() {
+    if (!m_paused) {
+        return false;
+    }
+    if (!m_keepaliveFrames) {
+        return true;
+    }
+
+    auto now = std::chrono::steady_clock::now();
+    if (now - m_lastKeepaliveFrame < std::chrono::seconds(1)) {
+        return true;
+    }
+    m_lastKeepaliveFrame = now;
+
+    return false;
+}
+
+void CEncoder::SetIdleFrameRate(float fps) { m_idleFps = fps; }
+
+// The client keeps showing the last frame in between
+bool CEncoder::SkipIdleFrame() {
+    float fps = m_idleFps;
+    if (fps <= 0.f) {
+        return false;
+    }
+
+    auto now = std::chrono::steady_clock::now();
+    if (now - m_lastIdleFrame < std::chrono::duration<float>(1.f / fps)) {
+        return true;
+    }
+    m_lastIdleFrame = now;
+
+    return false;
+}
@@ -3,12 +3,17 @@
 
 #include "shared/threadtools.h"
 
+#include "CrossAdapterCopy.h"
 #include "FrameRender.h"
+#include "MirrorCapture.h"
+#include "MotionEstimation.h"
 #include "VideoEncoder.h"
 #include "VideoEncoderAMF.h"
 #include "VideoEncoderNVENC.h"
 #include "VideoEncoderVPL.h"
 #include "alvr_server/Utils.h"
+#include <atomic>
+#include <chrono>
 #include <d3d11.h>
 #include <d3d11_1.h>
 #include <map>
@@ -32,7 +37,10 @@ public:
     CEncoder();
     ~CEncoder();
 
-    void Initialize(std::shared_ptr<CD3DRender> d3dRender);
+    // encodeD3DRender is null if the encoder uses the adapter of SteamVR
+    void Initialize(
+        std::shared_ptr<CD3DRender> d3dRender, std::shared_ptr<CD3DRender> encodeD3DRender
+    );
 
     void SetViewParams(
         vr::HmdRect2_t projLeft,
@@ -45,6 +53,7 @@ public:
         ID3D11Texture2D* pTexture[][2],
         vr::VRTextureBounds_t bounds[][2],
         vr::HmdMatrix34_t poses[],
+        const vr::HmdMatrix34_t& targetPose,
         int layerCount,
         bool recentering,
         uint64_t presentationTime,
@@ -67,14 +76,33 @@ public:
 
     void CaptureFrame();
 
+    void SetTestPattern(bool enabled);
+    void SetMirrorEnabled(bool enabled);
+    void SetPaused(bool paused, bool keepaliveFrames);
+    void SetIdleFrameRate(float fps);
+
 private:
     CThreadEvent m_newFrameReady, m_encodeFinished;
     std::shared_ptr<VideoEncoder> m_videoEncoder;
     bool m_bExiting;
     uint64_t m_presentationTime;
     uint64_t m_targetTimestampNs;
+    std::atomic_bool m_testPattern = false;
+    std::atomic_bool m_paused = false;
+    std::atomic_bool m_keepaliveFrames = false;
+    std::chrono::steady_clock::time_point m_lastKeepaliveFrame;
+    // 0 if not idle
+    std::atomic<float> m_idleFps = 0.f;
+    std::chrono::steady_clock::time_point m_lastIdleFrame;
+
+    bool SkipPausedFrame();
+    bool SkipIdleFrame();
 
     std::shared_ptr<FrameRender> m_FrameRender;
+    std::unique_ptr<MirrorCapture> m_mirrorCapture;
+    // Set only with space warp
+    std::unique_ptr<MotionEstimation> m_motionEstimation;
+    std::unique_ptr<CrossAdapterCopy> m_crossAdapterCopy;
 
     IDRScheduler m_scheduler;
 };
@@ -0,0 +1,109 @@
+#include "CrossAdapterCopy.h"
+#include "alvr_server/Logger.h"
+#include "alvr_server/Utils.h"
+
+#include <algorithm>
+#include <cstring>
+
+CrossAdapterCopy::CrossAdapterCopy(
+    std::shared_ptr<CD3DRender> source, std::shared_ptr<CD3DRender> destination
+)
+    : m_source(source)
+    , m_destination(destination) { }
+
+ID3D11Texture2D* CrossAdapterCopy::Copy(ID3D11Texture2D* pTexture) {
+    D3D11_TEXTURE2D_DESC desc;
+    pTexture->GetDesc(&desc);
+    if (!m_outputTexture || desc.Width != m_desc.Width || desc.Height != m_desc.Height
+        || desc.Format != m_desc.Format) {
+        if (!CreateTextures(desc)) {
+            return nullptr;
+        }
+    }
+
+    auto sourceContext = m_source->GetContext();
+    auto destinationContext = m_destination->GetContext();
+
+    sourceContext->CopyResource(m_readbackTexture.Get(), pTexture);
+
+    // The map waits for the composition of the frame
+    D3D11_MAPPED_SUBRESOURCE source;
+    HRESULT hr = sourceContext->Map(m_readbackTexture.Get(), 0, D3D11_MAP_READ, 0, &source);
+    if (FAILED(hr)) {
+        Error("Failed to map the readback texture %p %ls", hr, GetErrorStr(hr).c_str());
+        return nullptr;
+    }
+    D3D11_MAPPED_SUBRESOURCE destination;
+    hr = destinationContext->Map(m_uploadTexture.Get(), 0, D3D11_MAP_WRITE, 0, &destination);
+    if (FAILED(hr)) {
+        Error("Failed to map the upload texture %p %ls", hr, GetErrorStr(hr).c_str());
+        sourceContext->Unmap(m_readbackTexture.Get(), 0);
+        return nullptr;
+    }
+
+    // The chroma plane follows the luma plane with the same pitch
+    auto rowSize = std::min(source.RowPitch, destination.RowPitch);
+    for (uint32_t y = 0; y < m_rowCount; y++) {
+        memcpy(
+            (uint8_t*)destination.pData + y * destination.RowPitch,
+            (const uint8_t*)source.pData + y * source.RowPitch,
+            rowSize
+        );
+    }
+
+    destinationContext->Unmap(m_uploadTexture.Get(), 0);
+    sourceContext->Unmap(m_readbackTexture.Get(), 0);
+
+    destinationContext->CopyResource(m_outputTexture.Get(), m_uploadTexture.Get());
+
+    return m_outputTexture.Get();
+}
+
+bool CrossAdapterCopy::CreateTextures(const D3D11_TEXTURE2D_DESC& desc) {
+    m_readbackTexture.Reset();
+    m_uploadTexture.Reset();
+    m_outputTexture.Reset();
+    m_desc = desc;
+
+    m_rowCount = desc.Format == DXGI_FORMAT_NV12 || desc.Format == DXGI_FORMAT_P010
+        ? desc.Height + desc.Height / 2
+        : desc.Height;
+
+    D3D11_TEXTURE2D_DESC stagingDesc = {};
+    stagingDesc.Width = desc.Width;
+    stagingDesc.Height = desc.Height;
+    stagingDesc.Format = desc.Format;
+    stagingDesc.MipLevels = 1;
+    stagingDesc.ArraySize = 1;
+    stagingDesc.SampleDesc.Count = 1;
+    stagingDesc.Usage = D3D11_USAGE_STAGING;
+
+    stagingDesc.CPUAccessFlags = D3D11_CPU_ACCESS_READ;
+    HRESULT hr = m_source->GetDevice()->CreateTexture2D(&stagingDesc, nullptr, &m_readbackTexture);
+    if (FAILED(hr)) {
+        Error("Failed to create the readback texture %p %ls", hr, GetErrorStr(hr).c_str());
+        return false;
+    }
+
+    stagingDesc.CPUAccessFlags = D3D11_CPU_ACCESS_WRITE;
+    auto device = m_destination->GetDevice();
+    hr = device->CreateTexture2D(&stagingDesc, nullptr, &m_uploadTexture);
+    if (FAILED(hr)) {
+        Error("Failed to create the upload texture %p %ls", hr, GetErrorStr(hr).c_str());
+        m_readbackTexture.Reset();
+        return false;
+    }
+
+    // Same usage as the source, the encoders may bind it
+    D3D11_TEXTURE2D_DESC outputDesc = desc;
+    outputDesc.MiscFlags = 0;
+    hr = device->CreateTexture2D(&outputDesc, nullptr, &m_outputTexture);
+    if (FAILED(hr)) {
+        Error("Failed to create the encoder input texture %p %ls", hr, GetErrorStr(hr).c_str());
+        m_readbackTexture.Reset();
+        m_uploadTexture.Reset();
+        return false;
+    }
+
+    return true;
+}
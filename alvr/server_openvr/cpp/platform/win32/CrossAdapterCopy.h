@@ -0,0 +1,30 @@
+#pragma once
+
+#include "shared/d3drender.h"
+
+#include <memory>
+#include <wrl.h>
+
+// Copies the frames composed on the adapter of SteamVR to the adapter of the encoder. D3D11 can't
+// open the textures of another adapter, so they go through system memory: the frame is read back
+// on the source adapter and uploaded to the destination one.
+class CrossAdapterCopy {
+public:
+    CrossAdapterCopy(std::shared_ptr<CD3DRender> source, std::shared_ptr<CD3DRender> destination);
+
+    // Returns the copy on the destination adapter, or null if the copy failed
+    ID3D11Texture2D* Copy(ID3D11Texture2D* pTexture);
+
+private:
+    bool CreateTextures(const D3D11_TEXTURE2D_DESC& desc);
+
+    std::shared_ptr<CD3DRender> m_source;
+    std::shared_ptr<CD3DRender> m_destination;
+
+    Microsoft::WRL::ComPtr<ID3D11Texture2D> m_readbackTexture;
+    Microsoft::WRL::ComPtr<ID3D11Texture2D> m_uploadTexture;
+    Microsoft::WRL::ComPtr<ID3D11Texture2D> m_outputTexture;
+    D3D11_TEXTURE2D_DESC m_desc = {};
+    // Both planes for the YUV formats
+    uint32_t m_rowCount = 0;
+};
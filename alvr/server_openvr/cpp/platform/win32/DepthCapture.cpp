@@ -0,0 +1,206 @@
+#include "DepthCapture.h"
+#include "alvr_server/Logger.h"
+#include "alvr_server/Settings.h"
+#include "alvr_server/Utils.h"
+#include "alvr_server/bindings.h"
+
+#include <algorithm>
+#include <cmath>
+
+DepthCapture::DepthCapture(std::shared_ptr<CD3DRender> pD3DRender)
+    : m_pD3DRender(pD3DRender)
+    , m_ring {}
+    , m_writeIndex(0)
+    , m_unsupportedFormatReported(false) {
+    auto& settings = Settings::Instance();
+    uint32_t divisor = std::max(settings.m_depthResolutionDivisor, 1u);
+
+    m_width = std::max(settings.m_renderWidth / 2 / divisor, 1u);
+    m_height = std::max(settings.m_renderHeight / divisor, 1u);
+    m_buffer.resize(m_width * 2 * m_height);
+}
+
+void DepthCapture::Capture(
+    ID3D11Texture2D* pDepthTexture[2],
+    const vr::VRTextureBounds_t bounds[2],
+    const vr::HmdMatrix44_t& projection,
+    uint64_t targetTimestampNs
+) {
+    // Send the copies finished since the last frame, oldest first. A copy that is not finished
+    // keeps the following ones pending, so the depth frames are sent in order.
+    for (int i = 0; i < STAGING_RING_SIZE; i++) {
+        auto& slot = m_ring[(m_writeIndex + i) % STAGING_RING_SIZE];
+        if (slot.pending && !TryReadSlot(slot)) {
+            break;
+        }
+    }
+
+    if (!pDepthTexture[0] || !pDepthTexture[1] || targetTimestampNs == 0) {
+        return;
+    }
+
+    // For a D3D projection, z_clip = m22 * z + m23 and w_clip = -z. Depth 0 maps to nearZ and depth
+    // 1 to farZ, which gives nearZ > farZ for reversed depth and an infinite far plane for m22 = -1
+    float m22 = projection.m[2][2];
+    float m23 = projection.m[2][3];
+    if (m23 == 0.0f) {
+        // Not a perspective projection
+        return;
+    }
+    float nearZ = m22 != 0.0f ? m23 / m22 : INFINITY;
+    float farZ = m22 != -1.0f ? m23 / (m22 + 1.0f) : INFINITY;
+    if (std::isnan(nearZ) || std::isnan(farZ) || nearZ <= 0.0f || farZ <= 0.0f) {
+        return;
+    }
+
+    // If the GPU is more than a ring behind, the oldest copy is dropped
+    auto& slot = m_ring[m_writeIndex];
+    slot.pending = false;
+    for (int eye = 0; eye < 2; eye++) {
+        if (!CopyEye(slot, eye, pDepthTexture[eye])) {
+            return;
+        }
+        slot.bounds[eye] = bounds[eye];
+    }
+    slot.nearZ = nearZ;
+    slot.farZ = farZ;
+    slot.targetTimestampNs = targetTimestampNs;
+    slot.pending = true;
+
+    m_writeIndex = (m_writeIndex + 1) % STAGING_RING_SIZE;
+}
+
+bool DepthCapture::CopyEye(StagingSlot& slot, int eye, ID3D11Texture2D* pDepthTexture) {
+    D3D11_TEXTURE2D_DESC desc;
+    pDepthTexture->GetDesc(&desc);
+
+    if (desc.SampleDesc.Count > 1) {
+        // Multisampled textures cannot be copied to a staging texture
+        return false;
+    }
+
+    auto& staging = slot.textures[eye];
+    D3D11_TEXTURE2D_DESC stagingDesc = {};
+    if (staging) {
+        staging->GetDesc(&stagingDesc);
+    }
+    if (!staging || stagingDesc.Width != desc.Width || stagingDesc.Height != desc.Height
+        || stagingDesc.Format != desc.Format) {
+        stagingDesc = desc;
+        stagingDesc.MipLevels = 1;
+        stagingDesc.ArraySize = 1;
+        stagingDesc.Usage = D3D11_USAGE_STAGING;
+        stagingDesc.BindFlags = 0;
+        stagingDesc.CPUAccessFlags = D3D11_CPU_ACCESS_READ;
+        stagingDesc.MiscFlags = 0;
+
+        staging.Reset();
+        HRESULT hr
+            = m_pD3DRender->GetDevice()->CreateTexture2D(&stagingDesc, nullptr, &staging);
+        if (FAILED(hr)) {
+            Error("Failed to create the depth staging texture %p %ls", hr, GetErrorStr(hr).c_str());
+            return false;
+        }
+    }
+
+    m_pD3DRender->GetContext()->CopyResource(staging.Get(), pDepthTexture);
+
+    return true;
+}
+
+bool DepthCapture::TryReadSlot(StagingSlot& slot) {
+    auto context = m_pD3DRender->GetContext();
+
+    D3D11_MAPPED_SUBRESOURCE mapped[2];
+    for (int eye = 0; eye < 2; eye++) {
+        HRESULT hr = context->Map(
+            slot.textures[eye].Get(), 0, D3D11_MAP_READ, D3D11_MAP_FLAG_DO_NOT_WAIT, &mapped[eye]
+        );
+        if (hr == DXGI_ERROR_WAS_STILL_DRAWING) {
+            if (eye == 1) {
+                context->Unmap(slot.textures[0].Get(), 0);
+            }
+            return false;
+        }
+        if (FAILED(hr)) {
+            Error("Failed to map the depth staging texture %p %ls", hr, GetErrorStr(hr).c_str());
+            if (eye == 1) {
+                context->Unmap(slot.textures[0].Get(), 0);
+            }
+            slot.pending = false;
+            return true;
+        }
+    }
+
+    bool supported = true;
+    for (int eye = 0; eye < 2 && supported; eye++) {
+        D3D11_TEXTURE2D_DESC desc;
+        slot.textures[eye]->GetDesc(&desc);
+
+        supported = ReadEye(mapped[eye], desc, eye, slot.bounds[eye]);
+
+        if (!supported && !m_unsupportedFormatReported) {
+            Warn("Unsupported depth format %d, the depth is not streamed", desc.Format);
+            m_unsupportedFormatReported = true;
+        }
+    }
+
+    context->Unmap(slot.textures[0].Get(), 0);
+    context->Unmap(slot.textures[1].Get(), 0);
+    slot.pending = false;
+
+    if (supported) {
+        SendDepth(
+            slot.targetTimestampNs, m_width, m_height, m_buffer.data(), slot.nearZ, slot.farZ
+        );
+    }
+
+    return true;
+}
+
+bool DepthCapture::ReadEye(
+    const D3D11_MAPPED_SUBRESOURCE& mapped,
+    const D3D11_TEXTURE2D_DESC& desc,
+    int eye,
+    const vr::VRTextureBounds_t& bounds
+) {
+    for (uint32_t y = 0; y < m_height; y++) {
+        float v = bounds.vMin + (bounds.vMax - bounds.vMin) * (y + 0.5f) / m_height;
+        uint32_t ty = std::min((uint32_t)std::max(v * desc.Height, 0.0f), desc.Height - 1);
+        auto row = (const uint8_t*)mapped.pData + ty * mapped.RowPitch;
+
+        for (uint32_t x = 0; x < m_width; x++) {
+            float u = bounds.uMin + (bounds.uMax - bounds.uMin) * (x + 0.5f) / m_width;
+            uint32_t tx = std::min((uint32_t)std::max(u * desc.Width, 0.0f), desc.Width - 1);
+
+            float depth;
+            switch (desc.Format) {
+            case DXGI_FORMAT_R32_TYPELESS:
+            case DXGI_FORMAT_R32_FLOAT:
+            case DXGI_FORMAT_D32_FLOAT:
+                depth = ((const float*)row)[tx];
+                break;
+            case DXGI_FORMAT_R32G8X24_TYPELESS:
+            case DXGI_FORMAT_D32_FLOAT_S8X24_UINT:
+                // The stencil follows the depth in each 8 byte texel
+                depth = ((const float*)row)[tx * 2];
+                break;
+            case DXGI_FORMAT_R24G8_TYPELESS:
+            case DXGI_FORMAT_D24_UNORM_S8_UINT:
+                depth = (((const uint32_t*)row)[tx] & 0xFFFFFF) / 16777215.0f;
+                break;
+            case DXGI_FORMAT_R16_TYPELESS:
+            case DXGI_FORMAT_D16_UNORM:
+                depth = ((const uint16_t*)row)[tx] / 65535.0f;
+                break;
+            default:
+                return false;
+            }
+
+            m_buffer[y * m_width * 2 + eye * m_width + x]
+                = (uint16_t)std::lround(std::clamp(depth, 0.0f, 1.0f) * 65535.0f);
+        }
+    }
+
+    return true;
+}
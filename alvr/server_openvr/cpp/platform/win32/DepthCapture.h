@@ -0,0 +1,56 @@
+#pragma once
+
+#include "alvr_server/openvr_driver_wrap.h"
+#include "shared/d3drender.h"
+
+#include <memory>
+#include <vector>
+
+// Reads back the depth submitted by the game at a low resolution and sends it to the client. Both
+// eyes are packed side by side.
+//
+// The copies go through a ring of staging textures and are read back without waiting for the GPU,
+// so the depth of a frame is sent a few frames later instead of stalling the compositor.
+class DepthCapture {
+public:
+    DepthCapture(std::shared_ptr<CD3DRender> pD3DRender);
+
+    void Capture(
+        ID3D11Texture2D* pDepthTexture[2],
+        const vr::VRTextureBounds_t bounds[2],
+        const vr::HmdMatrix44_t& projection,
+        uint64_t targetTimestampNs
+    );
+
+private:
+    static const int STAGING_RING_SIZE = 3;
+
+    struct StagingSlot {
+        Microsoft::WRL::ComPtr<ID3D11Texture2D> textures[2];
+        vr::VRTextureBounds_t bounds[2];
+        float nearZ;
+        float farZ;
+        uint64_t targetTimestampNs;
+        bool pending;
+    };
+
+    bool CopyEye(StagingSlot& slot, int eye, ID3D11Texture2D* pDepthTexture);
+    // Returns false if the copy is not finished yet
+    bool TryReadSlot(StagingSlot& slot);
+    // Returns false if the format is not supported
+    bool ReadEye(
+        const D3D11_MAPPED_SUBRESOURCE& mapped,
+        const D3D11_TEXTURE2D_DESC& desc,
+        int eye,
+        const vr::VRTextureBounds_t& bounds
+    );
+
+    std::shared_ptr<CD3DRender> m_pD3DRender;
+    StagingSlot m_ring[STAGING_RING_SIZE];
+    // Next slot to copy to. The oldest pending copy is the first pending slot after it
+    int m_writeIndex;
+    uint32_t m_width;
+    uint32_t m_height;
+    std::vector<uint16_t> m_buffer;
+    bool m_unsupportedFormatReported;
+};
@@ -4,20 +4,45 @@
 #include "alvr_server/Utils.h"
 #include "alvr_server/bindings.h"
 
+#include <algorithm>
+
 using Microsoft::WRL::ComPtr;
 using namespace d3d_render_utils;
 
 namespace {
 
+struct EyeFoveationVars {
+    float eyeWidthRatio;
+    float eyeHeightRatio;
+
+    float centerSizeX;
+    float centerSizeY;
+    float centerShiftX;
+    float centerShiftY;
+    float edgeRatioX;
+    float edgeRatioY;
+
+    float radialCenterX;
+    float radialCenterY;
+    float _align[2];
+};
+
 struct FoveationVars {
     uint32_t targetEyeWidth;
     uint32_t targetEyeHeight;
     uint32_t optimizedEyeWidth;
     uint32_t optimizedEyeHeight;
 
-    float eyeWidthRatio;
-    float eyeHeightRatio;
+    float edgePreservationStrength;
+    uint32_t mode;
+    float radialAlpha;
+    float radialScale;
+
+    // Left and right eyes
+    EyeFoveationVars eyes[2];
+};
 
+struct EyeFoveationSettings {
     float centerSizeX;
     float centerSizeY;
     float centerShiftX;
@@ -26,58 +51,110 @@ struct FoveationVars {
     float edgeRatioY;
 };
 
-FoveationVars CalculateFoveationVars() {
-    float targetEyeWidth = (float)Settings::Instance().m_renderWidth / 2;
-    float targetEyeHeight = (float)Settings::Instance().m_renderHeight;
+EyeFoveationSettings GetEyeFoveationSettings(bool isRightEye) {
+    auto& settings = Settings::Instance();
+    if (isRightEye) {
+        return { settings.m_foveationRightCenterSizeX,  settings.m_foveationRightCenterSizeY,
+                 settings.m_foveationRightCenterShiftX, settings.m_foveationRightCenterShiftY,
+                 settings.m_foveationRightEdgeRatioX,   settings.m_foveationRightEdgeRatioY };
+    }
 
-    float centerSizeX = (float)Settings::Instance().m_foveationCenterSizeX;
-    float centerSizeY = (float)Settings::Instance().m_foveationCenterSizeY;
-    float centerShiftX = (float)Settings::Instance().m_foveationCenterShiftX;
-    float centerShiftY = (float)Settings::Instance().m_foveationCenterShiftY;
-    float edgeRatioX = (float)Settings::Instance().m_foveationEdgeRatioX;
-    float edgeRatioY = (float)Settings::Instance().m_foveationEdgeRatioY;
+    return { settings.m_foveationCenterSizeX,  settings.m_foveationCenterSizeY,
+             settings.m_foveationCenterShiftX, settings.m_foveationCenterShiftY,
+             settings.m_foveationEdgeRatioX,   settings.m_foveationEdgeRatioY };
+}
 
-    float edgeSizeX = targetEyeWidth - centerSizeX * targetEyeWidth;
-    float edgeSizeY = targetEyeHeight - centerSizeY * targetEyeHeight;
+// Two zones mode. Sets the size of the compressed view before the alignment for the encoder
+void CalculateTwoZoneEyeVars(
+    const EyeFoveationSettings& eye,
+    float targetEyeWidth,
+    float targetEyeHeight,
+    EyeFoveationVars& vars,
+    float& optimizedEyeWidth,
+    float& optimizedEyeHeight
+) {
+    float edgeSizeX = targetEyeWidth - eye.centerSizeX * targetEyeWidth;
+    float edgeSizeY = targetEyeHeight - eye.centerSizeY * targetEyeHeight;
 
     float centerSizeXAligned
-        = 1. - ceil(edgeSizeX / (edgeRatioX * 2.)) * (edgeRatioX * 2.) / targetEyeWidth;
+        = 1. - ceil(edgeSizeX / (eye.edgeRatioX * 2.)) * (eye.edgeRatioX * 2.) / targetEyeWidth;
     float centerSizeYAligned
-        = 1. - ceil(edgeSizeY / (edgeRatioY * 2.)) * (edgeRatioY * 2.) / targetEyeHeight;
+        = 1. - ceil(edgeSizeY / (eye.edgeRatioY * 2.)) * (eye.edgeRatioY * 2.) / targetEyeHeight;
 
     float edgeSizeXAligned = targetEyeWidth - centerSizeXAligned * targetEyeWidth;
     float edgeSizeYAligned = targetEyeHeight - centerSizeYAligned * targetEyeHeight;
 
-    float centerShiftXAligned = ceil(centerShiftX * edgeSizeXAligned / (edgeRatioX * 2.))
-        * (edgeRatioX * 2.) / edgeSizeXAligned;
-    float centerShiftYAligned = ceil(centerShiftY * edgeSizeYAligned / (edgeRatioY * 2.))
-        * (edgeRatioY * 2.) / edgeSizeYAligned;
-
-    float foveationScaleX = (centerSizeXAligned + (1. - centerSizeXAligned) / edgeRatioX);
-    float foveationScaleY = (centerSizeYAligned + (1. - centerSizeYAligned) / edgeRatioY);
-
-    float optimizedEyeWidth = foveationScaleX * targetEyeWidth;
-    float optimizedEyeHeight = foveationScaleY * targetEyeHeight;
-
-    // round the frame dimensions to a number of pixel multiple of 32 for the encoder
-    auto optimizedEyeWidthAligned = (uint32_t)ceil(optimizedEyeWidth / 32.f) * 32;
-    auto optimizedEyeHeightAligned = (uint32_t)ceil(optimizedEyeHeight / 32.f) * 32;
-
-    float eyeWidthRatioAligned = optimizedEyeWidth / optimizedEyeWidthAligned;
-    float eyeHeightRatioAligned = optimizedEyeHeight / optimizedEyeHeightAligned;
-
-    return { (uint32_t)targetEyeWidth,
-             (uint32_t)targetEyeHeight,
-             optimizedEyeWidthAligned,
-             optimizedEyeHeightAligned,
-             eyeWidthRatioAligned,
-             eyeHeightRatioAligned,
-             centerSizeXAligned,
-             centerSizeYAligned,
-             centerShiftXAligned,
-             centerShiftYAligned,
-             edgeRatioX,
-             edgeRatioY };
+    float centerShiftXAligned = ceil(eye.centerShiftX * edgeSizeXAligned / (eye.edgeRatioX * 2.))
+        * (eye.edgeRatioX * 2.) / edgeSizeXAligned;
+    float centerShiftYAligned = ceil(eye.centerShiftY * edgeSizeYAligned / (eye.edgeRatioY * 2.))
+        * (eye.edgeRatioY * 2.) / edgeSizeYAligned;
+
+    float foveationScaleX = (centerSizeXAligned + (1. - centerSizeXAligned) / eye.edgeRatioX);
+    float foveationScaleY = (centerSizeYAligned + (1. - centerSizeYAligned) / eye.edgeRatioY);
+
+    optimizedEyeWidth = foveationScaleX * targetEyeWidth;
+    optimizedEyeHeight = foveationScaleY * targetEyeHeight;
+
+    vars.centerSizeX = centerSizeXAligned;
+    vars.centerSizeY = centerSizeYAligned;
+    vars.centerShiftX = centerShiftXAligned;
+    vars.centerShiftY = centerShiftYAligned;
+    vars.edgeRatioX = eye.edgeRatioX;
+    vars.edgeRatioY = eye.edgeRatioY;
+}
+
+FoveationVars CalculateFoveationVars() {
+    float targetEyeWidth = (float)Settings::Instance().m_renderWidth / 2;
+    float targetEyeHeight = (float)Settings::Instance().m_renderHeight;
+
+    // Radial mode: along each axis the source coordinate is a quadratic function of the compressed
+    // one, with slope alpha at the center and 2 - alpha at the edges
+    bool radial = Settings::Instance().m_foveationMode == ALVR_FOVEATION_MODE_RADIAL;
+    float radialAlpha = 2. / (Settings::Instance().m_foveationEdgeCompression + 1.);
+    float radialScale = Settings::Instance().m_foveationCenterStrength * radialAlpha;
+
+    FoveationVars vars = {};
+    vars.targetEyeWidth = (uint32_t)targetEyeWidth;
+    vars.targetEyeHeight = (uint32_t)targetEyeHeight;
+    vars.edgePreservationStrength = Settings::Instance().m_foveationEdgePreservationStrength;
+    vars.mode = radial ? ALVR_FOVEATION_MODE_RADIAL : ALVR_FOVEATION_MODE_TWO_ZONE;
+    vars.radialAlpha = radialAlpha;
+    vars.radialScale = radialScale;
+
+    float optimizedEyeWidths[2];
+    float optimizedEyeHeights[2];
+    for (int i = 0; i < 2; i++) {
+        auto eye = GetEyeFoveationSettings(i == 1);
+        if (radial) {
+            optimizedEyeWidths[i] = radialScale * targetEyeWidth;
+            optimizedEyeHeights[i] = radialScale * targetEyeHeight;
+            vars.eyes[i].radialCenterX = .5 + eye.centerShiftX * .25;
+            vars.eyes[i].radialCenterY = .5 + eye.centerShiftY * .25;
+        } else {
+            CalculateTwoZoneEyeVars(
+                eye,
+                targetEyeWidth,
+                targetEyeHeight,
+                vars.eyes[i],
+                optimizedEyeWidths[i],
+                optimizedEyeHeights[i]
+            );
+        }
+    }
+
+    // The halves of the frame have the size of the largest compressed view, the other one is
+    // padded. Round the frame dimensions to a number of pixel multiple of 32 for the encoder
+    float optimizedEyeWidth = std::max(optimizedEyeWidths[0], optimizedEyeWidths[1]);
+    float optimizedEyeHeight = std::max(optimizedEyeHeights[0], optimizedEyeHeights[1]);
+    vars.optimizedEyeWidth = (uint32_t)ceil(optimizedEyeWidth / 32.f) * 32;
+    vars.optimizedEyeHeight = (uint32_t)ceil(optimizedEyeHeight / 32.f) * 32;
+
+    for (int i = 0; i < 2; i++) {
+        vars.eyes[i].eyeWidthRatio = optimizedEyeWidths[i] / vars.optimizedEyeWidth;
+        vars.eyes[i].eyeHeightRatio = optimizedEyeHeights[i] / vars.optimizedEyeHeight;
+    }
+
+    return vars;
 }
 }
 
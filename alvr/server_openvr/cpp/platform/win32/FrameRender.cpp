@@ -124,6 +124,7 @@ bool FrameRender::Startup() {
         Error("Failed to create staging texture!\n");
         return false;
     }
+    m_compositionTexture = compositionTexture;
 
     HRESULT hr = m_pD3DRender->GetDevice()->CreateRenderTargetView(
         compositionTexture.Get(), NULL, &m_pRenderTargetView
@@ -515,11 +516,14 @@ bool FrameRender::Startup() {
                 0.0,
                 0.0 };
 
+        bool fullRange = Settings::Instance().m_colorRange == ALVR_COLOR_RANGE_FULL;
         YUVParams& paramStruct = paramStruct_bt2020_8bit_full;
         if (Settings::Instance().m_use10bitEncoder) {
-            paramStruct = paramStruct_bt2020_10bit_full;
+            paramStruct
+                = fullRange ? paramStruct_bt2020_10bit_full : paramStruct_bt2020_10bit_limited;
         } else {
-            paramStruct = paramStruct_bt2020_8bit_full;
+            paramStruct
+                = fullRange ? paramStruct_bt2020_8bit_full : paramStruct_bt2020_8bit_limited;
         }
 
         ComPtr<ID3D11Buffer> paramBuffer = CreateBuffer(m_pD3DRender->GetDevice(), paramStruct);
@@ -557,8 +561,10 @@ bool FrameRender::RenderFrame(
     ID3D11Texture2D* pTexture[][2],
     vr::VRTextureBounds_t bounds[][2],
     vr::HmdMatrix34_t poses[],
+    const vr::HmdMatrix34_t& targetPose,
     int layerCount,
     bool recentering,
+    bool testPattern,
     const std::string& message,
     const std::string& debugText
 ) {
@@ -602,8 +608,10 @@ bool FrameRender::RenderFrame(
         = DirectX::XMMatrixInverse(nullptr, HmdMatrix_AsDxMatPosOnly(m_eyeToHead[0]));
     DirectX::XMMATRIX hmdToEyeMatR
         = DirectX::XMMatrixInverse(nullptr, HmdMatrix_AsDxMatPosOnly(m_eyeToHead[1]));
-    DirectX::XMMATRIX hmdPoseForTargetTs
-        = HmdMatrix_AsDxMatOrientOnly(poses[0]); // Set to HmdMatrix_AsDxMat to debug the rendering
+    // Layers are rotated from their own pose to the target pose. The target pose is the pose of the
+    // first layer, unless the frame is re-projected to a newer head orientation.
+    // Set to HmdMatrix_AsDxMat to debug the rendering
+    DirectX::XMMATRIX hmdPoseForTargetTs = HmdMatrix_AsDxMatOrientOnly(targetPose);
 
     // I think the negative Y basis is a handedness thing?
     DirectX::XMMATRIX identityMat = DirectX::XMLoadFloat4x4(&_identityMat);
@@ -865,6 +873,10 @@ bool FrameRender::RenderFrame(
     m_pD3DRender->GetContext()->RSSetViewports(1, &m_viewport);
     m_pD3DRender->GetContext()->RSSetScissorRects(1, &m_scissor);
 
+    if (testPattern) {
+        RenderTestPattern();
+    }
+
     if (enableColorCorrection) {
         m_colorCorrectionPipeline->Render();
     }
@@ -882,8 +894,39 @@ bool FrameRender::RenderFrame(
     return true;
 }
 
+void FrameRender::RenderTestPattern() {
+    D3D11_TEXTURE2D_DESC desc;
+    m_compositionTexture->GetDesc(&desc);
+    uint32_t eyeWidth = desc.Width / 2;
+
+    m_testPatternBuffer.resize(eyeWidth * desc.Height * 4);
+    DrawTestPattern(m_testPatternBuffer.data(), eyeWidth, desc.Height, eyeWidth * 4);
+
+    const void* data = m_testPatternBuffer.data();
+    uint32_t rowPitch = eyeWidth * 4;
+    if (Settings::Instance().m_enableHdr) {
+        // The pattern uses only fully saturated channels, so no sRGB conversion is needed before
+        // writing 0.0 or 1.0 as half floats
+        m_testPatternHalfBuffer.resize(m_testPatternBuffer.size());
+        for (size_t i = 0; i < m_testPatternBuffer.size(); i++) {
+            m_testPatternHalfBuffer[i] = m_testPatternBuffer[i] > 127 ? 0x3C00 : 0;
+        }
+        data = m_testPatternHalfBuffer.data();
+        rowPitch = eyeWidth * 8;
+    }
+
+    for (uint32_t eye = 0; eye < 2; eye++) {
+        D3D11_BOX box = { eye * eyeWidth, 0, 0, (eye + 1) * eyeWidth, desc.Height, 1 };
+        m_pD3DRender->GetContext()->UpdateSubresource(
+            m_compositionTexture.Get(), 0, &box, data, rowPitch, 0
+        );
+    }
+}
+
 ComPtr<ID3D11Texture2D> FrameRender::GetTexture() { return m_pStagingTexture; }
 
+ComPtr<ID3D11Texture2D> FrameRender::GetCompositionTexture() { return m_compositionTexture; }
+
 void FrameRender::GetEncodingResolution(uint32_t* width, uint32_t* height) {
     if (enableFFE) {
         m_ffr->GetOptimizedResolution(width, height);
@@ -3,6 +3,7 @@
 #include <memory>
 #include <stdint.h>
 #include <string>
+#include <vector>
 
 #include <d3d11.h>
 #include <d3dcompiler.h>
@@ -55,17 +56,24 @@ public:
         ID3D11Texture2D* pTexture[][2],
         vr::VRTextureBounds_t bounds[][2],
         vr::HmdMatrix34_t poses[],
+        const vr::HmdMatrix34_t& targetPose,
         int layerCount,
         bool recentering,
+        bool testPattern,
         const std::string& message,
         const std::string& debugText
     );
     void GetEncodingResolution(uint32_t* width, uint32_t* height);
 
     ComPtr<ID3D11Texture2D> GetTexture();
+    // Before color correction, foveation and YUV conversion
+    ComPtr<ID3D11Texture2D> GetCompositionTexture();
 
 private:
+    void RenderTestPattern();
+
     std::shared_ptr<CD3DRender> m_pD3DRender;
+    ComPtr<ID3D11Texture2D> m_compositionTexture;
     ComPtr<ID3D11Texture2D> m_pStagingTexture;
 
     ComPtr<ID3D11VertexShader> m_pVertexShader;
@@ -92,6 +100,9 @@ private:
     ComPtr<ID3D11Resource> m_messageBGTexture;
     ComPtr<ID3D11ShaderResourceView> m_messageBGResourceView;
 
+    std::vector<uint8_t> m_testPatternBuffer;
+    std::vector<uint16_t> m_testPatternHalfBuffer;
+
     vr::HmdRect2_t m_viewProj[2];
     vr::HmdMatrix34_t m_eyeToHead[2];
 
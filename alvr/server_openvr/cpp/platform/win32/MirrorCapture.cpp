@@ -0,0 +1,199 @@
+#include "MirrorCapture.h"
+#include "alvr_server/Logger.h"
+#include "alvr_server/Utils.h"
+#include "alvr_server/bindings.h"
+
+#include <algorithm>
+#include <cmath>
+#include <cstring>
+
+namespace {
+const auto MIRROR_INTERVAL = std::chrono::milliseconds(100);
+const uint32_t MAX_MIRROR_WIDTH = 960;
+
+float HalfToFloat(uint16_t half) {
+    uint32_t sign = (half & 0x8000) << 16;
+    uint32_t exponent = (half >> 10) & 0x1F;
+    uint32_t mantissa = half & 0x3FF;
+
+    uint32_t bits;
+    if (exponent == 0) {
+        // Zero and denormals, which are too dark to matter here
+        bits = sign;
+    } else if (exponent == 31) {
+        bits = sign | 0x7F800000 | (mantissa << 13);
+    } else {
+        bits = sign | ((exponent + 112) << 23) | (mantissa << 13);
+    }
+
+    float value;
+    memcpy(&value, &bits, sizeof(value));
+    return value;
+}
+
+uint8_t LinearToSrgb(float value) {
+    value = std::clamp(value, 0.0f, 1.0f);
+    float srgb = value <= 0.0031308f ? value * 12.92f
+                                     : 1.055f * std::pow(value, 1.0f / 2.4f) - 0.055f;
+    return (uint8_t)std::lround(srgb * 255.0f);
+}
+}
+
+MirrorCapture::MirrorCapture(std::shared_ptr<CD3DRender> pD3DRender)
+    : m_pD3DRender(pD3DRender) { }
+
+void MirrorCapture::Capture(ID3D11Texture2D* pTexture) {
+    if (!m_enabled) {
+        if (m_mipTexture) {
+            m_mipView.Reset();
+            m_mipTexture.Reset();
+            m_stagingTexture.Reset();
+            m_copyPending = false;
+        }
+        return;
+    }
+
+    auto now = std::chrono::steady_clock::now();
+    if (!pTexture || now - m_lastCapture < MIRROR_INTERVAL) {
+        return;
+    }
+
+    // The copy issued by the previous capture is read now, so the render thread never waits for it
+    if (m_copyPending && !ReadStaging()) {
+        return;
+    }
+    m_lastCapture = now;
+
+    D3D11_TEXTURE2D_DESC desc;
+    pTexture->GetDesc(&desc);
+    if (!m_mipTexture || desc.Width != m_sourceDesc.Width || desc.Height != m_sourceDesc.Height
+        || desc.Format != m_sourceDesc.Format) {
+        if (!CreateTextures(desc)) {
+            return;
+        }
+    }
+
+    // Downscaling through the mip chain keeps the readback small without a dedicated shader
+    auto context = m_pD3DRender->GetContext();
+    context->CopySubresourceRegion(m_mipTexture.Get(), 0, 0, 0, 0, pTexture, 0, nullptr);
+    context->GenerateMips(m_mipView.Get());
+    context->CopySubresourceRegion(
+        m_stagingTexture.Get(), 0, 0, 0, 0, m_mipTexture.Get(), m_mipLevel, nullptr
+    );
+    m_copyPending = true;
+}
+
+bool MirrorCapture::CreateTextures(const D3D11_TEXTURE2D_DESC& desc) {
+    m_mipView.Reset();
+    m_mipTexture.Reset();
+    m_stagingTexture.Reset();
+    m_copyPending = false;
+
+    if (desc.Format != DXGI_FORMAT_R8G8B8A8_UNORM_SRGB
+        && desc.Format != DXGI_FORMAT_R16G16B16A16_FLOAT) {
+        if (!m_unsupportedFormatReported) {
+            Warn("Unsupported mirror format %d, the mirror is not shown", desc.Format);
+            m_unsupportedFormatReported = true;
+        }
+        return false;
+    }
+    m_sourceDesc = desc;
+
+    D3D11_TEXTURE2D_DESC mipDesc = {};
+    mipDesc.Width = desc.Width;
+    mipDesc.Height = desc.Height;
+    mipDesc.Format = desc.Format;
+    mipDesc.MipLevels = 0;
+    mipDesc.ArraySize = 1;
+    mipDesc.SampleDesc.Count = 1;
+    mipDesc.Usage = D3D11_USAGE_DEFAULT;
+    mipDesc.BindFlags = D3D11_BIND_SHADER_RESOURCE | D3D11_BIND_RENDER_TARGET;
+    mipDesc.MiscFlags = D3D11_RESOURCE_MISC_GENERATE_MIPS;
+
+    auto device = m_pD3DRender->GetDevice();
+    HRESULT hr = device->CreateTexture2D(&mipDesc, nullptr, &m_mipTexture);
+    if (FAILED(hr)) {
+        Error("Failed to create the mirror texture %p %ls", hr, GetErrorStr(hr).c_str());
+        return false;
+    }
+    hr = device->CreateShaderResourceView(m_mipTexture.Get(), nullptr, &m_mipView);
+    if (FAILED(hr)) {
+        Error("Failed to create the mirror texture view %p %ls", hr, GetErrorStr(hr).c_str());
+        m_mipTexture.Reset();
+        return false;
+    }
+
+    m_mipLevel = 0;
+    while ((desc.Width >> m_mipLevel) > MAX_MIRROR_WIDTH) {
+        m_mipLevel++;
+    }
+    m_width = std::max(desc.Width >> m_mipLevel, 1u);
+    m_height = std::max(desc.Height >> m_mipLevel, 1u);
+
+    D3D11_TEXTURE2D_DESC stagingDesc = {};
+    stagingDesc.Width = m_width;
+    stagingDesc.Height = m_height;
+    stagingDesc.Format = desc.Format;
+    stagingDesc.MipLevels = 1;
+    stagingDesc.ArraySize = 1;
+    stagingDesc.SampleDesc.Count = 1;
+    stagingDesc.Usage = D3D11_USAGE_STAGING;
+    stagingDesc.CPUAccessFlags = D3D11_CPU_ACCESS_READ;
+
+    hr = device->CreateTexture2D(&stagingDesc, nullptr, &m_stagingTexture);
+    if (FAILED(hr)) {
+        Error("Failed to create the mirror staging texture %p %ls", hr, GetErrorStr(hr).c_str());
+        m_mipView.Reset();
+        m_mipTexture.Reset();
+        return false;
+    }
+
+    m_buffer.resize(m_width * m_height * 4);
+
+    return true;
+}
+
+bool MirrorCapture::ReadStaging() {
+    auto context = m_pD3DRender->GetContext();
+
+    D3D11_MAPPED_SUBRESOURCE mapped;
+    HRESULT hr = context->Map(
+        m_stagingTexture.Get(), 0, D3D11_MAP_READ, D3D11_MAP_FLAG_DO_NOT_WAIT, &mapped
+    );
+    if (hr == DXGI_ERROR_WAS_STILL_DRAWING) {
+        return false;
+    }
+    m_copyPending = false;
+    if (FAILED(hr)) {
+        Error("Failed to map the mirror staging texture %p %ls", hr, GetErrorStr(hr).c_str());
+        return true;
+    }
+
+    for (uint32_t y = 0; y < m_height; y++) {
+        auto row = (const uint8_t*)mapped.pData + y * mapped.RowPitch;
+        auto out = m_buffer.data() + y * m_width * 4;
+
+        if (m_sourceDesc.Format == DXGI_FORMAT_R8G8B8A8_UNORM_SRGB) {
+            memcpy(out, row, m_width * 4);
+            // The composition alpha is not meaningful
+            for (uint32_t x = 0; x < m_width; x++) {
+                out[x * 4 + 3] = 255;
+            }
+        } else {
+            // scRGB, values above 1 are clipped
+            auto texels = (const uint16_t*)row;
+            for (uint32_t x = 0; x < m_width; x++) {
+                for (int c = 0; c < 3; c++) {
+                    out[x * 4 + c] = LinearToSrgb(HalfToFloat(texels[x * 4 + c]));
+                }
+                out[x * 4 + 3] = 255;
+            }
+        }
+    }
+
+    context->Unmap(m_stagingTexture.Get(), 0);
+
+    SendMirrorFrame(m_width, m_height, m_buffer.data());
+
+    return true;
+}
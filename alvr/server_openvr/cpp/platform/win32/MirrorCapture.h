@@ -0,0 +1,39 @@
+#pragma once
+
+#include "shared/d3drender.h"
+
+#include <atomic>
+#include <chrono>
+#include <memory>
+#include <vector>
+
+// Reads back a downscaled copy of the composed frame at a low rate and sends it to the dashboard
+// mirror window. Nothing is done while the mirror is disabled.
+class MirrorCapture {
+public:
+    MirrorCapture(std::shared_ptr<CD3DRender> pD3DRender);
+
+    void SetEnabled(bool enabled) { m_enabled = enabled; }
+
+    void Capture(ID3D11Texture2D* pTexture);
+
+private:
+    bool CreateTextures(const D3D11_TEXTURE2D_DESC& desc);
+    bool ReadStaging();
+
+    std::shared_ptr<CD3DRender> m_pD3DRender;
+    std::atomic_bool m_enabled = false;
+    std::chrono::steady_clock::time_point m_lastCapture;
+
+    Microsoft::WRL::ComPtr<ID3D11Texture2D> m_mipTexture;
+    Microsoft::WRL::ComPtr<ID3D11ShaderResourceView> m_mipView;
+    Microsoft::WRL::ComPtr<ID3D11Texture2D> m_stagingTexture;
+    D3D11_TEXTURE2D_DESC m_sourceDesc = {};
+    UINT m_mipLevel = 0;
+    uint32_t m_width = 0;
+    uint32_t m_height = 0;
+    bool m_copyPending = false;
+
+    std::vector<uint8_t> m_buffer;
+    bool m_unsupportedFormatReported = false;
+};
@@ -0,0 +1,224 @@
+#include "MotionEstimation.h"
+#include "alvr_server/Logger.h"
+#include "alvr_server/Settings.h"
+#include "alvr_server/Utils.h"
+#include "alvr_server/bindings.h"
+
+#include <algorithm>
+#include <climits>
+
+namespace {
+const uint32_t MAX_VIEW_WIDTH = 512;
+// Same as alvr_packets::INVALID_MOTION_VECTOR
+const int16_t INVALID_MOTION_VECTOR = INT16_MIN;
+
+void CreateEstimator(NvEncoderD3D11& encoder) {
+    NV_ENC_INITIALIZE_PARAMS initializeParams = { NV_ENC_INITIALIZE_PARAMS_VER };
+    NV_ENC_CONFIG encodeConfig = { NV_ENC_CONFIG_VER };
+    initializeParams.encodeConfig = &encodeConfig;
+
+    // The H.264 motion vectors are per 16x16 macroblock, whatever codec is streamed
+    encoder.CreateDefaultEncoderParams(
+        &initializeParams, NV_ENC_CODEC_H264_GUID, NV_ENC_PRESET_P4_GUID
+    );
+    encoder.CreateEncoder(&initializeParams);
+}
+}
+
+MotionEstimation::MotionEstimation(
+    std::shared_ptr<CD3DRender> pD3DRender, uint32_t width, uint32_t height
+)
+    : m_pD3DRender(pD3DRender)
+    , m_mipLevel(0)
+    , m_referenceTimestampNs(0) {
+    if (Settings::Instance().m_enableHdr) {
+        throw MakeException("HDR frames are not supported");
+    }
+
+    while (((width / 2) >> m_mipLevel) > MAX_VIEW_WIDTH) {
+        m_mipLevel++;
+    }
+    m_viewWidth = std::max((width >> m_mipLevel) / 2, 1u);
+    m_viewHeight = std::max(height >> m_mipLevel, 1u);
+    m_gridWidth = (m_viewWidth + BLOCK_SIZE - 1) / BLOCK_SIZE;
+    m_gridHeight = (m_viewHeight + BLOCK_SIZE - 1) / BLOCK_SIZE;
+    m_vectors.resize(m_gridWidth * m_gridHeight * 2 * 2);
+
+    auto device = m_pD3DRender->GetDevice();
+
+    try {
+        m_encoder = std::make_unique<NvEncoderD3D11>(
+            device, m_viewWidth, m_viewHeight, NV_ENC_BUFFER_FORMAT_ABGR, 0, true
+        );
+        CreateEstimator(*m_encoder);
+    } catch (NVENCException e) {
+        throw MakeException(
+            "NvEnc motion estimation failed. Code=%d %hs", e.getErrorCode(), e.what()
+        );
+    }
+
+    D3D11_TEXTURE2D_DESC mipDesc = {};
+    mipDesc.Width = width;
+    mipDesc.Height = height;
+    mipDesc.Format = DXGI_FORMAT_R8G8B8A8_UNORM_SRGB;
+    mipDesc.MipLevels = m_mipLevel + 1;
+    mipDesc.ArraySize = 1;
+    mipDesc.SampleDesc.Count = 1;
+    mipDesc.Usage = D3D11_USAGE_DEFAULT;
+    mipDesc.BindFlags = D3D11_BIND_SHADER_RESOURCE | D3D11_BIND_RENDER_TARGET;
+    mipDesc.MiscFlags = D3D11_RESOURCE_MISC_GENERATE_MIPS;
+
+    HRESULT hr = device->CreateTexture2D(&mipDesc, nullptr, &m_mipTexture);
+    if (FAILED(hr)) {
+        throw MakeException(
+            "Failed to create the motion estimation texture %p %ls", hr, GetErrorStr(hr).c_str()
+        );
+    }
+    hr = device->CreateShaderResourceView(m_mipTexture.Get(), nullptr, &m_mipView);
+    if (FAILED(hr)) {
+        throw MakeException(
+            "Failed to create the motion estimation texture view %p %ls",
+            hr,
+            GetErrorStr(hr).c_str()
+        );
+    }
+
+    // Same format as the inputs of the estimator. The sRGB values are copied as they are
+    D3D11_TEXTURE2D_DESC referenceDesc = {};
+    referenceDesc.Width = m_viewWidth;
+    referenceDesc.Height = m_viewHeight;
+    referenceDesc.Format = DXGI_FORMAT_R8G8B8A8_UNORM;
+    referenceDesc.MipLevels = 1;
+    referenceDesc.ArraySize = 1;
+    referenceDesc.SampleDesc.Count = 1;
+    referenceDesc.Usage = D3D11_USAGE_DEFAULT;
+
+    for (int view = 0; view < 2; view++) {
+        hr = device->CreateTexture2D(&referenceDesc, nullptr, &m_referenceTextures[view]);
+        if (FAILED(hr)) {
+            throw MakeException(
+                "Failed to create the motion estimation reference %p %ls",
+                hr,
+                GetErrorStr(hr).c_str()
+            );
+        }
+    }
+
+    Info(
+        "Estimating the motion at %dx%d per view for space warp", m_viewWidth, m_viewHeight
+    );
+}
+
+MotionEstimation::~MotionEstimation() {
+    if (m_encoder) {
+        m_encoder->DestroyEncoder();
+    }
+}
+
+bool MotionEstimation::IsSupported(ID3D11Device* pDevice) {
+    if (Settings::Instance().m_enableHdr) {
+        Info("Space warp is not available with HDR");
+        return false;
+    }
+
+    try {
+        NvEncoderD3D11 encoder(pDevice, 256, 256, NV_ENC_BUFFER_FORMAT_ABGR, 0, true);
+        CreateEstimator(encoder);
+        encoder.DestroyEncoder();
+    } catch (NVENCException e) {
+        Info(
+            "Space warp is not available, NVENC cannot estimate the motion. Code=%d %hs",
+            e.getErrorCode(),
+            e.what()
+        );
+        return false;
+    }
+
+    return true;
+}
+
+void MotionEstimation::Estimate(ID3D11Texture2D* pTexture, uint64_t targetTimestampNs) {
+    if (!pTexture || targetTimestampNs == 0) {
+        return;
+    }
+
+    auto context = m_pD3DRender->GetContext();
+    context->CopySubresourceRegion(m_mipTexture.Get(), 0, 0, 0, 0, pTexture, 0, nullptr);
+    context->GenerateMips(m_mipView.Get());
+
+    bool estimated = m_referenceTimestampNs != 0;
+    for (int view = 0; view < 2; view++) {
+        D3D11_BOX box = { view * m_viewWidth, 0, 0, (view + 1) * m_viewWidth, m_viewHeight, 1 };
+
+        if (estimated) {
+            auto input = (ID3D11Texture2D*)m_encoder->GetNextInputFrame()->inputPtr;
+            auto reference = (ID3D11Texture2D*)m_encoder->GetNextReferenceFrame()->inputPtr;
+            context->CopySubresourceRegion(
+                input, 0, 0, 0, 0, m_mipTexture.Get(), m_mipLevel, &box
+            );
+            context->CopyResource(reference, m_referenceTextures[view].Get());
+
+            try {
+                std::vector<uint8_t> mvData;
+                m_encoder->RunMotionEstimation(mvData);
+                estimated = ReadVectors(mvData, view);
+            } catch (NVENCException e) {
+                Error("NvEnc motion estimation failed. Code=%d %hs", e.getErrorCode(), e.what());
+                estimated = false;
+            }
+        }
+
+        // The current frame is the reference of the next one
+        context->CopySubresourceRegion(
+            m_referenceTextures[view].Get(), 0, 0, 0, 0, m_mipTexture.Get(), m_mipLevel, &box
+        );
+    }
+
+    if (estimated) {
+        SendMotionVectors(
+            targetTimestampNs,
+            m_referenceTimestampNs,
+            m_viewWidth,
+            m_viewHeight,
+            BLOCK_SIZE,
+            m_gridWidth,
+            m_gridHeight,
+            m_vectors.data()
+        );
+    }
+    m_referenceTimestampNs = targetTimestampNs;
+}
+
+bool MotionEstimation::ReadVectors(const std::vector<uint8_t>& mvData, int view) {
+    uint32_t count = m_gridWidth * m_gridHeight;
+    if (mvData.size() < count * sizeof(NV_ENC_H264_MV_DATA)) {
+        Error("Unexpected motion estimation output size %d", (int)mvData.size());
+        return false;
+    }
+
+    auto blocks = (const NV_ENC_H264_MV_DATA*)mvData.data();
+    auto out = m_vectors.data() + view * count * 2;
+    for (uint32_t i = 0; i < count; i++) {
+        auto& block = blocks[i];
+
+        // Intra blocks have no match in the reference frame
+        if (block.mbType == 0) {
+            out[i * 2] = INVALID_MOTION_VECTOR;
+            out[i * 2 + 1] = 0;
+            continue;
+        }
+
+        // The partitions of the block are averaged: 16x16, 8x8, 16x8 and 8x16
+        int partitions = block.partitionType == 1 ? 4 : (block.partitionType == 0 ? 1 : 2);
+        int x = 0;
+        int y = 0;
+        for (int p = 0; p < partitions; p++) {
+            x += block.mv[p].mvx;
+            y += block.mv[p].mvy;
+        }
+        out[i * 2] = (int16_t)std::max(x / partitions, INT16_MIN + 1);
+        out[i * 2 + 1] = (int16_t)(y / partitions);
+    }
+
+    return true;
+}
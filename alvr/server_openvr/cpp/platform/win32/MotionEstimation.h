@@ -0,0 +1,49 @@
+#pragma once
+
+#include "NvEncoderD3D11.h"
+#include "shared/d3drender.h"
+
+#include <memory>
+#include <vector>
+
+// Estimates the motion of the blocks of each view between two frames sent in a row, with the motion
+// estimation only mode of NVENC, and sends the motion vectors to the client for space warp. The
+// composed frame is downscaled through the mip chain first, the motion vectors are only needed at a
+// low resolution.
+//
+// The estimation runs after the frame is handed to the encoder, so it doesn't delay the frame.
+class MotionEstimation {
+public:
+    // Throws if the motion cannot be estimated on this device
+    MotionEstimation(std::shared_ptr<CD3DRender> pD3DRender, uint32_t width, uint32_t height);
+    ~MotionEstimation();
+
+    // Creates a small estimator to check that NVENC supports the motion estimation only mode
+    static bool IsSupported(ID3D11Device* pDevice);
+
+    // pTexture is the composed frame, with both views side by side
+    void Estimate(ID3D11Texture2D* pTexture, uint64_t targetTimestampNs);
+
+private:
+    static const uint32_t BLOCK_SIZE = 16;
+
+    bool ReadVectors(const std::vector<uint8_t>& mvData, int view);
+
+    std::shared_ptr<CD3DRender> m_pD3DRender;
+    std::unique_ptr<NvEncoderD3D11> m_encoder;
+
+    Microsoft::WRL::ComPtr<ID3D11Texture2D> m_mipTexture;
+    Microsoft::WRL::ComPtr<ID3D11ShaderResourceView> m_mipView;
+    // Downscaled views of the previous frame, the reference of the estimation
+    Microsoft::WRL::ComPtr<ID3D11Texture2D> m_referenceTextures[2];
+    UINT m_mipLevel;
+    uint32_t m_viewWidth;
+    uint32_t m_viewHeight;
+    uint32_t m_gridWidth;
+    uint32_t m_gridHeight;
+    // 0 until the first frame is stored as reference
+    uint64_t m_referenceTimestampNs;
+
+    // Pairs of i16 in quarter pixels, left view then right view
+    std::vector<int16_t> m_vectors;
+};
@@ -164,6 +164,11 @@ public:
     */
     int GetEncodeHeight() const { return m_nHeight; }
 
+    /**
+    *  @brief  This function is used to get the average QP of the last encoded frame.
+    */
+    uint32_t GetLastFrameAverageQp() const { return m_nLastFrameAverageQp; }
+
     /**
     *   @brief  This function is used to get the current frame size based on pixel format.
     */
@@ -444,5 +449,6 @@ private:
     std::vector<NV_ENC_OUTPUT_PTR> m_vMVDataOutputBuffer;
     uint32_t m_nMaxEncodeWidth = 0;
     uint32_t m_nMaxEncodeHeight = 0;
+    uint32_t m_nLastFrameAverageQp = 0;
     void* m_hModule = nullptr;
 };
@@ -1,11 +1,17 @@
 #include "OvrDirectModeComponent.h"
+#include "alvr_server/bindings.h"
 
 OvrDirectModeComponent::OvrDirectModeComponent(
     std::shared_ptr<CD3DRender> pD3DRender, std::shared_ptr<PoseHistory> poseHistory
 )
     : m_pD3DRender(pD3DRender)
     , m_poseHistory(poseHistory)
-    , m_submitLayer(0) { }
+    , m_submitLayer(0)
+    , m_depthSubmitted(false) {
+    if (Settings::Instance().m_enableDepthStream) {
+        m_depthCapture = std::make_unique<DepthCapture>(pD3DRender);
+    }
+}
 
 void OvrDirectModeComponent::SetEncoder(std::shared_ptr<CEncoder> pEncoder) {
     m_pEncoder = pEncoder;
@@ -181,6 +187,13 @@ void OvrDirectModeComponent::SubmitLayer(const SubmitLayerPerEye_t (&perEye)[2])
             m_targetTimestampNs = 0;
             m_framePoseRotation = HmdQuaternion_Init(0.0, 0.0, 0.0, 0.0);
         }
+
+        // Only the first layer is checked, the overlays have no depth
+        bool depthSubmitted = perEye[0].hDepthTexture != 0;
+        if (Settings::Instance().m_enableDepthStream && depthSubmitted != m_depthSubmitted) {
+            m_depthSubmitted = depthSubmitted;
+            ReportDepthSubmission(depthSubmitted);
+        }
     }
     if (m_submitLayer < MAX_LAYERS) {
         m_submitLayers[m_submitLayer][0] = perEye[0];
@@ -217,7 +230,17 @@ void OvrDirectModeComponent::Present(vr::SharedTextureHandle_t syncTexture) {
 
     ID3D11Texture2D* pSyncTexture = m_pD3DRender->GetSharedTexture((HANDLE)syncTexture);
     if (!pSyncTexture) {
-        Warn("[VDispDvr] SyncTexture is NULL!");
+        // The textures of SteamVR can be opened only on its adapter
+        if (!m_syncTextureErrorReported) {
+            std::wstring adapterName;
+            m_pD3DRender->GetAdapterInfo(nullptr, adapterName);
+            Error(
+                "Failed to open the sync texture of SteamVR on %ls. SteamVR probably renders on "
+                "another adapter, check the graphics settings of Windows for vrcompositor.exe",
+                adapterName.c_str()
+            );
+            m_syncTextureErrorReported = true;
+        }
         m_presentMutex.unlock();
         return;
     }
@@ -329,11 +352,27 @@ void OvrDirectModeComponent::CopyTexture(uint32_t layerCount) {
 
         uint64_t submitFrameIndex = m_targetTimestampNs;
 
+        // Re-project the layers to the latest head orientation, which is now as fresh as it can be
+        // before encoding. The frame keeps its timestamp and the pose it was re-projected to is
+        // reported separately, so the client uses it as reference and doesn't correct it again.
+        vr::HmdMatrix34_t targetPose = poses[0];
+        bool reprojected = false;
+        if (Settings::Instance().m_serverReprojection && layerCount > 0
+            && m_targetTimestampNs != 0) {
+            auto latestPose = m_poseHistory->GetLatestPose();
+            if (latestPose && latestPose->targetTimestampNs > m_targetTimestampNs) {
+                targetPose = latestPose->rotationMatrix;
+                ReportServerReprojection(m_targetTimestampNs, latestPose->targetTimestampNs);
+                reprojected = true;
+            }
+        }
+
         // Copy entire texture to staging so we can read the pixels to send to remote device.
         m_pEncoder->CopyToStaging(
             pTexture,
             bounds,
             poses,
+            targetPose,
             layerCount,
             false,
             presentationTime,
@@ -342,6 +381,22 @@ void OvrDirectModeComponent::CopyTexture(uint32_t layerCount) {
             debugText
         );
 
+        // Only the first layer has depth. After the re-projection the depth does not match the
+        // frame anymore
+        if (m_depthCapture && layerCount > 0 && !reprojected) {
+            ID3D11Texture2D* pDepthTexture[2] = { nullptr, nullptr };
+            for (int eye = 0; eye < 2; eye++) {
+                auto it = m_handleMap.find((HANDLE)m_submitLayers[0][eye].hDepthTexture);
+                if (it != m_handleMap.end()) {
+                    pDepthTexture[eye] = it->second.first->textures[it->second.second].Get();
+                }
+            }
+
+            m_depthCapture->Capture(
+                pDepthTexture, bounds[0], m_submitLayers[0][0].mProjection, submitFrameIndex
+            );
+        }
+
         m_pD3DRender->GetContext()->Flush();
     }
 }
@@ -1,5 +1,6 @@
 #pragma once
 #include "CEncoder.h"
+#include "DepthCapture.h"
 #include "alvr_server/PoseHistory.h"
 #include "alvr_server/Utils.h"
 #include "alvr_server/openvr_driver_wrap.h"
@@ -70,6 +71,9 @@ private:
     vr::HmdQuaternion_t m_framePoseRotation;
     uint64_t m_targetTimestampNs;
     uint64_t m_prevTargetTimestampNs;
+    bool m_depthSubmitted;
+    bool m_syncTextureErrorReported = false;
+    std::unique_ptr<DepthCapture> m_depthCapture;
 
     std::mutex m_presentMutex;
 };
@@ -2,6 +2,8 @@
 
 #include "alvr_server/Logger.h"
 #include "alvr_server/Settings.h"
+#include "alvr_server/Utils.h"
+#include "alvr_server/bindings.h"
 
 #define AMF_THROW_IF(expr)                                                                         \
     {                                                                                              \
@@ -11,6 +13,29 @@
         }                                                                                          \
     }
 
+namespace {
+
+bool IsFullRange() { return Settings::Instance().m_colorRange == ALVR_COLOR_RANGE_FULL; }
+
+// Use BT.2020 for HDR encoding, BT.709 otherwise
+AMF_VIDEO_CONVERTER_COLOR_PROFILE_ENUM GetColorProfile() {
+    if (Settings::Instance().m_enableHdr) {
+        return IsFullRange() ? AMF_VIDEO_CONVERTER_COLOR_PROFILE_FULL_2020
+                             : AMF_VIDEO_CONVERTER_COLOR_PROFILE_2020;
+    } else {
+        return IsFullRange() ? AMF_VIDEO_CONVERTER_COLOR_PROFILE_FULL_709
+                             : AMF_VIDEO_CONVERTER_COLOR_PROFILE_709;
+    }
+}
+
+AMF_COLOR_TRANSFER_CHARACTERISTIC_ENUM GetTransferCharacteristic() {
+    return Settings::Instance().m_transferFunction == ALVR_TRANSFER_FUNCTION_GAMMA22
+        ? AMF_COLOR_TRANSFER_CHARACTERISTIC_GAMMA22
+        : AMF_COLOR_TRANSFER_CHARACTERISTIC_IEC61966_2_1;
+}
+
+}
+
 const wchar_t* VideoEncoderAMF::START_TIME_PROPERTY = L"StartTimeProperty";
 const wchar_t* VideoEncoderAMF::FRAME_INDEX_PROPERTY = L"FrameIndexProperty";
 
@@ -103,6 +128,7 @@ VideoEncoderAMF::VideoEncoderAMF(std::shared_ptr<CD3DRender> d3dRender, int widt
     , m_renderWidth(width)
     , m_renderHeight(height)
     , m_bitrateInMBits(30)
+    , m_presets(GetEncoderPresets())
     , m_surfaceFormat(amf::AMF_SURFACE_RGBA)
     , m_use10bit(Settings::Instance().m_use10bitEncoder)
     , m_hasQueryTimeout(false) {
@@ -197,7 +223,7 @@ amf::AMFComponentPtr VideoEncoderAMF::MakeEncoder(
         amfEncoder->SetProperty(AMF_VIDEO_ENCODER_FRAMERATE, ::AMFConstructRate(frameRateIn, 1));
         amfEncoder->SetProperty(AMF_VIDEO_ENCODER_B_PIC_PATTERN, 0);
 
-        switch (Settings::Instance().m_encoderQualityPreset) {
+        switch (m_presets.encoder_quality_preset) {
         case ALVR_QUALITY:
             amfEncoder->SetProperty(
                 AMF_VIDEO_ENCODER_QUALITY_PRESET, AMF_VIDEO_ENCODER_QUALITY_PRESET_QUALITY
@@ -222,8 +248,8 @@ amf::AMFComponentPtr VideoEncoderAMF::MakeEncoder(
             caps->GetProperty(AMF_VIDEO_ENCODER_CAPS_QUERY_TIMEOUT_SUPPORT, &m_hasQueryTimeout);
         }
 
-        if (Settings::Instance().m_enableAmfPreAnalysis) {
-            if (!Settings::Instance().m_useAmfPreproc || Settings::Instance().m_use10bitEncoder) {
+        if (m_presets.enable_amf_pre_analysis) {
+            if (!m_presets.use_amf_preproc || Settings::Instance().m_use10bitEncoder) {
                 Warn("Pre-analysis could not be enabled because \"Use preproc\" is not enabled or "
                      "\"Reduce color banding\" is enabled.");
             } else if (m_hasPreAnalysis) {
@@ -231,7 +257,7 @@ amf::AMFComponentPtr VideoEncoderAMF::MakeEncoder(
                      "enabled.");
                 amfEncoder->SetProperty(
                     AMF_VIDEO_ENCODER_PRE_ANALYSIS_ENABLE,
-                    Settings::Instance().m_enableAmfPreAnalysis
+                    true
                 );
             } else {
                 Warn("Pre-analysis could not be enabled because your GPU does not support it for "
@@ -239,14 +265,13 @@ amf::AMFComponentPtr VideoEncoderAMF::MakeEncoder(
             }
         }
 
-        // Enable Full Range
-        amfEncoder->SetProperty(AMF_VIDEO_ENCODER_FULL_RANGE_COLOR, true);
+        amfEncoder->SetProperty(AMF_VIDEO_ENCODER_FULL_RANGE_COLOR, IsFullRange());
 
         // Use BT.2020 for HDR encoding, BT.709 otherwise.
         if (Settings::Instance().m_enableHdr) {
             // Also specify the input formats for HDR to prevent incorrect color conversions
             amfEncoder->SetProperty(
-                AMF_VIDEO_ENCODER_INPUT_COLOR_PROFILE, AMF_VIDEO_CONVERTER_COLOR_PROFILE_FULL_2020
+                AMF_VIDEO_ENCODER_INPUT_COLOR_PROFILE, GetColorProfile()
             );
             amfEncoder->SetProperty(
                 AMF_VIDEO_ENCODER_INPUT_TRANSFER_CHARACTERISTIC,
@@ -257,23 +282,23 @@ amf::AMFComponentPtr VideoEncoderAMF::MakeEncoder(
             );
 
             amfEncoder->SetProperty(
-                AMF_VIDEO_ENCODER_OUTPUT_COLOR_PROFILE, AMF_VIDEO_CONVERTER_COLOR_PROFILE_FULL_2020
+                AMF_VIDEO_ENCODER_OUTPUT_COLOR_PROFILE, GetColorProfile()
             );
             amfEncoder->SetProperty(
                 AMF_VIDEO_ENCODER_OUTPUT_TRANSFER_CHARACTERISTIC,
-                AMF_COLOR_TRANSFER_CHARACTERISTIC_GAMMA22
-            ); // sRGB
+                GetTransferCharacteristic()
+            );
             amfEncoder->SetProperty(
                 AMF_VIDEO_ENCODER_OUTPUT_COLOR_PRIMARIES, AMF_COLOR_PRIMARIES_BT2020
             );
         } else {
             amfEncoder->SetProperty(
-                AMF_VIDEO_ENCODER_OUTPUT_COLOR_PROFILE, AMF_VIDEO_CONVERTER_COLOR_PROFILE_FULL_709
+                AMF_VIDEO_ENCODER_OUTPUT_COLOR_PROFILE, GetColorProfile()
             );
             amfEncoder->SetProperty(
                 AMF_VIDEO_ENCODER_OUTPUT_TRANSFER_CHARACTERISTIC,
-                AMF_COLOR_TRANSFER_CHARACTERISTIC_GAMMA22
-            ); // sRGB
+                GetTransferCharacteristic()
+            );
             amfEncoder->SetProperty(
                 AMF_VIDEO_ENCODER_OUTPUT_COLOR_PRIMARIES, AMF_COLOR_PRIMARIES_BT709
             );
@@ -291,6 +316,14 @@ amf::AMFComponentPtr VideoEncoderAMF::MakeEncoder(
         // Turns Off IDR/I Frames
         amfEncoder->SetProperty(AMF_VIDEO_ENCODER_IDR_PERIOD, 0);
 
+        if (Settings::Instance().m_intraRefreshRecoveryPeriod > 0) {
+            uint32_t period = Settings::Instance().m_intraRefreshRecoveryPeriod;
+            uint32_t mbCount = ((width + 15) / 16) * ((height + 15) / 16);
+            amfEncoder->SetProperty(
+                AMF_VIDEO_ENCODER_INTRA_REFRESH_NUM_MBS_PER_SLOT, (mbCount + period - 1) / period
+            );
+        }
+
         // Disable AUD to produce the same stream format as VideoEncoderNVENC.
         // FIXME: This option doesn't work in 22.10.3, but works in versions prior 22.5.1
         amfEncoder->SetProperty(AMF_VIDEO_ENCODER_INSERT_AUD, false);
@@ -334,7 +367,7 @@ amf::AMFComponentPtr VideoEncoderAMF::MakeEncoder(
             AMF_VIDEO_ENCODER_HEVC_FRAMERATE, ::AMFConstructRate(frameRateIn, 1)
         );
 
-        switch (Settings::Instance().m_encoderQualityPreset) {
+        switch (m_presets.encoder_quality_preset) {
         case ALVR_QUALITY:
             amfEncoder->SetProperty(
                 AMF_VIDEO_ENCODER_HEVC_QUALITY_PRESET, AMF_VIDEO_ENCODER_HEVC_QUALITY_PRESET_QUALITY
@@ -374,8 +407,8 @@ amf::AMFComponentPtr VideoEncoderAMF::MakeEncoder(
             );
         }
 
-        if (Settings::Instance().m_enableAmfPreAnalysis) {
-            if (!Settings::Instance().m_useAmfPreproc || Settings::Instance().m_use10bitEncoder) {
+        if (m_presets.enable_amf_pre_analysis) {
+            if (!m_presets.use_amf_preproc || Settings::Instance().m_use10bitEncoder) {
                 Warn("Pre-analysis could not be enabled because \"Use preproc\" is not enabled or "
                      "\"Reduce color banding\" is enabled.");
             } else if (m_hasPreAnalysis) {
@@ -383,7 +416,7 @@ amf::AMFComponentPtr VideoEncoderAMF::MakeEncoder(
                      "enabled.");
                 amfEncoder->SetProperty(
                     AMF_VIDEO_ENCODER_HEVC_PRE_ANALYSIS_ENABLE,
-                    Settings::Instance().m_enableAmfPreAnalysis
+                    true
                 );
             } else {
                 Warn("Pre-analysis could not be enabled because your GPU does not support it for "
@@ -391,9 +424,10 @@ amf::AMFComponentPtr VideoEncoderAMF::MakeEncoder(
             }
         }
 
-        // Enable Full Range
         amfEncoder->SetProperty(
-            AMF_VIDEO_ENCODER_HEVC_NOMINAL_RANGE, AMF_VIDEO_ENCODER_HEVC_NOMINAL_RANGE_FULL
+            AMF_VIDEO_ENCODER_HEVC_NOMINAL_RANGE,
+            IsFullRange() ? AMF_VIDEO_ENCODER_HEVC_NOMINAL_RANGE_FULL
+                          : AMF_VIDEO_ENCODER_HEVC_NOMINAL_RANGE_STUDIO
         );
 
         // Use BT.2020 for HDR encoding, BT.709 otherwise.
@@ -401,7 +435,7 @@ amf::AMFComponentPtr VideoEncoderAMF::MakeEncoder(
             // Also specify the input formats for HDR to prevent incorrect color conversions
             amfEncoder->SetProperty(
                 AMF_VIDEO_ENCODER_HEVC_INPUT_COLOR_PROFILE,
-                AMF_VIDEO_CONVERTER_COLOR_PROFILE_FULL_2020
+                GetColorProfile()
             );
             amfEncoder->SetProperty(
                 AMF_VIDEO_ENCODER_HEVC_INPUT_TRANSFER_CHARACTERISTIC,
@@ -413,24 +447,24 @@ amf::AMFComponentPtr VideoEncoderAMF::MakeEncoder(
 
             amfEncoder->SetProperty(
                 AMF_VIDEO_ENCODER_HEVC_OUTPUT_COLOR_PROFILE,
-                AMF_VIDEO_CONVERTER_COLOR_PROFILE_FULL_2020
+                GetColorProfile()
             );
             amfEncoder->SetProperty(
                 AMF_VIDEO_ENCODER_HEVC_OUTPUT_TRANSFER_CHARACTERISTIC,
-                AMF_COLOR_TRANSFER_CHARACTERISTIC_GAMMA22
-            ); // sRGB
+                GetTransferCharacteristic()
+            );
             amfEncoder->SetProperty(
                 AMF_VIDEO_ENCODER_HEVC_OUTPUT_COLOR_PRIMARIES, AMF_COLOR_PRIMARIES_BT2020
             );
         } else {
             amfEncoder->SetProperty(
                 AMF_VIDEO_ENCODER_HEVC_OUTPUT_COLOR_PROFILE,
-                AMF_VIDEO_CONVERTER_COLOR_PROFILE_FULL_709
+                GetColorProfile()
             );
             amfEncoder->SetProperty(
                 AMF_VIDEO_ENCODER_HEVC_OUTPUT_TRANSFER_CHARACTERISTIC,
-                AMF_COLOR_TRANSFER_CHARACTERISTIC_GAMMA22
-            ); // sRGB
+                GetTransferCharacteristic()
+            );
             amfEncoder->SetProperty(
                 AMF_VIDEO_ENCODER_HEVC_OUTPUT_COLOR_PRIMARIES, AMF_COLOR_PRIMARIES_BT709
             );
@@ -453,6 +487,15 @@ amf::AMFComponentPtr VideoEncoderAMF::MakeEncoder(
         // Set infinite GOP length
         amfEncoder->SetProperty(AMF_VIDEO_ENCODER_HEVC_GOP_SIZE, 0);
 
+        if (Settings::Instance().m_intraRefreshRecoveryPeriod > 0) {
+            uint32_t period = Settings::Instance().m_intraRefreshRecoveryPeriod;
+            uint32_t ctbCount = ((width + 63) / 64) * ((height + 63) / 64);
+            amfEncoder->SetProperty(
+                AMF_VIDEO_ENCODER_HEVC_INTRA_REFRESH_NUM_CTBS_PER_SLOT,
+                (ctbCount + period - 1) / period
+            );
+        }
+
         // Disable AUD to produce the same stream format as VideoEncoderNVENC.
         // FIXME: This option doesn't work in 22.10.3, but works in versions prior 22.5.1
         amfEncoder->SetProperty(AMF_VIDEO_ENCODER_HEVC_INSERT_AUD, false);
@@ -496,7 +539,7 @@ amf::AMFComponentPtr VideoEncoderAMF::MakeEncoder(
             AMF_VIDEO_ENCODER_AV1_FRAMERATE, ::AMFConstructRate(frameRateIn, 1)
         );
 
-        switch (Settings::Instance().m_encoderQualityPreset) {
+        switch (m_presets.encoder_quality_preset) {
         case ALVR_QUALITY:
             amfEncoder->SetProperty(
                 AMF_VIDEO_ENCODER_AV1_QUALITY_PRESET, AMF_VIDEO_ENCODER_AV1_QUALITY_PRESET_QUALITY
@@ -545,8 +588,8 @@ amf::AMFComponentPtr VideoEncoderAMF::MakeEncoder(
             caps->GetProperty(AMF_VIDEO_ENCODER_AV1_CAP_PRE_ANALYSIS, &m_hasPreAnalysis);
         }
 
-        if (Settings::Instance().m_enableAmfPreAnalysis) {
-            if (!Settings::Instance().m_useAmfPreproc || Settings::Instance().m_use10bitEncoder) {
+        if (m_presets.enable_amf_pre_analysis) {
+            if (!m_presets.use_amf_preproc || Settings::Instance().m_use10bitEncoder) {
                 Warn("Pre-analysis could not be enabled because \"Use preproc\" is not enabled or "
                      "\"Reduce color banding\" is enabled.");
             } else if (m_hasPreAnalysis) {
@@ -554,7 +597,7 @@ amf::AMFComponentPtr VideoEncoderAMF::MakeEncoder(
                      "enabled.");
                 amfEncoder->SetProperty(
                     AMF_VIDEO_ENCODER_AV1_PRE_ANALYSIS_ENABLE,
-                    Settings::Instance().m_enableAmfPreAnalysis
+                    true
                 );
             } else {
                 Warn("Pre-analysis could not be enabled because your GPU does not support it for "
@@ -562,9 +605,9 @@ amf::AMFComponentPtr VideoEncoderAMF::MakeEncoder(
             }
         }
 
-        // Enable Full Range
+        amfEncoder->SetProperty(AMF_VIDEO_ENCODER_AV1_OUTPUT_COLOR_PROFILE, GetColorProfile());
         amfEncoder->SetProperty(
-            AMF_VIDEO_ENCODER_AV1_OUTPUT_COLOR_PROFILE, AMF_VIDEO_CONVERTER_COLOR_PROFILE_JPEG
+            AMF_VIDEO_ENCODER_AV1_OUTPUT_TRANSFER_CHARACTERISTIC, GetTransferCharacteristic()
         );
 
         // May impact performance but improves quality in high-motion areas
@@ -575,6 +618,17 @@ amf::AMFComponentPtr VideoEncoderAMF::MakeEncoder(
         // Set infinite GOP length
         amfEncoder->SetProperty(AMF_VIDEO_ENCODER_AV1_GOP_SIZE, 0);
 
+        if (Settings::Instance().m_intraRefreshRecoveryPeriod > 0) {
+            amfEncoder->SetProperty(
+                AMF_VIDEO_ENCODER_AV1_INTRA_REFRESH_MODE,
+                AMF_VIDEO_ENCODER_AV1_INTRA_REFRESH_MODE__CONTINUOUS
+            );
+            amfEncoder->SetProperty(
+                AMF_VIDEO_ENCODER_AV1_INTRAREFRESH_STRIPES,
+                Settings::Instance().m_intraRefreshRecoveryPeriod
+            );
+        }
+
         amfEncoder->SetProperty(
             AMF_VIDEO_ENCODER_AV1_VBV_BUFFER_SIZE, bitRateIn / frameRateIn * 1.2
         );
@@ -656,7 +710,7 @@ void VideoEncoderAMF::Initialize() {
             MakeConverter(m_surfaceFormat, m_renderWidth, m_renderHeight, inFormat)
         );
     } else {
-        if (Settings::Instance().m_useAmfPreproc) {
+        if (m_presets.use_amf_preproc) {
             inFormat = amf::AMF_SURFACE_NV12;
             m_amfComponents.emplace_back(
                 MakeConverter(m_surfaceFormat, m_renderWidth, m_renderHeight, inFormat)
@@ -677,6 +731,8 @@ void VideoEncoderAMF::Initialize() {
         m_amfComponents.back(), std::bind(&VideoEncoderAMF::Receive, this, std::placeholders::_1)
     ));
 
+    SetIntraRefreshPeriod(Settings::Instance().m_intraRefreshRecoveryPeriod);
+
     Debug("Successfully initialized VideoEncoderAMF.\n");
 }
 
@@ -689,6 +745,7 @@ void VideoEncoderAMF::Shutdown() {
         component->Release();
         delete component;
     }
+    m_amfComponents.clear();
 
     m_amfContext->Terminate();
     m_amfContext = NULL;
@@ -708,6 +765,15 @@ void VideoEncoderAMF::Transmit(
     // Surface is cached by AMF.
 
     auto params = GetDynamicEncoderParams();
+    if (params.updated && EncoderPresetsChanged(params.presets, m_presets)) {
+        // The preprocessor is part of the pipeline and pre-analysis can only be set before the
+        // encoder is initialized
+        Info("AMF: The encoder effort changed, recreating the encoder");
+        m_presets = params.presets;
+        Shutdown();
+        Initialize();
+        insertIDR = true;
+    }
     if (params.updated) {
         amf_int64 bitRateIn = params.bitrate_bps / params.framerate * m_refreshRate; // in bps
 
@@ -1,5 +1,6 @@
 #pragma once
 #include "VideoEncoder.h"
+#include "alvr_server/bindings.h"
 
 #include "../../shared/amf/public/common/AMFFactory.h"
 #include "../../shared/amf/public/common/AMFSTL.h"
@@ -108,6 +109,7 @@ private:
     int m_renderWidth;
     int m_renderHeight;
     int m_bitrateInMBits;
+    FfiEncoderPresets m_presets;
 
     bool m_hasQueryTimeout;
     bool m_hasPreAnalysis;
@@ -4,6 +4,34 @@
 #include "alvr_server/Logger.h"
 #include "alvr_server/Settings.h"
 #include "alvr_server/Utils.h"
+#include "alvr_server/bindings.h"
+
+#include <algorithm>
+#include <cmath>
+
+namespace {
+
+bool IsFullRange() { return Settings::Instance().m_colorRange == ALVR_COLOR_RANGE_FULL; }
+
+NV_ENC_VUI_TRANSFER_CHARACTERISTIC GetTransferCharacteristic() {
+    return Settings::Instance().m_transferFunction == ALVR_TRANSFER_FUNCTION_GAMMA22
+        ? NV_ENC_VUI_TRANSFER_CHARACTERISTIC_BT470M
+        : NV_ENC_VUI_TRANSFER_CHARACTERISTIC_SRGB;
+}
+
+GUID GetCodecGuid(int codec) {
+    switch (codec) {
+    case ALVR_CODEC_H264:
+        return NV_ENC_CODEC_H264_GUID;
+    case ALVR_CODEC_AV1:
+        return NV_ENC_CODEC_AV1_GUID;
+    case ALVR_CODEC_HEVC:
+    default:
+        return NV_ENC_CODEC_HEVC_GUID;
+    }
+}
+
+}
 
 VideoEncoderNVENC::VideoEncoderNVENC(std::shared_ptr<CD3DRender> pD3DRender, int width, int height)
     : m_pD3DRender(pD3DRender)
@@ -11,7 +39,10 @@ VideoEncoderNVENC::VideoEncoderNVENC(std::shared_ptr<CD3DRender> pD3DRender, int
     , m_refreshRate(Settings::Instance().m_refreshRate)
     , m_renderWidth(width)
     , m_renderHeight(height)
-    , m_bitrateInMBits(30) { }
+    , m_bitrateInMBits(30)
+    , m_presets(GetEncoderPresets())
+    , m_adaptiveQuantizationMode(Settings::Instance().m_nvencAdaptiveQuantizationMode)
+    , m_yuv444(Settings::Instance().m_chromaSubsampling == ALVR_CHROMA_SUBSAMPLING_444) { }
 
 VideoEncoderNVENC::~VideoEncoderNVENC() { }
 
@@ -45,6 +76,9 @@ void VideoEncoderNVENC::Initialize() {
         );
     }
 
+    m_tuningPreset = m_presets.nvenc_tuning_preset;
+    ValidatePresets();
+
     NV_ENC_INITIALIZE_PARAMS initializeParams = { NV_ENC_INITIALIZE_PARAMS_VER };
     NV_ENC_CONFIG encodeConfig = { NV_ENC_CONFIG_VER };
     initializeParams.encodeConfig = &encodeConfig;
@@ -67,6 +101,9 @@ void VideoEncoderNVENC::Initialize() {
         throw MakeException("NvEnc CreateEncoder failed. Code=%d %hs", e.getErrorCode(), e.what());
     }
 
+    // The intra refresh parameters are set through the NVENC options
+    SetIntraRefreshPeriod(Settings::Instance().m_intraRefreshRecoveryPeriod);
+
     Debug("CNvEncoder is successfully initialized.\n");
 }
 
@@ -96,6 +133,14 @@ void VideoEncoderNVENC::Transmit(
     ID3D11Texture2D* pTexture, uint64_t presentationTime, uint64_t targetTimestampNs, bool insertIDR
 ) {
     auto params = GetDynamicEncoderParams();
+    if (params.updated && EncoderPresetsChanged(params.presets, m_presets)) {
+        // The preset and the tuning are chosen when the session is opened
+        Info("NVENC: The encoder effort changed, recreating the encoder");
+        m_presets = params.presets;
+        Shutdown();
+        Initialize();
+        insertIDR = true;
+    }
     if (params.updated) {
         m_bitrateInMBits = params.bitrate_bps / 1'000'000;
         NV_ENC_INITIALIZE_PARAMS initializeParams = { NV_ENC_INITIALIZE_PARAMS_VER };
@@ -126,6 +171,15 @@ void VideoEncoderNVENC::Transmit(
         Debug("Inserting IDR frame.\n");
         picParams.encodePicFlags = NV_ENC_PIC_FLAG_FORCEIDR;
     }
+    if (Settings::Instance().m_enableGazeBitrateAllocation) {
+        // Without regions the map is not set and the frame is encoded uniformly
+        auto gazeRegions = GetGazeRegions();
+        if (gazeRegions.count > 0) {
+            FillQpDeltaMap(gazeRegions);
+            picParams.qpDeltaMap = m_qpDeltaMap.data();
+            picParams.qpDeltaMapSize = (uint32_t)m_qpDeltaMap.size();
+        }
+    }
     m_NvNecoder->EncodeFrame(vPacket, &picParams);
 
     for (std::vector<uint8_t>& packet : vPacket) {
@@ -155,7 +209,79 @@ void VideoEncoderNVENC::Transmit(
             fpOut.write(reinterpret_cast<char*>(buf), len);
         }
 
-        ParseFrameNals(m_codec, buf, len, targetTimestampNs, insertIDR);
+        ParseFrameNals(
+            m_codec,
+            buf,
+            len,
+            targetTimestampNs,
+            insertIDR,
+            (int)m_NvNecoder->GetLastFrameAverageQp()
+        );
+    }
+}
+
+void VideoEncoderNVENC::ValidatePresets() {
+    // The presets and tunings themselves are available with any driver that supports the NVENC API
+    // version used, otherwise opening the session fails. Some tunings and options depend on the GPU.
+    GUID codecGuid = GetCodecGuid(m_codec);
+
+    if (m_tuningPreset == NV_ENC_TUNING_INFO_LOSSLESS
+        && !m_NvNecoder->GetCapabilityValue(codecGuid, NV_ENC_CAPS_SUPPORT_LOSSLESS_ENCODE)) {
+        Warn("NVENC: Lossless encoding is not supported by this GPU, using low latency tuning");
+        m_tuningPreset = NV_ENC_TUNING_INFO_LOW_LATENCY;
+    }
+
+    if (m_adaptiveQuantizationMode == TemporalAQ
+        && !m_NvNecoder->GetCapabilityValue(codecGuid, NV_ENC_CAPS_SUPPORT_TEMPORAL_AQ)) {
+        Warn("NVENC: Temporal adaptive quantization is not supported by this GPU, using spatial");
+        m_adaptiveQuantizationMode = SpatialAQ;
+    }
+
+    if (Settings::Instance().m_chromaSubsampling == ALVR_CHROMA_SUBSAMPLING_422) {
+        Warn("NVENC: 4:2:2 chroma subsampling is not supported, using 4:2:0");
+    }
+    if (m_yuv444
+        && !m_NvNecoder->GetCapabilityValue(codecGuid, NV_ENC_CAPS_SUPPORT_YUV444_ENCODE)) {
+        Warn("NVENC: 4:4:4 chroma subsampling is not supported by this GPU, using 4:2:0");
+        m_yuv444 = false;
+    }
+
+    Info(
+        "NVENC: Using preset P%d, tuning %d, multi-pass %d",
+        m_presets.nvenc_quality_preset,
+        m_tuningPreset,
+        m_presets.nvenc_multi_pass
+    );
+}
+
+void VideoEncoderNVENC::FillQpDeltaMap(const FfiGazeRegions& regions) {
+    // The map has a value per macroblock for H264, per CTB for HEVC and per superblock for AV1
+    int blockSize = 64;
+    int qpOffset = regions.qpOffset * 255 / 51;
+    if (m_codec == ALVR_CODEC_H264) {
+        blockSize = 16;
+        qpOffset = regions.qpOffset;
+    } else if (m_codec == ALVR_CODEC_HEVC) {
+        blockSize = 32;
+        qpOffset = regions.qpOffset;
+    }
+
+    int blocksX = (m_renderWidth + blockSize - 1) / blockSize;
+    int blocksY = (m_renderHeight + blockSize - 1) / blockSize;
+    m_qpDeltaMap.assign(blocksX * blocksY, 0);
+
+    for (unsigned int i = 0; i < regions.count; i++) {
+        const float* rect = regions.rects[i];
+        int left = std::clamp((int)(rect[0] * m_renderWidth) / blockSize, 0, blocksX);
+        int top = std::clamp((int)(rect[1] * m_renderHeight) / blockSize, 0, blocksY);
+        int right = std::clamp((int)ceil(rect[2] * m_renderWidth / blockSize), 0, blocksX);
+        int bottom = std::clamp((int)ceil(rect[3] * m_renderHeight / blockSize), 0, blocksY);
+
+        for (int y = top; y < bottom; y++) {
+            for (int x = left; x < right; x++) {
+                m_qpDeltaMap[y * blocksX + x] = (int8_t)std::clamp(qpOffset, -128, 127);
+            }
+        }
     }
 }
 
@@ -168,23 +294,12 @@ void VideoEncoderNVENC::FillEncodeConfig(
 ) {
     auto& encodeConfig = *initializeParams.encodeConfig;
 
-    GUID encoderGUID;
-    switch (m_codec) {
-    case ALVR_CODEC_H264:
-        encoderGUID = NV_ENC_CODEC_H264_GUID;
-        break;
-    case ALVR_CODEC_HEVC:
-        encoderGUID = NV_ENC_CODEC_HEVC_GUID;
-        break;
-    case ALVR_CODEC_AV1:
-        encoderGUID = NV_ENC_CODEC_AV1_GUID;
-        break;
-    }
+    GUID encoderGUID = GetCodecGuid(m_codec);
 
     GUID qualityPreset;
     // See recommended NVENC settings for low-latency encoding.
     // https://docs.nvidia.com/video-technologies/video-codec-sdk/nvenc-video-encoder-api-prog-guide/#recommended-nvenc-settings
-    switch (Settings::Instance().m_nvencQualityPreset) {
+    switch (m_presets.nvenc_quality_preset) {
     case 7:
         qualityPreset = NV_ENC_PRESET_P7_GUID;
         break;
@@ -209,8 +324,7 @@ void VideoEncoderNVENC::FillEncodeConfig(
         break;
     }
 
-    NV_ENC_TUNING_INFO tuningPreset
-        = static_cast<NV_ENC_TUNING_INFO>(Settings::Instance().m_nvencTuningPreset);
+    NV_ENC_TUNING_INFO tuningPreset = static_cast<NV_ENC_TUNING_INFO>(m_tuningPreset);
 
     m_NvNecoder->CreateDefaultEncoderParams(
         &initializeParams, encoderGUID, qualityPreset, tuningPreset
@@ -271,17 +385,14 @@ void VideoEncoderNVENC::FillEncodeConfig(
 
         config.h264VUIParameters.videoSignalTypePresentFlag = 1;
         config.h264VUIParameters.videoFormat = NV_ENC_VUI_VIDEO_FORMAT_UNSPECIFIED;
-        config.h264VUIParameters.videoFullRangeFlag = 1;
+        config.h264VUIParameters.videoFullRangeFlag = IsFullRange();
         config.h264VUIParameters.colourDescriptionPresentFlag = 1;
+        config.h264VUIParameters.transferCharacteristics = GetTransferCharacteristic();
         if (Settings::Instance().m_enableHdr) {
             config.h264VUIParameters.colourPrimaries = NV_ENC_VUI_COLOR_PRIMARIES_BT2020;
-            config.h264VUIParameters.transferCharacteristics
-                = NV_ENC_VUI_TRANSFER_CHARACTERISTIC_SRGB;
             config.h264VUIParameters.colourMatrix = NV_ENC_VUI_MATRIX_COEFFS_BT2020_NCL;
         } else {
             config.h264VUIParameters.colourPrimaries = NV_ENC_VUI_COLOR_PRIMARIES_BT709;
-            config.h264VUIParameters.transferCharacteristics
-                = NV_ENC_VUI_TRANSFER_CHARACTERISTIC_SRGB;
             config.h264VUIParameters.colourMatrix = NV_ENC_VUI_MATRIX_COEFFS_BT709;
         }
     } break;
@@ -304,23 +415,27 @@ void VideoEncoderNVENC::FillEncodeConfig(
             encodeConfig.encodeCodecConfig.hevcConfig.pixelBitDepthMinus8 = 2;
         }
 
+        // The RGB input is converted to 4:4:4 by the encoder. HDR input is already NV12, the
+        // server does not negotiate 4:4:4 with HDR.
+        if (m_yuv444) {
+            encodeConfig.profileGUID = NV_ENC_HEVC_PROFILE_FREXT_GUID;
+            config.chromaFormatIDC = 3;
+        }
+
         if (Settings::Instance().m_fillerData) {
             config.enableFillerDataInsertion = Settings::Instance().m_rateControlMode == ALVR_CBR;
         }
 
         config.hevcVUIParameters.videoSignalTypePresentFlag = 1;
         config.hevcVUIParameters.videoFormat = NV_ENC_VUI_VIDEO_FORMAT_UNSPECIFIED;
-        config.hevcVUIParameters.videoFullRangeFlag = 1;
+        config.hevcVUIParameters.videoFullRangeFlag = IsFullRange();
         config.hevcVUIParameters.colourDescriptionPresentFlag = 1;
+        config.hevcVUIParameters.transferCharacteristics = GetTransferCharacteristic();
         if (Settings::Instance().m_enableHdr) {
             config.hevcVUIParameters.colourPrimaries = NV_ENC_VUI_COLOR_PRIMARIES_BT2020;
-            config.hevcVUIParameters.transferCharacteristics
-                = NV_ENC_VUI_TRANSFER_CHARACTERISTIC_SRGB;
             config.hevcVUIParameters.colourMatrix = NV_ENC_VUI_MATRIX_COEFFS_BT2020_NCL;
         } else {
             config.hevcVUIParameters.colourPrimaries = NV_ENC_VUI_COLOR_PRIMARIES_BT709;
-            config.hevcVUIParameters.transferCharacteristics
-                = NV_ENC_VUI_TRANSFER_CHARACTERISTIC_SRGB;
             config.hevcVUIParameters.colourMatrix = NV_ENC_VUI_MATRIX_COEFFS_BT709;
         }
     } break;
@@ -348,14 +463,13 @@ void VideoEncoderNVENC::FillEncodeConfig(
         }
 
         config.chromaFormatIDC = 1; // 4:2:0, 4:4:4 currently not supported
-        config.colorRange = 1;
+        config.colorRange = IsFullRange();
+        config.transferCharacteristics = GetTransferCharacteristic();
         if (Settings::Instance().m_enableHdr) {
             config.colorPrimaries = NV_ENC_VUI_COLOR_PRIMARIES_BT2020;
-            config.transferCharacteristics = NV_ENC_VUI_TRANSFER_CHARACTERISTIC_SRGB;
             config.matrixCoefficients = NV_ENC_VUI_MATRIX_COEFFS_BT2020_NCL;
         } else {
             config.colorPrimaries = NV_ENC_VUI_COLOR_PRIMARIES_BT709;
-            config.transferCharacteristics = NV_ENC_VUI_TRANSFER_CHARACTERISTIC_SRGB;
             config.matrixCoefficients = NV_ENC_VUI_MATRIX_COEFFS_BT709;
         }
     } break;
@@ -378,8 +492,7 @@ void VideoEncoderNVENC::FillEncodeConfig(
         encodeConfig.rcParams.rateControlMode = NV_ENC_PARAMS_RC_VBR;
         break;
     }
-    encodeConfig.rcParams.multiPass
-        = static_cast<NV_ENC_MULTI_PASS>(Settings::Instance().m_nvencMultiPass);
+    encodeConfig.rcParams.multiPass = static_cast<NV_ENC_MULTI_PASS>(m_presets.nvenc_multi_pass);
     encodeConfig.rcParams.lowDelayKeyFrameScale = 1;
 
     if (Settings::Instance().m_nvencLowDelayKeyFrameScale != -1) {
@@ -393,9 +506,13 @@ void VideoEncoderNVENC::FillEncodeConfig(
     encodeConfig.rcParams.vbvInitialDelay = maxFrameSize * 1.1;
     encodeConfig.rcParams.maxBitRate = static_cast<uint32_t>(bitrate_bps);
     encodeConfig.rcParams.averageBitRate = static_cast<uint32_t>(bitrate_bps);
-    if (Settings::Instance().m_nvencAdaptiveQuantizationMode == SpatialAQ) {
+    // The delta map is set per frame around the gaze point
+    if (Settings::Instance().m_enableGazeBitrateAllocation) {
+        encodeConfig.rcParams.qpMapMode = NV_ENC_QP_MAP_DELTA;
+    }
+    if (m_adaptiveQuantizationMode == SpatialAQ) {
         encodeConfig.rcParams.enableAQ = 1;
-    } else if (Settings::Instance().m_nvencAdaptiveQuantizationMode == TemporalAQ) {
+    } else if (m_adaptiveQuantizationMode == TemporalAQ) {
         encodeConfig.rcParams.enableTemporalAQ = 1;
     }
 
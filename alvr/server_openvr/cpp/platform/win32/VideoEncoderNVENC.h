@@ -4,6 +4,7 @@
 #include "VideoEncoder.h"
 #include "shared/d3drender.h"
 #include <memory>
+#include <vector>
 
 enum AdaptiveQuantizationMode { SpatialAQ = 1, TemporalAQ = 2 };
 
@@ -24,6 +25,8 @@ public:
     );
 
 private:
+    // Falls back to the supported options
+    void ValidatePresets();
     void FillEncodeConfig(
         NV_ENC_INITIALIZE_PARAMS& initializeParams,
         int refreshRate,
@@ -31,6 +34,7 @@ private:
         int renderHeight,
         uint64_t bitrate_bps
     );
+    void FillQpDeltaMap(const FfiGazeRegions& regions);
 
     std::ofstream fpOut;
     std::shared_ptr<NvEncoder> m_NvNecoder;
@@ -42,4 +46,10 @@ private:
     int m_renderWidth;
     int m_renderHeight;
     int m_bitrateInMBits;
+    FfiEncoderPresets m_presets;
+    // Can fall back to low latency if the GPU doesn't support the preset
+    uint32_t m_tuningPreset;
+    uint32_t m_adaptiveQuantizationMode;
+    bool m_yuv444;
+    std::vector<int8_t> m_qpDeltaMap;
 };
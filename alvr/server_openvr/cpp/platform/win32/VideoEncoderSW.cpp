@@ -8,16 +8,21 @@
 
 #include <algorithm>
 #include <array>
+#include <chrono>
+#include <cstring>
 #include <iostream>
 #include <string>
 
-VideoEncoderSW::VideoEncoderSW(std::shared_ptr<CD3DRender> d3dRender, int width, int height)
+VideoEncoderSW::VideoEncoderSW(
+    std::shared_ptr<CD3DRender> d3dRender, int width, int height, bool fallback
+)
     : m_d3dRender(d3dRender)
-    , m_codec(ALVR_CODEC_H264)
+    , m_codec(Settings::Instance().m_codec == ALVR_CODEC_AV1 ? ALVR_CODEC_AV1 : ALVR_CODEC_H264)
     , m_refreshRate(Settings::Instance().m_refreshRate)
     , m_renderWidth(width)
     , m_renderHeight(height)
-    , m_bitrateInMBits(30) {
+    , m_bitrateInMBits(30)
+    , m_fallback(fallback) {
     // #ifdef ALVR_DEBUG_LOG
     //     av_log_set_level(AV_LOG_DEBUG);
     //     av_log_set_callback(LibVALog);
@@ -40,44 +45,68 @@ void VideoEncoderSW::Initialize() {
 
     const auto& settings = Settings::Instance();
 
-    // Query codec
-    AVCodecID codecId = ToFFMPEGCodec(m_codec);
-    if (!codecId)
-        throw MakeException("Invalid requested codec %d", m_codec);
+    if (settings.m_codec == ALVR_CODEC_HEVC) {
+        Warn("HEVC is not supported by the software encoder. Using h264 instead.");
+    }
 
-    const AVCodec* codec = avcodec_find_encoder(codecId);
-    if (codec == NULL)
-        throw MakeException("Could not find codec id %d", codecId);
+    // Query codec
+    const AVCodec* codec = nullptr;
+    if (m_codec == ALVR_CODEC_AV1) {
+        codec = avcodec_find_encoder_by_name("libsvtav1");
+        if (codec == NULL) {
+            Warn("SVT-AV1 encoder is not available. Using h264 instead.");
+            m_codec = ALVR_CODEC_H264;
+        }
+    }
+    if (codec == NULL) {
+        AVCodecID codecId = ToFFMPEGCodec(m_codec);
+        if (!codecId)
+            throw MakeException("Invalid requested codec %d", m_codec);
+
+        codec = avcodec_find_encoder(codecId);
+        if (codec == NULL)
+            throw MakeException("Could not find codec id %d", codecId);
+    }
 
     // Initialize CodecContext
     m_codecContext = avcodec_alloc_context3(codec);
     if (m_codecContext == NULL)
-        throw MakeException("Failed to allocate encoder id %d", codecId);
+        throw MakeException("Failed to allocate encoder %s", codec->name);
 
     // Set codec settings
     AVDictionary* opt = NULL;
-    av_dict_set(&opt, "preset", "ultrafast", 0);
-    av_dict_set(&opt, "tune", "zerolatency", 0);
+    if (m_codec == ALVR_CODEC_AV1) {
+        av_dict_set_int(&opt, "preset", settings.m_swSvtAv1Preset, 0);
+        // Low delay prediction structure (no reordering), subjective quality tuning
+        std::string params = "pred-struct=1:tune=0:fast-decode=1";
+        if (settings.m_swThreadCount > 0) {
+            params += ":lp=" + std::to_string(settings.m_swThreadCount);
+        }
+        av_dict_set(&opt, "svtav1-params", params.c_str(), 0);
+    } else {
+        av_dict_set(&opt, "preset", "ultrafast", 0);
+        av_dict_set(&opt, "tune", "zerolatency", 0);
 
-    switch (settings.m_h264Profile) {
-    case ALVR_H264_PROFILE_BASELINE:
-        m_codecContext->profile = FF_PROFILE_H264_BASELINE;
-        break;
-    case ALVR_H264_PROFILE_MAIN:
-        m_codecContext->profile = FF_PROFILE_H264_MAIN;
-        break;
-    default:
-    case ALVR_H264_PROFILE_HIGH:
-        m_codecContext->profile = FF_PROFILE_H264_HIGH;
-        break;
-    }
-    switch (settings.m_entropyCoding) {
-    case ALVR_CABAC:
-        av_dict_set(&opt, "coder", "ac", 0);
-        break;
-    case ALVR_CAVLC:
-        av_dict_set(&opt, "coder", "vlc", 0);
-        break;
+        switch (settings.m_h264Profile) {
+        case ALVR_H264_PROFILE_BASELINE:
+            m_codecContext->profile = FF_PROFILE_H264_BASELINE;
+            break;
+        case ALVR_H264_PROFILE_MAIN:
+            m_codecContext->profile = FF_PROFILE_H264_MAIN;
+            break;
+        default:
+        case ALVR_H264_PROFILE_HIGH:
+            m_codecContext->profile = FF_PROFILE_H264_HIGH;
+            break;
+        }
+        switch (settings.m_entropyCoding) {
+        case ALVR_CABAC:
+            av_dict_set(&opt, "coder", "ac", 0);
+            break;
+        case ALVR_CAVLC:
+            av_dict_set(&opt, "coder", "vlc", 0);
+            break;
+        }
     }
 
     m_codecContext->width = m_renderWidth;
@@ -87,14 +116,16 @@ void VideoEncoderSW::Initialize() {
     m_codecContext->sample_aspect_ratio = AVRational { 1, 1 };
     m_codecContext->pix_fmt
         = settings.m_use10bitEncoder ? AV_PIX_FMT_YUV420P10 : AV_PIX_FMT_YUV420P;
-    m_codecContext->color_range = AVCOL_RANGE_JPEG;
+    m_codecContext->color_range
+        = settings.m_colorRange == ALVR_COLOR_RANGE_FULL ? AVCOL_RANGE_JPEG : AVCOL_RANGE_MPEG;
+    m_codecContext->color_trc = settings.m_transferFunction == ALVR_TRANSFER_FUNCTION_GAMMA22
+        ? AVCOL_TRC_GAMMA22
+        : AVCOL_TRC_IEC61966_2_1;
     if (settings.m_enableHdr) {
         m_codecContext->color_primaries = AVCOL_PRI_BT2020;
-        m_codecContext->color_trc = AVCOL_TRC_GAMMA22;
         m_codecContext->colorspace = AVCOL_SPC_BT2020_NCL;
     } else {
         m_codecContext->color_primaries = AVCOL_PRI_BT709;
-        m_codecContext->color_trc = AVCOL_TRC_GAMMA22;
         m_codecContext->colorspace = AVCOL_SPC_BT709;
     }
     m_codecContext->max_b_frames = 0;
@@ -146,7 +177,19 @@ void VideoEncoderSW::Shutdown() {
 void VideoEncoderSW::Transmit(
     ID3D11Texture2D* pTexture, uint64_t presentationTime, uint64_t targetTimestampNs, bool insertIDR
 ) {
-    // Handle bitrate changes
+    if (m_fallback) {
+        auto now = std::chrono::steady_clock::now();
+        if (now > m_lastFallbackWarning + SW_FALLBACK_WARNING_INTERVAL) {
+            m_lastFallbackWarning = now;
+            Warn(
+                "No hardware encoder is available, streaming with software encoding. Expect "
+                "higher latency and CPU usage."
+            );
+        }
+    }
+
+    // Handle bitrate changes. libx264 reconfigures itself when these change, SVT-AV1 keeps the
+    // initial bitrate
     auto params = GetDynamicEncoderParams();
     if (params.updated) {
         m_codecContext->bit_rate = params.bitrate_bps;
@@ -204,6 +247,23 @@ void VideoEncoderSW::Transmit(
             return;
         }
         Debug("Successfully initialized SWScaler.");
+
+        // swscale outputs limited range by default, make it match the bitstream metadata. With HDR
+        // the input is already YUV with the same range.
+        int fullRange = m_codecContext->color_range == AVCOL_RANGE_JPEG;
+        const int* coefficients = sws_getCoefficients(
+            Settings::Instance().m_enableHdr ? SWS_CS_BT2020 : SWS_CS_ITU709
+        );
+        sws_setColorspaceDetails(
+            m_scalerContext,
+            coefficients,
+            Settings::Instance().m_enableHdr ? fullRange : 1,
+            coefficients,
+            fullRange,
+            0,
+            1 << 16,
+            1 << 16
+        );
     }
 
     // We got the texture, populate tansferredFrame with data
@@ -253,7 +313,16 @@ void VideoEncoderSW::Transmit(
         }
         // Send encoded frame to client
         bool isIdr = (packet->flags & AV_PKT_FLAG_KEY) != 0;
-        ParseFrameNals(m_codec, packet->data, packet->size, packet->pts, isIdr);
+        // The quality is the QP scaled by FF_QP2LAMBDA
+        int averageQp = -1;
+        size_t statsSize = 0;
+        auto stats = av_packet_get_side_data(packet, AV_PKT_DATA_QUALITY_STATS, &statsSize);
+        if (stats && statsSize >= sizeof(uint32_t)) {
+            uint32_t quality;
+            memcpy(&quality, stats, sizeof(quality));
+            averageQp = (int)(quality / FF_QP2LAMBDA);
+        }
+        ParseFrameNals(m_codec, packet->data, packet->size, packet->pts, isIdr, averageQp);
         // Debug("Sent encoded packet to client");
         av_packet_free(&packet);
     }
@@ -2,6 +2,7 @@
 
 #pragma once
 
+#include <chrono>
 #include <wrl.h>
 
 #include "ALVR-common/packet_types.h"
@@ -20,7 +21,9 @@ using Microsoft::WRL::ComPtr;
 // Software video encoder using FFMPEG
 class VideoEncoderSW : public VideoEncoder {
 public:
-    VideoEncoderSW(std::shared_ptr<CD3DRender> pD3DRender, int width, int height);
+    VideoEncoderSW(
+        std::shared_ptr<CD3DRender> pD3DRender, int width, int height, bool fallback
+    );
     ~VideoEncoderSW();
 
     void Initialize();
@@ -55,6 +58,9 @@ private:
     int m_renderWidth;
     int m_renderHeight;
     int m_bitrateInMBits;
+
+    bool m_fallback;
+    std::chrono::steady_clock::time_point m_lastFallbackWarning;
 };
 
 #endif // ALVR_GPL
@@ -33,7 +33,8 @@ VideoEncoderVPL::VideoEncoderVPL(std::shared_ptr<CD3DRender> pD3DRender, int wid
     : m_pD3DRender(pD3DRender)
     , m_renderWidth(width)
     , m_renderHeight(height)
-    , m_bitrateInMBits(30) {
+    , m_bitrateInMBits(30)
+    , m_presets(GetEncoderPresets()) {
     VPL_DEBUG("constructed");
 }
 
@@ -72,6 +73,12 @@ void VideoEncoderVPL::Transmit(
 
     auto dynParams = GetDynamicEncoderParams();
     if (dynParams.updated) {
+        // The target usage is the only preset used by VPL, it can be changed with a reset
+        if (EncoderPresetsChanged(dynParams.presets, m_presets)) {
+            m_presets = dynParams.presets;
+            ChooseQualityPreset();
+            m_vplEncodeParams.mfx.TargetUsage = m_vplQualityPreset;
+        }
         m_vplEncodeParams.mfx.TargetKbps = dynParams.bitrate_bps / 1000;
         MFXVideoENCODE_Reset(m_vplSession, &m_vplEncodeParams);
     }
@@ -180,7 +187,7 @@ void VideoEncoderVPL::InitVpl() {
 void VideoEncoderVPL::InitVplEncode() {
     m_vplEncodeParams.IOPattern = MFX_IOPATTERN_IN_VIDEO_MEMORY;
     m_vplEncodeParams.mfx.LowPower = MFX_CODINGOPTION_ON;
-    m_vplEncodeParams.AsyncDepth = 1;
+    m_vplEncodeParams.AsyncDepth = Settings::Instance().m_encoderAsyncDepth;
     m_vplEncodeParams.mfx.CodecId = m_vplCodec;
     m_vplEncodeParams.mfx.CodecProfile = m_vplCodecProfile;
     m_vplEncodeParams.mfx.TargetUsage = m_vplQualityPreset;
@@ -311,7 +318,22 @@ void VideoEncoderVPL::ChooseParams() {
         }
     }
 
-    switch (s.m_encoderQualityPreset) {
+    ChooseQualityPreset();
+
+    switch (s.m_rateControlMode) {
+    case ALVR_CBR:
+        m_vplRateControlMode = MFX_RATECONTROL_CBR;
+        break;
+    case ALVR_VBR:
+        m_vplRateControlMode = MFX_RATECONTROL_VBR;
+        break;
+    default:
+        ERROR_THROW("invalid rate control mode");
+    }
+}
+
+void VideoEncoderVPL::ChooseQualityPreset() {
+    switch (m_presets.encoder_quality_preset) {
     case ALVR_QUALITY:
         m_vplQualityPreset = MFX_TARGETUSAGE_BEST_QUALITY;
         break;
@@ -324,17 +346,6 @@ void VideoEncoderVPL::ChooseParams() {
     default:
         ERROR_THROW("invalid encoder quality preset");
     }
-
-    switch (s.m_rateControlMode) {
-    case ALVR_CBR:
-        m_vplRateControlMode = MFX_RATECONTROL_CBR;
-        break;
-    case ALVR_VBR:
-        m_vplRateControlMode = MFX_RATECONTROL_VBR;
-        break;
-    default:
-        ERROR_THROW("invalid rate control mode");
-    }
 }
 
 void VideoEncoderVPL::CheckVPLConfig() {
@@ -1,6 +1,7 @@
 #pragma once
 
 #include "VideoEncoder.h"
+#include "alvr_server/bindings.h"
 #include "shared/d3drender.h"
 #include <atlbase.h>
 #include <d3d11.h>
@@ -34,6 +35,7 @@ public:
 private:
     void CheckVPLConfig();
     void ChooseParams();
+    void ChooseQualityPreset();
     void InitTransferTex();
     void InitVpl();
     void InitVplEncode();
@@ -46,6 +48,7 @@ private:
     int m_renderHeight;
     int m_refreshRate;
     int m_bitrateInMBits;
+    FfiEncoderPresets m_presets;
 
     mfxU32 m_vplCodec;
     mfxU32 m_vplCodecProfile;
@@ -17,26 +17,100 @@ use bindings::*;
 use alvr_common::{
     BUTTON_INFO, HAND_LEFT_ID, HAND_RIGHT_ID, HAND_TRACKER_LEFT_ID, HAND_TRACKER_RIGHT_ID, HEAD_ID,
     Pose, ViewParams, error,
+    glam::{UVec2, Vec2},
     parking_lot::{Mutex, RwLock},
     settings_schema::Switch,
     warn,
 };
 use alvr_filesystem as afs;
 use alvr_packets::{ButtonValue, Haptics};
-use alvr_server_core::{HandType, ServerCoreContext, ServerCoreEvent};
-use alvr_session::{CodecType, ControllersConfig};
+use alvr_server_core::{EncoderPresets, HandType, ServerCoreContext, ServerCoreEvent};
+use alvr_server_io::{SteamvrSettingEdit, SteamvrSettingValue};
+use alvr_session::{ChaperoneSyncMode, CodecType, ControllersConfig};
 use std::{
     collections::VecDeque,
-    ffi::{CString, OsStr, c_char, c_void},
+    ffi::{CStr, CString, OsStr, c_char, c_void},
     ptr,
-    sync::{Once, mpsc},
+    sync::{
+        Once,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
     thread,
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 static SERVER_CORE_CONTEXT: RwLock<Option<ServerCoreContext>> = RwLock::new(None);
 static LOCAL_VIEW_PARAMS: RwLock<[ViewParams; 2]> = RwLock::new([ViewParams::DUMMY; 2]);
 static HEAD_POSE_QUEUE: Mutex<VecDeque<(Duration, Pose)>> = Mutex::new(VecDeque::new());
+// Frame timestamp and timestamp of the head pose the frame has been re-projected to
+static SERVER_REPROJECTION_QUEUE: Mutex<VecDeque<(Duration, Duration)>> =
+    Mutex::new(VecDeque::new());
+// Pose of the last frame sent, used for the frames whose pose is no longer in the queues
+static LAST_FRAME_HEAD_POSE: Mutex<Option<Pose>> = Mutex::new(None);
+static MISSING_POSE_FRAMES: AtomicUsize = AtomicUsize::new(0);
+static LAST_MISSING_POSE_WARNING: Mutex<Option<Instant>> = Mutex::new(None);
+// The chaperone is set again when the OpenVR client is initialized after a SteamVR restart
+// The driver reports it when the HMD is activated, which can happen before the context exists
+static MOTION_VECTORS_SUPPORTED: AtomicBool = AtomicBool::new(false);
+static LAST_PLAYSPACE: Mutex<Option<(Vec2, Vec<Vec2>)>> = Mutex::new(None);
+
+fn set_steamvr_setting(edit: &SteamvrSettingEdit) {
+    let (Ok(section), Ok(key)) = (
+        CString::new(edit.section.clone()),
+        CString::new(edit.key.clone()),
+    ) else {
+        error!(
+            "Invalid SteamVR setting {:?}/{:?}, skipping it",
+            edit.section, edit.key
+        );
+        return;
+    };
+
+    unsafe {
+        match edit.value {
+            Some(SteamvrSettingValue::Bool(value)) => {
+                SetSteamvrSettingBool(section.as_ptr(), key.as_ptr(), value)
+            }
+            Some(SteamvrSettingValue::Int(value)) => {
+                SetSteamvrSettingInt(section.as_ptr(), key.as_ptr(), value)
+            }
+            Some(SteamvrSettingValue::Float(value)) => {
+                SetSteamvrSettingFloat(section.as_ptr(), key.as_ptr(), value)
+            }
+            None => RemoveSteamvrSetting(section.as_ptr(), key.as_ptr()),
+        }
+    }
+}
+
+fn set_chaperone(area: Vec2, perimeter: &[Vec2]) {
+    match alvr_server_core::settings().headset.chaperone_sync {
+        ChaperoneSyncMode::SyncFromHeadset => {
+            let perimeter = if perimeter.len() >= 3 {
+                perimeter.to_vec()
+            } else {
+                let half_area = area / 2.0;
+                vec![
+                    Vec2::new(-half_area.x, -half_area.y),
+                    Vec2::new(-half_area.x, half_area.y),
+                    Vec2::new(half_area.x, half_area.y),
+                    Vec2::new(half_area.x, -half_area.y),
+                ]
+            };
+
+            unsafe {
+                SetChaperoneBounds(
+                    perimeter.as_ptr().cast(),
+                    perimeter.len() as _,
+                    area.x,
+                    area.y,
+                )
+            };
+        }
+        ChaperoneSyncMode::KeepSteamVr => (),
+        ChaperoneSyncMode::Disabled => unsafe { SetChaperoneArea(area.x, area.y) },
+    }
+}
 
 fn event_loop(events_receiver: mpsc::Receiver<ServerCoreEvent>) {
     thread::spawn(move || {
@@ -70,9 +144,10 @@ fn event_loop(events_receiver: mpsc::Receiver<ServerCoreEvent>) {
                 ServerCoreEvent::Battery(info) => unsafe {
                     SetBattery(info.device_id, info.gauge_value, info.is_plugged)
                 },
-                ServerCoreEvent::PlayspaceSync(bounds) => unsafe {
-                    SetChaperoneArea(bounds.x, bounds.y)
-                },
+                ServerCoreEvent::PlayspaceSync { area, perimeter } => {
+                    set_chaperone(area, &perimeter);
+                    *LAST_PLAYSPACE.lock() = Some((area, perimeter));
+                }
                 ServerCoreEvent::LocalViewParams(params) => unsafe {
                     *LOCAL_VIEW_PARAMS.write() = params;
 
@@ -86,7 +161,8 @@ fn event_loop(events_receiver: mpsc::Receiver<ServerCoreEvent>) {
                     let headset_config = &alvr_server_core::settings().headset;
 
                     let controllers_config = headset_config.controllers.clone().into_option();
-                    let track_body = headset_config.body_tracking.enabled();
+                    let track_body = headset_config.body_tracking.enabled()
+                        || headset_config.external_trackers.enabled();
 
                     let tracked = controllers_config.as_ref().is_some_and(|c| c.tracked);
                     let detached_controllers = headset_config
@@ -100,7 +176,9 @@ fn event_loop(events_receiver: mpsc::Receiver<ServerCoreEvent>) {
                         let controllers_pose_time_offset = context.get_tracker_pose_time_offset();
                         // We need to remove the additional offset that SteamVR adds
                         let target_controller_timestamp =
-                            target_timestamp.saturating_sub(controllers_pose_time_offset);
+                            poll_timestamp + context.get_controllers_motion_to_photon_latency();
+                        let target_controller_timestamp = target_controller_timestamp
+                            .saturating_sub(controllers_pose_time_offset);
 
                         let ffi_head_motion = if let Some(motion) =
                             context.get_device_motion(*HEAD_ID, poll_timestamp)
@@ -158,7 +236,7 @@ fn event_loop(events_receiver: mpsc::Receiver<ServerCoreEvent>) {
                         ) = if let Some(ControllersConfig {
                             hand_skeleton: Switch::Enabled(hand_skeleton_config),
                             ..
-                        }) = controllers_config
+                        }) = &controllers_config
                         {
                             let left_hand_skeleton = context
                                 .get_hand_skeleton(HandType::Left, poll_timestamp)
@@ -189,6 +267,39 @@ fn event_loop(events_receiver: mpsc::Receiver<ServerCoreEvent>) {
                             (None, None, false, false)
                         };
 
+                        // A side that reports no controller is using hand tracking
+                        let (
+                            ffi_left_controller_motion,
+                            ffi_left_hand_skeleton,
+                            ffi_right_controller_motion,
+                            ffi_right_hand_skeleton,
+                        ) = if let Some(enabled) =
+                            controllers_config.as_ref().map(|c| &c.enabled_devices)
+                        {
+                            let left_controller_motion =
+                                ffi_left_controller_motion.filter(|_| enabled.left_controller);
+                            let right_controller_motion =
+                                ffi_right_controller_motion.filter(|_| enabled.right_controller);
+                            let show_left_hand =
+                                left_controller_motion.is_some() || enabled.left_hand;
+                            let show_right_hand =
+                                right_controller_motion.is_some() || enabled.right_hand;
+
+                            (
+                                left_controller_motion,
+                                ffi_left_hand_skeleton.filter(|_| show_left_hand),
+                                right_controller_motion,
+                                ffi_right_hand_skeleton.filter(|_| show_right_hand),
+                            )
+                        } else {
+                            (
+                                ffi_left_controller_motion,
+                                ffi_left_hand_skeleton,
+                                ffi_right_controller_motion,
+                                ffi_right_hand_skeleton,
+                            )
+                        };
+
                         let ffi_left_hand_data = FfiHandData {
                             controllerMotion: if let Some(motion) = &ffi_left_controller_motion {
                                 motion
@@ -251,6 +362,16 @@ fn event_loop(events_receiver: mpsc::Receiver<ServerCoreEvent>) {
                                 ffi_body_tracker_motions.len() as i32,
                             )
                         };
+
+                        let ffi_eye_gaze =
+                            context.get_forwarded_eye_gaze().map(tracking::to_ffi_quat);
+                        unsafe {
+                            SetEyeGaze(
+                                ffi_eye_gaze
+                                    .as_ref()
+                                    .map_or(ptr::null(), |gaze| ptr::from_ref(gaze)),
+                            )
+                        };
                     }
                 }
                 ServerCoreEvent::Buttons(entries) => {
@@ -273,6 +394,29 @@ fn event_loop(events_receiver: mpsc::Receiver<ServerCoreEvent>) {
                 }
                 ServerCoreEvent::RequestIDR => unsafe { RequestIDR() },
                 ServerCoreEvent::CaptureFrame => unsafe { CaptureFrame() },
+                ServerCoreEvent::SetTestPattern(enabled) => unsafe { SetTestPattern(enabled) },
+                ServerCoreEvent::SetMirrorEnabled(enabled) => unsafe { SetMirrorEnabled(enabled) },
+                ServerCoreEvent::SetEncodingPaused {
+                    paused,
+                    keepalive_frames,
+                } => unsafe { SetEncodingPaused(paused, keepalive_frames) },
+                ServerCoreEvent::SetIdleFrameRate(fps) => unsafe {
+                    SetIdleFrameRate(fps.unwrap_or(0.0))
+                },
+                ServerCoreEvent::SetSteamvrSettings(edits) => {
+                    for edit in edits {
+                        set_steamvr_setting(&edit);
+                    }
+
+                    // The dashboard is sent what SteamVR saved, not what was requested
+                    if unsafe { SyncSteamvrSettings() } {
+                        if let Some(context) = &*SERVER_CORE_CONTEXT.read() {
+                            context.report_steamvr_settings_changed();
+                        }
+                    } else {
+                        error!("SteamVR failed to save the video settings");
+                    }
+                }
                 ServerCoreEvent::GameRenderLatencyFeedback(game_latency) => {
                     if cfg!(target_os = "linux") && game_latency.as_secs_f32() > 0.25 {
                         let now = Instant::now();
@@ -312,10 +456,13 @@ extern "C" fn driver_ready_idle(set_default_chap: bool) {
         unsafe { InitOpenvrClient() };
 
         if set_default_chap {
+            let (area, perimeter) = LAST_PLAYSPACE
+                .lock()
+                .clone()
+                .unwrap_or((Vec2::new(2.0, 2.0), vec![]));
+
             // call this when inside a new thread. Calling this on the parent thread will crash SteamVR
-            unsafe {
-                SetChaperoneArea(2.0, 2.0);
-            }
+            set_chaperone(area, &perimeter);
         }
     });
 }
@@ -373,19 +520,96 @@ extern "C" fn set_video_config_nals(buffer_ptr: *const u8, len: i32, codec: i32)
     }
 }
 
-extern "C" fn send_video(timestamp_ns: u64, buffer_ptr: *mut u8, len: i32, is_idr: bool) {
+extern "C" fn set_intra_refresh_period(period_frames: u32) {
+    if let Some(context) = &*SERVER_CORE_CONTEXT.read() {
+        context.set_intra_refresh_period((period_frames > 0).then_some(period_frames));
+    }
+}
+
+extern "C" fn report_encoder_fallback(
+    selected_backend: *const c_char,
+    used_backend: *const c_char,
+) {
+    let selected_backend = unsafe { CStr::from_ptr(selected_backend) };
+    let used_backend = unsafe { CStr::from_ptr(used_backend) };
+
+    if let Some(context) = &*SERVER_CORE_CONTEXT.read() {
+        context.report_encoder_fallback(
+            &selected_backend.to_string_lossy(),
+            &used_backend.to_string_lossy(),
+        );
+    }
+}
+
+// average_qp is negative if the encoder doesn't report it
+// Rate limited, the pose queue can lag behind for many frames in a row
+fn report_missing_pose(timestamp: Duration) {
+    let count = MISSING_POSE_FRAMES.fetch_add(1, Ordering::Relaxed) + 1;
+
+    let now = Instant::now();
+    let mut last_warning_lock = LAST_MISSING_POSE_WARNING.lock();
+    if last_warning_lock.is_none_or(|last| now.saturating_duration_since(last).as_secs() >= 5) {
+        *last_warning_lock = Some(now);
+
+        warn!(
+            "No head pose found for frame {timestamp:?}, using the pose of the last frame. \
+            Frames without pose so far: {count}"
+        );
+    }
+}
+
+extern "C" fn send_video(
+    timestamp_ns: u64,
+    buffer_ptr: *mut u8,
+    len: i32,
+    is_idr: bool,
+    average_qp: i32,
+) {
     if let Some(context) = &*SERVER_CORE_CONTEXT.read() {
         let timestamp = Duration::from_nanos(timestamp_ns);
         let buffer = unsafe { std::slice::from_raw_parts(buffer_ptr, len as usize) };
 
-        let Some(head_pose) = HEAD_POSE_QUEUE
+        let head_pose_queue_lock = HEAD_POSE_QUEUE.lock();
+        let find_head_pose = |timestamp| {
+            head_pose_queue_lock
+                .iter()
+                .find_map(|(ts, pose)| (*ts == timestamp).then_some(*pose))
+        };
+
+        let reprojection_timestamp = SERVER_REPROJECTION_QUEUE
             .lock()
             .iter()
-            .find_map(|(ts, pose)| (*ts == timestamp).then_some(*pose))
-        else {
-            // We can't submit the frame without its pose
-            return;
+            .find_map(|(ts, pose_ts)| (*ts == timestamp).then_some(*pose_ts));
+
+        let head_pose = find_head_pose(timestamp).and_then(|mut head_pose| {
+            if let Some(pose_timestamp) = reprojection_timestamp {
+                // The reprojection is rotational only, the position is still the one used for
+                // rendering. Without the reprojected orientation the client would correct the
+                // frame a second time with the wrong reference
+                head_pose.orientation = find_head_pose(pose_timestamp)?.orientation;
+            }
+
+            Some(head_pose)
+        });
+        drop(head_pose_queue_lock);
+
+        let mut last_head_pose_lock = LAST_FRAME_HEAD_POSE.lock();
+        let head_pose = if let Some(head_pose) = head_pose {
+            *last_head_pose_lock = Some(head_pose);
+
+            head_pose
+        } else {
+            report_missing_pose(timestamp);
+
+            // The frame is still sent to avoid a stall in the stream. The client reprojection
+            // will be off by the head movement since the last frame
+            let Some(head_pose) = *last_head_pose_lock else {
+                return;
+            };
+
+            head_pose
         };
+        drop(last_head_pose_lock);
 
         let local_views_params = LOCAL_VIEW_PARAMS.read();
 
@@ -400,7 +624,24 @@ extern "C" fn send_video(timestamp_ns: u64, buffer_ptr: *mut u8, len: i32, is_id
             },
         ];
 
-        context.send_video_nal(timestamp, global_view_params, is_idr, buffer.to_vec());
+        context.send_video_nal(
+            timestamp,
+            global_view_params,
+            is_idr,
+            u32::try_from(average_qp).ok(),
+            buffer.to_vec(),
+        );
+    }
+}
+
+fn to_ffi_encoder_presets(presets: &EncoderPresets) -> FfiEncoderPresets {
+    FfiEncoderPresets {
+        nvenc_quality_preset: presets.nvenc_quality_preset as u32,
+        nvenc_tuning_preset: presets.nvenc_tuning_preset as u32,
+        nvenc_multi_pass: presets.nvenc_multi_pass as u32,
+        encoder_quality_preset: presets.quality_preset as u32,
+        use_amf_preproc: presets.amf_use_preproc as u32,
+        enable_amf_pre_analysis: presets.amf_enable_pre_analysis as u32,
     }
 }
 
@@ -412,12 +653,128 @@ extern "C" fn get_dynamic_encoder_params() -> FfiDynamicEncoderParams {
             updated: 1,
             bitrate_bps: params.bitrate_bps as u64,
             framerate: params.framerate,
+            presets: to_ffi_encoder_presets(&params.presets),
         }
     } else {
         FfiDynamicEncoderParams::default()
     }
 }
 
+extern "C" fn get_encoder_presets() -> FfiEncoderPresets {
+    let video = alvr_server_core::settings().video;
+
+    to_ffi_encoder_presets(&alvr_server_core::encoder_presets(
+        video.encoder_effort,
+        &video.encoder_config,
+    ))
+}
+
+extern "C" fn get_gaze_regions() -> FfiGazeRegions {
+    let mut ffi_regions = FfiGazeRegions::default();
+
+    if let Some(context) = &*SERVER_CORE_CONTEXT.read()
+        && let Some(regions) = context.get_gaze_regions(&LOCAL_VIEW_PARAMS.read())
+    {
+        for rect in regions.rects.iter().flatten() {
+            ffi_regions.rects[ffi_regions.count as usize] =
+                [rect.min.x, rect.min.y, rect.max.x, rect.max.y];
+            ffi_regions.count += 1;
+        }
+        ffi_regions.qpOffset = regions.qp_offset;
+    }
+
+    ffi_regions
+}
+
+extern "C" fn report_depth_submission(submitted: bool) {
+    if let Some(context) = &*SERVER_CORE_CONTEXT.read() {
+        context.report_depth_submission(submitted);
+    }
+}
+
+extern "C" fn send_depth(
+    timestamp_ns: u64,
+    width: u32,
+    height: u32,
+    data_ptr: *const u16,
+    near_z: f32,
+    far_z: f32,
+) {
+    if let Some(context) = &*SERVER_CORE_CONTEXT.read() {
+        // Both views side by side
+        let data = unsafe { std::slice::from_raw_parts(data_ptr, (width * 2 * height) as usize) };
+
+        context.send_depth(
+            Duration::from_nanos(timestamp_ns),
+            UVec2::new(width, height),
+            near_z,
+            far_z,
+            data,
+        );
+    }
+}
+
+extern "C" fn report_motion_vectors_support(supported: bool) {
+    MOTION_VECTORS_SUPPORTED.store(supported, Ordering::Relaxed);
+
+    if let Some(context) = &*SERVER_CORE_CONTEXT.read() {
+        context.report_motion_vectors_support(supported);
+    }
+}
+
+extern "C" fn report_scene_application(pid: u32) {
+    if let Some(context) = &*SERVER_CORE_CONTEXT.read() {
+        context.report_scene_application(pid);
+    }
+}
+
+extern "C" fn send_motion_vectors(
+    timestamp_ns: u64,
+    reference_timestamp_ns: u64,
+    view_width: u32,
+    view_height: u32,
+    block_size: u32,
+    grid_width: u32,
+    grid_height: u32,
+    data_ptr: *const i16,
+) {
+    if let Some(context) = &*SERVER_CORE_CONTEXT.read() {
+        // Two components per block, both views
+        let data = unsafe {
+            std::slice::from_raw_parts(data_ptr, (grid_width * grid_height * 4) as usize)
+        };
+
+        context.send_motion_vectors(
+            Duration::from_nanos(timestamp_ns),
+            Duration::from_nanos(reference_timestamp_ns),
+            UVec2::new(view_width, view_height),
+            block_size,
+            UVec2::new(grid_width, grid_height),
+            data,
+        );
+    }
+}
+
+extern "C" fn send_mirror_frame(width: u32, height: u32, rgba_ptr: *const u8) {
+    if let Some(context) = &*SERVER_CORE_CONTEXT.read() {
+        let rgba = unsafe { std::slice::from_raw_parts(rgba_ptr, (width * height * 4) as usize) };
+
+        context.send_mirror_frame(UVec2::new(width, height), rgba);
+    }
+}
+
+extern "C" fn draw_test_pattern(data: *mut u8, width: u32, height: u32, row_pitch: u32) {
+    // The buffer can be shared with the other view, so the last row ends after the view width
+    let len = (height.saturating_sub(1) * row_pitch + width * 4) as usize;
+    let rgba = unsafe { std::slice::from_raw_parts_mut(data, len) };
+    let time_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_millis() as u64;
+
+    alvr_server_core::draw_test_pattern(rgba, width, height, row_pitch as usize, time_ms);
+}
+
 extern "C" fn report_composed(timestamp_ns: u64, offset_ns: u64) {
     if let Some(context) = &*SERVER_CORE_CONTEXT.read() {
         context.report_composed(
@@ -427,6 +784,21 @@ extern "C" fn report_composed(timestamp_ns: u64, offset_ns: u64) {
     }
 }
 
+extern "C" fn report_server_reprojection(timestamp_ns: u64, pose_timestamp_ns: u64) {
+    let timestamp = Duration::from_nanos(timestamp_ns);
+    let pose_timestamp = Duration::from_nanos(pose_timestamp_ns);
+
+    let mut queue_lock = SERVER_REPROJECTION_QUEUE.lock();
+    queue_lock.push_back((timestamp, pose_timestamp));
+    while queue_lock.len() > 360 {
+        queue_lock.pop_front();
+    }
+
+    if let Some(context) = &*SERVER_CORE_CONTEXT.read() {
+        context.report_server_reprojection(timestamp, pose_timestamp);
+    }
+}
+
 extern "C" fn report_present(timestamp_ns: u64, offset_ns: u64) {
     if let Some(context) = &*SERVER_CORE_CONTEXT.read() {
         context.report_present(
@@ -539,9 +911,21 @@ pub unsafe extern "C" fn HmdDriverFactory(
             DriverReadyIdle = Some(driver_ready_idle);
             HapticsSend = Some(send_haptics);
             SetVideoConfigNals = Some(set_video_config_nals);
+            SetIntraRefreshPeriod = Some(set_intra_refresh_period);
+            ReportEncoderFallback = Some(report_encoder_fallback);
             VideoSend = Some(send_video);
             GetDynamicEncoderParams = Some(get_dynamic_encoder_params);
+            GetEncoderPresets = Some(get_encoder_presets);
+            GetGazeRegions = Some(get_gaze_regions);
+            ReportDepthSubmission = Some(report_depth_submission);
+            SendDepth = Some(send_depth);
+            ReportMotionVectorsSupport = Some(report_motion_vectors_support);
+            SendMotionVectors = Some(send_motion_vectors);
+            ReportSceneApplication = Some(report_scene_application);
+            SendMirrorFrame = Some(send_mirror_frame);
+            DrawTestPattern = Some(draw_test_pattern);
             ReportComposed = Some(report_composed);
+            ReportServerReprojection = Some(report_server_reprojection);
             ReportPresent = Some(report_present);
             WaitForVSync = Some(wait_for_vsync);
             ShutdownRuntime = Some(shutdown_driver);
@@ -556,7 +940,11 @@ pub unsafe extern "C" fn HmdDriverFactory(
 
         let (context, events_receiver) = ServerCoreContext::new();
 
-        *SERVER_CORE_CONTEXT.write() = Some(context);
+        // Locked first, so that a report of the driver is not lost in between
+        let mut context_lock = SERVER_CORE_CONTEXT.write();
+        context.report_motion_vectors_support(MOTION_VECTORS_SUPPORTED.load(Ordering::Relaxed));
+        *context_lock = Some(context);
+        drop(context_lock);
 
         event_loop(events_receiver);
     });
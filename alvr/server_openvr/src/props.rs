@@ -508,21 +508,17 @@ pub extern "C" fn set_device_openvr_props(instance_ptr: *mut c_void, device_id:
                     set_prop(TrackingSystemNameString, "htc");
                     set_prop(ManufacturerNameString, "HTC");
                     set_prop(RenderModelNameString, "vr_controller_vive_1_5");
+                    set_prop(ModelNumberString, "Vive. Controller MV");
                     if left_hand {
-                        set_prop(
-                            ModelNumberString,
-                            "ALVR Remote Controller (Left Controller)",
-                        );
                         set_prop(RegisteredDeviceTypeString, "htc/vive_controller_Left");
                     } else if right_hand {
-                        set_prop(
-                            ModelNumberString,
-                            "ALVR Remote Controller (Right Controller)",
-                        );
                         set_prop(RegisteredDeviceTypeString, "htc/vive_controller_Right");
                     }
                     set_prop(ControllerTypeString, "vive_controller");
-                    set_prop(InputProfilePathString, "{oculus}/input/touch_profile.json");
+                    set_prop(
+                        InputProfilePathString,
+                        "{htc}/input/vive_controller_profile.json",
+                    );
                     set_icons("{htc}/icons/controller");
                 }
                 ControllersEmulationMode::ViveTracker => {
@@ -12,8 +12,6 @@ use std::{
     sync::LazyLock,
 };
 
-const DEG_TO_RAD: f32 = PI / 180.0;
-
 pub static BODY_TRACKER_IDS: LazyLock<[u64; 8]> = LazyLock::new(|| {
     [
         // Upper body
@@ -38,7 +36,7 @@ fn to_ffi_fov(fov: Fov) -> FfiFov {
     }
 }
 
-fn to_ffi_quat(quat: Quat) -> FfiQuat {
+pub fn to_ffi_quat(quat: Quat) -> FfiQuat {
     FfiQuat {
         x: quat.x,
         y: quat.y,
@@ -70,37 +68,29 @@ pub fn to_ffi_view_params(params: ViewParams) -> FfiViewParams {
     }
 }
 
+// Offset of the matching emulation profile, for the left controller
+fn profile_pose_offset(config: &ControllersConfig) -> Pose {
+    config
+        .profile_pose_offsets
+        .iter()
+        .find(|offset| offset.emulation_mode == config.emulation_mode)
+        .map(|offset| alvr_common::pose_from_offset(offset.position_offset, offset.rotation_offset))
+        .unwrap_or(Pose::IDENTITY)
+}
+
+// The profile offset is applied also to the hand skeleton, so that the rendered hand model stays
+// aligned with the offset controller pose
 fn get_hand_skeleton_offsets(config: &HeadsetConfig) -> (Pose, Pose) {
-    let left_offset;
-    let right_offset;
     if let Switch::Enabled(controllers) = &config.controllers {
-        let t = controllers.left_hand_tracking_position_offset;
-        let r = controllers.left_hand_tracking_rotation_offset;
-
-        left_offset = Pose {
-            orientation: Quat::from_euler(
-                EulerRot::XYZ,
-                r[0] * DEG_TO_RAD,
-                r[1] * DEG_TO_RAD,
-                r[2] * DEG_TO_RAD,
-            ),
-            position: Vec3::new(t[0], t[1], t[2]),
-        };
-        right_offset = Pose {
-            orientation: Quat::from_euler(
-                EulerRot::XYZ,
-                r[0] * DEG_TO_RAD,
-                -r[1] * DEG_TO_RAD,
-                -r[2] * DEG_TO_RAD,
-            ),
-            position: Vec3::new(-t[0], t[1], t[2]),
-        };
+        let left_offset = alvr_common::pose_from_offset(
+            controllers.left_hand_tracking_position_offset,
+            controllers.left_hand_tracking_rotation_offset,
+        ) * profile_pose_offset(controllers);
+
+        (left_offset, left_offset.mirrored_x())
     } else {
-        left_offset = Pose::IDENTITY;
-        right_offset = Pose::IDENTITY;
+        (Pose::IDENTITY, Pose::IDENTITY)
     }
-
-    (left_offset, right_offset)
 }
 
 fn to_ffi_skeleton(skeleton: &[Pose; 31]) -> FfiHandSkeleton {
@@ -126,14 +116,21 @@ pub fn to_openvr_ffi_hand_skeleton(
     hand_skeleton: &[Pose; 26],
 ) -> FfiHandSkeleton {
     let (left_hand_skeleton_offset, right_hand_skeleton_offset) = get_hand_skeleton_offsets(config);
-    let id = device_id;
 
-    let pose_offset = if id == *HAND_LEFT_ID {
+    let pose_offset = if device_id == *HAND_LEFT_ID {
         left_hand_skeleton_offset
     } else {
         right_hand_skeleton_offset
     };
 
+    to_ffi_skeleton(&to_openvr_hand_skeleton(
+        device_id,
+        pose_offset,
+        hand_skeleton,
+    ))
+}
+
+fn to_openvr_hand_skeleton(id: u64, pose_offset: Pose, hand_skeleton: &[Pose; 26]) -> [Pose; 31] {
     // global joints
     let gj = hand_skeleton;
 
@@ -166,8 +163,10 @@ pub fn to_openvr_ffi_hand_skeleton(
     // Convert from global to local joint pose. The orientation frame of reference is also
     // converted from OpenXR to SteamVR (hand-specific!)
     pub fn local_pose(id: u64, parent: Pose, current: Pose) -> Pose {
-        let o = parent.orientation.conjugate() * current.orientation;
-        let p = parent.orientation.conjugate() * (current.position - parent.position);
+        let Pose {
+            orientation: o,
+            position: p,
+        } = parent.inverse() * current;
 
         // Convert to SteamVR frame of reference
         let (orientation, position) = if id == *HAND_LEFT_ID {
@@ -189,22 +188,21 @@ pub fn to_openvr_ffi_hand_skeleton(
     }
 
     // Adjust hand position based on the emulated controller for joints
-    // parented to the root.
+    // parented to the root. The position of the offset is in the frame rotated by the offset
+    // itself, unlike a pose composition, which the hand models are calibrated against.
+    let controller_pose = gj[0]
+        * Pose {
+            orientation: pose_offset.orientation,
+            position: pose_offset.orientation * pose_offset.position,
+        };
     let root_parented_pose = |pose: Pose| -> Pose {
         let sign = if id == *HAND_LEFT_ID { -1.0 } else { 1.0 };
-        let orientation = pose_offset.orientation.conjugate()
-            * gj[0].orientation.conjugate()
-            * pose.orientation
-            * Quat::from_euler(EulerRot::XZY, PI, sign * FRAC_PI_2, 0.0);
-
-        let position = -pose_offset.position
-            + pose_offset.orientation.conjugate()
-                * gj[0].orientation.conjugate()
-                * (pose.position - gj[0].position);
+        let root_pose = controller_pose.inverse() * pose;
 
         Pose {
-            orientation,
-            position,
+            orientation: root_pose.orientation
+                * Quat::from_euler(EulerRot::XZY, PI, sign * FRAC_PI_2, 0.0),
+            position: root_pose.position,
         }
     };
 
@@ -214,13 +212,9 @@ pub fn to_openvr_ffi_hand_skeleton(
         position: gj[1].position,
     };
 
-    let skeleton = [
+    [
         // Palm. NB: this is ignored by SteamVR
-        Pose {
-            orientation: gj[0].orientation * pose_offset.orientation,
-            position: gj[0].position
-                + gj[0].orientation * pose_offset.orientation * pose_offset.position,
-        },
+        controller_pose,
         // Wrist
         root_parented_pose(gj[1]),
         // Thumb
@@ -258,9 +252,7 @@ pub fn to_openvr_ffi_hand_skeleton(
         aux_orientation(id, root_parented_pose(gj[14])),
         aux_orientation(id, root_parented_pose(gj[19])),
         aux_orientation(id, root_parented_pose(gj[24])),
-    ];
-
-    to_ffi_skeleton(&skeleton)
+    ]
 }
 
 // Apply controller offsets workarounds for SteamVR
@@ -269,39 +261,97 @@ pub fn offset_controller_motion(
     device_id: u64,
     motion: DeviceMotion,
 ) -> DeviceMotion {
-    let t = config.left_controller_position_offset;
-    let r = config.left_controller_rotation_offset;
+    let left_offset = alvr_common::pose_from_offset(
+        config.left_controller_position_offset,
+        config.left_controller_rotation_offset,
+    ) * profile_pose_offset(config);
 
     let pose_offset = if device_id == *HAND_LEFT_ID {
-        Pose {
-            orientation: Quat::from_euler(
-                EulerRot::XYZ,
-                r[0] * DEG_TO_RAD,
-                r[1] * DEG_TO_RAD,
-                r[2] * DEG_TO_RAD,
-            ),
-            position: Vec3::new(t[0], t[1], t[2]),
-        }
+        left_offset
     } else if device_id == *HAND_RIGHT_ID {
-        Pose {
-            orientation: Quat::from_euler(
-                EulerRot::XYZ,
-                r[0] * DEG_TO_RAD,
-                -r[1] * DEG_TO_RAD,
-                -r[2] * DEG_TO_RAD,
-            ),
-            position: Vec3::new(-t[0], t[1], t[2]),
-        }
+        left_offset.mirrored_x()
     } else {
         panic!("device_id is not associated to a controller");
     };
 
     DeviceMotion {
-        pose: motion.pose * pose_offset,
-        linear_velocity: motion.linear_velocity
-            + motion
-                .angular_velocity
-                .cross(motion.pose.orientation * pose_offset.position),
+        // SteamVR expects the angular velocity in the controller local space
         angular_velocity: motion.pose.orientation.conjugate() * motion.angular_velocity,
+        ..alvr_common::apply_controller_offset(motion, pose_offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alvr_common::assert_pose_eq;
+
+    const EPSILON: f32 = 1e-4;
+
+    fn hand_skeleton() -> [Pose; 26] {
+        std::array::from_fn(|index| {
+            let index = index as f32;
+            Pose {
+                orientation: Quat::from_euler(EulerRot::YXZ, 0.1 * index, -0.05 * index, 0.3),
+                position: Vec3::new(0.01 * index, 1.2 - 0.005 * index, -0.3 + 0.02 * index),
+            }
+        })
+    }
+
+    // The palm and the joints parented to the root must keep the placement computed before the
+    // pose math was shared, which the hand models of SteamVR are aligned to
+    #[test]
+    fn test_hand_skeleton_root_joints() {
+        let joints = hand_skeleton();
+        let offset = alvr_common::pose_from_offset([0.02, -0.03, 0.05], [20.0, -10.0, 35.0]);
+
+        for (id, offset) in [
+            (*HAND_LEFT_ID, offset),
+            (*HAND_RIGHT_ID, offset.mirrored_x()),
+        ] {
+            let skeleton = to_openvr_hand_skeleton(id, offset, &joints);
+
+            let (root, q) = (joints[0], offset.orientation);
+            let expected_palm = Pose {
+                orientation: root.orientation * q,
+                position: root.position + root.orientation * q * offset.position,
+            };
+            assert_pose_eq(skeleton[0], expected_palm);
+
+            let sign = if id == *HAND_LEFT_ID { -1.0 } else { 1.0 };
+            let expected_root_parented = |pose: Pose| Pose {
+                orientation: q.conjugate()
+                    * root.orientation.conjugate()
+                    * pose.orientation
+                    * Quat::from_euler(EulerRot::XZY, PI, sign * FRAC_PI_2, 0.0),
+                position: -offset.position
+                    + q.conjugate()
+                        * root.orientation.conjugate()
+                        * (pose.position - root.position),
+            };
+            // Wrist
+            assert_pose_eq(skeleton[1], expected_root_parented(joints[1]));
+            // Aux bones
+            for (aux_index, tip_index) in [(26, 4), (27, 9), (28, 14), (29, 19), (30, 24)] {
+                assert!(
+                    skeleton[aux_index]
+                        .position
+                        .abs_diff_eq(expected_root_parented(joints[tip_index]).position, EPSILON)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_hand_skeleton_without_offset() {
+        let joints = hand_skeleton();
+        let skeleton = to_openvr_hand_skeleton(*HAND_RIGHT_ID, Pose::IDENTITY, &joints);
+
+        assert_pose_eq(skeleton[0], joints[0]);
+        // The wrist is placed relative to the palm
+        assert!(skeleton[1].position.abs_diff_eq(
+            joints[0].inverse().transform_point(joints[1].position),
+            EPSILON
+        ));
     }
 }
@@ -4,13 +4,14 @@ pub use settings::*;
 pub use settings_schema;
 
 use alvr_common::{
-    ALVR_VERSION, ConnectionState, ToAny,
+    ALVR_VERSION, AxisCurve, AxisResponse, ConnectionState, Pose, ToAny,
     anyhow::{Result, bail},
+    error,
     semver::Version,
 };
 use serde::{Deserialize, Serialize};
 use serde_json as json;
-use settings_schema::{NumberType, SchemaNode};
+use settings_schema::{NumberType, SchemaNode, Switch};
 use std::{
     collections::{HashMap, HashSet},
     net::IpAddr,
@@ -38,38 +39,61 @@ pub struct OpenvrConfig {
     pub tracking_ref_only: bool,
     pub enable_vive_tracker_proxy: bool,
     pub minimum_idr_interval_ms: u64,
+    pub keyframe_interval_ms: u64, // 0 means disabled
+    pub server_reprojection: bool,
     pub adapter_index: u32,
+    pub adapter_name: String, // empty means selection by index
+    pub cross_adapter_encoding: bool,
     pub codec: u8,
     pub h264_profile: u32,
     pub refresh_rate: u32,
     pub use_10bit_encoder: bool,
     pub encoding_gamma: f32,
     pub enable_hdr: bool,
+    pub color_range: u32,
+    pub transfer_function: u32,
+    pub chroma_subsampling: u32,
     pub force_hdr_srgb_correction: bool,
     pub clamp_hdr_extended_range: bool,
-    pub enable_amf_pre_analysis: bool,
     pub enable_vbaq: bool,
     pub enable_amf_hmqb: bool,
-    pub use_amf_preproc: bool,
     pub amf_preproc_sigma: u32,
     pub amf_preproc_tor: u32,
-    pub encoder_quality_preset: u32,
     pub rate_control_mode: u32,
     pub filler_data: bool,
     pub entropy_coding: u32,
+    pub encoder_backend: u32,
     pub force_sw_encoding: bool,
     pub sw_thread_count: u32,
+    pub encoder_async_depth: u32,
+    pub sw_svt_av1_preset: u32,
     pub controller_is_tracker: bool,
     pub controllers_enabled: bool,
     pub body_tracking_vive_enabled: bool,
     pub body_tracking_has_legs: bool,
     pub enable_foveated_encoding: bool,
+    pub foveation_mode: u32,
+    pub foveation_center_strength: f32,
+    pub foveation_edge_compression: f32,
     pub foveation_center_size_x: f32,
     pub foveation_center_size_y: f32,
     pub foveation_center_shift_x: f32,
     pub foveation_center_shift_y: f32,
     pub foveation_edge_ratio_x: f32,
     pub foveation_edge_ratio_y: f32,
+    // Same as the left eye if the settings have no override
+    pub foveation_right_center_size_x: f32,
+    pub foveation_right_center_size_y: f32,
+    pub foveation_right_center_shift_x: f32,
+    pub foveation_right_center_shift_y: f32,
+    pub foveation_right_edge_ratio_x: f32,
+    pub foveation_right_edge_ratio_y: f32,
+    pub foveation_edge_preservation_strength: f32,
+    pub enable_gaze_bitrate_allocation: bool,
+    pub enable_depth_stream: bool,
+    pub depth_resolution_divisor: u32,
+    pub enable_space_warp: bool,
+    pub enable_eye_gaze: bool,
     pub enable_color_correction: bool,
     pub brightness: f32,
     pub contrast: f32,
@@ -78,15 +102,13 @@ pub struct OpenvrConfig {
     pub sharpening: f32,
     pub linux_async_compute: bool,
     pub linux_async_reprojection: bool,
-    pub nvenc_quality_preset: u32,
-    pub nvenc_tuning_preset: u32,
-    pub nvenc_multi_pass: u32,
     pub nvenc_adaptive_quantization_mode: u32,
     pub nvenc_low_delay_key_frame_scale: i64,
     pub nvenc_refresh_rate: i64,
     pub enable_intra_refresh: bool,
     pub intra_refresh_period: i64,
     pub intra_refresh_count: i64,
+    pub intra_refresh_recovery_period: u32,
     pub max_num_ref_frames: i64,
     pub gop_length: i64,
     pub p_frame_strategy: i64,
@@ -115,6 +137,14 @@ pub struct OpenvrConfig {
     pub _decoder_debug: bool,
 }
 
+// Subtree of the session settings that replaces the global one while a specific client is
+// connected. The path is relative to session_settings, array elements are referenced by index.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SettingsOverride {
+    pub path: Vec<String>,
+    pub value: json::Value,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ClientConnectionConfig {
     pub display_name: String,
@@ -122,6 +152,60 @@ pub struct ClientConnectionConfig {
     pub manual_ips: HashSet<IpAddr>,
     pub trusted: bool,
     pub connection_state: ConnectionState,
+    #[serde(default)]
+    pub settings_overrides: Vec<SettingsOverride>,
+}
+
+fn json_child_mut<'a>(value: &'a mut json::Value, segment: &str) -> Option<&'a mut json::Value> {
+    match value {
+        json::Value::Array(array) => array.get_mut(segment.parse::<usize>().ok()?),
+        _ => value.get_mut(segment),
+    }
+}
+
+// Overrides with a path not present in the session settings (because of a version upgrade) are
+// ignored
+pub fn apply_settings_overrides(
+    session_settings: &mut json::Value,
+    overrides: &[SettingsOverride],
+) {
+    'overrides: for settings_override in overrides {
+        let mut json_ref = &mut *session_settings;
+        for segment in &settings_override.path {
+            let Some(json_child) = json_child_mut(json_ref, segment) else {
+                continue 'overrides;
+            };
+            json_ref = json_child;
+        }
+
+        *json_ref = settings_override.value.clone();
+    }
+}
+
+// Overrides are kept disjoint: a new override replaces the ones of its subtree, and it is merged
+// into an existing override of a parent subtree
+pub fn insert_settings_override(
+    overrides: &mut Vec<SettingsOverride>,
+    new_override: SettingsOverride,
+) {
+    if let Some(parent) = overrides
+        .iter_mut()
+        .find(|o| new_override.path.starts_with(&o.path))
+    {
+        let mut json_ref = &mut parent.value;
+        for segment in &new_override.path[parent.path.len()..] {
+            let Some(json_child) = json_child_mut(json_ref, segment) else {
+                return;
+            };
+            json_ref = json_child;
+        }
+        *json_ref = new_override.value;
+
+        return;
+    }
+
+    overrides.retain(|o| !o.path.starts_with(&new_override.path));
+    overrides.push(new_override);
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -130,6 +214,8 @@ pub struct SessionConfig {
     pub openvr_config: OpenvrConfig,
     // The hashmap key is the hostname
     pub client_connections: HashMap<String, ClientConnectionConfig>,
+    // Transform from the space of the external trackers to the client reference space
+    pub external_trackers_calibration: Option<Pose>,
     pub session_settings: SessionSettings,
 }
 
@@ -155,6 +241,7 @@ impl Default for SessionConfig {
                 ..<_>::default()
             },
             client_connections: HashMap::new(),
+            external_trackers_calibration: None,
             session_settings: settings::session_settings_default(),
         }
     }
@@ -234,6 +321,117 @@ impl SessionConfig {
         .map_err(|e| dbg!(e))
         .unwrap()
     }
+
+    // Session as seen by a connected client: the global settings with the overrides of the client
+    // applied on top. If the overrides do not match the current settings types, the global settings
+    // are used.
+    pub fn with_client_overrides(&self, hostname: &str) -> Self {
+        let mut session = self.clone();
+
+        let Some(overrides) = self
+            .client_connections
+            .get(hostname)
+            .map(|config| &config.settings_overrides)
+            .filter(|overrides| !overrides.is_empty())
+        else {
+            return session;
+        };
+
+        let mut session_settings_json = json::to_value(&self.session_settings).unwrap();
+        apply_settings_overrides(&mut session_settings_json, overrides);
+
+        match json::from_value(session_settings_json) {
+            Ok(session_settings) => session.session_settings = session_settings,
+            Err(e) => error!("Invalid settings overrides for {hostname}: {e}"),
+        }
+
+        session
+    }
+
+    // Copy of the session that can be attached to bug reports. Hostnames, device names and IP
+    // addresses are replaced, the settings and the overrides of each client are kept.
+    pub fn redacted(&self) -> Self {
+        let mut hostnames = self.client_connections.keys().collect::<Vec<_>>();
+        hostnames.sort();
+
+        let client_connections = hostnames
+            .into_iter()
+            .enumerate()
+            .map(|(idx, hostname)| {
+                let name = format!("client{}", idx + 1);
+                let config = ClientConnectionConfig {
+                    display_name: name.clone(),
+                    current_ip: None,
+                    manual_ips: HashSet::new(),
+                    ..self.client_connections[hostname].clone()
+                };
+
+                (name, config)
+            })
+            .collect();
+
+        Self {
+            client_connections,
+            ..self.clone()
+        }
+    }
+}
+
+impl AxisProcessingConfig {
+    // Used by the server to process the inputs and by the dashboard to draw the curve
+    pub fn axis_response(&self) -> AxisResponse {
+        AxisResponse {
+            inner_deadzone: self.inner_deadzone,
+            outer_deadzone: self.outer_deadzone,
+            anti_deadzone: self.anti_deadzone,
+            curve: match &self.response_curve {
+                AxisResponseCurve::Linear => AxisCurve::Linear,
+                AxisResponseCurve::Power { exponent } => AxisCurve::Power(*exponent),
+                AxisResponseCurve::Custom { points } => AxisCurve::Points(
+                    points
+                        .iter()
+                        .map(|point| (point.input, point.output))
+                        .collect(),
+                ),
+            },
+        }
+    }
+}
+
+impl FoveatedEncodingConfig {
+    // Parameters of the left and right eyes. Without override, both eyes use the same parameters,
+    // which the compression mirrors for the right eye
+    pub fn eyes(&self) -> [FoveatedEncodingEyeConfig; 2] {
+        let left = FoveatedEncodingEyeConfig {
+            center_size_x: self.center_size_x,
+            center_size_y: self.center_size_y,
+            center_shift_x: self.center_shift_x,
+            center_shift_y: self.center_shift_y,
+            edge_ratio_x: self.edge_ratio_x,
+            edge_ratio_y: self.edge_ratio_y,
+        };
+        let right = self.right_eye.as_option().copied().unwrap_or(left);
+
+        [left, right]
+    }
+
+    // Inverse of eyes()
+    pub fn with_eyes(self, [left, right]: [FoveatedEncodingEyeConfig; 2]) -> Self {
+        Self {
+            center_size_x: left.center_size_x,
+            center_size_y: left.center_size_y,
+            center_shift_x: left.center_shift_x,
+            center_shift_y: left.center_shift_y,
+            edge_ratio_x: left.edge_ratio_x,
+            edge_ratio_y: left.edge_ratio_y,
+            right_eye: if right == left {
+                Switch::Disabled
+            } else {
+                Switch::Enabled(right)
+            },
+            ..self
+        }
+    }
 }
 
 // Current data extrapolation strategy: match both field name and value type exactly.
@@ -591,9 +789,180 @@ fn json_session_settings_to_settings(
     }
 }
 
+// When a setting takes effect. The scope is given by the "real-time" and "steamvr-restart" flags,
+// entries without flags inherit the scope of the parent.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum ApplyScope {
+    Live,
+    StreamRestart,
+    DriverRestart,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct SettingChange {
+    // Path in the session settings, without the "session_settings" root
+    pub path: Vec<String>,
+    pub scope: ApplyScope,
+}
+
+// Splits the json of a Choice into the variant name and the content, if any
+fn json_variant(value: &json::Value) -> Option<(&str, Option<&json::Value>)> {
+    match value {
+        json::Value::String(variant) => Some((variant, None)),
+        json::Value::Object(map) if map.len() == 1 => map
+            .iter()
+            .next()
+            .map(|(variant, content)| (variant.as_str(), Some(content))),
+        _ => None,
+    }
+}
+
+fn collect_setting_changes(
+    old: &json::Value,
+    new: &json::Value,
+    schema: &SchemaNode,
+    scope: ApplyScope,
+    path: &mut Vec<String>,
+    changes: &mut Vec<SettingChange>,
+) {
+    if old == new {
+        return;
+    }
+
+    let mut recurse = |segment: &str,
+                       old: &json::Value,
+                       new: &json::Value,
+                       schema: &SchemaNode,
+                       scope: ApplyScope,
+                       changes: &mut Vec<SettingChange>| {
+        path.push(segment.to_owned());
+        collect_setting_changes(old, new, schema, scope, path, changes);
+        path.pop();
+    };
+
+    match schema {
+        SchemaNode::Section { entries, .. } => {
+            for entry in entries {
+                let entry_scope = if entry.flags.contains("steamvr-restart") {
+                    ApplyScope::DriverRestart
+                } else if entry.flags.contains("real-time") {
+                    ApplyScope::Live
+                } else {
+                    scope
+                };
+
+                recurse(
+                    &entry.name,
+                    &old[&entry.name],
+                    &new[&entry.name],
+                    &entry.content,
+                    entry_scope,
+                    changes,
+                );
+            }
+        }
+        SchemaNode::Choice { variants, .. } => {
+            // Only the content changed if the variant is the same
+            if let (Some((old_variant, Some(old_content))), Some((new_variant, Some(new_content)))) =
+                (json_variant(old), json_variant(new))
+                && old_variant == new_variant
+                && let Some(content_schema) = variants
+                    .iter()
+                    .find(|named_entry| named_entry.name == new_variant)
+                    .and_then(|named_entry| named_entry.content.as_ref())
+            {
+                recurse(
+                    new_variant,
+                    old_content,
+                    new_content,
+                    content_schema,
+                    scope,
+                    changes,
+                );
+            } else {
+                changes.push(SettingChange {
+                    path: path.clone(),
+                    scope,
+                });
+            }
+        }
+        SchemaNode::Switch { content, .. } => {
+            if let (Some(old_content), Some(new_content)) = (old.get("Enabled"), new.get("Enabled"))
+            {
+                recurse("content", old_content, new_content, content, scope, changes);
+            } else {
+                changes.push(SettingChange {
+                    path: path.clone(),
+                    scope,
+                });
+            }
+        }
+        SchemaNode::Optional { content, .. } if !old.is_null() && !new.is_null() => {
+            recurse("content", old, new, content, scope, changes);
+        }
+        _ => changes.push(SettingChange {
+            path: path.clone(),
+            scope,
+        }),
+    }
+}
+
+// Elements of a vector setting can have a row header, edited by the dashboard in place of the
+// section entries. The header is made of hidden entries of the element section: "enabled", a
+// boolean that the consumers of the list must check, and "display_name", a text used as the title.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct ListEntryHeader {
+    pub enabled: bool,
+    pub display_name: bool,
+}
+
+pub fn list_entry_header(element_schema: &SchemaNode) -> ListEntryHeader {
+    let SchemaNode::Section { entries, .. } = element_schema else {
+        return ListEntryHeader::default();
+    };
+
+    let has_entry = |name: &str, matches: fn(&SchemaNode) -> bool| {
+        entries.iter().any(|entry| {
+            entry.name == name && entry.flags.contains("hidden") && matches(&entry.content)
+        })
+    };
+
+    ListEntryHeader {
+        enabled: has_entry("enabled", |node| matches!(node, SchemaNode::Boolean { .. })),
+        display_name: has_entry("display_name", |node| {
+            matches!(node, SchemaNode::Text { .. })
+        }),
+    }
+}
+
+// The settings that differ between old and new, in schema order
+pub fn setting_changes(old: &Settings, new: &Settings) -> Vec<SettingChange> {
+    let mut changes = vec![];
+    collect_setting_changes(
+        &json::to_value(old).unwrap(),
+        &json::to_value(new).unwrap(),
+        &Settings::schema(settings::session_settings_default()),
+        ApplyScope::StreamRestart,
+        &mut vec![],
+        &mut changes,
+    );
+
+    changes
+}
+
+// The least disruptive action that applies all the changes. None if they are all applied live
+pub fn required_apply_scope(changes: &[SettingChange]) -> Option<ApplyScope> {
+    changes
+        .iter()
+        .map(|change| change.scope)
+        .filter(|scope| *scope != ApplyScope::Live)
+        .max()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use settings_schema::Switch;
 
     #[test]
     fn test_manual_session_to_settings() {
@@ -647,4 +1016,266 @@ mod tests {
         assert_eq!(settings.video.preferred_fps, 60.0);
         assert!(settings.headset.controllers.as_option().is_none());
     }
+
+    fn settings_override(path: &str, value: json::Value) -> SettingsOverride {
+        SettingsOverride {
+            path: path.split('.').map(String::from).collect(),
+            value,
+        }
+    }
+
+    #[test]
+    fn test_client_overrides() {
+        let mut session = SessionConfig::default();
+        session.client_connections.insert(
+            "client.alvr".into(),
+            ClientConnectionConfig {
+                display_name: "Client".into(),
+                current_ip: None,
+                manual_ips: HashSet::new(),
+                trusted: true,
+                connection_state: ConnectionState::Disconnected,
+                settings_overrides: vec![
+                    settings_override("video.preferred_fps", json::json!(120.0)),
+                    settings_override("nonexistent.field", json::json!(1)),
+                ],
+            },
+        );
+
+        let client_settings = session.with_client_overrides("client.alvr").to_settings();
+        assert_eq!(client_settings.video.preferred_fps, 120.0);
+
+        // Other clients and the global settings are not affected
+        let global_fps = session.to_settings().video.preferred_fps;
+        assert_eq!(
+            session
+                .with_client_overrides("other.alvr")
+                .to_settings()
+                .video
+                .preferred_fps,
+            global_fps
+        );
+
+        // Values of the wrong type are discarded
+        session
+            .client_connections
+            .get_mut("client.alvr")
+            .unwrap()
+            .settings_overrides = vec![settings_override("video.preferred_fps", json::json!("a"))];
+        assert_eq!(
+            session
+                .with_client_overrides("client.alvr")
+                .to_settings()
+                .video
+                .preferred_fps,
+            global_fps
+        );
+    }
+
+    #[test]
+    fn test_insert_settings_override() {
+        let mut overrides = vec![
+            settings_override("video.preferred_fps", json::json!(72.0)),
+            settings_override("audio", json::json!({ "a": 1, "b": 2 })),
+        ];
+
+        // Replaces the overrides of the subtree
+        insert_settings_override(&mut overrides, settings_override("video", json::json!({})));
+        assert_eq!(overrides.len(), 2);
+        assert_eq!(overrides[1].path, ["video"]);
+
+        // Merged into the parent override
+        insert_settings_override(&mut overrides, settings_override("audio.b", json::json!(3)));
+        assert_eq!(overrides.len(), 2);
+        assert_eq!(overrides[0].value, json::json!({ "a": 1, "b": 3 }));
+    }
+
+    #[test]
+    fn test_session_redaction() {
+        let mut session = SessionConfig::default();
+        session.client_connections.insert(
+            "john-quest.alvr".into(),
+            ClientConnectionConfig {
+                display_name: "John's Quest".into(),
+                current_ip: Some("192.168.1.20".parse().unwrap()),
+                manual_ips: ["10.0.0.5".parse().unwrap()].into_iter().collect(),
+                trusted: true,
+                connection_state: ConnectionState::Disconnected,
+                settings_overrides: vec![settings_override(
+                    "video.preferred_fps",
+                    json::json!(90.0),
+                )],
+            },
+        );
+
+        let redacted = session.redacted();
+        let text = json::to_string(&redacted).unwrap();
+        for secret in ["john", "John", "192.168.1.20", "10.0.0.5"] {
+            assert!(!text.contains(secret), "{secret} was not redacted");
+        }
+
+        let client = &redacted.client_connections["client1"];
+        assert!(client.trusted);
+        assert_eq!(
+            client.settings_overrides,
+            session.client_connections["john-quest.alvr"].settings_overrides
+        );
+    }
+
+    #[test]
+    fn test_setting_changes() {
+        let old = SessionConfig::default().to_settings();
+
+        let changes = setting_changes(&old, &old.clone());
+        assert!(changes.is_empty());
+        assert_eq!(required_apply_scope(&changes), None);
+
+        let mut new = old.clone();
+        new.video.bitrate.mode = BitrateMode::ConstantMbps(123);
+        if let Switch::Enabled(controllers) = &mut new.headset.controllers {
+            controllers.linear_velocity_cutoff += 1.0;
+        }
+        let changes = setting_changes(&old, &new);
+        assert_eq!(
+            changes,
+            [
+                SettingChange {
+                    path: ["video", "bitrate", "mode", "ConstantMbps"]
+                        .map(String::from)
+                        .to_vec(),
+                    scope: ApplyScope::Live,
+                },
+                SettingChange {
+                    path: [
+                        "headset",
+                        "controllers",
+                        "content",
+                        "linear_velocity_cutoff"
+                    ]
+                    .map(String::from)
+                    .to_vec(),
+                    scope: ApplyScope::Live,
+                },
+            ]
+        );
+        assert_eq!(required_apply_scope(&changes), None);
+
+        new.connection.packet_size += 1;
+        let changes = setting_changes(&old, &new);
+        assert_eq!(changes.len(), 3);
+        assert_eq!(
+            required_apply_scope(&changes),
+            Some(ApplyScope::StreamRestart)
+        );
+
+        // Changing a nested field of a steamvr-restart entry restarts the driver too
+        new.video.preferred_fps = 90.0;
+        let changes = setting_changes(&old, &new);
+        assert_eq!(
+            required_apply_scope(&changes),
+            Some(ApplyScope::DriverRestart)
+        );
+
+        // Disabling a switch is a change of the switch itself
+        let mut new = old.clone();
+        new.headset.controllers = Switch::Disabled;
+        assert_eq!(
+            setting_changes(&old, &new)[0].path,
+            ["headset", "controllers"].map(String::from)
+        );
+    }
+
+    #[test]
+    fn test_list_editing() {
+        let default = settings::session_settings_default();
+        let element_schema =
+            ButtonMacroConfig::schema(default.headset.controllers.content.button_macros.element);
+        assert_eq!(
+            list_entry_header(&element_schema),
+            ListEntryHeader {
+                enabled: true,
+                display_name: true,
+            }
+        );
+        assert_eq!(
+            list_entry_header(&AxisCurvePoint::schema(AxisCurvePointDefault {
+                input: 0.0,
+                output: 0.0,
+            })),
+            ListEntryHeader::default()
+        );
+
+        // Edits made by the dashboard on the session JSON: insert, duplicate, toggle and reorder
+        let mut session_json = json::to_value(SessionConfig::default()).unwrap();
+        let controllers = &mut session_json["session_settings"]["headset"]["controllers"];
+        controllers["enabled"] = json::Value::Bool(true);
+        let macros = &mut controllers["content"]["button_macros"];
+
+        let mut first = macros["element"].clone();
+        first["display_name"] = json::json!("First");
+        let mut second = first.clone();
+        second["display_name"] = json::json!("Second");
+        second["action"]["variant"] = json::json!("RequestIdr");
+        let mut content = vec![first.clone(), second];
+        let mut duplicate = first;
+        duplicate["display_name"] = json::json!("First copy");
+        content.insert(1, duplicate);
+        content[2]["enabled"] = json::Value::Bool(false);
+        let moved = content.remove(2);
+        content.insert(0, moved);
+        macros["content"] = json::Value::Array(content);
+
+        let session = json::from_value::<SessionConfig>(session_json).unwrap();
+        let check = |session: &SessionConfig| {
+            let settings = session.to_settings();
+            let macros = &settings
+                .headset
+                .controllers
+                .as_option()
+                .unwrap()
+                .button_macros;
+            assert_eq!(
+                macros
+                    .iter()
+                    .map(|config| (config.display_name.as_str(), config.enabled))
+                    .collect::<Vec<_>>(),
+                [("Second", false), ("First", true), ("First copy", true)]
+            );
+            assert!(matches!(macros[0].action, ButtonMacroAction::RequestIdr));
+            assert!(matches!(macros[1].action, ButtonMacroAction::Recenter));
+        };
+        check(&session);
+
+        // The edited list survives saving and loading the session
+        let mut loaded = SessionConfig::default();
+        loaded
+            .merge_from_json(&json::to_value(&session).unwrap())
+            .unwrap();
+        check(&loaded);
+        assert_eq!(
+            json::to_value(&loaded).unwrap(),
+            json::to_value(&session).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_foveation_eyes() {
+        let settings = SessionConfig::default().to_settings();
+        let config = settings.video.foveated_encoding.into_option().unwrap();
+
+        // Symmetric settings use the same parameters for both eyes
+        let [left, right] = config.eyes();
+        assert_eq!(left, right);
+        assert_eq!(left.center_shift_x, config.center_shift_x);
+        assert!(config.clone().with_eyes([left, right]) == config);
+
+        let right = FoveatedEncodingEyeConfig {
+            center_shift_x: -0.2,
+            edge_ratio_x: 2.0,
+            ..left
+        };
+        let per_eye = config.clone().with_eyes([left, right]);
+        assert!(matches!(per_eye.right_eye, Switch::Enabled(eye) if eye == right));
+        assert_eq!(per_eye.eyes(), [left, right]);
+    }
 }
@@ -33,16 +33,27 @@ pub struct OpenvrProperty {
 pub enum FrameSize {
     Scale(#[schema(gui(slider(min = 0.25, max = 2.0, step = 0.01)))] f32),
 
+    // Arbitrary sizes are allowed, for example to match the headset panel. The encoded resolution
+    // is rounded down to a multiple of 32
     Absolute {
-        #[schema(gui(slider(min = 32, max = 8192, step = 32)))]
+        #[schema(gui(slider(min = 32, max = 8192, step = 1)))]
         width: u32,
-        #[schema(gui(slider(min = 32, max = 8192, step = 32)))]
+        #[schema(gui(slider(min = 32, max = 8192, step = 1)))]
         height: Option<u32>,
     },
 }
 
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[schema(gui = "button_group")]
+pub enum EncoderEffort {
+    Speed,
+    Balanced,
+    Quality,
+    Custom,
+}
+
 #[repr(u32)]
-#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
 pub enum EncoderQualityPreset {
     Quality = 0,
     Balanced = 1,
@@ -50,7 +61,7 @@ pub enum EncoderQualityPreset {
 }
 
 #[repr(u32)]
-#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
 pub enum EncoderQualityPresetNvidia {
     P1 = 1,
     P2 = 2,
@@ -62,7 +73,7 @@ pub enum EncoderQualityPresetNvidia {
 }
 
 #[repr(u32)]
-#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
 pub enum NvencTuningPreset {
     HighQuality = 1,
     LowLatency = 2,
@@ -71,7 +82,7 @@ pub enum NvencTuningPreset {
 }
 
 #[repr(u32)]
-#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
 pub enum NvencMultiPass {
     Disabled = 0,
     #[schema(strings(display_name = "1/4 resolution"))]
@@ -87,6 +98,32 @@ pub enum NvencAdaptiveQuantizationMode {
     Temporal = 2,
 }
 
+#[repr(u32)]
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[schema(gui = "button_group")]
+pub enum EncoderBackend {
+    Auto = 0,
+    #[schema(strings(display_name = "VAAPI"))]
+    Vaapi = 1,
+    #[schema(strings(display_name = "Vulkan Video"))]
+    VulkanVideo = 2,
+    Software = 3,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[schema(gui = "button_group")]
+pub enum VideoRecoveryMode {
+    #[schema(strings(display_name = "IDR"))]
+    Idr,
+    IntraRefresh {
+        #[schema(strings(
+            help = "Number of frames needed to refresh the whole image. Lower values recover faster but make each frame bigger"
+        ))]
+        #[schema(gui(slider(min = 10, max = 300, step = 5)), suffix = " frames")]
+        period_frames: u32,
+    },
+}
+
 #[repr(u8)]
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
 #[schema(gui = "button_group")]
@@ -107,25 +144,76 @@ pub enum EntropyCoding {
     Cabac = 0,
 }
 
+#[repr(u8)]
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[schema(gui = "button_group")]
+pub enum ColorRange {
+    Full = 0,
+    Limited = 1,
+}
+
+#[repr(u8)]
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[schema(gui = "button_group")]
+pub enum TransferFunction {
+    #[schema(strings(display_name = "sRGB"))]
+    Srgb = 0,
+    #[schema(strings(display_name = "Gamma 2.2"))]
+    Gamma22 = 1,
+}
+
+#[repr(u8)]
+#[derive(SettingsSchema, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[schema(gui = "button_group")]
+pub enum ChromaSubsampling {
+    #[default]
+    #[schema(strings(display_name = "4:2:0"))]
+    Yuv420 = 0,
+    #[schema(strings(display_name = "4:2:2"))]
+    Yuv422 = 1,
+    #[schema(strings(display_name = "4:4:4"))]
+    Yuv444 = 2,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[schema(gui = "button_group")]
+pub enum StreamAspectRatio {
+    Stretch,
+    Letterbox,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[schema(gui = "button_group")]
+pub enum ClientSwapchainFormat {
+    Auto,
+    #[schema(strings(display_name = "sRGB"))]
+    Srgb,
+    Linear,
+}
+
 /// Except for preset, the value of these fields is not applied if == -1 (flag)
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
 #[schema(collapsible)]
 pub struct NvencConfig {
     #[schema(strings(
-        help = "P1 is the fastest preset and P7 is the preset that produces better quality. P6 and P7 are too slow to be usable."
+        help = "P1 is the fastest preset and P7 is the preset that produces better quality. P6 and P7 are too slow to be usable. Applied only with the Custom encoder effort."
     ))]
-    #[schema(flag = "steamvr-restart")]
+    #[schema(flag = "real-time")]
     pub quality_preset: EncoderQualityPresetNvidia,
-    #[schema(flag = "steamvr-restart")]
+    #[schema(strings(
+        help = r"Low latency and ultra low latency are tuned for streaming. High quality increases the encoding latency.
+Lossless ignores the bitrate and is not supported by all GPUs, low latency is used instead in that case. Applied only with the Custom encoder effort."
+    ))]
+    #[schema(flag = "real-time")]
     pub tuning_preset: NvencTuningPreset,
     #[schema(strings(
-        help = "Reduce compression artifacts at the cost of small performance penalty"
+        help = "Reduce compression artifacts at the cost of small performance penalty. Applied only with the Custom encoder effort."
     ))]
-    #[schema(flag = "steamvr-restart")]
+    #[schema(flag = "real-time")]
     pub multi_pass: NvencMultiPass,
     #[schema(strings(
         help = r#"Spatial: Helps reduce color banding, but high-complexity scenes might look worse.
-Temporal: Helps improve overall encoding quality, very small trade-off in speed."#
+Temporal: Helps improve overall encoding quality, very small trade-off in speed. Spatial is used if the GPU does not support it."#
     ))]
     #[schema(flag = "steamvr-restart")]
     pub adaptive_quantization_mode: NvencAdaptiveQuantizationMode,
@@ -171,7 +259,7 @@ Allows the encoder to perform pre-analysis the motion of the video and use the i
         flag = "steamvr-restart"
     )]
     pub enable_hmqb: bool,
-    #[schema(flag = "steamvr-restart")]
+    #[schema(flag = "real-time")]
     pub use_preproc: bool,
     #[schema(gui(slider(min = 0, max = 10)))]
     #[schema(flag = "steamvr-restart")]
@@ -185,7 +273,7 @@ Allows the encoder to perform pre-analysis the motion of the video and use the i
             help = r#"Enables pre-analysis during encoding. This will likely result in reduced performance, but may increase quality.
 Does not work with the "Reduce color banding" option, requires enabling "Use preproc""#
         ),
-        flag = "steamvr-restart"
+        flag = "real-time"
     )]
     pub enable_pre_analysis: bool,
 }
@@ -200,9 +288,22 @@ pub struct SoftwareEncodingConfig {
     #[schema(flag = "steamvr-restart")]
     pub force_software_encoding: bool,
 
-    #[schema(strings(display_name = "Encoder thread count"))]
+    #[schema(strings(
+        display_name = "Encoder thread count",
+        help = r"Lower values leave more CPU time to the game, higher values reduce the encode time. 0 lets the encoder choose based on the number of CPU cores.
+Linux: x264 slice threads. Windows: FFmpeg encoder threads, and logical processors for SVT-AV1."
+    ))]
     #[schema(flag = "steamvr-restart")]
     pub thread_count: u32,
+
+    #[cfg_attr(not(target_os = "windows"), schema(flag = "hidden"))]
+    #[schema(strings(
+        display_name = "SVT-AV1 preset",
+        help = "Used when the AV1 codec is selected. Higher values are faster but lower quality"
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    #[schema(gui(slider(min = 8, max = 13)))]
+    pub svt_av1_preset: u32,
 }
 
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
@@ -233,7 +334,7 @@ pub struct HDRConfig {
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
 #[schema(collapsible)]
 pub struct EncoderConfig {
-    #[schema(flag = "steamvr-restart")]
+    #[schema(flag = "real-time")]
     #[schema(strings(
         display_name = "Quality preset",
         help = "Controls overall quality preset of the encoder. Works only on Windows AMD AMF, Linux VAAPI (AMD/Intel)."
@@ -287,6 +388,35 @@ CABAC produces better compression but it's significantly slower and may lead to
     #[schema(flag = "steamvr-restart")]
     pub encoding_gamma: Option<f32>,
 
+    #[schema(strings(
+        help = "Full range uses all the code values and gives the best precision. Limited range (16-235) is the video standard default and is handled more consistently by some decoders. Use the test pattern to check that the darkest and brightest steps are distinguishable."
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub color_range: ColorRange,
+
+    #[schema(strings(
+        help = "Transfer function written in the bitstream color metadata and used by the client to convert the decoded frames back to linear light. sRGB matches the SteamVR compositor output, gamma 2.2 gives slightly deeper shadows."
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub transfer_function: TransferFunction,
+
+    #[schema(strings(
+        help = r"Resolution of the color planes. 4:2:2 and 4:4:4 avoid the color bleeding around colored text and thin lines, at the cost of bitrate.
+Requires HEVC, no HDR and a client decoder that supports it, otherwise 4:2:0 is used. Windows NVENC: 4:4:4 only. Linux VAAPI: depends on the GPU. Other encoders use 4:2:0."
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub chroma_subsampling: ChromaSubsampling,
+
+    #[schema(strings(
+        display_name = "Hardware encoder async depth",
+        help = r"Number of frames the hardware encoder can have in flight. Higher values increase throughput on weak GPUs at the cost of latency.
+Windows Intel VPL: AsyncDepth. Linux VAAPI and Vulkan: FFmpeg async_depth. NVENC and AMF ignore it.
+For software encoding, see the encoder thread count."
+    ))]
+    #[schema(gui(slider(min = 1, max = 8, step = 1)))]
+    #[schema(flag = "steamvr-restart")]
+    pub async_depth: u32,
+
     #[schema(strings(display_name = "HDR"))]
     #[schema(flag = "steamvr-restart")]
     pub hdr: HDRConfig,
@@ -408,6 +538,65 @@ pub struct BitrateAdaptiveFramerateConfig {
     pub framerate_reset_threshold_multiplier: f32,
 }
 
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BitrateThermalThrottlingConfig {
+    #[schema(strings(help = "Bitrate multiplier used when the headset starts warming up"))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.1, max = 1.0, step = 0.01)))]
+    pub light_multiplier: f32,
+
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.1, max = 1.0, step = 0.01)))]
+    pub moderate_multiplier: f32,
+
+    #[schema(strings(
+        help = "Bitrate multiplier used when the headset reports severe or worse thermal status"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.1, max = 1.0, step = 0.01)))]
+    pub severe_multiplier: f32,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+#[schema(collapsible)]
+pub struct BitrateBenchmarkConfig {
+    #[schema(strings(display_name = "Minimum bitrate"))]
+    #[schema(gui(slider(min = 10, max = 500, step = 10)), suffix = "Mbps")]
+    pub min_bitrate_mbps: u64,
+
+    #[schema(strings(display_name = "Maximum bitrate"))]
+    #[schema(gui(slider(min = 10, max = 1000, step = 10)), suffix = "Mbps")]
+    pub max_bitrate_mbps: u64,
+
+    #[schema(strings(display_name = "Bitrate step"))]
+    #[schema(gui(slider(min = 5, max = 100, step = 5)), suffix = "Mbps")]
+    pub step_mbps: u64,
+
+    #[schema(strings(
+        display_name = "Step duration",
+        help = "How long each bitrate is held. The first second is not measured to let the encoder settle"
+    ))]
+    #[schema(gui(slider(min = 2, max = 20)), suffix = "s")]
+    pub step_duration_s: u64,
+
+    #[schema(strings(help = "Percentile of the network latency compared against the target"))]
+    #[schema(gui(slider(min = 50, max = 99)), suffix = "%")]
+    pub latency_percentile: u64,
+
+    #[schema(strings(display_name = "Target network latency"))]
+    #[schema(gui(slider(min = 1, max = 50)), suffix = "ms")]
+    pub target_latency_ms: u64,
+
+    #[schema(strings(display_name = "Maximum frame loss"))]
+    #[schema(gui(slider(min = 0.0, max = 10.0, step = 0.1)), suffix = "%")]
+    pub max_loss_percent: f32,
+
+    #[schema(strings(
+        help = "Set the recommended bitrate when the benchmark finishes. In adaptive mode, the maximum bitrate is set instead"
+    ))]
+    pub apply_result: bool,
+}
+
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
 #[schema(collapsible)]
 pub struct BitrateConfig {
@@ -420,6 +609,20 @@ pub struct BitrateConfig {
     #[schema(flag = "real-time")]
     pub adapt_to_framerate: Switch<BitrateAdaptiveFramerateConfig>,
 
+    #[schema(strings(
+        help = "Lower the bitrate when the headset reports it is heating up, to delay hard throttling. Supported only on Android 10+ headsets"
+    ))]
+    #[schema(flag = "real-time")]
+    pub thermal_throttling: Switch<BitrateThermalThrottlingConfig>,
+
+    #[schema(strings(
+        display_name = "Total bandwidth budget",
+        help = "Limits the total bandwidth used by the stream. The measured audio, control and protocol overhead is subtracted from the budget to get the maximum video bitrate"
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 10, max = 1000, logarithmic)), suffix = "Mbps")]
+    pub total_bandwidth_budget_mbps: Switch<u64>,
+
     #[schema(strings(help = "Controls the smoothness during calculations"))]
     pub history_size: usize,
 
@@ -429,6 +632,11 @@ This has an effect only on AMD GPUs."
     ))]
     #[schema(flag = "steamvr-restart")]
     pub image_corruption_fix: bool,
+
+    #[schema(strings(
+        help = "Used by the bitrate benchmark in the statistics tab, which finds the highest bitrate the network can sustain"
+    ))]
+    pub benchmark: BitrateBenchmarkConfig,
 }
 
 #[repr(u8)]
@@ -454,12 +662,67 @@ pub struct ClientsideFoveationConfig {
     pub vertical_offset_deg: f32,
 }
 
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum FoveatedEncodingMode {
+    #[schema(strings(
+        display_name = "Two zones",
+        help = "Full resolution center region and uniformly compressed edges, set by the center region and edge ratio settings"
+    ))]
+    TwoZone,
+    #[schema(strings(
+        help = "Resolution decreasing continuously from the center, without borders between zones. The center is moved by the center shift settings"
+    ))]
+    Radial {
+        #[schema(strings(
+            help = "Resolution kept at the center, relative to the full resolution"
+        ))]
+        #[schema(gui(slider(min = 0.5, max = 1.0, step = 0.01)))]
+        center_strength: f32,
+
+        #[schema(strings(help = "Ratio between the resolution at the center and at the edges"))]
+        #[schema(gui(slider(min = 1.0, max = 8.0, step = 0.1)))]
+        edge_compression: f32,
+    },
+}
+
+// The right view is mirrored horizontally by the compression, so a positive center shift X moves
+// the center toward the nose for both eyes
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct FoveatedEncodingEyeConfig {
+    #[schema(strings(display_name = "Center region width"))]
+    #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
+    pub center_size_x: f32,
+
+    #[schema(strings(display_name = "Center region height"))]
+    #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
+    pub center_size_y: f32,
+
+    #[schema(strings(display_name = "Center shift X"))]
+    #[schema(gui(slider(min = -1.0, max = 1.0, step = 0.01)))]
+    pub center_shift_x: f32,
+
+    #[schema(strings(display_name = "Center shift Y"))]
+    #[schema(gui(slider(min = -1.0, max = 1.0, step = 0.01)))]
+    pub center_shift_y: f32,
+
+    #[schema(strings(display_name = "Horizontal edge ratio"))]
+    #[schema(gui(slider(min = 1.0, max = 10.0, step = 1.0)))]
+    pub edge_ratio_x: f32,
+
+    #[schema(strings(display_name = "Vertical edge ratio"))]
+    #[schema(gui(slider(min = 1.0, max = 10.0, step = 1.0)))]
+    pub edge_ratio_y: f32,
+}
+
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
 #[schema(collapsible)]
 pub struct FoveatedEncodingConfig {
     #[schema(strings(help = "Force enable on smartphone clients"))]
     pub force_enable: bool,
 
+    #[schema(flag = "steamvr-restart")]
+    pub mode: FoveatedEncodingMode,
+
     #[schema(strings(display_name = "Center region width"))]
     #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
     #[schema(flag = "steamvr-restart")]
@@ -489,6 +752,21 @@ pub struct FoveatedEncodingConfig {
     #[schema(gui(slider(min = 1.0, max = 10.0, step = 1.0)))]
     #[schema(flag = "steamvr-restart")]
     pub edge_ratio_y: f32,
+
+    #[schema(strings(
+        display_name = "Right eye override",
+        help = "Separate parameters for the right eye, for headsets with canted displays or asymmetric views and for a dominant eye. Otherwise the parameters above are used for both eyes, mirrored. Requires a client with per-eye foveation support"
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub right_eye: Switch<FoveatedEncodingEyeConfig>,
+
+    #[schema(strings(
+        display_name = "Edge preservation strength",
+        help = "Reduces peripheral quality loss on high-contrast edges to avoid visible banding. 0 compresses the periphery uniformly"
+    ))]
+    #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
+    #[schema(flag = "steamvr-restart")]
+    pub edge_preservation_strength: f32,
 }
 
 #[repr(C)]
@@ -528,6 +806,17 @@ pub enum CodecType {
     AV1 = 2,
 }
 
+#[derive(SettingsSchema, Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[schema(gui = "button_group")]
+pub enum ClientGraphicsApi {
+    // Vulkan if supported by the runtime and the client build
+    #[default]
+    Auto,
+    #[schema(strings(display_name = "OpenGL ES"))]
+    OpenGlEs,
+    Vulkan,
+}
+
 #[repr(u8)]
 #[derive(SettingsSchema, Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
 #[schema(gui = "button_group")]
@@ -642,6 +931,77 @@ This is a similar effect to AR glasses."
     HsvChromaKey(#[schema(flag = "real-time")] HsvChromaKeyConfig),
 }
 
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct PassthroughEdgeColorConfig {
+    #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
+    pub red: f32,
+
+    #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
+    pub green: f32,
+
+    #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
+    pub blue: f32,
+
+    #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
+    pub alpha: f32,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct PassthroughColorLutConfig {
+    #[schema(strings(help = "Values above 1 darken the mid tones, values below 1 brighten them"))]
+    #[schema(gui(slider(min = 0.2, max = 3.0, step = 0.01)))]
+    pub gamma: f32,
+
+    #[schema(gui(slider(min = 0.0, max = 2.0, step = 0.01)))]
+    pub tint_red: f32,
+
+    #[schema(gui(slider(min = 0.0, max = 2.0, step = 0.01)))]
+    pub tint_green: f32,
+
+    #[schema(gui(slider(min = 0.0, max = 2.0, step = 0.01)))]
+    pub tint_blue: f32,
+
+    #[schema(strings(help = "Number of levels per color channel"))]
+    #[schema(gui(slider(min = 2, max = 16)))]
+    pub posterize_levels: Switch<u32>,
+
+    #[schema(strings(help = "Blend factor between the original and the mapped colors"))]
+    #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
+    pub weight: f32,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[schema(gui = "button_group")]
+pub enum PassthroughColorMap {
+    BrightnessContrastSaturation {
+        #[schema(gui(slider(min = -100.0, max = 100.0, step = 1.0)))]
+        brightness: f32,
+
+        #[schema(gui(slider(min = 0.0, max = 3.0, step = 0.01)))]
+        contrast: f32,
+
+        #[schema(gui(slider(min = 0.0, max = 3.0, step = 0.01)))]
+        saturation: f32,
+    },
+
+    #[schema(strings(
+        display_name = "Color LUT",
+        help = "Requires the XR_META_passthrough_color_lut extension"
+    ))]
+    ColorLut(PassthroughColorLutConfig),
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct PassthroughStyleConfig {
+    #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
+    pub opacity: f32,
+
+    #[schema(strings(help = "Highlights the edges detected in the passthrough image"))]
+    pub edge_color: Switch<PassthroughEdgeColorConfig>,
+
+    pub color_map: Switch<PassthroughColorMap>,
+}
+
 #[repr(u8)]
 #[derive(SettingsSchema, Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
 #[schema(gui = "button_group")]
@@ -670,6 +1030,69 @@ pub struct ClientsidePostProcessingConfig {
         help = "Improve clarity of high contrast edges and counteract blur.\nUseful when the input resolution is low compared to the headset display"
     ))]
     pub sharpening: ClientsidePostProcessingSharpeningMode,
+
+    #[schema(strings(
+        help = "Disable the sharpening while video is detected in front of a still head, like a movie in a virtual theater. Sharpening makes the compression artifacts of video content more visible"
+    ))]
+    pub disable_sharpening_on_video: bool,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct GazeBitrateAllocationConfig {
+    #[schema(strings(
+        help = "Side of the square region around the gaze point, relative to the view"
+    ))]
+    #[schema(gui(slider(min = 0.05, max = 0.5, step = 0.01)))]
+    pub region_size: f32,
+
+    #[schema(strings(
+        display_name = "QP reduction",
+        help = "Quantization decrease of the gaze region, on the H264/HEVC scale. The rate control compensates by using fewer bits for the rest of the frame"
+    ))]
+    #[schema(gui(slider(min = 1, max = 15)))]
+    pub qp_reduction: u32,
+
+    #[schema(strings(
+        help = "The whole frame is encoded uniformly if no gaze is received for this long"
+    ))]
+    #[schema(gui(slider(min = 50, max = 1000, step = 50)), suffix = "ms")]
+    pub gaze_timeout_ms: u64,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct IdleThrottlingConfig {
+    #[schema(strings(
+        help = "Time without changes on screen and without controller input before the frame rate is reduced"
+    ))]
+    #[schema(
+        gui(slider(min = 5.0, max = 300.0, step = 5.0, logarithmic)),
+        suffix = "s"
+    )]
+    pub trigger_seconds: f32,
+
+    #[schema(strings(
+        display_name = "Idle FPS",
+        help = "Frame rate of the encoding while idle. The headset keeps showing the last frame in between"
+    ))]
+    #[schema(gui(slider(min = 1.0, max = 30.0, step = 1.0)), suffix = "Hz")]
+    pub idle_fps: f32,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct DepthStreamConfig {
+    #[schema(strings(
+        help = "The depth is sent at the view resolution divided by this. Each halving of the divisor quadruples the bandwidth used by the depth"
+    ))]
+    #[schema(gui(slider(min = 4, max = 32, step = 2)))]
+    pub resolution_divisor: u32,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct SpaceWarpConfig {
+    #[schema(strings(
+        help = "Headset refresh rates for which space warp is used. The other refresh rates are streamed normally"
+    ))]
+    pub refresh_rates: Vec<f32>,
 }
 
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq, Debug)]
@@ -694,6 +1117,12 @@ pub struct VideoConfig {
     #[schema(flag = "real-time")]
     pub passthrough: Switch<PassthroughMode>,
 
+    #[schema(strings(
+        help = "Styling applied by the headset to the passthrough layer. Only supported on Meta headsets"
+    ))]
+    #[schema(flag = "real-time")]
+    pub passthrough_style: Switch<PassthroughStyleConfig>,
+
     pub bitrate: BitrateConfig,
 
     #[schema(strings(
@@ -709,6 +1138,28 @@ If you want to reduce the amount of pixelation on the edges, increase the center
     #[schema(flag = "steamvr-restart")]
     pub foveated_encoding: Switch<FoveatedEncodingConfig>,
 
+    #[schema(strings(
+        help = r"Encodes the region the user is looking at with higher quality, using the eye gaze reported by the headset. Supported by NVENC on Windows and by VAAPI and software encoding on Linux.
+Without eye tracking, or when the gaze is lost, the frame is encoded uniformly"
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub gaze_bitrate_allocation: Switch<GazeBitrateAllocationConfig>,
+
+    #[schema(strings(
+        help = r"Streams a low resolution depth buffer alongside the video, when the game submits one. The headset compositor uses it for positional reprojection and for the occlusion with passthrough and other layers.
+Requires XR_KHR_composition_layer_depth on the client. Windows only, GLES clients only"
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub stream_depth: Switch<DepthStreamConfig>,
+
+    #[schema(strings(
+        display_name = "Application Space Warp",
+        help = r"The game renders at half the refresh rate and the headset synthesizes every other frame from the streamed motion vectors and depth (XR_FB_space_warp). Requires depth streaming.
+The motion vectors are estimated by NVENC on a downscaled frame, Windows with an NVIDIA GPU only, SDR only. While the game doesn't submit depth, each frame is shown twice"
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub space_warp: Switch<SpaceWarpConfig>,
+
     #[schema(flag = "steamvr-restart")]
     pub color_correction: Switch<ColorCorrectionConfig>,
 
@@ -725,6 +1176,19 @@ If you want to reduce the amount of pixelation on the edges, increase the center
     #[schema(gui(slider(min = 0.50, max = 0.99, step = 0.01)))]
     pub buffering_history_weight: f32,
 
+    #[schema(strings(
+        display_name = "Encoded frame queue size",
+        help = "Maximum number of received frames waiting for a free decoder input buffer. When full, the oldest frame is dropped and a recovery frame is requested. If not set, the size is chosen based on the headset memory"
+    ))]
+    #[schema(gui(slider(min = 0, max = 64)), suffix = " frames")]
+    pub max_queued_encoded_frames: Option<u32>,
+
+    #[schema(strings(
+        display_name = "Client frame extrapolation",
+        help = r"When a new frame is not decoded in time for the headset refresh, the client reprojects the last frame to the current head rotation instead of showing it unchanged. This reduces judder but hides the missed frames: they are counted in the statistics and a warning is logged if they are frequent."
+    ))]
+    pub client_frame_extrapolation: bool,
+
     #[cfg_attr(not(target_os = "windows"), schema(flag = "hidden"))]
     #[schema(strings(
         help = r"This works only on Windows. It shouldn't be disabled except in certain circumstances when you know the VR game will not meet the target framerate."
@@ -732,8 +1196,59 @@ If you want to reduce the amount of pixelation on the edges, increase the center
     #[schema(flag = "real-time")]
     pub enforce_server_frame_pacing: bool,
 
-    #[schema(flag = "steamvr-restart")]
-    pub encoder_config: EncoderConfig,
+    #[cfg_attr(not(target_os = "windows"), schema(flag = "hidden"))]
+    #[schema(strings(
+        display_name = "Streamer-side reprojection",
+        help = r"This works only on Windows. Rotates the frame to the latest head orientation received from the headset right before encoding, and sends the corrected pose to the client so it is not corrected twice.
+The gained pose freshness is shown in the latency graph"
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub server_reprojection: bool,
+
+    #[cfg_attr(not(target_os = "linux"), schema(flag = "hidden"))]
+    #[schema(strings(
+        help = r"Auto uses NVENC on Nvidia and VAAPI on AMD/Intel. Vulkan Video supports only h264 and HEVC.
+If the selected backend fails to initialize, the next available one is used, with software encoding as the last resort"
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub encoder_backend: EncoderBackend,
+
+    #[schema(strings(
+        help = r"Trade-off between encoding latency and image quality. Speed, Balanced and Quality choose the presets of the encoder in use (NVENC, AMF, VAAPI, Vulkan Video or QSV).
+Custom uses the quality preset, NVENC and AMF options in the encoder config instead.
+Changing it while streaming recreates the encoder, without restarting SteamVR"
+    ))]
+    #[schema(flag = "real-time")]
+    pub encoder_effort: EncoderEffort,
+
+    #[schema(strings(
+        help = r"How the stream recovers from packet loss. IDR requests a full key frame, which is 5-10x bigger than a normal frame and can cause latency spikes.
+Intra refresh continuously refreshes a stripe of the image in every frame instead. It's supported only by NVENC and AMF; other encoders fall back to IDR"
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub recovery: VideoRecoveryMode,
+
+    #[schema(strings(
+        display_name = "Keyframe interval",
+        help = r"Send an IDR frame periodically, in addition to the ones requested by the client after packet loss. Requested IDR frames restart the interval.
+A short interval recovers faster on lossy networks, a long one saves bandwidth on clean networks. Ignored with intra refresh recovery"
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    #[schema(
+        gui(slider(min = 500, max = 30000, step = 500, logarithmic)),
+        suffix = "ms"
+    )]
+    pub keyframe_interval_ms: Switch<u64>,
+
+    #[cfg_attr(not(target_os = "windows"), schema(flag = "hidden"))]
+    #[schema(strings(
+        help = r"Reduces the frame rate of the encoding when the image doesn't change and the controllers are not used, for example when the headset is left facing a menu. The full frame rate is restored with a key frame on the first change or input.
+The changes are detected on a downscaled copy of the composed frame. Windows only"
+    ))]
+    pub idle_throttling: Switch<IdleThrottlingConfig>,
+
+    #[schema(flag = "steamvr-restart")]
+    pub encoder_config: EncoderConfig,
 
     #[schema(strings(
         help = "Attempts to use a software decoder on the device. Slow, but may work around broken codecs."
@@ -743,7 +1258,7 @@ If you want to reduce the amount of pixelation on the edges, increase the center
     pub mediacodec_extra_options: Vec<(String, MediacodecProperty)>,
 
     #[schema(strings(
-        help = "Resolution used for encoding and decoding. Relative to a single eye view."
+        help = "Resolution used for encoding and decoding. Relative to a single eye view. It is rounded down to a multiple of 32 and reduced if it exceeds the encoder limits."
     ))]
     #[schema(flag = "steamvr-restart")]
     pub transcoding_view_resolution: FrameSize,
@@ -761,11 +1276,26 @@ If you want to reduce the amount of pixelation on the edges, increase the center
 
     #[cfg_attr(not(target_os = "windows"), schema(flag = "hidden"))]
     #[schema(strings(
-        help = "You probably don't want to change this. Allows for changing adapter for ALVR compositor."
+        help = "Index of the adapter used for encoding. SteamVR renders on the adapter chosen in the graphics settings of Windows for vrcompositor.exe"
     ))]
     #[schema(flag = "steamvr-restart")]
     pub adapter_index: u32,
 
+    #[cfg_attr(not(target_os = "windows"), schema(flag = "hidden"))]
+    #[schema(strings(
+        help = "Selects the first adapter whose name contains this text, for example NVIDIA or Radeon. Takes precedence over the adapter index"
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub adapter_name: Switch<String>,
+
+    #[cfg_attr(not(target_os = "windows"), schema(flag = "hidden"))]
+    #[schema(strings(
+        display_name = "Cross-adapter encoding",
+        help = "Encode on the selected adapter even if SteamVR renders on another one. Every frame is copied between the adapters through system memory. If disabled, the adapter of SteamVR is used"
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub cross_adapter_encoding: bool,
+
     #[schema(strings(display_name = "Client-side foveation"))]
     pub clientside_foveation: Switch<ClientsideFoveationConfig>,
 
@@ -778,6 +1308,20 @@ If you want to reduce the amount of pixelation on the edges, increase the center
 
     #[schema(strings(help = "Snapdragon Game Super Resolution client-side upscaling"))]
     pub upscaling: Switch<UpscalingConfig>,
+
+    #[schema(strings(
+        help = r"How the stream is shown when its aspect ratio differs from the headset FOV. Stretch fills the FOV. Letterbox keeps the aspect ratio of the stream resolution and fills the rest with black bars.
+Useful with a custom stream resolution, but the image is smaller than the FOV in one direction"
+    ))]
+    pub aspect_ratio: StreamAspectRatio,
+
+    #[schema(strings(
+        display_name = "Client swapchain format",
+        help = r"Color format of the headset swapchain. sRGB converts the frames to linear light and lets the GPU encode them back on write. Linear writes the frames unchanged to an 8-bit linear swapchain, for runtimes that display it as sRGB.
+If colors look washed out try sRGB, if blacks look crushed try Linear. Auto selects the format per headset.
+With HDR enabled the float16 swapchain is always used and this setting has no effect"
+    ))]
+    pub client_swapchain_format: ClientSwapchainFormat,
 }
 
 #[derive(SettingsSchema, Serialize, Deserialize, Clone)]
@@ -892,11 +1436,34 @@ pub enum FaceTrackingSinkConfig {
     VrcFaceTracking,
 }
 
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct EyeGazeForwardingConfig {
+    #[schema(strings(
+        help = "Exponential smoothing of the gaze direction. Higher values are steadier but add latency"
+    ))]
+    #[schema(gui(slider(min = 0.0, max = 0.95, step = 0.05)))]
+    pub smoothing: f32,
+
+    #[schema(strings(
+        display_name = "Hold gaze during blinks",
+        help = "The last gaze direction is kept while the eyes are closed more than this amount, since the eye tracking is unreliable during blinks"
+    ))]
+    #[schema(gui(slider(min = 0.1, max = 1.0, step = 0.05)))]
+    pub blink_hold_threshold: Switch<f32>,
+}
+
 #[derive(SettingsSchema, Serialize, Deserialize, Clone)]
 #[schema(collapsible)]
 pub struct FaceTrackingConfig {
     pub sources: FaceTrackingSourcesConfig,
     pub sink: FaceTrackingSinkConfig,
+
+    #[schema(strings(
+        display_name = "Forward eye gaze",
+        help = "Sends where you are looking to the SteamVR eye tracking and to the applications listening to the sink. Gaze data can reveal sensitive information, when disabled only the facial expressions are sent"
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub eye_gaze: Switch<EyeGazeForwardingConfig>,
 }
 
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq, Default)]
@@ -961,6 +1528,44 @@ pub struct VMCConfig {
     pub orientation_correction: bool,
 }
 
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExternalTrackerRole {
+    Chest,
+    Hips,
+    LeftElbow,
+    RightElbow,
+    LeftKnee,
+    LeftFoot,
+    RightKnee,
+    RightFoot,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ExternalTrackerAssignment {
+    #[schema(strings(
+        help = "Name of the tracker or bone in the VMC messages, or index of the tracker in the VRChat OSC messages"
+    ))]
+    pub name: String,
+    pub role: ExternalTrackerRole,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+#[schema(collapsible)]
+pub struct ExternalTrackersConfig {
+    #[schema(strings(help = "UDP port where the VMC or VRChat OSC messages are received"))]
+    pub port: u16,
+
+    #[schema(flag = "real-time")]
+    pub assignments: Vec<ExternalTrackerAssignment>,
+
+    #[schema(strings(
+        help = "Duration of the capture started by the calibrate button in the debug tab. The external system must report the headset pose during the capture. Move and turn your head while it runs."
+    ))]
+    #[schema(gui(slider(min = 1, max = 30)), suffix = "s")]
+    #[schema(flag = "real-time")]
+    pub calibration_duration_s: u64,
+}
+
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub enum ControllersEmulationMode {
     #[schema(strings(display_name = "Rift S Touch"))]
@@ -989,6 +1594,19 @@ pub enum ControllersEmulationMode {
     },
 }
 
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct ControllerProfilePoseOffset {
+    pub emulation_mode: ControllersEmulationMode,
+
+    #[schema(strings(help = "Right controller offset is mirrored horizontally"))]
+    #[schema(gui(slider(min = -0.1, max = 0.1, step = 0.001)), suffix = "m")]
+    pub position_offset: [f32; 3],
+
+    #[schema(strings(help = "Right controller offset is mirrored horizontally"))]
+    #[schema(gui(slider(min = -45.0, max = 45.0, step = 0.1)), suffix = "°")]
+    pub rotation_offset: [f32; 3],
+}
+
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy)]
 pub struct HysteresisThreshold {
     #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
@@ -1037,6 +1655,136 @@ pub struct AutomaticButtonMappingConfig {
     pub force_threshold: f32,
 }
 
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ButtonMacroAction {
+    Recenter,
+    #[schema(strings(
+        help = "Toggles the passthrough until the client disconnects, without changing the settings"
+    ))]
+    TogglePassthrough,
+    #[schema(strings(help = "Stops sending the microphone of the headset, without disconnecting"))]
+    ToggleMicrophoneMute,
+    #[schema(strings(display_name = "Request IDR frame"))]
+    RequestIdr,
+    #[schema(strings(
+        help = "Toggles the blocking of the face, eye and body data, for streaming to an audience"
+    ))]
+    ToggleBiometricStreams,
+    #[schema(strings(
+        help = "Toggles the client-side sharpening, for example while watching video content"
+    ))]
+    ToggleSharpening,
+    #[schema(strings(
+        display_name = "Capture frame",
+        help = "Saves the next encoded frame, like the capture button in the dashboard"
+    ))]
+    CaptureFrame,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ButtonMacroConfig {
+    // Edited from the row header of the list
+    #[schema(flag = "hidden")]
+    pub enabled: bool,
+    #[schema(flag = "hidden")]
+    pub display_name: String,
+
+    #[schema(strings(help = "OpenXR-style paths of the buttons that must be held together"))]
+    pub chord: Vec<String>,
+
+    #[schema(strings(help = "Time the whole chord must be held before the action is triggered"))]
+    #[schema(gui(slider(min = 0, max = 3000, step = 50)), suffix = "ms")]
+    pub hold_duration_ms: u64,
+
+    pub action: ButtonMacroAction,
+
+    #[schema(strings(
+        help = "The buttons of the chord are released for SteamVR while the chord is held, so that the game does not react to them"
+    ))]
+    pub consume_buttons: bool,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AxisCurvePoint {
+    #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
+    pub input: f32,
+    #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
+    pub output: f32,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+pub enum AxisResponseCurve {
+    Linear,
+    Power {
+        #[schema(strings(
+            help = "Values above 1 give more precision near the center, values below 1 make the response faster"
+        ))]
+        #[schema(gui(slider(min = 0.2, max = 5.0, step = 0.05)))]
+        exponent: f32,
+    },
+    Custom {
+        #[schema(strings(
+            help = "Points of the curve, interpolated linearly. The curve starts at 0 and ends at 1"
+        ))]
+        points: Vec<AxisCurvePoint>,
+    },
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+#[schema(collapsible)]
+pub struct AxisProcessingConfig {
+    #[schema(strings(
+        help = "Values below this are ignored. Removes the thumbstick drift and the trigger jitter"
+    ))]
+    #[schema(gui(slider(min = 0.0, max = 0.5, step = 0.01)))]
+    pub inner_deadzone: f32,
+
+    #[schema(strings(
+        help = "Values closer than this to the maximum are treated as fully pushed"
+    ))]
+    #[schema(gui(slider(min = 0.0, max = 0.5, step = 0.01)))]
+    pub outer_deadzone: f32,
+
+    #[schema(strings(
+        help = "Smallest value sent outside of the inner deadzone. Compensates the deadzone applied by the game"
+    ))]
+    #[schema(gui(slider(min = 0.0, max = 0.5, step = 0.01)))]
+    pub anti_deadzone: f32,
+
+    pub response_curve: AxisResponseCurve,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AnalogAxesConfig {
+    pub thumbsticks: AxisProcessingConfig,
+    pub triggers: AxisProcessingConfig,
+    pub grips: AxisProcessingConfig,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+pub enum AnalogInputProcessingMode {
+    #[schema(strings(help = "Deadzones tuned for the controllers of the headset"))]
+    Automatic,
+    Custom(AnalogAxesConfig),
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct InputSourceSwitchConfig {
+    #[schema(strings(
+        help = "Time the new input source must stay active before switching to it. Prevents flickering when the controllers or the hands briefly lose tracking"
+    ))]
+    #[schema(gui(slider(min = 0, max = 2000, step = 50)), suffix = "ms")]
+    pub switch_delay_ms: u64,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct EnabledHandDevicesConfig {
+    pub left_controller: bool,
+    pub right_controller: bool,
+    pub left_hand: bool,
+    pub right_hand: bool,
+}
+
 #[derive(SettingsSchema, Serialize, Deserialize, Clone)]
 pub struct HandTrackingInteractionConfig {
     #[schema(flag = "real-time")]
@@ -1111,6 +1859,24 @@ pub struct HandTrackingInteractionConfig {
     pub repeat_delay: u32,
 }
 
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct HapticsBurstMergingConfig {
+    #[schema(strings(display_name = "Maximum gap"))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.0, max = 0.1, step = 0.005)), suffix = "s")]
+    pub max_gap_s: f32,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct HapticsLegacyPulsesConfig {
+    #[schema(strings(
+        help = "Length of the window the strength of the pulses is averaged over. Longer windows give smoother vibrations but react later."
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.01, max = 0.2, step = 0.01)), suffix = "s")]
+    pub window_s: f32,
+}
+
 #[derive(SettingsSchema, Serialize, Deserialize, Clone)]
 #[schema(collapsible)]
 pub struct HapticsConfig {
@@ -1126,6 +1892,37 @@ pub struct HapticsConfig {
     #[schema(flag = "real-time")]
     #[schema(gui(slider(min = 0.0, max = 0.1, step = 0.001)), suffix = "s")]
     pub min_duration_s: f32,
+
+    #[schema(strings(
+        display_name = "Minimum amplitude",
+        help = "Weaker vibrations are raised to this amplitude. Requests to stop the vibration are not affected."
+    ))]
+    #[schema(flag = "real-time")]
+    #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)))]
+    pub min_amplitude: f32,
+
+    #[schema(strings(
+        help = "Joins bursts of short events into a continuous vibration. Each vibration is extended until the next event, if it arrives within the maximum gap."
+    ))]
+    #[schema(flag = "real-time")]
+    pub merge_bursts: Switch<HapticsBurstMergingConfig>,
+
+    #[schema(strings(
+        display_name = "Convert legacy pulses",
+        help = "Older games use the legacy OpenVR haptic pulses, which last a few milliseconds and encode the strength in their duration. They are converted into vibrations with the strength averaged over a window."
+    ))]
+    #[schema(flag = "real-time")]
+    pub legacy_pulses: Switch<HapticsLegacyPulsesConfig>,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct HapticsGameOverride {
+    #[schema(strings(
+        help = "File name of the executable of the game, for example game.exe. The case is ignored."
+    ))]
+    pub executable_name: String,
+
+    pub haptics: HapticsConfig,
 }
 
 #[derive(SettingsSchema, Serialize, Deserialize, Clone)]
@@ -1151,6 +1948,25 @@ pub struct ControllersConfig {
     #[schema(flag = "real-time")]
     pub tracked: bool,
 
+    #[schema(flag = "real-time")]
+    #[schema(strings(
+        help = r"Disabled devices are hidden from SteamVR while they are in use, without disabling the other input. For example, disabling the hands removes the floating controllers when using hand tracking, and the controllers appear again as soon as they are picked up."
+    ))]
+    pub enabled_devices: EnabledHandDevicesConfig,
+
+    #[schema(strings(
+        display_name = "Automatic input source switch",
+        help = r"The headset detects for each hand whether a controller is held or the hand is tracked, and only the active source is sent to SteamVR."
+    ))]
+    pub input_source_switch: Switch<InputSourceSwitchConfig>,
+
+    #[schema(flag = "real-time")]
+    #[schema(strings(
+        display_name = "Analog input processing",
+        help = "Deadzones and response curves applied to the thumbsticks, triggers and grips before the button mapping"
+    ))]
+    pub analog_processing: Switch<AnalogInputProcessingMode>,
+
     #[schema(flag = "steamvr-restart")]
     #[schema(strings(
         help = "Enabling this passes skeletal hand data (finger tracking) to SteamVR."
@@ -1163,6 +1979,13 @@ pub struct ControllersConfig {
     ))]
     pub hand_tracking_interaction: Switch<HandTrackingInteractionConfig>,
 
+    #[schema(strings(
+        display_name = "Hand interaction profile",
+        help = r"Pinch and grasp recognized by the headset runtime are sent as trigger and grip of the controllers, when the runtime supports XR_EXT_hand_interaction.
+Disable the hand tracking interaction above to avoid duplicated inputs."
+    ))]
+    pub hand_interaction_profile: bool,
+
     #[schema(strings(
         display_name = "Prediction",
         help = r"Higher values make the controllers track smoother.
@@ -1172,9 +1995,24 @@ Currently this cannot be reliably estimated automatically. The correct value sho
     #[schema(gui(slider(min = 1.0, max = 10.0, logarithmic)), suffix = "frames")]
     pub steamvr_pipeline_frames: f32,
 
+    #[schema(flag = "real-time")]
+    #[schema(strings(
+        display_name = "Maximum prediction",
+        help = r"Maximum prediction for the controllers, independent from the head. Zero disables the extrapolation of the controller poses."
+    ))]
+    #[schema(gui(slider(min = 0, max = 200, step = 5)), suffix = "ms")]
+    pub max_prediction_ms: u64,
+
     #[schema(flag = "real-time")]
     pub haptics: Switch<HapticsConfig>,
 
+    #[schema(flag = "real-time")]
+    #[schema(strings(
+        display_name = "Per-game haptics",
+        help = "Haptics settings used instead of the ones above while the game is running, if haptics are enabled. They are selected after the per-client overrides are applied."
+    ))]
+    pub game_haptics: Vec<HapticsGameOverride>,
+
     #[schema(flag = "steamvr-restart")]
     pub emulation_mode: ControllersEmulationMode,
 
@@ -1214,10 +2052,23 @@ Currently this cannot be reliably estimated automatically. The correct value sho
     #[schema(gui(slider(min = -180.0, max = 180.0, step = 1.0)), suffix = "°")]
     pub left_hand_tracking_rotation_offset: [f32; 3],
 
+    #[schema(flag = "real-time")]
+    #[schema(strings(
+        display_name = "Per-profile pose offsets",
+        help = r"Offsets applied on top of the controller and hand tracking offsets when the emulation mode matches, to fix the grip pose of specific emulated controllers. Suggested values can be computed with the calibration tool in the Devices tab."
+    ))]
+    pub profile_pose_offsets: Vec<ControllerProfilePoseOffset>,
+
     #[schema(strings(help = "List of OpenXR-syle paths"))]
     pub button_mappings: Option<Vec<(String, Vec<ButtonBindingTarget>)>>,
 
     pub button_mapping_config: AutomaticButtonMappingConfig,
+
+    #[schema(flag = "real-time")]
+    #[schema(strings(
+        help = "Button chords that trigger streamer actions instead of (or in addition to) being sent to SteamVR"
+    ))]
+    pub button_macros: Vec<ButtonMacroConfig>,
 }
 
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy)]
@@ -1230,6 +2081,15 @@ pub enum PositionRecenteringMode {
     },
 }
 
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ChaperoneSyncMode {
+    #[schema(strings(display_name = "Sync from headset"))]
+    SyncFromHeadset,
+    #[schema(strings(display_name = "Keep SteamVR"))]
+    KeepSteamVr,
+    Disabled,
+}
+
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy)]
 pub enum RotationRecenteringMode {
     Disabled,
@@ -1250,6 +2110,175 @@ This will be configurable in the future."
     pub detached_controllers_steamvr_sink: bool,
 }
 
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct ViewOverrideConfig {
+    #[schema(strings(
+        display_name = "IPD",
+        help = "Distance between the eyes used for rendering, instead of the one reported by the headset"
+    ))]
+    #[schema(gui(slider(min = 50.0, max = 80.0, step = 0.5)), suffix = "mm")]
+    pub ipd_mm: Switch<f32>,
+
+    #[schema(strings(
+        display_name = "Convergence",
+        help = "Rotates each view inward (positive) or outward (negative) by this angle"
+    ))]
+    #[schema(gui(slider(min = -2.0, max = 2.0, step = 0.05)), suffix = "°")]
+    pub convergence_deg: f32,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[schema(gui = "button_group")]
+pub enum MarkerOriginMode {
+    Server,
+    #[schema(strings(display_name = "Client (co-location)"))]
+    Client,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct MarkerOriginConfig {
+    #[schema(strings(help = "Text encoded in the QR code that marks the origin"))]
+    pub marker_code: String,
+
+    #[schema(
+        strings(help = r"Server: the SteamVR playspace is aligned to the marker.
+Client (co-location): the headset moves its own tracking origin to the marker, so every headset that sees the same code agrees on coordinates. Server recentering is disabled.
+In both modes the last origin is kept while the marker is not visible.")
+    )]
+    pub mode: MarkerOriginMode,
+
+    #[schema(strings(
+        help = r"The playspace origin keeps following the marker while it is visible, instead of being set when the marker is found. Use it with a marker attached to a motion platform or a vehicle. Only used in server mode."
+    ))]
+    pub follow: Switch<MarkerFollowConfig>,
+
+    #[schema(strings(
+        help = r"The headset remembers the pose of the marker and sets the origin on startup, without waiting to see the marker again. The pose is updated when the marker is seen. Use it for fixed installations, with the stage tracking space."
+    ))]
+    pub remember_pose: bool,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MarkerFollowConfig {
+    #[schema(strings(
+        help = "Time constant of the filter applied to the marker pose. Higher values hide more detection jitter but make the playspace lag behind the platform"
+    ))]
+    #[schema(gui(slider(min = 0.0, max = 1.0, step = 0.01)), suffix = "s")]
+    pub smoothing_s: f32,
+
+    #[schema(strings(
+        help = "Follow the pitch and roll of the marker too, for motion platforms. Otherwise the origin stays level and only follows the position and heading"
+    ))]
+    pub follow_tilt: bool,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[schema(gui = "button_group")]
+pub enum KeyboardInjectionMode {
+    #[schema(strings(display_name = "Physical keys"))]
+    Scancode,
+    #[schema(strings(display_name = "Typed characters"))]
+    Unicode,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct PeripheralInputConfig {
+    #[schema(strings(
+        help = r"Physical keys: keys are injected by position and translated with the keyboard layout of the PC. Use this for games and shortcuts.
+Typed characters: printable keys are injected as the characters produced by the keyboard layout of the headset. Other keys are injected by position."
+    ))]
+    pub keyboard_mode: KeyboardInjectionMode,
+
+    #[schema(gui(slider(min = 0.1, max = 5.0, step = 0.1)), suffix = "x")]
+    pub mouse_sensitivity: f32,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct ClipboardSyncConfig {
+    #[schema(strings(display_name = "Headset to PC"))]
+    pub client_to_server: bool,
+
+    #[schema(strings(display_name = "PC to headset"))]
+    pub server_to_client: bool,
+
+    #[schema(strings(help = "Longer text is not synchronized"))]
+    #[schema(gui(slider(min = 1, max = 1024, logarithmic)), suffix = "KB")]
+    pub max_size_kb: u64,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct InHeadsetMenuConfig {
+    #[schema(strings(
+        help = "Buttons to hold together to open and close the menu. While the menu is open, the controller buttons are not sent to SteamVR."
+    ))]
+    pub open_chord: Vec<String>,
+
+    #[schema(gui(slider(min = 1, max = 100)), suffix = "Mbps")]
+    pub bitrate_step_mbps: u64,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[schema(gui = "button_group")]
+pub enum GestureHand {
+    Left,
+    Right,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[schema(gui = "button_group")]
+pub enum HandPoseGesture {
+    #[schema(strings(help = "Tips of the thumb and the index finger touching"))]
+    Pinch,
+    #[schema(strings(help = "Tips of all the fingers close to the palm"))]
+    Fist,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+pub enum GestureTrigger {
+    #[schema(strings(help = "Two presses of a button in quick succession"))]
+    DoubleTap {
+        #[schema(strings(
+            help = "OpenXR-style path of the button. Analog inputs are pressed past half of their range"
+        ))]
+        button: String,
+
+        #[schema(strings(help = "Maximum time between the two presses"))]
+        #[schema(gui(slider(min = 100, max = 1000, step = 50)), suffix = "ms")]
+        max_interval_ms: u64,
+    },
+    #[schema(strings(help = "Pose held with hand tracking"))]
+    HandPose {
+        hand: GestureHand,
+        pose: HandPoseGesture,
+
+        #[schema(gui(slider(min = 0, max = 3000, step = 50)), suffix = "ms")]
+        hold_duration_ms: u64,
+    },
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum GestureAction {
+    #[schema(strings(help = "Only if the in-headset menu is enabled"))]
+    ToggleInHeadsetMenu,
+    #[schema(strings(
+        display_name = "Streamer action",
+        help = "Same actions as the button macros"
+    ))]
+    Streamer(ButtonMacroAction),
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GestureConfig {
+    // Edited from the row header of the list
+    #[schema(flag = "hidden")]
+    pub enabled: bool,
+    #[schema(flag = "hidden")]
+    pub display_name: String,
+
+    pub trigger: GestureTrigger,
+    pub action: GestureAction,
+}
+
 #[derive(SettingsSchema, Serialize, Deserialize, Clone)]
 pub struct HeadsetConfig {
     #[schema(strings(
@@ -1268,6 +2297,20 @@ Tilted: the world gets tilted when long pressing the oculus button. This is usef
     #[schema(flag = "real-time")]
     pub rotation_recentering_mode: RotationRecenteringMode,
 
+    #[schema(strings(
+        help = r"Use the floor height estimated by the headset runtime, if supported. Otherwise the floor of the room-scale guardian is used.
+Headsets without a room-scale guardian fall back to a space centered on the head, which requires the Local position recentering mode to set the view height."
+    ))]
+    pub use_local_floor_space: bool,
+
+    #[schema(strings(
+        help = r"Sync from headset: the SteamVR chaperone follows the guardian of the headset, and it is updated on recentering.
+Keep SteamVR: the SteamVR chaperone setup is not modified.
+Disabled: only the play area size is set, and the SteamVR chaperone is hidden."
+    ))]
+    #[schema(gui = "button_group")]
+    pub chaperone_sync: ChaperoneSyncMode,
+
     #[schema(flag = "steamvr-restart")]
     pub controllers: Switch<ControllersConfig>,
 
@@ -1301,10 +2344,64 @@ Because of runtime limitations, this option is ignored when body tracking is act
     pub vmc: Switch<VMCConfig>,
 
     #[schema(strings(
-        help = "Maximum prediction for head and controllers. Used to avoid too much jitter during loading."
+        help = r"Receive trackers from other systems, like SlimeVR, through VMC or VRChat OSC messages, and show them in SteamVR as body trackers.
+The trackers are moved to the playspace with a calibration, computed from the headset pose reported by both systems. Use the calibrate button in the debug tab while streaming."
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub external_trackers: Switch<ExternalTrackersConfig>,
+
+    #[schema(strings(
+        help = "Maximum prediction for the head. Used to avoid too much jitter during loading. The controllers have a separate limit."
     ))]
     #[schema(gui(slider(min = 0, max = 200, step = 5)), suffix = "ms")]
     pub max_prediction_ms: u64,
+
+    #[schema(strings(
+        help = "Number of recent head poses kept by the client. When the headset runtime fails to locate the views at the display time, the pose is interpolated or extrapolated from this history instead of resetting to the origin."
+    ))]
+    #[schema(gui(slider(min = 2, max = 256)), suffix = " poses")]
+    pub pose_history_size: usize,
+
+    #[schema(strings(
+        help = "Override the eye separation and convergence used to render the stream. This can help with comfort issues on fixed IPD headsets."
+    ))]
+    pub view_override: Switch<ViewOverrideConfig>,
+
+    #[schema(strings(
+        help = r"Use a printed QR code as the playspace origin, overriding recentering. The origin lies on the floor below the code, facing the same direction of the code if hung on a wall, or the code top if laid on the floor.
+Headsets and PCs looking at the same code share the same origin. Requires a headset that supports marker tracking."
+    ))]
+    pub marker_origin: Switch<MarkerOriginConfig>,
+
+    #[schema(strings(
+        display_name = "Marker selection in the lobby",
+        help = r"The lobby tracks all the QR codes in view, and one can be chosen as origin by touching it. It is used in server mode when the marker origin is disabled. Requires a headset that supports marker tracking.
+Takes effect on the next startup of the client"
+    ))]
+    pub lobby_marker_selection: bool,
+
+    #[schema(strings(
+        display_name = "Keyboard and mouse passthrough",
+        help = r"Forward the Bluetooth keyboard and mouse paired with the headset to the PC. On Linux, write access to /dev/uinput is required.
+Press Ctrl+Shift+F12 on the keyboard to stop the injection until the headset reconnects."
+    ))]
+    pub peripheral_input: Switch<PeripheralInputConfig>,
+
+    #[schema(strings(
+        help = "Synchronize plain text copied on the headset and on the PC. Everything copied while streaming is sent to the other device."
+    ))]
+    pub clipboard_sync: Switch<ClipboardSyncConfig>,
+
+    #[schema(strings(
+        help = r"Small menu shown in the headset to change the bitrate, recenter and toggle passthrough and sharpening.
+Move the right thumbstick up and down to select an entry, left and right to change it, press A to confirm."
+    ))]
+    pub in_headset_menu: Switch<InHeadsetMenuConfig>,
+
+    #[schema(strings(
+        help = "Controller and hand gestures recognized by the client, to trigger actions without reserving buttons"
+    ))]
+    pub gestures: Vec<GestureConfig>,
 }
 
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy)]
@@ -1316,6 +2413,59 @@ pub enum SocketProtocol {
     Tcp,
 }
 
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+#[schema(collapsible)]
+pub struct ClientReconnectConfig {
+    #[schema(strings(help = "Time between the first attempts to find the streamer"))]
+    #[schema(gui(slider(min = 500, max = 10000, step = 500)), suffix = "ms")]
+    pub base_interval_ms: u64,
+
+    #[schema(strings(help = "The interval doubles after each attempt, up to this value"))]
+    #[schema(gui(slider(min = 1, max = 300)), suffix = "s")]
+    pub max_interval_s: u64,
+
+    #[schema(strings(
+        help = "The client stops searching after this many attempts, until the headset is taken off and put on again or the app is reopened"
+    ))]
+    pub max_retries: Switch<u32>,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+#[schema(collapsible)]
+pub struct KeepaliveConfig {
+    #[schema(strings(help = "Time between the keepalive packets sent by both sides"))]
+    #[schema(gui(slider(min = 100, max = 5000, step = 100)), suffix = "ms")]
+    pub interval_ms: u64,
+
+    #[schema(strings(
+        help = "The connection is closed when no control packet is received for this time, or when sending to the other side is blocked for this time. Lower values let the headset reconnect sooner after it turns off abruptly, higher values tolerate network hiccups"
+    ))]
+    #[schema(gui(slider(min = 1000, max = 30000, step = 500)), suffix = "ms")]
+    pub timeout_ms: u64,
+
+    #[schema(strings(
+        help = "The streamer closes the connection when it receives no tracking or other stream packets for this time. Not applied while the headset is in standby"
+    ))]
+    #[schema(gui(slider(min = 1000, max = 60000, step = 1000)), suffix = "ms")]
+    pub stream_inactivity_timeout_ms: Switch<u64>,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct PacketLossSimulationConfig {
+    #[schema(strings(help = "Average fraction of the video packets that are dropped"))]
+    #[schema(gui(slider(min = 0.0, max = 50.0, step = 0.5)), suffix = "%")]
+    pub loss_percent: f32,
+
+    #[schema(strings(
+        help = "Average number of consecutive packets dropped by each loss event. With 1 the losses are independent. The average loss stays the same"
+    ))]
+    #[schema(gui(slider(min = 1, max = 50)), suffix = " packets")]
+    pub mean_burst_length: u32,
+
+    #[schema(strings(help = "The same seed drops the same packets in every session"))]
+    pub seed: u64,
+}
+
 #[derive(SettingsSchema, Serialize, Deserialize, Clone)]
 pub struct DiscoveryConfig {
     #[cfg_attr(target_os = "linux", schema(flag = "hidden"))]
@@ -1405,7 +2555,7 @@ TCP: Slower than UDP, but more stable. Pick this if you experience video or audi
     pub allow_untrusted_http: bool,
 
     #[schema(strings(
-        help = r#"If the client, server or the network discarded one packet, discard packets until a IDR packet is found."#
+        help = r#"If the client, server or the network discarded one packet, discard packets until a IDR packet is found. With intra refresh recovery, the image is frozen until the refresh cycle completes."#
     ))]
     pub avoid_video_glitching: bool,
 
@@ -1427,6 +2577,18 @@ This could happen on TCP. A IDR frame is requested in this case."#
     ))]
     pub max_queued_server_video_frames: usize,
 
+    #[schema(strings(help = r"Burst: each video frame is sent as fast as possible.
+Paced: the frame is spread over part of the frame interval, which fills the buffers of the router less. Adds up to that time of latency. Only applies to UDP.
+In both modes, audio and haptics packets are sent between the parts of the video frames."))]
+    pub video_pacing: VideoPacing,
+
+    #[schema(strings(
+        help = r"Holds the tracking packets for up to this fraction of a frame to put the packets reordered by the network back in order. Adds latency to the tracking, only use it on networks with heavy reordering.
+When disabled, the packets received late are dropped."
+    ))]
+    #[schema(gui(slider(min = 0.1, max = 1.0, step = 0.05)), suffix = " frames")]
+    pub tracking_jitter_buffer: Switch<f32>,
+
     #[schema(suffix = " frames")]
     pub statistics_history_size: usize,
 
@@ -1437,6 +2599,52 @@ This could happen on TCP. A IDR frame is requested in this case."#
 
     #[schema(strings(display_name = "DSCP (packet prio hints)"))]
     pub dscp: Option<DscpTos>,
+
+    #[schema(
+        strings(help = r#"What to do when the headset is taken off or goes to standby.
+Pause encoding: the connection and SteamVR stay alive, the stream resumes with a IDR frame.
+Disconnect after: pause encoding, then disconnect if the headset is still in standby after the set time."#)
+    )]
+    pub standby_behavior: StandbyBehavior,
+
+    #[schema(strings(
+        help = "Detection of a streamer or headset that stopped responding without closing the connection, for example because the battery died"
+    ))]
+    pub keepalive: KeepaliveConfig,
+
+    #[schema(strings(
+        help = "How the client retries to find the streamer when it is not running. Sent to the client when it connects, so it applies from the next disconnection."
+    ))]
+    pub client_reconnect: ClientReconnectConfig,
+
+    #[cfg_attr(not(debug_assertions), schema(flag = "hidden"))]
+    #[schema(strings(
+        help = "The streamer drops video packets on purpose before sending them, to test the recovery from packet loss. Takes effect on the next connection"
+    ))]
+    pub video_packet_loss_simulation: Switch<PacketLossSimulationConfig>,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum VideoPacing {
+    Burst,
+    Paced {
+        #[schema(strings(help = "Fraction of the frame interval used to send each frame"))]
+        #[schema(gui(slider(min = 10.0, max = 90.0, step = 5.0)), suffix = "%")]
+        frame_interval_percent: f32,
+    },
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum StandbyBehavior {
+    PauseEncoding {
+        #[schema(strings(help = "Keep sending one frame per second while paused"))]
+        keepalive_frames: bool,
+    },
+    KeepStreaming,
+    DisconnectAfter {
+        #[schema(gui(slider(min = 1, max = 60)), suffix = " min")]
+        minutes: u32,
+    },
 }
 
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy)]
@@ -1469,6 +2677,15 @@ pub struct RawEventsConfig {
     pub hide_spammy_events: bool,
 }
 
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct LogRotationConfig {
+    #[schema(gui(slider(min = 1, max = 200, step = 1)), suffix = "MB")]
+    pub max_file_size_mb: u64,
+    #[schema(strings(help = "Counting the current file. The oldest file is deleted"))]
+    #[schema(gui(slider(min = 1, max = 20, step = 1)))]
+    pub max_files: usize,
+}
+
 #[derive(SettingsSchema, Serialize, Deserialize, Clone)]
 pub struct LoggingConfig {
     #[schema(strings(help = "Notification tips teach you how to use ALVR"))]
@@ -1487,8 +2704,27 @@ pub struct LoggingConfig {
     pub show_raw_events: Switch<RawEventsConfig>,
 
     #[schema(strings(help = "Write logs into the session_log.txt file."))]
+    #[schema(flag = "steamvr-restart")]
     pub log_to_disk: bool,
 
+    #[schema(strings(
+        help = "Start a new session_log.txt when it reaches the maximum size. The older files are renamed session_log.1.txt, session_log.2.txt and so on."
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub log_rotation: Switch<LogRotationConfig>,
+
+    #[schema(strings(
+        help = "Write session_log.txt as one JSON object per line, keeping the structured fields of the log records"
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub log_json_lines: bool,
+
+    #[schema(strings(
+        help = "Comma separated log levels by target, for example: alvr_sockets=debug, alvr_server_core::bitrate=trace. The levels are off, error, warn, info, debug and trace."
+    ))]
+    #[schema(flag = "real-time")]
+    pub target_levels: String,
+
     #[schema(flag = "real-time")]
     pub log_tracking: bool,
 
@@ -1522,6 +2758,18 @@ pub struct SteamvrLauncher {
                 Will run start_server.sh if it exists alongside session.json, and try to automatically find SteamVR otherwise."))
     )]
     pub direct_launch: bool,
+
+    #[schema(strings(
+        display_name = "Launch SteamVR when a trusted headset is found",
+        help = "While the dashboard is open and SteamVR is not running, SteamVR is launched as soon as a trusted headset is discovered on the network. Together with the wake-up button in the headset lobby and the dashboard opening at login, the PC can be started from the headset."
+    ))]
+    pub launch_on_client_discovery: bool,
+
+    #[schema(strings(
+        display_name = "Launch Steam app",
+        help = "Steam app ID launched after SteamVR was started for a discovered headset. The ID is shown in the URL of the store page."
+    ))]
+    pub autolaunch_app_id: Switch<u32>,
 }
 
 #[derive(SettingsSchema, Serialize, Deserialize, Clone)]
@@ -1561,6 +2809,53 @@ pub struct NewVersionPopupConfig {
     pub hide_while_version: String,
 }
 
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct PhotodiodeLatencyTestConfig {
+    #[schema(strings(help = "OpenXR path of the controller button that triggers the flash"))]
+    pub trigger_button: String,
+
+    #[schema(gui(slider(min = 1, max = 30)), suffix = " frames")]
+    pub flash_duration: u32,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub enum MetricsExporterMode {
+    Prometheus {
+        #[schema(strings(
+            help = "Address and port of the /metrics endpoint. Use 0.0.0.0 to let other machines scrape it"
+        ))]
+        bind_address: String,
+    },
+    Statsd {
+        #[schema(strings(
+            help = "Address and port of the statsd server, metrics are sent over UDP"
+        ))]
+        server_address: String,
+        prefix: String,
+    },
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct MetricsExporterConfig {
+    pub mode: MetricsExporterMode,
+
+    #[schema(strings(
+        help = "Interval between the statsd pushes, or between the updates of the values served to Prometheus"
+    ))]
+    #[schema(gui(slider(min = 100, max = 10000, step = 100)), suffix = "ms")]
+    pub update_interval_ms: u64,
+}
+
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+pub struct PrivacyConfig {
+    #[schema(strings(
+        display_name = "Block biometric streams",
+        help = "The headset stops sampling and sending the face expressions, the eye gaze and the body skeleton, regardless of the other settings. An indicator is shown in the headset. Can be toggled with a button macro"
+    ))]
+    #[schema(flag = "real-time")]
+    pub block_biometric_streams: bool,
+}
+
 #[derive(SettingsSchema, Serialize, Deserialize, Clone)]
 pub struct ExtraConfig {
     #[schema(strings(display_name = "SteamVR Launcher"))]
@@ -1576,7 +2871,33 @@ It does not update in real time.")
     )]
     pub velocities_multiplier: f32,
 
+    #[schema(strings(
+        help = r"Pressing the trigger button turns the whole view white, starting from the first frame rendered by the PC after receiving the input. Point a photodiode at a lens to measure the motion-to-photon latency.
+The server and the client log the timestamp of the flashed frame, enable the client log report to collect both in the server log."
+    ))]
+    pub photodiode_latency_test: Switch<PhotodiodeLatencyTestConfig>,
+
+    #[schema(strings(
+        help = "Export the streaming statistics (frame rates, latency, bitrate, packet loss, battery levels) to a monitoring system such as Grafana. The metrics are read-only and have no authentication"
+    ))]
+    #[schema(flag = "steamvr-restart")]
+    pub metrics_exporter: Switch<MetricsExporterConfig>,
+
+    #[schema(strings(
+        help = r"The client does not enable the optional OpenXR extensions (marker, eye, face and body tracking, passthrough), to recover from crashes caused by them. Takes effect on the next startup of the client.
+Safe mode is also used automatically for one startup after the client crashed while starting"
+    ))]
+    pub client_safe_mode: bool,
+
+    #[schema(strings(
+        help = "Graphics API used by the client to render the stream. Auto uses Vulkan if the runtime supports it, and falls back to OpenGL ES otherwise. Takes effect on the next startup of the client"
+    ))]
+    pub client_graphics_api: ClientGraphicsApi,
+
+    // Read by the dashboard only
+    #[schema(flag = "real-time")]
     pub open_setup_wizard: bool,
+    #[schema(flag = "real-time")]
     pub new_version_popup: Switch<NewVersionPopupConfig>,
 }
 
@@ -1586,6 +2907,7 @@ pub struct Settings {
     pub audio: AudioConfig,
     pub headset: HeadsetConfig,
     pub connection: ConnectionConfig,
+    pub privacy: PrivacyConfig,
     pub extra: ExtraConfig,
 }
 
@@ -1616,6 +2938,21 @@ pub fn session_settings_default() -> SettingsDefault {
         },
         content: vec![],
     };
+    let default_haptics = HapticsConfigDefault {
+        gui_collapsed: true,
+        intensity_multiplier: 1.0,
+        amplitude_curve: 1.0,
+        min_duration_s: 0.01,
+        min_amplitude: 0.0,
+        merge_bursts: SwitchDefault {
+            enabled: false,
+            content: HapticsBurstMergingConfigDefault { max_gap_s: 0.03 },
+        },
+        legacy_pulses: SwitchDefault {
+            enabled: false,
+            content: HapticsLegacyPulsesConfigDefault { window_s: 0.05 },
+        },
+    };
     let socket_buffer = SocketBufferSizeDefault {
         Custom: 100000,
         variant: SocketBufferSizeDefaultVariant::Maximum,
@@ -1624,6 +2961,26 @@ pub fn session_settings_default() -> SettingsDefault {
         send_size_bytes: socket_buffer.clone(),
         recv_size_bytes: socket_buffer,
     };
+    let default_axis_processing = |inner_deadzone| AxisProcessingConfigDefault {
+        gui_collapsed: true,
+        inner_deadzone,
+        outer_deadzone: 0.02,
+        anti_deadzone: 0.0,
+        response_curve: AxisResponseCurveDefault {
+            Power: AxisResponseCurvePowerDefault { exponent: 1.5 },
+            Custom: AxisResponseCurveCustomDefault {
+                points: VectorDefault {
+                    gui_collapsed: false,
+                    element: AxisCurvePointDefault {
+                        input: 0.5,
+                        output: 0.5,
+                    },
+                    content: vec![],
+                },
+            },
+            variant: AxisResponseCurveDefaultVariant::Linear,
+        },
+    };
 
     SettingsDefault {
         video: VideoConfigDefault {
@@ -1658,6 +3015,45 @@ pub fn session_settings_default() -> SettingsDefault {
                     },
                 },
             },
+            passthrough_style: SwitchDefault {
+                enabled: false,
+                content: PassthroughStyleConfigDefault {
+                    opacity: 1.0,
+                    edge_color: SwitchDefault {
+                        enabled: false,
+                        content: PassthroughEdgeColorConfigDefault {
+                            red: 0.0,
+                            green: 1.0,
+                            blue: 1.0,
+                            alpha: 1.0,
+                        },
+                    },
+                    color_map: SwitchDefault {
+                        enabled: false,
+                        content: PassthroughColorMapDefault {
+                            variant:
+                                PassthroughColorMapDefaultVariant::BrightnessContrastSaturation,
+                            BrightnessContrastSaturation:
+                                PassthroughColorMapBrightnessContrastSaturationDefault {
+                                    brightness: 0.0,
+                                    contrast: 1.0,
+                                    saturation: 1.0,
+                                },
+                            ColorLut: PassthroughColorLutConfigDefault {
+                                gamma: 1.0,
+                                tint_red: 1.0,
+                                tint_green: 1.0,
+                                tint_blue: 1.0,
+                                posterize_levels: SwitchDefault {
+                                    enabled: false,
+                                    content: 4,
+                                },
+                                weight: 1.0,
+                            },
+                        },
+                    },
+                },
+            },
             clientside_post_processing: SwitchDefault {
                 enabled: false,
                 content: ClientsidePostProcessingConfigDefault {
@@ -1667,6 +3063,7 @@ pub fn session_settings_default() -> SettingsDefault {
                     sharpening: ClientsidePostProcessingSharpeningModeDefault {
                         variant: ClientsidePostProcessingSharpeningModeDefaultVariant::Quality,
                     },
+                    disable_sharpening_on_video: false,
                 },
             },
             upscaling: SwitchDefault {
@@ -1678,13 +3075,29 @@ pub fn session_settings_default() -> SettingsDefault {
                     upscale_factor: 1.5,
                 },
             },
+            aspect_ratio: StreamAspectRatioDefault {
+                variant: StreamAspectRatioDefaultVariant::Stretch,
+            },
+            client_swapchain_format: ClientSwapchainFormatDefault {
+                variant: ClientSwapchainFormatDefaultVariant::Auto,
+            },
             adapter_index: 0,
+            adapter_name: SwitchDefault {
+                enabled: false,
+                content: "NVIDIA".into(),
+            },
+            cross_adapter_encoding: false,
             transcoding_view_resolution: view_resolution.clone(),
             emulated_headset_view_resolution: view_resolution,
             preferred_fps: 72.,
             max_buffering_frames: 2.0,
             buffering_history_weight: 0.90,
+            max_queued_encoded_frames: OptionalDefault {
+                set: false,
+                content: 16,
+            },
             enforce_server_frame_pacing: true,
+            server_reprojection: false,
             bitrate: BitrateConfigDefault {
                 gui_collapsed: false,
                 mode: BitrateModeDefault {
@@ -1728,12 +3141,56 @@ pub fn session_settings_default() -> SettingsDefault {
                         framerate_reset_threshold_multiplier: 2.0,
                     },
                 },
+                thermal_throttling: SwitchDefault {
+                    enabled: true,
+                    content: BitrateThermalThrottlingConfigDefault {
+                        light_multiplier: 0.9,
+                        moderate_multiplier: 0.75,
+                        severe_multiplier: 0.5,
+                    },
+                },
+                total_bandwidth_budget_mbps: SwitchDefault {
+                    enabled: false,
+                    content: 100,
+                },
                 history_size: 256,
                 image_corruption_fix: false,
+                benchmark: BitrateBenchmarkConfigDefault {
+                    gui_collapsed: true,
+                    min_bitrate_mbps: 60,
+                    max_bitrate_mbps: 400,
+                    step_mbps: 20,
+                    step_duration_s: 5,
+                    latency_percentile: 95,
+                    target_latency_ms: 15,
+                    max_loss_percent: 1.0,
+                    apply_result: false,
+                },
             },
             preferred_codec: CodecTypeDefault {
                 variant: CodecTypeDefaultVariant::H264,
             },
+            encoder_backend: EncoderBackendDefault {
+                variant: EncoderBackendDefaultVariant::Auto,
+            },
+            encoder_effort: EncoderEffortDefault {
+                variant: EncoderEffortDefaultVariant::Custom,
+            },
+            recovery: VideoRecoveryModeDefault {
+                variant: VideoRecoveryModeDefaultVariant::Idr,
+                IntraRefresh: VideoRecoveryModeIntraRefreshDefault { period_frames: 60 },
+            },
+            keyframe_interval_ms: SwitchDefault {
+                enabled: false,
+                content: 5000,
+            },
+            idle_throttling: SwitchDefault {
+                enabled: false,
+                content: IdleThrottlingConfigDefault {
+                    trigger_seconds: 30.0,
+                    idle_fps: 5.0,
+                },
+            },
             encoder_config: EncoderConfigDefault {
                 gui_collapsed: true,
                 rate_control_mode: RateControlModeDefault {
@@ -1754,6 +3211,15 @@ pub fn session_settings_default() -> SettingsDefault {
                     set: false,
                     content: 1.0,
                 },
+                color_range: ColorRangeDefault {
+                    variant: ColorRangeDefaultVariant::Full,
+                },
+                transfer_function: TransferFunctionDefault {
+                    variant: TransferFunctionDefaultVariant::Gamma22,
+                },
+                chroma_subsampling: ChromaSubsamplingDefault {
+                    variant: ChromaSubsamplingDefaultVariant::Yuv420,
+                },
                 hdr: HDRConfigDefault {
                     gui_collapsed: true,
                     enable: OptionalDefault {
@@ -1796,6 +3262,7 @@ pub fn session_settings_default() -> SettingsDefault {
                     variant: EncoderQualityPresetDefaultVariant::Speed,
                 },
                 enable_vbaq: false,
+                async_depth: 1,
                 amf: AmfConfigDefault {
                     gui_collapsed: true,
                     enable_pre_analysis: false,
@@ -1808,6 +3275,7 @@ pub fn session_settings_default() -> SettingsDefault {
                     gui_collapsed: true,
                     force_software_encoding: false,
                     thread_count: 0,
+                    svt_av1_preset: 12,
                 },
             },
             mediacodec_extra_options: {
@@ -1841,12 +3309,31 @@ pub fn session_settings_default() -> SettingsDefault {
                 content: FoveatedEncodingConfigDefault {
                     gui_collapsed: true,
                     force_enable: false,
+                    mode: FoveatedEncodingModeDefault {
+                        variant: FoveatedEncodingModeDefaultVariant::TwoZone,
+                        Radial: FoveatedEncodingModeRadialDefault {
+                            center_strength: 1.0,
+                            edge_compression: 3.0,
+                        },
+                    },
                     center_size_x: 0.45,
                     center_size_y: 0.4,
                     center_shift_x: 0.4,
                     center_shift_y: 0.1,
                     edge_ratio_x: 4.,
                     edge_ratio_y: 5.,
+                    right_eye: SwitchDefault {
+                        enabled: false,
+                        content: FoveatedEncodingEyeConfigDefault {
+                            center_size_x: 0.45,
+                            center_size_y: 0.4,
+                            center_shift_x: 0.4,
+                            center_shift_y: 0.1,
+                            edge_ratio_x: 4.,
+                            edge_ratio_y: 5.,
+                        },
+                    },
+                    edge_preservation_strength: 0.,
                 },
             },
             clientside_foveation: SwitchDefault {
@@ -1869,6 +3356,31 @@ pub fn session_settings_default() -> SettingsDefault {
                 },
             },
             force_software_decoder: false,
+            client_frame_extrapolation: false,
+            gaze_bitrate_allocation: SwitchDefault {
+                enabled: false,
+                content: GazeBitrateAllocationConfigDefault {
+                    region_size: 0.25,
+                    qp_reduction: 6,
+                    gaze_timeout_ms: 200,
+                },
+            },
+            stream_depth: SwitchDefault {
+                enabled: false,
+                content: DepthStreamConfigDefault {
+                    resolution_divisor: 16,
+                },
+            },
+            space_warp: SwitchDefault {
+                enabled: false,
+                content: SpaceWarpConfigDefault {
+                    refresh_rates: VectorDefault {
+                        gui_collapsed: false,
+                        element: 90.0,
+                        content: vec![90.0, 120.0],
+                    },
+                },
+            },
             color_correction: SwitchDefault {
                 enabled: false,
                 content: ColorCorrectionConfigDefault {
@@ -1937,6 +3449,16 @@ pub fn session_settings_default() -> SettingsDefault {
                         VrchatEyeOsc: FaceTrackingSinkConfigVrchatEyeOscDefault { port: 9000 },
                         variant: FaceTrackingSinkConfigDefaultVariant::VrchatEyeOsc,
                     },
+                    eye_gaze: SwitchDefault {
+                        enabled: false,
+                        content: EyeGazeForwardingConfigDefault {
+                            smoothing: 0.3,
+                            blink_hold_threshold: SwitchDefault {
+                                enabled: true,
+                                content: 0.6,
+                            },
+                        },
+                    },
                 },
             },
             multimodal_tracking: SwitchDefault {
@@ -1980,11 +3502,52 @@ pub fn session_settings_default() -> SettingsDefault {
                     orientation_correction: true,
                 },
             },
+            external_trackers: SwitchDefault {
+                enabled: false,
+                content: ExternalTrackersConfigDefault {
+                    gui_collapsed: true,
+                    port: 39540,
+                    assignments: VectorDefault {
+                        gui_collapsed: false,
+                        element: ExternalTrackerAssignmentDefault {
+                            name: "Hips".into(),
+                            role: ExternalTrackerRoleDefault {
+                                variant: ExternalTrackerRoleDefaultVariant::Hips,
+                            },
+                        },
+                        content: vec![],
+                    },
+                    calibration_duration_s: 5,
+                },
+            },
             controllers: SwitchDefault {
                 enabled: true,
                 content: ControllersConfigDefault {
                     gui_collapsed: false,
                     tracked: true,
+                    enabled_devices: EnabledHandDevicesConfigDefault {
+                        left_controller: true,
+                        right_controller: true,
+                        left_hand: true,
+                        right_hand: true,
+                    },
+                    input_source_switch: SwitchDefault {
+                        enabled: false,
+                        content: InputSourceSwitchConfigDefault {
+                            switch_delay_ms: 300,
+                        },
+                    },
+                    analog_processing: SwitchDefault {
+                        enabled: false,
+                        content: AnalogInputProcessingModeDefault {
+                            Custom: AnalogAxesConfigDefault {
+                                thumbsticks: default_axis_processing(0.1),
+                                triggers: default_axis_processing(0.03),
+                                grips: default_axis_processing(0.03),
+                            },
+                            variant: AnalogInputProcessingModeDefaultVariant::Automatic,
+                        },
+                    },
                     hand_skeleton: SwitchDefault {
                         enabled: true,
                         content: HandSkeletonConfigDefault {
@@ -2048,6 +3611,27 @@ pub fn session_settings_default() -> SettingsDefault {
                         },
                         force_threshold: 0.8,
                     },
+                    button_macros: VectorDefault {
+                        gui_collapsed: true,
+                        element: ButtonMacroConfigDefault {
+                            enabled: true,
+                            display_name: "".into(),
+                            chord: VectorDefault {
+                                gui_collapsed: false,
+                                element: "/user/hand/left/input/menu/click".into(),
+                                content: vec![
+                                    "/user/hand/left/input/menu/click".into(),
+                                    "/user/hand/left/input/trigger/click".into(),
+                                ],
+                            },
+                            hold_duration_ms: 1000,
+                            action: ButtonMacroActionDefault {
+                                variant: ButtonMacroActionDefaultVariant::Recenter,
+                            },
+                            consume_buttons: true,
+                        },
+                        content: vec![],
+                    },
                     hand_tracking_interaction: SwitchDefault {
                         enabled: false,
                         content: HandTrackingInteractionConfigDefault {
@@ -2065,7 +3649,9 @@ pub fn session_settings_default() -> SettingsDefault {
                             deactivation_delay: 100,
                         },
                     },
+                    hand_interaction_profile: true,
                     steamvr_pipeline_frames: 2.1,
+                    max_prediction_ms: 100,
                     linear_velocity_cutoff: 0.05,
                     angular_velocity_cutoff: 10.0,
                     left_controller_position_offset: ArrayDefault {
@@ -2084,14 +3670,42 @@ pub fn session_settings_default() -> SettingsDefault {
                         gui_collapsed: true,
                         content: [0.0, -45.0, -90.0],
                     },
+                    profile_pose_offsets: VectorDefault {
+                        gui_collapsed: true,
+                        element: ControllerProfilePoseOffsetDefault {
+                            emulation_mode: ControllersEmulationModeDefault {
+                                Custom: ControllersEmulationModeCustomDefault {
+                                    serial_number: "ALVR Controller".into(),
+                                    button_set: VectorDefault {
+                                        gui_collapsed: false,
+                                        element: "/user/hand/left/input/a/click".into(),
+                                        content: vec![],
+                                    },
+                                },
+                                variant: ControllersEmulationModeDefaultVariant::ValveIndex,
+                            },
+                            position_offset: ArrayDefault {
+                                gui_collapsed: false,
+                                content: [0.0; 3],
+                            },
+                            rotation_offset: ArrayDefault {
+                                gui_collapsed: false,
+                                content: [0.0; 3],
+                            },
+                        },
+                        content: vec![],
+                    },
                     haptics: SwitchDefault {
                         enabled: true,
-                        content: HapticsConfigDefault {
-                            gui_collapsed: true,
-                            intensity_multiplier: 1.0,
-                            amplitude_curve: 1.0,
-                            min_duration_s: 0.01,
+                        content: default_haptics.clone(),
+                    },
+                    game_haptics: VectorDefault {
+                        gui_collapsed: true,
+                        element: HapticsGameOverrideDefault {
+                            executable_name: "".into(),
+                            haptics: default_haptics,
                         },
+                        content: vec![],
                     },
                 },
             },
@@ -2102,7 +3716,101 @@ pub fn session_settings_default() -> SettingsDefault {
             rotation_recentering_mode: RotationRecenteringModeDefault {
                 variant: RotationRecenteringModeDefaultVariant::Yaw,
             },
+            use_local_floor_space: false,
+            chaperone_sync: ChaperoneSyncModeDefault {
+                variant: ChaperoneSyncModeDefaultVariant::Disabled,
+            },
             max_prediction_ms: 100,
+            pose_history_size: 64,
+            view_override: SwitchDefault {
+                enabled: false,
+                content: ViewOverrideConfigDefault {
+                    ipd_mm: SwitchDefault {
+                        enabled: false,
+                        content: 63.0,
+                    },
+                    convergence_deg: 0.0,
+                },
+            },
+            marker_origin: SwitchDefault {
+                enabled: false,
+                content: MarkerOriginConfigDefault {
+                    marker_code: "ALVR origin".into(),
+                    mode: MarkerOriginModeDefault {
+                        variant: MarkerOriginModeDefaultVariant::Server,
+                    },
+                    follow: SwitchDefault {
+                        enabled: false,
+                        content: MarkerFollowConfigDefault {
+                            smoothing_s: 0.15,
+                            follow_tilt: false,
+                        },
+                    },
+                    remember_pose: false,
+                },
+            },
+            lobby_marker_selection: false,
+            peripheral_input: SwitchDefault {
+                enabled: false,
+                content: PeripheralInputConfigDefault {
+                    keyboard_mode: KeyboardInjectionModeDefault {
+                        variant: KeyboardInjectionModeDefaultVariant::Scancode,
+                    },
+                    mouse_sensitivity: 1.0,
+                },
+            },
+            clipboard_sync: SwitchDefault {
+                enabled: false,
+                content: ClipboardSyncConfigDefault {
+                    client_to_server: true,
+                    server_to_client: true,
+                    max_size_kb: 64,
+                },
+            },
+            in_headset_menu: SwitchDefault {
+                enabled: false,
+                content: InHeadsetMenuConfigDefault {
+                    open_chord: VectorDefault {
+                        gui_collapsed: true,
+                        element: "/user/hand/left/input/thumbstick/click".into(),
+                        content: vec![
+                            "/user/hand/left/input/thumbstick/click".into(),
+                            "/user/hand/right/input/thumbstick/click".into(),
+                        ],
+                    },
+                    bitrate_step_mbps: 10,
+                },
+            },
+            gestures: VectorDefault {
+                gui_collapsed: true,
+                element: GestureConfigDefault {
+                    enabled: true,
+                    display_name: "".into(),
+                    trigger: GestureTriggerDefault {
+                        variant: GestureTriggerDefaultVariant::DoubleTap,
+                        DoubleTap: GestureTriggerDoubleTapDefault {
+                            button: "/user/hand/left/input/squeeze/value".into(),
+                            max_interval_ms: 400,
+                        },
+                        HandPose: GestureTriggerHandPoseDefault {
+                            hand: GestureHandDefault {
+                                variant: GestureHandDefaultVariant::Left,
+                            },
+                            pose: HandPoseGestureDefault {
+                                variant: HandPoseGestureDefaultVariant::Pinch,
+                            },
+                            hold_duration_ms: 1000,
+                        },
+                    },
+                    action: GestureActionDefault {
+                        variant: GestureActionDefaultVariant::Streamer,
+                        Streamer: ButtonMacroActionDefault {
+                            variant: ButtonMacroActionDefaultVariant::Recenter,
+                        },
+                    },
+                },
+                content: vec![],
+            },
         },
         connection: ConnectionConfigDefault {
             stream_protocol: SocketProtocolDefault {
@@ -2145,6 +3853,49 @@ pub fn session_settings_default() -> SettingsDefault {
             server_buffer_config: socket_buffer_config.clone(),
             client_buffer_config: socket_buffer_config,
             max_queued_server_video_frames: 1024,
+            video_pacing: VideoPacingDefault {
+                Paced: VideoPacingPacedDefault {
+                    frame_interval_percent: 50.0,
+                },
+                variant: VideoPacingDefaultVariant::Burst,
+            },
+            tracking_jitter_buffer: SwitchDefault {
+                enabled: false,
+                content: 0.5,
+            },
+            standby_behavior: StandbyBehaviorDefault {
+                PauseEncoding: StandbyBehaviorPauseEncodingDefault {
+                    keepalive_frames: false,
+                },
+                DisconnectAfter: StandbyBehaviorDisconnectAfterDefault { minutes: 10 },
+                variant: StandbyBehaviorDefaultVariant::PauseEncoding,
+            },
+            keepalive: KeepaliveConfigDefault {
+                gui_collapsed: true,
+                interval_ms: 500,
+                timeout_ms: 2000,
+                stream_inactivity_timeout_ms: SwitchDefault {
+                    enabled: true,
+                    content: 5000,
+                },
+            },
+            client_reconnect: ClientReconnectConfigDefault {
+                gui_collapsed: true,
+                base_interval_ms: 1000,
+                max_interval_s: 30,
+                max_retries: SwitchDefault {
+                    enabled: false,
+                    content: 100,
+                },
+            },
+            video_packet_loss_simulation: SwitchDefault {
+                enabled: false,
+                content: PacketLossSimulationConfigDefault {
+                    loss_percent: 5.0,
+                    mean_burst_length: 1,
+                    seed: 0,
+                },
+            },
             avoid_video_glitching: false,
             minimum_idr_interval_ms: 100,
             enable_on_connect_script: false,
@@ -2153,6 +3904,9 @@ pub fn session_settings_default() -> SettingsDefault {
             packet_size: 1400,
             statistics_history_size: 256,
         },
+        privacy: PrivacyConfigDefault {
+            block_biometric_streams: false,
+        },
         extra: ExtraConfigDefault {
             logging: LoggingConfigDefault {
                 client_log_report_level: SwitchDefault {
@@ -2162,6 +3916,15 @@ pub fn session_settings_default() -> SettingsDefault {
                     },
                 },
                 log_to_disk: cfg!(debug_assertions),
+                log_rotation: SwitchDefault {
+                    enabled: true,
+                    content: LogRotationConfigDefault {
+                        max_file_size_mb: 20,
+                        max_files: 5,
+                    },
+                },
+                log_json_lines: false,
+                target_levels: "".into(),
                 log_button_presses: false,
                 log_tracking: false,
                 log_haptics: false,
@@ -2196,6 +3959,11 @@ pub fn session_settings_default() -> SettingsDefault {
             steamvr_launcher: SteamvrLauncherDefault {
                 open_close_steamvr_with_dashboard: false,
                 direct_launch: false,
+                launch_on_client_discovery: false,
+                autolaunch_app_id: SwitchDefault {
+                    enabled: false,
+                    content: 0,
+                },
             },
             capture: CaptureConfigDefault {
                 startup_video_recording: false,
@@ -2214,6 +3982,33 @@ pub fn session_settings_default() -> SettingsDefault {
                 linux_async_reprojection: false,
             },
             velocities_multiplier: 1.0,
+            photodiode_latency_test: SwitchDefault {
+                enabled: false,
+                content: PhotodiodeLatencyTestConfigDefault {
+                    trigger_button: "/user/hand/right/input/a/click".into(),
+                    flash_duration: 5,
+                },
+            },
+            metrics_exporter: SwitchDefault {
+                enabled: false,
+                content: MetricsExporterConfigDefault {
+                    mode: MetricsExporterModeDefault {
+                        Prometheus: MetricsExporterModePrometheusDefault {
+                            bind_address: "127.0.0.1:9464".into(),
+                        },
+                        Statsd: MetricsExporterModeStatsdDefault {
+                            server_address: "127.0.0.1:8125".into(),
+                            prefix: "alvr".into(),
+                        },
+                        variant: MetricsExporterModeDefaultVariant::Prometheus,
+                    },
+                    update_interval_ms: 1000,
+                },
+            },
+            client_safe_mode: false,
+            client_graphics_api: ClientGraphicsApiDefault {
+                variant: ClientGraphicsApiDefaultVariant::Auto,
+            },
             open_setup_wizard: alvr_common::is_stable() || alvr_common::is_nightly(),
             new_version_popup: SwitchDefault {
                 enabled: alvr_common::is_stable(),
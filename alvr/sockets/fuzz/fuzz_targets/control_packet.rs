@@ -0,0 +1,13 @@
+#![no_main]
+
+use alvr_packets::{ClientConnectionResult, ClientControlPacket};
+use libfuzzer_sys::fuzz_target;
+
+// Packets the server receives on the control socket, before and after the handshake
+fuzz_target!(|data: &[u8]| {
+    alvr_sockets::decode_packet::<ClientConnectionResult>(data).ok();
+
+    if let Ok((_, size)) = alvr_sockets::decode_packet::<ClientControlPacket>(data) {
+        assert!(size <= data.len());
+    }
+});
@@ -0,0 +1,17 @@
+#![no_main]
+
+use alvr_packets::DepthPacketHeader;
+use libfuzzer_sys::fuzz_target;
+
+// The sample count is computed from the resolution in the header, as the client does
+fuzz_target!(|data: &[u8]| {
+    if let Ok((header, size)) = alvr_sockets::decode_packet::<DepthPacketHeader>(data)
+        && let Some(count) = header
+            .view_resolution
+            .x
+            .checked_mul(2)
+            .and_then(|width| width.checked_mul(header.view_resolution.y))
+    {
+        alvr_common::decode_depth(&data[size..], count as usize);
+    }
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use alvr_packets::TrackingData;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok((_, size)) = alvr_sockets::decode_packet::<TrackingData>(data) {
+        assert!(size <= data.len());
+    }
+});
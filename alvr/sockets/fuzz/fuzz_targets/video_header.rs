@@ -0,0 +1,11 @@
+#![no_main]
+
+use alvr_packets::VideoPacketHeader;
+use libfuzzer_sys::fuzz_target;
+
+// The header is followed by the NAL, which is split off at the decoded size
+fuzz_target!(|data: &[u8]| {
+    if let Ok((_, size)) = alvr_sockets::decode_packet::<VideoPacketHeader>(data) {
+        let _nal = &data[size..];
+    }
+});
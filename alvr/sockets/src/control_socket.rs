@@ -1,5 +1,5 @@
-use crate::{CONTROL_PORT, LOCAL_IP};
-use alvr_common::{ConResult, HandleTryAgain, ToCon, anyhow::Result, con_bail};
+use crate::{CONTROL_PORT, LOCAL_IP, MAX_CONTROL_PACKET_SIZE};
+use alvr_common::{AnyhowToCon, ConResult, HandleTryAgain, ToCon, anyhow::Result, con_bail};
 use alvr_session::{DscpTos, SocketBufferConfig};
 use bincode::config;
 use serde::{Serialize, de::DeserializeOwned};
@@ -8,6 +8,10 @@ use std::{
     marker::PhantomData,
     mem,
     net::{IpAddr, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
     time::{Duration, Instant},
 };
 
@@ -121,8 +125,13 @@ fn framed_recv<R: DeserializeOwned>(
             }
         }
 
-        let size = FRAMED_PREFIX_LENGTH + u32::from_le_bytes(payload_size_bytes) as usize;
-        buffer.resize(size, 0);
+        let payload_size = u32::from_le_bytes(payload_size_bytes) as usize;
+        if payload_size > MAX_CONTROL_PACKET_SIZE {
+            // The rest of the stream cannot be parsed after skipping the prefix
+            con_bail!("Control packet too large: {payload_size}B");
+        }
+
+        buffer.resize(FRAMED_PREFIX_LENGTH + payload_size, 0);
 
         recv_cursor.insert(0)
     };
@@ -139,24 +148,40 @@ fn framed_recv<R: DeserializeOwned>(
         }
     }
 
-    let (packet, _) =
-        bincode::serde::decode_from_slice(&buffer[FRAMED_PREFIX_LENGTH..], config::standard())
-            .to_con()?;
-
+    // The packet is consumed even if it is malformed
     *recv_cursor = None;
 
+    let (packet, _) = crate::decode_packet(&buffer[FRAMED_PREFIX_LENGTH..]).to_con()?;
+
     Ok(packet)
 }
 
 pub struct ControlSocketSender<T> {
     inner: TcpStream,
     buffer: Vec<u8>,
+    sent_bytes: Arc<AtomicUsize>,
     _phantom: PhantomData<T>,
 }
 
 impl<S: Serialize> ControlSocketSender<S> {
     pub fn send(&mut self, packet: &S) -> Result<()> {
-        framed_send(&mut self.inner, &mut self.buffer, packet)
+        framed_send(&mut self.inner, &mut self.buffer, packet)?;
+        self.sent_bytes
+            .fetch_add(self.buffer.len(), Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    // Bytes sent on the control socket, including the framing. The counter wraps around
+    pub fn sent_bytes_counter(&self) -> Arc<AtomicUsize> {
+        Arc::clone(&self.sent_bytes)
+    }
+
+    // A send fails instead of blocking when the peer stops reading, for example when its
+    // connection is half-open. After a failed send the connection must be closed, since the
+    // packet may have been written partially.
+    pub fn set_write_timeout(&self, timeout: Duration) -> Result<()> {
+        Ok(self.inner.set_write_timeout(Some(timeout))?)
     }
 }
 
@@ -176,6 +201,11 @@ impl<R: DeserializeOwned> ControlSocketReceiver<R> {
             timeout,
         )
     }
+
+    // The connection can be resumed from the returned socket only if no packet was partially read
+    pub fn into_stream(self) -> Option<TcpStream> {
+        self.recv_cursor.is_none().then_some(self.inner)
+    }
 }
 
 pub fn get_server_listener(timeout: Duration) -> Result<TcpListener> {
@@ -209,6 +239,11 @@ impl ProtoControlSocket {
         Ok((Self { inner: socket }, peer_ip))
     }
 
+    // Resumes a connection kept open by another process
+    pub fn from_stream(stream: TcpStream) -> Self {
+        Self { inner: stream }
+    }
+
     pub fn send<S: Serialize>(&mut self, packet: &S) -> Result<()> {
         framed_send(&mut self.inner, &mut vec![], packet)
     }
@@ -227,6 +262,7 @@ impl ProtoControlSocket {
             ControlSocketSender {
                 inner: self.inner.try_clone()?,
                 buffer: vec![0; FRAMED_PREFIX_LENGTH],
+                sent_bytes: Arc::new(AtomicUsize::new(0)),
                 _phantom: PhantomData,
             },
             ControlSocketReceiver {
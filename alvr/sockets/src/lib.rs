@@ -1,22 +1,30 @@
 mod control_socket;
+mod liveness;
+mod packet_loss;
+mod socket_keeper;
 mod stream_socket;
 
 use alvr_common::{anyhow::Result, info};
 use alvr_session::{DscpTos, SocketBufferConfig, SocketBufferSize};
+use bincode::config;
+use serde::de::DeserializeOwned;
 use socket2::Socket;
-use std::{
-    net::{IpAddr, Ipv4Addr},
-    time::Duration,
-};
+use std::net::{IpAddr, Ipv4Addr};
 
 pub use control_socket::*;
+pub use liveness::*;
+pub use packet_loss::*;
+pub use socket_keeper::*;
 pub use stream_socket::*;
 
 pub const LOCAL_IP: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
 pub const CONTROL_PORT: u16 = 9943;
 pub const HANDSHAKE_PACKET_SIZE_BYTES: usize = 56; // this may change in future protocols
-pub const KEEPALIVE_INTERVAL: Duration = Duration::from_millis(500);
-pub const KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Upper bounds for the length prefixes sent by the peer, checked before allocating the buffers.
+// Packets over the limit are dropped, or close the connection if the stream cannot be resynced.
+pub const MAX_CONTROL_PACKET_SIZE: usize = 16 * 1024 * 1024;
+pub const MAX_STREAM_PACKET_SIZE: usize = 32 * 1024 * 1024;
 
 pub const MDNS_SERVICE_TYPE: &str = "_alvr._tcp.local.";
 pub const MDNS_PROTOCOL_KEY: &str = "protocol";
@@ -24,6 +32,15 @@ pub const MDNS_DEVICE_ID_KEY: &str = "device_id";
 
 pub const WIRED_CLIENT_HOSTNAME: &str = "client.wired";
 
+// Decodes a packet received from the peer. Returns the packet and the number of bytes read. The
+// lengths of the strings and vectors are checked against the limit before allocating them.
+pub fn decode_packet<T: DeserializeOwned>(bytes: &[u8]) -> Result<(T, usize)> {
+    Ok(bincode::serde::decode_from_slice(
+        bytes,
+        config::standard().with_limit::<MAX_STREAM_PACKET_SIZE>(),
+    )?)
+}
+
 fn set_socket_buffers(socket: &socket2::Socket, buffer_config: SocketBufferConfig) -> Result<()> {
     info!(
         "Initial socket buffer size: send: {}B, recv: {}B",
@@ -0,0 +1,35 @@
+use std::time::{Duration, Instant};
+
+// Declares the peer dead when nothing is received from it for the timeout. While paused, for
+// example when the headset is in standby, the peer may stop sending and is never declared dead.
+// The timeout restarts when resumed.
+pub struct PeerLiveness {
+    timeout: Duration,
+    deadline: Instant,
+    paused: bool,
+}
+
+impl PeerLiveness {
+    pub fn new(timeout: Duration, now: Instant) -> Self {
+        Self {
+            timeout,
+            deadline: now + timeout,
+            paused: false,
+        }
+    }
+
+    pub fn report_received(&mut self, now: Instant) {
+        self.deadline = now + self.timeout;
+    }
+
+    pub fn set_paused(&mut self, paused: bool, now: Instant) {
+        if self.paused && !paused {
+            self.report_received(now);
+        }
+        self.paused = paused;
+    }
+
+    pub fn is_dead(&self, now: Instant) -> bool {
+        !self.paused && now > self.deadline
+    }
+}
@@ -0,0 +1,46 @@
+use alvr_session::PacketLossSimulationConfig;
+
+// Drops packets in bursts of random length, about mean_burst_length packets on average. The
+// probability of starting a burst is chosen so that the fraction of dropped packets is
+// loss_percent. The dropped packets only depend on the seed.
+#[derive(Clone)]
+pub struct PacketLossSimulator {
+    start_burst_probability: f64,
+    continue_burst_probability: f64,
+    in_burst: bool,
+    rng_state: u64,
+}
+
+impl PacketLossSimulator {
+    pub fn new(config: &PacketLossSimulationConfig) -> Self {
+        let loss = (config.loss_percent as f64 / 100.0).clamp(0.0, 1.0);
+        let mean_burst_length = config.mean_burst_length.max(1) as f64;
+
+        Self {
+            start_burst_probability: loss / (mean_burst_length - loss * (mean_burst_length - 1.0)),
+            continue_burst_probability: 1.0 - 1.0 / mean_burst_length,
+            in_burst: false,
+            rng_state: config.seed,
+        }
+    }
+
+    // SplitMix64, uniform in [0, 1)
+    fn next_random(&mut self) -> f64 {
+        self.rng_state = self.rng_state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.rng_state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+
+        (z >> 11) as f64 / (1_u64 << 53) as f64
+    }
+
+    // Call once per packet
+    pub fn should_drop(&mut self) -> bool {
+        // When a burst ends, a new one can start right away
+        let continues_burst = self.in_burst && self.next_random() < self.continue_burst_probability;
+        self.in_burst = continues_burst || self.next_random() < self.start_burst_probability;
+
+        self.in_burst
+    }
+}
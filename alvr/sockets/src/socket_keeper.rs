@@ -0,0 +1,592 @@
+// The sockets of a connection die with the process that owns them. While SteamVR restarts, the
+// dashboard keeps a copy of the sockets of the driver, so the new driver can resume the connection
+// with the client instead of reconnecting. On Linux the sockets are sent with SCM_RIGHTS through a
+// Unix socket at the endpoint path, only processes of the same user are served. On Windows they are
+// duplicated for the receiving process, the endpoint file contains the port of the loopback listener
+// used for the exchange. Since any process can connect to it, only requests coming from vrserver
+// (where the driver runs) or from the keeper process itself are served, and the sockets are
+// duplicated for the process that owns the requesting connection, never for a process ID sent on
+// the wire.
+// The sockets are kept only while the dashboard is running. The driver checks that the keeper is
+// reachable before asking the client to wait, otherwise the client reconnects as usual.
+
+use alvr_common::{
+    anyhow::{Result, bail},
+    warn,
+};
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, UdpSocket},
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+// Longer than a SteamVR restart. The sockets are closed afterwards, the client reconnects as usual
+const KEEP_TIMEOUT: Duration = Duration::from_secs(60);
+const EXCHANGE_TIMEOUT: Duration = Duration::from_secs(2);
+// Avoids spinning while the accept errors persist, for example when out of descriptors
+const ACCEPT_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_TAG_SIZE: usize = 1024;
+
+const HAND_OVER_REQUEST: u8 = b'H';
+const TAKE_REQUEST: u8 = b'T';
+const PING_REQUEST: u8 = b'P';
+const ACK: u8 = b'A';
+
+const TCP_KIND: u8 = 0;
+const UDP_KIND: u8 = 1;
+
+pub enum KeptSocket {
+    Tcp(TcpStream),
+    Udp(UdpSocket),
+}
+
+pub struct KeptSockets {
+    // Identifies the peer of the connection
+    pub tag: String,
+    pub sockets: Vec<KeptSocket>,
+}
+
+fn write_tag(stream: &mut impl Write, tag: &str) -> Result<()> {
+    if tag.len() > MAX_TAG_SIZE {
+        bail!("Tag too long");
+    }
+    stream.write_all(&(tag.len() as u16).to_le_bytes())?;
+    stream.write_all(tag.as_bytes())?;
+
+    Ok(())
+}
+
+fn read_tag(stream: &mut impl Read) -> Result<String> {
+    let mut len_bytes = [0; 2];
+    stream.read_exact(&mut len_bytes)?;
+    let len = u16::from_le_bytes(len_bytes) as usize;
+    if len > MAX_TAG_SIZE {
+        bail!("Tag too long");
+    }
+    let mut tag = vec![0; len];
+    stream.read_exact(&mut tag)?;
+
+    Ok(String::from_utf8(tag)?)
+}
+
+fn read_byte(stream: &mut impl Read) -> Result<u8> {
+    let mut byte = [0];
+    stream.read_exact(&mut byte)?;
+
+    Ok(byte[0])
+}
+
+// The sender keeps its sockets open until the receiver confirms, otherwise the duplicated
+// descriptors could be closed while in flight
+fn send_and_wait_ack(stream: &mut platform::Stream, sockets: &[KeptSocket]) -> Result<()> {
+    platform::send_sockets(stream, sockets)?;
+    if read_byte(stream)? != ACK {
+        bail!("Sockets not acknowledged");
+    }
+
+    Ok(())
+}
+
+fn receive_and_ack(stream: &mut platform::Stream) -> Result<Vec<KeptSocket>> {
+    let sockets = platform::receive_sockets(stream)?;
+    stream.write_all(&[ACK])?;
+
+    Ok(sockets)
+}
+
+// Runs for the whole lifetime of the dashboard. Only the sockets of the last hand over are kept.
+// Returns only if the listener cannot accept anymore, the failures of single requests are logged
+pub fn socket_keeper_loop(endpoint: &Path) -> Result<()> {
+    let listener = platform::listen(endpoint)?;
+    let mut kept = None::<(KeptSockets, Instant)>;
+
+    loop {
+        let mut stream = match platform::accept(&listener) {
+            Ok(stream) => stream,
+            Err(e) if platform::is_listener_closed(&e) => return Err(e.into()),
+            Err(e) => {
+                warn!("Socket keeper: failed to accept a request: {e}");
+                thread::sleep(ACCEPT_RETRY_INTERVAL);
+
+                continue;
+            }
+        };
+
+        let res = (|| {
+            stream.set_read_timeout(Some(EXCHANGE_TIMEOUT))?;
+            platform::check_requester(&stream)?;
+
+            match read_byte(&mut stream)? {
+                HAND_OVER_REQUEST => {
+                    let tag = read_tag(&mut stream)?;
+                    let sockets = receive_and_ack(&mut stream)?;
+                    kept = Some((KeptSockets { tag, sockets }, Instant::now()));
+                }
+                TAKE_REQUEST => match kept.take() {
+                    Some((sockets, kept_time)) if kept_time.elapsed() < KEEP_TIMEOUT => {
+                        stream.write_all(&[1])?;
+                        write_tag(&mut stream, &sockets.tag)?;
+                        send_and_wait_ack(&mut stream, &sockets.sockets)?;
+                    }
+                    _ => stream.write_all(&[0])?,
+                },
+                PING_REQUEST => stream.write_all(&[ACK])?,
+                _ => bail!("Unknown request"),
+            }
+
+            Ok(())
+        })();
+        if let Err(e) = res {
+            warn!("Socket keeper: {e}");
+        }
+    }
+}
+
+// The driver must not ask the client to wait for the restart if nobody can keep the sockets
+pub fn is_socket_keeper_running(endpoint: &Path) -> bool {
+    (|| -> Result<bool> {
+        let mut stream = platform::connect(endpoint)?;
+        stream.write_all(&[PING_REQUEST])?;
+
+        Ok(read_byte(&mut stream)? == ACK)
+    })()
+    .unwrap_or(false)
+}
+
+// The sockets can be dropped after this, the connection stays open through the copies of the
+// keeper
+pub fn hand_over_sockets(endpoint: &Path, sockets: &KeptSockets) -> Result<()> {
+    let mut stream = platform::connect(endpoint)?;
+    stream.write_all(&[HAND_OVER_REQUEST])?;
+    write_tag(&mut stream, &sockets.tag)?;
+
+    send_and_wait_ack(&mut stream, &sockets.sockets)
+}
+
+// Returns None if no sockets were handed over recently
+pub fn take_kept_sockets(endpoint: &Path) -> Result<Option<KeptSockets>> {
+    let mut stream = platform::connect(endpoint)?;
+    stream.write_all(&[TAKE_REQUEST])?;
+    if read_byte(&mut stream)? == 0 {
+        return Ok(None);
+    }
+    let tag = read_tag(&mut stream)?;
+    let sockets = receive_and_ack(&mut stream)?;
+
+    Ok(Some(KeptSockets { tag, sockets }))
+}
+
+#[cfg(unix)]
+mod platform {
+    use super::*;
+    use std::{
+        fs,
+        os::{
+            fd::{AsRawFd, FromRawFd, RawFd},
+            unix::{
+                fs::PermissionsExt,
+                net::{UnixListener, UnixStream},
+            },
+        },
+        ptr,
+    };
+
+    pub type Stream = UnixStream;
+
+    const MAX_SOCKETS: usize = 8;
+
+    pub fn listen(endpoint: &Path) -> Result<UnixListener> {
+        // Left behind by a previous dashboard
+        fs::remove_file(endpoint).ok();
+
+        let listener = UnixListener::bind(endpoint)?;
+        fs::set_permissions(endpoint, fs::Permissions::from_mode(0o600))?;
+
+        Ok(listener)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn peer_uid(stream: &UnixStream) -> Result<libc::uid_t> {
+        let mut credentials = unsafe { std::mem::zeroed::<libc::ucred>() };
+        let mut size = size_of::<libc::ucred>() as libc::socklen_t;
+        if unsafe {
+            libc::getsockopt(
+                stream.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_PEERCRED,
+                ptr::from_mut(&mut credentials).cast(),
+                &mut size,
+            )
+        } < 0
+        {
+            return Err(std::io::Error::last_os_error().into());
+        }
+
+        Ok(credentials.uid)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn peer_uid(stream: &UnixStream) -> Result<libc::uid_t> {
+        let mut uid = 0;
+        let mut gid = 0;
+        if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+
+        Ok(uid)
+    }
+
+    // Other users are rejected even if the permissions of the endpoint were changed
+    pub fn check_requester(stream: &UnixStream) -> Result<()> {
+        let uid = peer_uid(stream)?;
+        if uid != unsafe { libc::getuid() } {
+            bail!("Request from another user (uid {uid}) rejected");
+        }
+
+        Ok(())
+    }
+
+    pub fn accept(listener: &UnixListener) -> io::Result<UnixStream> {
+        listener.accept().map(|(stream, _)| stream)
+    }
+
+    pub fn is_listener_closed(error: &io::Error) -> bool {
+        matches!(
+            error.raw_os_error(),
+            Some(libc::EBADF | libc::EINVAL | libc::ENOTSOCK)
+        )
+    }
+
+    pub fn connect(endpoint: &Path) -> Result<UnixStream> {
+        let stream = UnixStream::connect(endpoint)?;
+        stream.set_read_timeout(Some(EXCHANGE_TIMEOUT))?;
+
+        Ok(stream)
+    }
+
+    fn control_buffer_size() -> usize {
+        unsafe { libc::CMSG_SPACE((MAX_SOCKETS * size_of::<RawFd>()) as _) as usize }
+    }
+
+    // The kinds of the sockets are sent as data, the descriptors as ancillary data
+    pub fn send_sockets(stream: &mut UnixStream, sockets: &[KeptSocket]) -> Result<()> {
+        if sockets.is_empty() || sockets.len() > MAX_SOCKETS {
+            bail!("Invalid number of sockets: {}", sockets.len());
+        }
+
+        let mut kinds = vec![];
+        let mut fds = vec![];
+        for socket in sockets {
+            match socket {
+                KeptSocket::Tcp(socket) => {
+                    kinds.push(TCP_KIND);
+                    fds.push(socket.as_raw_fd());
+                }
+                KeptSocket::Udp(socket) => {
+                    kinds.push(UDP_KIND);
+                    fds.push(socket.as_raw_fd());
+                }
+            }
+        }
+
+        let mut control = vec![0_u8; control_buffer_size()];
+        let fds_size = fds.len() * size_of::<RawFd>();
+
+        let mut iov = libc::iovec {
+            iov_base: kinds.as_mut_ptr().cast(),
+            iov_len: kinds.len(),
+        };
+        let mut message = unsafe { std::mem::zeroed::<libc::msghdr>() };
+        message.msg_iov = &mut iov;
+        message.msg_iovlen = 1;
+        message.msg_control = control.as_mut_ptr().cast();
+        message.msg_controllen = unsafe { libc::CMSG_SPACE(fds_size as _) } as _;
+
+        unsafe {
+            let header = libc::CMSG_FIRSTHDR(&message);
+            (*header).cmsg_level = libc::SOL_SOCKET;
+            (*header).cmsg_type = libc::SCM_RIGHTS;
+            (*header).cmsg_len = libc::CMSG_LEN(fds_size as _) as _;
+            ptr::copy_nonoverlapping(fds.as_ptr(), libc::CMSG_DATA(header).cast(), fds.len());
+
+            if libc::sendmsg(stream.as_raw_fd(), &message, 0) < 0 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn receive_sockets(stream: &mut UnixStream) -> Result<Vec<KeptSocket>> {
+        let mut kinds = [0_u8; MAX_SOCKETS];
+        let mut control = vec![0_u8; control_buffer_size()];
+
+        let mut iov = libc::iovec {
+            iov_base: kinds.as_mut_ptr().cast(),
+            iov_len: kinds.len(),
+        };
+        let mut message = unsafe { std::mem::zeroed::<libc::msghdr>() };
+        message.msg_iov = &mut iov;
+        message.msg_iovlen = 1;
+        message.msg_control = control.as_mut_ptr().cast();
+        message.msg_controllen = control.len() as _;
+
+        let received_size =
+            unsafe { libc::recvmsg(stream.as_raw_fd(), &mut message, libc::MSG_CMSG_CLOEXEC) };
+        if received_size < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+
+        let mut fds = vec![];
+        unsafe {
+            let mut header = libc::CMSG_FIRSTHDR(&message);
+            while !header.is_null() {
+                if (*header).cmsg_level == libc::SOL_SOCKET
+                    && (*header).cmsg_type == libc::SCM_RIGHTS
+                {
+                    let data_size = (*header).cmsg_len as usize - libc::CMSG_LEN(0) as usize;
+                    let data = libc::CMSG_DATA(header).cast::<RawFd>();
+                    for i in 0..data_size / size_of::<RawFd>() {
+                        fds.push(ptr::read_unaligned(data.add(i)));
+                    }
+                }
+                header = libc::CMSG_NXTHDR(&message, header);
+            }
+        }
+
+        // The descriptors are owned from here, so they are closed on error
+        let mut sockets = vec![];
+        for (i, fd) in fds.into_iter().enumerate() {
+            match kinds[..received_size as usize].get(i) {
+                Some(&TCP_KIND) => {
+                    sockets.push(KeptSocket::Tcp(unsafe { TcpStream::from_raw_fd(fd) }))
+                }
+                Some(&UDP_KIND) => {
+                    sockets.push(KeptSocket::Udp(unsafe { UdpSocket::from_raw_fd(fd) }))
+                }
+                _ => unsafe {
+                    libc::close(fd);
+                },
+            }
+        }
+        if sockets.len() != received_size as usize || message.msg_flags & libc::MSG_CTRUNC != 0 {
+            bail!("Sockets lost in transfer");
+        }
+
+        Ok(sockets)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::*;
+    use std::{
+        fs, mem,
+        net::{Ipv4Addr, SocketAddr, TcpListener},
+        os::windows::io::{AsRawSocket, FromRawSocket, RawSocket},
+        path::PathBuf,
+        process, ptr, slice,
+    };
+    use windows::{
+        Win32::{
+            Foundation::CloseHandle,
+            NetworkManagement::IpHelper::{
+                GetExtendedTcpTable, MIB_TCPROW_OWNER_PID, MIB_TCPTABLE_OWNER_PID,
+                TCP_TABLE_OWNER_PID_CONNECTIONS,
+            },
+            Networking::WinSock::{
+                AF_INET, FROM_PROTOCOL_INFO, SOCKET, WSA_FLAG_OVERLAPPED, WSADuplicateSocketW,
+                WSAEINVAL, WSAENOTSOCK, WSAPROTOCOL_INFOW, WSASocketW,
+            },
+            System::Threading::{
+                OpenProcess, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+                QueryFullProcessImageNameW,
+            },
+        },
+        core::PWSTR,
+    };
+
+    pub type Stream = TcpStream;
+
+    const INFO_SIZE: usize = mem::size_of::<WSAPROTOCOL_INFOW>();
+    // The driver is loaded by this process
+    const DRIVER_HOST_FNAME: &str = "vrserver.exe";
+
+    pub fn listen(endpoint: &Path) -> Result<TcpListener> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        fs::write(endpoint, listener.local_addr()?.port().to_string())?;
+
+        Ok(listener)
+    }
+
+    pub fn accept(listener: &TcpListener) -> io::Result<TcpStream> {
+        listener.accept().map(|(stream, _)| stream)
+    }
+
+    pub fn is_listener_closed(error: &io::Error) -> bool {
+        matches!(
+            error.raw_os_error(),
+            Some(code) if code == WSAENOTSOCK.0 || code == WSAEINVAL.0
+        )
+    }
+
+    pub fn connect(endpoint: &Path) -> Result<TcpStream> {
+        let port = fs::read_to_string(endpoint)?.trim().parse::<u16>()?;
+        let stream =
+            TcpStream::connect_timeout(&(Ipv4Addr::LOCALHOST, port).into(), EXCHANGE_TIMEOUT)?;
+        stream.set_read_timeout(Some(EXCHANGE_TIMEOUT))?;
+
+        Ok(stream)
+    }
+
+    // Looks up the process that owns the other end of an accepted loopback connection
+    fn peer_pid(stream: &TcpStream) -> Result<u32> {
+        let (SocketAddr::V4(local_address), SocketAddr::V4(peer_address)) =
+            (stream.local_addr()?, stream.peer_addr()?)
+        else {
+            bail!("Not an IPv4 connection");
+        };
+
+        let mut size = 0;
+        unsafe {
+            GetExtendedTcpTable(
+                None,
+                &mut size,
+                false,
+                AF_INET.0 as u32,
+                TCP_TABLE_OWNER_PID_CONNECTIONS,
+                0,
+            )
+        };
+        // Room for connections opened in the meantime
+        size += 16 * mem::size_of::<MIB_TCPROW_OWNER_PID>() as u32;
+        let mut buffer = vec![0_u32; (size as usize).div_ceil(4)];
+        let res = unsafe {
+            GetExtendedTcpTable(
+                Some(buffer.as_mut_ptr().cast()),
+                &mut size,
+                false,
+                AF_INET.0 as u32,
+                TCP_TABLE_OWNER_PID_CONNECTIONS,
+                0,
+            )
+        };
+        if res != 0 {
+            bail!("Failed to get the TCP table: {res}");
+        }
+
+        let table = buffer.as_ptr().cast::<MIB_TCPTABLE_OWNER_PID>();
+        let rows = unsafe {
+            slice::from_raw_parts((*table).table.as_ptr(), (*table).dwNumEntries as usize)
+        };
+
+        // The ports are in network byte order
+        let Some(row) = rows.iter().find(|row| {
+            u16::from_be(row.dwLocalPort as u16) == peer_address.port()
+                && u16::from_be(row.dwRemotePort as u16) == local_address.port()
+                && u32::from_be(row.dwLocalAddr) == u32::from(*peer_address.ip())
+        }) else {
+            bail!("Requesting process not found");
+        };
+
+        Ok(row.dwOwningPid)
+    }
+
+    fn process_path(pid: u32) -> Result<PathBuf> {
+        let handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid)? };
+
+        let mut path = vec![0_u16; 1024];
+        let mut size = path.len() as u32;
+        let res = unsafe {
+            QueryFullProcessImageNameW(
+                handle,
+                PROCESS_NAME_WIN32,
+                PWSTR(path.as_mut_ptr()),
+                &mut size,
+            )
+        };
+        unsafe { CloseHandle(handle).ok() };
+        res?;
+
+        Ok(PathBuf::from(String::from_utf16(&path[..size as usize])?))
+    }
+
+    // The loopback listener is reachable by any local process
+    pub fn check_requester(stream: &TcpStream) -> Result<()> {
+        let pid = peer_pid(stream)?;
+        if pid == process::id() {
+            return Ok(());
+        }
+
+        let path = process_path(pid)?;
+        if !path
+            .file_name()
+            .is_some_and(|fname| fname.eq_ignore_ascii_case(DRIVER_HOST_FNAME))
+        {
+            bail!("Request from {} rejected", path.display());
+        }
+
+        Ok(())
+    }
+
+    // The sockets are duplicated for the process that owns the connection
+    pub fn send_sockets(stream: &mut TcpStream, sockets: &[KeptSocket]) -> Result<()> {
+        let pid = peer_pid(stream)?;
+
+        stream.write_all(&[sockets.len() as u8])?;
+        for socket in sockets {
+            let (kind, raw_socket) = match socket {
+                KeptSocket::Tcp(socket) => (TCP_KIND, socket.as_raw_socket()),
+                KeptSocket::Udp(socket) => (UDP_KIND, socket.as_raw_socket()),
+            };
+
+            let mut info = WSAPROTOCOL_INFOW::default();
+            if unsafe { WSADuplicateSocketW(SOCKET(raw_socket as _), pid, &mut info) } != 0 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+
+            stream.write_all(&[kind])?;
+            stream.write_all(unsafe {
+                slice::from_raw_parts(ptr::from_ref(&info).cast::<u8>(), INFO_SIZE)
+            })?;
+        }
+
+        Ok(())
+    }
+
+    pub fn receive_sockets(stream: &mut TcpStream) -> Result<Vec<KeptSocket>> {
+        let count = read_byte(stream)?;
+        let mut sockets = vec![];
+        for _ in 0..count {
+            let kind = read_byte(stream)?;
+            if kind != TCP_KIND && kind != UDP_KIND {
+                bail!("Unknown socket kind");
+            }
+            let mut info = WSAPROTOCOL_INFOW::default();
+            stream.read_exact(unsafe {
+                slice::from_raw_parts_mut(ptr::from_mut(&mut info).cast::<u8>(), INFO_SIZE)
+            })?;
+
+            let socket = unsafe {
+                WSASocketW(
+                    FROM_PROTOCOL_INFO,
+                    FROM_PROTOCOL_INFO,
+                    FROM_PROTOCOL_INFO,
+                    Some(&info),
+                    0,
+                    WSA_FLAG_OVERLAPPED,
+                )?
+            };
+            let raw_socket = socket.0 as RawSocket;
+            sockets.push(if kind == TCP_KIND {
+                KeptSocket::Tcp(unsafe { TcpStream::from_raw_socket(raw_socket) })
+            } else {
+                KeptSocket::Udp(unsafe { UdpSocket::from_raw_socket(raw_socket) })
+            });
+        }
+
+        Ok(sockets)
+    }
+}
@@ -19,28 +19,97 @@
 mod tcp;
 mod udp;
 
+use crate::PacketLossSimulator;
 use alvr_common::{
-    AnyhowToCon, ConResult, HandleTryAgain, ToCon, anyhow::Result, parking_lot::Mutex,
+    AnyhowToCon, ConResult, HandleTryAgain, ToCon,
+    anyhow::Result,
+    parking_lot::{Mutex, MutexGuard},
 };
-use alvr_session::{DscpTos, SocketBufferConfig, SocketProtocol};
+use alvr_session::{DscpTos, PacketLossSimulationConfig, SocketBufferConfig, SocketProtocol};
 use bincode::config;
 use serde::{Serialize, de::DeserializeOwned};
 use std::{
     cmp::Ordering,
     collections::HashMap,
+    io,
     marker::PhantomData,
     mem,
     net::{IpAddr, TcpListener, UdpSocket},
     ops::{Deref, DerefMut},
-    sync::{Arc, mpsc},
-    time::Duration,
+    sync::{
+        Arc,
+        atomic::{self, AtomicUsize},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
+// Shards due within this time are sent without sleeping, since the sleeps are not more precise
+const PACING_BURST_DURATION: Duration = Duration::from_millis(1);
+
 trait MultiplexedSocketWriter {
     // Note: consts are not trait-safe, we require a method
     fn payload_offset(&self) -> usize;
 
-    fn send(&mut self, stream_id: u16, packet_index: u32, buffer: &mut Vec<u8>) -> Result<()>;
+    // Number of shards sent for a buffer of this size, counting the prefix
+    fn shards_count(&self, buffer_len: usize) -> usize;
+
+    // The shards must be sent in order. Returns the number of bytes sent, including the protocol
+    // overhead
+    fn send_shard(
+        &mut self,
+        stream_id: u16,
+        packet_index: u32,
+        buffer: &mut Vec<u8>,
+        shard_index: usize,
+        shards_count: usize,
+    ) -> Result<usize>;
+
+    fn set_write_timeout(&self, timeout: Duration) -> Result<()>;
+}
+
+// Senders of a higher priority take the socket between the shards of a packet of a lower
+// priority, so small packets are not delayed by a whole video frame. TCP packets are sent as one
+// shard, so they are only reordered between packets.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum StreamPriority {
+    Low,
+    Normal,
+    High,
+}
+
+struct SharedWriter {
+    socket: Mutex<Box<dyn MultiplexedSocketWriter + Send>>,
+    // Number of senders waiting for the socket, by priority
+    waiting_senders: [AtomicUsize; 3],
+}
+
+impl SharedWriter {
+    fn new(socket: Box<dyn MultiplexedSocketWriter + Send>) -> Arc<Self> {
+        Arc::new(Self {
+            socket: Mutex::new(socket),
+            waiting_senders: Default::default(),
+        })
+    }
+
+    fn lock(
+        &self,
+        priority: StreamPriority,
+    ) -> MutexGuard<'_, Box<dyn MultiplexedSocketWriter + Send>> {
+        let waiting = &self.waiting_senders[priority as usize];
+        waiting.fetch_add(1, atomic::Ordering::Relaxed);
+        let socket = self.socket.lock();
+        waiting.fetch_sub(1, atomic::Ordering::Relaxed);
+
+        socket
+    }
+
+    fn has_waiting_above(&self, priority: StreamPriority) -> bool {
+        self.waiting_senders[priority as usize + 1..]
+            .iter()
+            .any(|count| count.load(atomic::Ordering::Relaxed) > 0)
+    }
 }
 
 struct ReconstructedPacket {
@@ -52,6 +121,8 @@ struct StreamRecvQueues {
     used_buffer_sender: mpsc::Sender<Vec<u8>>,
     used_buffer_receiver: mpsc::Receiver<Vec<u8>>,
     packet_queue: mpsc::Sender<ReconstructedPacket>,
+    // Payload size limit for this stream, not counting the prefix
+    max_packet_size: usize,
 }
 
 trait MultiplexedSocketReader {
@@ -82,23 +153,104 @@ impl<H> DerefMut for Buffer<H> {
     }
 }
 
-#[derive(Clone)]
 pub struct StreamSender<H> {
-    inner: Arc<Mutex<Box<dyn MultiplexedSocketWriter + Send>>>,
+    inner: Arc<SharedWriter>,
     stream_id: u16,
+    priority: StreamPriority,
     payload_offset: usize,
     next_packet_index: u32,
     used_buffers: Vec<Vec<u8>>,
+    sent_bytes: Arc<AtomicUsize>,
+    stream_sent_bytes: Arc<AtomicUsize>,
+    pacing_duration: Option<Duration>,
+    loss_simulator: Option<PacketLossSimulator>,
     _phantom: PhantomData<H>,
 }
 
+// Implemented manually because the header type is not required to be Clone
+impl<H> Clone for StreamSender<H> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+            stream_id: self.stream_id,
+            priority: self.priority,
+            payload_offset: self.payload_offset,
+            next_packet_index: self.next_packet_index,
+            used_buffers: self.used_buffers.clone(),
+            sent_bytes: Arc::clone(&self.sent_bytes),
+            stream_sent_bytes: Arc::clone(&self.stream_sent_bytes),
+            pacing_duration: self.pacing_duration,
+            loss_simulator: self.loss_simulator.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
 impl<H> StreamSender<H> {
+    // For testing. The dropped packets are skipped like packets lost by the network, so the
+    // receiver sees a gap in the packet indices
+    pub fn simulate_packet_loss(&mut self, config: &PacketLossSimulationConfig) {
+        self.loss_simulator = Some(PacketLossSimulator::new(config));
+    }
+
+    // Spreads the shards of each packet over the duration instead of sending them in one burst,
+    // which fills the buffers of the routers less. The socket is released while waiting.
+    pub fn set_pacing(&mut self, duration: Option<Duration>) {
+        self.pacing_duration = duration;
+    }
+
+    // Bytes sent on this stream, including the protocol overhead. The counter wraps around
+    pub fn sent_bytes_counter(&self) -> Arc<AtomicUsize> {
+        Arc::clone(&self.stream_sent_bytes)
+    }
+
+    fn send_shards(&self, buffer: &mut Vec<u8>) -> Result<usize> {
+        let start = Instant::now();
+        let mut socket = self.inner.lock(self.priority);
+        let shards_count = socket.shards_count(buffer.len());
+
+        let mut sent_bytes = 0;
+        for shard_index in 0..shards_count {
+            if let Some(duration) = self.pacing_duration {
+                let due = start + duration.mul_f64(shard_index as f64 / shards_count as f64);
+                let now = Instant::now();
+                if due > now + PACING_BURST_DURATION {
+                    drop(socket);
+                    thread::sleep(due - now);
+                    socket = self.inner.lock(self.priority);
+                }
+            }
+
+            if shard_index > 0 && self.inner.has_waiting_above(self.priority) {
+                MutexGuard::bump(&mut socket);
+            }
+
+            sent_bytes += socket.send_shard(
+                self.stream_id,
+                self.next_packet_index,
+                buffer,
+                shard_index,
+                shards_count,
+            )?;
+        }
+
+        Ok(sent_bytes)
+    }
+
     /// Shard and send a buffer with zero copies and zero allocations.
     /// The prefix of each shard is written over the previously sent shard to avoid reallocations.
     pub fn send(&mut self, mut buffer: Buffer<H>) -> Result<()> {
-        self.inner
-            .lock()
-            .send(self.stream_id, self.next_packet_index, &mut buffer.inner)?;
+        let simulate_loss = self
+            .loss_simulator
+            .as_mut()
+            .is_some_and(|simulator| simulator.should_drop());
+        if !simulate_loss {
+            let sent_bytes = self.send_shards(&mut buffer.inner)?;
+            self.sent_bytes
+                .fetch_add(sent_bytes, atomic::Ordering::Relaxed);
+            self.stream_sent_bytes
+                .fetch_add(sent_bytes, atomic::Ordering::Relaxed);
+        }
 
         self.used_buffers.push(buffer.inner);
 
@@ -142,6 +294,7 @@ impl<H: Serialize> StreamSender<H> {
 pub struct ReceiverData<H> {
     buffer: Vec<u8>,
     payload_offset: usize,
+    index: u32,
     used_buffer_queue: mpsc::Sender<Vec<u8>>,
     had_packet_loss: bool,
     _phantom: PhantomData<H>,
@@ -151,14 +304,18 @@ impl<H> ReceiverData<H> {
     pub fn had_packet_loss(&self) -> bool {
         self.had_packet_loss
     }
+
+    // Sequence number of the packet in its stream
+    pub fn index(&self) -> u32 {
+        self.index
+    }
 }
 
 impl<H: DeserializeOwned> ReceiverData<H> {
     pub fn get(&self) -> Result<(H, &[u8])> {
         let payload = &self.buffer[self.payload_offset..];
 
-        let (header, decoded_size) =
-            bincode::serde::decode_from_slice(payload, config::standard())?;
+        let (header, decoded_size) = crate::decode_packet(payload)?;
 
         Ok((header, &payload[decoded_size..]))
     }
@@ -181,6 +338,7 @@ pub struct StreamReceiver<H> {
     packet_receiver: mpsc::Receiver<ReconstructedPacket>,
     used_buffer_queue: mpsc::Sender<Vec<u8>>,
     last_packet_index: Option<u32>,
+    discard_old_packets: bool,
     _phantom: PhantomData<H>,
 }
 
@@ -205,6 +363,11 @@ fn wrapping_cmp(lhs: u32, rhs: u32) -> Ordering {
 }
 
 impl<H: DeserializeOwned + Serialize> StreamReceiver<H> {
+    // Old packets are discarded by default. Disable for streams that reorder the packets themselves
+    pub fn set_discard_old_packets(&mut self, discard: bool) {
+        self.discard_old_packets = discard;
+    }
+
     pub fn recv(&mut self, timeout: Duration) -> ConResult<ReceiverData<H>> {
         let packet = self
             .packet_receiver
@@ -222,9 +385,20 @@ impl<H: DeserializeOwned + Serialize> StreamReceiver<H> {
                     had_packet_loss = true
                 }
                 Ordering::Less => {
-                    // Old packet, discard
-                    self.used_buffer_queue.send(packet.buffer).to_con()?;
-                    return alvr_common::try_again();
+                    if self.discard_old_packets {
+                        // Old packet, discard
+                        self.used_buffer_queue.send(packet.buffer).to_con()?;
+                        return alvr_common::try_again();
+                    }
+
+                    return Ok(ReceiverData {
+                        buffer: packet.buffer,
+                        payload_offset: self.payload_offset,
+                        index: packet.index,
+                        used_buffer_queue: self.used_buffer_queue.clone(),
+                        had_packet_loss: false,
+                        _phantom: PhantomData,
+                    });
                 }
             }
         }
@@ -233,6 +407,7 @@ impl<H: DeserializeOwned + Serialize> StreamReceiver<H> {
         Ok(ReceiverData {
             buffer: packet.buffer,
             payload_offset: self.payload_offset,
+            index: packet.index,
             used_buffer_queue: self.used_buffer_queue.clone(),
             had_packet_loss,
             _phantom: PhantomData,
@@ -273,22 +448,19 @@ impl StreamSocketBuilder {
         max_packet_size: usize,
         timeout: Duration,
     ) -> ConResult<StreamSocket> {
-        let (send_socket, receive_socket) = match self {
+        match self {
             StreamSocketBuilder::Udp(socket) => {
                 udp::connect(&socket, server_ip, port, timeout).to_con()?;
-                udp::split_multiplexed(socket, max_packet_size).to_con()?
+                StreamSocket::from_udp_socket(socket, max_packet_size).to_con()
             }
             StreamSocketBuilder::Tcp(listener) => {
                 let socket = tcp::accept_from_server(&listener, Some(server_ip), timeout)?;
-                tcp::split_multiplexed(socket, timeout).to_con()?
-            }
-        };
+                let (send_socket, receive_socket) =
+                    tcp::split_multiplexed(socket, timeout).to_con()?;
 
-        Ok(StreamSocket {
-            send_socket: Arc::new(Mutex::new(send_socket)),
-            receive_socket,
-            queues: HashMap::new(),
-        })
+                Ok(StreamSocket::new(send_socket, receive_socket, None))
+            }
+        }
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -301,44 +473,115 @@ impl StreamSocketBuilder {
         buffer_config: SocketBufferConfig,
         max_packet_size: usize,
     ) -> ConResult<StreamSocket> {
-        let (send_socket, receive_socket) = match protocol {
+        match protocol {
             SocketProtocol::Udp => {
                 let socket = udp::bind(port, dscp, buffer_config).to_con()?;
                 udp::connect(&socket, client_ip, port, timeout).to_con()?;
-                udp::split_multiplexed(socket, max_packet_size).to_con()?
+                StreamSocket::from_udp_socket(socket, max_packet_size).to_con()
             }
             SocketProtocol::Tcp => {
                 let socket = tcp::connect_to_client(timeout, &[client_ip], port, buffer_config)?;
-                tcp::split_multiplexed(socket, timeout).to_con()?
+                let (send_socket, receive_socket) =
+                    tcp::split_multiplexed(socket, timeout).to_con()?;
+
+                Ok(StreamSocket::new(send_socket, receive_socket, None))
             }
-        };
+        }
+    }
+}
 
-        Ok(StreamSocket {
-            send_socket: Arc::new(Mutex::new(send_socket)),
-            receive_socket,
-            queues: HashMap::new(),
-        })
+// Datagrams received before a connection is resumed must be discarded, their packet indices would
+// be mistaken for older packets of the new session
+pub fn discard_received_datagrams(socket: &UdpSocket) -> Result<()> {
+    let mut buffer = vec![0; u16::MAX as usize];
+
+    socket.set_nonblocking(true)?;
+    loop {
+        match socket.recv(&mut buffer) {
+            Ok(_) => (),
+            // On Windows, an unreachable peer is reported on the next receive
+            Err(e) if e.kind() == io::ErrorKind::ConnectionReset => (),
+            Err(_) => break,
+        }
     }
+    socket.set_nonblocking(false)?;
+
+    Ok(())
 }
 
 pub struct StreamSocket {
-    send_socket: Arc<Mutex<Box<dyn MultiplexedSocketWriter + Send>>>,
+    send_socket: Arc<SharedWriter>,
     receive_socket: Box<dyn MultiplexedSocketReader + Send>,
     queues: HashMap<u16, StreamRecvQueues>,
+    sent_bytes: Arc<AtomicUsize>,
+    udp_socket: Option<UdpSocket>,
 }
 
 impl StreamSocket {
+    fn new(
+        send_socket: Box<dyn MultiplexedSocketWriter + Send>,
+        receive_socket: Box<dyn MultiplexedSocketReader + Send>,
+        udp_socket: Option<UdpSocket>,
+    ) -> Self {
+        Self {
+            send_socket: SharedWriter::new(send_socket),
+            receive_socket,
+            queues: HashMap::new(),
+            sent_bytes: Arc::new(AtomicUsize::new(0)),
+            udp_socket,
+        }
+    }
+
+    // Resumes the stream on a socket already connected to the peer. The packet indices restart from
+    // zero, see discard_received_datagrams()
+    pub fn from_udp_socket(socket: UdpSocket, max_packet_size: usize) -> Result<Self> {
+        let udp_socket = socket.try_clone()?;
+        let (send_socket, receive_socket) = udp::split_multiplexed(socket, max_packet_size)?;
+
+        Ok(Self::new(send_socket, receive_socket, Some(udp_socket)))
+    }
+
+    // Another handle to the UDP socket, to keep the connection open in another process. A TCP
+    // stream cannot be resumed, the receiver could stop in the middle of a shard
+    pub fn try_clone_udp_socket(&self) -> Option<Result<UdpSocket>> {
+        self.udp_socket
+            .as_ref()
+            .map(|socket| Ok(socket.try_clone()?))
+    }
     pub fn request_stream<T>(&self, stream_id: u16) -> StreamSender<T> {
+        self.request_stream_with_priority(stream_id, StreamPriority::Normal)
+    }
+
+    pub fn request_stream_with_priority<T>(
+        &self,
+        stream_id: u16,
+        priority: StreamPriority,
+    ) -> StreamSender<T> {
         StreamSender {
             inner: Arc::clone(&self.send_socket),
             stream_id,
-            payload_offset: self.send_socket.lock().payload_offset(),
+            priority,
+            payload_offset: self.send_socket.socket.lock().payload_offset(),
             next_packet_index: 0,
             used_buffers: vec![],
+            sent_bytes: Arc::clone(&self.sent_bytes),
+            stream_sent_bytes: Arc::new(AtomicUsize::new(0)),
+            pacing_duration: None,
+            loss_simulator: None,
             _phantom: PhantomData,
         }
     }
 
+    // Sends fail instead of blocking when the peer stops reading. This applies to all streams
+    pub fn set_write_timeout(&self, timeout: Duration) -> Result<()> {
+        self.send_socket.socket.lock().set_write_timeout(timeout)
+    }
+
+    // Total bytes sent on all streams, including the protocol overhead. The counter wraps around
+    pub fn sent_bytes_counter(&self) -> Arc<AtomicUsize> {
+        Arc::clone(&self.sent_bytes)
+    }
+
     // max_concurrent_buffers: number of buffers allocated by this call which will be reused to
     // receive packets for this stream ID. If packets are not read fast enough, the shards received
     // for this particular stream will be discarded
@@ -346,6 +589,21 @@ impl StreamSocket {
         &mut self,
         stream_id: u16,
         max_concurrent_buffers: usize,
+    ) -> StreamReceiver<T> {
+        self.subscribe_to_stream_with_max_size(
+            stream_id,
+            max_concurrent_buffers,
+            crate::MAX_STREAM_PACKET_SIZE,
+        )
+    }
+
+    // Packets larger than max_packet_size bytes (header and payload) are dropped before
+    // allocating their buffer. Use a small limit for streams of fixed size packets.
+    pub fn subscribe_to_stream_with_max_size<T>(
+        &mut self,
+        stream_id: u16,
+        max_concurrent_buffers: usize,
+        max_packet_size: usize,
     ) -> StreamReceiver<T> {
         let (packet_sender, packet_receiver) = mpsc::channel();
         let (used_buffer_sender, used_buffer_receiver) = mpsc::channel();
@@ -360,6 +618,7 @@ impl StreamSocket {
                 used_buffer_sender: used_buffer_sender.clone(),
                 used_buffer_receiver,
                 packet_queue: packet_sender,
+                max_packet_size,
             },
         );
 
@@ -368,6 +627,7 @@ impl StreamSocket {
             packet_receiver,
             used_buffer_queue: used_buffer_sender,
             last_packet_index: None,
+            discard_old_packets: true,
             _phantom: PhantomData,
         }
     }
@@ -1,7 +1,7 @@
 use super::{
     MultiplexedSocketReader, MultiplexedSocketWriter, ReconstructedPacket, StreamRecvQueues,
 };
-use crate::LOCAL_IP;
+use crate::{LOCAL_IP, MAX_STREAM_PACKET_SIZE};
 use alvr_common::{ConResult, HandleTryAgain, ToCon, anyhow::Result, con_bail};
 use alvr_session::{DscpTos, SocketBufferConfig};
 use socket2::Socket;
@@ -95,8 +95,20 @@ impl MultiplexedSocketWriter for MultiplexedTcpWriter {
         PACKET_PREFIX_SIZE
     }
 
+    // The receiver expects the packets whole
+    fn shards_count(&self, _: usize) -> usize {
+        1
+    }
+
     // `buffer` contains the payload offset by `payload_offset()`
-    fn send(&mut self, stream_id: u16, packet_index: u32, buffer: &mut Vec<u8>) -> Result<()> {
+    fn send_shard(
+        &mut self,
+        stream_id: u16,
+        packet_index: u32,
+        buffer: &mut Vec<u8>,
+        _: usize,
+        _: usize,
+    ) -> Result<usize> {
         let payload_size = buffer.len() - PACKET_PREFIX_SIZE;
 
         buffer[0..2].copy_from_slice(&stream_id.to_le_bytes());
@@ -105,7 +117,11 @@ impl MultiplexedSocketWriter for MultiplexedTcpWriter {
 
         self.inner.write_all(buffer)?;
 
-        Ok(())
+        Ok(buffer.len())
+    }
+
+    fn set_write_timeout(&self, timeout: Duration) -> Result<()> {
+        Ok(self.inner.set_write_timeout(Some(timeout))?)
     }
 }
 
@@ -150,6 +166,15 @@ impl MultiplexedSocketReader for MultiplexedTcpReader {
             let packet_index = u32::from_le_bytes(prefix_bytes[2..6].try_into().unwrap());
             let payload_size = u32::from_le_bytes(prefix_bytes[6..10].try_into().unwrap()) as usize;
 
+            let max_packet_size = stream_queues
+                .get(&stream_id)
+                .map_or(MAX_STREAM_PACKET_SIZE, |queue| queue.max_packet_size);
+            if payload_size > max_packet_size {
+                // Skipping the packet would require reading it anyway, the connection is closed
+                // instead
+                con_bail!("Packet too large for stream {stream_id}: {payload_size}B");
+            }
+
             let mut buffer = match stream_queues.get(&stream_id) {
                 Some(queue) => queue
                     .used_buffer_receiver
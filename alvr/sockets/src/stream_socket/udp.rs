@@ -20,6 +20,9 @@ pub const SHARD_PREFIX_SIZE: usize = mem::size_of::<u16>() // stream ID
     + mem::size_of::<u32>() // shards count
     + mem::size_of::<u32>(); // shards index
 
+// IPv4 and UDP headers, counted in the sent bytes
+const IP_UDP_HEADERS_SIZE: usize = 20 + 8;
+
 fn socket_peek(socket: &mut Socket, buffer: &mut [u8]) -> ConResult<usize> {
     #[cfg(windows)]
     const FLAGS: c_int = 0x02 | 0x8000; // MSG_PEEK | MSG_PARTIAL
@@ -67,29 +70,41 @@ impl MultiplexedSocketWriter for MultiplexedUdpWriter {
         SHARD_PREFIX_SIZE
     }
 
-    fn send(&mut self, stream_id: u16, packet_index: u32, buffer: &mut Vec<u8>) -> Result<()> {
+    fn shards_count(&self, buffer_len: usize) -> usize {
+        // rounding up:
+        (buffer_len - SHARD_PREFIX_SIZE).div_ceil(self.max_packet_size - SHARD_PREFIX_SIZE)
+    }
+
+    fn send_shard(
+        &mut self,
+        stream_id: u16,
+        packet_index: u32,
+        buffer: &mut Vec<u8>,
+        shard_idx: usize,
+        shards_count: usize,
+    ) -> Result<usize> {
         let max_shard_size = self.max_packet_size - SHARD_PREFIX_SIZE;
         let payload_size = buffer.len() - SHARD_PREFIX_SIZE;
-        // rounding up:
-        let shards_count = payload_size.div_ceil(max_shard_size);
 
-        for shard_idx in 0..shards_count {
-            // this overlaps with the previous shard, this is intended behavior and allows to
-            // reduce allocations
-            let shard_start_position = shard_idx * max_shard_size;
-            let shard_size = usize::min(max_shard_size, payload_size - shard_start_position);
+        // this overlaps with the previous shard, this is intended behavior and allows to
+        // reduce allocations
+        let shard_start_position = shard_idx * max_shard_size;
+        let shard_size = usize::min(max_shard_size, payload_size - shard_start_position);
 
-            let shard_view = &mut buffer[shard_start_position..][..SHARD_PREFIX_SIZE + shard_size];
+        let shard_view = &mut buffer[shard_start_position..][..SHARD_PREFIX_SIZE + shard_size];
 
-            shard_view[0..2].copy_from_slice(&stream_id.to_le_bytes());
-            shard_view[2..6].copy_from_slice(&packet_index.to_le_bytes());
-            shard_view[6..10].copy_from_slice(&(shards_count as u32).to_le_bytes());
-            shard_view[10..14].copy_from_slice(&(shard_idx as u32).to_le_bytes());
+        shard_view[0..2].copy_from_slice(&stream_id.to_le_bytes());
+        shard_view[2..6].copy_from_slice(&packet_index.to_le_bytes());
+        shard_view[6..10].copy_from_slice(&(shards_count as u32).to_le_bytes());
+        shard_view[10..14].copy_from_slice(&(shard_idx as u32).to_le_bytes());
 
-            self.inner.send(shard_view)?;
-        }
+        self.inner.send(shard_view)?;
 
-        Ok(())
+        Ok(shard_size + SHARD_PREFIX_SIZE + IP_UDP_HEADERS_SIZE)
+    }
+
+    fn set_write_timeout(&self, timeout: Duration) -> Result<()> {
+        Ok(self.inner.set_write_timeout(Some(timeout))?)
     }
 }
 
@@ -147,6 +162,11 @@ impl MultiplexedSocketReader for MultiplexedUdpReader {
         let Some(queues) = stream_queues.get(&stream_id) else {
             return discard_and_try_again(&self.inner);
         };
+
+        // The shards count bounds the reserved capacity. The packet is dropped before allocating
+        if maybe_shards_count > queues.max_packet_size.div_ceil(max_shard_data_size) {
+            return discard_and_try_again(&self.inner);
+        }
         let in_progress_packets = self.in_progress_packets.entry(stream_id).or_default();
 
         let in_progress_packet = if let Some(packet) = in_progress_packets.get_mut(&packet_index) {
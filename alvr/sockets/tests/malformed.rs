@@ -0,0 +1,181 @@
+// Crafted frames sent by a misbehaving peer. The receiving side must return errors or drop the
+// packets, without panicking or allocating the sizes claimed by the length prefixes. The peer is a
+// raw TCP socket, the receiving side is the one of the server.
+
+use alvr_common::{ConResult, ConnectionError};
+use alvr_packets::{ClientConnectionResult, HAPTICS, Haptics, TRACKING};
+use alvr_session::{SocketBufferConfig, SocketProtocol};
+use alvr_sockets::{ProtoControlSocket, StreamSocket, StreamSocketBuilder};
+use bincode::config;
+use std::{
+    io::Write,
+    net::{IpAddr, Ipv4Addr, TcpListener, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+
+const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+const TIMEOUT: Duration = Duration::from_secs(5);
+const RECV_TIMEOUT: Duration = Duration::from_millis(100);
+const PACKET_SIZE: usize = 1400;
+
+// Returns the first result that is not ConnectionError::TryAgain
+fn retry<T>(mut f: impl FnMut() -> ConResult<T>) -> ConResult<T> {
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        match f() {
+            Err(ConnectionError::TryAgain(e)) => {
+                assert!(Instant::now() < deadline, "Timed out: {e}")
+            }
+            res => return res,
+        }
+    }
+}
+
+fn assert_closed<T>(res: ConResult<T>) {
+    match res {
+        Ok(_) => panic!("The malformed packet was accepted"),
+        Err(ConnectionError::TryAgain(e)) => panic!("Unexpected TryAgain: {e}"),
+        Err(ConnectionError::Other(_)) => (),
+    }
+}
+
+// Connects a server control socket to a raw socket
+fn control_socket_pair() -> (ProtoControlSocket, TcpStream) {
+    let listener = TcpListener::bind((LOCALHOST, 0)).unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let peer_thread = thread::spawn(move || listener.accept().unwrap().0);
+    let socket = ProtoControlSocket::from_stream(TcpStream::connect((LOCALHOST, port)).unwrap());
+
+    (socket, peer_thread.join().unwrap())
+}
+
+// Connects a server stream socket to a raw socket
+fn stream_socket_pair() -> (StreamSocket, TcpStream) {
+    let listener = TcpListener::bind((LOCALHOST, 0)).unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let peer_thread = thread::spawn(move || listener.accept().unwrap().0);
+    let socket = StreamSocketBuilder::connect_to_client(
+        TIMEOUT,
+        LOCALHOST,
+        port,
+        SocketProtocol::Tcp,
+        None,
+        SocketBufferConfig::default(),
+        PACKET_SIZE,
+    )
+    .unwrap_or_else(|e| panic!("{e}"));
+
+    (socket, peer_thread.join().unwrap())
+}
+
+fn control_frame(payload: &[u8]) -> Vec<u8> {
+    let mut frame = (payload.len() as u32).to_le_bytes().to_vec();
+    frame.extend_from_slice(payload);
+
+    frame
+}
+
+fn stream_frame(stream_id: u16, packet_index: u32, payload_size: u32, payload: &[u8]) -> Vec<u8> {
+    let mut frame = stream_id.to_le_bytes().to_vec();
+    frame.extend_from_slice(&packet_index.to_le_bytes());
+    frame.extend_from_slice(&payload_size.to_le_bytes());
+    frame.extend_from_slice(payload);
+
+    frame
+}
+
+fn encoded_haptics(device_id: u64) -> Vec<u8> {
+    let haptics = Haptics {
+        device_id,
+        duration: Duration::from_millis(10),
+        frequency: 160.0,
+        amplitude: 0.5,
+    };
+
+    bincode::serde::encode_to_vec(&haptics, config::standard()).unwrap()
+}
+
+#[test]
+fn test_oversized_control_prefix() {
+    let (mut socket, mut peer) = control_socket_pair();
+
+    // 4 GiB claimed by the prefix, followed by nothing
+    peer.write_all(&u32::MAX.to_le_bytes()).unwrap();
+    assert_closed(retry(|| {
+        socket.recv::<ClientConnectionResult>(RECV_TIMEOUT)
+    }));
+}
+
+#[test]
+fn test_malformed_control_packet() {
+    let (mut socket, mut peer) = control_socket_pair();
+
+    // Out of range variant of ClientConnectionResult
+    peer.write_all(&control_frame(&[200])).unwrap();
+    assert_closed(retry(|| {
+        socket.recv::<ClientConnectionResult>(RECV_TIMEOUT)
+    }));
+}
+
+#[test]
+fn test_oversized_string_length() {
+    // ConnectionAccepted with protocol ID 0 and a platform string of 1 TiB, encoded as a u64
+    // varint. The length must be rejected before allocating the string.
+    let mut payload = vec![0, 0, 253];
+    payload.extend_from_slice(&(1_u64 << 40).to_le_bytes());
+    payload.extend_from_slice(b"loopback");
+
+    assert!(alvr_sockets::decode_packet::<ClientConnectionResult>(&payload).is_err());
+    assert!(alvr_sockets::decode_packet::<ClientConnectionResult>(&[]).is_err());
+}
+
+#[test]
+fn test_oversized_stream_packet() {
+    let (mut socket, mut peer) = stream_socket_pair();
+    socket.subscribe_to_stream_with_max_size::<Haptics>(HAPTICS, 10, 1024);
+
+    // Over the limit of the stream by one byte
+    peer.write_all(&stream_frame(HAPTICS, 0, 1025, &[0; 1025]))
+        .unwrap();
+    assert_closed(retry(|| socket.recv()));
+
+    // Unknown streams are limited too
+    let (mut socket, mut peer) = stream_socket_pair();
+    peer.write_all(&stream_frame(TRACKING, 0, u32::MAX, &[]))
+        .unwrap();
+    assert_closed(retry(|| socket.recv()));
+}
+
+#[test]
+fn test_malformed_stream_packet_dropped() {
+    let (mut socket, mut peer) = stream_socket_pair();
+    let mut receiver = socket.subscribe_to_stream_with_max_size::<Haptics>(HAPTICS, 10, 1024);
+
+    let mut frames = vec![];
+    // Truncated header
+    let haptics = encoded_haptics(1);
+    frames.extend(stream_frame(HAPTICS, 0, 3, &haptics[..3]));
+    // Packet of a stream that is not subscribed
+    frames.extend(stream_frame(TRACKING, 0, 4, &[1, 2, 3, 4]));
+    // Valid packet, which is received after the malformed ones
+    let haptics = encoded_haptics(2);
+    frames.extend(stream_frame(HAPTICS, 1, haptics.len() as u32, &haptics));
+    peer.write_all(&frames).unwrap();
+
+    for _ in 0..3 {
+        retry(|| socket.recv()).unwrap_or_else(|e| panic!("{e}"));
+    }
+
+    let data = retry(|| receiver.recv(RECV_TIMEOUT)).unwrap_or_else(|e| panic!("{e}"));
+    assert!(data.get_header().is_err());
+    drop(data);
+
+    let data = retry(|| receiver.recv(RECV_TIMEOUT)).unwrap_or_else(|e| panic!("{e}"));
+    let haptics = data.get_header().unwrap();
+    assert_eq!(haptics.device_id, 2);
+    assert_eq!(haptics.amplitude, 0.5);
+    assert!(!data.had_packet_loss());
+}
@@ -0,0 +1,178 @@
+// The sockets handed over to the keeper stay connected while the original handles are closed, like
+// the sockets of the driver during a SteamVR restart
+
+use alvr_packets::{ClientControlPacket, ServerControlPacket};
+use alvr_sockets::{KeptSocket, KeptSockets, ProtoControlSocket};
+use std::{
+    env,
+    net::{Ipv4Addr, TcpListener, TcpStream, UdpSocket},
+    path::PathBuf,
+    process, thread,
+    time::{Duration, Instant},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn start_keeper(name: &str) -> PathBuf {
+    let endpoint = env::temp_dir().join(format!("alvr_socket_keeper_{name}_{}", process::id()));
+
+    thread::spawn({
+        let endpoint = endpoint.clone();
+        move || alvr_sockets::socket_keeper_loop(&endpoint).unwrap()
+    });
+
+    // Wait for the listener
+    let deadline = Instant::now() + TIMEOUT;
+    while !alvr_sockets::is_socket_keeper_running(&endpoint) {
+        assert!(Instant::now() < deadline, "Keeper not started");
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    endpoint
+}
+
+#[test]
+fn connection_survives_hand_over() {
+    let endpoint = start_keeper("hand_over");
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let driver_control = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (client_control, _) = listener.accept().unwrap();
+
+    let driver_udp = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let client_udp = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    driver_udp
+        .connect(client_udp.local_addr().unwrap())
+        .unwrap();
+    client_udp
+        .connect(driver_udp.local_addr().unwrap())
+        .unwrap();
+    client_udp.set_read_timeout(Some(TIMEOUT)).unwrap();
+    let driver_udp_port = driver_udp.local_addr().unwrap().port();
+
+    alvr_sockets::hand_over_sockets(
+        &endpoint,
+        &KeptSockets {
+            tag: "client.test".into(),
+            sockets: vec![KeptSocket::Tcp(driver_control), KeptSocket::Udp(driver_udp)],
+        },
+    )
+    .unwrap();
+    // The original handles are closed, like when the driver process exits
+
+    // The client sends while no process owns the connection except the keeper
+    let mut client_control = ProtoControlSocket::from_stream(client_control);
+    client_control
+        .send(&ClientControlPacket::RestartAck)
+        .unwrap();
+
+    let kept = alvr_sockets::take_kept_sockets(&endpoint).unwrap().unwrap();
+    assert_eq!(kept.tag, "client.test");
+    let mut sockets = kept.sockets.into_iter();
+    let Some(KeptSocket::Tcp(driver_control)) = sockets.next() else {
+        panic!("Expected the control socket first");
+    };
+    let Some(KeptSocket::Udp(driver_udp)) = sockets.next() else {
+        panic!("Expected the stream socket");
+    };
+    assert_eq!(driver_udp.local_addr().unwrap().port(), driver_udp_port);
+
+    let mut driver_control = ProtoControlSocket::from_stream(driver_control);
+    assert!(matches!(
+        driver_control.recv(TIMEOUT),
+        Ok(ClientControlPacket::RestartAck)
+    ));
+    driver_control
+        .send(&ServerControlPacket::DriverResumed)
+        .unwrap();
+    assert!(matches!(
+        client_control.recv(TIMEOUT),
+        Ok(ServerControlPacket::DriverResumed)
+    ));
+
+    driver_udp.send(&[1, 2, 3]).unwrap();
+    let mut buffer = [0; 16];
+    let size = client_udp.recv(&mut buffer).unwrap();
+    assert_eq!(&buffer[..size], &[1, 2, 3]);
+}
+
+#[test]
+fn sockets_are_taken_once() {
+    let endpoint = start_keeper("taken_once");
+
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    alvr_sockets::hand_over_sockets(
+        &endpoint,
+        &KeptSockets {
+            tag: String::new(),
+            sockets: vec![KeptSocket::Udp(socket)],
+        },
+    )
+    .unwrap();
+
+    assert!(
+        alvr_sockets::take_kept_sockets(&endpoint)
+            .unwrap()
+            .is_some()
+    );
+    assert!(
+        alvr_sockets::take_kept_sockets(&endpoint)
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[test]
+fn keeper_not_running() {
+    let endpoint = env::temp_dir().join(format!("alvr_socket_keeper_missing_{}", process::id()));
+
+    assert!(!alvr_sockets::is_socket_keeper_running(&endpoint));
+}
+
+#[cfg(unix)]
+#[test]
+fn endpoint_is_private() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let endpoint = start_keeper("private");
+
+    let mode = std::fs::metadata(&endpoint).unwrap().permissions().mode();
+    assert_eq!(mode & 0o077, 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn keeper_survives_failed_requests() {
+    use std::{io::Write, os::unix::net::UnixStream};
+
+    let endpoint = start_keeper("failed_requests");
+
+    // Closed before sending the request
+    drop(UnixStream::connect(&endpoint).unwrap());
+    UnixStream::connect(&endpoint)
+        .unwrap()
+        .write_all(&[b'?'])
+        .unwrap();
+
+    assert!(alvr_sockets::is_socket_keeper_running(&endpoint));
+}
+
+#[test]
+fn stale_datagrams_are_discarded() {
+    let receiver = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let sender = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    sender.connect(receiver.local_addr().unwrap()).unwrap();
+    receiver.set_read_timeout(Some(TIMEOUT)).unwrap();
+
+    for _ in 0..3 {
+        sender.send(&[0; 100]).unwrap();
+    }
+    // Make sure they arrived
+    thread::sleep(Duration::from_millis(100));
+    alvr_sockets::discard_received_datagrams(&receiver).unwrap();
+
+    sender.send(&[7]).unwrap();
+    let mut buffer = [0; 100];
+    let size = receiver.recv(&mut buffer).unwrap();
+    assert_eq!(&buffer[..size], &[7]);
+}
@@ -0,0 +1,873 @@
+// Tests of the sockets and packets used for streaming, over loopback and without a headset or a
+// GPU. The handshake and the stream start are reproduced by the tests, the connection loops of
+// alvr_server_core and alvr_client_core are not run, so a regression in their negotiation code is
+// not caught here. The server side uses a stub encoder that produces deterministic NAL units. The
+// streams use TCP, since the UDP sockets of the client and the server bind the same port and cannot
+// run on the same host.
+
+use alvr_common::{
+    ConResult, ConnectionError, DeviceMotion, HEAD_ID, Pose, ViewParams,
+    glam::{Quat, UVec2, Vec3},
+};
+use alvr_packets::{
+    ClientConnectionResult, ClientControlPacket, ClientStatistics, ConnectionAcceptedInfo,
+    DecoderInitializationConfig, FaceData, NegotiatedStreamingConfig, NegotiatedStreamingConfigExt,
+    STATISTICS, ServerControlPacket, StreamConfig, StreamConfigPacket, TRACKING, TrackingData,
+    VIDEO, VideoPacketHeader, VideoStreamingCapabilities, VideoStreamingCapabilitiesExt,
+};
+use alvr_session::{
+    ChromaSubsampling, CodecType, FoveatedEncodingEyeConfig, PacketLossSimulationConfig,
+    SessionConfig, SocketBufferConfig, SocketProtocol,
+};
+use alvr_sockets::{
+    ControlSocketReceiver, ControlSocketSender, PacketLossSimulator, PeerLiveness, PeerType,
+    ProtoControlSocket, StreamPriority, StreamReceiver, StreamSender, StreamSocket,
+    StreamSocketBuilder,
+};
+use std::{
+    io::{self, Read, Write},
+    net::{IpAddr, Ipv4Addr, TcpListener, TcpStream},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+const TIMEOUT: Duration = Duration::from_secs(5);
+const RECV_TIMEOUT: Duration = Duration::from_millis(100);
+const PACKET_SIZE: usize = 1400;
+// Stream ID, packet index and payload size of each packet of a TCP stream socket
+const TCP_PACKET_PREFIX_SIZE: usize = 10;
+const FRAME_INTERVAL: Duration = Duration::from_millis(11);
+// Like the keepalive timeout, a send blocked for longer fails
+const WRITE_TIMEOUT: Duration = Duration::from_millis(500);
+
+// Retries on ConnectionError::TryAgain, like the connection loops
+fn retry<T>(mut f: impl FnMut() -> ConResult<T>) -> T {
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        match f() {
+            Ok(value) => return value,
+            Err(ConnectionError::TryAgain(e)) => {
+                assert!(Instant::now() < deadline, "Timed out: {e}")
+            }
+            Err(ConnectionError::Other(e)) => panic!("{e}"),
+        }
+    }
+}
+
+fn server_packet_name(packet: &ServerControlPacket) -> &'static str {
+    match packet {
+        ServerControlPacket::StartStream => "StartStream",
+        ServerControlPacket::DecoderConfig(_) => "DecoderConfig",
+        ServerControlPacket::Restarting => "Restarting",
+        ServerControlPacket::KeepAlive => "KeepAlive",
+        _ => "Other",
+    }
+}
+
+fn client_packet_name(packet: &ClientControlPacket) -> &'static str {
+    match packet {
+        ClientControlPacket::StreamReady => "StreamReady",
+        ClientControlPacket::RequestIdr => "RequestIdr",
+        ClientControlPacket::KeepAlive => "KeepAlive",
+        _ => "Other",
+    }
+}
+
+fn client_capabilities() -> VideoStreamingCapabilities {
+    VideoStreamingCapabilities {
+        default_view_resolution: UVec2::new(1920, 1832),
+        max_view_resolution: UVec2::new(2880, 2720),
+        refresh_rates: vec![72.0, 90.0, 120.0],
+        microphone_sample_rate: 48000,
+        foveated_encoding: true,
+        encoder_high_profile: true,
+        encoder_10_bits: false,
+        encoder_av1: false,
+        prefer_10bit: false,
+        preferred_encoding_gamma: 1.0,
+        prefer_hdr: false,
+        ext_str: String::new(),
+    }
+    .with_ext(VideoStreamingCapabilitiesExt {
+        depth_layers: true,
+        space_warp: false,
+        decoder_chroma_422: false,
+        decoder_chroma_444: true,
+        radial_foveated_encoding: true,
+        per_eye_foveated_encoding: true,
+        flat_display: false,
+    })
+}
+
+// Different for each eye, to check that the right eye is not mirrored from the left one
+fn foveated_encoding_eyes() -> [FoveatedEncodingEyeConfig; 2] {
+    let settings = SessionConfig::default().to_settings();
+    let [left, right] = settings
+        .video
+        .foveated_encoding
+        .into_option()
+        .unwrap()
+        .eyes();
+
+    [
+        left,
+        FoveatedEncodingEyeConfig {
+            center_shift_x: -0.1,
+            ..right
+        },
+    ]
+}
+
+// Stub of the negotiation of the server
+fn negotiate(capabilities: &VideoStreamingCapabilities) -> NegotiatedStreamingConfig {
+    NegotiatedStreamingConfig {
+        view_resolution: capabilities.default_view_resolution,
+        refresh_rate_hint: *capabilities.refresh_rates.last().unwrap(),
+        game_audio_sample_rate: 48000,
+        enable_foveated_encoding: capabilities.foveated_encoding,
+        encoding_gamma: capabilities.preferred_encoding_gamma,
+        enable_hdr: capabilities.prefer_hdr,
+        wired: false,
+        ext_str: String::new(),
+    }
+    .with_ext(NegotiatedStreamingConfigExt {
+        enable_depth_stream: capabilities.ext().unwrap().depth_layers,
+        enable_space_warp: false,
+        chroma_subsampling: if capabilities.ext().unwrap().decoder_chroma_444 {
+            ChromaSubsampling::Yuv444
+        } else {
+            ChromaSubsampling::Yuv420
+        },
+        enable_radial_foveated_encoding: capabilities.ext().unwrap().radial_foveated_encoding,
+        foveated_encoding_eyes: capabilities
+            .ext()
+            .unwrap()
+            .per_eye_foveated_encoding
+            .then(foveated_encoding_eyes),
+    })
+}
+
+// Annex B start code, NAL type and frame index, followed by a pattern that depends on the frame
+fn fake_nal(frame_index: u32, is_idr: bool) -> Vec<u8> {
+    let mut nal = vec![0, 0, 0, 1, if is_idr { 0x65 } else { 0x41 }];
+    nal.extend_from_slice(&frame_index.to_le_bytes());
+    nal.extend((0..3000_u32).map(|i| (i ^ frame_index) as u8));
+
+    nal
+}
+
+fn frame_index(nal: &[u8]) -> u32 {
+    u32::from_le_bytes(nal[5..9].try_into().unwrap())
+}
+
+#[derive(Default)]
+struct StubEncoder {
+    next_frame: u32,
+}
+
+impl StubEncoder {
+    fn encode(&mut self, is_idr: bool) -> (VideoPacketHeader, Vec<u8>) {
+        let frame_index = self.next_frame;
+        self.next_frame += 1;
+
+        let header = VideoPacketHeader {
+            timestamp: FRAME_INTERVAL * frame_index,
+            global_view_params: [ViewParams::DUMMY; 2],
+            is_idr,
+            intra_refresh_period: None,
+            average_qp: Some(frame_index % 52),
+            encode_duration: Duration::from_millis(3),
+            idle_throttled: false,
+        };
+
+        (header, fake_nal(frame_index, is_idr))
+    }
+}
+
+// Forwards the stream socket from the server to the client, dropping the video packets selected by
+// should_drop(packet_index). The packets from the client are forwarded untouched.
+fn spawn_lossy_proxy(client_port: u16, should_drop: fn(u32) -> bool) -> u16 {
+    let listener = TcpListener::bind((LOCALHOST, 0)).unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    thread::spawn(move || {
+        let (mut from_server, _) = listener.accept().unwrap();
+        let mut to_client = TcpStream::connect((LOCALHOST, client_port)).unwrap();
+
+        thread::spawn({
+            let mut from_client = to_client.try_clone().unwrap();
+            let mut to_server = from_server.try_clone().unwrap();
+            move || io::copy(&mut from_client, &mut to_server).ok()
+        });
+
+        let mut prefix = [0; TCP_PACKET_PREFIX_SIZE];
+        while from_server.read_exact(&mut prefix).is_ok() {
+            let stream_id = u16::from_le_bytes(prefix[0..2].try_into().unwrap());
+            let packet_index = u32::from_le_bytes(prefix[2..6].try_into().unwrap());
+            let payload_size = u32::from_le_bytes(prefix[6..10].try_into().unwrap()) as usize;
+
+            let mut payload = vec![0; payload_size];
+            if from_server.read_exact(&mut payload).is_err() {
+                break;
+            }
+
+            if stream_id == VIDEO && should_drop(packet_index) {
+                continue;
+            }
+
+            if to_client
+                .write_all(&prefix)
+                .and_then(|()| to_client.write_all(&payload))
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+
+    port
+}
+
+// Receives the packets of all streams of the socket until running is cleared
+fn spawn_socket_thread(mut socket: StreamSocket, running: Arc<AtomicBool>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        while running.load(Ordering::Relaxed) {
+            if let Err(ConnectionError::Other(_)) = socket.recv() {
+                return;
+            }
+        }
+    })
+}
+
+// Control sockets of both sides after the handshake
+struct Connection {
+    client_sender: ControlSocketSender<ClientControlPacket>,
+    client_receiver: ControlSocketReceiver<ServerControlPacket>,
+    server_sender: ControlSocketSender<ServerControlPacket>,
+    server_receiver: ControlSocketReceiver<ClientControlPacket>,
+    // Received by the client
+    stream_config: StreamConfig,
+    // Received by the server
+    capabilities: VideoStreamingCapabilities,
+    client_received: Vec<&'static str>,
+    server_received: Vec<&'static str>,
+}
+
+impl Connection {
+    // The client listens for the server on client_listener
+    fn handshake(client_listener: &TcpListener) -> Self {
+        let port = client_listener.local_addr().unwrap().port();
+
+        let server_thread = thread::spawn(move || {
+            // The control port of the client is fixed, the test connects to the listener instead
+            let stream = TcpStream::connect((LOCALHOST, port)).unwrap();
+            stream.set_nodelay(true).unwrap();
+            let mut socket = ProtoControlSocket::from_stream(stream);
+
+            let ClientConnectionResult::ConnectionAccepted(info) =
+                retry(|| socket.recv::<ClientConnectionResult>(RECV_TIMEOUT))
+            else {
+                panic!("The client is in standby");
+            };
+            assert_eq!(info.client_protocol_id, alvr_common::protocol_id_u64());
+            let capabilities = info.streaming_capabilities.unwrap();
+
+            let config_packet =
+                StreamConfigPacket::new(&SessionConfig::default(), negotiate(&capabilities))
+                    .unwrap();
+            socket.send(&config_packet).unwrap();
+
+            let (mut sender, receiver) = socket.split(RECV_TIMEOUT).unwrap();
+            sender.send(&ServerControlPacket::StartStream).unwrap();
+
+            (sender, receiver, capabilities)
+        });
+
+        let (mut socket, server_ip) = retry(|| {
+            ProtoControlSocket::connect_to(RECV_TIMEOUT, PeerType::Server(client_listener))
+        });
+        assert_eq!(server_ip, LOCALHOST);
+
+        socket
+            .send(&ClientConnectionResult::ConnectionAccepted(Box::new(
+                ConnectionAcceptedInfo {
+                    client_protocol_id: alvr_common::protocol_id_u64(),
+                    platform_string: "loopback".into(),
+                    server_ip,
+                    streaming_capabilities: Some(client_capabilities()),
+                },
+            )))
+            .unwrap();
+        let stream_config = retry(|| socket.recv::<StreamConfigPacket>(RECV_TIMEOUT))
+            .to_stream_config()
+            .unwrap();
+        let (client_sender, client_receiver) = socket.split(RECV_TIMEOUT).unwrap();
+
+        let (server_sender, server_receiver, capabilities) = server_thread.join().unwrap();
+
+        Self {
+            client_sender,
+            client_receiver,
+            server_sender,
+            server_receiver,
+            stream_config,
+            capabilities,
+            client_received: vec![],
+            server_received: vec![],
+        }
+    }
+
+    fn client_recv(&mut self) -> ServerControlPacket {
+        let packet = retry(|| self.client_receiver.recv(RECV_TIMEOUT));
+        self.client_received.push(server_packet_name(&packet));
+
+        packet
+    }
+
+    fn server_recv(&mut self) -> ClientControlPacket {
+        let packet = retry(|| self.server_receiver.recv(RECV_TIMEOUT));
+        self.server_received.push(client_packet_name(&packet));
+
+        packet
+    }
+
+    // Follows the handshake up to the decoder configuration. If set, proxy receives the port of the
+    // client and returns the port the server connects to.
+    fn start_stream(&mut self, proxy: Option<&dyn Fn(u16) -> u16>) -> Streaming {
+        assert!(matches!(
+            self.client_recv(),
+            ServerControlPacket::StartStream
+        ));
+
+        let builder = StreamSocketBuilder::listen_for_server(
+            TIMEOUT,
+            0,
+            SocketProtocol::Tcp,
+            None,
+            SocketBufferConfig::default(),
+        )
+        .unwrap();
+        let client_port = match &builder {
+            StreamSocketBuilder::Tcp(listener) => listener.local_addr().unwrap().port(),
+            StreamSocketBuilder::Udp(_) => unreachable!(),
+        };
+        self.client_sender
+            .send(&ClientControlPacket::StreamReady)
+            .unwrap();
+
+        assert!(matches!(
+            self.server_recv(),
+            ClientControlPacket::StreamReady
+        ));
+        let server_port = proxy.map_or(client_port, |proxy| proxy(client_port));
+        let mut server_socket = StreamSocketBuilder::connect_to_client(
+            RECV_TIMEOUT,
+            LOCALHOST,
+            server_port,
+            SocketProtocol::Tcp,
+            None,
+            SocketBufferConfig::default(),
+            PACKET_SIZE,
+        )
+        .unwrap_or_else(|e| panic!("{e}"));
+        server_socket.set_write_timeout(WRITE_TIMEOUT).unwrap();
+
+        let mut client_socket = builder
+            .accept_from_server(LOCALHOST, client_port, PACKET_SIZE, RECV_TIMEOUT)
+            .unwrap_or_else(|e| panic!("{e}"));
+        client_socket.set_write_timeout(WRITE_TIMEOUT).unwrap();
+
+        let video_sender = server_socket.request_stream_with_priority(VIDEO, StreamPriority::Low);
+        let video_receiver = client_socket.subscribe_to_stream(VIDEO, 10);
+        let tracking_sender = client_socket.request_stream(TRACKING);
+        let tracking_receiver = server_socket.subscribe_to_stream(TRACKING, 10);
+        let statistics_sender = client_socket.request_stream(STATISTICS);
+        let statistics_receiver = server_socket.subscribe_to_stream(STATISTICS, 10);
+
+        let running = Arc::new(AtomicBool::new(true));
+        let threads = vec![
+            spawn_socket_thread(server_socket, Arc::clone(&running)),
+            spawn_socket_thread(client_socket, Arc::clone(&running)),
+        ];
+
+        self.server_sender
+            .send(&ServerControlPacket::DecoderConfig(
+                DecoderInitializationConfig {
+                    codec: CodecType::H264,
+                    config_buffer: fake_nal(u32::MAX, false),
+                    ext_str: String::new(),
+                },
+            ))
+            .unwrap();
+        assert!(matches!(
+            self.client_recv(),
+            ServerControlPacket::DecoderConfig(DecoderInitializationConfig {
+                codec: CodecType::H264,
+                ..
+            })
+        ));
+
+        Streaming {
+            video_sender,
+            video_receiver,
+            tracking_sender,
+            tracking_receiver,
+            statistics_sender,
+            statistics_receiver,
+            encoder: StubEncoder::default(),
+            running,
+            threads,
+        }
+    }
+}
+
+struct Streaming {
+    video_sender: StreamSender<VideoPacketHeader>,
+    video_receiver: StreamReceiver<VideoPacketHeader>,
+    tracking_sender: StreamSender<TrackingData>,
+    tracking_receiver: StreamReceiver<TrackingData>,
+    statistics_sender: StreamSender<ClientStatistics>,
+    statistics_receiver: StreamReceiver<ClientStatistics>,
+    encoder: StubEncoder,
+    running: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl Streaming {
+    fn send_frame(&mut self, is_idr: bool) -> (VideoPacketHeader, Vec<u8>) {
+        let (header, nal) = self.encoder.encode(is_idr);
+        self.video_sender
+            .send_header_with_payload(&header, &nal)
+            .unwrap();
+
+        (header, nal)
+    }
+
+    // Returns the header, the NAL and whether some previous frames were lost
+    fn recv_frame(&mut self) -> (VideoPacketHeader, Vec<u8>, bool) {
+        let data = retry(|| self.video_receiver.recv(RECV_TIMEOUT));
+        let (header, nal) = data.get().unwrap();
+
+        (header, nal.to_vec(), data.had_packet_loss())
+    }
+}
+
+impl Drop for Streaming {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        for thread in self.threads.drain(..) {
+            thread.join().ok();
+        }
+    }
+}
+
+// Receives the statistics for the duration, like the stream receive thread of the server. Returns
+// true as soon as the client is declared dead.
+fn poll_stream_liveness(
+    streaming: &mut Streaming,
+    liveness: &mut PeerLiveness,
+    duration: Duration,
+) -> bool {
+    let deadline = Instant::now() + duration;
+    while Instant::now() < deadline {
+        if liveness.is_dead(Instant::now()) {
+            return true;
+        }
+
+        match streaming.statistics_receiver.recv(RECV_TIMEOUT / 10) {
+            Ok(_) => liveness.report_received(Instant::now()),
+            Err(ConnectionError::TryAgain(_)) => (),
+            Err(ConnectionError::Other(e)) => panic!("{e}"),
+        }
+    }
+
+    false
+}
+
+fn client_listener() -> TcpListener {
+    alvr_sockets::bind(TIMEOUT, 0, None, SocketBufferConfig::default()).unwrap()
+}
+
+#[test]
+fn test_stream_start() {
+    let listener = client_listener();
+    let mut connection = Connection::handshake(&listener);
+
+    // The server received the capabilities and the client the negotiated config
+    assert_eq!(
+        connection.capabilities.refresh_rates,
+        vec![72.0, 90.0, 120.0]
+    );
+    assert!(connection.capabilities.ext().unwrap().depth_layers);
+    let negotiated = &connection.stream_config.negotiated_config;
+    assert_eq!(negotiated.view_resolution, UVec2::new(1920, 1832));
+    assert_eq!(negotiated.refresh_rate_hint, 120.0);
+    assert!(negotiated.ext().unwrap().enable_depth_stream);
+    assert_eq!(
+        negotiated.ext().unwrap().chroma_subsampling,
+        ChromaSubsampling::Yuv444
+    );
+    assert_eq!(
+        negotiated.ext().unwrap().foveated_encoding_eyes,
+        Some(foveated_encoding_eyes())
+    );
+    assert_eq!(
+        connection.stream_config.server_version,
+        SessionConfig::default().server_version
+    );
+
+    let mut streaming = connection.start_stream(None);
+    let (sent_header, sent_nal) = streaming.send_frame(true);
+    let (header, nal, had_loss) = streaming.recv_frame();
+    assert!(header.is_idr);
+    assert_eq!(header.timestamp, sent_header.timestamp);
+    assert_eq!(header.average_qp, sent_header.average_qp);
+    assert_eq!(header.encode_duration, sent_header.encode_duration);
+    assert_eq!(nal, sent_nal);
+    assert!(!had_loss);
+
+    assert_eq!(connection.client_received, ["StartStream", "DecoderConfig"]);
+    assert_eq!(connection.server_received, ["StreamReady"]);
+}
+
+#[test]
+fn test_tracking_and_statistics() {
+    let listener = client_listener();
+    let mut connection = Connection::handshake(&listener);
+    let mut streaming = connection.start_stream(None);
+
+    let head_pose = Pose {
+        orientation: Quat::from_rotation_y(0.5),
+        position: Vec3::new(0.0, 1.6, 0.2),
+    };
+    streaming
+        .tracking_sender
+        .send_header(&TrackingData {
+            poll_timestamp: Duration::from_millis(100),
+            device_motions: vec![(
+                *HEAD_ID,
+                DeviceMotion {
+                    pose: head_pose,
+                    linear_velocity: Vec3::ZERO,
+                    angular_velocity: Vec3::ZERO,
+                },
+            )],
+            hand_skeletons: [None, None],
+            face: FaceData::default(),
+            body: None,
+        })
+        .unwrap();
+
+    let tracking = retry(|| streaming.tracking_receiver.recv(RECV_TIMEOUT))
+        .get_header()
+        .unwrap();
+    assert_eq!(tracking.poll_timestamp, Duration::from_millis(100));
+    let (device_id, motion) = tracking.device_motions[0];
+    assert_eq!(device_id, *HEAD_ID);
+    assert!(motion.pose.position.abs_diff_eq(head_pose.position, 1e-6));
+
+    // The statistics of a frame identify it by its timestamp
+    streaming.send_frame(true);
+    let (header, _, _) = streaming.recv_frame();
+    streaming
+        .statistics_sender
+        .send_header(&ClientStatistics {
+            target_timestamp: header.timestamp,
+            video_decode: Duration::from_millis(4),
+            ..Default::default()
+        })
+        .unwrap();
+    let statistics = retry(|| streaming.statistics_receiver.recv(RECV_TIMEOUT))
+        .get_header()
+        .unwrap();
+    assert_eq!(statistics.target_timestamp, header.timestamp);
+    assert_eq!(statistics.video_decode, Duration::from_millis(4));
+}
+
+#[test]
+fn test_packet_loss_recovery() {
+    let listener = client_listener();
+    let mut connection = Connection::handshake(&listener);
+    let drop_frames = |port| spawn_lossy_proxy(port, |index| index == 2 || index == 3);
+    let mut streaming = connection.start_stream(Some(&drop_frames));
+
+    for index in 0..6 {
+        streaming.send_frame(index == 0);
+    }
+
+    // The loss is detected on the first frame after the gap
+    let received = (0..4).map(|_| streaming.recv_frame()).collect::<Vec<_>>();
+    let indices = received
+        .iter()
+        .map(|(_, nal, _)| frame_index(nal))
+        .collect::<Vec<_>>();
+    assert_eq!(indices, [0, 1, 4, 5]);
+    let losses = received
+        .iter()
+        .map(|(_, _, had_loss)| *had_loss)
+        .collect::<Vec<_>>();
+    assert_eq!(losses, [false, false, true, false]);
+    assert_eq!(received[2].1, fake_nal(4, false));
+
+    // The client asks an IDR frame after the loss
+    connection
+        .client_sender
+        .send(&ClientControlPacket::RequestIdr)
+        .unwrap();
+    assert!(matches!(
+        connection.server_recv(),
+        ClientControlPacket::RequestIdr
+    ));
+    streaming.send_frame(true);
+    let (header, nal, had_loss) = streaming.recv_frame();
+    assert!(header.is_idr);
+    assert_eq!(nal, fake_nal(6, true));
+    assert!(!had_loss);
+
+    assert_eq!(connection.client_received, ["StartStream", "DecoderConfig"]);
+    assert_eq!(connection.server_received, ["StreamReady", "RequestIdr"]);
+}
+
+#[test]
+fn test_reconnect() {
+    let listener = client_listener();
+
+    for _ in 0..2 {
+        let mut connection = Connection::handshake(&listener);
+        let mut streaming = connection.start_stream(None);
+
+        // Each connection starts a new stream
+        streaming.send_frame(true);
+        let data = retry(|| streaming.video_receiver.recv(RECV_TIMEOUT));
+        assert_eq!(data.index(), 0);
+        assert!(data.get_header().unwrap().is_idr);
+        drop(data);
+
+        assert_eq!(connection.client_received, ["StartStream", "DecoderConfig"]);
+        assert_eq!(connection.server_received, ["StreamReady"]);
+    }
+}
+
+#[test]
+fn test_half_open_client_detected() {
+    let listener = client_listener();
+    let mut connection = Connection::handshake(&listener);
+    let streaming = connection.start_stream(None);
+
+    // The client sends some keepalives, then stops without closing the sockets, like a headset
+    // that turned off
+    let keepalive_timeout = Duration::from_millis(300);
+    let mut liveness = PeerLiveness::new(keepalive_timeout, Instant::now());
+    let Connection {
+        client_sender,
+        server_receiver,
+        ..
+    } = &mut connection;
+    let (keepalive_count, last_keepalive) = thread::scope(|scope| {
+        scope.spawn(|| {
+            for _ in 0..10 {
+                client_sender.send(&ClientControlPacket::KeepAlive).unwrap();
+                thread::sleep(Duration::from_millis(30));
+            }
+        });
+
+        // Like the control receive thread of the server
+        let mut count = 0;
+        let mut last_keepalive = Instant::now();
+        while !liveness.is_dead(Instant::now()) {
+            match server_receiver.recv(RECV_TIMEOUT) {
+                Ok(ClientControlPacket::KeepAlive) => {
+                    count += 1;
+                    last_keepalive = Instant::now();
+                    liveness.report_received(last_keepalive);
+                }
+                Ok(packet) => panic!("Unexpected {}", client_packet_name(&packet)),
+                Err(ConnectionError::TryAgain(_)) => (),
+                Err(ConnectionError::Other(e)) => panic!("{e}"),
+            }
+        }
+
+        (count, last_keepalive)
+    });
+    assert_eq!(keepalive_count, 10);
+    let detection_delay = last_keepalive.elapsed();
+    assert!(detection_delay >= keepalive_timeout);
+    assert!(detection_delay < keepalive_timeout + RECV_TIMEOUT * 3);
+
+    // The dead connection is torn down and the same client connects again right away
+    drop(streaming);
+    drop(connection);
+    let reconnect_start = Instant::now();
+    let mut connection = Connection::handshake(&listener);
+    let mut streaming = connection.start_stream(None);
+    streaming.send_frame(true);
+    assert!(streaming.recv_frame().0.is_idr);
+    assert!(reconnect_start.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn test_stream_inactivity_in_standby() {
+    let listener = client_listener();
+    let mut connection = Connection::handshake(&listener);
+    let mut streaming = connection.start_stream(None);
+
+    let inactivity_timeout = Duration::from_millis(300);
+    let mut liveness = PeerLiveness::new(inactivity_timeout, Instant::now());
+
+    streaming
+        .statistics_sender
+        .send_header(&ClientStatistics::default())
+        .unwrap();
+    assert!(!poll_stream_liveness(
+        &mut streaming,
+        &mut liveness,
+        inactivity_timeout / 2
+    ));
+
+    // In standby the client is kept even if it stops streaming
+    liveness.set_paused(true, Instant::now());
+    assert!(!poll_stream_liveness(
+        &mut streaming,
+        &mut liveness,
+        inactivity_timeout * 2
+    ));
+
+    // After leaving standby the timeout starts again
+    liveness.set_paused(false, Instant::now());
+    let resume = Instant::now();
+    assert!(!poll_stream_liveness(
+        &mut streaming,
+        &mut liveness,
+        inactivity_timeout / 2
+    ));
+    assert!(poll_stream_liveness(
+        &mut streaming,
+        &mut liveness,
+        inactivity_timeout
+    ));
+    assert!(resume.elapsed() >= inactivity_timeout);
+}
+
+#[test]
+fn test_send_to_stalled_client_fails() {
+    let listener = client_listener();
+    let mut connection = Connection::handshake(&listener);
+    let mut streaming = connection.start_stream(None);
+
+    // The client stops reading the video. Once its buffers are full the TCP window closes and the
+    // sends of the server fail after the write timeout, instead of blocking the disconnection
+    let (header, _) = streaming.encoder.encode(true);
+    let payload = vec![0; 1024 * 1024];
+    let start = Instant::now();
+    while streaming
+        .video_sender
+        .send_header_with_payload(&header, &payload)
+        .is_ok()
+    {
+        assert!(start.elapsed() < TIMEOUT * 4, "The sends never failed");
+    }
+}
+
+#[test]
+fn test_simulated_video_packet_loss() {
+    let config = PacketLossSimulationConfig {
+        loss_percent: 20.0,
+        mean_burst_length: 3,
+        seed: 7,
+    };
+
+    let mut simulator = PacketLossSimulator::new(&config);
+    let drops = (0..100_000)
+        .map(|_| simulator.should_drop())
+        .collect::<Vec<_>>();
+    let dropped_count = drops.iter().filter(|dropped| **dropped).count();
+    let burst_count = drops.windows(2).filter(|w| !w[0] && w[1]).count();
+    let loss = dropped_count as f32 / drops.len() as f32;
+    let mean_burst_length = dropped_count as f32 / burst_count as f32;
+    assert!((loss - 0.2).abs() < 0.01, "{loss}");
+    assert!(
+        (2.8..3.7).contains(&mean_burst_length),
+        "{mean_burst_length}"
+    );
+
+    // The same seed drops the same packets
+    let mut simulator = PacketLossSimulator::new(&config);
+    assert!(
+        drops
+            .iter()
+            .all(|dropped| simulator.should_drop() == *dropped)
+    );
+    let mut simulator = PacketLossSimulator::new(&PacketLossSimulationConfig { seed: 8, ..config });
+    assert!(
+        !drops
+            .iter()
+            .all(|dropped| simulator.should_drop() == *dropped)
+    );
+
+    // The receiver sees the dropped frames as lost by the network
+    let listener = client_listener();
+    let mut connection = Connection::handshake(&listener);
+    let mut streaming = connection.start_stream(None);
+    streaming.video_sender.simulate_packet_loss(&config);
+
+    let mut previous_dropped = false;
+    let mut received_count = 0;
+    for (frame_index, dropped) in drops.iter().take(200).enumerate() {
+        let (sent_header, _) = streaming.send_frame(frame_index % 50 == 0);
+        if *dropped {
+            previous_dropped = true;
+            continue;
+        }
+
+        let (header, _, had_packet_loss) = streaming.recv_frame();
+        assert_eq!(header.timestamp, sent_header.timestamp);
+        // Losses before the first received packet cannot be detected
+        assert_eq!(had_packet_loss, previous_dropped && received_count > 0);
+        previous_dropped = false;
+        received_count += 1;
+    }
+    assert_eq!(
+        received_count,
+        drops[..200].iter().filter(|dropped| !**dropped).count()
+    );
+}
+
+#[test]
+fn test_stream_sent_bytes() {
+    let listener = client_listener();
+    let mut connection = Connection::handshake(&listener);
+    let mut streaming = connection.start_stream(None);
+
+    // The clones of a sender share the counter, other streams have their own
+    let video_sent_bytes = streaming.video_sender.sent_bytes_counter();
+    let cloned_video_sent_bytes = streaming.video_sender.clone().sent_bytes_counter();
+    let tracking_sent_bytes = streaming.tracking_sender.sent_bytes_counter();
+
+    // TCP packets are sent as one shard, so they are not delayed by the pacing
+    streaming
+        .video_sender
+        .set_pacing(Some(Duration::from_secs(1)));
+    let send_start = Instant::now();
+    let (header, nal) = streaming.send_frame(true);
+    assert!(send_start.elapsed() < Duration::from_millis(500));
+    streaming.recv_frame();
+
+    // The TCP prefix is 10 bytes
+    let header_size = bincode::serde::encode_to_vec(&header, bincode::config::standard())
+        .unwrap()
+        .len();
+    assert_eq!(
+        video_sent_bytes.load(Ordering::Relaxed),
+        10 + header_size + nal.len()
+    );
+    assert_eq!(
+        cloned_video_sent_bytes.load(Ordering::Relaxed),
+        video_sent_bytes.load(Ordering::Relaxed)
+    );
+    assert_eq!(tracking_sent_bytes.load(Ordering::Relaxed), 0);
+}
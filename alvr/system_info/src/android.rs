@@ -1,9 +1,20 @@
-use alvr_common::warn;
-use jni::{JNIEnv, JavaVM, objects::JObject, sys::jobject};
-use std::net::{IpAddr, Ipv4Addr};
+use alvr_common::{debug, warn};
+use jni::{
+    JNIEnv, JavaVM,
+    objects::{JIntArray, JObject, JObjectArray},
+    sys::jobject,
+};
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    path::PathBuf,
+};
 
 pub const MICROPHONE_PERMISSION: &str = "android.permission.RECORD_AUDIO";
 
+// MediaCodecInfo.CodecCapabilities.COLOR_Format* values
+pub const COLOR_FORMAT_YUV422_FLEXIBLE: i32 = 0x7F422888;
+pub const COLOR_FORMAT_YUV444_FLEXIBLE: i32 = 0x7F444888;
+
 pub fn vm() -> JavaVM {
     unsafe { JavaVM::from_raw(ndk_context::android_context().vm().cast()).unwrap() }
 }
@@ -256,3 +267,420 @@ pub fn get_battery_status() -> (f32, bool) {
 
     (level as f32 / scale as f32, plugged > 0)
 }
+
+// Returns one of PowerManager.THERMAL_STATUS_* values, or None if not supported (API < 29)
+pub fn get_thermal_status() -> Option<i32> {
+    if get_api_level() < 29 {
+        return None;
+    }
+
+    let vm = vm();
+    let mut env = vm.attach_current_thread().unwrap();
+
+    let power_manager = get_system_service(&mut env, "power");
+    let status = env
+        .call_method(power_manager, "getCurrentThermalStatus", "()I", &[])
+        .unwrap()
+        .i()
+        .unwrap();
+
+    Some(status)
+}
+
+// Android TV and Google TV devices, which run the flat display client
+pub fn is_television() -> bool {
+    let vm = vm();
+    let mut env = vm.attach_current_thread().unwrap();
+
+    let package_manager = env
+        .call_method(
+            unsafe { JObject::from_raw(context()) },
+            "getPackageManager",
+            "()Landroid/content/pm/PackageManager;",
+            &[],
+        )
+        .unwrap()
+        .l()
+        .unwrap();
+    let feature = env.new_string("android.software.leanback").unwrap();
+
+    env.call_method(
+        package_manager,
+        "hasSystemFeature",
+        "(Ljava/lang/String;)Z",
+        &[(&feature).into()],
+    )
+    .unwrap()
+    .z()
+    .unwrap()
+}
+
+// Returns false for the headset buttons and for devices that cannot be queried
+pub fn is_external_input_device(device_id: i32) -> bool {
+    let vm = vm();
+    let mut env = vm.attach_current_thread().unwrap();
+
+    let device = match env
+        .call_static_method(
+            "android/view/InputDevice",
+            "getDevice",
+            "(I)Landroid/view/InputDevice;",
+            &[device_id.into()],
+        )
+        .and_then(|device| device.l())
+    {
+        Ok(device) if !device.is_null() => device,
+        _ => return false,
+    };
+
+    // isExternal() is available from API level 29
+    match env
+        .call_method(&device, "isExternal", "()Z", &[])
+        .and_then(|res| res.z())
+    {
+        Ok(is_external) => is_external,
+        Err(e) => {
+            env.exception_clear().ok();
+            warn!("Failed to query input device {device_id}: {e}");
+
+            false
+        }
+    }
+}
+
+// App specific directory in the shared storage, accessible with ADB without root
+pub fn external_files_dir() -> Option<PathBuf> {
+    let vm = vm();
+    let mut env = vm.attach_current_thread().unwrap();
+
+    let res = (|| -> jni::errors::Result<Option<PathBuf>> {
+        let dir = env
+            .call_method(
+                unsafe { JObject::from_raw(context()) },
+                "getExternalFilesDir",
+                "(Ljava/lang/String;)Ljava/io/File;",
+                &[(&JObject::null()).into()],
+            )?
+            .l()?;
+        if dir.is_null() {
+            return Ok(None);
+        }
+
+        let path = env
+            .call_method(&dir, "getAbsolutePath", "()Ljava/lang/String;", &[])?
+            .l()?;
+        let path = env.get_string((&path).into())?;
+
+        Ok(Some(PathBuf::from(path.to_string_lossy().into_owned())))
+    })();
+
+    res.unwrap_or_else(|_| {
+        env.exception_clear().ok();
+
+        None
+    })
+}
+
+pub struct WifiConnection {
+    pub frequency_mhz: u32,
+    pub channel_width_mhz: Option<u32>,
+    pub rssi_dbm: i32,
+    pub tx_link_speed_mbps: Option<u32>,
+    pub rx_link_speed_mbps: Option<u32>,
+    pub bssid: Option<String>,
+}
+
+// Returns None if the headset is not connected to a Wi-Fi network. The BSSID and the channel width
+// require the location permission.
+pub fn get_wifi_connection() -> Option<WifiConnection> {
+    let api_level = get_api_level();
+
+    let vm = vm();
+    let mut env = vm.attach_current_thread().unwrap();
+
+    let res = (|| -> jni::errors::Result<Option<WifiConnection>> {
+        let wifi_manager = get_system_service(&mut env, "wifi");
+        let wifi_info = env
+            .call_method(
+                &wifi_manager,
+                "getConnectionInfo",
+                "()Landroid/net/wifi/WifiInfo;",
+                &[],
+            )?
+            .l()?;
+        if wifi_info.is_null() {
+            return Ok(None);
+        }
+
+        let frequency = env
+            .call_method(&wifi_info, "getFrequency", "()I", &[])?
+            .i()?;
+        if frequency <= 0 {
+            return Ok(None);
+        }
+
+        let rssi_dbm = env.call_method(&wifi_info, "getRssi", "()I", &[])?.i()?;
+
+        // The link speeds are -1 when unknown
+        let to_speed = |speed: i32| u32::try_from(speed).ok().filter(|speed| *speed > 0);
+        let (tx_link_speed_mbps, rx_link_speed_mbps) = if api_level >= 29 {
+            (
+                to_speed(
+                    env.call_method(&wifi_info, "getTxLinkSpeedMbps", "()I", &[])?
+                        .i()?,
+                ),
+                to_speed(
+                    env.call_method(&wifi_info, "getRxLinkSpeedMbps", "()I", &[])?
+                        .i()?,
+                ),
+            )
+        } else {
+            let speed = env
+                .call_method(&wifi_info, "getLinkSpeed", "()I", &[])?
+                .i()?;
+
+            (to_speed(speed), None)
+        };
+
+        let bssid = env
+            .call_method(&wifi_info, "getBSSID", "()Ljava/lang/String;", &[])?
+            .l()?;
+        let bssid = if bssid.is_null() {
+            None
+        } else {
+            Some(
+                env.get_string((&bssid).into())?
+                    .to_string_lossy()
+                    .into_owned(),
+            )
+        }
+        // Placeholder returned without the location permission
+        .filter(|bssid| bssid != "02:00:00:00:00:00");
+
+        let mut channel_width_mhz = None;
+        if let Some(bssid) = &bssid {
+            let results = env
+                .call_method(&wifi_manager, "getScanResults", "()Ljava/util/List;", &[])?
+                .l()?;
+            let count = if results.is_null() {
+                0
+            } else {
+                env.call_method(&results, "size", "()I", &[])?.i()?
+            };
+
+            for i in 0..count {
+                let result = env
+                    .call_method(&results, "get", "(I)Ljava/lang/Object;", &[i.into()])?
+                    .l()?;
+                let result_bssid = env.get_field(&result, "BSSID", "Ljava/lang/String;")?.l()?;
+                let matches = !result_bssid.is_null()
+                    && env.get_string((&result_bssid).into())?.to_string_lossy() == **bssid;
+
+                if matches {
+                    // ScanResult.CHANNEL_WIDTH_* constants
+                    channel_width_mhz = match env.get_field(&result, "channelWidth", "I")?.i()? {
+                        0 => Some(20),
+                        1 => Some(40),
+                        2 => Some(80),
+                        3 | 4 => Some(160),
+                        5 => Some(320),
+                        _ => None,
+                    };
+                }
+
+                // The scan results can hold more objects than the local references table
+                env.delete_local_ref(result_bssid)?;
+                env.delete_local_ref(result)?;
+
+                if matches {
+                    break;
+                }
+            }
+        }
+
+        Ok(Some(WifiConnection {
+            frequency_mhz: frequency as u32,
+            channel_width_mhz,
+            rssi_dbm,
+            tx_link_speed_mbps,
+            rx_link_speed_mbps,
+            bssid,
+        }))
+    })();
+
+    res.unwrap_or_else(|e| {
+        env.exception_clear().ok();
+        // Polled periodically, a warning would flood the log
+        debug!("Failed to read the Wi-Fi connection: {e}");
+
+        None
+    })
+}
+
+// MediaCodec has no constants for the HEVC range extension profiles, a decoder supports 4:2:2 or
+// 4:4:4 if it lists the corresponding flexible color format
+pub fn decoder_supports_color_format(mime: &str, color_format: i32) -> bool {
+    let vm = vm();
+    let mut env = vm.attach_current_thread().unwrap();
+
+    let res = (|| -> jni::errors::Result<bool> {
+        let codec_list = env.new_object(
+            "android/media/MediaCodecList",
+            "(I)V",
+            &[0.into()], // MediaCodecList.REGULAR_CODECS
+        )?;
+        let codec_infos = env
+            .call_method(
+                &codec_list,
+                "getCodecInfos",
+                "()[Landroid/media/MediaCodecInfo;",
+                &[],
+            )?
+            .l()?;
+        let codec_infos = JObjectArray::from(codec_infos);
+        let mime_jstring = env.new_string(mime)?;
+
+        for i in 0..env.get_array_length(&codec_infos)? {
+            let info = env.get_object_array_element(&codec_infos, i)?;
+
+            let is_encoder = env.call_method(&info, "isEncoder", "()Z", &[])?.z()?;
+            let types = env
+                .call_method(&info, "getSupportedTypes", "()[Ljava/lang/String;", &[])?
+                .l()?;
+            let types = JObjectArray::from(types);
+
+            let mut supports_mime = false;
+            for j in 0..env.get_array_length(&types)? {
+                let ty = env.get_object_array_element(&types, j)?;
+                supports_mime |= env
+                    .get_string((&ty).into())?
+                    .to_string_lossy()
+                    .eq_ignore_ascii_case(mime);
+                env.delete_local_ref(ty)?;
+            }
+
+            let mut supports_color_format = false;
+            if !is_encoder && supports_mime {
+                let capabilities = env
+                    .call_method(
+                        &info,
+                        "getCapabilitiesForType",
+                        "(Ljava/lang/String;)Landroid/media/MediaCodecInfo$CodecCapabilities;",
+                        &[(&mime_jstring).into()],
+                    )?
+                    .l()?;
+                let color_formats = env.get_field(&capabilities, "colorFormats", "[I")?.l()?;
+                let color_formats = JIntArray::from(color_formats);
+
+                let mut formats = vec![0; env.get_array_length(&color_formats)? as usize];
+                env.get_int_array_region(&color_formats, 0, &mut formats)?;
+                supports_color_format = formats.contains(&color_format);
+
+                env.delete_local_ref(color_formats)?;
+                env.delete_local_ref(capabilities)?;
+            }
+
+            // The codec list can hold more objects than the local references table
+            env.delete_local_ref(types)?;
+            env.delete_local_ref(info)?;
+
+            if supports_color_format {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    })();
+
+    res.unwrap_or_else(|e| {
+        env.exception_clear().ok();
+        warn!("Failed to query the {mime} decoders: {e}");
+
+        false
+    })
+}
+
+// Returns None if the clipboard is empty or does not contain plain text
+pub fn get_clipboard_text() -> Option<String> {
+    let vm = vm();
+    let mut env = vm.attach_current_thread().unwrap();
+
+    let res = (|| -> jni::errors::Result<Option<String>> {
+        let manager = get_system_service(&mut env, "clipboard");
+
+        let clip = env
+            .call_method(
+                &manager,
+                "getPrimaryClip",
+                "()Landroid/content/ClipData;",
+                &[],
+            )?
+            .l()?;
+        if clip.is_null() {
+            return Ok(None);
+        }
+
+        let item = env
+            .call_method(
+                &clip,
+                "getItemAt",
+                "(I)Landroid/content/ClipData$Item;",
+                &[0.into()],
+            )?
+            .l()?;
+        let text = env
+            .call_method(&item, "getText", "()Ljava/lang/CharSequence;", &[])?
+            .l()?;
+        if text.is_null() {
+            return Ok(None);
+        }
+
+        let text = env
+            .call_method(&text, "toString", "()Ljava/lang/String;", &[])?
+            .l()?;
+        let text = env.get_string((&text).into())?;
+
+        Ok(Some(text.to_string_lossy().into_owned()))
+    })();
+
+    res.unwrap_or_else(|e| {
+        env.exception_clear().ok();
+        warn!("Failed to read the clipboard: {e}");
+
+        None
+    })
+}
+
+pub fn set_clipboard_text(text: &str) {
+    let vm = vm();
+    let mut env = vm.attach_current_thread().unwrap();
+
+    let res = (|| -> jni::errors::Result<()> {
+        let manager = get_system_service(&mut env, "clipboard");
+
+        let label = env.new_string("ALVR")?;
+        let text = env.new_string(text)?;
+        let clip = env
+            .call_static_method(
+                "android/content/ClipData",
+                "newPlainText",
+                "(Ljava/lang/CharSequence;Ljava/lang/CharSequence;)Landroid/content/ClipData;",
+                &[(&label).into(), (&text).into()],
+            )?
+            .l()?;
+
+        env.call_method(
+            &manager,
+            "setPrimaryClip",
+            "(Landroid/content/ClipData;)V",
+            &[(&clip).into()],
+        )?;
+
+        Ok(())
+    })();
+
+    if let Err(e) = res {
+        env.exception_clear().ok();
+        warn!("Failed to write the clipboard: {e}");
+    }
+}
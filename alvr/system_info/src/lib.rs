@@ -176,6 +176,21 @@ pub fn platform(runtime_name: Option<String>, runtime_version: Option<u64>) -> P
     }
 }
 
+// Reads MemTotal from /proc/meminfo. Returns None on platforms without procfs
+pub fn total_memory_bytes() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let kib = meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemTotal:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+
+    Some(kib * 1024)
+}
+
 #[cfg(not(target_os = "android"))]
 pub fn local_ip() -> std::net::IpAddr {
     use std::net::{IpAddr, Ipv4Addr};
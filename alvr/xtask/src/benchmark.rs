@@ -0,0 +1,108 @@
+use xshell::{Shell, cmd};
+
+// Encoding resolutions of the presets (both eyes side by side)
+const RESOLUTIONS: [(u32, u32); 4] = [(2144, 1072), (2784, 1408), (3232, 1632), (3664, 1920)];
+const REFRESH_RATES: [u32; 2] = [72, 90];
+const DURATION_S: u32 = 10;
+const BITRATE_MBPS: u32 = 30;
+
+// Below this margin the game and the rest of the pipeline don't have enough CPU time left
+const MIN_SPEED: f32 = 1.3;
+
+// Last "speed=1.23x" printed by FFmpeg
+fn parse_speed(output: &str) -> Option<f32> {
+    let (_, tail) = output.rsplit_once("speed=")?;
+    tail.trim_start().split('x').next()?.trim().parse().ok()
+}
+
+// Runs the same x264 and SVT-AV1 configurations the software encoder uses, including the RGB to
+// YUV conversion, on a synthetic moving pattern
+pub fn bench_software_encoder() {
+    let sh = Shell::new().unwrap();
+
+    if cmd!(sh, "ffmpeg -version")
+        .quiet()
+        .ignore_stdout()
+        .run()
+        .is_err()
+    {
+        eprintln!("FFmpeg is required for the benchmark");
+        return;
+    }
+    let encoders = cmd!(sh, "ffmpeg -hide_banner -encoders")
+        .quiet()
+        .read()
+        .unwrap_or_default();
+
+    let threads = std::thread::available_parallelism()
+        .map(|count| count.get())
+        .unwrap_or(1);
+    println!("Software encoder benchmark, {threads} CPU threads");
+    println!("Realtime if the speed is at least {MIN_SPEED}x\n");
+    println!("| Codec | Encoded resolution | Refresh rate | Speed | Realtime |");
+    println!("| ----- | ------------------ | ------------ | ----- | -------- |");
+
+    let bitrate = format!("{BITRATE_MBPS}M");
+    // One frame worth of buffer at 72 Hz, like the constant bitrate mode
+    let bufsize = format!("{}k", BITRATE_MBPS * 1000 / 72);
+    let duration = DURATION_S.to_string();
+
+    for (codec, available) in [
+        ("h264", encoders.contains("libx264")),
+        ("AV1", encoders.contains("libsvtav1")),
+    ] {
+        if !available {
+            println!("| {codec} | - | - | - | encoder not available |");
+            continue;
+        }
+
+        for (width, height) in RESOLUTIONS {
+            for fps in REFRESH_RATES {
+                let source = format!("testsrc2=size={width}x{height}:rate={fps},format=bgra");
+                let encoder_args = if codec == "h264" {
+                    vec![
+                        "-c:v",
+                        "libx264",
+                        "-preset",
+                        "ultrafast",
+                        "-tune",
+                        "zerolatency",
+                        "-maxrate",
+                        &bitrate,
+                        "-bufsize",
+                        &bufsize,
+                    ]
+                } else {
+                    vec![
+                        "-c:v",
+                        "libsvtav1",
+                        "-preset",
+                        "12",
+                        "-svtav1-params",
+                        "pred-struct=1:tune=0:fast-decode=1",
+                    ]
+                };
+
+                let output = cmd!(
+                    sh,
+                    "ffmpeg -hide_banner -f lavfi -i {source} -t {duration}
+                        -pix_fmt yuv420p {encoder_args...} -b:v {bitrate} -f null -"
+                )
+                .quiet()
+                .ignore_status()
+                .output()
+                .unwrap();
+
+                let speed = parse_speed(&String::from_utf8_lossy(&output.stderr));
+                let (speed, realtime) = match speed {
+                    Some(speed) => (
+                        format!("{speed:.2}x"),
+                        if speed >= MIN_SPEED { "yes" } else { "no" },
+                    ),
+                    None => ("-".into(), "failed"),
+                };
+                println!("| {codec} | {width}x{height} | {fps} Hz | {speed} | {realtime} |");
+            }
+        }
+    }
+}
@@ -178,6 +178,23 @@ pub fn build_streamer(
         .unwrap();
     }
 
+    // Build the test pattern streamer, launched by the dashboard when SteamVR is not running
+    {
+        let _push_guard = sh.push_dir(afs::crate_dir("server_core"));
+        cmd!(
+            sh,
+            "cargo build {common_flags_ref...} --bin alvr_test_pattern"
+        )
+        .run()
+        .unwrap();
+
+        sh.copy_file(
+            artifacts_dir.join(afs::exec_fname("alvr_test_pattern")),
+            build_layout.test_pattern_exe(),
+        )
+        .unwrap();
+    }
+
     // copy dependencies
     if cfg!(windows) {
         sh.copy_file(
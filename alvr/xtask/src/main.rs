@@ -1,3 +1,4 @@
+mod benchmark;
 mod build;
 mod ci;
 mod command;
@@ -41,6 +42,7 @@ SUBCOMMANDS:
     clean               Removes all build artifacts and dependencies
     bump                Bump streamer and client package versions
     clippy              Show warnings for selected clippy lints
+    bench-sw-encoder    Measure the speed of the software encoder at the preset resolutions. Requires FFmpeg
     kill-oculus         Kill all Oculus processes
 
 FLAGS:
@@ -273,6 +275,7 @@ fn main() {
                 "check-licenses" => {
                     packaging::generate_licenses();
                 }
+                "bench-sw-encoder" => benchmark::bench_software_encoder(),
                 "kill-oculus" => kill_oculus_processes(),
                 _ => print_help_and_exit("Unrecognized subcommand."),
             }